@@ -0,0 +1,107 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Measures walking a tiling layout of 1000 nodes with [`snapshot_iter`], counting the heap
+//! allocations made while doing so.
+//!
+//! Run it with `cargo bench --bench snapshot_iter`. Walking the layout should allocate nothing
+//! but each iterator's stack and path, however many nodes there are.
+//!
+//! [`snapshot_iter`]: GroupNode::snapshot_iter
+
+use std::{
+	alloc::{GlobalAlloc, Layout, System},
+	hint::black_box,
+	sync::atomic::{AtomicUsize, Ordering},
+	time::Instant,
+};
+
+use aquariwm::layout::{GroupNode, LayoutSettings, TilingLayout};
+
+/// The number of times the layout is walked.
+const RUNS: usize = 10_000;
+
+/// The number of allocations made (including reallocations) since the program started.
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The [system allocator], counting its allocations in [`ALLOCATIONS`].
+///
+/// [system allocator]: System
+struct Counting;
+
+unsafe impl GlobalAlloc for Counting {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.realloc(ptr, layout, new_size)
+	}
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Creates a layout of 1000 nodes, not counting the root group, nested 4 groups deep.
+fn layout() -> TilingLayout<u32> {
+	let mut windows = 0;
+	let mut window = || {
+		windows += 1;
+		format!("w{windows}")
+	};
+
+	let mut description = String::from("h(");
+	for _ in 0..50 {
+		let [a, b, c, d, e, f, g, h, i, j, k, l, m, n] = [(); 14].map(|()| window());
+
+		description.push_str(&format!(
+			"v(h({a} {b} {c}) h({d} {e} v({f} {g} {h} h({i} {j} {k}) h({l} {m} {n})))) "
+		));
+	}
+	description.push(')');
+
+	let mut layout = TilingLayout::from_description(&description, |name| name.strip_prefix('w')?.parse().ok())
+		.expect("the description is valid");
+	layout.resize(0, 0, 1920, 1080, &LayoutSettings::default());
+
+	layout
+}
+
+fn main() {
+	let layout = layout();
+	let root: &GroupNode<u32> = &layout;
+
+	let nodes = root.snapshot_iter().count();
+	assert_eq!(nodes, 1001);
+
+	let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+	let start = Instant::now();
+
+	for _ in 0..RUNS {
+		for node in black_box(root).snapshot_iter() {
+			black_box(node);
+		}
+	}
+
+	let elapsed = start.elapsed();
+	let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+	println!(
+		"walked {nodes} nodes {RUNS} times in {elapsed:?} ({:?} per walk)",
+		elapsed / RUNS as u32
+	);
+	println!(
+		"{allocations} allocations ({} per walk)",
+		allocations as f64 / RUNS as f64
+	);
+
+	// The stack and the path.
+	assert!(allocations <= 2 * RUNS, "walking the layout allocated per node");
+}
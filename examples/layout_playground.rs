@@ -0,0 +1,278 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Drives AquariWM's layout managers without a display server, previewing the layout in the
+//! terminal after every command, along with the layout's [description].
+//!
+//! Run it with `cargo run --example layout_playground`, then enter commands (one per line):
+//!
+//! | Command | Effect                                                 |
+//! |---------|--------------------------------------------------------|
+//! | `a`     | Adds a window.                                         |
+//! | `d`     | Removes the focused window.                            |
+//! | `n`/`p` | Focuses the next/previous window.                      |
+//! | `r`     | Rotates the orientation of the focused window's group. |
+//! | `s`     | Swaps the focused window with the next window.         |
+//! | `v`     | Splits the focused window into a group of its own.     |
+//! | `m`     | Switches to the next layout manager.                   |
+//! | `q`     | Quits.                                                 |
+//!
+//! [description]: TilingLayout::from_description
+
+use std::{
+	convert::Infallible,
+	io::{self, BufRead, Write},
+};
+
+use aquariwm::{
+	layout::{
+		managers::{Spiral, Stack},
+		Axis,
+		CurrentLayout,
+		GroupNode,
+		LayoutSettings,
+		NodeKind,
+		Orientation,
+		TilingLayout,
+		Visibility,
+	},
+	preview,
+	state::{AquariWm, FocusCause, MapState},
+};
+
+/// The dimensions of the pretend screen.
+const SCREEN: (u32, u32) = (1920, 1080);
+/// The dimensions of the preview, in character cells.
+const GRID: (usize, usize) = (96, 24);
+
+/// The layout managers which can be switched between.
+#[derive(Debug, Clone, Copy)]
+enum Manager {
+	Stack,
+	Spiral,
+}
+
+impl Manager {
+	/// Returns the layout manager after this one.
+	const fn next(self) -> Self {
+		match self {
+			Self::Stack => Self::Spiral,
+			Self::Spiral => Self::Stack,
+		}
+	}
+
+	/// Creates AquariWM's state with a tiling layout managed by this layout manager, containing
+	/// the given `windows`.
+	fn state(self, windows: Vec<u32>) -> AquariWm<u32> {
+		let windows = windows.into_iter().map(|window| (window, MapState::Mapped));
+		let settings = LayoutSettings::default();
+		let (width, height) = SCREEN;
+
+		match self {
+			Self::Stack => {
+				AquariWm::with_tiling_layout_and_windows::<Stack<u32>>(0, 0, width, height, windows, settings)
+			},
+			Self::Spiral => {
+				AquariWm::with_tiling_layout_and_windows::<Spiral<u32>>(0, 0, width, height, windows, settings)
+			},
+		}
+	}
+}
+
+struct Playground {
+	state: AquariWm<u32>,
+	manager: Manager,
+
+	/// The window which will be added next.
+	next_window: u32,
+}
+
+impl Playground {
+	fn new() -> Self {
+		let manager = Manager::Stack;
+
+		Self {
+			state: manager.state(Vec::new()),
+			manager,
+
+			next_window: 1,
+		}
+	}
+
+	fn layout(&self) -> &TilingLayout<u32> {
+		self.state
+			.tiling_layout()
+			.expect("the playground always has a tiling layout")
+	}
+
+	fn layout_mut(&mut self) -> &mut TilingLayout<u32> {
+		match &mut self.state.layout {
+			CurrentLayout::Tiled(manager) => manager.layout_mut(),
+			CurrentLayout::Floating => unreachable!("the playground always has a tiling layout"),
+		}
+	}
+
+	/// Returns the windows in the layout, in order.
+	fn windows(&self) -> Vec<u32> {
+		self.layout().windows().into_iter().copied().collect()
+	}
+
+	/// Returns the window `offset` places after the focused window, wrapping around.
+	fn window_after_focused(&self, offset: isize) -> Option<u32> {
+		let windows = self.windows();
+		let focused = self.state.focused?;
+		let index = windows.iter().position(|&window| window == focused)? as isize;
+
+		Some(windows[(index + offset).rem_euclid(windows.len() as isize) as usize])
+	}
+
+	/// Returns the group containing the focused window, and the window's index within it.
+	fn focused_group_mut(&mut self) -> Option<(&mut GroupNode<u32>, usize)> {
+		let focused = self.state.focused?;
+		let layout = self.layout_mut();
+
+		let path = layout.path_of(layout.id_of_window(&focused)?)?;
+		let (&index, path) = path.split_last()?;
+
+		let mut group: &mut GroupNode<u32> = layout;
+		for &index in path {
+			group = group.get_mut(index)?.unwrap_group_mut();
+		}
+
+		Some((group, index))
+	}
+
+	/// Performs the given `command`, returning whether the playground should keep running.
+	fn perform(&mut self, command: &str) -> bool {
+		match command {
+			"a" => {
+				let window = self.next_window;
+				self.next_window += 1;
+
+				self.state
+					.add_window(window, MapState::Mapped)
+					.expect("new windows are never already in the layout");
+				self.state.set_focused(Some(window), FocusCause::Keyboard);
+			},
+			"d" => {
+				if let Some(focused) = self.state.focused {
+					let next = self.window_after_focused(1).filter(|&next| next != focused);
+
+					self.state.remove_window(&focused);
+					self.state.set_focused(next, FocusCause::WindowClosed);
+				}
+			},
+
+			"n" => self
+				.state
+				.set_focused(self.window_after_focused(1), FocusCause::Keyboard),
+			"p" => self
+				.state
+				.set_focused(self.window_after_focused(-1), FocusCause::Keyboard),
+
+			"r" => {
+				if let Some((group, _)) = self.focused_group_mut() {
+					group.rotate_by(1);
+				}
+			},
+			"s" => {
+				if let (Some(focused), Some(next)) = (self.state.focused, self.window_after_focused(1)) {
+					self.state.swap_windows(&focused, &next);
+				}
+			},
+			"v" => {
+				if let Some((group, index)) = self.focused_group_mut() {
+					let orientation = match group.orientation().axis() {
+						Axis::Horizontal => Orientation::TopToBottom,
+						Axis::Vertical => Orientation::LeftToRight,
+					};
+
+					if let Some(node) = group.remove(index) {
+						let window = node.unwrap_window().into_window();
+
+						group
+							.insert_group_with(index, orientation, |group| group.push_window_back(window))
+							.expect("groups nested too deeply are flattened by default");
+					}
+				}
+			},
+
+			"m" => {
+				self.manager = self.manager.next();
+
+				let focused = self.state.focused;
+				self.state = self.manager.state(self.windows());
+				self.state.set_focused(focused, FocusCause::Keyboard);
+			},
+
+			"q" => return false,
+
+			_ => println!("Unknown command {command:?}; see the example's documentation for commands"),
+		}
+
+		true
+	}
+
+	/// Applies the changes made to the layout and prints a preview of it.
+	fn print(&mut self) {
+		self.state
+			.apply_changes(|_, _, _, _, _, _| Ok::<_, Infallible>(()))
+			.unwrap_or_else(|never| match never {});
+
+		let (width, height) = SCREEN;
+		let (columns, rows) = GRID;
+
+		let mut snapshots = self.layout().snapshot_iter();
+		if let Some(focused) = &self.state.focused {
+			snapshots = snapshots.focused(focused);
+		}
+
+		let tiles: Vec<(String, (i32, i32, u32, u32))> = snapshots
+			.filter_map(|node| match node.kind {
+				NodeKind::Window {
+					window,
+					visibility: Visibility::Visible,
+				} => {
+					let label = match node.flags.focused {
+						true => format!("{window} (focused)"),
+						false => window.to_string(),
+					};
+
+					Some((label, node.rect))
+				},
+
+				_ => None,
+			})
+			.collect();
+
+		let boxes = tiles.iter().map(|(label, rect)| {
+			(
+				preview::scale_to_grid(*rect, (0, 0, width, height), columns, rows),
+				label.as_str(),
+			)
+		});
+
+		for line in preview::render(boxes, columns, rows) {
+			println!("{line}");
+		}
+		print!("{:?} layout:\n{}", self.manager, preview::outline(self.layout()));
+		println!("{}", self.layout().to_description(|window| format!("w{window}")));
+	}
+}
+
+fn main() -> io::Result<()> {
+	let mut playground = Playground::new();
+	playground.print();
+
+	for line in io::stdin().lock().lines() {
+		if !playground.perform(line?.trim()) {
+			break;
+		}
+
+		playground.print();
+		io::stdout().flush()?;
+	}
+
+	Ok(())
+}
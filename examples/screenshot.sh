@@ -0,0 +1,45 @@
+#!/bin/sh
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+# Takes a screenshot of the focused window, or of the window with the given mark, with `maim`.
+#
+#     examples/screenshot.sh [mark] > window.png
+#
+# The geometry is the one AquariWM believes the window has, read from the window list it exports
+# in the `_AQUARIWM_WINDOWS` property of the root window, so the screenshot is of the window's
+# tile even while the window is still catching up with it. The same geometries, and those of
+# groups, outputs and the work area, can be logged with the `geometry-of`, `geometry-of-group`,
+# `focused-output-geometry` and `workarea` key binding actions, e.g.:
+#
+#     [key-bindings]
+#     "Super+g" = "geometry-of focused"
+#
+# Requires `xprop`, `jq` and `maim`.
+
+set -eu
+
+mark="${1:-}"
+
+# `xprop` prints the property as an escaped string: `_AQUARIWM_WINDOWS = "{\"version\":1,...}"`.
+windows="$(xprop -root -notype _AQUARIWM_WINDOWS | sed -e 's/^[^"]*"//' -e 's/"$//' -e 's/\\"/"/g')"
+
+geometry="$(printf '%s' "$windows" | jq -r --arg mark "$mark" '
+	.windows[]
+	| select(if $mark == "" then .focused else (.marks | index($mark)) != null end)
+	| .geometry
+	| select(. != null)
+	| "\(.width)x\(.height)+\(.x)+\(.y)"
+')"
+
+if [ -z "$geometry" ]; then
+	if [ -n "$mark" ]; then
+		echo "No window marked $mark is shown" >&2
+	else
+		echo "No window is focused" >&2
+	fi
+	exit 1
+fi
+
+exec maim --geometry "$geometry"
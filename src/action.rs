@@ -0,0 +1,1378 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [Actions] performed on AquariWM's state, whether by key bindings or anything else which acts on
+//! behalf of the user.
+//!
+//! Every action has a single written form, which is what [key bindings] in the configuration file
+//! are given: its [name], followed by its arguments separated by whitespace, e.g.
+//! `resize-focused horizontal +5%` or `focus-output-by-name "HDMI 1"`. Arguments containing
+//! whitespace or quotes are quoted with `"` or `'`, and `\` escapes the character after it outside
+//! of single quotes. Actions are [parsed] from that form, [displayed] in it, and serialized as it.
+//!
+//! [Actions]: Action
+//! [key bindings]: crate::config::KeyBinding
+//! [name]: NAMES
+//! [parsed]: Action::from_str
+//! [displayed]: Display
+
+use std::{
+	borrow::Cow,
+	fmt::{self, Display, Formatter},
+	path::PathBuf,
+	str::FromStr,
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::{
+	config::suggestion,
+	ignore::Matcher,
+	layout::{self, managers::UnknownManagerError},
+	output,
+	placement::FloatPreset,
+	presets::PresetError,
+	query::{GroupQuery, Query, WindowQuery},
+	workspaces::WorkspaceError,
+};
+
+/// The names of the [actions], as they are given in key bindings.
+///
+/// [actions]: Action
+pub const NAMES: &[&str] = &[
+	"adjust-gap",
+	"resize-primary",
+	"resize-focused",
+	"swap-with-primary",
+	"move-tab-left",
+	"move-tab-right",
+	"toggle-manual",
+	"toggle-group-maximize",
+	"focus-output",
+	"focus-output-by-name",
+	"move-window-to-output",
+	"move-to-output-and-follow",
+	"switch-to-workspace",
+	"move-to-workspace",
+	"move-to-workspace-and-follow",
+	"focus-parent",
+	"focus-child",
+	"focus-next",
+	"focus-previous",
+	"toggle-sticky",
+	"float-preset",
+	"minimize-focused",
+	"restore-minimized",
+	"force-kill-focused",
+	"ignore-add",
+	"ignore-remove",
+	"ignore-list",
+	"ignore-focused-window",
+	"adopt-unignored",
+	"save-layout",
+	"load-layout",
+	"set-workspace-layout",
+	"compact-layout",
+	"mark",
+	"unmark",
+	"focus-mark",
+	"swap-with-mark",
+	"send-mark-to-current-workspace",
+	"key-grab-list",
+	"query-latency",
+	"toggle-dry-run",
+	"geometry-of",
+	"geometry-of-group",
+	"focused-output-geometry",
+	"workarea",
+	"set-wallpaper",
+	"reload-config",
+];
+
+/// An action performed on AquariWM's [state], typically in response to a key binding.
+///
+/// Actions are performed with [`AquariWm::perform`].
+///
+/// [state]: crate::state::AquariWm
+/// [`AquariWm::perform`]: crate::state::AquariWm::perform
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Action {
+	/// Adjusts the effective [window gap] of the focused window's group by the given number of
+	/// logical pixels, clamped at zero.
+	///
+	/// The adjusted gap becomes that group's [gap override].
+	///
+	/// [window gap]: crate::layout::LayoutSettings::window_gap
+	/// [gap override]: crate::layout::GroupNode::set_gap
+	AdjustGap(i32),
+
+	/// Grows the [layout manager]'s primary area by the given number of logical pixels, or shrinks
+	/// it if negative.
+	///
+	/// [layout manager]: crate::layout::TilingLayoutManager
+	ResizePrimary(i32),
+	/// Resizes the focused window along the given `axis` by the given `amount`, in logical pixels
+	/// or as a percentage of its group.
+	///
+	/// The window is resized in the deepest group along the `axis`, and is clamped so that neither
+	/// it nor the window or group it trades space with becomes too small. The number of logical
+	/// pixels it was actually resized by is given in the [outcome].
+	///
+	/// If a group is the [focus target], the whole group is resized within its parents instead.
+	///
+	/// See [`TilingLayout::resize_window`].
+	///
+	/// [outcome]: Outcome::Resized
+	/// [focus target]: crate::state::AquariWm::focus_target
+	/// [`TilingLayout::resize_window`]: crate::layout::TilingLayout::resize_window
+	ResizeFocused { axis: layout::Axis, amount: layout::Amount },
+	/// Swaps the focused window with the [layout manager]'s primary window.
+	///
+	/// Which window is focused afterwards is controlled by the [swap focus] setting.
+	///
+	/// [layout manager]: crate::layout::TilingLayoutManager
+	/// [swap focus]: crate::layout::LayoutSettings::swap_focus
+	SwapWithPrimary,
+	/// Moves the focused window's tab one place to the left within the innermost [stacked] group
+	/// containing it.
+	///
+	/// Only the order of the group's children changes: the tab stays [active] if it was, and
+	/// nothing is resized.
+	///
+	/// [stacked]: crate::layout::GroupNode::is_stacked
+	/// [active]: crate::layout::GroupNode::active
+	MoveTabLeft,
+	/// Moves the focused window's tab one place to the right within the innermost [stacked] group
+	/// containing it.
+	///
+	/// See [`MoveTabLeft`].
+	///
+	/// [stacked]: crate::layout::GroupNode::is_stacked
+	/// [`MoveTabLeft`]: Action::MoveTabLeft
+	MoveTabRight,
+	/// Toggles whether the focused window's group is [manual], i.e. whether the layout stops
+	/// balancing it.
+	///
+	/// [manual]: crate::layout::GroupNode::is_manual
+	ToggleManual,
+	/// Toggles whether the focused window is [maximized] along the axis of its group, with its
+	/// siblings shrunk to strips, restoring their sizes from before when toggled again.
+	///
+	/// [maximized]: crate::layout::GroupNode::maximized
+	ToggleGroupMaximize,
+
+	/// Focuses the output in the given direction from the [active output], wrapping around or
+	/// stopping at the edges according to the [output edges] setting.
+	///
+	/// The tiled window nearest to the center of the output is focused.
+	///
+	/// [active output]: crate::state::AquariWm::active_output
+	/// [output edges]: crate::output::OutputEdges
+	FocusOutput(output::Direction),
+	/// Focuses the output of the given name.
+	///
+	/// The tiled window nearest to the center of the output is focused.
+	FocusOutputByName(String),
+	/// Moves the focused window to the output in the given direction from its own, keeping it
+	/// focused.
+	///
+	/// A tiled window joins the group of the tiled window nearest to the center of that output. A
+	/// floating window must be moved by the display server, as given by the [outcome]. If a group
+	/// is the [focus target], the group is moved intact, joining the nearest tiled window's group.
+	///
+	/// [outcome]: Outcome::MovedToOutput
+	/// [focus target]: crate::state::AquariWm::focus_target
+	MoveWindowToOutput(output::Direction),
+	/// Moves the focused window to the output in the given direction from its own and makes that
+	/// output the [active output], as i3's `move container to output` followed by `focus output`
+	/// does.
+	///
+	/// This is what [`MoveWindowToOutput`] does, as it keeps the window focused: the window is
+	/// moved and the active output follows it in one step, so both outputs are re-tiled together
+	/// the next time changes are applied, and only then is focus given to the window. It is never
+	/// shown on its new output before it has been configured there.
+	///
+	/// [active output]: crate::state::AquariWm::active_output
+	/// [`MoveWindowToOutput`]: Action::MoveWindowToOutput
+	MoveToOutputAndFollow(output::Direction),
+
+	/// Switches to the [workspace] at the given index (`switch-to-workspace <number>`, counting
+	/// from 1), creating it if it is the workspace after the last.
+	///
+	/// Only the display server can hide and show the windows on the workspaces, so it must switch
+	/// workspaces, as given by the [outcome].
+	///
+	/// [workspace]: crate::workspaces
+	/// [outcome]: Outcome::SwitchToWorkspace
+	SwitchToWorkspace(usize),
+	/// Moves the focused window to the [workspace] at the given index (`move-to-workspace
+	/// <number>`, counting from 1), creating it if it is the workspace after the last.
+	///
+	/// The window is hidden until its workspace is switched to. Only the display server can hide
+	/// the window, so it must move it, as given by the [outcome].
+	///
+	/// [workspace]: crate::workspaces
+	/// [outcome]: Outcome::MoveToWorkspace
+	MoveToWorkspace(usize),
+	/// Moves the focused window to the [workspace] at the given index and switches to it
+	/// (`move-to-workspace-and-follow <number>`, counting from 1), keeping the window focused.
+	///
+	/// Unlike a [move] followed by a [switch], the window is never hidden: the windows on the
+	/// workspace left behind are hidden, and those on the new workspace shown, in a single
+	/// re-tile. Only the display server can hide and show the windows, so it must move the window,
+	/// as given by the [outcome].
+	///
+	/// [workspace]: crate::workspaces
+	/// [move]: Action::MoveToWorkspace
+	/// [switch]: Action::SwitchToWorkspace
+	/// [outcome]: Outcome::MoveToWorkspaceAndFollow
+	MoveToWorkspaceAndFollow(usize),
+
+	/// Selects the group containing the [focus target] as the new focus target, so that actions
+	/// which act on the focused window act on every window in that group at once.
+	///
+	/// Repeating it selects the next group out, up to the child groups of the tiling layout's root.
+	/// The selected group stays the focus target until [`FocusChild`] is performed or another
+	/// window is focused.
+	///
+	/// [focus target]: crate::state::AquariWm::focus_target
+	/// [`FocusChild`]: Action::FocusChild
+	FocusParent,
+	/// Returns the [focus target] to the group or window it was before the last [`FocusParent`].
+	///
+	/// [focus target]: crate::state::AquariWm::focus_target
+	/// [`FocusParent`]: Action::FocusParent
+	FocusChild,
+	/// Focuses the window after the focused window in the order windows are read on screen,
+	/// wrapping around to the first.
+	///
+	/// Tiled windows are read by their positions according to the [reading order], followed by
+	/// floating windows from the bottom of the stack to the top. Only the display server knows the
+	/// stacking order of floating windows, so it must focus the window, as given by the [outcome].
+	///
+	/// [reading order]: crate::layout::LayoutSettings::reading_order
+	/// [outcome]: Outcome::FocusNext
+	FocusNext,
+	/// Focuses the window before the focused window in the order windows are read on screen,
+	/// wrapping around to the last.
+	///
+	/// See [`FocusNext`] for the order. The display server must focus the window, as given by the
+	/// [outcome].
+	///
+	/// [`FocusNext`]: Action::FocusNext
+	/// [outcome]: Outcome::FocusPrevious
+	FocusPrevious,
+
+	/// Toggles whether the focused window is [sticky], i.e. shown on every workspace.
+	///
+	/// Whether the window is now sticky is given in the [outcome].
+	///
+	/// [sticky]: crate::state::WindowState::sticky
+	/// [outcome]: Outcome::Sticky
+	ToggleSticky,
+	/// Moves the focused window to the given [preset] geometry on the [active output]
+	/// (`float-preset <preset>`), making it float first if it is tiled.
+	///
+	/// The window must be moved by the display server, as given by the [outcome].
+	///
+	/// [preset]: FloatPreset
+	/// [active output]: crate::state::AquariWm::active_output
+	/// [outcome]: Outcome::ApplyFloatPreset
+	ApplyFloatPreset(FloatPreset),
+
+	/// Iconifies (minimizes) the focused window, removing it from the layout until it is
+	/// [restored].
+	///
+	/// The window must be unmapped and marked as iconified by the display server, as given by the
+	/// [outcome].
+	///
+	/// [restored]: Action::RestoreMinimized
+	/// [outcome]: Outcome::MinimizeFocused
+	MinimizeFocused,
+	/// Restores the window which has been [iconified] for the longest, putting it back where the
+	/// [insertion strategy] places it and focusing it.
+	///
+	/// Only the display server knows windows' classes, which restored windows may be grouped by, so
+	/// it must restore the window, as given by the [outcome].
+	///
+	/// [iconified]: crate::state::AquariWm::iconify_window
+	/// [insertion strategy]: crate::layout::LayoutSettings::insertion_strategy
+	/// [outcome]: Outcome::RestoreMinimized
+	RestoreMinimized,
+	/// Forcibly disconnects the focused window's client from the display server, e.g. because it
+	/// has [stopped responding], once this has been asked for twice within [two seconds].
+	///
+	/// The client's process is also killed with `SIGKILL` if [configured], and its process ID is
+	/// verified. Only the display server can disconnect clients, so it must do so, as given by the
+	/// [outcome].
+	///
+	/// [stopped responding]: crate::ping
+	/// [two seconds]: crate::ping::KILL_CONFIRMATION
+	/// [configured]: crate::ping::PingSettings::kill_process
+	/// [outcome]: Outcome::ForceKillFocused
+	ForceKillFocused,
+
+	/// Adds the given matcher to the [ignore list] (`ignore-add <class|id>`).
+	///
+	/// Managed windows which it matches must be passed through by the display server, as given by
+	/// the [outcome].
+	///
+	/// [ignore list]: crate::state::AquariWm::ignored
+	/// [outcome]: Outcome::IgnoreListChanged
+	Ignore(Matcher),
+	/// Removes the given matcher from the [ignore list] (`ignore-remove <class|id>`).
+	///
+	/// Windows which it matched remain passed through until they are [adopted].
+	///
+	/// [ignore list]: crate::state::AquariWm::ignored
+	/// [adopted]: Action::AdoptUnignored
+	Unignore(Matcher),
+	/// Lists the matchers in the [ignore list] (`ignore-list`), as given by the [outcome].
+	///
+	/// [ignore list]: crate::state::AquariWm::ignored
+	/// [outcome]: Outcome::IgnoreList
+	ListIgnored,
+	/// Stops managing the focused window on the spot, and adds it to the [ignore list] by its ID.
+	///
+	/// The window must be passed through by the display server, as given by the [outcome].
+	///
+	/// [ignore list]: crate::state::AquariWm::ignored
+	/// [outcome]: Outcome::IgnoreFocused
+	IgnoreFocusedWindow,
+	/// Manages the windows which were passed through but no longer match the [ignore list].
+	///
+	/// Only the display server knows which windows it passed through, so it must manage them, as
+	/// given by the [outcome].
+	///
+	/// [ignore list]: crate::state::AquariWm::ignored
+	/// [outcome]: Outcome::AdoptUnignored
+	AdoptUnignored,
+
+	/// Saves the shape of the tiling layout as the [preset] of the given name.
+	///
+	/// [preset]: crate::presets
+	SaveLayout(String),
+	/// Rearranges the tiling layout's windows into the shape of the [preset] of the given name.
+	///
+	/// Layout managers expect the layout to be in the shape they gave it, so they may arrange
+	/// windows which are added or removed afterwards unexpectedly.
+	///
+	/// [preset]: crate::presets
+	LoadLayout(String),
+	/// Replaces the current workspace's [layout manager] with the [built-in] layout manager of the
+	/// given name, which takes over the workspace's windows.
+	///
+	/// Other workspaces keep their layout managers.
+	///
+	/// [layout manager]: crate::layout::TilingLayoutManager
+	/// [built-in]: crate::layout::managers::BuiltIn
+	SetWorkspaceLayout(String),
+	/// [Compacts] the tiling layout, repairing any sizes which have drifted.
+	///
+	/// This is also done automatically once the layout has been left unchanged for a while.
+	///
+	/// [Compacts]: crate::layout::TilingLayout::compact
+	CompactLayout,
+
+	/// Marks the focused window with the given [mark], moving the mark from any other window it
+	/// named.
+	///
+	/// [mark]: crate::marks
+	Mark(String),
+	/// Removes the given [mark] or, if none is given, every mark of the focused window.
+	///
+	/// [mark]: crate::marks
+	Unmark(Option<String>),
+	/// Focuses the window named by the given [mark], if it is mapped.
+	///
+	/// [mark]: crate::marks
+	FocusMark(String),
+	/// Swaps the places of the focused window and the window named by the given [mark], wherever
+	/// they are in the tiling layout, keeping the focused window focused.
+	///
+	/// Only tiled windows can be swapped. If a group is the [focus target], the whole group swaps
+	/// places with the marked window, unless that window is within it.
+	///
+	/// [mark]: crate::marks
+	/// [focus target]: crate::state::AquariWm::focus_target
+	SwapWithMark(String),
+	/// Sends the window named by the given [mark] to the current workspace, showing it if it was on
+	/// another workspace.
+	///
+	/// Only the display server can show the window, so it must move it, as given by the [outcome].
+	///
+	/// [mark]: crate::marks
+	/// [outcome]: Outcome::SendMarkToCurrentWorkspace
+	SendMarkToCurrentWorkspace(String),
+
+	/// Lists whether each key binding was grabbed, is received from raw key events instead, or is
+	/// unavailable (`key-grab-list`), to diagnose conflicts with other clients' grabs.
+	///
+	/// Only the display server knows how each key binding is received, so it must list them, as
+	/// given by the [outcome].
+	///
+	/// [outcome]: Outcome::ListKeyGrabs
+	ListKeyGrabs,
+	/// Reports the [latencies] of user-visible operations (`query-latency`), if they are being
+	/// measured.
+	///
+	/// Only the display server measures latencies, so it must report them, as given by the
+	/// [outcome].
+	///
+	/// [latencies]: crate::metrics
+	/// [outcome]: Outcome::QueryLatency
+	QueryLatency,
+	/// Toggles whether requests are logged instead of sent (`toggle-dry-run`), e.g. to check what
+	/// a key binding would do without disturbing any windows.
+	///
+	/// Only the display server sends requests, so it must toggle the dry run, as given by the
+	/// [outcome].
+	///
+	/// [outcome]: Outcome::ToggleDryRun
+	ToggleDryRun,
+	/// Answers a geometry [query], e.g. `geometry-of focused`.
+	///
+	/// Only the display server knows the IDs and floating geometries of windows, so it must answer
+	/// the query, as given by the [outcome].
+	///
+	/// [query]: Query
+	/// [outcome]: Outcome::Query
+	Query(Query),
+
+	/// Shows the PNG image at the given path as the [wallpaper] (`set-wallpaper <path>`), until the
+	/// wallpaper is changed in the configuration file.
+	///
+	/// Only the display server draws the background, so it must load and show the image, as given
+	/// by the [outcome].
+	///
+	/// [wallpaper]: crate::wallpaper
+	/// [outcome]: Outcome::SetWallpaper
+	SetWallpaper(PathBuf),
+	/// Reads the [configuration file] again, applying whatever changed in it.
+	///
+	/// Only the display server knows which settings it is running with, so it must reload the
+	/// configuration, as given by the [outcome].
+	///
+	/// [configuration file]: crate::config
+	/// [outcome]: Outcome::ReloadConfig
+	ReloadConfig,
+}
+
+/// What came of [performing] an [action]: its [outcome], or why it couldn't be performed.
+///
+/// [performing]: crate::state::AquariWm::perform
+/// [action]: Action
+/// [outcome]: Outcome
+pub type ActionResult = Result<Outcome, ActionError>;
+
+/// What came of [performing] an [action].
+///
+/// [performing]: crate::state::AquariWm::perform
+/// [action]: Action
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub enum Outcome {
+	/// The action was performed, or there was nothing for it to change.
+	#[default]
+	Done,
+
+	/// The focused window was [resized] by the given number of logical pixels.
+	///
+	/// This may be less than the amount requested if the resize was clamped, or `0` if no part of
+	/// it could be applied.
+	///
+	/// [resized]: Action::ResizeFocused
+	Resized(i32),
+	/// The focused window was [moved] to the output of the given name.
+	///
+	/// Floating windows are not part of the tiling layout, so they must be moved onto the output by
+	/// the display server.
+	///
+	/// [moved]: Action::MoveWindowToOutput
+	MovedToOutput(String),
+	/// The workspace at the given index is to be [switched to].
+	///
+	/// The display server must switch to it with [`switch_to_workspace`], hiding and showing the
+	/// windows which leave and join the current workspace.
+	///
+	/// [switched to]: Action::SwitchToWorkspace
+	/// [`switch_to_workspace`]: crate::state::AquariWm::switch_to_workspace
+	SwitchToWorkspace(usize),
+	/// The focused window is to be [moved] to the workspace at the given index.
+	///
+	/// The display server must move it with [`move_window_to_workspace`], hiding it if it leaves
+	/// the current workspace.
+	///
+	/// [moved]: Action::MoveToWorkspace
+	/// [`move_window_to_workspace`]: crate::state::AquariWm::move_window_to_workspace
+	MoveToWorkspace(usize),
+	/// The focused window is to be [moved] to the workspace at the given index, which is switched
+	/// to.
+	///
+	/// The display server must move it with [`move_window_to_workspace_and_follow`], hiding and
+	/// showing the windows which leave and join the current workspace.
+	///
+	/// [moved]: Action::MoveToWorkspaceAndFollow
+	/// [`move_window_to_workspace_and_follow`]: crate::state::AquariWm::move_window_to_workspace_and_follow
+	MoveToWorkspaceAndFollow(usize),
+	/// The window named by the given mark is to be [sent] to the current workspace.
+	///
+	/// The display server must move it with [`move_window_to_workspace`], showing it if it was on
+	/// another workspace.
+	///
+	/// [sent]: Action::SendMarkToCurrentWorkspace
+	/// [`move_window_to_workspace`]: crate::state::AquariWm::move_window_to_workspace
+	SendMarkToCurrentWorkspace(String),
+	/// The [next] window in visual order is to be focused.
+	///
+	/// The display server must focus it with [`focus_next`], giving the stacking order of the
+	/// floating windows.
+	///
+	/// [next]: Action::FocusNext
+	/// [`focus_next`]: crate::state::AquariWm::focus_next
+	FocusNext,
+	/// The [previous] window in visual order is to be focused.
+	///
+	/// The display server must focus it with [`focus_previous`], giving the stacking order of the
+	/// floating windows.
+	///
+	/// [previous]: Action::FocusPrevious
+	/// [`focus_previous`]: crate::state::AquariWm::focus_previous
+	FocusPrevious,
+	/// The focused window was made [sticky] if `true`, or no longer sticky if `false`.
+	///
+	/// The display server must show the change, e.g. by setting the window's state properties.
+	///
+	/// [sticky]: Action::ToggleSticky
+	Sticky(bool),
+	/// The focused window, which is floating, is to be moved to the given [preset].
+	///
+	/// Only the display server knows floating windows' geometry and size hints, so it must compute
+	/// the window's geometry with [`place_preset`] and move it.
+	///
+	/// [preset]: Action::ApplyFloatPreset
+	/// [`place_preset`]: crate::placement::place_preset
+	ApplyFloatPreset(FloatPreset),
+	/// The focused window is to be [minimized].
+	///
+	/// The display server must iconify it, unmapping it and marking it as iconified.
+	///
+	/// [minimized]: Action::MinimizeFocused
+	MinimizeFocused,
+	/// The oldest iconified window is to be [restored].
+	///
+	/// The display server must restore it, mapping it and marking it as no longer iconified.
+	///
+	/// [restored]: Action::RestoreMinimized
+	RestoreMinimized,
+	/// The focused window's client is to be [force killed].
+	///
+	/// The display server must disconnect it once this has been confirmed.
+	///
+	/// [force killed]: Action::ForceKillFocused
+	ForceKillFocused,
+
+	/// The [ignore list] was changed.
+	///
+	/// The display server must pass through managed windows which are now ignored.
+	///
+	/// [ignore list]: crate::state::AquariWm::ignored
+	IgnoreListChanged,
+	/// The matchers in the [ignore list], as [listed].
+	///
+	/// [ignore list]: crate::state::AquariWm::ignored
+	/// [listed]: Action::ListIgnored
+	IgnoreList(Vec<Matcher>),
+	/// The focused window is to be [ignored].
+	///
+	/// The display server must stop managing it and pass it through.
+	///
+	/// [ignored]: Action::IgnoreFocusedWindow
+	IgnoreFocused,
+	/// Windows which no longer match the [ignore list] are to be [adopted].
+	///
+	/// The display server must manage the windows it passed through which are no longer ignored.
+	///
+	/// [ignore list]: crate::state::AquariWm::ignored
+	/// [adopted]: Action::AdoptUnignored
+	AdoptUnignored,
+
+	/// How each key binding is received is to be [listed].
+	///
+	/// [listed]: Action::ListKeyGrabs
+	ListKeyGrabs,
+	/// The [latencies] of user-visible operations are to be [reported].
+	///
+	/// [latencies]: crate::metrics
+	/// [reported]: Action::QueryLatency
+	QueryLatency,
+	/// Whether requests are logged instead of sent is to be [toggled].
+	///
+	/// [toggled]: Action::ToggleDryRun
+	ToggleDryRun,
+	/// The given geometry [query] is to be [answered].
+	///
+	/// [query]: Query
+	/// [answered]: crate::query::answer
+	Query(Query),
+
+	/// The PNG image at the given path is to be shown as the [wallpaper].
+	///
+	/// The display server must load the image and draw the background with it.
+	///
+	/// [wallpaper]: Action::SetWallpaper
+	SetWallpaper(PathBuf),
+	/// The [configuration file] is to be [reloaded].
+	///
+	/// The display server must read it again and apply the settings which changed.
+	///
+	/// [configuration file]: crate::config
+	/// [reloaded]: Action::ReloadConfig
+	ReloadConfig,
+}
+
+/// An error returned when an [action] can't be [performed].
+///
+/// [action]: Action
+/// [performed]: crate::state::AquariWm::perform
+#[derive(Debug, Error)]
+pub enum ActionError {
+	#[error("no window is focused")]
+	NoFocusedWindow,
+	#[error("there is no tiling layout")]
+	NoTilingLayout,
+	#[error("the focused window isn't tiled")]
+	NotTiled,
+	/// The [focus target] isn't in a group along the given axis which it could be resized in.
+	///
+	/// [focus target]: crate::state::AquariWm::focus_target
+	#[error("the focus target has no {} group to be resized in", axis_name(*.0))]
+	NoGroupToResize(layout::Axis),
+	#[error("the focus target has no parent group")]
+	NoParentGroup,
+	#[error("groups can't be swapped with the primary window")]
+	GroupNotSwappable,
+	#[error("the focused window isn't in a stacked group")]
+	NotStacked,
+
+	#[error("there is no output in direction `{}`", direction_name(*.0))]
+	NoOutputInDirection(output::Direction),
+	#[error("there is no connected output named {0:?}")]
+	UnknownOutput(String),
+	/// The focused window or group can't be moved to the output of the given name, as there are no
+	/// tiled windows on it to join.
+	#[error("output {0:?} has no tiled windows to join")]
+	NothingToJoin(String),
+
+	#[error("no window is minimized")]
+	NothingMinimized,
+
+	#[error(transparent)]
+	Workspace(#[from] WorkspaceError),
+
+	#[error("there is no window marked {0:?}")]
+	UnknownMark(String),
+	/// The window named by the given mark isn't mapped.
+	#[error("there is no shown window marked {0:?}")]
+	MarkNotShown(String),
+	/// The window named by the given mark can't be swapped with the [focus target], as either isn't
+	/// tiled, or the marked window is within the focused group.
+	///
+	/// [focus target]: crate::state::AquariWm::focus_target
+	#[error("only tiled windows outside the focus target can be swapped with the window marked {0:?}")]
+	NotSwappable(String),
+
+	#[error("failed to save layout preset {name:?}: {source}")]
+	SavePreset { name: String, source: PresetError },
+	#[error("failed to load layout preset {name:?}: {source}")]
+	LoadPreset { name: String, source: PresetError },
+	#[error(transparent)]
+	UnknownLayoutManager(#[from] UnknownManagerError),
+}
+
+impl ActionError {
+	/// Returns whether the action failed, rather than there having been nothing for it to act on.
+	///
+	/// There being nothing to act on, like no window being focused, is expected when a key binding
+	/// is pressed at the wrong time. Actions fail when they name something which doesn't exist, or
+	/// when reading or writing files for them fails.
+	pub const fn is_failure(&self) -> bool {
+		matches!(
+			self,
+			Self::UnknownOutput(_)
+				| Self::Workspace(_)
+				| Self::SavePreset { .. }
+				| Self::LoadPreset { .. }
+				| Self::UnknownLayoutManager(_)
+		)
+	}
+}
+
+/// An error returned when parsing an [`Action`] fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum ParseActionError {
+	#[error("expected an action")]
+	Empty,
+	/// There is no action of the given name.
+	#[error("unknown action `{0}`{}", suggestion(.0, NAMES.iter().copied()))]
+	Unknown(String),
+	/// A quoted argument isn't closed by the given quote character.
+	#[error("missing closing `{0}`")]
+	UnterminatedQuote(char),
+
+	/// The action was given the wrong arguments.
+	#[error("`{action}` expects {expected}")]
+	Arguments {
+		action: &'static str,
+		/// A description of the arguments the action expects.
+		expected: &'static str,
+	},
+}
+
+impl FromStr for Action {
+	type Err = ParseActionError;
+
+	/// Parses an action from its [name] followed by its arguments, separated by whitespace, e.g.
+	/// `adjust-gap -5` or `resize-focused horizontal +10%`.
+	///
+	/// Arguments may be quoted, as in `mark "my editor"`; see the [module documentation].
+	///
+	/// [name]: NAMES
+	/// [module documentation]: self
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let words = split_words(s)?;
+		let Some((name, args)) = words.split_first() else {
+			return Err(ParseActionError::Empty);
+		};
+		let args: Vec<_> = args.iter().map(String::as_str).collect();
+
+		let Some(&action) = NAMES.iter().find(|&&action| action == name) else {
+			return Err(ParseActionError::Unknown(name.clone()));
+		};
+
+		// Parses the action from its arguments, or returns `None` if they don't fit.
+		let parse =
+			|expected| move |parsed: Option<Self>| parsed.ok_or(ParseActionError::Arguments { action, expected });
+		let none = parse("no arguments");
+		let pixels = parse("a number of pixels");
+		let direction = parse("`left`, `right`, `up`, or `down`");
+		let one = parse("one argument");
+		let workspace = parse("a workspace number, counting from 1");
+
+		match (action, args.as_slice()) {
+			("adjust-gap", args) => pixels(single(args).and_then(|delta| delta.parse().ok()).map(Self::AdjustGap)),
+			("resize-primary", args) => pixels(
+				single(args)
+					.and_then(|delta| delta.parse().ok())
+					.map(Self::ResizePrimary),
+			),
+			("resize-focused", args) => {
+				let axis = match args.first() {
+					Some(&"horizontal") => Some(layout::Axis::Horizontal),
+					Some(&"vertical") => Some(layout::Axis::Vertical),
+					_ => None,
+				};
+				let amount = match args.get(1) {
+					Some(amount) => match amount.strip_suffix('%') {
+						Some(percent) => percent.parse().ok().map(layout::Amount::Percent),
+						None => amount.parse().ok().map(layout::Amount::Pixels),
+					},
+					None => None,
+				};
+
+				parse("`horizontal` or `vertical` and a number of pixels or a percentage")(
+					axis.zip(amount)
+						.filter(|_| args.len() == 2)
+						.map(|(axis, amount)| Self::ResizeFocused { axis, amount }),
+				)
+			},
+
+			("focus-output", args) => direction(single(args).and_then(parse_direction).map(Self::FocusOutput)),
+			("focus-output-by-name", args) => one(single(args).map(|name| Self::FocusOutputByName(name.to_owned()))),
+			("move-window-to-output", args) => {
+				direction(single(args).and_then(parse_direction).map(Self::MoveWindowToOutput))
+			},
+			("switch-to-workspace", args) => {
+				workspace(single(args).and_then(parse_workspace).map(Self::SwitchToWorkspace))
+			},
+			("move-to-output-and-follow", args) => {
+				direction(single(args).and_then(parse_direction).map(Self::MoveToOutputAndFollow))
+			},
+			("move-to-workspace", args) => workspace(single(args).and_then(parse_workspace).map(Self::MoveToWorkspace)),
+			("move-to-workspace-and-follow", args) => workspace(
+				single(args)
+					.and_then(parse_workspace)
+					.map(Self::MoveToWorkspaceAndFollow),
+			),
+
+			("float-preset", args) => parse("a preset like `centered-60x70`, `half-left` or `50x100+25+0`")(
+				single(args)
+					.and_then(|preset| preset.parse().ok())
+					.map(Self::ApplyFloatPreset),
+			),
+
+			("ignore-add" | "ignore-remove", args) => {
+				let matcher = single(args).and_then(|matcher| matcher.parse().ok());
+
+				parse("a class or a window ID")(matcher.map(|matcher| match action {
+					"ignore-add" => Self::Ignore(matcher),
+					_ => Self::Unignore(matcher),
+				}))
+			},
+
+			("save-layout", args) => one(single(args).map(|name| Self::SaveLayout(name.to_owned()))),
+			("load-layout", args) => one(single(args).map(|name| Self::LoadLayout(name.to_owned()))),
+			("set-workspace-layout", args) => one(single(args).map(|name| Self::SetWorkspaceLayout(name.to_owned()))),
+			("mark", args) => one(single(args).map(|mark| Self::Mark(mark.to_owned()))),
+			("unmark", []) => Ok(Self::Unmark(None)),
+			("unmark", args) => one(single(args).map(|mark| Self::Unmark(Some(mark.to_owned())))),
+			("focus-mark", args) => one(single(args).map(|mark| Self::FocusMark(mark.to_owned()))),
+			("swap-with-mark", args) => one(single(args).map(|mark| Self::SwapWithMark(mark.to_owned()))),
+			("send-mark-to-current-workspace", args) => {
+				one(single(args).map(|mark| Self::SendMarkToCurrentWorkspace(mark.to_owned())))
+			},
+
+			("geometry-of", args) => {
+				one(single(args).map(|window| Self::Query(Query::GeometryOf(WindowQuery::parse(window)))))
+			},
+			("geometry-of-group", args) => parse("a path like `1/0`, or `focused-group`")(
+				single(args)
+					.and_then(GroupQuery::parse)
+					.map(|group| Self::Query(Query::GeometryOfGroup(group))),
+			),
+			("workarea", args) => one(single(args).map(|workspace| Self::Query(Query::Workarea(workspace.to_owned())))),
+			("set-wallpaper", args) => {
+				parse("the path of a PNG image")(single(args).map(|path| Self::SetWallpaper(path.into())))
+			},
+
+			(_, [_, ..]) => none(None),
+
+			("swap-with-primary", []) => Ok(Self::SwapWithPrimary),
+			("move-tab-left", []) => Ok(Self::MoveTabLeft),
+			("move-tab-right", []) => Ok(Self::MoveTabRight),
+			("toggle-manual", []) => Ok(Self::ToggleManual),
+			("toggle-group-maximize", []) => Ok(Self::ToggleGroupMaximize),
+			("focus-parent", []) => Ok(Self::FocusParent),
+			("focus-child", []) => Ok(Self::FocusChild),
+			("focus-next", []) => Ok(Self::FocusNext),
+			("focus-previous", []) => Ok(Self::FocusPrevious),
+			("toggle-sticky", []) => Ok(Self::ToggleSticky),
+			("minimize-focused", []) => Ok(Self::MinimizeFocused),
+			("restore-minimized", []) => Ok(Self::RestoreMinimized),
+			("force-kill-focused", []) => Ok(Self::ForceKillFocused),
+			("ignore-list", []) => Ok(Self::ListIgnored),
+			("ignore-focused-window", []) => Ok(Self::IgnoreFocusedWindow),
+			("adopt-unignored", []) => Ok(Self::AdoptUnignored),
+			("compact-layout", []) => Ok(Self::CompactLayout),
+			("key-grab-list", []) => Ok(Self::ListKeyGrabs),
+			("query-latency", []) => Ok(Self::QueryLatency),
+			("toggle-dry-run", []) => Ok(Self::ToggleDryRun),
+			("focused-output-geometry", []) => Ok(Self::Query(Query::FocusedOutputGeometry)),
+			("reload-config", []) => Ok(Self::ReloadConfig),
+
+			(_, []) => unreachable!("every action name is parsed"),
+		}
+	}
+}
+
+/// Returns the only argument in `args`, if there is exactly one.
+fn single<'a>(args: &[&'a str]) -> Option<&'a str> {
+	match args {
+		[arg] => Some(arg),
+		_ => None,
+	}
+}
+
+/// Parses an [output direction].
+///
+/// [output direction]: output::Direction
+fn parse_direction(direction: &str) -> Option<output::Direction> {
+	match direction {
+		"left" => Some(output::Direction::Left),
+		"right" => Some(output::Direction::Right),
+		"up" => Some(output::Direction::Up),
+		"down" => Some(output::Direction::Down),
+
+		_ => None,
+	}
+}
+
+/// Parses the index of a [workspace], which is written counting from 1.
+///
+/// [workspace]: crate::workspaces
+fn parse_workspace(number: &str) -> Option<usize> {
+	number.parse::<usize>().ok()?.checked_sub(1)
+}
+
+/// Returns the written form of an [axis], as it is parsed by [`Action::from_str`].
+///
+/// [axis]: layout::Axis
+const fn axis_name(axis: layout::Axis) -> &'static str {
+	match axis {
+		layout::Axis::Horizontal => "horizontal",
+		layout::Axis::Vertical => "vertical",
+	}
+}
+
+/// Returns the written form of an [output direction], as it is [parsed].
+///
+/// [output direction]: output::Direction
+/// [parsed]: parse_direction
+const fn direction_name(direction: output::Direction) -> &'static str {
+	match direction {
+		output::Direction::Left => "left",
+		output::Direction::Right => "right",
+		output::Direction::Up => "up",
+		output::Direction::Down => "down",
+	}
+}
+
+/// Splits a written action into its words, separated by whitespace, removing quotes and escapes.
+///
+/// Text within `"` or `'` is part of the word it is in, even if it contains whitespace, so `""`
+/// is an empty word. Outside of single quotes, `\` escapes the character after it.
+fn split_words(s: &str) -> Result<Vec<String>, ParseActionError> {
+	let mut words = Vec::new();
+	// The word being split, if one has started.
+	let mut word: Option<String> = None;
+	let mut quote = None;
+	let mut chars = s.chars();
+
+	while let Some(c) = chars.next() {
+		match (quote, c) {
+			(Some(open), c) if c == open => quote = None,
+			// Nothing is escaped within single quotes.
+			(Some('\''), c) => word.get_or_insert_with(String::new).push(c),
+
+			(_, '\\') => {
+				let escaped = chars.next().unwrap_or('\\');
+				word.get_or_insert_with(String::new).push(escaped);
+			},
+
+			(Some(_), c) => word.get_or_insert_with(String::new).push(c),
+			(None, '"' | '\'') => {
+				quote = Some(c);
+				word.get_or_insert_with(String::new);
+			},
+			(None, c) if c.is_whitespace() => words.extend(word.take()),
+			(None, c) => word.get_or_insert_with(String::new).push(c),
+		}
+	}
+
+	match quote {
+		Some(quote) => Err(ParseActionError::UnterminatedQuote(quote)),
+
+		None => {
+			words.extend(word);
+			Ok(words)
+		},
+	}
+}
+
+/// Quotes the given argument of an action if it would otherwise not be [split] into that single
+/// word.
+///
+/// [split]: split_words
+pub(crate) fn quote(arg: &str) -> Cow<'_, str> {
+	let plain = !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\'));
+
+	match plain {
+		true => Cow::Borrowed(arg),
+		false => Cow::Owned(format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))),
+	}
+}
+
+impl Action {
+	/// Returns the [name] of the action, as it is written.
+	///
+	/// [name]: NAMES
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::AdjustGap(_) => "adjust-gap",
+			Self::ResizePrimary(_) => "resize-primary",
+			Self::ResizeFocused { .. } => "resize-focused",
+			Self::SwapWithPrimary => "swap-with-primary",
+			Self::MoveTabLeft => "move-tab-left",
+			Self::MoveTabRight => "move-tab-right",
+			Self::ToggleManual => "toggle-manual",
+			Self::ToggleGroupMaximize => "toggle-group-maximize",
+
+			Self::FocusOutput(_) => "focus-output",
+			Self::FocusOutputByName(_) => "focus-output-by-name",
+			Self::MoveWindowToOutput(_) => "move-window-to-output",
+			Self::MoveToOutputAndFollow(_) => "move-to-output-and-follow",
+			Self::SwitchToWorkspace(_) => "switch-to-workspace",
+			Self::MoveToWorkspace(_) => "move-to-workspace",
+			Self::MoveToWorkspaceAndFollow(_) => "move-to-workspace-and-follow",
+
+			Self::FocusParent => "focus-parent",
+			Self::FocusChild => "focus-child",
+			Self::FocusNext => "focus-next",
+			Self::FocusPrevious => "focus-previous",
+
+			Self::ToggleSticky => "toggle-sticky",
+			Self::ApplyFloatPreset(_) => "float-preset",
+			Self::MinimizeFocused => "minimize-focused",
+			Self::RestoreMinimized => "restore-minimized",
+			Self::ForceKillFocused => "force-kill-focused",
+
+			Self::Ignore(_) => "ignore-add",
+			Self::Unignore(_) => "ignore-remove",
+			Self::ListIgnored => "ignore-list",
+			Self::IgnoreFocusedWindow => "ignore-focused-window",
+			Self::AdoptUnignored => "adopt-unignored",
+
+			Self::SaveLayout(_) => "save-layout",
+			Self::LoadLayout(_) => "load-layout",
+			Self::SetWorkspaceLayout(_) => "set-workspace-layout",
+			Self::CompactLayout => "compact-layout",
+
+			Self::Mark(_) => "mark",
+			Self::Unmark(_) => "unmark",
+			Self::FocusMark(_) => "focus-mark",
+			Self::SwapWithMark(_) => "swap-with-mark",
+			Self::SendMarkToCurrentWorkspace(_) => "send-mark-to-current-workspace",
+
+			Self::ListKeyGrabs => "key-grab-list",
+			Self::QueryLatency => "query-latency",
+			Self::ToggleDryRun => "toggle-dry-run",
+			Self::Query(Query::GeometryOf(_)) => "geometry-of",
+			Self::Query(Query::GeometryOfGroup(_)) => "geometry-of-group",
+			Self::Query(Query::FocusedOutputGeometry) => "focused-output-geometry",
+			Self::Query(Query::Workarea(_)) => "workarea",
+
+			Self::SetWallpaper(_) => "set-wallpaper",
+			Self::ReloadConfig => "reload-config",
+		}
+	}
+}
+
+/// Actions are written as they are [parsed]: their [name] followed by their arguments, quoted if
+/// necessary. Relative amounts are always signed, e.g. `resize-focused horizontal +5%`.
+///
+/// [parsed]: Action::from_str
+/// [name]: Action::name
+impl Display for Action {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		// Queries are written the same way when their answers are logged.
+		if let Self::Query(query) = self {
+			return write!(f, "{query}");
+		}
+
+		f.write_str(self.name())?;
+
+		match self {
+			Self::AdjustGap(delta) | Self::ResizePrimary(delta) => write!(f, " {delta:+}"),
+			Self::ResizeFocused { axis, amount } => match amount {
+				layout::Amount::Pixels(pixels) => write!(f, " {} {pixels:+}", axis_name(*axis)),
+				layout::Amount::Percent(percent) => write!(f, " {} {percent:+}%", axis_name(*axis)),
+			},
+
+			Self::FocusOutput(direction)
+			| Self::MoveWindowToOutput(direction)
+			| Self::MoveToOutputAndFollow(direction) => write!(f, " {}", direction_name(*direction)),
+			Self::SwitchToWorkspace(index) | Self::MoveToWorkspace(index) | Self::MoveToWorkspaceAndFollow(index) => {
+				write!(f, " {}", index + 1)
+			},
+			Self::ApplyFloatPreset(preset) => write!(f, " {preset}"),
+			Self::Ignore(matcher) | Self::Unignore(matcher) => write!(f, " {}", quote(&matcher.to_string())),
+
+			Self::FocusOutputByName(name)
+			| Self::SaveLayout(name)
+			| Self::LoadLayout(name)
+			| Self::SetWorkspaceLayout(name)
+			| Self::Mark(name)
+			| Self::Unmark(Some(name))
+			| Self::FocusMark(name)
+			| Self::SwapWithMark(name)
+			| Self::SendMarkToCurrentWorkspace(name) => write!(f, " {}", quote(name)),
+			Self::SetWallpaper(path) => write!(f, " {}", quote(&path.to_string_lossy())),
+
+			_ => Ok(()),
+		}
+	}
+}
+
+/// Actions are serialized as they are [displayed].
+///
+/// [displayed]: Display
+impl Serialize for Action {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(self)
+	}
+}
+
+/// Actions are deserialized from strings, as they are [parsed].
+///
+/// [parsed]: Action::from_str
+impl<'de> Deserialize<'de> for Action {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let action = <Cow<str>>::deserialize(deserializer)?;
+
+		action.parse().map_err(de::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::window::WindowId;
+
+	#[test]
+	fn parse() {
+		assert_eq!("adjust-gap -5".parse(), Ok(Action::AdjustGap(-5)));
+		assert_eq!(
+			"resize-focused  vertical 10%".parse(),
+			Ok(Action::ResizeFocused {
+				axis: layout::Axis::Vertical,
+				amount: layout::Amount::Percent(10),
+			})
+		);
+		assert_eq!(
+			"move-window-to-output left".parse(),
+			Ok(Action::MoveWindowToOutput(output::Direction::Left))
+		);
+		assert_eq!(
+			"ignore-add 0x2a".parse(),
+			Ok(Action::Ignore(Matcher::Window(WindowId(0x2a))))
+		);
+		assert_eq!("switch-to-workspace 1".parse(), Ok(Action::SwitchToWorkspace(0)));
+		assert_eq!("move-to-workspace 5".parse(), Ok(Action::MoveToWorkspace(4)));
+		assert_eq!(
+			"move-to-workspace-and-follow 2".parse(),
+			Ok(Action::MoveToWorkspaceAndFollow(1))
+		);
+		assert_eq!(
+			"move-to-output-and-follow right".parse(),
+			Ok(Action::MoveToOutputAndFollow(output::Direction::Right))
+		);
+		assert!("switch-to-workspace 0".parse::<Action>().is_err());
+		assert!("move-to-workspace web".parse::<Action>().is_err());
+		assert_eq!("reload-config".parse(), Ok(Action::ReloadConfig));
+		assert_eq!("focus-parent".parse(), Ok(Action::FocusParent));
+		assert_eq!("focus-next".parse(), Ok(Action::FocusNext));
+		assert_eq!("move-tab-left".parse(), Ok(Action::MoveTabLeft));
+		assert_eq!("toggle-manual".parse(), Ok(Action::ToggleManual));
+		assert_eq!("toggle-group-maximize".parse(), Ok(Action::ToggleGroupMaximize));
+		assert_eq!(
+			"float-preset centered-60".parse(),
+			Ok(Action::ApplyFloatPreset(FloatPreset::Centered {
+				width_pct: 60,
+				height_pct: 60,
+			}))
+		);
+		assert_eq!(
+			"set-workspace-layout Spiral".parse(),
+			Ok(Action::SetWorkspaceLayout("Spiral".to_owned()))
+		);
+		assert_eq!("compact-layout".parse(), Ok(Action::CompactLayout));
+		assert_eq!("key-grab-list".parse(), Ok(Action::ListKeyGrabs));
+		assert_eq!("query-latency".parse(), Ok(Action::QueryLatency));
+		assert_eq!("toggle-dry-run".parse(), Ok(Action::ToggleDryRun));
+		assert_eq!(
+			"geometry-of 0x2a".parse(),
+			Ok(Action::Query(Query::GeometryOf(WindowQuery::Id(WindowId(0x2a)))))
+		);
+		assert_eq!(
+			"geometry-of-group /1/0".parse(),
+			Ok(Action::Query(Query::GeometryOfGroup(GroupQuery::Path(vec![1, 0]))))
+		);
+		assert!("geometry-of-group 1/x".parse::<Action>().is_err());
+
+		assert_eq!("unmark".parse(), Ok(Action::Unmark(None)));
+		assert_eq!("unmark mail".parse(), Ok(Action::Unmark(Some("mail".to_owned()))));
+		assert_eq!(
+			"swap-with-mark editor".parse(),
+			Ok(Action::SwapWithMark("editor".to_owned()))
+		);
+		assert!("mark".parse::<Action>().is_err());
+
+		assert_eq!(
+			"set-wallpaper \"~/Pictures/Sea Shore.png\"".parse(),
+			Ok(Action::SetWallpaper("~/Pictures/Sea Shore.png".into()))
+		);
+		assert!("set-wallpaper".parse::<Action>().is_err());
+
+		assert_eq!(
+			"reload-configs".parse::<Action>(),
+			Err(ParseActionError::Unknown("reload-configs".to_owned()))
+		);
+		assert_eq!(
+			"toggle-sticky now".parse::<Action>(),
+			Err(ParseActionError::Arguments {
+				action: "toggle-sticky",
+				expected: "no arguments",
+			})
+		);
+		assert!("focus-output sideways".parse::<Action>().is_err());
+		assert!("resize-focused horizontal".parse::<Action>().is_err());
+	}
+
+	#[test]
+	fn parse_arguments() {
+		let resize = |amount| Action::ResizeFocused {
+			axis: layout::Axis::Horizontal,
+			amount,
+		};
+
+		// Relative amounts may be signed either way.
+		assert_eq!("adjust-gap +5".parse(), Ok(Action::AdjustGap(5)));
+		assert_eq!("resize-primary -50".parse(), Ok(Action::ResizePrimary(-50)));
+		assert_eq!(
+			"resize-focused horizontal +5%".parse(),
+			Ok(resize(layout::Amount::Percent(5)))
+		);
+		assert_eq!(
+			"resize-focused horizontal -12".parse(),
+			Ok(resize(layout::Amount::Pixels(-12)))
+		);
+		assert!("resize-focused horizontal 5%%".parse::<Action>().is_err());
+		assert!("resize-focused horizontal +".parse::<Action>().is_err());
+
+		// Names with spaces are quoted, with either kind of quote.
+		assert_eq!(
+			"focus-output-by-name \"HDMI 1\"".parse(),
+			Ok(Action::FocusOutputByName("HDMI 1".to_owned()))
+		);
+		assert_eq!(
+			"save-layout 'code review'".parse(),
+			Ok(Action::SaveLayout("code review".to_owned()))
+		);
+		assert_eq!("mark my\\ editor".parse(), Ok(Action::Mark("my editor".to_owned())));
+		assert_eq!(
+			"ignore-add class:\"Steam Overlay\"".parse(),
+			Ok(Action::Ignore(Matcher::Class("Steam Overlay".to_owned())))
+		);
+		assert_eq!(
+			"workarea \"\"".parse(),
+			Ok(Action::Query(Query::Workarea(String::new())))
+		);
+		assert_eq!("  \t focus-next  ".parse(), Ok(Action::FocusNext));
+		assert!("mark two words".parse::<Action>().is_err());
+
+		assert_eq!("".parse::<Action>(), Err(ParseActionError::Empty));
+		assert_eq!(
+			"mark \"mail".parse::<Action>(),
+			Err(ParseActionError::UnterminatedQuote('"'))
+		);
+		assert_eq!(
+			"resize-focusd horizontal 5".parse::<Action>().unwrap_err().to_string(),
+			"unknown action `resize-focusd`; did you mean `resize-focused`?"
+		);
+	}
+
+	#[test]
+	fn split() {
+		assert_eq!(
+			split_words("a  b\tc"),
+			Ok(vec!["a".to_owned(), "b".to_owned(), "c".to_owned()])
+		);
+		assert_eq!(split_words("a\"b c\"d"), Ok(vec!["ab cd".to_owned()]));
+		assert_eq!(
+			split_words("'a\\b' \"a\\\"b\""),
+			Ok(vec!["a\\b".to_owned(), "a\"b".to_owned()])
+		);
+		assert_eq!(split_words("'' x"), Ok(vec![String::new(), "x".to_owned()]));
+		assert_eq!(split_words("trailing\\"), Ok(vec!["trailing\\".to_owned()]));
+		assert_eq!(split_words("   "), Ok(vec![]));
+		assert_eq!(split_words("'open"), Err(ParseActionError::UnterminatedQuote('\'')));
+	}
+
+	/// Tests that every action is written as it is parsed, and that every action name is covered.
+	#[test]
+	fn round_trip() {
+		let actions = [
+			Action::AdjustGap(-5),
+			Action::ResizePrimary(50),
+			Action::ResizeFocused {
+				axis: layout::Axis::Vertical,
+				amount: layout::Amount::Percent(-10),
+			},
+			Action::SwapWithPrimary,
+			Action::MoveTabLeft,
+			Action::MoveTabRight,
+			Action::ToggleManual,
+			Action::ToggleGroupMaximize,
+			Action::FocusOutput(output::Direction::Up),
+			Action::FocusOutputByName("HDMI 1".to_owned()),
+			Action::MoveWindowToOutput(output::Direction::Right),
+			Action::MoveToOutputAndFollow(output::Direction::Down),
+			Action::SwitchToWorkspace(0),
+			Action::MoveToWorkspace(4),
+			Action::MoveToWorkspaceAndFollow(2),
+			Action::FocusParent,
+			Action::FocusChild,
+			Action::FocusNext,
+			Action::FocusPrevious,
+			Action::ToggleSticky,
+			Action::ApplyFloatPreset(FloatPreset::Centered {
+				width_pct: 60,
+				height_pct: 70,
+			}),
+			Action::MinimizeFocused,
+			Action::RestoreMinimized,
+			Action::ForceKillFocused,
+			Action::Ignore(Matcher::Class("Steam \"Big Picture\"".to_owned())),
+			Action::Unignore(Matcher::Window(WindowId(0x2a))),
+			Action::ListIgnored,
+			Action::IgnoreFocusedWindow,
+			Action::AdoptUnignored,
+			Action::SaveLayout("code review".to_owned()),
+			Action::LoadLayout("coding".to_owned()),
+			Action::SetWorkspaceLayout("Spiral".to_owned()),
+			Action::CompactLayout,
+			Action::Mark("back\\slash".to_owned()),
+			Action::Unmark(None),
+			Action::Unmark(Some("it's".to_owned())),
+			Action::FocusMark("editor".to_owned()),
+			Action::SwapWithMark("mail".to_owned()),
+			Action::SendMarkToCurrentWorkspace("music".to_owned()),
+			Action::ListKeyGrabs,
+			Action::QueryLatency,
+			Action::ToggleDryRun,
+			Action::Query(Query::GeometryOf(WindowQuery::Mark("my editor".to_owned()))),
+			Action::Query(Query::GeometryOfGroup(GroupQuery::Path(vec![1, 0]))),
+			Action::Query(Query::FocusedOutputGeometry),
+			Action::Query(Query::Workarea("main".to_owned())),
+			Action::SetWallpaper("/usr/share/backgrounds/sea shore.png".into()),
+			Action::ReloadConfig,
+		];
+
+		for action in &actions {
+			let written = action.to_string();
+
+			assert!(written.starts_with(action.name()), "{written}");
+			assert_eq!(written.parse().as_ref(), Ok(action), "{written}");
+		}
+
+		let mut names: Vec<_> = actions.iter().map(Action::name).collect();
+		names.dedup();
+		assert_eq!(names, NAMES);
+
+		assert_eq!(
+			Action::ResizeFocused {
+				axis: layout::Axis::Horizontal,
+				amount: layout::Amount::Percent(5),
+			}
+			.to_string(),
+			"resize-focused horizontal +5%"
+		);
+		assert_eq!(
+			Action::FocusOutputByName("HDMI 1".to_owned()).to_string(),
+			"focus-output-by-name \"HDMI 1\""
+		);
+	}
+
+	#[test]
+	fn serde() {
+		let action = Action::Mark("my editor".to_owned());
+		let json = serde_json::to_string(&action).unwrap();
+
+		assert_eq!(json, r#""mark \"my editor\"""#);
+		assert_eq!(serde_json::from_str::<Action>(&json).unwrap(), action);
+
+		let error = serde_json::from_str::<Action>(r#""focus-outptu left""#).unwrap_err();
+		assert!(error.to_string().contains("did you mean `focus-output`?"), "{error}");
+	}
+}
@@ -0,0 +1,165 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Holding windows back from the tiling layout until they have stayed mapped for a moment.
+//!
+//! Splash screens, misbehaving tooltips, and some popups are mapped and unmapped again within tens
+//! of milliseconds. If each of them were tiled, the whole layout would visibly reflow twice for
+//! nothing. Windows which lack clear [signals] that they are normal windows are instead mapped
+//! straight away as temporary floats, and only admitted into the tiling layout once they are still
+//! mapped after the [admission delay]:
+//!
+//! - a window without clear signals is [pending] when it is mapped;
+//! - it is [admitted] into the tiling layout if it is still mapped once its deadline has passed;
+//! - it has [vanished] if it is unmapped before then, having never changed the layout.
+//!
+//! Each window's admission is kept in its [state] in the client map.
+//!
+//! [signals]: Signals
+//! [admission delay]: DEFAULT_ADMISSION_DELAY
+//! [pending]: Admission::Pending
+//! [admitted]: Admission::Admitted
+//! [vanished]: Admission::Vanished
+//! [state]: crate::state::WindowState::admission
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// The default time a window without clear [signals] floats for before it is admitted into the
+/// tiling layout.
+///
+/// [signals]: Signals
+pub const DEFAULT_ADMISSION_DELAY: Duration = Duration::from_millis(150);
+
+/// Where a window is in being admitted into the tiling layout.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum Admission {
+	/// The window was admitted into the tiling layout, or never had to wait to be.
+	#[default]
+	Admitted,
+	/// The window is mapped as a temporary float until it is admitted at the `deadline`.
+	Pending { deadline: Instant },
+	/// The window was unmapped while it was pending, without ever being tiled.
+	Vanished,
+}
+
+impl Admission {
+	/// Returns whether the window is waiting to be admitted.
+	#[inline]
+	pub const fn is_pending(&self) -> bool {
+		matches!(self, Self::Pending { .. })
+	}
+
+	/// Returns whether the window is due to be admitted at the time `now`.
+	#[inline]
+	pub fn is_due(&self, now: Instant) -> bool {
+		matches!(self, Self::Pending { deadline } if now >= *deadline)
+	}
+}
+
+/// What a window's `_NET_WM_WINDOW_TYPE` property says it is.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum WindowType {
+	/// The window has no `_NET_WM_WINDOW_TYPE` property.
+	#[default]
+	Absent,
+	/// The window's preferred type is `_NET_WM_WINDOW_TYPE_NORMAL`.
+	Normal,
+	/// The window's preferred type is any other type.
+	Other,
+}
+
+/// What is known about a window when it is mapped which tells whether it is a normal window worth
+/// tiling straight away.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct Signals {
+	/// Whether the window has a `WM_CLASS` property.
+	pub class: bool,
+	/// What the window's `_NET_WM_WINDOW_TYPE` property says it is.
+	pub window_type: WindowType,
+	/// Whether the window was created with no area to speak of.
+	///
+	/// The X server doesn't allow empty windows, so windows which are one pixel wide or high count
+	/// too.
+	pub zero_size: bool,
+	/// Whether a [rule] matches the window.
+	///
+	/// [rule]: crate::rules::Rule
+	pub ruled: bool,
+}
+
+impl Signals {
+	/// Returns whether the window has to wait to be admitted into the tiling layout.
+	///
+	/// Windows which a rule matches, and normal windows with a class, are admitted straight away.
+	/// Otherwise, windows without a class, without a window type, or without any area wait.
+	pub const fn needs_admission(&self) -> bool {
+		if self.ruled || (self.class && matches!(self.window_type, WindowType::Normal)) {
+			return false;
+		}
+
+		!self.class || matches!(self.window_type, WindowType::Absent) || self.zero_size
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn needs_admission() {
+		let clear = Signals {
+			class: true,
+			window_type: WindowType::Normal,
+			..Signals::default()
+		};
+		assert!(!clear.needs_admission());
+		// A normal window with a class is trusted even if it starts out empty.
+		assert!(!Signals {
+			zero_size: true,
+			..clear
+		}
+		.needs_admission());
+
+		// Any weak signal is enough to wait...
+		for weak in [
+			Signals { class: false, ..clear },
+			Signals {
+				window_type: WindowType::Absent,
+				..clear
+			},
+			Signals {
+				window_type: WindowType::Other,
+				zero_size: true,
+				..clear
+			},
+		] {
+			assert!(weak.needs_admission(), "{weak:?}");
+			// ...unless a rule matches the window.
+			assert!(!Signals { ruled: true, ..weak }.needs_admission());
+		}
+
+		// Windows of other types with a class are tiled straight away, as they always were.
+		assert!(!Signals {
+			window_type: WindowType::Other,
+			..clear
+		}
+		.needs_admission());
+	}
+
+	#[test]
+	fn due() {
+		let start = Instant::now();
+		let pending = Admission::Pending {
+			deadline: start + DEFAULT_ADMISSION_DELAY,
+		};
+
+		assert!(pending.is_pending());
+		assert!(!pending.is_due(start));
+		assert!(pending.is_due(start + DEFAULT_ADMISSION_DELAY));
+		assert!(!Admission::Admitted.is_due(start + DEFAULT_ADMISSION_DELAY));
+		assert!(!Admission::Vanished.is_pending());
+	}
+}
@@ -0,0 +1,108 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Settings for how windows look, as opposed to where they are [laid out].
+//!
+//! [laid out]: crate::layout
+
+use std::{collections::HashSet, fmt, num::ParseFloatError, str::FromStr};
+
+use thiserror::Error;
+
+/// Settings for how windows look.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Appearance {
+	/// The opacity of unfocused [tiled] windows, or [`None`] if they are not dimmed.
+	///
+	/// This is only a hint to the compositor (e.g. picom), which is responsible for actually
+	/// drawing windows with the given opacity.
+	///
+	/// [tiled]: crate::layout::Mode::Tiled
+	pub unfocused_opacity: Option<Opacity>,
+	/// The classes of windows which are never dimmed, like video players.
+	pub no_dim: HashSet<String>,
+	/// Whether a lone [tiled] window has its border removed, which is given back once another
+	/// window is tiled alongside it.
+	///
+	/// [tiled]: crate::layout::Mode::Tiled
+	pub smart_borders: bool,
+}
+
+/// The opacity of a window, from `0` (fully transparent) to `1` (fully opaque).
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Opacity(f64);
+
+impl Default for Opacity {
+	#[inline]
+	fn default() -> Self {
+		Self::OPAQUE
+	}
+}
+
+impl Opacity {
+	/// Fully opaque.
+	pub const OPAQUE: Self = Self(1.0);
+
+	/// Creates a new opacity.
+	///
+	/// Returns [`None`] if the `opacity` is not between `0` and `1` (inclusive).
+	pub fn new(opacity: f64) -> Option<Self> {
+		(0.0..=1.0).contains(&opacity).then_some(Self(opacity))
+	}
+
+	/// Returns the opacity as a floating point number.
+	#[inline(always)]
+	pub const fn get(&self) -> f64 {
+		self.0
+	}
+
+	/// Returns the opacity as a 32-bit cardinal, as used by the `_NET_WM_WINDOW_OPACITY` property:
+	/// `0` is fully transparent and `0xffffffff` is fully opaque.
+	#[inline]
+	pub fn to_cardinal(&self) -> u32 {
+		(self.0 * (u32::MAX as f64)).round() as u32
+	}
+}
+
+impl fmt::Display for Opacity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.0)
+	}
+}
+
+/// An error returned when parsing an [`Opacity`] fails.
+#[derive(Debug, Error)]
+pub enum ParseOpacityError {
+	#[error(transparent)]
+	Float(#[from] ParseFloatError),
+
+	/// The opacity was not between `0` and `1`.
+	#[error("opacities must be between 0 and 1")]
+	OutOfRange,
+}
+
+impl FromStr for Opacity {
+	type Err = ParseOpacityError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::new(s.trim().parse()?).ok_or(ParseOpacityError::OutOfRange)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cardinal() {
+		assert_eq!(Opacity::OPAQUE.to_cardinal(), 0xffff_ffff);
+		assert_eq!(Opacity::new(0.0).unwrap().to_cardinal(), 0);
+		assert_eq!(Opacity::new(0.5).unwrap().to_cardinal(), 0x8000_0000);
+		assert_eq!("0.92".parse::<Opacity>().unwrap().to_cardinal(), 0xeb85_1eb7);
+
+		assert!("1.5".parse::<Opacity>().is_err());
+		assert!("-0.1".parse::<Opacity>().is_err());
+		assert!("NaN".parse::<Opacity>().is_err());
+	}
+}
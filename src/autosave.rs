@@ -0,0 +1,464 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Automatic snapshots of AquariWM's layout, so that a crash loses as little of the user's window
+//! arrangement as possible.
+//!
+//! A snapshot is written once the layout has been left unchanged for a short [idle period], and at
+//! least every [interval] while the layout keeps changing. No snapshot is written while nothing
+//! has changed, and snapshots are never written more often than every [`MIN_SPACING`].
+//!
+//! Snapshots are written to a temporary file and then renamed over `state.json`, so `state.json`
+//! is never partially written. The previous [`BACKUPS`] snapshots are kept as `state.json.1.bak`
+//! (the newest) to `state.json.3.bak` (the oldest).
+//!
+//! [idle period]: DEFAULT_IDLE
+//! [interval]: Autosave::new
+
+use std::{
+	collections::BTreeMap,
+	env,
+	fs,
+	io::{self, Write},
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::{
+	layout::TilingLayout,
+	marks::Marks,
+	window::{ManagedWindow, WindowId},
+};
+
+/// The default maximum time between a change to the layout and it being written to a snapshot.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+/// How long the layout must be left unchanged before it is written to a snapshot.
+pub const DEFAULT_IDLE: Duration = Duration::from_secs(2);
+/// The minimum time between two snapshots being written.
+pub const MIN_SPACING: Duration = Duration::from_secs(5);
+
+/// The number of older snapshots kept as backups.
+pub const BACKUPS: usize = 3;
+
+/// A snapshot of a workspace: its tiling layout, the [name] of its layout manager, and the
+/// [marks] of its windows.
+///
+/// Snapshots written before the layout manager was recorded have no `manager`, and those written
+/// before marks were recorded have no marks.
+///
+/// [name]: crate::layout::TilingLayoutManager::name
+/// [marks]: crate::marks
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct WorkspaceSnapshot<Layout, Marks> {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub manager: Option<String>,
+	#[serde(flatten)]
+	pub layout: Layout,
+	#[serde(default)]
+	pub marks: Marks,
+}
+
+/// A [`WorkspaceSnapshot`] as it is persisted, with windows given by their [IDs].
+///
+/// The display server [resolves] the IDs to its windows again when the snapshot is restored.
+///
+/// [IDs]: WindowId
+/// [resolves]: WorkspaceSnapshot::resolve
+pub type PersistedWorkspace = WorkspaceSnapshot<TilingLayout<WindowId>, BTreeMap<String, WindowId>>;
+
+impl PersistedWorkspace {
+	/// Resolves the window [IDs] in the snapshot to windows, where `resolve` returns the window
+	/// with the given ID.
+	///
+	/// Marks of windows which can't be resolved are dropped. Returns [`None`] if any window in the
+	/// layout can't be resolved, as the layout would have to be reshaped without it.
+	///
+	/// [IDs]: WindowId
+	pub fn resolve<Window: ManagedWindow>(
+		self,
+		mut resolve: impl FnMut(WindowId) -> Option<Window>,
+	) -> Option<WorkspaceSnapshot<TilingLayout<Window>, Marks<Window>>> {
+		Some(WorkspaceSnapshot {
+			manager: self.manager,
+			layout: self.layout.try_map_windows(|&id| resolve(id))?,
+			marks: Marks::resolve(self.marks, resolve),
+		})
+	}
+}
+
+/// The file name of the newest snapshot.
+const FILE_NAME: &str = "state.json";
+
+/// Decides when to write snapshots of the layout, and writes them.
+#[derive(Debug)]
+pub struct Autosave {
+	/// The directory in which snapshots are written.
+	dir: PathBuf,
+
+	/// The maximum time between a change and it being written to a snapshot.
+	interval: Duration,
+	/// How long the layout must be left unchanged before it is written to a snapshot.
+	idle: Duration,
+
+	/// When the layout first changed since the last snapshot, if it has changed.
+	dirty_since: Option<Instant>,
+	/// When the layout last changed.
+	last_change: Option<Instant>,
+	/// When a snapshot was last written (or attempted).
+	last_write: Option<Instant>,
+}
+
+impl Autosave {
+	/// Creates an autosave component which writes snapshots to the given `dir`, at least every
+	/// `interval` while the layout is changing.
+	pub fn new(dir: impl Into<PathBuf>, interval: Duration) -> Self {
+		Self {
+			dir: dir.into(),
+
+			interval,
+			idle: DEFAULT_IDLE,
+
+			dirty_since: None,
+			last_change: None,
+			last_write: None,
+		}
+	}
+
+	/// Returns the default directory for snapshots, `$XDG_RUNTIME_DIR/aquariwm`.
+	///
+	/// Returns [`None`] if `XDG_RUNTIME_DIR` is not set.
+	pub fn default_dir() -> Option<PathBuf> {
+		env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join("aquariwm"))
+	}
+
+	/// Returns the directory in which snapshots are written.
+	pub fn dir(&self) -> &Path {
+		&self.dir
+	}
+
+	/// Records that the layout changed at the given time, so that it needs to be written to a
+	/// snapshot.
+	pub fn mark_dirty(&mut self, now: Instant) {
+		self.dirty_since.get_or_insert(now);
+		self.last_change = Some(now);
+	}
+
+	/// Returns whether the layout has changed since the last snapshot was written.
+	pub const fn is_dirty(&self) -> bool {
+		self.dirty_since.is_some()
+	}
+
+	/// Returns when the next snapshot is due, or [`None`] if the layout has not changed.
+	///
+	/// This is the earlier of the layout having been idle for long enough and the interval having
+	/// passed since the layout first changed, but no sooner than [`MIN_SPACING`] after the last
+	/// snapshot.
+	pub fn deadline(&self) -> Option<Instant> {
+		let (dirty_since, last_change) = (self.dirty_since?, self.last_change?);
+
+		let due = (dirty_since + self.interval).min(last_change + self.idle);
+
+		Some(match self.last_write {
+			Some(last_write) => due.max(last_write + MIN_SPACING),
+			None => due,
+		})
+	}
+
+	/// Writes the `snapshot` if one is [due].
+	///
+	/// Returns whether a snapshot was written. Failures are logged, and retried no sooner than
+	/// [`MIN_SPACING`] later.
+	///
+	/// [due]: Self::deadline
+	pub fn tick(&mut self, now: Instant, snapshot: &impl Serialize) -> bool {
+		match self.deadline() {
+			Some(deadline) if now >= deadline => self.write(now, snapshot),
+			_ => false,
+		}
+	}
+
+	/// Writes the `snapshot` when AquariWM is shutting down, if the layout has changed since the
+	/// last snapshot.
+	///
+	/// Like any other snapshot, this is skipped if the last snapshot was written less than
+	/// [`MIN_SPACING`] ago.
+	pub fn shutdown(&mut self, now: Instant, snapshot: &impl Serialize) -> bool {
+		let too_soon = self.last_write.is_some_and(|last_write| now < last_write + MIN_SPACING);
+
+		if !self.is_dirty() || too_soon {
+			return false;
+		}
+
+		self.write(now, snapshot)
+	}
+
+	/// Writes the `snapshot` immediately, however recently the last snapshot was written.
+	///
+	/// This is used when the connection to the display server is lost, after which the layout can
+	/// no longer change and AquariWM won't get another chance to write it.
+	pub fn write_now(&mut self, now: Instant, snapshot: &impl Serialize) -> bool {
+		self.write(now, snapshot)
+	}
+
+	/// Writes the `snapshot`, logging any failure.
+	fn write(&mut self, now: Instant, snapshot: &impl Serialize) -> bool {
+		self.last_write = Some(now);
+
+		match write_snapshot(&self.dir, snapshot) {
+			Ok(()) => {
+				event!(Level::DEBUG, "Wrote layout snapshot to {:?}", self.dir);
+
+				self.dirty_since = None;
+				self.last_change = None;
+
+				true
+			},
+
+			Err(error) => {
+				event!(
+					Level::WARN,
+					"Failed to write layout snapshot to {:?}: {error}",
+					self.dir
+				);
+
+				false
+			},
+		}
+	}
+}
+
+/// Returns the path of the snapshot of the given `generation` in the given `dir`.
+///
+/// Generation 0 is the newest snapshot, `state.json`; the backups are generations 1 to
+/// [`BACKUPS`].
+fn snapshot_path(dir: &Path, generation: usize) -> PathBuf {
+	match generation {
+		0 => dir.join(FILE_NAME),
+		generation => dir.join(format!("{FILE_NAME}.{generation}.bak")),
+	}
+}
+
+/// Atomically writes the `snapshot` to `state.json` in the given `dir`, rotating the previous
+/// snapshots into backups.
+fn write_snapshot(dir: &Path, snapshot: &impl Serialize) -> io::Result<()> {
+	fs::create_dir_all(dir)?;
+
+	// Write the snapshot to a temporary file first, so that a crash while writing it can't leave a
+	// partially written `state.json`.
+	let temp = dir.join(format!("{FILE_NAME}.tmp"));
+	{
+		let mut file = fs::File::create(&temp)?;
+
+		file.write_all(&serde_json::to_vec(snapshot)?)?;
+		file.sync_all()?;
+	}
+
+	// Shift each backup back by one generation, dropping the oldest.
+	for generation in (1..BACKUPS).rev() {
+		match fs::rename(snapshot_path(dir, generation), snapshot_path(dir, generation + 1)) {
+			Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+			_ => (),
+		}
+	}
+
+	// Keep the current snapshot as the newest backup, without ever removing `state.json`.
+	let (current, backup) = (snapshot_path(dir, 0), snapshot_path(dir, 1));
+	match fs::remove_file(&backup) {
+		Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+		_ => (),
+	}
+	match fs::hard_link(&current, &backup) {
+		Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+		// Not every filesystem supports hard links.
+		Err(_) => {
+			fs::copy(&current, &backup)?;
+		},
+
+		Ok(()) => (),
+	}
+
+	fs::rename(&temp, &current)
+}
+
+/// Reads the newest valid snapshot in the given `dir`.
+///
+/// `state.json` is preferred; if it is missing or can't be deserialized, the backups are tried
+/// from newest to oldest. Returns [`None`] if there is no valid snapshot.
+pub fn restore<Snapshot: DeserializeOwned>(dir: &Path) -> Option<Snapshot> {
+	(0..=BACKUPS).find_map(|generation| {
+		let path = snapshot_path(dir, generation);
+
+		let contents = match fs::read(&path) {
+			Ok(contents) => contents,
+
+			Err(error) if error.kind() == io::ErrorKind::NotFound => return None,
+			Err(error) => {
+				event!(Level::WARN, "Failed to read layout snapshot {path:?}: {error}");
+
+				return None;
+			},
+		};
+
+		match serde_json::from_slice(&contents) {
+			Ok(snapshot) => {
+				event!(Level::INFO, "Restoring layout snapshot {path:?}");
+
+				Some(snapshot)
+			},
+
+			Err(error) => {
+				event!(Level::WARN, "Ignoring invalid layout snapshot {path:?}: {error}");
+
+				None
+			},
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::process;
+
+	use super::*;
+
+	/// Returns an empty temporary directory for the test with the given `name`.
+	fn temp_dir(name: &str) -> PathBuf {
+		let dir = env::temp_dir().join(format!("aquariwm-autosave-{}-{name}", process::id()));
+
+		match fs::remove_dir_all(&dir) {
+			Err(error) if error.kind() != io::ErrorKind::NotFound => panic!("{error}"),
+			_ => dir,
+		}
+	}
+
+	#[test]
+	fn scheduling() {
+		let dir = temp_dir("scheduling");
+		let start = Instant::now();
+		let at = |seconds: u64| start + Duration::from_secs(seconds);
+
+		let mut autosave = Autosave::new(&dir, Duration::from_secs(60));
+
+		// Nothing is written while the layout is unchanged.
+		assert_eq!(autosave.deadline(), None);
+		assert!(!autosave.tick(at(100), &0));
+		assert!(!autosave.shutdown(at(100), &0));
+
+		// A snapshot is written once the layout is idle...
+		autosave.mark_dirty(at(100));
+		autosave.mark_dirty(at(101));
+		assert_eq!(autosave.deadline(), Some(at(103)));
+		assert!(!autosave.tick(at(102), &1));
+		assert!(autosave.tick(at(103), &1));
+		assert!(!autosave.is_dirty());
+		assert_eq!(restore::<u32>(&dir), Some(1));
+
+		// ...but no sooner than 5 seconds after the last one, even on shutdown.
+		autosave.mark_dirty(at(104));
+		assert_eq!(autosave.deadline(), Some(at(108)));
+		assert!(!autosave.shutdown(at(107), &2));
+		assert!(autosave.shutdown(at(108), &2));
+		assert_eq!(restore::<u32>(&dir), Some(2));
+
+		// While the layout keeps changing, a snapshot is still written every interval.
+		for second in 200..260 {
+			autosave.mark_dirty(at(second));
+			assert!(!autosave.tick(at(second), &3));
+		}
+		autosave.mark_dirty(at(260));
+		assert!(autosave.tick(at(260), &3));
+
+		// Failures are logged, and retried later.
+		fs::remove_dir_all(&dir).unwrap();
+		fs::write(&dir, "not a directory").unwrap();
+
+		autosave.mark_dirty(at(300));
+		assert!(!autosave.tick(at(302), &4));
+		assert!(autosave.is_dirty());
+		assert_eq!(autosave.deadline(), Some(at(307)));
+
+		fs::remove_file(&dir).unwrap();
+		assert!(autosave.tick(at(307), &4));
+		assert_eq!(restore::<u32>(&dir), Some(4));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn rotation() {
+		let dir = temp_dir("rotation");
+
+		for snapshot in 1..=5u32 {
+			write_snapshot(&dir, &snapshot).unwrap();
+		}
+
+		let read = |generation| fs::read_to_string(snapshot_path(&dir, generation)).ok();
+		assert_eq!(
+			(0..=BACKUPS + 1).map(read).collect::<Vec<_>>(),
+			[Some("5"), Some("4"), Some("3"), Some("2"), None].map(|contents| contents.map(String::from))
+		);
+		assert!(!dir.join("state.json.tmp").exists());
+
+		// Invalid snapshots fall back to the newest valid backup.
+		fs::write(snapshot_path(&dir, 0), "{").unwrap();
+		fs::remove_file(snapshot_path(&dir, 1)).unwrap();
+		assert_eq!(restore::<u32>(&dir), Some(3));
+
+		for generation in 0..=BACKUPS {
+			fs::write(snapshot_path(&dir, generation), "[").unwrap();
+		}
+		assert_eq!(restore::<u32>(&dir), None);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	/// Tests that a layout's stacked groups' active children and its maximized children survive
+	/// being written to a snapshot and restored.
+	#[test]
+	fn layout_round_trip() {
+		use crate::layout::{LayoutSettings, Orientation};
+
+		let dir = temp_dir("layout-round-trip");
+		let settings = LayoutSettings::new().window_gap(0);
+		let apply = |layout: &mut TilingLayout<WindowId>| {
+			layout
+				.apply_changes(&mut |_, _, _, _, _, _| Ok::<_, ()>(()), &settings)
+				.unwrap();
+		};
+
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1200, 600, &settings);
+		layout.push_window_back(WindowId(1));
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| {
+				group.push_windows_back([WindowId(2), WindowId(3)]);
+				group.set_stacked(true);
+			})
+			.unwrap();
+		layout.push_window_back(WindowId(4));
+		apply(&mut layout);
+		let widths: Vec<_> = layout.iter().map(|node| node.width()).collect();
+
+		let (three, four) = (layout.id_of_window(&WindowId(3)).unwrap(), layout[2].id());
+		assert!(layout[1].unwrap_group_mut().set_active(three));
+		assert_eq!(layout.toggle_maximized(four, 20), Some(true));
+		apply(&mut layout);
+
+		write_snapshot(&dir, &layout).unwrap();
+		let mut restored: TilingLayout<WindowId> = restore(&dir).unwrap();
+
+		assert_eq!(restored[1].unwrap_group_ref().active(), Some(three));
+		assert_eq!(restored.maximized(), Some(four));
+		assert_eq!(restored[2].width(), 1200 - 2 * 20);
+
+		// The sizes from before the window was maximized are restored.
+		assert_eq!(restored.toggle_maximized(four, 20), Some(false));
+		apply(&mut restored);
+		assert_eq!(restored.iter().map(|node| node.width()).collect::<Vec<_>>(), widths);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -2,6 +2,23 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::path::PathBuf;
+
+#[cfg(all(feature = "x11", feature = "testing"))]
+use aquariwm::display_server::x11::test_client::{self, Hint};
+use aquariwm::{
+	appearance::Opacity,
+	config::{Config, ModifierChord, ThemeConfig},
+	display_server::DispatchMode,
+	fullscreen::FullscreenPolicy,
+	layout::{LeftoverPixels, Orientation, ReadingOrder, TabNeighbor},
+	output::{self, OutputAssignment, ScaleOverride},
+	pointer_warp::PointerWarp,
+	rules::Rule,
+	theme::Preset,
+	urgency::FocusStealing,
+	window_groups::GroupBehavior,
+};
 use clap::Parser;
 
 #[derive(Debug, Parser)]
@@ -16,12 +33,259 @@ pub struct Cli {
 	#[arg(long = "no-testing", alias = "no-test", overrides_with = "testing")]
 	pub no_testing: bool,
 
+	/// The configuration file, whose settings are overridden by those given on the command line.
+	///
+	/// Defaults to `$XDG_CONFIG_HOME/aquariwm/config.toml`.
+	#[arg(long, value_name = "PATH")]
+	pub config: Option<PathBuf>,
+
 	#[arg(long = "window-gap", alias = "gap")]
 	/// The gap between windows in a tiling layout.
 	pub window_gap: Option<u32>,
 
+	/// Groups new windows with the first window of the same class, instead of letting the layout
+	/// manager place them.
+	#[arg(long = "group-by-class")]
+	pub group_by_class: bool,
+
+	/// Sizes tiled windows to their resize increments, e.g. terminals to a whole number of cells,
+	/// rather than filling their tiles exactly.
+	#[arg(long = "honor-increments")]
+	pub honor_increments: bool,
+	/// What is done with the pixels left over when windows are sized to their resize increments:
+	/// `last-unconstrained` to give them to the last window in the group without increments,
+	/// `gaps` to widen the gaps between the windows, or `margin` to leave them after the last
+	/// window.
+	#[arg(long = "leftover-pixels", value_name = "POLICY")]
+	pub leftover_pixels: Option<LeftoverPixels>,
+	/// Gives a lone tiled window the whole screen, without the window gap around it.
+	#[arg(long = "smart-gaps")]
+	pub smart_gaps: bool,
+	/// Whether windows are read in `rows` or `columns` when they are cycled through by their
+	/// positions.
+	#[arg(long = "reading-order", value_name = "ORDER")]
+	pub reading_order: Option<ReadingOrder>,
+	/// Whether the tab to the `right` or `left` of a stacked group's active tab is shown when the
+	/// active tab is closed.
+	#[arg(long = "active-on-removal", value_name = "SIDE")]
+	pub active_on_removal: Option<TabNeighbor>,
+	/// The size of the strip each sibling of a window maximized within its group is shrunk to.
+	#[arg(long = "maximize-strip", value_name = "PIXELS")]
+	pub maximize_strip: Option<u32>,
+
+	/// The scale factor of an output, overriding the scale factor derived from its physical size.
+	///
+	/// This is given as `OUTPUT=FACTOR` (e.g. `HDMI-1=1.5`), or just `FACTOR` to apply to every
+	/// output without a more specific override. It may be given multiple times.
+	#[arg(long = "scale", value_name = "[OUTPUT=]FACTOR")]
+	pub scales: Vec<ScaleOverride>,
+
+	/// The output on which new windows are opened.
+	#[arg(long = "new-window-output", value_enum, default_value_t)]
+	pub new_window_output: NewWindowOutput,
+
+	/// Wraps around to the output on the opposite side when focusing or moving windows past the
+	/// last output in a direction.
+	#[arg(long = "wrap-outputs")]
+	pub wrap_outputs: bool,
+
+	/// Cycles through the windows on every output when switching windows with Super+Tab, rather
+	/// than only those on the active output.
+	#[arg(long = "switch-all-outputs")]
+	pub switch_all_outputs: bool,
+
+	/// The orientation of the layout on the primary output, overriding the layout manager's.
+	#[arg(long = "orientation", value_enum)]
+	pub orientation: Option<OrientationArg>,
+	/// The orientation of the layout when the primary output is taller than it is wide, e.g.
+	/// `top-to-bottom` for a vertical monitor.
+	///
+	/// Wider (or square) outputs use `--orientation`, or `left-to-right` if it isn't given.
+	#[arg(long = "portrait-orientation", value_enum)]
+	pub portrait_orientation: Option<OrientationArg>,
+	/// Reorients the layout according to `--orientation` and `--portrait-orientation` when the
+	/// primary output is rotated.
+	#[arg(long = "rotate-with-outputs")]
+	pub rotate_with_outputs: bool,
+
+	/// Pins a workspace to an output, given as `WORKSPACE=OUTPUT` (e.g. `1=HDMI-1`).
+	///
+	/// New windows open on their workspace's pinned output. While a pinned output is disconnected,
+	/// its workspaces are moved to the primary output. It may be given multiple times.
+	#[arg(long = "pin", value_name = "WORKSPACE=OUTPUT", value_parser = output::parse_pin)]
+	pub pins: Vec<(u32, String)>,
+
+	/// Dims unfocused tiled windows to the given opacity, between 0 and 1 (e.g. `0.92`).
+	///
+	/// This sets the `_NET_WM_WINDOW_OPACITY` property, so a compositor like picom is needed for it
+	/// to have an effect.
+	#[arg(long = "unfocused-opacity", value_name = "OPACITY")]
+	pub unfocused_opacity: Option<Opacity>,
+
+	/// Never dims windows of the given class, e.g. video players. It may be given multiple times.
+	#[arg(long = "no-dim", value_name = "CLASS")]
+	pub no_dim: Vec<String>,
+	/// Removes the border of a lone tiled window, giving it back once another window is tiled.
+	#[arg(long = "smart-borders")]
+	pub smart_borders: bool,
+	/// The built-in theme giving the colors of everything AquariWM draws: `default`, or
+	/// `high-contrast`.
+	#[arg(long = "theme", value_name = "PRESET")]
+	pub theme: Option<Preset>,
+
+	/// When windows which demand attention (e.g. authentication dialogs) may steal focus: `never`,
+	/// `always`, or the number of milliseconds since the last key press after which dialogs may.
+	#[arg(long = "focus-stealing", value_name = "POLICY")]
+	pub focus_stealing: Option<FocusStealing>,
+
+	/// What happens while a window is fullscreen: `normal`, or `game` to keep focus on it and ask
+	/// the compositor not to composite it. With `game-allow-urgent`, windows which demand attention
+	/// may still steal focus from it.
+	#[arg(long = "fullscreen", value_name = "POLICY")]
+	pub fullscreen_policy: Option<FullscreenPolicy>,
+
+	/// Warps the pointer to the center of the focused window: `never`, `on-focus-change`,
+	/// `on-window-move` (e.g. swapping it with the primary window), or `both`. The pointer isn't
+	/// warped if focus followed it, or if it is already inside the window.
+	#[arg(long = "pointer-warp", value_name = "POLICY")]
+	pub pointer_warp: Option<PointerWarp>,
+	/// Shows where the next window would be tiled while the given modifiers, like `Super+Control`,
+	/// are held.
+	#[arg(long = "insertion-preview", value_name = "MODIFIERS")]
+	pub insertion_preview: Option<ModifierChord>,
+	/// The number of seconds between pings of every window to detect hung clients, whose windows
+	/// are shown with a red border until they answer. Windows are also pinged when they are
+	/// focused; `0`, the default, only pings them then.
+	#[arg(long = "ping-interval", value_name = "SECONDS")]
+	pub ping_interval: Option<u64>,
+	/// Which actions apply to the whole window group of an application's window: `ignore`,
+	/// `iconify-together`, `move-together` (to another output), or `full`, which also marks a
+	/// group's leader as urgent when any of its windows is.
+	#[arg(long = "group-behavior", value_name = "BEHAVIOR")]
+	pub group_behavior: Option<GroupBehavior>,
+
+	/// Changes where windows matching a rule are placed: `<action>:<conditions>`, where the action
+	/// is `float`, `tile`, or `output=<name>`, and the conditions are `class=<glob>` and
+	/// `title=<glob>`, followed by `retitle` to re-evaluate the rule whenever the window's title
+	/// changes. It may be given multiple times; the first matching rule applies.
+	#[arg(long = "rule", value_name = "RULE")]
+	pub rules: Vec<Rule>,
+
+	/// Restores the layout from the newest valid snapshot written before AquariWM last exited.
+	#[arg(long)]
+	pub restore: bool,
+	/// Waits for the X server to come back and reconnects to it if the connection to it is lost,
+	/// rather than exiting. The layout is restored from the snapshot written when it was lost.
+	#[arg(long)]
+	pub reconnect: bool,
+	/// Measures how long mapping windows, changing focus with the keyboard, and unmapping windows
+	/// take, exporting the latencies in the `_AQUARIWM_LATENCY` property of the root window and
+	/// logging them periodically at the DEBUG level.
+	#[arg(long = "latency-metrics")]
+	pub latency_metrics: bool,
+	/// Maps windows on other screens than AquariWM's, as on multi-screen ("Zaphod") X setups,
+	/// without managing them when they ask to be mapped, rather than leaving them alone.
+	#[arg(long = "map-other-screens")]
+	pub map_other_screens: bool,
+	/// Doesn't export DISPLAY into the systemd user manager and the D-Bus activation environment
+	/// once AquariWM has registered. While testing, the nested X server's display is exported, so
+	/// this keeps programs started by services on the outer session's display.
+	#[arg(long = "no-session-env")]
+	pub no_session_env: bool,
+
+	/// The maximum number of seconds between a change to the layout and it being written to a
+	/// snapshot. Defaults to 60.
+	#[arg(long = "autosave-interval", value_name = "SECONDS")]
+	pub autosave_interval: Option<u64>,
+
+	/// The minimum number of milliseconds between re-tiles of the layout caused by bursts of
+	/// updates, like monitors being (un)plugged. Defaults to 16.
+	#[arg(long = "frame-interval", value_name = "MILLISECONDS")]
+	pub frame_interval: Option<u64>,
+	/// The number of milliseconds re-tiling the layout waits for more windows while windows are
+	/// mapped and unmapped in quick succession, e.g. as a session is restored. Defaults to 30, and
+	/// is capped at 100.
+	#[arg(long = "map-debounce", value_name = "MILLISECONDS")]
+	pub map_debounce: Option<u64>,
+	/// The number of milliseconds windows without clear signals that they are normal windows, like
+	/// splash screens, float for before they are tiled, so that windows which are unmapped again
+	/// straight away don't re-tile the layout. Defaults to 150; `0` tiles them straight away.
+	#[arg(long = "admission-delay", value_name = "MILLISECONDS")]
+	pub admission_delay: Option<u64>,
+	/// The number of windows reconfigured per event loop iteration when re-tiling the layout, so
+	/// that very large layouts don't hold up input handling. `0`, the default, reconfigures every
+	/// window straight away.
+	#[arg(long = "apply-chunk-size", value_name = "WINDOWS")]
+	pub apply_chunk_size: Option<usize>,
+	/// The maximum number of times per second a tiled window is configured to its tile again when
+	/// it keeps asking for another geometry, so that misbehaving clients can't cause a configure
+	/// loop. Defaults to 10.
+	#[arg(long = "configure-rate-limit", value_name = "TIMES")]
+	pub configure_rate_limit: Option<u32>,
+
+	/// Logs the changes AquariWM would make to windows instead of making them.
+	#[arg(long = "dry-run")]
+	pub dry_run: bool,
+	/// Observes windows alongside another running window manager, without registering as the
+	/// window manager, and logs the changes AquariWM would make to them.
+	///
+	/// This implies `--dry-run`.
+	#[arg(long = "observe-only")]
+	pub observe_only: bool,
+
+	/// Records every input AquariWM acts on to the given file, so that the session can be replayed
+	/// against the layout model with `--replay`.
+	#[arg(long, value_name = "PATH")]
+	pub record: Option<PathBuf>,
+	/// Replays a recording made with `--record` against the layout model, without connecting to a
+	/// display server.
+	///
+	/// The state is printed after each step, and replaying stops at the first step which doesn't
+	/// reach the recorded state. The recording should be replayed with the same configuration it
+	/// was recorded with.
+	#[cfg(feature = "x11")]
+	#[arg(long, value_name = "PATH", conflicts_with = "record")]
+	pub replay: Option<PathBuf>,
+
+	/// The display server to run AquariWM with, which may only be left out with `--replay`.
 	#[command(subcommand)]
-	pub subcommand: Subcommand,
+	pub subcommand: Option<Subcommand>,
+}
+
+/// The output on which new windows are opened when no workspaces are [pinned].
+///
+/// [pinned]: Cli::pins
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, clap::ValueEnum)]
+pub enum NewWindowOutput {
+	/// The output containing the focused window.
+	#[default]
+	Focus,
+	/// The output containing the pointer.
+	Pointer,
+	/// The primary output.
+	Primary,
+}
+
+/// An [orientation] of the layout.
+///
+/// [orientation]: Orientation
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, clap::ValueEnum)]
+pub enum OrientationArg {
+	LeftToRight,
+	TopToBottom,
+	RightToLeft,
+	BottomToTop,
+}
+
+impl From<OrientationArg> for Orientation {
+	fn from(orientation: OrientationArg) -> Self {
+		match orientation {
+			OrientationArg::LeftToRight => Self::LeftToRight,
+			OrientationArg::TopToBottom => Self::TopToBottom,
+			OrientationArg::RightToLeft => Self::RightToLeft,
+			OrientationArg::BottomToTop => Self::BottomToTop,
+		}
+	}
 }
 
 impl Cli {
@@ -38,6 +302,83 @@ impl Cli {
 		#[cfg(not(feature = "testing"))]
 		false
 	}
+
+	/// Returns whether AquariWM changes windows or only logs what it would do.
+	pub const fn dispatch_mode(&self) -> DispatchMode {
+		match (self.observe_only, self.dry_run) {
+			(true, _) => DispatchMode::ObserveOnly,
+			(false, true) => DispatchMode::DryRun,
+			(false, false) => DispatchMode::Live,
+		}
+	}
+
+	/// Returns the policy for choosing which output new windows open on.
+	pub fn output_assignment(&self) -> OutputAssignment {
+		if !self.pins.is_empty() {
+			return OutputAssignment::Pinned(self.pins.iter().cloned().collect());
+		}
+
+		match self.new_window_output {
+			NewWindowOutput::Focus => OutputAssignment::FollowFocus,
+			NewWindowOutput::Pointer => OutputAssignment::FollowPointer,
+			NewWindowOutput::Primary => OutputAssignment::Primary,
+		}
+	}
+
+	/// Returns the configuration file's path, if it is given or there is a default path.
+	pub fn config_path(&self) -> Option<PathBuf> {
+		self.config.clone().or_else(Config::default_path)
+	}
+
+	/// Returns the settings given on the command line, which override those in the configuration
+	/// file.
+	pub fn overrides(&self) -> Config {
+		// Flags can only turn settings on, so they are left to the configuration file if not given.
+		let flag = |given: bool| given.then_some(true);
+
+		Config {
+			window_gap: self.window_gap,
+			group_by_class: flag(self.group_by_class),
+			honor_increments: flag(self.honor_increments),
+			leftover_pixels: self.leftover_pixels,
+			smart_gaps: flag(self.smart_gaps),
+			reading_order: self.reading_order,
+			active_on_removal: self.active_on_removal,
+			maximize_strip: self.maximize_strip,
+
+			orientation: self.orientation.map(Orientation::from),
+			portrait_orientation: self.portrait_orientation.map(Orientation::from),
+			rotate_with_outputs: flag(self.rotate_with_outputs),
+			wrap_outputs: flag(self.wrap_outputs),
+			switch_all_outputs: flag(self.switch_all_outputs),
+
+			unfocused_opacity: self.unfocused_opacity,
+			no_dim: self.no_dim.clone(),
+			smart_borders: flag(self.smart_borders),
+			theme: ThemeConfig {
+				preset: self.theme,
+				..ThemeConfig::default()
+			},
+
+			focus_stealing: self.focus_stealing,
+			fullscreen: self.fullscreen_policy,
+			pointer_warp: self.pointer_warp,
+			insertion_preview: self.insertion_preview.clone(),
+			ping_interval: self.ping_interval,
+			group_behavior: self.group_behavior,
+			rules: self.rules.clone(),
+
+			scales: self.scales.clone(),
+			frame_interval: self.frame_interval,
+			map_debounce: self.map_debounce,
+			admission_delay: self.admission_delay,
+			apply_chunk_size: self.apply_chunk_size,
+			configure_rate_limit: self.configure_rate_limit,
+			autosave_interval: self.autosave_interval,
+
+			..Config::default()
+		}
+	}
 }
 
 #[derive(Debug, clap::Subcommand)]
@@ -48,4 +389,36 @@ pub enum Subcommand {
 	/// Launch AquariWM running in X11 mode.
 	#[cfg(feature = "x11")]
 	X11,
+
+	/// Run a minimal X client for AquariWM to manage while testing.
+	#[cfg(all(feature = "x11", feature = "testing"))]
+	#[command(hide = true)]
+	TestClient {
+		/// The class given to the client's window in its `WM_CLASS` property.
+		#[arg(long, default_value = test_client::DEFAULT_CLASS)]
+		class: String,
+		/// A size hint given to the client's window, as `min=WIDTHxHEIGHT` or `max=WIDTHxHEIGHT`.
+		/// It may be given multiple times.
+		#[arg(long = "hints", value_name = "HINT")]
+		hints: Vec<Hint>,
+		/// The process ID advertised in the window's `_NET_WM_PID` property, instead of the
+		/// client's own.
+		#[arg(long)]
+		pid: Option<u32>,
+		/// The machine named in the window's `WM_CLIENT_MACHINE` property, instead of this one.
+		#[arg(long)]
+		client_machine: Option<String>,
+		/// Stops handling events once the window is mapped, as though the client had hung, so that
+		/// it doesn't answer pings.
+		#[arg(long)]
+		hang: bool,
+		/// Creates a second window in the same window group, both naming the first window as their
+		/// group leader.
+		#[arg(long)]
+		group: bool,
+		/// Creates the window override-redirect, like a menu or a tooltip, so that it is never
+		/// managed, and maps it itself.
+		#[arg(long)]
+		override_redirect: bool,
+	},
 }
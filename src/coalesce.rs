@@ -0,0 +1,398 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Coalescing of bursts of updates, so that expensive work like re-tiling the layout is done at
+//! most once per [interval] with the latest update, rather than once for every update.
+//!
+//! This is useful wherever updates can arrive faster than they are worth acting on: pointer motion
+//! while dragging, storms of RandR notifications while monitors are (un)plugged, or windows being
+//! mapped and unmapped in quick succession.
+//!
+//! Storms of windows being mapped and unmapped, e.g. as a session is restored, are instead
+//! [debounced]: the re-tile waits for the storm to die down.
+//!
+//! Work which is only worth doing once things have settled, like [compacting] the layout, waits
+//! for an [idle] period instead.
+//!
+//! [interval]: Coalescer::new
+//! [debounced]: PendingApply
+//! [compacting]: crate::layout::TilingLayout::compact
+//! [idle]: IdleTimer
+
+use std::time::{Duration, Instant};
+
+/// The default minimum time between two updates being acted on: about one frame at 60Hz.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(16);
+
+/// The default time a [pending apply] waits for more windows to be mapped or unmapped.
+///
+/// [pending apply]: PendingApply
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(30);
+/// The longest a [pending apply] is ever deferred, so that the first window of a storm still
+/// appears promptly.
+///
+/// [pending apply]: PendingApply
+pub const MAX_DEFERRAL: Duration = Duration::from_millis(100);
+/// How long the layout must go unchanged before it is [compacted].
+///
+/// [compacted]: crate::layout::TilingLayout::compact
+pub const COMPACT_IDLE: Duration = Duration::from_secs(5);
+
+/// Holds on to the latest of a burst of updates until it is due to be acted on.
+///
+/// An update is due as soon as it is [pushed] if no update has been acted on for at least the
+/// interval; otherwise, it is due once the interval has passed since the last update was acted on.
+/// Updates pushed while another is pending replace it.
+///
+/// Times are passed in explicitly rather than read from the clock, so that the coalescer can be
+/// driven by any timer.
+///
+/// [pushed]: Self::push
+#[derive(Debug, Clone)]
+pub struct Coalescer<T> {
+	/// The minimum time between two updates being acted on.
+	interval: Duration,
+
+	/// The latest pending update and when the first update since the last one acted on was pushed.
+	pending: Option<(Instant, T)>,
+	/// When an update was last acted on.
+	last_flush: Option<Instant>,
+}
+
+impl<T> Default for Coalescer<T> {
+	#[inline]
+	fn default() -> Self {
+		Self::new(DEFAULT_INTERVAL)
+	}
+}
+
+impl<T> Coalescer<T> {
+	/// Creates a coalescer which acts on updates at most once every `interval`.
+	pub const fn new(interval: Duration) -> Self {
+		Self {
+			interval,
+
+			pending: None,
+			last_flush: None,
+		}
+	}
+
+	/// Returns the minimum time between two updates being acted on.
+	#[inline(always)]
+	pub const fn interval(&self) -> Duration {
+		self.interval
+	}
+
+	/// Returns whether there is an update waiting to be acted on.
+	#[inline(always)]
+	pub const fn is_pending(&self) -> bool {
+		self.pending.is_some()
+	}
+
+	/// Records the given `update`, pushed at the given time, replacing any pending update.
+	pub fn push(&mut self, now: Instant, update: T) {
+		let since = self.pending.take().map_or(now, |(since, _)| since);
+
+		self.pending = Some((since, update));
+	}
+
+	/// Returns when the pending update is due to be acted on, or [`None`] if there is no pending
+	/// update.
+	pub fn deadline(&self) -> Option<Instant> {
+		let (since, _) = self.pending.as_ref()?;
+
+		Some(match self.last_flush {
+			Some(last_flush) => (*since).max(last_flush + self.interval),
+			None => *since,
+		})
+	}
+
+	/// Takes the pending update if it is [due].
+	///
+	/// [due]: Self::deadline
+	pub fn poll(&mut self, now: Instant) -> Option<T> {
+		match self.deadline() {
+			Some(deadline) if now >= deadline => self.flush(now),
+			_ => None,
+		}
+	}
+
+	/// Takes the pending update, whether it is due or not.
+	///
+	/// This is used to act on the final update of a burst, e.g. when a drag ends.
+	pub fn flush(&mut self, now: Instant) -> Option<T> {
+		let (_, update) = self.pending.take()?;
+		self.last_flush = Some(now);
+
+		Some(update)
+	}
+
+	/// Discards the pending update, if any.
+	pub fn cancel(&mut self) -> Option<T> {
+		self.pending.take().map(|(_, update)| update)
+	}
+}
+
+/// A deferred re-tile of the layout, applied once a storm of windows being mapped and unmapped has
+/// died down.
+///
+/// Each window is admitted into the layout as soon as it is mapped or unmapped, so that their order
+/// is kept, but applying the layout (and configuring every window) is [deferred]: it is due once no
+/// window has been deferred for the debounce delay, but never later than [`MAX_DEFERRAL`] after the
+/// first. Events which need consistent geometry [flush] it early.
+///
+/// [deferred]: Self::defer
+/// [flush]: Self::flush
+#[derive(Debug, Clone)]
+pub struct PendingApply {
+	/// How long to wait for more windows to be mapped or unmapped.
+	delay: Duration,
+
+	/// When the first and latest windows since the last apply were deferred.
+	pending: Option<(Instant, Instant)>,
+	/// The number of applies which have been due.
+	applies: u64,
+}
+
+impl Default for PendingApply {
+	#[inline]
+	fn default() -> Self {
+		Self::new(DEFAULT_DEBOUNCE)
+	}
+}
+
+impl PendingApply {
+	/// Creates a pending apply which waits for `delay` after each window for more windows, capped
+	/// at [`MAX_DEFERRAL`].
+	pub fn new(delay: Duration) -> Self {
+		Self {
+			delay: delay.min(MAX_DEFERRAL),
+
+			pending: None,
+			applies: 0,
+		}
+	}
+
+	/// Returns how long the apply waits for more windows to be mapped or unmapped.
+	#[inline(always)]
+	pub const fn delay(&self) -> Duration {
+		self.delay
+	}
+
+	/// Returns whether there is an apply waiting to be made.
+	#[inline(always)]
+	pub const fn is_pending(&self) -> bool {
+		self.pending.is_some()
+	}
+
+	/// Returns the number of applies which have been [polled] or [flushed].
+	///
+	/// [polled]: Self::poll
+	/// [flushed]: Self::flush
+	#[inline(always)]
+	pub const fn applies(&self) -> u64 {
+		self.applies
+	}
+
+	/// Defers applying the layout for a window mapped or unmapped at the given time.
+	pub fn defer(&mut self, now: Instant) {
+		let first = self.pending.map_or(now, |(first, _)| first);
+
+		self.pending = Some((first, now));
+	}
+
+	/// Returns when the apply is due, or [`None`] if there is no pending apply.
+	pub fn deadline(&self) -> Option<Instant> {
+		let (first, latest) = self.pending?;
+
+		Some((latest + self.delay).min(first + MAX_DEFERRAL))
+	}
+
+	/// Returns whether the layout is to be applied now, taking the pending apply if it is [due].
+	///
+	/// [due]: Self::deadline
+	pub fn poll(&mut self, now: Instant) -> bool {
+		match self.deadline() {
+			Some(deadline) if now >= deadline => self.flush(),
+			_ => false,
+		}
+	}
+
+	/// Returns whether the layout is to be applied now, taking the pending apply whether it is due
+	/// or not.
+	///
+	/// This is used before events which need the windows' geometry to be consistent with the
+	/// layout, like a tiled window's configure request.
+	pub const fn flush(&mut self) -> bool {
+		let flushed = self.pending.take().is_some();
+		if flushed {
+			self.applies += 1;
+		}
+
+		flushed
+	}
+}
+
+/// A timer which is due once nothing has [touched] it for its idle period.
+///
+/// Unlike a [pending apply], it can be put off indefinitely: it only fires once things have
+/// settled.
+///
+/// [touched]: Self::touch
+/// [pending apply]: PendingApply
+#[derive(Debug, Clone)]
+pub struct IdleTimer {
+	/// How long nothing must touch the timer for before it is due.
+	idle: Duration,
+
+	/// When the timer was last touched, if it hasn't fired since.
+	touched: Option<Instant>,
+}
+
+impl IdleTimer {
+	/// Creates a timer which is due once it has been left untouched for `idle`.
+	#[inline]
+	pub const fn new(idle: Duration) -> Self {
+		Self { idle, touched: None }
+	}
+
+	/// Puts the timer off until it has been left untouched for its idle period from the given
+	/// time.
+	#[inline]
+	pub const fn touch(&mut self, now: Instant) {
+		self.touched = Some(now);
+	}
+
+	/// Returns when the timer is due, or [`None`] if it hasn't been touched since it last fired.
+	pub fn deadline(&self) -> Option<Instant> {
+		self.touched.map(|touched| touched + self.idle)
+	}
+
+	/// Returns whether the timer fires now, taking it if it is [due].
+	///
+	/// [due]: Self::deadline
+	pub fn poll(&mut self, now: Instant) -> bool {
+		match self.deadline() {
+			Some(deadline) if now >= deadline => self.touched.take().is_some(),
+			_ => false,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const INTERVAL: Duration = Duration::from_millis(16);
+
+	/// Returns the time the given number of milliseconds after `start`.
+	fn at(start: Instant, millis: u64) -> Instant {
+		start + Duration::from_millis(millis)
+	}
+
+	#[test]
+	fn latest_value() {
+		let start = Instant::now();
+		let mut coalescer = Coalescer::new(INTERVAL);
+
+		assert_eq!(coalescer.deadline(), None);
+		assert_eq!(coalescer.poll(start), None);
+
+		// An update after an idle period is due immediately.
+		coalescer.push(start, 1);
+		assert_eq!(coalescer.deadline(), Some(start));
+		assert_eq!(coalescer.poll(start), Some(1));
+		assert!(!coalescer.is_pending());
+
+		// Updates within the interval are held back, and only the latest is acted on.
+		coalescer.push(at(start, 2), 2);
+		coalescer.push(at(start, 5), 3);
+		coalescer.push(at(start, 9), 4);
+		assert_eq!(coalescer.deadline(), Some(at(start, 16)));
+		assert_eq!(coalescer.poll(at(start, 15)), None);
+		assert_eq!(coalescer.poll(at(start, 16)), Some(4));
+
+		// The interval is measured from when the last update was acted on, even if that was late.
+		coalescer.push(at(start, 20), 5);
+		assert_eq!(coalescer.poll(at(start, 31)), None);
+		assert_eq!(coalescer.poll(at(start, 40)), Some(5));
+		coalescer.push(at(start, 41), 6);
+		assert_eq!(coalescer.deadline(), Some(at(start, 56)));
+
+		// A cancelled update is never acted on.
+		assert_eq!(coalescer.cancel(), Some(6));
+		assert_eq!(coalescer.poll(at(start, 100)), None);
+	}
+
+	#[test]
+	fn flush() {
+		let start = Instant::now();
+		let mut coalescer = Coalescer::new(INTERVAL);
+
+		coalescer.push(start, 1);
+		assert_eq!(coalescer.poll(start), Some(1));
+
+		// The final update is taken even though it isn't due yet...
+		coalescer.push(at(start, 4), 2);
+		assert_eq!(coalescer.flush(at(start, 4)), Some(2));
+		assert_eq!(coalescer.flush(at(start, 5)), None);
+
+		// ...and the next update is held back by the interval from then.
+		coalescer.push(at(start, 10), 3);
+		assert_eq!(coalescer.deadline(), Some(at(start, 20)));
+	}
+
+	#[test]
+	fn debounce() {
+		let start = Instant::now();
+		let mut pending = PendingApply::new(Duration::from_millis(30));
+
+		assert_eq!(pending.deadline(), None);
+		assert!(!pending.poll(start));
+
+		// Each window pushes the apply back...
+		pending.defer(start);
+		assert_eq!(pending.deadline(), Some(at(start, 30)));
+		pending.defer(at(start, 20));
+		assert!(!pending.poll(at(start, 30)));
+		assert_eq!(pending.deadline(), Some(at(start, 50)));
+
+		// ...but never further than the cap from the first.
+		for millis in (40..=140).step_by(20) {
+			pending.defer(at(start, millis));
+		}
+		assert_eq!(pending.deadline(), Some(at(start, 100)));
+		assert!(pending.poll(at(start, 100)));
+		assert!(!pending.is_pending());
+
+		// A flush applies the layout straight away, once.
+		pending.defer(at(start, 150));
+		assert!(pending.flush());
+		assert!(!pending.flush());
+		assert_eq!(pending.applies(), 2);
+
+		// The delay itself is capped too.
+		assert_eq!(PendingApply::new(Duration::from_secs(1)).delay(), MAX_DEFERRAL);
+	}
+
+	#[test]
+	fn idle() {
+		let start = Instant::now();
+		let mut timer = IdleTimer::new(Duration::from_millis(50));
+
+		assert_eq!(timer.deadline(), None);
+		assert!(!timer.poll(start));
+
+		// Each touch puts the timer off, however long that takes.
+		for millis in (0..=200).step_by(40) {
+			timer.touch(at(start, millis));
+			assert!(!timer.poll(at(start, millis + 10)));
+		}
+		assert_eq!(timer.deadline(), Some(at(start, 250)));
+
+		// It fires once, and not again until it is touched.
+		assert!(timer.poll(at(start, 250)));
+		assert!(!timer.poll(at(start, 500)));
+	}
+}
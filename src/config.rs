@@ -0,0 +1,1517 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The configuration file, `$XDG_CONFIG_HOME/aquariwm/config.toml`.
+//!
+//! The file takes the same settings as the command line, which override it, as well as [key
+//! bindings] and commands to [run at startup]:
+//!
+//! ```toml
+//! window-gap = 10
+//! group-by-class = true
+//! honor-increments = true
+//! layout-manager = "Stack"
+//! orientation = "left-to-right"
+//! portrait-orientation = "top-to-bottom"
+//! unfocused-opacity = 0.92
+//! no-dim = ["mpv"]
+//! background-color = "#1d1f21"
+//! wallpaper = "/usr/share/backgrounds/default.png"
+//! focus-stealing = 500
+//! pointer-warp = "both"
+//! insertion-preview = "Super+Control"
+//! ping-interval = 30
+//! group-behavior = "iconify-together"
+//! rules = ["float:class=pavucontrol", "float:title=*Picture-in-Picture*,retitle"]
+//! autostart = ["picom", "nm-applet"]
+//!
+//! [key-bindings]
+//! "Super+Shift+Return" = "spawn alacritty"
+//! "Super+h" = "resize-primary -50"
+//! "Super+n" = "none"
+//!
+//! [workspaces.1]
+//! layout-manager = "Spiral"
+//!
+//! [theme]
+//! preset = "high-contrast"
+//! focused-border = "#ffcc00"
+//! ```
+//!
+//! Workspaces are given by name or by their position, counting from 1.
+//!
+//! Every setting is optional, and missing settings take their defaults. Unknown settings are
+//! warned about rather than rejected, so that a configuration file can be shared with newer
+//! versions of AquariWM.
+//!
+//! The file can be [reloaded] while AquariWM is running (with a key binding or `SIGHUP`): the
+//! [changes] are applied with a single re-tile of the layout, except for those to settings which
+//! only take effect when AquariWM is [restarted].
+//!
+//! [key bindings]: KeyBindings
+//! [run at startup]: Config::autostart
+//! [reloaded]: crate::action::Action::ReloadConfig
+//! [changes]: Changes
+//! [restarted]: Changes::restart
+
+use std::{
+	collections::BTreeMap,
+	env,
+	fmt,
+	fmt::{Display, Formatter},
+	fs,
+	io,
+	marker::PhantomData,
+	num::NonZeroUsize,
+	path::{Path, PathBuf},
+	str::FromStr,
+	time::Duration,
+};
+
+use serde::{de, Deserialize, Deserializer};
+use thiserror::Error;
+use tracing::{event, Level};
+
+use crate::{
+	action::{self, Action},
+	appearance::Opacity,
+	display_server::Options,
+	fullscreen::FullscreenPolicy,
+	layout::{managers::BuiltIn, InsertionStrategy, LeftoverPixels, Orientation, ReadingOrder, TabNeighbor},
+	output::{CloneOverlap, DefaultOrientation, OutputEdges, ScaleOverride},
+	pointer_warp::PointerWarp,
+	rules::Rule,
+	status::WORKSPACE,
+	switcher::Scope,
+	theme::{Preset, Rgba, Slot},
+	urgency::FocusStealing,
+	wallpaper::Color,
+	window_groups::GroupBehavior,
+};
+
+/// The top-level settings, which are suggested when an unknown setting is found.
+const SETTINGS: &[&str] = &[
+	"window-gap",
+	"group-by-class",
+	"honor-increments",
+	"leftover-pixels",
+	"smart-gaps",
+	"reading-order",
+	"active-on-removal",
+	"layout-manager",
+	"workspaces",
+	"orientation",
+	"portrait-orientation",
+	"rotate-with-outputs",
+	"wrap-outputs",
+	"switch-all-outputs",
+	"unfocused-opacity",
+	"no-dim",
+	"smart-borders",
+	"theme",
+	"background-color",
+	"wallpaper",
+	"focus-stealing",
+	"fullscreen",
+	"pointer-warp",
+	"insertion-preview",
+	"ping-interval",
+	"ping-timeout",
+	"unresponsive-border-color",
+	"force-kill-process",
+	"group-behavior",
+	"group-across-outputs",
+	"rules",
+	"autostart",
+	"key-bindings",
+	"scales",
+	"clone-overlap",
+	"frame-interval",
+	"map-debounce",
+	"admission-delay",
+	"apply-chunk-size",
+	"configure-rate-limit",
+	"autosave-interval",
+];
+
+/// The names of the [commands] other than [actions].
+///
+/// [commands]: Command
+/// [actions]: Command::Action
+const COMMANDS: &[&str] = &[
+	"close-window",
+	"spawn-terminal",
+	"spawn",
+	"next-window",
+	"previous-window",
+	"none",
+];
+
+/// Named keys, with their keysyms, which can be bound in addition to printable characters.
+///
+/// Keysyms are shared by X11 and XKB, so they don't depend on the display server.
+const KEYS: &[(&str, u32)] = &[
+	("Return", 0xff0d),
+	("Tab", 0xff09),
+	("Escape", 0xff1b),
+	("space", 0x0020),
+	("BackSpace", 0xff08),
+	("Delete", 0xffff),
+	("Insert", 0xff63),
+	("Home", 0xff50),
+	("End", 0xff57),
+	("Page_Up", 0xff55),
+	("Page_Down", 0xff56),
+	("Left", 0xff51),
+	("Up", 0xff52),
+	("Right", 0xff53),
+	("Down", 0xff54),
+	("Print", 0xff61),
+	("F1", 0xffbe),
+	("F2", 0xffbf),
+	("F3", 0xffc0),
+	("F4", 0xffc1),
+	("F5", 0xffc2),
+	("F6", 0xffc3),
+	("F7", 0xffc4),
+	("F8", 0xffc5),
+	("F9", 0xffc6),
+	("F10", 0xffc7),
+	("F11", 0xffc8),
+	("F12", 0xffc9),
+	("comma", 0x002c),
+	("period", 0x002e),
+	("minus", 0x002d),
+	("equal", 0x003d),
+	("plus", 0x002b),
+	("slash", 0x002f),
+	("backslash", 0x005c),
+	("semicolon", 0x003b),
+	("apostrophe", 0x0027),
+	("grave", 0x0060),
+	("bracketleft", 0x005b),
+	("bracketright", 0x005d),
+];
+
+/// The contents of the configuration file.
+///
+/// Settings which aren't given are [`None`] (or empty), so that configurations can be [layered]:
+/// the command line's settings are a configuration too.
+///
+/// [layered]: Self::over
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+	/// The gap between windows in the tiling layout.
+	pub window_gap: Option<u32>,
+	/// Whether new windows are grouped with the first window of the same class.
+	pub group_by_class: Option<bool>,
+	/// Whether tiled windows are sized to their resize increments, e.g. terminals to whole cells.
+	pub honor_increments: Option<bool>,
+	/// What is done with the pixels left over when windows are sized to their resize increments.
+	#[serde(deserialize_with = "parsed")]
+	pub leftover_pixels: Option<LeftoverPixels>,
+	/// Whether a lone tiled window fills the whole screen, without the window gap around it.
+	pub smart_gaps: Option<bool>,
+	/// Whether windows are read in `rows` or `columns` when they are cycled through by their
+	/// positions.
+	#[serde(deserialize_with = "parsed")]
+	pub reading_order: Option<ReadingOrder>,
+	/// Whether the tab to the `right` or `left` of a stacked group's active tab is shown when the
+	/// active tab is closed.
+	#[serde(deserialize_with = "parsed")]
+	pub active_on_removal: Option<TabNeighbor>,
+	/// The size of the strip each sibling of a window maximized within its group is shrunk to.
+	pub maximize_strip: Option<u32>,
+	/// The layout manager of workspaces which aren't given one in [`workspaces`].
+	///
+	/// [`workspaces`]: Self::workspaces
+	#[serde(deserialize_with = "parsed")]
+	pub layout_manager: Option<BuiltIn>,
+	/// The settings of individual workspaces, by their names or positions (counting from 1).
+	pub workspaces: BTreeMap<String, WorkspaceConfig>,
+
+	/// The orientation of the layout on the primary output.
+	#[serde(deserialize_with = "parsed")]
+	pub orientation: Option<Orientation>,
+	/// The orientation of the layout when the primary output is taller than it is wide.
+	#[serde(deserialize_with = "parsed")]
+	pub portrait_orientation: Option<Orientation>,
+	/// Whether the layout is reoriented when the primary output is rotated.
+	pub rotate_with_outputs: Option<bool>,
+	/// Whether focusing or moving windows past the last output in a direction wraps around.
+	pub wrap_outputs: Option<bool>,
+	/// Whether switching windows cycles through the windows on every output.
+	pub switch_all_outputs: Option<bool>,
+
+	/// The opacity to which unfocused tiled windows are dimmed.
+	#[serde(deserialize_with = "parsed")]
+	pub unfocused_opacity: Option<Opacity>,
+	/// The classes of windows which are never dimmed.
+	pub no_dim: Vec<String>,
+	/// Whether a lone tiled window has its border removed.
+	pub smart_borders: Option<bool>,
+	/// The colors of everything AquariWM draws itself.
+	pub theme: ThemeConfig,
+	/// The color with which the background is filled wherever the wallpaper doesn't cover it.
+	#[serde(deserialize_with = "parsed")]
+	pub background_color: Option<Color>,
+	/// The path of a PNG image shown as the wallpaper, scaled to cover each output.
+	pub wallpaper: Option<PathBuf>,
+
+	/// When windows which demand attention may steal focus.
+	#[serde(deserialize_with = "parsed")]
+	pub focus_stealing: Option<FocusStealing>,
+	/// What happens while a window is fullscreen.
+	#[serde(deserialize_with = "parsed")]
+	pub fullscreen: Option<FullscreenPolicy>,
+	/// When the pointer is warped to the focused window.
+	#[serde(deserialize_with = "parsed")]
+	pub pointer_warp: Option<PointerWarp>,
+	/// The modifiers which show where the next window would be tiled while they are held.
+	#[serde(deserialize_with = "parsed")]
+	pub insertion_preview: Option<ModifierChord>,
+	/// The number of seconds between pings of every window to detect hung clients, or `0` to only
+	/// ping windows when they are focused.
+	pub ping_interval: Option<u64>,
+	/// The number of milliseconds a ping may go unanswered before the window's client is marked
+	/// unresponsive.
+	pub ping_timeout: Option<u64>,
+	/// The border color of the windows of unresponsive clients, which the [theme]'s `hung-border`
+	/// takes precedence over.
+	///
+	/// [theme]: Self::theme
+	#[serde(deserialize_with = "parsed")]
+	pub unresponsive_border_color: Option<Color>,
+	/// Whether force killing a client also kills its process, rather than only disconnecting it.
+	pub force_kill_process: Option<bool>,
+	/// Which actions apply to the whole window group of the window they are performed on.
+	#[serde(deserialize_with = "parsed")]
+	pub group_behavior: Option<GroupBehavior>,
+	/// Whether actions on window groups also apply to windows on other outputs.
+	pub group_across_outputs: Option<bool>,
+	/// Rules which change where matching windows are placed, the first matching rule applying.
+	#[serde(deserialize_with = "parsed_list")]
+	pub rules: Vec<Rule>,
+
+	/// Shell commands which are run when AquariWM starts, but not when the configuration is
+	/// reloaded.
+	pub autostart: Vec<String>,
+	/// Key bindings, which override the default key bindings of the same keys.
+	pub key_bindings: KeyBindings,
+
+	/// The scale factor overrides of outputs.
+	#[serde(deserialize_with = "parsed_list")]
+	pub scales: Vec<ScaleOverride>,
+	/// The fraction of the smaller of two monitors which must be overlapped by the other for them
+	/// to be treated as clones of each other.
+	#[serde(deserialize_with = "parsed")]
+	pub clone_overlap: Option<CloneOverlap>,
+	/// The minimum number of milliseconds between re-tiles caused by bursts of updates.
+	pub frame_interval: Option<u64>,
+	/// The number of milliseconds re-tiling the layout waits for more windows while windows are
+	/// mapped and unmapped in quick succession.
+	pub map_debounce: Option<u64>,
+	/// The number of milliseconds windows without clear signals that they are normal windows float
+	/// for before they are tiled, or `0` to tile them straight away.
+	pub admission_delay: Option<u64>,
+	/// The number of windows reconfigured per event loop iteration when applying the layout, or
+	/// `0` to reconfigure every window straight away.
+	pub apply_chunk_size: Option<usize>,
+	/// The maximum number of times per second a tiled window is configured to its tile again when
+	/// it keeps asking for another geometry.
+	pub configure_rate_limit: Option<u32>,
+	/// The maximum number of seconds between a change to the layout and it being written to a
+	/// snapshot.
+	pub autosave_interval: Option<u64>,
+}
+
+/// The settings of a workspace.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct WorkspaceConfig {
+	/// The workspace's layout manager.
+	#[serde(deserialize_with = "parsed")]
+	pub layout_manager: Option<BuiltIn>,
+}
+
+/// The settings of the [theme]: a preset, and the colors overriding it, by their [slots].
+///
+/// [theme]: crate::theme
+/// [slots]: Slot
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ThemeConfig {
+	/// The built-in theme which the colors are given over.
+	pub preset: Option<Preset>,
+	/// The colors overriding the preset's.
+	pub colors: BTreeMap<Slot, Rgba>,
+}
+
+/// Key bindings, in the order they were given.
+///
+/// No key is bound twice.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct KeyBindings(Vec<KeyBinding>);
+
+/// A key binding: what is done when a [chord] is pressed.
+///
+/// [chord]: Chord
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct KeyBinding {
+	pub chord: Chord,
+	pub command: Command,
+}
+
+/// A key pressed with some modifiers, like `Super+Shift+Return`.
+///
+/// Chords are parsed case-insensitively, and letter keys are the same whether Shift is held or
+/// not: `Super+Shift+H` is `Super+Shift+h`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Chord {
+	/// The modifiers, in a consistent order.
+	pub modifiers: Vec<Modifier>,
+	/// The key's keysym.
+	pub keysym: u32,
+}
+
+/// Modifiers held together without a key, like `Super+Control`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct ModifierChord {
+	/// The modifiers, in a consistent order.
+	pub modifiers: Vec<Modifier>,
+}
+
+/// A logical modifier used in a key binding.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum Modifier {
+	Shift,
+	Control,
+	Alt,
+	Super,
+}
+
+/// What is done when a [key binding] is pressed.
+///
+/// [key binding]: KeyBinding
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Command {
+	/// Closes the focused window, or every window in the focused group (`close-window`).
+	CloseWindow,
+	/// Launches the terminal set in the `TERM` environment variable (`spawn-terminal`).
+	SpawnTerminal,
+	/// Runs the given shell command (`spawn <command>`).
+	Spawn(String),
+
+	/// Performs the given [action], by its name and arguments.
+	///
+	/// [action]: Action
+	Action(Action),
+	/// Starts or continues [switching windows] to the next window (`next-window`).
+	///
+	/// [switching windows]: crate::switcher
+	NextWindow,
+	/// Starts or continues [switching windows] to the previous window (`previous-window`).
+	///
+	/// [switching windows]: crate::switcher
+	PreviousWindow,
+
+	/// Removes the default key binding of the key (`none`).
+	Unbind,
+}
+
+/// What changed between two sets of [options], as when the configuration is reloaded.
+///
+/// [options]: Options
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Changes {
+	/// The layout's settings, or how the layout is oriented for and moved between outputs, changed.
+	///
+	/// The layout must be re-tiled.
+	pub layout: bool,
+	/// The layout manager of the current workspace changed.
+	pub layout_manager: bool,
+	/// How windows look changed.
+	pub appearance: bool,
+	/// The theme changed, so everything AquariWM draws must be drawn again.
+	pub theme: bool,
+	/// What the background shows changed, so it must be drawn again.
+	pub wallpaper: bool,
+	/// When windows which demand attention may steal focus changed.
+	pub focus_stealing: bool,
+	/// When the pointer is warped to the focused window changed.
+	pub pointer_warp: bool,
+	/// The modifiers which show the insertion preview changed.
+	pub insertion_preview: bool,
+	/// How hung clients are detected or dealt with changed.
+	pub ping: bool,
+	/// How window groups are treated changed.
+	pub groups: bool,
+	/// The rules changed.
+	pub rules: bool,
+	/// The key bindings changed, so they must be grabbed again.
+	pub key_bindings: bool,
+
+	/// The settings which changed but only take effect when AquariWM is restarted, by their names
+	/// in the configuration file.
+	pub restart: Vec<&'static str>,
+}
+
+/// An error returned when [loading] the configuration file fails.
+///
+/// [loading]: Config::load
+#[derive(Debug, Error)]
+pub enum Error {
+	/// The file couldn't be read.
+	#[error("failed to read {}: {source}", path.display())]
+	Read { path: PathBuf, source: io::Error },
+	/// The file isn't valid, e.g. because it isn't valid TOML or a setting has the wrong type.
+	///
+	/// The error gives the line and column at which the file is invalid.
+	#[error("invalid configuration in {}:\n{source}", path.display())]
+	Invalid { path: PathBuf, source: toml::de::Error },
+}
+
+/// An error returned when parsing a [`Chord`] fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum ParseChordError {
+	#[error("unknown modifier `{0}`; expected `Super`, `Shift`, `Control`, or `Alt`")]
+	UnknownModifier(String),
+	#[error("expected modifiers, like `Super+Control`")]
+	NoModifiers,
+	#[error("unknown key `{0}`{}", suggestion(.0, KEYS.iter().map(|(name, _)| *name)))]
+	UnknownKey(String),
+}
+
+/// An error returned when parsing a [`Command`] fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum ParseCommandError {
+	#[error("unknown action `{0}`{}", suggestion(.0, COMMANDS.iter().chain(action::NAMES).copied()))]
+	Unknown(String),
+	#[error("`spawn` expects a command to run")]
+	NothingToSpawn,
+	#[error(transparent)]
+	Action(action::ParseActionError),
+}
+
+impl Config {
+	/// Returns the path of the configuration file: `$XDG_CONFIG_HOME/aquariwm/config.toml`, or
+	/// `~/.config/aquariwm/config.toml` if `XDG_CONFIG_HOME` is not set.
+	pub fn default_path() -> Option<PathBuf> {
+		let dir = match env::var_os("XDG_CONFIG_HOME") {
+			Some(dir) => PathBuf::from(dir),
+			None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+		};
+
+		Some(dir.join("aquariwm").join("config.toml"))
+	}
+
+	/// Reads the configuration file at the given `path`, warning about any unknown settings.
+	///
+	/// If there is no file at the `path`, the configuration is empty, so every setting takes its
+	/// default.
+	pub fn load(path: &Path) -> Result<Self, Error> {
+		let text = match fs::read_to_string(path) {
+			Ok(text) => text,
+
+			Err(error) if error.kind() == io::ErrorKind::NotFound => {
+				event!(
+					Level::INFO,
+					"No configuration file at {}; using the defaults",
+					path.display()
+				);
+
+				return Ok(Self::default());
+			},
+			Err(source) => {
+				return Err(Error::Read {
+					path: path.to_owned(),
+					source,
+				})
+			},
+		};
+
+		let (config, unknown) = Self::parse(&text).map_err(|source| Error::Invalid {
+			path: path.to_owned(),
+			source,
+		})?;
+		for key in unknown {
+			event!(
+				Level::WARN,
+				"Ignoring unknown setting `{key}` in {}{}",
+				path.display(),
+				suggestion(&key, SETTINGS.iter().copied())
+			);
+		}
+
+		Ok(config)
+	}
+
+	/// Parses a configuration from the given TOML `text`, returning it along with the paths of any
+	/// unknown settings, which are ignored.
+	pub fn parse(text: &str) -> Result<(Self, Vec<String>), toml::de::Error> {
+		let mut unknown = Vec::new();
+		let config = serde_ignored::deserialize(toml::Deserializer::new(text), |path| {
+			unknown.push(path.to_string());
+		})?;
+
+		Ok((config, unknown))
+	}
+
+	/// Layers this configuration over the `base` configuration: the settings given in this
+	/// configuration take precedence, and lists are combined.
+	///
+	/// This configuration's rules come first, so they take precedence too, and its key bindings
+	/// override the `base` configuration's bindings of the same keys.
+	pub fn over(self, base: Self) -> Self {
+		Self {
+			window_gap: self.window_gap.or(base.window_gap),
+			group_by_class: self.group_by_class.or(base.group_by_class),
+			honor_increments: self.honor_increments.or(base.honor_increments),
+			leftover_pixels: self.leftover_pixels.or(base.leftover_pixels),
+			smart_gaps: self.smart_gaps.or(base.smart_gaps),
+			reading_order: self.reading_order.or(base.reading_order),
+			active_on_removal: self.active_on_removal.or(base.active_on_removal),
+			maximize_strip: self.maximize_strip.or(base.maximize_strip),
+			layout_manager: self.layout_manager.or(base.layout_manager),
+			workspaces: {
+				let mut workspaces = base.workspaces;
+
+				for (name, workspace) in self.workspaces {
+					let base = workspaces.remove(&name).unwrap_or_default();
+
+					workspaces.insert(
+						name,
+						WorkspaceConfig {
+							layout_manager: workspace.layout_manager.or(base.layout_manager),
+						},
+					);
+				}
+
+				workspaces
+			},
+
+			orientation: self.orientation.or(base.orientation),
+			portrait_orientation: self.portrait_orientation.or(base.portrait_orientation),
+			rotate_with_outputs: self.rotate_with_outputs.or(base.rotate_with_outputs),
+			wrap_outputs: self.wrap_outputs.or(base.wrap_outputs),
+			switch_all_outputs: self.switch_all_outputs.or(base.switch_all_outputs),
+
+			unfocused_opacity: self.unfocused_opacity.or(base.unfocused_opacity),
+			no_dim: [base.no_dim, self.no_dim].concat(),
+			smart_borders: self.smart_borders.or(base.smart_borders),
+			theme: ThemeConfig {
+				preset: self.theme.preset.or(base.theme.preset),
+				colors: {
+					let mut colors = base.theme.colors;
+					colors.extend(self.theme.colors);
+
+					colors
+				},
+			},
+			background_color: self.background_color.or(base.background_color),
+			wallpaper: self.wallpaper.or(base.wallpaper),
+
+			focus_stealing: self.focus_stealing.or(base.focus_stealing),
+			fullscreen: self.fullscreen.or(base.fullscreen),
+			pointer_warp: self.pointer_warp.or(base.pointer_warp),
+			insertion_preview: self.insertion_preview.or(base.insertion_preview),
+			ping_interval: self.ping_interval.or(base.ping_interval),
+			ping_timeout: self.ping_timeout.or(base.ping_timeout),
+			unresponsive_border_color: self.unresponsive_border_color.or(base.unresponsive_border_color),
+			force_kill_process: self.force_kill_process.or(base.force_kill_process),
+			group_behavior: self.group_behavior.or(base.group_behavior),
+			group_across_outputs: self.group_across_outputs.or(base.group_across_outputs),
+			rules: [self.rules, base.rules].concat(),
+
+			autostart: [base.autostart, self.autostart].concat(),
+			key_bindings: base.key_bindings.overridden_by(self.key_bindings),
+
+			scales: [self.scales, base.scales].concat(),
+			clone_overlap: self.clone_overlap.or(base.clone_overlap),
+			frame_interval: self.frame_interval.or(base.frame_interval),
+			map_debounce: self.map_debounce.or(base.map_debounce),
+			admission_delay: self.admission_delay.or(base.admission_delay),
+			apply_chunk_size: self.apply_chunk_size.or(base.apply_chunk_size),
+			configure_rate_limit: self.configure_rate_limit.or(base.configure_rate_limit),
+			autosave_interval: self.autosave_interval.or(base.autosave_interval),
+		}
+	}
+
+	/// Sets the given `options` according to the settings given in this configuration, leaving the
+	/// others as they are.
+	pub fn apply(self, options: &mut Options) {
+		if let Some(window_gap) = self.window_gap {
+			options.settings.window_gap = window_gap;
+		}
+		if let Some(group_by_class) = self.group_by_class {
+			options.settings.insertion_strategy = match group_by_class {
+				true => InsertionStrategy::GroupByClass,
+				false => InsertionStrategy::Manager,
+			};
+		}
+		if let Some(honor_increments) = self.honor_increments {
+			options.settings.honor_increments = honor_increments;
+		}
+		if let Some(leftover_pixels) = self.leftover_pixels {
+			options.settings.leftover_pixels = leftover_pixels;
+		}
+		if let Some(smart_gaps) = self.smart_gaps {
+			options.settings.smart_gaps = smart_gaps;
+		}
+		if let Some(reading_order) = self.reading_order {
+			options.settings.reading_order = reading_order;
+		}
+		if let Some(active_on_removal) = self.active_on_removal {
+			options.settings.active_on_removal = active_on_removal;
+		}
+		if let Some(maximize_strip) = self.maximize_strip {
+			options.settings.maximize_strip = maximize_strip;
+		}
+		if let Some(layout_manager) = self.layout_manager {
+			options.layout_manager = layout_manager;
+		}
+		for (name, workspace) in self.workspaces {
+			if let Some(layout_manager) = workspace.layout_manager {
+				options.workspace_layout_managers.insert(name, layout_manager);
+			}
+		}
+
+		match (self.orientation, self.portrait_orientation) {
+			(orientation, Some(portrait)) => {
+				options.default_orientation = Some(DefaultOrientation::ByAspect {
+					landscape: orientation.unwrap_or(Orientation::LeftToRight),
+					portrait,
+				});
+			},
+			(Some(orientation), None) => options.default_orientation = Some(DefaultOrientation::Fixed(orientation)),
+
+			(None, None) => (),
+		}
+		if let Some(rotate_with_outputs) = self.rotate_with_outputs {
+			options.rotate_with_outputs = rotate_with_outputs;
+		}
+		if let Some(wrap_outputs) = self.wrap_outputs {
+			options.output_edges = match wrap_outputs {
+				true => OutputEdges::Wrap,
+				false => OutputEdges::Clamp,
+			};
+		}
+		if let Some(switch_all_outputs) = self.switch_all_outputs {
+			options.switch_scope = match switch_all_outputs {
+				true => Scope::AllOutputs,
+				false => Scope::ActiveOutput,
+			};
+		}
+
+		if let Some(opacity) = self.unfocused_opacity {
+			options.appearance.unfocused_opacity = Some(opacity);
+		}
+		options.appearance.no_dim.extend(self.no_dim);
+		if let Some(smart_borders) = self.smart_borders {
+			options.appearance.smart_borders = smart_borders;
+		}
+		// The theme's colors are given over its preset, and take precedence over the older
+		// `unresponsive-border-color`.
+		if let Some(preset) = self.theme.preset {
+			options.theme = preset.theme();
+		}
+		if let Some(color) = self.unresponsive_border_color {
+			options.theme.set(Slot::HungBorder, color.into());
+		}
+		for (slot, color) in self.theme.colors {
+			options.theme.set(slot, color);
+		}
+		if let Some(color) = self.background_color {
+			options.wallpaper.color = color;
+		}
+		if let Some(image) = self.wallpaper {
+			options.wallpaper.image = Some(image);
+		}
+
+		if let Some(focus_stealing) = self.focus_stealing {
+			options.focus_stealing = focus_stealing;
+		}
+		if let Some(fullscreen) = self.fullscreen {
+			options.fullscreen_policy = fullscreen;
+		}
+		if let Some(pointer_warp) = self.pointer_warp {
+			options.pointer_warp = pointer_warp;
+		}
+		if let Some(insertion_preview) = self.insertion_preview {
+			options.insertion_preview = Some(insertion_preview);
+		}
+		if let Some(ping_interval) = self.ping_interval {
+			options.ping.interval = (ping_interval > 0).then(|| Duration::from_secs(ping_interval));
+		}
+		if let Some(ping_timeout) = self.ping_timeout {
+			options.ping.timeout = Duration::from_millis(ping_timeout);
+		}
+		if let Some(force_kill_process) = self.force_kill_process {
+			options.ping.kill_process = force_kill_process;
+		}
+		if let Some(behavior) = self.group_behavior {
+			options.groups.behavior = behavior;
+		}
+		if let Some(across_outputs) = self.group_across_outputs {
+			options.groups.across_outputs = across_outputs;
+		}
+		options.rules.extend(self.rules);
+
+		options.autostart.extend(self.autostart);
+		options.key_bindings = options.key_bindings.clone().overridden_by(self.key_bindings);
+
+		options.scales.extend(self.scales);
+		if let Some(clone_overlap) = self.clone_overlap {
+			options.clone_overlap = clone_overlap;
+		}
+		if let Some(frame_interval) = self.frame_interval {
+			options.frame_interval = Duration::from_millis(frame_interval);
+		}
+		if let Some(map_debounce) = self.map_debounce {
+			options.map_debounce = Duration::from_millis(map_debounce);
+		}
+		if let Some(admission_delay) = self.admission_delay {
+			options.admission_delay = Duration::from_millis(admission_delay);
+		}
+		if let Some(apply_chunk_size) = self.apply_chunk_size {
+			options.apply_chunk_size = NonZeroUsize::new(apply_chunk_size);
+		}
+		if let Some(configure_rate_limit) = self.configure_rate_limit {
+			options.configure_rate_limit = configure_rate_limit;
+		}
+		if let Some(autosave_interval) = self.autosave_interval {
+			options.autosave_interval = Duration::from_secs(autosave_interval);
+		}
+	}
+}
+
+impl KeyBindings {
+	/// Creates key bindings from the given `bindings`, in order, each overriding the bindings of
+	/// the same key before it.
+	pub fn new(bindings: impl IntoIterator<Item = KeyBinding>) -> Self {
+		Self::default().overridden_by(Self(bindings.into_iter().collect()))
+	}
+
+	/// Returns the key bindings, in the order they were given.
+	#[inline(always)]
+	pub fn bindings(&self) -> &[KeyBinding] {
+		&self.0
+	}
+
+	/// Returns these key bindings with those of the same keys replaced by the `other` key bindings,
+	/// which are added after them.
+	fn overridden_by(mut self, other: Self) -> Self {
+		self.0
+			.retain(|binding| other.0.iter().all(|other| other.chord != binding.chord));
+		self.0.extend(other.0);
+
+		self
+	}
+}
+
+impl Changes {
+	/// Returns what changed from the `old` options to the `new` options.
+	pub fn between(old: &Options, new: &Options) -> Self {
+		let mut restart = Vec::new();
+		if old.scales != new.scales {
+			restart.push("scales");
+		}
+		if old.clone_overlap != new.clone_overlap {
+			restart.push("clone-overlap");
+		}
+		if old.frame_interval != new.frame_interval {
+			restart.push("frame-interval");
+		}
+		if old.map_debounce != new.map_debounce {
+			restart.push("map-debounce");
+		}
+		if old.admission_delay != new.admission_delay {
+			restart.push("admission-delay");
+		}
+		if old.apply_chunk_size != new.apply_chunk_size {
+			restart.push("apply-chunk-size");
+		}
+		if old.configure_rate_limit != new.configure_rate_limit {
+			restart.push("configure-rate-limit");
+		}
+		if old.autosave_interval != new.autosave_interval {
+			restart.push("autosave-interval");
+		}
+		// Leaving or entering game mode while windows are fullscreen would leave them in the wrong
+		// state.
+		if old.fullscreen_policy != new.fullscreen_policy {
+			restart.push("fullscreen");
+		}
+
+		Self {
+			layout: old.settings != new.settings
+				|| old.default_orientation != new.default_orientation
+				|| old.rotate_with_outputs != new.rotate_with_outputs
+				|| old.output_edges != new.output_edges
+				|| old.switch_scope != new.switch_scope,
+			// Every workspace shares the first workspace's layout manager.
+			layout_manager: old.layout_manager_for(0, WORKSPACE) != new.layout_manager_for(0, WORKSPACE),
+			appearance: old.appearance != new.appearance,
+			theme: old.theme != new.theme,
+			wallpaper: old.wallpaper != new.wallpaper,
+			focus_stealing: old.focus_stealing != new.focus_stealing,
+			pointer_warp: old.pointer_warp != new.pointer_warp,
+			insertion_preview: old.insertion_preview != new.insertion_preview,
+			ping: old.ping != new.ping,
+			groups: old.groups != new.groups,
+			rules: old.rules != new.rules,
+			key_bindings: old.key_bindings != new.key_bindings,
+
+			restart,
+		}
+	}
+
+	/// Returns whether nothing changed.
+	pub fn is_empty(&self) -> bool {
+		*self == Self::default()
+	}
+}
+
+impl FromStr for Chord {
+	type Err = ParseChordError;
+
+	/// Parses a chord from its modifiers and key, separated by `+`, like `Super+Shift+Return`.
+	///
+	/// The key is either a printable character, like `h` or `+`, or one of the named keys, like
+	/// `Return`, `comma`, or `F1`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		// `+` itself can be bound, e.g. as `Super++`.
+		let (modifiers, key) = match s.strip_suffix("++") {
+			Some(modifiers) => (modifiers, "+"),
+			None => s.rsplit_once('+').unwrap_or(("", s)),
+		};
+
+		let parsed = parse_modifiers(modifiers)?;
+
+		let mut chars = key.chars();
+		let keysym = match (chars.next(), chars.next()) {
+			// Printable ASCII characters are their own keysyms. Letters are bound by their lowercase
+			// keysyms, which are what the keyboard mapping gives their keys.
+			(Some(char), None) if char.is_ascii_graphic() => char.to_ascii_lowercase() as u32,
+
+			_ => KEYS
+				.iter()
+				.find(|(name, _)| name.eq_ignore_ascii_case(key))
+				.map(|&(_, keysym)| keysym)
+				.ok_or_else(|| ParseChordError::UnknownKey(key.to_owned()))?,
+		};
+
+		Ok(Self {
+			modifiers: parsed,
+			keysym,
+		})
+	}
+}
+
+impl Display for Chord {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		for modifier in &self.modifiers {
+			write!(f, "{modifier:?}+")?;
+		}
+
+		match KEYS.iter().find(|&&(_, keysym)| keysym == self.keysym) {
+			Some((name, _)) => write!(f, "{name}"),
+			None => match char::from_u32(self.keysym) {
+				Some(char) => write!(f, "{char}"),
+				None => write!(f, "{:#x}", self.keysym),
+			},
+		}
+	}
+}
+
+impl FromStr for ModifierChord {
+	type Err = ParseChordError;
+
+	/// Parses modifiers separated by `+`, like `Super+Control`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match parse_modifiers(s)? {
+			modifiers if modifiers.is_empty() => Err(ParseChordError::NoModifiers),
+			modifiers => Ok(Self { modifiers }),
+		}
+	}
+}
+
+/// Parses the modifiers of a chord, separated by `+`, in a consistent order and without repeats.
+fn parse_modifiers(s: &str) -> Result<Vec<Modifier>, ParseChordError> {
+	let mut parsed = Vec::new();
+	for modifier in s.split('+').filter(|modifier| !modifier.is_empty()) {
+		parsed.push(match modifier.to_lowercase().as_str() {
+			"super" | "mod4" | "logo" => Modifier::Super,
+			"shift" => Modifier::Shift,
+			"control" | "ctrl" => Modifier::Control,
+			"alt" | "mod1" => Modifier::Alt,
+
+			_ => return Err(ParseChordError::UnknownModifier(modifier.to_owned())),
+		});
+	}
+	parsed.sort_unstable();
+	parsed.dedup();
+
+	Ok(parsed)
+}
+
+impl FromStr for Command {
+	type Err = ParseCommandError;
+
+	/// Parses a command from its name, followed by its arguments for `spawn` and [actions], like
+	/// `spawn alacritty` or `resize-primary -50`.
+	///
+	/// [actions]: Action
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		let (name, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+
+		match name {
+			"close-window" => Ok(Self::CloseWindow),
+			"spawn-terminal" => Ok(Self::SpawnTerminal),
+			"spawn" => match rest.trim() {
+				"" => Err(ParseCommandError::NothingToSpawn),
+				command => Ok(Self::Spawn(command.to_owned())),
+			},
+			"next-window" => Ok(Self::NextWindow),
+			"previous-window" => Ok(Self::PreviousWindow),
+			"none" => Ok(Self::Unbind),
+
+			_ => match s.parse() {
+				Ok(action) => Ok(Self::Action(action)),
+
+				Err(action::ParseActionError::Unknown(name)) => Err(ParseCommandError::Unknown(name)),
+				Err(error) => Err(ParseCommandError::Action(error)),
+			},
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for KeyBindings {
+	/// Deserializes key bindings from a table of [chords] and [commands], rejecting chords which
+	/// are the same key written differently.
+	///
+	/// [chords]: Chord
+	/// [commands]: Command
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct Visitor;
+
+		impl<'de> de::Visitor<'de> for Visitor {
+			type Value = KeyBindings;
+
+			fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+				write!(f, "a table of key bindings")
+			}
+
+			fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+				// The chords as they were written, for the error if one is bound twice.
+				let mut written: Vec<String> = Vec::new();
+				let mut bindings = Vec::new();
+
+				while let Some(key) = map.next_key::<String>()? {
+					let chord: Chord = key.parse().map_err(de::Error::custom)?;
+					let Parsed(command) = map.next_value()?;
+
+					if let Some(index) = bindings.iter().position(|binding: &KeyBinding| binding.chord == chord) {
+						return Err(de::Error::custom(format!(
+							"`{key}` is bound twice: it is the same key binding as `{}`",
+							written[index]
+						)));
+					}
+
+					written.push(key);
+					bindings.push(KeyBinding { chord, command });
+				}
+
+				Ok(KeyBindings(bindings))
+			}
+		}
+
+		deserializer.deserialize_map(Visitor)
+	}
+}
+
+impl<'de> Deserialize<'de> for ThemeConfig {
+	/// Deserializes the theme from a table of its `preset` and the colors of its [slots], naming
+	/// the slot in the error if a color is invalid.
+	///
+	/// Unknown slots are ignored, like unknown settings.
+	///
+	/// [slots]: Slot
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct Visitor;
+
+		impl<'de> de::Visitor<'de> for Visitor {
+			type Value = ThemeConfig;
+
+			fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+				write!(f, "a table of a theme preset and colors")
+			}
+
+			fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+				let mut theme = ThemeConfig::default();
+
+				while let Some(key) = map.next_key::<String>()? {
+					if key == "preset" {
+						let Parsed(preset) = map.next_value()?;
+						theme.preset = Some(preset);
+
+						continue;
+					}
+
+					let Ok(slot) = key.parse::<Slot>() else {
+						map.next_value::<de::IgnoredAny>()?;
+						continue;
+					};
+					let color: String = map.next_value()?;
+					let color = color
+						.parse()
+						.map_err(|error| de::Error::custom(format!("invalid color for `{key}`: {error}")))?;
+
+					theme.colors.insert(slot, color);
+				}
+
+				Ok(theme)
+			}
+		}
+
+		deserializer.deserialize_map(Visitor)
+	}
+}
+
+/// A value which is deserialized with its [`FromStr`] implementation, as it would be parsed from
+/// the command line.
+///
+/// Numbers are parsed from their text, so that settings like `unfocused-opacity = 0.92` and
+/// `focus-stealing = 500` don't have to be quoted.
+struct Parsed<T>(T);
+
+impl<'de, T> Deserialize<'de> for Parsed<T>
+where
+	T: FromStr,
+	T::Err: Display,
+{
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct Visitor<T>(PhantomData<T>);
+
+		impl<'de, T> de::Visitor<'de> for Visitor<T>
+		where
+			T: FromStr,
+			T::Err: Display,
+		{
+			type Value = Parsed<T>;
+
+			fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+				write!(f, "a string or a number")
+			}
+
+			fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+				value.parse().map(Parsed).map_err(E::custom)
+			}
+
+			fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+				self.visit_str(&value.to_string())
+			}
+
+			fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+				self.visit_str(&value.to_string())
+			}
+
+			fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+				self.visit_str(&value.to_string())
+			}
+		}
+
+		deserializer.deserialize_any(Visitor(PhantomData))
+	}
+}
+
+/// Deserializes an optional setting with its [`FromStr`] implementation.
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: FromStr,
+	T::Err: Display,
+{
+	Parsed::deserialize(deserializer).map(|Parsed(value)| Some(value))
+}
+
+/// Deserializes a list setting with its items' [`FromStr`] implementation.
+fn parsed_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: FromStr,
+	T::Err: Display,
+{
+	let items: Vec<Parsed<T>> = Vec::deserialize(deserializer)?;
+
+	Ok(items.into_iter().map(|Parsed(item)| item).collect())
+}
+
+/// Returns a suggestion of the most similar of the `candidates` to the unknown `name`, to be
+/// appended to an error message, or nothing if none are similar enough.
+pub(crate) fn suggestion<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+	// Typos of more than a third of a name are unlikely to be typos at all.
+	let threshold = (name.chars().count() / 3).max(1);
+
+	let closest = candidates
+		.into_iter()
+		.map(|candidate| {
+			(
+				edit_distance(&name.to_lowercase(), &candidate.to_lowercase()),
+				candidate,
+			)
+		})
+		.filter(|&(distance, _)| distance <= threshold)
+		.min_by_key(|&(distance, _)| distance);
+
+	match closest {
+		Some((_, candidate)) => format!("; did you mean `{candidate}`?"),
+		None => String::new(),
+	}
+}
+
+/// Returns the Levenshtein distance between `a` and `b`: the number of characters which must be
+/// inserted, removed, or substituted to turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().collect();
+	// The distances from the prefix of `a` so far to each prefix of `b`.
+	let mut distances: Vec<usize> = (0..=b.len()).collect();
+
+	for (i, a) in a.chars().enumerate() {
+		let mut diagonal = distances[0];
+		distances[0] = i + 1;
+
+		for (j, &b) in b.iter().enumerate() {
+			let substitution = diagonal + usize::from(a != b);
+			diagonal = distances[j + 1];
+
+			distances[j + 1] = substitution.min(distances[j] + 1).min(diagonal + 1);
+		}
+	}
+
+	distances[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+	use std::process;
+
+	use super::*;
+	use crate::{
+		layout::{managers::Stack, Reconfigured},
+		state::{AquariWm, MapState},
+	};
+
+	#[test]
+	fn parse() {
+		let (config, unknown) = Config::parse(
+			r##"
+				window-gap = 10
+				orientation = "top-to-bottom"
+				unfocused-opacity = 0.92
+				focus-stealing = 500
+				leftover-pixels = "gaps"
+				reading-order = "columns"
+				active-on-removal = "left"
+				rules = ["float:class=pavucontrol"]
+				insertion-preview = "ctrl+Super"
+				background-color = "#282828"
+				wallpaper = "/tmp/wallpaper.png"
+				clone-overlap = 0.8
+				ping-interval = 0
+				unresponsive-border-color = "#ff0000"
+				window-gapp = 5
+
+				[key-bindings]
+				"Super+Shift+Return" = "spawn alacritty --class Terminal"
+				"super+H" = "resize-primary -50"
+				"Super+comma" = "focus-output left"
+				"Super+n" = "none"
+			"##,
+		)
+		.unwrap();
+
+		assert_eq!(config.window_gap, Some(10));
+		assert_eq!(config.orientation, Some(Orientation::TopToBottom));
+		assert_eq!(config.unfocused_opacity, Opacity::new(0.92));
+		assert_eq!(config.clone_overlap, CloneOverlap::new(0.8));
+		assert_eq!(
+			config.focus_stealing,
+			Some(FocusStealing::IfIdleFor(Duration::from_millis(500)))
+		);
+		assert_eq!(config.leftover_pixels, Some(LeftoverPixels::Gaps));
+		assert_eq!(config.reading_order, Some(ReadingOrder::Columns));
+		assert_eq!(config.active_on_removal, Some(TabNeighbor::Left));
+		assert_eq!(config.rules.len(), 1);
+		assert_eq!(
+			config.insertion_preview,
+			Some(ModifierChord {
+				modifiers: vec![Modifier::Control, Modifier::Super]
+			})
+		);
+		assert_eq!(config.background_color, Some(Color::new(0x282828)));
+		assert_eq!(config.wallpaper, Some(PathBuf::from("/tmp/wallpaper.png")));
+		assert_eq!(config.ping_interval, Some(0));
+		assert_eq!(config.unresponsive_border_color, Some(Color::new(0xff0000)));
+		assert_eq!(unknown, ["window-gapp"]);
+
+		let bindings: Vec<_> = config
+			.key_bindings
+			.bindings()
+			.iter()
+			.map(|binding| (binding.chord.to_string(), binding.command.clone()))
+			.collect();
+		assert_eq!(
+			bindings,
+			[
+				(
+					"Shift+Super+Return".to_owned(),
+					Command::Spawn("alacritty --class Terminal".to_owned())
+				),
+				("Super+h".to_owned(), Command::Action(Action::ResizePrimary(-50))),
+				(
+					"Super+comma".to_owned(),
+					Command::Action(Action::FocusOutput(crate::output::Direction::Left))
+				),
+				("Super+n".to_owned(), Command::Unbind),
+			]
+		);
+	}
+
+	#[test]
+	fn errors() {
+		let error = |text| Config::parse(text).unwrap_err().to_string();
+
+		// Errors give the line and column at which the file is invalid.
+		let message = error("window-gap = 10\norientation = \"sideways\"\n");
+		assert!(message.contains("line 2, column 15"), "{message}");
+		assert!(message.contains("expected `left-to-right`"), "{message}");
+
+		let message = error("[key-bindings]\n\"Super+h\" = \"swap-with-primry\"\n");
+		assert!(message.contains("line 2"), "{message}");
+		assert!(
+			message.contains("unknown action `swap-with-primry`; did you mean `swap-with-primary`?"),
+			"{message}"
+		);
+
+		let message = error("[key-bindings]\n\"Super+Shift+h\" = \"close-window\"\n\"shift+super+H\" = \"none\"\n");
+		assert!(
+			message.contains("`shift+super+H` is bound twice: it is the same key binding as `Super+Shift+h`"),
+			"{message}"
+		);
+
+		let message = error("[key-bindings]\n\"Hyper+h\" = \"close-window\"\n");
+		assert!(message.contains("unknown modifier `Hyper`"), "{message}");
+		let message = error("[key-bindings]\n\"Super+Retrun\" = \"close-window\"\n");
+		assert!(message.contains("did you mean `Return`?"), "{message}");
+
+		let message = error("insertion-preview = \"+\"\n");
+		assert!(message.contains("expected modifiers"), "{message}");
+	}
+
+	#[test]
+	fn layers() {
+		let (file, _) = Config::parse(
+			r#"
+				window-gap = 10
+				orientation = "top-to-bottom"
+				rules = ["float:class=mpv"]
+
+				[key-bindings]
+				"Super+h" = "close-window"
+			"#,
+		)
+		.unwrap();
+		let command_line = Config {
+			window_gap: Some(20),
+			portrait_orientation: Some(Orientation::LeftToRight),
+			rules: vec!["tile:class=mpv".parse().unwrap()],
+			key_bindings: KeyBindings::new([KeyBinding {
+				chord: "Super+h".parse().unwrap(),
+				command: Command::NextWindow,
+			}]),
+			..Config::default()
+		};
+
+		let mut options = Options::default();
+		command_line.over(file).apply(&mut options);
+
+		assert_eq!(options.settings.window_gap, 20);
+		assert_eq!(
+			options.default_orientation,
+			Some(DefaultOrientation::ByAspect {
+				landscape: Orientation::TopToBottom,
+				portrait: Orientation::LeftToRight,
+			})
+		);
+		// The command line's rules come first, so they take precedence.
+		assert_eq!(
+			options.rules,
+			["tile:class=mpv".parse().unwrap(), "float:class=mpv".parse().unwrap()]
+		);
+		assert_eq!(options.key_bindings.bindings().len(), 1);
+		assert_eq!(options.key_bindings.bindings()[0].command, Command::NextWindow);
+	}
+
+	#[test]
+	fn changes() {
+		let options = Options::default();
+		let configured = |text| {
+			let mut options = Options::default();
+			Config::parse(text).unwrap().0.apply(&mut options);
+
+			options
+		};
+
+		assert!(Changes::between(&options, &configured("")).is_empty());
+		assert_eq!(
+			Changes::between(&options, &configured("window-gap = 5\nframe-interval = 8")),
+			Changes {
+				layout: true,
+				restart: vec!["frame-interval"],
+				..Changes::default()
+			}
+		);
+		assert_eq!(
+			Changes::between(
+				&options,
+				&configured("no-dim = [\"mpv\"]\n[key-bindings]\n\"Super+x\" = \"close-window\"")
+			),
+			Changes {
+				appearance: true,
+				key_bindings: true,
+				..Changes::default()
+			}
+		);
+		assert_eq!(
+			Changes::between(&options, &configured("background-color = \"#000\"")),
+			Changes {
+				wallpaper: true,
+				..Changes::default()
+			}
+		);
+	}
+
+	#[test]
+	fn theme() {
+		let (config, unknown) = Config::parse(
+			r##"
+				unresponsive-border-color = "#00ff00"
+
+				[theme]
+				preset = "high-contrast"
+				focused-border = "#f80"
+				hung-border = "red"
+				focussed-border = "#000"
+			"##,
+		)
+		.unwrap();
+		assert_eq!(config.theme.preset, Some(Preset::HighContrast));
+		assert_eq!(unknown, ["theme.focussed-border"]);
+
+		let mut options = Options::default();
+		config.apply(&mut options);
+
+		let high_contrast = Preset::HighContrast.theme();
+		assert_eq!(options.theme.get(Slot::FocusedBorder), Rgba::opaque(0xff8800));
+		// The theme's colors take precedence over `unresponsive-border-color`.
+		assert_eq!(options.theme.get(Slot::HungBorder), Rgba::opaque(0xff0000));
+		assert_eq!(
+			options.theme.get(Slot::UnfocusedBorder),
+			high_contrast.get(Slot::UnfocusedBorder)
+		);
+		assert_eq!(
+			Changes::between(&Options::default(), &options),
+			Changes {
+				theme: true,
+				..Changes::default()
+			}
+		);
+
+		// Invalid colors name the key they were given for.
+		let message = Config::parse("[theme]\noverlay-fill = \"#12345\"\n")
+			.unwrap_err()
+			.to_string();
+		assert!(message.contains("invalid color for `overlay-fill`"), "{message}");
+		let message = Config::parse("[theme]\nurgent-border = \"ornage\"\n")
+			.unwrap_err()
+			.to_string();
+		assert!(message.contains("invalid color for `urgent-border`"), "{message}");
+		assert!(message.contains("did you mean `orange`?"), "{message}");
+	}
+
+	#[test]
+	fn layout_managers() {
+		let mut options = Options::default();
+		Config::parse(
+			"layout-manager = \"spiral\"\n[workspaces.2]\nlayout-manager = \
+			 \"Append\"\n[workspaces.web]\nlayout-manager = \"Stack\"",
+		)
+		.unwrap()
+		.0
+		.apply(&mut options);
+
+		assert_eq!(options.layout_manager_for(0, "1"), BuiltIn::Spiral);
+		// By position...
+		assert_eq!(options.layout_manager_for(1, "mail"), BuiltIn::Append);
+		// ...or by name, which takes precedence.
+		assert_eq!(options.layout_manager_for(1, "web"), BuiltIn::Stack);
+
+		let changes = Changes::between(&Options::default(), &options);
+		assert!(changes.layout_manager);
+
+		assert!(Config::parse("layout-manager = \"Tabbed\"").is_err());
+	}
+
+	/// Tests that reloading a configuration file with a changed gap moves the tiles with a single
+	/// re-tile.
+	#[test]
+	fn reload_gap() {
+		let dir = env::temp_dir().join(format!("aquariwm-config-test-{}", process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("config.toml");
+
+		let options = |gap| {
+			fs::write(&path, format!("window-gap = {gap}\n")).unwrap();
+
+			Options {
+				config: Some(path.clone()),
+				..Options::default()
+			}
+			.reload()
+			.unwrap()
+		};
+		let tiles = |state: &mut AquariWm<u32>| {
+			let mut tiles = Vec::new();
+			state
+				.apply_changes(|&window, x, y, width, height, _| {
+					tiles.push((window, x, y, width, height));
+
+					Ok::<_, ()>(Reconfigured::Applied)
+				})
+				.unwrap();
+
+			tiles
+		};
+
+		let old = options(10);
+		let mut state = AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, old.settings.clone());
+		state.add_windows([(1, MapState::Mapped), (2, MapState::Mapped)]);
+		let before = tiles(&mut state);
+		assert_eq!(before[0].1, 10);
+
+		let new = options(20);
+		let changes = Changes::between(&old, &new);
+		assert!(changes.layout && changes.restart.is_empty());
+
+		state.set_settings(new.settings);
+		let after = tiles(&mut state);
+		assert_eq!(after.len(), 2);
+		assert_eq!((after[0].1, after[0].2), (20, 20));
+		assert_ne!(before, after);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
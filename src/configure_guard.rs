@@ -0,0 +1,233 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Guards against configure request loops with tiled windows.
+//!
+//! Tiled windows' geometry is decided by the layout, so AquariWM answers their configure requests
+//! by re-asserting the geometry it last gave them. Some clients respond to every `ConfigureNotify`
+//! with another request for the geometry they wanted, and some toolkits send bursts of identical
+//! requests, which can ping-pong with the re-asserted geometry into a loop which never settles.
+//!
+//! A [`ConfigureGuard`] remembers the geometry [asserted] for each window and [decides] how to
+//! answer each request:
+//! - requests for exactly the asserted geometry are only answered with a synthetic
+//!   `ConfigureNotify`, as nothing needs to change;
+//! - requests for another geometry are answered by re-asserting the window's geometry, but at most
+//!   a [limited] number of times per second for each window;
+//! - requests beyond that limit are dropped, so that a misbehaving client is no longer told
+//!   anything to respond to.
+//!
+//! [asserted]: ConfigureGuard::assert
+//! [decides]: ConfigureGuard::on_request
+//! [limited]: ConfigureGuard::new
+
+use std::{
+	collections::HashMap,
+	hash::Hash,
+	time::{Duration, Instant},
+};
+
+use crate::placement::{Rect, Requested};
+
+/// The default number of times per second a window's geometry is re-asserted in response to its
+/// configure requests.
+pub const DEFAULT_REASSERT_LIMIT: u32 = 10;
+
+/// The period over which re-asserts are [limited].
+///
+/// [limited]: ConfigureGuard::new
+const PERIOD: Duration = Duration::from_secs(1);
+
+/// How a tiled window's configure request should be answered.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Verdict {
+	/// The request is for the geometry the window already has: it is only told its geometry with
+	/// a synthetic `ConfigureNotify`.
+	Notify,
+	/// The request is for another geometry: the window is configured to its asserted geometry
+	/// again.
+	Reassert,
+	/// The window's geometry has been re-asserted too often recently: the request is dropped.
+	///
+	/// `first` is whether this is the first request dropped since the window was last limited, so
+	/// that it is only warned about once.
+	Limited { first: bool },
+}
+
+/// The geometry asserted for a window, and how often it has been re-asserted recently.
+#[derive(Debug, Clone)]
+struct Asserted {
+	geometry: Rect,
+
+	/// When the current period of re-asserts started, if the geometry has been re-asserted.
+	since: Option<Instant>,
+	/// The number of times the geometry has been re-asserted since then.
+	reasserts: u32,
+	/// Whether a request has been dropped since then.
+	limited: bool,
+}
+
+/// Remembers the geometry asserted for each tiled window, and decides how their configure requests
+/// are answered.
+///
+/// Times are passed in explicitly rather than read from the clock, so that the guard can be driven
+/// by any timer.
+#[derive(Debug, Clone)]
+pub struct ConfigureGuard<Window> {
+	/// The maximum number of re-asserts per window per second.
+	limit: u32,
+
+	asserted: HashMap<Window, Asserted>,
+}
+
+impl<Window> Default for ConfigureGuard<Window> {
+	#[inline]
+	fn default() -> Self {
+		Self::new(DEFAULT_REASSERT_LIMIT)
+	}
+}
+
+impl<Window> ConfigureGuard<Window> {
+	/// Creates a guard which re-asserts each window's geometry at most `limit` times per second.
+	pub fn new(limit: u32) -> Self {
+		Self {
+			limit,
+
+			asserted: HashMap::new(),
+		}
+	}
+}
+
+impl<Window: Eq + Hash> ConfigureGuard<Window> {
+	/// Records that the given `window` has been configured to the given `geometry`.
+	pub fn assert(&mut self, window: Window, geometry: Rect) {
+		self.asserted
+			.entry(window)
+			.and_modify(|asserted| asserted.geometry = geometry)
+			.or_insert(Asserted {
+				geometry,
+
+				since: None,
+				reasserts: 0,
+				limited: false,
+			});
+	}
+
+	/// Returns the geometry last [asserted] for the given `window`, if there is one.
+	///
+	/// [asserted]: Self::assert
+	pub fn asserted(&self, window: &Window) -> Option<Rect> {
+		self.asserted.get(window).map(|asserted| asserted.geometry)
+	}
+
+	/// Decides how the given `window`'s configure request for the `requested` geometry is answered
+	/// at the time `now`, or returns [`None`] if no geometry has been [asserted] for it.
+	///
+	/// Values which aren't requested are left as they are, so only the requested values are
+	/// compared with the asserted geometry. The border width isn't compared, as tiled windows'
+	/// borders are decided by AquariWM.
+	///
+	/// [asserted]: Self::assert
+	pub fn on_request(&mut self, window: &Window, requested: &Requested, now: Instant) -> Option<Verdict> {
+		let limit = self.limit;
+		let asserted = self.asserted.get_mut(window)?;
+
+		let (x, y, width, height) = asserted.geometry;
+		let matches = requested.x.is_none_or(|requested| requested == x)
+			&& requested.y.is_none_or(|requested| requested == y)
+			&& requested.width.is_none_or(|requested| requested == width)
+			&& requested.height.is_none_or(|requested| requested == height);
+		if matches {
+			return Some(Verdict::Notify);
+		}
+
+		let expired = asserted
+			.since
+			.is_none_or(|since| now.saturating_duration_since(since) >= PERIOD);
+		if expired {
+			asserted.since = Some(now);
+			asserted.reasserts = 0;
+			asserted.limited = false;
+		}
+
+		if asserted.reasserts < limit {
+			asserted.reasserts += 1;
+
+			Some(Verdict::Reassert)
+		} else {
+			let first = !asserted.limited;
+			asserted.limited = true;
+
+			Some(Verdict::Limited { first })
+		}
+	}
+
+	/// Forgets the given `window`, e.g. because it is no longer tiled.
+	pub fn forget(&mut self, window: &Window) {
+		self.asserted.remove(window);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rate_limited() {
+		let start = Instant::now();
+		let at = |millis| start + Duration::from_millis(millis);
+
+		let mut guard = ConfigureGuard::new(3);
+		let requested = Requested {
+			width: Some(100),
+			height: Some(100),
+			..Requested::default()
+		};
+
+		assert_eq!(guard.on_request(&1, &requested, at(0)), None);
+		guard.assert(1, (0, 0, 960, 1080));
+
+		// Requests for the asserted geometry are never limited.
+		let current = Requested {
+			x: Some(0),
+			width: Some(960),
+			..Requested::default()
+		};
+		for millis in 0..10 {
+			assert_eq!(guard.on_request(&1, &current, at(millis)), Some(Verdict::Notify));
+		}
+
+		// Other requests are re-asserted up to the limit within a second...
+		for millis in [0, 10, 20] {
+			assert_eq!(guard.on_request(&1, &requested, at(millis)), Some(Verdict::Reassert));
+		}
+		// ...after which they are dropped, only warned about once...
+		assert_eq!(
+			guard.on_request(&1, &requested, at(30)),
+			Some(Verdict::Limited { first: true })
+		);
+		assert_eq!(
+			guard.on_request(&1, &requested, at(999)),
+			Some(Verdict::Limited { first: false })
+		);
+		// ...until the second is up.
+		assert_eq!(guard.on_request(&1, &requested, at(1000)), Some(Verdict::Reassert));
+
+		// Windows are limited separately, and re-asserting a new geometry doesn't reset the limit.
+		guard.assert(2, (960, 0, 960, 1080));
+		assert_eq!(guard.on_request(&2, &requested, at(1000)), Some(Verdict::Reassert));
+		guard.assert(1, (0, 0, 100, 1080));
+		for millis in [1001, 1002] {
+			assert_eq!(guard.on_request(&1, &requested, at(millis)), Some(Verdict::Reassert));
+		}
+		assert_eq!(
+			guard.on_request(&1, &requested, at(1003)),
+			Some(Verdict::Limited { first: true })
+		);
+
+		guard.forget(&1);
+		assert_eq!(guard.asserted(&1), None);
+		assert_eq!(guard.on_request(&1, &requested, at(1004)), None);
+	}
+}
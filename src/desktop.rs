@@ -0,0 +1,174 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Deciding which desktop (workspace) a window opens on.
+//!
+//! Applications may ask for their windows to open on a particular desktop with the EWMH
+//! [`_NET_WM_DESKTOP`] property, e.g. when a browser or an IDE restores its session. Several
+//! sources can say where a window belongs, and the first one which names a desktop that is
+//! [available] is [used], in order of precedence:
+//!
+//! 1. a [rule] which matches the window;
+//! 2. the desktop the window's client [requested];
+//! 3. the desktop the window's class was [remembered] on;
+//! 4. the current desktop.
+//!
+//! Each [workspace] is a desktop, and a window may also be [on every desktop] (i.e. sticky). No
+//! rule gives a desktop yet, and placement memory only remembers whether a window was sticky.
+//!
+//! [`_NET_WM_DESKTOP`]: https://specifications.freedesktop.org/wm-spec/latest/ar01s05.html#id-1.6.8
+//! [available]: resolve
+//! [used]: resolve
+//! [rule]: Candidates::rule
+//! [requested]: Candidates::requested
+//! [remembered]: Candidates::remembered
+//! [workspace]: crate::workspaces
+//! [on every desktop]: Desktop::All
+
+use serde::{Deserialize, Serialize};
+
+/// The `_NET_WM_DESKTOP` of a window which is shown on every desktop.
+pub const ALL_DESKTOPS: u32 = 0xFFFF_FFFF;
+
+/// The desktop a window is on.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum Desktop {
+	/// The desktop at the given index, counting from 0.
+	Index(u32),
+	/// Every desktop: the window is sticky.
+	All,
+}
+
+/// Where the [desktop] a window opens on came from.
+///
+/// [desktop]: Desktop
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Source {
+	Rule,
+	Requested,
+	Remembered,
+	/// None of the other sources gave an available desktop, so the window opens on the current
+	/// desktop.
+	Current,
+}
+
+/// The [desktops] which each source says a window should open on, if any.
+///
+/// [desktops]: Desktop
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct Candidates {
+	/// The desktop given by a [rule] which matches the window.
+	///
+	/// [rule]: crate::rules::Rule
+	pub rule: Option<Desktop>,
+	/// The desktop the window's client requested in its `_NET_WM_DESKTOP` property.
+	pub requested: Option<Desktop>,
+	/// The desktop the window's class was [remembered] on.
+	///
+	/// [remembered]: crate::placement_memory
+	pub remembered: Option<Desktop>,
+}
+
+impl Desktop {
+	/// Decodes a `_NET_WM_DESKTOP` value.
+	pub const fn from_ewmh(desktop: u32) -> Self {
+		match desktop {
+			ALL_DESKTOPS => Self::All,
+			index => Self::Index(index),
+		}
+	}
+
+	/// Encodes the desktop as a `_NET_WM_DESKTOP` value.
+	pub const fn to_ewmh(self) -> u32 {
+		match self {
+			Self::Index(index) => index,
+			Self::All => ALL_DESKTOPS,
+		}
+	}
+
+	/// Returns whether the desktop is one of the first `available` desktops, or every desktop.
+	pub const fn is_available(self, available: u32) -> bool {
+		match self {
+			Self::Index(index) => index < available,
+			Self::All => true,
+		}
+	}
+}
+
+/// Returns the desktop a window opens on, and where it came from, given the [candidates] and the
+/// `current` desktop.
+///
+/// The first candidate, in order of precedence, which is one of the first `available` desktops is
+/// used; candidates naming desktops which don't exist, and can't be created, are skipped. Desktops
+/// which can be created on demand are counted as available.
+///
+/// [candidates]: Candidates
+pub fn resolve(candidates: Candidates, current: u32, available: u32) -> (Desktop, Source) {
+	let Candidates {
+		rule,
+		requested,
+		remembered,
+	} = candidates;
+
+	[
+		(rule, Source::Rule),
+		(requested, Source::Requested),
+		(remembered, Source::Remembered),
+	]
+	.into_iter()
+	.find_map(|(desktop, source)| {
+		desktop
+			.filter(|desktop| desktop.is_available(available))
+			.map(|desktop| (desktop, source))
+	})
+	.unwrap_or((Desktop::Index(current), Source::Current))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ewmh() {
+		assert_eq!(Desktop::from_ewmh(2), Desktop::Index(2));
+		assert_eq!(Desktop::from_ewmh(ALL_DESKTOPS), Desktop::All);
+		assert_eq!(Desktop::All.to_ewmh(), ALL_DESKTOPS);
+		assert_eq!(Desktop::Index(0).to_ewmh(), 0);
+	}
+
+	#[test]
+	fn precedence() {
+		use Desktop::{All, Index};
+
+		let table = [
+			// (rule, requested, remembered) => resolved
+			((None, None, None), (Index(1), Source::Current)),
+			(
+				(Some(Index(2)), Some(Index(3)), Some(Index(0))),
+				(Index(2), Source::Rule),
+			),
+			((None, Some(Index(3)), Some(Index(0))), (Index(3), Source::Requested)),
+			((None, None, Some(Index(0))), (Index(0), Source::Remembered)),
+			((None, Some(All), Some(Index(0))), (All, Source::Requested)),
+			((Some(Index(0)), Some(All), None), (Index(0), Source::Rule)),
+			// Desktops which aren't available are skipped.
+			((Some(Index(4)), Some(Index(3)), None), (Index(3), Source::Requested)),
+			((None, Some(Index(4)), Some(All)), (All, Source::Remembered)),
+			(
+				(Some(Index(9)), Some(Index(4)), Some(Index(7))),
+				(Index(1), Source::Current),
+			),
+		];
+
+		for ((rule, requested, remembered), expected) in table {
+			let candidates = Candidates {
+				rule,
+				requested,
+				remembered,
+			};
+
+			assert_eq!(resolve(candidates, 1, 4), expected, "{candidates:?}");
+		}
+	}
+}
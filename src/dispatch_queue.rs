@@ -0,0 +1,425 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Time-sliced applies of the layout, so that re-tiling a very large layout doesn't block the
+//! event loop.
+//!
+//! Computing the layout's changes is fast, but reconfiguring hundreds of windows in one go can
+//! delay input handling and focus changes noticeably. When applies are time-sliced, the windows'
+//! new geometry is [pushed] to a [`DispatchQueue`] as it is computed, and the queue hands it out
+//! in [chunks], one per event loop iteration, so that events are handled in between.
+//!
+//! Each queued reconfiguration is tied to the compute pass which produced it by a generation. A
+//! change to the layout while chunks are still queued [invalidates] them, so that nothing more is
+//! handed out until the changes are computed again: windows whose geometry changed are pushed
+//! again, replacing their stale reconfigurations, and the rest are then [revalidated].
+//!
+//! Whether applies are time-sliced or not, what is sent in one flush is ordered by [phase]: windows
+//! which are already shown are reconfigured first, then windows which are about to be shown, which
+//! are only mapped once they are in their tiles. Focus is given last. Requests which must wait for
+//! the rest of the flush are [held] until then, so that nothing is ever shown at a stale position.
+//!
+//! [pushed]: DispatchQueue::push
+//! [chunks]: DispatchQueue::next_chunk
+//! [invalidates]: DispatchQueue::invalidate
+//! [revalidated]: DispatchQueue::revalidate
+//! [phase]: Phase
+//! [held]: Ordered
+
+use std::{collections::VecDeque, mem, num::NonZeroUsize};
+
+use crate::layout::Visibility;
+
+/// A suggested number of windows to reconfigure per event loop iteration.
+pub const DEFAULT_CHUNK_SIZE: usize = 50;
+
+/// A window's new geometry, as computed when applying the layout.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Reconfigure<Window> {
+	pub window: Window,
+
+	pub x: i32,
+	pub y: i32,
+	pub width: u32,
+	pub height: u32,
+
+	pub visibility: Visibility,
+}
+
+/// The ordering class of a request sent while applying the layout.
+///
+/// Requests are sent in the order of their phases, as declared here.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum Phase {
+	/// Reconfiguring a window which is already shown.
+	ConfigureExisting,
+	/// Reconfiguring a window which has yet to be shown.
+	ConfigureNew,
+	/// Mapping a window, once every window has been reconfigured.
+	Map,
+	/// Giving a window focus, which can only be done once it is mapped.
+	Focus,
+}
+
+/// Requests held back until the end of an event loop iteration, to be sent in [phase] order.
+///
+/// [phase]: Phase
+#[derive(Debug, Clone)]
+pub struct Ordered<Request> {
+	/// The held requests, in the order they were held, each with its phase.
+	held: Vec<(Phase, Request)>,
+}
+
+/// The windows waiting to be reconfigured by a time-sliced apply.
+///
+/// Each window is queued at most once: pushing a window's geometry again replaces the geometry it
+/// was queued with.
+#[derive(Debug, Clone)]
+pub struct DispatchQueue<Window> {
+	/// The number of windows reconfigured per chunk, or [`None`] if applies aren't time-sliced.
+	chunk_size: Option<NonZeroUsize>,
+
+	/// The generation of the current compute pass.
+	generation: u64,
+	/// The queued reconfigurations, in the order they were computed, each with the generation it
+	/// was computed in.
+	queued: VecDeque<(u64, Reconfigure<Window>)>,
+	/// Windows which were mapped since they were last reconfigured, which are reconfigured first.
+	newly_mapped: Vec<Window>,
+}
+
+impl<Window> Default for DispatchQueue<Window> {
+	fn default() -> Self {
+		Self::new(None)
+	}
+}
+
+impl<Window> DispatchQueue<Window> {
+	/// Creates an empty queue which hands out `chunk_size` windows at a time, or every window at
+	/// once if `chunk_size` is [`None`].
+	pub const fn new(chunk_size: Option<NonZeroUsize>) -> Self {
+		Self {
+			chunk_size,
+
+			generation: 0,
+			queued: VecDeque::new(),
+			newly_mapped: Vec::new(),
+		}
+	}
+
+	/// Returns whether applies are time-sliced, rather than every window being reconfigured
+	/// straight away.
+	#[inline(always)]
+	pub const fn is_sliced(&self) -> bool {
+		self.chunk_size.is_some()
+	}
+
+	/// Returns the generation of the current compute pass.
+	#[inline(always)]
+	pub const fn generation(&self) -> u64 {
+		self.generation
+	}
+
+	/// Returns whether there are windows waiting to be reconfigured.
+	pub fn is_pending(&self) -> bool {
+		!self.queued.is_empty()
+	}
+
+	/// Marks the queued reconfigurations as possibly stale because the layout has changed since
+	/// they were computed.
+	///
+	/// Nothing is handed out until the changes are computed and the queue is [revalidated].
+	///
+	/// [revalidated]: Self::revalidate
+	pub const fn invalidate(&mut self) {
+		self.generation += 1;
+	}
+
+	/// Marks the queued reconfigurations as current once the changes which [invalidated] them have
+	/// been computed.
+	///
+	/// Windows whose geometry changed have been [pushed] again, so the rest still have the
+	/// geometry they were queued with.
+	///
+	/// [invalidated]: Self::invalidate
+	/// [pushed]: Self::push
+	pub fn revalidate(&mut self) {
+		for (generation, _) in &mut self.queued {
+			*generation = self.generation;
+		}
+	}
+}
+
+impl<Request> Default for Ordered<Request> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<Request> Ordered<Request> {
+	/// Creates an empty set of held requests.
+	pub const fn new() -> Self {
+		Self { held: Vec::new() }
+	}
+
+	/// Returns whether no requests are held.
+	pub const fn is_empty(&self) -> bool {
+		self.held.is_empty()
+	}
+
+	/// Holds the given `request` until the held requests are [taken].
+	///
+	/// [taken]: Self::take
+	pub fn push(&mut self, phase: Phase, request: Request) {
+		self.held.push((phase, request));
+	}
+
+	/// Returns whether a held request matches `pred`.
+	pub fn contains(&self, pred: impl Fn(&Request) -> bool) -> bool {
+		self.held.iter().any(|(_, request)| pred(request))
+	}
+
+	/// Drops the held requests matching `pred`, returning whether there were any.
+	pub fn cancel(&mut self, pred: impl Fn(&Request) -> bool) -> bool {
+		let len = self.held.len();
+		self.held.retain(|(_, request)| !pred(request));
+
+		self.held.len() != len
+	}
+
+	/// Takes the held requests in [phase] order, keeping the order they were held in within each
+	/// phase.
+	///
+	/// [phase]: Phase
+	pub fn take(&mut self) -> Vec<Request> {
+		let mut held = mem::take(&mut self.held);
+		held.sort_by_key(|&(phase, _)| phase);
+
+		held.into_iter().map(|(_, request)| request).collect()
+	}
+}
+
+impl<Window: PartialEq + Clone> DispatchQueue<Window> {
+	/// Queues the given `reconfigure`, replacing the window's stale reconfiguration if it is
+	/// already queued.
+	pub fn push(&mut self, reconfigure: Reconfigure<Window>) {
+		let generation = self.generation;
+
+		match self
+			.queued
+			.iter_mut()
+			.find(|(_, queued)| queued.window == reconfigure.window)
+		{
+			Some(queued) => *queued = (generation, reconfigure),
+			None => self.queued.push_back((generation, reconfigure)),
+		}
+	}
+
+	/// Records that the given `window` has just been mapped, so that it is reconfigured in the
+	/// first chunk it is queued for.
+	pub fn mapped(&mut self, window: Window) {
+		if !self.newly_mapped.contains(&window) {
+			self.newly_mapped.push(window);
+		}
+	}
+
+	/// Takes the next chunk of windows to reconfigure.
+	///
+	/// The `focused` window and newly [mapped] windows are taken first, as they are the ones the
+	/// user is looking at. Within the chunk, newly mapped windows [come after] the windows which
+	/// are already shown. Nothing is taken while the queue is [invalidated].
+	///
+	/// [come after]: Phase::ConfigureNew
+	/// [mapped]: Self::mapped
+	/// [invalidated]: Self::invalidate
+	pub fn next_chunk(&mut self, focused: Option<&Window>) -> Vec<Reconfigure<Window>> {
+		if self.queued.iter().any(|&(generation, _)| generation != self.generation) {
+			return Vec::new();
+		}
+		let chunk_size = self.chunk_size.map_or(self.queued.len(), NonZeroUsize::get);
+
+		let urgent = |window: &Window| Some(window) == focused || self.newly_mapped.contains(window);
+		let (mut chunk, rest): (Vec<_>, Vec<_>) = self.queued.drain(..).partition(|(_, queued)| urgent(&queued.window));
+		chunk.extend(rest);
+
+		self.queued = chunk.split_off(chunk_size.min(chunk.len())).into();
+		let mut chunk: Vec<_> = chunk.into_iter().map(|(_, reconfigure)| reconfigure).collect();
+		chunk.sort_by_key(|reconfigure| match self.newly_mapped.contains(&reconfigure.window) {
+			true => Phase::ConfigureNew,
+			false => Phase::ConfigureExisting,
+		});
+
+		self.newly_mapped
+			.retain(|window| chunk.iter().all(|reconfigure| &reconfigure.window != window));
+		if self.queued.is_empty() {
+			self.newly_mapped.clear();
+		}
+
+		chunk
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::HashMap, convert::Infallible};
+
+	use super::*;
+	use crate::{
+		layout::{managers::Stack, LayoutSettings, Reconfigured},
+		state::{AquariWm, MapState},
+	};
+
+	/// Computes the changes to the layout, pushing them to the `queue`.
+	fn compute(state: &mut AquariWm<u32>, queue: &mut DispatchQueue<u32>) {
+		state
+			.apply_changes(|&window, x, y, width, height, visibility| {
+				queue.push(Reconfigure {
+					window,
+					x,
+					y,
+					width,
+					height,
+					visibility,
+				});
+
+				Ok::<_, Infallible>(Reconfigured::Applied)
+			})
+			.unwrap();
+	}
+
+	fn changed(state: &AquariWm<u32>) -> bool {
+		state
+			.tiling_layout()
+			.is_some_and(|layout| layout.stats().pending_changes != 0)
+	}
+
+	/// Applies a 500-window layout in chunks, changing the layout part of the way through, and
+	/// checks that every window is only ever sent the geometry the layout currently gives it.
+	#[test]
+	fn time_sliced() {
+		let mut state = AquariWm::with_tiling_layout_and_windows::<Stack<u32>>(
+			0,
+			0,
+			1920,
+			1080,
+			(1..=500).map(|window| (window, MapState::Mapped)),
+			LayoutSettings::new().window_gap(0),
+		);
+		let mut queue = DispatchQueue::new(NonZeroUsize::new(DEFAULT_CHUNK_SIZE));
+		// What the mock display server was sent for each window.
+		let mut sent = HashMap::new();
+
+		compute(&mut state, &mut queue);
+		assert_eq!(queue.queued.len(), 500);
+
+		let mut chunks = 0;
+		while queue.is_pending() {
+			// Part of the way through, a window is closed and another is mapped and focused.
+			if chunks == 3 {
+				state.remove_window(&450);
+				state.add_window(501, MapState::Mapped).unwrap();
+				queue.mapped(501);
+			}
+			if changed(&state) {
+				queue.invalidate();
+				assert!(queue.next_chunk(None).is_empty());
+
+				compute(&mut state, &mut queue);
+				queue.revalidate();
+			}
+
+			let chunk = queue.next_chunk(state.focused.as_ref());
+			assert!(chunk.len() <= DEFAULT_CHUNK_SIZE);
+			if chunks == 3 {
+				assert_eq!(
+					chunk.last().map(|reconfigure| reconfigure.window),
+					Some(501),
+					"the newly mapped window isn't reconfigured in the first chunk, after the shown windows"
+				);
+			}
+
+			for reconfigure in chunk {
+				let window = reconfigure.window;
+				let tiled = state
+					.tiling_layout()
+					.is_some_and(|layout| layout.contains_window(&window));
+				// Windows which have left the layout aren't reconfigured.
+				if !tiled {
+					continue;
+				}
+
+				let geometry = (reconfigure.x, reconfigure.y, reconfigure.width, reconfigure.height);
+				assert_eq!(
+					state.tile_of(&window),
+					Some(geometry),
+					"window {window} was sent stale geometry"
+				);
+				sent.insert(window, geometry);
+			}
+
+			chunks += 1;
+		}
+
+		assert!(chunks >= 10);
+		for window in (1..=501).filter(|&window| window != 450) {
+			assert_eq!(sent.get(&window).copied(), state.tile_of(&window), "window {window}");
+		}
+	}
+
+	#[test]
+	fn unsliced() {
+		let mut queue = DispatchQueue::new(None);
+		for window in 1..=3 {
+			queue.push(Reconfigure {
+				window,
+				x: 0,
+				y: 0,
+				width: 100,
+				height: 100,
+				visibility: Visibility::Visible,
+			});
+		}
+
+		assert!(!queue.is_sliced());
+		// Windows are only queued once, with their latest geometry.
+		queue.push(Reconfigure {
+			window: 2,
+			x: 0,
+			y: 0,
+			width: 50,
+			height: 100,
+			visibility: Visibility::Visible,
+		});
+
+		let chunk = queue.next_chunk(Some(&3));
+		assert_eq!(
+			chunk.iter().map(|reconfigure| reconfigure.window).collect::<Vec<_>>(),
+			[3, 1, 2]
+		);
+		assert_eq!(chunk[2].width, 50);
+		assert!(!queue.is_pending());
+	}
+
+	#[test]
+	fn phases() {
+		let mut ordered = Ordered::new();
+		ordered.push(Phase::Focus, "focus 1");
+		ordered.push(Phase::Map, "map 1");
+		ordered.push(Phase::ConfigureNew, "configure 1");
+		ordered.push(Phase::Map, "map 2");
+		ordered.push(Phase::ConfigureExisting, "configure 3");
+		ordered.push(Phase::ConfigureExisting, "configure 4");
+
+		assert!(ordered.contains(|&request| request == "map 2"));
+		assert!(ordered.cancel(|&request| request == "map 2"));
+		assert!(!ordered.cancel(|&request| request == "map 2"));
+
+		// Requests are taken by phase, in the order they were held within each phase.
+		assert_eq!(
+			ordered.take(),
+			["configure 3", "configure 4", "configure 1", "map 1", "focus 1"]
+		);
+		assert!(ordered.is_empty());
+	}
+}
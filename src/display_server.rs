@@ -2,20 +2,314 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::{error::Error, future::Future};
+use std::{collections::BTreeMap, error::Error, future::Future, num::NonZeroUsize, path::PathBuf, time::Duration};
 
 #[cfg(feature = "wayland")]
 pub use wayland::Wayland;
 #[cfg(feature = "x11")]
 pub use x11::X11;
 
-use crate::layout::LayoutSettings;
+use crate::{
+	admission,
+	appearance::Appearance,
+	coalesce,
+	config::{self, Config, KeyBindings, ModifierChord},
+	configure_guard,
+	fullscreen::FullscreenPolicy,
+	layout::{managers::BuiltIn, LayoutSettings},
+	output::{CloneOverlap, DefaultOrientation, OutputAssignment, OutputEdges, ScaleOverride},
+	ping::PingSettings,
+	pointer_warp::PointerWarp,
+	rules::Rule,
+	switcher::Scope,
+	theme::Theme,
+	urgency::FocusStealing,
+	wallpaper::Wallpaper,
+	window_groups::GroupSettings,
+};
 
 #[cfg(feature = "wayland")]
 pub mod wayland;
 #[cfg(feature = "x11")]
 pub mod x11;
 
+/// Options with which a [display server] is [run].
+///
+/// Most options can be given in the [configuration file], which is [reloaded] while AquariWM is
+/// running.
+///
+/// [display server]: DisplayServer
+/// [run]: DisplayServer::run
+/// [configuration file]: crate::config
+/// [reloaded]: Self::reload
+#[derive(Debug, Clone)]
+pub struct Options {
+	/// Whether AquariWM should be launched in a testing window.
+	pub testing: bool,
+	pub settings: LayoutSettings,
+	/// The layout manager of workspaces which aren't given one in the
+	/// [`workspace_layout_managers`].
+	///
+	/// [`workspace_layout_managers`]: Self::workspace_layout_managers
+	pub layout_manager: BuiltIn,
+	/// The layout managers of workspaces, by their names or positions (counting from 1).
+	pub workspace_layout_managers: BTreeMap<String, BuiltIn>,
+
+	/// The [scale factor] overrides given for outputs.
+	///
+	/// [scale factor]: crate::output::Scale
+	pub scales: Vec<ScaleOverride>,
+	/// How much monitors must overlap to be treated as [clones] of each other, mirroring the same
+	/// output.
+	///
+	/// [clones]: crate::output::Output::group_clones
+	pub clone_overlap: CloneOverlap,
+	/// The policy for choosing which output new windows open on.
+	pub output_assignment: OutputAssignment,
+	/// Whether focusing or moving windows between outputs wraps around at the edges of the
+	/// outputs.
+	pub output_edges: OutputEdges,
+	/// How the orientation of the layout's root group is chosen for the primary output, or
+	/// [`None`] to keep the layout manager's orientation.
+	pub default_orientation: Option<DefaultOrientation>,
+	/// Whether the layout's root group is reoriented for the primary output when it is rotated,
+	/// according to the [`default_orientation`].
+	///
+	/// [`default_orientation`]: Self::default_orientation
+	pub rotate_with_outputs: bool,
+	/// Which windows are cycled through when switching windows.
+	pub switch_scope: Scope,
+
+	/// Whether the layout should be restored from the newest valid [snapshot].
+	///
+	/// [snapshot]: crate::autosave
+	pub restore: bool,
+	/// Whether AquariWM waits for the display server to come back and reconnects to it if the
+	/// connection to it is lost, rather than exiting.
+	pub reconnect: bool,
+	/// Whether the latencies of user-visible operations are measured and exported; see
+	/// [`metrics`].
+	///
+	/// [`metrics`]: crate::metrics
+	pub latency_metrics: bool,
+	/// Whether windows on other screens than AquariWM's, as on multi-screen X setups, are mapped
+	/// without being managed when they ask to be mapped, rather than left alone.
+	pub map_other_screens: bool,
+	/// Whether the display is exported into the [session environment] once AquariWM has
+	/// registered, so that programs started by services appear on it.
+	///
+	/// [session environment]: crate::session_env
+	pub session_env: bool,
+	/// The maximum time between a change to the layout and it being written to a [snapshot].
+	///
+	/// [snapshot]: crate::autosave
+	pub autosave_interval: Duration,
+
+	/// The minimum time between bursts of updates, like RandR notifications, being [coalesced]
+	/// into re-tiles of the layout.
+	///
+	/// [coalesced]: crate::coalesce
+	pub frame_interval: Duration,
+	/// How long re-tiling the layout waits for more windows while windows are mapped and unmapped
+	/// in quick succession, capped at [`MAX_DEFERRAL`].
+	///
+	/// [`MAX_DEFERRAL`]: crate::coalesce::MAX_DEFERRAL
+	pub map_debounce: Duration,
+	/// How long windows without clear signals that they are normal windows, like splash screens,
+	/// float for before they are tiled, or zero to tile them straight away; see [`admission`].
+	///
+	/// [`admission`]: crate::admission
+	pub admission_delay: Duration,
+	/// The number of windows reconfigured per event loop iteration when applying the layout, or
+	/// [`None`] to reconfigure every window straight away.
+	///
+	/// Time-slicing applies keeps AquariWM responsive with very large layouts; see
+	/// [`dispatch_queue`].
+	///
+	/// [`dispatch_queue`]: crate::dispatch_queue
+	pub apply_chunk_size: Option<NonZeroUsize>,
+	/// The maximum number of times per second a tiled window is configured to its tile again in
+	/// response to its requests for another geometry; see [`configure_guard`].
+	///
+	/// [`configure_guard`]: crate::configure_guard
+	pub configure_rate_limit: u32,
+
+	/// Settings for how windows look.
+	pub appearance: Appearance,
+	/// The colors of everything AquariWM draws itself, like window borders and overlays.
+	pub theme: Theme,
+	/// What the background shows wherever no window covers the screen.
+	pub wallpaper: Wallpaper,
+
+	/// When windows which demand attention may steal focus.
+	pub focus_stealing: FocusStealing,
+	/// What happens while a window is fullscreen.
+	pub fullscreen_policy: FullscreenPolicy,
+	/// When the pointer is warped to the focused window.
+	pub pointer_warp: PointerWarp,
+	/// The modifiers which show where the next window would be tiled while they are held, if any.
+	pub insertion_preview: Option<ModifierChord>,
+	/// How hung clients are detected by [pinging] their windows, and dealt with.
+	///
+	/// [pinging]: crate::ping
+	pub ping: PingSettings,
+	/// How the windows of an application's [window group] are treated as a unit.
+	///
+	/// [window group]: crate::window_groups
+	pub groups: GroupSettings,
+
+	/// Rules which change where matching windows are placed.
+	pub rules: Vec<Rule>,
+
+	/// Shell commands which are run when AquariWM starts.
+	pub autostart: Vec<String>,
+	/// Key bindings which override the display server's default key bindings.
+	pub key_bindings: KeyBindings,
+
+	/// Whether AquariWM changes windows or only logs what it would do.
+	pub dispatch_mode: DispatchMode,
+	/// The file every input AquariWM acts on is [recorded] to, if any.
+	///
+	/// [recorded]: crate::replay
+	pub record: Option<PathBuf>,
+
+	/// The path of the [configuration file], if there is one.
+	///
+	/// [configuration file]: crate::config
+	pub config: Option<PathBuf>,
+	/// Settings which take precedence over those in the [configuration file], like those given on
+	/// the command line.
+	///
+	/// [configuration file]: crate::config
+	pub overrides: Config,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			testing: false,
+			settings: LayoutSettings::default(),
+			layout_manager: BuiltIn::default(),
+			workspace_layout_managers: BTreeMap::new(),
+
+			scales: Vec::new(),
+			clone_overlap: CloneOverlap::default(),
+			output_assignment: OutputAssignment::default(),
+			output_edges: OutputEdges::default(),
+			default_orientation: None,
+			rotate_with_outputs: false,
+			switch_scope: Scope::default(),
+
+			restore: false,
+			reconnect: false,
+			latency_metrics: false,
+			map_other_screens: false,
+			session_env: true,
+			autosave_interval: Duration::from_secs(60),
+
+			frame_interval: Duration::from_millis(16),
+			map_debounce: coalesce::DEFAULT_DEBOUNCE,
+			admission_delay: admission::DEFAULT_ADMISSION_DELAY,
+			apply_chunk_size: None,
+			configure_rate_limit: configure_guard::DEFAULT_REASSERT_LIMIT,
+
+			appearance: Appearance::default(),
+			theme: Theme::default(),
+			wallpaper: Wallpaper::default(),
+
+			focus_stealing: FocusStealing::default(),
+			fullscreen_policy: FullscreenPolicy::default(),
+			pointer_warp: PointerWarp::default(),
+			insertion_preview: None,
+			ping: PingSettings::default(),
+			groups: GroupSettings::default(),
+
+			rules: Vec::new(),
+
+			autostart: Vec::new(),
+			key_bindings: KeyBindings::default(),
+
+			dispatch_mode: DispatchMode::default(),
+			record: None,
+
+			config: None,
+			overrides: Config::default(),
+		}
+	}
+}
+
+impl Options {
+	/// Returns the layout manager of the workspace at the given `index` (counting from 0) with the
+	/// given `name`.
+	///
+	/// A layout manager given for the workspace's name takes precedence over one given for its
+	/// position.
+	pub fn layout_manager_for(&self, index: usize, name: &str) -> BuiltIn {
+		let managers = &self.workspace_layout_managers;
+
+		managers
+			.get(name)
+			.or_else(|| managers.get(&(index + 1).to_string()))
+			.copied()
+			.unwrap_or(self.layout_manager)
+	}
+
+	/// Reads the [configuration file] again, returning these options with the settings given in it
+	/// and the [overrides].
+	///
+	/// Settings which are given neither in the configuration file nor in the overrides take their
+	/// defaults, so settings removed from the configuration file are reset. The options which
+	/// can't be configured, like whether AquariWM is [testing], are kept.
+	///
+	/// [configuration file]: crate::config
+	/// [overrides]: Self::overrides
+	/// [testing]: Self::testing
+	pub fn reload(&self) -> Result<Self, config::Error> {
+		let file = match &self.config {
+			Some(path) => Config::load(path)?,
+			None => Config::default(),
+		};
+
+		let mut options = Self {
+			testing: self.testing,
+			output_assignment: self.output_assignment.clone(),
+			restore: self.restore,
+			reconnect: self.reconnect,
+			latency_metrics: self.latency_metrics,
+			map_other_screens: self.map_other_screens,
+			session_env: self.session_env,
+			dispatch_mode: self.dispatch_mode,
+			record: self.record.clone(),
+
+			config: self.config.clone(),
+			overrides: self.overrides.clone(),
+
+			..Self::default()
+		};
+		self.overrides.clone().over(file).apply(&mut options);
+
+		Ok(options)
+	}
+}
+
+/// Whether AquariWM changes windows, or only logs the changes it would make.
+///
+/// This is useful for debugging, and for safely trying AquariWM out on a live session.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum DispatchMode {
+	/// AquariWM manages windows as normal.
+	#[default]
+	Live,
+	/// AquariWM registers as the window manager and maintains its layout, but only logs the
+	/// changes it would make to windows.
+	///
+	/// Windows are not mapped, so they don't appear.
+	DryRun,
+	/// AquariWM doesn't register as the window manager, so that it can run alongside another one;
+	/// it observes windows being mapped and unmapped, and logs the changes it would make to them.
+	ObserveOnly,
+}
+
 /// An implementation of AquariWM for a particular display server (i.e. X11 or Wayland).
 pub trait DisplayServer {
 	/// The return type used by the display server's [`run`] function.
@@ -25,8 +319,8 @@ pub trait DisplayServer {
 	/// The name of the display server (e.g. `"X11"`).
 	const NAME: &'static str;
 
-	/// Runs the AquariWM implementation for this display server.
-	fn run(testing: bool, settings: LayoutSettings) -> Self::Output;
+	/// Runs the AquariWM implementation for this display server with the given `options`.
+	fn run(options: Options) -> Self::Output;
 
 	/// Returns AquariWM's title, formatted with the display server [`NAME`].
 	///
@@ -4,7 +4,7 @@
 
 use std::{any::Any, env, time::Duration};
 
-#[cfg(feature = "testing")]
+#[cfg(feature = "testing-gui")]
 use smithay::backend::winit::{self, WinitEvent};
 use smithay::{
 	backend::renderer::{
@@ -25,8 +25,8 @@ use thiserror::Error;
 use tracing::{event, span, Level};
 
 use crate::{
-	display_server::{DisplayServer, SyncDisplayServer},
-	layout::LayoutSettings,
+	display_server::{DispatchMode, DisplayServer, Options, SyncDisplayServer},
+	output::ScaleOverride,
 };
 
 pub mod grabs;
@@ -50,7 +50,7 @@ pub enum Error {
 	#[error(transparent)]
 	WaylandInit(#[from] InitError),
 
-	#[cfg(feature = "testing")]
+	#[cfg(feature = "testing-gui")]
 	#[error(transparent)]
 	Winit(#[from] winit::Error),
 }
@@ -65,19 +65,34 @@ impl DisplayServer for Wayland {
 	type Output = Result<(), Error>;
 	const NAME: &'static str = "Wayland";
 
-	fn run(testing: bool, settings: LayoutSettings) -> Result<(), Error> {
+	fn run(options: Options) -> Result<(), Error> {
+		let Options {
+			testing,
+			settings,
+			scales,
+			dispatch_mode,
+			..
+		} = options;
+
 		// Log initialisation.
 		let init_span = span!(Level::INFO, "Initialising").entered();
 
+		if dispatch_mode != DispatchMode::Live {
+			event!(
+				Level::WARN,
+				"Dry runs and observing are not supported by the Wayland compositor; ignoring"
+			);
+		}
+
 		// Create an event loop for the compositor to run with.
 		let mut event_loop = <EventLoop<state::WaylandState>>::try_new()?;
 		// Initialise the AquariWM state.
 		let mut state = state::WaylandState::new(Display::new()?, &mut event_loop, settings);
 
-		// Init winit for testing if the testing feature is enabled.
-		#[cfg(feature = "testing")]
+		// Init winit for testing if the testing-gui feature is enabled.
+		#[cfg(feature = "testing-gui")]
 		if testing {
-			Self::init_winit(&mut event_loop, &mut state)?;
+			Self::init_winit(&mut event_loop, &mut state, &scales)?;
 
 			// Attempt to launch a terminal.
 			match crate::launch_terminal() {
@@ -85,6 +100,13 @@ impl DisplayServer for Wayland {
 				Err(error) => event!(Level::WARN, "Failed to launch terminal: {error}"),
 			}
 		}
+		#[cfg(not(feature = "testing-gui"))]
+		if testing {
+			event!(
+				Level::WARN,
+				"Testing the Wayland compositor requires the `testing-gui` feature"
+			);
+		}
 
 		// End the initialisation span.
 		init_span.exit();
@@ -96,10 +118,11 @@ impl DisplayServer for Wayland {
 }
 
 impl Wayland {
-	#[cfg(feature = "testing")]
+	#[cfg(feature = "testing-gui")]
 	pub fn init_winit(
 		event_loop: &mut EventLoop<state::WaylandState>,
 		state: &mut state::WaylandState,
+		scales: &[ScaleOverride],
 	) -> Result<(), Error> {
 		let _span = span!(Level::DEBUG, "Initialising winit").entered();
 
@@ -117,10 +140,12 @@ impl Wayland {
 			refresh: FPS * MS_PER_SECOND,
 		};
 
+		const OUTPUT_NAME: &str = "winit";
+
 		// Create a fake output for the winit window.
 		let output = Output::new(
 			// Output name.
-			"winit".to_owned(),
+			OUTPUT_NAME.to_owned(),
 			// Properties of the fake output.
 			PhysicalProperties {
 				// No physical size because there is no physical monitor.
@@ -132,10 +157,13 @@ impl Wayland {
 		);
 		output.create_global::<state::WaylandState>(&state.display_handle);
 
+		// There is no physical size to derive a scale factor from, so only use an override, if given.
+		let scale = ScaleOverride::find(scales, OUTPUT_NAME).map(|scale| output::Scale::Fractional(scale.factor()));
+
 		output.change_current_state(
 			Some(output_mode),
 			Some(Transform::Flipped180),
-			None,
+			scale,
 			// Move to 0,0.
 			Some((0, 0).into()),
 		);
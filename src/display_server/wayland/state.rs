@@ -2,7 +2,14 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::{ffi::OsString, sync::Arc, time};
+use std::{
+	ffi::OsString,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time,
+};
 
 use smithay::{
 	backend::renderer::utils::on_commit_buffer_handler,
@@ -47,12 +54,40 @@ use smithay::{
 		socket::ListeningSocketSource,
 	},
 };
+use tracing::{event, Level};
 
 use super::grabs::{move_grab::MoveSurfaceGrab, resize_grab::ResizeSurfaceGrab};
-use crate::{layout::LayoutSettings, state::MapState};
+use crate::{
+	layout::LayoutSettings,
+	state::MapState,
+	window::{ManagedWindow, WindowId},
+};
 
 type Point<N = i32, Space = LogicalSpace> = smithay::utils::Point<N, Space>;
 
+/// The [ID] to be given to the next window which is asked for one.
+///
+/// [ID]: WindowId
+static NEXT_WINDOW_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The [ID] given to a window, kept in its user data.
+///
+/// [ID]: WindowId
+struct Id(WindowId);
+
+/// Wayland windows have no ID of their own which is unique across clients, so each is given the
+/// next free [ID] the first time it is asked for one.
+///
+/// [ID]: WindowId
+impl ManagedWindow for wl::Window {
+	fn id(&self) -> WindowId {
+		let user_data = self.user_data();
+		user_data.insert_if_missing_threadsafe(|| Id(WindowId(NEXT_WINDOW_ID.fetch_add(1, Ordering::Relaxed))));
+
+		user_data.get::<Id>().expect("the ID was just inserted").0
+	}
+}
+
 pub struct WaylandState {
 	pub start_time: time::Instant,
 	pub socket_name: OsString,
@@ -129,7 +164,9 @@ impl XdgShellHandler for WaylandState {
 	fn new_toplevel(&mut self, surface: ToplevelSurface) {
 		let window = wl::Window::new(surface);
 		// Track the window.
-		self.aquariwm_state.add_window(window.clone(), MapState::Mapped);
+		if let Err(error) = self.aquariwm_state.add_window(window.clone(), MapState::Mapped) {
+			event!(Level::WARN, "Failed to track new toplevel: {error}");
+		}
 
 		self.space.map_element(window, (0, 0), false);
 
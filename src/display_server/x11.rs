@@ -2,27 +2,45 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::{env, fmt::Debug, future::Future, io, thread};
+use std::{
+	collections::{HashMap, HashSet},
+	env,
+	fmt::Debug,
+	future::Future,
+	io,
+	iter,
+	mem,
+	path::Path,
+	sync::{Mutex, MutexGuard},
+	thread,
+	time::{Duration, Instant},
+};
 
 use futures::{future, try_join};
 use thiserror::Error;
+use tokio::{signal, time};
 use tracing::{event, span, Level};
 use x11rb_async::{
 	self as x11rb,
 	connection::Connection,
 	protocol::{
+		randr::{self, ConnectionExt as _, ScreenChangeNotifyEvent as ScreenChangeNotify},
+		xinput::{self, ConnectionExt as _, RawKeyPressEvent as RawKeyPress, RawKeyReleaseEvent as RawKeyRelease},
 		xproto::{
 			self as x11,
+			ButtonReleaseEvent as ButtonRelease,
 			ChangeWindowAttributesAux as Attributes,
 			ConnectionExt,
-			CreateNotifyEvent as CreateNotify,
-			DestroyNotifyEvent as DestroyNotify,
 			EnterNotifyEvent as EnterNotify,
 			EventMask,
-			InputFocus,
+			FocusOutEvent as FocusOut,
 			KeyPressEvent as KeyPress,
+			KeyReleaseEvent as KeyRelease,
+			MapNotifyEvent as MapNotify,
 			MapRequestEvent as MapRequest,
-			UnmapNotifyEvent as UnmapNotify,
+			MappingNotifyEvent as MappingNotify,
+			MotionNotifyEvent as MotionNotify,
+			PropertyNotifyEvent as PropertyNotify,
 		},
 		Event,
 	},
@@ -30,15 +48,85 @@ use x11rb_async::{
 };
 
 use crate::{
-	display_server::{AsyncDisplayServer, DisplayServer},
-	layout,
-	layout::LayoutSettings,
-	state,
+	autosave::{self, Autosave, PersistedWorkspace},
+	coalesce::{self, Coalescer, IdleTimer, PendingApply},
+	config::{self, Changes},
+	configure_guard::ConfigureGuard,
+	dispatch_queue::{DispatchQueue, Phase, Reconfigure},
+	display_server::{AsyncDisplayServer, DisplayServer, Options},
+	fullscreen::FocusRequest,
+	ignore::IgnoreList,
+	layout::{self, managers::BuiltIn, Reconfigured, Visibility},
+	metrics::{Metrics, Operation, Progress},
+	moveresize::Drag,
+	output::{CloneChange, CloneOverlap, Migration, Output, OutputAssignment, Scale, ScaleOverride},
+	pid,
+	ping::Pings,
+	placement::{self, Geometry, SizeHints},
+	placement_memory::PlacementMemory,
+	pointer_warp::{self, PointerWarp, Trigger},
+	session_env::{self, Session},
+	state::{self, FocusCause},
+	status::{self, Part, Status, StatusPublisher},
+	switcher,
+	theme::Slot,
+	wallpaper::{Color, Image},
+	window_groups::WindowGroups,
+	window_list::WindowList,
 };
 
+mod bindings;
+mod dispatch;
+mod focus;
+mod modifiers;
+mod property;
+mod reparent;
+pub mod replay;
+mod snapshot;
+#[cfg(feature = "testing")]
+pub mod test_client;
 #[cfg(feature = "testing")]
 mod testing;
 mod util;
+mod visual;
+mod wallpaper;
+mod window;
+mod wm;
+mod wm_state;
+
+use bindings::{key_bindings, Binding, GrabStatus, KeyGrabs, Keys, SwitchKeys};
+use dispatch::{Dispatcher, Request};
+use modifiers::{keysym, Keymap, Modifier, ModifierMap, Modifiers};
+use snapshot::{ClientSnapshot, Property};
+use util::{ClassifiedError, ConnectionExt as _};
+use visual::{Palettes, Visual};
+use wallpaper::RootBackground;
+use window::{ClientWindow, UtilityWindow, WindowRole};
+use wm::{Overlay, Prefetched, SessionCommand, Wm};
+
+/// The time given to requests which take effect immediately.
+const CURRENT_TIME: u32 = 0;
+
+/// The width of the border of [overlays], such as the [insertion preview].
+///
+/// [overlays]: Overlay
+/// [insertion preview]: Options::insertion_preview
+const PREVIEW_BORDER_WIDTH: u32 = 4;
+
+/// The exit code with which AquariWM exits when its connection to the X server is lost, so that a
+/// session supervisor can tell that it should be restarted. This is `EX_TEMPFAIL` from
+/// `sysexits.h`.
+pub const CONNECTION_LOST_EXIT_CODE: i32 = 75;
+
+/// The number of times AquariWM attempts to reconnect to the X server after the connection to it
+/// is lost, if [reconnecting] is enabled.
+///
+/// [reconnecting]: Options::reconnect
+const RECONNECT_ATTEMPTS: u32 = 10;
+/// The delay before the first attempt to reconnect, which is doubled for each attempt after it.
+const RECONNECT_DELAY: Duration = Duration::from_millis(250);
+/// The maximum delay between attempts to reconnect.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(8);
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -51,6 +139,9 @@ pub enum Error {
 	/// An error in a request's reply.
 	#[error(transparent)]
 	Reply(#[from] x11rb::errors::ReplyError),
+	/// An error generating an ID for a new resource, or in a request's reply.
+	#[error(transparent)]
+	ReplyOrId(#[from] x11rb::errors::ReplyOrIdError),
 
 	/// There was an error parsing a [`x11::MapState`].
 	#[error("There was an error attempting to parse a MapState: {0}")]
@@ -59,10 +150,59 @@ pub enum Error {
 	///
 	/// [floating]: layout::Mode::Floating
 	#[error("The given window ({0}) is tiled, not floating")]
-	NonFloatingWindow(x11::Window),
+	NonFloatingWindow(ClientWindow),
 
 	#[error(transparent)]
 	Io(#[from] io::Error),
+
+	/// The testing backend given by the `AQUARIWM_TEST_BACKEND` env variable was invalid.
+	#[cfg(feature = "testing")]
+	#[error(transparent)]
+	TestBackend(#[from] testing::ParseModeError),
+}
+
+impl Error {
+	/// Returns whether this is a `BadWindow` error, caused by a request for a window which no
+	/// longer exists.
+	fn is_bad_window(&self) -> bool {
+		let (Self::Reply(x11rb::errors::ReplyError::X11Error(error))
+		| Self::ReplyOrId(x11rb::errors::ReplyOrIdError::X11Error(error))) = self
+		else {
+			return false;
+		};
+
+		error.error_kind == x11rb::protocol::ErrorKind::Window
+	}
+
+	/// Returns whether this error means that the connection to the X server was lost, rather than
+	/// that a request failed.
+	///
+	/// Once the connection is lost, every request fails, so nothing more can be done on the X
+	/// server.
+	pub const fn is_connection_loss(&self) -> bool {
+		use x11rb::errors::{ConnectionError, ReplyError, ReplyOrIdError};
+
+		let (Self::Connection(error)
+		| Self::Reply(ReplyError::ConnectionError(error))
+		| Self::ReplyOrId(ReplyOrIdError::ConnectionError(error))) = self
+		else {
+			return false;
+		};
+
+		matches!(error, ConnectionError::IoError(_) | ConnectionError::UnknownError)
+	}
+}
+
+impl From<ClassifiedError> for Error {
+	fn from(error: ClassifiedError) -> Self {
+		match error {
+			ClassifiedError::Connection(error) => Self::Connection(error),
+
+			ClassifiedError::WindowGone(error) | ClassifiedError::Access(error) | ClassifiedError::Other(error) => {
+				Self::Reply(x11rb::errors::ReplyError::X11Error(error))
+			},
+		}
+	}
 }
 
 pub type Result<T, Err = Error> = std::result::Result<T, Err>;
@@ -73,6 +213,16 @@ pub struct X11 {
 	pub conn: RustConnection,
 	/// The root window for the screen.
 	pub root: x11::Window,
+
+	/// What AquariWM knows about the windows it manages, which decides how events are handled
+	/// independently of the connection.
+	handler: Mutex<Wm>,
+
+	/// The atoms used by AquariWM which are not predefined by the X11 protocol.
+	atoms: util::Atoms,
+
+	/// Decides whether requests which change windows are sent or only logged.
+	dispatcher: Dispatcher,
 }
 
 impl AsyncDisplayServer for X11 {
@@ -83,17 +233,120 @@ impl DisplayServer for X11 {
 	type Output = impl Future<Output = Result<(), Error>>;
 	const NAME: &'static str = "X11";
 
-	fn run(testing: bool, settings: LayoutSettings) -> Self::Output {
+	fn run(options: Options) -> Self::Output {
 		async move {
-			let init_span = span!(Level::INFO, "Initialisation").entered();
-
 			// Spawn Xephyr - a nested X server - if `testing` is enabled so AquariWM runs in a testing
 			// window. Keep it in scope so it can be killed when it is dropped.
 			#[cfg(feature = "testing")]
-			let _process = testing.then_some(testing::Xephyr::spawn()?);
+			let nested = match options.testing {
+				true => Some(testing::Xephyr::spawn(testing::Mode::from_env()?)?),
+				false => None,
+			};
+			// The display AquariWM connects to, which is the one exported into the session
+			// environment: the nested X server's if testing, or the one given by the `DISPLAY` env
+			// variable.
+			#[cfg(feature = "testing")]
+			let display = match &nested {
+				Some(nested) => Some(nested.display().to_owned()),
+				None => env::var("DISPLAY").ok(),
+			};
+			#[cfg(not(feature = "testing"))]
+			let display = env::var("DISPLAY").ok();
+
+			// The options in effect, which the configuration is reloaded over.
+			let mut current = options;
+			let mut restore = current.restore;
+			let mut initial = true;
+			// The number of failed attempts to reconnect since the connection was lost.
+			let mut attempts = 0;
+
+			loop {
+				match Self::session(&mut current, display.as_deref(), restore, mem::take(&mut initial)).await {
+					// The connection was lost: wait for the X server to come back.
+					Err(error) if current.reconnect && error.is_connection_loss() => attempts = 0,
+					// The X server hasn't come back yet.
+					Err(Error::Connect(error)) if current.reconnect && attempts > 0 => {
+						if attempts == RECONNECT_ATTEMPTS {
+							event!(Level::ERROR, "Gave up reconnecting to the X server: {error}");
+
+							return Err(error.into());
+						}
+					},
+
+					result => return result,
+				}
 
-			// Connect to the X server on the display specified by the `DISPLAY` env variable.
-			let (connection, screen_num, drive) = RustConnection::connect(None).await?;
+				let delay = reconnect_delay(attempts);
+				event!(Level::INFO, "Reconnecting to the X server in {delay:?}");
+
+				time::sleep(delay).await;
+				attempts += 1;
+				// The layout is restored from the snapshot written when the connection was lost.
+				restore = true;
+			}
+		}
+	}
+}
+
+impl X11 {
+	/// Runs a session of AquariWM with a new connection to the X server, until it exits or the
+	/// connection is lost.
+	///
+	/// The session starts from scratch: AquariWM connects to the given `display`, or the one given
+	/// by the `DISPLAY` env variable if [`None`], registers as the window manager, adopts the
+	/// existing windows, and restores the layout if `restore` is set. The `initial` session also
+	/// exports the display into the [session environment] and runs the autostart commands; later
+	/// sessions, after reconnecting, leave the programs they launched running.
+	///
+	/// [session environment]: crate::session_env
+	fn session<'a>(
+		current: &'a mut Options,
+		display: Option<&'a str>,
+		restore: bool,
+		initial: bool,
+	) -> impl Future<Output = Result<()>> + 'a {
+		// Every workspace shares the first workspace's layout manager.
+		let layout_manager = current.layout_manager_for(0, status::WORKSPACE);
+		let Options {
+			testing,
+			settings,
+			scales,
+			clone_overlap,
+			output_assignment,
+			output_edges,
+			mut default_orientation,
+			mut rotate_with_outputs,
+			switch_scope,
+			autosave_interval,
+			frame_interval,
+			map_debounce,
+			admission_delay,
+			apply_chunk_size,
+			configure_rate_limit,
+			latency_metrics,
+			map_other_screens,
+			session_env,
+			appearance,
+			mut wallpaper,
+			focus_stealing,
+			fullscreen_policy,
+			mut pointer_warp,
+			mut insertion_preview,
+			ping,
+			groups,
+			theme,
+			rules,
+			autostart,
+			key_bindings: configured_bindings,
+			dispatch_mode,
+			..
+		} = current.clone();
+
+		async move {
+			let init_span = span!(Level::INFO, "Initialisation").entered();
+
+			// Connect to the X server on the display.
+			let (connection, screen_num, drive) = RustConnection::connect(display).await?;
 
 			// Spawn a task that reads from the connection.
 			tokio::spawn(async move {
@@ -107,190 +360,1257 @@ impl DisplayServer for X11 {
 			let screen = &setup.roots[screen_num];
 			// Get the root window of the screen.
 			let (width, height, root) = (screen.width_in_pixels, screen.height_in_pixels, screen.root);
-
+			let width_mm = screen.width_in_millimeters;
+			// The theme's colors are resolved for the screen's visual once, up front.
+			let screen_visual = Visual::find(screen, screen.root_visual, screen.default_colormap);
+			let palettes = Palettes::new(theme, screen.root_depth, screen_visual);
 			// Wrap the connection to provide easy access to utility methods.
-			let wm = Self { conn: connection, root };
+			let atoms = util::Atoms::intern(&connection).await?;
+			let wm = Self {
+				conn: connection,
+				root,
+
+				handler: Mutex::new(Wm {
+					pending_apply: PendingApply::new(map_debounce),
+					admission_delay,
+					dispatch_queue: DispatchQueue::new(apply_chunk_size),
+					configure_guard: ConfigureGuard::new(configure_rate_limit),
+					metrics: Metrics::new(latency_metrics),
+					pings: Pings::new(ping, Instant::now()),
+					groups: WindowGroups::new(groups),
+					palettes,
+					atoms,
+					appearance,
+					focus_stealing,
+					output_assignment,
+					map_other_screens,
+					drag_motion: Coalescer::new(frame_interval),
+					..Wm::new(root, (0, 0, width as u32, height as u32), fullscreen_policy, rules)
+				}),
+
+				atoms,
+
+				dispatcher: Dispatcher::new(dispatch_mode),
+			};
 
 			// Attempt to register as a window manager.
 			match wm.register_window_manager().await {
+				Ok(_) if wm.dispatcher.is_observe_only() => {
+					event!(Level::INFO, "Observing windows alongside the running window manager")
+				},
 				Ok(_) => event!(Level::INFO, "Successfully registered window manager"),
 
 				// If we failed to register the window manager, exit AquariWM.
+				Err(error @ ClassifiedError::Access(_)) => {
+					event!(
+						Level::ERROR,
+						"Failed to register AquariWM as a window manager; another window manager is already running"
+					);
+
+					return Err(error.into());
+				},
 				Err(error) => {
 					event!(
 						Level::ERROR,
-						"Failed to register AquariWM as a window manager; another window manager is probably already \
-						 running:\n{}",
+						"Failed to register AquariWM as a window manager:\n{}",
 						error
 					);
 
-					return Err(error);
+					return Err(error.into());
 				},
 			}
+			if wm.dispatcher.is_dry_run() {
+				event!(
+					Level::INFO,
+					"Dry run: changes to windows will be logged instead of made"
+				);
+			}
+			if !wm.dispatcher.is_observe_only() {
+				wm.advertise_ewmh_support().await?;
+			}
 
-			const ENTER: u8 = 0x0d;
-			let exit_window_grab = async {
-				wm.conn
-					.grab_key(
-						false,
-						root,
-						x11::ModMask::M4 | x11::ModMask::SHIFT,
-						b'I',
-						x11::GrabMode::ASYNC,
-						x11::GrabMode::ASYNC,
-					)
-					.await?
-					.ignore_error();
+			// Grab the key bindings, remembering which keys and modifiers they were resolved to. Those
+			// which can't be grabbed are matched against raw key events instead, if they are available.
+			let raw_keys = wm.select_raw_key_events().await?;
+			if insertion_preview.is_some() && !raw_keys {
+				event!(
+					Level::WARN,
+					"XInput2 is not available, so the insertion preview can't be shown"
+				);
+			}
+			let mut key_table = key_bindings(&configured_bindings);
+			let keys = wm.grab_key_bindings(&key_table, raw_keys).await?;
+			wm.handler().keys = keys;
+
+			let windows = wm.query_windows().await?;
+			// The existing geometries of mapped windows, so that they keep their relative sizes when
+			// they are tiled.
+			let mut geometries = HashMap::new();
+			for &(window, mapped) in &windows {
+				if mapped == state::MapState::Mapped {
+					// Property changes are selected before the snapshot is fetched, so that none are
+					// missed.
+					wm.select_window_events(window).await?;
+					let snapshot = wm.fetch_snapshot(window).await?;
+					wm.handler().snapshots.insert(window, snapshot.clone());
+					wm.handler().update_group(window);
+
+					if snapshot.is_fullscreen(&wm.atoms) {
+						wm.handler().fullscreen.insert(window);
+					}
+					if let Some(class) = snapshot.class {
+						wm.handler().classes.insert(window, class);
+					}
+					wm.handler().update_title(window);
+					if let Some(visual) = snapshot.visual {
+						wm.handler().visuals.insert(window, visual);
+					}
+
+					// The window may have been destroyed in the meantime.
+					if let Some(geometry) = snapshot.geometry {
+						geometries.insert(
+							window,
+							(
+								geometry.x as i32,
+								geometry.y as i32,
+								geometry.width as u32,
+								geometry.height as u32,
+							),
+						);
+					}
+				}
+			}
+
+			let autosave_dir = Autosave::default_dir();
+			// Windows reopen where the last window of their class was closed, even in a new session.
+			let mut placements = autosave_dir
+				.as_deref()
+				.map_or_else(PlacementMemory::default, PlacementMemory::read);
 
-				<Result<_, Error>>::Ok(())
+			let restored = match &autosave_dir {
+				Some(dir) if restore => autosave::restore::<PersistedWorkspace>(dir)
+					.and_then(|snapshot| snapshot.resolve(ClientWindow::from_id)),
+				_ => None,
 			};
-			let spawn_terminal_grab = async {
-				wm.conn
-					.grab_key(
-						false,
-						root,
-						x11::ModMask::M4 | x11::ModMask::SHIFT,
-						ENTER,
-						x11::GrabMode::ASYNC,
-						x11::GrabMode::ASYNC,
-					)
-					.await?
-					.ignore_error();
 
-				Ok(())
+			let is_restored = restored.is_some();
+			let mut state = match restored {
+				Some(snapshot) => {
+					// The workspace keeps the layout manager it was saved with.
+					let manager = match snapshot.manager.as_deref().map(str::parse::<BuiltIn>) {
+						Some(Ok(manager)) => manager,
+						Some(Err(error)) => {
+							event!(Level::WARN, "{error}; using the {layout_manager} layout manager");
+							layout_manager
+						},
+
+						None => layout_manager,
+					};
+
+					let mut state = state::AquariWm::with_restored_manager(
+						manager.init(snapshot.layout, iter::empty()),
+						(0, 0, width as u32, height as u32),
+						windows.iter().copied(),
+						settings,
+					);
+					// Marks of windows which have since been destroyed are dropped.
+					state.marks = snapshot.marks;
+					state.marks.retain(|window| state.windows.contains_key(window));
+
+					// Restored windows which are currently unmapped were probably hidden by the previous
+					// AquariWM instance, so treat them as hidden: they will be mapped again if visible.
+					wm.handler()
+						.hidden
+						.extend(windows.iter().filter_map(|(window, mapped)| {
+							let restored = state.windows.get(window).map(|state| state.mapped);
+
+							(*mapped == state::MapState::Unmapped && restored == Some(state::MapState::Mapped))
+								.then_some(*window)
+						}));
+
+					state
+				},
+
+				None => {
+					if restore {
+						event!(Level::WARN, "No valid layout snapshot to restore");
+					}
+
+					let mut state =
+						state::AquariWm::with_tiling_layout_and_geometries::<layout::managers::Stack<ClientWindow>>(
+							0,
+							0,
+							width as u32,
+							height as u32,
+							windows.into_iter().map(|(window, mapped)| {
+								let geometry = geometries.get(&window).copied().unwrap_or_default();
+
+								(window, mapped, geometry)
+							}),
+							settings,
+						);
+					// Only `Stack` adopts the geometries of windows which were already open; other
+					// layout managers take them over in the order they were adopted.
+					state.set_layout_manager(layout_manager);
+
+					state
+				},
 			};
-			try_join!(exit_window_grab, spawn_terminal_grab)?;
 
-			let mut state = state::AquariWm::with_tiling_layout_and_windows::<layout::managers::Stack<x11::Window>>(
-				0,
-				0,
-				width as u32,
-				height as u32,
-				wm.query_windows().await?,
-				settings,
-			);
+			// Windows ignored in a previous session are still ignored.
+			if let Some(dir) = &autosave_dir {
+				state.ignored = IgnoreList::read(dir);
+			}
+
+			let mut autosave = autosave_dir.map(|dir| Autosave::new(dir, autosave_interval));
+			if autosave.is_none() {
+				event!(
+					Level::WARN,
+					"XDG_RUNTIME_DIR is not set; the layout will not be autosaved"
+				);
+			}
+
+			// Track changes to the outputs so that the layout can be adapted to them.
+			if let Ok(cookie) = wm.conn.randr_select_input(root, randr::NotifyMask::SCREEN_CHANGE).await {
+				cookie.ignore_error();
+			}
+
+			state.outputs = wm
+				.query_outputs(width, height, width_mm, &scales, clone_overlap)
+				.await?;
+			state.output_edges = output_edges;
+			state.switch_scope = switch_scope;
+
+			// Fill the background, so that windows don't leave trails behind them as they move.
+			let mut screen_size = (width, height);
+			let mut background = RootBackground::new(wm.screen());
+			let mut wallpaper_image = load_wallpaper(wallpaper.image.as_deref());
+			wm.draw_wallpaper(
+				&mut background,
+				screen_size,
+				&state.outputs,
+				wallpaper.color,
+				wallpaper_image.as_ref(),
+			)
+			.await?;
+
+			// The tiling layout spans the whole screen, so it uses the primary output's scale factor.
+			let scale = Output::primary(&state.outputs).map_or(Scale::IDENTITY, |output| output.scale);
+
+			event!(Level::INFO, "Using a scale factor of {scale}");
+			state.set_scale(scale);
 
-			if testing {
+			// A new layout is oriented for the primary output; a restored one keeps its orientation.
+			let orientation = default_orientation
+				.zip(Output::primary(&state.outputs))
+				.map(|(policy, primary)| policy.for_output(primary));
+			if let (Some(orientation), false) = (orientation, is_restored) {
+				event!(Level::INFO, "Using a {orientation:?} layout for the primary output");
+				state.set_root_orientation(orientation);
+			}
+
+			if testing && initial {
 				event!(Level::INFO, "Testing mode enabled");
 
-				// Attempt to launch a terminal.
-				match crate::launch_terminal() {
-					Ok((name, _)) => event!(Level::INFO, "Launched {name:?}"),
-					Err(error) => event!(Level::WARN, "Failed to launch terminal: {error}"),
+				// Launch two test clients to be managed, so that testing doesn't depend on a terminal
+				// emulator being installed.
+				#[cfg(feature = "testing")]
+				for _ in 0..2 {
+					match test_client::spawn(test_client::DEFAULT_CLASS) {
+						Ok(_) => event!(Level::INFO, "Launched a test client"),
+						Err(error) => event!(Level::WARN, "Failed to launch a test client: {error}"),
+					}
 				}
 			}
 
 			init_span.exit();
 			let event_loop_span = span!(Level::DEBUG, "Event loop");
 
-			let resize_window = |window: &_, x, y, width, height| wm.reconfigure_window(*window, x, y, width, height);
+			let resize_window = |window: &_, x, y, width, height, visibility| {
+				wm.reconfigure_or_queue_window(*window, x, y, width, height, visibility)
+			};
 
-			loop {
-				let _span = event_loop_span.enter();
+			// Only the first session is recorded, from the state its header describes. The layout is
+			// applied first, so that replaying starts from the same geometries.
+			let mut recorder = match &current.record {
+				Some(path) if initial => {
+					state.apply_changes_async(resize_window).await?;
+
+					let header = replay::Header::of(&wm.handler(), &state, current);
+					replay::Recorder::create(path, &header, &state).unwrap_or_else(|error| {
+						event!(Level::ERROR, "Failed to record inputs to {}: {error}", path.display());
+
+						replay::Recorder::disabled()
+					})
+				},
+
+				_ => replay::Recorder::disabled(),
+			};
+
+			let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())?;
+			// `SIGHUP` reloads the configuration, like the reload key binding.
+			let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())?;
+			let mut reload = false;
+			// Screen changes come in storms while monitors are (un)plugged; only the latest is used.
+			let mut screen_changes = Coalescer::new(frame_interval);
+
+			// Sizes which have drifted in the layout are repaired once it has been left alone.
+			let mut compaction = IdleTimer::new(coalesce::COMPACT_IDLE);
+
+			// Programs started by services, rather than by AquariWM, only find the display once it is
+			// in the session environment, where it stays after reconnecting. A window manager being
+			// observed is left to do this itself.
+			if initial && session_env && !wm.dispatcher.is_observe_only() {
+				if let Some(display) = display {
+					session_env::export(&Session::detect(), display);
+				}
+			}
+
+			// Autostart commands are only run once, not again after reconnecting.
+			if initial {
+				for command in &autostart {
+					match crate::spawn(command) {
+						Ok(process) => {
+							let output = state.active_output.name().map(ToOwned::to_owned);
+
+							wm.handler().launches.record(process.id(), output, Instant::now());
+						},
+						Err(error) => event!(Level::WARN, "Failed to run autostart command `{command}`: {error}"),
+					}
+				}
+			}
+			// The status exported for external bars, which is published at most once per iteration.
+			let mut status = StatusPublisher::new();
+			// The window list exported for external window switchers, as it was last published.
+			let mut window_list = None;
+			// The focused window when the pointer was last considered for warping to it.
+			let mut warped_focus = None;
+			// The focused window when it was last pinged.
+			let mut pinged_focus = None;
+
+			// Connection loss from waiting for events, flushing, or sending requests all ends up here.
+			let result: Result<()> = async {
+				loop {
+					let _span = event_loop_span.enter();
+					// Everything done since the last iteration is recorded along with the input it was
+					// done for.
+					recorder.checkpoint(&state);
+
+					// Apply the changes to the configuration file, with a single re-tile of the layout.
+					if mem::take(&mut reload) {
+						let new = match current.reload() {
+							Ok(new) => new,
+
+							Err(error) => {
+								event!(Level::ERROR, "Failed to reload the configuration: {error}");
+								continue;
+							},
+						};
+						let changes = Changes::between(current, &new);
+
+						if changes.is_empty() {
+							event!(Level::INFO, "Reloaded the configuration; nothing changed");
+						}
+
+						if changes.layout {
+							state.output_edges = new.output_edges;
+							state.switch_scope = new.switch_scope;
+							rotate_with_outputs = new.rotate_with_outputs;
+
+							// The layout is only reoriented if its default orientation changed, so that it
+							// isn't reset for every reload.
+							if new.default_orientation != default_orientation {
+								default_orientation = new.default_orientation;
+
+								let orientation = default_orientation
+									.zip(Output::primary(&state.outputs))
+									.map(|(policy, primary)| policy.for_output(primary));
+								if let Some(orientation) = orientation {
+									state.set_root_orientation(orientation);
+								}
+							}
+
+							state.set_settings(new.settings.clone());
+						}
+						// Other workspaces would keep their layout managers, but AquariWM has a single
+						// workspace.
+						if changes.layout_manager {
+							state.set_layout_manager(new.layout_manager_for(0, status::WORKSPACE));
+						}
+						if changes.layout || changes.layout_manager {
+							state.apply_changes_async(resize_window).await?;
+						}
+
+						if changes.appearance {
+							wm.handler().appearance = new.appearance.clone();
+
+							wm.undim_all().await?;
+							let mut windows: Vec<_> = state.windows.keys().copied().collect();
+							windows.sort_unstable();
+
+							for window in windows {
+								let requests = wm.handler().update_opacity(&state, window);
+								wm.dispatch_all(requests).await?;
+							}
+						}
+						if changes.theme {
+							// Every border is painted again at the start of the next iteration, in a single pass.
+							let overlays = {
+								let mut handler = wm.handler();
+								handler.palettes.set_theme(new.theme.clone());
+								handler.painted.clear();
+
+								[handler.preview_overlay.window, handler.drop_overlay.window]
+							};
+
+							for overlay in overlays.into_iter().flatten() {
+								wm.paint_overlay(overlay).await?;
+							}
+						}
+						if changes.wallpaper {
+							wallpaper = new.wallpaper.clone();
+							wallpaper_image = load_wallpaper(wallpaper.image.as_deref());
+
+							wm.draw_wallpaper(
+								&mut background,
+								screen_size,
+								&state.outputs,
+								wallpaper.color,
+								wallpaper_image.as_ref(),
+							)
+							.await?;
+						}
+						if changes.focus_stealing {
+							wm.handler().focus_stealing = new.focus_stealing;
+						}
+						if changes.pointer_warp {
+							pointer_warp = new.pointer_warp;
+						}
+						if changes.insertion_preview {
+							insertion_preview = new.insertion_preview.clone();
+						}
+						if changes.ping {
+							wm.handler().pings.set_settings(new.ping.clone(), Instant::now());
+						}
+						if changes.groups {
+							wm.handler().groups.set_settings(new.groups.clone());
+						}
+						if changes.rules {
+							wm.handler().rules.replace(new.rules.clone());
+						}
+
+						if changes.key_bindings {
+							wm.conn
+								.ungrab_key(x11::Grab::ANY, root, x11::ModMask::ANY)
+								.await?
+								.ignore_error();
+
+							key_table = key_bindings(&new.key_bindings);
+							let keys = wm.grab_key_bindings(&key_table, raw_keys).await?;
+							wm.handler().keys = keys;
+						}
+
+						if !changes.restart.is_empty() {
+							event!(
+								Level::WARN,
+								"Changes to {} only take effect when AquariWM is restarted",
+								changes.restart.join(", ")
+							);
+						}
+
+						*current = new;
 
-				// Flush the requests of the previous iteration, if there are any to flush.
-				wm.conn.flush().await?;
+						let input = replay::Input::Other {
+							event: "reload".to_owned(),
+						};
+						recorder.note(input, Instant::now());
+						recorder.checkpoint(&state);
+					}
 
-				// Wait for the next event.
-				let event = wm.conn.wait_for_event().await?;
-				event!(Level::TRACE, "{:?}", event);
+					let layout_changed = state.take_changed();
+					let placements_changed = placements.take_changed();
+					if layout_changed || placements_changed {
+						if let Some(autosave) = &mut autosave {
+							autosave.mark_dirty(Instant::now());
+						}
+					}
+					if layout_changed {
+						compaction.touch(Instant::now());
+					}
+
+					// If the screen's outputs have changed, adapt the layout to the new outputs.
+					if let Some((width, height, mwidth)) = screen_changes.poll(Instant::now()) {
+						let new_outputs = wm.query_outputs(width, height, mwidth, &scales, clone_overlap).await?;
+
+						for change in CloneChange::between(&state.outputs, &new_outputs) {
+							match change {
+								CloneChange::Grouped { output, clones } => {
+									event!(Level::INFO, "Outputs {clones:?} are now clones of output {output:?}");
+								},
+								CloneChange::Split { output, clones } => {
+									event!(
+										Level::INFO,
+										"Outputs {clones:?} are no longer clones of output {output:?}"
+									);
+								},
+							}
+						}
 
-				match event {
-					// Track the state of newly created windows.
-					Event::CreateNotify(CreateNotify { window, .. }) => {
-						state.add_window(window, state::MapState::Unmapped);
+						let migrations = wm.handler().output_assignment.migrations(&state.outputs, &new_outputs);
+						for Migration { workspace, from, to } in migrations {
+							event!(
+								Level::INFO,
+								"Moving workspace {workspace} from output {from:?} to {to:?}"
+							);
+						}
 
+						// A new primary output, or a rotated one, may call for the layout to be reoriented.
+						let rotations = default_orientation.and_then(|policy| {
+							policy.rotations(
+								Output::primary(&state.outputs),
+								Output::primary(&new_outputs),
+								rotate_with_outputs,
+							)
+						});
+						if let Some(rotations) = rotations {
+							event!(
+								Level::INFO,
+								"Rotating the layout by {rotations} for the new primary output"
+							);
+							state.rotate_root(rotations);
+						}
+						state.outputs = new_outputs;
+
+						// Each output shows the whole wallpaper image, scaled for its new resolution.
+						screen_size = (width, height);
+						wm.draw_wallpaper(
+							&mut background,
+							screen_size,
+							&state.outputs,
+							wallpaper.color,
+							wallpaper_image.as_ref(),
+						)
+						.await?;
+
+						let scale = Output::primary(&state.outputs).map_or(Scale::IDENTITY, |output| output.scale);
+
+						// Re-tile the layout once for the new outputs.
+						state.resize(0, 0, width as u32, height as u32);
+						state.set_scale(scale);
 						state.apply_changes_async(resize_window).await?;
-					},
-					// Stop tracking the state of destroyed windows.
-					Event::DestroyNotify(DestroyNotify { window, .. }) => {
-						state.remove_window(&window);
 
+						let input = replay::Input::Screen {
+							width,
+							height,
+							outputs: state.outputs.clone(),
+							rotations,
+						};
+						recorder.note(input, Instant::now());
+						recorder.checkpoint(&state);
+					}
+
+					// Tile the windows which have stayed mapped for the admission delay, along with the
+					// windows being mapped and unmapped around them.
+					let now = Instant::now();
+					let admitted = wm.handler().admit_due(&mut state, now);
+					for &window in &admitted {
+						let requests = wm.handler().update_opacity(&state, window);
+						wm.dispatch_all(requests).await?;
+					}
+					if !admitted.is_empty() {
+						wm.handler().pending_apply.defer(Instant::now());
+
+						recorder.note(replay::Input::Admit, now);
+						recorder.checkpoint(&state);
+					}
+
+					// Re-tile the layout once for a storm of windows being mapped and unmapped, once it has
+					// died down.
+					if wm.handler().pending_apply.poll(Instant::now()) {
 						state.apply_changes_async(resize_window).await?;
-					},
 
-					// If a client requests to map its window, map it.
-					Event::MapRequest(MapRequest { window, .. }) => {
-						state.map_window(&window);
+						recorder.note(replay::Input::Apply, Instant::now());
+						recorder.checkpoint(&state);
+					}
+
+					// Reconfigure the next chunk of windows of a time-sliced re-tile. If the layout has changed
+					// since they were queued, the changes are computed first, so that no window is sent
+					// stale geometry; a pending re-tile is made along with them.
+					if wm.handler().dispatch_queue.is_pending() {
+						let changed = state
+							.tiling_layout()
+							.is_some_and(|layout| layout.stats().pending_changes != 0);
+						if changed {
+							wm.handler().dispatch_queue.invalidate();
+							wm.handler().pending_apply.flush();
+
+							state.apply_changes_async(resize_window).await?;
+							wm.handler().dispatch_queue.revalidate();
+						}
 
-						try_join!(
-							async {
-								wm.conn.map_window(window).await?.check().await?;
+						let chunk = wm.handler().dispatch_queue.next_chunk(state.focused.as_ref());
+						wm.reconfigure_chunk(&mut state, chunk).await?;
 
-								Ok(())
-							},
-							state.apply_changes_async(resize_window)
-						)?;
-					},
-					// If a client's window is unmapped, update state accordingly.
-					Event::UnmapNotify(UnmapNotify { window, .. }) => {
-						state.unmap_window(&window);
+						recorder.note(replay::Input::Apply, Instant::now());
+						recorder.checkpoint(&state);
+					}
 
+					// Repair any sizes which have drifted in the layout once it has been left alone for a
+					// while. A healthy layout is left as it is.
+					if compaction.poll(Instant::now()) && !state.compact_layout().is_empty() {
 						state.apply_changes_async(resize_window).await?;
-					},
 
-					// If a client requests to configure its window, honor it. For a tiling layout, this
-					// should modify the configure request to place it in the tiling layout.
-					Event::ConfigureRequest(request) => {
-						wm.honor_configure_window(&request).await?;
-					},
+						recorder.note(replay::Input::Compact, Instant::now());
+						recorder.checkpoint(&state);
+					}
 
-					// If a client requests to raise or lower its window, honor it. For a tiling layout,
-					// this should be rejected for tiled windows, as they should always be at the bottom
-					// of the stack.
-					Event::CirculateRequest(request) => {
-						wm.circulate_window(&state, request.window, request.place).await?;
-					},
+					// Move or resize the dragged window to follow the pointer.
+					let motion = {
+						let mut handler = wm.handler();
 
-					// Focus a window when the cursor enters it.
-					// TODO: move floating windows above (avoid flickering bug).
-					// TODO: implement focus behavior setting
-					Event::EnterNotify(EnterNotify { event, .. }) => {
-						const CURRENT_TIME: u32 = 0;
+						handler.drag_motion.poll(Instant::now()).zip(handler.drag.take())
+					};
+					if let Some((pointer, mut drag)) = motion {
+						let requests = wm.handler().drag_step(&mut state, *drag.window(), drag.motion(pointer));
+						wm.dispatch_all(requests).await?;
+						state.apply_changes_async(resize_window).await?;
 
-						wm.conn
-							.set_input_focus(InputFocus::PARENT, event, CURRENT_TIME)
-							.await?
-							.ignore_error();
-					},
+						let input = replay::Input::Other {
+							event: "drag".to_owned(),
+						};
+						recorder.note(input, Instant::now());
+						recorder.checkpoint(&state);
 
-					Event::KeyPress(KeyPress {
-						event, state, detail, ..
-					}) => {
-						event!(
-							Level::INFO,
-							"Key pressed, {event}, {state:?}, {detail}",
-							event = event,
-							state = state,
-							detail = detail,
-						);
+						// Highlight where a dragged tiled window would be dropped.
+						if state.tiling_layout().is_some_and(|layout| drag.hover(layout, pointer)) {
+							wm.update_drop_overlay(&state, Some(&drag)).await?;
+						}
+						wm.handler().drag = Some(drag);
+					}
+
+					// Remove the border of a lone tiled window, giving it back once another window is tiled
+					// alongside it.
+					let smart_borders = wm.handler().appearance.smart_borders;
+					let lone = smart_borders
+						.then(|| state.tiling_layout().and_then(|layout| layout.single_window().copied()))
+						.flatten();
+					let requests = wm.handler().update_borders(lone);
+					wm.dispatch_all(requests).await?;
+
+					// Show where the next window would be tiled while exactly the insertion preview's
+					// modifiers are held, following focus and the layout as they change.
+					let preview_held = insertion_preview.as_ref().is_some_and(|preview| {
+						let handler = wm.handler();
+						let modifiers = &handler.keys.modifiers;
+
+						modifiers.normalize(handler.held_modifiers.mask()) == modifiers.mask(&preview.modifiers)
+					});
+					wm.update_insertion_preview(&state, preview_held).await?;
+
+					// Warp the pointer to the focused window once it has been focused or moved, now that its
+					// geometry is final. Focus moving between candidates while switching windows doesn't
+					// count until the session ends, and the pointer waits for a pending re-tile.
+					if !state.is_switching() && !wm.handler().pending_apply.is_pending() {
+						let focused_moved = mem::take(&mut wm.handler().focused_moved);
+						let trigger = match (state.focused != warped_focus, focused_moved) {
+							(true, _) => Some(Trigger::FocusChange(state.focus_cause)),
+							(false, true) => Some(Trigger::WindowMove),
+							(false, false) => None,
+						};
+						warped_focus = state.focused;
+
+						if let Some(trigger) = trigger {
+							let dragging = wm.handler().drag.is_some();
+							wm.warp_pointer(&state, pointer_warp, trigger, dragging).await?;
+						}
+					}
 
-						if state == x11::KeyButMask::MOD4 | x11::KeyButMask::SHIFT {
-							match detail {
-								ENTER => {
-									if let Err(error) = crate::launch_terminal() {
-										event!(Level::WARN, "Failed to launch terminal: {error}");
-									}
+					// Ping the focused window once it has been focused, and every window once a round of pings
+					// is due, then mark the clients of the windows whose pings have timed out as
+					// unresponsive.
+					if state.focused != pinged_focus {
+						pinged_focus = state.focused;
+
+						if let Some(window) = pinged_focus {
+							wm.ping(window).await?;
+						}
+					}
+					if wm.handler().pings.round_due(Instant::now()) {
+						let mut windows: Vec<_> = state.windows.keys().copied().collect();
+						windows.sort_unstable();
+
+						for window in windows {
+							wm.ping(window).await?;
+						}
+					}
+					let requests = wm.handler().sweep_pings(Instant::now());
+					wm.dispatch_all(requests).await?;
+					// Give every window the border color for its state, now that this iteration's changes to
+					// focus, urgency and responsiveness have been made.
+					let requests = wm.handler().paint_borders(&state);
+					wm.dispatch_all(requests).await?;
+
+					// Publish the status for external bars and the window list for external window
+					// switchers once all of the previous iteration's changes have been made, so that they
+					// don't flicker.
+					if !wm.dispatcher.is_observe_only() {
+						let (current, list) = {
+							let handler = wm.handler();
+
+							(
+								Status::of(&state, |window| handler.is_urgent(window)),
+								handler.window_list(&state),
+							)
+						};
+						status.update(current);
+
+						wm.publish_status(&mut status).await?;
+						wm.publish_window_list(&mut window_list, list).await?;
+					}
+
+					// Flush the requests of the previous iteration, if there are any to flush.
+					wm.dispatch_ordered().await?;
+					wm.conn.flush().await?;
+					wm.settle_metrics(&state).await?;
+
+					let autosave_due = sleep_until(autosave.as_ref().and_then(Autosave::deadline));
+					let screen_change_due = sleep_until(screen_changes.deadline());
+					let drag_motion_due = sleep_until(wm.handler().drag_motion.deadline());
+					let pending_apply_due = sleep_until(wm.handler().pending_apply.deadline());
+					let admission_due = sleep_until(state.admission_deadline());
+					let compaction_due = sleep_until(compaction.deadline());
+					let chunk_due = sleep_until(wm.handler().dispatch_queue.is_pending().then(Instant::now));
+					let report_due = sleep_until(wm.handler().metrics.report_deadline());
+					let ping_due = sleep_until(wm.handler().pings.deadline());
+
+					// Wait for the next event, writing snapshots of the layout while waiting.
+					let event = tokio::select! {
+						event = wm.conn.wait_for_event() => event?,
+
+						() = autosave_due => {
+							if let (Some(autosave), Some(snapshot)) = (&mut autosave, state.workspace_snapshot()) {
+								if autosave.tick(Instant::now(), &snapshot) {
+									placements.write(autosave.dir());
+								}
+							}
+
+							continue;
+						},
+
+						// The latest screen change and pointer motion, a pending re-tile, admissions, and
+						// compaction are acted on at the start of the next iteration.
+						() = screen_change_due => continue,
+						() = drag_motion_due => continue,
+						() = pending_apply_due => continue,
+						() = admission_due => continue,
+						() = compaction_due => continue,
+						// The next chunk of a time-sliced re-tile is reconfigured once any events which are
+						// already waiting have been handled.
+						() = chunk_due => continue,
+						// Timed out pings and rounds of pings are acted on at the start of the next iteration.
+						() = ping_due => continue,
+						() = report_due => {
+							if let Some(report) = wm.handler().metrics.poll_report(Instant::now()) {
+								event!(Level::DEBUG, "Latencies: {report}");
+							}
+
+							continue;
+						},
+
+						_ = hangup.recv() => {
+							event!(Level::INFO, "Received SIGHUP; reloading the configuration");
+
+							reload = true;
+							continue;
+						},
+
+						_ = terminate.recv() => {
+							event!(Level::INFO, "Received SIGTERM; exiting");
+
+							if let (Some(autosave), Some(snapshot)) = (&mut autosave, state.workspace_snapshot()) {
+								autosave.shutdown(Instant::now(), &snapshot);
+								placements.write(autosave.dir());
+							}
+							// Don't leave windows dimmed, borderless, in the theme's border colors, iconified out
+							// of reach, or bypassing the compositor after AquariWM exits.
+							wm.undim_all().await?;
+							wm.destroy_overlay(|wm| &mut wm.preview_overlay).await?;
+							wm.destroy_overlay(|wm| &mut wm.drop_overlay).await?;
+							let requests = wm.handler().update_borders(None);
+							wm.dispatch_all(requests).await?;
+							let requests = wm.handler().reset_borders();
+							wm.dispatch_all(requests).await?;
+							let requests = wm.handler().leave_game_mode();
+							wm.dispatch_all(requests).await?;
+							let requests = wm.handler().deiconify_all(&state);
+							wm.dispatch_all(requests).await?;
+							wm.conn.flush().await?;
+
+							return Ok(());
+						},
+					};
+					event!(Level::TRACE, "{:?}", event);
+					wm.record_timestamp(&event);
+					// When the event was decoded, which is when the operation it starts begins.
+					let received = Instant::now();
+					// Events which are handled through the layout model are recorded as what was read
+					// to handle them; any other event is only recorded by its name.
+					if recorder.is_recording() {
+						recorder.note(replay::Input::other(&event), received);
+					}
+
+					// Key bindings which couldn't be grabbed are matched against raw key events, which are
+					// handled as though they were grabbed. Those which were grabbed are only matched
+					// against their grabbed key events, so that they aren't performed twice.
+					let event = match event {
+						Event::XinputRawKeyPress(RawKeyPress { detail, time, .. }) => {
+							let Ok(detail) = u8::try_from(detail) else {
+								continue;
+							};
+							let (mask, fallback) = {
+								let mut handler = wm.handler();
+								let Wm {
+									keys, held_modifiers, ..
+								} = &mut *handler;
+								held_modifiers.press(&keys.grabs.modifier_map, detail);
+
+								let mask = keys.modifiers.normalize(held_modifiers.mask());
+								(mask, keys.grabs.fallback.contains(&(detail, mask)))
+							};
+							// The keyboard is grabbed while switching windows, so every key press is
+							// reported anyway.
+							if state.is_switching() || !fallback {
+								continue;
+							}
+
+							Event::KeyPress(KeyPress {
+								detail,
+								time,
+								root,
+								event: root,
+								state: mask.into(),
+								same_screen: true,
+								..KeyPress::default()
+							})
+						},
+						Event::XinputRawKeyRelease(RawKeyRelease { detail, .. }) => {
+							if let Ok(detail) = u8::try_from(detail) {
+								wm.handler().held_modifiers.release(detail);
+							}
+
+							continue;
+						},
+
+						event => event,
+					};
+
+					// Override-redirect windows, like menus and tooltips, are never managed: their events
+					// only keep track of them, so that they are never configured, restacked, or focused.
+					if let Some(WindowRole::Unmanaged(window)) = wm.resolve_subject(&state, &event).await? {
+						match event {
+							Event::DestroyNotify(_) => {
+								wm.handler().override_redirect.remove(&window);
+							},
+							Event::EnterNotify(EnterNotify { root_x, root_y, .. }) => {
+								state.pointer_moved(root_x as i32, root_y as i32);
+							},
+
+							_ => (),
+						}
+
+						continue;
+					}
+
+					// Windows are only followed as they are mapped while observing another window manager.
+					let observed_map = matches!(event, Event::MapNotify(_));
+					// Managed windows' snapshots are kept up to date before their property changes are
+					// handled.
+					if let Event::PropertyNotify(notify) = &event {
+						wm.property_changed(notify).await?;
+					}
+					// Drags and window switching sessions which the event starts or ends grab or release
+					// the pointer and keyboard.
+					let (dragging, switching) = (wm.handler().drag.is_some(), state.is_switching());
+
+					match event {
+						// Track the state of newly created windows.
+						Event::CreateNotify(notify) => {
+							let input = replay::Input::Create {
+								window: notify.window,
+								override_redirect: notify.override_redirect,
+							};
+							recorder.note(input, received);
+
+							let requests = wm.handler().on_create_notify(&mut state, &notify);
+							wm.dispatch_all(requests).await?;
+
+							state.apply_changes_async(resize_window).await?;
+						},
+						// Stop tracking the state of destroyed windows.
+						Event::DestroyNotify(notify) => {
+							let window = notify.window;
+							let locked = wm.handler().focus_lock.holder().copied();
+							let mapped = wm.handler().mapped_client(&state, window);
+
+							recorder.note(replay::Input::Destroy { window }, received);
+							let requests = wm.handler().on_destroy_notify(&mut state, &mut placements, &notify);
+							wm.dispatch_all(requests).await?;
+							if let Some(window) = mapped {
+								wm.handler().metrics.begin(Some(window), Operation::Unmap, received);
+							}
+
+							wm.handler().end_drag_of(|drag| drag.raw() == window);
+
+							state.apply_changes_async(resize_window).await?;
+							// A fullscreen window which dies abruptly releases the focus lock.
+							let requests = wm.handler().focus_lock_released(&state, locked);
+							wm.dispatch_all(requests).await?;
+						},
+
+						// If a client requests to map its window, map it. When observing another window
+						// manager, which maps windows itself, follow the windows it maps instead.
+						Event::MapRequest(MapRequest { window, .. })
+						| Event::MapNotify(MapNotify {
+							window,
+							override_redirect: false,
+							..
+						}) if !observed_map || wm.dispatcher.is_observe_only() => {
+							let Some(window) = wm.handler().resolve_top_level(&state, window) else {
+								continue;
+							};
+
+							// Everything the window is managed with is prefetched in a single round trip.
+							// Property changes are selected first, so that none are missed before the
+							// snapshot is kept.
+							wm.select_window_events(window).await?;
+							let prefetched = wm.prefetch(window).await?;
+
+							let requests = wm.handler().on_map(
+								&mut state,
+								&mut placements,
+								&mut recorder,
+								window,
+								prefetched,
+								received,
+							);
+							wm.dispatch_all(requests).await?;
+
+							// Re-tiling the layout for a newly managed window waits for the windows mapped
+							// along with it.
+							if !wm.handler().pending_apply.is_pending() {
+								state.apply_changes_async(resize_window).await?;
+							}
+						},
+						// Windows' urgency, fullscreen state, titles and size hints are followed as their
+						// properties change.
+						Event::PropertyNotify(notify) => {
+							let requests =
+								wm.handler()
+									.on_property_notify(&mut state, &mut recorder, &notify, received);
+							wm.dispatch_all(requests).await?;
+
+							if !wm.handler().pending_apply.is_pending() {
+								state.apply_changes_async(resize_window).await?;
+							}
+						},
+						// Windows reparented away from the root window are no longer top-level windows, so
+						// they are removed from the layout; windows reparented back to the root window are
+						// managed again, and mapped in response to the map request which follows.
+						Event::ReparentNotify(notify) => {
+							let window = notify.window;
+
+							let input = replay::Input::Reparent {
+								window,
+								parent: notify.parent,
+							};
+							recorder.note(input, received);
+							let requests = wm.handler().on_reparent_notify(&mut state, &notify);
+							wm.dispatch_all(requests).await?;
+
+							wm.handler()
+								.end_drag_of(|drag| drag.raw() == window && !state.windows.contains_key(drag));
+
+							state.apply_changes_async(resize_window).await?;
+						},
+						// If a client's window is unmapped, update state accordingly. Windows unmapped by
+						// AquariWM to hide them remain in the layout.
+						Event::UnmapNotify(notify) => {
+							let locked = wm.handler().focus_lock.holder().copied();
+							let mapped = wm.handler().mapped_client(&state, notify.window);
+
+							let role = wm.handler().resolve_window(&state, notify.window);
+							if let WindowRole::Client(window) = role {
+								let input = replay::Input::Unmap {
+									window: window.raw(),
+									expected: wm.handler().expected_unmaps.contains_key(&window),
+								};
+								recorder.note(input, received);
+							}
+							let requests = wm.handler().on_unmap_notify(&mut state, &mut placements, &notify);
+							wm.dispatch_all(requests).await?;
+							// Windows unmapped by AquariWM itself stay mapped as far as the layout is
+							// concerned, so they aren't measured.
+							let unmapped =
+								mapped.filter(|window| wm.handler().mapped_client(&state, window.raw()).is_none());
+							if let Some(window) = unmapped {
+								wm.handler().metrics.begin(Some(window), Operation::Unmap, received);
+							}
+
+							// Unmaps join the re-tile of the windows being mapped and unmapped around them.
+							wm.handler().pending_apply.defer(Instant::now());
+							let requests = wm.handler().focus_lock_released(&state, locked);
+							wm.dispatch_all(requests).await?;
+						},
+
+						// If the screen's outputs have changed, adapt the layout to the new outputs once the
+						// change is due.
+						Event::RandrScreenChangeNotify(ScreenChangeNotify {
+							width, height, mwidth, ..
+						}) => screen_changes.push(Instant::now(), (width, height, mwidth)),
+
+						// If a client requests to configure its window, honor it. Tiled windows are kept in
+						// their tiles, without being caught in a loop with clients which keep asking for
+						// another geometry. Floating windows are kept on their output.
+						Event::ConfigureRequest(request) => {
+							let role = wm.handler().resolve_window(&state, request.window);
+							let floating = match role {
+								WindowRole::Client(window) if state.windows[&window].mode == layout::Mode::Floating => {
+									Some(window)
 								},
 
-								b'I' => {
-									wm.conn.destroy_window(event).await?.ignore_error();
+								_ => None,
+							};
+							// Tiled windows are configured to their tiles, so a pending re-tile is applied
+							// first.
+							let tiled = matches!(role, WindowRole::Client(_)) && floating.is_none();
+							if tiled && wm.handler().pending_apply.flush() {
+								state.apply_changes_async(resize_window).await?;
+							}
+							let hints = match floating {
+								Some(window) => match wm.prepare_configure_floating(window).await? {
+									Some(hints) => hints,
+									// The window no longer exists.
+									None => continue,
 								},
+								None => SizeHints::default(),
+							};
+
+							let requests = wm
+								.handler()
+								.on_configure_request(&state, &request, &hints, Instant::now());
+							wm.dispatch_all(requests).await?;
+						},
+
+						// If a client requests to raise or lower its window, honor it. For a tiling layout,
+						// this should be rejected for tiled windows, as they should always be at the bottom
+						// of the stack.
+						Event::CirculateRequest(request) => {
+							let role = wm.handler().resolve_window(&state, request.window);
+
+							match role {
+								WindowRole::Client(window) => {
+									wm.circulate_window(&state, window, request.place).await?
+								},
+								role => event!(Level::DEBUG, "Ignoring circulate request of {role}"),
+							}
+						},
+
+						// Focus a window when the cursor enters it, unless focus is locked to a fullscreen
+						// window.
+						// TODO: move floating windows above (avoid flickering bug).
+						// TODO: implement focus behavior setting
+						Event::EnterNotify(EnterNotify {
+							event, root_x, root_y, ..
+						}) => {
+							state.pointer_moved(root_x as i32, root_y as i32);
+
+							let requests = {
+								let mut handler = wm.handler();
+
+								match handler.resolve_window(&state, event) {
+									WindowRole::Client(window) => {
+										match handler.may_focus(&state, Some(window), FocusRequest::Pointer) {
+											true => handler.focus_window(&mut state, window, FocusCause::Pointer),
+											false => Vec::new(),
+										}
+									},
+									WindowRole::Root => match handler.may_focus(&state, None, FocusRequest::Pointer) {
+										true => handler.give_focus(None),
+										false => Vec::new(),
+									},
+
+									WindowRole::PassedThrough(_)
+									| WindowRole::Utility(_)
+									| WindowRole::Unmanaged(_)
+									| WindowRole::Unknown(_) => Vec::new(),
+								}
+							};
+							wm.dispatch_all(requests).await?;
+						},
+
+						// Clients answer pings, and clients and pagers ask for windows to be made sticky,
+						// moved to another desktop, activated, or moved or resized interactively.
+						Event::ClientMessage(message) => {
+							let requests = wm.handler().on_client_message(&mut state, &message);
+							wm.dispatch_all(requests).await?;
+
+							state.apply_changes_async(resize_window).await?;
+						},
+
+						Event::MotionNotify(MotionNotify { root_x, root_y, .. }) => {
+							wm.handler()
+								.on_pointer_motion((root_x as i32, root_y as i32), Instant::now());
+						},
+						// Releasing the pointer ends the drag.
+						Event::ButtonRelease(ButtonRelease { root_x, root_y, .. }) => {
+							let requests = wm
+								.handler()
+								.on_button_release(&mut state, (root_x as i32, root_y as i32));
+							wm.dispatch_all(requests).await?;
+
+							state.apply_changes_async(resize_window).await?;
+						},
+
+						// If the keyboard or modifier mapping has changed, the key bindings may now correspond to
+						// different keycodes or modifiers, so grab them again.
+						//
+						// XKB layout changes (e.g. with `setxkbmap`) also generate a core `MappingNotify` event.
+						Event::MappingNotify(MappingNotify { request, .. })
+							if request == x11::Mapping::KEYBOARD || request == x11::Mapping::MODIFIER =>
+						{
+							wm.conn
+								.ungrab_key(x11::Grab::ANY, root, x11::ModMask::ANY)
+								.await?
+								.ignore_error();
+
+							let keys = wm.grab_key_bindings(&key_table, raw_keys).await?;
+							wm.handler().keys = keys;
+						},
+
+						Event::KeyPress(press) => {
+							let (requests, command) =
+								wm.handler().on_key_press(&mut state, &mut recorder, &press, received);
+							wm.dispatch_all(requests).await?;
+
+							match command {
+								Some(SessionCommand::SetWallpaper(path)) => {
+									if let Some(image) = load_wallpaper(Some(&path)) {
+										wallpaper_image = Some(image);
+
+										wm.draw_wallpaper(
+											&mut background,
+											screen_size,
+											&state.outputs,
+											wallpaper.color,
+											wallpaper_image.as_ref(),
+										)
+										.await?;
+									}
+								},
+								// The configuration is reloaded at the start of the next iteration.
+								Some(SessionCommand::ReloadConfig) => reload = true,
 
-								_ => (),
+								Some(command) => wm.run_command(&state, autosave.as_ref(), command),
+								None => (),
 							}
-						}
-					},
 
-					_ => (),
+							state.apply_changes_async(resize_window).await?;
+						},
+						// Key releases may have been missed while the keyboard was grabbed by another client,
+						// so forget the modifiers which are no longer held rather than leave them stuck.
+						Event::FocusOut(FocusOut { mode, .. })
+							if mode == x11::NotifyMode::GRAB && wm.handler().held_modifiers.mask() != 0 =>
+						{
+							let keys = wm.conn.query_keymap().await?.reply().await?.keys;
+							wm.handler().on_focus_out(&keys);
+						},
+						Event::KeyRelease(KeyRelease { detail, .. }) => {
+							let requests = wm.handler().on_key_release(&mut state, detail);
+							wm.dispatch_all(requests).await?;
+
+							state.apply_changes_async(resize_window).await?;
+						},
+
+						_ => (),
+					}
+
+					if wm.update_grabs(&mut state, dragging, switching).await? {
+						state.apply_changes_async(resize_window).await?;
+					}
+				}
+			}
+			.await;
+
+			if let Err(error) = &result {
+				if error.is_connection_loss() {
+					handle_connection_loss(error, autosave.as_mut(), &state, &placements);
 				}
 			}
+
+			result
 		}
 	}
 }
 
+/// Loads the [wallpaper] image at the given `path`, if any, warning if it can't be loaded.
+///
+/// [wallpaper]: crate::wallpaper
+fn load_wallpaper(path: Option<&Path>) -> Option<Image> {
+	match Image::load(path?) {
+		Ok(image) => Some(image),
+
+		Err(error) => {
+			event!(Level::WARN, "Failed to load the wallpaper: {error}");
+			None
+		},
+	}
+}
+
+/// Returns the delay before the given attempt to reconnect to the X server, starting from 0.
+fn reconnect_delay(attempt: u32) -> Duration {
+	RECONNECT_DELAY
+		.saturating_mul(2u32.saturating_pow(attempt))
+		.min(MAX_RECONNECT_DELAY)
+}
+
+/// Handles the connection to the X server having been lost with the given `error`.
+///
+/// Nothing can be cleaned up on the X server, as the connection is gone, so this only writes a
+/// snapshot of the layout, so that it can be restored after reconnecting or by the next instance
+/// of AquariWM.
+fn handle_connection_loss(
+	error: &Error,
+	autosave: Option<&mut Autosave>,
+	state: &state::AquariWm<ClientWindow>,
+	placements: &PlacementMemory,
+) {
+	event!(Level::ERROR, "Lost the connection to the X server: {error}");
+
+	match (autosave, state.workspace_snapshot()) {
+		(Some(autosave), Some(snapshot)) => {
+			autosave.write_now(Instant::now(), &snapshot);
+			placements.write(autosave.dir());
+		},
+
+		(None, _) => event!(Level::WARN, "XDG_RUNTIME_DIR is not set; the layout could not be saved"),
+		(_, None) => (),
+	}
+}
+
+/// Waits until the given `deadline`, or forever if there is none.
+async fn sleep_until(deadline: Option<Instant>) {
+	match deadline {
+		Some(deadline) => time::sleep_until(deadline.into()).await,
+		None => future::pending().await,
+	}
+}
+
 impl X11 {
-	/// Resizes the given `window` to the given dimensions.
+	/// Resizes the given `window` to the given dimensions, and shows or hides it according to its
+	/// `visibility`.
+	///
+	/// [Hidden] windows are unmapped rather than reconfigured; they are reconfigured to their
+	/// up-to-date dimensions and mapped again when they become visible.
 	///
 	/// This is required because if the `resize_window` closure were to use an `async`
 	/// block, it would have to be `async move` in order to move `width` and `height`, which
@@ -298,56 +1618,888 @@ impl X11 {
 	///
 	/// The `resize_window` closure is required because
 	/// [`state::AquariWm::apply_changes_async`] does not expect a [`Self`] parameter.
-	async fn reconfigure_window(&self, window: x11::Window, x: i32, y: i32, width: u32, height: u32) -> Result<()> {
-		Ok(self
-			.conn
-			.configure_window(
-				window,
-				&x11::ConfigureWindowAux::new().x(x).y(y).width(width).height(height),
-			)
-			.await?
-			.check()
-			.await?)
-	}
-
-	/// Circulates the given [floating] `window` in the given `direction`.
 	///
-	/// # Errors
-	/// If the given `window` is not [floating], a [`NonFloatingWindow` error] will be returned.
-	/// This is because tiled windows are always meant to be at the bottom of the window stack, and
-	/// tiled windows are non-overlapping: their order in the window stack does not matter.
+	/// If the `window` no longer exists, it is [skipped] so that it can be removed from the layout.
 	///
-	/// # Panics
-	/// If the given `window` is not tracked by the given window manager `state`, this function will
-	/// panic. This shouldn't happen if the window comes from the X server.
+	/// [Hidden]: Visibility::Hidden
+	/// [skipped]: Reconfigured::SkipWindow
+	async fn reconfigure_window(
+		&self,
+		window: ClientWindow,
+		x: i32,
+		y: i32,
+		width: u32,
+		height: u32,
+		visibility: Visibility,
+	) -> Result<Reconfigured> {
+		match self
+			.try_reconfigure_window(window, x, y, width, height, visibility)
+			.await
+		{
+			Err(error) if error.is_bad_window() => {
+				event!(
+					Level::DEBUG,
+					"Window {window} no longer exists; removing it from the layout"
+				);
+
+				Ok(Reconfigured::SkipWindow)
+			},
+
+			result => result.map(|()| Reconfigured::Applied),
+		}
+	}
+
+	/// [Reconfigures] the given `window`, or queues it to be reconfigured in a later chunk if
+	/// re-tiles are [time-sliced].
 	///
-	/// [floating]: layout::Mode::Floating
-	/// [`NonFloatingWindow` error]: CirculateWindowError::NonFloatingWindow
-	pub async fn circulate_window<Direction>(
+	/// [Reconfigures]: Self::reconfigure_window
+	/// [time-sliced]: crate::dispatch_queue
+	async fn reconfigure_or_queue_window(
 		&self,
-		state: &state::AquariWm<x11::Window>,
-		window: x11::Window,
-		direction: Direction,
-	) -> Result<()>
-	where
-		Direction: TryInto<CirculateDirection>,
-		Direction::Error: Into<Error>,
-	{
-		match state.windows.get(&window) {
-			// If it is a floating window...
-			Some(state::WindowState {
-				mode: layout::Mode::Floating,
-				..
-			}) => {
-				let direction: CirculateDirection = direction.try_into().map_err(|error| error.into())?;
+		window: ClientWindow,
+		x: i32,
+		y: i32,
+		width: u32,
+		height: u32,
+		visibility: Visibility,
+	) -> Result<Reconfigured> {
+		{
+			let mut handler = self.handler();
+
+			if handler.dispatch_queue.is_sliced() {
+				handler.dispatch_queue.push(Reconfigure {
+					window,
+					x,
+					y,
+					width,
+					height,
+					visibility,
+				});
+
+				return Ok(Reconfigured::Applied);
+			}
+		}
 
-				self.conn
-					.circulate_window(direction.into(), window)
-					.await
-					.map_err(Error::from)?
-					.check()
-					.await
-					.map_err(Error::from)?;
+		self.reconfigure_window(window, x, y, width, height, visibility).await
+	}
+
+	/// [Reconfigures] each window in the given `chunk` of a [time-sliced] re-tile which is still
+	/// tiled, removing windows which no longer exist from the layout.
+	///
+	/// [Reconfigures]: Self::reconfigure_window
+	/// [time-sliced]: crate::dispatch_queue
+	async fn reconfigure_chunk(
+		&self,
+		state: &mut state::AquariWm<ClientWindow>,
+		chunk: Vec<Reconfigure<ClientWindow>>,
+	) -> Result<()> {
+		for reconfigure in chunk {
+			let Reconfigure {
+				window,
+				x,
+				y,
+				width,
+				height,
+				visibility,
+			} = reconfigure;
+
+			// Windows may have been closed or made floating since they were queued.
+			let tiled = state
+				.tiling_layout()
+				.is_some_and(|layout| layout.contains_window(&window));
+			if !tiled {
+				continue;
+			}
+
+			let reconfigured = self.reconfigure_window(window, x, y, width, height, visibility).await?;
+			if reconfigured == Reconfigured::SkipWindow {
+				state.remove_window(&window);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Reconfigures or hides the given `window`, as in [`reconfigure_window`], returning any error.
+	///
+	/// [`reconfigure_window`]: Self::reconfigure_window
+	async fn try_reconfigure_window(
+		&self,
+		window: ClientWindow,
+		x: i32,
+		y: i32,
+		width: u32,
+		height: u32,
+		visibility: Visibility,
+	) -> Result<()> {
+		match visibility {
+			Visibility::Hidden => {
+				let requests = self.handler().hide_window(window);
+
+				for request in requests {
+					self.dispatch(request).await?.check().await?;
+				}
+			},
+
+			Visibility::Visible => {
+				self.handler().configure_guard.assert(window, (x, y, width, height));
+				self.dispatch(Request::Configure(
+					window,
+					x11::ConfigureWindowAux::new().x(x).y(y).width(width).height(height),
+				))
+				.await?
+				.check()
+				.await?;
+
+				let was_hidden = self.handler().hidden.remove(&window);
+
+				// The window is only mapped once every other window has been reconfigured, so that
+				// it never appears over a window that has yet to make room for it.
+				if was_hidden {
+					self.handler().ordered.push(Phase::Map, Request::Map(window));
+				}
+			},
+		}
+
+		Ok(())
+	}
+
+	/// Locks what AquariWM knows about the windows it manages.
+	fn handler(&self) -> MutexGuard<'_, Wm> {
+		self.handler.lock().unwrap()
+	}
+
+	/// Returns the screen of the root window AquariWM is managing.
+	fn screen(&self) -> &x11::Screen {
+		self.conn
+			.setup()
+			.roots
+			.iter()
+			.find(|screen| screen.root == self.root)
+			.expect("the root window is one of the screens' roots")
+	}
+
+	/// Fetches a [snapshot] of the given `window` as it is managed, [verifying] the process ID it
+	/// advertises.
+	///
+	/// Features which act on process IDs are disabled for windows whose process IDs can't be
+	/// verified.
+	///
+	/// [snapshot]: snapshot
+	/// [verifying]: pid
+	async fn fetch_snapshot(&self, window: ClientWindow) -> Result<ClientSnapshot> {
+		let mut snapshot = ClientSnapshot::fetch(&self.conn, &self.atoms, self.screen(), window.raw()).await?;
+
+		match snapshot.verify_pid(&pid::Host::local(), pid::uptime(), pid::start_time) {
+			Ok(_) | Err(pid::Unverified::NoPid) => (),
+
+			Err(reason) => event!(
+				Level::DEBUG,
+				"Not acting on the process ID of window {window}, as {reason}"
+			),
+		}
+
+		Ok(snapshot)
+	}
+
+	/// Fetches what is needed to handle the given `window` being mapped: a [snapshot] of it, and
+	/// the pointer's position if the [output assignment] follows it.
+	///
+	/// [snapshot]: Self::fetch_snapshot
+	/// [output assignment]: Wm::output_assignment
+	async fn prefetch(&self, window: ClientWindow) -> Result<Prefetched> {
+		let snapshot = self.fetch_snapshot(window).await?;
+
+		let follow_pointer = self.handler().output_assignment == OutputAssignment::FollowPointer;
+		let pointer = match follow_pointer {
+			true => {
+				let pointer = self.conn.query_pointer(self.root).await?.reply().await?;
+
+				Some((pointer.root_x as i32, pointer.root_y as i32))
+			},
+
+			false => None,
+		};
+
+		Ok(Prefetched { snapshot, pointer })
+	}
+
+	/// Updates the kept [snapshot] of the window whose property changed in the given `notify`
+	/// event, if the window is managed and the property is one which snapshots include.
+	///
+	/// Only the changed property is fetched again; deleted properties aren't fetched at all.
+	///
+	/// [snapshot]: snapshot
+	async fn property_changed(&self, notify: &PropertyNotify) -> Result<()> {
+		let window = ClientWindow::new(notify.window);
+		let Some(property) = Property::from_atom(&self.atoms, notify.atom) else {
+			return Ok(());
+		};
+		let Some(mut snapshot) = self.handler().snapshots.remove(&window) else {
+			return Ok(());
+		};
+
+		match notify.state {
+			x11::Property::DELETE => snapshot.clear(window.raw(), property),
+			_ => {
+				snapshot
+					.refresh(&self.conn, &self.atoms, window.raw(), property)
+					.await?
+			},
+		}
+		self.handler().snapshots.insert(window, snapshot);
+
+		Ok(())
+	}
+
+	/// Warps the pointer to the center of the [`focused`] window after the given `trigger`, if the
+	/// [pointer warp] `setting` says so.
+	///
+	/// `grabbed` is whether the pointer is grabbed, e.g. because a window is being dragged.
+	///
+	/// [`focused`]: state::AquariWm::focused
+	/// [pointer warp]: PointerWarp
+	async fn warp_pointer(
+		&self,
+		state: &state::AquariWm<ClientWindow>,
+		setting: PointerWarp,
+		trigger: Trigger,
+		grabbed: bool,
+	) -> Result<()> {
+		// Avoid querying the pointer when it is never warped.
+		if setting == PointerWarp::Never {
+			return Ok(());
+		}
+		let Some(window) = state.focused else {
+			return Ok(());
+		};
+
+		let floating = self
+			.handler()
+			.floating
+			.get(&window)
+			.map(|geometry| (geometry.x, geometry.y, geometry.width, geometry.height));
+		let Some(rect) = state.tile_of(&window).or(floating) else {
+			return Ok(());
+		};
+
+		let pointer = self.conn.query_pointer(self.root).await?.reply().await?;
+		let pointer = (i32::from(pointer.root_x), i32::from(pointer.root_y));
+
+		match pointer_warp::decide(setting, trigger, rect, pointer, grabbed) {
+			pointer_warp::Decision::Warp(x, y) => {
+				event!(Level::DEBUG, "Warping the pointer to window {window} after {trigger:?}");
+
+				self.dispatch(Request::WarpPointer(x as i16, y as i16))
+					.await?
+					.ignore_error();
+			},
+			pointer_warp::Decision::Skip(reason) => {
+				event!(Level::TRACE, "Not warping the pointer to window {window}: {reason}");
+			},
+		}
+
+		Ok(())
+	}
+
+	/// Sends the requests [held] until the end of the event loop iteration, in [phase] order, then
+	/// gives focus to the window it was [held for] if it has been shown.
+	///
+	/// [held]: Wm::ordered
+	/// [phase]: Phase
+	/// [held for]: Wm::held_focus
+	async fn dispatch_ordered(&self) -> Result<()> {
+		let requests = self.handler().ordered.take();
+
+		for request in requests {
+			self.dispatch(request).await?.ignore_error();
+		}
+
+		let requests = self.handler().release_held_focus();
+		self.dispatch_all(requests).await?;
+
+		Ok(())
+	}
+
+	/// [Resolves] the window the given `event` is about, if it is about one.
+	///
+	/// Whether the window is override-redirect is [recorded] first if the event says so. Windows
+	/// which aren't otherwise known and haven't been classified yet, like those which are stale or
+	/// were created before AquariWM started, are queried once.
+	///
+	/// [Resolves]: Wm::resolve_window
+	/// [recorded]: Wm::classify
+	async fn resolve_subject(
+		&self,
+		state: &state::AquariWm<ClientWindow>,
+		event: &Event,
+	) -> Result<Option<WindowRole>> {
+		let (window, override_redirect) = match event {
+			Event::CreateNotify(x11::CreateNotifyEvent {
+				window,
+				override_redirect,
+				..
+			})
+			| Event::MapNotify(MapNotify {
+				window,
+				override_redirect,
+				..
+			})
+			| Event::ReparentNotify(x11::ReparentNotifyEvent {
+				window,
+				override_redirect,
+				..
+			})
+			| Event::ConfigureNotify(x11::ConfigureNotifyEvent {
+				window,
+				override_redirect,
+				..
+			}) => (*window, Some(*override_redirect)),
+
+			Event::DestroyNotify(x11::DestroyNotifyEvent { window, .. })
+			| Event::UnmapNotify(x11::UnmapNotifyEvent { window, .. })
+			| Event::MapRequest(MapRequest { window, .. })
+			| Event::ConfigureRequest(x11::ConfigureRequestEvent { window, .. })
+			| Event::CirculateRequest(x11::CirculateRequestEvent { window, .. })
+			| Event::PropertyNotify(PropertyNotify { window, .. })
+			| Event::EnterNotify(EnterNotify { event: window, .. }) => (*window, None),
+			Event::ClientMessage(message) => (message.window, None),
+
+			_ => return Ok(None),
+		};
+
+		if let Some(override_redirect) = override_redirect {
+			self.handler().classify(window, override_redirect);
+		}
+
+		let role = self.handler().resolve_window(state, window);
+		if !matches!(role, WindowRole::Unknown(_)) || self.handler().is_classified(window) {
+			return Ok(Some(role));
+		}
+
+		let Ok(attributes) = self.conn.get_window_attributes(window).await?.reply().await else {
+			// The window no longer exists.
+			return Ok(Some(role));
+		};
+
+		let mut handler = self.handler();
+		handler.classify(window, attributes.override_redirect);
+
+		Ok(Some(handler.resolve_window(state, window)))
+	}
+
+	/// Records the server timestamp of the given `event` as the [latest], if it has one.
+	///
+	/// [latest]: Wm::timestamp
+	fn record_timestamp(&self, event: &Event) {
+		let time = match event {
+			Event::KeyPress(KeyPress { time, .. }) | Event::KeyRelease(KeyRelease { time, .. }) => *time,
+			Event::ButtonPress(x11::ButtonPressEvent { time, .. })
+			| Event::ButtonRelease(ButtonRelease { time, .. }) => *time,
+			Event::MotionNotify(MotionNotify { time, .. }) => *time,
+			Event::EnterNotify(EnterNotify { time, .. }) | Event::LeaveNotify(x11::LeaveNotifyEvent { time, .. }) => {
+				*time
+			},
+			Event::PropertyNotify(PropertyNotify { time, .. }) => *time,
+
+			_ => return,
+		};
+
+		self.handler().timestamp = time;
+	}
+
+	/// [Pings] the given `window` as of the [latest event's timestamp], if its client supports
+	/// `_NET_WM_PING` and it isn't being waited on already.
+	///
+	/// [Pings]: crate::ping
+	/// [latest event's timestamp]: Wm::timestamp
+	async fn ping(&self, window: ClientWindow) -> Result<()> {
+		let supported = self
+			.handler()
+			.snapshots
+			.get(&window)
+			.is_some_and(|snapshot| snapshot.protocols.contains(&self.atoms._NET_WM_PING));
+		// Pings which are only logged would never be answered.
+		if !supported || self.dispatcher.is_dry_run() {
+			return Ok(());
+		}
+
+		let time = self.handler().timestamp;
+		if self.handler().pings.ping(window, time, Instant::now()) {
+			self.dispatch(Request::Ping(window, time)).await?.ignore_error();
+		}
+
+		Ok(())
+	}
+
+	/// Carries out the given [command] returned by an event handler which can be carried out on its
+	/// own: launching a program, killing a process, toggling the dry run, or saving the ignore list
+	/// to the `autosave` directory.
+	///
+	/// Showing a wallpaper and reloading the configuration are left to the session.
+	///
+	/// [command]: SessionCommand
+	fn run_command(&self, state: &state::AquariWm<ClientWindow>, autosave: Option<&Autosave>, command: SessionCommand) {
+		match command {
+			SessionCommand::Spawn(command) => {
+				let process = match command {
+					Some(command) => match crate::spawn(&command) {
+						Ok(process) => process,
+						Err(error) => {
+							event!(Level::WARN, "Failed to run `{command}`: {error}");
+							return;
+						},
+					},
+					None => match crate::launch_terminal() {
+						Ok((_, process)) => process,
+						Err(error) => {
+							event!(Level::WARN, "Failed to launch terminal: {error}");
+							return;
+						},
+					},
+				};
+				let output = state.active_output.name().map(ToOwned::to_owned);
+
+				self.handler().launches.record(process.id(), output, Instant::now());
+			},
+			// Processes are only killed when requests are really sent.
+			SessionCommand::KillProcess(window, pid) if !self.dispatcher.is_dry_run() => match pid.kill() {
+				Ok(true) => event!(Level::INFO, "Killed the process of window {window}"),
+				Ok(false) => event!(
+					Level::INFO,
+					"Not killing the process of window {window}: its process ID isn't verified"
+				),
+				Err(error) => event!(Level::WARN, "Failed to kill the process of window {window}: {error}"),
+			},
+			SessionCommand::KillProcess(..) => (),
+
+			SessionCommand::ToggleDryRun => {
+				let dry_run = !self.dispatcher.is_dry_run();
+
+				match self.dispatcher.set_dry_run(dry_run) {
+					true if dry_run => event!(Level::INFO, "Started a dry run"),
+					true => event!(Level::INFO, "Ended the dry run"),
+					false => event!(
+						Level::WARN,
+						"The dry run can't be ended while observing another window manager"
+					),
+				}
+			},
+			SessionCommand::SaveIgnoreList => {
+				if let Some(autosave) = autosave {
+					state.ignored.write(autosave.dir());
+				}
+			},
+
+			SessionCommand::SetWallpaper(_) | SessionCommand::ReloadConfig => (),
+		}
+	}
+
+	/// Grabs or releases the pointer and keyboard for a [drag] or [window switching] session which
+	/// was started or ended by handling an event, given whether one was `dragging` and `switching`
+	/// before.
+	///
+	/// Drags which can't be grabbed for are ended straight away, as are those started while
+	/// observing another window manager, which does its own dragging. If Super was already released
+	/// by the time the keyboard was grabbed for window switching, its release was missed, so the
+	/// session is committed to right away; returns whether it was, so that the layout can be
+	/// updated.
+	///
+	/// [drag]: Wm::drag
+	/// [window switching]: switcher
+	async fn update_grabs(
+		&self,
+		state: &mut state::AquariWm<ClientWindow>,
+		dragging: bool,
+		switching: bool,
+	) -> Result<bool> {
+		let dragged = self.handler().drag.as_ref().map(|drag| *drag.window());
+		match (dragging, dragged) {
+			(false, Some(window)) => {
+				let grabbed = match self.dispatcher.is_observe_only() {
+					true => {
+						event!(
+							Level::DEBUG,
+							"Not dragging window {window}: the window manager being observed does its own dragging"
+						);
+
+						false
+					},
+
+					false => match self.grab_pointer().await? {
+						true => true,
+
+						false => {
+							event!(Level::WARN, "Failed to grab the pointer to drag window {window}");
+
+							false
+						},
+					},
+				};
+
+				if !grabbed {
+					self.handler().end_drag_of(|_| true);
+				}
+			},
+			(true, None) => {
+				self.conn.ungrab_pointer(CURRENT_TIME).await?.ignore_error();
+				self.update_drop_overlay(state, None).await?;
+			},
+
+			_ => (),
+		}
+
+		match (switching, state.is_switching()) {
+			(false, true) => {
+				let super_ = self.handler().keys.modifiers.super_;
+				if self.grab_keyboard(super_).await? {
+					return Ok(false);
+				}
+
+				let requests = self.handler().switch_windows(state, switcher::Key::Release);
+				self.dispatch_all(requests).await?;
+				self.conn.ungrab_keyboard(CURRENT_TIME).await?.ignore_error();
+
+				Ok(true)
+			},
+			(true, false) => {
+				self.conn.ungrab_keyboard(CURRENT_TIME).await?.ignore_error();
+
+				Ok(false)
+			},
+
+			_ => Ok(false),
+		}
+	}
+
+	/// Grabs the keyboard on the root window so that every key press and release is reported to
+	/// AquariWM during a [window switching] session.
+	///
+	/// Returns whether the modifier of the given `mask` is still held once the keyboard is grabbed.
+	///
+	/// [window switching]: switcher
+	async fn grab_keyboard(&self, mask: u16) -> Result<bool> {
+		let reply = self
+			.conn
+			.grab_keyboard(
+				false,
+				self.root,
+				CURRENT_TIME,
+				x11::GrabMode::ASYNC,
+				x11::GrabMode::ASYNC,
+			)
+			.await?
+			.reply()
+			.await?;
+
+		if reply.status != x11::GrabStatus::SUCCESS {
+			event!(Level::WARN, "Failed to grab the keyboard for switching windows");
+		}
+
+		let pointer = self.conn.query_pointer(self.root).await?.reply().await?;
+
+		Ok(u16::from(pointer.mask) & mask != 0)
+	}
+
+	/// Draws the [wallpaper] on the root window, for the given `outputs` on a screen of the given
+	/// `size`, unless this is a [dry run].
+	///
+	/// [wallpaper]: crate::wallpaper
+	/// [dry run]: Dispatcher::is_dry_run
+	async fn draw_wallpaper(
+		&self,
+		background: &mut RootBackground,
+		size: (u16, u16),
+		outputs: &[Output],
+		color: Color,
+		image: Option<&Image>,
+	) -> Result<()> {
+		if self.dispatcher.is_dry_run() {
+			event!(Level::DEBUG, "Dry run: not drawing the wallpaper");
+			return Ok(());
+		}
+
+		background
+			.draw(&self.conn, &self.atoms, size, outputs, color, image)
+			.await?;
+
+		Ok(())
+	}
+
+	/// Undims every [dimmed] window.
+	///
+	/// [dimmed]: Self::update_opacity
+	async fn undim_all(&self) -> Result<()> {
+		let mut dimmed: Vec<_> = self.handler().dimmed.drain().collect();
+		// Windows are undimmed in a consistent order, whatever the order of the set.
+		dimmed.sort_unstable();
+
+		for window in dimmed {
+			self.dispatch(Request::SetOpacity(window, None)).await?.ignore_error();
+		}
+
+		Ok(())
+	}
+
+	/// Shows the [insertion preview] overlay over the tile the next window would be given if it is
+	/// `held`, or hides it otherwise.
+	///
+	/// The overlay is only moved when the predicted tile changes, so this is called whenever focus
+	/// or the layout may have changed. Nothing is shown in a [dry run].
+	///
+	/// [insertion preview]: Options::insertion_preview
+	/// [dry run]: Dispatcher::is_dry_run
+	async fn update_insertion_preview(&self, state: &state::AquariWm<ClientWindow>, held: bool) -> Result<()> {
+		let predicted = match held && !self.dispatcher.is_dry_run() {
+			// The next window's class isn't known yet, so it isn't predicted to be grouped with any
+			// other window.
+			true => state.predict_insertion(|_| false),
+			false => None,
+		};
+
+		self.show_overlay(state, |wm| &mut wm.preview_overlay, predicted).await
+	}
+
+	/// Shows the [drop overlay] over where the tiled window being [dragged] would be dropped, or
+	/// hides it if it wouldn't be dropped anywhere or isn't being dragged.
+	///
+	/// [drop overlay]: Wm::drop_overlay
+	/// [dragged]: Drag::Swap
+	async fn update_drop_overlay(
+		&self,
+		state: &state::AquariWm<ClientWindow>,
+		drag: Option<&Drag<ClientWindow>>,
+	) -> Result<()> {
+		let highlight = match drag {
+			Some(Drag::Swap { target: Some(drop), .. }) => Some(drop.highlight()),
+
+			_ => None,
+		};
+
+		self.show_overlay(state, |wm| &mut wm.drop_overlay, highlight).await
+	}
+
+	/// Shows the given `overlay` over the given rectangle, or hides it if there is none, creating
+	/// it first if it hasn't been created yet.
+	///
+	/// The overlay is only configured if the rectangle changed. It is stacked above the tiled
+	/// windows, but below any fullscreen window.
+	async fn show_overlay(
+		&self,
+		state: &state::AquariWm<ClientWindow>,
+		overlay: fn(&mut Wm) -> &mut Overlay,
+		rect: Option<placement::Rect>,
+	) -> Result<()> {
+		let Overlay { window, shown } = *overlay(&mut self.handler());
+		if rect == shown {
+			return Ok(());
+		}
+		let window = match window {
+			Some(window) => window,
+
+			None => {
+				let window = self.create_overlay().await?;
+				overlay(&mut self.handler()).window = Some(window);
+
+				window
+			},
+		};
+
+		match rect {
+			Some((x, y, width, height)) => {
+				let fullscreen = self.handler().fullscreen.iter().copied().find(|window| {
+					state
+						.windows
+						.get(window)
+						.is_some_and(|window| window.mapped == state::MapState::Mapped)
+				});
+
+				let border = 2 * PREVIEW_BORDER_WIDTH;
+				let aux = x11::ConfigureWindowAux::new()
+					.x(x)
+					.y(y)
+					.width(width.saturating_sub(border).max(1))
+					.height(height.saturating_sub(border).max(1));
+				let aux = match fullscreen {
+					Some(fullscreen) => aux.sibling(fullscreen.raw()).stack_mode(x11::StackMode::BELOW),
+					None => aux.stack_mode(x11::StackMode::ABOVE),
+				};
+
+				self.conn.configure_window(window.raw(), &aux).await?;
+				if shown.is_none() {
+					self.conn.map_window(window.raw()).await?;
+				}
+			},
+
+			None => {
+				self.conn.unmap_window(window.raw()).await?;
+			},
+		}
+		overlay(&mut self.handler()).shown = rect;
+
+		Ok(())
+	}
+
+	/// Creates an [overlay] window: an override-redirect window, so that it is never managed, with
+	/// a translucent fill if there is a compositor.
+	///
+	/// [overlay]: Overlay
+	async fn create_overlay(&self) -> Result<UtilityWindow> {
+		const COPY_FROM_PARENT: u32 = 0;
+
+		let overlay = UtilityWindow::new(self.conn.generate_id().await?);
+		self.conn
+			.checked(self.conn.create_window(
+				COPY_FROM_PARENT as u8,
+				overlay.raw(),
+				self.root,
+				0,
+				0,
+				1,
+				1,
+				PREVIEW_BORDER_WIDTH as u16,
+				x11::WindowClass::INPUT_OUTPUT,
+				COPY_FROM_PARENT,
+				&x11::CreateWindowAux::new().override_redirect(1),
+			))
+			.await?;
+		// The overlay is AquariWM's own, so it mustn't be managed when its creation is notified.
+		self.handler().utility.insert(overlay);
+
+		self.paint_overlay(overlay).await?;
+
+		Ok(overlay)
+	}
+
+	/// Gives the given [overlay] window the theme's [fill] and [outline] colors, with the fill's
+	/// alpha channel as its opacity for compositors to show the tiles beneath it through it.
+	///
+	/// [overlay]: Overlay
+	/// [fill]: Slot::OverlayFill
+	/// [outline]: Slot::OverlayOutline
+	async fn paint_overlay(&self, overlay: UtilityWindow) -> Result<()> {
+		let (fill, outline, alpha) = {
+			let handler = self.handler();
+			let palette = handler.palettes.screen();
+
+			(
+				palette.get(Slot::OverlayFill),
+				palette.get(Slot::OverlayOutline),
+				handler.palettes.theme().get(Slot::OverlayFill).alpha(),
+			)
+		};
+
+		let aux = x11::ChangeWindowAttributesAux::new()
+			.background_pixel(fill)
+			.border_pixel(outline);
+		self.conn.change_window_attributes(overlay.raw(), &aux).await?;
+		// Each byte of the alpha channel is repeated, so that opaque is `0xffffffff`.
+		self.conn
+			.set_property_cardinals(
+				overlay.raw(),
+				self.atoms._NET_WM_WINDOW_OPACITY,
+				x11::AtomEnum::CARDINAL,
+				&[u32::from(alpha) * 0x0101_0101],
+			)
+			.await?;
+		// Shown overlays are drawn again with their new background.
+		self.conn.clear_area(false, overlay.raw(), 0, 0, 0, 0).await?;
+
+		Ok(())
+	}
+
+	/// Destroys the given [overlay], if it has been created.
+	///
+	/// [overlay]: Overlay
+	async fn destroy_overlay(&self, overlay: fn(&mut Wm) -> &mut Overlay) -> Result<()> {
+		let window = std::mem::take(overlay(&mut self.handler())).window;
+
+		if let Some(window) = window {
+			self.conn.destroy_window(window.raw()).await?;
+		}
+
+		Ok(())
+	}
+
+	/// Selects the events AquariWM needs on the given managed `window`: changes to its properties,
+	/// key presses to know when the user was last typing, and focus changes to notice when other
+	/// clients grab the keyboard.
+	async fn select_window_events(&self, window: ClientWindow) -> Result<()> {
+		self.conn
+			.change_window_attributes(
+				window.raw(),
+				&Attributes::new()
+					.event_mask(EventMask::PROPERTY_CHANGE | EventMask::KEY_PRESS | EventMask::FOCUS_CHANGE),
+			)
+			.await?
+			.ignore_error();
+
+		Ok(())
+	}
+
+	/// Grabs the pointer on the root window so that its motion and button releases are reported to
+	/// AquariWM during a [drag], returning whether it was grabbed.
+	///
+	/// [drag]: Drag
+	async fn grab_pointer(&self) -> Result<bool> {
+		const NONE: u32 = 0;
+
+		let reply = self
+			.conn
+			.grab_pointer(
+				false,
+				self.root,
+				EventMask::POINTER_MOTION | EventMask::BUTTON_RELEASE,
+				x11::GrabMode::ASYNC,
+				x11::GrabMode::ASYNC,
+				NONE,
+				NONE,
+				CURRENT_TIME,
+			)
+			.await?
+			.reply()
+			.await?;
+
+		Ok(reply.status == x11::GrabStatus::SUCCESS)
+	}
+
+	/// Circulates the given [floating] `window` in the given `direction`.
+	///
+	/// # Errors
+	/// If the given `window` is not [floating], a [`NonFloatingWindow` error] will be returned.
+	/// This is because tiled windows are always meant to be at the bottom of the window stack, and
+	/// tiled windows are non-overlapping: their order in the window stack does not matter.
+	///
+	/// # Panics
+	/// If the given `window` is not tracked by the given window manager `state`, this function will
+	/// panic. This shouldn't happen if the window comes from the X server.
+	///
+	/// [floating]: layout::Mode::Floating
+	/// [`NonFloatingWindow` error]: CirculateWindowError::NonFloatingWindow
+	pub async fn circulate_window<Direction>(
+		&self,
+		state: &state::AquariWm<ClientWindow>,
+		window: ClientWindow,
+		direction: Direction,
+	) -> Result<()>
+	where
+		Direction: TryInto<CirculateDirection>,
+		Direction::Error: Into<Error>,
+	{
+		match state.windows.get(&window) {
+			// If it is a floating window...
+			Some(state::WindowState {
+				mode: layout::Mode::Floating,
+				..
+			}) => {
+				let direction: CirculateDirection = direction.try_into().map_err(|error| error.into())?;
+
+				self.dispatch(Request::Circulate(window, direction.into()))
+					.await
+					.map_err(Error::from)?
+					.check()
+					.await
+					.map_err(Error::from)?;
 
 				// If we're moving the window to the bottom, then we have to move all the tiling windows
 				// below it.
@@ -363,8 +2515,7 @@ impl X11 {
 
 					// Move each tiled window to the bottom of the window stack.
 					future::try_join_all(tiled_windows.map(|&window| async move {
-						self.conn
-							.circulate_window(CirculateDirection::MoveToBottom.into(), window)
+						self.dispatch(Request::Circulate(window, CirculateDirection::MoveToBottom.into()))
 							.await?
 							.check()
 							.await
@@ -384,50 +2535,633 @@ impl X11 {
 		}
 	}
 
-	/// Honors a [configure window request] without modifying it.
+	/// Prepares for a configure request of the given [floating] `window` to be [handled], returning
+	/// its size hints.
 	///
-	/// [configure window request]: x11::ConfigureRequestEvent
-	pub async fn honor_configure_window(&self, request: &x11::ConfigureRequestEvent) -> Result<()> {
-		self.conn
-			.configure_window(request.window, &util::ConfigureValues::from(request).into())
+	/// If the window became floating without being placed by AquariWM, its current geometry is
+	/// queried and [stored] first. Returns [`None`] if the window no longer exists.
+	///
+	/// [floating]: layout::Mode::Floating
+	/// [handled]: Wm::on_configure_request
+	/// [stored]: Wm::floating
+	async fn prepare_configure_floating(&self, window: ClientWindow) -> Result<Option<SizeHints>> {
+		let stored = self.handler().floating.contains_key(&window);
+
+		if !stored {
+			let Ok(geometry) = self.conn.get_geometry(window.raw()).await?.reply().await else {
+				return Ok(None);
+			};
+
+			self.handler().floating.insert(
+				window,
+				Geometry {
+					x: geometry.x as i32,
+					y: geometry.y as i32,
+
+					width: geometry.width as u32,
+					height: geometry.height as u32,
+
+					border_width: geometry.border_width as u32,
+				},
+			);
+		}
+
+		let hints = self
+			.conn
+			.get_property_cardinals(
+				window.raw(),
+				x11::AtomEnum::WM_NORMAL_HINTS,
+				x11::AtomEnum::WM_SIZE_HINTS,
+				18,
+			)
 			.await?;
 
-		Ok(())
+		Ok(Some(util::size_hints(&hints)))
 	}
 
 	/// Registers for the `SUBSTRUCTURE_NOTIFY` and `SUBSTRUCTURE_REDIRECT` event masks on the root
 	/// window; that is, register as a window manager.
-	async fn register_window_manager(&self) -> Result<()> {
-		let register_event_masks = self
-			.conn
-			.change_window_attributes(
+	///
+	/// If [observing] another window manager, only `SUBSTRUCTURE_NOTIFY` is registered for, which
+	/// doesn't conflict with it.
+	///
+	/// [observing]: Dispatcher::is_observe_only
+	async fn register_window_manager(&self) -> Result<(), ClassifiedError> {
+		let event_mask = match self.dispatcher.is_observe_only() {
+			true => EventMask::SUBSTRUCTURE_NOTIFY,
+			false => EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+		};
+
+		self.conn
+			.checked(
+				self.conn
+					.change_window_attributes(self.root, &Attributes::new().event_mask(event_mask)),
+			)
+			.await
+	}
+
+	/// Advertises the [EWMH] hints that AquariWM supports in the root window's `_NET_SUPPORTED`
+	/// property.
+	///
+	/// Clients only trust `_NET_SUPPORTED` if the window manager also creates a
+	/// `_NET_SUPPORTING_WM_CHECK` window, so that is created too.
+	///
+	/// [EWMH]: https://specifications.freedesktop.org/wm-spec/latest/
+	async fn advertise_ewmh_support(&self) -> Result<()> {
+		const COPY_FROM_PARENT: u32 = 0;
+
+		let check = UtilityWindow::new(self.conn.generate_id().await?);
+		self.conn
+			.checked(self.conn.create_window(
+				COPY_FROM_PARENT as u8,
+				check.raw(),
 				self.root,
-				&Attributes::new().event_mask(EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT),
+				-1,
+				-1,
+				1,
+				1,
+				0,
+				x11::WindowClass::INPUT_ONLY,
+				COPY_FROM_PARENT,
+				&x11::CreateWindowAux::new(),
+			))
+			.await?;
+		// The check window is AquariWM's own, so it mustn't be managed when its creation is notified.
+		self.handler().utility.insert(check);
+
+		for window in [self.root, check.raw()] {
+			self.conn
+				.checked(self.conn.set_property_cardinals(
+					window,
+					self.atoms._NET_SUPPORTING_WM_CHECK,
+					x11::AtomEnum::WINDOW,
+					&[check.raw()],
+				))
+				.await?;
+		}
+		self.conn
+			.checked(self.conn.set_property_string(
+				check.raw(),
+				self.atoms._NET_WM_NAME,
+				self.atoms.UTF8_STRING,
+				"AquariWM",
+			))
+			.await?;
+
+		let supported = [
+			self.atoms._NET_SUPPORTING_WM_CHECK,
+			self.atoms._NET_WM_MOVERESIZE,
+			self.atoms._NET_WM_STATE,
+			self.atoms._NET_WM_STATE_STICKY,
+			self.atoms._NET_WM_STATE_HIDDEN,
+			self.atoms._NET_WM_DESKTOP,
+			self.atoms._NET_WM_PING,
+			self.atoms._NET_ACTIVE_WINDOW,
+			self.atoms._NET_NUMBER_OF_DESKTOPS,
+			self.atoms._NET_DESKTOP_NAMES,
+			self.atoms._NET_CURRENT_DESKTOP,
+		];
+		self.conn
+			.checked(
+				self.conn
+					.set_property_atoms(self.root, self.atoms._NET_SUPPORTED, &supported),
 			)
 			.await?;
 
-		register_event_masks.check().await?;
 		Ok(())
 	}
 
+	/// Settles the [latency metrics] at the flush point, then publishes them in the
+	/// `_AQUARIWM_LATENCY` property of the root window if any operation has completed since they
+	/// were last published.
+	///
+	/// Windows are mapped at their final geometry, and the layout has settled, once no re-tile is
+	/// pending or being reconfigured. A key press which didn't change the focus never will.
+	///
+	/// [latency metrics]: Wm::metrics
+	async fn settle_metrics(&self, state: &state::AquariWm<ClientWindow>) -> Result<()> {
+		let report = {
+			let mut handler = self.handler();
+			if !handler.metrics.is_enabled() {
+				return Ok(());
+			}
+
+			let settled = !handler.pending_apply.is_pending()
+				&& !handler.dispatch_queue.is_pending()
+				&& state
+					.tiling_layout()
+					.is_none_or(|layout| layout.stats().pending_changes == 0);
+
+			handler
+				.metrics
+				.settle(Instant::now(), |key, operation| match operation {
+					// Windows which are unmapped again before they have settled are never mapped at
+					// their final geometry.
+					Operation::Map => match key.and_then(|window| state.windows.get(&window)) {
+						Some(window_state) if window_state.mapped == state::MapState::Mapped => {
+							match settled && !window_state.admission.is_pending() {
+								true => Progress::Done,
+								false => Progress::Pending,
+							}
+						},
+
+						_ => Progress::Cancelled,
+					},
+					Operation::Focus => match state.focused != *key {
+						true => Progress::Done,
+						false => Progress::Cancelled,
+					},
+					Operation::Unmap => match settled {
+						true => Progress::Done,
+						false => Progress::Pending,
+					},
+				});
+
+			match handler.metrics.take_changed() {
+				true => handler.metrics.report(),
+				false => return Ok(()),
+			}
+		};
+
+		self.conn
+			.set_property_string(
+				self.root,
+				self.atoms._AQUARIWM_LATENCY,
+				self.atoms.UTF8_STRING,
+				&report.encode(),
+			)
+			.await?
+			.ignore_error();
+
+		Ok(())
+	}
+
+	/// Publishes the [status] on the root window for external bars, if it has changed since it was
+	/// last published.
+	///
+	/// The EWMH desktop properties are updated, as well as the `_AQUARIWM_STATUS` property, which
+	/// holds the whole status as JSON. Only the properties which changed are replaced.
+	///
+	/// [status]: Status
+	async fn publish_status(&self, publisher: &mut StatusPublisher) -> Result<()> {
+		let Some((status, dirty)) = publisher.flush() else {
+			return Ok(());
+		};
+
+		for part in dirty {
+			let (root, cardinal, utf8_string) = (self.root, x11::AtomEnum::CARDINAL, self.atoms.UTF8_STRING);
+
+			let cookie = match part {
+				Part::WorkspaceCount => {
+					let count = status.workspaces.len() as u32;
+
+					self.conn
+						.set_property_cardinals(root, self.atoms._NET_NUMBER_OF_DESKTOPS, cardinal, &[count])
+						.await?
+				},
+				Part::WorkspaceNames => {
+					self.conn
+						.set_property_strings(
+							root,
+							self.atoms._NET_DESKTOP_NAMES,
+							utf8_string,
+							status.workspace_names(),
+						)
+						.await?
+				},
+				Part::ActiveWorkspace => {
+					let active = status.active_workspace() as u32;
+
+					self.conn
+						.set_property_cardinals(root, self.atoms._NET_CURRENT_DESKTOP, cardinal, &[active])
+						.await?
+				},
+
+				Part::Status => {
+					self.conn
+						.set_property_string(root, self.atoms._AQUARIWM_STATUS, utf8_string, &status.encode())
+						.await?
+				},
+			};
+
+			cookie.ignore_error();
+		}
+
+		Ok(())
+	}
+
+	/// Publishes the [window list] on the root window for external window switchers as JSON in the
+	/// `_AQUARIWM_WINDOWS` property, if it has changed since it was `published`.
+	///
+	/// [window list]: WindowList
+	async fn publish_window_list(&self, published: &mut Option<WindowList>, list: WindowList) -> Result<()> {
+		if published.as_ref() == Some(&list) {
+			return Ok(());
+		}
+
+		self.conn
+			.set_property_string(
+				self.root,
+				self.atoms._AQUARIWM_WINDOWS,
+				self.atoms.UTF8_STRING,
+				&list.encode(),
+			)
+			.await?
+			.ignore_error();
+		*published = Some(list);
+
+		Ok(())
+	}
+
+	/// Grabs the [key bindings] on the root window.
+	///
+	/// The key bindings' keysyms and modifiers are resolved with the current keyboard and modifier
+	/// mappings, and each key binding is grabbed for every combination of the lock modifiers (e.g.
+	/// NumLock and CapsLock) so that it still works while they are on.
+	///
+	/// If a key binding can't be grabbed, e.g. because another client has already grabbed it, the
+	/// grabs that succeeded for its key are released again, so that it isn't received twice, and
+	/// it is matched against raw key events instead if `raw_keys` are [selected].
+	///
+	/// Returns the [keys] the key bindings were resolved to, and how each of them is received.
+	///
+	/// [key bindings]: key_bindings
+	/// [selected]: Self::select_raw_key_events
+	/// [keys]: Keys
+	async fn grab_key_bindings(&self, key_bindings: &[(Vec<Modifier>, u32, Binding)], raw_keys: bool) -> Result<Keys> {
+		let setup = self.conn.setup();
+		let (min_keycode, max_keycode) = (setup.min_keycode, setup.max_keycode);
+
+		let (modifier_map, keyboard_map) = try_join!(
+			async { Ok::<_, Error>(self.conn.get_modifier_mapping().await?.reply().await?) },
+			async {
+				let count = max_keycode - min_keycode + 1;
+
+				Ok(self
+					.conn
+					.get_keyboard_mapping(min_keycode, count)
+					.await?
+					.reply()
+					.await?)
+			},
+		)?;
+
+		let keymap = Keymap::new(min_keycode, keyboard_map.keysyms_per_keycode, keyboard_map.keysyms);
+		let modifier_map = ModifierMap::new(modifier_map.keycodes_per_modifier(), modifier_map.keycodes);
+
+		let modifiers = Modifiers::discover(&modifier_map, &keymap);
+		event!(Level::DEBUG, "Resolved modifiers: {modifiers:?}");
+
+		let mut bindings = HashMap::new();
+		let mut statuses = Vec::new();
+		let mut fallback = HashSet::new();
+
+		for (binding_modifiers, keysym, binding) in key_bindings {
+			let keysym = *keysym;
+			let mask = modifiers.mask(binding_modifiers);
+			let keycodes = keymap.keycodes(keysym);
+			let chord = config::Chord {
+				modifiers: binding_modifiers.clone(),
+				keysym,
+			};
+
+			if keycodes.is_empty() {
+				event!(Level::WARN, "No key is mapped to keysym {keysym:#x} for {binding:?}");
+			}
+			let mut status = match keycodes.is_empty() {
+				true => GrabStatus::Unavailable,
+				false => GrabStatus::Grabbed,
+			};
+
+			for keycode in keycodes {
+				bindings.insert((keycode, mask), binding.clone());
+
+				// Grabbing keys would steal them from the window manager being observed.
+				if self.dispatcher.is_observe_only() {
+					status = GrabStatus::Unavailable;
+					continue;
+				}
+
+				// Every combination is requested before any is checked, so that they take a single
+				// round trip.
+				let mut cookies = Vec::new();
+				for ignored in modifiers.ignored_combinations() {
+					let cookie = self
+						.conn
+						.grab_key(
+							false,
+							self.root,
+							x11::ModMask::from(mask | ignored),
+							keycode,
+							x11::GrabMode::ASYNC,
+							x11::GrabMode::ASYNC,
+						)
+						.await?;
+
+					cookies.push(cookie);
+				}
+
+				let mut failure = None;
+				for cookie in cookies {
+					match cookie.check().await.map_err(ClassifiedError::from) {
+						Ok(()) => (),
+
+						Err(ClassifiedError::Connection(error)) => return Err(error.into()),
+						Err(error) => failure = failure.or(Some(error)),
+					}
+				}
+				let Some(error) = failure else {
+					continue;
+				};
+
+				for ignored in modifiers.ignored_combinations() {
+					self.conn
+						.ungrab_key(keycode, self.root, x11::ModMask::from(mask | ignored))
+						.await?
+						.ignore_error();
+				}
+
+				if raw_keys {
+					event!(
+						Level::WARN,
+						"Failed to grab {chord} for {binding:?}, so it is matched against raw key events instead: \
+						 {error}"
+					);
+
+					fallback.insert((keycode, mask));
+					status = status.max(GrabStatus::Fallback);
+				} else {
+					event!(Level::WARN, "Failed to grab {chord} for {binding:?}: {error}");
+
+					bindings.remove(&(keycode, mask));
+					status = GrabStatus::Unavailable;
+				}
+			}
+
+			statuses.push((chord, status));
+		}
+
+		// These are only needed while the keyboard is grabbed, so they aren't grabbed themselves.
+		let switch_keys = SwitchKeys {
+			escape: keymap.keycodes(keysym::ESCAPE),
+			modifier: [keysym::SUPER_L, keysym::SUPER_R]
+				.into_iter()
+				.flat_map(|keysym| keymap.keycodes(keysym))
+				.collect(),
+		};
+		let grabs = KeyGrabs {
+			statuses,
+			fallback,
+
+			modifier_map,
+		};
+
+		Ok(Keys {
+			modifiers,
+			bindings,
+			switch_keys,
+			grabs,
+		})
+	}
+
+	/// Selects raw XInput2 key events on the root window, with which the key bindings that couldn't
+	/// be grabbed are received instead, returning whether they were selected.
+	///
+	/// Raw key events are reported for every key press, whichever client has the keyboard focus or
+	/// has grabbed the key. They aren't selected while [observing] another window manager, the
+	/// key bindings of which would be performed too.
+	///
+	/// [observing]: Dispatcher::is_observe_only
+	async fn select_raw_key_events(&self) -> Result<bool> {
+		if self.dispatcher.is_observe_only() {
+			return Ok(false);
+		}
+
+		// Raw key events are reported to the root window regardless of grabs since XInput 2.1.
+		let version = match self.conn.xinput_xi_query_version(2, 1).await {
+			Ok(cookie) => cookie.reply().await,
+			Err(error) => {
+				event!(Level::WARN, "XInput2 is not available: {error}");
+				return Ok(false);
+			},
+		};
+		match version {
+			Ok(version) if (version.major_version, version.minor_version) >= (2, 1) => (),
+
+			Ok(version) => {
+				event!(
+					Level::WARN,
+					"XInput {}.{} is too old for raw key events",
+					version.major_version,
+					version.minor_version
+				);
+				return Ok(false);
+			},
+			Err(error) => {
+				event!(Level::WARN, "XInput2 is not available: {error}");
+				return Ok(false);
+			},
+		}
+
+		let mask = xinput::EventMask {
+			deviceid: xinput::Device::ALL_MASTER.into(),
+			mask: vec![xinput::XIEventMask::RAW_KEY_PRESS | xinput::XIEventMask::RAW_KEY_RELEASE],
+		};
+		let selected = self
+			.conn
+			.checked(self.conn.xinput_xi_select_events(self.root, &[mask]))
+			.await;
+
+		match selected {
+			Ok(()) => Ok(true),
+
+			Err(ClassifiedError::Connection(error)) => Err(error.into()),
+			Err(error) => {
+				event!(Level::WARN, "Failed to select raw key events: {error}");
+				Ok(false)
+			},
+		}
+	}
+
+	/// Queries the screen's [outputs] with RandR.
+	///
+	/// Each output's [scale factor] is taken from the given `scales` overrides or, if there is no
+	/// override for it, derived from the output's physical size. If RandR monitors are not
+	/// available, the whole screen, of the given dimensions, is returned as the only output.
+	///
+	/// Monitors which overlap by at least the `clone_overlap` are [grouped] into one output, as
+	/// they mirror each other.
+	///
+	/// [outputs]: Output
+	/// [scale factor]: Scale
+	/// [grouped]: Output::group_clones
+	async fn query_outputs(
+		&self,
+		width: u16,
+		height: u16,
+		width_mm: u16,
+		scales: &[ScaleOverride],
+		clone_overlap: CloneOverlap,
+	) -> Result<Vec<Output>> {
+		let screen = {
+			const NAME: &str = "screen";
+
+			Output {
+				name: NAME.to_owned(),
+
+				x: 0,
+				y: 0,
+
+				width: width as u32,
+				height: height as u32,
+
+				primary: true,
+				scale: ScaleOverride::find(scales, NAME)
+					.unwrap_or_else(|| Scale::from_physical(width as u32, width_mm as u32)),
+
+				clones: Vec::new(),
+			}
+		};
+
+		let monitors = match self.conn.randr_get_monitors(self.root, true).await {
+			Ok(cookie) => match cookie.reply().await {
+				Ok(reply) if !reply.monitors.is_empty() => reply.monitors,
+
+				Ok(_) => return Ok(vec![screen]),
+				Err(error) => {
+					event!(
+						Level::WARN,
+						"Failed to query RandR monitors, using the whole screen: {error}"
+					);
+
+					return Ok(vec![screen]);
+				},
+			},
+
+			Err(x11rb::errors::ConnectionError::UnsupportedExtension) => {
+				event!(
+					Level::WARN,
+					"RandR is not supported, using the whole screen as one output"
+				);
+
+				return Ok(vec![screen]);
+			},
+			Err(error) => return Err(error.into()),
+		};
+
+		// Send GetAtomName requests for each monitor's name.
+		let cookies =
+			future::try_join_all(monitors.iter().map(|monitor| self.conn.get_atom_name(monitor.name))).await?;
+		let names = future::try_join_all(cookies.into_iter().map(|cookie| cookie.reply())).await?;
+
+		let monitors = monitors
+			.into_iter()
+			.zip(names)
+			.map(|(monitor, name)| {
+				let name = String::from_utf8_lossy(&name.name).into_owned();
+				let scale = ScaleOverride::find(scales, &name)
+					.unwrap_or_else(|| Scale::from_physical(monitor.width as u32, monitor.width_in_millimeters));
+
+				Output {
+					name,
+
+					x: monitor.x as i32,
+					y: monitor.y as i32,
+
+					width: monitor.width as u32,
+					height: monitor.height as u32,
+
+					primary: monitor.primary,
+					scale,
+
+					clones: Vec::new(),
+				}
+			})
+			.collect();
+
+		Ok(Output::group_clones(monitors, clone_overlap))
+	}
 	/// Queries the children of the `root` window and their [map states].
 	///
+	/// Override-redirect windows are [classified] as such, and left out.
+	///
 	/// [map states]: state::MapState
-	async fn query_windows(&self) -> Result<Vec<(x11::Window, state::MapState)>> {
-		let windows = self.conn.query_tree(self.root).await?.reply().await?.children;
+	/// [classified]: Wm::classify
+	async fn query_windows(&self) -> Result<Vec<(ClientWindow, state::MapState)>> {
+		let children = self.conn.query_tree(self.root).await?.reply().await?.children;
+
+		// The children of the root window other than AquariWM's own windows are clients' top-level
+		// windows.
+		let windows: Vec<_> = {
+			let handler = self.handler();
+
+			children
+				.into_iter()
+				.filter(|&window| !handler.utility.contains(&UtilityWindow::new(window)))
+				.map(ClientWindow::new)
+				.collect()
+		};
 
 		// Send GetWindowAttributes requests for each window.
-		let cookies =
-			future::try_join_all(windows.iter().map(|&window| self.conn.get_window_attributes(window))).await?;
+		let cookies = future::try_join_all(
+			windows
+				.iter()
+				.map(|window| self.conn.get_window_attributes(window.raw())),
+		)
+		.await?;
 		let replies = future::try_join_all(cookies.into_iter().map(|cookie| cookie.reply())).await?;
 
-		let map_states = replies.into_iter().map(|reply| reply.map_state.try_into());
+		let mut handler = self.handler();
+		for (window, reply) in windows.iter().zip(&replies) {
+			handler.classify(window.raw(), reply.override_redirect);
+		}
 
 		// Zip windows up with their map states.
 		Ok(windows
 			.into_iter()
-			.zip(map_states)
-			.map(|(window, map_state)| map_state.map(|map_state| (window, map_state)))
+			.zip(replies)
+			.filter(|(_, reply)| !reply.override_redirect)
+			.map(|(window, reply)| reply.map_state.try_into().map(|map_state| (window, map_state)))
 			.try_collect()?)
 	}
 }
@@ -0,0 +1,247 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! AquariWM's key bindings, and the keycodes and modifier masks they were grabbed with.
+
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::{self, Display, Formatter},
+};
+
+use super::modifiers::{keysym, Modifier, ModifierMap, Modifiers};
+use crate::{
+	action::Action,
+	config::{self, Command, KeyBindings},
+	output,
+	switcher,
+};
+
+/// The number of logical pixels by which the window gap is adjusted.
+const GAP_STEP: i32 = 5;
+/// The number of logical pixels by which the primary area of the layout is resized.
+const RESIZE_STEP: i32 = 50;
+
+/// What is done when a key binding is pressed.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Binding {
+	/// Destroys the focused window, or every window in the focused group.
+	CloseWindow,
+	/// Launches the terminal set in the `TERM` environment variable.
+	SpawnTerminal,
+	/// Runs the given shell command.
+	Spawn(String),
+
+	/// Performs the given [action].
+	///
+	/// [action]: Action
+	Action(Action),
+	/// Starts or continues [switching windows] with the given key.
+	///
+	/// [switching windows]: switcher
+	SwitchWindows(switcher::Key),
+}
+
+/// The keycodes which control a [window switching] session while the keyboard is grabbed for it.
+///
+/// [window switching]: switcher
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SwitchKeys {
+	/// The keycodes of Escape, which cancels the session.
+	pub escape: Vec<u8>,
+	/// The keycodes of the Super keys, the release of which commits to the selected window.
+	pub modifier: Vec<u8>,
+}
+
+/// How a key binding is received.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum GrabStatus {
+	/// The key binding was grabbed.
+	Grabbed,
+	/// The key binding couldn't be grabbed, e.g. because another client already grabbed it, so it
+	/// is matched against raw XInput2 key events instead.
+	Fallback,
+	/// The key binding can't be received: it couldn't be grabbed and XInput2 isn't available, or
+	/// no key is mapped to its keysym.
+	Unavailable,
+}
+
+impl Display for GrabStatus {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Grabbed => write!(f, "grabbed"),
+			Self::Fallback => write!(f, "fallback"),
+			Self::Unavailable => write!(f, "unavailable"),
+		}
+	}
+}
+
+/// How the key bindings are received, as [grabbed].
+///
+/// [grabbed]: super::X11::grab_key_bindings
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct KeyGrabs {
+	/// The status of each key binding, by its chord.
+	pub statuses: Vec<(config::Chord, GrabStatus)>,
+	/// The keycodes and [normalized] modifier masks of the key bindings which couldn't be grabbed,
+	/// which are matched against raw key events instead.
+	///
+	/// [normalized]: Modifiers::normalize
+	pub fallback: HashSet<(u8, u16)>,
+
+	/// The modifier mapping, with which the modifiers held during raw key events are [tracked].
+	///
+	/// [tracked]: super::modifiers::ModifierState
+	pub modifier_map: ModifierMap,
+}
+
+/// The key bindings as they were last [grabbed], resolved to keycodes and modifier masks.
+///
+/// [grabbed]: super::X11::grab_key_bindings
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Keys {
+	/// The modifier masks of Alt and Super, and of the lock modifiers which are ignored.
+	pub modifiers: Modifiers,
+	/// What is done when each key binding is pressed, by its keycode and [normalized] modifier
+	/// mask.
+	///
+	/// [normalized]: Modifiers::normalize
+	pub bindings: HashMap<(u8, u16), Binding>,
+	/// The keycodes which control [window switching] sessions.
+	///
+	/// [window switching]: switcher
+	pub switch_keys: SwitchKeys,
+	/// How each key binding is received.
+	pub grabs: KeyGrabs,
+}
+
+/// AquariWM's key bindings: the modifiers and keysym of each key binding.
+const KEY_BINDINGS: &[(&[Modifier], u32, Binding)] = &[
+	(
+		&[Modifier::Super, Modifier::Shift],
+		keysym::RETURN,
+		Binding::SpawnTerminal,
+	),
+	(
+		&[Modifier::Super, Modifier::Shift],
+		keysym::LOWERCASE_I,
+		Binding::CloseWindow,
+	),
+	(
+		&[Modifier::Super, Modifier::Shift],
+		keysym::EQUAL,
+		Binding::Action(Action::AdjustGap(GAP_STEP)),
+	),
+	(
+		&[Modifier::Super, Modifier::Shift],
+		keysym::MINUS,
+		Binding::Action(Action::AdjustGap(-GAP_STEP)),
+	),
+	(
+		&[Modifier::Super],
+		keysym::RETURN,
+		Binding::Action(Action::SwapWithPrimary),
+	),
+	(
+		&[Modifier::Super],
+		keysym::LOWERCASE_H,
+		Binding::Action(Action::ResizePrimary(-RESIZE_STEP)),
+	),
+	(
+		&[Modifier::Super],
+		keysym::LOWERCASE_L,
+		Binding::Action(Action::ResizePrimary(RESIZE_STEP)),
+	),
+	(
+		&[Modifier::Super],
+		keysym::COMMA,
+		Binding::Action(Action::FocusOutput(output::Direction::Left)),
+	),
+	(
+		&[Modifier::Super],
+		keysym::PERIOD,
+		Binding::Action(Action::FocusOutput(output::Direction::Right)),
+	),
+	(
+		&[Modifier::Super, Modifier::Shift],
+		keysym::COMMA,
+		Binding::Action(Action::MoveWindowToOutput(output::Direction::Left)),
+	),
+	(
+		&[Modifier::Super, Modifier::Shift],
+		keysym::PERIOD,
+		Binding::Action(Action::MoveWindowToOutput(output::Direction::Right)),
+	),
+	(
+		&[Modifier::Super],
+		keysym::LOWERCASE_S,
+		Binding::Action(Action::ToggleSticky),
+	),
+	(
+		&[Modifier::Super],
+		keysym::LOWERCASE_N,
+		Binding::Action(Action::MinimizeFocused),
+	),
+	(
+		&[Modifier::Super, Modifier::Shift],
+		keysym::LOWERCASE_N,
+		Binding::Action(Action::RestoreMinimized),
+	),
+	(
+		&[Modifier::Super, Modifier::Shift],
+		keysym::LOWERCASE_U,
+		Binding::Action(Action::IgnoreFocusedWindow),
+	),
+	(
+		&[Modifier::Super],
+		keysym::TAB,
+		Binding::SwitchWindows(switcher::Key::Next),
+	),
+	(
+		&[Modifier::Super, Modifier::Shift],
+		keysym::TAB,
+		Binding::SwitchWindows(switcher::Key::Previous),
+	),
+	(
+		&[Modifier::Super, Modifier::Shift],
+		keysym::LOWERCASE_R,
+		Binding::Action(Action::ReloadConfig),
+	),
+];
+
+/// Returns the [default key bindings], overridden by the `configured` key bindings of the same
+/// keys.
+///
+/// [default key bindings]: KEY_BINDINGS
+pub fn key_bindings(configured: &KeyBindings) -> Vec<(Vec<Modifier>, u32, Binding)> {
+	let mut bindings: Vec<_> = KEY_BINDINGS
+		.iter()
+		.map(|(modifiers, keysym, binding)| {
+			let mut modifiers = modifiers.to_vec();
+			// Configured chords' modifiers are sorted, so that the same key is found however its
+			// modifiers are ordered.
+			modifiers.sort_unstable();
+
+			(modifiers, *keysym, binding.clone())
+		})
+		.collect();
+
+	for config::KeyBinding { chord, command } in configured.bindings() {
+		bindings.retain(|(modifiers, keysym, _)| (modifiers, *keysym) != (&chord.modifiers, chord.keysym));
+
+		let binding = match command {
+			Command::CloseWindow => Binding::CloseWindow,
+			Command::SpawnTerminal => Binding::SpawnTerminal,
+			Command::Spawn(command) => Binding::Spawn(command.clone()),
+
+			Command::Action(action) => Binding::Action(action.clone()),
+			Command::NextWindow => Binding::SwitchWindows(switcher::Key::Next),
+			Command::PreviousWindow => Binding::SwitchWindows(switcher::Key::Previous),
+
+			Command::Unbind => continue,
+		};
+		bindings.push((chord.modifiers.clone(), chord.keysym, binding));
+	}
+
+	bindings
+}
@@ -0,0 +1,454 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The seam between AquariWM deciding what to do with windows and actually changing them.
+//!
+//! Every request which changes what is on the screen is [dispatched] as a [`Request`], so that in
+//! a [dry run] it can be logged instead of sent. [Event handlers] return the requests they decide
+//! on rather than dispatching them themselves.
+//!
+//! [dispatched]: X11::dispatch
+//! [dry run]: DispatchMode::DryRun
+//! [Event handlers]: super::wm::Wm
+
+use std::{
+	fmt,
+	fmt::{Debug, Display, Formatter},
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+use tracing::{event, Level};
+use x11rb_async::{
+	errors::ReplyError,
+	protocol::xproto::{self as x11, ConnectionExt},
+	rust_connection::RustConnection,
+	VoidCookie,
+};
+
+use super::{util::ConnectionExt as _, window::ClientWindow, ConnResult, X11};
+use crate::{display_server::DispatchMode, placement::Geometry};
+
+/// A request which changes what is on the screen.
+#[derive(Clone, Copy)]
+pub enum Request {
+	/// Configures a window's geometry or stacking.
+	Configure(ClientWindow, x11::ConfigureWindowAux),
+	/// Sends a window a synthetic `ConfigureNotify` event telling it its geometry, e.g. because
+	/// its configure request was only partially honored.
+	NotifyConfigure(ClientWindow, Geometry),
+	/// Maps a window.
+	Map(ClientWindow),
+	/// Unmaps a window.
+	Unmap(ClientWindow),
+	/// Gives a window input focus, or the root window if [`None`], as of the given server
+	/// timestamp.
+	Focus(Option<ClientWindow>, x11::Timestamp),
+	/// Sends a window a `WM_TAKE_FOCUS` message with the given server timestamp, telling its client
+	/// that it may take input focus.
+	TakeFocus(ClientWindow, x11::Timestamp),
+	/// Sends a window a `_NET_WM_PING` message with the given server timestamp, which its client
+	/// sends back if it is still responsive.
+	Ping(ClientWindow, x11::Timestamp),
+	/// Destroys a window.
+	Destroy(ClientWindow),
+	/// Closes the connection of a window's client, destroying all of its windows.
+	KillClient(ClientWindow),
+	/// Raises or lowers a window.
+	Circulate(ClientWindow, x11::Circulate),
+	/// Sets a window's `_NET_WM_WINDOW_OPACITY` property, or removes it if [`None`].
+	SetOpacity(ClientWindow, Option<u32>),
+	/// Sets a window's `_NET_WM_BYPASS_COMPOSITOR` property, or removes it if [`None`].
+	SetBypassCompositor(ClientWindow, Option<u32>),
+	/// Adds the given state to a window's `_NET_WM_STATE` property if `true`, or removes it if
+	/// `false`, keeping its other states.
+	SetState(ClientWindow, x11::Atom, bool),
+	/// Marks a window as iconified if `true`, or as no longer iconified if `false`, in its
+	/// `WM_STATE` and `_NET_WM_STATE` properties.
+	SetIconic(ClientWindow, bool),
+	/// Sets a window's `_NET_WM_DESKTOP` property.
+	SetDesktop(ClientWindow, u32),
+	/// Sets a window's border to the given pixel, or back to its parent's border if [`None`].
+	SetBorder(ClientWindow, Option<u32>),
+	/// Moves the pointer to the given position on the root window.
+	WarpPointer(i16, i16),
+}
+
+/// Decides whether [requests] are sent to the X server or only logged.
+///
+/// [requests]: Request
+#[derive(Debug)]
+pub struct Dispatcher {
+	/// Whether AquariWM is observing another window manager, rather than managing windows itself.
+	observe_only: bool,
+	/// Whether requests are logged instead of sent.
+	dry_run: AtomicBool,
+}
+
+/// The outcome of [dispatching] a request.
+///
+/// [dispatching]: X11::dispatch
+pub enum Dispatched<'conn> {
+	/// The request was sent to the X server.
+	Sent(VoidCookie<'conn, RustConnection>),
+	/// The request was only logged.
+	Logged,
+}
+
+/// Requests are debug-formatted as they are logged in a [dry run].
+///
+/// [dry run]: DispatchMode::DryRun
+impl Debug for Request {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		Display::fmt(self, f)
+	}
+}
+
+impl Display for Request {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Configure(window, values) => {
+				write!(f, "configure window {window}:")?;
+
+				let fields = [
+					("x", values.x.map(i64::from)),
+					("y", values.y.map(i64::from)),
+					("width", values.width.map(i64::from)),
+					("height", values.height.map(i64::from)),
+					("border width", values.border_width.map(i64::from)),
+					("sibling", values.sibling.map(i64::from)),
+				];
+				for (name, value) in fields {
+					if let Some(value) = value {
+						write!(f, " {name} {value}")?;
+					}
+				}
+				if let Some(stack_mode) = values.stack_mode {
+					write!(f, " stack mode {stack_mode:?}")?;
+				}
+
+				Ok(())
+			},
+			Self::NotifyConfigure(window, geometry) => write!(
+				f,
+				"tell window {window} it is at ({}, {}) with size {}x{}",
+				geometry.x, geometry.y, geometry.width, geometry.height
+			),
+
+			Self::Map(window) => write!(f, "map window {window}"),
+			Self::Unmap(window) => write!(f, "unmap window {window}"),
+			Self::Focus(Some(window), _) => write!(f, "focus window {window}"),
+			Self::Focus(None, _) => write!(f, "focus the root window"),
+			Self::TakeFocus(window, _) => write!(f, "tell window {window} to take focus"),
+			Self::Ping(window, _) => write!(f, "ping window {window}"),
+			Self::Destroy(window) => write!(f, "destroy window {window}"),
+			Self::KillClient(window) => write!(f, "kill the client of window {window}"),
+			Self::Circulate(window, direction) => write!(f, "circulate window {window} ({direction:?})"),
+
+			Self::SetOpacity(window, Some(opacity)) => write!(f, "set the opacity of window {window} to {opacity:#x}"),
+			Self::SetOpacity(window, None) => write!(f, "remove the opacity of window {window}"),
+			Self::SetBypassCompositor(window, Some(bypass)) => {
+				write!(f, "set the compositor bypass hint of window {window} to {bypass}")
+			},
+			Self::SetBypassCompositor(window, None) => {
+				write!(f, "remove the compositor bypass hint of window {window}")
+			},
+			Self::SetState(window, state, true) => write!(f, "add state {state} to window {window}"),
+			Self::SetState(window, state, false) => write!(f, "remove state {state} from window {window}"),
+			Self::SetIconic(window, true) => write!(f, "mark window {window} as iconified"),
+			Self::SetIconic(window, false) => write!(f, "mark window {window} as no longer iconified"),
+			Self::SetDesktop(window, desktop) => write!(f, "set the desktop of window {window} to {desktop:#x}"),
+			Self::SetBorder(window, Some(pixel)) => write!(f, "set the border of window {window} to {pixel:#x}"),
+			Self::SetBorder(window, None) => write!(f, "reset the border of window {window}"),
+			Self::WarpPointer(x, y) => write!(f, "warp the pointer to ({x}, {y})"),
+		}
+	}
+}
+
+impl Dispatcher {
+	/// Creates a dispatcher for the given `mode`.
+	pub const fn new(mode: DispatchMode) -> Self {
+		Self {
+			observe_only: matches!(mode, DispatchMode::ObserveOnly),
+			dry_run: AtomicBool::new(!matches!(mode, DispatchMode::Live)),
+		}
+	}
+
+	/// Returns whether AquariWM is observing another window manager, rather than managing windows
+	/// itself.
+	///
+	/// Requests are never sent while observing.
+	#[inline(always)]
+	pub const fn is_observe_only(&self) -> bool {
+		self.observe_only
+	}
+
+	/// Returns whether requests are logged instead of sent.
+	#[inline]
+	pub fn is_dry_run(&self) -> bool {
+		self.dry_run.load(Ordering::Relaxed)
+	}
+
+	/// Sets whether requests are logged instead of sent, returning whether that was changed.
+	///
+	/// A dry run can't be ended while [observing] another window manager.
+	///
+	/// [observing]: Self::is_observe_only
+	pub fn set_dry_run(&self, dry_run: bool) -> bool {
+		if self.observe_only {
+			return false;
+		}
+
+		self.dry_run.swap(dry_run, Ordering::Relaxed) != dry_run
+	}
+}
+
+impl Dispatched<'_> {
+	/// Returns whether the request was sent to the X server.
+	#[inline(always)]
+	pub const fn is_sent(&self) -> bool {
+		matches!(self, Self::Sent(_))
+	}
+
+	/// Waits for the X server to process the request, returning any error that it caused.
+	pub async fn check(self) -> Result<(), ReplyError> {
+		match self {
+			Self::Sent(cookie) => cookie.check().await,
+			Self::Logged => Ok(()),
+		}
+	}
+
+	/// Ignores any error that the request causes.
+	pub fn ignore_error(self) {
+		if let Self::Sent(cookie) = self {
+			cookie.ignore_error();
+		}
+	}
+}
+
+impl X11 {
+	/// Sends the given `request` to the X server or, in a [dry run], logs it instead.
+	///
+	/// [dry run]: Dispatcher::is_dry_run
+	pub async fn dispatch(&self, request: Request) -> ConnResult<Dispatched<'_>> {
+		if self.dispatcher.is_dry_run() {
+			event!(Level::INFO, "Dry run: would {request}");
+
+			return Ok(Dispatched::Logged);
+		}
+
+		let cookie = match request {
+			Request::Configure(window, values) => self.conn.configure_window(window.raw(), &values).await?,
+			Request::NotifyConfigure(window, geometry) => {
+				const NONE: u32 = 0;
+
+				let window = window.raw();
+
+				let event = x11::ConfigureNotifyEvent {
+					response_type: x11::CONFIGURE_NOTIFY_EVENT,
+					sequence: 0,
+
+					event: window,
+					window,
+					above_sibling: NONE,
+
+					x: geometry.x as i16,
+					y: geometry.y as i16,
+					width: geometry.width as u16,
+					height: geometry.height as u16,
+					border_width: geometry.border_width as u16,
+
+					override_redirect: false,
+				};
+
+				self.conn
+					.send_event(false, window, x11::EventMask::STRUCTURE_NOTIFY, event)
+					.await?
+			},
+			Request::Map(window) => self.conn.map_window(window.raw()).await?,
+			Request::Unmap(window) => {
+				// The `UnmapNotify` event this generates is expected, so that the window isn't removed
+				// from the layout; none is generated in a dry run.
+				*self.handler().expected_unmaps.entry(window).or_default() += 1;
+
+				self.conn.unmap_window(window.raw()).await?
+			},
+			Request::Focus(window, time) => {
+				let window = window.map_or(self.root, ClientWindow::raw);
+
+				self.conn.set_input_focus(x11::InputFocus::PARENT, window, time).await?
+			},
+			Request::TakeFocus(window, time) => {
+				self.conn
+					.send_client_message(
+						window.raw(),
+						self.atoms.WM_PROTOCOLS,
+						[self.atoms.WM_TAKE_FOCUS, time, 0, 0, 0],
+					)
+					.await?
+			},
+			Request::Ping(window, time) => {
+				self.conn
+					.send_client_message(
+						window.raw(),
+						self.atoms.WM_PROTOCOLS,
+						[self.atoms._NET_WM_PING, time, window.raw(), 0, 0],
+					)
+					.await?
+			},
+			Request::Destroy(window) => self.conn.destroy_window(window.raw()).await?,
+			Request::KillClient(window) => self.conn.kill_client(window.raw()).await?,
+			Request::Circulate(window, direction) => self.conn.circulate_window(direction, window.raw()).await?,
+
+			Request::SetOpacity(window, Some(opacity)) => {
+				self.conn
+					.set_property_cardinals(
+						window.raw(),
+						self.atoms._NET_WM_WINDOW_OPACITY,
+						x11::AtomEnum::CARDINAL,
+						&[opacity],
+					)
+					.await?
+			},
+			Request::SetOpacity(window, None) => {
+				self.conn
+					.delete_property(window.raw(), self.atoms._NET_WM_WINDOW_OPACITY)
+					.await?
+			},
+			Request::SetBypassCompositor(window, Some(bypass)) => {
+				self.conn
+					.set_property_cardinals(
+						window.raw(),
+						self.atoms._NET_WM_BYPASS_COMPOSITOR,
+						x11::AtomEnum::CARDINAL,
+						&[bypass],
+					)
+					.await?
+			},
+			Request::SetBypassCompositor(window, None) => {
+				self.conn
+					.delete_property(window.raw(), self.atoms._NET_WM_BYPASS_COMPOSITOR)
+					.await?
+			},
+			Request::SetState(window, state, set) => self.set_state(window, state, set).await?,
+			Request::SetIconic(window, iconic) => {
+				const NORMAL_STATE: u32 = 1;
+				const ICONIC_STATE: u32 = 3;
+				const NO_ICON: u32 = 0;
+
+				let wm_state = if iconic { ICONIC_STATE } else { NORMAL_STATE };
+				self.conn
+					.set_property_cardinals(
+						window.raw(),
+						self.atoms.WM_STATE,
+						self.atoms.WM_STATE,
+						&[wm_state, NO_ICON],
+					)
+					.await?
+					.ignore_error();
+
+				self.set_state(window, self.atoms._NET_WM_STATE_HIDDEN, iconic).await?
+			},
+			Request::SetDesktop(window, desktop) => {
+				self.conn
+					.set_property_cardinals(
+						window.raw(),
+						self.atoms._NET_WM_DESKTOP,
+						x11::AtomEnum::CARDINAL,
+						&[desktop],
+					)
+					.await?
+			},
+			Request::SetBorder(window, Some(pixel)) => {
+				self.conn
+					.change_window_attributes(window.raw(), &x11::ChangeWindowAttributesAux::new().border_pixel(pixel))
+					.await?
+			},
+			Request::SetBorder(window, None) => {
+				const COPY_FROM_PARENT: u32 = 0;
+
+				self.conn
+					.change_window_attributes(
+						window.raw(),
+						&x11::ChangeWindowAttributesAux::new().border_pixmap(COPY_FROM_PARENT),
+					)
+					.await?
+			},
+			Request::WarpPointer(x, y) => {
+				const NONE: u32 = 0;
+
+				self.conn.warp_pointer(NONE, self.root, 0, 0, 0, 0, x, y).await?
+			},
+		};
+
+		Ok(Dispatched::Sent(cookie))
+	}
+
+	/// Adds the given `state` to the given `window`'s `_NET_WM_STATE` property if `set`, or removes
+	/// it otherwise, keeping its other states.
+	async fn set_state(
+		&self,
+		window: ClientWindow,
+		state: x11::Atom,
+		set: bool,
+	) -> ConnResult<VoidCookie<'_, RustConnection>> {
+		let mut states = match self
+			.conn
+			.get_property_atoms(window.raw(), self.atoms._NET_WM_STATE)
+			.await
+		{
+			Ok(states) => states,
+
+			Err(ReplyError::ConnectionError(error)) => return Err(error),
+			// The window no longer exists, so setting its states will fail too.
+			Err(ReplyError::X11Error(_)) => Vec::new(),
+		};
+
+		states.retain(|&other| other != state);
+		if set {
+			states.push(state);
+		}
+
+		self.conn
+			.set_property_atoms(window.raw(), self.atoms._NET_WM_STATE, &states)
+			.await
+	}
+
+	/// [Dispatches] each of the given `requests` in order, such as those decided on by an event
+	/// handler, ignoring any errors they cause.
+	///
+	/// Errors are ignored because the windows the requests are for may have been destroyed in the
+	/// meantime.
+	///
+	/// [Dispatches]: Self::dispatch
+	pub async fn dispatch_all(&self, requests: impl IntoIterator<Item = Request>) -> ConnResult<()> {
+		for request in requests {
+			self.dispatch(request).await?.ignore_error();
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn toggle_dry_run() {
+		let dispatcher = Dispatcher::new(DispatchMode::Live);
+		assert!(!dispatcher.is_dry_run());
+
+		assert!(dispatcher.set_dry_run(true));
+		assert!(dispatcher.is_dry_run());
+		assert!(!dispatcher.set_dry_run(true));
+
+		assert!(dispatcher.set_dry_run(false));
+		assert!(!dispatcher.is_dry_run());
+
+		// Nothing is ever sent while observing another window manager.
+		let observer = Dispatcher::new(DispatchMode::ObserveOnly);
+		assert!(!observer.set_dry_run(false));
+		assert!(observer.is_dry_run());
+	}
+}
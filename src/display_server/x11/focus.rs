@@ -0,0 +1,138 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! How input focus is given to clients, according to their [ICCCM input model].
+//!
+//! Clients say whether they want the window manager to give them input focus with the input hint
+//! in their `WM_HINTS` property, and whether they want to be told when they could take focus
+//! themselves by listing `WM_TAKE_FOCUS` in their `WM_PROTOCOLS` property. Together, these decide
+//! which of four [input models] a client uses, and so [how focus is given] to it.
+//!
+//! [ICCCM input model]: https://x.org/releases/X11R7.6/doc/xorg-docs/specs/ICCCM/icccm.html#input_focus
+//! [input models]: InputModel
+//! [how focus is given]: InputModel::assignment
+
+/// How a client expects to receive input focus.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum InputModel {
+	/// The client never expects keyboard input.
+	NoInput,
+	/// The client expects the window manager to give it input focus.
+	#[default]
+	Passive,
+	/// The client expects the window manager to give it input focus, and may move focus between
+	/// its own windows when told it can with `WM_TAKE_FOCUS`.
+	LocallyActive,
+	/// The client gives itself input focus when told it can with `WM_TAKE_FOCUS`, e.g. so that it
+	/// can choose which of its windows is focused.
+	GloballyActive,
+}
+
+/// Which window should be given input focus with `SetInputFocus` by an [assignment].
+///
+/// [assignment]: Assignment
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Target {
+	/// The window being focused.
+	Window,
+	/// The root window, so that focus isn't left on the previously focused window when the window
+	/// being focused doesn't take keyboard input.
+	Fallback,
+}
+
+/// What the window manager does to focus a window, as decided by its [input model].
+///
+/// [input model]: InputModel
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Assignment {
+	/// Which window is given input focus with `SetInputFocus`, if any.
+	pub input_focus: Option<Target>,
+	/// Whether the client is sent a `WM_TAKE_FOCUS` message.
+	pub take_focus: bool,
+}
+
+impl InputModel {
+	/// Returns the input model of a client with the given input hint and whether it supports the
+	/// `WM_TAKE_FOCUS` protocol.
+	///
+	/// Clients without an input hint are assumed to want input focus, as many clients which do
+	/// leave it out.
+	pub const fn new(input: Option<bool>, take_focus: bool) -> Self {
+		let input = match input {
+			Some(input) => input,
+			None => true,
+		};
+
+		match (input, take_focus) {
+			(false, false) => Self::NoInput,
+			(true, false) => Self::Passive,
+			(true, true) => Self::LocallyActive,
+			(false, true) => Self::GloballyActive,
+		}
+	}
+
+	/// Returns what the window manager does to focus a window of a client with this input model.
+	pub const fn assignment(&self) -> Assignment {
+		let (input_focus, take_focus) = match self {
+			Self::NoInput => (Some(Target::Fallback), false),
+			Self::Passive => (Some(Target::Window), false),
+			Self::LocallyActive => (Some(Target::Window), true),
+			Self::GloballyActive => (None, true),
+		};
+
+		Assignment {
+			input_focus,
+			take_focus,
+		}
+	}
+}
+
+/// Returns the input hint from the 32-bit items of a `WM_HINTS` property, if the hint is set.
+pub fn input_hint(wm_hints: &[u32]) -> Option<bool> {
+	const INPUT_HINT: u32 = 1;
+
+	match wm_hints {
+		[flags, input, ..] if flags & INPUT_HINT != 0 => Some(*input != 0),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn models() {
+		let assignment = |input, take_focus| {
+			let Assignment {
+				input_focus,
+				take_focus,
+			} = InputModel::new(input, take_focus).assignment();
+
+			(input_focus, take_focus)
+		};
+
+		assert_eq!(assignment(Some(true), false), (Some(Target::Window), false));
+		assert_eq!(assignment(None, false), (Some(Target::Window), false));
+		assert_eq!(assignment(Some(true), true), (Some(Target::Window), true));
+		assert_eq!(assignment(None, true), (Some(Target::Window), true));
+
+		// Globally active clients focus themselves...
+		assert_eq!(assignment(Some(false), true), (None, true));
+		// ...but focus isn't left dangling for clients which don't take input at all.
+		assert_eq!(assignment(Some(false), false), (Some(Target::Fallback), false));
+	}
+
+	#[test]
+	fn hints() {
+		// Flags, then the input hint.
+		assert_eq!(input_hint(&[1, 0, 0]), Some(false));
+		assert_eq!(input_hint(&[1 | 1 << 8, 1]), Some(true));
+
+		// The input hint isn't set.
+		assert_eq!(input_hint(&[1 << 8, 0]), None);
+		assert_eq!(input_hint(&[1]), None);
+		assert_eq!(input_hint(&[]), None);
+	}
+}
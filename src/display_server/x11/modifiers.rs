@@ -0,0 +1,473 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Resolution of keysyms to keycodes and of logical modifiers to modifier masks.
+//!
+//! Both of these depend on the keyboard mapping and modifier mapping of the X server, which can
+//! change at any time (e.g. when switching keyboard layouts with `setxkbmap`, or remapping
+//! CapsLock to Control), so they are resolved again whenever a `MappingNotify` event is received.
+//!
+//! Key bindings which can't be grabbed are matched against the [modifiers held], as tracked from
+//! raw key events, instead of the modifier state of core key events.
+//!
+//! [modifiers held]: ModifierState
+
+use std::collections::HashMap;
+
+/// Keysyms used by AquariWM's key bindings and for discovering modifier masks.
+pub mod keysym {
+	pub const TAB: u32 = 0xff09;
+	pub const RETURN: u32 = 0xff0d;
+	pub const ESCAPE: u32 = 0xff1b;
+	pub const COMMA: u32 = 0x002c;
+	pub const MINUS: u32 = 0x002d;
+	pub const PERIOD: u32 = 0x002e;
+	pub const EQUAL: u32 = 0x003d;
+	pub const LOWERCASE_H: u32 = 0x0068;
+	pub const LOWERCASE_I: u32 = 0x0069;
+	pub const LOWERCASE_L: u32 = 0x006c;
+	pub const LOWERCASE_N: u32 = 0x006e;
+	pub const LOWERCASE_R: u32 = 0x0072;
+	pub const LOWERCASE_S: u32 = 0x0073;
+	pub const LOWERCASE_U: u32 = 0x0075;
+
+	pub const NUM_LOCK: u32 = 0xff7f;
+	pub const SCROLL_LOCK: u32 = 0xff14;
+
+	pub const ALT_L: u32 = 0xffe9;
+	pub const ALT_R: u32 = 0xffea;
+	pub const META_L: u32 = 0xffe7;
+	pub const META_R: u32 = 0xffe8;
+	pub const SUPER_L: u32 = 0xffeb;
+	pub const SUPER_R: u32 = 0xffec;
+}
+
+/// The mask of the Shift modifier.
+pub const SHIFT: u16 = 1 << 0;
+/// The mask of the Lock (i.e. CapsLock) modifier.
+pub const LOCK: u16 = 1 << 1;
+/// The mask of the Control modifier.
+pub const CONTROL: u16 = 1 << 2;
+/// The mask of the Mod1 modifier, which is conventionally Alt.
+pub const MOD1: u16 = 1 << 3;
+/// The mask of the Mod4 modifier, which is conventionally Super.
+pub const MOD4: u16 = 1 << 6;
+
+/// The mask of all eight modifiers (Shift, Lock, Control, and Mod1 through Mod5).
+///
+/// The other bits of a key event's state are the pointer buttons and, with XKB, the keyboard
+/// group, neither of which should affect whether a key binding matches.
+pub const ALL_MODIFIERS: u16 = 0xff;
+
+/// A logical modifier used in a key binding.
+///
+/// Alt and Super are resolved to whichever of Mod1 through Mod5 their keys are mapped to, so key
+/// bindings keep working if those are swapped.
+pub use crate::config::Modifier;
+
+/// The keysyms bound to each keycode, as returned by `GetKeyboardMapping`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Keymap {
+	min_keycode: u8,
+	keysyms_per_keycode: u8,
+
+	keysyms: Vec<u32>,
+}
+
+/// The keycodes mapped to each of the eight modifiers, as returned by `GetModifierMapping`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ModifierMap {
+	keycodes_per_modifier: u8,
+
+	keycodes: Vec<u8>,
+}
+
+/// The modifiers held down, tracked from the presses and releases of their keys.
+///
+/// Raw XInput2 key events don't carry the modifier state that core key events do, so this tracks
+/// it instead. Each held key is tracked separately, so releasing one of two keys of the same
+/// modifier (e.g. both Shift keys) leaves the modifier held.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ModifierState {
+	/// The mask of the modifiers of each held modifier key, as mapped when it was pressed.
+	held: HashMap<u8, u16>,
+}
+
+/// The modifier masks resolved from a [`ModifierMap`] and [`Keymap`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct Modifiers {
+	/// The mask of the modifier that Alt (or Meta) is mapped to.
+	pub alt: u16,
+	/// The mask of the modifier that Super is mapped to.
+	pub super_: u16,
+
+	/// The mask of modifiers which are ignored when matching key bindings: CapsLock, NumLock and
+	/// ScrollLock.
+	pub ignored: u16,
+}
+
+impl Keymap {
+	/// Creates a keymap from the keysyms of consecutive keycodes starting at `min_keycode`.
+	pub const fn new(min_keycode: u8, keysyms_per_keycode: u8, keysyms: Vec<u32>) -> Self {
+		Self {
+			min_keycode,
+			keysyms_per_keycode,
+
+			keysyms,
+		}
+	}
+
+	/// Returns the keysyms bound to the given `keycode`.
+	pub fn keysyms(&self, keycode: u8) -> &[u32] {
+		let per_keycode = self.keysyms_per_keycode as usize;
+
+		let Some(index) = keycode.checked_sub(self.min_keycode) else {
+			return &[];
+		};
+		let start = (index as usize) * per_keycode;
+
+		self.keysyms.get(start..(start + per_keycode)).unwrap_or(&[])
+	}
+
+	/// Returns the keycodes to which the given `keysym` is bound.
+	///
+	/// Only the first keyboard group (the first two keysyms of each keycode) is searched: keycodes
+	/// do not change when switching groups, so searching the other groups would bind the same key
+	/// twice or bind keys which only produce the `keysym` in another layout.
+	pub fn keycodes(&self, keysym: u32) -> Vec<u8> {
+		let per_keycode = (self.keysyms_per_keycode as usize).max(1);
+		let group_width = per_keycode.min(2);
+
+		self.keysyms
+			.chunks(per_keycode)
+			.enumerate()
+			.filter(|(_, keysyms)| keysyms[..group_width.min(keysyms.len())].contains(&keysym))
+			.filter_map(|(index, _)| u8::try_from((self.min_keycode as usize) + index).ok())
+			.collect()
+	}
+}
+
+impl ModifierMap {
+	/// Creates a modifier map from the keycodes of each of the eight modifiers, in order.
+	///
+	/// Unused entries are `0`.
+	pub const fn new(keycodes_per_modifier: u8, keycodes: Vec<u8>) -> Self {
+		Self {
+			keycodes_per_modifier,
+
+			keycodes,
+		}
+	}
+
+	/// Returns the combined mask of the modifiers to which any of the given `keysyms` are mapped.
+	pub fn mask_of(&self, keymap: &Keymap, keysyms: &[u32]) -> u16 {
+		let per_modifier = (self.keycodes_per_modifier as usize).max(1);
+
+		self.keycodes
+			.chunks(per_modifier)
+			.take(8)
+			.enumerate()
+			.filter(|(_, keycodes)| {
+				keycodes.iter().any(|&keycode| {
+					keycode != 0 && keymap.keysyms(keycode).iter().any(|keysym| keysyms.contains(keysym))
+				})
+			})
+			.fold(0, |mask, (modifier, _)| mask | (1 << modifier))
+	}
+
+	/// Returns the combined mask of the modifiers to which the given `keycode` is mapped, or `0`
+	/// if it isn't a modifier key.
+	pub fn modifier_of(&self, keycode: u8) -> u16 {
+		let per_modifier = (self.keycodes_per_modifier as usize).max(1);
+
+		self.keycodes
+			.chunks(per_modifier)
+			.take(8)
+			.enumerate()
+			.filter(|(_, keycodes)| keycode != 0 && keycodes.contains(&keycode))
+			.fold(0, |mask, (modifier, _)| mask | (1 << modifier))
+	}
+}
+
+impl ModifierState {
+	/// Records that the given `keycode` was pressed, holding its modifiers if it is a modifier key.
+	pub fn press(&mut self, modifier_map: &ModifierMap, keycode: u8) {
+		let mask = modifier_map.modifier_of(keycode);
+
+		if mask != 0 {
+			self.held.insert(keycode, mask);
+		}
+	}
+
+	/// Records that the given `keycode` was released.
+	///
+	/// The modifiers it held are released even if the modifier mapping has changed since it was
+	/// pressed. Releasing a key which wasn't seen being pressed, e.g. because it was already held
+	/// when tracking started, does nothing.
+	pub fn release(&mut self, keycode: u8) {
+		self.held.remove(&keycode);
+	}
+
+	/// Forgets the held keys which are no longer pressed, according to the given bit vector of
+	/// pressed keycodes, as returned by `QueryKeymap`.
+	///
+	/// Releases can be missed, e.g. while another client grabs the keyboard, which would leave
+	/// modifiers stuck down; this recovers from that.
+	pub fn retain_pressed(&mut self, keys: &[u8; 32]) {
+		self.held
+			.retain(|&keycode, _| keys[(keycode / 8) as usize] & (1 << (keycode % 8)) != 0);
+	}
+
+	/// Returns the mask of the held modifiers.
+	pub fn mask(&self) -> u16 {
+		self.held.values().fold(0, |mask, modifier| mask | modifier)
+	}
+}
+
+impl Modifiers {
+	/// Discovers the modifier masks of Alt, Super, and the ignored lock modifiers.
+	///
+	/// If Alt or Super are not mapped to any modifier, they fall back to their conventional
+	/// modifiers, Mod1 and Mod4 respectively.
+	pub fn discover(modifier_map: &ModifierMap, keymap: &Keymap) -> Self {
+		use keysym::*;
+
+		let or_default = |mask, default| if mask == 0 { default } else { mask };
+
+		let alt = modifier_map.mask_of(keymap, &[ALT_L, ALT_R, META_L, META_R]);
+		let super_ = modifier_map.mask_of(keymap, &[SUPER_L, SUPER_R]);
+		let locks = modifier_map.mask_of(keymap, &[NUM_LOCK, SCROLL_LOCK]);
+
+		Self {
+			alt: or_default(alt & !super_, MOD1),
+			super_: or_default(super_, MOD4),
+
+			// Never ignore a modifier that a binding might use.
+			ignored: (LOCK | locks) & !(SHIFT | CONTROL | alt | super_),
+		}
+	}
+
+	/// Returns the modifier mask of the given logical `modifiers`.
+	pub fn mask(&self, modifiers: &[Modifier]) -> u16 {
+		modifiers.iter().fold(0, |mask, modifier| {
+			mask | match modifier {
+				Modifier::Shift => SHIFT,
+				Modifier::Control => CONTROL,
+				Modifier::Alt => self.alt,
+				Modifier::Super => self.super_,
+			}
+		})
+	}
+
+	/// Returns every combination of the [ignored] modifiers, including none of them.
+	///
+	/// A key binding must be grabbed with each of these combinations added to its modifiers, or
+	/// the grab won't match while (for example) NumLock is on.
+	///
+	/// [ignored]: Self::ignored
+	pub fn ignored_combinations(&self) -> impl Iterator<Item = u16> {
+		let ignored = self.ignored;
+
+		// Iterate over the subsets of `ignored`.
+		let mut next = Some(0u16);
+
+		std::iter::from_fn(move || {
+			let current = next?;
+			next = (current != ignored).then(|| (current.wrapping_sub(ignored)) & ignored);
+
+			Some(current)
+		})
+	}
+
+	/// Canonicalizes the `state` of a key event for matching against key bindings, removing the
+	/// [ignored] modifiers, pointer buttons, and keyboard group.
+	///
+	/// [ignored]: Self::ignored
+	pub const fn normalize(&self, state: u16) -> u16 {
+		state & ALL_MODIFIERS & !self.ignored
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{keysym::*, *};
+
+	const MIN_KEYCODE: u8 = 8;
+
+	// Keycodes from a typical evdev keymap.
+	const SHIFT_L: u8 = 50;
+	const CAPS_LOCK: u8 = 66;
+	const CONTROL_L: u8 = 37;
+	const ALT_L_KEY: u8 = 64;
+	const NUM_LOCK_KEY: u8 = 77;
+	const SCROLL_LOCK_KEY: u8 = 78;
+	const SUPER_L_KEY: u8 = 133;
+	const RETURN_KEY: u8 = 36;
+	const Y_KEY: u8 = 29;
+	const Z_KEY: u8 = 52;
+
+	/// Creates a keymap with two groups (`us,de`), where the Y and Z keys are swapped in the
+	/// second group.
+	fn keymap() -> Keymap {
+		const PER_KEYCODE: usize = 4;
+
+		let mut keysyms = vec![0; (256 - MIN_KEYCODE as usize) * PER_KEYCODE];
+		let mut bind = |keycode: u8, bound: [u32; PER_KEYCODE]| {
+			let start = ((keycode - MIN_KEYCODE) as usize) * PER_KEYCODE;
+			keysyms[start..(start + PER_KEYCODE)].copy_from_slice(&bound);
+		};
+
+		bind(SHIFT_L, [0xffe1; 4]);
+		bind(CAPS_LOCK, [0xffe5; 4]);
+		bind(CONTROL_L, [0xffe3; 4]);
+		bind(ALT_L_KEY, [ALT_L, META_L, ALT_L, META_L]);
+		bind(NUM_LOCK_KEY, [NUM_LOCK; 4]);
+		bind(SCROLL_LOCK_KEY, [SCROLL_LOCK; 4]);
+		bind(SUPER_L_KEY, [SUPER_L; 4]);
+		bind(RETURN_KEY, [RETURN; 4]);
+		bind(Y_KEY, [0x79, 0x59, 0x7a, 0x5a]);
+		bind(Z_KEY, [0x7a, 0x5a, 0x79, 0x59]);
+
+		Keymap::new(MIN_KEYCODE, PER_KEYCODE as u8, keysyms)
+	}
+
+	/// Creates a modifier map with two keycodes per modifier from the given keycodes of Shift,
+	/// Lock, Control, and Mod1 through Mod5.
+	fn modifier_map(modifiers: [[u8; 2]; 8]) -> ModifierMap {
+		ModifierMap::new(2, modifiers.concat())
+	}
+
+	#[test]
+	fn default_modifiers() {
+		let map = modifier_map([
+			[SHIFT_L, 0],
+			[CAPS_LOCK, 0],
+			[CONTROL_L, 0],
+			[ALT_L_KEY, 0],
+			[NUM_LOCK_KEY, 0],
+			[0, 0],
+			[SUPER_L_KEY, 0],
+			[SCROLL_LOCK_KEY, 0],
+		]);
+		let modifiers = Modifiers::discover(&map, &keymap());
+
+		assert_eq!(modifiers.alt, MOD1);
+		assert_eq!(modifiers.super_, MOD4);
+		// CapsLock, NumLock (Mod2), and ScrollLock (Mod5).
+		assert_eq!(modifiers.ignored, LOCK | (1 << 4) | (1 << 7));
+
+		let super_ = modifiers.mask(&[Modifier::Super]);
+		assert_eq!(super_, MOD4);
+
+		// Super still matches with NumLock on, CapsLock on, a pointer button held, and the
+		// second keyboard group active.
+		let num_lock = 1 << 4;
+		let button_1 = 1 << 8;
+		let group_2 = 1 << 13;
+		for state in [MOD4, MOD4 | num_lock, MOD4 | LOCK | num_lock, MOD4 | button_1 | group_2] {
+			assert_eq!(modifiers.normalize(state), super_, "state = {state:#x}");
+		}
+		assert_ne!(modifiers.normalize(MOD4 | SHIFT), super_);
+
+		// Every combination of the three ignored modifiers is grabbed.
+		let mut combinations: Vec<_> = modifiers.ignored_combinations().collect();
+		combinations.sort_unstable();
+		combinations.dedup();
+		assert_eq!(combinations.len(), 8);
+		assert!(combinations.iter().all(|&mask| mask & !modifiers.ignored == 0));
+	}
+
+	#[test]
+	fn remapped_modifiers() {
+		// CapsLock is Control, Super and Alt are swapped, and NumLock is on Mod3.
+		let map = modifier_map([
+			[SHIFT_L, 0],
+			[0, 0],
+			[CONTROL_L, CAPS_LOCK],
+			[SUPER_L_KEY, 0],
+			[0, 0],
+			[NUM_LOCK_KEY, 0],
+			[ALT_L_KEY, 0],
+			[0, 0],
+		]);
+		let modifiers = Modifiers::discover(&map, &keymap());
+
+		assert_eq!(modifiers.super_, MOD1);
+		assert_eq!(modifiers.alt, MOD4);
+		assert_eq!(modifiers.ignored, LOCK | (1 << 5));
+		assert_eq!(modifiers.mask(&[Modifier::Super, Modifier::Shift]), MOD1 | SHIFT);
+
+		// Without any mapping at all, the conventional modifiers are used.
+		let modifiers = Modifiers::discover(&ModifierMap::new(0, Vec::new()), &keymap());
+
+		assert_eq!((modifiers.alt, modifiers.super_, modifiers.ignored), (MOD1, MOD4, LOCK));
+		assert_eq!(modifiers.ignored_combinations().collect::<Vec<_>>(), [0, LOCK]);
+	}
+
+	#[test]
+	fn keycodes() {
+		let keymap = keymap();
+
+		assert_eq!(keymap.keycodes(RETURN), [RETURN_KEY]);
+		// Only the first group is used, so switching to the second group doesn't move bindings.
+		assert_eq!(keymap.keycodes(0x7a), [Z_KEY]);
+		assert_eq!(keymap.keycodes(0x59), [Y_KEY]);
+		assert!(keymap.keycodes(MINUS).is_empty());
+
+		assert_eq!(keymap.keysyms(MIN_KEYCODE - 1), &[] as &[u32]);
+	}
+
+	#[test]
+	fn modifier_state() {
+		const SHIFT_R: u8 = 62;
+
+		let map = modifier_map([
+			[SHIFT_L, SHIFT_R],
+			[CAPS_LOCK, 0],
+			[CONTROL_L, 0],
+			[ALT_L_KEY, 0],
+			[NUM_LOCK_KEY, 0],
+			[0, 0],
+			[SUPER_L_KEY, 0],
+			[0, 0],
+		]);
+		let mut state = ModifierState::default();
+
+		assert_eq!(map.modifier_of(SUPER_L_KEY), MOD4);
+		assert_eq!(map.modifier_of(RETURN_KEY), 0);
+		assert_eq!(map.modifier_of(0), 0);
+
+		// Other keys don't affect the modifiers.
+		state.press(&map, SUPER_L_KEY);
+		state.press(&map, RETURN_KEY);
+		assert_eq!(state.mask(), MOD4);
+
+		// Shift stays held until both Shift keys are released, in whichever order.
+		state.press(&map, SHIFT_L);
+		state.press(&map, SHIFT_R);
+		state.release(SHIFT_L);
+		assert_eq!(state.mask(), MOD4 | SHIFT);
+		state.release(SHIFT_R);
+		assert_eq!(state.mask(), MOD4);
+
+		// A key released without being seen pressed is ignored.
+		state.release(CONTROL_L);
+		assert_eq!(state.mask(), MOD4);
+
+		// Super is still held after being remapped, until it is released.
+		let remapped = modifier_map([[0; 2]; 8]);
+		state.press(&remapped, ALT_L_KEY);
+		assert_eq!(state.mask(), MOD4);
+		state.release(SUPER_L_KEY);
+		assert_eq!(state.mask(), 0);
+
+		// Super's release was missed; only Control is still pressed.
+		state.press(&map, SUPER_L_KEY);
+		state.press(&map, CONTROL_L);
+		let mut keys = [0; 32];
+		keys[(CONTROL_L / 8) as usize] |= 1 << (CONTROL_L % 8);
+		state.retain_pressed(&keys);
+		assert_eq!(state.mask(), CONTROL);
+	}
+}
@@ -0,0 +1,109 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Decoding and encoding of window property values.
+//!
+//! A property's value is a list of 8, 16 or 32-bit items, given by its format. Clients can set
+//! properties to anything, so a property may have the wrong format, be shorter than expected, or
+//! be missing entirely (in which case its format is `0`); every decoder here treats these as
+//! having no value rather than failing.
+
+/// The format of properties made up of 8-bit items, like strings.
+pub const FORMAT_8: u8 = 8;
+/// The format of properties made up of 32-bit items, like atoms, windows and cardinals.
+pub const FORMAT_32: u8 = 32;
+
+/// Decodes the 32-bit items of a property `value` of the given `format`.
+///
+/// If the property isn't of the 32-bit format, it is empty. If the `value` was cut short partway
+/// through an item, that item is left out.
+pub fn decode32(format: u8, value: &[u8]) -> Vec<u32> {
+	if format != FORMAT_32 {
+		return Vec::new();
+	}
+
+	value
+		.chunks_exact(4)
+		.map(|item| u32::from_ne_bytes([item[0], item[1], item[2], item[3]]))
+		.collect()
+}
+
+/// Encodes the given 32-bit `items` as a property value of the 32-bit format.
+pub fn encode32(items: &[u32]) -> Vec<u8> {
+	items.iter().flat_map(|item| item.to_ne_bytes()).collect()
+}
+
+/// Encodes the given `strings` as a property value of the 8-bit format, each null-terminated.
+pub fn encode_strings<'string>(strings: impl IntoIterator<Item = &'string str>) -> Vec<u8> {
+	strings
+		.into_iter()
+		.flat_map(|string| string.bytes().chain([0]))
+		.collect()
+}
+
+/// Decodes the null-separated strings of a property `value` of the given `format`.
+///
+/// The last string may or may not be null-terminated. Invalid UTF-8 is replaced, as clients often
+/// set `STRING` properties, which are Latin-1, to UTF-8 anyway. If the property isn't of the
+/// 8-bit format, it has no strings.
+pub fn decode_strings(format: u8, value: &[u8]) -> Vec<String> {
+	if format != FORMAT_8 || value.is_empty() {
+		return Vec::new();
+	}
+
+	let value = value.strip_suffix(&[0]).unwrap_or(value);
+
+	value
+		.split(|&byte| byte == 0)
+		.map(|string| String::from_utf8_lossy(string).into_owned())
+		.collect()
+}
+
+/// Decodes a single string from a property `value` of the given `format`.
+///
+/// Returns [`None`] if the property isn't of the 8-bit format or its first string is empty.
+pub fn decode_string(format: u8, value: &[u8]) -> Option<String> {
+	decode_strings(format, value)
+		.into_iter()
+		.next()
+		.filter(|string| !string.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn items32() {
+		let value = encode32(&[1, 2, u32::MAX]);
+		assert_eq!(decode32(FORMAT_32, &value), [1, 2, u32::MAX]);
+
+		// A reply cut short partway through its last item.
+		assert_eq!(decode32(FORMAT_32, &value[..10]), [1, 2]);
+		// A property set with the wrong format.
+		assert!(decode32(FORMAT_8, &value).is_empty());
+		// A missing property.
+		assert!(decode32(0, &[]).is_empty());
+	}
+
+	#[test]
+	fn strings() {
+		// `WM_CLASS` is the instance name followed by the class name, each null-terminated.
+		assert_eq!(decode_strings(FORMAT_8, b"firefox\0Firefox\0"), ["firefox", "Firefox"]);
+		assert_eq!(decode_strings(FORMAT_8, b"firefox\0Firefox"), ["firefox", "Firefox"]);
+		assert_eq!(decode_strings(FORMAT_8, b"\0Firefox\0"), ["", "Firefox"]);
+		assert_eq!(
+			decode_strings(FORMAT_8, &encode_strings(["1", "", "web"])),
+			["1", "", "web"]
+		);
+
+		assert_eq!(decode_string(FORMAT_8, b"AquariWM").as_deref(), Some("AquariWM"));
+		assert_eq!(decode_string(FORMAT_8, b"caf\xe9").as_deref(), Some("caf\u{fffd}"));
+
+		assert_eq!(decode_string(FORMAT_8, b""), None);
+		assert_eq!(decode_string(FORMAT_8, b"\0"), None);
+		assert_eq!(decode_string(FORMAT_32, &encode32(&[0x4141_4141])), None);
+		assert!(decode_strings(0, &[]).is_empty());
+	}
+}
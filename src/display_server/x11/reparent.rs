@@ -0,0 +1,86 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Deciding what a `ReparentNotify` event means for a managed window.
+//!
+//! Only children of the root window are managed. A client may reparent one of its windows into
+//! another window, e.g. to embed it, at which point it is no longer a top-level window and must be
+//! un-managed; it may later reparent it back to the root window, at which point it is managed
+//! again as if it had just been created.
+//!
+//! AquariWM doesn't reparent windows itself, as it doesn't draw frames, so every `ReparentNotify`
+//! event is for a reparent made by a client, and there are no reparents of its own to expect.
+
+/// What a `ReparentNotify` event means for the reparented window.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Reparent {
+	/// The window was reparented into a window other than the root window, so it is no longer
+	/// managed.
+	Away,
+	/// The window was reparented back to the root window, so it is managed again.
+	ToRoot,
+}
+
+impl Reparent {
+	/// Returns what reparenting a window to the given `parent` means.
+	pub const fn classify(parent: u32, root: u32) -> Self {
+		match parent == root {
+			true => Self::ToRoot,
+			false => Self::Away,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		layout::{managers::Stack, LayoutSettings},
+		state::{AquariWm, MapState},
+	};
+
+	const ROOT: u32 = 1;
+
+	/// Tests that a client reparenting one of its windows into another and back un-manages and
+	/// re-manages it.
+	#[test]
+	fn reparent_and_back() {
+		let windows = [(2, MapState::Mapped), (3, MapState::Mapped)];
+		let mut state = AquariWm::with_tiling_layout_and_windows::<Stack<u32>>(
+			0,
+			0,
+			1920,
+			1080,
+			windows,
+			LayoutSettings::default(),
+		);
+
+		// Handles a `ReparentNotify` event the way the display server does.
+		let reparent = |state: &mut AquariWm<u32>, window: u32, parent: u32| {
+			let reparent = Reparent::classify(parent, ROOT);
+
+			match reparent {
+				Reparent::Away => state.remove_window(&window),
+				Reparent::ToRoot => state.add_window(window, MapState::Unmapped).unwrap(),
+			}
+
+			reparent
+		};
+		let tiled = |state: &AquariWm<u32>| {
+			let mut windows: Vec<u32> = state.tiling_layout().unwrap().windows().into_iter().copied().collect();
+			windows.sort_unstable();
+
+			windows
+		};
+
+		// Embedding window 3 in window 2 removes it from the layout.
+		assert_eq!(reparent(&mut state, 3, 2), Reparent::Away);
+		assert_eq!(tiled(&state), [2]);
+
+		// Reparenting it back to the root window manages it again, once its map request is handled.
+		assert_eq!(reparent(&mut state, 3, ROOT), Reparent::ToRoot);
+		state.map_window(&3).unwrap();
+		assert_eq!(tiled(&state), [2, 3]);
+	}
+}
@@ -0,0 +1,878 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [Recording] the inputs the X11 window manager acts on, and replaying them through the
+//! window manager's handlers without a connection to an X server.
+//!
+//! Events aren't recorded raw: each [input] is an owned mirror of the fields the handlers read,
+//! along with what was queried from the X server to handle it, like a mapped window's class. The
+//! requests the handlers return are dropped when replaying, as there is no X server to make them
+//! to.
+//!
+//! Only what goes through the handlers and the layout model is replayed: windows being created,
+//! destroyed, reparented, mapped and unmapped, their titles and size hints changing,
+//! actions, screen changes, admissions, deferred re-tiles, and compaction. Anything else which
+//! changes the state, like dragging a window or reloading the configuration, is recorded as an
+//! [input which isn't replayed], so that replaying stops there rather than carrying on from a
+//! different state.
+//!
+//! [Recording]: crate::replay
+//! [input]: Input
+//! [input which isn't replayed]: Input::Other
+
+use std::{
+	collections::BTreeMap,
+	fmt,
+	fmt::{Display, Formatter},
+	io::BufRead,
+	iter,
+	time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+use x11rb_async::protocol::xproto as x11;
+
+use super::{
+	window::ClientWindow,
+	wm::{Client, Wm},
+};
+use crate::{
+	action::Action,
+	admission::WindowType,
+	autosave::PersistedWorkspace,
+	desktop::Desktop,
+	display_server::Options,
+	ignore::Matcher,
+	layout::{self, managers::BuiltIn, Increments, Reconfigured},
+	output::{Output, Scale},
+	placement::Geometry,
+	placement_memory::{Placement, PlacementMemory},
+	replay::{self, Fingerprint, Record, ReplayError},
+	state::{AquariWm, FocusCause, MapState, WindowState},
+	status,
+	window::{ManagedWindow, WindowId},
+	window_groups::WindowGroups,
+};
+
+/// An input acted on by the X11 window manager, as it is [recorded].
+///
+/// [recorded]: crate::replay
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Input {
+	/// A `CreateNotify` event.
+	Create {
+		window: x11::Window,
+		override_redirect: bool,
+	},
+	/// A `DestroyNotify` event.
+	Destroy { window: x11::Window },
+	/// A `ReparentNotify` event.
+	Reparent { window: x11::Window, parent: x11::Window },
+	/// An `UnmapNotify` event, and whether AquariWM unmapped the window itself.
+	Unmap { window: x11::Window, expected: bool },
+	/// A window being mapped, with what was queried about it to map it.
+	Map {
+		window: x11::Window,
+		class: Option<String>,
+		title: Option<String>,
+		/// The name of the output the window's program was launched on.
+		launch_output: Option<String>,
+		remembered: Option<Placement>,
+		sticky: bool,
+		desktop: Option<Desktop>,
+		geometry: Option<Geometry>,
+		window_type: WindowType,
+		/// Whether the window was floated before it was mapped, e.g. because it is transient.
+		floating: bool,
+		increments: Option<Increments>,
+		min_size: Option<(u32, u32)>,
+	},
+	/// A window's title changing.
+	Title { window: x11::Window, title: Option<String> },
+	/// A window's resize increments and minimum size changing.
+	SizeHints {
+		window: x11::Window,
+		increments: Option<Increments>,
+		min_size: Option<(u32, u32)>,
+	},
+	/// An action performed with a key binding.
+	Action { action: Action },
+	/// The screen's outputs changing, and the number of times the layout was rotated for them.
+	Screen {
+		width: u16,
+		height: u16,
+		outputs: Vec<Output>,
+		rotations: Option<i32>,
+	},
+	/// The windows whose admission delay has passed being admitted.
+	Admit,
+	/// A deferred re-tile.
+	Apply,
+	/// Sizes which have drifted in the layout being repaired.
+	Compact,
+
+	/// Something which isn't replayed, like an event which the window manager doesn't handle.
+	Other { event: String },
+}
+
+impl Input {
+	/// Returns the input for the given `window` being mapped with only its `class` known, as
+	/// [ignored] windows are.
+	///
+	/// [ignored]: crate::ignore
+	pub fn map_class(window: x11::Window, class: Option<String>) -> Self {
+		Self::Map {
+			window,
+			class,
+			title: None,
+			launch_output: None,
+			remembered: None,
+			sticky: false,
+			desktop: None,
+			geometry: None,
+			window_type: WindowType::default(),
+			floating: false,
+			increments: None,
+			min_size: None,
+		}
+	}
+
+	/// Returns an input which isn't replayed for the given `event`, named by its type.
+	pub fn other(event: &impl fmt::Debug) -> Self {
+		let event = format!("{event:?}");
+		let name = event.split(['(', ' ', '{']).next().unwrap_or_default();
+
+		Self::Other { event: name.to_owned() }
+	}
+}
+
+impl replay::Input for Input {
+	fn is_replayed(&self) -> bool {
+		match self {
+			// Layout presets are read from and written to disk.
+			Self::Action { action } => !matches!(action, Action::SaveLayout(_) | Action::LoadLayout(_)),
+			Self::Other { .. } => false,
+
+			_ => true,
+		}
+	}
+
+	fn unattributed() -> Self {
+		Self::Other {
+			event: "unattributed".to_owned(),
+		}
+	}
+}
+
+impl Display for Input {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Create { window, .. } => write!(f, "create {window}"),
+			Self::Destroy { window } => write!(f, "destroy {window}"),
+			Self::Reparent { window, parent, .. } => write!(f, "reparent {window} to {parent}"),
+			Self::Unmap { window, .. } => write!(f, "unmap {window}"),
+			Self::Map { window, .. } => write!(f, "map {window}"),
+			Self::Title { window, .. } => write!(f, "title of {window}"),
+			Self::SizeHints { window, .. } => write!(f, "size hints of {window}"),
+			Self::Action { action } => write!(f, "`{action}`"),
+			Self::Screen { width, height, .. } => write!(f, "screen change to {width}x{height}"),
+			Self::Admit => write!(f, "admission"),
+			Self::Apply => write!(f, "deferred re-tile"),
+			Self::Compact => write!(f, "compaction"),
+
+			Self::Other { event } => write!(f, "{event}"),
+		}
+	}
+}
+
+/// What is recorded about a window in a [`Header`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct WindowRecord {
+	pub window: x11::Window,
+	pub mode: layout::Mode,
+	pub mapped: MapState,
+	pub sticky: bool,
+}
+
+/// The state a recording starts from.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Header {
+	pub root: x11::Window,
+	pub screen: (u32, u32),
+	/// A fingerprint of the [options] which change how the layout is arranged, so that replaying
+	/// with different options can be warned about.
+	///
+	/// [options]: options_fingerprint
+	pub options: Fingerprint,
+
+	pub outputs: Vec<Output>,
+	pub scale: Scale,
+	/// The tiling layout, if there is one.
+	pub workspace: Option<PersistedWorkspace>,
+	pub windows: Vec<WindowRecord>,
+	pub ignored: Vec<Matcher>,
+	pub classes: BTreeMap<x11::Window, String>,
+	pub titles: BTreeMap<x11::Window, String>,
+
+	pub focused: Option<WindowId>,
+	pub state: Fingerprint,
+}
+
+impl Header {
+	/// Describes the given `wm` and `state`, which are run with the given `options`.
+	pub fn of(wm: &Wm, state: &AquariWm<ClientWindow>, options: &Options) -> Self {
+		let mut windows: Vec<_> = state
+			.windows
+			.iter()
+			.map(|(window, window_state)| WindowRecord {
+				window: window.raw(),
+				mode: window_state.mode,
+				mapped: window_state.mapped,
+				sticky: window_state.sticky,
+			})
+			.collect();
+		windows.sort_unstable_by_key(|record| record.window);
+
+		Self {
+			root: wm.root,
+			screen: (wm.screen.2, wm.screen.3),
+			options: options_fingerprint(options),
+
+			outputs: state.outputs.clone(),
+			scale: state.scale,
+			workspace: state.workspace_snapshot(),
+			windows,
+			ignored: state.ignored.matchers().to_vec(),
+			classes: wm
+				.classes
+				.iter()
+				.map(|(window, class)| (window.raw(), class.clone()))
+				.collect(),
+			titles: wm
+				.titles
+				.iter()
+				.map(|(window, title)| (window.raw(), title.clone()))
+				.collect(),
+
+			focused: state.focused.as_ref().map(ManagedWindow::id),
+			state: Fingerprint::of(state),
+		}
+	}
+}
+
+/// Returns a fingerprint of the given `options` which change how the layout is arranged.
+pub fn options_fingerprint(options: &Options) -> Fingerprint {
+	let layout = format!(
+		"{:?} {} {:?} {:?}",
+		options.settings,
+		options.layout_manager_for(0, status::WORKSPACE),
+		options.admission_delay,
+		options.rules,
+	);
+
+	Fingerprint::hash(layout.as_bytes())
+}
+
+/// A step of a replay, once it has been replayed.
+#[derive(Debug)]
+pub struct Step<'replay> {
+	/// The number of the step, or `0` for the state the recording starts from.
+	pub seq: u64,
+	/// The input replayed, or [`None`] for the state the recording starts from.
+	pub input: Option<&'replay Input>,
+	pub state: Fingerprint,
+	/// The [description] of the state after the step.
+	///
+	/// [description]: replay::describe
+	pub description: String,
+}
+
+impl Display for Step<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self.input {
+			Some(input) => writeln!(f, "step {}: {input} => {}", self.seq, self.state)?,
+			None => writeln!(f, "initial state => {}", self.state)?,
+		}
+
+		for line in self.description.lines() {
+			writeln!(f, "    {line}")?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Replays a recording through the window manager's handlers.
+pub struct Replayer {
+	wm: Wm,
+	state: AquariWm<ClientWindow>,
+
+	/// Where destroyed and unmapped windows' placements are remembered while replaying.
+	placements: PlacementMemory,
+	/// The instant recorded times are counted from.
+	start: Instant,
+}
+
+impl Replayer {
+	/// Restores the state the recording with the given `header` starts from, to be replayed with
+	/// the given `options`.
+	pub fn new(header: &Header, options: &Options) -> Self {
+		let (width, height) = header.screen;
+		let rect = (0, 0, width, height);
+
+		let mut wm = Wm {
+			admission_delay: options.admission_delay,
+			groups: WindowGroups::new(options.groups.clone()),
+			..Wm::new(header.root, rect, options.fullscreen_policy, options.rules.clone())
+		};
+		wm.classes = (header.classes.iter())
+			.map(|(&window, class)| (ClientWindow::new(window), class.clone()))
+			.collect();
+		wm.titles = (header.titles.iter())
+			.map(|(&window, title)| (ClientWindow::new(window), title.clone()))
+			.collect();
+
+		// Floating windows are tracked without being put in the layout.
+		let tiled = (header.windows.iter())
+			.filter(|record| record.mode == layout::Mode::Tiled)
+			.map(|record| (ClientWindow::new(record.window), record.mapped));
+		let workspace = (header.workspace.clone()).and_then(|snapshot| snapshot.resolve(ClientWindow::from_id));
+
+		let mut state = match workspace {
+			Some(snapshot) => {
+				let manager = (snapshot.manager.as_deref())
+					.and_then(|manager| manager.parse::<BuiltIn>().ok())
+					.unwrap_or(options.layout_manager);
+
+				let mut state = AquariWm::with_restored_manager(
+					manager.init(snapshot.layout, iter::empty()),
+					rect,
+					tiled,
+					options.settings.clone(),
+				);
+				state.marks = snapshot.marks;
+
+				state
+			},
+
+			None => AquariWm::with_windows(tiled, options.settings.clone()),
+		};
+
+		for record in &header.windows {
+			let window_state = (state.windows)
+				.entry(ClientWindow::new(record.window))
+				.or_insert_with(|| WindowState::new(record.mapped));
+
+			window_state.mode = record.mode;
+			window_state.mapped = record.mapped;
+			window_state.sticky = record.sticky;
+		}
+
+		for matcher in &header.ignored {
+			state.ignored.add(matcher.clone());
+		}
+		state.outputs = header.outputs.clone();
+		state.output_edges = options.output_edges;
+		state.switch_scope = options.switch_scope;
+		state.set_scale(header.scale);
+
+		let mut replayer = Self {
+			wm,
+			state,
+
+			placements: PlacementMemory::default(),
+			start: Instant::now(),
+		};
+		replayer.apply();
+		replayer.focus(header.focused);
+
+		replayer
+	}
+
+	/// Applies the changes made to the layout, as though every window was reconfigured.
+	fn apply(&mut self) {
+		let _ = self
+			.state
+			.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(Reconfigured::Applied));
+	}
+
+	/// Focuses the window with the given `id`, as recorded.
+	fn focus(&mut self, id: Option<WindowId>) {
+		let window = id.and_then(ClientWindow::from_id);
+
+		self.state.set_focused(window, FocusCause::Keyboard);
+	}
+
+	/// Replays the given `record`, returning the [fingerprint] of the resulting state if it
+	/// matches the recorded one.
+	///
+	/// [fingerprint]: Fingerprint::of
+	pub fn step(&mut self, record: &Record<Input>) -> Result<Fingerprint, ReplayError> {
+		let now = replay::instant_at(self.start, record.at);
+
+		self.handle(&record.input, now);
+		if !record.pending {
+			self.apply();
+		}
+		self.focus(record.focused);
+
+		let found = Fingerprint::of(&self.state);
+		if found == record.state {
+			return Ok(found);
+		}
+
+		Err(match replay::Input::is_replayed(&record.input) {
+			true => ReplayError::Diverged {
+				seq: record.seq,
+				input: record.input.to_string(),
+				expected: record.state,
+				found,
+			},
+			false => ReplayError::NotReplayed {
+				seq: record.seq,
+				input: record.input.to_string(),
+			},
+		})
+	}
+
+	/// Handles the given `input` as the X11 window manager did at the time `now`.
+	fn handle(&mut self, input: &Input, now: Instant) {
+		let Self {
+			wm, state, placements, ..
+		} = self;
+		let root = wm.root;
+
+		match input.clone() {
+			Input::Create {
+				window,
+				override_redirect,
+			} => {
+				let notify = x11::CreateNotifyEvent {
+					parent: root,
+					window,
+					override_redirect,
+					..Default::default()
+				};
+				wm.on_create_notify(state, &notify);
+			},
+			Input::Destroy { window } => {
+				let notify = x11::DestroyNotifyEvent {
+					event: window,
+					window,
+					..Default::default()
+				};
+				wm.on_destroy_notify(state, placements, &notify);
+			},
+			Input::Reparent { window, parent } => {
+				let notify = x11::ReparentNotifyEvent {
+					event: window,
+					window,
+					parent,
+					..Default::default()
+				};
+				wm.on_reparent_notify(state, &notify);
+			},
+			Input::Unmap { window, expected } => {
+				if expected {
+					*wm.expected_unmaps.entry(ClientWindow::new(window)).or_default() += 1;
+				}
+
+				let notify = x11::UnmapNotifyEvent {
+					event: window,
+					window,
+					..Default::default()
+				};
+				wm.on_unmap_notify(state, placements, &notify);
+			},
+
+			Input::Map {
+				window,
+				class,
+				title,
+				launch_output,
+				remembered,
+				sticky,
+				desktop,
+				geometry,
+				window_type,
+				floating,
+				increments,
+				min_size,
+			} => {
+				let request = x11::MapRequestEvent {
+					parent: root,
+					window,
+					..Default::default()
+				};
+				let client_window = ClientWindow::new(window);
+
+				// Ignored windows are passed through before anything else is done with them.
+				if state.ignored.matches(client_window.id(), class.as_deref()) {
+					let client = Client {
+						class,
+						..Client::default()
+					};
+					wm.on_map_request(state, &request, client);
+
+					return;
+				}
+
+				if floating {
+					if let Some(window_state) = state.windows.get_mut(&client_window) {
+						window_state.set_floating();
+					}
+				}
+				match title {
+					Some(title) => wm.titles.insert(client_window, title),
+					None => wm.titles.remove(&client_window),
+				};
+
+				let client = Client {
+					class,
+					launch_output: launch_output.and_then(|name| Output::named(&state.outputs, &name).cloned()),
+					remembered,
+					sticky,
+					desktop,
+					geometry,
+					visual: None,
+					window_type,
+				};
+				wm.hold_admission(state, client_window, &client, now);
+				wm.on_map_request(state, &request, client);
+				wm.evaluate_rules(state, client_window, false);
+				state.set_increments(&client_window, increments);
+				state.set_minimum_size(&client_window, min_size, wm.border_width(client_window));
+			},
+			Input::Title { window, title } => {
+				let window = ClientWindow::new(window);
+
+				match title {
+					Some(title) => wm.titles.insert(window, title),
+					None => wm.titles.remove(&window),
+				};
+				wm.evaluate_rules(state, window, true);
+			},
+			Input::SizeHints {
+				window,
+				increments,
+				min_size,
+			} => {
+				let window = ClientWindow::new(window);
+
+				state.set_increments(&window, increments);
+				state.set_minimum_size(&window, min_size, wm.border_width(window));
+			},
+
+			Input::Action { action } if replay::Input::is_replayed(input) => {
+				wm.perform(state, action);
+			},
+			Input::Screen {
+				width,
+				height,
+				outputs,
+				rotations,
+			} => {
+				if let Some(rotations) = rotations {
+					state.rotate_root(rotations);
+				}
+				state.outputs = outputs;
+
+				let scale = Output::primary(&state.outputs).map_or(Scale::IDENTITY, |output| output.scale);
+				state.resize(0, 0, width as u32, height as u32);
+				state.set_scale(scale);
+			},
+			Input::Admit => {
+				wm.admit_due(state, now);
+			},
+			Input::Compact => {
+				state.compact_layout();
+			},
+
+			Input::Apply | Input::Action { .. } | Input::Other { .. } => (),
+		}
+	}
+}
+
+/// Replays the recording read from the given `reader` with the given `options`, reporting each
+/// step once it has been replayed.
+///
+/// Returns the number of steps replayed, or an error for the first step which didn't reach the
+/// recorded state.
+pub fn replay(reader: impl BufRead, options: &Options, mut report: impl FnMut(&Step)) -> Result<u64, ReplayError> {
+	let (header, records): (Header, Vec<Record<Input>>) = replay::read(reader)?;
+
+	if header.options != options_fingerprint(options) {
+		event!(
+			Level::WARN,
+			"The recording was made with different layout options; replay it with the same configuration"
+		);
+	}
+
+	let mut replayer = Replayer::new(&header, options);
+
+	let found = Fingerprint::of(&replayer.state);
+	report(&Step {
+		seq: 0,
+		input: None,
+		state: found,
+		description: replay::describe(&replayer.state),
+	});
+	if found != header.state {
+		return Err(ReplayError::Diverged {
+			seq: 0,
+			input: "initial state".to_owned(),
+			expected: header.state,
+			found,
+		});
+	}
+
+	for record in &records {
+		let state = replayer.step(record)?;
+
+		report(&Step {
+			seq: record.seq,
+			input: Some(&record.input),
+			state,
+			description: replay::describe(&replayer.state),
+		});
+	}
+
+	Ok(records.len() as u64)
+}
+
+/// The [recorder] of the inputs the X11 window manager acts on.
+///
+/// [recorder]: replay::Recorder
+pub type Recorder = replay::Recorder<Input>;
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+	use crate::{admission, layout::managers::Stack};
+
+	/// A recording of a session of windows being mapped and unmapped, as a regression test of the
+	/// layout they end up in.
+	const FIXTURE: &str = include_str!("../../../tests/fixtures/x11-session.jsonl");
+
+	fn output() -> Output {
+		Output {
+			name: "HDMI-1".to_owned(),
+			x: 0,
+			y: 0,
+			width: 1920,
+			height: 1080,
+			primary: true,
+			scale: Scale::IDENTITY,
+			clones: Vec::new(),
+		}
+	}
+
+	fn map(window: x11::Window, class: Option<&str>) -> Input {
+		Input::Map {
+			window,
+			class: class.map(ToOwned::to_owned),
+			title: None,
+			launch_output: None,
+			remembered: None,
+			sticky: false,
+			desktop: None,
+			geometry: None,
+			window_type: match class {
+				Some(_) => WindowType::Normal,
+				None => WindowType::Absent,
+			},
+			floating: false,
+			increments: None,
+			min_size: None,
+		}
+	}
+
+	/// Tests that the state a recording starts from is restored exactly, nested groups and floating
+	/// windows included.
+	#[test]
+	fn restore() {
+		let options = Options::default();
+		let mut state = AquariWm::with_tiling_layout_and_windows::<Stack<ClientWindow>>(
+			0,
+			0,
+			1920,
+			1080,
+			(2..6).map(|window| (ClientWindow::new(window), MapState::Mapped)),
+			options.settings.clone(),
+		);
+		state.outputs = vec![output()];
+		state
+			.windows
+			.insert(ClientWindow::new(9), WindowState::new(MapState::Mapped));
+		state.float_window(&ClientWindow::new(9));
+		state.perform(Action::SwapWithPrimary).ok();
+		state
+			.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(Reconfigured::Applied))
+			.unwrap();
+
+		let wm = Wm::new(1, (0, 0, 1920, 1080), options.fullscreen_policy, Vec::new());
+		let header = Header::of(&wm, &state, &options);
+		let json = serde_json::to_string(&header).unwrap();
+
+		let replayer = Replayer::new(&serde_json::from_str(&json).unwrap(), &options);
+		assert_eq!(replay::describe(&replayer.state), replay::describe(&state));
+	}
+
+	#[test]
+	fn fixture() {
+		let mut steps = Vec::new();
+		let replayed = replay(FIXTURE.as_bytes(), &Options::default(), |step| {
+			steps.push((step.seq, step.input.cloned()))
+		});
+
+		assert_eq!(replayed.unwrap(), 14);
+		assert_eq!(steps.len(), 15);
+		assert!(steps.iter().any(|(_, input)| input == &Some(Input::Admit)));
+	}
+
+	/// Tests that the first step whose state doesn't match the recording is pinpointed.
+	#[test]
+	fn divergence() {
+		// A window floats where it was tiled in the recording.
+		let mut lines: Vec<_> = FIXTURE.lines().map(ToOwned::to_owned).collect();
+		let mut record: Record<Input> = serde_json::from_str(&lines[5]).unwrap();
+		let Input::Map { floating, .. } = &mut record.input else {
+			panic!("expected step 5 to map a window: {record:?}");
+		};
+		*floating = true;
+		lines[5] = serde_json::to_string(&record).unwrap();
+
+		let error = replay(lines.join("\n").as_bytes(), &Options::default(), |_| ()).unwrap_err();
+		assert!(matches!(error, ReplayError::Diverged { seq: 5, .. }), "{error}");
+
+		// Changes which can't be replayed stop the replay.
+		let mut lines: Vec<_> = FIXTURE.lines().map(ToOwned::to_owned).collect();
+		let mut record: Record<Input> = serde_json::from_str(&lines[12]).unwrap();
+		record.input = Input::Other {
+			event: "ButtonPress".to_owned(),
+		};
+		lines[12] = serde_json::to_string(&record).unwrap();
+
+		let error = replay(lines.join("\n").as_bytes(), &Options::default(), |_| ()).unwrap_err();
+		assert!(matches!(error, ReplayError::NotReplayed { seq: 12, .. }), "{error}");
+	}
+
+	#[test]
+	fn event_names() {
+		let event = x11::MapRequestEvent::default();
+
+		assert_eq!(
+			Input::other(&event),
+			Input::Other {
+				event: "MapRequestEvent".to_owned()
+			}
+		);
+	}
+
+	/// Writes the fixture from a scripted session, driving the handlers the way the X11 window
+	/// manager does.
+	#[test]
+	#[ignore = "writes the fixture"]
+	fn write_fixture() {
+		let options = Options::default();
+		let header = Header {
+			root: 1,
+			screen: (1920, 1080),
+			options: options_fingerprint(&options),
+
+			outputs: vec![output()],
+			scale: Scale::IDENTITY,
+			workspace: AquariWm::<ClientWindow>::with_tiling_layout::<Stack<ClientWindow>>(
+				0,
+				0,
+				1920,
+				1080,
+				options.settings.clone(),
+			)
+			.workspace_snapshot(),
+			windows: Vec::new(),
+			ignored: Vec::new(),
+			classes: BTreeMap::new(),
+			titles: BTreeMap::new(),
+
+			focused: None,
+			state: Fingerprint(0),
+		};
+		let mut replayer = Replayer::new(&header, &options);
+		let header = Header::of(&replayer.wm, &replayer.state, &options);
+
+		let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/x11-session.jsonl");
+		let mut recorder = Recorder::create(path.as_ref(), &header, &replayer.state).unwrap();
+		let start = Instant::now();
+		let delay = admission::DEFAULT_ADMISSION_DELAY;
+
+		let script = [
+			(
+				0,
+				Input::Create {
+					window: 2,
+					override_redirect: false,
+				},
+			),
+			(1, map(2, Some("terminal"))),
+			(40, Input::Apply),
+			(
+				100,
+				Input::Create {
+					window: 3,
+					override_redirect: false,
+				},
+			),
+			(101, map(3, Some("terminal"))),
+			(
+				102,
+				Input::Create {
+					window: 4,
+					override_redirect: false,
+				},
+			),
+			(103, map(4, None)),
+			(140, Input::Apply),
+			(103 + delay.as_millis() as u64 + 1, Input::Admit),
+			(300, Input::Apply),
+			(
+				400,
+				Input::Action {
+					action: Action::SwapWithPrimary,
+				},
+			),
+			(
+				500,
+				Input::Unmap {
+					window: 3,
+					expected: false,
+				},
+			),
+			(501, Input::Destroy { window: 3 }),
+			(540, Input::Apply),
+		];
+
+		for (millis, input) in script {
+			let now = start + Duration::from_millis(millis);
+
+			replayer.handle(&input, now);
+			let applied = matches!(input, Input::Apply | Input::Action { .. } | Input::Destroy { .. });
+			if applied {
+				replayer.apply();
+			}
+			if let Input::Map { window, .. } = &input {
+				replayer
+					.state
+					.set_focused(Some(ClientWindow::new(*window)), FocusCause::Pointer);
+			}
+
+			recorder.note(input, now);
+			recorder.checkpoint(&replayer.state);
+		}
+	}
+}
@@ -0,0 +1,742 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Prefetching what AquariWM reads about a window when it manages it.
+//!
+//! Managing a window reads a dozen of its properties, its geometry and its attributes. Fetching
+//! each of them when it is first needed costs a round trip apiece, which adds up to a visible
+//! delay before the window appears, and more so when many windows are mapped at once, like when a
+//! session is restored. Instead, every request for a window's [snapshot] is sent before any reply
+//! is waited for, so that [fetching] it takes a single round trip, and the manage path reads from
+//! it.
+//!
+//! A snapshot is kept for each managed window, and is [updated] one property at a time as the
+//! window's `PropertyNotify` events arrive, rather than fetched again. A property which can't be
+//! read is taken to be missing, so that one bad property doesn't lose the rest of the snapshot.
+//!
+//! [snapshot]: ClientSnapshot
+//! [fetching]: ClientSnapshot::fetch
+//! [updated]: ClientSnapshot::set
+
+use x11rb_async::{
+	errors::ConnectionError,
+	protocol::xproto::{self as x11, ConnectionExt as _},
+	rust_connection::RustConnection,
+};
+
+use super::{focus, focus::InputModel, property, util, util::Atoms, visual::Visual};
+use crate::{
+	admission::WindowType,
+	desktop::Desktop,
+	pid::{self, Host, Unverified, VerifiedPid},
+	placement::{Geometry, SizeHints},
+};
+
+/// A property included in [snapshots].
+///
+/// [snapshots]: ClientSnapshot
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Property {
+	/// `WM_CLASS`: the window's instance and class names.
+	Class,
+	/// `_NET_WM_NAME`: the window's UTF-8 title.
+	NetName,
+	/// `WM_NAME`: the window's title, for clients which don't set `_NET_WM_NAME`.
+	Name,
+	/// `_NET_WM_PID`: the process ID of the window's client.
+	Pid,
+	/// `WM_CLIENT_MACHINE`: the machine the window's client runs on.
+	ClientMachine,
+	/// `WM_TRANSIENT_FOR`: the window the window belongs to, like the main window of a dialog.
+	TransientFor,
+	/// `_NET_WM_WINDOW_TYPE`: what kind of window the window is.
+	WindowType,
+	/// `_NET_WM_STATE`: whether the window is fullscreen, sticky, etc.
+	State,
+	/// `_NET_WM_DESKTOP`: the desktop the window is on.
+	Desktop,
+	/// `WM_NORMAL_HINTS`: the window's size hints.
+	NormalHints,
+	/// `WM_HINTS`: the window's urgency and input hints, among others.
+	Hints,
+	/// `WM_PROTOCOLS`: the ICCCM protocols the window's client supports.
+	Protocols,
+	/// `_NET_WM_BYPASS_COMPOSITOR`: whether the window asks not to be composited.
+	BypassCompositor,
+}
+
+impl Property {
+	/// Every property included in snapshots.
+	pub const ALL: [Self; 13] = [
+		Self::Class,
+		Self::NetName,
+		Self::Name,
+		Self::Pid,
+		Self::ClientMachine,
+		Self::TransientFor,
+		Self::WindowType,
+		Self::State,
+		Self::Desktop,
+		Self::NormalHints,
+		Self::Hints,
+		Self::Protocols,
+		Self::BypassCompositor,
+	];
+
+	/// Returns the property with the given `atom` as its name, if it is included in snapshots.
+	pub fn from_atom(atoms: &Atoms, atom: x11::Atom) -> Option<Self> {
+		Self::ALL.into_iter().find(|property| property.request(atoms).0 == atom)
+	}
+
+	/// Returns the name of the property, the type it is read as, and how many 32-bit units of it
+	/// are read, as given to `GetProperty`.
+	pub fn request(self, atoms: &Atoms) -> (x11::Atom, x11::Atom, u32) {
+		use x11::AtomEnum;
+
+		let (name, type_, length) = match self {
+			Self::Class => (AtomEnum::WM_CLASS.into(), AtomEnum::ANY, 2048),
+			Self::NetName => (atoms._NET_WM_NAME, AtomEnum::ANY, 2048),
+			Self::Name => (AtomEnum::WM_NAME.into(), AtomEnum::ANY, 2048),
+			Self::Pid => (atoms._NET_WM_PID, AtomEnum::CARDINAL, 1),
+			Self::ClientMachine => (AtomEnum::WM_CLIENT_MACHINE.into(), AtomEnum::ANY, 2048),
+			Self::TransientFor => (AtomEnum::WM_TRANSIENT_FOR.into(), AtomEnum::WINDOW, 1),
+			Self::WindowType => (atoms._NET_WM_WINDOW_TYPE, AtomEnum::ATOM, 1024),
+			Self::State => (atoms._NET_WM_STATE, AtomEnum::ATOM, 1024),
+			Self::Desktop => (atoms._NET_WM_DESKTOP, AtomEnum::CARDINAL, 1),
+			// The `WM_SIZE_HINTS` type has 18 fields.
+			Self::NormalHints => (AtomEnum::WM_NORMAL_HINTS.into(), AtomEnum::WM_SIZE_HINTS, 18),
+			// The flags, up to the window group.
+			Self::Hints => (AtomEnum::WM_HINTS.into(), AtomEnum::WM_HINTS, 9),
+			Self::Protocols => (atoms.WM_PROTOCOLS, AtomEnum::ATOM, 1024),
+			Self::BypassCompositor => (atoms._NET_WM_BYPASS_COMPOSITOR, AtomEnum::CARDINAL, 1),
+		};
+
+		(name, type_.into(), length)
+	}
+}
+
+/// What AquariWM knows about a window's properties, geometry and attributes.
+#[derive(Debug, Clone, Default)]
+pub struct ClientSnapshot {
+	/// The class in the window's `WM_CLASS` property, if it isn't empty.
+	pub class: Option<String>,
+	/// The window's `_NET_WM_NAME` property, if it isn't empty.
+	pub net_name: Option<String>,
+	/// The window's `WM_NAME` property, if it isn't empty.
+	pub name: Option<String>,
+	/// The process ID in the window's `_NET_WM_PID` property, if it isn't 0.
+	///
+	/// This is only ever acted on once it has been [verified].
+	///
+	/// [verified]: Self::verify_pid
+	advertised_pid: Option<u32>,
+	/// The window's `WM_CLIENT_MACHINE` property, if it isn't empty.
+	pub client_machine: Option<String>,
+	/// The window's process ID, if it was [verified] when the window was managed and neither its
+	/// `_NET_WM_PID` nor its `WM_CLIENT_MACHINE` property has changed since.
+	///
+	/// [verified]: Self::verify_pid
+	pub pid: VerifiedPid,
+	/// The window in the window's `WM_TRANSIENT_FOR` property, if it is neither `None` nor the
+	/// window itself.
+	pub transient_for: Option<x11::Window>,
+	/// The atoms in the window's `_NET_WM_WINDOW_TYPE` property, preferred type first.
+	pub window_types: Vec<x11::Atom>,
+	/// The atoms in the window's `_NET_WM_STATE` property.
+	pub states: Vec<x11::Atom>,
+	/// The window's `_NET_WM_DESKTOP` property, if it has one.
+	pub desktop: Option<u32>,
+	/// The 32-bit items of the window's `WM_NORMAL_HINTS` property.
+	pub normal_hints: Vec<u32>,
+	/// The 32-bit items of the window's `WM_HINTS` property, up to its window group.
+	pub hints: Vec<u32>,
+	/// The atoms in the window's `WM_PROTOCOLS` property.
+	pub protocols: Vec<x11::Atom>,
+	/// The window's `_NET_WM_BYPASS_COMPOSITOR` property, if it has one.
+	pub bypass_compositor: Option<u32>,
+
+	/// The window's geometry, and the root window of its screen, unless it no longer existed.
+	pub geometry: Option<x11::GetGeometryReply>,
+	/// The window's visual, unless it no longer existed or its visual isn't one of the screen's.
+	pub visual: Option<Visual>,
+}
+
+impl ClientSnapshot {
+	/// Fetches a snapshot of the given `window` on the given `screen` in a single round trip.
+	///
+	/// Every request is sent before any reply is waited for. Requests which fail, e.g. because the
+	/// window was destroyed in the meantime, leave their part of the snapshot empty; only the
+	/// connection failing is an error.
+	pub async fn fetch(
+		conn: &RustConnection,
+		atoms: &Atoms,
+		screen: &x11::Screen,
+		window: x11::Window,
+	) -> Result<Self, ConnectionError> {
+		let mut properties = Vec::with_capacity(Property::ALL.len());
+		for property in Property::ALL {
+			let (name, type_, length) = property.request(atoms);
+
+			properties.push((
+				property,
+				conn.get_property(false, window, name, type_, 0, length).await?,
+			));
+		}
+		let geometry = conn.get_geometry(window).await?;
+		let attributes = conn.get_window_attributes(window).await?;
+
+		let mut snapshot = Self::default();
+		for (property, cookie) in properties {
+			if let Ok(reply) = cookie.reply().await {
+				snapshot.set(window, property, reply.format, &reply.value);
+			}
+		}
+		snapshot.geometry = geometry.reply().await.ok();
+		snapshot.visual = attributes
+			.reply()
+			.await
+			.ok()
+			.and_then(|attributes| Visual::find(screen, attributes.visual, attributes.colormap));
+
+		Ok(snapshot)
+	}
+
+	/// Fetches the given `property` of the given `window` again, updating it in the snapshot.
+	///
+	/// If the property can't be read, it is taken to be missing.
+	pub async fn refresh(
+		&mut self,
+		conn: &RustConnection,
+		atoms: &Atoms,
+		window: x11::Window,
+		property: Property,
+	) -> Result<(), ConnectionError> {
+		let (name, type_, length) = property.request(atoms);
+		let cookie = conn.get_property(false, window, name, type_, 0, length).await?;
+
+		match cookie.reply().await {
+			Ok(reply) => self.set(window, property, reply.format, &reply.value),
+			Err(_) => self.clear(window, property),
+		}
+
+		Ok(())
+	}
+
+	/// Decodes the given `property` of the given `window` from its `format` and `value`, as read
+	/// with its [request], replacing it in the snapshot.
+	///
+	/// [request]: Property::request
+	pub fn set(&mut self, window: x11::Window, property: Property, format: u8, value: &[u8]) {
+		let items = || property::decode32(format, value);
+		let first = || items().first().copied();
+
+		match property {
+			// `WM_CLASS` is the instance name followed by the class name.
+			Property::Class => {
+				self.class = property::decode_strings(format, value)
+					.into_iter()
+					.nth(1)
+					.filter(|class| !class.is_empty());
+			},
+			Property::NetName => self.net_name = property::decode_string(format, value),
+			Property::Name => self.name = property::decode_string(format, value),
+
+			// The process ID has to be verified again if either property changes.
+			Property::Pid => {
+				self.advertised_pid = first().filter(|&pid| pid != 0);
+				self.pid = VerifiedPid::default();
+			},
+			Property::ClientMachine => {
+				self.client_machine = property::decode_string(format, value);
+				self.pid = VerifiedPid::default();
+			},
+			// A window of 0 is `None`.
+			Property::TransientFor => {
+				self.transient_for = first().filter(|&parent| parent != 0 && parent != window);
+			},
+			Property::WindowType => self.window_types = items(),
+			Property::State => self.states = items(),
+			Property::Desktop => self.desktop = first(),
+			Property::NormalHints => self.normal_hints = items(),
+			Property::Hints => self.hints = items(),
+			Property::Protocols => self.protocols = items(),
+			Property::BypassCompositor => self.bypass_compositor = first(),
+		}
+	}
+
+	/// Removes the given `property` of the given `window` from the snapshot, as if it had been
+	/// deleted.
+	pub fn clear(&mut self, window: x11::Window, property: Property) {
+		self.set(window, property, 0, &[]);
+	}
+
+	/// [Verifies] the window's advertised process ID on the given `host`, keeping the result as
+	/// the window's [`pid`].
+	///
+	/// `managed` is when the window was managed, in clock ticks since boot, and `start_time`
+	/// returns when a process started; see [`pid::verify`].
+	///
+	/// [Verifies]: pid
+	/// [`pid`]: Self::pid
+	pub fn verify_pid(
+		&mut self,
+		host: &Host,
+		managed: Option<u64>,
+		start_time: impl Fn(u32) -> Option<u64>,
+	) -> Result<u32, Unverified> {
+		let verified = pid::verify(
+			self.advertised_pid,
+			self.client_machine.as_deref(),
+			host,
+			managed,
+			start_time,
+		);
+		self.pid = VerifiedPid::from(&verified);
+
+		verified
+	}
+
+	/// Returns the window's title from its `_NET_WM_NAME` property or, failing that, its `WM_NAME`
+	/// property.
+	pub fn title(&self) -> Option<&str> {
+		self.net_name.as_deref().or(self.name.as_deref())
+	}
+
+	/// Returns what the window's `_NET_WM_WINDOW_TYPE` property says it is, going by its preferred
+	/// (first) type.
+	pub fn window_type(&self, atoms: &Atoms) -> WindowType {
+		match self.window_types.first() {
+			Some(&window_type) if window_type == atoms._NET_WM_WINDOW_TYPE_NORMAL => WindowType::Normal,
+			Some(_) => WindowType::Other,
+			None => WindowType::Absent,
+		}
+	}
+
+	/// Returns whether the window's `_NET_WM_STATE` property says it is fullscreen.
+	pub fn is_fullscreen(&self, atoms: &Atoms) -> bool {
+		self.states.contains(&atoms._NET_WM_STATE_FULLSCREEN)
+	}
+
+	/// Returns whether the window's `_NET_WM_STATE` property says it is sticky.
+	pub fn is_sticky(&self, atoms: &Atoms) -> bool {
+		self.states.contains(&atoms._NET_WM_STATE_STICKY)
+	}
+
+	/// Returns the desktop the window's `_NET_WM_DESKTOP` property says it is on, if any.
+	pub fn desktop(&self) -> Option<Desktop> {
+		self.desktop.map(Desktop::from_ewmh)
+	}
+
+	/// Returns whether the window has the urgency hint set in its `WM_HINTS` property.
+	pub fn is_urgent(&self) -> bool {
+		const URGENCY_HINT: u32 = 1 << 8;
+
+		self.hints.first().is_some_and(|flags| flags & URGENCY_HINT != 0)
+	}
+
+	/// Returns the [group leader] named in the window's `WM_HINTS` property, if any.
+	///
+	/// [group leader]: crate::window_groups
+	pub fn window_group(&self) -> Option<x11::Window> {
+		const WINDOW_GROUP_HINT: u32 = 1 << 6;
+
+		// The window group is the last of the nine items.
+		match self.hints[..] {
+			[flags, _, _, _, _, _, _, _, leader] if flags & WINDOW_GROUP_HINT != 0 && leader != 0 => Some(leader),
+			_ => None,
+		}
+	}
+
+	/// Returns the [input model] of the window's client from its `WM_HINTS` and `WM_PROTOCOLS`
+	/// properties.
+	///
+	/// [input model]: InputModel
+	pub fn input_model(&self, atoms: &Atoms) -> InputModel {
+		InputModel::new(
+			focus::input_hint(&self.hints),
+			self.protocols.contains(&atoms.WM_TAKE_FOCUS),
+		)
+	}
+
+	/// Returns the window's size hints from its `WM_NORMAL_HINTS` property.
+	pub fn size_hints(&self) -> SizeHints {
+		util::size_hints(&self.normal_hints)
+	}
+
+	/// Returns the window's geometry as it was when the snapshot was fetched, if the window still
+	/// existed then.
+	pub fn fetched_geometry(&self) -> Option<Geometry> {
+		self.geometry.as_ref().map(|geometry| Geometry {
+			x: geometry.x as i32,
+			y: geometry.y as i32,
+
+			width: geometry.width as u32,
+			height: geometry.height as u32,
+
+			border_width: geometry.border_width as u32,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use super::*;
+	use crate::display_server::x11::property::{encode32, encode_strings, FORMAT_32, FORMAT_8};
+
+	const WINDOW: x11::Window = 0x40_0001;
+
+	/// A property as an X server would store it: its type, its format and its value.
+	type Stored = (x11::Atom, u8, Vec<u8>);
+
+	/// A window's properties, by name.
+	#[derive(Debug, Clone, Default)]
+	struct Properties(HashMap<x11::Atom, Stored>);
+
+	impl Properties {
+		/// Answers a `GetProperty` request as the X server would.
+		///
+		/// A property of another type than the one requested is returned with its format, but
+		/// without its value, and at most `length` 32-bit units of a value are returned.
+		fn get(&self, property: x11::Atom, type_: x11::Atom, length: u32) -> (u8, Vec<u8>) {
+			match self.0.get(&property) {
+				None => (0, Vec::new()),
+
+				Some((actual, format, _)) if type_ != u32::from(x11::AtomEnum::ANY) && type_ != *actual => {
+					(*format, Vec::new())
+				},
+				Some((_, format, value)) => {
+					let end = value.len().min(length as usize * 4);
+
+					(*format, value[..end].to_vec())
+				},
+			}
+		}
+	}
+
+	/// What the per-property fetches which the manage path made before snapshots existed decided
+	/// about a window, each from its own `GetProperty` request.
+	#[derive(Debug, PartialEq, Eq)]
+	struct Decisions {
+		class: Option<String>,
+		title: Option<String>,
+		pid: Option<u32>,
+		transient_for: Option<x11::Window>,
+		window_type: WindowType,
+		fullscreen: bool,
+		sticky: bool,
+		desktop: Option<Desktop>,
+		urgent: bool,
+		input_model: InputModel,
+		size_hints: SizeHints,
+		bypass_compositor: Option<u32>,
+	}
+
+	impl Decisions {
+		/// Decides from a single `GetProperty` request per decision, as the manage path used to.
+		fn per_property(atoms: &Atoms, properties: &Properties) -> Self {
+			use x11::AtomEnum;
+
+			let cardinals = |property: x11::Atom, type_: AtomEnum, length| {
+				let (format, value) = properties.get(property, type_.into(), length);
+				property::decode32(format, &value)
+			};
+			let atoms_of = |property| cardinals(property, AtomEnum::ATOM, 1024);
+			let string = |property: x11::Atom| {
+				let (format, value) = properties.get(property, AtomEnum::ANY.into(), 2048);
+				property::decode_string(format, &value)
+			};
+
+			let (format, value) = properties.get(AtomEnum::WM_CLASS.into(), AtomEnum::ANY.into(), 2048);
+			let class = property::decode_strings(format, &value)
+				.into_iter()
+				.nth(1)
+				.filter(|class| !class.is_empty());
+			let states = atoms_of(atoms._NET_WM_STATE);
+			let hints = cardinals(AtomEnum::WM_HINTS.into(), AtomEnum::WM_HINTS, 2);
+
+			Self {
+				class,
+				title: string(atoms._NET_WM_NAME).or_else(|| string(AtomEnum::WM_NAME.into())),
+				pid: cardinals(atoms._NET_WM_PID, AtomEnum::CARDINAL, 1)
+					.first()
+					.copied()
+					.filter(|&pid| pid != 0),
+				transient_for: cardinals(AtomEnum::WM_TRANSIENT_FOR.into(), AtomEnum::WINDOW, 1)
+					.first()
+					.copied()
+					.filter(|&parent| parent != 0 && parent != WINDOW),
+				window_type: match atoms_of(atoms._NET_WM_WINDOW_TYPE).first() {
+					Some(&window_type) if window_type == atoms._NET_WM_WINDOW_TYPE_NORMAL => WindowType::Normal,
+					Some(_) => WindowType::Other,
+					None => WindowType::Absent,
+				},
+				fullscreen: states.contains(&atoms._NET_WM_STATE_FULLSCREEN),
+				sticky: states.contains(&atoms._NET_WM_STATE_STICKY),
+				desktop: cardinals(atoms._NET_WM_DESKTOP, AtomEnum::CARDINAL, 1)
+					.first()
+					.map(|&desktop| Desktop::from_ewmh(desktop)),
+				urgent: cardinals(AtomEnum::WM_HINTS.into(), AtomEnum::WM_HINTS, 1)
+					.first()
+					.is_some_and(|flags| flags & (1 << 8) != 0),
+				input_model: InputModel::new(
+					focus::input_hint(&hints),
+					atoms_of(atoms.WM_PROTOCOLS).contains(&atoms.WM_TAKE_FOCUS),
+				),
+				size_hints: util::size_hints(&cardinals(
+					AtomEnum::WM_NORMAL_HINTS.into(),
+					AtomEnum::WM_SIZE_HINTS,
+					18,
+				)),
+				bypass_compositor: cardinals(atoms._NET_WM_BYPASS_COMPOSITOR, AtomEnum::CARDINAL, 1)
+					.first()
+					.copied(),
+			}
+		}
+
+		/// Decides from a snapshot.
+		fn snapshot(atoms: &Atoms, snapshot: &ClientSnapshot) -> Self {
+			Self {
+				class: snapshot.class.clone(),
+				title: snapshot.title().map(str::to_owned),
+				pid: snapshot.advertised_pid,
+				transient_for: snapshot.transient_for,
+				window_type: snapshot.window_type(atoms),
+				fullscreen: snapshot.is_fullscreen(atoms),
+				sticky: snapshot.is_sticky(atoms),
+				desktop: snapshot.desktop(),
+				urgent: snapshot.is_urgent(),
+				input_model: snapshot.input_model(atoms),
+				size_hints: snapshot.size_hints(),
+				bypass_compositor: snapshot.bypass_compositor,
+			}
+		}
+	}
+
+	/// Builds a snapshot from the `properties` as [`ClientSnapshot::fetch`] does from the X
+	/// server's replies.
+	fn snapshot(atoms: &Atoms, properties: &Properties) -> ClientSnapshot {
+		let mut snapshot = ClientSnapshot::default();
+
+		for property in Property::ALL {
+			let (name, type_, length) = property.request(atoms);
+			let (format, value) = properties.get(name, type_, length);
+
+			snapshot.set(WINDOW, property, format, &value);
+		}
+
+		snapshot
+	}
+
+	/// Returns the ways each property can be set, including with the wrong type or format, cut
+	/// short, or longer than is read.
+	fn variants(atoms: &Atoms) -> Vec<(x11::Atom, Vec<Stored>)> {
+		use x11::AtomEnum;
+
+		let atom = |atom: AtomEnum| u32::from(atom);
+		let [atom_type, cardinal, string, window, wm_hints, wm_size_hints] = [
+			AtomEnum::ATOM,
+			AtomEnum::CARDINAL,
+			AtomEnum::STRING,
+			AtomEnum::WINDOW,
+			AtomEnum::WM_HINTS,
+			AtomEnum::WM_SIZE_HINTS,
+		]
+		.map(u32::from);
+		let strings = |type_, strings: &[&str]| (type_, FORMAT_8, encode_strings(strings.iter().copied()));
+		let items = |type_, items: &[u32]| (type_, FORMAT_32, encode32(items));
+		let fullscreen = atoms._NET_WM_STATE_FULLSCREEN;
+		let sticky = atoms._NET_WM_STATE_STICKY;
+
+		vec![
+			(
+				atom(AtomEnum::WM_CLASS),
+				vec![
+					strings(string, &["firefox", "Firefox"]),
+					strings(string, &["firefox"]),
+					strings(string, &["firefox", ""]),
+					strings(atoms.UTF8_STRING, &["kitty", "kitty", "extra"]),
+					items(string, &[0x4141_4141]),
+				],
+			),
+			(
+				atoms._NET_WM_NAME,
+				vec![
+					strings(atoms.UTF8_STRING, &["Title"]),
+					strings(atoms.UTF8_STRING, &[""]),
+					items(atoms.UTF8_STRING, &[1]),
+				],
+			),
+			(
+				atom(AtomEnum::WM_NAME),
+				vec![strings(string, &["Legacy"]), strings(string, &["caf\u{e9}"])],
+			),
+			(
+				atoms._NET_WM_PID,
+				vec![
+					items(cardinal, &[4242]),
+					items(cardinal, &[0]),
+					items(cardinal, &[4242, 7]),
+					items(window, &[4242]),
+				],
+			),
+			(
+				atom(AtomEnum::WM_TRANSIENT_FOR),
+				vec![
+					items(window, &[0x20_0001]),
+					items(window, &[0]),
+					items(window, &[WINDOW]),
+					items(cardinal, &[0x20_0001]),
+					(window, FORMAT_8, vec![1, 2, 3, 4]),
+				],
+			),
+			(
+				atoms._NET_WM_WINDOW_TYPE,
+				vec![
+					items(atom_type, &[atoms._NET_WM_WINDOW_TYPE_NORMAL]),
+					items(atom_type, &[atoms._NET_WM_STATE, atoms._NET_WM_WINDOW_TYPE_NORMAL]),
+					items(atom_type, &[]),
+					items(cardinal, &[atoms._NET_WM_WINDOW_TYPE_NORMAL]),
+				],
+			),
+			(
+				atoms._NET_WM_STATE,
+				vec![
+					items(atom_type, &[fullscreen]),
+					items(atom_type, &[sticky, fullscreen]),
+					items(atom_type, &[atoms._NET_WM_STATE_HIDDEN]),
+					(atom_type, FORMAT_32, encode32(&[sticky, fullscreen])[..6].to_vec()),
+				],
+			),
+			(
+				atoms._NET_WM_DESKTOP,
+				vec![
+					items(cardinal, &[2]),
+					items(cardinal, &[crate::desktop::ALL_DESKTOPS]),
+					items(cardinal, &[]),
+				],
+			),
+			(
+				atom(AtomEnum::WM_NORMAL_HINTS),
+				vec![
+					items(wm_size_hints, &[(1 << 4) | (1 << 6), 0, 0, 0, 0, 200, 100, 0, 0, 8, 16]),
+					items(
+						wm_size_hints,
+						&[1 << 6, 0, 0, 0, 0, 0, 0, 0, 0, 10, 20, 0, 0, 0, 0, 0, 0, 0, 99],
+					),
+					items(wm_size_hints, &[1 << 6, 0, 0]),
+					items(cardinal, &[1 << 6, 0, 0, 0, 0, 0, 0, 0, 0, 10, 20]),
+				],
+			),
+			(
+				atom(AtomEnum::WM_HINTS),
+				vec![
+					items(wm_hints, &[1 | (1 << 8), 0]),
+					items(wm_hints, &[1, 1, 0, 0]),
+					items(wm_hints, &[1 << 8]),
+					items(cardinal, &[1 << 8, 1]),
+					items(wm_hints, &[1 | (1 << 6), 1, 0, 0, 0, 0, 0, 0, 0x20_0001]),
+				],
+			),
+			(
+				atoms.WM_PROTOCOLS,
+				vec![
+					items(atom_type, &[atoms.WM_TAKE_FOCUS]),
+					items(atom_type, &[atoms.WM_STATE]),
+				],
+			),
+			(
+				atoms._NET_WM_BYPASS_COMPOSITOR,
+				vec![items(cardinal, &[1]), items(cardinal, &[2, 1]), items(atom_type, &[1])],
+			),
+		]
+	}
+
+	/// Checks that snapshots decide the same as the per-property fetches they replaced, for
+	/// windows with no properties, with each way of setting each property on its own, and with
+	/// combinations of them.
+	#[test]
+	fn compatibility() {
+		let atoms = Atoms::synthetic();
+		let variants = variants(&atoms);
+
+		let mut cases = vec![Properties::default()];
+		for (property, values) in &variants {
+			for value in values {
+				cases.push(Properties(HashMap::from([(*property, value.clone())])));
+			}
+		}
+		// Every property at once, cycling through their variants at different rates.
+		for case in 0..16 {
+			let properties = variants
+				.iter()
+				.enumerate()
+				.map(|(index, (property, values))| (*property, values[(case * (index + 1)) % values.len()].clone()))
+				.collect();
+			cases.push(Properties(properties));
+		}
+
+		for properties in cases {
+			let snapshot = snapshot(&atoms, &properties);
+
+			assert_eq!(
+				Decisions::snapshot(&atoms, &snapshot),
+				Decisions::per_property(&atoms, &properties),
+				"{properties:?}"
+			);
+		}
+	}
+
+	/// Tests that properties are updated and cleared one at a time.
+	#[test]
+	fn incremental() {
+		let atoms = Atoms::synthetic();
+		let mut snapshot = ClientSnapshot::default();
+
+		snapshot.set(WINDOW, Property::NetName, FORMAT_8, b"Old");
+		snapshot.set(WINDOW, Property::Name, FORMAT_8, b"Legacy");
+		snapshot.set(
+			WINDOW,
+			Property::State,
+			FORMAT_32,
+			&encode32(&[atoms._NET_WM_STATE_FULLSCREEN]),
+		);
+		assert_eq!(snapshot.title(), Some("Old"));
+		assert!(snapshot.is_fullscreen(&atoms));
+
+		snapshot.clear(WINDOW, Property::NetName);
+		assert_eq!(snapshot.title(), Some("Legacy"));
+		assert!(snapshot.is_fullscreen(&atoms));
+
+		// A verified process ID is forgotten when either property it was verified from changes.
+		let host = Host::new("aquarium", "");
+		snapshot.set(WINDOW, Property::Pid, FORMAT_32, &encode32(&[4242]));
+		snapshot.set(WINDOW, Property::ClientMachine, FORMAT_8, b"aquarium");
+		assert_eq!(snapshot.pid.get(), None);
+		assert_eq!(snapshot.verify_pid(&host, Some(100), |_| Some(50)), Ok(4242));
+		assert_eq!(snapshot.pid.get(), Some(4242));
+		snapshot.set(WINDOW, Property::ClientMachine, FORMAT_8, b"tank");
+		assert_eq!(snapshot.pid.get(), None);
+
+		// The window group is only read if its flag is set.
+		let hints = [1 << 6, 0, 0, 0, 0, 0, 0, 0, 0x20_0001];
+		snapshot.set(WINDOW, Property::Hints, FORMAT_32, &encode32(&hints));
+		assert_eq!(snapshot.window_group(), Some(0x20_0001));
+		snapshot.set(
+			WINDOW,
+			Property::Hints,
+			FORMAT_32,
+			&encode32(&[0, 0, 0, 0, 0, 0, 0, 0, 0x20_0001]),
+		);
+		assert_eq!(snapshot.window_group(), None);
+
+		assert_eq!(Property::from_atom(&atoms, atoms._NET_WM_STATE), Some(Property::State));
+		assert_eq!(
+			Property::from_atom(&atoms, x11::AtomEnum::WM_HINTS.into()),
+			Some(Property::Hints)
+		);
+		assert_eq!(Property::from_atom(&atoms, atoms._AQUARIWM_STATUS), None);
+	}
+}
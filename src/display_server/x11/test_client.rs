@@ -0,0 +1,284 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A minimal X client for AquariWM to manage while testing, so that testing doesn't depend on a
+//! terminal emulator being installed.
+//!
+//! The client is run as a separate process with the hidden `test-client` subcommand - see
+//! [`spawn`].
+
+use std::{env, future, io, num::ParseIntError, process, str::FromStr};
+
+use thiserror::Error;
+use tracing::{event, Level};
+use x11rb_async::{
+	connection::Connection,
+	protocol::{
+		xproto::{self as x11, ConnectionExt},
+		Event,
+	},
+	rust_connection::RustConnection,
+};
+
+use super::{
+	util::{self, ConnectionExt as _},
+	Result,
+};
+use crate::pid::Host;
+
+/// The class given to test clients by default.
+pub const DEFAULT_CLASS: &str = "aquariwm-test";
+
+/// The size of a test client's window before it is managed.
+const DEFAULT_SIZE: (u16, u16) = (320, 240);
+
+/// A size hint set in a test client's `WM_NORMAL_HINTS` property.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Hint {
+	/// The minimum width and height of the window.
+	MinSize(u32, u32),
+	/// The maximum width and height of the window.
+	MaxSize(u32, u32),
+}
+
+/// Who a test client's window says it belongs to.
+///
+/// By default, the window advertises the client's own process ID and the local host, as well
+/// behaved clients do; either can be overridden to test that false claims aren't trusted.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct Identity {
+	/// The process ID advertised in the window's `_NET_WM_PID` property instead of the client's
+	/// own.
+	pub pid: Option<u32>,
+	/// The machine named in the window's `WM_CLIENT_MACHINE` property instead of the local host.
+	pub machine: Option<String>,
+}
+
+/// An error returned when parsing a [`Hint`] fails.
+#[derive(Debug, Error)]
+pub enum ParseHintError {
+	/// The hint was not given as `NAME=WIDTHxHEIGHT`.
+	#[error("expected `min=WIDTHxHEIGHT` or `max=WIDTHxHEIGHT`")]
+	Format,
+	/// The hint's name was neither `min` nor `max`.
+	#[error("unknown hint `{0}`; expected `min` or `max`")]
+	UnknownHint(String),
+	/// The hint's width or height was not a number.
+	#[error(transparent)]
+	Size(#[from] ParseIntError),
+}
+
+impl FromStr for Hint {
+	type Err = ParseHintError;
+
+	/// Parses `min=WIDTHxHEIGHT` or `max=WIDTHxHEIGHT`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (name, size) = s.split_once('=').ok_or(ParseHintError::Format)?;
+		let (width, height) = size.split_once('x').ok_or(ParseHintError::Format)?;
+		let (width, height) = (width.trim().parse()?, height.trim().parse()?);
+
+		match name.trim() {
+			"min" => Ok(Self::MinSize(width, height)),
+			"max" => Ok(Self::MaxSize(width, height)),
+
+			name => Err(ParseHintError::UnknownHint(name.to_owned())),
+		}
+	}
+}
+
+/// Spawns a test client with the given `class` as a separate process, connecting to the display
+/// given by the `DISPLAY` env variable.
+pub fn spawn(class: &str) -> io::Result<process::Child> {
+	process::Command::new(env::current_exe()?)
+		.args(["test-client", "--class", class])
+		.spawn()
+}
+
+/// Runs a test client with the given `class`, size `hints` and [identity] until it is killed or its
+/// connection to the X server is closed.
+///
+/// The client creates a top-level normal window with a solid background, which is redrawn by the
+/// X server whenever it is exposed, and logs the geometry the window is configured with. It answers
+/// `_NET_WM_PING` messages unless it `hang`s, in which case it stops handling events once the
+/// window is mapped.
+///
+/// If it is a `group`, the client creates a second such window, and both name the first as their
+/// group leader in their `WM_HINTS` properties. If `override_redirect` is set, its windows are
+/// created override-redirect, like menus and tooltips, so that they are never managed.
+///
+/// [identity]: Identity
+pub async fn run(
+	class: &str,
+	hints: &[Hint],
+	identity: &Identity,
+	hang: bool,
+	group: bool,
+	override_redirect: bool,
+) -> Result<()> {
+	const COPY_FROM_PARENT: u32 = 0;
+
+	// Connect to the X server on the display specified by the `DISPLAY` env variable.
+	let (conn, screen_num, drive) = RustConnection::connect(None).await?;
+
+	// Spawn a task that reads from the connection.
+	tokio::spawn(async move {
+		if let Err(error) = drive.await {
+			event!(Level::ERROR, "Error while driving the X11 connection: {}", error);
+		}
+	});
+
+	let screen = &conn.setup().roots[screen_num];
+	let (root, background) = (screen.root, screen.white_pixel);
+
+	let atoms = util::Atoms::intern(&conn).await?;
+	let pid = identity.pid.unwrap_or_else(process::id);
+	let machine = identity.machine.clone().unwrap_or_else(|| Host::local().to_string());
+
+	let mut windows = Vec::new();
+	for _ in 0..if group { 2 } else { 1 } {
+		let window = conn.generate_id().await?;
+		conn.checked(
+			conn.create_window(
+				COPY_FROM_PARENT as u8,
+				window,
+				root,
+				0,
+				0,
+				DEFAULT_SIZE.0,
+				DEFAULT_SIZE.1,
+				0,
+				x11::WindowClass::INPUT_OUTPUT,
+				COPY_FROM_PARENT,
+				&x11::CreateWindowAux::new()
+					.background_pixel(background)
+					.override_redirect(u32::from(override_redirect))
+					.event_mask(x11::EventMask::EXPOSURE | x11::EventMask::STRUCTURE_NOTIFY),
+			),
+		)
+		.await?;
+
+		// `WM_CLASS` is the instance name followed by the class name, each null-terminated.
+		let wm_class = format!("{class}\0{class}\0");
+		for (property, value) in [(x11::AtomEnum::WM_CLASS, &*wm_class), (x11::AtomEnum::WM_NAME, class)] {
+			conn.checked(conn.set_property_string(window, property, x11::AtomEnum::STRING, value))
+				.await?;
+		}
+
+		// The window is declared a normal window, so that it is tiled straight away.
+		conn.checked(conn.set_property_atoms(window, atoms._NET_WM_WINDOW_TYPE, &[atoms._NET_WM_WINDOW_TYPE_NORMAL]))
+			.await?;
+		conn.checked(conn.set_property_atoms(window, atoms.WM_PROTOCOLS, &[atoms._NET_WM_PING]))
+			.await?;
+
+		conn.checked(conn.set_property_cardinals(window, atoms._NET_WM_PID, x11::AtomEnum::CARDINAL, &[pid]))
+			.await?;
+		conn.checked(conn.set_property_string(
+			window,
+			x11::AtomEnum::WM_CLIENT_MACHINE,
+			x11::AtomEnum::STRING,
+			&machine,
+		))
+		.await?;
+
+		if !hints.is_empty() {
+			conn.checked(conn.set_property_cardinals(
+				window,
+				x11::AtomEnum::WM_NORMAL_HINTS,
+				x11::AtomEnum::WM_SIZE_HINTS,
+				&size_hints(hints),
+			))
+			.await?;
+		}
+
+		// Both windows of a group name the first as their group leader.
+		if group {
+			let leader = windows.first().copied().unwrap_or(window);
+
+			conn.checked(conn.set_property_cardinals(
+				window,
+				x11::AtomEnum::WM_HINTS,
+				x11::AtomEnum::WM_HINTS,
+				&window_group(leader),
+			))
+			.await?;
+		}
+
+		windows.push(window);
+	}
+
+	for &window in &windows {
+		conn.checked(conn.map_window(window)).await?;
+		event!(Level::INFO, "Mapped test client window {window} with class {class:?}");
+	}
+
+	if hang {
+		event!(Level::INFO, "Hanging");
+		conn.flush().await?;
+
+		return future::pending().await;
+	}
+
+	loop {
+		match conn.wait_for_event().await? {
+			Event::ConfigureNotify(notify) if windows.contains(&notify.window) => event!(
+				Level::DEBUG,
+				"Configured to {}x{} at ({}, {})",
+				notify.width,
+				notify.height,
+				notify.x,
+				notify.y,
+			),
+
+			// Pings are answered by sending them back to the root window.
+			Event::ClientMessage(mut message)
+				if message.type_ == atoms.WM_PROTOCOLS && message.data.as_data32()[0] == atoms._NET_WM_PING =>
+			{
+				message.window = root;
+
+				let mask = x11::EventMask::SUBSTRUCTURE_NOTIFY | x11::EventMask::SUBSTRUCTURE_REDIRECT;
+				conn.send_event(false, root, mask, message).await?;
+				conn.flush().await?;
+			},
+
+			// The X server fills exposed areas with the background, so there is nothing to draw.
+			Event::Expose(_) => (),
+
+			_ => (),
+		}
+	}
+}
+
+/// Returns the values of a `WM_HINTS` property naming the given `leader` as the window group.
+const fn window_group(leader: x11::Window) -> [u32; 9] {
+	const WINDOW_GROUP_HINT: u32 = 1 << 6;
+
+	// The flags, followed by the input hint, initial state, icon pixmap, window and position, icon
+	// mask, and finally the window group.
+	[WINDOW_GROUP_HINT, 0, 0, 0, 0, 0, 0, 0, leader]
+}
+
+/// Returns the values of a `WM_NORMAL_HINTS` property (of the `WM_SIZE_HINTS` type) with the given
+/// `hints`.
+fn size_hints(hints: &[Hint]) -> [u32; 18] {
+	const P_MIN_SIZE: u32 = 1 << 4;
+	const P_MAX_SIZE: u32 = 1 << 5;
+
+	// The flags, followed by the (obsolete) position and size, then the minimum and maximum sizes.
+	let mut values = [0; 18];
+
+	for hint in hints {
+		match *hint {
+			Hint::MinSize(width, height) => {
+				values[0] |= P_MIN_SIZE;
+				values[5..7].copy_from_slice(&[width, height]);
+			},
+			Hint::MaxSize(width, height) => {
+				values[0] |= P_MAX_SIZE;
+				values[7..9].copy_from_slice(&[width, height]);
+			},
+		}
+	}
+
+	values
+}
@@ -2,88 +2,2651 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::{process, sync::mpsc, time::Duration};
+use std::{
+	fs,
+	path::Path,
+	process,
+	str::FromStr,
+	time::{Duration, Instant},
+};
 
-use winit::{
-	event::{Event as WinitEvent, WindowEvent as WinitWindowEvent},
-	event_loop::EventLoopBuilder as WinitEventLoopBuilder,
-	platform::x11::EventLoopBuilderExtX11,
-	window::WindowBuilder as WinitWindowBuilder,
+#[cfg(feature = "testing-gui")]
+use {
+	std::sync::mpsc,
+	winit::{
+		event::{Event as WinitEvent, WindowEvent as WinitWindowEvent},
+		event_loop::EventLoopBuilder as WinitEventLoopBuilder,
+		platform::x11::EventLoopBuilderExtX11,
+		window::WindowBuilder as WinitWindowBuilder,
+	},
 };
 
 use crate::display_server::x11::*;
 
-pub struct Xephyr(pub process::Child);
+/// The environment variable used to choose the testing [`Mode`], e.g. for CI.
+///
+/// See [`Mode::from_str`] for its accepted values.
+pub const BACKEND_VAR: &str = "AQUARIWM_TEST_BACKEND";
+
+/// How long to wait for the nested X server to accept connections.
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often to check whether the nested X server accepts connections.
+const READY_POLL: Duration = Duration::from_millis(50);
+
+/// The screen dimensions used by [`Mode::Headless`] and by default for [`Mode::Standalone`].
+const DEFAULT_SCREEN: (u16, u16) = (1280, 720);
+
+/// How the nested X server used for testing is displayed.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Mode {
+	/// Xephyr is parented to a resizable `winit` window.
+	#[cfg(feature = "testing-gui")]
+	Parented,
+	/// Xephyr runs in its own window of the given width and height.
+	Standalone(u16, u16),
+	/// The X server isn't displayed at all.
+	///
+	/// Xvfb is used if it is available; otherwise, Xephyr is run [standalone], which requires an
+	/// X server to display it.
+	///
+	/// [standalone]: Mode::Standalone
+	Headless,
+}
+
+/// An error parsing a [`Mode`].
+#[derive(Debug, Error)]
+#[error("Unknown testing backend {0:?}; expected `parented`, `standalone[:WIDTHxHEIGHT]` or `headless`")]
+pub struct ParseModeError(String);
+
+impl FromStr for Mode {
+	type Err = ParseModeError;
+
+	/// Parses `parented`, `standalone`, `standalone:WIDTHxHEIGHT` (e.g. `standalone:800x600`) or
+	/// `headless`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let error = || ParseModeError(s.to_owned());
+
+		match s.trim().split_once(':') {
+			#[cfg(feature = "testing-gui")]
+			None if s.trim() == "parented" => Ok(Self::Parented),
+			None if s.trim() == "headless" => Ok(Self::Headless),
+			None if s.trim() == "standalone" => Ok(Self::Standalone(DEFAULT_SCREEN.0, DEFAULT_SCREEN.1)),
+
+			Some(("standalone", dimensions)) => {
+				let (width, height) = dimensions.split_once('x').ok_or_else(error)?;
+
+				Ok(Self::Standalone(
+					width.parse().map_err(|_| error())?,
+					height.parse().map_err(|_| error())?,
+				))
+			},
+
+			_ => Err(error()),
+		}
+	}
+}
+
+impl Mode {
+	/// Returns the mode given by the [`AQUARIWM_TEST_BACKEND`] environment variable.
+	///
+	/// If it is not set, `Parented` is used if the `testing-gui` feature is enabled and there is
+	/// an X server to display it, and [`Headless`] is used otherwise.
+	///
+	/// [`AQUARIWM_TEST_BACKEND`]: BACKEND_VAR
+	/// [`Headless`]: Mode::Headless
+	pub fn from_env() -> Result<Self, ParseModeError> {
+		match env::var(BACKEND_VAR) {
+			Ok(mode) => mode.parse(),
+
+			#[cfg(feature = "testing-gui")]
+			Err(_) if env::var_os("DISPLAY").is_some() => Ok(Self::Parented),
+			Err(_) => Ok(Self::Headless),
+		}
+	}
+}
+
+/// A nested X server for testing AquariWM in, killed when dropped.
+///
+/// Despite the name, this may be Xvfb when running [headless].
+///
+/// [headless]: Mode::Headless
+pub struct Xephyr {
+	/// The nested X server's process.
+	pub process: process::Child,
+	/// The nested X server's display, like `:1`.
+	display: String,
+}
 
 impl Drop for Xephyr {
 	fn drop(&mut self) {
-		let Self(child) = self;
-
-		child.kill().expect("Failed to kill Xephyr");
+		self.process.kill().expect("Failed to kill Xephyr");
 	}
 }
 
 impl Xephyr {
-	pub fn spawn() -> io::Result<Self> {
-		const TESTING_DISPLAY: &str = ":1";
+	/// Spawns a nested X server in the given `mode` and sets the `DISPLAY` env variable to it once
+	/// it accepts connections.
+	///
+	/// The env variable is inherited by the programs launched while testing; AquariWM itself
+	/// connects to the [display] explicitly.
+	///
+	/// [display]: Self::display
+	pub fn spawn(mode: Mode) -> io::Result<Self> {
+		Self::spawn_on(mode, free_display())
+	}
+
+	/// Spawns a nested X server in the given `mode` on the given display `number`, as with
+	/// [`spawn`].
+	///
+	/// This is used to bring back an X server which was killed; the socket it left behind is
+	/// removed, so the display must not be in use.
+	///
+	/// [`spawn`]: Self::spawn
+	pub fn spawn_on(mode: Mode, number: u32) -> io::Result<Self> {
+		let display = format!(":{number}");
+
+		// A killed X server doesn't clean up its socket.
+		match fs::remove_file(format!("/tmp/.X11-unix/X{number}")) {
+			Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+			_ => (),
+		}
+
+		let (mut command, name) = match mode {
+			#[cfg(feature = "testing-gui")]
+			Mode::Parented => {
+				let mut command = process::Command::new("Xephyr");
+				// Run `Xephyr` in a `winit` window.
+				command
+					.arg("-resizeable")
+					.args(["-parent", &spawn_window().to_string()]);
 
-		let (transmitter, receiver) = mpsc::channel();
+				(command, "Xephyr")
+			},
+
+			Mode::Standalone(width, height) => (xephyr(width, height), "Xephyr"),
+
+			Mode::Headless if xvfb_available() => {
+				let (width, height) = DEFAULT_SCREEN;
+
+				let mut command = process::Command::new("Xvfb");
+				command.args(["-screen", "0", &format!("{width}x{height}x24")]);
 
-		// Create and run a `winit` window for `Xephyr` to use in another thread so it doesn't block the
-		// main thread.
-		// TODO: use tokio for this instead!
-		thread::spawn(move || {
-			event!(Level::DEBUG, "Initialising winit window");
+				(command, "Xvfb")
+			},
+			Mode::Headless => {
+				event!(
+					Level::WARN,
+					"Xvfb is not available; falling back to a standalone Xephyr window"
+				);
+
+				(xephyr(DEFAULT_SCREEN.0, DEFAULT_SCREEN.1), "Xephyr")
+			},
+		};
 
-			let event_loop = WinitEventLoopBuilder::new().with_any_thread(true).build().unwrap();
-			let window = WinitWindowBuilder::new()
-				.with_title(X11::title())
-				.build(&event_loop)
+		event!(Level::DEBUG, "Initialising {name} on display :{number}");
+		let process = match command.arg(&display).spawn() {
+			Ok(process) => Self {
+				process,
+				display: display.clone(),
+			},
+
+			Err(error) => {
+				event!(Level::ERROR, "Error while attempting to initialise {name}: {error}");
+
+				return Err(error);
+			},
+		};
+
+		process.wait_until_ready(number)?;
+		// Set the `DISPLAY` env variable to the nested X server's display.
+		env::set_var("DISPLAY", &display);
+
+		if mode != Mode::Headless {
+			// Spawn the `picom` compositor, if possible.
+			let _ = process::Command::new("picom").spawn();
+		}
+
+		Ok(process)
+	}
+
+	/// Returns the nested X server's display, like `:1`.
+	#[inline(always)]
+	pub fn display(&self) -> &str {
+		&self.display
+	}
+
+	/// Waits until the X server with the given `display` number accepts connections.
+	fn wait_until_ready(&self, display: u32) -> io::Result<()> {
+		let socket = format!("/tmp/.X11-unix/X{display}");
+		let start = Instant::now();
+
+		while !Path::new(&socket).exists() {
+			if start.elapsed() > READY_TIMEOUT {
+				return Err(io::Error::new(
+					io::ErrorKind::TimedOut,
+					format!("X server on display :{display} did not start in time"),
+				));
+			}
+
+			thread::sleep(READY_POLL);
+		}
+
+		Ok(())
+	}
+}
+
+/// Returns a `Xephyr` command which runs Xephyr in its own window of the given dimensions.
+fn xephyr(width: u16, height: u16) -> process::Command {
+	let mut command = process::Command::new("Xephyr");
+	command.args(["-screen", &format!("{width}x{height}")]);
+
+	command
+}
+
+/// Returns whether Xvfb is installed.
+fn xvfb_available() -> bool {
+	env::var_os("PATH").is_some_and(|path| env::split_paths(&path).any(|dir| dir.join("Xvfb").is_file()))
+}
+
+/// Returns the lowest display number, starting at 1, which isn't in use by another X server.
+fn free_display() -> u32 {
+	(1..)
+		.find(|display| {
+			let lock = format!("/tmp/.X{display}-lock");
+			let socket = format!("/tmp/.X11-unix/X{display}");
+
+			!Path::new(&lock).exists() && !Path::new(&socket).exists()
+		})
+		.expect("ran out of display numbers")
+}
+
+/// Creates and runs a `winit` window for Xephyr to use in another thread, returning its window ID.
+#[cfg(feature = "testing-gui")]
+fn spawn_window() -> u64 {
+	let (transmitter, receiver) = mpsc::channel();
+
+	// Run the window in another thread so it doesn't block the main thread.
+	// TODO: use tokio for this instead!
+	thread::spawn(move || {
+		event!(Level::DEBUG, "Initialising winit window");
+
+		let event_loop = WinitEventLoopBuilder::new().with_any_thread(true).build().unwrap();
+		let window = WinitWindowBuilder::new()
+			.with_title(X11::title())
+			.build(&event_loop)
+			.unwrap();
+
+		// Send the window's window ID back to the main thread so it can be supplied to `Xephyr`.
+		transmitter.send(u64::from(window.id())).unwrap();
+
+		event_loop
+			.run(move |event, target| {
+				if let WinitEvent::WindowEvent {
+					event: WinitWindowEvent::CloseRequested,
+					..
+				} = event
+				{
+					target.exit()
+				}
+			})
+			.unwrap();
+	});
+
+	receiver.recv().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		action::{Action, Outcome},
+		display_server::x11::wm::Client,
+		launch::Launches,
+		moveresize::{self, Direction},
+		placement::FloatPreset,
+	};
+
+	#[test]
+	fn parse_mode() {
+		assert_eq!("headless".parse::<Mode>().unwrap(), Mode::Headless);
+		assert_eq!("standalone".parse::<Mode>().unwrap(), Mode::Standalone(1280, 720));
+		assert_eq!(
+			"standalone:800x600".parse::<Mode>().unwrap(),
+			Mode::Standalone(800, 600)
+		);
+
+		assert!("standalone:800".parse::<Mode>().is_err());
+		assert!("windowed".parse::<Mode>().is_err());
+	}
+
+	/// Spawns a headless X server and connects to it.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn headless() {
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (connection, ..) = RustConnection::connect(None).await.unwrap();
+		assert!(!connection.setup().roots.is_empty());
+	}
+
+	/// Tests that the pointer lands in the focused window's new tile after the window is swapped
+	/// with the primary window with the keyboard.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn pointer_warp() {
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = conn.setup().roots[screen_num].root;
+		let screen = (0, 0, DEFAULT_SCREEN.0 as u32, DEFAULT_SCREEN.1 as u32);
+
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+		let wm = X11 {
+			atoms,
+			conn,
+			root,
+
+			handler: Mutex::new(Wm {
+				atoms,
+				..Wm::new(root, screen, Default::default(), Vec::new())
+			}),
+
+			dispatcher: Dispatcher::new(crate::display_server::DispatchMode::Live),
+		};
+
+		let (_, _, width, height) = screen;
+		let mut state = state::AquariWm::with_tiling_layout::<layout::managers::Stack<ClientWindow>>(
+			0,
+			0,
+			width,
+			height,
+			layout::LayoutSettings::new().window_gap(0),
+		);
+		state.add_windows((1..=3).map(|window| (ClientWindow::new(window), state::MapState::Mapped)));
+		// The windows don't exist, so they are only tiled.
+		let tile = |_: &_, _, _, _, _, _| async { Ok::<_, Error>(Reconfigured::Applied) };
+		state.apply_changes_async(tile).await.unwrap();
+
+		let window = ClientWindow::new(2);
+		state.set_focused_from(Some(window), FocusCause::Keyboard);
+		wm.dispatch(Request::WarpPointer(0, 0)).await.unwrap().ignore_error();
+
+		state.perform(Action::SwapWithPrimary).unwrap();
+		state.apply_changes_async(tile).await.unwrap();
+		wm.warp_pointer(&state, PointerWarp::OnWindowMove, Trigger::WindowMove, false)
+			.await
+			.unwrap();
+
+		let (x, y, width, height) = state.tile_of(&window).unwrap();
+		let pointer = wm.conn.query_pointer(root).await.unwrap().reply().await.unwrap();
+		let (pointer_x, pointer_y) = (i32::from(pointer.root_x), i32::from(pointer.root_y));
+
+		assert!(
+			(x..x + width as i32).contains(&pointer_x) && (y..y + height as i32).contains(&pointer_y),
+			"the pointer at ({pointer_x}, {pointer_y}) isn't in the tile {:?}",
+			(x, y, width, height)
+		);
+	}
+
+	/// Tests that the drop overlay follows the pointer across the middle of a tile, its edges, and
+	/// the dragged window's own tile while a tiled window is dragged, and that releasing it drops
+	/// the window beside the target in a single change.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn drop_zones() {
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = conn.setup().roots[screen_num].root;
+		let screen = (0, 0, DEFAULT_SCREEN.0 as u32, DEFAULT_SCREEN.1 as u32);
+
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+		let wm = X11 {
+			atoms,
+			conn,
+			root,
+
+			handler: Mutex::new(Wm {
+				atoms,
+				..Wm::new(root, screen, Default::default(), Vec::new())
+			}),
+
+			dispatcher: Dispatcher::new(crate::display_server::DispatchMode::Live),
+		};
+
+		let (_, _, width, height) = screen;
+		let mut state = state::AquariWm::with_tiling_layout::<layout::managers::Stack<ClientWindow>>(
+			0,
+			0,
+			width,
+			height,
+			layout::LayoutSettings::new().window_gap(0),
+		);
+		state.add_windows((1..=3).map(|window| (ClientWindow::new(window), state::MapState::Mapped)));
+		// The windows don't exist, so they are only tiled.
+		let tile = |_: &_, _, _, _, _, _| async { Ok::<_, Error>(Reconfigured::Applied) };
+		state.apply_changes_async(tile).await.unwrap();
+
+		let [first, second, third] = [1, 2, 3].map(ClientWindow::new);
+		let (x, y, width, height) = state.tile_of(&first).unwrap();
+		let request = moveresize::Request {
+			x: x + width as i32 / 2,
+			y: y + height as i32 / 2,
+			direction: Direction::Move,
+			button: 1,
+		};
+		let mut drag = Drag::start(first, &request, (x, y, width, height), state.tiling_layout()).unwrap();
+
+		// Returns the overlay's geometry, including its border, if it is mapped.
+		let overlay = || async {
+			let window = wm.handler().drop_overlay.window.unwrap().raw();
+			let attributes = wm
+				.conn
+				.get_window_attributes(window)
+				.await
+				.unwrap()
+				.reply()
+				.await
 				.unwrap();
+			let geometry = wm.conn.get_geometry(window).await.unwrap().reply().await.unwrap();
+			let border = 2 * u32::from(geometry.border_width);
 
-			// Send the window's window ID back to the main thread so it can be supplied to `Xephyr`.
-			transmitter.send(u64::from(window.id())).unwrap();
+			(attributes.map_state == x11::MapState::VIEWABLE).then_some((
+				i32::from(geometry.x),
+				i32::from(geometry.y),
+				u32::from(geometry.width) + border,
+				u32::from(geometry.height) + border,
+			))
+		};
+		let mut hover = |pointer| {
+			drag.hover(state.tiling_layout().unwrap(), pointer);
+			drag.clone()
+		};
 
-			event_loop
-				.run(move |event, target| {
-					if let WinitEvent::WindowEvent {
-						event: WinitWindowEvent::CloseRequested,
-						..
-					} = event
-					{
-						target.exit()
+		// The middle of the second window's tile swaps the windows.
+		let (x, y, width, height) = state.tile_of(&second).unwrap();
+		let center = (x + width as i32 / 2, y + height as i32 / 2);
+		let hovered = hover(center);
+		wm.update_drop_overlay(&state, Some(&hovered)).await.unwrap();
+		assert_eq!(overlay().await, Some((x, y, width, height)));
+
+		// Its top edge inserts the window above it.
+		let hovered = hover((center.0, y + 1));
+		wm.update_drop_overlay(&state, Some(&hovered)).await.unwrap();
+		assert_eq!(overlay().await, Some((x, y, width, height / 2)));
+
+		// The dragged window's own tile is nowhere to drop it.
+		let hovered = hover((request.x, request.y));
+		wm.update_drop_overlay(&state, Some(&hovered)).await.unwrap();
+		assert_eq!(overlay().await, None);
+
+		// Releasing it at the third window's right edge moves it to the right of the third window.
+		let (x, y, width, height) = state.tile_of(&third).unwrap();
+		let Drag::Swap { target: Some(drop), .. } = hover((x + width as i32 - 1, y + height as i32 / 2)) else {
+			panic!("the window wouldn't be dropped beside the third window");
+		};
+		assert!(state.drop_window(&first, &drop));
+		state.apply_changes_async(tile).await.unwrap();
+
+		let (first_x, first_y, ..) = state.tile_of(&first).unwrap();
+		let (third_x, third_y, ..) = state.tile_of(&third).unwrap();
+		assert!(first_x > third_x && first_y == third_y);
+	}
+
+	/// Tests that a storm of windows being mapped, as when a session is restored, is tiled with a
+	/// small number of re-tiles, and that every window ends up in its tile.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn map_storm() {
+		const COPY_FROM_PARENT: u32 = 0;
+		const WINDOWS: usize = 20;
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = conn.setup().roots[screen_num].root;
+		let screen = (0, 0, DEFAULT_SCREEN.0 as u32, DEFAULT_SCREEN.1 as u32);
+
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+		let wm = X11 {
+			atoms,
+			conn,
+			root,
+
+			handler: Mutex::new(Wm {
+				atoms,
+				..Wm::new(root, screen, Default::default(), Vec::new())
+			}),
+
+			dispatcher: Dispatcher::new(crate::display_server::DispatchMode::Live),
+		};
+		wm.register_window_manager().await.unwrap();
+
+		// The windows are mapped by another client in one go.
+		let (client, _, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let mut windows = Vec::new();
+		for _ in 0..WINDOWS {
+			let window = client.generate_id().await.unwrap();
+			client
+				.checked(client.create_window(
+					COPY_FROM_PARENT as u8,
+					window,
+					root,
+					0,
+					0,
+					320,
+					240,
+					0,
+					x11::WindowClass::INPUT_OUTPUT,
+					COPY_FROM_PARENT,
+					&x11::CreateWindowAux::new(),
+				))
+				.await
+				.unwrap();
+
+			windows.push(window);
+		}
+		for &window in &windows {
+			client.map_window(window).await.unwrap();
+		}
+		client.flush().await.unwrap();
+
+		let (_, _, width, height) = screen;
+		let mut state = state::AquariWm::with_tiling_layout::<layout::managers::Stack<ClientWindow>>(
+			0,
+			0,
+			width,
+			height,
+			layout::LayoutSettings::new().window_gap(0),
+		);
+		let resize_window = |window: &_, x, y, width, height, visibility| {
+			wm.reconfigure_window(*window, x, y, width, height, visibility)
+		};
+
+		// Handle the storm as the event loop does.
+		let timeout = Instant::now() + Duration::from_secs(5);
+		let mut mapped = 0;
+		while mapped < WINDOWS || wm.handler().pending_apply.is_pending() {
+			if wm.handler().pending_apply.poll(Instant::now()) {
+				state.apply_changes_async(resize_window).await.unwrap();
+			}
+			wm.dispatch_ordered().await.unwrap();
+			wm.conn.flush().await.unwrap();
+
+			let pending_apply_due = sleep_until(wm.handler().pending_apply.deadline());
+			let event = tokio::select! {
+				event = wm.conn.wait_for_event() => event.unwrap(),
+
+				() = pending_apply_due => continue,
+				() = sleep_until(Some(timeout)) => panic!("only {mapped} of {WINDOWS} windows were mapped"),
+			};
+
+			match event {
+				Event::CreateNotify(notify) => {
+					let requests = wm.handler().on_create_notify(&mut state, &notify);
+					wm.dispatch_all(requests).await.unwrap();
+				},
+				Event::MapRequest(request) => {
+					let requests = wm.handler().on_map_request(&mut state, &request, Client::default());
+					wm.dispatch_all(requests).await.unwrap();
+					wm.handler().pending_apply.defer(Instant::now());
+
+					mapped += 1;
+				},
+
+				_ => (),
+			}
+		}
+
+		let applies = wm.handler().pending_apply.applies();
+		assert!(applies <= 3, "{WINDOWS} windows were mapped with {applies} re-tiles");
+
+		for window in windows {
+			let tile = state.tile_of(&ClientWindow::new(window)).unwrap();
+			let geometry = client.get_geometry(window).await.unwrap().reply().await.unwrap();
+
+			assert_eq!(
+				(
+					i32::from(geometry.x),
+					i32::from(geometry.y),
+					u32::from(geometry.width),
+					u32::from(geometry.height)
+				),
+				tile,
+				"window {window} isn't in its tile"
+			);
+		}
+	}
+
+	/// Tests that moving a window to another workspace and following it there configures and maps
+	/// the window waiting on that workspace exactly once, without hiding the moved window.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn move_and_follow() {
+		const COPY_FROM_PARENT: u32 = 0;
+		/// How long the windows are watched for events after each step.
+		const SETTLED: Duration = Duration::from_millis(250);
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = conn.setup().roots[screen_num].root;
+		let screen = (0, 0, DEFAULT_SCREEN.0 as u32, DEFAULT_SCREEN.1 as u32);
+
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+		let wm = X11 {
+			atoms,
+			conn,
+			root,
+
+			handler: Mutex::new(Wm {
+				atoms,
+				..Wm::new(root, screen, Default::default(), Vec::new())
+			}),
+
+			dispatcher: Dispatcher::new(crate::display_server::DispatchMode::Live),
+		};
+
+		// The test client records the events its windows receive.
+		let (client, _, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let mut windows = Vec::new();
+		for _ in 0..2 {
+			let window = client.generate_id().await.unwrap();
+			client
+				.checked(client.create_window(
+					COPY_FROM_PARENT as u8,
+					window,
+					root,
+					0,
+					0,
+					320,
+					240,
+					0,
+					x11::WindowClass::INPUT_OUTPUT,
+					COPY_FROM_PARENT,
+					&x11::CreateWindowAux::new().event_mask(x11::EventMask::STRUCTURE_NOTIFY),
+				))
+				.await
+				.unwrap();
+
+			windows.push(window);
+		}
+		let (moved, waiting) = (windows[0], windows[1]);
+		client.map_window(moved).await.unwrap();
+		client.flush().await.unwrap();
+
+		// Counts the `ConfigureNotify`, `MapNotify` and `UnmapNotify` events for each window until
+		// the windows have settled.
+		let notifies = || {
+			let client = &client;
+
+			async move {
+				let mut counts: HashMap<x11::Window, (usize, usize, usize)> = HashMap::new();
+				let deadline = Instant::now() + SETTLED;
+
+				loop {
+					tokio::select! {
+						event = client.wait_for_event() => match event.unwrap() {
+							Event::ConfigureNotify(notify) => counts.entry(notify.window).or_default().0 += 1,
+							Event::MapNotify(notify) => counts.entry(notify.window).or_default().1 += 1,
+							Event::UnmapNotify(notify) => counts.entry(notify.window).or_default().2 += 1,
+
+							_ => (),
+						},
+
+						() = sleep_until(Some(deadline)) => return counts,
 					}
-				})
+				}
+			}
+		};
+
+		let (_, _, width, height) = screen;
+		let mut state = state::AquariWm::with_tiling_layout::<layout::managers::Stack<ClientWindow>>(
+			0,
+			0,
+			width,
+			height,
+			layout::LayoutSettings::new().window_gap(0),
+		);
+		let resize_window = |window: &_, x, y, width, height, visibility| {
+			wm.reconfigure_window(*window, x, y, width, height, visibility)
+		};
+		let (moved, waiting) = (ClientWindow::new(moved), ClientWindow::new(waiting));
+		state.add_windows([moved, waiting].map(|window| (window, state::MapState::Mapped)));
+		state.set_focused_from(Some(moved), FocusCause::Keyboard);
+
+		// The other window waits on the second workspace, without ever having been shown.
+		let requests = wm.handler().move_to_workspace(&mut state, waiting, 1);
+		wm.dispatch_all(requests).await.unwrap();
+		state.apply_changes_async(resize_window).await.unwrap();
+		wm.dispatch_ordered().await.unwrap();
+		wm.conn.flush().await.unwrap();
+		notifies().await;
+
+		let (requests, _) = wm.handler().perform(&mut state, Action::MoveToWorkspaceAndFollow(1));
+		wm.dispatch_all(requests).await.unwrap();
+		state.apply_changes_async(resize_window).await.unwrap();
+		wm.dispatch_ordered().await.unwrap();
+		wm.conn.flush().await.unwrap();
+		assert_eq!(state.workspaces.current(), 1);
+		assert_eq!(state.focused, Some(moved));
+
+		let counts = notifies().await;
+		let (configures, maps, unmaps) = counts.get(&waiting.raw()).copied().unwrap_or_default();
+		assert_eq!(
+			(configures, maps, unmaps),
+			(1, 1, 0),
+			"the waiting window was configured {configures} times, mapped {maps} times and unmapped {unmaps} times"
+		);
+		// The moved window stays shown throughout.
+		let (_, maps, unmaps) = counts.get(&moved.raw()).copied().unwrap_or_default();
+		assert_eq!((maps, unmaps), (0, 0));
+
+		let geometry = client.get_geometry(moved.raw()).await.unwrap().reply().await.unwrap();
+		assert_eq!(
+			(
+				i32::from(geometry.x),
+				i32::from(geometry.y),
+				u32::from(geometry.width),
+				u32::from(geometry.height)
+			),
+			state.tile_of(&moved).unwrap()
+		);
+	}
+
+	/// Tests that newly tiled windows are first drawn in their tiles, rather than at the geometry
+	/// their clients asked for, by recording the geometry each window has when it is first exposed.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn first_expose() {
+		const COPY_FROM_PARENT: u32 = 0;
+		const REPETITIONS: usize = 10;
+		const REQUESTED: (i32, i32, u32, u32) = (0, 0, 320, 240);
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = conn.setup().roots[screen_num].root;
+		let screen = (0, 0, DEFAULT_SCREEN.0 as u32, DEFAULT_SCREEN.1 as u32);
+
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+		let wm = X11 {
+			atoms,
+			conn,
+			root,
+
+			handler: Mutex::new(Wm {
+				atoms,
+				..Wm::new(root, screen, Default::default(), Vec::new())
+			}),
+
+			dispatcher: Dispatcher::new(crate::display_server::DispatchMode::Live),
+		};
+		wm.register_window_manager().await.unwrap();
+
+		let (_, _, width, height) = screen;
+		let mut state = state::AquariWm::with_tiling_layout::<layout::managers::Stack<ClientWindow>>(
+			0,
+			0,
+			width,
+			height,
+			layout::LayoutSettings::new().window_gap(0),
+		);
+		let resize_window = |window: &_, x, y, width, height, visibility| {
+			wm.reconfigure_window(*window, x, y, width, height, visibility)
+		};
+
+		let (client, _, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+
+		for repetition in 0..REPETITIONS {
+			let window = client.generate_id().await.unwrap();
+			let (x, y, width, height) = REQUESTED;
+			client
+				.checked(
+					client.create_window(
+						COPY_FROM_PARENT as u8,
+						window,
+						root,
+						x as i16,
+						y as i16,
+						width as u16,
+						height as u16,
+						0,
+						x11::WindowClass::INPUT_OUTPUT,
+						COPY_FROM_PARENT,
+						&x11::CreateWindowAux::new()
+							.event_mask(x11::EventMask::EXPOSURE | x11::EventMask::STRUCTURE_NOTIFY),
+					),
+				)
+				.await
 				.unwrap();
+			client.map_window(window).await.unwrap();
+			client.flush().await.unwrap();
+
+			let timeout = Instant::now() + Duration::from_secs(5);
+
+			// Handle the map request and the re-tile it causes as the event loop does.
+			let request = loop {
+				let event = tokio::select! {
+					event = wm.conn.wait_for_event() => event.unwrap(),
+					() = sleep_until(Some(timeout)) => panic!("window {window} was never mapped"),
+				};
+
+				match event {
+					Event::CreateNotify(notify) => {
+						let requests = wm.handler().on_create_notify(&mut state, &notify);
+						wm.dispatch_all(requests).await.unwrap();
+					},
+					Event::MapRequest(request) if request.window == window => break request,
+
+					_ => (),
+				}
+			};
+			let requests = wm.handler().on_map_request(&mut state, &request, Client::default());
+			wm.dispatch_all(requests).await.unwrap();
+			wm.handler().pending_apply.defer(Instant::now());
+			wm.conn.flush().await.unwrap();
+
+			while wm.handler().pending_apply.is_pending() {
+				let pending_apply_due = wm.handler().pending_apply.deadline();
+				sleep_until(pending_apply_due).await;
+
+				if wm.handler().pending_apply.poll(Instant::now()) {
+					state.apply_changes_async(resize_window).await.unwrap();
+				}
+			}
+			wm.dispatch_ordered().await.unwrap();
+			wm.conn.flush().await.unwrap();
+
+			// The client sees its window's geometry change before it is first exposed.
+			let mut geometry = REQUESTED;
+			loop {
+				let event = tokio::select! {
+					event = client.wait_for_event() => event.unwrap(),
+					() = sleep_until(Some(timeout)) => panic!("window {window} was never exposed"),
+				};
+
+				match event {
+					Event::ConfigureNotify(notify) if notify.window == window => {
+						geometry = (
+							i32::from(notify.x),
+							i32::from(notify.y),
+							u32::from(notify.width),
+							u32::from(notify.height),
+						);
+					},
+					Event::Expose(expose) if expose.window == window => break,
+
+					_ => (),
+				}
+			}
+
+			let tile = state.tile_of(&ClientWindow::new(window)).unwrap();
+			assert_ne!(tile, REQUESTED);
+			assert_eq!(
+				geometry, tile,
+				"window {window} was first exposed outside its tile in repetition {repetition}"
+			);
+		}
+	}
+
+	/// Tests that floating windows' requests to be restacked relative to their siblings are
+	/// realized in the X server's window stack, and that a floating window can't be stacked below
+	/// a tiled window.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn floating_restack() {
+		const COPY_FROM_PARENT: u32 = 0;
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = conn.setup().roots[screen_num].root;
+		let screen = (0, 0, DEFAULT_SCREEN.0 as u32, DEFAULT_SCREEN.1 as u32);
+
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+		let wm = X11 {
+			atoms,
+			conn,
+			root,
+
+			handler: Mutex::new(Wm {
+				atoms,
+				..Wm::new(root, screen, Default::default(), Vec::new())
+			}),
+
+			dispatcher: Dispatcher::new(crate::display_server::DispatchMode::Live),
+		};
+		wm.register_window_manager().await.unwrap();
+
+		// One tiled window, then three floating windows, stacked in the order they are created.
+		let (client, _, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let mut windows = Vec::new();
+		for _ in 0..4 {
+			let window = client.generate_id().await.unwrap();
+			client
+				.checked(client.create_window(
+					COPY_FROM_PARENT as u8,
+					window,
+					root,
+					0,
+					0,
+					320,
+					240,
+					0,
+					x11::WindowClass::INPUT_OUTPUT,
+					COPY_FROM_PARENT,
+					&x11::CreateWindowAux::new(),
+				))
+				.await
+				.unwrap();
+
+			windows.push(window);
+		}
+		let [tiled, first, second, third] = windows[..] else {
+			unreachable!()
+		};
+
+		let (_, _, width, height) = screen;
+		let mut state = state::AquariWm::with_tiling_layout::<layout::managers::Stack<ClientWindow>>(
+			0,
+			0,
+			width,
+			height,
+			layout::LayoutSettings::new().window_gap(0),
+		);
+		state.add_windows(
+			windows
+				.iter()
+				.map(|&window| (ClientWindow::new(window), state::MapState::Mapped)),
+		);
+		for window in [first, second, third] {
+			state
+				.windows
+				.get_mut(&ClientWindow::new(window))
+				.unwrap()
+				.set_floating();
+		}
+		wm.handler()
+			.floating_layer
+			.sync([first, second, third].map(ClientWindow::new));
+
+		let cases = [
+			// Above a floating sibling.
+			((first, third, x11::StackMode::ABOVE), [tiled, second, third, first]),
+			// Below a floating sibling.
+			((third, second, x11::StackMode::BELOW), [tiled, third, second, first]),
+			// Below a tiled window, which isn't a valid sibling: the bottom of the floating layer.
+			((first, tiled, x11::StackMode::BELOW), [tiled, first, third, second]),
+		];
+		for ((window, sibling, stack_mode), expected) in cases {
+			let aux = x11::ConfigureWindowAux::new().sibling(sibling).stack_mode(stack_mode);
+			client.configure_window(window, &aux).await.unwrap();
+			client.flush().await.unwrap();
+
+			// Handle the configure request as the event loop does.
+			let request = loop {
+				if let Event::ConfigureRequest(request) = wm.conn.wait_for_event().await.unwrap() {
+					break request;
+				}
+			};
+			let requests = wm
+				.handler()
+				.on_configure_request(&state, &request, &SizeHints::default(), Instant::now());
+			wm.dispatch_all(requests).await.unwrap();
+			wm.conn.flush().await.unwrap();
+
+			let tree = client.query_tree(root).await.unwrap().reply().await.unwrap();
+			let order: Vec<_> = tree
+				.children
+				.into_iter()
+				.filter(|child| windows.contains(child))
+				.collect();
+
+			assert_eq!(
+				order, expected,
+				"window {window} was restacked {stack_mode:?} {sibling}"
+			);
+		}
+	}
+
+	/// Tests that a tiled window whose client asks for its old geometry every time it is told its
+	/// geometry doesn't keep AquariWM in a configure loop.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn configure_loop() {
+		const COPY_FROM_PARENT: u32 = 0;
+		const OLD_GEOMETRY: (i16, i16, u16, u16) = (0, 0, 320, 240);
+		/// How long the window must go without asking to be configured for AquariWM to have
+		/// settled.
+		const SETTLED: Duration = Duration::from_millis(250);
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = conn.setup().roots[screen_num].root;
+		let screen = (0, 0, DEFAULT_SCREEN.0 as u32, DEFAULT_SCREEN.1 as u32);
+
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+		let wm = X11 {
+			atoms,
+			conn,
+			root,
+
+			handler: Mutex::new(Wm {
+				atoms,
+				..Wm::new(root, screen, Default::default(), Vec::new())
+			}),
+
+			dispatcher: Dispatcher::new(crate::display_server::DispatchMode::Live),
+		};
+		wm.register_window_manager().await.unwrap();
+
+		// The misbehaving client listens for its window being configured.
+		let (client, _, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let window = client.generate_id().await.unwrap();
+		let (x, y, width, height) = OLD_GEOMETRY;
+		client
+			.checked(client.create_window(
+				COPY_FROM_PARENT as u8,
+				window,
+				root,
+				x,
+				y,
+				width,
+				height,
+				0,
+				x11::WindowClass::INPUT_OUTPUT,
+				COPY_FROM_PARENT,
+				&x11::CreateWindowAux::new().event_mask(x11::EventMask::STRUCTURE_NOTIFY),
+			))
+			.await
+			.unwrap();
+
+		let (_, _, screen_width, screen_height) = screen;
+		let mut state = state::AquariWm::with_tiling_layout::<layout::managers::Stack<ClientWindow>>(
+			0,
+			0,
+			screen_width,
+			screen_height,
+			layout::LayoutSettings::new().window_gap(0),
+		);
+		state
+			.add_window(ClientWindow::new(window), state::MapState::Mapped)
+			.unwrap();
+		let resize_window = |window: &_, x, y, width, height, visibility| {
+			wm.reconfigure_window(*window, x, y, width, height, visibility)
+		};
+		state.apply_changes_async(resize_window).await.unwrap();
+		wm.conn.flush().await.unwrap();
+
+		// Handle the client's requests as the event loop does, until it stops asking.
+		let timeout = Instant::now() + Duration::from_secs(5);
+		let mut round_trips = 0;
+		loop {
+			tokio::select! {
+				event = client.wait_for_event() => {
+					if let Event::ConfigureNotify(notify) = event.unwrap() {
+						if notify.window == window {
+							let aux = x11::ConfigureWindowAux::new()
+								.x(i32::from(x))
+								.y(i32::from(y))
+								.width(u32::from(width))
+								.height(u32::from(height));
+							client.configure_window(window, &aux).await.unwrap();
+							client.flush().await.unwrap();
+						}
+					}
+				},
+
+				event = wm.conn.wait_for_event() => {
+					if let Event::ConfigureRequest(request) = event.unwrap() {
+						let requests =
+							wm.handler()
+								.on_configure_request(&state, &request, &SizeHints::default(), Instant::now());
+						wm.dispatch_all(requests).await.unwrap();
+						wm.conn.flush().await.unwrap();
+
+						round_trips += 1;
+					}
+				},
+
+				() = tokio::time::sleep(SETTLED) => break,
+				() = sleep_until(Some(timeout)) => panic!("still configuring after {round_trips} round trips"),
+			}
+		}
+
+		let limit = crate::configure_guard::DEFAULT_REASSERT_LIMIT as usize;
+		assert!(
+			round_trips <= limit + 1,
+			"AquariWM settled after {round_trips} round trips"
+		);
+
+		let tile = state.tile_of(&ClientWindow::new(window)).unwrap();
+		let geometry = client.get_geometry(window).await.unwrap().reply().await.unwrap();
+		assert_eq!(
+			(
+				i32::from(geometry.x),
+				i32::from(geometry.y),
+				u32::from(geometry.width),
+				u32::from(geometry.height)
+			),
+			tile,
+			"the window was moved out of its tile"
+		);
+	}
+
+	/// Tests that a tiled window moved to the left half of the active output with a float preset
+	/// floats and fills exactly that half of the output's work area.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn float_preset() {
+		const COPY_FROM_PARENT: u32 = 0;
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = conn.setup().roots[screen_num].root;
+		let screen = (0, 0, DEFAULT_SCREEN.0 as u32, DEFAULT_SCREEN.1 as u32);
+
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+		let wm = X11 {
+			atoms,
+			conn,
+			root,
+
+			handler: Mutex::new(Wm {
+				atoms,
+				..Wm::new(root, screen, Default::default(), Vec::new())
+			}),
+
+			dispatcher: Dispatcher::new(crate::display_server::DispatchMode::Live),
+		};
+
+		let window = wm.conn.generate_id().await.unwrap();
+		wm.conn
+			.checked(wm.conn.create_window(
+				COPY_FROM_PARENT as u8,
+				window,
+				root,
+				0,
+				0,
+				320,
+				240,
+				0,
+				x11::WindowClass::INPUT_OUTPUT,
+				COPY_FROM_PARENT,
+				&x11::CreateWindowAux::new(),
+			))
+			.await
+			.unwrap();
+
+		let (_, _, width, height) = screen;
+		let mut state = state::AquariWm::with_tiling_layout::<layout::managers::Stack<ClientWindow>>(
+			0,
+			0,
+			width,
+			height,
+			layout::LayoutSettings::new().window_gap(0),
+		);
+		let window = ClientWindow::new(window);
+		state.add_window(window, state::MapState::Mapped).unwrap();
+		state.set_focused_from(Some(window), FocusCause::Keyboard);
+
+		let outcome = state.perform(Action::ApplyFloatPreset(FloatPreset::HalfLeft)).unwrap();
+		assert_eq!(outcome, Outcome::ApplyFloatPreset(FloatPreset::HalfLeft));
+		assert_eq!(state.windows[&window].mode, layout::Mode::Floating);
+		// The window's geometry is known from its snapshot.
+		let snapshot = wm.fetch_snapshot(window).await.unwrap();
+		wm.handler().snapshots.insert(window, snapshot);
+		let requests = wm.handler().apply_float_preset(&state, window, &FloatPreset::HalfLeft);
+		wm.dispatch_all(requests).await.unwrap();
+
+		let (x, y, work_area_width, work_area_height) =
+			placement::work_area(state.active_output.get(&state.outputs), &state.outputs, screen);
+		let expected = (x, y, work_area_width / 2, work_area_height);
+
+		let geometry = wm.conn.get_geometry(window.raw()).await.unwrap().reply().await.unwrap();
+		assert_eq!(
+			(
+				i32::from(geometry.x),
+				i32::from(geometry.y),
+				u32::from(geometry.width),
+				u32::from(geometry.height)
+			),
+			expected
+		);
+		let stored = wm.handler().floating[&window];
+		assert_eq!((stored.x, stored.y, stored.width, stored.height), expected);
+	}
+
+	/// Waits until a window manager has advertised itself on the X server to which the `client` is
+	/// connected.
+	async fn wait_for_wm(client: &RustConnection, root: x11::Window) {
+		let atoms = util::Atoms::intern(client).await.unwrap();
+
+		loop {
+			let check = client
+				.get_property(false, root, atoms._NET_SUPPORTING_WM_CHECK, x11::AtomEnum::WINDOW, 0, 1)
+				.await
+				.unwrap()
+				.reply()
+				.await
+				.unwrap();
+			if check.value_len > 0 {
+				return;
+			}
+
+			time::sleep(READY_POLL).await;
+		}
+	}
+
+	/// Counts the `ConfigureNotify` events for the given `windows` received by the `client` over
+	/// the given `period`.
+	async fn configures(client: &RustConnection, windows: &[x11::Window], period: Duration) -> usize {
+		let deadline = Instant::now() + period;
+		let mut configures = 0;
+
+		loop {
+			tokio::select! {
+				event = client.wait_for_event() => {
+					if let Event::ConfigureNotify(notify) = event.unwrap() {
+						if windows.contains(&notify.window) {
+							configures += 1;
+						}
+					}
+				},
+
+				() = sleep_until(Some(deadline)) => return configures,
+			}
+		}
+	}
+
+	/// Returns a runtime directory in which a test's snapshots are written, after clearing it.
+	fn runtime_dir(test: &str) -> std::path::PathBuf {
+		let dir = env::temp_dir().join(format!("aquariwm-{test}-{}", process::id()));
+		let _ = fs::remove_dir_all(&dir);
+
+		dir
+	}
+
+	/// Tests that AquariWM writes a snapshot of the layout and exits with a connection loss when
+	/// the X server is killed underneath it.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn connection_loss() {
+		let runtime_dir = runtime_dir("connection-loss");
+		env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+
+		let server = Xephyr::spawn(Mode::Headless).unwrap();
+		let (client, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = client.setup().roots[screen_num].root;
+
+		let run = X11::run(Options::default());
+		tokio::pin!(run);
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited before registering: {result:?}"),
+			() = wait_for_wm(&client, root) => (),
+		}
+
+		drop(server);
+		let result = time::timeout(Duration::from_secs(10), run)
+			.await
+			.expect("AquariWM didn't notice the connection was lost");
+
+		let error = result.expect_err("AquariWM exited successfully");
+		assert!(error.is_connection_loss(), "unexpected error: {error}");
+		assert!(runtime_dir.join("aquariwm/state.json").is_file());
+	}
+
+	/// Tests that AquariWM registers as the window manager again when the X server is killed and
+	/// brought back, if reconnecting is enabled.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn reconnect() {
+		let runtime_dir = runtime_dir("reconnect");
+		env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+
+		let number = free_display();
+		let server = Xephyr::spawn_on(Mode::Headless, number).unwrap();
+		let (client, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = client.setup().roots[screen_num].root;
+
+		let run = X11::run(Options {
+			reconnect: true,
+			..Options::default()
 		});
-		let window_id = receiver.recv().unwrap();
-
-		event!(Level::DEBUG, "Initialising Xephyr");
-		match process::Command::new("Xephyr")
-			.arg("-resizeable")
-			// Run `Xephyr` in the `winit` window.
-			.args(["-parent", &window_id.to_string()])
-			.arg(TESTING_DISPLAY)
-			.spawn()
-		{
-			Ok(process) => {
-				// Set the `DISPLAY` env variable to `TESTING_DISPLAY`.
-				env::set_var("DISPLAY", TESTING_DISPLAY);
-
-				// Sleep for 1s to wait for Xephyr to launch. Not ideal.
-				thread::sleep(Duration::from_secs(1));
-
-				// Spawn the `picom` compositor, if possible.
-				let _ = process::Command::new("picom").spawn();
-
-				Ok(Self(process))
-			},
+		tokio::pin!(run);
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited before registering: {result:?}"),
+			() = wait_for_wm(&client, root) => (),
+		}
 
-			Err(error) => {
-				event!(Level::ERROR, "Error while attempting to initialise Xephyr: {error}");
+		drop(server);
+		// Let AquariWM notice before the X server comes back.
+		time::sleep(Duration::from_millis(100)).await;
+		let _server = Xephyr::spawn_on(Mode::Headless, number).unwrap();
+
+		let (client, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = client.setup().roots[screen_num].root;
+		let registered = async {
+			wait_for_wm(&client, root).await;
 
-				Err(error)
+			// Another client can't redirect the root window's substructure while AquariWM does.
+			let event_mask = EventMask::SUBSTRUCTURE_REDIRECT;
+			client
+				.checked(client.change_window_attributes(root, &Attributes::new().event_mask(event_mask)))
+				.await
+		};
+
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited instead of reconnecting: {result:?}"),
+			result = time::timeout(Duration::from_secs(10), registered) => {
+				let result = result.expect("AquariWM didn't reconnect");
+				assert!(matches!(result, Err(ClassifiedError::Access(_))), "AquariWM didn't register again");
 			},
 		}
+		assert!(runtime_dir.join("aquariwm/state.json").is_file());
+	}
+
+	/// Tests that a window without clear signals that it is a normal window, mapped and destroyed
+	/// again within 50ms like a splash screen, never re-tiles the windows already tiled.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn splash_screen() {
+		const COPY_FROM_PARENT: u32 = 0;
+		/// How long the tiles must go without being configured for the layout to have settled.
+		const SETTLED: Duration = Duration::from_millis(250);
+
+		let runtime_dir = runtime_dir("splash-screen");
+		env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+		let (client, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = client.setup().roots[screen_num].root;
+		let atoms = util::Atoms::intern(&client).await.unwrap();
+
+		let run = X11::run(Options::default());
+		tokio::pin!(run);
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited before registering: {result:?}"),
+			() = wait_for_wm(&client, root) => (),
+		}
+
+		let mut windows = Vec::new();
+		for _ in 0..3 {
+			let window = client.generate_id().await.unwrap();
+			client
+				.checked(client.create_window(
+					COPY_FROM_PARENT as u8,
+					window,
+					root,
+					0,
+					0,
+					320,
+					240,
+					0,
+					x11::WindowClass::INPUT_OUTPUT,
+					COPY_FROM_PARENT,
+					&x11::CreateWindowAux::new().event_mask(x11::EventMask::STRUCTURE_NOTIFY),
+				))
+				.await
+				.unwrap();
+
+			windows.push(window);
+		}
+		let (tiles, splash) = (&windows[..2], windows[2]);
+
+		// Two normal windows are tiled, and left to settle.
+		for &window in tiles {
+			client
+				.checked(client.set_property_string(
+					window,
+					x11::AtomEnum::WM_CLASS,
+					x11::AtomEnum::STRING,
+					"tile\0tile\0",
+				))
+				.await
+				.unwrap();
+			client
+				.checked(client.set_property_atoms(
+					window,
+					atoms._NET_WM_WINDOW_TYPE,
+					&[atoms._NET_WM_WINDOW_TYPE_NORMAL],
+				))
+				.await
+				.unwrap();
+			client.map_window(window).await.unwrap();
+		}
+		client.flush().await.unwrap();
+
+		let settled = async { while configures(&client, tiles, SETTLED).await > 0 {} };
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited while tiling: {result:?}"),
+			() = settled => (),
+		}
+
+		// The splash screen, without a class or window type, is gone again as soon as it is mapped.
+		let flashed = async {
+			let mapped_at = Instant::now();
+			client.map_window(splash).await.unwrap();
+			client.flush().await.unwrap();
+
+			let mut while_mapping = 0;
+			loop {
+				match client.wait_for_event().await.unwrap() {
+					Event::MapNotify(notify) if notify.window == splash => break,
+					Event::ConfigureNotify(notify) if tiles.contains(&notify.window) => while_mapping += 1,
+
+					_ => (),
+				}
+			}
+
+			client.destroy_window(splash).await.unwrap();
+			client.flush().await.unwrap();
+			assert!(
+				mapped_at.elapsed() < Duration::from_millis(50),
+				"the splash screen took {:?} to be mapped and destroyed",
+				mapped_at.elapsed()
+			);
+
+			// Long enough for the splash screen to have been admitted if it were still mapped.
+			while_mapping + configures(&client, tiles, crate::admission::DEFAULT_ADMISSION_DELAY * 3).await
+		};
+
+		let configured = tokio::select! {
+			result = &mut run => panic!("AquariWM exited after the splash screen: {result:?}"),
+			configured = flashed => configured,
+		};
+		assert_eq!(configured, 0, "the tiles were configured {configured} times");
+	}
+
+	/// Tests that a window which asks to open on every desktop before it is mapped does so without
+	/// the current desktop changing, that a request for a desktop which doesn't exist is ignored,
+	/// and that a window can be moved back onto the first desktop after it is mapped.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn requested_desktop() {
+		const COPY_FROM_PARENT: u32 = 0;
+		/// How long AquariWM is given to update a property.
+		const TIMEOUT: Duration = Duration::from_secs(2);
+
+		let runtime_dir = runtime_dir("requested-desktop");
+		env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+		let (client, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = client.setup().roots[screen_num].root;
+		let atoms = util::Atoms::intern(&client).await.unwrap();
+
+		let run = X11::run(Options::default());
+		tokio::pin!(run);
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited before registering: {result:?}"),
+			() = wait_for_wm(&client, root) => (),
+		}
+
+		// One window asks to be on every desktop, and the other on a desktop which doesn't exist.
+		let mut windows = Vec::new();
+		for desktop in [crate::desktop::ALL_DESKTOPS, 5] {
+			let window = client.generate_id().await.unwrap();
+			client
+				.checked(client.create_window(
+					COPY_FROM_PARENT as u8,
+					window,
+					root,
+					0,
+					0,
+					320,
+					240,
+					0,
+					x11::WindowClass::INPUT_OUTPUT,
+					COPY_FROM_PARENT,
+					&x11::CreateWindowAux::new(),
+				))
+				.await
+				.unwrap();
+			client
+				.checked(client.set_property_string(
+					window,
+					x11::AtomEnum::WM_CLASS,
+					x11::AtomEnum::STRING,
+					"desktop\0desktop\0",
+				))
+				.await
+				.unwrap();
+			client
+				.checked(client.set_property_cardinals(
+					window,
+					atoms._NET_WM_DESKTOP,
+					x11::AtomEnum::CARDINAL,
+					&[desktop],
+				))
+				.await
+				.unwrap();
+			client.map_window(window).await.unwrap();
+
+			windows.push(window);
+		}
+		client.flush().await.unwrap();
+		let [everywhere, nowhere] = windows[..] else {
+			unreachable!()
+		};
+
+		// Waits until the given `property` of the given `window` is the `expected` value.
+		let property = |window, property, expected: u32| {
+			let client = &client;
+
+			async move {
+				loop {
+					let values = client
+						.get_property_cardinals(window, property, x11::AtomEnum::CARDINAL, 1)
+						.await
+						.unwrap();
+					if values.first() == Some(&expected) {
+						return;
+					}
+
+					time::sleep(READY_POLL).await;
+				}
+			}
+		};
+		let settled = async {
+			time::timeout(
+				TIMEOUT,
+				property(everywhere, atoms._NET_WM_DESKTOP, crate::desktop::ALL_DESKTOPS),
+			)
+			.await
+			.expect("the window wasn't put on every desktop");
+			time::timeout(TIMEOUT, property(nowhere, atoms._NET_WM_DESKTOP, 0))
+				.await
+				.expect("the window wasn't put on the first desktop");
+		};
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited while mapping: {result:?}"),
+			() = settled => (),
+		}
+
+		let states = client
+			.get_property_atoms(everywhere, atoms._NET_WM_STATE)
+			.await
+			.unwrap();
+		assert!(states.contains(&atoms._NET_WM_STATE_STICKY));
+		// The view stayed on the current desktop.
+		let current = client
+			.get_property_cardinals(root, atoms._NET_CURRENT_DESKTOP, x11::AtomEnum::CARDINAL, 1)
+			.await
+			.unwrap();
+		assert_eq!(current, [0]);
+
+		// A pager moves the window back onto the first desktop.
+		let event = x11::ClientMessageEvent::new(property::FORMAT_32, everywhere, atoms._NET_WM_DESKTOP, [0; 5]);
+		client
+			.send_event(
+				false,
+				root,
+				x11::EventMask::SUBSTRUCTURE_REDIRECT | x11::EventMask::SUBSTRUCTURE_NOTIFY,
+				event,
+			)
+			.await
+			.unwrap();
+		client.flush().await.unwrap();
+
+		let moved = time::timeout(TIMEOUT, property(everywhere, atoms._NET_WM_DESKTOP, 0));
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited while moving the window: {result:?}"),
+			moved = moved => moved.expect("the window wasn't moved to the first desktop"),
+		}
+	}
+
+	/// Tests that a window being mapped and unmapped 20 times is measured as 20 completed map
+	/// operations, with latencies which are neither zero nor absurd.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn latency_metrics() {
+		use crate::metrics::Report;
+
+		const COPY_FROM_PARENT: u32 = 0;
+		const CYCLES: u64 = 20;
+		/// How long AquariWM is given to complete each operation.
+		const TIMEOUT: Duration = Duration::from_secs(2);
+
+		let runtime_dir = runtime_dir("latency-metrics");
+		env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+		let (client, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = client.setup().roots[screen_num].root;
+		let atoms = util::Atoms::intern(&client).await.unwrap();
+
+		let run = X11::run(Options {
+			latency_metrics: true,
+			..Options::default()
+		});
+		tokio::pin!(run);
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited before registering: {result:?}"),
+			() = wait_for_wm(&client, root) => (),
+		}
+
+		let window = client.generate_id().await.unwrap();
+		client
+			.checked(client.create_window(
+				COPY_FROM_PARENT as u8,
+				window,
+				root,
+				0,
+				0,
+				320,
+				240,
+				0,
+				x11::WindowClass::INPUT_OUTPUT,
+				COPY_FROM_PARENT,
+				&x11::CreateWindowAux::new(),
+			))
+			.await
+			.unwrap();
+		client
+			.checked(client.set_property_string(
+				window,
+				x11::AtomEnum::WM_CLASS,
+				x11::AtomEnum::STRING,
+				"metrics\0metrics\0",
+			))
+			.await
+			.unwrap();
+		client
+			.checked(client.set_property_atoms(window, atoms._NET_WM_WINDOW_TYPE, &[atoms._NET_WM_WINDOW_TYPE_NORMAL]))
+			.await
+			.unwrap();
+
+		// Waits until the published report satisfies `done`, returning it.
+		let report = |done: Box<dyn Fn(&Report) -> bool>| {
+			let (client, atoms) = (&client, &atoms);
+
+			async move {
+				loop {
+					let reply = client
+						.get_property(false, root, atoms._AQUARIWM_LATENCY, x11::AtomEnum::ANY, 0, 2048)
+						.await
+						.unwrap()
+						.reply()
+						.await
+						.unwrap();
+					let report = property::decode_string(reply.format, &reply.value)
+						.and_then(|json| serde_json::from_str::<Report>(&json).ok());
+					if let Some(report) = report.filter(|report| done(report)) {
+						return report;
+					}
+
+					time::sleep(READY_POLL).await;
+				}
+			}
+		};
+
+		// Each map is measured before the window is unmapped again, so that none is cancelled.
+		let cycles = async {
+			let mut last = Report::default();
+
+			for cycle in 1..=CYCLES {
+				client.map_window(window).await.unwrap();
+				client.flush().await.unwrap();
+				let mapped = report(Box::new(move |report| report.map.completed == cycle));
+				last = time::timeout(TIMEOUT, mapped).await.expect("the map wasn't measured");
+
+				client.unmap_window(window).await.unwrap();
+				client.flush().await.unwrap();
+				let unmapped = report(Box::new(move |report| report.unmap.completed == cycle));
+				time::timeout(TIMEOUT, unmapped)
+					.await
+					.expect("the unmap wasn't measured");
+			}
+
+			last
+		};
+		let report = tokio::select! {
+			result = &mut run => panic!("AquariWM exited while mapping the window: {result:?}"),
+			report = cycles => report,
+		};
+
+		assert_eq!(report.map.completed, CYCLES);
+		assert_eq!(report.orphans, 0);
+		assert!(report.map.p50_us > 0 && report.map.max_us < 1_000_000, "{report}");
+	}
+
+	/// Tests that a 32-bit ARGB window, whose depth differs from the root window's, is managed and
+	/// tiled without AquariWM running into any protocol errors.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn argb_window() {
+		/// How long AquariWM is given to tile the window.
+		const TIMEOUT: Duration = Duration::from_secs(2);
+		const WIDTH: u16 = 320;
+		const HEIGHT: u16 = 240;
+
+		let runtime_dir = runtime_dir("argb-window");
+		env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+		let (client, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let screen = &client.setup().roots[screen_num];
+		let root = screen.root;
+		let atoms = util::Atoms::intern(&client).await.unwrap();
+
+		let argb = screen
+			.allowed_depths
+			.iter()
+			.filter(|depth| depth.depth == 32)
+			.flat_map(|depth| &depth.visuals)
+			.find(|visual| visual.class == x11::VisualClass::TRUE_COLOR)
+			.expect("the X server has no 32-bit true color visual")
+			.visual_id;
+
+		let run = X11::run(Options::default());
+		tokio::pin!(run);
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited before registering: {result:?}"),
+			() = wait_for_wm(&client, root) => (),
+		}
+
+		// ARGB windows need a colormap and border pixel of their own, or creating them fails with a
+		// `BadMatch` error.
+		let colormap = client.generate_id().await.unwrap();
+		client
+			.checked(client.create_colormap(x11::ColormapAlloc::NONE, colormap, root, argb))
+			.await
+			.unwrap();
+		let window = client.generate_id().await.unwrap();
+		client
+			.checked(
+				client.create_window(
+					32,
+					window,
+					root,
+					0,
+					0,
+					WIDTH,
+					HEIGHT,
+					0,
+					x11::WindowClass::INPUT_OUTPUT,
+					argb,
+					&x11::CreateWindowAux::new()
+						.colormap(colormap)
+						.border_pixel(0)
+						.event_mask(x11::EventMask::STRUCTURE_NOTIFY),
+				),
+			)
+			.await
+			.unwrap();
+		client
+			.checked(client.set_property_string(window, x11::AtomEnum::WM_CLASS, x11::AtomEnum::STRING, "argb\0argb\0"))
+			.await
+			.unwrap();
+		client
+			.checked(client.set_property_atoms(window, atoms._NET_WM_WINDOW_TYPE, &[atoms._NET_WM_WINDOW_TYPE_NORMAL]))
+			.await
+			.unwrap();
+		client.map_window(window).await.unwrap();
+		client.flush().await.unwrap();
+
+		let tiled = async {
+			loop {
+				match client.wait_for_event().await.unwrap() {
+					Event::ConfigureNotify(notify)
+						if notify.window == window && (notify.width, notify.height) != (WIDTH, HEIGHT) =>
+					{
+						return (notify.width, notify.height);
+					},
+
+					_ => (),
+				}
+			}
+		};
+		let (width, height) = tokio::select! {
+			result = &mut run => panic!("AquariWM exited while managing the ARGB window: {result:?}"),
+			tiled = time::timeout(TIMEOUT, tiled) => tiled.expect("the ARGB window wasn't tiled"),
+		};
+
+		// The window is alone, so its tile takes up most of the screen.
+		assert!(
+			width > WIDTH && height > HEIGHT,
+			"the ARGB window was configured to {width}x{height}"
+		);
+	}
+
+	/// Starts a proxy to the X server on the given `display` which delays everything the X server
+	/// sends by the given `latency`, returning the display number the proxy is reached on.
+	///
+	/// Replies are delayed as they are received, not one after another, so requests which are sent
+	/// together still take a single round trip.
+	fn latency_proxy(display: u32, latency: Duration) -> u32 {
+		use tokio::{
+			io::{self, AsyncReadExt as _, AsyncWriteExt as _},
+			net::{UnixListener, UnixStream},
+			sync::mpsc,
+		};
+
+		let number = free_display();
+		let listener = UnixListener::bind(format!("/tmp/.X11-unix/X{number}")).unwrap();
+
+		tokio::spawn(async move {
+			while let Ok((client, _)) = listener.accept().await {
+				let server = UnixStream::connect(format!("/tmp/.X11-unix/X{display}")).await.unwrap();
+				let (mut client_reader, mut client_writer) = client.into_split();
+				let (mut server_reader, mut server_writer) = server.into_split();
+
+				tokio::spawn(async move { io::copy(&mut client_reader, &mut server_writer).await });
+
+				let (sender, mut receiver) = mpsc::unbounded_channel();
+				tokio::spawn(async move {
+					let mut buffer = vec![0; 64 * 1024];
+
+					while let Ok(read @ 1..) = server_reader.read(&mut buffer).await {
+						let due = time::Instant::now() + latency;
+
+						if sender.send((due, buffer[..read].to_vec())).is_err() {
+							break;
+						}
+					}
+				});
+				tokio::spawn(async move {
+					while let Some((due, data)) = receiver.recv().await {
+						time::sleep_until(due).await;
+
+						if client_writer.write_all(&data).await.is_err() {
+							break;
+						}
+					}
+				});
+			}
+		});
+
+		number
+	}
+
+	/// Tests that a window's [snapshot] is fetched in a single round trip, where fetching its
+	/// properties one at a time takes a round trip each, over a connection with added latency.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	///
+	/// [snapshot]: ClientSnapshot
+	#[tokio::test]
+	async fn snapshot_round_trip() {
+		const LATENCY: Duration = Duration::from_millis(100);
+		const COPY_FROM_PARENT: u32 = 0;
+
+		let display = free_display();
+		let _server = Xephyr::spawn_on(Mode::Headless, display).unwrap();
+		let proxied = latency_proxy(display, LATENCY);
+
+		let (client, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = client.setup().roots[screen_num].root;
+		let atoms = util::Atoms::intern(&client).await.unwrap();
+
+		let window = client.generate_id().await.unwrap();
+		client
+			.checked(client.create_window(
+				COPY_FROM_PARENT as u8,
+				window,
+				root,
+				10,
+				20,
+				320,
+				240,
+				0,
+				x11::WindowClass::INPUT_OUTPUT,
+				COPY_FROM_PARENT,
+				&x11::CreateWindowAux::new(),
+			))
+			.await
+			.unwrap();
+		client
+			.checked(client.set_property_strings(
+				window,
+				x11::AtomEnum::WM_CLASS,
+				x11::AtomEnum::STRING,
+				["snapshot", "Snapshot"],
+			))
+			.await
+			.unwrap();
+		client
+			.checked(client.set_property_string(window, atoms._NET_WM_NAME, atoms.UTF8_STRING, "Prefetched"))
+			.await
+			.unwrap();
+		client
+			.checked(client.set_property_string(
+				window,
+				x11::AtomEnum::WM_CLIENT_MACHINE,
+				x11::AtomEnum::STRING,
+				"aquarium",
+			))
+			.await
+			.unwrap();
+		client
+			.checked(client.set_property_atoms(window, atoms._NET_WM_STATE, &[atoms._NET_WM_STATE_STICKY]))
+			.await
+			.unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(Some(&format!(":{proxied}"))).await.unwrap();
+		tokio::spawn(drive);
+		let screen = &conn.setup().roots[screen_num];
+
+		let start = Instant::now();
+		let snapshot = ClientSnapshot::fetch(&conn, &atoms, screen, window).await.unwrap();
+		let elapsed = start.elapsed();
+
+		assert_eq!(snapshot.class.as_deref(), Some("Snapshot"));
+		assert_eq!(snapshot.title(), Some("Prefetched"));
+		assert_eq!(snapshot.client_machine.as_deref(), Some("aquarium"));
+		assert!(snapshot.is_sticky(&atoms));
+		assert_eq!(
+			snapshot.geometry.map(|geometry| (geometry.x, geometry.y)),
+			Some((10, 20))
+		);
+		assert!(snapshot.visual.is_some());
+		assert!(
+			(LATENCY..2 * LATENCY).contains(&elapsed),
+			"fetching the snapshot took {elapsed:?}"
+		);
+
+		let start = Instant::now();
+		for property in Property::ALL {
+			let (name, type_, length) = property.request(&atoms);
+
+			conn.get_property(false, window, name, type_, 0, length)
+				.await
+				.unwrap()
+				.reply()
+				.await
+				.unwrap();
+		}
+		let elapsed = start.elapsed();
+
+		assert!(
+			elapsed >= LATENCY * Property::ALL.len() as u32,
+			"fetching each property took {elapsed:?}"
+		);
+
+		fs::remove_file(format!("/tmp/.X11-unix/X{proxied}")).unwrap();
+	}
+
+	#[tokio::test]
+	async fn untrusted_pid() {
+		const COPY_FROM_PARENT: u32 = 0;
+		const NONEXISTENT: u32 = 0x7FFF_FFFF;
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let screen = &conn.setup().roots[screen_num];
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+
+		let host = pid::Host::local();
+		let identities = [
+			("genuine", process::id(), host.to_string()),
+			("remote", process::id(), String::from("elsewhere.invalid")),
+			("exited", NONEXISTENT, host.to_string()),
+		];
+
+		let mut snapshots = Vec::new();
+
+		for (class, id, machine) in identities {
+			let window = conn.generate_id().await.unwrap();
+			conn.checked(conn.create_window(
+				COPY_FROM_PARENT as u8,
+				window,
+				screen.root,
+				0,
+				0,
+				100,
+				100,
+				0,
+				x11::WindowClass::INPUT_OUTPUT,
+				COPY_FROM_PARENT,
+				&x11::CreateWindowAux::new(),
+			))
+			.await
+			.unwrap();
+			conn.checked(conn.set_property_strings(
+				window,
+				x11::AtomEnum::WM_CLASS,
+				x11::AtomEnum::STRING,
+				[class, class],
+			))
+			.await
+			.unwrap();
+			conn.checked(conn.set_property_cardinals(window, atoms._NET_WM_PID, x11::AtomEnum::CARDINAL, &[id]))
+				.await
+				.unwrap();
+			conn.checked(conn.set_property_string(
+				window,
+				x11::AtomEnum::WM_CLIENT_MACHINE,
+				x11::AtomEnum::STRING,
+				&machine,
+			))
+			.await
+			.unwrap();
+
+			let mut snapshot = ClientSnapshot::fetch(&conn, &atoms, screen, window).await.unwrap();
+			let result = snapshot.verify_pid(&host, pid::uptime(), pid::start_time);
+
+			snapshots.push((class, result, snapshot));
+		}
+
+		let [(_, genuine, genuine_snapshot), (_, remote, remote_snapshot), (_, exited, exited_snapshot)] =
+			&snapshots[..]
+		else {
+			unreachable!()
+		};
+
+		assert_eq!(genuine, &Ok(process::id()));
+		assert_eq!(genuine_snapshot.pid.get(), Some(process::id()));
+
+		assert!(matches!(remote, Err(pid::Unverified::Remote { .. })), "{remote:?}");
+		assert_eq!(remote_snapshot.pid.get(), None);
+
+		assert_eq!(exited, &Err(pid::Unverified::NoProcess(NONEXISTENT)));
+		assert_eq!(exited_snapshot.pid.get(), None);
+
+		// A remote window claiming the PID of a launched program isn't placed as though it were its
+		// window, but the program's own window is.
+		let mut launches = Launches::default();
+		launches.record(process::id(), Some(String::from("launched")), Instant::now());
+
+		let take = |launches: &mut Launches, snapshot: &ClientSnapshot| {
+			snapshot
+				.pid
+				.get()
+				.and_then(|pid| launches.take(pid, Instant::now(), |_| None))
+		};
+
+		assert!(take(&mut launches, remote_snapshot).is_none());
+		assert_eq!(
+			take(&mut launches, genuine_snapshot).and_then(|launch| launch.output),
+			Some(String::from("launched"))
+		);
+	}
+
+	/// Tests that a client which stops answering pings is marked unresponsive once its ping times
+	/// out, and that force killing it, once confirmed, destroys its window.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn hung_client() {
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = conn.setup().roots[screen_num].root;
+		let screen = (0, 0, DEFAULT_SCREEN.0 as u32, DEFAULT_SCREEN.1 as u32);
+
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+		let wm = X11 {
+			atoms,
+			conn,
+			root,
+
+			handler: Mutex::new(Wm {
+				atoms,
+				..Wm::new(root, screen, Default::default(), Vec::new())
+			}),
+
+			dispatcher: Dispatcher::new(crate::display_server::DispatchMode::Live),
+		};
+
+		// The client runs in this process, but on its own connection, which is what gets killed.
+		tokio::spawn(async {
+			let _ = test_client::run(test_client::DEFAULT_CLASS, &[], &Default::default(), true, false, false).await;
+		});
+		let children = || async {
+			let tree = wm.conn.query_tree(root).await.unwrap().reply().await.unwrap();
+
+			tree.children
+		};
+		let window = time::timeout(READY_TIMEOUT, async {
+			loop {
+				for window in children().await {
+					let attributes = wm.conn.get_window_attributes(window).await.unwrap().reply().await;
+
+					if attributes.is_ok_and(|attributes| attributes.map_state == x11::MapState::VIEWABLE) {
+						return ClientWindow::new(window);
+					}
+				}
+
+				time::sleep(READY_POLL).await;
+			}
+		})
+		.await
+		.expect("the test client's window wasn't mapped");
+
+		let snapshot = ClientSnapshot::fetch(&wm.conn, &wm.atoms, wm.screen(), window.raw())
+			.await
+			.unwrap();
+		assert!(snapshot.protocols.contains(&wm.atoms._NET_WM_PING));
+		wm.handler().snapshots.insert(window, snapshot);
+
+		let (_, _, width, height) = screen;
+		let mut state = state::AquariWm::with_tiling_layout::<layout::managers::Stack<ClientWindow>>(
+			0,
+			0,
+			width,
+			height,
+			layout::LayoutSettings::new().window_gap(0),
+		);
+		state.add_windows([(window, state::MapState::Mapped)]);
+
+		wm.ping(window).await.unwrap();
+		// The ping hasn't timed out yet.
+		assert!(wm.handler().sweep_pings(Instant::now()).is_empty());
+
+		let requests = wm.handler().sweep_pings(Instant::now() + crate::ping::DEFAULT_TIMEOUT);
+		assert_eq!(requests.len(), 1);
+		wm.dispatch_all(requests).await.unwrap();
+		let list = wm.handler().window_list(&state);
+		assert!(list.windows[0].unresponsive);
+
+		// The first force kill only asks for confirmation.
+		state.set_focused_from(Some(window), FocusCause::Keyboard);
+		let (requests, _) = wm.handler().perform(&mut state, Action::ForceKillFocused);
+		wm.dispatch_all(requests).await.unwrap();
+		assert!(children().await.contains(&window.raw()));
+
+		// Requests are processed in order, so the window is gone by the time the tree is queried.
+		let (requests, _) = wm.handler().perform(&mut state, Action::ForceKillFocused);
+		wm.dispatch_all(requests).await.unwrap();
+		assert!(!children().await.contains(&window.raw()));
+	}
+
+	/// Tests that iconifying either window of a window group iconifies the other too when windows
+	/// are iconified together.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn window_group() {
+		use crate::window_groups::{GroupBehavior, GroupSettings};
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = conn.setup().roots[screen_num].root;
+		let screen = (0, 0, DEFAULT_SCREEN.0 as u32, DEFAULT_SCREEN.1 as u32);
+
+		let settings = GroupSettings {
+			behavior: GroupBehavior::IconifyTogether,
+			across_outputs: false,
+		};
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+		let wm = X11 {
+			atoms,
+			conn,
+			root,
+
+			handler: Mutex::new(Wm {
+				atoms,
+				groups: WindowGroups::new(settings),
+				..Wm::new(root, screen, Default::default(), Vec::new())
+			}),
+
+			dispatcher: Dispatcher::new(crate::display_server::DispatchMode::Live),
+		};
+
+		tokio::spawn(async {
+			let _ = test_client::run(test_client::DEFAULT_CLASS, &[], &Default::default(), false, true, false).await;
+		});
+		let viewable = || async {
+			let tree = wm.conn.query_tree(root).await.unwrap().reply().await.unwrap();
+			let mut viewable = Vec::new();
+
+			for window in tree.children {
+				let attributes = wm.conn.get_window_attributes(window).await.unwrap().reply().await;
+
+				if attributes.is_ok_and(|attributes| attributes.map_state == x11::MapState::VIEWABLE) {
+					viewable.push(ClientWindow::new(window));
+				}
+			}
+
+			viewable
+		};
+		let mut windows = time::timeout(READY_TIMEOUT, async {
+			loop {
+				let windows = viewable().await;
+				if windows.len() == 2 {
+					return windows;
+				}
+
+				time::sleep(READY_POLL).await;
+			}
+		})
+		.await
+		.expect("the test client's windows weren't mapped");
+		// The leader was created first, so it has the lower ID.
+		windows.sort_unstable();
+		let [leader, member] = [windows[0], windows[1]];
+
+		for &window in &windows {
+			let snapshot = ClientSnapshot::fetch(&wm.conn, &wm.atoms, wm.screen(), window.raw())
+				.await
+				.unwrap();
+			assert_eq!(snapshot.window_group(), Some(leader.raw()));
+
+			wm.handler().snapshots.insert(window, snapshot);
+			wm.handler().update_group(window);
+		}
+		assert_eq!(wm.handler().groups.leader_of(&member), Some(&leader));
+
+		let (_, _, width, height) = screen;
+		let mut state = state::AquariWm::with_tiling_layout::<layout::managers::Stack<ClientWindow>>(
+			0,
+			0,
+			width,
+			height,
+			layout::LayoutSettings::new().window_gap(0),
+		);
+		state.add_windows(windows.iter().map(|&window| (window, state::MapState::Mapped)));
+
+		// Targeting the member iconifies the leader too, each only once.
+		let requests = wm.handler().iconify_group(&mut state, member);
+		wm.dispatch_all(requests).await.unwrap();
+		assert!(state.is_iconified(&leader) && state.is_iconified(&member));
+		assert_eq!(state.iconified().len(), 2);
+		assert!(viewable().await.is_empty());
+	}
+
+	#[tokio::test]
+	async fn wallpaper() {
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+
+		let (conn, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let screen = &conn.setup().roots[screen_num];
+		let atoms = util::Atoms::intern(&conn).await.unwrap();
+
+		let size = (screen.width_in_pixels, screen.height_in_pixels);
+		let mut background = RootBackground::new(screen);
+
+		// Assumes the usual 24-bit true color visual, with 32-bit pixels.
+		let root_pixel = || async {
+			let reply = conn
+				.get_image(x11::ImageFormat::Z_PIXMAP, screen.root, 0, 0, 1, 1, !0)
+				.await
+				.unwrap()
+				.reply()
+				.await
+				.unwrap();
+			let data: [u8; 4] = reply.data[..4].try_into().unwrap();
+
+			u32::from_le_bytes(data) & 0xFF_FFFF
+		};
+		let root_pixmaps = || async {
+			let mut pixmaps = Vec::new();
+
+			for property in [atoms._XROOTPMAP_ID, atoms.ESETROOT_PMAP_ID] {
+				pixmaps.extend(
+					conn.get_property_cardinals(screen.root, property, x11::AtomEnum::PIXMAP, 1)
+						.await
+						.unwrap(),
+				);
+			}
+
+			pixmaps
+		};
+
+		background
+			.draw(&conn, &atoms, size, &[], Color::new(0x33_66_99), None)
+			.await
+			.unwrap();
+		assert_eq!(root_pixel().await, 0x33_66_99);
+		assert!(root_pixmaps().await.is_empty());
+
+		// Without any outputs to cover, the image's pixmap is filled with the color.
+		let image = Image::new(1, 1, vec![0xFF_00_00]).unwrap();
+		background
+			.draw(&conn, &atoms, size, &[], Color::new(0x00_FF_00), Some(&image))
+			.await
+			.unwrap();
+		assert_eq!(root_pixel().await, 0x00_FF_00);
+
+		let pixmaps = root_pixmaps().await;
+		assert_eq!(pixmaps.len(), 2);
+		assert_eq!(pixmaps[0], pixmaps[1]);
+
+		// Going back to a solid color clears the advertised pixmap.
+		background
+			.draw(&conn, &atoms, size, &[], Color::new(0x33_66_99), None)
+			.await
+			.unwrap();
+		assert_eq!(root_pixel().await, 0x33_66_99);
+		assert!(root_pixmaps().await.is_empty());
+	}
+
+	/// Tests that an override-redirect window overlapping a tiled window is never configured,
+	/// restacked, or focused as the pointer moves across it and the layout is re-tiled.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn override_redirect() {
+		const NONE: u32 = 0;
+		/// The class of the override-redirect test client's window.
+		const MENU_CLASS: &str = "aquariwm-test-menu";
+		/// How long AquariWM is given to react to each step.
+		const SETTLED: Duration = Duration::from_millis(250);
+
+		/// Waits until there are `count` viewable windows of the given `class`, returning them.
+		async fn viewable(client: &RustConnection, root: x11::Window, class: &str, count: usize) -> Vec<x11::Window> {
+			let windows = async {
+				loop {
+					let tree = client.query_tree(root).await.unwrap().reply().await.unwrap();
+					let mut windows = Vec::new();
+
+					for window in tree.children {
+						let attributes = client.get_window_attributes(window).await.unwrap().reply().await;
+						let wm_class = client
+							.get_property(false, window, x11::AtomEnum::WM_CLASS, x11::AtomEnum::STRING, 0, 64)
+							.await
+							.unwrap()
+							.reply()
+							.await;
+
+						if attributes.is_ok_and(|attributes| attributes.map_state == x11::MapState::VIEWABLE)
+							&& wm_class.is_ok_and(|wm_class| {
+								wm_class.value.split(|&byte| byte == 0).nth(1) == Some(class.as_bytes())
+							}) {
+							windows.push(window);
+						}
+					}
+
+					if windows.len() == count {
+						return windows;
+					}
+
+					time::sleep(READY_POLL).await;
+				}
+			};
+
+			time::timeout(READY_TIMEOUT, windows)
+				.await
+				.unwrap_or_else(|_| panic!("{count} windows of class {class:?} weren't mapped"))
+		}
+
+		let runtime_dir = runtime_dir("override-redirect");
+		env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+		let (client, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = client.setup().roots[screen_num].root;
+
+		let run = X11::run(Options::default());
+		tokio::pin!(run);
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited before registering: {result:?}"),
+			() = wait_for_wm(&client, root) => (),
+		}
+
+		let steps = async {
+			// A tiled window, then an override-redirect window overlapping its tile.
+			tokio::spawn(async {
+				let _ = test_client::run(
+					test_client::DEFAULT_CLASS,
+					&[],
+					&Default::default(),
+					false,
+					false,
+					false,
+				)
+				.await;
+			});
+			let [tiled] = viewable(&client, root, test_client::DEFAULT_CLASS, 1).await[..] else {
+				unreachable!()
+			};
+			tokio::spawn(async {
+				let _ = test_client::run(MENU_CLASS, &[], &Default::default(), false, false, true).await;
+			});
+			let [menu] = viewable(&client, root, MENU_CLASS, 1).await[..] else {
+				unreachable!()
+			};
+
+			// Any configuration of the window, including restacking it, is notified.
+			client
+				.checked(client.change_window_attributes(
+					menu,
+					&x11::ChangeWindowAttributesAux::new().event_mask(x11::EventMask::STRUCTURE_NOTIFY),
+				))
+				.await
+				.unwrap();
+			let geometry = client.get_geometry(menu).await.unwrap().reply().await.unwrap();
+			let tile = client.get_geometry(tiled).await.unwrap().reply().await.unwrap();
+			assert!(
+				geometry.x < tile.x + tile.width as i16 && tile.x < geometry.x + geometry.width as i16,
+				"the override-redirect window doesn't overlap the tile"
+			);
+
+			let focused = || async { client.get_input_focus().await.unwrap().reply().await.unwrap().focus };
+
+			// The pointer moves onto the override-redirect window, off it into the tile, and back.
+			let (inside, outside) = (
+				(geometry.x + 10, geometry.y + 10),
+				(tile.x + tile.width as i16 - 10, tile.y + tile.height as i16 - 10),
+			);
+			for (x, y) in [inside, outside, inside] {
+				client.warp_pointer(NONE, root, 0, 0, 0, 0, x, y).await.unwrap();
+				client.flush().await.unwrap();
+				time::sleep(SETTLED).await;
+
+				assert_ne!(focused().await, menu, "the override-redirect window was focused");
+			}
+
+			// Another tiled window is mapped, re-tiling the layout.
+			tokio::spawn(async {
+				let _ = test_client::run(
+					test_client::DEFAULT_CLASS,
+					&[],
+					&Default::default(),
+					false,
+					false,
+					false,
+				)
+				.await;
+			});
+			viewable(&client, root, test_client::DEFAULT_CLASS, 2).await;
+			assert_ne!(focused().await, menu, "the override-redirect window was focused");
+
+			assert_eq!(
+				configures(&client, &[menu], SETTLED).await,
+				0,
+				"the override-redirect window was configured or restacked"
+			);
+			let moved = client.get_geometry(menu).await.unwrap().reply().await.unwrap();
+			assert_eq!(
+				(moved.x, moved.y, moved.width, moved.height),
+				(geometry.x, geometry.y, geometry.width, geometry.height)
+			);
+		};
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited: {result:?}"),
+			() = steps => (),
+		}
+	}
+
+	/// Tests that the focused window's border is drawn in the theme's focused border color, and
+	/// drawn again in the new color once the configuration file is changed and reloaded.
+	///
+	/// This needs Xvfb (or Xephyr and an X server to display it) to be installed.
+	#[tokio::test]
+	async fn theme_reload() {
+		const COPY_FROM_PARENT: u32 = 0;
+		const NONE: u32 = 0;
+		const BORDER_WIDTH: u16 = 4;
+
+		/// Waits until the border of the given `window` is drawn on the screen in the given 24-bit
+		/// `rgb` color.
+		///
+		/// Assumes the usual 24-bit true color visual, with 32-bit pixels.
+		async fn wait_for_border(client: &RustConnection, root: x11::Window, window: x11::Window, rgb: u32) {
+			let drawn = async {
+				loop {
+					let geometry = client.get_geometry(window).await.unwrap().reply().await.unwrap();
+					let reply = client
+						.get_image(
+							x11::ImageFormat::Z_PIXMAP,
+							root,
+							geometry.x + 1,
+							geometry.y + 1,
+							1,
+							1,
+							!0,
+						)
+						.await
+						.unwrap()
+						.reply()
+						.await
+						.unwrap();
+					let data: [u8; 4] = reply.data[..4].try_into().unwrap();
+
+					if u32::from_le_bytes(data) & 0xFF_FFFF == rgb {
+						return;
+					}
+
+					time::sleep(READY_POLL).await;
+				}
+			};
+
+			time::timeout(READY_TIMEOUT, drawn)
+				.await
+				.unwrap_or_else(|_| panic!("the border wasn't drawn in #{rgb:06x}"));
+		}
+
+		let runtime_dir = runtime_dir("theme-reload");
+		env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+		fs::create_dir_all(&runtime_dir).unwrap();
+		let config = runtime_dir.join("config.toml");
+		fs::write(&config, "[theme]\nfocused-border = \"#ff0000\"\n").unwrap();
+
+		let _server = Xephyr::spawn(Mode::Headless).unwrap();
+		let (client, screen_num, drive) = RustConnection::connect(None).await.unwrap();
+		tokio::spawn(drive);
+		let root = client.setup().roots[screen_num].root;
+		let atoms = util::Atoms::intern(&client).await.unwrap();
+
+		let options = Options {
+			config: Some(config.clone()),
+			..Options::default()
+		};
+		let run = X11::run(options.reload().unwrap());
+		tokio::pin!(run);
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited before registering: {result:?}"),
+			() = wait_for_wm(&client, root) => (),
+		}
+
+		let steps = async {
+			let window = client.generate_id().await.unwrap();
+			client
+				.checked(client.create_window(
+					COPY_FROM_PARENT as u8,
+					window,
+					root,
+					0,
+					0,
+					320,
+					240,
+					BORDER_WIDTH,
+					x11::WindowClass::INPUT_OUTPUT,
+					COPY_FROM_PARENT,
+					&x11::CreateWindowAux::new(),
+				))
+				.await
+				.unwrap();
+			client
+				.checked(client.set_property_atoms(
+					window,
+					atoms._NET_WM_WINDOW_TYPE,
+					&[atoms._NET_WM_WINDOW_TYPE_NORMAL],
+				))
+				.await
+				.unwrap();
+			client.checked(client.map_window(window)).await.unwrap();
+
+			let viewable = async {
+				loop {
+					let attributes = client
+						.get_window_attributes(window)
+						.await
+						.unwrap()
+						.reply()
+						.await
+						.unwrap();
+					if attributes.map_state == x11::MapState::VIEWABLE {
+						return;
+					}
+
+					time::sleep(READY_POLL).await;
+				}
+			};
+			time::timeout(READY_TIMEOUT, viewable)
+				.await
+				.expect("the window wasn't mapped");
+
+			// The pointer moves into the window, focusing it.
+			let geometry = client.get_geometry(window).await.unwrap().reply().await.unwrap();
+			let (x, y) = (
+				geometry.x + geometry.width as i16 / 2,
+				geometry.y + geometry.height as i16 / 2,
+			);
+			client.warp_pointer(NONE, root, 0, 0, 0, 0, x, y).await.unwrap();
+			client.flush().await.unwrap();
+			wait_for_border(&client, root, window, 0xFF_0000).await;
+
+			// The configuration file is changed and reloaded.
+			fs::write(&config, "[theme]\nfocused-border = \"#00ff00\"\n").unwrap();
+			let status = process::Command::new("kill")
+				.args(["-HUP", &process::id().to_string()])
+				.status()
+				.unwrap();
+			assert!(status.success());
+			wait_for_border(&client, root, window, 0x00_FF00).await;
+		};
+		tokio::select! {
+			result = &mut run => panic!("AquariWM exited: {result:?}"),
+			() = steps => (),
+		}
 	}
 }
@@ -2,12 +2,386 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use x11rb_async::protocol::xproto as x11;
+use std::future::Future;
+
+use thiserror::Error;
+use x11rb_async::{
+	errors::{ConnectionError, ReplyError},
+	protocol::{
+		xproto::{self as x11, ConnectionExt as _},
+		ErrorKind,
+	},
+	rust_connection::RustConnection,
+	x11_utils::X11Error,
+	VoidCookie,
+};
+
+use super::property;
+use crate::placement::{Gravity, SizeHints};
+
+/// Defines the [`Atoms`] struct with a field for each of the given atom names.
+macro_rules! atoms {
+	($($(#[$attr:meta])* $name:ident),* $(,)?) => {
+		/// The atoms used by AquariWM which are not predefined by the X11 protocol.
+		#[allow(non_snake_case)]
+		#[derive(Debug, Clone, Copy)]
+		pub struct Atoms {
+			$($(#[$attr])* pub $name: x11::Atom,)*
+		}
+
+		impl Atoms {
+			/// Interns the atoms.
+			///
+			/// Every `InternAtom` request is sent before any reply is waited for, so interning
+			/// takes a single round trip.
+			#[allow(non_snake_case)]
+			pub async fn intern(conn: &RustConnection) -> Result<Self, ReplyError> {
+				$(let $name = conn.intern_atom(false, stringify!($name).as_bytes()).await?;)*
+
+				Ok(Self {
+					$($name: $name.reply().await?.atom,)*
+				})
+			}
+
+			/// Returns atoms with distinct made-up values, for window managers which don't connect
+			/// to an X server, like those replaying a recording.
+			#[allow(non_snake_case)]
+			pub const fn synthetic() -> Self {
+				let mut next = 0x1000;
+				$(let $name = {
+					next += 1;
+					next
+				};)*
+
+				Self { $($name,)* }
+			}
+		}
+	};
+}
+
+atoms! {
+	_NET_SUPPORTED,
+	_NET_SUPPORTING_WM_CHECK,
+	_NET_WM_NAME,
+	UTF8_STRING,
+
+	_NET_WM_WINDOW_OPACITY,
+	_NET_WM_STATE,
+	_NET_WM_STATE_FULLSCREEN,
+	_NET_WM_STATE_STICKY,
+	_NET_WM_STATE_HIDDEN,
+	_NET_WM_DESKTOP,
+	_NET_WM_MOVERESIZE,
+	_NET_WM_PID,
+	_NET_WM_PING,
+	_NET_ACTIVE_WINDOW,
+	_NET_WM_BYPASS_COMPOSITOR,
+	_NET_WM_WINDOW_TYPE,
+	_NET_WM_WINDOW_TYPE_NORMAL,
+
+	WM_PROTOCOLS,
+	WM_TAKE_FOCUS,
+	WM_STATE,
+
+	_NET_NUMBER_OF_DESKTOPS,
+	_NET_DESKTOP_NAMES,
+	_NET_CURRENT_DESKTOP,
+	_AQUARIWM_STATUS,
+	_AQUARIWM_WINDOWS,
+	_AQUARIWM_LATENCY,
+
+	_XROOTPMAP_ID,
+	ESETROOT_PMAP_ID,
+}
+
+/// An error caused by a request, classified by how AquariWM should react to it.
+#[derive(Debug, Error)]
+pub enum ClassifiedError {
+	/// The window the request was for no longer exists (a `BadWindow` or `BadDrawable` error).
+	///
+	/// Clients can destroy their windows at any time, so this is expected: the window should just
+	/// be forgotten.
+	#[error("the window no longer exists: {0:?}")]
+	WindowGone(X11Error),
+	/// Another client already has the access which the request asked for (a `BadAccess` error),
+	/// e.g. because another window manager is running.
+	#[error("access was denied: {0:?}")]
+	Access(X11Error),
+	/// Any other error caused by the request.
+	#[error("the request failed: {0:?}")]
+	Other(X11Error),
+
+	/// The connection to the X server failed.
+	#[error(transparent)]
+	Connection(#[from] ConnectionError),
+}
+
+impl From<ReplyError> for ClassifiedError {
+	fn from(error: ReplyError) -> Self {
+		match error {
+			ReplyError::ConnectionError(error) => Self::Connection(error),
+
+			ReplyError::X11Error(error) => match error.error_kind {
+				ErrorKind::Window | ErrorKind::Drawable => Self::WindowGone(error),
+				ErrorKind::Access => Self::Access(error),
+
+				_ => Self::Other(error),
+			},
+		}
+	}
+}
+
+/// Helpers for the requests which AquariWM makes throughout the x11 module.
+///
+/// Properties are [decoded] leniently: a property which is missing, has the wrong format, or was
+/// cut short is read as having no value (or as much of it as is complete) rather than failing.
+///
+/// [decoded]: property
+pub trait ConnectionExt {
+	/// Returns the atoms in the given `property` of the given `window`.
+	async fn get_property_atoms(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+	) -> Result<Vec<x11::Atom>, ReplyError>;
+
+	/// Returns the 32-bit values of the given `type_` (e.g. `CARDINAL` or `WINDOW`) in the given
+	/// `property` of the given `window`, reading at most `length` of them.
+	async fn get_property_cardinals(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+		type_: impl Into<x11::Atom> + Send + 'static,
+		length: u32,
+	) -> Result<Vec<u32>, ReplyError>;
+
+	/// Replaces the given `property` of the given `window` with the given `atoms`.
+	async fn set_property_atoms(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+		atoms: &[x11::Atom],
+	) -> Result<VoidCookie<'_, RustConnection>, ConnectionError>;
+
+	/// Replaces the given `property` of the given `window` with the given 32-bit `values` of the
+	/// given `type_` (e.g. `CARDINAL` or `WINDOW`).
+	async fn set_property_cardinals(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+		type_: impl Into<x11::Atom> + Send + 'static,
+		values: &[u32],
+	) -> Result<VoidCookie<'_, RustConnection>, ConnectionError>;
+
+	/// Replaces the given `property` of the given `window` with the given `value` of the given
+	/// string `type_` (i.e. `STRING` or `UTF8_STRING`).
+	async fn set_property_string(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+		type_: impl Into<x11::Atom> + Send + 'static,
+		value: &str,
+	) -> Result<VoidCookie<'_, RustConnection>, ConnectionError>;
+
+	/// Replaces the given `property` of the given `window` with the given `values` of the given
+	/// string `type_` (i.e. `STRING` or `UTF8_STRING`), each null-terminated.
+	async fn set_property_strings<'string>(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+		type_: impl Into<x11::Atom> + Send + 'static,
+		values: impl IntoIterator<Item = &'string str>,
+	) -> Result<VoidCookie<'_, RustConnection>, ConnectionError>;
+
+	/// Sends a `ClientMessage` event of the given `type_` with the given 32-bit `data` to the
+	/// client which created the given `window`.
+	///
+	/// This is how ICCCM protocols, like `WM_DELETE_WINDOW`, are sent to clients.
+	async fn send_client_message(
+		&self,
+		window: x11::Window,
+		type_: impl Into<x11::Atom> + Send + 'static,
+		data: [u32; 5],
+	) -> Result<VoidCookie<'_, RustConnection>, ConnectionError>;
+
+	/// Sends the given `request` and waits for the X server to process it, [classifying] any
+	/// error it caused.
+	///
+	/// [classifying]: ClassifiedError
+	async fn checked<'conn>(
+		&'conn self,
+		request: impl Future<Output = Result<VoidCookie<'conn, RustConnection>, ConnectionError>>,
+	) -> Result<(), ClassifiedError>;
+}
+
+impl ConnectionExt for RustConnection {
+	async fn get_property_atoms(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+	) -> Result<Vec<x11::Atom>, ReplyError> {
+		self.get_property_cardinals(window, property, x11::AtomEnum::ATOM, 1024)
+			.await
+	}
+
+	async fn get_property_cardinals(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+		type_: impl Into<x11::Atom> + Send + 'static,
+		length: u32,
+	) -> Result<Vec<u32>, ReplyError> {
+		let reply = self
+			.get_property(false, window, property, type_, 0, length)
+			.await?
+			.reply()
+			.await?;
+
+		Ok(property::decode32(reply.format, &reply.value))
+	}
+
+	async fn set_property_atoms(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+		atoms: &[x11::Atom],
+	) -> Result<VoidCookie<'_, RustConnection>, ConnectionError> {
+		self.set_property_cardinals(window, property, x11::AtomEnum::ATOM, atoms)
+			.await
+	}
+
+	async fn set_property_cardinals(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+		type_: impl Into<x11::Atom> + Send + 'static,
+		values: &[u32],
+	) -> Result<VoidCookie<'_, RustConnection>, ConnectionError> {
+		self.change_property(
+			x11::PropMode::REPLACE,
+			window,
+			property,
+			type_,
+			property::FORMAT_32,
+			values.len() as u32,
+			&property::encode32(values),
+		)
+		.await
+	}
+
+	async fn set_property_string(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+		type_: impl Into<x11::Atom> + Send + 'static,
+		value: &str,
+	) -> Result<VoidCookie<'_, RustConnection>, ConnectionError> {
+		self.change_property(
+			x11::PropMode::REPLACE,
+			window,
+			property,
+			type_,
+			property::FORMAT_8,
+			value.len() as u32,
+			value.as_bytes(),
+		)
+		.await
+	}
+
+	async fn set_property_strings<'string>(
+		&self,
+		window: x11::Window,
+		property: impl Into<x11::Atom> + Send + 'static,
+		type_: impl Into<x11::Atom> + Send + 'static,
+		values: impl IntoIterator<Item = &'string str>,
+	) -> Result<VoidCookie<'_, RustConnection>, ConnectionError> {
+		let value = property::encode_strings(values);
+
+		self.change_property(
+			x11::PropMode::REPLACE,
+			window,
+			property,
+			type_,
+			property::FORMAT_8,
+			value.len() as u32,
+			&value,
+		)
+		.await
+	}
+
+	async fn send_client_message(
+		&self,
+		window: x11::Window,
+		type_: impl Into<x11::Atom> + Send + 'static,
+		data: [u32; 5],
+	) -> Result<VoidCookie<'_, RustConnection>, ConnectionError> {
+		let event = x11::ClientMessageEvent::new(property::FORMAT_32, window, type_, data);
+
+		self.send_event(false, window, x11::EventMask::NO_EVENT, event).await
+	}
+
+	async fn checked<'conn>(
+		&'conn self,
+		request: impl Future<Output = Result<VoidCookie<'conn, RustConnection>, ConnectionError>>,
+	) -> Result<(), ClassifiedError> {
+		request.await?.check().await?;
+
+		Ok(())
+	}
+}
+
+/// Parses the `values` of a `WM_NORMAL_HINTS` property (of the `WM_SIZE_HINTS` type).
+///
+/// Fields which are missing from older, shorter versions of the property are left unset.
+pub fn size_hints(values: &[u32]) -> SizeHints {
+	const US_POSITION: u32 = 1;
+	const P_POSITION: u32 = 1 << 2;
+	const P_MIN_SIZE: u32 = 1 << 4;
+	const P_MAX_SIZE: u32 = 1 << 5;
+	const P_RESIZE_INC: u32 = 1 << 6;
+	const P_BASE_SIZE: u32 = 1 << 8;
+	const P_WIN_GRAVITY: u32 = 1 << 9;
+
+	let flags = values.first().copied().unwrap_or_default();
+	// Returns the pair of values at the given index if the given flag is set.
+	let pair = |flag: u32, index: usize| match values.get(index..index + 2) {
+		Some(&[width, height]) if flags & flag != 0 => Some((width, height)),
+		_ => None,
+	};
+
+	SizeHints {
+		position: flags & (US_POSITION | P_POSITION) != 0,
+
+		min_size: pair(P_MIN_SIZE, 5),
+		max_size: pair(P_MAX_SIZE, 7),
+		size_increment: pair(P_RESIZE_INC, 9),
+		base_size: pair(P_BASE_SIZE, 15),
+
+		gravity: match values.get(17) {
+			Some(&gravity) if flags & P_WIN_GRAVITY != 0 => match x11::Gravity::from(gravity) {
+				x11::Gravity::NORTH => Gravity::North,
+				x11::Gravity::NORTH_EAST => Gravity::NorthEast,
+				x11::Gravity::WEST => Gravity::West,
+				x11::Gravity::CENTER => Gravity::Center,
+				x11::Gravity::EAST => Gravity::East,
+				x11::Gravity::SOUTH_WEST => Gravity::SouthWest,
+				x11::Gravity::SOUTH => Gravity::South,
+				x11::Gravity::SOUTH_EAST => Gravity::SouthEast,
+				x11::Gravity::STATIC => Gravity::Static,
+
+				_ => Gravity::NorthWest,
+			},
+
+			_ => Gravity::NorthWest,
+		},
+	}
+}
 
 /// Represents the values of a [`x11::ConfigureRequestEvent`] or [`x11::configure_window`] request
 /// as optional fields.
 ///
 /// Why this is not how they are represented in neither `xcb` nor `x11rb`, I cannot fathom.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
 pub struct ConfigureValues {
 	/// Configures the x-coordinate of the window.
 	pub x: Option<i16>,
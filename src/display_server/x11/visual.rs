@@ -0,0 +1,405 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Choosing colors compatible with windows' visuals for AquariWM to decorate them with.
+//!
+//! Most windows share the root window's depth and visual, but not all do: compositing-aware
+//! clients create 32-bit ARGB windows so that they can be translucent, for example. The screen's
+//! pixels mean something else in such a window's visual, so its border colors can't simply be
+//! copied from the screen's.
+//!
+//! Each managed window's [visual] is queried once when it is mapped, and how AquariWM may decorate
+//! it is [decided] from its depth, its visual class, and whether it has a colormap:
+//!
+//! - windows with the screen's depth are [decorated with the screen's defaults];
+//! - windows of another depth with a true color visual and a colormap are [decorated with their
+//!   own] visual, depth and colormap, with colors encoded as [pixels of their visual];
+//! - other windows are [left undecorated], without a border color, as decorating them would mean
+//!   allocating colors in a colormap they may not even have.
+//!
+//! Overlays, like the insertion preview, are children of the root window which don't belong to any
+//! client window, so they are always created with the screen's defaults.
+//!
+//! The [theme]'s colors are resolved into a [palette] of pixels once for the screen and once for
+//! each other visual that windows are decorated with, and again only when the theme changes, so
+//! that the sites drawing with them never encode colors themselves.
+//!
+//! [visual]: Visual
+//! [decided]: decorations
+//! [decorated with the screen's defaults]: Decorations::Screen
+//! [decorated with their own]: Decorations::Own
+//! [pixels of their visual]: Visual::pixel
+//! [left undecorated]: Decorations::Undecorated
+//! [theme]: crate::theme
+//! [palette]: Palette
+
+use std::collections::HashMap;
+
+use x11rb_async::protocol::xproto as x11;
+
+use crate::theme::{Rgba, Slot, Theme};
+
+/// What is known about the visual of a window.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Visual {
+	/// The ID of the visual.
+	pub id: x11::Visualid,
+	/// The depth of the window, in bits per pixel.
+	pub depth: u8,
+	pub class: x11::VisualClass,
+	/// The masks of the red, green, and blue bits of pixels of the visual.
+	pub masks: (u32, u32, u32),
+	/// The window's colormap, if it has one.
+	pub colormap: Option<x11::Colormap>,
+}
+
+/// The pixel of each of a [theme]'s colors, encoded for one visual.
+///
+/// [theme]: Theme
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Palette {
+	pixels: [u32; Slot::COUNT],
+}
+
+/// The [palettes] of a [theme] for the screen and for the visuals windows are decorated with,
+/// resolved when they are first needed and discarded when the theme changes.
+///
+/// [palettes]: Palette
+/// [theme]: Theme
+#[derive(Debug, Clone)]
+pub struct Palettes {
+	theme: Theme,
+
+	/// The depth of the screen's root window.
+	screen_depth: u8,
+	/// The visual of the screen's root window, if it is a true color visual whose pixels can be
+	/// encoded.
+	screen_visual: Option<Visual>,
+	/// The palette for the screen's visual.
+	screen: Palette,
+	/// The palette for each visual [decorated with its own] pixels, by visual ID.
+	///
+	/// [decorated with its own]: Decorations::Own
+	own: HashMap<x11::Visualid, Palette>,
+}
+
+/// Which resources AquariWM may create for a window with a given [visual], and with which
+/// parameters.
+///
+/// [visual]: Visual
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Decorations {
+	/// The window has the screen's depth, so resources are created with the screen's defaults.
+	Screen,
+	/// Resources are created with the window's own visual, depth and colormap.
+	Own,
+	/// No resources are created for the window: it gets no border color.
+	Undecorated,
+}
+
+/// Returns which resources may be created for a window of the given `depth` and visual `class`,
+/// according to whether it `has_colormap`, on a screen of the given `screen_depth`.
+///
+/// Only windows with true color visuals are decorated with their own visuals: their pixels can be
+/// encoded from colors without a colormap's cells being allocated.
+pub fn decorations(depth: u8, class: x11::VisualClass, has_colormap: bool, screen_depth: u8) -> Decorations {
+	if depth == screen_depth {
+		Decorations::Screen
+	} else if class == x11::VisualClass::TRUE_COLOR && has_colormap {
+		Decorations::Own
+	} else {
+		Decorations::Undecorated
+	}
+}
+
+impl Visual {
+	/// Looks up the visual with the given `id` among those allowed on the `screen`, for a window
+	/// with the given `colormap`.
+	///
+	/// Returns [`None`] if the visual isn't one of the screen's.
+	pub fn find(screen: &x11::Screen, id: x11::Visualid, colormap: x11::Colormap) -> Option<Self> {
+		screen.allowed_depths.iter().find_map(|depth| {
+			let visual = depth.visuals.iter().find(|visual| visual.visual_id == id)?;
+
+			Some(Self {
+				id,
+				depth: depth.depth,
+				class: visual.class,
+				masks: (visual.red_mask, visual.green_mask, visual.blue_mask),
+				colormap: (colormap != u32::from(x11::ColormapEnum::NONE)).then_some(colormap),
+			})
+		})
+	}
+
+	/// Returns which resources may be created for a window with this visual on a screen of the
+	/// given `screen_depth`; see [`decorations`].
+	pub fn decorations(&self, screen_depth: u8) -> Decorations {
+		decorations(self.depth, self.class, self.colormap.is_some(), screen_depth)
+	}
+
+	/// Encodes a 24-bit RGB color as a pixel of this (true color) visual.
+	///
+	/// Bits of the depth outside of the color masks, like an alpha channel, are set, so that the
+	/// color is opaque.
+	pub const fn pixel(&self, rgb: u32) -> u32 {
+		self.pixel_rgba(Rgba::opaque(rgb))
+	}
+
+	/// Encodes a color as a pixel of this (true color) visual, honoring its alpha channel if the
+	/// visual has one.
+	///
+	/// The bits of the depth outside of the color masks are the visual's alpha channel, as in
+	/// 32-bit ARGB visuals, and the color channels are premultiplied by it, as compositors expect.
+	/// Visuals without an alpha channel can only show the color opaque.
+	pub const fn pixel_rgba(&self, color: Rgba) -> u32 {
+		const fn channel(value: u32, mask: u32) -> u32 {
+			let max = (1u64 << mask.count_ones()) - 1;
+			let scaled = (value as u64 * max + 127) / 255;
+
+			(scaled as u32) << mask.trailing_zeros()
+		}
+		const fn premultiply(value: u32, alpha: u32) -> u32 {
+			(value * alpha + 127) / 255
+		}
+
+		let (red, green, blue) = self.masks;
+		let depth = match self.depth {
+			32.. => u32::MAX,
+			depth => (1 << depth) - 1,
+		};
+		let alpha_mask = depth & !(red | green | blue);
+		let (alpha, alpha_bits) = match alpha_mask {
+			0 => (u8::MAX as u32, 0),
+			_ => (color.alpha() as u32, channel(color.alpha() as u32, alpha_mask)),
+		};
+
+		let rgb = color.rgb();
+		let (r, g, b) = ((rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF, rgb & 0xFF);
+
+		channel(premultiply(r, alpha), red)
+			| channel(premultiply(g, alpha), green)
+			| channel(premultiply(b, alpha), blue)
+			| alpha_bits
+	}
+}
+
+impl Palette {
+	/// Resolves the pixel of each of the `theme`'s colors with the given `pixel` function.
+	pub fn resolve(theme: &Theme, pixel: impl Fn(Rgba) -> u32) -> Self {
+		let mut pixels = [0; Slot::COUNT];
+		for (slot, color) in theme.iter() {
+			pixels[slot.index()] = pixel(color);
+		}
+
+		Self { pixels }
+	}
+
+	/// Returns the pixel of the given `slot`'s color.
+	#[inline(always)]
+	pub const fn get(&self, slot: Slot) -> u32 {
+		self.pixels[slot.index()]
+	}
+}
+
+impl Default for Palettes {
+	/// Returns the palettes of the [default theme] for a 24-bit screen whose pixels are their RGB
+	/// values, until the screen's visual is known.
+	///
+	/// [default theme]: Theme::default
+	fn default() -> Self {
+		Self::new(Theme::default(), 24, None)
+	}
+}
+
+impl Palettes {
+	/// Creates the palettes of the given `theme` for a screen with the given `screen_depth` and
+	/// root `screen_visual`, resolving the screen's palette.
+	///
+	/// Colors are encoded as pixels of the screen's visual if it is a true color visual, and as
+	/// their RGB values otherwise.
+	pub fn new(theme: Theme, screen_depth: u8, screen_visual: Option<Visual>) -> Self {
+		let screen_visual = screen_visual.filter(|visual| visual.class == x11::VisualClass::TRUE_COLOR);
+		let screen = Self::resolve_screen(&theme, screen_visual);
+
+		Self {
+			theme,
+
+			screen_depth,
+			screen_visual,
+			screen,
+			own: HashMap::new(),
+		}
+	}
+
+	fn resolve_screen(theme: &Theme, screen_visual: Option<Visual>) -> Palette {
+		match screen_visual {
+			Some(visual) => Palette::resolve(theme, |color| visual.pixel_rgba(color)),
+			None => Palette::resolve(theme, |color| color.rgb()),
+		}
+	}
+
+	/// Returns the theme the palettes are resolved from.
+	#[inline(always)]
+	pub const fn theme(&self) -> &Theme {
+		&self.theme
+	}
+
+	/// Returns the depth of the screen's root window, which decides how windows are [decorated].
+	///
+	/// [decorated]: Visual::decorations
+	#[inline(always)]
+	pub const fn screen_depth(&self) -> u8 {
+		self.screen_depth
+	}
+
+	/// Replaces the theme, resolving the screen's palette again and discarding the others, which
+	/// are resolved again when they are next needed.
+	pub fn set_theme(&mut self, theme: Theme) {
+		self.screen = Self::resolve_screen(&theme, self.screen_visual);
+		self.own.clear();
+		self.theme = theme;
+	}
+
+	/// Returns the palette for the screen's visual, which [overlays] and windows decorated with
+	/// the screen's defaults are drawn with.
+	///
+	/// [overlays]: super::wm::Overlay
+	#[inline(always)]
+	pub const fn screen(&self) -> &Palette {
+		&self.screen
+	}
+
+	/// Returns the palette for a window with the given `visual`, resolving it if it hasn't been
+	/// yet, or [`None`] if the window is [left undecorated].
+	///
+	/// Windows whose visual isn't known are drawn with the screen's palette.
+	///
+	/// [left undecorated]: Decorations::Undecorated
+	pub fn for_visual(&mut self, visual: Option<&Visual>) -> Option<&Palette> {
+		let Some(visual) = visual else {
+			return Some(&self.screen);
+		};
+
+		match visual.decorations(self.screen_depth) {
+			Decorations::Screen => Some(&self.screen),
+			Decorations::Own => Some(
+				self.own
+					.entry(visual.id)
+					.or_insert_with(|| Palette::resolve(&self.theme, |color| visual.pixel_rgba(color))),
+			),
+
+			Decorations::Undecorated => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SCREEN_DEPTH: u8 = 24;
+
+	fn visual(depth: u8, masks: (u32, u32, u32), colormap: Option<x11::Colormap>) -> Visual {
+		Visual {
+			id: 0x21,
+			depth,
+			class: x11::VisualClass::TRUE_COLOR,
+			masks,
+			colormap,
+		}
+	}
+
+	#[test]
+	fn decisions() {
+		use x11::VisualClass;
+
+		let table = [
+			// (depth, class, has colormap) => decorations
+			((24, VisualClass::TRUE_COLOR, true), Decorations::Screen),
+			((24, VisualClass::PSEUDO_COLOR, false), Decorations::Screen),
+			// 32-bit ARGB windows...
+			((32, VisualClass::TRUE_COLOR, true), Decorations::Own),
+			// ...can't be decorated without a colormap.
+			((32, VisualClass::TRUE_COLOR, false), Decorations::Undecorated),
+			// Colors can't be encoded for other visual classes without allocating them.
+			((8, VisualClass::PSEUDO_COLOR, true), Decorations::Undecorated),
+			((32, VisualClass::DIRECT_COLOR, true), Decorations::Undecorated),
+			((8, VisualClass::STATIC_GRAY, true), Decorations::Undecorated),
+		];
+
+		for ((depth, class, has_colormap), expected) in table {
+			assert_eq!(
+				decorations(depth, class, has_colormap, SCREEN_DEPTH),
+				expected,
+				"{depth}-bit {class:?} visual, with colormap: {has_colormap}"
+			);
+		}
+	}
+
+	#[test]
+	fn pixels() {
+		let rgb = visual(24, (0xFF_0000, 0x00_FF00, 0x00_00FF), None);
+		assert_eq!(rgb.pixel(0x12_3456), 0x12_3456);
+
+		// The alpha channel of ARGB visuals is made opaque.
+		let argb = visual(32, (0xFF_0000, 0x00_FF00, 0x00_00FF), None);
+		assert_eq!(argb.pixel(0x12_3456), 0xFF12_3456);
+
+		// Channels are scaled to the width of their masks.
+		let rgb565 = visual(16, (0xF800, 0x07E0, 0x001F), None);
+		assert_eq!(rgb565.pixel(0xFF_FFFF), 0xFFFF);
+		assert_eq!(rgb565.pixel(0xFF_0000), 0xF800);
+		assert_eq!(rgb565.pixel(0x00_0000), 0);
+	}
+
+	#[test]
+	fn pixels_with_alpha() {
+		let translucent = Rgba::new(0x12_3456, 0x80);
+
+		// 24-bit visuals have no alpha channel, so colors are opaque...
+		let rgb = visual(24, (0xFF_0000, 0x00_FF00, 0x00_00FF), None);
+		assert_eq!(rgb.pixel_rgba(translucent), 0x12_3456);
+		// ...while 32-bit ARGB visuals have their alpha channel set, with the color premultiplied.
+		let argb = visual(32, (0xFF_0000, 0x00_FF00, 0x00_00FF), None);
+		assert_eq!(argb.pixel_rgba(translucent), 0x8009_1A2B);
+		assert_eq!(argb.pixel_rgba(Rgba::opaque(0x12_3456)), argb.pixel(0x12_3456));
+		assert_eq!(argb.pixel_rgba(Rgba::new(0xFF_FFFF, 0)), 0);
+	}
+
+	#[test]
+	fn palettes() {
+		let masks = (0xFF_0000, 0x00_FF00, 0x00_00FF);
+		let mut theme = Theme::default();
+		theme.set(Slot::FocusedBorder, Rgba::new(0x12_3456, 0x80));
+
+		let mut palettes = Palettes::new(theme.clone(), SCREEN_DEPTH, Some(visual(24, masks, None)));
+		assert_eq!(palettes.screen().get(Slot::FocusedBorder), 0x12_3456);
+
+		// Each depth's pixels are encoded for its own visual.
+		let argb = Visual {
+			id: 0x22,
+			..visual(32, masks, Some(0x40_0001))
+		};
+		assert_eq!(
+			palettes
+				.for_visual(Some(&argb))
+				.map(|palette| palette.get(Slot::FocusedBorder)),
+			Some(0x8009_1A2B)
+		);
+		let screen = *palettes.screen();
+		assert_eq!(palettes.for_visual(None), Some(&screen));
+		assert_eq!(palettes.for_visual(Some(&visual(32, masks, None))), None);
+
+		// Changing the theme resolves every palette again.
+		theme.set(Slot::FocusedBorder, Rgba::opaque(0xFF_0000));
+		palettes.set_theme(theme);
+		assert_eq!(palettes.screen().get(Slot::FocusedBorder), 0xFF_0000);
+		assert_eq!(
+			palettes
+				.for_visual(Some(&argb))
+				.map(|palette| palette.get(Slot::FocusedBorder)),
+			Some(0xFFFF_0000)
+		);
+	}
+}
@@ -0,0 +1,306 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Drawing the [wallpaper] as the root window's background.
+//!
+//! A solid color is drawn by setting the root window's background pixel. An image is drawn into a
+//! pixmap which becomes the root window's background, and which is advertised in the
+//! `_XROOTPMAP_ID` and `ESETROOT_PMAP_ID` properties so that pseudo-transparent terminals can draw
+//! the part of the wallpaper behind them.
+//!
+//! As with other wallpaper setters, the pixmap is created on a connection of its own which is
+//! closed with its resources retained permanently, so that the wallpaper outlives AquariWM's
+//! connection. Whoever replaces the wallpaper frees the old pixmap by killing the client which
+//! created it: when both properties name the same pixmap, it was retained this way, so nothing
+//! else is lost with it.
+//!
+//! A wallpaper set by another program, like `feh`, is left alone unless AquariWM is given an image
+//! of its own to show.
+//!
+//! [wallpaper]: crate::wallpaper
+
+use thiserror::Error;
+use tracing::{event, Level};
+use x11rb_async::{
+	connection::Connection,
+	errors::{ConnectError, ReplyError, ReplyOrIdError},
+	protocol::xproto::{self as x11, ConnectionExt as _},
+	rust_connection::RustConnection,
+};
+
+use super::{
+	util::{Atoms, ClassifiedError, ConnectionExt as _},
+	visual::Visual,
+};
+use crate::{
+	output::Output,
+	wallpaper::{self, Color, Image},
+};
+
+/// The maximum length of a request without the BIG-REQUESTS extension, in bytes.
+const MAX_REQUEST_BYTES: usize = 0xFFFF * 4;
+/// The length of a `PutImage` request without its image data, in bytes.
+const PUT_IMAGE_HEADER_BYTES: usize = 24;
+
+/// The background of a screen's root window, as AquariWM last drew it.
+#[derive(Debug)]
+pub struct RootBackground {
+	root: x11::Window,
+	/// The depth of the root window, in bits per pixel.
+	depth: u8,
+	/// The root window's visual, if it is a true color visual.
+	visual: Option<Visual>,
+
+	/// The pixmap AquariWM last made the root window's background, if any.
+	pixmap: Option<x11::Pixmap>,
+}
+
+/// An error creating a wallpaper pixmap on a connection of its own.
+#[derive(Debug, Error)]
+enum PixmapError {
+	#[error(transparent)]
+	Connect(#[from] ConnectError),
+	#[error(transparent)]
+	Request(#[from] ClassifiedError),
+	#[error(transparent)]
+	Id(#[from] ReplyOrIdError),
+	#[error(transparent)]
+	Reply(#[from] ReplyError),
+
+	/// Images are only drawn for screens whose pixels are 32 bits, as almost every screen's are.
+	#[error("images can't be drawn on a screen of depth {0}")]
+	UnsupportedDepth(u8),
+}
+
+impl RootBackground {
+	/// Creates the background of the given `screen`'s root window, which AquariWM hasn't drawn yet.
+	pub fn new(screen: &x11::Screen) -> Self {
+		Self {
+			root: screen.root,
+			depth: screen.root_depth,
+			visual: Visual::find(screen, screen.root_visual, screen.default_colormap),
+
+			pixmap: None,
+		}
+	}
+
+	/// Draws the background of the screen, which is currently of the given `size`: the `image`, if
+	/// any, scaled to cover each of the `outputs`, with the rest of the screen filled with the
+	/// `color`.
+	///
+	/// Without an `image`, a wallpaper set by another program is left alone.
+	pub async fn draw(
+		&mut self,
+		conn: &RustConnection,
+		atoms: &Atoms,
+		size: (u16, u16),
+		outputs: &[Output],
+		color: Color,
+		image: Option<&Image>,
+	) -> Result<(), ClassifiedError> {
+		let root = self.root;
+		let current = root_pixmap(conn, atoms, root).await?;
+
+		let pixmap = match image {
+			Some(image) => match self.create_pixmap(conn.setup(), size, outputs, color, image).await {
+				Ok(pixmap) => Some(pixmap),
+
+				Err(error) => {
+					event!(Level::WARN, "Failed to draw the wallpaper image: {error}");
+					None
+				},
+			},
+
+			None => None,
+		};
+		if pixmap.is_none() && current.is_some_and(|current| Some(current.pixmap) != self.pixmap) {
+			event!(Level::DEBUG, "Leaving the wallpaper set by another program in place");
+
+			return Ok(());
+		}
+
+		// The replaced pixmap is freed by killing the client which retained it.
+		if let Some(current) = current.filter(|current| current.retained) {
+			if Some(current.pixmap) != pixmap {
+				// The pixmap may have been freed already, e.g. by a program which didn't clear the
+				// properties.
+				let _ = conn.checked(conn.kill_client(current.pixmap)).await;
+			}
+		}
+
+		match pixmap {
+			Some(pixmap) => {
+				for property in [atoms._XROOTPMAP_ID, atoms.ESETROOT_PMAP_ID] {
+					conn.set_property_cardinals(root, property, x11::AtomEnum::PIXMAP, &[pixmap])
+						.await?;
+				}
+				conn.change_window_attributes(root, &x11::ChangeWindowAttributesAux::new().background_pixmap(pixmap))
+					.await?;
+			},
+
+			None => {
+				if current.is_some() {
+					for property in [atoms._XROOTPMAP_ID, atoms.ESETROOT_PMAP_ID] {
+						conn.delete_property(root, property).await?;
+					}
+				}
+				conn.change_window_attributes(
+					root,
+					&x11::ChangeWindowAttributesAux::new().background_pixel(self.pixel(color.rgb())),
+				)
+				.await?;
+			},
+		}
+		// Redraw the whole background, wherever it is exposed.
+		conn.checked(conn.clear_area(false, root, 0, 0, 0, 0)).await?;
+
+		self.pixmap = pixmap;
+
+		Ok(())
+	}
+
+	/// Encodes a 24-bit RGB color as a pixel of the root window's visual.
+	fn pixel(&self, rgb: u32) -> u32 {
+		self.visual.map_or(rgb, |visual| visual.pixel(rgb))
+	}
+
+	/// Draws the wallpaper `image` for the screen, of the given size, into a new pixmap, created
+	/// on a connection of its own which is closed with the pixmap retained permanently.
+	async fn create_pixmap(
+		&self,
+		setup: &x11::Setup,
+		(width, height): (u16, u16),
+		outputs: &[Output],
+		color: Color,
+		image: &Image,
+	) -> Result<x11::Pixmap, PixmapError> {
+		let depth = self.depth;
+
+		let Some(bits_per_pixel) = setup
+			.pixmap_formats
+			.iter()
+			.find(|format| format.depth == depth)
+			.map(|format| format.bits_per_pixel)
+			.filter(|&bits| bits == 32)
+		else {
+			return Err(PixmapError::UnsupportedDepth(depth));
+		};
+		let msb_first = setup.image_byte_order == x11::ImageOrder::MSB_FIRST;
+
+		let pixels = wallpaper::compose(width.into(), height.into(), outputs, Some(image), color);
+		let data = encode(&pixels, |rgb| self.pixel(rgb), msb_first);
+
+		let (conn, _, drive) = RustConnection::connect(None).await?;
+		let driver = tokio::spawn(drive);
+
+		let result = async {
+			conn.checked(conn.set_close_down_mode(x11::CloseDown::RETAIN_PERMANENT))
+				.await?;
+
+			let pixmap = conn.generate_id().await?;
+			conn.checked(conn.create_pixmap(depth, pixmap, self.root, width, height))
+				.await?;
+
+			let gc = conn.generate_id().await?;
+			conn.checked(conn.create_gc(gc, pixmap, &x11::CreateGCAux::new()))
+				.await?;
+
+			let row_bytes = usize::from(width) * usize::from(bits_per_pixel / 8);
+			let rows = ((MAX_REQUEST_BYTES - PUT_IMAGE_HEADER_BYTES) / row_bytes).max(1);
+
+			for (index, strip) in data.chunks(rows * row_bytes).enumerate() {
+				conn.checked(conn.put_image(
+					x11::ImageFormat::Z_PIXMAP,
+					pixmap,
+					gc,
+					width,
+					(strip.len() / row_bytes) as u16,
+					0,
+					(index * rows) as i16,
+					0,
+					depth,
+					strip,
+				))
+				.await?;
+			}
+			conn.checked(conn.free_gc(gc)).await?;
+
+			Ok(pixmap)
+		}
+		.await;
+
+		// The connection is closed once it is dropped, leaving the pixmap behind as its resources
+		// are retained permanently.
+		driver.abort();
+
+		result
+	}
+}
+
+/// The pixmap advertised as the root window's background.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct RootPixmap {
+	pixmap: x11::Pixmap,
+	/// Whether `ESETROOT_PMAP_ID` names the same pixmap, meaning that it was retained permanently
+	/// by the client which created it, so it may be freed by killing that client.
+	retained: bool,
+}
+
+/// Returns the pixmap advertised in the `root` window's `_XROOTPMAP_ID` property, if any.
+async fn root_pixmap(
+	conn: &RustConnection,
+	atoms: &Atoms,
+	root: x11::Window,
+) -> Result<Option<RootPixmap>, ReplyError> {
+	let pixmap = conn
+		.get_property_cardinals(root, atoms._XROOTPMAP_ID, x11::AtomEnum::PIXMAP, 1)
+		.await?;
+	let Some(&pixmap) = pixmap
+		.first()
+		.filter(|&&pixmap| pixmap != u32::from(x11::PixmapEnum::NONE))
+	else {
+		return Ok(None);
+	};
+
+	let esetroot = conn
+		.get_property_cardinals(root, atoms.ESETROOT_PMAP_ID, x11::AtomEnum::PIXMAP, 1)
+		.await?;
+
+	Ok(Some(RootPixmap {
+		pixmap,
+		retained: esetroot.first() == Some(&pixmap),
+	}))
+}
+
+/// Encodes 24-bit RGB `pixels` as the data of a 32-bit-per-pixel `ZPixmap` image, converting each
+/// to a `pixel` of the image's visual and ordering its bytes most significant first if
+/// `msb_first`.
+fn encode(pixels: &[u32], pixel: impl Fn(u32) -> u32, msb_first: bool) -> Vec<u8> {
+	pixels
+		.iter()
+		.flat_map(|&rgb| match msb_first {
+			true => pixel(rgb).to_be_bytes(),
+			false => pixel(rgb).to_le_bytes(),
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn encoding() {
+		let pixels = [0x11_22_33, 0xaa_bb_cc];
+
+		assert_eq!(
+			encode(&pixels, |rgb| rgb, false),
+			[0x33, 0x22, 0x11, 0x00, 0xcc, 0xbb, 0xaa, 0x00]
+		);
+		assert_eq!(
+			encode(&pixels, |rgb| rgb | 0xff00_0000, true),
+			[0xff, 0x11, 0x22, 0x33, 0xff, 0xaa, 0xbb, 0xcc]
+		);
+	}
+}
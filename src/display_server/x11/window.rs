@@ -0,0 +1,129 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Typed window IDs, which say what a window is to AquariWM.
+//!
+//! The X server identifies every window by a raw [`x11::Window`] ID, whether it is a client's
+//! window or a window AquariWM creates for itself, like the EWMH check window. Keeping these apart
+//! in the types means that, e.g., AquariWM's own windows can't be tiled or focused; IDs are only
+//! converted back to raw IDs when a request is made.
+//!
+//! Raw IDs from events are [resolved] to what they are, with the [client map] as the single source
+//! of truth for which client windows are managed.
+//!
+//! [resolved]: super::wm::Wm::resolve_window
+//! [client map]: crate::state::AquariWm::windows
+
+use std::{
+	fmt,
+	fmt::{Display, Formatter},
+};
+
+use serde::{Deserialize, Serialize};
+use x11rb_async::protocol::xproto as x11;
+
+use crate::window::{ManagedWindow, WindowId};
+
+/// A client's top-level window.
+///
+/// Client windows are what AquariWM manages: they are tracked in the [client map], tiled in the
+/// layout, and focused.
+///
+/// [client map]: crate::state::AquariWm::windows
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ClientWindow(x11::Window);
+
+/// A window created by AquariWM for its own use, like the EWMH check window, which is never
+/// managed.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct UtilityWindow(x11::Window);
+
+/// What a raw window ID from an event is to AquariWM, as [resolved] by the window manager.
+///
+/// [resolved]: super::wm::Wm::resolve_window
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum WindowRole {
+	/// The root window.
+	Root,
+	/// A managed [client window].
+	///
+	/// [client window]: ClientWindow
+	Client(ClientWindow),
+	/// A client window which is [ignored], and so passed through rather than managed.
+	///
+	/// [ignored]: crate::ignore
+	PassedThrough(ClientWindow),
+	/// One of AquariWM's own windows.
+	Utility(UtilityWindow),
+	/// An override-redirect window, like a menu or a tooltip, which its client places and stacks
+	/// itself, so it is never managed.
+	Unmanaged(x11::Window),
+	/// A window which AquariWM doesn't know about, e.g. because it was only just created, or
+	/// because it no longer exists.
+	Unknown(x11::Window),
+}
+
+macro_rules! impl_window {
+	($($Window:ident),+) => {
+		$(
+			impl $Window {
+				/// Wraps the given raw window ID.
+				#[inline(always)]
+				pub const fn new(window: x11::Window) -> Self {
+					Self(window)
+				}
+
+				/// Returns the raw window ID, to make a request for the window.
+				#[inline(always)]
+				pub const fn raw(self) -> x11::Window {
+					self.0
+				}
+			}
+
+			impl Display for $Window {
+				fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+					Display::fmt(&self.0, f)
+				}
+			}
+		)+
+	};
+}
+
+impl_window!(ClientWindow, UtilityWindow);
+
+/// A client window's [ID] is its raw window ID.
+///
+/// [ID]: WindowId
+impl ManagedWindow for ClientWindow {
+	#[inline(always)]
+	fn id(&self) -> WindowId {
+		WindowId::from(self.0)
+	}
+}
+
+impl ClientWindow {
+	/// Resolves the given [ID] to the client window it is the ID of, or returns [`None`] if it
+	/// can't be a raw window ID.
+	///
+	/// Whether the window is managed, or even exists, is up to the caller to check.
+	///
+	/// [ID]: WindowId
+	pub fn from_id(id: WindowId) -> Option<Self> {
+		x11::Window::try_from(id.0).ok().map(Self)
+	}
+}
+
+impl Display for WindowRole {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Root => write!(f, "the root window"),
+			Self::Client(window) => write!(f, "client window {window}"),
+			Self::PassedThrough(window) => write!(f, "ignored window {window}"),
+			Self::Utility(window) => write!(f, "AquariWM's window {window}"),
+			Self::Unmanaged(window) => write!(f, "override-redirect window {window}"),
+			Self::Unknown(window) => write!(f, "unknown window {window}"),
+		}
+	}
+}
@@ -0,0 +1,2885 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Deciding what to do in response to events from the X server.
+//!
+//! Each event is handled by an `on_*` method of [`Wm`], which updates AquariWM's state and returns
+//! the [requests] to make as a result, rather than making them: they are [dispatched] by the
+//! caller. Anything which has to be asked of the X server first, like a window's class, is queried
+//! by the caller and passed in, and anything else which has to be done outside of the window
+//! manager, like launching a program, is returned as a [command] for the caller to carry out.
+//!
+//! [requests]: Request
+//! [dispatched]: super::X11::dispatch
+//! [command]: SessionCommand
+
+use std::{
+	collections::{HashMap, HashSet},
+	mem,
+	path::PathBuf,
+	time::{Duration, Instant, SystemTime},
+};
+
+use tracing::{event, Level};
+use x11rb_async::protocol::xproto as x11;
+
+use super::{
+	bindings::{Binding, Keys},
+	dispatch::Request,
+	focus::{InputModel, Target},
+	modifiers::ModifierState,
+	reparent::Reparent,
+	replay::{Input, Recorder},
+	snapshot::ClientSnapshot,
+	util::{Atoms, ConfigureValues},
+	visual::{Decorations, Palettes, Visual},
+	window::{ClientWindow, UtilityWindow, WindowRole},
+	wm_state::StateRequest,
+	CURRENT_TIME,
+};
+use crate::{
+	action::{Action, Outcome},
+	admission::{self, Admission, Signals, WindowType},
+	appearance::Appearance,
+	coalesce::{Coalescer, PendingApply},
+	configure_guard::{ConfigureGuard, Verdict},
+	desktop::{self, Desktop},
+	dispatch_queue::{DispatchQueue, Ordered, Phase},
+	fullscreen::{self, FocusLock, FocusRequest, FullscreenPolicy},
+	ignore::Matcher,
+	launch::{self, Launches},
+	layout::{self, AddWindowError},
+	metrics::{Metrics, Operation},
+	moveresize::{self, Direction, Drag},
+	output::{Output, OutputAssignment, Routing},
+	pid::VerifiedPid,
+	ping::{KillConfirmation, PingSettings, Pings, Pong, KILL_CONFIRMATION},
+	placement::{self, FloatPreset, Geometry, SizeHints},
+	placement_memory::{Placement, PlacementMemory},
+	query::{self, Answer, Query},
+	rules::{Rule, RuleAction, Rules, Transition},
+	stacking::{self, Position},
+	state::{self, AquariWm, FocusCause, WorkspaceChange},
+	switcher::{self, Step},
+	theme::Slot,
+	urgency::{self, FocusStealing, WindowKind},
+	window::ManagedWindow,
+	window_groups::{GroupAction, WindowGroups},
+	window_list::{self, WindowList},
+	workspaces::{Name, Workspaces},
+};
+
+mod floating;
+mod focus;
+mod input;
+mod map;
+mod messages;
+mod properties;
+
+/// The part of the X11 window manager which doesn't depend on the connection: what it knows about
+/// the windows it manages, beyond their place in the layout.
+#[derive(Debug)]
+pub struct Wm {
+	/// The root window for the screen.
+	pub root: x11::Window,
+	/// The whole screen, which floating windows are kept on if there are no outputs.
+	pub screen: placement::Rect,
+
+	/// Windows which have been unmapped by AquariWM because they are [hidden] or [iconified].
+	///
+	/// [hidden]: layout::Visibility::Hidden
+	/// [iconified]: AquariWm::iconify_window
+	pub hidden: HashSet<ClientWindow>,
+	/// The number of `UnmapNotify` events expected for each window as a result of AquariWM
+	/// unmapping it to hide it.
+	///
+	/// These events are ignored, so that hidden windows aren't removed from the layout.
+	pub expected_unmaps: HashMap<ClientWindow, usize>,
+	/// The class of each mapped window which has a `WM_CLASS` property.
+	///
+	/// This is used to [group windows by class].
+	///
+	/// [group windows by class]: layout::InsertionStrategy::GroupByClass
+	pub classes: HashMap<ClientWindow, String>,
+	/// The title of each window which has a `_NET_WM_NAME` or `WM_NAME` property, updated as it
+	/// changes.
+	pub titles: HashMap<ClientWindow, String>,
+
+	/// Windows which have been dimmed by setting their `_NET_WM_WINDOW_OPACITY` property.
+	pub dimmed: HashSet<ClientWindow>,
+	/// Windows which are demanding attention with the urgency hint in their `WM_HINTS` property.
+	pub urgent: HashSet<ClientWindow>,
+	/// Windows which are fullscreen according to their `_NET_WM_STATE` property.
+	pub fullscreen: HashSet<ClientWindow>,
+	/// The geometry of each [floating] window, as last configured by AquariWM.
+	///
+	/// AquariWM is the source of truth for floating windows' geometry: their configure requests
+	/// are merged over this geometry rather than querying the X server each time.
+	///
+	/// [floating]: layout::Mode::Floating
+	pub floating: HashMap<ClientWindow, Geometry>,
+	/// The stacking order of the shown [floating] windows, which are kept above the tiled windows.
+	///
+	/// [floating]: layout::Mode::Floating
+	pub floating_layer: stacking::Layer<ClientWindow>,
+	/// The [input model] of each window's client which has been focused, read from its `WM_HINTS`
+	/// and `WM_PROTOCOLS` properties.
+	pub input_models: HashMap<ClientWindow, InputModel>,
+	/// The geometry each window had when it was first mapped, before AquariWM configured it.
+	///
+	/// Tiled windows are restored to this geometry when they are [ignored].
+	///
+	/// [ignored]: crate::ignore
+	pub original: HashMap<ClientWindow, Geometry>,
+	/// The [visual] of each window, queried when it was first mapped, which decides how its border
+	/// colors are encoded.
+	///
+	/// [visual]: super::visual
+	pub visuals: HashMap<ClientWindow, Visual>,
+	/// The [snapshot] of each managed window's properties, fetched when it was last mapped and
+	/// updated as its properties change.
+	///
+	/// [snapshot]: ClientSnapshot
+	pub snapshots: HashMap<ClientWindow, ClientSnapshot>,
+	/// The [theme]'s colors, resolved into pixels for the screen and each visual windows are
+	/// decorated with.
+	///
+	/// [theme]: crate::theme
+	pub palettes: Palettes,
+	/// The border pixel each managed window was last given by [`paint_borders`], so that borders
+	/// are only set when their colors change.
+	///
+	/// [`paint_borders`]: Self::paint_borders
+	pub painted: HashMap<ClientWindow, u32>,
+	/// The lone tiled window whose border has been removed by [smart borders], if any.
+	///
+	/// [smart borders]: crate::appearance::Appearance::smart_borders
+	pub borderless: Option<ClientWindow>,
+
+	/// The fullscreen window in [game mode], if there is one.
+	///
+	/// [game mode]: FullscreenPolicy::GameMode
+	pub focus_lock: FocusLock<ClientWindow>,
+	/// The `_NET_WM_BYPASS_COMPOSITOR` property each window in [game mode] had before AquariWM set
+	/// it, which is restored when the window leaves game mode.
+	///
+	/// [game mode]: FullscreenPolicy::GameMode
+	pub bypassed: HashMap<ClientWindow, Option<u32>>,
+
+	/// The rules which change where windows are placed, and which of them have applied to which
+	/// windows.
+	pub rules: Rules<ClientWindow>,
+
+	/// The mapped windows which are [ignored], with their classes if they have any.
+	///
+	/// These windows are passed through: they aren't tracked in AquariWM's state, and their
+	/// configure requests are honored verbatim.
+	///
+	/// [ignored]: crate::ignore
+	pub passed_through: HashMap<ClientWindow, Option<String>>,
+
+	/// The windows created by AquariWM for its own use, which are never managed.
+	pub utility: HashSet<UtilityWindow>,
+	/// Whether each window which has been [classified] is override-redirect, as last seen in its
+	/// events or queried, until it is destroyed.
+	///
+	/// [classified]: Self::classify
+	pub override_redirect: HashMap<x11::Window, bool>,
+	/// The overlay showing where the next window would be tiled.
+	pub preview_overlay: Overlay,
+	/// The overlay showing where a tiled window being [dragged] would be dropped.
+	///
+	/// [dragged]: crate::moveresize::Drag::Swap
+	pub drop_overlay: Overlay,
+
+	/// The re-tile deferred while windows are mapped and unmapped in quick succession.
+	pub pending_apply: PendingApply,
+	/// How long windows without clear signals that they are normal windows float for before they
+	/// are [admitted] into the tiling layout, or zero to tile them straight away.
+	///
+	/// [admitted]: crate::admission
+	pub admission_delay: Duration,
+	/// The windows waiting to be reconfigured by a [time-sliced] re-tile.
+	///
+	/// [time-sliced]: crate::dispatch_queue
+	pub dispatch_queue: DispatchQueue<ClientWindow>,
+	/// The requests held until the end of the event loop iteration, to be sent in [phase] order
+	/// after everything else.
+	///
+	/// [phase]: crate::dispatch_queue::Phase
+	pub ordered: Ordered<Request>,
+	/// The window to be given focus once it is shown, if focus was given to it while it was
+	/// [hidden].
+	pub held_focus: Option<ClientWindow>,
+	/// The geometry last given to each tiled window, which their configure requests are answered
+	/// with, and how often it has been re-asserted.
+	pub configure_guard: ConfigureGuard<ClientWindow>,
+	/// The latencies of user-visible operations, keyed by the window they are for, if [enabled].
+	///
+	/// [enabled]: crate::display_server::Options::latency_metrics
+	pub metrics: Metrics<Option<ClientWindow>>,
+	/// The outstanding pings of windows, and which windows' clients are [unresponsive].
+	///
+	/// [unresponsive]: crate::ping
+	pub pings: Pings<ClientWindow>,
+	/// The [window groups] of the managed windows, as named in their `WM_HINTS` properties.
+	///
+	/// [window groups]: crate::window_groups
+	pub groups: WindowGroups<ClientWindow>,
+
+	/// The atoms which client messages and property changes are recognized by.
+	pub atoms: Atoms,
+	/// The server timestamp of the latest event which had one.
+	///
+	/// Focus is given as of this timestamp rather than `CurrentTime`, as the ICCCM requires.
+	pub timestamp: x11::Timestamp,
+	/// How windows are shown, e.g. whether unfocused windows are dimmed.
+	pub appearance: Appearance,
+	/// Whether urgent windows may steal focus.
+	pub focus_stealing: FocusStealing,
+	/// Which output new windows open on, if nothing else decides it.
+	pub output_assignment: OutputAssignment,
+	/// Whether windows on other screens are mapped when they ask to be, without being managed.
+	pub map_other_screens: bool,
+
+	/// The key bindings, as they were last grabbed.
+	pub keys: Keys,
+	/// The modifiers held, as tracked from raw key events.
+	pub held_modifiers: ModifierState,
+	/// When the user last pressed a key, which decides whether urgent windows may steal focus.
+	pub last_input: Option<Instant>,
+	/// Programs launched by key bindings, so that their windows open where they were launched.
+	pub launches: Launches,
+	/// The window being moved or resized by the pointer, if any.
+	pub drag: Option<Drag<ClientWindow>>,
+	/// The pointer's latest position during a [drag], which is acted on at most once per frame.
+	///
+	/// [drag]: Self::drag
+	pub drag_motion: Coalescer<(i32, i32)>,
+	/// Whether the focused window has been moved since the pointer was last considered for
+	/// warping to it.
+	pub focused_moved: bool,
+}
+
+/// What is known about a window when its map request is handled, queried from the X server
+/// beforehand.
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+	/// The window's class, if it has a `WM_CLASS` property.
+	pub class: Option<String>,
+	/// The output which was active when the window's program was launched by AquariWM, if it was.
+	pub launch_output: Option<Output>,
+	/// Where the last window of the window's class was closed, if the window reopens there.
+	pub remembered: Option<Placement>,
+	/// Whether the window asked to be sticky in its `_NET_WM_STATE` property.
+	pub sticky: bool,
+	/// The desktop the window asked to open on in its `_NET_WM_DESKTOP` property, if any.
+	pub desktop: Option<Desktop>,
+	/// The window's geometry before it is mapped.
+	pub geometry: Option<Geometry>,
+	/// The window's visual, if it could be queried.
+	pub visual: Option<Visual>,
+	/// What the window's `_NET_WM_WINDOW_TYPE` property says it is.
+	pub window_type: WindowType,
+}
+
+/// What is queried from the X server to handle a window being mapped, beforehand.
+#[derive(Debug, Clone)]
+pub struct Prefetched {
+	/// The window's properties, attributes and geometry.
+	pub snapshot: ClientSnapshot,
+	/// The pointer's position, if the [output assignment] follows it.
+	///
+	/// [output assignment]: Wm::output_assignment
+	pub pointer: Option<(i32, i32)>,
+}
+
+/// Something which must be done outside of the window manager in response to an event, which is
+/// returned to the caller to carry out.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SessionCommand {
+	/// Runs the given shell command, or launches the terminal if [`None`].
+	Spawn(Option<String>),
+	/// Kills the verified process of the given window's client, which has been force killed.
+	KillProcess(ClientWindow, VerifiedPid),
+
+	/// Starts or ends a dry run.
+	ToggleDryRun,
+	/// Saves the [ignore list], which has changed.
+	///
+	/// [ignore list]: AquariWm::ignored
+	SaveIgnoreList,
+	/// Shows the given wallpaper image.
+	SetWallpaper(PathBuf),
+	/// Reloads the configuration.
+	ReloadConfig,
+}
+
+/// An overlay window created by AquariWM to highlight part of the screen, which is created when it
+/// is first shown.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct Overlay {
+	/// The overlay window, once it has been created.
+	pub window: Option<UtilityWindow>,
+	/// The rectangle the overlay is shown over, if it is shown.
+	pub shown: Option<placement::Rect>,
+}
+
+impl Wm {
+	/// Creates the state of a window manager for the given `root` window, which isn't managing
+	/// any windows yet, with the given `fullscreen_policy` and `rules`.
+	pub fn new(
+		root: x11::Window,
+		screen: placement::Rect,
+		fullscreen_policy: FullscreenPolicy,
+		rules: Vec<Rule>,
+	) -> Self {
+		Self {
+			root,
+			screen,
+
+			hidden: HashSet::new(),
+			expected_unmaps: HashMap::new(),
+			classes: HashMap::new(),
+			titles: HashMap::new(),
+
+			dimmed: HashSet::new(),
+			urgent: HashSet::new(),
+			fullscreen: HashSet::new(),
+			floating: HashMap::new(),
+			floating_layer: stacking::Layer::new(),
+			input_models: HashMap::new(),
+			original: HashMap::new(),
+			visuals: HashMap::new(),
+			snapshots: HashMap::new(),
+			palettes: Palettes::default(),
+			painted: HashMap::new(),
+			borderless: None,
+
+			focus_lock: FocusLock::new(fullscreen_policy),
+			bypassed: HashMap::new(),
+
+			rules: Rules::new(rules),
+
+			passed_through: HashMap::new(),
+
+			utility: HashSet::new(),
+			override_redirect: HashMap::new(),
+			preview_overlay: Overlay::default(),
+			drop_overlay: Overlay::default(),
+
+			pending_apply: PendingApply::default(),
+			admission_delay: admission::DEFAULT_ADMISSION_DELAY,
+			dispatch_queue: DispatchQueue::default(),
+			ordered: Ordered::new(),
+			held_focus: None,
+			configure_guard: ConfigureGuard::default(),
+			metrics: Metrics::new(false),
+			pings: Pings::new(PingSettings::default(), Instant::now()),
+			groups: WindowGroups::default(),
+
+			atoms: Atoms::synthetic(),
+			timestamp: CURRENT_TIME,
+			appearance: Appearance::default(),
+			focus_stealing: FocusStealing::default(),
+			output_assignment: OutputAssignment::default(),
+			map_other_screens: false,
+
+			keys: Keys::default(),
+			held_modifiers: ModifierState::default(),
+			last_input: None,
+			launches: Launches::default(),
+			drag: None,
+			drag_motion: Coalescer::default(),
+			focused_moved: false,
+		}
+	}
+
+	/// Resolves what the given raw `window` ID from an event is to AquariWM.
+	///
+	/// Client windows are only [managed] if they are in the `state`'s client map, so an ID which is
+	/// stale because its window was destroyed resolves to [`WindowRole::Unknown`]. Windows which
+	/// aren't otherwise known are [unmanaged] if they have been [classified] as override-redirect.
+	///
+	/// [managed]: WindowRole::Client
+	/// [unmanaged]: WindowRole::Unmanaged
+	/// [classified]: Self::classify
+	pub fn resolve_window(&self, state: &AquariWm<ClientWindow>, window: x11::Window) -> WindowRole {
+		if window == self.root {
+			return WindowRole::Root;
+		}
+
+		let client = ClientWindow::new(window);
+		if state.windows.contains_key(&client) {
+			return WindowRole::Client(client);
+		}
+		if self.passed_through.contains_key(&client) {
+			return WindowRole::PassedThrough(client);
+		}
+
+		let utility = UtilityWindow::new(window);
+		if self.utility.contains(&utility) {
+			return WindowRole::Utility(utility);
+		}
+
+		match self.override_redirect.get(&window) {
+			Some(true) => WindowRole::Unmanaged(window),
+			_ => WindowRole::Unknown(window),
+		}
+	}
+
+	/// Records whether the given `window` is `override_redirect`, as seen in one of its events or
+	/// queried.
+	///
+	/// Override-redirect windows, like menus and tooltips, are placed and stacked by their clients
+	/// themselves: they are never managed, so AquariWM never configures, restacks, or focuses them.
+	pub fn classify(&mut self, window: x11::Window, override_redirect: bool) {
+		self.override_redirect.insert(window, override_redirect);
+	}
+
+	/// Returns whether the given `window` has been [classified].
+	///
+	/// [classified]: Self::classify
+	pub fn is_classified(&self, window: x11::Window) -> bool {
+		self.override_redirect.contains_key(&window)
+	}
+
+	/// [Resolves] the given raw `window` ID from an event which is only generated for top-level
+	/// windows, like a map request, to a client window.
+	///
+	/// As AquariWM's own windows are [resolved] first, an [unknown] window is taken to be a client
+	/// window which isn't tracked yet. Returns [`None`] for the root window and AquariWM's own
+	/// windows.
+	///
+	/// [Resolves]: Self::resolve_window
+	/// [resolved]: Self::resolve_window
+	/// [unknown]: WindowRole::Unknown
+	pub fn resolve_top_level(&self, state: &AquariWm<ClientWindow>, window: x11::Window) -> Option<ClientWindow> {
+		match self.resolve_window(state, window) {
+			WindowRole::Client(window) | WindowRole::PassedThrough(window) => Some(window),
+			WindowRole::Unknown(window) => Some(ClientWindow::new(window)),
+
+			role => {
+				event!(Level::DEBUG, "Not treating {role} as a client window");
+
+				None
+			},
+		}
+	}
+
+	/// Starts tracking the state of a newly created window, unless it is override-redirect.
+	pub fn on_create_notify(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		notify: &x11::CreateNotifyEvent,
+	) -> Vec<Request> {
+		self.classify(notify.window, notify.override_redirect);
+
+		if let Some(window) = self.resolve_top_level(state, notify.window) {
+			self.manage(state, window);
+		}
+
+		Vec::new()
+	}
+
+	/// Stops tracking the state of a destroyed window, remembering its placement first.
+	pub fn on_destroy_notify(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		placements: &mut PlacementMemory,
+		notify: &x11::DestroyNotifyEvent,
+	) -> Vec<Request> {
+		let role = self.resolve_window(state, notify.window);
+		// The window's ID may be reused for a new window.
+		self.override_redirect.remove(&notify.window);
+
+		match role {
+			WindowRole::Client(window) | WindowRole::PassedThrough(window) => {
+				self.remember_placement(state, placements, window);
+				self.unmanage(state, window);
+			},
+			WindowRole::Utility(window) => {
+				self.utility.remove(&window);
+			},
+
+			WindowRole::Root | WindowRole::Unmanaged(_) | WindowRole::Unknown(_) => (),
+		}
+
+		Vec::new()
+	}
+
+	/// Maps a window which asked to be mapped, tiling it unless it is floating.
+	///
+	/// [Ignored] windows are passed through before any other policy is applied to them; windows
+	/// which were passed through but are no longer ignored are managed again. Windows reopen where
+	/// the last window of their class was closed if that was remembered, overriding the
+	/// [insertion strategy], or else on the output they were launched on.
+	///
+	/// [Ignored]: crate::ignore
+	/// [insertion strategy]: layout::InsertionStrategy
+	pub fn on_map_request(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		request: &x11::MapRequestEvent,
+		client: Client,
+	) -> Vec<Request> {
+		let Some(window) = self.resolve_top_level(state, request.window) else {
+			return Vec::new();
+		};
+		let Client {
+			class,
+			launch_output,
+			remembered,
+			sticky,
+			desktop,
+			geometry,
+			visual,
+			..
+		} = client;
+
+		if state.ignored.matches(window.id(), class.as_deref()) {
+			let mut requests = self.pass_through(state, window, class);
+			requests.push(Request::Map(window));
+
+			return requests;
+		}
+		// Windows which were passed through aren't tracked.
+		if !state.windows.contains_key(&window) {
+			self.manage(state, window);
+		}
+		// Clients restore their iconified windows by mapping them.
+		let iconified = state.is_iconified(&window);
+		if iconified {
+			self.hidden.remove(&window);
+		}
+		if let Some(geometry) = geometry {
+			self.original.entry(window).or_insert(geometry);
+		}
+		if let Some(visual) = visual {
+			self.visuals.entry(window).or_insert(visual);
+		}
+
+		let remembered_tile = remembered.as_ref().is_some_and(|placement| placement.path.is_some());
+		let mapped = match class {
+			Some(class) => {
+				// A remembered tile takes precedence over grouping windows by class.
+				let mapped = match remembered_tile {
+					true => state.map_window(&window),
+					false => state.map_window_grouped_by(&window, |other| self.classes.get(other) == Some(&class)),
+				};
+				self.classes.insert(window, class);
+
+				mapped
+			},
+
+			None => state.map_window(&window),
+		};
+
+		// A replayed map request for a window which is already tiled re-maps it with its existing
+		// geometry.
+		let added = mapped.is_ok();
+		match mapped {
+			Err(AddWindowError::AlreadyPresent) => {
+				event!(Level::DEBUG, "Re-applying geometry of tiled window {window}");
+				state.refresh_window(&window);
+			},
+
+			Ok(()) => {
+				match (&launch_output, &remembered) {
+					(Some(output), _) => {
+						state.move_window_to_output(&window, &output.name);
+					},
+					(None, Some(placement)) => {
+						placement.restore(state, &window);
+					},
+
+					(None, None) => (),
+				}
+
+				// Clients may ask for their windows to be sticky before mapping them, or to open on
+				// every desktop or another workspace, which hides them until it is switched to.
+				let desktop = Self::resolve_desktop(window, desktop, remembered.as_ref(), &state.workspaces);
+				state.set_sticky(&window, sticky || desktop == Desktop::All);
+
+				match desktop {
+					Desktop::Index(index) if index as usize != state.workspaces.current() => {
+						let moved =
+							state.move_window_to_workspace(&window, index as usize, Self::same_class(&self.classes));
+
+						if let Err(error) = moved {
+							event!(
+								Level::WARN,
+								"Failed to open window {window} on desktop {index}: {error}"
+							);
+						}
+					},
+
+					_ => (),
+				}
+			},
+		}
+
+		// Newly tiled windows are only mapped once the re-tile has configured them to their tiles, so
+		// that they never appear at the geometry their clients chose.
+		let tiled = added
+			&& state
+				.tiling_layout()
+				.is_some_and(|layout| layout.contains_window(&window));
+
+		let mut requests = Vec::new();
+		match tiled || state.is_away(&window) {
+			true => {
+				self.hidden.insert(window);
+			},
+			false => requests.push(Request::Map(window)),
+		}
+		if iconified {
+			requests.push(Request::SetIconic(window, false));
+		}
+
+		requests
+	}
+
+	/// Returns the [desktop] the given `window` opens on out of the `workspaces`, given the desktop
+	/// its client `requested` and where its class was `remembered`.
+	///
+	/// The workspace after the last is available, as it is created when a window opens on it.
+	/// Placement memory only remembers whether windows were sticky, not which workspaces they were
+	/// on.
+	///
+	/// [desktop]: desktop
+	fn resolve_desktop(
+		window: ClientWindow,
+		requested: Option<Desktop>,
+		remembered: Option<&Placement>,
+		workspaces: &Workspaces<ClientWindow>,
+	) -> Desktop {
+		let candidates = desktop::Candidates {
+			rule: None,
+			requested,
+			remembered: remembered.filter(|placement| placement.sticky).map(|_| Desktop::All),
+		};
+		let (current, available) = (workspaces.current() as u32, workspaces.names().len() as u32 + 1);
+		let (desktop, source) = desktop::resolve(candidates, current, available);
+
+		if requested.is_some_and(|requested| requested != desktop) {
+			event!(
+				Level::DEBUG,
+				"Opening window {window} on desktop {desktop:?} from {source:?} rather than requested desktop \
+				 {requested:?}"
+			);
+		}
+
+		desktop
+	}
+
+	/// Holds the given `window`, which is about to be mapped, back from the tiling layout for the
+	/// [admission delay] if the `client` lacks clear [signals] that it is a normal window,
+	/// returning whether it was held back.
+	///
+	/// A window held back floats until it is [admitted]; the caller is responsible for giving it a
+	/// floating geometry. Its title must be cached first, so that rules which match on it are
+	/// taken into account.
+	///
+	/// [admission delay]: Self::admission_delay
+	/// [signals]: Signals
+	/// [admitted]: Self::admit_due
+	pub fn hold_admission(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		client: &Client,
+		now: Instant,
+	) -> bool {
+		if self.admission_delay.is_zero() {
+			return false;
+		}
+
+		let class = client.class.as_deref();
+		let title = self.titles.get(&window).map(String::as_str);
+		let signals = Signals {
+			class: class.is_some(),
+			window_type: client.window_type,
+			zero_size: client
+				.geometry
+				.is_some_and(|geometry| geometry.width <= 1 || geometry.height <= 1),
+			ruled: self.rules.rules().iter().any(|rule| rule.matches(class, title)),
+		};
+		if !signals.needs_admission() || !state.hold_admission(&window, now + self.admission_delay) {
+			return false;
+		}
+
+		event!(
+			Level::DEBUG,
+			"Holding window {window} back from the tiling layout until it is admitted: {signals:?}"
+		);
+		true
+	}
+
+	/// Admits the windows [held back] from the tiling layout whose admission delay has passed by
+	/// the time `now`, returning those which are no longer floating.
+	///
+	/// Windows which a rule has applied to in the meantime stay where the rule put them.
+	///
+	/// [held back]: Self::hold_admission
+	pub fn admit_due(&mut self, state: &mut AquariWm<ClientWindow>, now: Instant) -> Vec<ClientWindow> {
+		let mut admitted = Vec::new();
+
+		for window in state.due_admissions(now) {
+			if self.rules.applied_to(&window).is_some() {
+				if let Some(window_state) = state.windows.get_mut(&window) {
+					window_state.admission = Admission::Admitted;
+				}
+
+				continue;
+			}
+
+			let class = self.classes.get(&window);
+			state.admit_window_grouped_by(&window, |other| class.is_some() && self.classes.get(other) == class);
+
+			if state.windows[&window].mode == layout::Mode::Tiled {
+				event!(Level::DEBUG, "Admitted window {window} into the tiling layout");
+
+				self.floating.remove(&window);
+				admitted.push(window);
+			}
+		}
+
+		admitted
+	}
+
+	/// Un-manages a window reparented away from the root window, as it is no longer a top-level
+	/// window, and manages a window reparented back to the root window again.
+	///
+	/// A window managed again is mapped in response to the map request which follows.
+	pub fn on_reparent_notify(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		notify: &x11::ReparentNotifyEvent,
+	) -> Vec<Request> {
+		let (window, parent) = (notify.window, notify.parent);
+
+		match Reparent::classify(parent, self.root) {
+			Reparent::Away => {
+				if let WindowRole::Client(window) | WindowRole::PassedThrough(window) =
+					self.resolve_window(state, window)
+				{
+					event!(
+						Level::DEBUG,
+						"Window {window} was reparented to {parent}; un-managing it"
+					);
+					self.unmanage(state, window);
+				}
+			},
+			Reparent::ToRoot => {
+				if let Some(window) = self.resolve_top_level(state, window) {
+					event!(
+						Level::DEBUG,
+						"Window {window} was reparented to the root window; managing it"
+					);
+					self.manage(state, window);
+				}
+			},
+		}
+
+		Vec::new()
+	}
+
+	/// Updates the state of an unmapped window, remembering its placement first. Windows unmapped
+	/// by AquariWM to hide them remain in the layout.
+	pub fn on_unmap_notify(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		placements: &mut PlacementMemory,
+		notify: &x11::UnmapNotifyEvent,
+	) -> Vec<Request> {
+		let window = match self.resolve_window(state, notify.window) {
+			WindowRole::Client(window) => window,
+
+			// Windows which are passed through are only remembered while they are mapped.
+			WindowRole::PassedThrough(window) => {
+				self.passed_through.remove(&window);
+
+				return Vec::new();
+			},
+			_ => return Vec::new(),
+		};
+
+		if self.take_expected_unmap(window) {
+			return Vec::new();
+		}
+
+		self.remember_placement(state, placements, window);
+		state.unmap_window(&window);
+		self.rules.withdraw(state, &window);
+		// Windows which vanished before they were admitted only floated while they waited.
+		if state.windows[&window].admission == Admission::Vanished {
+			event!(Level::DEBUG, "Window {window} vanished before it was admitted");
+
+			self.floating.remove(&window);
+		}
+
+		// Closed windows leave game mode.
+		match self.focus_lock.holder() == Some(&window) {
+			true => self.leave_game_mode(),
+			false => Vec::new(),
+		}
+	}
+
+	/// Returns the client window with the given raw ID if it is managed and mapped, so that the
+	/// layout settling once it is unmapped can be [measured].
+	///
+	/// [measured]: Self::metrics
+	pub fn mapped_client(&self, state: &AquariWm<ClientWindow>, window: x11::Window) -> Option<ClientWindow> {
+		match self.resolve_window(state, window) {
+			WindowRole::Client(window)
+				if state
+					.windows
+					.get(&window)
+					.is_some_and(|window_state| window_state.mapped == state::MapState::Mapped) =>
+			{
+				Some(window)
+			},
+
+			_ => None,
+		}
+	}
+
+	/// Evaluates the [rules] for the given `window`, either because it was just mapped or because
+	/// its title changed.
+	///
+	/// [rules]: crate::rules
+	pub fn evaluate_rules(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		title_changed: bool,
+	) -> Transition {
+		let class = self.classes.get(&window).map(String::as_str);
+		let title = self.titles.get(&window).map(String::as_str);
+		let floating = self
+			.floating
+			.get(&window)
+			.map(|geometry| (geometry.x, geometry.y, geometry.width, geometry.height));
+
+		match title_changed {
+			true => self.rules.on_title_change(state, &window, class, title, floating),
+			false => self.rules.on_map(state, &window, class, title, floating),
+		}
+	}
+
+	/// Returns the [list] of managed windows for external window switchers, assembled from what is
+	/// known about them rather than querying the X server.
+	///
+	/// [list]: WindowList
+	pub fn window_list(&self, state: &AquariWm<ClientWindow>) -> WindowList {
+		WindowList::of(state, &self.floating_order(state), |window| window_list::Details {
+			title: self.titles.get(window).map(String::as_str),
+			class: self.classes.get(window).map(String::as_str),
+
+			floating: self
+				.floating
+				.get(window)
+				.map(|geometry| (geometry.x, geometry.y, geometry.width, geometry.height)),
+			urgent: self.is_urgent(window),
+			unresponsive: self.pings.is_unresponsive(window),
+			fullscreen: self.fullscreen.contains(window),
+		})
+	}
+
+	/// [Answers] the given geometry `query` from what is known about the managed windows, rather
+	/// than querying the X server.
+	///
+	/// [Answers]: query::answer
+	pub fn answer(&self, state: &AquariWm<ClientWindow>, query: &Query) -> Option<Answer> {
+		query::answer(
+			state,
+			query,
+			|id| ClientWindow::from_id(id).filter(|window| state.windows.contains_key(window)),
+			|window| {
+				self.floating
+					.get(window)
+					.map(|geometry| (geometry.x, geometry.y, geometry.width, geometry.height))
+			},
+		)
+	}
+
+	/// Answers a query of the latencies measured so far, or says that they aren't measured.
+	pub fn answer_latency(&self) -> String {
+		match self.metrics.is_enabled() {
+			true => format!("Latencies: {}", self.metrics.report()),
+			false => "Latencies aren't measured; run AquariWM with --latency-metrics".to_owned(),
+		}
+	}
+
+	/// Records whether the given `window` is `fullscreen`, and puts it into [game mode] if it has
+	/// become fullscreen, or takes it out of game mode if it no longer is, returning the requests
+	/// to set or restore its `_NET_WM_BYPASS_COMPOSITOR` property.
+	///
+	/// `bypass` is the window's `_NET_WM_BYPASS_COMPOSITOR` property, if it has one. A window
+	/// which explicitly asked to be composited keeps its hint. If another window was in game mode,
+	/// it leaves it: only the latest fullscreen window is in game mode.
+	///
+	/// [game mode]: FullscreenPolicy::GameMode
+	pub fn fullscreen_changed(&mut self, window: ClientWindow, fullscreen: bool, bypass: Option<u32>) -> Vec<Request> {
+		const BYPASS: u32 = 1;
+		const NEVER_BYPASS: u32 = 2;
+
+		match fullscreen {
+			true => self.fullscreen.insert(window),
+			false => self.fullscreen.remove(&window),
+		};
+		let holder = self.focus_lock.holder() == Some(&window);
+
+		match (fullscreen, holder) {
+			(true, false) if self.focus_lock.policy().is_game_mode() => {
+				let mut requests = self.leave_game_mode();
+
+				event!(Level::DEBUG, "Window {window} is entering game mode");
+				self.focus_lock.lock(window);
+
+				if bypass != Some(NEVER_BYPASS) {
+					self.bypassed.insert(window, bypass);
+					requests.push(Request::SetBypassCompositor(window, Some(BYPASS)));
+				}
+
+				requests
+			},
+			(false, true) => self.leave_game_mode(),
+
+			_ => Vec::new(),
+		}
+	}
+
+	/// Takes the window in [game mode] out of it, if there is one, returning the requests to
+	/// restore its `_NET_WM_BYPASS_COMPOSITOR` property.
+	///
+	/// [game mode]: FullscreenPolicy::GameMode
+	pub fn leave_game_mode(&mut self) -> Vec<Request> {
+		let Some(&window) = self.focus_lock.holder() else {
+			return Vec::new();
+		};
+
+		event!(Level::DEBUG, "Window {window} is leaving game mode");
+		self.focus_lock.release(&window);
+
+		match self.bypassed.remove(&window) {
+			Some(bypass) => vec![Request::SetBypassCompositor(window, bypass)],
+			None => Vec::new(),
+		}
+	}
+
+	/// Removes the border of the given `lone` tiled window, if it has changed, returning the
+	/// requests to do so and to give the window which was previously lone its border back.
+	///
+	/// Windows are given back the border they had when they were first mapped.
+	pub fn update_borders(&mut self, lone: Option<ClientWindow>) -> Vec<Request> {
+		if lone == self.borderless {
+			return Vec::new();
+		}
+		let mut requests = Vec::new();
+
+		if let Some(previous) = mem::replace(&mut self.borderless, lone) {
+			if let Some(geometry) = self.original.get(&previous) {
+				let aux = x11::ConfigureWindowAux::new().border_width(geometry.border_width);
+				requests.push(Request::Configure(previous, aux));
+			}
+		}
+		if let Some(window) = lone {
+			event!(Level::DEBUG, "Removing the border of lone window {window}");
+			requests.push(Request::Configure(
+				window,
+				x11::ConfigureWindowAux::new().border_width(0),
+			));
+		}
+
+		requests
+	}
+
+	/// Marks the clients of the windows whose [pings] have timed out as of the time `now` as
+	/// unresponsive, returning the requests to give their windows the [hung border color].
+	///
+	/// [pings]: crate::ping
+	/// [hung border color]: Slot::HungBorder
+	pub fn sweep_pings(&mut self, now: Instant) -> Vec<Request> {
+		let mut requests = Vec::new();
+
+		for window in self.pings.sweep(now) {
+			event!(Level::WARN, "The client of window {window} isn't responding to pings");
+
+			if let Some(pixel) = self.border_pixel(window, Slot::HungBorder) {
+				self.painted.insert(window, pixel);
+				requests.push(Request::SetBorder(window, Some(pixel)));
+			}
+		}
+
+		requests
+	}
+
+	/// Returns the requests to give each managed window the [theme]'s border color for its
+	/// state, for the windows whose border colors changed since they were last painted.
+	///
+	/// The border color of the windows of unresponsive clients takes precedence, then that of
+	/// windows demanding attention, then that of the focused window. Windows which are [left
+	/// undecorated] keep their own border colors.
+	///
+	/// [theme]: crate::theme
+	/// [left undecorated]: super::visual::Decorations::Undecorated
+	pub fn paint_borders(&mut self, state: &AquariWm<ClientWindow>) -> Vec<Request> {
+		let mut windows: Vec<_> = state.windows.keys().copied().collect();
+		// Windows are painted in a consistent order.
+		windows.sort_unstable();
+
+		let mut requests = Vec::new();
+		for window in windows {
+			let slot = if self.pings.is_unresponsive(&window) {
+				Slot::HungBorder
+			} else if self.is_urgent(&window) {
+				Slot::UrgentBorder
+			} else if state.focused == Some(window) {
+				Slot::FocusedBorder
+			} else {
+				Slot::UnfocusedBorder
+			};
+
+			let Some(pixel) = self.border_pixel(window, slot) else {
+				continue;
+			};
+			if self.painted.insert(window, pixel) != Some(pixel) {
+				requests.push(Request::SetBorder(window, Some(pixel)));
+			}
+		}
+
+		requests
+	}
+
+	/// Returns the pixel of the given `slot`'s color for the given `window`'s visual, or [`None`]
+	/// if the window is [left undecorated].
+	///
+	/// [left undecorated]: super::visual::Decorations::Undecorated
+	fn border_pixel(&mut self, window: ClientWindow, slot: Slot) -> Option<u32> {
+		let visual = self.visuals.get(&window);
+
+		self.palettes.for_visual(visual).map(|palette| palette.get(slot))
+	}
+
+	/// Returns the requests to give the windows whose borders were [painted] their own borders
+	/// back, e.g. because AquariWM is exiting.
+	///
+	/// [painted]: Self::paint_borders
+	pub fn reset_borders(&mut self) -> Vec<Request> {
+		let mut windows: Vec<_> = self.painted.drain().map(|(window, _)| window).collect();
+		// Windows are reset in a consistent order.
+		windows.sort_unstable();
+
+		windows
+			.into_iter()
+			.map(|window| Request::SetBorder(window, None))
+			.collect()
+	}
+
+	/// Honors a window's request to be configured at the time `now`.
+	///
+	/// Tiled windows which have been given a tile are kept in it, as [guarded] against configure
+	/// request loops: requests for the window's current geometry are only answered with a synthetic
+	/// `ConfigureNotify` event, and other requests by configuring the window to its tile again, at
+	/// most a limited number of times per second. The requests of other tiled windows and of
+	/// [ignored] windows are honored without modifying them. [Floating] windows with a known
+	/// geometry are configured as decided by [`placement::configure_floating`] according to their
+	/// `hints`: they are kept on the output their center is on, or the primary output (or the
+	/// [screen], without outputs) if their center isn't on any. If such a request couldn't be
+	/// honored as asked, the window is told its actual geometry with a synthetic `ConfigureNotify`
+	/// event, as the ICCCM requires.
+	///
+	/// Floating windows' requests to be restacked are [restacked in the floating layer] rather
+	/// than forwarded, so that they can't be stacked below the tiled windows.
+	///
+	/// [guarded]: crate::configure_guard
+	/// [ignored]: crate::ignore
+	/// [Floating]: layout::Mode::Floating
+	/// [screen]: Self::screen
+	/// [restacked in the floating layer]: Self::restack_floating
+	pub fn on_configure_request(
+		&mut self,
+		state: &AquariWm<ClientWindow>,
+		request: &x11::ConfigureRequestEvent,
+		hints: &SizeHints,
+		now: Instant,
+	) -> Vec<Request> {
+		let Some(window) = self.resolve_top_level(state, request.window) else {
+			return Vec::new();
+		};
+		let values = ConfigureValues::from(request);
+		let requested = placement::Requested {
+			x: values.x.map(i32::from),
+			y: values.y.map(i32::from),
+
+			width: values.width.map(u32::from),
+			height: values.height.map(u32::from),
+
+			border_width: values.border_width.map(u32::from),
+		};
+
+		let tiled = state
+			.tiling_layout()
+			.is_some_and(|layout| layout.contains_window(&window));
+		if tiled {
+			if let Some(requests) = self.answer_tiled(window, values, &requested, now) {
+				return requests;
+			}
+		}
+
+		let floating = state
+			.windows
+			.get(&window)
+			.is_some_and(|window_state| window_state.mode == layout::Mode::Floating);
+		if !floating {
+			return vec![Request::Configure(window, values.into())];
+		}
+
+		let mut requests = match values.stack_mode {
+			Some(stack_mode) => {
+				let sibling = values.sibling.map(ClientWindow::new);
+				self.restack_floating(state, window, stack_mode, sibling)
+			},
+			None => Vec::new(),
+		};
+		let Some(&current) = self.floating.get(&window) else {
+			let values = ConfigureValues {
+				sibling: None,
+				stack_mode: None,
+				..values
+			};
+			if values != ConfigureValues::default() {
+				requests.insert(0, Request::Configure(window, values.into()));
+			}
+
+			return requests;
+		};
+
+		let center = (
+			current.x + (current.width as i32) / 2,
+			current.y + (current.height as i32) / 2,
+		);
+		let output = placement::work_area(
+			Output::at(&state.outputs, center.0, center.1),
+			&state.outputs,
+			self.screen,
+		);
+
+		let (geometry, honored) = placement::configure_floating(current, &requested, hints.gravity, output);
+		self.floating.insert(window, geometry);
+
+		let aux = x11::ConfigureWindowAux::new()
+			.x(geometry.x)
+			.y(geometry.y)
+			.width(geometry.width)
+			.height(geometry.height)
+			.border_width(geometry.border_width);
+		requests.insert(0, Request::Configure(window, aux));
+
+		if !honored {
+			event!(
+				Level::DEBUG,
+				"Partially honored configure request of floating window {window}: kept at ({}, {}) with size {}x{}",
+				geometry.x,
+				geometry.y,
+				geometry.width,
+				geometry.height
+			);
+
+			requests.push(Request::NotifyConfigure(window, geometry));
+		}
+
+		requests
+	}
+
+	/// Answers the given tiled `window`'s configure request for the `requested` geometry as
+	/// decided by the [configure guard], or returns [`None`] if it hasn't been given a tile yet.
+	///
+	/// Requests to restack the window are still honored.
+	///
+	/// [configure guard]: Self::configure_guard
+	fn answer_tiled(
+		&mut self,
+		window: ClientWindow,
+		values: ConfigureValues,
+		requested: &placement::Requested,
+		now: Instant,
+	) -> Option<Vec<Request>> {
+		let verdict = self.configure_guard.on_request(&window, requested, now)?;
+		let (x, y, width, height) = self.configure_guard.asserted(&window)?;
+		let geometry = Geometry {
+			x,
+			y,
+			width,
+			height,
+			border_width: self.border_width(window),
+		};
+
+		let mut requests = Vec::new();
+		match verdict {
+			Verdict::Notify => requests.push(Request::NotifyConfigure(window, geometry)),
+
+			Verdict::Reassert => {
+				event!(
+					Level::DEBUG,
+					"Keeping tiled window {window} at ({x}, {y}) with size {width}x{height}"
+				);
+
+				// The window is told its geometry by the resulting `ConfigureNotify` event.
+				let aux = x11::ConfigureWindowAux::new().x(x).y(y).width(width).height(height);
+				requests.push(Request::Configure(window, aux));
+			},
+
+			Verdict::Limited { first } => {
+				if first {
+					event!(
+						Level::WARN,
+						"Tiled window {window} ({:?}) keeps asking for another geometry; ignoring its configure \
+						 requests for now",
+						self.classes.get(&window)
+					);
+				}
+
+				return Some(Vec::new());
+			},
+		}
+
+		let restack = ConfigureValues {
+			sibling: values.sibling,
+			stack_mode: values.stack_mode,
+			..ConfigureValues::default()
+		};
+		if restack != ConfigureValues::default() {
+			requests.insert(0, Request::Configure(window, restack.into()));
+		}
+
+		Some(requests)
+	}
+
+	/// Returns the border width the given `window` currently has: none if its border has been
+	/// removed by [smart borders], or otherwise the border width it had when it was first mapped.
+	///
+	/// [smart borders]: crate::appearance::Appearance::smart_borders
+	pub fn border_width(&self, window: ClientWindow) -> u32 {
+		match self.borderless == Some(window) {
+			true => 0,
+			false => self.original.get(&window).map_or(0, |geometry| geometry.border_width),
+		}
+	}
+
+	/// Restacks the given [floating] `window` in the floating layer as asked by the given
+	/// `stack_mode` and `sibling`, returning the requests to restack the windows whose places in
+	/// the stack changed.
+	///
+	/// The `sibling` is only used if it is another shown floating window; otherwise, the window is
+	/// moved to the top or the bottom of the floating layer. `TopIf` and `Opposite` are treated as
+	/// `Above`, and `BottomIf` as `Below`, as AquariWM doesn't track which windows occlude which.
+	///
+	/// [floating]: layout::Mode::Floating
+	pub fn restack_floating(
+		&mut self,
+		state: &AquariWm<ClientWindow>,
+		window: ClientWindow,
+		stack_mode: x11::StackMode,
+		sibling: Option<ClientWindow>,
+	) -> Vec<Request> {
+		let position = match stack_mode {
+			x11::StackMode::BELOW | x11::StackMode::BOTTOM_IF => Position::Below,
+			_ => Position::Above,
+		};
+
+		self.sync_floating_layer(state);
+		let restacks = self.floating_layer.restack(&window, position, sibling.as_ref());
+		event!(
+			Level::DEBUG,
+			"Restacking floating window {window}, moving {} windows",
+			restacks.len()
+		);
+
+		restacks.into_iter().map(Self::restack_request).collect()
+	}
+
+	/// Raises the given [floating] `window` to the top of the floating layer, returning the
+	/// requests to do so.
+	///
+	/// [floating]: layout::Mode::Floating
+	pub fn raise_floating(&mut self, state: &AquariWm<ClientWindow>, window: ClientWindow) -> Vec<Request> {
+		self.sync_floating_layer(state);
+
+		let restacks = self.floating_layer.raise(&window);
+		restacks.into_iter().map(Self::restack_request).collect()
+	}
+
+	/// Updates the [floating layer] to contain the shown floating windows.
+	///
+	/// [floating layer]: Self::floating_layer
+	fn sync_floating_layer(&mut self, state: &AquariWm<ClientWindow>) {
+		let shown = self.shown_floating(state);
+
+		self.floating_layer.sync(shown);
+	}
+
+	/// Returns the shown floating windows from the bottom of the [floating layer] to the top, as it
+	/// would be [synced], without changing it.
+	///
+	/// [floating layer]: Self::floating_layer
+	/// [synced]: stacking::Layer::sync
+	pub fn floating_order(&self, state: &AquariWm<ClientWindow>) -> Vec<ClientWindow> {
+		let mut layer = self.floating_layer.clone();
+		layer.sync(self.shown_floating(state));
+
+		layer.order().to_vec()
+	}
+
+	/// Returns the floating windows which are shown.
+	fn shown_floating(&self, state: &AquariWm<ClientWindow>) -> Vec<ClientWindow> {
+		state
+			.windows
+			.iter()
+			.filter_map(|(&window, window_state)| {
+				let shown = window_state.mode == layout::Mode::Floating
+					&& window_state.mapped == state::MapState::Mapped
+					&& !state.is_iconified(&window)
+					&& !self.hidden.contains(&window);
+
+				shown.then_some(window)
+			})
+			.collect()
+	}
+
+	/// Returns the request to perform the given `restack`.
+	fn restack_request(restack: stacking::Restack<ClientWindow>) -> Request {
+		let stack_mode = match restack.position {
+			Position::Above => x11::StackMode::ABOVE,
+			Position::Below => x11::StackMode::BELOW,
+		};
+		let aux = x11::ConfigureWindowAux::new()
+			.sibling(restack.sibling.raw())
+			.stack_mode(stack_mode);
+
+		Request::Configure(restack.window, aux)
+	}
+
+	/// [Iconifies] the given mapped `window`, marking it as iconified.
+	///
+	/// The window must then be [hidden], if it isn't already.
+	///
+	/// [Iconifies]: AquariWm::iconify_window
+	/// [hidden]: Self::hide_window
+	pub fn iconify_window(&mut self, state: &mut AquariWm<ClientWindow>, window: ClientWindow) -> Vec<Request> {
+		if !state.iconify_window(&window) {
+			return Vec::new();
+		}
+		event!(Level::DEBUG, "Iconifying window {window}");
+
+		vec![Request::SetIconic(window, true)]
+	}
+
+	/// [Iconifies] the given `window`, along with the rest of its [window group] if the group
+	/// behavior says to, hiding each window which is newly iconified.
+	///
+	/// [Iconifies]: Self::iconify_window
+	/// [window group]: crate::window_groups
+	pub fn iconify_group(&mut self, state: &mut AquariWm<ClientWindow>, window: ClientWindow) -> Vec<Request> {
+		let group = self.group_of(state, GroupAction::Iconify, window);
+
+		let mut requests = Vec::new();
+		for window in group {
+			let iconified = self.iconify_window(state, window);
+
+			if !iconified.is_empty() {
+				requests.extend(iconified);
+				requests.extend(self.hide_window(window));
+			}
+		}
+
+		requests
+	}
+
+	/// Marks the given `window` as [hidden], returning the request to unmap it if it is newly
+	/// hidden.
+	///
+	/// A window whose map was still [ordered] was never shown, so there is nothing to unmap.
+	///
+	/// [hidden]: Self::hidden
+	/// [ordered]: Self::ordered
+	pub fn hide_window(&mut self, window: ClientWindow) -> Vec<Request> {
+		let never_shown = self
+			.ordered
+			.cancel(|request| matches!(request, Request::Map(map) if *map == window));
+
+		match self.hidden.insert(window) && !never_shown {
+			true => vec![Request::Unmap(window)],
+			false => Vec::new(),
+		}
+	}
+
+	/// Updates the [window group] of the given `window` from the `WM_HINTS` property in its
+	/// snapshot.
+	///
+	/// [window group]: crate::window_groups
+	pub fn update_group(&mut self, window: ClientWindow) {
+		let leader = self
+			.snapshots
+			.get(&window)
+			.and_then(ClientSnapshot::window_group)
+			.map(ClientWindow::new);
+
+		if self.groups.set_leader(window, leader) {
+			event!(Level::DEBUG, "Window {window} is now in the window group of {leader:?}");
+		}
+	}
+
+	/// Returns the windows which the given `action` performed on the `window` applies to,
+	/// [expanded] to the rest of its window group if the group behavior says so.
+	///
+	/// [expanded]: WindowGroups::expand
+	pub fn group_of(
+		&self,
+		state: &AquariWm<ClientWindow>,
+		action: GroupAction,
+		window: ClientWindow,
+	) -> Vec<ClientWindow> {
+		// Windows which aren't mapped, like group leaders which only stand in for their application,
+		// are left out; the others are compared by their outputs, if there are any.
+		self.groups.expand(action, window, |window| {
+			let mapped = state
+				.windows
+				.get(window)
+				.is_some_and(|window_state| window_state.mapped == state::MapState::Mapped);
+
+			mapped.then(|| self.output_of(state, window).map(|output| output.name.clone()))
+		})
+	}
+
+	/// Returns whether the given `window` is demanding attention, either itself or, if the group
+	/// behavior shares urgency, through a window in the window group it leads.
+	pub fn is_urgent(&self, window: &ClientWindow) -> bool {
+		self.groups.is_urgent(window, |window| self.urgent.contains(window))
+	}
+
+	/// Returns the output the given managed `window` is shown on: the output of its tile if it is
+	/// tiled, or the output containing its center if it is floating.
+	fn output_of<'state>(
+		&self,
+		state: &'state AquariWm<ClientWindow>,
+		window: &ClientWindow,
+	) -> Option<&'state Output> {
+		if let Some(output) = state.output_of(window) {
+			return Some(output);
+		}
+		let geometry = self.floating.get(window)?;
+
+		Output::at(
+			&state.outputs,
+			geometry.x + (geometry.width as i32) / 2,
+			geometry.y + (geometry.height as i32) / 2,
+		)
+	}
+
+	/// [Restores] the given iconified `window`, grouping it with the windows of its class if the
+	/// [insertion strategy] says to, and marks it as no longer iconified.
+	///
+	/// Tiled windows are mapped when they are next reconfigured, so that they appear in their new
+	/// tile; other windows are mapped straight away.
+	///
+	/// [Restores]: AquariWm::restore_window_grouped_by
+	/// [insertion strategy]: layout::InsertionStrategy
+	pub fn restore_window(&mut self, state: &mut AquariWm<ClientWindow>, window: ClientWindow) -> Vec<Request> {
+		let (class, desktop) = (self.classes.get(&window), state.desktop_of(&window));
+		if !state.restore_window_grouped_by(&window, |other| class.is_some() && self.classes.get(other) == class) {
+			return Vec::new();
+		}
+		event!(Level::DEBUG, "Restoring iconified window {window}");
+
+		let mut requests = Vec::new();
+
+		let tiled = state
+			.tiling_layout()
+			.is_some_and(|layout| layout.contains_window(&window));
+		if !tiled && self.hidden.remove(&window) {
+			requests.push(Request::Map(window));
+		}
+		requests.push(Request::SetIconic(window, false));
+		// Windows are restored onto the current workspace.
+		if desktop != state.desktop_of(&window) {
+			requests.push(Request::SetDesktop(window, state.desktop_of(&window).to_ewmh()));
+		}
+
+		requests
+	}
+
+	/// [Restores] the window which has been iconified for the longest, if any.
+	///
+	/// [Restores]: Self::restore_window
+	pub fn restore_oldest(&mut self, state: &mut AquariWm<ClientWindow>) -> Vec<Request> {
+		match state.iconified().first() {
+			Some(&window) => self.restore_window(state, window),
+			None => Vec::new(),
+		}
+	}
+
+	/// Maps every iconified window, and every window [away] on another workspace, again without
+	/// tiling them, so that they aren't left unmapped when AquariWM exits.
+	///
+	/// [away]: AquariWm::is_away
+	pub fn deiconify_all(&mut self, state: &AquariWm<ClientWindow>) -> Vec<Request> {
+		let mut requests: Vec<_> = state
+			.iconified()
+			.iter()
+			.flat_map(|&window| {
+				self.hidden.remove(&window);
+
+				[Request::Map(window), Request::SetIconic(window, false)]
+			})
+			.collect();
+
+		for &window in state.away() {
+			if self.hidden.remove(&window) {
+				requests.push(Request::Map(window));
+			}
+		}
+
+		requests
+	}
+
+	/// [Switches] to the workspace at the given `index`, grouping the windows put back into the
+	/// tiling layout with the windows of their classes if the [insertion strategy] says to.
+	///
+	/// [Switches]: AquariWm::switch_to_workspace
+	/// [insertion strategy]: layout::InsertionStrategy
+	pub fn switch_workspace(&mut self, state: &mut AquariWm<ClientWindow>, index: usize) -> Vec<Request> {
+		let switched = state.switch_to_workspace(index, &mut self.output_assignment, Self::same_class(&self.classes));
+
+		match switched {
+			Ok(change) => {
+				event!(Level::DEBUG, "Switched to workspace {}", index + 1);
+
+				self.show_workspace_change(state, change)
+			},
+
+			Err(error) => {
+				event!(Level::WARN, "Failed to switch workspaces: {error}");
+
+				Vec::new()
+			},
+		}
+	}
+
+	/// [Moves] the given `window` to the workspace at the given `index`, hiding it if it leaves the
+	/// current workspace, or showing it if it joins it.
+	///
+	/// [Moves]: AquariWm::move_window_to_workspace
+	pub fn move_to_workspace(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		index: usize,
+	) -> Vec<Request> {
+		let sticky = state
+			.windows
+			.get(&window)
+			.is_some_and(|window_state| window_state.sticky);
+
+		match state.move_window_to_workspace(&window, index, Self::same_class(&self.classes)) {
+			Ok(change) => {
+				event!(Level::DEBUG, "Moved window {window} to workspace {}", index + 1);
+
+				let mut requests = self.show_workspace_change(state, change);
+				// Sticky windows stop being sticky when they are moved to a workspace.
+				if sticky {
+					requests.push(Request::SetState(window, self.atoms._NET_WM_STATE_STICKY, false));
+				}
+
+				requests
+			},
+
+			Err(error) => {
+				event!(
+					Level::WARN,
+					"Failed to move window {window} to another workspace: {error}"
+				);
+
+				Vec::new()
+			},
+		}
+	}
+
+	/// [Moves] the given `window` to the workspace at the given `index` and switches to it in one
+	/// step.
+	///
+	/// The windows on the workspace left behind are unmapped straight away, but the windows on
+	/// the new workspace are only mapped once they have been configured in their tiles, and the
+	/// window is only focused once they are: nothing is shown before it has been configured.
+	///
+	/// [Moves]: AquariWm::move_window_to_workspace_and_follow
+	pub fn move_to_workspace_and_follow(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		index: usize,
+	) -> Vec<Request> {
+		let sticky = state
+			.windows
+			.get(&window)
+			.is_some_and(|window_state| window_state.sticky);
+		let moved = state.move_window_to_workspace_and_follow(
+			&window,
+			index,
+			&mut self.output_assignment,
+			Self::same_class(&self.classes),
+		);
+
+		match moved {
+			Ok(change) => {
+				event!(
+					Level::DEBUG,
+					"Moved window {window} to workspace {} and followed it",
+					index + 1
+				);
+
+				let mut requests = self.show_workspace_change(state, change);
+				if sticky {
+					requests.push(Request::SetState(window, self.atoms._NET_WM_STATE_STICKY, false));
+				}
+
+				requests
+			},
+
+			Err(error) => {
+				event!(
+					Level::WARN,
+					"Failed to move window {window} to another workspace: {error}"
+				);
+
+				Vec::new()
+			},
+		}
+	}
+
+	/// Shows the given workspace `change`: the windows which left the current workspace are hidden,
+	/// those which joined it are shown again, and the `_NET_WM_DESKTOP` properties of the windows
+	/// whose desktops changed are updated.
+	///
+	/// As with restored windows, tiled windows are mapped when they are next reconfigured, so that
+	/// they appear in their new tiles; other windows are mapped straight away.
+	fn show_workspace_change(
+		&mut self,
+		state: &AquariWm<ClientWindow>,
+		change: WorkspaceChange<ClientWindow>,
+	) -> Vec<Request> {
+		let WorkspaceChange {
+			hidden,
+			shown,
+			desktops,
+		} = change;
+		let mut requests = Vec::new();
+
+		for window in hidden {
+			requests.extend(self.hide_window(window));
+		}
+		for window in shown {
+			let tiled = state
+				.tiling_layout()
+				.is_some_and(|layout| layout.contains_window(&window));
+
+			if !tiled && self.hidden.remove(&window) {
+				requests.push(Request::Map(window));
+			}
+		}
+		requests.extend(
+			desktops
+				.into_iter()
+				.map(|(window, desktop)| Request::SetDesktop(window, desktop.to_ewmh())),
+		);
+
+		requests
+	}
+
+	/// Returns whether two windows have the same class, given the `classes` of the windows which
+	/// have them.
+	fn same_class(classes: &HashMap<ClientWindow, String>) -> impl Fn(&ClientWindow, &ClientWindow) -> bool + '_ {
+		|window, other| {
+			classes
+				.get(window)
+				.is_some_and(|class| classes.get(other) == Some(class))
+		}
+	}
+
+	/// Stops managing the given mapped `window` and passes it through, adding it to the
+	/// [ignore list] by its ID.
+	pub fn ignore_window(&mut self, state: &mut AquariWm<ClientWindow>, window: ClientWindow) -> Vec<Request> {
+		state.ignored.add(Matcher::Window(window.id()));
+
+		let class = self.classes.get(&window).cloned();
+		self.pass_through(state, window, class)
+	}
+
+	/// Stops managing the mapped windows which match the [ignore list], passing them through.
+	///
+	/// [ignore list]: AquariWm::ignored
+	pub fn apply_ignore_list(&mut self, state: &mut AquariWm<ClientWindow>) -> Vec<Request> {
+		let mut ignored: Vec<_> = state
+			.windows
+			.iter()
+			.filter(|(window, window_state)| {
+				let class = self.classes.get(window).map(String::as_str);
+
+				window_state.mapped == state::MapState::Mapped && state.ignored.matches(window.id(), class)
+			})
+			.map(|(&window, _)| window)
+			.collect();
+		// Windows are passed through in a consistent order, whatever the order of the map.
+		ignored.sort_unstable();
+
+		ignored
+			.into_iter()
+			.flat_map(|window| {
+				let class = self.classes.get(&window).cloned();
+
+				self.pass_through(state, window, class)
+			})
+			.collect()
+	}
+
+	/// Manages the windows which were passed through but no longer match the [ignore list] again,
+	/// as if they had just been mapped.
+	///
+	/// [ignore list]: AquariWm::ignored
+	pub fn adopt_unignored(&mut self, state: &mut AquariWm<ClientWindow>) -> Vec<Request> {
+		let mut adopted: Vec<_> = self
+			.passed_through
+			.iter()
+			.filter(|(window, class)| !state.ignored.matches(window.id(), class.as_deref()))
+			.map(|(&window, class)| (window, class.clone()))
+			.collect();
+		adopted.sort_unstable();
+
+		adopted
+			.into_iter()
+			.flat_map(|(window, class)| {
+				event!(Level::DEBUG, "Window {window} is no longer ignored; managing it");
+
+				let request = x11::MapRequestEvent {
+					parent: self.root,
+					window: window.raw(),
+					..Default::default()
+				};
+				let client = Client {
+					class,
+					..Client::default()
+				};
+
+				// Ignored windows are already shown, so they aren't held back until the re-tile.
+				let mut requests = self.on_map_request(state, &request, client);
+				if self.hidden.remove(&window) {
+					requests.insert(0, Request::Map(window));
+				}
+
+				requests
+			})
+			.collect()
+	}
+
+	/// Stops managing the given `window` and passes it through, restoring the geometry it had
+	/// before it was managed if it was tiled, and undoing anything done to show its state.
+	fn pass_through(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		class: Option<String>,
+	) -> Vec<Request> {
+		event!(Level::DEBUG, "Ignoring window {window}");
+		let mut requests = Vec::new();
+
+		let tiled = state.windows.get(&window).is_some_and(|window_state| {
+			window_state.mode == layout::Mode::Tiled && window_state.mapped == state::MapState::Mapped
+		});
+		if let (true, Some(geometry)) = (tiled, self.original.get(&window)) {
+			let aux = x11::ConfigureWindowAux::new()
+				.x(geometry.x)
+				.y(geometry.y)
+				.width(geometry.width)
+				.height(geometry.height)
+				.border_width(geometry.border_width);
+
+			requests.push(Request::Configure(window, aux));
+		}
+		if self.hidden.contains(&window) {
+			requests.push(Request::Map(window));
+		}
+		if self.dimmed.contains(&window) {
+			requests.push(Request::SetOpacity(window, None));
+		}
+
+		self.unmanage(state, window);
+		self.passed_through.insert(window, class);
+
+		requests
+	}
+
+	/// Returns whether an `UnmapNotify` event for the given `window` was expected because AquariWM
+	/// unmapped it to hide it, consuming that expectation.
+	fn take_expected_unmap(&mut self, window: ClientWindow) -> bool {
+		let Some(count) = self.expected_unmaps.get_mut(&window) else {
+			return false;
+		};
+
+		*count -= 1;
+		if *count == 0 {
+			self.expected_unmaps.remove(&window);
+		}
+
+		true
+	}
+
+	/// Starts managing the given top-level `window`, either because it was just created or because
+	/// it was reparented back to the root window.
+	///
+	/// The window is tracked as unmapped; it is mapped when the map request for it is handled.
+	fn manage(&mut self, state: &mut AquariWm<ClientWindow>, window: ClientWindow) {
+		// Anything remembered from when the window was last managed is stale.
+		self.rules.withdraw(state, &window);
+		self.forget(window);
+
+		if let Err(error) = state.add_window(window, state::MapState::Unmapped) {
+			event!(Level::WARN, "Failed to track new window {window}: {error}");
+		}
+	}
+
+	/// Stops managing the given `window`, either because it was destroyed or because it was
+	/// reparented away from the root window, removing it from the layout.
+	fn unmanage(&mut self, state: &mut AquariWm<ClientWindow>, window: ClientWindow) {
+		self.rules.withdraw(state, &window);
+		self.forget(window);
+		state.remove_window(&window);
+	}
+
+	/// Remembers the placement of the given (closing) `window` under its class, if it has one and
+	/// is mapped.
+	fn remember_placement(
+		&self,
+		state: &AquariWm<ClientWindow>,
+		placements: &mut PlacementMemory,
+		window: ClientWindow,
+	) {
+		let Some(class) = self.classes.get(&window) else {
+			return;
+		};
+		// Windows which were never admitted into the tiling layout only floated while they waited.
+		if state
+			.windows
+			.get(&window)
+			.is_some_and(|window_state| window_state.admission.is_pending())
+		{
+			return;
+		}
+		let floating = self
+			.floating
+			.get(&window)
+			.map(|geometry| (geometry.x, geometry.y, geometry.width, geometry.height));
+
+		if let Some(placement) = Placement::capture(state, &window, floating) {
+			event!(Level::DEBUG, "Remembering the placement of window {window} ({class:?})");
+
+			placements.remember(class, placement, SystemTime::now());
+		}
+	}
+
+	/// Forgets everything known about the given (destroyed) `window`.
+	fn forget(&mut self, window: ClientWindow) {
+		self.hidden.remove(&window);
+		self.expected_unmaps.remove(&window);
+		self.classes.remove(&window);
+		self.titles.remove(&window);
+		self.dimmed.remove(&window);
+		self.urgent.remove(&window);
+		self.fullscreen.remove(&window);
+		self.floating.remove(&window);
+		self.input_models.remove(&window);
+		self.original.remove(&window);
+		self.visuals.remove(&window);
+		self.snapshots.remove(&window);
+		self.painted.remove(&window);
+		self.configure_guard.forget(&window);
+		self.pings.forget(&window);
+		self.groups.forget(&window);
+		if self.borderless == Some(window) {
+			self.borderless = None;
+		}
+		if self.held_focus == Some(window) {
+			self.held_focus = None;
+		}
+		self.ordered.cancel(|request| match *request {
+			Request::Map(held) | Request::TakeFocus(held, _) | Request::Focus(Some(held), _) => held == window,
+			_ => false,
+		});
+
+		// Forgotten windows are usually destroyed, so their compositor bypass hint isn't restored.
+		self.focus_lock.release(&window);
+		self.bypassed.remove(&window);
+
+		self.passed_through.remove(&window);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Mutex;
+
+	use super::*;
+	use crate::{
+		action::Action,
+		desktop::ALL_DESKTOPS,
+		layout::{managers::Stack, LayoutSettings},
+		metrics::{Operation, Progress},
+		rules::RuleAction,
+		window::WindowId,
+	};
+
+	const ROOT: x11::Window = 1;
+
+	/// Creates a window manager with the given windows tiled on a 1920x1080 screen.
+	fn wm(windows: &[x11::Window]) -> (Wm, AquariWm<ClientWindow>) {
+		let state = AquariWm::with_tiling_layout_and_windows::<Stack<ClientWindow>>(
+			0,
+			0,
+			1920,
+			1080,
+			windows
+				.iter()
+				.map(|&window| (ClientWindow::new(window), state::MapState::Mapped)),
+			LayoutSettings::default(),
+		);
+
+		(
+			Wm::new(ROOT, (0, 0, 1920, 1080), FullscreenPolicy::default(), Vec::new()),
+			state,
+		)
+	}
+
+	/// Returns the windows in the tiling layout, in order.
+	fn tiled(state: &AquariWm<ClientWindow>) -> Vec<x11::Window> {
+		let mut windows: Vec<_> = state
+			.tiling_layout()
+			.unwrap()
+			.windows()
+			.into_iter()
+			.map(|window| window.raw())
+			.collect();
+		windows.sort_unstable();
+
+		windows
+	}
+
+	/// Describes the given `requests` as they would be logged in a dry run.
+	fn describe(requests: &[Request]) -> Vec<String> {
+		requests.iter().map(ToString::to_string).collect()
+	}
+
+	#[test]
+	fn create_and_map() {
+		let (mut wm, mut state) = wm(&[2]);
+
+		let create = x11::CreateNotifyEvent {
+			parent: ROOT,
+			window: 3,
+			..Default::default()
+		};
+		assert!(describe(&wm.on_create_notify(&mut state, &create)).is_empty());
+		// The window is tracked, but only tiled once it is mapped.
+		assert!(state.windows.contains_key(&ClientWindow::new(3)));
+		assert_eq!(tiled(&state), [2]);
+
+		let map = x11::MapRequestEvent {
+			parent: ROOT,
+			window: 3,
+			..Default::default()
+		};
+		let client = Client {
+			class: Some("Alacritty".to_owned()),
+			sticky: true,
+			..Client::default()
+		};
+		// The window is only mapped once it has been configured to its tile.
+		assert!(describe(&wm.on_map_request(&mut state, &map, client)).is_empty());
+		assert!(wm.hidden.contains(&ClientWindow::new(3)));
+		assert_eq!(tiled(&state), [2, 3]);
+		assert_eq!(
+			wm.classes.get(&ClientWindow::new(3)).map(String::as_str),
+			Some("Alacritty")
+		);
+		assert!(state.windows[&ClientWindow::new(3)].sticky);
+
+		// A replayed map request doesn't tile the window twice.
+		assert_eq!(
+			describe(&wm.on_map_request(&mut state, &map, Client::default())),
+			["map window 3"]
+		);
+		assert_eq!(tiled(&state), [2, 3]);
+	}
+
+	#[test]
+	fn unmap() {
+		let (mut wm, mut state) = wm(&[2, 3]);
+		let mut placements = PlacementMemory::default();
+
+		// Windows unmapped by AquariWM to hide them stay in the layout...
+		wm.expected_unmaps.insert(ClientWindow::new(3), 1);
+		let unmap = x11::UnmapNotifyEvent {
+			event: ROOT,
+			window: 3,
+			..Default::default()
+		};
+		assert!(describe(&wm.on_unmap_notify(&mut state, &mut placements, &unmap)).is_empty());
+		assert_eq!(tiled(&state), [2, 3]);
+		assert!(wm.expected_unmaps.is_empty());
+
+		// ...but windows unmapped by their clients don't, and their placements are remembered.
+		wm.classes.insert(ClientWindow::new(3), "Alacritty".to_owned());
+		assert!(describe(&wm.on_unmap_notify(&mut state, &mut placements, &unmap)).is_empty());
+		assert_eq!(tiled(&state), [2]);
+		assert_eq!(state.windows[&ClientWindow::new(3)].mapped, state::MapState::Unmapped);
+		assert_eq!(placements.len(), 1);
+	}
+
+	/// Tests that windows without clear signals float until they are admitted, without touching the
+	/// layout or being remembered if they vanish first.
+	#[test]
+	fn admission() {
+		let start = Instant::now();
+		let at = |millis| start + Duration::from_millis(millis);
+
+		let (mut wm, mut state) = wm(&[2]);
+		let mut placements = PlacementMemory::default();
+		let map = |wm: &mut Wm, state: &mut AquariWm<ClientWindow>, window, client: Client, now| {
+			wm.on_create_notify(
+				state,
+				&x11::CreateNotifyEvent {
+					parent: ROOT,
+					window,
+					..Default::default()
+				},
+			);
+			let held = wm.hold_admission(state, ClientWindow::new(window), &client, now);
+			if held {
+				wm.floating.insert(ClientWindow::new(window), Geometry::default());
+			}
+
+			let request = x11::MapRequestEvent {
+				parent: ROOT,
+				window,
+				..Default::default()
+			};
+			wm.on_map_request(state, &request, client);
+
+			held
+		};
+		let splash = || Client {
+			class: Some("splash".to_owned()),
+			..Client::default()
+		};
+
+		// Normal windows with a class are tiled straight away.
+		let normal = Client {
+			class: Some("Alacritty".to_owned()),
+			window_type: WindowType::Normal,
+			..Client::default()
+		};
+		assert!(!map(&mut wm, &mut state, 3, normal, at(0)));
+		assert_eq!(tiled(&state), [2, 3]);
+
+		// Windows without a window type float until they are admitted...
+		assert!(map(&mut wm, &mut state, 4, splash(), at(0)));
+		assert!(map(&mut wm, &mut state, 5, splash(), at(50)));
+		assert_eq!(tiled(&state), [2, 3]);
+
+		// ...unless they vanish first, leaving nothing behind.
+		let unmap = x11::UnmapNotifyEvent {
+			event: ROOT,
+			window: 4,
+			..Default::default()
+		};
+		wm.on_unmap_notify(&mut state, &mut placements, &unmap);
+		assert_eq!(state.windows[&ClientWindow::new(4)].admission, Admission::Vanished);
+		assert!(!wm.floating.contains_key(&ClientWindow::new(4)));
+		assert!(placements.is_empty());
+		assert!(wm.admit_due(&mut state, at(150)).is_empty());
+
+		assert_eq!(wm.admit_due(&mut state, at(200)), [ClientWindow::new(5)]);
+		assert_eq!(tiled(&state), [2, 3, 5]);
+		assert!(wm.floating.is_empty());
+
+		// Windows which a rule matches aren't held back.
+		wm.rules.replace(vec!["tile:class=splash".parse().unwrap()]);
+		assert!(!map(&mut wm, &mut state, 6, splash(), at(200)));
+
+		// Nor is any window without an admission delay.
+		wm.admission_delay = Duration::ZERO;
+		assert!(!map(&mut wm, &mut state, 7, Client::default(), at(200)));
+		assert_eq!(tiled(&state), [2, 3, 5, 6, 7]);
+	}
+
+	#[test]
+	fn configure_tiled() {
+		let (mut wm, state) = wm(&[2]);
+
+		let request = x11::ConfigureRequestEvent {
+			window: 2,
+			x: 5,
+			y: 5,
+			width: 10,
+			height: 10,
+			value_mask: x11::ConfigWindow::X
+				| x11::ConfigWindow::Y
+				| x11::ConfigWindow::WIDTH
+				| x11::ConfigWindow::HEIGHT,
+			..Default::default()
+		};
+		// Tiled windows' requests are honored as they are until they are given a tile: the layout
+		// reconfigures them anyway.
+		assert_eq!(
+			describe(&wm.on_configure_request(&state, &request, &SizeHints::default(), Instant::now())),
+			["configure window 2: x 5 y 5 width 10 height 10"]
+		);
+
+		// Then they are kept in their tile...
+		let start = Instant::now();
+		wm.configure_guard = ConfigureGuard::new(2);
+		wm.configure_guard.assert(ClientWindow::new(2), (0, 0, 1920, 1080));
+		for _ in 0..2 {
+			assert_eq!(
+				describe(&wm.on_configure_request(&state, &request, &SizeHints::default(), start)),
+				["configure window 2: x 0 y 0 width 1920 height 1080"]
+			);
+		}
+		// ...until they have asked for another geometry too often.
+		assert!(wm
+			.on_configure_request(&state, &request, &SizeHints::default(), start)
+			.is_empty());
+
+		// Requests for the window's current geometry are only answered by telling it so.
+		let request = x11::ConfigureRequestEvent {
+			window: 2,
+			width: 1920,
+			value_mask: x11::ConfigWindow::WIDTH,
+			..Default::default()
+		};
+		assert_eq!(
+			describe(&wm.on_configure_request(&state, &request, &SizeHints::default(), start)),
+			["tell window 2 it is at (0, 0) with size 1920x1080"]
+		);
+	}
+
+	#[test]
+	fn smart_borders() {
+		let (mut wm, mut state) = wm(&[2]);
+		let geometry = Geometry {
+			x: 10,
+			y: 20,
+			width: 300,
+			height: 200,
+			border_width: 2,
+		};
+		wm.original.insert(ClientWindow::new(2), geometry);
+		let lone = |state: &AquariWm<ClientWindow>| state.tiling_layout().unwrap().single_window().copied();
+
+		assert_eq!(lone(&state), Some(ClientWindow::new(2)));
+		assert_eq!(
+			describe(&wm.update_borders(lone(&state))),
+			["configure window 2: border width 0"]
+		);
+		assert!(wm.update_borders(lone(&state)).is_empty());
+
+		// The border is given back once another window is tiled alongside it...
+		state.add_window(ClientWindow::new(3), state::MapState::Mapped).unwrap();
+		assert_eq!(lone(&state), None);
+		assert_eq!(
+			describe(&wm.update_borders(lone(&state))),
+			["configure window 2: border width 2"]
+		);
+
+		// ...and taken away again once it is alone.
+		state.remove_window(&ClientWindow::new(3));
+		assert_eq!(
+			describe(&wm.update_borders(lone(&state))),
+			["configure window 2: border width 0"]
+		);
+	}
+
+	#[test]
+	fn configure_floating() {
+		let (mut wm, mut state) = wm(&[]);
+
+		state.add_window(ClientWindow::new(3), state::MapState::Mapped).unwrap();
+		state.windows.get_mut(&ClientWindow::new(3)).unwrap().set_floating();
+		let current = Geometry {
+			x: 100,
+			y: 100,
+			width: 400,
+			height: 300,
+			border_width: 0,
+		};
+		wm.floating.insert(ClientWindow::new(3), current);
+
+		// Floating windows can't move themselves off the screen; they are told where they were kept.
+		let request = x11::ConfigureRequestEvent {
+			window: 3,
+			x: 5000,
+			value_mask: x11::ConfigWindow::X,
+			..Default::default()
+		};
+		assert_eq!(
+			describe(&wm.on_configure_request(&state, &request, &SizeHints::default(), Instant::now())),
+			[
+				"configure window 3: x 1888 y 100 width 400 height 300 border width 0",
+				"tell window 3 it is at (1888, 100) with size 400x300",
+			]
+		);
+		assert_eq!(wm.floating[&ClientWindow::new(3)], Geometry { x: 1888, ..current });
+
+		// Requests which can be honored as asked aren't followed by a synthetic `ConfigureNotify`.
+		let request = x11::ConfigureRequestEvent {
+			window: 3,
+			width: 500,
+			value_mask: x11::ConfigWindow::WIDTH,
+			..Default::default()
+		};
+		assert_eq!(
+			describe(&wm.on_configure_request(&state, &request, &SizeHints::default(), Instant::now())),
+			["configure window 3: x 1888 y 100 width 500 height 300 border width 0"]
+		);
+	}
+
+	#[test]
+	fn restack_floating() {
+		let (mut wm, mut state) = wm(&[2]);
+
+		for window in [3, 4, 5] {
+			state
+				.add_window(ClientWindow::new(window), state::MapState::Mapped)
+				.unwrap();
+			state
+				.windows
+				.get_mut(&ClientWindow::new(window))
+				.unwrap()
+				.set_floating();
+		}
+		wm.floating_layer.sync([3, 4, 5].map(ClientWindow::new));
+		let restack = |wm: &mut Wm, window, stack_mode, sibling| {
+			let request = x11::ConfigureRequestEvent {
+				window,
+				sibling,
+				stack_mode,
+				value_mask: x11::ConfigWindow::SIBLING | x11::ConfigWindow::STACK_MODE,
+				..Default::default()
+			};
+			describe(&wm.on_configure_request(&state, &request, &SizeHints::default(), Instant::now()))
+		};
+
+		assert_eq!(
+			restack(&mut wm, 3, x11::StackMode::ABOVE, 5),
+			["configure window 3: sibling 5 stack mode ABOVE"]
+		);
+		// Floating windows can't be stacked below tiled windows: they go to the bottom of the
+		// floating layer instead.
+		assert_eq!(
+			restack(&mut wm, 5, x11::StackMode::BELOW, 2),
+			["configure window 5: sibling 4 stack mode BELOW"]
+		);
+		assert_eq!(restack(&mut wm, 4, x11::StackMode::BELOW, 3), Vec::<String>::new());
+		assert_eq!(wm.floating_layer.order(), [5, 4, 3].map(ClientWindow::new));
+	}
+
+	#[test]
+	fn destroy() {
+		let (mut wm, mut state) = wm(&[2, 3]);
+		let mut placements = PlacementMemory::default();
+
+		wm.classes.insert(ClientWindow::new(3), "Alacritty".to_owned());
+		wm.dimmed.insert(ClientWindow::new(3));
+		wm.expected_unmaps.insert(ClientWindow::new(3), 1);
+
+		let destroy = x11::DestroyNotifyEvent {
+			event: ROOT,
+			window: 3,
+			..Default::default()
+		};
+		assert!(describe(&wm.on_destroy_notify(&mut state, &mut placements, &destroy)).is_empty());
+		assert!(!state.windows.contains_key(&ClientWindow::new(3)));
+		assert_eq!(tiled(&state), [2]);
+
+		// Everything known about the window is forgotten, once its placement is remembered.
+		assert!(wm.classes.is_empty() && wm.dimmed.is_empty() && wm.expected_unmaps.is_empty());
+		assert!(placements.recall("Alacritty", SystemTime::now()).is_some());
+	}
+
+	#[test]
+	fn game_mode() {
+		let (mut wm, mut state) = wm(&[2, 3, 4]);
+		let mut placements = PlacementMemory::default();
+		wm.focus_lock = FocusLock::new(FullscreenPolicy::GameMode {
+			urgent_overrides: false,
+		});
+
+		// Fullscreen windows bypass the compositor while they are in game mode...
+		assert_eq!(
+			describe(&wm.fullscreen_changed(ClientWindow::new(2), true, None)),
+			["set the compositor bypass hint of window 2 to 1"]
+		);
+		assert_eq!(wm.focus_lock.holder(), Some(&ClientWindow::new(2)));
+		// ...and their own hint is restored when they leave it.
+		assert_eq!(
+			describe(&wm.fullscreen_changed(ClientWindow::new(2), false, None)),
+			["remove the compositor bypass hint of window 2"]
+		);
+		assert_eq!(wm.focus_lock.holder(), None);
+
+		// Windows which asked to be composited stay composited.
+		assert!(describe(&wm.fullscreen_changed(ClientWindow::new(3), true, Some(2))).is_empty());
+		// Only the latest fullscreen window is in game mode.
+		assert_eq!(
+			describe(&wm.fullscreen_changed(ClientWindow::new(4), true, Some(0))),
+			["set the compositor bypass hint of window 4 to 1"]
+		);
+		assert_eq!(wm.focus_lock.holder(), Some(&ClientWindow::new(4)));
+
+		// Closed windows leave game mode.
+		let unmap = x11::UnmapNotifyEvent {
+			event: ROOT,
+			window: 4,
+			..Default::default()
+		};
+		assert_eq!(
+			describe(&wm.on_unmap_notify(&mut state, &mut placements, &unmap)),
+			["set the compositor bypass hint of window 4 to 0"]
+		);
+		assert_eq!(wm.focus_lock.holder(), None);
+
+		// Destroyed windows release the lock without restoring anything.
+		wm.fullscreen_changed(ClientWindow::new(2), true, None);
+		let destroy = x11::DestroyNotifyEvent {
+			event: ROOT,
+			window: 2,
+			..Default::default()
+		};
+		assert!(describe(&wm.on_destroy_notify(&mut state, &mut placements, &destroy)).is_empty());
+		assert_eq!(wm.focus_lock.holder(), None);
+		assert!(wm.bypassed.is_empty());
+	}
+
+	#[test]
+	fn title_rules() {
+		let (_, mut state) = wm(&[2, 3]);
+		let rule = "float:class=firefox,title=*Picture-in-Picture*,retitle"
+			.parse()
+			.unwrap();
+		let mut wm = Wm::new(ROOT, (0, 0, 1920, 1080), FullscreenPolicy::default(), vec![rule]);
+		let mut placements = PlacementMemory::default();
+
+		let window = ClientWindow::new(3);
+		wm.classes.insert(window, "firefox".to_owned());
+		let retitle = |wm: &mut Wm, title: &str| {
+			wm.titles.insert(window, title.to_owned());
+		};
+
+		for _ in 0..2 {
+			retitle(&mut wm, "Picture-in-Picture");
+			let transition = wm.evaluate_rules(&mut state, window, true);
+			assert_eq!(transition.applied, Some(RuleAction::Float));
+			assert_eq!(tiled(&state), [2]);
+
+			retitle(&mut wm, "Mozilla Firefox");
+			let transition = wm.evaluate_rules(&mut state, window, true);
+			assert!(transition.reverted.is_some());
+			assert_eq!(tiled(&state), [2, 3]);
+		}
+
+		// Windows closed while a rule applies are tiled again when they reopen, until their title
+		// matches again.
+		retitle(&mut wm, "Picture-in-Picture");
+		wm.evaluate_rules(&mut state, window, true);
+		let unmap = x11::UnmapNotifyEvent {
+			event: ROOT,
+			window: 3,
+			..Default::default()
+		};
+		wm.on_unmap_notify(&mut state, &mut placements, &unmap);
+		assert!(wm.rules.applied_to(&window).is_none());
+		assert_eq!(state.windows[&window].mode, layout::Mode::Tiled);
+
+		// Destroyed windows leave nothing behind.
+		state.map_window(&window).unwrap();
+		wm.evaluate_rules(&mut state, window, false);
+		let destroy = x11::DestroyNotifyEvent {
+			event: ROOT,
+			window: 3,
+			..Default::default()
+		};
+		wm.on_destroy_notify(&mut state, &mut placements, &destroy);
+		assert!(wm.rules.applied_to(&window).is_none());
+		assert!(wm.titles.is_empty());
+	}
+
+	#[test]
+	fn window_list() {
+		let (mut wm, mut state) = wm(&[2, 3]);
+		wm.titles.insert(ClientWindow::new(2), "Terminal".to_owned());
+		wm.classes.insert(ClientWindow::new(3), "mpv".to_owned());
+		wm.fullscreen_changed(ClientWindow::new(3), true, None);
+		state.set_focused(Some(ClientWindow::new(3)), state::FocusCause::Keyboard);
+
+		let list = wm.window_list(&state);
+		assert_eq!(list.version, window_list::VERSION);
+		assert_eq!(
+			list.windows
+				.iter()
+				.map(|window| (window.id, window.title.as_deref(), window.class.as_deref()))
+				.collect::<Vec<_>>(),
+			[(WindowId(2), Some("Terminal"), None), (WindowId(3), None, Some("mpv"))]
+		);
+		assert!(list.windows[1].focused && list.windows[1].fullscreen && !list.windows[0].fullscreen);
+
+		// The list follows the cached state as it changes.
+		wm.fullscreen_changed(ClientWindow::new(3), false, None);
+		wm.urgent.insert(ClientWindow::new(2));
+		let list = wm.window_list(&state);
+		assert!(!list.windows[1].fullscreen && list.windows[0].urgent);
+	}
+
+	#[test]
+	fn ignore_on_map() {
+		let (mut wm, mut state) = wm(&[2]);
+		state.ignored.add("xmag*".parse().unwrap());
+
+		let create = x11::CreateNotifyEvent {
+			parent: ROOT,
+			window: 3,
+			..Default::default()
+		};
+		wm.on_create_notify(&mut state, &create);
+
+		// Ignored windows are mapped, but not tracked, let alone tiled...
+		let map = x11::MapRequestEvent {
+			parent: ROOT,
+			window: 3,
+			..Default::default()
+		};
+		let client = Client {
+			class: Some("xmagnify".to_owned()),
+			..Client::default()
+		};
+		assert_eq!(describe(&wm.on_map_request(&mut state, &map, client)), ["map window 3"]);
+		assert!(!state.windows.contains_key(&ClientWindow::new(3)));
+		assert_eq!(tiled(&state), [2]);
+
+		// ...and their configure requests are honored verbatim.
+		let request = x11::ConfigureRequestEvent {
+			window: 3,
+			x: 5000,
+			value_mask: x11::ConfigWindow::X,
+			..Default::default()
+		};
+		assert_eq!(
+			describe(&wm.on_configure_request(&state, &request, &SizeHints::default(), Instant::now())),
+			["configure window 3: x 5000"]
+		);
+
+		// Once unmapped, they are forgotten.
+		let unmap = x11::UnmapNotifyEvent {
+			event: ROOT,
+			window: 3,
+			..Default::default()
+		};
+		let mut placements = PlacementMemory::default();
+		assert!(describe(&wm.on_unmap_notify(&mut state, &mut placements, &unmap)).is_empty());
+		assert!(wm.passed_through.is_empty());
+
+		// If they are no longer ignored when they are mapped again, they are managed again.
+		state.ignored.remove(&"xmag*".parse().unwrap());
+		let client = Client {
+			class: Some("xmagnify".to_owned()),
+			..Client::default()
+		};
+		assert!(describe(&wm.on_map_request(&mut state, &map, client)).is_empty());
+		assert_eq!(tiled(&state), [2, 3]);
+	}
+
+	#[test]
+	fn ignore_and_adopt() {
+		let (mut wm, mut state) = wm(&[]);
+
+		for (window, class) in [(2, "Alacritty"), (3, "onboard")] {
+			let create = x11::CreateNotifyEvent {
+				parent: ROOT,
+				window,
+				..Default::default()
+			};
+			wm.on_create_notify(&mut state, &create);
+
+			let map = x11::MapRequestEvent {
+				parent: ROOT,
+				window,
+				..Default::default()
+			};
+			let client = Client {
+				class: Some(class.to_owned()),
+				geometry: Some(Geometry {
+					x: 10,
+					y: 20,
+					width: 300,
+					height: 200,
+					border_width: 1,
+				}),
+				..Client::default()
+			};
+			wm.on_map_request(&mut state, &map, client);
+		}
+		wm.dimmed.insert(ClientWindow::new(3));
+		assert_eq!(tiled(&state), [2, 3]);
+
+		// Managed windows which come to be ignored are restored to their geometry from before they
+		// were managed, and shown if they were yet to be.
+		state.ignored.add(Matcher::Class("onboard".to_owned()));
+		assert_eq!(
+			describe(&wm.apply_ignore_list(&mut state)),
+			[
+				"configure window 3: x 10 y 20 width 300 height 200 border width 1",
+				"map window 3",
+				"remove the opacity of window 3",
+			]
+		);
+		assert_eq!(tiled(&state), [2]);
+		assert_eq!(wm.passed_through[&ClientWindow::new(3)].as_deref(), Some("onboard"));
+		assert!(wm.dimmed.is_empty());
+
+		// The focused window is ignored by its ID.
+		state.set_focused(Some(ClientWindow::new(2)), state::FocusCause::Keyboard);
+		assert_eq!(
+			describe(&wm.ignore_window(&mut state, ClientWindow::new(2))),
+			[
+				"configure window 2: x 10 y 20 width 300 height 200 border width 1",
+				"map window 2"
+			]
+		);
+		assert!(state.windows.is_empty() && state.focused.is_none());
+		assert!(state.ignored.matches(WindowId(2), None));
+
+		// Windows are only adopted once they are no longer ignored.
+		assert!(describe(&wm.adopt_unignored(&mut state)).is_empty());
+		state.ignored.remove(&Matcher::Window(WindowId(2)));
+		assert_eq!(describe(&wm.adopt_unignored(&mut state)), ["map window 2"]);
+		assert_eq!(tiled(&state), [2]);
+		assert_eq!(wm.passed_through.keys().collect::<Vec<_>>(), [&ClientWindow::new(3)]);
+	}
+
+	#[test]
+	fn iconify_and_restore() {
+		let (mut wm, mut state) = wm(&[2, 3, 4]);
+		let mut placements = PlacementMemory::default();
+		state.set_focused(Some(ClientWindow::new(3)), state::FocusCause::Keyboard);
+
+		// Iconified windows are unmapped by the caller, which expects their `UnmapNotify` events.
+		assert_eq!(
+			describe(&wm.iconify_window(&mut state, ClientWindow::new(3))),
+			["mark window 3 as iconified"]
+		);
+		assert!(wm.iconify_window(&mut state, ClientWindow::new(3)).is_empty());
+		assert_eq!(tiled(&state), [2, 4]);
+		wm.hidden.insert(ClientWindow::new(3));
+		wm.expected_unmaps.insert(ClientWindow::new(3), 1);
+		let unmap = x11::UnmapNotifyEvent {
+			event: ROOT,
+			window: 3,
+			..Default::default()
+		};
+		wm.on_unmap_notify(&mut state, &mut placements, &unmap);
+		assert_eq!(state.iconified(), [ClientWindow::new(3)]);
+
+		// Restored tiled windows are mapped once they are reconfigured in their new tile.
+		assert_eq!(
+			describe(&wm.restore_oldest(&mut state)),
+			["mark window 3 as no longer iconified"]
+		);
+		assert_eq!(tiled(&state), [2, 3, 4]);
+		assert_eq!(state.focused, Some(ClientWindow::new(3)));
+		assert!(wm.hidden.contains(&ClientWindow::new(3)));
+		assert!(wm.restore_oldest(&mut state).is_empty());
+
+		// Clients restore their iconified windows by mapping them.
+		wm.iconify_window(&mut state, ClientWindow::new(4));
+		wm.hidden.insert(ClientWindow::new(4));
+		let map = x11::MapRequestEvent {
+			parent: ROOT,
+			window: 4,
+			..Default::default()
+		};
+		assert_eq!(
+			describe(&wm.on_map_request(&mut state, &map, Client::default())),
+			["mark window 4 as no longer iconified"]
+		);
+		assert!(state.iconified().is_empty() && wm.hidden.contains(&ClientWindow::new(4)));
+
+		// Iconified windows are mapped again when AquariWM exits.
+		wm.iconify_window(&mut state, ClientWindow::new(2));
+		wm.hidden.insert(ClientWindow::new(2));
+		assert_eq!(
+			describe(&wm.deiconify_all(&state)),
+			["map window 2", "mark window 2 as no longer iconified"]
+		);
+		assert!(!wm.hidden.contains(&ClientWindow::new(2)));
+	}
+
+	/// Tests that switching workspaces, and moving windows between them, hides and shows windows
+	/// and updates their desktops, whether asked for by a binding or by a pager.
+	#[test]
+	fn workspaces() {
+		let (mut wm, mut state) = wm(&[2, 3, 4]);
+		for window in [2, 3, 4] {
+			state.set_focused(Some(ClientWindow::new(window)), state::FocusCause::Keyboard);
+		}
+		let names = |state: &AquariWm<ClientWindow>| -> Vec<String> {
+			state.workspaces.names().iter().map(ToString::to_string).collect()
+		};
+
+		// Moving the focused window to a new workspace hides it.
+		let (requests, _) = wm.perform(&mut state, Action::MoveToWorkspace(1));
+		assert_eq!(
+			describe(&requests),
+			["unmap window 4", "set the desktop of window 4 to 0x1", "focus window 3"]
+		);
+		assert_eq!(names(&state), ["1", "2"]);
+		assert_eq!(tiled(&state), [2, 3]);
+
+		// Switching to it hides the other windows, and shows it again once it has been
+		// reconfigured in its tile.
+		let (requests, _) = wm.perform(&mut state, Action::SwitchToWorkspace(1));
+		assert_eq!(describe(&requests), ["unmap window 2", "unmap window 3"]);
+		assert_eq!(tiled(&state), [4]);
+		assert!(wm.hidden.contains(&ClientWindow::new(4)));
+		assert_eq!(wm.held_focus, Some(ClientWindow::new(4)));
+
+		// Pagers move windows between desktops, and switch desktops, leaving empty workspaces to be
+		// collected.
+		let desktop = x11::ClientMessageEvent::new(32, 4, wm.atoms._NET_WM_DESKTOP, [0, 0, 0, 0, 0]);
+		assert_eq!(
+			describe(&wm.on_client_message(&mut state, &desktop)),
+			["set the desktop of window 4 to 0x0", "focus the root window"]
+		);
+		let current = x11::ClientMessageEvent::new(32, ROOT, wm.atoms._NET_CURRENT_DESKTOP, [0, 0, 0, 0, 0]);
+		// The tiled windows are mapped once they have been reconfigured.
+		assert!(wm.on_client_message(&mut state, &current).is_empty());
+		assert_eq!(names(&state), ["1"]);
+		assert_eq!(tiled(&state), [2, 3, 4]);
+
+		// Once the windows have been shown, following a window to another workspace only unmaps
+		// the windows left behind: the window itself stays shown and focused.
+		wm.hidden.clear();
+		state.set_focused(Some(ClientWindow::new(2)), state::FocusCause::Keyboard);
+		let (requests, _) = wm.perform(&mut state, Action::MoveToWorkspaceAndFollow(1));
+		assert_eq!(
+			describe(&requests),
+			["unmap window 3", "unmap window 4", "set the desktop of window 2 to 0x1"]
+		);
+		assert_eq!(tiled(&state), [2]);
+
+		// Window switchers focus windows on other workspaces by activating them, switching to
+		// their workspaces.
+		let activate = x11::ClientMessageEvent::new(32, 3, wm.atoms._NET_ACTIVE_WINDOW, [2, 0, 0, 0, 0]);
+		assert_eq!(
+			describe(&wm.on_client_message(&mut state, &activate)),
+			["unmap window 2"]
+		);
+		assert_eq!(state.workspaces.current(), 0);
+		assert_eq!(tiled(&state), [3, 4]);
+		assert_eq!(state.focused, Some(ClientWindow::new(3)));
+		assert_eq!(wm.held_focus, Some(ClientWindow::new(3)));
+
+		// Windows on other workspaces are mapped again when AquariWM exits.
+		assert_eq!(describe(&wm.deiconify_all(&state)), ["map window 2"]);
+	}
+
+	#[test]
+	fn perform() {
+		let (mut wm, mut state) = wm(&[2, 3]);
+		state.set_focused(Some(ClientWindow::new(2)), state::FocusCause::Keyboard);
+
+		let (requests, command) = wm.perform(&mut state, Action::FocusNext);
+		assert_eq!(describe(&requests), ["focus window 3"]);
+		assert!(command.is_none());
+
+		// Minimized windows are hidden right away.
+		let (requests, command) = wm.perform(&mut state, Action::MinimizeFocused);
+		assert_eq!(
+			describe(&requests),
+			["mark window 3 as iconified", "unmap window 3", "focus window 2"]
+		);
+		assert!(command.is_none());
+
+		// What can't be done by the window manager is left to the session.
+		let (requests, command) = wm.perform(&mut state, Action::ToggleDryRun);
+		assert!(requests.is_empty());
+		assert!(matches!(command, Some(SessionCommand::ToggleDryRun)));
+	}
+
+	#[test]
+	fn client_messages() {
+		let (mut wm, mut state) = wm(&[]);
+		let message = |type_, data| x11::ClientMessageEvent::new(32, 3, type_, data);
+
+		state.add_window(ClientWindow::new(3), state::MapState::Mapped).unwrap();
+		state.windows.get_mut(&ClientWindow::new(3)).unwrap().set_floating();
+		let current = Geometry {
+			x: 100,
+			y: 100,
+			width: 400,
+			height: 300,
+			border_width: 0,
+		};
+		wm.floating.insert(ClientWindow::new(3), current);
+
+		// Pagers move windows onto every desktop by making them sticky.
+		let desktop = message(wm.atoms._NET_WM_DESKTOP, [ALL_DESKTOPS, 0, 0, 0, 0]);
+		let sticky = format!("add state {} to window 3", wm.atoms._NET_WM_STATE_STICKY);
+		assert_eq!(
+			describe(&wm.on_client_message(&mut state, &desktop)),
+			[sticky.as_str(), "set the desktop of window 3 to 0xffffffff"]
+		);
+		assert!(state.windows[&ClientWindow::new(3)].sticky);
+
+		// Dragging a header bar moves the window with the pointer until it is released.
+		let moveresize = message(wm.atoms._NET_WM_MOVERESIZE, [150, 110, 8, 1, 1]);
+		assert!(wm.on_client_message(&mut state, &moveresize).is_empty());
+		assert!(wm.drag.is_some());
+
+		wm.on_pointer_motion((250, 160), Instant::now());
+		assert_eq!(
+			describe(&wm.on_button_release(&mut state, (250, 160))),
+			["configure window 3: x 200 y 150 width 400 height 300"]
+		);
+		assert!(wm.drag.is_none());
+		assert!(wm.on_button_release(&mut state, (300, 300)).is_empty());
+	}
+
+	#[test]
+	fn resolve() {
+		let (mut wm, mut state) = wm(&[2]);
+		let mut placements = PlacementMemory::default();
+		wm.utility.insert(UtilityWindow::new(5));
+
+		assert_eq!(wm.resolve_window(&state, ROOT), WindowRole::Root);
+		assert_eq!(wm.resolve_window(&state, 2), WindowRole::Client(ClientWindow::new(2)));
+		assert_eq!(wm.resolve_window(&state, 5), WindowRole::Utility(UtilityWindow::new(5)));
+
+		// AquariWM's own windows aren't managed when their creation is notified.
+		let create = x11::CreateNotifyEvent {
+			parent: ROOT,
+			window: 5,
+			..Default::default()
+		};
+		wm.on_create_notify(&mut state, &create);
+		assert!(!state.windows.contains_key(&ClientWindow::new(5)));
+
+		// The IDs of destroyed windows are stale.
+		let destroy = x11::DestroyNotifyEvent {
+			event: ROOT,
+			window: 2,
+			..Default::default()
+		};
+		wm.on_destroy_notify(&mut state, &mut placements, &destroy);
+		assert_eq!(wm.resolve_window(&state, 2), WindowRole::Unknown(2));
+	}
+
+	#[test]
+	fn override_redirect() {
+		let (mut wm, mut state) = wm(&[2]);
+		let mut placements = PlacementMemory::default();
+
+		// Override-redirect windows aren't managed when their creation is notified...
+		let create = x11::CreateNotifyEvent {
+			parent: ROOT,
+			window: 3,
+			override_redirect: true,
+			..Default::default()
+		};
+		wm.on_create_notify(&mut state, &create);
+		assert!(!state.windows.contains_key(&ClientWindow::new(3)));
+		assert_eq!(wm.resolve_window(&state, 3), WindowRole::Unmanaged(3));
+		assert_eq!(wm.resolve_top_level(&state, 3), None);
+
+		// ...nor are they configured.
+		let configure = x11::ConfigureRequestEvent {
+			parent: ROOT,
+			window: 3,
+			width: 100,
+			height: 100,
+			value_mask: x11::ConfigWindow::WIDTH | x11::ConfigWindow::HEIGHT,
+			..Default::default()
+		};
+		assert!(wm
+			.on_configure_request(&state, &configure, &SizeHints::default(), Instant::now())
+			.is_empty());
+
+		// Other windows are still managed.
+		let create = x11::CreateNotifyEvent {
+			window: 4,
+			override_redirect: false,
+			..create
+		};
+		wm.on_create_notify(&mut state, &create);
+		assert_eq!(wm.resolve_window(&state, 4), WindowRole::Client(ClientWindow::new(4)));
+
+		// The classification is forgotten once the window is destroyed, as its ID may be reused.
+		let destroy = x11::DestroyNotifyEvent {
+			event: ROOT,
+			window: 3,
+			..Default::default()
+		};
+		wm.on_destroy_notify(&mut state, &mut placements, &destroy);
+		assert!(!wm.is_classified(3));
+		assert_eq!(wm.resolve_window(&state, 3), WindowRole::Unknown(3));
+	}
+
+	#[test]
+	fn reparent() {
+		let (mut wm, mut state) = wm(&[2, 3]);
+
+		// A window embedded by its client is no longer managed...
+		let away = x11::ReparentNotifyEvent {
+			window: 3,
+			parent: 2,
+			..Default::default()
+		};
+		assert!(describe(&wm.on_reparent_notify(&mut state, &away)).is_empty());
+		assert!(!state.windows.contains_key(&ClientWindow::new(3)));
+
+		// ...until it is reparented back to the root window.
+		let back = x11::ReparentNotifyEvent {
+			window: 3,
+			parent: ROOT,
+			..Default::default()
+		};
+		assert!(describe(&wm.on_reparent_notify(&mut state, &back)).is_empty());
+		assert_eq!(state.windows[&ClientWindow::new(3)].mapped, state::MapState::Unmapped);
+	}
+
+	#[test]
+	fn answer_latency() {
+		let (mut wm, _state) = wm(&[2]);
+		assert!(wm.answer_latency().starts_with("Latencies aren't measured"));
+
+		let start = Instant::now();
+		wm.metrics = Metrics::new(true);
+		wm.metrics.begin(Some(ClientWindow::new(2)), Operation::Map, start);
+		wm.metrics
+			.settle(start + Duration::from_millis(4), |_, _| Progress::Done);
+
+		// The query path locks the handler once, so this mustn't deadlock.
+		let wm = Mutex::new(wm);
+		let answer = wm.lock().unwrap().answer_latency();
+		assert!(answer.starts_with("Latencies: map: "), "{answer}");
+	}
+}
@@ -0,0 +1,251 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl Wm {
+	/// Returns the geometry the given `window` is known to have, without querying the X server.
+	///
+	/// The geometry of a [floating] window is the one AquariWM last gave it, and that of a tiled
+	/// window is its tile's. Other windows are taken to still have the geometry in their
+	/// [snapshot].
+	///
+	/// [floating]: Self::floating
+	/// [snapshot]: Self::snapshots
+	pub fn known_geometry(&self, window: ClientWindow) -> Option<Geometry> {
+		if let Some(&geometry) = self.floating.get(&window) {
+			return Some(geometry);
+		}
+		if let Some((x, y, width, height)) = self.configure_guard.asserted(&window) {
+			return Some(Geometry {
+				x,
+				y,
+				width,
+				height,
+				border_width: self.border_width(window),
+			});
+		}
+
+		self.snapshots.get(&window).and_then(ClientSnapshot::fetched_geometry)
+	}
+
+	/// Returns the size hints in the given `window`'s [snapshot], or none if it has no snapshot.
+	///
+	/// [snapshot]: Self::snapshots
+	pub(super) fn size_hints(&self, window: ClientWindow) -> SizeHints {
+		self.snapshots
+			.get(&window)
+			.map(ClientSnapshot::size_hints)
+			.unwrap_or_default()
+	}
+
+	/// Places the given [floating] `window` within the `output` based on its [known geometry] and
+	/// `WM_NORMAL_HINTS`, centering it on its `parent` if it is transient, returning the request to
+	/// do so.
+	///
+	/// See [`placement::place_floating`] for how the window is placed.
+	///
+	/// [floating]: layout::Mode::Floating
+	/// [known geometry]: Self::known_geometry
+	pub fn place_floating(
+		&mut self,
+		window: ClientWindow,
+		output: placement::Rect,
+		parent: Option<x11::Window>,
+	) -> Vec<Request> {
+		let requested = self.known_geometry(window);
+
+		self.place_requested(window, requested, output, parent)
+	}
+
+	/// Places the given [floating] `window` as [`place_floating`] does, but based on the given
+	/// `requested` geometry rather than the window's known geometry, e.g. that from its
+	/// [snapshot].
+	///
+	/// Nothing is placed if the window's geometry isn't known, i.e. if it no longer existed.
+	///
+	/// [floating]: layout::Mode::Floating
+	/// [`place_floating`]: Self::place_floating
+	/// [snapshot]: Self::snapshots
+	pub fn place_requested(
+		&mut self,
+		window: ClientWindow,
+		requested: Option<Geometry>,
+		output: placement::Rect,
+		parent: Option<x11::Window>,
+	) -> Vec<Request> {
+		let Some(requested) = requested else {
+			return Vec::new();
+		};
+		let hints = self.size_hints(window);
+
+		// The parent may have been destroyed in the meantime.
+		let parent = parent
+			.and_then(|parent| self.known_geometry(ClientWindow::new(parent)))
+			.map(|parent| (parent.x, parent.y, parent.width, parent.height));
+
+		let (x, y, width, height) = placement::place_floating(requested, &hints, output, parent);
+		event!(
+			Level::DEBUG,
+			"Placing floating window {window} at ({x}, {y}) with size {width}x{height}"
+		);
+		self.floating.insert(
+			window,
+			Geometry {
+				x,
+				y,
+				width,
+				height,
+				..requested
+			},
+		);
+
+		vec![Request::Configure(
+			window,
+			x11::ConfigureWindowAux::new().x(x).y(y).width(width).height(height),
+		)]
+	}
+
+	/// Moves the given floating `window` to the given geometry, e.g. because a window of the same
+	/// class was last closed there, returning the request to do so.
+	pub fn restore_floating(&mut self, window: ClientWindow, (x, y, width, height): placement::Rect) -> Vec<Request> {
+		let border_width = self.known_geometry(window).map_or(0, |geometry| geometry.border_width);
+
+		event!(
+			Level::DEBUG,
+			"Restoring floating window {window} at ({x}, {y}) with size {width}x{height}"
+		);
+		self.floating.insert(
+			window,
+			Geometry {
+				x,
+				y,
+				width,
+				height,
+				border_width,
+			},
+		);
+
+		vec![Request::Configure(
+			window,
+			x11::ConfigureWindowAux::new().x(x).y(y).width(width).height(height),
+		)]
+	}
+
+	/// Moves the given [floating] `window` to the given `preset` on the [active output], storing
+	/// its new geometry, and returns the request to do so.
+	///
+	/// See [`placement::place_preset`] for how the window is placed.
+	///
+	/// [floating]: layout::Mode::Floating
+	/// [active output]: AquariWm::active_output
+	pub fn apply_float_preset(
+		&mut self,
+		state: &AquariWm<ClientWindow>,
+		window: ClientWindow,
+		preset: &FloatPreset,
+	) -> Vec<Request> {
+		// Windows which have only just become floating have their geometry stored first.
+		if !self.floating.contains_key(&window) {
+			let Some(geometry) = self.known_geometry(window) else {
+				return Vec::new();
+			};
+
+			self.floating.insert(window, geometry);
+		}
+		let hints = self.size_hints(window);
+
+		let output = state.active_output.get(&state.outputs);
+		let work_area = placement::work_area(output, &state.outputs, self.screen);
+
+		let Some(geometry) = self.floating.get_mut(&window) else {
+			return Vec::new();
+		};
+		let (x, y, width, height) = placement::place_preset(preset, work_area, &hints, geometry.border_width);
+		*geometry = Geometry {
+			x,
+			y,
+			width,
+			height,
+			..*geometry
+		};
+		event!(
+			Level::DEBUG,
+			"Moving floating window {window} to {preset} at ({x}, {y}) with size {width}x{height}"
+		);
+
+		vec![Request::Configure(
+			window,
+			x11::ConfigureWindowAux::new().x(x).y(y).width(width).height(height),
+		)]
+	}
+
+	/// Moves the given `window` onto the output of the given `name` if it is floating, after it was
+	/// [moved to that output], returning the request to do so.
+	///
+	/// Tiled windows are moved by the tiling layout instead.
+	///
+	/// [moved to that output]: crate::action::Action::MoveWindowToOutput
+	pub fn move_floating_to_output(
+		&mut self,
+		state: &AquariWm<ClientWindow>,
+		window: ClientWindow,
+		name: &str,
+	) -> Vec<Request> {
+		let floating = state
+			.windows
+			.get(&window)
+			.is_some_and(|window_state| window_state.mode == layout::Mode::Floating);
+
+		match (floating, Output::named(&state.outputs, name)) {
+			(true, Some(output)) => {
+				let output = (output.x, output.y, output.width, output.height);
+
+				self.place_floating(window, output, None)
+			},
+
+			_ => Vec::new(),
+		}
+	}
+
+	/// Carries out a `step` of a [drag] of the given `window`.
+	///
+	/// Floating windows are configured by the returned request, whereas changes to the tiling
+	/// layout are only made to the `state`: they are applied by the caller.
+	///
+	/// [drag]: Drag
+	pub fn drag_step(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		step: Option<moveresize::Step<'_>>,
+	) -> Vec<Request> {
+		match step {
+			Some(moveresize::Step::Configure((x, y, width, height))) => {
+				if let Some(geometry) = self.floating.get_mut(&window) {
+					*geometry = Geometry {
+						x,
+						y,
+						width,
+						height,
+						..*geometry
+					};
+				}
+
+				vec![Request::Configure(
+					window,
+					x11::ConfigureWindowAux::new().x(x).y(y).width(width).height(height),
+				)]
+			},
+
+			Some(moveresize::Step::MoveEdge(edge, position)) => {
+				state.move_edge(edge, position);
+
+				Vec::new()
+			},
+
+			None => Vec::new(),
+		}
+	}
+}
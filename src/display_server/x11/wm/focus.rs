@@ -0,0 +1,275 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl Wm {
+	/// Focuses the given `window`, updating its opacity and that of the previously focused window.
+	///
+	/// The `cause` of the focus change decides whether the [active output] follows it.
+	///
+	/// [active output]: AquariWm::active_output
+	pub fn focus_window(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		cause: FocusCause,
+	) -> Vec<Request> {
+		// Ignored windows are never focused by AquariWM.
+		if self.passed_through.contains_key(&window) {
+			return Vec::new();
+		}
+
+		let mut requests = Vec::new();
+		if state.windows.contains_key(&window) && state.focused != Some(window) {
+			let previous = state.focused;
+			let previous_target = state.focus_target_windows();
+			state.set_focused_from(Some(window), cause);
+
+			for window in previous.into_iter().chain([window]) {
+				requests.extend(self.update_opacity(state, window));
+			}
+			requests.extend(self.focus_target_changed(state, previous_target));
+		}
+
+		requests.extend(self.give_focus(Some(window)));
+		requests
+	}
+
+	/// Gives input focus to the [`focused`] window if it was changed from `previous` (e.g. by an
+	/// [action]), updating the opacity of both windows.
+	///
+	/// If no window is focused any more, the root window is given input focus.
+	///
+	/// [`focused`]: AquariWm::focused
+	/// [action]: crate::action::Action
+	pub fn focus_changed(&mut self, state: &AquariWm<ClientWindow>, previous: Option<ClientWindow>) -> Vec<Request> {
+		if state.focused == previous {
+			return Vec::new();
+		}
+
+		let mut requests = Vec::new();
+		for window in [previous, state.focused].into_iter().flatten() {
+			requests.extend(self.update_opacity(state, window));
+		}
+		requests.extend(self.give_focus(state.focused));
+
+		requests
+	}
+
+	/// Updates the opacity of the windows which have entered or left the [focus target], given the
+	/// windows which were in it before.
+	///
+	/// Every window in a focused group is shown as focused, as AquariWM draws no borders to show it
+	/// with.
+	///
+	/// [focus target]: AquariWm::focus_target
+	pub fn focus_target_changed(
+		&mut self,
+		state: &AquariWm<ClientWindow>,
+		previous: Vec<ClientWindow>,
+	) -> Vec<Request> {
+		let current = state.focus_target_windows();
+		if current == previous {
+			return Vec::new();
+		}
+
+		let mut windows: Vec<_> = previous.into_iter().chain(current).collect();
+		// Windows are updated in a consistent order, and only once each.
+		windows.sort_unstable();
+		windows.dedup();
+
+		windows
+			.into_iter()
+			.flat_map(|window| self.update_opacity(state, window))
+			.collect()
+	}
+
+	/// Returns the requests to give input focus to the given `window` according to its client's
+	/// [input model], or to the root window if [`None`], as of the [latest event's timestamp].
+	///
+	/// Focus can't be given to a window which isn't mapped, so focus given to a window which is
+	/// still [hidden] is [held] until it is shown, and focus given to a window which is about to be
+	/// shown is [ordered] after it.
+	///
+	/// See [`InputModel::assignment`] for how focus is given.
+	///
+	/// [input model]: InputModel
+	/// [latest event's timestamp]: Self::timestamp
+	/// [hidden]: Self::hidden
+	/// [held]: Self::held_focus
+	/// [ordered]: Self::ordered
+	pub fn give_focus(&mut self, window: Option<ClientWindow>) -> Vec<Request> {
+		let time = self.timestamp;
+
+		self.held_focus = None;
+		let held = match window {
+			Some(window) if self.hidden.contains(&window) => {
+				self.held_focus = Some(window);
+
+				return Vec::new();
+			},
+
+			Some(window) => self
+				.ordered
+				.contains(|request| matches!(request, Request::Map(map) if *map == window)),
+			None => false,
+		};
+
+		let assignment = match window {
+			Some(window) => self.input_model(window).assignment(),
+			None => InputModel::Passive.assignment(),
+		};
+
+		let mut requests = Vec::new();
+		match assignment.input_focus {
+			Some(Target::Window) => requests.push(Request::Focus(window, time)),
+			Some(Target::Fallback) => requests.push(Request::Focus(None, time)),
+
+			None => (),
+		}
+		if let (true, Some(window)) = (assignment.take_focus, window) {
+			requests.push(Request::TakeFocus(window, time));
+		}
+
+		if held {
+			for request in requests {
+				self.ordered.push(Phase::Focus, request);
+			}
+
+			return Vec::new();
+		}
+
+		requests
+	}
+
+	/// Gives input focus to the window it was [held] for, once it has been shown.
+	///
+	/// [held]: Self::held_focus
+	pub fn release_held_focus(&mut self) -> Vec<Request> {
+		match self.held_focus {
+			Some(window) if !self.hidden.contains(&window) => self.give_focus(Some(window)),
+			_ => Vec::new(),
+		}
+	}
+
+	/// Returns the [input model] of the given `window`'s client, reading it from the `WM_HINTS`
+	/// and `WM_PROTOCOLS` properties in its [snapshot] if it isn't known already.
+	///
+	/// Windows without a snapshot are taken to use the passive input model, like most clients.
+	///
+	/// [input model]: InputModel
+	/// [snapshot]: Self::snapshots
+	fn input_model(&mut self, window: ClientWindow) -> InputModel {
+		if let Some(&model) = self.input_models.get(&window) {
+			return model;
+		}
+		let Some(snapshot) = self.snapshots.get(&window) else {
+			return InputModel::default();
+		};
+
+		let model = snapshot.input_model(&self.atoms);
+		self.input_models.insert(window, model);
+
+		model
+	}
+
+	/// Returns whether focus may move to the given `target` window, or the root window if [`None`],
+	/// because of the given `request`, as [decided] by the focus lock.
+	///
+	/// [decided]: FocusLock::decide
+	pub fn may_focus(
+		&self,
+		state: &AquariWm<ClientWindow>,
+		target: Option<ClientWindow>,
+		request: FocusRequest,
+	) -> bool {
+		let lock = &self.focus_lock;
+
+		match lock.decide(state.focused.as_ref(), target.as_ref(), request) {
+			fullscreen::Decision::Allow => true,
+
+			fullscreen::Decision::Suppress => {
+				if let Some(holder) = lock.holder() {
+					event!(
+						Level::DEBUG,
+						"Not letting {request} move focus away from fullscreen window {holder}"
+					);
+				}
+
+				false
+			},
+		}
+	}
+
+	/// Dims the given `window` if it is a [tiled] window outside the [focus target], or undims it
+	/// otherwise, returning the requests to do so.
+	///
+	/// Windows are dimmed by setting their `_NET_WM_WINDOW_OPACITY` property to the
+	/// [`unfocused_opacity`]. Windows whose class is in [`no_dim`] and [fullscreen] windows are
+	/// never dimmed. While the [focus lock] is engaged, only the fullscreen window's opacity
+	/// changes.
+	///
+	/// [tiled]: layout::Mode::Tiled
+	/// [focus target]: AquariWm::focus_target
+	/// [`unfocused_opacity`]: Appearance::unfocused_opacity
+	/// [`no_dim`]: Appearance::no_dim
+	/// [fullscreen]: Self::fullscreen
+	/// [focus lock]: FocusLock
+	pub fn update_opacity(&mut self, state: &AquariWm<ClientWindow>, window: ClientWindow) -> Vec<Request> {
+		let Some(opacity) = self.appearance.unfocused_opacity else {
+			return Vec::new();
+		};
+		let lock = &self.focus_lock;
+		if lock.is_engaged(state.focused.as_ref()) && lock.holder() != Some(&window) {
+			return Vec::new();
+		}
+
+		let tiled = state
+			.windows
+			.get(&window)
+			.is_some_and(|window_state| window_state.mode == layout::Mode::Tiled);
+		let no_dim = self
+			.classes
+			.get(&window)
+			.is_some_and(|class| self.appearance.no_dim.contains(class));
+
+		let dim = tiled && !no_dim && !state.in_focus_target(&window) && !self.fullscreen.contains(&window);
+
+		match dim {
+			true => {
+				self.dimmed.insert(window);
+
+				vec![Request::SetOpacity(window, Some(opacity.to_cardinal()))]
+			},
+
+			false if self.dimmed.remove(&window) => vec![Request::SetOpacity(window, None)],
+			false => Vec::new(),
+		}
+	}
+
+	/// Updates the opacity of every window if `locked`, the window which held the [focus lock]
+	/// before an event was handled, no longer holds it, as opacity is left as it is while the lock
+	/// is engaged.
+	///
+	/// [focus lock]: FocusLock
+	pub fn focus_lock_released(
+		&mut self,
+		state: &AquariWm<ClientWindow>,
+		locked: Option<ClientWindow>,
+	) -> Vec<Request> {
+		if locked.is_none() || self.focus_lock.holder() == locked.as_ref() {
+			return Vec::new();
+		}
+
+		let mut windows: Vec<_> = state.windows.keys().copied().collect();
+		// Windows are updated in a consistent order, whatever the order of the map.
+		windows.sort_unstable();
+
+		windows
+			.into_iter()
+			.flat_map(|window| self.update_opacity(state, window))
+			.collect()
+	}
+}
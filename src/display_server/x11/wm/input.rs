@@ -0,0 +1,365 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl Wm {
+	/// Acts on a key press, recording the actions performed to be replayed.
+	///
+	/// Key bindings are grabbed on the root window; key presses in managed windows are only
+	/// selected to know when the user was last typing. Anything which must be done outside of the
+	/// window manager, like launching a program, is returned as a [command] for the caller to
+	/// carry out. Changes to the layout are applied by the caller.
+	///
+	/// [command]: SessionCommand
+	pub fn on_key_press(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		recorder: &mut Recorder,
+		press: &x11::KeyPressEvent,
+		received: Instant,
+	) -> (Vec<Request>, Option<SessionCommand>) {
+		self.last_input = Some(received);
+
+		if press.event != self.root {
+			return (Vec::new(), None);
+		}
+		self.metrics.begin(state.focused, Operation::Focus, received);
+
+		event!(
+			Level::INFO,
+			"Key pressed, {event}, {state:?}, {detail}",
+			event = press.event,
+			state = press.state,
+			detail = press.detail,
+		);
+
+		// Ignore lock modifiers (e.g. NumLock), pointer buttons, and the keyboard group.
+		let chord = (press.detail, self.keys.modifiers.normalize(u16::from(press.state)));
+
+		// The keyboard is grabbed while switching windows, so every key press is reported; only
+		// those which control the session are acted on.
+		if state.is_switching() {
+			let key = match self.keys.bindings.get(&chord) {
+				Some(Binding::SwitchWindows(key)) => Some(*key),
+				_ if self.keys.switch_keys.escape.contains(&press.detail) => Some(switcher::Key::Cancel),
+				_ => None,
+			};
+
+			return match key {
+				Some(key) => (self.switch_windows(state, key), None),
+				None => (Vec::new(), None),
+			};
+		}
+
+		match self.keys.bindings.get(&chord).cloned() {
+			Some(Binding::SpawnTerminal) => (Vec::new(), Some(SessionCommand::Spawn(None))),
+			Some(Binding::Spawn(command)) => (Vec::new(), Some(SessionCommand::Spawn(Some(command)))),
+
+			// Every window in a focused group is closed.
+			Some(Binding::CloseWindow) => {
+				let requests = state.focus_target_windows().into_iter().map(Request::Destroy);
+
+				(requests.collect(), None)
+			},
+
+			Some(Binding::Action(action)) => {
+				let input = Input::Action { action: action.clone() };
+				recorder.note(input, received);
+
+				self.perform(state, action)
+			},
+			Some(Binding::SwitchWindows(key)) => (self.switch_windows(state, key), None),
+
+			None => (Vec::new(), None),
+		}
+	}
+
+	/// Performs the given `action`, carrying out what its [outcome] asks of the window manager.
+	///
+	/// Anything which must be done outside of the window manager, like reloading the
+	/// configuration, is returned as a [command] for the caller to carry out. Changes to the layout
+	/// are applied by the caller.
+	///
+	/// [outcome]: Outcome
+	/// [command]: SessionCommand
+	pub fn perform(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		action: Action,
+	) -> (Vec<Request>, Option<SessionCommand>) {
+		let previous = state.focused;
+		let previous_target = state.focus_target_windows();
+		// The focused window's window group is taken along to another output, if the group
+		// behavior says to.
+		let moved_group = match (&action, previous) {
+			(Action::MoveWindowToOutput(_) | Action::MoveToOutputAndFollow(_), Some(window)) => {
+				self.group_of(state, GroupAction::Move, window)
+			},
+			_ => Vec::new(),
+		};
+		self.focused_moved = matches!(
+			action,
+			Action::SwapWithPrimary
+				| Action::MoveWindowToOutput(_)
+				| Action::MoveToOutputAndFollow(_)
+				| Action::MoveToWorkspaceAndFollow(_)
+				| Action::SwapWithMark(_)
+		);
+
+		let mut requests = Vec::new();
+		let mut command = None;
+		match state.perform(action.clone()) {
+			Ok(Outcome::MovedToOutput(name)) => {
+				if let Some(window) = previous {
+					requests.extend(self.move_floating_to_output(state, window, &name));
+				}
+
+				// Windows in the focus target were already moved along with it.
+				for window in moved_group {
+					if previous_target.contains(&window) {
+						continue;
+					}
+
+					state.move_window_to_output(&window, &name);
+					requests.extend(self.move_floating_to_output(state, window, &name));
+				}
+			},
+			Ok(Outcome::Sticky(_)) => {
+				if let Some(window) = previous {
+					requests.extend(self.sticky_changed(state, window));
+				}
+			},
+			Ok(Outcome::SwitchToWorkspace(index)) => requests.extend(self.switch_workspace(state, index)),
+			Ok(Outcome::MoveToWorkspace(index)) => {
+				if let Some(window) = previous {
+					requests.extend(self.move_to_workspace(state, window, index));
+				}
+			},
+			Ok(Outcome::MoveToWorkspaceAndFollow(index)) => {
+				if let Some(window) = previous {
+					requests.extend(self.move_to_workspace_and_follow(state, window, index));
+				}
+			},
+			Ok(Outcome::SendMarkToCurrentWorkspace(mark)) => {
+				if let Some(&window) = state.marks.window(&mark) {
+					let current = state.workspaces.current();
+
+					requests.extend(self.move_to_workspace(state, window, current));
+				}
+			},
+			// Floating windows aren't raised when focused, since that would change their order,
+			// skipping windows while cycling through them.
+			Ok(Outcome::FocusNext) => {
+				let floating = self.floating_order(state);
+				state.focus_next(&floating);
+			},
+			Ok(Outcome::FocusPrevious) => {
+				let floating = self.floating_order(state);
+				state.focus_previous(&floating);
+			},
+			Ok(Outcome::ApplyFloatPreset(preset)) => {
+				if let Some(window) = previous {
+					requests.extend(self.apply_float_preset(state, window, &preset));
+				}
+			},
+
+			Ok(Outcome::MinimizeFocused) => {
+				if let Some(window) = previous {
+					requests.extend(self.iconify_group(state, window));
+				}
+			},
+			Ok(Outcome::RestoreMinimized) => requests.extend(self.restore_oldest(state)),
+			Ok(Outcome::ForceKillFocused) => {
+				if let Some(window) = previous {
+					let (kill, process) = self.force_kill(window);
+
+					requests.extend(kill);
+					command = process;
+				}
+			},
+
+			Ok(Outcome::IgnoreListChanged) => {
+				requests.extend(self.apply_ignore_list(state));
+				command = Some(SessionCommand::SaveIgnoreList);
+			},
+			Ok(Outcome::IgnoreFocused) => {
+				if let Some(window) = previous {
+					requests.extend(self.ignore_window(state, window));
+				}
+				command = Some(SessionCommand::SaveIgnoreList);
+			},
+			Ok(Outcome::IgnoreList(matchers)) => {
+				let matchers: Vec<_> = matchers.iter().map(ToString::to_string).collect();
+
+				event!(Level::INFO, "Ignored windows: {}", matchers.join(", "));
+			},
+			Ok(Outcome::Query(query)) => match self.answer(state, &query) {
+				Some(answer) => event!(Level::INFO, "{query}: {answer}"),
+				None => event!(Level::INFO, "{query}: nothing is shown to answer about"),
+			},
+			Ok(Outcome::QueryLatency) => {
+				let answer = self.answer_latency();
+				event!(Level::INFO, "{answer}");
+			},
+			Ok(Outcome::ToggleDryRun) => command = Some(SessionCommand::ToggleDryRun),
+			Ok(Outcome::ListKeyGrabs) => {
+				let statuses: Vec<_> = (self.keys.grabs.statuses.iter())
+					.map(|(chord, status)| format!("{chord}: {status}"))
+					.collect();
+
+				event!(Level::INFO, "Key bindings: {}", statuses.join(", "));
+			},
+			Ok(Outcome::AdoptUnignored) => requests.extend(self.adopt_unignored(state)),
+			Ok(Outcome::SetWallpaper(path)) => command = Some(SessionCommand::SetWallpaper(path)),
+			Ok(Outcome::ReloadConfig) => command = Some(SessionCommand::ReloadConfig),
+
+			Ok(_) => (),
+
+			Err(error) if error.is_failure() => {
+				event!(Level::WARN, "Failed to perform `{action}`: {error}")
+			},
+			Err(error) => event!(Level::DEBUG, "Not performing `{action}`: {error}"),
+		}
+
+		requests.extend(self.focus_changed(state, previous));
+		requests.extend(self.focus_target_changed(state, previous_target));
+
+		(requests, command)
+	}
+
+	/// Force kills the given `window`'s client once it has been asked for twice within
+	/// [`KILL_CONFIRMATION`], returning the request to close its connection and, if [configured],
+	/// the command to kill its process.
+	///
+	/// [configured]: PingSettings::kill_process
+	fn force_kill(&mut self, window: ClientWindow) -> (Vec<Request>, Option<SessionCommand>) {
+		if self.pings.confirm_kill(window, Instant::now()) == KillConfirmation::Pending {
+			event!(
+				Level::INFO,
+				"Force kill the client of window {window} again within {KILL_CONFIRMATION:?} to confirm"
+			);
+
+			return (Vec::new(), None);
+		}
+
+		event!(Level::WARN, "Force killing the client of window {window}");
+
+		let pid = self.snapshots.get(&window).map(|snapshot| snapshot.pid);
+		let command = match (self.pings.settings().kill_process, pid) {
+			(true, Some(pid)) => Some(SessionCommand::KillProcess(window, pid)),
+			_ => None,
+		};
+
+		(vec![Request::KillClient(window)], command)
+	}
+
+	/// Feeds the given `key` to the [window switching] session, starting one if needed, then raises
+	/// and focuses the selected window.
+	///
+	/// The caller grabs the keyboard for the duration of the session, so that Tab, Escape and the
+	/// release of Super are reported even though they aren't grabbed as key bindings.
+	///
+	/// [window switching]: switcher
+	pub fn switch_windows(&mut self, state: &mut AquariWm<ClientWindow>, key: switcher::Key) -> Vec<Request> {
+		let previous = state.focused;
+
+		let floating = self.floating_order(state);
+		let Some(step) = state.switch_windows(key, &floating) else {
+			return Vec::new();
+		};
+
+		let mut requests = Vec::new();
+		match step {
+			Step::Focus(window) | Step::Commit(window) => {
+				let floating = state
+					.windows
+					.get(&window)
+					.is_some_and(|window_state| window_state.mode == layout::Mode::Floating);
+
+				// Tiled windows don't overlap, so only floating windows need to be raised.
+				if floating {
+					requests.extend(self.raise_floating(state, window));
+				}
+			},
+
+			Step::Cancel(_) => (),
+		}
+		requests.extend(self.focus_changed(state, previous));
+
+		requests
+	}
+
+	/// Acts on a key release: releasing Super commits to the window selected while switching
+	/// windows.
+	pub fn on_key_release(&mut self, state: &mut AquariWm<ClientWindow>, detail: u8) -> Vec<Request> {
+		match state.is_switching() && self.keys.switch_keys.modifier.contains(&detail) {
+			true => self.switch_windows(state, switcher::Key::Release),
+			false => Vec::new(),
+		}
+	}
+
+	/// Forgets the [held modifiers] which are no longer pressed according to the given `keys`, the
+	/// keyboard's state queried after focus was grabbed away from AquariWM.
+	///
+	/// Key releases may have been missed while the keyboard was grabbed by another client, so this
+	/// keeps modifiers from being left stuck.
+	///
+	/// [held modifiers]: Self::held_modifiers
+	pub fn on_focus_out(&mut self, keys: &[u8; 32]) {
+		self.held_modifiers.retain_pressed(keys);
+	}
+
+	/// Ends the [drag] if the `ended` predicate returns true for the dragged window, e.g. because
+	/// it was destroyed.
+	///
+	/// The caller ungrabs the pointer.
+	///
+	/// [drag]: Self::drag
+	pub fn end_drag_of(&mut self, ended: impl FnOnce(&ClientWindow) -> bool) {
+		if self.drag.as_ref().is_some_and(|drag| ended(drag.window())) {
+			self.drag = None;
+			self.drag_motion.cancel();
+		}
+	}
+
+	/// Records the `pointer`'s latest position during a [drag], to be acted on once the
+	/// [`drag_motion`] is due.
+	///
+	/// [drag]: Self::drag
+	/// [`drag_motion`]: Self::drag_motion
+	pub fn on_pointer_motion(&mut self, pointer: (i32, i32), now: Instant) {
+		if self.drag.is_some() {
+			self.drag_motion.push(now, pointer);
+		}
+	}
+
+	/// Ends the [drag], if there is one, as the pointer is released at the given `pointer`
+	/// position, dropping a dragged tiled window where it was released: swapping it with the
+	/// window there, or moving it beside it.
+	///
+	/// The caller ungrabs the pointer. Changes to the layout are applied by the caller.
+	///
+	/// [drag]: Self::drag
+	pub fn on_button_release(&mut self, state: &mut AquariWm<ClientWindow>, pointer: (i32, i32)) -> Vec<Request> {
+		let Some(mut drag) = self.drag.take() else {
+			return Vec::new();
+		};
+		self.drag_motion.cancel();
+
+		let requests = self.drag_step(state, *drag.window(), drag.motion(pointer));
+		if let Some(layout) = state.tiling_layout() {
+			drag.hover(layout, pointer);
+		}
+		if let Drag::Swap {
+			window,
+			target: Some(drop),
+		} = &drag
+		{
+			state.drop_window(window, drop);
+		}
+
+		requests
+	}
+}
@@ -0,0 +1,247 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl Wm {
+	/// Manages the given top-level `window` as it is mapped, from what was [prefetched] about it,
+	/// recording it to be replayed.
+	///
+	/// Windows on other screens are left alone, and [ignored] windows are passed through. Other
+	/// windows open on the output their program was launched on, where the last window of their
+	/// class was closed, or as the [output assignment] decides; transient windows float above the
+	/// windows they belong to. Re-tiling the layout for the window is [deferred] until the windows
+	/// mapped along with it have been mapped too.
+	///
+	/// [prefetched]: Prefetched
+	/// [ignored]: crate::ignore
+	/// [output assignment]: Self::output_assignment
+	/// [deferred]: Self::pending_apply
+	pub fn on_map(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		placements: &mut PlacementMemory,
+		recorder: &mut Recorder,
+		window: ClientWindow,
+		prefetched: Prefetched,
+		received: Instant,
+	) -> Vec<Request> {
+		let Prefetched { snapshot, pointer } = prefetched;
+		// Maps followed while observing another window manager are handled like map requests.
+		let request = x11::MapRequestEvent {
+			parent: self.root,
+			window: window.raw(),
+			..Default::default()
+		};
+
+		// Windows on other screens, as on multi-screen ("Zaphod") setups, aren't AquariWM's to
+		// manage.
+		let Some(on_screen) = &snapshot.geometry else {
+			return Vec::new();
+		};
+		if on_screen.root != self.root {
+			return match self.map_other_screens {
+				true => {
+					event!(
+						Level::DEBUG,
+						"Mapping window {window} on another screen without managing it"
+					);
+
+					vec![Request::Map(window)]
+				},
+
+				false => {
+					event!(Level::DEBUG, "Leaving window {window} on another screen alone");
+
+					Vec::new()
+				},
+			};
+		}
+
+		// Ignored windows are passed through before any other policy is applied to them.
+		let class = snapshot.class.clone();
+		if state.ignored.matches(window.id(), class.as_deref()) {
+			recorder.note(Input::map_class(window.raw(), class.clone()), received);
+
+			let client = Client {
+				class,
+				..Client::default()
+			};
+			let requests = self.on_map_request(state, &request, client);
+			self.dispatch_queue.mapped(window);
+
+			return requests;
+		}
+		self.metrics.begin(Some(window), Operation::Map, received);
+
+		let parent = snapshot.transient_for;
+		let geometry = snapshot.fetched_geometry();
+		let client = Client {
+			class,
+			// Clients may ask for their windows to be sticky, or to open on a particular desktop,
+			// before mapping them.
+			sticky: snapshot.is_sticky(&self.atoms),
+			desktop: snapshot.desktop(),
+			geometry,
+			window_type: snapshot.window_type(&self.atoms),
+			visual: snapshot.visual,
+			..Client::default()
+		};
+		let pid = snapshot.pid;
+		self.snapshots.insert(window, snapshot);
+		self.update_group(window);
+
+		// Windows of programs launched by AquariWM open on the output which was active when they
+		// were launched, if their process IDs can be trusted.
+		let launch = match pid.get() {
+			Some(pid) => self.launches.take(pid, received, launch::parent_pid),
+			None => None,
+		};
+		let launch_output = launch
+			.and_then(|launch| launch.output)
+			.and_then(|name| Output::named(&state.outputs, &name).cloned());
+
+		// Otherwise, windows reopen where the last window of their class was closed, overriding the
+		// insertion strategy.
+		let unmapped = state
+			.windows
+			.get(&window)
+			.is_some_and(|window_state| window_state.mapped == state::MapState::Unmapped);
+		let remembered = match (&client.class, &launch_output) {
+			(Some(class), None) if unmapped => placements.recall(class, SystemTime::now()).cloned(),
+			_ => None,
+		};
+		let remembered_output = remembered
+			.as_ref()
+			.and_then(|placement| placement.output(&state.outputs))
+			.cloned();
+
+		let output = match launch_output.clone().or(remembered_output) {
+			Some(output) => Some(output),
+			None => self.route_window(state, pointer).cloned(),
+		};
+		if let Some(output) = &output {
+			event!(Level::DEBUG, "Opening window {window} on output {:?}", output.name);
+		}
+
+		// Transient windows, like dialogs, float above the windows they belong to.
+		let mut requests = Vec::new();
+		if let Some(parent) = parent {
+			if let Some(window_state) = state.windows.get_mut(&window) {
+				window_state.set_floating();
+			}
+
+			let output = placement::work_area(output.as_ref(), &state.outputs, self.screen);
+			requests.extend(self.place_requested(window, geometry, output, Some(parent)));
+		} else if let Some(geometry) = remembered
+			.as_ref()
+			.and_then(|placement| placement.floating_on(output.as_ref()))
+		{
+			if let Some(window_state) = state.windows.get_mut(&window) {
+				window_state.set_floating();
+			}
+
+			requests.extend(self.restore_floating(window, geometry));
+		}
+
+		if let Some(visual) = client.visual {
+			let decorations = visual.decorations(self.palettes.screen_depth());
+			if decorations != Decorations::Screen {
+				event!(
+					Level::DEBUG,
+					"Window {window} has a {}-bit visual, so it is decorated with {decorations:?}",
+					visual.depth
+				);
+			}
+		}
+		let client = Client {
+			launch_output,
+			remembered,
+			..client
+		};
+
+		// Windows without clear signals that they are normal windows, like splash screens, float
+		// until they have stayed mapped for the admission delay, so that those which are gone again
+		// straight away never re-tile the layout. Rules which match on the title are taken into
+		// account.
+		self.update_title(window);
+		if recorder.is_recording() {
+			let hints = self.size_hints(window);
+
+			let input = Input::Map {
+				window: window.raw(),
+				class: client.class.clone(),
+				title: self.titles.get(&window).cloned(),
+				launch_output: client.launch_output.as_ref().map(|output| output.name.clone()),
+				remembered: client.remembered.clone(),
+				sticky: client.sticky,
+				desktop: client.desktop,
+				geometry: client.geometry,
+				window_type: client.window_type,
+				floating: (state.windows.get(&window))
+					.is_some_and(|window_state| window_state.mode == layout::Mode::Floating),
+				increments: hints.increments(),
+				min_size: hints.min_size,
+			};
+			recorder.note(input, received);
+		}
+		if self.hold_admission(state, window, &client, received) {
+			let output = placement::work_area(output.as_ref(), &state.outputs, self.screen);
+			requests.extend(self.place_requested(window, geometry, output, None));
+		}
+
+		let mapped = self.on_map_request(state, &request, client);
+
+		requests.extend(self.sticky_changed(state, window));
+		// Fullscreen windows are never dimmed, so whether the window is fullscreen is known first.
+		requests.extend(self.update_fullscreen(state, window));
+		requests.extend(self.update_opacity(state, window));
+		requests.extend(self.apply_rules(state, window, false));
+
+		requests.extend(mapped);
+		self.update_size_hints(state, window);
+		// The window is in the layout, but re-tiling it waits for the windows mapped along with it.
+		self.pending_apply.defer(received);
+
+		// Windows may demand attention as soon as they are mapped.
+		requests.extend(self.update_urgency(state, window, received));
+
+		requests
+	}
+
+	/// Chooses the [output] on which a new window is opened using the [output assignment] policy,
+	/// given the `pointer`'s position if the policy follows it.
+	///
+	/// [output]: Output
+	/// [output assignment]: Self::output_assignment
+	fn route_window<'state>(
+		&self,
+		state: &'state AquariWm<ClientWindow>,
+		pointer: Option<(i32, i32)>,
+	) -> Option<&'state Output> {
+		// Named workspaces can't be pinned to outputs, and no workspace is numbered 0.
+		let workspace = match &state.workspaces.names()[state.workspaces.current()] {
+			Name::Number(number) => *number,
+			Name::Named(_) => 0,
+		};
+
+		let focus = state
+			.focused
+			.and_then(|focused| self.known_geometry(focused))
+			.map(|geometry| {
+				(
+					geometry.x + (geometry.width as i32) / 2,
+					geometry.y + (geometry.height as i32) / 2,
+				)
+			});
+
+		self.output_assignment.route(Routing {
+			outputs: &state.outputs,
+			workspace,
+
+			focus,
+			pointer,
+		})
+	}
+}
@@ -0,0 +1,276 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl Wm {
+	/// Acts on a client message about a managed window: a client answering a ping, or a client or
+	/// pager asking for a window to be made sticky, moved to another desktop, activated, or moved
+	/// or resized interactively. Pagers may also ask for another desktop to be switched to.
+	///
+	/// A move or resize starts a [drag]; the caller grabs the pointer for it. Changes to the layout
+	/// are applied by the caller.
+	///
+	/// [drag]: Self::drag
+	pub fn on_client_message(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		message: &x11::ClientMessageEvent,
+	) -> Vec<Request> {
+		if message.format != 32 {
+			return Vec::new();
+		}
+		let data = message.data.as_data32();
+
+		if message.window == self.root && message.type_ == self.atoms._NET_CURRENT_DESKTOP {
+			return self.on_current_desktop_request(state, data[0]);
+		}
+
+		// Clients answer pings by sending them back to the root window, naming their window.
+		let window = match message.type_ == self.atoms.WM_PROTOCOLS && data[0] == self.atoms._NET_WM_PING {
+			true => data[2],
+			false => message.window,
+		};
+		let WindowRole::Client(window) = self.resolve_window(state, window) else {
+			return Vec::new();
+		};
+
+		if message.type_ == self.atoms.WM_PROTOCOLS {
+			if data[0] == self.atoms._NET_WM_PING {
+				self.pong(window, data[1]);
+			}
+
+			Vec::new()
+		} else if message.type_ == self.atoms._NET_WM_STATE {
+			self.on_state_request(state, window, data)
+		} else if message.type_ == self.atoms._NET_WM_DESKTOP {
+			self.on_desktop_request(state, window, Desktop::from_ewmh(data[0]))
+		} else if message.type_ == self.atoms._NET_ACTIVE_WINDOW {
+			self.on_activation(state, window, data)
+		} else if message.type_ == self.atoms._NET_WM_MOVERESIZE {
+			self.on_moveresize(state, window, data)
+		} else {
+			Vec::new()
+		}
+	}
+
+	/// Records the given `window`'s client answering the ping with the given `time`.
+	///
+	/// If its client was unresponsive, the window is given its usual border color back when
+	/// borders are next [painted].
+	///
+	/// [painted]: Self::paint_borders
+	fn pong(&mut self, window: ClientWindow, time: x11::Timestamp) {
+		if self.pings.pong(&window, time) == Pong::Recovered {
+			event!(Level::INFO, "The client of window {window} is responding again");
+		}
+	}
+
+	/// Makes the given `window` sticky, or no longer sticky, when its client requests it with a
+	/// `_NET_WM_STATE` message with the given `data`.
+	fn on_state_request(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		data: [u32; 5],
+	) -> Vec<Request> {
+		let Some(request) = StateRequest::decode(data) else {
+			event!(Level::DEBUG, "Ignoring _NET_WM_STATE request with an unknown action");
+			return Vec::new();
+		};
+		if !request.changes(self.atoms._NET_WM_STATE_STICKY) {
+			return Vec::new();
+		}
+
+		let Some(sticky) = state.windows.get(&window).map(|window_state| window_state.sticky) else {
+			return Vec::new();
+		};
+		state.set_sticky(&window, request.action.apply(sticky));
+
+		self.sticky_changed(state, window)
+	}
+
+	/// Moves the given `window` to the given `desktop` when its client, or a pager, requests it.
+	///
+	/// Windows may be moved onto every desktop, or to any workspace including the one after the
+	/// last, which is created for it.
+	fn on_desktop_request(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		desktop: Desktop,
+	) -> Vec<Request> {
+		if !desktop.is_available(state.workspaces.names().len() as u32 + 1) {
+			event!(
+				Level::DEBUG,
+				"Ignoring _NET_WM_DESKTOP request for window {window}: desktop {desktop:?} doesn't exist"
+			);
+			return Vec::new();
+		}
+
+		match desktop {
+			Desktop::All => {
+				if !state.set_sticky(&window, true) {
+					return Vec::new();
+				}
+
+				self.sticky_changed(state, window)
+			},
+
+			Desktop::Index(index) => {
+				let previous = state.focused;
+				let previous_target = state.focus_target_windows();
+
+				let mut requests = self.move_to_workspace(state, window, index as usize);
+				requests.extend(self.focus_changed(state, previous));
+				requests.extend(self.focus_target_changed(state, previous_target));
+
+				requests
+			},
+		}
+	}
+
+	/// Switches to the given `desktop` when a pager asks for it with a `_NET_CURRENT_DESKTOP`
+	/// message.
+	fn on_current_desktop_request(&mut self, state: &mut AquariWm<ClientWindow>, desktop: u32) -> Vec<Request> {
+		let previous = state.focused;
+		let previous_target = state.focus_target_windows();
+
+		let mut requests = self.switch_workspace(state, desktop as usize);
+		requests.extend(self.focus_changed(state, previous));
+		requests.extend(self.focus_target_changed(state, previous_target));
+
+		requests
+	}
+
+	/// Restores the given iconified `window`, or switches to the workspace of a window on another
+	/// workspace and focuses it there, when a client, like a taskbar, asks for it to be activated
+	/// with a `_NET_ACTIVE_WINDOW` message with the given `data`.
+	///
+	/// Window switchers, which identify themselves as pagers, may also focus windows which are
+	/// already shown: this is how a window picked from the window list is focused (`focus-window
+	/// <id>`).
+	fn on_activation(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		data: [u32; 5],
+	) -> Vec<Request> {
+		const SOURCE_PAGER: u32 = 2;
+
+		let switcher = data[0] == SOURCE_PAGER;
+		let (iconified, away) = (state.is_iconified(&window), state.is_away(&window));
+		if !iconified && !away && !switcher {
+			event!(
+				Level::DEBUG,
+				"Ignoring _NET_ACTIVE_WINDOW request for window {window}: it is already shown"
+			);
+			return Vec::new();
+		}
+
+		let request = match switcher {
+			true => FocusRequest::Switcher,
+			false => FocusRequest::Activation,
+		};
+		if !self.may_focus(state, Some(window), request) {
+			return Vec::new();
+		}
+
+		match iconified {
+			true => {
+				let previous = state.focused;
+
+				let mut requests = self.restore_window(state, window);
+				requests.extend(self.focus_changed(state, previous));
+
+				requests
+			},
+
+			false => {
+				let previous = state.focused;
+				let mut requests = Vec::new();
+
+				if let (true, Desktop::Index(index)) = (away, state.desktop_of(&window)) {
+					requests.extend(self.switch_workspace(state, index as usize));
+
+					// Focus may have moved on from a window hidden by switching workspaces.
+					if let Some(previous) = previous.filter(|&previous| state.focused != Some(previous)) {
+						requests.extend(self.update_opacity(state, previous));
+					}
+				}
+				// The output the window is on becomes the active output.
+				requests.extend(self.focus_window(state, window, FocusCause::Activation));
+
+				requests
+			},
+		}
+	}
+
+	/// Starts or cancels a [drag] of the given `window` when its client requests it with a
+	/// `_NET_WM_MOVERESIZE` message with the given `data`, e.g. because its header bar is dragged.
+	///
+	/// Only one window is dragged at a time. The drag starts from the window's [known geometry].
+	///
+	/// [drag]: Self::drag
+	/// [known geometry]: Self::known_geometry
+	fn on_moveresize(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		data: [u32; 5],
+	) -> Vec<Request> {
+		let Some(request) = moveresize::Request::decode(data) else {
+			event!(
+				Level::DEBUG,
+				"Ignoring _NET_WM_MOVERESIZE request with an unknown direction"
+			);
+			return Vec::new();
+		};
+
+		match request.direction {
+			Direction::Cancel => match self.drag.take() {
+				Some(drag) => {
+					self.drag_motion.cancel();
+
+					self.drag_step(state, *drag.window(), drag.cancel())
+				},
+
+				None => Vec::new(),
+			},
+
+			Direction::MoveKeyboard | Direction::ResizeKeyboard => {
+				event!(
+					Level::DEBUG,
+					"Ignoring keyboard move or resize of window {window}: not supported"
+				);
+
+				Vec::new()
+			},
+
+			Direction::Move | Direction::Resize(_) => {
+				let (Some(window_state), None) = (state.windows.get(&window), &self.drag) else {
+					return Vec::new();
+				};
+				let Some(geometry) = self.known_geometry(window) else {
+					return Vec::new();
+				};
+				let geometry = (geometry.x, geometry.y, geometry.width, geometry.height);
+
+				let layout = state
+					.tiling_layout()
+					.filter(|_| window_state.mode == layout::Mode::Tiled);
+
+				self.drag = Drag::start(window, &request, geometry, layout);
+				if self.drag.is_none() {
+					event!(
+						Level::DEBUG,
+						"Ignoring resize of window {window}: it shares no edge with another window there"
+					);
+				}
+
+				Vec::new()
+			},
+		}
+	}
+}
@@ -0,0 +1,256 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl Wm {
+	/// Acts on a change to one of a managed window's properties, once its [snapshot] has been
+	/// updated with it, recording the changes which are replayed.
+	///
+	/// A changed urgency hint may let the window steal focus, a changed `_NET_WM_STATE` may put it
+	/// into or take it out of game mode, and a changed title may make rules apply to it. Changes to
+	/// the layout are applied by the caller.
+	///
+	/// [snapshot]: Self::snapshots
+	pub fn on_property_notify(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		recorder: &mut Recorder,
+		notify: &x11::PropertyNotifyEvent,
+		received: Instant,
+	) -> Vec<Request> {
+		let WindowRole::Client(window) = self.resolve_window(state, notify.window) else {
+			return Vec::new();
+		};
+		let atom = notify.atom;
+
+		if atom == u32::from(x11::AtomEnum::WM_HINTS) {
+			// The window's input hint and window group may have changed too.
+			self.input_models.remove(&window);
+			self.update_group(window);
+
+			self.update_urgency(state, window, received)
+		} else if atom == self.atoms._NET_WM_STATE {
+			self.update_fullscreen(state, window)
+		} else if atom == self.atoms._NET_WM_NAME || atom == u32::from(x11::AtomEnum::WM_NAME) {
+			self.update_title(window);
+			let input = Input::Title {
+				window: window.raw(),
+				title: self.titles.get(&window).cloned(),
+			};
+			recorder.note(input, received);
+
+			self.apply_rules(state, window, true)
+		} else if atom == u32::from(x11::AtomEnum::WM_NORMAL_HINTS) {
+			let hints = self.size_hints(window);
+			let input = Input::SizeHints {
+				window: window.raw(),
+				increments: hints.increments(),
+				min_size: hints.min_size,
+			};
+			recorder.note(input, received);
+
+			self.update_size_hints(state, window);
+			Vec::new()
+		} else {
+			// If the window's supported protocols have changed, its input model may have too.
+			if atom == self.atoms.WM_PROTOCOLS {
+				self.input_models.remove(&window);
+			}
+
+			Vec::new()
+		}
+	}
+
+	/// Caches the title of the given `window` from the `_NET_WM_NAME` property in its [snapshot]
+	/// or, failing that, its `WM_NAME` property.
+	///
+	/// [snapshot]: Self::snapshots
+	pub fn update_title(&mut self, window: ClientWindow) {
+		let title = self
+			.snapshots
+			.get(&window)
+			.and_then(ClientSnapshot::title)
+			.map(str::to_owned);
+
+		match title {
+			Some(title) => self.titles.insert(window, title),
+			None => self.titles.remove(&window),
+		};
+	}
+
+	/// Reads the resize increments and minimum size of the given tiled `window` from the
+	/// `WM_NORMAL_HINTS` in its [snapshot], returning whether it is in the tiling layout.
+	///
+	/// [snapshot]: Self::snapshots
+	pub fn update_size_hints(&self, state: &mut AquariWm<ClientWindow>, window: ClientWindow) -> bool {
+		let hints = self.size_hints(window);
+
+		state.set_minimum_size(&window, hints.min_size, self.border_width(window));
+		state.set_increments(&window, hints.increments())
+	}
+
+	/// Tracks whether the given `window` is demanding attention with the urgency hint in the
+	/// `WM_HINTS` in its [snapshot], letting it steal focus if it has just become urgent and the
+	/// [focus stealing] policy allows it at the time `now`.
+	///
+	/// [snapshot]: Self::snapshots
+	/// [focus stealing]: Self::focus_stealing
+	pub fn update_urgency(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		now: Instant,
+	) -> Vec<Request> {
+		let snapshot = self.snapshots.get(&window);
+		let urgent = snapshot.is_some_and(ClientSnapshot::is_urgent);
+		let kind = match snapshot.and_then(|snapshot| snapshot.transient_for) {
+			Some(_) => WindowKind::Dialog,
+			None => WindowKind::Normal,
+		};
+
+		let newly_urgent = match urgent {
+			true => self.urgent.insert(window),
+
+			false => {
+				self.urgent.remove(&window);
+
+				false
+			},
+		};
+		if !newly_urgent || state.focused == Some(window) {
+			return Vec::new();
+		}
+
+		match urgency::decide(self.focus_stealing, self.last_input, now, kind) {
+			urgency::Decision::Steal => {
+				if !self.may_focus(state, Some(window), FocusRequest::Urgent) {
+					return Vec::new();
+				}
+
+				event!(Level::DEBUG, "Urgent window {window} is stealing focus");
+
+				self.focus_window(state, window, FocusCause::Urgency)
+			},
+
+			urgency::Decision::Suppress(reason) => {
+				event!(Level::DEBUG, "Urgent window {window} is not stealing focus: {reason}");
+
+				Vec::new()
+			},
+		}
+	}
+
+	/// Records whether the given `window` is fullscreen according to the `_NET_WM_STATE` in its
+	/// [snapshot], putting it into [game mode] if it has become fullscreen, or taking it out of
+	/// game mode if it no longer is.
+	///
+	/// [snapshot]: Self::snapshots
+	/// [game mode]: FullscreenPolicy::GameMode
+	pub fn update_fullscreen(&mut self, state: &AquariWm<ClientWindow>, window: ClientWindow) -> Vec<Request> {
+		let Some(snapshot) = self.snapshots.get(&window) else {
+			return Vec::new();
+		};
+		let fullscreen = snapshot.is_fullscreen(&self.atoms);
+		let bypass = match fullscreen && self.focus_lock.policy().is_game_mode() {
+			true => snapshot.bypass_compositor,
+			false => None,
+		};
+
+		let locked = self.focus_lock.holder().copied();
+
+		let mut requests = self.fullscreen_changed(window, fullscreen, bypass);
+		requests.extend(self.focus_lock_released(state, locked));
+
+		requests
+	}
+
+	/// [Evaluates the rules] for the given `window`, either because it was just mapped or because
+	/// its title changed, giving it a floating geometry if it newly floats.
+	///
+	/// [Evaluates the rules]: Self::evaluate_rules
+	pub fn apply_rules(
+		&mut self,
+		state: &mut AquariWm<ClientWindow>,
+		window: ClientWindow,
+		title_changed: bool,
+	) -> Vec<Request> {
+		let Some(mode) = state.windows.get(&window).map(|window_state| window_state.mode) else {
+			return Vec::new();
+		};
+		if title_changed && !self.rules.reevaluated_on_title_change() {
+			return Vec::new();
+		}
+
+		let output = state.output_of(&window).cloned();
+
+		let transition = self.evaluate_rules(state, window, title_changed);
+		if transition.is_unchanged() {
+			return Vec::new();
+		}
+
+		let floating = state
+			.windows
+			.get(&window)
+			.is_some_and(|window_state| window_state.mode == layout::Mode::Floating);
+		let moved_to = match &transition.applied {
+			Some(RuleAction::Output(name)) => Output::named(&state.outputs, name).cloned(),
+			_ => None,
+		};
+		// Windows reverted to floating return to where they were.
+		let restored = transition
+			.reverted
+			.as_ref()
+			.and_then(|previous| previous.placement.as_ref())
+			.and_then(|placement| placement.floating_on(placement.output(&state.outputs)));
+
+		let mut requests = match (mode, floating) {
+			(_, false) => {
+				self.floating.remove(&window);
+
+				Vec::new()
+			},
+
+			(layout::Mode::Floating, true) if moved_to.is_none() => match (&transition.applied, restored) {
+				(None, Some(geometry)) => self.restore_floating(window, geometry),
+				_ => Vec::new(),
+			},
+			// Windows which newly float, or are moved to another output, are placed on their output.
+			(_, true) => {
+				let output = placement::work_area(moved_to.or(output).as_ref(), &state.outputs, self.screen);
+
+				self.place_floating(window, output, None)
+			},
+		};
+		requests.extend(self.update_opacity(state, window));
+
+		requests
+	}
+
+	/// Returns the requests to show whether the given `window` is [sticky] in its `_NET_WM_STATE`
+	/// property and which [desktop] it is on in its `_NET_WM_DESKTOP` property, raising it if it is
+	/// a sticky floating window.
+	///
+	/// [desktop]: AquariWm::desktop_of
+	/// [sticky]: state::WindowState::sticky
+	pub fn sticky_changed(&mut self, state: &AquariWm<ClientWindow>, window: ClientWindow) -> Vec<Request> {
+		let Some(window_state) = state.windows.get(&window) else {
+			return Vec::new();
+		};
+		let sticky = window_state.sticky;
+
+		let desktop = state.desktop_of(&window);
+		let mut requests = vec![
+			Request::SetState(window, self.atoms._NET_WM_STATE_STICKY, sticky),
+			Request::SetDesktop(window, desktop.to_ewmh()),
+		];
+
+		// Sticky floating windows stay above the tiled windows.
+		if sticky && window_state.mode == layout::Mode::Floating {
+			requests.extend(self.raise_floating(state, window));
+		}
+
+		requests
+	}
+}
@@ -0,0 +1,98 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Requests from clients to change their windows' [`_NET_WM_STATE`].
+//!
+//! Clients change the state of their mapped windows, e.g. to make them sticky, by sending the root
+//! window a `_NET_WM_STATE` client message naming one or two states and whether to add, remove or
+//! toggle them. This module [decodes] those messages.
+//!
+//! [`_NET_WM_STATE`]: https://specifications.freedesktop.org/wm-spec/latest/ar01s05.html#id-1.6.8
+//! [decodes]: StateRequest::decode
+
+/// Whether a [state request] adds, removes or toggles its states.
+///
+/// [state request]: StateRequest
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum StateAction {
+	Remove,
+	Add,
+	Toggle,
+}
+
+/// A client's request to change one or two of its window's states.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct StateRequest {
+	pub action: StateAction,
+	/// The atoms of the states to change; the second is `0` (`None`) if only one is changed.
+	pub properties: [u32; 2],
+}
+
+impl StateAction {
+	/// Returns whether a state which is currently `set` is set after this action.
+	pub const fn apply(&self, set: bool) -> bool {
+		match self {
+			Self::Remove => false,
+			Self::Add => true,
+			Self::Toggle => !set,
+		}
+	}
+}
+
+impl StateRequest {
+	/// Decodes a request from the 32-bit `data` of a `_NET_WM_STATE` client message.
+	///
+	/// Returns [`None`] if the action is unknown.
+	pub const fn decode(data: [u32; 5]) -> Option<Self> {
+		let action = match data[0] {
+			0 => StateAction::Remove,
+			1 => StateAction::Add,
+			2 => StateAction::Toggle,
+
+			_ => return None,
+		};
+
+		Some(Self {
+			action,
+			properties: [data[1], data[2]],
+		})
+	}
+
+	/// Returns whether the request changes the state of the given atom.
+	pub fn changes(&self, state: u32) -> bool {
+		const NONE: u32 = 0;
+
+		state != NONE && self.properties.contains(&state)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const STICKY: u32 = 300;
+	const ABOVE: u32 = 301;
+
+	#[test]
+	fn requests() {
+		let request = StateRequest::decode([1, STICKY, 0, 1, 0]).unwrap();
+		assert_eq!(request.action, StateAction::Add);
+		assert!(request.changes(STICKY));
+		assert!(!request.changes(ABOVE));
+		// An unused second state doesn't match `None`.
+		assert!(!request.changes(0));
+
+		let request = StateRequest::decode([2, ABOVE, STICKY, 2, 0]).unwrap();
+		assert!(request.changes(STICKY) && request.changes(ABOVE));
+
+		assert_eq!(StateRequest::decode([3, STICKY, 0, 1, 0]), None);
+	}
+
+	#[test]
+	fn actions() {
+		assert_eq!([false, true].map(|set| StateAction::Remove.apply(set)), [false, false]);
+		assert_eq!([false, true].map(|set| StateAction::Add.apply(set)), [true, true]);
+		assert_eq!([false, true].map(|set| StateAction::Toggle.apply(set)), [true, false]);
+	}
+}
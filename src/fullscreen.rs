@@ -0,0 +1,324 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! What happens while a window, like a game or a video player, is fullscreen.
+//!
+//! With [game mode], a fullscreen window takes the [focus lock]: while it is focused, incidental
+//! focus changes, like the pointer wandering onto another output, are [suppressed], and its
+//! neighbours' decorations are left as they are. The lock is released when the window stops being
+//! fullscreen or is closed.
+//!
+//! Which focus changes the lock suppresses is decided by [`FocusLock::decide`].
+//!
+//! [game mode]: FullscreenPolicy::GameMode
+//! [focus lock]: FocusLock
+//! [suppressed]: Decision::Suppress
+
+use std::{
+	fmt,
+	fmt::{Display, Formatter},
+	str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::window::ManagedWindow;
+
+/// What happens while a window is fullscreen.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum FullscreenPolicy {
+	/// Fullscreen windows are treated like any other window.
+	#[default]
+	Normal,
+	/// Fullscreen windows take the [focus lock], and ask the compositor to stop compositing them.
+	///
+	/// [focus lock]: FocusLock
+	GameMode {
+		/// Whether [urgent] windows may still steal focus from the fullscreen window, if their
+		/// [focus stealing policy] lets them.
+		///
+		/// [urgent]: crate::urgency
+		/// [focus stealing policy]: crate::urgency::FocusStealing
+		urgent_overrides: bool,
+	},
+}
+
+/// What is asking for focus to change.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum FocusRequest {
+	/// The pointer entered a window, or the root window.
+	Pointer,
+	/// The user explicitly asked for focus to change with a key binding.
+	Keyboard,
+	/// A window became [urgent], and its [focus stealing policy] lets it steal focus.
+	///
+	/// [urgent]: crate::urgency
+	/// [focus stealing policy]: crate::urgency::FocusStealing
+	Urgent,
+	/// A client, like a taskbar, asked for a window to be activated.
+	Activation,
+	/// A window switcher asked for a window to be focused on the user's behalf, having presented
+	/// the [window list] to the user.
+	///
+	/// [window list]: crate::window_list
+	Switcher,
+	/// The user switched to another workspace, focusing a window on it.
+	WorkspaceSwitch,
+}
+
+/// Whether a focus change happens, as [decided] by the [focus lock].
+///
+/// [decided]: FocusLock::decide
+/// [focus lock]: FocusLock
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Decision {
+	/// Focus changes.
+	Allow,
+	/// Focus stays on the window holding the lock.
+	Suppress,
+}
+
+/// The window which is fullscreen in [game mode], if there is one.
+///
+/// Only one window holds the lock at a time, and the lock is only *engaged* while that window is
+/// focused: if the user explicitly focuses another window, focus changes as usual until the
+/// fullscreen window is focused again.
+///
+/// [game mode]: FullscreenPolicy::GameMode
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FocusLock<Window> {
+	policy: FullscreenPolicy,
+	holder: Option<Window>,
+}
+
+impl FullscreenPolicy {
+	/// Returns whether fullscreen windows take the [focus lock].
+	///
+	/// [focus lock]: FocusLock
+	#[inline(always)]
+	pub const fn is_game_mode(self) -> bool {
+		matches!(self, Self::GameMode { .. })
+	}
+}
+
+impl<Window: ManagedWindow> FocusLock<Window> {
+	/// Creates a focus lock, which isn't held by any window yet, for the given `policy`.
+	pub const fn new(policy: FullscreenPolicy) -> Self {
+		Self { policy, holder: None }
+	}
+
+	/// Returns what happens while a window is fullscreen.
+	#[inline(always)]
+	pub const fn policy(&self) -> FullscreenPolicy {
+		self.policy
+	}
+
+	/// Returns the window which holds the lock, if any.
+	#[inline(always)]
+	pub const fn holder(&self) -> Option<&Window> {
+		self.holder.as_ref()
+	}
+
+	/// Gives the lock to the given (fullscreen) `window`, returning the window which held it
+	/// before, if that was another window.
+	///
+	/// The lock is never taken unless the [policy] is [game mode].
+	///
+	/// [policy]: Self::policy
+	/// [game mode]: FullscreenPolicy::GameMode
+	pub fn lock(&mut self, window: Window) -> Option<Window> {
+		if !self.policy.is_game_mode() {
+			return None;
+		}
+
+		self.holder
+			.replace(window)
+			.filter(|previous| Some(previous) != self.holder.as_ref())
+	}
+
+	/// Releases the lock if it is held by the given `window`, returning whether it was.
+	pub fn release(&mut self, window: &Window) -> bool {
+		if self.holder.as_ref() == Some(window) {
+			self.holder = None;
+
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Returns whether the lock is held by the `focused` window, so that focus changes may be
+	/// [suppressed].
+	///
+	/// [suppressed]: Decision::Suppress
+	pub fn is_engaged(&self, focused: Option<&Window>) -> bool {
+		self.holder.is_some() && self.holder.as_ref() == focused
+	}
+
+	/// Decides whether focus changes from the `focused` window to the `target` window (or the root
+	/// window, if [`None`]) because of the given `request`.
+	///
+	/// While the lock is [engaged], only explicit requests from the user (key bindings, workspace
+	/// switches, and window switchers) change focus, as well as urgent windows if the [policy] lets
+	/// them override the lock. Focus can always move to the window holding the lock.
+	///
+	/// [engaged]: Self::is_engaged
+	/// [policy]: FullscreenPolicy::GameMode::urgent_overrides
+	pub fn decide(&self, focused: Option<&Window>, target: Option<&Window>, request: FocusRequest) -> Decision {
+		if !self.is_engaged(focused) || target == self.holder.as_ref() {
+			return Decision::Allow;
+		}
+
+		match (request, self.policy) {
+			(FocusRequest::Keyboard | FocusRequest::WorkspaceSwitch | FocusRequest::Switcher, _) => Decision::Allow,
+			(FocusRequest::Urgent, FullscreenPolicy::GameMode { urgent_overrides: true }) => Decision::Allow,
+
+			(FocusRequest::Urgent | FocusRequest::Pointer | FocusRequest::Activation, _) => Decision::Suppress,
+		}
+	}
+}
+
+impl Display for FocusRequest {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Pointer => write!(f, "the pointer"),
+			Self::Keyboard => write!(f, "a key binding"),
+			Self::Urgent => write!(f, "an urgent window"),
+			Self::Activation => write!(f, "an activation request"),
+			Self::Switcher => write!(f, "a window switcher"),
+			Self::WorkspaceSwitch => write!(f, "a workspace switch"),
+		}
+	}
+}
+
+/// An error returned when parsing a [`FullscreenPolicy`] fails.
+#[derive(Debug, Error)]
+#[error("expected `normal`, `game`, or `game-allow-urgent`, found {0:?}")]
+pub struct ParseFullscreenPolicyError(String);
+
+impl FromStr for FullscreenPolicy {
+	type Err = ParseFullscreenPolicyError;
+
+	/// Parses `normal`, `game` for [game mode], or `game-allow-urgent` for game mode which
+	/// [urgent windows may override].
+	///
+	/// [game mode]: FullscreenPolicy::GameMode
+	/// [urgent windows may override]: FullscreenPolicy::GameMode::urgent_overrides
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim() {
+			"normal" => Ok(Self::Normal),
+			"game" => Ok(Self::GameMode {
+				urgent_overrides: false,
+			}),
+			"game-allow-urgent" => Ok(Self::GameMode { urgent_overrides: true }),
+
+			other => Err(ParseFullscreenPolicyError(other.to_owned())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const GAME: u32 = 1;
+	const OTHER: u32 = 2;
+
+	const STRICT: FullscreenPolicy = FullscreenPolicy::GameMode {
+		urgent_overrides: false,
+	};
+	const LENIENT: FullscreenPolicy = FullscreenPolicy::GameMode { urgent_overrides: true };
+
+	fn locked(policy: FullscreenPolicy) -> FocusLock<u32> {
+		let mut lock = FocusLock::new(policy);
+		lock.lock(GAME);
+
+		lock
+	}
+
+	#[test]
+	fn matrix() {
+		use Decision::{Allow, Suppress};
+		use FocusRequest::*;
+
+		// (policy, focused, target, request, decision)
+		#[rustfmt::skip]
+		let cases = [
+			// Engaged: the game is focused and focus would move elsewhere.
+			(STRICT, Some(GAME), Some(OTHER), Pointer, Suppress),
+			(STRICT, Some(GAME), None, Pointer, Suppress),
+			(STRICT, Some(GAME), Some(OTHER), Keyboard, Allow),
+			(STRICT, Some(GAME), Some(OTHER), Urgent, Suppress),
+			(STRICT, Some(GAME), Some(OTHER), Activation, Suppress),
+			(STRICT, Some(GAME), Some(OTHER), WorkspaceSwitch, Allow),
+			(STRICT, Some(GAME), Some(OTHER), Switcher, Allow),
+
+			(LENIENT, Some(GAME), Some(OTHER), Pointer, Suppress),
+			(LENIENT, Some(GAME), Some(OTHER), Keyboard, Allow),
+			(LENIENT, Some(GAME), Some(OTHER), Urgent, Allow),
+			(LENIENT, Some(GAME), Some(OTHER), Activation, Suppress),
+			(LENIENT, Some(GAME), Some(OTHER), WorkspaceSwitch, Allow),
+
+			// Focus can always move to the game.
+			(STRICT, Some(GAME), Some(GAME), Pointer, Allow),
+			(STRICT, Some(OTHER), Some(GAME), Pointer, Allow),
+			(STRICT, Some(OTHER), Some(GAME), Activation, Allow),
+
+			// Not engaged: the game isn't focused.
+			(STRICT, Some(OTHER), Some(3), Pointer, Allow),
+			(STRICT, Some(OTHER), None, Pointer, Allow),
+			(STRICT, None, Some(OTHER), Urgent, Allow),
+			(STRICT, None, Some(OTHER), Activation, Allow),
+		];
+
+		for (policy, focused, target, request, decision) in cases {
+			let lock = locked(policy);
+
+			assert_eq!(
+				lock.decide(focused.as_ref(), target.as_ref(), request),
+				decision,
+				"{policy:?}: {request} moving focus from {focused:?} to {target:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn lock() {
+		// The lock is never taken outside of game mode.
+		let mut lock = FocusLock::new(FullscreenPolicy::Normal);
+		assert_eq!(lock.lock(GAME), None);
+		assert_eq!(lock.holder(), None);
+		assert_eq!(
+			lock.decide(Some(&GAME), Some(&OTHER), FocusRequest::Pointer),
+			Decision::Allow
+		);
+
+		let mut lock = FocusLock::new(STRICT);
+		assert_eq!(lock.lock(GAME), None);
+		// Locking again with the same window doesn't displace it.
+		assert_eq!(lock.lock(GAME), None);
+		// Another fullscreen window takes the lock.
+		assert_eq!(lock.lock(OTHER), Some(GAME));
+		assert_eq!(lock.holder(), Some(&OTHER));
+
+		// Only the holder releases the lock.
+		assert!(!lock.release(&GAME));
+		assert!(lock.release(&OTHER));
+		assert_eq!(lock.holder(), None);
+		assert!(!lock.is_engaged(Some(&OTHER)));
+		assert_eq!(
+			lock.decide(Some(&OTHER), Some(&GAME), FocusRequest::Pointer),
+			Decision::Allow
+		);
+	}
+
+	#[test]
+	fn parse() {
+		assert_eq!("normal".parse::<FullscreenPolicy>().unwrap(), FullscreenPolicy::Normal);
+		assert_eq!(" game".parse::<FullscreenPolicy>().unwrap(), STRICT);
+		assert_eq!("game-allow-urgent".parse::<FullscreenPolicy>().unwrap(), LENIENT);
+
+		assert!("games".parse::<FullscreenPolicy>().is_err());
+	}
+}
@@ -0,0 +1,297 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Windows which AquariWM leaves alone, like screenshot tools, screen magnifiers and on-screen
+//! keyboards, which misbehave when they are managed.
+//!
+//! Windows matching any of the [matchers] in the [ignore list] - by a glob of their class, or by
+//! their window ID - are passed through when they are mapped, before any other policy is applied:
+//! they are never tiled, focused by AquariWM or dimmed, and their configure requests are honored
+//! verbatim. The list can be changed at runtime with [actions], and is written to `ignored.json`
+//! alongside the [layout snapshots], so that it persists across sessions.
+//!
+//! [matchers]: Matcher
+//! [ignore list]: IgnoreList
+//! [actions]: crate::action::Action::Ignore
+//! [layout snapshots]: crate::autosave
+
+use std::{
+	fmt,
+	fmt::{Display, Formatter},
+	fs,
+	io::{self, Write},
+	path::Path,
+	str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::window::WindowId;
+
+/// The file name of the ignore list.
+const FILE_NAME: &str = "ignored.json";
+
+/// Matches windows to be ignored.
+///
+/// Matchers are parsed from `<class|id>`: a window ID in decimal or `0x`-prefixed hexadecimal, or
+/// otherwise a class glob. A class which looks like a window ID can be given as `class:<class>`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub enum Matcher {
+	/// Matches windows whose class matches the glob, in which `*` matches any number of characters
+	/// and `?` matches any one character.
+	Class(String),
+	/// Matches the window with the given [ID].
+	///
+	/// [ID]: WindowId
+	Window(WindowId),
+}
+
+/// Windows which are [ignored] by AquariWM.
+///
+/// [ignored]: self
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct IgnoreList {
+	/// The matchers, in the order they were added.
+	matchers: Vec<Matcher>,
+}
+
+impl Matcher {
+	/// Returns whether the matcher matches a window with the given ID and `class`.
+	pub fn matches(&self, window: WindowId, class: Option<&str>) -> bool {
+		match self {
+			Self::Class(glob) => class.is_some_and(|class| glob_matches(glob, class)),
+			Self::Window(id) => *id == window,
+		}
+	}
+}
+
+impl FromStr for Matcher {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Some(class) = s.strip_prefix("class:") {
+			return match class.is_empty() {
+				true => Err("the class must not be empty"),
+				false => Ok(Self::Class(class.to_owned())),
+			};
+		}
+
+		match s.parse() {
+			Ok(id) => Ok(Self::Window(id)),
+
+			Err(_) if s.is_empty() => Err("expected a class or a window ID"),
+			Err(_) => Ok(Self::Class(s.to_owned())),
+		}
+	}
+}
+
+impl Display for Matcher {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Class(glob) => write!(f, "class:{glob}"),
+			Self::Window(id) => write!(f, "{id:#x}"),
+		}
+	}
+}
+
+impl IgnoreList {
+	/// Creates an empty ignore list.
+	#[inline]
+	pub const fn new() -> Self {
+		Self { matchers: Vec::new() }
+	}
+
+	/// Returns the ignore list's matchers, in the order they were added.
+	#[inline(always)]
+	pub fn matchers(&self) -> &[Matcher] {
+		&self.matchers
+	}
+
+	/// Adds the given `matcher` to the ignore list.
+	///
+	/// Returns [`false`] if it was already in the list.
+	pub fn add(&mut self, matcher: Matcher) -> bool {
+		if self.matchers.contains(&matcher) {
+			return false;
+		}
+
+		self.matchers.push(matcher);
+		true
+	}
+
+	/// Removes the given `matcher` from the ignore list.
+	///
+	/// Returns [`false`] if it wasn't in the list.
+	pub fn remove(&mut self, matcher: &Matcher) -> bool {
+		let len = self.matchers.len();
+		self.matchers.retain(|other| other != matcher);
+
+		self.matchers.len() != len
+	}
+
+	/// Returns whether any of the ignore list's matchers matches a window with the given ID and
+	/// `class`.
+	pub fn matches(&self, window: WindowId, class: Option<&str>) -> bool {
+		self.matchers.iter().any(|matcher| matcher.matches(window, class))
+	}
+
+	/// Reads the ignore list written in the given `dir`.
+	///
+	/// If there is no ignore list, or it can't be read, the list is empty.
+	pub fn read(dir: &Path) -> Self {
+		let path = dir.join(FILE_NAME);
+
+		let contents = match fs::read(&path) {
+			Ok(contents) => contents,
+
+			Err(error) if error.kind() == io::ErrorKind::NotFound => return Self::new(),
+			Err(error) => {
+				event!(Level::WARN, "Failed to read ignored windows {path:?}: {error}");
+
+				return Self::new();
+			},
+		};
+
+		match serde_json::from_slice(&contents) {
+			Ok(matchers) => Self { matchers },
+
+			Err(error) => {
+				event!(Level::WARN, "Ignoring invalid ignored windows {path:?}: {error}");
+
+				Self::new()
+			},
+		}
+	}
+
+	/// Atomically writes the ignore list to `ignored.json` in the given `dir`, logging any failure.
+	///
+	/// Returns whether the list was written.
+	pub fn write(&self, dir: &Path) -> bool {
+		match self.try_write(dir) {
+			Ok(()) => true,
+
+			Err(error) => {
+				event!(Level::WARN, "Failed to write ignored windows to {dir:?}: {error}");
+
+				false
+			},
+		}
+	}
+
+	fn try_write(&self, dir: &Path) -> io::Result<()> {
+		fs::create_dir_all(dir)?;
+
+		let temp = dir.join(format!("{FILE_NAME}.tmp"));
+		{
+			let mut file = fs::File::create(&temp)?;
+
+			file.write_all(&serde_json::to_vec(&self.matchers)?)?;
+			file.sync_all()?;
+		}
+
+		fs::rename(&temp, dir.join(FILE_NAME))
+	}
+}
+
+/// Returns whether the given `text` matches the `glob`, in which `*` matches any number of
+/// characters and `?` matches any one character.
+pub(crate) fn glob_matches(glob: &str, text: &str) -> bool {
+	let (glob, text): (Vec<_>, Vec<_>) = (glob.chars().collect(), text.chars().collect());
+	let (mut g, mut t) = (0, 0);
+	// The position of the last `*` in the glob, and of the text it was tried against.
+	let mut backtrack = None;
+
+	while t < text.len() {
+		match glob.get(g) {
+			Some('*') => {
+				backtrack = Some((g, t));
+				g += 1;
+			},
+			Some(&c) if c == '?' || c == text[t] => {
+				g += 1;
+				t += 1;
+			},
+
+			// Let the last `*` match one more character, if there was one.
+			_ => match backtrack {
+				Some((star, matched)) => {
+					backtrack = Some((star, matched + 1));
+
+					g = star + 1;
+					t = matched + 1;
+				},
+				None => return false,
+			},
+		}
+	}
+
+	glob[g..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, process};
+
+	use super::*;
+
+	#[test]
+	fn globs() {
+		assert!(glob_matches("flameshot", "flameshot"));
+		assert!(glob_matches("*shot", "flameshot"));
+		assert!(glob_matches("f*s*t", "flameshot"));
+		assert!(glob_matches("?lameshot", "flameshot"));
+		assert!(glob_matches("*", ""));
+
+		assert!(!glob_matches("flameshot", "Flameshot"));
+		assert!(!glob_matches("*shot?", "flameshot"));
+		assert!(!glob_matches("f*x", "flameshot"));
+	}
+
+	#[test]
+	fn parse() {
+		assert_eq!("0x1a00003".parse(), Ok(Matcher::Window(WindowId(0x1a00003))));
+		assert_eq!("27262979".parse(), Ok(Matcher::Window(WindowId(27262979))));
+		assert_eq!("onboard".parse(), Ok(Matcher::Class("onboard".to_owned())));
+		assert_eq!("class:1234".parse(), Ok(Matcher::Class("1234".to_owned())));
+		assert!("".parse::<Matcher>().is_err());
+
+		// Matchers are displayed as they can be parsed again.
+		for matcher in [Matcher::Window(WindowId(0x1a00003)), Matcher::Class("1234".to_owned())] {
+			assert_eq!(matcher.to_string().parse(), Ok(matcher));
+		}
+	}
+
+	#[test]
+	fn add_and_remove() {
+		let mut list = IgnoreList::new();
+
+		assert!(list.add(Matcher::Class("Xmag*".to_owned())));
+		assert!(list.add(Matcher::Window(WindowId(7))));
+		assert!(!list.add(Matcher::Window(WindowId(7))));
+
+		assert!(list.matches(WindowId(3), Some("Xmagnify")));
+		assert!(list.matches(WindowId(7), None));
+		assert!(!list.matches(WindowId(3), None));
+
+		assert!(list.remove(&Matcher::Window(WindowId(7))));
+		assert!(!list.remove(&Matcher::Window(WindowId(7))));
+		assert!(!list.matches(WindowId(7), None));
+	}
+
+	#[test]
+	fn persist() {
+		let dir = env::temp_dir().join(format!("aquariwm-ignore-test-{}", process::id()));
+
+		let mut list = IgnoreList::new();
+		list.add(Matcher::Class("flameshot".to_owned()));
+		list.add(Matcher::Window(WindowId(7)));
+
+		assert!(list.write(&dir));
+		assert_eq!(IgnoreList::read(&dir), list);
+
+		fs::remove_dir_all(&dir).unwrap();
+		assert_eq!(IgnoreList::read(&dir), IgnoreList::new());
+	}
+}
@@ -0,0 +1,220 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Tracking where programs were launched, so that their windows open there.
+//!
+//! Programs can take several seconds to open their first window after they are launched, by which
+//! time the user may have moved on to another output. When AquariWM launches a program, it
+//! [records] which output was active; when a window is mapped, its process (or any of that
+//! process's ancestors, for programs launched through a shell or which fork a child to open their
+//! windows) is [matched] against those records, and the window is opened on the recorded output.
+//!
+//! [records]: Launches::record
+//! [matched]: Launches::take
+
+use std::{
+	fs,
+	time::{Duration, Instant},
+};
+
+/// How long after a program is launched its windows may still be matched to its launch.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many ancestors of a window's process are checked for a launch.
+const MAX_ANCESTORS: usize = 16;
+
+/// A program which was launched by AquariWM.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Launch {
+	/// The process ID of the launched program.
+	pub pid: u32,
+	/// The name of the output which was active when the program was launched, if any.
+	pub output: Option<String>,
+
+	/// When the program was launched.
+	pub time: Instant,
+}
+
+/// Recent [launches] whose windows haven't been mapped yet.
+///
+/// [launches]: Launch
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Launches {
+	/// The recorded launches, oldest first.
+	launches: Vec<Launch>,
+
+	/// How long after a launch its windows may still be matched to it.
+	timeout: Duration,
+}
+
+impl Default for Launches {
+	#[inline]
+	fn default() -> Self {
+		Self::new(DEFAULT_TIMEOUT)
+	}
+}
+
+impl Launches {
+	/// Creates an empty set of launches which expire after the given `timeout`.
+	#[inline]
+	pub const fn new(timeout: Duration) -> Self {
+		Self {
+			launches: Vec::new(),
+
+			timeout,
+		}
+	}
+
+	/// Returns the recorded launches which haven't been matched, oldest first.
+	///
+	/// Launches are only forgotten once they have expired when another launch is [recorded] or
+	/// [matched], so this may include expired launches.
+	///
+	/// [recorded]: Self::record
+	/// [matched]: Self::take
+	#[inline]
+	pub fn launches(&self) -> &[Launch] {
+		&self.launches
+	}
+
+	/// Records that the program with the given `pid` was launched at the given time (`now`) while
+	/// the output of the given name was active.
+	pub fn record(&mut self, pid: u32, output: Option<String>, now: Instant) {
+		self.expire(now);
+
+		self.launches.push(Launch { pid, output, time: now });
+	}
+
+	/// Removes and returns the launch of the process with the given `pid` or its nearest ancestor
+	/// which was launched, if it hasn't expired by the given time (`now`).
+	///
+	/// `parent` returns the parent of a process, such as [`parent_pid`]; ancestors are checked up
+	/// to 16 generations back.
+	pub fn take(&mut self, pid: u32, now: Instant, parent: impl Fn(u32) -> Option<u32>) -> Option<Launch> {
+		self.expire(now);
+
+		if self.launches.is_empty() {
+			return None;
+		}
+
+		let mut pid = Some(pid);
+
+		for _ in 0..=MAX_ANCESTORS {
+			// Process 0 isn't a real process, and everything descends from process 1.
+			let current = pid.filter(|&pid| pid > 1)?;
+
+			if let Some(index) = self.launches.iter().position(|launch| launch.pid == current) {
+				return Some(self.launches.remove(index));
+			}
+
+			pid = parent(current);
+		}
+
+		None
+	}
+
+	/// Forgets launches which have expired by the given time (`now`).
+	fn expire(&mut self, now: Instant) {
+		let timeout = self.timeout;
+
+		self.launches
+			.retain(|launch| now.saturating_duration_since(launch.time) <= timeout);
+	}
+}
+
+/// Returns the parent of the process with the given `pid`, as listed in `/proc`.
+///
+/// Returns [`None`] if the process doesn't exist (anymore), or if `/proc` isn't available.
+pub fn parent_pid(pid: u32) -> Option<u32> {
+	let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+
+	parse_parent_pid(&stat)
+}
+
+/// Parses the parent process ID from the contents of a `/proc/<pid>/stat` file.
+///
+/// The file starts with the process ID, its name in parentheses, its state, then its parent's
+/// process ID. The name may itself contain spaces and parentheses, so the fields after it are
+/// found from the last closing parenthesis.
+fn parse_parent_pid(stat: &str) -> Option<u32> {
+	let (_, fields) = stat.rsplit_once(')')?;
+	let mut fields = fields.split_whitespace();
+
+	// Skip the state.
+	fields.next()?;
+
+	fields.next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A process tree: a shell (100) launched by AquariWM, which started a program (200) which
+	/// forked a child (300) to open its windows.
+	fn parent(pid: u32) -> Option<u32> {
+		match pid {
+			300 => Some(200),
+			200 => Some(100),
+			100 => Some(1),
+			500 => Some(1),
+
+			_ => None,
+		}
+	}
+
+	#[test]
+	fn matching() {
+		let start = Instant::now();
+		let mut launches = Launches::default();
+
+		launches.record(100, Some("DP-1".to_owned()), start);
+		launches.record(400, Some("HDMI-1".to_owned()), start);
+
+		// Unrelated processes don't match.
+		assert_eq!(launches.take(500, start, parent), None);
+
+		// Descendents of the launched process match it, consuming the launch.
+		let launch = launches.take(300, start + Duration::from_secs(2), parent).unwrap();
+		assert_eq!((launch.pid, launch.output.as_deref()), (100, Some("DP-1")));
+		assert_eq!(launches.take(300, start, parent), None);
+
+		// The launched process itself matches.
+		assert_eq!(launches.take(400, start, parent).map(|launch| launch.pid), Some(400));
+		assert!(launches.launches().is_empty());
+	}
+
+	#[test]
+	fn expiry() {
+		let start = Instant::now();
+		let mut launches = Launches::new(Duration::from_secs(10));
+
+		launches.record(100, None, start);
+		launches.record(200, None, start + Duration::from_secs(5));
+
+		assert_eq!(launches.take(100, start + Duration::from_secs(11), |_| None), None);
+		assert_eq!(
+			launches
+				.take(200, start + Duration::from_secs(15), |_| None)
+				.map(|launch| launch.pid),
+			Some(200)
+		);
+
+		// Expired launches are forgotten when new launches are recorded.
+		launches.record(300, None, start);
+		launches.record(400, None, start + Duration::from_secs(30));
+		assert_eq!(launches.launches().len(), 1);
+	}
+
+	#[test]
+	fn stat() {
+		assert_eq!(parse_parent_pid("300 (bash) S 200 300 300 0 -1"), Some(200));
+		// Process names can contain spaces and parentheses.
+		assert_eq!(parse_parent_pid("300 (Web Content (1)) R 42 300 300 0"), Some(42));
+
+		assert_eq!(parse_parent_pid("300 (bash"), None);
+		assert_eq!(parse_parent_pid("300 (bash) S"), None);
+		assert_eq!(parse_parent_pid(""), None);
+	}
+}
@@ -2,9 +2,24 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::{collections::VecDeque, fmt::Debug};
+use std::{
+	any,
+	collections::VecDeque,
+	fmt::Debug,
+	hash::{Hash, Hasher},
+	sync::atomic::{AtomicU64, Ordering},
+};
 
 use derive_extras::builder;
+use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+/// A conformance test suite for [layout managers], which checks them against randomized sequences
+/// of operations.
+///
+/// [layout managers]: TilingLayoutManager
+#[cfg(any(test, feature = "testing"))]
+pub mod conformance;
 
 /// Contains `impl` blocks for types defined in [layout].
 ///
@@ -13,17 +28,44 @@ use derive_extras::builder;
 /// [layout]: self
 mod implementations;
 
+/// Contains [`CurrentLayout::isolate`], which isolates AquariWM from [layout managers] panicking,
+/// and the [hot-swapping] of layout managers it falls back with.
+///
+/// [layout managers]: TilingLayoutManager
+/// [hot-swapping]: CurrentLayout::swap_manager
+mod isolation;
+
+/// Contains [`CurrentLayout::predict_addition`], which predicts where a window would be added to
+/// the layout without changing it.
+mod prediction;
+
 /// Default [layout managers] that come with AquariWM.
 ///
 /// [layout managers]: TilingLayoutManager
 pub mod managers;
 
+/// Contains [`SharedLayout`], an internally synchronized handle to a [tiling layout].
+///
+/// Its changes are applied to the layout in place before windows are reconfigured, rather than
+/// being committed afterwards with a generation check; see [`SharedLayout`] for why.
+///
+/// [tiling layout]: TilingLayout
+mod shared;
+
+pub use isolation::ManagerPanic;
+pub use prediction::Slot;
+pub use shared::SharedLayout;
+
 // This is a false positive: `derive_extras::Default` is not the same as `Default`.
 #[allow(unused_qualifications)]
 /// Controls settings used when [applying] a [tiling layout].
 ///
+/// Sizes are specified in logical pixels; they are converted to device pixels for a particular
+/// output with [`scaled`].
+///
 /// [applying]: GroupNode::apply_changes
 /// [tiling layout]: TilingLayout
+/// [`scaled`]: LayoutSettings::scaled
 #[derive(Debug, PartialEq, Eq, Hash, Clone, derive_extras::Default, builder)]
 #[new]
 pub struct LayoutSettings {
@@ -33,13 +75,290 @@ pub struct LayoutSettings {
 	/// [tiling layout]: TilingLayout
 	#[default = 15]
 	pub window_gap: u32,
+
+	/// Where new windows are inserted into the [tiling layout].
+	///
+	/// [tiling layout]: TilingLayout
+	pub insertion_strategy: InsertionStrategy,
+
+	/// What happens when a window which is already in the [tiling layout] is added to it again.
+	///
+	/// [tiling layout]: TilingLayout
+	pub duplicate_windows: DuplicateWindows,
+
+	/// The maximum depth at which [groups] may be nested in the [tiling layout].
+	///
+	/// The root group is at depth `0`, and each group nested within it is one deeper than its
+	/// parent. What happens to groups which would be nested any deeper is controlled by the
+	/// [depth policy].
+	///
+	/// Groups can always be added to the root group, so a maximum depth of `0` is treated as `1`.
+	///
+	/// [groups]: GroupNode
+	/// [tiling layout]: TilingLayout
+	/// [depth policy]: Self::depth_policy
+	#[default = 64]
+	pub max_depth: usize,
+
+	/// What happens to [groups] which would be nested deeper than the [maximum depth].
+	///
+	/// [groups]: GroupNode
+	/// [maximum depth]: Self::max_depth
+	pub depth_policy: DepthPolicy,
+
+	/// Which window is focused after the focused window is [swapped with the primary window].
+	///
+	/// [swapped with the primary window]: TilingLayoutManager::swap_with_primary
+	pub swap_focus: SwapFocus,
+
+	/// Which neighbor of a group's [active] child becomes active when the active child is
+	/// removed, e.g. when a tab of a [stacked] group is closed.
+	///
+	/// [active]: GroupNode::active
+	/// [stacked]: GroupNode::is_stacked
+	pub active_on_removal: TabNeighbor,
+
+	/// The order in which windows are read on screen, which is the order they are [cycled through]
+	/// by their positions.
+	///
+	/// [cycled through]: crate::action::Action::FocusNext
+	pub reading_order: ReadingOrder,
+
+	/// Whether windows are sized to their [resize increments], e.g. so that terminals are a whole
+	/// number of cells, rather than filling their tiles exactly.
+	///
+	/// [resize increments]: Increments
+	pub honor_increments: bool,
+
+	/// What is done with the pixels left over when windows are sized to their
+	/// [resize increments].
+	///
+	/// [resize increments]: Self::honor_increments
+	pub leftover_pixels: LeftoverPixels,
+
+	/// Whether a lone tiled window is given the whole of the [tiling layout]'s area, without the
+	/// [window gap] around it.
+	///
+	/// [tiling layout]: TilingLayout
+	/// [window gap]: Self::window_gap
+	pub smart_gaps: bool,
+
+	/// The size, along their group's axis, of the strip each sibling of a [maximized] node is
+	/// shrunk to.
+	///
+	/// [maximized]: GroupNode::maximized
+	#[default = 40]
+	pub maximize_strip: u32,
+}
+
+/// Where new windows are inserted into the [tiling layout].
+///
+/// [tiling layout]: TilingLayout
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum InsertionStrategy {
+	/// New windows are placed by the [layout manager].
+	///
+	/// [layout manager]: TilingLayoutManager
+	#[default]
+	Manager,
+
+	/// New windows join the first group containing a window of the same class.
+	///
+	/// If there is no such group, the first window of the same class has its tile split into a
+	/// [dissolving] group containing both windows. New windows with no other window of the same
+	/// class are placed by the [layout manager].
+	///
+	/// [dissolving]: GroupNode::is_dissolving
+	/// [layout manager]: TilingLayoutManager
+	GroupByClass,
+}
+
+/// What happens when a window which is already in the [tiling layout] is added to it again, e.g.
+/// because a map request was replayed after an unmap was missed.
+///
+/// A window is never tiled twice, as its two tiles would fight over its geometry.
+///
+/// [tiling layout]: TilingLayout
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum DuplicateWindows {
+	/// The window is left where it is, and [`AddWindowError::AlreadyPresent`] is returned.
+	#[default]
+	Reject,
+	/// The window's existing node is removed, and the window is added again as if it were new.
+	Relocate,
+}
+
+/// What happens to [groups] which would be nested deeper than the [maximum depth] of the
+/// [tiling layout].
+///
+/// [groups]: GroupNode
+/// [maximum depth]: LayoutSettings::max_depth
+/// [tiling layout]: TilingLayout
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum DepthPolicy {
+	/// The group's children are added to its would-be parent in its place.
+	#[default]
+	Flatten,
+	/// The group is not added, and a [`DepthError`] is returned.
+	Reject,
+}
+
+/// Which window is focused after the focused window is [swapped with the primary window].
+///
+/// [swapped with the primary window]: TilingLayoutManager::swap_with_primary
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum SwapFocus {
+	/// The focused window stays focused, following it to its new position.
+	#[default]
+	Follow,
+	/// Focus stays where the focused window was, moving to the window swapped into its place.
+	Stay,
+}
+
+/// Which neighbor of a [group]'s [active] child becomes active when the active child is removed.
+///
+/// If the removed child has no neighbor on the preferred side, its neighbor on the other side
+/// becomes active instead.
+///
+/// [group]: GroupNode
+/// [active]: GroupNode::active
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum TabNeighbor {
+	/// The child after the removed child, i.e. the tab to its right.
+	#[default]
+	Right,
+	/// The child before the removed child, i.e. the tab to its left.
+	Left,
+}
+
+/// An error returned when parsing a [`TabNeighbor`] fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error("expected `right` or `left`, found {0:?}")]
+pub struct ParseTabNeighborError(String);
+
+/// The resize increments of a window, e.g. a terminal's cell size: beyond its base size, the window
+/// is only sized in whole steps.
+///
+/// Increments are only honored if [`LayoutSettings::honor_increments`] is set. A step of `0` or `1`
+/// doesn't constrain the window.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Increments {
+	/// The width of each step.
+	pub width: u32,
+	/// The height of each step.
+	pub height: u32,
+
+	/// The width from which steps are counted.
+	pub base_width: u32,
+	/// The height from which steps are counted.
+	pub base_height: u32,
+}
+
+/// What is done with the pixels left over in a [group] when its windows are sized to their
+/// [resize increments].
+///
+/// Pixels left over along the group's [secondary dimension] are always left as a margin after the
+/// window.
+///
+/// [group]: GroupNode
+/// [resize increments]: Increments
+/// [secondary dimension]: Node::secondary_dimension
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum LeftoverPixels {
+	/// The pixels are given to the group's last child which isn't constrained by resize increments
+	/// along the group's axis, or are left as a margin if every child is constrained.
+	#[default]
+	LastUnconstrained,
+	/// The pixels are shared out between the gaps between the group's children, or are left as a
+	/// margin if there is only one child.
+	Gaps,
+	/// The pixels are left as a margin after the group's last child.
+	Margin,
+}
+
+/// An error returned when parsing a [`LeftoverPixels`] policy fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error("expected `last-unconstrained`, `gaps`, or `margin`, found {0:?}")]
+pub struct ParseLeftoverPixelsError(String);
+
+/// An error returned when a [group] would be nested deeper than the [maximum depth] of the
+/// [tiling layout] and [nesting too deeply is rejected].
+///
+/// [group]: GroupNode
+/// [maximum depth]: LayoutSettings::max_depth
+/// [tiling layout]: TilingLayout
+/// [nesting too deeply is rejected]: DepthPolicy::Reject
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Error)]
+#[error("a group would be nested at depth {depth}, deeper than the maximum depth of {max_depth}")]
+pub struct DepthError {
+	/// The depth the deepest group would have been nested at.
+	pub depth: usize,
+	/// The [maximum depth] of the tiling layout.
+	///
+	/// [maximum depth]: LayoutSettings::max_depth
+	pub max_depth: usize,
+}
+
+/// A broken invariant of a [tiling layout]'s tree, found by [`TilingLayout::validate`].
+///
+/// [tiling layout]: TilingLayout
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Error)]
+pub enum TreeInvariantError {
+	/// A [group] is nested deeper than the [maximum depth].
+	///
+	/// [group]: GroupNode
+	/// [maximum depth]: LayoutSettings::max_depth
+	#[error("group {group:?} is nested at depth {depth}, deeper than the maximum depth of {max_depth}")]
+	TooDeep {
+		group: NodeId,
+		depth: usize,
+		max_depth: usize,
+	},
+
+	/// A [node] appears more than once in the tree, so a [group] may be its own ancestor.
+	///
+	/// [node]: Node
+	/// [group]: GroupNode
+	#[error("node {0:?} appears more than once in the tree")]
+	Repeated(NodeId),
+
+	/// The sizes of a [group]'s children along its [axis] don't add up to the total it has
+	/// recorded for them.
+	///
+	/// [group]: GroupNode
+	/// [axis]: Orientation::axis
+	#[error("the children of group {group:?} add up to {actual} pixels, rather than {expected}")]
+	DimensionMismatch { group: NodeId, expected: u32, actual: u32 },
+
+	/// The number of windows or groups in the tree, or how deeply groups are nested in it, doesn't
+	/// match the count kept as the tree is changed.
+	///
+	/// This is only checked in debug builds.
+	#[error("the tree has {actual} {what}, but {counted} were counted as it was changed")]
+	Miscounted {
+		what: &'static str,
+		counted: usize,
+		actual: usize,
+	},
+}
+
+/// An error returned when adding a window to the [tiling layout] fails.
+///
+/// [tiling layout]: TilingLayout
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Error)]
+pub enum AddWindowError {
+	/// The window is already in the tiling layout, and [duplicate windows] are rejected.
+	///
+	/// [duplicate windows]: DuplicateWindows
+	#[error("the window is already in the tiling layout")]
+	AlreadyPresent,
 }
 
 /// Whether a window is [`Tiled`] or [`Floating`].
 ///
 /// [`Tiled`]: Mode::Tiled
 /// [`Floating`]: Mode::Floating
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize, Deserialize)]
 pub enum Mode {
 	/// When a tiling layout is active, the window is tiled.
 	///
@@ -52,6 +371,74 @@ pub enum Mode {
 	Floating,
 }
 
+/// Whether a [tiled] window can currently be seen.
+///
+/// Hidden windows do not need to be shown (or even reconfigured) by the display server until they
+/// become visible again.
+///
+/// [tiled]: Mode::Tiled
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum Visibility {
+	/// The window can be seen.
+	#[default]
+	Visible,
+
+	/// The window cannot be seen.
+	///
+	/// A window is hidden if it is (or is in) a non-[active] child of a [stacked] group, or if it
+	/// lies entirely outside of the [tiling layout].
+	///
+	/// [active]: GroupNode::active
+	/// [stacked]: GroupNode::is_stacked
+	/// [tiling layout]: TilingLayout
+	Hidden,
+}
+
+/// The outcome of reconfiguring a single window while [applying] changes to a [tiling layout].
+///
+/// `()` converts to [`Applied`], so functions which reconfigure windows can return `Ok(())` if
+/// they never skip a window.
+///
+/// [applying]: GroupNode::apply_changes
+/// [tiling layout]: TilingLayout
+/// [`Applied`]: Reconfigured::Applied
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum Reconfigured {
+	/// The window was reconfigured.
+	#[default]
+	Applied,
+	/// The window was reconfigured, but the display server adjusted the coordinates and dimensions
+	/// it was given, e.g. to honor its minimum size, so it has these instead.
+	Adjusted { x: i32, y: i32, width: u32, height: u32 },
+
+	/// The window could not be reconfigured because it no longer exists, so it should be removed
+	/// from the layout.
+	///
+	/// The rest of the layout is still applied.
+	SkipWindow,
+}
+
+/// A visible window which was reconfigured when changes to a [tiling layout] were [applied], as
+/// [reported] to the layout manager.
+///
+/// [tiling layout]: TilingLayout
+/// [applied]: GroupNode::apply_changes
+/// [reported]: TilingLayoutManager::changes_applied
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct AppliedChange<Window> {
+	pub window: Window,
+	/// The coordinates and dimensions the layout gave the window, as `(x, y, width, height)`.
+	pub requested: (i32, i32, u32, u32),
+	/// The coordinates and dimensions the window really has, as `(x, y, width, height)`.
+	///
+	/// These are the same as those `requested` unless the display server [adjusted] them, or
+	/// [`None`] if the window was [skipped] because it no longer exists.
+	///
+	/// [adjusted]: Reconfigured::Adjusted
+	/// [skipped]: Reconfigured::SkipWindow
+	pub actual: Option<(i32, i32, u32, u32)>,
+}
+
 /// AquariWM's current window layout manager.
 #[derive(Default)]
 pub enum CurrentLayout<Window> {
@@ -69,6 +456,7 @@ pub enum CurrentLayout<Window> {
 ///
 /// [tiled]: Mode::Tiled
 /// [layout manager]: TilingLayoutManager
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TilingLayout<Window> {
 	root: GroupNode<Window>,
 
@@ -77,9 +465,69 @@ pub struct TilingLayout<Window> {
 
 	width: u32,
 	height: u32,
+
+	/// Whether the latest [`apply_changes`] call was deferred because the layout's dimensions were
+	/// too small to fit its nodes.
+	///
+	/// [`apply_changes`]: TilingLayout::apply_changes
+	deferred: bool,
+	/// The percentage of the root group which was [covered] the last time changes were applied, if
+	/// they have been.
+	///
+	/// [covered]: LayoutStats::coverage
+	#[serde(skip)]
+	coverage: Option<f64>,
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+/// Statistics about a [tiling layout], as returned by [`TilingLayout::stats`].
+///
+/// [tiling layout]: TilingLayout
+#[derive(Debug, PartialEq, Clone)]
+pub struct LayoutStats {
+	/// The number of windows in the layout.
+	pub windows: usize,
+	/// The number of groups in the layout, not including the root group.
+	pub groups: usize,
+	/// The [depth] of the most deeply nested group, or `0` if there are no groups.
+	///
+	/// [depth]: GroupNode::depth
+	pub max_depth: usize,
+	/// The number of windows within each of the root group's children, in order.
+	pub root_windows: Vec<usize>,
+
+	/// The number of groups with changes which have not been [applied] yet.
+	///
+	/// [applied]: TilingLayout::apply_changes
+	pub pending_changes: usize,
+	/// The percentage of the root group's size along its [axis] which its children and the gaps
+	/// between them filled the last time changes were [applied], if they have been.
+	///
+	/// This should always be `100` for a non-empty layout: anything else means that sizes have
+	/// drifted while being rescaled.
+	///
+	/// [axis]: Orientation::axis
+	/// [applied]: TilingLayout::apply_changes
+	pub coverage: Option<f64>,
+}
+
+/// The work done by [compacting] a [tiling layout].
+///
+/// [compacting]: TilingLayout::compact
+/// [tiling layout]: TilingLayout
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Compaction {
+	/// The number of empty groups and chains of groups which were [pruned].
+	///
+	/// [pruned]: GroupNode::prune_empty
+	pub pruned: usize,
+	/// The number of nodes whose sizes had drifted, and which will be reconfigured the next time
+	/// changes are [applied].
+	///
+	/// [applied]: TilingLayout::apply_changes
+	pub resized: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Orientation {
 	/// [Nodes] are ordered [horizontally] from left to right.
 	///
@@ -114,12 +562,59 @@ pub enum Orientation {
 	BottomToTop,
 }
 
+/// An error returned when parsing an [`Orientation`] fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error("expected `left-to-right`, `top-to-bottom`, `right-to-left`, or `bottom-to-top`, found {0:?}")]
+pub struct ParseOrientationError(String);
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Axis {
 	Horizontal,
 	Vertical,
 }
 
+/// Which side of a node another node is [moved beside] it on.
+///
+/// [moved beside]: GroupNode::move_beside_by_id
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Side {
+	/// Before the node: to its left, or above it.
+	Before,
+	/// After the node: to its right, or below it.
+	After,
+}
+
+/// The order in which windows are read on screen, used to [order them visually].
+///
+/// [order them visually]: TilingLayout::visual_order_by
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum ReadingOrder {
+	/// Windows are read in rows: top-to-bottom, then left-to-right.
+	#[default]
+	Rows,
+	/// Windows are read in columns: left-to-right, then top-to-bottom.
+	Columns,
+}
+
+/// An error returned when parsing a [`ReadingOrder`] fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error("expected `rows` or `columns`, found {0:?}")]
+pub struct ParseReadingOrderError(String);
+
+/// An amount by which to [resize] a window's node along an [axis].
+///
+/// [resize]: TilingLayout::resize_window
+/// [axis]: Axis
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Amount {
+	/// A number of pixels.
+	Pixels(i32),
+	/// A percentage of the [primary dimension] of the group in which the node is resized.
+	///
+	/// [primary dimension]: Node::primary_dimension
+	Percent(i32),
+}
+
 /// Represents a node in a [layout] tree.
 ///
 /// This can either be a [group] or a [window].
@@ -128,18 +623,266 @@ pub enum Axis {
 ///
 /// [group]: GroupNode
 /// [window]: Window
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+// Boxing groups would add an allocation and an indirection to every group in the tree, just to
+// save space in window nodes.
+#[allow(clippy::large_enum_variant)]
 pub enum Node<Window> {
 	Group(GroupNode<Window>),
 	Window(WindowNode<Window>),
 }
 
+/// How a [node] is sized along its group's [axis].
+///
+/// [node]: Node
+/// [axis]: Orientation::axis
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum Sizing {
+	/// The node shares the space left over by [fixed] nodes with its other proportional siblings.
+	///
+	/// Proportional nodes keep their sizes relative to one another when their group is resized.
+	/// The weight is the node's share of the space whenever that space is divided afresh: when the
+	/// node is added, or when its group is [equalized]. A weight of `0` is treated as `1`.
+	///
+	/// [fixed]: Sizing::Fixed
+	/// [equalized]: GroupNode::equalize
+	Proportional(u32),
+	/// The node is always exactly the given number of pixels, or whatever is left of its group if
+	/// that is less.
+	Fixed(u32),
+}
+
+/// How [proportional] nodes added to a group are sized the next time changes are applied.
+///
+/// Fractions are clamped between `0.0` and `1.0`.
+///
+/// [proportional]: Sizing::Proportional
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum AdditionSizing {
+	/// Each added node is given a share of the group according to its [weight], as if the
+	/// group's proportional nodes were divided afresh.
+	///
+	/// [weight]: Sizing::Proportional
+	EqualShare,
+	/// Each added node is given the given fraction of the space shared by the group's proportional
+	/// nodes.
+	FractionOfGroup(f32),
+	/// Each added node takes the given fraction of the node it [splits], which keeps the rest.
+	///
+	/// Added nodes which don't split a node, or which split a node that was itself just added or
+	/// is [fixed], are given an [equal share] instead.
+	///
+	/// [splits]: GroupNode::insert_window_splitting
+	/// [fixed]: Sizing::Fixed
+	/// [equal share]: AdditionSizing::EqualShare
+	FractionOfSplitNode(f32),
+}
+
+/// A node added to a group since changes were last applied to it.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+struct Addition {
+	/// The index of the added node.
+	index: usize,
+	/// The index of the existing node which the added node splits, if any.
+	splits: Option<usize>,
+}
+
+/// Whether one of a group's children is [maximized] within it.
+///
+/// [maximized]: GroupNode::maximized
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default, Serialize, Deserialize)]
+enum Maximize {
+	/// None of the group's children are maximized.
+	#[default]
+	None,
+	/// The child with the given ID takes up the whole group, but for a strip of the given size for
+	/// each of its siblings.
+	Maximized {
+		child: NodeId,
+		strip: u32,
+		/// The sizes of the group's children from before any of them was maximized, by ID.
+		restore: Vec<(NodeId, u32)>,
+	},
+	/// The given sizes of the group's children from before one of them was maximized are to be
+	/// restored the next time changes are applied.
+	Restoring(Vec<(NodeId, u32)>),
+}
+
+/// A stable identifier for a [node] in a [layout] tree.
+///
+/// Unlike a node's index within its group, a node's ID does not change when other nodes are
+/// inserted, removed, or moved around it, nor when the node itself is moved to another group. IDs
+/// are never reused: every node created during AquariWM's lifetime is given a unique ID (though a
+/// [cloned] node shares its ID with the original).
+///
+/// IDs are preserved when a layout is serialized. Once a layout is deserialized, new nodes are
+/// given IDs higher than any in it.
+///
+/// [node]: Node
+/// [layout]: TilingLayout
+/// [cloned]: Clone
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NodeId(u64);
+
+/// The next [`NodeId`] to be given to a new [node].
+///
+/// [node]: Node
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The [`NodeId`] of a [node], which is ignored when comparing or hashing the node.
+///
+/// Nodes are compared structurally: two nodes with the same contents are equal, even if they have
+/// different IDs.
+///
+/// [node]: Node
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(transparent)]
+struct Identity(NodeId);
+
+impl Identity {
+	/// Returns a new, never before used, identity.
+	fn next() -> Self {
+		Self(NodeId(NEXT_ID.fetch_add(1, Ordering::Relaxed)))
+	}
+}
+
+impl<'de> Deserialize<'de> for Identity {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let id = NodeId::deserialize(deserializer)?;
+		// New nodes mustn't be given the IDs of deserialized ones.
+		NEXT_ID.fetch_max(id.0.saturating_add(1), Ordering::Relaxed);
+
+		Ok(Self(id))
+	}
+}
+
+impl PartialEq for Identity {
+	#[inline(always)]
+	fn eq(&self, _other: &Self) -> bool {
+		true
+	}
+}
+
+impl Eq for Identity {}
+
+impl Hash for Identity {
+	#[inline(always)]
+	fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+/// A [group]'s depth in its [layout] tree, and the settings of that tree which are needed as the
+/// group is changed: its [maximum depth], and which child becomes [active on removal].
+///
+/// This is equal for every group, like [`Identity`], so that groups which are otherwise equal
+/// compare equal wherever they are nested.
+///
+/// [group]: GroupNode
+/// [layout]: TilingLayout
+/// [maximum depth]: LayoutSettings::max_depth
+/// [active on removal]: LayoutSettings::active_on_removal
+#[derive(Debug, Clone, Copy)]
+struct Nesting {
+	depth: usize,
+
+	max_depth: usize,
+	policy: DepthPolicy,
+
+	active_on_removal: TabNeighbor,
+}
+
+impl Default for Nesting {
+	#[inline]
+	fn default() -> Self {
+		let settings = LayoutSettings::default();
+
+		Self {
+			depth: 0,
+
+			max_depth: settings.max_depth,
+			policy: settings.depth_policy,
+
+			active_on_removal: settings.active_on_removal,
+		}
+	}
+}
+
+impl PartialEq for Nesting {
+	#[inline(always)]
+	fn eq(&self, _other: &Self) -> bool {
+		true
+	}
+}
+
+impl Eq for Nesting {}
+
+impl Hash for Nesting {
+	#[inline(always)]
+	fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+/// The number of windows and groups within a [group], and how many levels of groups are nested
+/// within it, counted as its children are changed.
+///
+/// Children which are borrowed mutably may be changed without the group knowing, so the tally is
+/// then marked `stale` and is counted again from the tallies of the group's children when it is
+/// next needed.
+///
+/// This is equal for every group, like [`Identity`].
+///
+/// [group]: GroupNode
+#[derive(Debug, Clone, Copy)]
+struct Tally {
+	windows: usize,
+	groups: usize,
+	/// The number of levels of groups nested within the group: `0` if it contains no groups.
+	height: usize,
+
+	stale: bool,
+}
+
+impl PartialEq for Tally {
+	#[inline(always)]
+	fn eq(&self, _other: &Self) -> bool {
+		true
+	}
+}
+
+impl Eq for Tally {}
+
+impl Hash for Tally {
+	#[inline(always)]
+	fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
 /// Represents a group of [nodes] in a [layout] tree.
 ///
+/// # Nesting groups too deeply
+/// Groups can't be nested deeper than the [maximum depth] of the layout. A group which would be
+/// nested any deeper is [flattened]: its children are added in its place. If that is [rejected]
+/// instead, nothing is added and a [`DepthError`] is returned.
+///
 /// [nodes]: Node
 /// [layout]: TilingLayout
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+/// [maximum depth]: LayoutSettings::max_depth
+/// [flattened]: DepthPolicy::Flatten
+/// [rejected]: DepthPolicy::Reject
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub struct GroupNode<Window> {
+	// Layouts serialized before IDs were preserved are given new IDs.
+	#[serde(default = "Identity::next")]
+	id: Identity,
+	/// The group's depth in the tree, and the maximum depth of the tree.
+	///
+	/// This is recalculated when changes are applied, so it is not preserved.
+	#[serde(skip)]
+	nesting: Nesting,
+	/// The number of windows and groups within the group.
+	///
+	/// This is counted again once a layout is deserialized.
+	#[serde(skip, default = "Tally::stale")]
+	tally: Tally,
+
 	orientation: Orientation,
 
 	children: VecDeque<Node<Window>>,
@@ -149,7 +892,7 @@ pub struct GroupNode<Window> {
 	/// Additions to `nodes` made by the [layout manager] in the latest [`add_window`] or
 	/// [`remove_window`] call.
 	///
-	/// This is a sorted list of indexes.
+	/// This is sorted by index.
 	///
 	/// Additions are tracked so that nodes can be resized afterwards. This prevents multiple
 	/// resizings per node, which is particularly important when it comes to the resized windows.
@@ -158,8 +901,13 @@ pub struct GroupNode<Window> {
 	///
 	/// [`add_window`]: TilingLayoutManager::add_window
 	/// [`remove_window`]: TilingLayoutManager::remove_window
-	additions: VecDeque<usize>,
+	additions: VecDeque<Addition>,
 	total_removed_primary: u32,
+	/// How the group's additions are sized, overriding the [addition sizing] of its parent group.
+	///
+	/// [addition sizing]: Self::addition_sizing()
+	#[serde(default)]
+	addition_sizing: Option<AdditionSizing>,
 
 	/// The new [`orientation`] for the group set by the [layout manager] in the latest
 	/// [`add_window`] or [`remove_window`] call.
@@ -171,6 +919,86 @@ pub struct GroupNode<Window> {
 	/// [`remove_window`]: TilingLayoutManager::remove_window
 	new_orientation: Option<Orientation>,
 
+	/// The [window gap] override for the group, if any.
+	///
+	/// If this is [`None`], the group inherits the effective window gap of its parent group or,
+	/// for the root group, the layout-wide [window gap].
+	///
+	/// [window gap]: LayoutSettings::window_gap
+	gap: Option<u32>,
+	/// The new [`gap`] override for the group set since changes were last applied.
+	///
+	/// [`gap`]: Self::gap()
+	new_gap: Option<Option<u32>>,
+	/// The effective window gap that was used the last time changes were applied to the group.
+	///
+	/// This is tracked so that the group is laid out again if the gap it inherits changes.
+	applied_gap: u32,
+
+	/// Whether the group is [stacked].
+	///
+	/// [stacked]: Self::is_stacked()
+	stacked: bool,
+	/// Whether the group is to be [stacked] once changes are next applied.
+	///
+	/// [stacked]: Self::is_stacked()
+	new_stacked: Option<bool>,
+	/// The [active] child of the group.
+	///
+	/// [active]: Self::active()
+	#[serde(default)]
+	active: Option<NodeId>,
+	/// The new [active] child of the group set since changes were last applied.
+	///
+	/// [active]: Self::active()
+	#[serde(default)]
+	new_active: Option<NodeId>,
+
+	/// Whether the group is [dissolving].
+	///
+	/// [dissolving]: Self::is_dissolving()
+	#[serde(default)]
+	dissolving: bool,
+	/// Whether the group is [kept alive] while it is empty.
+	///
+	/// [kept alive]: Self::is_kept_alive()
+	#[serde(default)]
+	keep_alive: bool,
+	/// Whether the group is [manual].
+	///
+	/// [manual]: Self::is_manual()
+	#[serde(default)]
+	manual: bool,
+	/// The group's [maximized] child, if any, and the sizes to restore its children to.
+	///
+	/// [maximized]: Self::maximized()
+	#[serde(default)]
+	maximize: Maximize,
+
+	/// The group's [sizing] within its parent.
+	///
+	/// [sizing]: Sizing
+	#[serde(default)]
+	sizing: Sizing,
+	/// The new [sizing] for the group set since changes were last applied to its parent.
+	///
+	/// [sizing]: Sizing
+	#[serde(default)]
+	new_sizing: Option<Sizing>,
+	/// The number of pixels by which the group's tile in its parent is bigger than the group,
+	/// because of its siblings' [resize increments].
+	///
+	/// [resize increments]: Increments
+	#[serde(skip)]
+	slack: i32,
+	/// Whether the group's [proportional] children are to be [equalized] once changes are next
+	/// applied.
+	///
+	/// [proportional]: Sizing::Proportional
+	/// [equalized]: Self::equalize
+	#[serde(default)]
+	equalize: bool,
+
 	new_width: Option<u32>,
 	new_height: Option<u32>,
 
@@ -187,14 +1015,62 @@ pub struct GroupNode<Window> {
 /// Represents a [node] containing a window.
 ///
 /// [node]: Node
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub struct WindowNode<Window> {
+	// Layouts serialized before IDs were preserved are given new IDs.
+	#[serde(default = "Identity::next")]
+	id: Identity,
+
 	window: Window,
 	/// Whether the `window` was changed in the latest [`add_window`] or [`remove_window`] call.
 	///
 	/// [`add_window`]: TilingLayoutManager::add_window
 	/// [`remove_window`]: TilingLayoutManager::remove_window
 	window_changed: bool,
+	/// The [visibility] of the window the last time changes were applied.
+	///
+	/// [visibility]: Visibility
+	// The display server's visibility state is not preserved when a layout is deserialized.
+	#[serde(skip)]
+	visibility: Visibility,
+
+	/// The window node's [sizing] within its group.
+	///
+	/// [sizing]: Sizing
+	#[serde(default)]
+	sizing: Sizing,
+	/// The new [sizing] for the window node set since changes were last applied to its group.
+	///
+	/// [sizing]: Sizing
+	#[serde(default)]
+	new_sizing: Option<Sizing>,
+
+	/// The window's [resize increments], if it has any.
+	///
+	/// [resize increments]: Increments
+	// Resize increments are read from the window again when it is managed.
+	#[serde(skip)]
+	increments: Option<Increments>,
+	/// Whether the window's [resize increments] have changed since changes were last applied to its
+	/// group.
+	///
+	/// [resize increments]: Increments
+	#[serde(skip)]
+	increments_changed: bool,
+	/// The number of pixels by which the window's tile in its group is bigger than the window,
+	/// because of [resize increments].
+	///
+	/// [resize increments]: Increments
+	#[serde(skip)]
+	slack: i32,
+
+	/// The window's minimum size from its size hints, if it has one.
+	// Size hints are read from the window again when it is managed.
+	#[serde(skip)]
+	min_size: Option<(u32, u32)>,
+	/// The width of the window's border, which is drawn around its tile.
+	#[serde(skip)]
+	border_width: u32,
 
 	width: u32,
 	height: u32,
@@ -203,6 +1079,164 @@ pub struct WindowNode<Window> {
 	y: i32,
 }
 
+/// An edge between two adjacent [nodes] in a group, as found by [`TilingLayout::edge_at`].
+///
+/// [nodes]: Node
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct EdgeHit {
+	/// The path of indexes to the group containing the edge; empty for the root group.
+	pub path: Vec<usize>,
+	/// The index of the node before the edge; the node after the edge is at `index + 1`.
+	///
+	/// This is the index to [resize] to move the edge.
+	///
+	/// [resize]: GroupNode::resize_child
+	pub index: usize,
+	/// The [axis] of the group, along which the edge can be moved.
+	///
+	/// [axis]: Orientation::axis
+	pub axis: Axis,
+	/// The coordinate along the `axis` at which the node before the edge ends.
+	///
+	/// If there is a [window gap], the node after the edge starts a gap's width later.
+	///
+	/// [window gap]: LayoutSettings::window_gap
+	pub position: i32,
+}
+
+/// The shape of a [tiling layout]: the structure of its groups, their orientations, and the
+/// relative sizes of their children, without the windows within it.
+///
+/// A snapshot [taken] of one layout can be [applied] to another to rearrange that layout's windows
+/// into the same shape.
+///
+/// [tiling layout]: TilingLayout
+/// [taken]: TilingLayout::snapshot
+/// [applied]: TilingLayout::apply_snapshot
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct LayoutSnapshot {
+	pub orientation: Orientation,
+	/// The group's children, in the order they are [iterated] in.
+	///
+	/// [iterated]: GroupNode::iter
+	pub children: Vec<SnapshotNode>,
+}
+
+/// A child of a [`LayoutSnapshot`].
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SnapshotNode {
+	/// The child's share of its group's size along the group's [axis].
+	///
+	/// Weights are relative to the other children in the same group, so they needn't add up to
+	/// `1`.
+	///
+	/// [axis]: Orientation::axis
+	pub weight: f64,
+	pub shape: Shape,
+}
+
+/// The shape of a [`SnapshotNode`]: either a slot for a window or a nested group.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Shape {
+	Window,
+	Group(LayoutSnapshot),
+}
+
+/// A node seen while [walking] a [tiling layout], as yielded by its [`SnapshotIter`].
+///
+/// [walking]: GroupNode::snapshot_iter
+/// [tiling layout]: TilingLayout
+/// [`SnapshotIter`]: GroupNode::snapshot_iter
+#[derive(Debug, PartialEq)]
+pub struct NodeSnapshot<'layout, Window> {
+	pub id: NodeId,
+	/// The number of groups the node is nested within; `0` for the root group.
+	pub depth: usize,
+	/// The node's [index] within its group; `0` for the root group.
+	///
+	/// [index]: GroupNode::get
+	pub index: usize,
+	pub kind: NodeKind<'layout, Window>,
+	/// The x and y coordinates and width and height of the node, as they were when changes were
+	/// last [applied].
+	///
+	/// These are the same geometries given to `resize_window` for windows.
+	///
+	/// [applied]: TilingLayout::apply_changes
+	pub rect: (i32, i32, u32, u32),
+	pub flags: NodeFlags,
+}
+
+/// Whether a [`NodeSnapshot`] is of a window or a group, along with what is specific to each.
+#[derive(Debug, PartialEq)]
+pub enum NodeKind<'layout, Window> {
+	Window {
+		window: &'layout Window,
+		/// The window's [visibility] as of the last time changes were [applied].
+		///
+		/// [visibility]: WindowNode::visibility
+		/// [applied]: TilingLayout::apply_changes
+		visibility: Visibility,
+	},
+	Group {
+		orientation: Orientation,
+		/// Whether the group is [stacked].
+		///
+		/// [stacked]: GroupNode::is_stacked
+		stacked: bool,
+		/// The number of children in the group.
+		len: usize,
+	},
+}
+
+/// Flags describing the state of a [`NodeSnapshot`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct NodeFlags {
+	/// Whether the node is the window the snapshots were [focused on], or a group containing it.
+	///
+	/// [focused on]: GroupNode::snapshot_iter
+	pub focused: bool,
+	/// Whether the node or one of the groups containing it has changes which have not been
+	/// [applied] yet, so its `rect` may be about to change.
+	///
+	/// [applied]: TilingLayout::apply_changes
+	pub pending: bool,
+}
+
+/// An error returned when parsing a [description] of a [tiling layout] fails.
+///
+/// Positions are byte offsets into the description.
+///
+/// [description]: TilingLayout::from_description
+/// [tiling layout]: TilingLayout
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Error)]
+pub enum ParseDescriptionError {
+	/// Something other than what was `expected` was `found` (or the description ended, if
+	/// [`None`]).
+	#[error("expected {expected} at {position}, found {}", describe_found(.found))]
+	Expected {
+		position: usize,
+		expected: &'static str,
+		found: Option<char>,
+	},
+
+	/// A weight wasn't a positive number, percentage, or ratio.
+	#[error("invalid weight `{weight}` at {position}: weights must be positive numbers, like `60%`, `0.6`, or `3/5`")]
+	Weight { position: usize, weight: String },
+
+	/// A window name couldn't be resolved to a window.
+	#[error("unknown window {name:?} at {position}")]
+	Unresolved { position: usize, name: String },
+}
+
+/// Describes what was found instead of what was expected in a [`ParseDescriptionError`].
+fn describe_found(found: &Option<char>) -> String {
+	match found {
+		Some(found) => format!("`{found}`"),
+		None => "the end of the description".to_owned(),
+	}
+}
+
 /// Manages a [tiling layout], restructuring the layout when a window needs to be [added] or
 /// [removed].
 ///
@@ -277,6 +1311,26 @@ pub unsafe trait TilingLayoutManager<Window>: Send + Sync + 'static {
 		WindowsIter: IntoIterator<Item = Window>,
 		WindowsIter::IntoIter: ExactSizeIterator;
 
+	/// Creates the layout manager with the given `windows`, adopting their existing coordinates and
+	/// dimensions, e.g. when tiling windows which were already open.
+	///
+	/// Layout managers may use the geometries to decide where each window goes, and may add the
+	/// windows with [`adopt_window_back`] so that their relative sizes survive the first time the
+	/// layout's changes are applied.
+	///
+	/// The default implementation ignores the geometries and calls [`init`].
+	///
+	/// [`adopt_window_back`]: GroupNode::adopt_window_back
+	/// [`init`]: Self::init
+	fn init_with_geometries<WindowsIter>(layout: TilingLayout<Window>, windows: WindowsIter) -> Self
+	where
+		Self: Sized,
+		WindowsIter: IntoIterator<Item = (Window, (i32, i32, u32, u32))>,
+		WindowsIter::IntoIter: ExactSizeIterator,
+	{
+		Self::init(layout, windows.into_iter().map(|(window, _)| window))
+	}
+
 	/// Returns a shared reference to the [layout] managed by the layout manager.
 	///
 	/// [layout]: TilingLayout
@@ -287,6 +1341,17 @@ pub unsafe trait TilingLayoutManager<Window>: Send + Sync + 'static {
 	/// [layout]: TilingLayout
 	fn layout_mut(&mut self) -> &mut TilingLayout<Window>;
 
+	/// Returns the name of the layout manager, e.g. for status bars to display.
+	///
+	/// The default implementation returns the name of the implementing type, without its path or
+	/// generic parameters.
+	fn name(&self) -> &'static str {
+		let name = any::type_name::<Self>();
+		let name = name.split('<').next().unwrap_or(name);
+
+		name.rsplit("::").next().unwrap_or(name)
+	}
+
 	/// Add the given `window` to the layout.
 	///
 	/// # Implementation notes
@@ -308,4 +1373,124 @@ pub unsafe trait TilingLayoutManager<Window>: Send + Sync + 'static {
 	///
 	/// [trait documentation]: Self
 	fn remove_window(&mut self, window: &Window);
+
+	/// Notifies the layout manager that the focused window has changed to the given `window`.
+	///
+	/// This is called by AquariWM whenever the focused window changes, including to [`None`] when
+	/// the focused window is removed (before [`remove_window`] is called for it). The `window` is
+	/// not necessarily in the layout: it may be a floating window.
+	///
+	/// # Implementation notes
+	/// The layout manager *may* remember the focused window to inform later operations, like
+	/// where to [add windows], but it *should not* restructure the layout in response.
+	///
+	/// The default implementation does nothing.
+	///
+	/// [`remove_window`]: Self::remove_window
+	/// [add windows]: Self::add_window
+	fn focused_changed(&mut self, window: Option<&Window>) {
+		let _ = window;
+	}
+
+	/// Grows the layout manager's primary area by `delta` pixels, shrinking the rest of the layout
+	/// to make room.
+	///
+	/// A negative `delta` shrinks the primary area instead. This is called by AquariWM when the
+	/// user requests it, typically with a key binding.
+	///
+	/// # Implementation notes
+	/// The layout manager *may* resize any nodes in the layout, but it *must not* add or remove
+	/// windows. Layout managers without a primary area need not do anything.
+	///
+	/// The default implementation does nothing.
+	fn resize_primary(&mut self, delta: i32) {
+		let _ = delta;
+	}
+
+	/// Swaps the `focused` window with the layout manager's primary window, returning whether
+	/// any windows were swapped.
+	///
+	/// This is called by AquariWM when the user requests it, typically with a key binding. If the
+	/// `focused` window is already the primary window, it *may* be swapped with the next window
+	/// instead.
+	///
+	/// # Implementation notes
+	/// The layout manager *should* [swap the windows] of the two window nodes, rather than the
+	/// nodes themselves, so that AquariWM can find the window swapped into the `focused` window's
+	/// place to [focus it instead]. It *must not* add or remove windows.
+	///
+	/// The default implementation [swaps the `focused` window with the first window] in the
+	/// layout.
+	///
+	/// [swap the windows]: GroupNode::swap_windows_by_id
+	/// [focus it instead]: SwapFocus::Stay
+	/// [swaps the `focused` window with the first window]: TilingLayout::swap_with_first
+	fn swap_with_primary(&mut self, focused: &Window) -> bool
+	where
+		Window: PartialEq,
+	{
+		self.layout_mut().swap_with_first(focused)
+	}
+
+	/// Adds the given `window` to the layout alongside the first window matching `same_class`.
+	///
+	/// This is called by AquariWM instead of [`add_window`] when the [insertion strategy] is
+	/// [`GroupByClass`]. The `window` is [grouped] with the first window of the same class, or
+	/// [added] as usual if there is none.
+	///
+	/// # Implementation notes
+	/// The default implementation is expected to be sufficient for all layout managers.
+	///
+	/// [`add_window`]: Self::add_window
+	/// [added]: Self::add_window
+	/// [insertion strategy]: LayoutSettings::insertion_strategy
+	/// [`GroupByClass`]: InsertionStrategy::GroupByClass
+	/// [grouped]: GroupNode::group_window_by
+	fn add_window_grouped(&mut self, window: Window, same_class: &dyn Fn(&Window) -> bool) {
+		if let Err(window) = self.layout_mut().group_window_by(window, same_class) {
+			self.add_window(window);
+		}
+	}
+
+	/// Removes the given `window` from the layout, dissolving its group if it was [grouped].
+	///
+	/// This is called by AquariWM instead of [`remove_window`], which is only called for windows
+	/// that aren't in a [dissolving] group.
+	///
+	/// # Implementation notes
+	/// The default implementation is expected to be sufficient for all layout managers.
+	///
+	/// [grouped]: Self::add_window_grouped
+	/// [`remove_window`]: Self::remove_window
+	/// [dissolving]: GroupNode::is_dissolving
+	fn remove_window_grouped(&mut self, window: &Window)
+	where
+		Window: PartialEq,
+	{
+		if !self.layout_mut().remove_grouped_window(window) {
+			self.remove_window(window);
+		}
+	}
+
+	/// Notifies the layout manager of the visible windows which were reconfigured when the
+	/// layout's changes were last applied, along with the coordinates and dimensions they really
+	/// have.
+	///
+	/// This is called by AquariWM once each time changes are applied which reconfigure any visible
+	/// windows, with each window reported once. A window's actual geometry differs from the one
+	/// requested by the layout if the display server [adjusted] it, e.g. to honor the window's
+	/// minimum size, or if the window was [skipped] because it no longer exists.
+	///
+	/// # Implementation notes
+	/// The layout manager *may* reconcile any state it keeps about the layout with the windows'
+	/// actual geometries, but it *must not* add or remove windows. Changes it makes to the layout
+	/// are applied the next time changes are applied.
+	///
+	/// The default implementation does nothing.
+	///
+	/// [adjusted]: Reconfigured::Adjusted
+	/// [skipped]: Reconfigured::SkipWindow
+	fn changes_applied(&mut self, changes: &[AppliedChange<Window>]) {
+		let _ = changes;
+	}
 }
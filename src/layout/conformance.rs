@@ -0,0 +1,638 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{
+	any::{self, Any},
+	collections::HashMap,
+	convert::Infallible,
+	fmt,
+	fmt::{Display, Formatter},
+	panic::{self, AssertUnwindSafe},
+};
+
+use super::*;
+
+/// A rectangle given as its coordinates and dimensions.
+type Rect = (i32, i32, u32, u32);
+
+// This is a false positive: `derive_extras::Default` is not the same as `Default`.
+#[allow(unused_qualifications)]
+/// Controls how thoroughly a layout manager is [checked].
+///
+/// [checked]: check_manager
+#[derive(Debug, PartialEq, Eq, Hash, Clone, derive_extras::Default, builder)]
+#[new]
+pub struct ConformanceConfig {
+	/// The seed of the first run. Each run after it uses the next seed.
+	pub seed: u64,
+	/// The number of runs, each with its own seed.
+	#[default = 16]
+	pub runs: u64,
+	/// The number of random operations performed in each run, before the remaining windows are
+	/// removed.
+	#[default = 200]
+	pub operations: usize,
+
+	/// The number of windows the layout manager is [initialised] with.
+	///
+	/// [initialised]: TilingLayoutManager::init
+	#[default = 3]
+	pub initial_windows: u32,
+	/// The maximum number of windows in the layout at once.
+	#[default = 12]
+	pub max_windows: u32,
+
+	/// The initial width of the layout.
+	#[default = 1920]
+	pub width: u32,
+	/// The initial height of the layout.
+	#[default = 1080]
+	pub height: u32,
+	/// The settings with which changes are applied.
+	pub settings: LayoutSettings,
+}
+
+/// An operation performed on a layout manager during a [conformance check].
+///
+/// [conformance check]: check_manager
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Operation {
+	/// The window is [added].
+	///
+	/// [added]: TilingLayoutManager::add_window
+	Add(u32),
+	/// The window is [removed].
+	///
+	/// [removed]: TilingLayoutManager::remove_window
+	Remove(u32),
+	/// The root group is [rotated] by the given number of rotations.
+	///
+	/// [rotated]: GroupNode::rotate_by
+	Rotate(i32),
+	/// The layout is [resized] to the given dimensions.
+	///
+	/// [resized]: TilingLayout::resize
+	Resize { width: u32, height: u32 },
+}
+
+/// The operations performed in a run of a [conformance check], which can be [replayed].
+///
+/// [conformance check]: check_manager
+/// [replayed]: replay
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Trace {
+	/// The seed from which the operations were generated.
+	pub seed: u64,
+
+	/// The windows the layout manager was [initialised] with.
+	///
+	/// [initialised]: TilingLayoutManager::init
+	pub initial: Vec<u32>,
+	/// The operations performed after the layout manager was initialised, in order.
+	pub operations: Vec<Operation>,
+}
+
+/// A check failed by a layout manager during a [conformance check].
+///
+/// [conformance check]: check_manager
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Error)]
+pub enum Violation {
+	/// The layout manager panicked with the given message.
+	#[error("the layout manager panicked: {0}")]
+	Panicked(String),
+
+	/// The root group's orientation isn't the one it was [initialised] with, rotated by any
+	/// [rotations] since.
+	///
+	/// [initialised]: TilingLayoutManager::orientation
+	/// [rotations]: Operation::Rotate
+	#[error("the root group's orientation is {actual:?}, rather than {expected:?}")]
+	Orientation { expected: Orientation, actual: Orientation },
+
+	/// A window which should be in the layout isn't.
+	#[error("window {0} is missing from the layout")]
+	Missing(u32),
+	/// A window appears in the layout the given number of times.
+	#[error("window {0} appears in the layout {1} times")]
+	Repeated(u32, usize),
+	/// A window which was never added, or has been removed, is in the layout.
+	#[error("window {0} is in the layout, but it was never added or has been removed")]
+	Unexpected(u32),
+
+	/// A visible window isn't within the layout's bounds.
+	#[error("window {window} at {rect:?} is outside of the layout at {layout:?}")]
+	OutOfBounds { window: u32, rect: Rect, layout: Rect },
+	/// Two visible windows overlap.
+	#[error("windows {0} and {1} overlap")]
+	Overlapping(u32, u32),
+
+	/// The layout's tree is invalid.
+	#[error(transparent)]
+	Invalid(#[from] TreeInvariantError),
+}
+
+/// A failed [conformance check]: the [violation] found, and the [trace] of the operations which
+/// led to it, the last of which was the one after which it was found.
+///
+/// [conformance check]: check_manager
+/// [violation]: Violation
+/// [trace]: Trace
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Error)]
+#[error("{violation}, after {trace}")]
+pub struct Failure {
+	pub violation: Violation,
+	pub trace: Trace,
+}
+
+/// A small, seeded pseudo-random number generator ([SplitMix64]), so that runs are reproducible.
+///
+/// [SplitMix64]: https://prng.di.unimi.it/splitmix64.c
+struct Rng(u64);
+
+/// A layout manager under test, and what is expected of it.
+struct Harness<Manager> {
+	manager: Manager,
+	settings: LayoutSettings,
+
+	/// The windows which should be in the layout.
+	windows: Vec<u32>,
+	/// The orientation the root group should have.
+	orientation: Orientation,
+	/// The geometry and visibility last reported for each window in the layout.
+	reported: HashMap<u32, (Rect, Visibility)>,
+}
+
+/// Checks that the given layout `Manager` conforms to what is expected of [layout managers],
+/// panicking with the failing seed and its [trace] if it doesn't.
+///
+/// Each run [initialises] the layout manager and performs random [operations] on it, seeded so
+/// that the run can be reproduced, then removes every window left. After each operation, changes
+/// are applied and it is checked that:
+/// - the layout manager didn't panic;
+/// - the root group's [orientation] is the layout manager's, rotated by any rotations since;
+/// - every window which should be in the layout appears in it exactly once, and no others do;
+/// - the layout's tree is [valid];
+/// - no visible windows overlap, and they are all within the layout's bounds, unless the changes
+///   were [deferred].
+///
+/// [layout managers]: TilingLayoutManager
+/// [trace]: Trace
+/// [initialises]: TilingLayoutManager::init
+/// [operations]: Operation
+/// [orientation]: TilingLayoutManager::orientation
+/// [valid]: TilingLayout::validate
+/// [deferred]: TilingLayout::is_deferred
+pub fn check_manager<Manager>(config: ConformanceConfig)
+where
+	Manager: TilingLayoutManager<u32>,
+{
+	let name = any::type_name::<Manager>();
+
+	for seed in config.seed..config.seed.saturating_add(config.runs) {
+		if let Err(failure) = run::<Manager>(seed, &config) {
+			panic!("{name} failed its conformance check with seed {seed}: {failure}");
+		}
+	}
+}
+
+/// Performs one run of a [conformance check] with the given `seed`, returning the [trace] of the
+/// operations performed.
+///
+/// # Errors
+/// Returns a [`Failure`] if any check fails.
+///
+/// [conformance check]: check_manager
+/// [trace]: Trace
+pub fn run<Manager>(seed: u64, config: &ConformanceConfig) -> Result<Trace, Failure>
+where
+	Manager: TilingLayoutManager<u32>,
+{
+	let mut rng = Rng(seed);
+	let mut trace = Trace {
+		seed,
+
+		initial: (1..=config.initial_windows).collect(),
+		operations: Vec::new(),
+	};
+	let mut next_window = config.initial_windows + 1;
+
+	let mut harness = match Harness::<Manager>::init(&trace.initial, config) {
+		Ok(harness) => harness,
+		Err(violation) => return Err(Failure { violation, trace }),
+	};
+
+	for _ in 0..config.operations {
+		let operation = match (harness.windows.len() as u32, rng.below(20)) {
+			(0, _) => Operation::Add(next_window),
+			(len, 0..=7) if len < config.max_windows => Operation::Add(next_window),
+			(_, 0..=14) => Operation::Remove(harness.windows[rng.below(harness.windows.len() as u64) as usize]),
+
+			(_, 15..=16) => Operation::Rotate([-1, 1, 2][rng.below(3) as usize]),
+			(..) => Operation::Resize {
+				width: rng.between(config.width / 4, config.width),
+				height: rng.between(config.height / 4, config.height),
+			},
+		};
+		if let Operation::Add(_) = operation {
+			next_window += 1;
+		}
+
+		trace.operations.push(operation);
+		if let Err(violation) = harness.perform(operation) {
+			return Err(Failure { violation, trace });
+		}
+	}
+
+	// Windows added then removed must leave an empty (valid) layout.
+	while !harness.windows.is_empty() {
+		let operation = Operation::Remove(harness.windows[rng.below(harness.windows.len() as u64) as usize]);
+
+		trace.operations.push(operation);
+		if let Err(violation) = harness.perform(operation) {
+			return Err(Failure { violation, trace });
+		}
+	}
+
+	Ok(trace)
+}
+
+/// Replays the given `trace` of a [conformance check] run against the given layout `Manager`,
+/// e.g. to reproduce a failure while debugging it.
+///
+/// # Errors
+/// Returns a [`Failure`] if any check fails, with the `trace` up to the operation after which it
+/// did.
+///
+/// [conformance check]: check_manager
+pub fn replay<Manager>(trace: &Trace, config: &ConformanceConfig) -> Result<(), Failure>
+where
+	Manager: TilingLayoutManager<u32>,
+{
+	let failure = |violation, performed| Failure {
+		violation,
+		trace: Trace {
+			operations: trace.operations[..performed].to_vec(),
+
+			..trace.clone()
+		},
+	};
+
+	let mut harness = Harness::<Manager>::init(&trace.initial, config).map_err(|violation| failure(violation, 0))?;
+
+	for (i, &operation) in trace.operations.iter().enumerate() {
+		harness
+			.perform(operation)
+			.map_err(|violation| failure(violation, i + 1))?;
+	}
+
+	Ok(())
+}
+
+/// Creates a layout from its textual [description], sized as in the given `config`, naming each
+/// window `w` followed by its number, e.g. `h(60% v(w1 w2) 40% w3)`.
+///
+/// This is meant for defining fixtures in tests: it panics if the description is invalid.
+///
+/// [description]: TilingLayout::from_description
+pub fn fixture(description: &str, config: &ConformanceConfig) -> TilingLayout<u32> {
+	let mut layout = TilingLayout::from_description(description, |name| name.strip_prefix('w')?.parse().ok())
+		.unwrap_or_else(|error| panic!("invalid fixture {description:?}: {error}"));
+	layout.resize(0, 0, config.width, config.height, &config.settings);
+
+	layout
+}
+
+/// Returns the [description] of a layout whose windows are named as in [fixtures].
+///
+/// [description]: TilingLayout::to_description
+/// [fixtures]: fixture
+pub fn describe(layout: &TilingLayout<u32>) -> String {
+	layout.to_description(|window| format!("w{window}"))
+}
+
+impl<Manager> Harness<Manager>
+where
+	Manager: TilingLayoutManager<u32>,
+{
+	/// Initialises the layout `Manager` with the `initial` windows, and checks the result.
+	fn init(initial: &[u32], config: &ConformanceConfig) -> Result<Self, Violation> {
+		let settings = config.settings.clone();
+		let layout = TilingLayout::new(Manager::orientation(), 0, 0, config.width, config.height, &settings);
+
+		let windows = initial.to_vec();
+		let manager = catch_panic(AssertUnwindSafe(|| Manager::init(layout, windows.clone())))?;
+
+		let mut harness = Self {
+			manager,
+			settings,
+
+			windows,
+			orientation: Manager::orientation(),
+			reported: HashMap::new(),
+		};
+		harness.apply()?;
+		harness.verify()?;
+
+		Ok(harness)
+	}
+
+	/// Performs the given `operation`, applies the resulting changes, and checks the result.
+	fn perform(&mut self, operation: Operation) -> Result<(), Violation> {
+		let Self { manager, settings, .. } = self;
+
+		catch_panic(AssertUnwindSafe(|| match operation {
+			Operation::Add(window) => manager.add_window(window),
+			Operation::Remove(window) => manager.remove_window(&window),
+
+			Operation::Rotate(rotations) => manager.layout_mut().rotate_by(rotations),
+			Operation::Resize { width, height } => manager.layout_mut().resize(0, 0, width, height, settings),
+		}))?;
+
+		match operation {
+			Operation::Add(window) => self.windows.push(window),
+			Operation::Remove(window) => {
+				self.windows.retain(|&other| other != window);
+				self.reported.remove(&window);
+			},
+
+			Operation::Rotate(rotations) => self.orientation = self.orientation.rotated_by(rotations),
+			Operation::Resize { .. } => (),
+		}
+
+		self.apply()?;
+		self.verify()
+	}
+
+	/// Applies the layout's changes, recording the geometry and visibility reported for each
+	/// window.
+	fn apply(&mut self) -> Result<(), Violation> {
+		let Self {
+			manager,
+			settings,
+			reported,
+			..
+		} = self;
+
+		catch_panic(AssertUnwindSafe(|| {
+			manager.layout_mut().apply_changes(
+				&mut |&window, x, y, width, height, visibility| {
+					reported.insert(window, ((x, y, width, height), visibility));
+
+					Ok::<_, Infallible>(())
+				},
+				settings,
+			)
+		}))?
+		.unwrap_or_else(|never| match never {});
+
+		Ok(())
+	}
+
+	/// Checks that the layout is what is expected of it.
+	fn verify(&self) -> Result<(), Violation> {
+		let layout = self.manager.layout();
+
+		if layout.orientation() != self.orientation {
+			return Err(Violation::Orientation {
+				expected: self.orientation,
+				actual: layout.orientation(),
+			});
+		}
+
+		let mut counts = HashMap::new();
+		for &window in layout.windows() {
+			*counts.entry(window).or_insert(0) += 1;
+		}
+		for window in &self.windows {
+			match counts.remove(window) {
+				None => return Err(Violation::Missing(*window)),
+				Some(1) => (),
+				Some(count) => return Err(Violation::Repeated(*window, count)),
+			}
+		}
+		if let Some(&window) = counts.keys().min() {
+			return Err(Violation::Unexpected(window));
+		}
+
+		layout.validate()?;
+
+		// Deferred changes haven't been applied, so the windows are where they were.
+		if layout.is_deferred() {
+			return Ok(());
+		}
+
+		let bounds = (layout.x, layout.y, layout.width, layout.height);
+		let visible: Vec<_> = self
+			.windows
+			.iter()
+			.filter_map(|window| match self.reported.get(window) {
+				Some(&(rect, Visibility::Visible)) => Some((*window, rect)),
+				_ => None,
+			})
+			.collect();
+
+		for (i, &(window, rect)) in visible.iter().enumerate() {
+			if !contains(bounds, rect) {
+				return Err(Violation::OutOfBounds {
+					window,
+					rect,
+					layout: bounds,
+				});
+			}
+
+			if let Some(&(other, _)) = visible[i + 1..].iter().find(|(_, other)| overlap(rect, *other)) {
+				return Err(Violation::Overlapping(window, other));
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl Rng {
+	/// Returns the next pseudo-random number.
+	const fn next(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+		z ^ (z >> 31)
+	}
+
+	/// Returns a pseudo-random number less than `n`, which must not be `0`.
+	const fn below(&mut self, n: u64) -> u64 {
+		self.next() % n
+	}
+
+	/// Returns a pseudo-random number from `min` to `max` inclusive.
+	fn between(&mut self, min: u32, max: u32) -> u32 {
+		let (min, max) = (min.min(max), min.max(max));
+
+		min + (self.below(u64::from(max - min) + 1) as u32)
+	}
+}
+
+impl Display for Trace {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "seed {}: initialised with windows {:?}", self.seed, self.initial)?;
+
+		for (i, operation) in self.operations.iter().enumerate() {
+			write!(f, "\n{:>5}. ", i + 1)?;
+
+			match operation {
+				Operation::Add(window) => write!(f, "add window {window}")?,
+				Operation::Remove(window) => write!(f, "remove window {window}")?,
+
+				Operation::Rotate(rotations) => write!(f, "rotate the root group by {rotations}")?,
+				Operation::Resize { width, height } => write!(f, "resize the layout to {width}x{height}")?,
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Calls `f`, returning a [`Violation::Panicked`] if it panics.
+fn catch_panic<T>(f: impl FnOnce() -> T + panic::UnwindSafe) -> Result<T, Violation> {
+	panic::catch_unwind(f).map_err(|payload| Violation::Panicked(panic_message(&*payload)))
+}
+
+/// Returns the message of a panic with the given `payload`.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+	match payload.downcast_ref::<&str>() {
+		Some(message) => (*message).to_owned(),
+		None => payload
+			.downcast_ref::<String>()
+			.cloned()
+			.unwrap_or_else(|| "(no message)".to_owned()),
+	}
+}
+
+/// Returns whether the `outer` rectangle contains the `inner` rectangle.
+fn contains((x, y, width, height): Rect, (inner_x, inner_y, inner_width, inner_height): Rect) -> bool {
+	let (right, bottom) = (i64::from(x) + i64::from(width), i64::from(y) + i64::from(height));
+
+	inner_x >= x
+		&& inner_y >= y
+		&& i64::from(inner_x) + i64::from(inner_width) <= right
+		&& i64::from(inner_y) + i64::from(inner_height) <= bottom
+}
+
+/// Returns whether the rectangles `a` and `b` overlap by any area.
+///
+/// Windows left with no space don't overlap anything.
+fn overlap((ax, ay, a_width, a_height): Rect, (bx, by, b_width, b_height): Rect) -> bool {
+	let (ax, ay, bx, by) = (i64::from(ax), i64::from(ay), i64::from(bx), i64::from(by));
+
+	let empty = a_width == 0 || a_height == 0 || b_width == 0 || b_height == 0;
+
+	!empty
+		&& ax < bx + i64::from(b_width)
+		&& bx < ax + i64::from(a_width)
+		&& ay < by + i64::from(b_height)
+		&& by < ay + i64::from(a_height)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A layout manager which forgets to remove windows, and tiles every window over the last.
+	struct Broken {
+		layout: TilingLayout<u32>,
+	}
+
+	unsafe impl TilingLayoutManager<u32> for Broken {
+		fn orientation() -> Orientation {
+			Orientation::LeftToRight
+		}
+
+		fn init<WindowsIter>(layout: TilingLayout<u32>, windows: WindowsIter) -> Self
+		where
+			WindowsIter: IntoIterator<Item = u32>,
+			WindowsIter::IntoIter: ExactSizeIterator,
+		{
+			let mut broken = Self { layout };
+			broken.layout.push_windows_back(windows);
+
+			broken
+		}
+
+		fn layout(&self) -> &TilingLayout<u32> {
+			&self.layout
+		}
+
+		fn layout_mut(&mut self) -> &mut TilingLayout<u32> {
+			&mut self.layout
+		}
+
+		fn add_window(&mut self, window: u32) {
+			self.layout.push_window_back(window);
+		}
+
+		fn remove_window(&mut self, _window: &u32) {}
+	}
+
+	/// Tests that a failure is reported with the trace that led to it, which reproduces it when
+	/// replayed.
+	#[test]
+	fn failure_is_reproducible() {
+		let config = ConformanceConfig::new().operations(50);
+
+		let failure = run::<Broken>(3, &config).unwrap_err();
+		assert_eq!(failure.trace.seed, 3);
+		assert!(matches!(failure.violation, Violation::Unexpected(_)));
+		assert!(matches!(failure.trace.operations.last(), Some(Operation::Remove(_))));
+
+		assert_eq!(replay::<Broken>(&failure.trace, &config), Err(failure.clone()));
+	}
+
+	/// Tests that the same seed always performs the same operations.
+	#[test]
+	fn deterministic() {
+		let config = ConformanceConfig::new().operations(100);
+
+		let trace = run::<managers::Stack<u32>>(7, &config).unwrap();
+		assert_eq!(run::<managers::Stack<u32>>(7, &config), Ok(trace.clone()));
+		assert_eq!(replay::<managers::Stack<u32>>(&trace, &config), Ok(()));
+	}
+
+	/// Tests that fixtures are tiled as described.
+	#[test]
+	fn fixtures() {
+		let config = ConformanceConfig::new()
+			.width(1000)
+			.height(500)
+			.settings(LayoutSettings::new().window_gap(0));
+		let mut layout = fixture("h(60% v(w1 w2) 40% w3)", &config);
+
+		let mut geometry = HashMap::new();
+		layout
+			.apply_changes(
+				&mut |&window, x, y, width, height, _| {
+					geometry.insert(window, (x, y, width, height));
+
+					Ok::<_, Infallible>(())
+				},
+				&config.settings,
+			)
+			.unwrap_or_else(|never| match never {});
+
+		assert_eq!(layout.validate(), Ok(()));
+		assert_eq!(describe(&layout), "h(60% v(w1 w2) 40% w3)");
+		assert_eq!(geometry[&3], (600, 0, 400, 500));
+	}
+
+	#[test]
+	fn geometry() {
+		assert!(contains((0, 0, 100, 100), (10, 10, 90, 90)));
+		assert!(!contains((0, 0, 100, 100), (10, 10, 91, 90)));
+
+		assert!(overlap((0, 0, 100, 100), (99, 99, 10, 10)));
+		assert!(!overlap((0, 0, 100, 100), (100, 0, 10, 10)));
+		assert!(!overlap((0, 0, 100, 100), (50, 50, 0, 10)));
+	}
+}
@@ -6,12 +6,51 @@ use std::{
 	borrow::{Borrow, BorrowMut},
 	mem,
 	ops::{Deref, DerefMut, Index, IndexMut},
+	str::FromStr,
 };
 
 use super::*;
-
+use crate::output::Scale;
+
+mod compact;
+mod depth;
+mod description;
+mod edges;
+#[cfg(test)]
+mod fixtures;
+mod grouping;
+mod ids;
+mod increments;
 mod iter;
+mod manual;
+mod map;
+mod maximize;
 mod node_changes;
+mod primary;
+mod prune;
+mod resize;
+mod sizing;
+mod snapshot;
+mod stacked;
+mod stats;
+mod traversal;
+mod validate;
+mod visual;
+
+impl LayoutSettings {
+	/// Returns these settings with their logical sizes converted to device pixels using the given
+	/// `scale`.
+	///
+	/// See [`Scale`] for how sizes are rounded.
+	pub fn scaled(&self, scale: Scale) -> Self {
+		Self {
+			window_gap: scale.to_device(self.window_gap),
+			maximize_strip: scale.to_device(self.maximize_strip),
+
+			..self.clone()
+		}
+	}
+}
 
 impl<Window> CurrentLayout<Window> {
 	/// Creates a new [tiled layout] using the given layout `Manager` type parameter.
@@ -47,12 +86,37 @@ impl<Window> CurrentLayout<Window> {
 
 		Self::Tiled(Box::new(Manager::init(layout, windows)))
 	}
+
+	/// Creates a new [tiled layout] using the given layout `Manager` type parameter containing the
+	/// given `windows`, adopting their existing coordinates and dimensions.
+	///
+	/// See [`TilingLayoutManager::init_with_geometries`] for how the geometries are used.
+	///
+	/// [tiled layout]: Self::Tiled
+	#[inline]
+	pub(crate) fn tiled_with_geometries<Manager, Windows>(
+		x: i32,
+		y: i32,
+		width: u32,
+		height: u32,
+		windows: Windows,
+		settings: &LayoutSettings,
+	) -> Self
+	where
+		Manager: TilingLayoutManager<Window>,
+		Windows: IntoIterator<Item = (Window, (i32, i32, u32, u32))>,
+		Windows::IntoIter: ExactSizeIterator,
+	{
+		let layout = TilingLayout::new(Manager::orientation(), x, y, width, height, settings);
+
+		Self::Tiled(Box::new(Manager::init_with_geometries(layout, windows)))
+	}
 }
 
 impl<Window> TilingLayout<Window> {
 	/// Creates an empty layout of the given `orientation`.
 	#[inline]
-	pub(crate) const fn new(
+	pub(crate) fn new(
 		orientation: Orientation,
 		x: i32,
 		y: i32,
@@ -62,6 +126,15 @@ impl<Window> TilingLayout<Window> {
 	) -> Self {
 		let padding = settings.window_gap;
 
+		let mut root = GroupNode::with(
+			orientation,
+			x + (padding as i32),
+			y + (padding as i32),
+			width.saturating_sub(2 * padding),
+			height.saturating_sub(2 * padding),
+		);
+		root.nesting = Nesting::root(settings);
+
 		Self {
 			x,
 			y,
@@ -69,16 +142,49 @@ impl<Window> TilingLayout<Window> {
 			width,
 			height,
 
-			root: GroupNode::with(
-				orientation,
-				x + (padding as i32),
-				y + (padding as i32),
-				width - (2 * padding),
-				height - (2 * padding),
-			),
+			deferred: false,
+			coverage: None,
+
+			root,
 		}
 	}
 
+	/// Resizes the tiling layout to the given coordinates and dimensions.
+	///
+	/// Please note that for the nodes in the layout to be updated, [state::AquariWm::apply_changes]
+	#[cfg_attr(feature = "async", doc = "or [state::AquariWm::apply_changes_async]")]
+	/// must be called.
+	///
+	/// [state::AquariWm::apply_changes]: crate::state::AquariWm::apply_changes
+	#[cfg_attr(
+		feature = "async",
+		doc = "[state::AquariWm::apply_changes_async]: crate::state::AquariWm::apply_changes_async"
+	)]
+	pub fn resize(&mut self, x: i32, y: i32, width: u32, height: u32, settings: &LayoutSettings) {
+		self.x = x;
+		self.y = y;
+
+		self.width = width;
+		self.height = height;
+
+		self.update_settings(settings);
+	}
+
+	/// Returns whether the latest attempt to [apply changes] to the layout was deferred.
+	///
+	/// Changes are deferred when the layout's dimensions are degenerate: too small to fit every
+	/// node with the [window gap] between them (including a width or height of `0`, which can
+	/// happen transiently during monitor hotplugging). Deferred changes remain pending, and are
+	/// applied automatically once the layout is [resized] to dimensions that fit.
+	///
+	/// [apply changes]: TilingLayout::apply_changes
+	/// [window gap]: LayoutSettings::window_gap
+	/// [resized]: TilingLayout::resize
+	#[inline(always)]
+	pub const fn is_deferred(&self) -> bool {
+		self.deferred
+	}
+
 	/// Updates the tiling layout with the given `settings`.
 	///
 	/// Please note that for the nodes in the layout to be updated, [state::AquariWm::apply_changes]
@@ -91,13 +197,68 @@ impl<Window> TilingLayout<Window> {
 		doc = "[state::AquariWm::apply_changes_async]: crate::state::AquariWm::apply_changes_async"
 	)]
 	pub(crate) fn update_settings(&mut self, settings: &LayoutSettings) {
-		let padding = settings.window_gap;
+		let (x, y, width, height) = self.root_bounds(settings);
+
+		self.root.new_x = Some(x);
+		self.root.new_y = Some(y);
 
-		self.root.new_x = Some(self.x + (padding as i32));
-		self.root.new_y = Some(self.y + (padding as i32));
+		self.root.new_width = Some(width);
+		self.root.new_height = Some(height);
+		// Groups nested deeper than a lowered maximum depth are flattened.
+		self.root.set_nesting(Nesting::root(settings));
+		self.root.flatten_too_deep();
+		// Whether resize increments are honored, or what is done with the pixels left over, may
+		// have changed.
+		self.root.refresh_increments();
+	}
 
-		self.root.new_width = Some(self.width - (2 * padding));
-		self.root.new_height = Some(self.height - (2 * padding));
+	/// Returns the window in the layout if it is the only node in the layout, or [`None`]
+	/// otherwise.
+	///
+	/// A lone window fills the whole layout, and with [smart gaps] it isn't surrounded by the
+	/// [window gap] either.
+	///
+	/// [smart gaps]: LayoutSettings::smart_gaps
+	/// [window gap]: LayoutSettings::window_gap
+	pub fn single_window(&self) -> Option<&Window> {
+		match self.root.children.as_slices() {
+			([Node::Window(node)], []) | ([], [Node::Window(node)]) => Some(&node.window),
+			_ => None,
+		}
+	}
+
+	/// Returns the coordinates and dimensions of the root group: the layout's area, less the
+	/// [window gap] around it unless [smart gaps] apply to a [lone window].
+	///
+	/// [window gap]: LayoutSettings::window_gap
+	/// [smart gaps]: LayoutSettings::smart_gaps
+	/// [lone window]: Self::single_window
+	pub(super) fn root_bounds(&self, settings: &LayoutSettings) -> (i32, i32, u32, u32) {
+		let padding = match settings.smart_gaps && self.single_window().is_some() {
+			true => 0,
+			false => settings.window_gap,
+		};
+
+		(
+			self.x + (padding as i32),
+			self.y + (padding as i32),
+			self.width.saturating_sub(2 * padding),
+			self.height.saturating_sub(2 * padding),
+		)
+	}
+}
+
+impl<Window> Drop for TilingLayout<Window> {
+	fn drop(&mut self) {
+		// Dropping nested groups recursively could overflow the stack for deeply nested layouts,
+		// so the layout's groups are taken apart one by one instead.
+		let mut nodes: Vec<_> = mem::take(&mut self.root.children).into();
+
+		while let Some(node) = nodes.pop() {
+			if let Node::Group(mut group) = node {
+				nodes.extend(mem::take(&mut group.children));
+			}
+		}
 	}
 }
 
@@ -198,7 +359,7 @@ impl<Window> Node<Window> {
 	/// [window node]: WindowNode
 	/// [new]: WindowNode::new
 	#[inline(always)]
-	pub(crate) const fn new_window(window: Window) -> Self {
+	pub(crate) fn new_window(window: Window) -> Self {
 		Self::Window(WindowNode::new(window))
 	}
 
@@ -212,7 +373,7 @@ impl<Window> Node<Window> {
 	/// [Window]: Self::Window
 	/// [with]: WindowNode::with
 	#[inline(always)]
-	pub(crate) const fn new_window_with(window: Window, x: i32, y: i32, width: u32, height: u32) -> Self {
+	pub(crate) fn new_window_with(window: Window, x: i32, y: i32, width: u32, height: u32) -> Self {
 		Self::Window(WindowNode::with(window, x, y, width, height))
 	}
 
@@ -225,7 +386,7 @@ impl<Window> Node<Window> {
 	/// [Group]: Self::Group
 	/// [new]: GroupNode::new
 	#[inline(always)]
-	pub(crate) const fn new_group(orientation: Orientation) -> Self {
+	pub(crate) fn new_group(orientation: Orientation) -> Self {
 		Self::Group(GroupNode::new(orientation))
 	}
 
@@ -239,19 +400,22 @@ impl<Window> Node<Window> {
 	/// [Group]: Self::Group
 	/// [with]: GroupNode::with
 	#[inline(always)]
-	pub(crate) const fn new_group_with(orientation: Orientation, x: i32, y: i32, width: u32, height: u32) -> Self {
+	pub(crate) fn new_group_with(orientation: Orientation, x: i32, y: i32, width: u32, height: u32) -> Self {
 		Self::Group(GroupNode::with(orientation, x, y, width, height))
 	}
 
+	/// Returns the x-coordinate of the node.
 	#[inline]
-	pub(crate) const fn x(&self) -> i32 {
+	pub const fn x(&self) -> i32 {
 		match self {
 			Self::Window(node) => node.x,
 			Self::Group(node) => node.x,
 		}
 	}
 
-	pub(crate) const fn y(&self) -> i32 {
+	/// Returns the y-coordinate of the node.
+	#[inline]
+	pub const fn y(&self) -> i32 {
 		match self {
 			Self::Window(node) => node.y,
 			Self::Group(node) => node.y,
@@ -260,7 +424,7 @@ impl<Window> Node<Window> {
 
 	/// Returns the width of the node.
 	#[inline]
-	pub(crate) const fn width(&self) -> u32 {
+	pub const fn width(&self) -> u32 {
 		match self {
 			Self::Window(node) => node.width,
 			Self::Group(node) => node.width,
@@ -269,7 +433,7 @@ impl<Window> Node<Window> {
 
 	/// Returns the height of the node.
 	#[inline]
-	pub(crate) const fn height(&self) -> u32 {
+	pub const fn height(&self) -> u32 {
 		match self {
 			Self::Window(node) => node.height,
 			Self::Group(node) => node.height,
@@ -280,7 +444,9 @@ impl<Window> Node<Window> {
 	pub(crate) fn set_x(&mut self, x: i32) {
 		match self {
 			Self::Window(node) => node.x = x,
-			Self::Group(node) => node.x = x,
+			// Changes to groups are staged so that they lay out their children again when they are
+			// applied; otherwise, the change would never reach their descendents.
+			Self::Group(node) => node.new_x = (node.x != x).then_some(x),
 		}
 	}
 
@@ -288,7 +454,7 @@ impl<Window> Node<Window> {
 	pub(crate) fn set_y(&mut self, y: i32) {
 		match self {
 			Self::Window(node) => node.y = y,
-			Self::Group(node) => node.y = y,
+			Self::Group(node) => node.new_y = (node.y != y).then_some(y),
 		}
 	}
 
@@ -297,7 +463,7 @@ impl<Window> Node<Window> {
 	pub(crate) fn set_width(&mut self, width: u32) {
 		match self {
 			Self::Window(node) => node.width = width,
-			Self::Group(node) => node.width = width,
+			Self::Group(node) => node.new_width = (node.width != width).then_some(width),
 		}
 	}
 
@@ -306,7 +472,7 @@ impl<Window> Node<Window> {
 	pub(crate) fn set_height(&mut self, height: u32) {
 		match self {
 			Self::Window(node) => node.height = height,
-			Self::Group(node) => node.height = height,
+			Self::Group(node) => node.new_height = (node.height != height).then_some(height),
 		}
 	}
 
@@ -376,16 +542,29 @@ impl<Window> WindowNode<Window> {
 	/// It is useful to create a window node with no coordinates or size if they are meant to be
 	/// filled in later.
 	#[inline(always)]
-	pub(crate) const fn new(window: Window) -> Self {
+	pub(crate) fn new(window: Window) -> Self {
 		Self::with(window, 0, 0, 0, 0)
 	}
 
 	/// Creates a window node of the given `window` with the given coordinates and dimensions.
 	#[inline(always)]
-	pub(crate) const fn with(window: Window, x: i32, y: i32, width: u32, height: u32) -> Self {
+	pub(crate) fn with(window: Window, x: i32, y: i32, width: u32, height: u32) -> Self {
 		Self {
+			id: Identity::next(),
+
 			window,
 			window_changed: false,
+			visibility: Visibility::Visible,
+
+			sizing: Sizing::default(),
+			new_sizing: None,
+
+			increments: None,
+			increments_changed: false,
+			slack: 0,
+
+			min_size: None,
+			border_width: 0,
 
 			x,
 			y,
@@ -402,21 +581,76 @@ impl<Window> WindowNode<Window> {
 	}
 
 	/// Sets the window node's window to the given `window`.
+	///
+	/// The new window has no [resize increments] or [minimum size] until they are [set].
+	///
+	/// [resize increments]: Increments
+	/// [minimum size]: Self::minimum_size
+	/// [set]: Self::set_increments
 	#[inline]
 	pub fn set_window(&mut self, window: Window) {
 		self.window_changed = true;
+		// The new window's visibility has not been reported yet.
+		self.visibility = Visibility::Visible;
+		self.set_increments(None);
+		self.set_minimum_size(None, 0);
 
 		self.window = window;
 	}
 
 	/// Replaces the window node's window with the given `window`, returning the previous one.
+	///
+	/// The new window has no [resize increments] or [minimum size] until they are [set].
+	///
+	/// [resize increments]: Increments
+	/// [minimum size]: Self::minimum_size
+	/// [set]: Self::set_increments
 	#[inline]
 	pub fn replace_window(&mut self, window: Window) -> Window {
 		self.window_changed = true;
+		// The new window's visibility has not been reported yet.
+		self.visibility = Visibility::Visible;
+		self.set_increments(None);
+		self.set_minimum_size(None, 0);
 
 		mem::replace(&mut self.window, window)
 	}
 
+	/// Swaps the window node's window with that of the `other` window node.
+	///
+	/// Both nodes keep their coordinates, dimensions, and [sizing]; only their windows are
+	/// exchanged. Each window keeps the [visibility] last reported for it, so that it is shown or
+	/// hidden as its new node requires when changes are next applied, and its
+	/// [resize increments] and [minimum size].
+	///
+	/// [sizing]: Sizing
+	/// [visibility]: Visibility
+	/// [resize increments]: Increments
+	/// [minimum size]: Self::minimum_size
+	#[inline]
+	pub fn swap_windows(&mut self, other: &mut WindowNode<Window>) {
+		self.window_changed = true;
+		other.window_changed = true;
+
+		mem::swap(&mut self.window, &mut other.window);
+		mem::swap(&mut self.visibility, &mut other.visibility);
+
+		let (increments, other_increments) = (self.increments, other.increments);
+		self.set_increments(other_increments);
+		other.set_increments(increments);
+
+		mem::swap(&mut self.min_size, &mut other.min_size);
+		mem::swap(&mut self.border_width, &mut other.border_width);
+	}
+
+	/// Returns the window's [visibility] as of the last time changes were applied.
+	///
+	/// [visibility]: Visibility
+	#[inline(always)]
+	pub const fn visibility(&self) -> Visibility {
+		self.visibility
+	}
+
 	/// Returns the window node's window.
 	#[inline(always)]
 	pub fn into_window(self) -> Window {
@@ -459,14 +693,18 @@ impl<Window> GroupNode<Window> {
 	///
 	/// It is useful to create a group with no size if that size is intended to be filled in later.
 	#[inline(always)]
-	pub(crate) const fn new(orientation: Orientation) -> Self {
+	pub(crate) fn new(orientation: Orientation) -> Self {
 		Self::with(orientation, 0, 0, 0, 0)
 	}
 
 	/// Creates an empty group of the given `orientation` and dimensions.
 	#[inline]
-	pub(crate) const fn with(orientation: Orientation, x: i32, y: i32, width: u32, height: u32) -> Self {
+	pub(crate) fn with(orientation: Orientation, x: i32, y: i32, width: u32, height: u32) -> Self {
 		Self {
+			id: Identity::next(),
+			nesting: Nesting::default(),
+			tally: Tally::EMPTY,
+
 			orientation,
 
 			children: VecDeque::new(),
@@ -474,9 +712,29 @@ impl<Window> GroupNode<Window> {
 
 			additions: VecDeque::new(),
 			total_removed_primary: 0,
+			addition_sizing: None,
 
 			new_orientation: None,
 
+			gap: None,
+			new_gap: None,
+			applied_gap: 0,
+
+			stacked: false,
+			new_stacked: None,
+			active: None,
+			new_active: None,
+
+			dissolving: false,
+			keep_alive: false,
+			manual: false,
+			maximize: Maximize::None,
+
+			sizing: Sizing::default(),
+			new_sizing: None,
+			slack: 0,
+			equalize: false,
+
 			new_x: None,
 			new_y: None,
 
@@ -572,6 +830,12 @@ impl<Window> GroupNode<Window> {
 		}
 	}
 
+	/// Returns the coordinates and dimensions of the group, as of when changes were last applied.
+	#[inline]
+	pub const fn bounds(&self) -> (i32, i32, u32, u32) {
+		(self.x, self.y, self.width, self.height)
+	}
+
 	/// Returns a mutable reference to the [node] at the given `index`, or [`None`] if the `index`
 	/// is out of bounds.
 	///
@@ -584,6 +848,8 @@ impl<Window> GroupNode<Window> {
 				let last = self.children.len() - 1;
 				last - index
 			};
+			// The node may be changed without the group knowing.
+			self.invalidate_tally();
 
 			Some(&mut self.children[index])
 		} else {
@@ -667,6 +933,9 @@ impl<Window> Index<usize> for GroupNode<Window> {
 
 impl<Window> IndexMut<usize> for GroupNode<Window> {
 	fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+		// The node may be changed without the group knowing.
+		self.invalidate_tally();
+
 		if !self.orientation().reversed() {
 			&mut self.children[index]
 		} else {
@@ -730,14 +999,7 @@ impl Orientation {
 	/// A positive number of rotations will rotate the orientation clockwise, while a negative
 	/// number of rotations will rotate the orientation counter-clockwise.
 	pub fn rotated_by(&self, rotations: i32) -> Self {
-		let current = match self {
-			Orientation::LeftToRight => 0,
-			Orientation::TopToBottom => 1,
-			Orientation::RightToLeft => 2,
-			Orientation::BottomToTop => 3,
-		};
-
-		match (current + rotations).rem_euclid(4) {
+		match (self.quarter_turns() + rotations).rem_euclid(4) {
 			0 => Orientation::LeftToRight,
 			1 => Orientation::TopToBottom,
 			2 => Orientation::RightToLeft,
@@ -746,6 +1008,47 @@ impl Orientation {
 			_ => unreachable!(".rem_euclid(4) returns a value within 0..4"),
 		}
 	}
+
+	/// Returns the fewest rotations by which this orientation must be [rotated] to become the
+	/// `other` orientation.
+	///
+	/// Opposite orientations are two clockwise rotations apart.
+	///
+	/// [rotated]: Self::rotated_by
+	pub const fn rotations_to(&self, other: &Self) -> i32 {
+		match (other.quarter_turns() - self.quarter_turns()).rem_euclid(4) {
+			3 => -1,
+			rotations => rotations,
+		}
+	}
+
+	/// Returns the number of clockwise rotations from [`LeftToRight`] to this orientation.
+	///
+	/// [`LeftToRight`]: Orientation::LeftToRight
+	const fn quarter_turns(&self) -> i32 {
+		match self {
+			Orientation::LeftToRight => 0,
+			Orientation::TopToBottom => 1,
+			Orientation::RightToLeft => 2,
+			Orientation::BottomToTop => 3,
+		}
+	}
+}
+
+impl FromStr for Orientation {
+	type Err = ParseOrientationError;
+
+	/// Parses `left-to-right`, `top-to-bottom`, `right-to-left`, or `bottom-to-top`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim() {
+			"left-to-right" => Ok(Self::LeftToRight),
+			"top-to-bottom" => Ok(Self::TopToBottom),
+			"right-to-left" => Ok(Self::RightToLeft),
+			"bottom-to-top" => Ok(Self::BottomToTop),
+
+			other => Err(ParseOrientationError(other.to_owned())),
+		}
+	}
 }
 
 impl Axis {
@@ -0,0 +1,354 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use truncate_integer::Shrink;
+
+use super::{node_changes::distribute_remainder, *};
+
+impl Compaction {
+	/// Returns whether compacting the layout did nothing, because it was healthy.
+	#[inline(always)]
+	pub const fn is_empty(&self) -> bool {
+		self.pruned == 0 && self.resized == 0
+	}
+}
+
+impl<Window> TilingLayout<Window> {
+	/// Repairs sizes which have drifted within the layout, e.g. through rounding, clamping, or
+	/// failed applies, so that every group's children fill it exactly again.
+	///
+	/// Empty groups and chains of groups are [pruned], and the children of every group which has
+	/// no changes pending are given the sizes they take up on screen, so long as they tile the
+	/// group without overlapping. Otherwise, they are rescaled from their current proportions to
+	/// fill the group exactly, with [fixed] nodes keeping their sizes and proportional nodes with
+	/// no size at all given a fresh share. [Manual] groups, and everything within them, are left as
+	/// they are. The layout's statistics are counted again.
+	///
+	/// Only the nodes whose sizes or positions actually change are reconfigured the next time
+	/// changes are [applied], so compacting a healthy layout does nothing.
+	///
+	/// [pruned]: GroupNode::prune_empty
+	/// [fixed]: Sizing::Fixed
+	/// [Manual]: GroupNode::is_manual
+	/// [applied]: TilingLayout::apply_changes
+	pub fn compact(&mut self) -> Compaction {
+		let pruned = self.root.prune_empty();
+		let resized = self.root.compact();
+		self.root.recount();
+
+		Compaction { pruned, resized }
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Repairs the sizes of the children of this group and every group within it, as with
+	/// [`TilingLayout::compact`], returning the number of nodes resized.
+	///
+	/// Every tally is marked as stale, so that they are all counted again.
+	fn compact(&mut self) -> usize {
+		let mut resized = 0;
+
+		// Groups are compacted with an explicit stack, rather than recursively, so that deeply
+		// nested layouts can't overflow the stack. Groups are compacted before their children, so
+		// that children which are resized are laid out again rather than compacted.
+		let mut groups = vec![self];
+
+		while let Some(group) = groups.pop() {
+			group.invalidate_tally();
+
+			// Manual groups, and everything within them, are left as they were arranged.
+			if group.manual {
+				continue;
+			}
+			if !group.changes_made() && !group.is_stacked() {
+				resized += group.compact_children();
+			}
+
+			groups.extend(group.children.iter_mut().filter_map(|node| match node {
+				Node::Group(child) => Some(child),
+				Node::Window(_) => None,
+			}));
+		}
+
+		resized
+	}
+
+	/// Repairs the sizes of the group's children, returning the number of children resized.
+	fn compact_children(&mut self) -> usize {
+		let len = self.children.len();
+		if len == 0 {
+			return 0;
+		}
+
+		let axis = self.orientation.axis();
+		let reversed = self.orientation.reversed();
+
+		let gap = self.applied_gap;
+		let available = self
+			.primary_dimension()
+			.saturating_sub((len as u32 - 1).saturating_mul(gap));
+
+		let slots: Vec<_> = self.children.iter().map(pending_slot).collect();
+		let primaries: Vec<u32> = slots.iter().map(|&slot| primary_of(slot, axis).1).collect();
+		// The size of each child's tile, including any space left by its resize increments.
+		let tiles: Vec<u32> = self
+			.children
+			.iter()
+			.zip(&primaries)
+			.map(|(node, &primary)| (i64::from(primary) + i64::from(node.slack())).clamp(0, u32::MAX.into()) as u32)
+			.collect();
+		let proportional: Vec<bool> = self
+			.children
+			.iter()
+			.map(|node| node.sizing().weight().is_some())
+			.collect();
+
+		let total_primary: u64 = primaries.iter().map(|&primary| u64::from(primary)).sum();
+		let total_tiles: u64 = tiles.iter().map(|&tile| u64::from(tile)).sum();
+		// Proportional nodes are only expected to have a size if there is room for them all.
+		let proportional_count = proportional.iter().filter(|&&proportional| proportional).count();
+		let stale = |tiles: &[u32]| {
+			available as usize >= proportional_count
+				&& tiles
+					.iter()
+					.zip(&proportional)
+					.any(|(&tile, &proportional)| proportional && tile == 0)
+		};
+
+		if total_primary == u64::from(self.total_node_primary) && total_tiles == u64::from(available) && !stale(&tiles)
+		{
+			return 0;
+		}
+
+		// Children are laid out along the axis from the group's primary coordinate in order, or in
+		// reverse order if the group is reversed.
+		let order: Vec<usize> = match reversed {
+			false => (0..len).collect(),
+			true => (0..len).rev().collect(),
+		};
+
+		let new_tiles = match self.tiles_on_screen(&slots, &order) {
+			Some(tiles) if !stale(&tiles) => tiles,
+			_ => self.rescaled_tiles(&tiles, &proportional, available, reversed),
+		};
+
+		let mut resized = 0;
+		let mut coord = i64::from(self.primary_coord());
+
+		for index in order {
+			let tile = new_tiles[index];
+			let (old_coord, old_primary) = primary_of(slots[index], axis);
+
+			let new_coord = coord.clamp(i32::MIN.into(), i32::MAX.into()) as i32;
+			coord += i64::from(tile) + i64::from(gap);
+
+			// Children which keep their tiles keep whatever size they were given within them.
+			let primary = match tile == tiles[index] {
+				true => old_primary,
+				false => tile,
+			};
+
+			if new_coord == old_coord && primary == old_primary {
+				continue;
+			}
+
+			let (x, y, width, height) = slots[index];
+			let slot = match axis {
+				Axis::Horizontal => (new_coord, y, primary, height),
+				Axis::Vertical => (x, new_coord, width, primary),
+			};
+
+			let node = &mut self.children[index];
+			node.take_slot(slot);
+			node.set_tile(tile, primary);
+
+			resized += 1;
+		}
+
+		self.total_node_primary = self
+			.children
+			.iter()
+			.map(|node| primary_of(pending_slot(node), axis).1)
+			.fold(0, u32::saturating_add);
+
+		resized
+	}
+
+	/// Returns the tiles of the group's children taken from their positions on screen, visited in
+	/// the given `order`, or [`None`] if they don't tile the group without overlapping.
+	fn tiles_on_screen(&self, slots: &[(i32, i32, u32, u32)], order: &[usize]) -> Option<Vec<u32>> {
+		let axis = self.orientation.axis();
+		let gap = i64::from(self.applied_gap);
+		let end = i64::from(self.primary_coord()) + i64::from(self.primary_dimension());
+
+		let coords: Vec<i64> = order
+			.iter()
+			.map(|&index| i64::from(primary_of(slots[index], axis).0))
+			.collect();
+		if coords.first() != Some(&i64::from(self.primary_coord())) {
+			return None;
+		}
+
+		let mut tiles = vec![0; slots.len()];
+
+		for (i, &index) in order.iter().enumerate() {
+			let next = match coords.get(i + 1) {
+				Some(&next) => next - gap,
+				None => end,
+			};
+
+			tiles[index] = u32::try_from(next - coords[i]).ok()?;
+		}
+
+		Some(tiles)
+	}
+
+	/// Returns the given `tiles` of the group's children rescaled to fill the `available` space
+	/// exactly.
+	///
+	/// [Fixed] nodes keep their tiles, [proportional] nodes with no size are given a share of the
+	/// space by their weight, and the other proportional nodes share what is left, keeping their
+	/// sizes relative to one another. Leftover pixels are shared out by the usual rule.
+	///
+	/// [Fixed]: Sizing::Fixed
+	/// [proportional]: Sizing::Proportional
+	fn rescaled_tiles(&self, tiles: &[u32], proportional: &[bool], available: u32, reversed: bool) -> Vec<u32> {
+		let mut new_tiles = tiles.to_vec();
+
+		let mut remaining = u64::from(available);
+		for (tile, _) in new_tiles
+			.iter_mut()
+			.zip(proportional)
+			.filter(|(_, &proportional)| !proportional)
+		{
+			*tile = (*tile).min(remaining.shrink());
+			remaining -= u64::from(*tile);
+		}
+
+		let weights: Vec<u64> = self
+			.children
+			.iter()
+			.map(|node| node.sizing().weight().map_or(0, u64::from))
+			.collect();
+		let total_weight: u64 = weights.iter().sum();
+
+		let sized: u64 = (0..tiles.len())
+			.filter(|&index| proportional[index])
+			.map(|index| u64::from(tiles[index]))
+			.sum();
+
+		// Nodes with no size are given a fresh share, and the rest are rescaled into what is left.
+		let mut fresh = 0;
+		for index in (0..tiles.len()).filter(|&index| proportional[index]) {
+			if tiles[index] == 0 || sized == 0 {
+				new_tiles[index] = (remaining * weights[index] / total_weight.max(1)).shrink();
+				fresh += u64::from(new_tiles[index]);
+			}
+		}
+
+		let rest = remaining.saturating_sub(fresh);
+		for index in (0..tiles.len()).filter(|&index| proportional[index] && tiles[index] != 0) {
+			if let Some(tile) = (u64::from(tiles[index]) * rest).checked_div(sized) {
+				new_tiles[index] = tile.shrink();
+			}
+		}
+
+		distribute_remainder(&mut new_tiles, proportional, available, reversed);
+
+		new_tiles
+	}
+}
+
+/// Returns the node's coordinates and dimensions, including those which are pending for a group.
+fn pending_slot<Window>(node: &Node<Window>) -> (i32, i32, u32, u32) {
+	match node {
+		Node::Window(_) => node.slot(),
+		Node::Group(group) => (
+			group.new_x.unwrap_or(group.x),
+			group.new_y.unwrap_or(group.y),
+			group.new_width.unwrap_or(group.width),
+			group.new_height.unwrap_or(group.height),
+		),
+	}
+}
+
+/// Returns the coordinate and dimension of the given slot along the given `axis`.
+const fn primary_of((x, y, width, height): (i32, i32, u32, u32), axis: Axis) -> (i32, u32) {
+	match axis {
+		Axis::Horizontal => (x, width),
+		Axis::Vertical => (y, height),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::{nested, reconfigured};
+
+	/// Tests that compacting a healthy layout does nothing.
+	#[test]
+	fn healthy() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = nested(&settings);
+
+		assert!(layout.compact().is_empty());
+		assert_eq!(reconfigured(&mut layout, &settings), []);
+	}
+
+	/// Tests that a node whose size has drifted from the space it takes up on screen is given that
+	/// space again, without any other nodes changing.
+	#[test]
+	fn drifted() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = nested(&settings);
+
+		layout[1].unwrap_group_mut().children[0].unwrap_window_mut().height -= 10;
+		assert!(layout.validate().is_err());
+
+		assert_eq!(layout.compact(), Compaction { pruned: 0, resized: 1 });
+		assert_eq!(layout.validate(), Ok(()));
+		assert_eq!(reconfigured(&mut layout, &settings), [(2, 600, 0, 600, 300)]);
+
+		// Once repaired, there is nothing left to do.
+		assert!(layout.compact().is_empty());
+	}
+
+	/// Tests that nodes which overlap are rescaled to fill their group from their proportions.
+	#[test]
+	fn overlapping() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = nested(&settings);
+
+		let window = layout[0].unwrap_window_mut();
+		window.x = 5;
+		window.width = 590;
+
+		assert_eq!(layout.compact(), Compaction { pruned: 0, resized: 2 });
+		assert_eq!(layout.validate(), Ok(()));
+		assert_eq!(
+			reconfigured(&mut layout, &settings),
+			[(1, 0, 0, 595, 600), (2, 595, 0, 605, 300), (3, 595, 300, 605, 300)]
+		);
+	}
+
+	/// Tests that proportional nodes with no size at all are given a share of their group.
+	#[test]
+	fn zero_sized() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = nested(&settings);
+
+		let group = layout[1].unwrap_group_mut();
+		group.children[0].unwrap_window_mut().height = 0;
+		group.children[1].unwrap_window_mut().y = 0;
+		group.children[1].unwrap_window_mut().height = 600;
+		group.total_node_primary = 600;
+
+		assert_eq!(layout.compact(), Compaction { pruned: 0, resized: 2 });
+		assert_eq!(layout.validate(), Ok(()));
+		assert_eq!(
+			reconfigured(&mut layout, &settings),
+			[(2, 600, 0, 600, 300), (3, 600, 300, 600, 300)]
+		);
+	}
+}
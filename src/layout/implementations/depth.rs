@@ -0,0 +1,430 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl Nesting {
+	/// Returns the nesting of the root group of a layout with the given `settings`.
+	pub(super) const fn root(settings: &LayoutSettings) -> Self {
+		Self {
+			depth: 0,
+
+			// Groups can always be added to the root group.
+			max_depth: if settings.max_depth == 0 { 1 } else { settings.max_depth },
+			policy: settings.depth_policy,
+
+			active_on_removal: settings.active_on_removal,
+		}
+	}
+
+	/// Returns the nesting of a group's children.
+	pub(super) const fn child(&self) -> Self {
+		Self {
+			depth: self.depth.saturating_add(1),
+
+			..*self
+		}
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Returns the group's depth in its [layout] tree.
+	///
+	/// The root group is at depth `0`, and each group nested within it is one deeper than its
+	/// parent.
+	///
+	/// [layout]: TilingLayout
+	#[inline(always)]
+	pub const fn depth(&self) -> usize {
+		self.nesting.depth
+	}
+
+	/// Returns whether a group can be nested within this group without exceeding the
+	/// [maximum depth].
+	///
+	/// Groups which would be nested any deeper are [flattened or rejected].
+	///
+	/// [maximum depth]: LayoutSettings::max_depth
+	/// [flattened or rejected]: DepthPolicy
+	#[inline(always)]
+	pub const fn can_nest_group(&self) -> bool {
+		self.nesting.child().depth <= self.nesting.max_depth
+	}
+
+	/// Sets the nesting of the group to the given `nesting`, and that of every group within it
+	/// accordingly.
+	pub(super) fn set_nesting(&mut self, nesting: Nesting) {
+		self.nesting = nesting;
+
+		let mut groups = vec![self];
+
+		while let Some(group) = groups.pop() {
+			let nesting = group.nesting.child();
+
+			for node in &mut group.children {
+				if let Node::Group(child) = node {
+					child.nesting = nesting;
+
+					groups.push(child);
+				}
+			}
+		}
+	}
+
+	/// Returns how many levels of groups are nested within the group: `0` if it contains no
+	/// groups.
+	pub(super) fn nested_levels(&self) -> usize {
+		let mut levels = 0;
+		let mut groups = vec![(self, 0)];
+
+		while let Some((group, level)) = groups.pop() {
+			levels = levels.max(level);
+
+			for node in &group.children {
+				if let Node::Group(child) = node {
+					groups.push((child, level + 1));
+				}
+			}
+		}
+
+		levels
+	}
+
+	/// Returns whether a group with the given number of `levels` of groups nested within it must be
+	/// flattened if it is nested within this group.
+	///
+	/// # Errors
+	/// Returns a [`DepthError`] if it would be nested too deeply and that is [rejected].
+	///
+	/// [rejected]: DepthPolicy::Reject
+	pub(super) const fn check_nesting(&self, levels: usize) -> Result<bool, DepthError> {
+		let Nesting { max_depth, policy, .. } = self.nesting;
+		let depth = self.nesting.child().depth.saturating_add(levels);
+
+		if depth <= max_depth {
+			return Ok(false);
+		}
+
+		match policy {
+			DepthPolicy::Flatten => Ok(true),
+			DepthPolicy::Reject => Err(DepthError { depth, max_depth }),
+		}
+	}
+
+	/// Returns a new child group of the given `orientation`, nested within this group.
+	fn new_child_group(&self, orientation: Orientation) -> GroupNode<Window> {
+		let mut group = GroupNode::new(orientation);
+		group.nesting = self.nesting.child();
+
+		group
+	}
+
+	/// Adds a new group of the given `orientation` to this group with `add`, then initialises it
+	/// with the given `init` function.
+	///
+	/// If the new group would be nested too deeply and is [flattened], it is initialised on its
+	/// own and its children are added with `add` in its place - in reverse order if `reverse` is
+	/// true, for `add` functions which add each node before the last.
+	///
+	/// [flattened]: DepthPolicy::Flatten
+	pub(super) fn add_group_with(
+		&mut self,
+		orientation: Orientation,
+		init: impl FnOnce(&mut GroupNode<Window>),
+		mut add: impl FnMut(&mut Self, Node<Window>) -> usize,
+		reverse: bool,
+	) -> Result<(), DepthError> {
+		let mut group = self.new_child_group(orientation);
+
+		if !self.check_nesting(0)? {
+			let index = add(self, Node::Group(group));
+
+			match &mut self.children[index] {
+				Node::Group(group) => init(group),
+				Node::Window(_) => unreachable!("we know this node is a group, because we just added it"),
+			}
+			// The group was counted while it was still empty.
+			self.count_changed(index, Tally::EMPTY);
+
+			return Ok(());
+		}
+
+		// Any groups nested within the flattened group are flattened into it in turn, so it only
+		// contains windows.
+		init(&mut group);
+
+		let nodes: Vec<_> = group.into_iter().collect();
+		if reverse {
+			for node in nodes.into_iter().rev() {
+				add(self, node);
+			}
+		} else {
+			for node in nodes {
+				add(self, node);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Adds new empty groups of the given `orientations` to this group with `add`.
+	///
+	/// Empty groups which would be nested too deeply and are [flattened] leave nothing in their
+	/// place.
+	///
+	/// [flattened]: DepthPolicy::Flatten
+	pub(super) fn add_groups(
+		&mut self,
+		orientations: impl IntoIterator<Item = Orientation>,
+		add: impl FnOnce(&mut Self, Vec<Node<Window>>),
+	) -> Result<(), DepthError> {
+		if self.check_nesting(0)? {
+			return Ok(());
+		}
+
+		let groups = orientations
+			.into_iter()
+			.map(|orientation| Node::Group(self.new_child_group(orientation)))
+			.collect();
+		add(self, groups);
+
+		Ok(())
+	}
+
+	/// Flattens every group within this group which is nested deeper than the
+	/// [maximum depth] into its ancestor at the maximum depth, returning whether any were.
+	///
+	/// [maximum depth]: LayoutSettings::max_depth
+	pub(super) fn flatten_too_deep(&mut self) -> bool {
+		let mut flattened = false;
+		let mut groups = vec![self];
+
+		while let Some(group) = groups.pop() {
+			if !group.can_nest_group() {
+				flattened |= group.flatten();
+
+				continue;
+			}
+
+			// Flattening a group changes the tallies of every group it is within.
+			group.invalidate_tally();
+
+			for node in &mut group.children {
+				if let Node::Group(child) = node {
+					groups.push(child);
+				}
+			}
+		}
+
+		flattened
+	}
+
+	/// Replaces every group within this group with the windows within it, keeping their order,
+	/// returning whether there were any.
+	///
+	/// The group's windows are given their sizes afresh when changes are next applied.
+	fn flatten(&mut self) -> bool {
+		if !self.children.iter().any(Node::is_group) {
+			return false;
+		}
+		let reversed = self.orientation.reversed();
+
+		// Nodes still to be flattened, with the next node in the group's order last.
+		let children = mem::take(&mut self.children);
+		let mut nodes: Vec<_> = match reversed {
+			false => children.into_iter().rev().collect(),
+			true => children.into_iter().collect(),
+		};
+		let mut windows = VecDeque::with_capacity(nodes.len());
+
+		while let Some(node) = nodes.pop() {
+			match node {
+				Node::Window(_) => windows.push_back(node),
+				Node::Group(group) => nodes.extend(group.into_iter().rev()),
+			}
+		}
+
+		if reversed {
+			windows.make_contiguous().reverse();
+		}
+		self.tally = Tally {
+			windows: windows.len(),
+			..Tally::EMPTY
+		};
+		self.children = windows;
+
+		self.additions.clear();
+		self.total_removed_primary = 0;
+		self.total_node_primary = 0;
+		self.equalize = true;
+
+		// Make sure the group lays out its new children, even if its size hasn't changed.
+		self.new_width.get_or_insert(self.width);
+		self.new_height.get_or_insert(self.height);
+
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	fn layout(max_depth: usize, depth_policy: DepthPolicy) -> (TilingLayout<u32>, LayoutSettings) {
+		let settings = LayoutSettings::new()
+			.window_gap(0)
+			.max_depth(max_depth)
+			.depth_policy(depth_policy);
+
+		let layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 1000, &settings);
+
+		(layout, settings)
+	}
+
+	/// Returns the innermost group along the first group child of each group.
+	fn innermost(group: &GroupNode<u32>) -> &GroupNode<u32> {
+		let mut group = group;
+
+		while let Some(Node::Group(child)) = group.iter().find(|node| node.is_group()) {
+			group = child;
+		}
+
+		group
+	}
+
+	/// Splits the innermost group's last window with a new group `count` times, as a layout
+	/// manager splitting in a loop would.
+	fn split_repeatedly(layout: &mut TilingLayout<u32>, count: u32) -> Result<(), DepthError> {
+		layout.push_window_back(0);
+
+		let mut group: &mut GroupNode<u32> = layout;
+		for window in 1..=count {
+			let orientation = group.orientation().rotated_by(1);
+			group.insert_group_splitting_with(group.len() - 1, orientation, |group| group.push_window_back(window))?;
+
+			if let Some(index) = group.iter().position(Node::is_group) {
+				group = group[index].unwrap_group_mut();
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Tests that groups nested beyond the maximum depth are flattened, keeping every window.
+	#[test]
+	fn flatten() {
+		let (mut layout, settings) = layout(3, DepthPolicy::Flatten);
+
+		split_repeatedly(&mut layout, 10).unwrap();
+
+		let innermost = innermost(&layout);
+		assert_eq!(innermost.depth(), 3);
+		assert!(!innermost.can_nest_group());
+		assert_eq!(innermost.len(), 8);
+
+		let mut windows: Vec<u32> = layout.windows().into_iter().copied().collect();
+		windows.sort_unstable();
+		assert_eq!(windows, (0..=10).collect::<Vec<_>>());
+
+		apply(&mut layout, &settings);
+		assert_eq!(layout.validate(), Ok(()));
+	}
+
+	/// Tests that groups nested beyond the maximum depth are rejected, leaving the layout as it
+	/// was.
+	#[test]
+	fn reject() {
+		let (mut layout, _) = layout(3, DepthPolicy::Reject);
+
+		assert_eq!(
+			split_repeatedly(&mut layout, 10),
+			Err(DepthError { depth: 4, max_depth: 3 })
+		);
+		assert_eq!(innermost(&layout).depth(), 3);
+		assert_eq!(layout.windows().len(), 4);
+
+		// A group with groups nested within it can't be moved anywhere they would be too deep.
+		let nested = layout[1].id();
+		let innermost = innermost(&layout).id();
+		layout.push_group_back(Orientation::LeftToRight).unwrap();
+		let group = layout[2].id();
+
+		assert_eq!(
+			layout.transfer_by_id(nested, group, 0),
+			Err(DepthError { depth: 4, max_depth: 3 })
+		);
+		assert_eq!(layout.transfer_by_id(innermost, group, 0), Ok(true));
+	}
+
+	/// Tests that transferring a group deeper flattens whatever ends up beyond the maximum depth.
+	#[test]
+	fn transfer_flattens() {
+		let (mut layout, settings) = layout(3, DepthPolicy::Flatten);
+
+		split_repeatedly(&mut layout, 3).unwrap();
+		layout.push_group_back(Orientation::LeftToRight).unwrap();
+
+		let nested = layout[1].id();
+		let group = layout[2].id();
+		assert_eq!(layout.transfer_by_id(nested, group, 0), Ok(true));
+
+		let innermost = innermost(&layout);
+		assert_eq!(innermost.depth(), 3);
+		assert_eq!(innermost.windows().len(), 2);
+		assert_eq!(layout.windows().len(), 4);
+
+		apply(&mut layout, &settings);
+		assert_eq!(layout.validate(), Ok(()));
+	}
+
+	/// Tests that a layout split in a loop ten thousand times neither overflows the stack nor
+	/// hangs, whether it is flattened or, with no maximum depth, really nested that deeply.
+	#[test]
+	fn adversarial_depth() {
+		const SPLITS: u32 = 10_000;
+
+		let count_reconfigured = |layout: &mut TilingLayout<u32>, settings: &LayoutSettings| {
+			let mut count = 0;
+
+			layout
+				.apply_changes(
+					&mut |_, _, _, _, _, _| {
+						count += 1;
+
+						Ok::<_, ()>(())
+					},
+					settings,
+				)
+				.unwrap();
+
+			count
+		};
+
+		let (mut flattened, settings) = layout(LayoutSettings::default().max_depth, DepthPolicy::Flatten);
+		split_repeatedly(&mut flattened, SPLITS).unwrap();
+
+		assert_eq!(innermost(&flattened).depth(), 64);
+		assert_eq!(flattened.windows().len(), SPLITS as usize + 1);
+
+		// The layout is too small to fit ten thousand windows, so the changes are deferred.
+		assert_eq!(count_reconfigured(&mut flattened, &settings), 0);
+
+		flattened.resize(0, 0, 100_000, 100_000, &settings);
+		assert_eq!(count_reconfigured(&mut flattened, &settings), SPLITS + 1);
+
+		let (mut nested, settings) = layout(usize::MAX, DepthPolicy::Reject);
+		split_repeatedly(&mut nested, SPLITS).unwrap();
+
+		assert_eq!(innermost(&nested).depth(), SPLITS as usize);
+		assert_eq!(nested.windows().len(), SPLITS as usize + 1);
+
+		assert_eq!(count_reconfigured(&mut nested, &settings), 0);
+		assert!(nested.is_deferred());
+
+		nested.resize(0, 0, 100_000, 100_000, &settings);
+		assert_eq!(count_reconfigured(&mut nested, &settings), SPLITS + 1);
+		assert_eq!(nested.validate(), Ok(()));
+	}
+}
@@ -0,0 +1,847 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fmt::Write as _;
+
+use super::*;
+
+/// A group in a parsed description.
+#[derive(Debug, PartialEq, Clone)]
+struct Group {
+	orientation: Orientation,
+	children: Vec<Child>,
+}
+
+/// A child of a [`Group`] in a parsed description.
+#[derive(Debug, PartialEq, Clone)]
+struct Child {
+	/// The position of the child in the description.
+	position: usize,
+	/// The child's weight, as a fraction of its group, if it was given one.
+	weight: Option<f64>,
+	described: Described,
+}
+
+/// A [`Child`] in a parsed description: either a window's name or a nested group.
+#[derive(Debug, PartialEq, Clone)]
+enum Described {
+	Window(String),
+	Group(Group),
+}
+
+/// A recursive-descent parser of descriptions.
+struct Parser<'description> {
+	description: &'description str,
+	/// The byte offset of the next character in the `description`.
+	position: usize,
+}
+
+impl<Window> TilingLayout<Window> {
+	/// Creates a layout with the tree given by its textual `description`, like
+	/// `h(60% v(a b) 40% c)`, resolving the names of its windows with `resolve`.
+	///
+	/// The layout has no size until it is [resized], after which the first [applied changes] give
+	/// each node its weight of its group. Empty groups nested in the layout are left out of it.
+	///
+	/// # Notation
+	/// ```text
+	/// description := group
+	/// group       := orientation "(" child* ")"
+	/// orientation := "h" | "v" | "hr" | "vr"
+	/// child       := weight? (group | name)
+	/// weight      := number "%" | number | number "/" number
+	/// name        := bare | '"' (character | '\"' | '\\')* '"'
+	/// ```
+	///
+	/// Children are separated by whitespace, which is allowed anywhere but within a weight or a
+	/// bare name, or between an orientation and its `(`.
+	///
+	/// The orientations `h`, `v`, `hr`, and `vr` are [left to right], [top to bottom], [right to
+	/// left], and [bottom to top]. A reversed group's children are still listed in the order they
+	/// appear in on screen. A bare name is any run of characters other than whitespace, `(`, `)`,
+	/// `"`, and `\`, which doesn't start with a digit or `.`, as those start a weight.
+	///
+	/// Weights are fractions of their group: `60%`, `0.6`, and `3/5` are all the same weight.
+	/// Children without one equally share whatever the weighted children leave of their group, or
+	/// are given the weighted children's average weight if they leave nothing. Weights are
+	/// relative, so they needn't add up to the whole group.
+	///
+	/// # Errors
+	/// Returns a [`ParseDescriptionError`] giving the position of the error in the description if
+	/// it isn't valid, or if `resolve` returns [`None`] for one of its names.
+	///
+	/// [resized]: Self::resize
+	/// [applied changes]: Self::apply_changes
+	/// [left to right]: Orientation::LeftToRight
+	/// [top to bottom]: Orientation::TopToBottom
+	/// [right to left]: Orientation::RightToLeft
+	/// [bottom to top]: Orientation::BottomToTop
+	pub fn from_description(
+		description: &str,
+		mut resolve: impl FnMut(&str) -> Option<Window>,
+	) -> Result<Self, ParseDescriptionError> {
+		let group = Parser::new(description).parse()?;
+
+		let mut windows = VecDeque::new();
+		let snapshot = group.snapshot(&mut resolve, &mut windows)?;
+
+		let mut layout = Self::new(snapshot.orientation, 0, 0, 0, 0, &LayoutSettings::default());
+		layout.root.fill(&snapshot, &mut windows);
+
+		Ok(layout)
+	}
+
+	/// Returns the canonical [description] of the layout's tree, naming its windows with `name`.
+	///
+	/// Each child's weight is its share of its group, rounded to a whole percentage so that the
+	/// group's children add up to `100%`, and left out if the group's children share it equally.
+	///
+	/// [description]: Self::from_description
+	pub fn to_description(&self, mut name: impl FnMut(&Window) -> String) -> String {
+		let mut names = self.windows().into_iter().map(&mut name);
+
+		let mut description = String::new();
+		describe(&self.snapshot(), &mut names, &mut description);
+
+		description
+	}
+}
+
+impl<Window> TilingLayout<Slot<Window>> {
+	/// Creates a layout from its textual `description`, as with [`from_description`], except
+	/// that names which `resolve` can't resolve become [placeholders].
+	///
+	/// The placeholders are meant to be [filled] by the next windows which are mapped, e.g. when
+	/// a layout is described by a name for each window, not all of which exist yet.
+	///
+	/// [`from_description`]: Self::from_description
+	/// [placeholders]: Slot::Placeholder
+	/// [filled]: Self::fill_placeholder
+	pub fn from_description_with_placeholders(
+		description: &str,
+		mut resolve: impl FnMut(&str) -> Option<Window>,
+	) -> Result<Self, ParseDescriptionError> {
+		Self::from_description(description, |name| {
+			Some(resolve(name).map_or(Slot::Placeholder, Slot::Window))
+		})
+	}
+
+	/// Returns the number of [placeholders] left in the layout.
+	///
+	/// [placeholders]: Slot::Placeholder
+	pub fn placeholders(&self) -> usize {
+		self.windows()
+			.into_iter()
+			.filter(|slot| matches!(slot, Slot::Placeholder))
+			.count()
+	}
+
+	/// Fills the first [placeholder] left in the layout, in the order windows are [iterated] in,
+	/// with the given `window`.
+	///
+	/// # Errors
+	/// Returns the `window` if there are no placeholders left.
+	///
+	/// [placeholder]: Slot::Placeholder
+	/// [iterated]: GroupNode::windows
+	pub fn fill_placeholder(&mut self, window: Window) -> Result<(), Window> {
+		match self.root.first_placeholder() {
+			Some(node) => {
+				node.set_window(Slot::Window(window));
+
+				Ok(())
+			},
+
+			None => Err(window),
+		}
+	}
+}
+
+impl<Window> GroupNode<Slot<Window>> {
+	/// Returns the first window node within the group which is a [placeholder], if any.
+	///
+	/// [placeholder]: Slot::Placeholder
+	fn first_placeholder(&mut self) -> Option<&mut WindowNode<Slot<Window>>> {
+		self.iter_mut().find_map(|node| match node {
+			Node::Window(node) => matches!(node.window(), Slot::Placeholder).then_some(node),
+			Node::Group(group) => group.first_placeholder(),
+		})
+	}
+}
+
+impl Group {
+	/// Converts the group to a [snapshot] of its shape, resolving its windows' names with
+	/// `resolve` and adding them to `windows` in the order their slots are filled.
+	///
+	/// [snapshot]: LayoutSnapshot
+	fn snapshot<Window>(
+		self,
+		resolve: &mut impl FnMut(&str) -> Option<Window>,
+		windows: &mut VecDeque<WindowNode<Window>>,
+	) -> Result<LayoutSnapshot, ParseDescriptionError> {
+		let weights = weights(&self.children);
+
+		let children = self
+			.children
+			.into_iter()
+			.zip(weights)
+			.map(|(child, weight)| {
+				let shape = match child.described {
+					Described::Window(name) => match resolve(&name) {
+						Some(window) => {
+							windows.push_back(WindowNode::new(window));
+
+							Shape::Window
+						},
+
+						None => {
+							return Err(ParseDescriptionError::Unresolved {
+								position: child.position,
+								name,
+							})
+						},
+					},
+
+					Described::Group(group) => Shape::Group(group.snapshot(resolve, windows)?),
+				};
+
+				Ok(SnapshotNode { weight, shape })
+			})
+			.collect::<Result<_, _>>()?;
+
+		Ok(LayoutSnapshot {
+			orientation: self.orientation,
+			children,
+		})
+	}
+}
+
+/// Returns the weight of each of the `children`, filling in those which weren't given one.
+fn weights(children: &[Child]) -> Vec<f64> {
+	let given: Vec<_> = children.iter().filter_map(|child| child.weight).collect();
+	let total: f64 = given.iter().sum();
+
+	let unweighted = children.len() - given.len();
+	let share = match (1.0 - total, given.len()) {
+		// Share whatever is left of the group equally.
+		(rest, _) if rest > 0.0 => rest / (unweighted.max(1) as f64),
+
+		(_, 0) => 1.0,
+		(_, weighted) => total / (weighted as f64),
+	};
+
+	children.iter().map(|child| child.weight.unwrap_or(share)).collect()
+}
+
+/// Writes the canonical description of the group of the given `snapshot` to `description`, taking
+/// the names of its windows from `names`.
+fn describe(snapshot: &LayoutSnapshot, names: &mut impl Iterator<Item = String>, description: &mut String) {
+	description.push_str(match snapshot.orientation {
+		Orientation::LeftToRight => "h",
+		Orientation::TopToBottom => "v",
+		Orientation::RightToLeft => "hr",
+		Orientation::BottomToTop => "vr",
+	});
+	description.push('(');
+
+	let weights: Vec<_> = snapshot.children.iter().map(|child| child.weight).collect();
+	let (percents, weighted) = percents(&weights);
+
+	for (index, (child, percent)) in snapshot.children.iter().zip(percents).enumerate() {
+		if index > 0 {
+			description.push(' ');
+		}
+		if weighted {
+			let _ = write!(description, "{percent}% ");
+		}
+
+		match &child.shape {
+			Shape::Window => write_name(&names.next().unwrap_or_default(), description),
+			Shape::Group(group) => describe(group, names, description),
+		}
+	}
+
+	description.push(')');
+}
+
+/// Rounds the given `weights` to whole percentages of their total which add up to `100`, each at
+/// least `1`, returning them and whether they differ from an equal share by at least half a
+/// percent.
+fn percents(weights: &[f64]) -> (Vec<u32>, bool) {
+	let total: f64 = weights.iter().sum();
+	if weights.is_empty() || total <= 0.0 {
+		return (vec![1; weights.len()], false);
+	}
+
+	let exact: Vec<_> = weights.iter().map(|weight| weight / total * 100.0).collect();
+	let equal = 100.0 / (weights.len() as f64);
+	let weighted = exact.iter().any(|percent| (percent - equal).abs() >= 0.5);
+
+	// Percentages are rounded down, and what's left is given to those which lost the most.
+	let mut percents: Vec<_> = exact.iter().map(|percent| percent.floor() as u32).collect();
+	let mut order: Vec<_> = (0..exact.len()).collect();
+	order.sort_by(|&a, &b| (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor())));
+
+	let left = 100_u32.saturating_sub(percents.iter().sum());
+	for &index in order.iter().cycle().take(left as usize) {
+		percents[index] += 1;
+	}
+
+	// A weight of `0%` isn't valid, so shares that small are taken from the largest.
+	while let Some(index) = percents.iter().position(|&percent| percent == 0) {
+		let largest = (0..percents.len())
+			.max_by_key(|&index| percents[index])
+			.unwrap_or(index);
+		if percents[largest] <= 1 {
+			break;
+		}
+
+		percents[largest] -= 1;
+		percents[index] = 1;
+	}
+
+	(percents, weighted)
+}
+
+/// Writes the given window `name` to `description`, quoting it if it can't be written bare.
+fn write_name(name: &str, description: &mut String) {
+	let bare =
+		name.chars().next().is_some_and(|first| !starts_weight(first)) && name.chars().all(|char| !ends_name(char));
+
+	if bare {
+		description.push_str(name);
+		return;
+	}
+
+	description.push('"');
+	for char in name.chars() {
+		if matches!(char, '"' | '\\') {
+			description.push('\\');
+		}
+		description.push(char);
+	}
+	description.push('"');
+}
+
+/// Returns whether the given character starts a weight.
+const fn starts_weight(char: char) -> bool {
+	char.is_ascii_digit() || char == '.'
+}
+
+/// Returns whether the given character ends a bare name.
+const fn ends_name(char: char) -> bool {
+	char.is_whitespace() || matches!(char, '(' | ')' | '"' | '\\')
+}
+
+impl<'description> Parser<'description> {
+	const fn new(description: &'description str) -> Self {
+		Self {
+			description,
+			position: 0,
+		}
+	}
+
+	/// Parses the whole description.
+	fn parse(mut self) -> Result<Group, ParseDescriptionError> {
+		self.skip_whitespace();
+		let group = self.group()?;
+
+		self.skip_whitespace();
+		match self.peek() {
+			None => Ok(group),
+			Some(_) => self.expected("the end of the description"),
+		}
+	}
+
+	/// Parses a group, including its orientation.
+	fn group(&mut self) -> Result<Group, ParseDescriptionError> {
+		let Some(orientation) = self.orientation() else {
+			return self.expected("a group, like `h(a b)`");
+		};
+		// The orientation is only recognized if it is followed by `(`.
+		self.bump();
+
+		let mut children = Vec::new();
+
+		loop {
+			self.skip_whitespace();
+
+			match self.peek() {
+				Some(')') => {
+					self.bump();
+
+					return Ok(Group { orientation, children });
+				},
+
+				None => return self.expected("a window, a group, or `)`"),
+				Some(_) => children.push(self.child()?),
+			}
+		}
+	}
+
+	/// Parses a child of a group, with its weight if it has one.
+	fn child(&mut self) -> Result<Child, ParseDescriptionError> {
+		let position = self.position;
+
+		let weight = match self.peek() {
+			Some(char) if starts_weight(char) => {
+				let weight = self.weight()?;
+				self.skip_whitespace();
+
+				Some(weight)
+			},
+
+			_ => None,
+		};
+
+		let described = match self.peek() {
+			_ if self.peek_orientation().is_some() => Described::Group(self.group()?),
+
+			Some('"') => Described::Window(self.quoted_name()?),
+			Some(char) if !ends_name(char) && !starts_weight(char) => {
+				let start = self.position;
+				while self.peek().is_some_and(|char| !ends_name(char)) {
+					self.bump();
+				}
+
+				Described::Window(self.description[start..self.position].to_owned())
+			},
+
+			_ => return self.expected("a window or a group"),
+		};
+
+		Ok(Child {
+			position,
+			weight,
+			described,
+		})
+	}
+
+	/// Parses a weight: a percentage, a number, or a ratio.
+	fn weight(&mut self) -> Result<f64, ParseDescriptionError> {
+		let start = self.position;
+
+		let mut weight = self.number()?;
+		match self.peek() {
+			Some('%') => {
+				self.bump();
+				weight /= 100.0;
+			},
+			Some('/') => {
+				self.bump();
+				if !self.peek().is_some_and(starts_weight) {
+					return self.expected("a number");
+				}
+
+				weight /= self.number()?;
+			},
+
+			_ => (),
+		}
+
+		match weight.is_finite() && weight > 0.0 {
+			true => Ok(weight),
+			false => Err(ParseDescriptionError::Weight {
+				position: start,
+				weight: self.description[start..self.position].to_owned(),
+			}),
+		}
+	}
+
+	/// Parses a number made of digits and a decimal point.
+	fn number(&mut self) -> Result<f64, ParseDescriptionError> {
+		let start = self.position;
+		while self.peek().is_some_and(starts_weight) {
+			self.bump();
+		}
+
+		let number = &self.description[start..self.position];
+		number.parse().map_err(|_| ParseDescriptionError::Weight {
+			position: start,
+			weight: number.to_owned(),
+		})
+	}
+
+	/// Parses a quoted name, unescaping `\"` and `\\`.
+	fn quoted_name(&mut self) -> Result<String, ParseDescriptionError> {
+		// The opening quote.
+		self.bump();
+
+		let mut name = String::new();
+
+		loop {
+			match self.peek() {
+				Some('"') => {
+					self.bump();
+
+					return Ok(name);
+				},
+
+				Some('\\') => {
+					self.bump();
+
+					match self.peek() {
+						Some(char @ ('"' | '\\')) => {
+							self.bump();
+							name.push(char);
+						},
+
+						_ => return self.expected("`\"` or `\\` after `\\`"),
+					}
+				},
+
+				Some(char) => {
+					self.bump();
+					name.push(char);
+				},
+				None => return self.expected("`\"`"),
+			}
+		}
+	}
+
+	/// Consumes an orientation if one starts a group at the current position, returning it.
+	///
+	/// The group's `(` is left to be consumed.
+	fn orientation(&mut self) -> Option<Orientation> {
+		let (orientation, length) = self.peek_orientation()?;
+		self.position += length;
+
+		Some(orientation)
+	}
+
+	/// Returns the orientation starting a group at the current position and its length, without
+	/// consuming it.
+	fn peek_orientation(&self) -> Option<(Orientation, usize)> {
+		let rest = &self.description[self.position..];
+
+		let (orientation, token) = [
+			(Orientation::RightToLeft, "hr("),
+			(Orientation::BottomToTop, "vr("),
+			(Orientation::LeftToRight, "h("),
+			(Orientation::TopToBottom, "v("),
+		]
+		.into_iter()
+		.find(|(_, token)| rest.starts_with(token))?;
+
+		Some((orientation, token.len() - 1))
+	}
+
+	/// Returns an error that the `expected` thing wasn't found at the current position.
+	fn expected<T>(&self, expected: &'static str) -> Result<T, ParseDescriptionError> {
+		Err(ParseDescriptionError::Expected {
+			position: self.position,
+			expected,
+			found: self.peek(),
+		})
+	}
+
+	/// Returns the next character, without consuming it.
+	fn peek(&self) -> Option<char> {
+		self.description[self.position..].chars().next()
+	}
+
+	/// Consumes the next character.
+	fn bump(&mut self) {
+		if let Some(char) = self.peek() {
+			self.position += char.len_utf8();
+		}
+	}
+
+	fn skip_whitespace(&mut self) {
+		while self.peek().is_some_and(char::is_whitespace) {
+			self.bump();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::{reconfigured, Rect};
+
+	/// Creates a 1000 by 600 layout from the given `description`, naming each window by its
+	/// single-character name.
+	fn layout(description: &str) -> TilingLayout<char> {
+		let mut layout = TilingLayout::from_description(description, |name| {
+			let mut chars = name.chars();
+
+			chars.next().filter(|_| chars.next().is_none())
+		})
+		.unwrap();
+		layout.resize(0, 0, 1000, 600, &LayoutSettings::new().window_gap(0));
+
+		layout
+	}
+
+	/// Applies the changes made to the given `layout`, returning the rectangles of its windows,
+	/// sorted by window.
+	fn apply(layout: &mut TilingLayout<char>) -> Vec<Rect<char>> {
+		reconfigured(layout, &LayoutSettings::new().window_gap(0))
+	}
+
+	fn canonical(layout: &TilingLayout<char>) -> String {
+		layout.to_description(char::to_string)
+	}
+
+	fn error(description: &str) -> ParseDescriptionError {
+		TilingLayout::<String>::from_description(description, |name| Some(name.to_owned()))
+			.err()
+			.unwrap()
+	}
+
+	#[test]
+	fn example() {
+		let mut layout = layout("h(60% v(a b) 40% c)");
+
+		assert_eq!(
+			apply(&mut layout),
+			[('a', 0, 0, 600, 300), ('b', 0, 300, 600, 300), ('c', 600, 0, 400, 600)]
+		);
+		assert_eq!(canonical(&layout), "h(60% v(a b) 40% c)");
+	}
+
+	#[test]
+	fn weighting() {
+		// Percentages, numbers, and ratios are the same weights.
+		for description in ["v(25% a 75% b)", "v(0.25 a 0.75 b)", "v(1/4 a 3/4 b)", "v(1/4a 3/4b)"] {
+			let mut layout = layout(description);
+
+			assert_eq!(
+				apply(&mut layout),
+				[('a', 0, 0, 1000, 150), ('b', 0, 150, 1000, 450)],
+				"{description}"
+			);
+			assert_eq!(canonical(&layout), "v(25% a 75% b)");
+		}
+
+		// Unweighted children share what is left...
+		let mut layout = layout("h(50% a b c)");
+		assert_eq!(
+			apply(&mut layout),
+			[('a', 0, 0, 500, 600), ('b', 500, 0, 250, 600), ('c', 750, 0, 250, 600)]
+		);
+		// ...or are given the average weight if nothing is.
+		assert_eq!(canonical(&self::layout("h(100% a 100% b c)")), "h(a b c)");
+		// Weights are relative.
+		assert_eq!(canonical(&self::layout("h(2 a 6 b)")), "h(25% a 75% b)");
+	}
+
+	#[test]
+	fn orientations() {
+		let mut layout = layout(" hr( a  vr(b c) ) ");
+
+		// Children of reversed groups are listed in the order they appear.
+		assert_eq!(
+			apply(&mut layout),
+			[
+				('a', 0, 0, 500, 600),
+				('b', 500, 0, 500, 300),
+				('c', 500, 300, 500, 300)
+			]
+		);
+		assert_eq!(canonical(&layout), "hr(a vr(b c))");
+		assert_eq!(layout.orientation(), Orientation::RightToLeft);
+
+		// Orientations only start groups when they are followed by `(`.
+		assert_eq!(canonical(&self::layout("v(h v)")), "v(h v)");
+		// Empty groups are left out.
+		assert_eq!(canonical(&self::layout("v(a h() b)")), "v(a b)");
+		assert_eq!(canonical(&self::layout("h()")), "h()");
+	}
+
+	#[test]
+	fn names() {
+		let names = [
+			"firefox",
+			"my term",
+			"2nd",
+			".hidden",
+			"a\"b",
+			"back\\slash",
+			"",
+			"(x)",
+			"ünï",
+		];
+		let description = format!(
+			"h({})",
+			names
+				.iter()
+				.map(|name| {
+					let mut quoted = String::new();
+					write_name(name, &mut quoted);
+
+					quoted
+				})
+				.collect::<Vec<_>>()
+				.join(" ")
+		);
+		assert_eq!(
+			description,
+			r#"h(firefox "my term" "2nd" ".hidden" "a\"b" "back\\slash" "" "(x)" ünï)"#
+		);
+
+		let layout = TilingLayout::from_description(&description, |name| Some(name.to_owned())).unwrap();
+		let windows: Vec<_> = layout.windows().into_iter().map(String::as_str).collect();
+		assert_eq!(windows, names);
+
+		assert_eq!(layout.to_description(Clone::clone), description);
+	}
+
+	#[test]
+	fn errors() {
+		let expected = |position, expected, found| ParseDescriptionError::Expected {
+			position,
+			expected,
+			found,
+		};
+
+		assert_eq!(error(""), expected(0, "a group, like `h(a b)`", None));
+		assert_eq!(error("a"), expected(0, "a group, like `h(a b)`", Some('a')));
+		assert_eq!(error("h (a)"), expected(0, "a group, like `h(a b)`", Some('h')));
+		assert_eq!(error("h(a b"), expected(5, "a window, a group, or `)`", None));
+		assert_eq!(error("h(a) b"), expected(5, "the end of the description", Some('b')));
+		assert_eq!(error("h(a 50%)"), expected(7, "a window or a group", Some(')')));
+		assert_eq!(error("h(a\\b)"), expected(3, "a window or a group", Some('\\')));
+		assert_eq!(error(r#"h("a)"#), expected(5, "`\"`", None));
+		assert_eq!(error(r#"h("a\n")"#), expected(5, "`\"` or `\\` after `\\`", Some('n')));
+		assert_eq!(error("h(1/ a)"), expected(4, "a number", Some(' ')));
+
+		for (description, position, weight) in [
+			("h(0% a)", 2, "0%"),
+			("v(a 1.2.3 b)", 4, "1.2.3"),
+			("h(3/0 a)", 2, "3/0"),
+		] {
+			assert_eq!(
+				error(description),
+				ParseDescriptionError::Weight {
+					position,
+					weight: weight.to_owned()
+				},
+				"{description}"
+			);
+		}
+
+		assert_eq!(
+			TilingLayout::<char>::from_description("h(a bc)", |name| name.chars().next().filter(|_| name.len() == 1))
+				.err(),
+			Some(ParseDescriptionError::Unresolved {
+				position: 4,
+				name: "bc".to_owned()
+			})
+		);
+		assert_eq!(
+			error("v(a ]").to_string(),
+			"expected a window, a group, or `)` at 5, found the end of the description"
+		);
+	}
+
+	#[test]
+	fn placeholders() {
+		let mut layout = TilingLayout::from_description_with_placeholders("h(a v(_ b _))", |name| {
+			(name != "_").then(|| name.to_owned())
+		})
+		.unwrap();
+		assert_eq!(layout.placeholders(), 2);
+
+		layout.fill_placeholder("c".to_owned()).unwrap();
+		layout.fill_placeholder("d".to_owned()).unwrap();
+		assert_eq!(layout.fill_placeholder("e".to_owned()), Err("e".to_owned()));
+
+		assert_eq!(layout.placeholders(), 0);
+		assert_eq!(
+			layout.to_description(|slot| match slot {
+				Slot::Window(name) => name.clone(),
+				Slot::Placeholder => "_".to_owned(),
+			}),
+			"h(a v(c b d))"
+		);
+	}
+
+	/// A small, seeded pseudo-random number generator ([SplitMix64]).
+	///
+	/// [SplitMix64]: https://prng.di.unimi.it/splitmix64.c
+	struct Rng(u64);
+
+	impl Rng {
+		fn below(&mut self, n: u64) -> u64 {
+			self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+			let mut z = self.0;
+			z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+			z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+			(z ^ (z >> 31)) % n
+		}
+	}
+
+	/// Generates a random description of a group nested at the given `depth`, naming its windows
+	/// from `next`.
+	fn random_group(rng: &mut Rng, depth: u32, next: &mut u32) -> String {
+		let orientation = ["h", "v", "hr", "vr"][rng.below(4) as usize];
+		let weighted = rng.below(2) == 0;
+
+		let children: Vec<_> = (0..=rng.below(3))
+			.map(|_| {
+				let weight = match weighted {
+					true => format!("{}% ", 1 + rng.below(99)),
+					false => String::new(),
+				};
+				let node = match depth < 3 && rng.below(3) == 0 {
+					true => random_group(rng, depth + 1, next),
+					false => {
+						*next += 1;
+
+						// Names which must be quoted, and which look like weights or orientations.
+						match rng.below(4) {
+							0 => format!("\"window {next}\""),
+							1 => format!("\"{next}\""),
+							2 => format!("h{next}"),
+							_ => format!("w{next}"),
+						}
+					},
+				};
+
+				weight + &node
+			})
+			.collect();
+
+		format!("{orientation}({})", children.join(" "))
+	}
+
+	/// Tests that the canonical description of a layout describes the same layout, for many
+	/// randomly generated descriptions.
+	#[test]
+	fn round_trip() {
+		for seed in 0..256 {
+			let mut rng = Rng(seed);
+			let description = random_group(&mut rng, 0, &mut 0);
+
+			let layout = TilingLayout::from_description(&description, |name| Some(name.to_owned())).unwrap();
+			let canonical = layout.to_description(Clone::clone);
+
+			let reparsed = TilingLayout::from_description(&canonical, |name| Some(name.to_owned())).unwrap();
+			assert_eq!(reparsed.to_description(Clone::clone), canonical, "{description}");
+			assert_eq!(reparsed.windows(), layout.windows(), "{description}");
+
+			// Weights survive the round trip to within their rounding.
+			let weights = |layout: &TilingLayout<String>| {
+				let mut weights = Vec::new();
+				let mut groups = vec![layout.snapshot()];
+				while let Some(group) = groups.pop() {
+					for child in group.children {
+						weights.push(child.weight);
+						if let Shape::Group(group) = child.shape {
+							groups.push(group);
+						}
+					}
+				}
+
+				weights
+			};
+			for (before, after) in weights(&layout).into_iter().zip(weights(&reparsed)) {
+				assert!((before - after).abs() < 0.02, "{description}: {before} became {after}");
+			}
+		}
+	}
+}
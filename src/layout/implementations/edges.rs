@@ -0,0 +1,266 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl<Window> TilingLayout<Window> {
+	/// Returns the edge between two adjacent nodes which the given point lies on, if any.
+	///
+	/// A point lies on an edge if it is in the gap between the two nodes, or within `tolerance`
+	/// pixels of either side of that gap, and it is within `tolerance` pixels of the group
+	/// containing them along the other axis. The edges of the layout itself are not edges between
+	/// nodes, so they are never returned.
+	///
+	/// # Tie-breaks
+	/// Edges in deeper groups are preferred: near a corner where the edge of a group's child group
+	/// meets an edge of the group itself, the child group's edge is returned. A point in a group's
+	/// gap is only considered to be in a child group if it is within `tolerance` pixels of it.
+	///
+	/// If the point lies on more than one edge in the same group (because the nodes between them
+	/// are smaller than the `tolerance`), the closest edge is returned, and of equally close edges,
+	/// the first. Of edges in different groups of the same depth, the edge in the first group is
+	/// returned.
+	///
+	/// Positions are those from when changes were last applied.
+	pub fn edge_at(&self, x: i32, y: i32, tolerance: u32) -> Option<EdgeHit> {
+		let mut path = Vec::new();
+
+		self.root.edge_at((x as i64, y as i64), tolerance as i64, &mut path)
+	}
+
+	/// Moves the given `edge` so that the node before it ends at the given `position` along the
+	/// edge's axis, resizing the nodes on either side of it.
+	///
+	/// The nodes keep a size of at least one pixel. Moving an edge again before the changes are
+	/// applied replaces the previous move. Returns [`false`] if the edge is no longer in the
+	/// layout, e.g. because its group has since been removed or reoriented.
+	///
+	/// Please note that for the nodes in the layout to be updated, [state::AquariWm::apply_changes]
+	#[cfg_attr(feature = "async", doc = "or [state::AquariWm::apply_changes_async]")]
+	/// must be called.
+	///
+	/// [state::AquariWm::apply_changes]: crate::state::AquariWm::apply_changes
+	#[cfg_attr(
+		feature = "async",
+		doc = "[state::AquariWm::apply_changes_async]: crate::state::AquariWm::apply_changes_async"
+	)]
+	pub fn move_edge(&mut self, edge: &EdgeHit, position: i32) -> bool {
+		let Some(group) = self.root.group_at_mut(&edge.path) else {
+			return false;
+		};
+		if group.orientation.axis() != edge.axis || edge.index + 1 >= group.len() {
+			return false;
+		}
+
+		let node = &group[edge.index];
+		let end = match edge.axis {
+			Axis::Horizontal => node.x() + (node.width() as i32),
+			Axis::Vertical => node.y() + (node.height() as i32),
+		};
+
+		group.resize_child(edge.index, position - end)
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Returns the deepest edge between two adjacent nodes within this group which the given
+	/// `point` lies on, if any, where `path` is the path to this group.
+	///
+	/// See [`TilingLayout::edge_at`] for more information.
+	fn edge_at(&self, point: (i64, i64), tolerance: i64, path: &mut Vec<usize>) -> Option<EdgeHit> {
+		// `i64` is used so that adding the `tolerance` can't overflow.
+		let within = |(x, y, width, height): (i32, i32, u32, u32)| {
+			let (x, y) = (x as i64, y as i64);
+			let (right, bottom) = (x + (width as i64), y + (height as i64));
+
+			(x - tolerance..=right + tolerance).contains(&point.0)
+				&& (y - tolerance..=bottom + tolerance).contains(&point.1)
+		};
+
+		let mut deepest: Option<EdgeHit> = None;
+
+		for (index, node) in self.iter().enumerate() {
+			let Node::Group(group) = node else {
+				continue;
+			};
+
+			if !within(node.slot()) {
+				continue;
+			}
+
+			path.push(index);
+
+			if let Some(hit) = group.edge_at(point, tolerance, path) {
+				if deepest
+					.as_ref()
+					.is_none_or(|deepest| hit.path.len() > deepest.path.len())
+				{
+					deepest = Some(hit);
+				}
+			}
+
+			path.pop();
+		}
+
+		if deepest.is_some() {
+			return deepest;
+		}
+
+		// Every child of a stacked group fills the whole group, so there are no edges between them.
+		if self.stacked || !within((self.x, self.y, self.width, self.height)) {
+			return None;
+		}
+
+		// The nodes' current coordinates and dimensions are those of the applied orientation.
+		let axis = self.orientation.axis();
+		let primary = |node: &Node<Window>| match axis {
+			Axis::Horizontal => (node.x() as i64, node.width() as i64),
+			Axis::Vertical => (node.y() as i64, node.height() as i64),
+		};
+		let coord = match axis {
+			Axis::Horizontal => point.0,
+			Axis::Vertical => point.1,
+		};
+
+		// Nodes are iterated in order of their coordinates, even in reversed groups.
+		self.iter()
+			.zip(self.iter().skip(1))
+			.enumerate()
+			.filter_map(|(index, (before, after))| {
+				let ((before_coord, before_dimension), (after_coord, _)) = (primary(before), primary(after));
+				let (start, end) = (before_coord + before_dimension, after_coord);
+
+				let distance = if coord < start {
+					start - coord
+				} else if coord > end {
+					coord - end
+				} else {
+					0
+				};
+
+				(distance <= tolerance).then(|| {
+					let hit = EdgeHit {
+						path: path.clone(),
+						index,
+						axis,
+						position: start as i32,
+					};
+
+					(distance, hit)
+				})
+			})
+			.min_by_key(|(distance, _)| *distance)
+			.map(|(_, hit)| hit)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	const TOLERANCE: u32 = 4;
+
+	/// A 2 by 2 layout, `[(1 / 2) | (3 / 4)]`, with a window gap of 10 pixels: the groups are 485
+	/// pixels wide and the windows 285 pixels tall.
+	fn layout() -> TilingLayout<u32> {
+		let settings = LayoutSettings::new().window_gap(10);
+
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 600, &settings);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([1, 2]))
+			.unwrap();
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([3, 4]))
+			.unwrap();
+
+		apply(&mut layout, &settings);
+
+		layout
+	}
+
+	const fn hit(path: Vec<usize>, axis: Axis, position: i32) -> Option<EdgeHit> {
+		Some(EdgeHit {
+			path,
+			index: 0,
+			axis,
+			position,
+		})
+	}
+
+	#[test]
+	fn edges() {
+		let layout = layout();
+
+		let root = hit(vec![], Axis::Horizontal, 495);
+		let left = hit(vec![0], Axis::Vertical, 295);
+		let right = hit(vec![1], Axis::Vertical, 295);
+
+		// Gap centers.
+		assert_eq!(layout.edge_at(500, 100, TOLERANCE), root);
+		assert_eq!(layout.edge_at(200, 300, TOLERANCE), left);
+		assert_eq!(layout.edge_at(800, 300, TOLERANCE), right);
+
+		// Either side of the gap, within the tolerance.
+		assert_eq!(layout.edge_at(491, 100, TOLERANCE), root);
+		assert_eq!(layout.edge_at(509, 100, TOLERANCE), root);
+		assert_eq!(layout.edge_at(200, 309, TOLERANCE), left);
+
+		// Anywhere in the gap counts, even without any tolerance.
+		assert_eq!(layout.edge_at(495, 100, 0), root);
+		assert_eq!(layout.edge_at(505, 100, 0), root);
+		assert_eq!(layout.edge_at(494, 100, 0), None);
+
+		// Outside the tolerance.
+		assert_eq!(layout.edge_at(490, 100, TOLERANCE), None);
+		assert_eq!(layout.edge_at(200, 100, TOLERANCE), None);
+		assert_eq!(layout.edge_at(200, 310, TOLERANCE), None);
+	}
+
+	#[test]
+	fn corners() {
+		let layout = layout();
+
+		// Near the corner, the nested group's edge is preferred.
+		assert_eq!(layout.edge_at(497, 300, TOLERANCE), hit(vec![0], Axis::Vertical, 295));
+		assert_eq!(layout.edge_at(503, 293, TOLERANCE), hit(vec![1], Axis::Vertical, 295));
+		// In the middle of the root's gap, the point is too far from either nested group.
+		assert_eq!(layout.edge_at(500, 300, TOLERANCE), hit(vec![], Axis::Horizontal, 495));
+
+		// The edges of the layout itself are not edges between nodes.
+		assert_eq!(layout.edge_at(5, 300, TOLERANCE), None);
+		assert_eq!(layout.edge_at(995, 300, TOLERANCE), None);
+		assert_eq!(layout.edge_at(500, 598, TOLERANCE), None);
+		assert_eq!(layout.edge_at(500, 1000, TOLERANCE), None);
+		assert_eq!(layout.edge_at(-500, -500, TOLERANCE), None);
+	}
+
+	#[test]
+	fn move_edge() {
+		let settings = LayoutSettings::new().window_gap(10);
+		let mut layout = layout();
+
+		let root = layout.edge_at(500, 100, 0).unwrap();
+		assert!(layout.move_edge(&root, 595));
+		apply(&mut layout, &settings);
+		assert_eq!(layout.edge_at(600, 100, 0), hit(vec![], Axis::Horizontal, 595));
+
+		// Moving the edge again before applying the changes replaces the previous move.
+		assert!(layout.move_edge(&root, 295));
+		assert!(layout.move_edge(&root, 495));
+		apply(&mut layout, &settings);
+		assert_eq!(layout.edge_at(500, 100, 0), hit(vec![], Axis::Horizontal, 495));
+
+		// Edges in nested groups are moved along their own axis.
+		let left = layout.edge_at(200, 300, 0).unwrap();
+		assert!(layout.move_edge(&left, 195));
+		apply(&mut layout, &settings);
+		assert_eq!(layout.edge_at(200, 200, 0), hit(vec![0], Axis::Vertical, 195));
+		assert_eq!(layout.edge_at(800, 300, 0), hit(vec![1], Axis::Vertical, 295));
+
+		// Edges which are no longer in the layout aren't moved.
+		let gone = EdgeHit { index: 1, ..root };
+		assert!(!layout.move_edge(&gone, 495));
+	}
+}
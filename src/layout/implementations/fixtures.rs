@@ -0,0 +1,61 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Layouts and helpers shared by the tiling layout's tests.
+
+use super::*;
+
+/// A window and the coordinates and dimensions it was reconfigured with.
+pub type Rect<Window = u32> = (Window, i32, i32, u32, u32);
+
+/// Creates a 1200 by 600 layout of window `1` beside a group of windows `2` above `3`, with its
+/// changes applied.
+///
+/// ```text
+/// ┌───┬───┐
+/// │   │ 2 │
+/// │ 1 ├───┤
+/// │   │ 3 │
+/// └───┴───┘
+/// ```
+pub fn nested(settings: &LayoutSettings) -> TilingLayout<u32> {
+	let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1200, 600, settings);
+	layout.push_window_back(1);
+	layout
+		.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([2, 3]))
+		.unwrap();
+	apply(&mut layout, settings);
+
+	layout
+}
+
+/// Applies the changes made to the given `layout`, ignoring which windows were reconfigured.
+pub fn apply<Window: Clone>(layout: &mut TilingLayout<Window>, settings: &LayoutSettings) {
+	layout
+		.apply_changes(&mut |_, _, _, _, _, _| Ok::<_, ()>(()), settings)
+		.unwrap();
+}
+
+/// Applies the changes made to the given `layout`, returning the rectangles of the windows that
+/// were reconfigured, sorted by window.
+pub fn reconfigured<Window: Clone + Ord>(
+	layout: &mut TilingLayout<Window>,
+	settings: &LayoutSettings,
+) -> Vec<Rect<Window>> {
+	let mut reconfigured = Vec::new();
+
+	layout
+		.apply_changes(
+			&mut |window: &Window, x, y, width, height, _| -> Result<(), ()> {
+				reconfigured.push((window.clone(), x, y, width, height));
+
+				Ok(())
+			},
+			settings,
+		)
+		.unwrap();
+
+	reconfigured.sort();
+	reconfigured
+}
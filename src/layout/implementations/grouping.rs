@@ -0,0 +1,244 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl<Window> GroupNode<Window> {
+	/// Returns whether the group is dissolving.
+	///
+	/// A dissolving group is created to [group windows together], and is replaced by its
+	/// remaining child once it is left with only one.
+	///
+	/// [group windows together]: Self::group_window_by
+	#[inline(always)]
+	pub const fn is_dissolving(&self) -> bool {
+		self.dissolving
+	}
+
+	/// Returns the path of indexes to the first group containing a window matching `pred`, or
+	/// [`None`] if there is no such group.
+	///
+	/// A group's own children are searched before the groups within it, so the group closest to
+	/// this one is found first. The path to this group itself is empty. [Manual] groups within this
+	/// group are treated as leaves: they, and the groups within them, are not searched.
+	///
+	/// # See also
+	/// - [`path_of`](Self::path_of)
+	///
+	/// [Manual]: Self::is_manual
+	pub fn find_group_by(&self, pred: impl Fn(&Window) -> bool) -> Option<Vec<usize>> {
+		let mut path = Vec::new();
+
+		self.find_group_path(&pred, &mut path).then_some(path)
+	}
+
+	fn find_group_path(&self, pred: &impl Fn(&Window) -> bool, path: &mut Vec<usize>) -> bool {
+		let contains_match = self.iter().any(|node| match node {
+			Node::Window(node) => pred(node.window()),
+			Node::Group(_) => false,
+		});
+
+		if contains_match {
+			return true;
+		}
+
+		for (index, node) in self.iter().enumerate() {
+			if let Node::Group(group) = node {
+				if group.manual {
+					continue;
+				}
+				path.push(index);
+
+				if group.find_group_path(pred, path) {
+					return true;
+				}
+
+				path.pop();
+			}
+		}
+
+		false
+	}
+
+	/// Adds the given `window` alongside the first window matching `pred`.
+	///
+	/// If the [first group containing a matching window] is [dissolving], the `window` is added to
+	/// the end of that group. Otherwise, the first matching window in that group has its tile
+	/// split into a new dissolving group containing both windows.
+	///
+	/// Returns the `window` back if no window matches `pred`.
+	///
+	/// [first group containing a matching window]: Self::find_group_by
+	/// [dissolving]: Self::is_dissolving
+	pub fn group_window_by(&mut self, window: Window, pred: impl Fn(&Window) -> bool) -> Result<(), Window> {
+		let Some(group) = self.find_group_by(&pred).and_then(|path| self.group_at_mut(&path)) else {
+			return Err(window);
+		};
+
+		if group.dissolving {
+			group.push_window_back(window);
+
+			return Ok(());
+		}
+
+		let Some(index) = group.iter().position(|node| match node {
+			Node::Window(node) => pred(node.window()),
+			Node::Group(_) => false,
+		}) else {
+			return Err(window);
+		};
+
+		// Beyond the maximum depth, the window is placed next to the matching window instead.
+		if !group.can_nest_group() {
+			group.insert_window_splitting(index, window);
+
+			return Ok(());
+		}
+
+		// Insert the new group before the matching window so that the window can then be moved into
+		// it.
+		let orientation = group.orientation().rotated_by(1);
+		group
+			.insert_group_with(index, orientation, |new_group| new_group.dissolving = true)
+			.expect("we just checked that the group can be nested");
+
+		if let Some(node) = group.remove(index + 1) {
+			let new_group = group[index].unwrap_group_mut();
+
+			new_group.push_node_back(node);
+			new_group.push_window_back(window);
+		}
+
+		Ok(())
+	}
+
+	/// Removes the given `window` if it is in a [dissolving] group, dissolving that group if it is
+	/// left with only one child, unless it is [manual].
+	///
+	/// Returns whether the `window` was removed.
+	///
+	/// [dissolving]: Self::is_dissolving
+	/// [manual]: Self::is_manual
+	pub fn remove_grouped_window(&mut self, window: &Window) -> bool
+	where
+		Window: PartialEq,
+	{
+		for index in 0..self.len() {
+			let Node::Group(group) = &mut self[index] else {
+				continue;
+			};
+
+			let position = group.dissolving.then(|| {
+				group.iter().position(|node| match node {
+					Node::Window(node) => node.window() == window,
+					Node::Group(_) => false,
+				})
+			});
+
+			match position.flatten() {
+				Some(position) => {
+					group.remove(position);
+
+					// Replace the group with its remaining child. The child is inserted before the
+					// group is removed so that this group is never left empty.
+					if group.len() == 1 && !group.manual {
+						if let Some(node) = group.pop_front() {
+							self.insert_node(index, node);
+							self.remove(index + 1);
+						}
+					}
+
+					return true;
+				},
+
+				None => {
+					if group.remove_grouped_window(window) {
+						return true;
+					}
+				},
+			}
+		}
+
+		false
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+	struct MockWindow {
+		id: u32,
+		class: &'static str,
+	}
+
+	const fn window(id: u32, class: &'static str) -> MockWindow {
+		MockWindow { id, class }
+	}
+
+	/// Describes the structure of the `group`: `[1 (2 3)]` is a group containing window 1 and a
+	/// dissolving group of windows 2 and 3.
+	fn describe(group: &GroupNode<MockWindow>) -> String {
+		let children: Vec<_> = group
+			.iter()
+			.map(|node| match node {
+				Node::Window(node) => node.window().id.to_string(),
+				Node::Group(group) => describe(group),
+			})
+			.collect();
+
+		match group.is_dissolving() {
+			true => format!("({})", children.join(" ")),
+			false => format!("[{}]", children.join(" ")),
+		}
+	}
+
+	#[test]
+	fn group_by_class() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1200, 600, &settings);
+
+		let insert = |layout: &mut TilingLayout<MockWindow>, new: MockWindow| {
+			let class = new.class;
+
+			if let Err(new) = layout.group_window_by(new, |window| window.class == class) {
+				layout.push_window_back(new);
+			}
+		};
+
+		layout.push_window_back(window(1, "browser"));
+
+		// The first window of a class has nothing to join.
+		assert_eq!(layout.find_group_by(|window| window.class == "terminal"), None);
+		insert(&mut layout, window(2, "terminal"));
+		apply(&mut layout, &settings);
+		assert_eq!(describe(&layout), "[1 2]");
+
+		// The second window of a class splits the first one's tile.
+		insert(&mut layout, window(3, "terminal"));
+		apply(&mut layout, &settings);
+		assert_eq!(describe(&layout), "[1 (2 3)]");
+		assert_eq!(layout[1].width(), 600);
+		assert_eq!(layout[1].unwrap_group_ref()[1].height(), 300);
+
+		// Further windows of that class join its group.
+		assert_eq!(layout.find_group_by(|window| window.class == "terminal"), Some(vec![1]));
+		insert(&mut layout, window(4, "terminal"));
+		insert(&mut layout, window(5, "browser"));
+		apply(&mut layout, &settings);
+		assert_eq!(describe(&layout), "[(1 5) (2 3 4)]");
+
+		// Windows not in a dissolving group are left to the caller.
+		assert!(!layout.remove_grouped_window(&window(6, "editor")));
+
+		// The group dissolves once it drops to one member.
+		assert!(layout.remove_grouped_window(&window(3, "terminal")));
+		assert!(layout.remove_grouped_window(&window(2, "terminal")));
+		apply(&mut layout, &settings);
+		assert_eq!(describe(&layout), "[(1 5) 4]");
+		assert_eq!((layout[1].x(), layout[1].width(), layout[1].height()), (600, 600, 600));
+	}
+}
@@ -0,0 +1,593 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl<Window> Node<Window> {
+	/// Returns the node's [ID].
+	///
+	/// [ID]: NodeId
+	#[inline]
+	pub const fn id(&self) -> NodeId {
+		match self {
+			Self::Window(node) => node.id.0,
+			Self::Group(node) => node.id.0,
+		}
+	}
+
+	/// Moves the node into the slot with the given coordinates and dimensions, such that it is
+	/// reconfigured the next time changes are applied.
+	pub(super) const fn take_slot(&mut self, (x, y, width, height): (i32, i32, u32, u32)) {
+		match self {
+			Self::Window(node) => {
+				node.x = x;
+				node.y = y;
+
+				node.width = width;
+				node.height = height;
+
+				node.window_changed = true;
+			},
+
+			Self::Group(node) => {
+				node.new_x = Some(x);
+				node.new_y = Some(y);
+
+				node.new_width = Some(width);
+				node.new_height = Some(height);
+			},
+		}
+	}
+
+	/// Returns the node's coordinates and dimensions.
+	pub(super) const fn slot(&self) -> (i32, i32, u32, u32) {
+		(self.x(), self.y(), self.width(), self.height())
+	}
+}
+
+impl<Window> WindowNode<Window> {
+	/// Returns the window node's [ID].
+	///
+	/// [ID]: NodeId
+	#[inline(always)]
+	pub const fn id(&self) -> NodeId {
+		self.id.0
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Returns the group's [ID].
+	///
+	/// [ID]: NodeId
+	#[inline(always)]
+	pub const fn id(&self) -> NodeId {
+		self.id.0
+	}
+
+	/// Returns the index of the child [node] with the given `id`, or [`None`] if there is no such
+	/// child.
+	///
+	/// Only the group's direct children are searched; see [`path_of`] to search its descendents.
+	///
+	/// [node]: Node
+	/// [`path_of`]: Self::path_of
+	pub fn position_of(&self, id: NodeId) -> Option<usize> {
+		self.iter().position(|node| node.id() == id)
+	}
+
+	/// Returns the path of indexes to the descendent [node] with the given `id`, or [`None`] if
+	/// there is no such descendent.
+	///
+	/// The first index in the path is the index of a child of this group, the second is the index
+	/// of a child of that node, and so on. The path to this group itself is empty.
+	///
+	/// [node]: Node
+	pub fn path_of(&self, id: NodeId) -> Option<Vec<usize>> {
+		let mut path = Vec::new();
+
+		(self.id() == id || self.find_path(id, &mut path)).then_some(path)
+	}
+
+	fn find_path(&self, id: NodeId, path: &mut Vec<usize>) -> bool {
+		for (index, node) in self.iter().enumerate() {
+			path.push(index);
+
+			if node.id() == id {
+				return true;
+			}
+
+			if let Node::Group(group) = node {
+				if group.find_path(id, path) {
+					return true;
+				}
+			}
+
+			path.pop();
+		}
+
+		false
+	}
+
+	/// Returns the [ID] of the descendent window node containing the given `window`, or [`None`] if
+	/// there is no such descendent.
+	///
+	/// [ID]: NodeId
+	pub fn id_of_window(&self, window: &Window) -> Option<NodeId>
+	where
+		Window: PartialEq,
+	{
+		self.iter().find_map(|node| match node {
+			Node::Window(node) if node.window() == window => Some(node.id()),
+			Node::Window(_) => None,
+
+			Node::Group(group) => group.id_of_window(window),
+		})
+	}
+
+	/// Returns whether the given `window` is in this group or its descendents.
+	#[inline]
+	pub fn contains_window(&self, window: &Window) -> bool
+	where
+		Window: PartialEq,
+	{
+		self.id_of_window(window).is_some()
+	}
+
+	/// Marks the descendent window node containing the given `window` as changed, so that the
+	/// window is reconfigured the next time changes are applied even if its tile hasn't changed.
+	///
+	/// Returns [`false`] if there is no such descendent.
+	pub fn refresh_window(&mut self, window: &Window) -> bool
+	where
+		Window: PartialEq,
+	{
+		let Some(path) = self.id_of_window(window).and_then(|id| self.path_of(id)) else {
+			return false;
+		};
+
+		match self.node_at_mut(&path) {
+			Some(Node::Window(node)) => {
+				node.window_changed = true;
+
+				true
+			},
+
+			_ => false,
+		}
+	}
+
+	/// Returns the descendent [node] with the given `id`, or [`None`] if there is no such
+	/// descendent.
+	///
+	/// [node]: Node
+	pub fn node_by_id(&self, id: NodeId) -> Option<&Node<Window>> {
+		let path = self.path_of(id)?;
+		let (&last, path) = path.split_last()?;
+
+		self.group_at(path).and_then(|group| group.get(last))
+	}
+
+	/// Returns the [ID] of the descendent window node at the given `path`, or of the window node
+	/// nearest to it if the layout has changed since the `path` was found.
+	///
+	/// Each index in the `path` is clamped to the last child of its group; if the `path` ends at a
+	/// group, or continues past a window node, the first window within that group or that window
+	/// node is used. Returns [`None`] if there are no window nodes on the way.
+	///
+	/// [ID]: NodeId
+	pub fn window_near_path(&self, path: &[usize]) -> Option<NodeId> {
+		let (index, path) = path.split_first().unwrap_or((&0, &[]));
+		let node = self.get((*index).min(self.len().checked_sub(1)?))?;
+
+		match node {
+			Node::Window(node) => Some(node.id()),
+			Node::Group(group) => group.window_near_path(path),
+		}
+	}
+
+	/// Returns the descendent group at the given [path], or [`None`] if there is no group there.
+	///
+	/// [path]: Self::path_of
+	pub fn group_at(&self, path: &[usize]) -> Option<&GroupNode<Window>> {
+		match path.split_first() {
+			None => Some(self),
+
+			Some((&index, path)) => match self.get(index)? {
+				Node::Group(group) => group.group_at(path),
+				Node::Window(_) => None,
+			},
+		}
+	}
+
+	/// Returns a mutable reference to the descendent group at the given `path`.
+	pub(super) fn group_at_mut(&mut self, path: &[usize]) -> Option<&mut GroupNode<Window>> {
+		match path.split_first() {
+			None => Some(self),
+
+			Some((&index, path)) => match self.get_mut(index)? {
+				Node::Group(group) => group.group_at_mut(path),
+				Node::Window(_) => None,
+			},
+		}
+	}
+
+	/// Returns a mutable reference to the descendent [node] at the given (non-empty) `path`.
+	///
+	/// [node]: Node
+	pub(super) fn node_at_mut(&mut self, path: &[usize]) -> Option<&mut Node<Window>> {
+		let (&last, path) = path.split_last()?;
+
+		self.group_at_mut(path)?.get_mut(last)
+	}
+
+	/// Removes the descendent [node] with the given `id`.
+	///
+	/// Returns [`None`] if there is no such descendent.
+	///
+	/// [node]: Node
+	pub fn remove_by_id(&mut self, id: NodeId) -> Option<Node<Window>> {
+		let path = self.path_of(id)?;
+		let (&last, path) = path.split_last()?;
+
+		self.group_at_mut(path)?.remove(last)
+	}
+
+	/// Swaps the positions of the descendent [nodes] with the given IDs, which may be in different
+	/// groups.
+	///
+	/// Each node takes the other's coordinates and dimensions. Returns [`false`] if either node is
+	/// not a descendent of this group, or if one node contains the other.
+	///
+	/// [nodes]: Node
+	pub fn swap_ids(&mut self, a: NodeId, b: NodeId) -> bool {
+		if a == b {
+			return self.path_of(a).is_some();
+		}
+
+		let (Some(path_a), Some(path_b)) = (self.path_of(a), self.path_of(b)) else {
+			return false;
+		};
+		// Neither node may be this group, nor contain the other.
+		if path_a.is_empty() || path_b.is_empty() || path_a.starts_with(&path_b) || path_b.starts_with(&path_a) {
+			return false;
+		}
+
+		// Temporarily put a placeholder in `a`'s place so that both nodes can be moved.
+		let placeholder = Node::new_group(Orientation::LeftToRight);
+
+		let node_a = self.node_at_mut(&path_a).expect("we just found the path to `a`");
+		let mut node_a = mem::replace(node_a, placeholder);
+		let slot_a = node_a.slot();
+
+		let node_b = self.node_at_mut(&path_b).expect("we just found the path to `b`");
+		let slot_b = node_b.slot();
+
+		node_a.take_slot(slot_b);
+		let mut node_b = mem::replace(node_b, node_a);
+
+		node_b.take_slot(slot_a);
+		*self.node_at_mut(&path_a).expect("we just found the path to `a`") = node_b;
+
+		true
+	}
+
+	/// Swaps the windows of the descendent window nodes with the given IDs, which may be in
+	/// different groups, as with [`WindowNode::swap_windows`].
+	///
+	/// Unlike [`swap_ids`], the nodes themselves stay where they are, so each ID stays with its
+	/// position in the layout rather than with its window. Returns [`false`] if either node is not
+	/// a descendent window node of this group.
+	///
+	/// [`swap_ids`]: Self::swap_ids
+	pub fn swap_windows_by_id(&mut self, a: NodeId, b: NodeId) -> bool {
+		let (Some(path_a), Some(path_b)) = (self.path_of(a), self.path_of(b)) else {
+			return false;
+		};
+		if !matches!(self.node_at_mut(&path_a), Some(Node::Window(_))) {
+			return false;
+		}
+		if a == b {
+			return true;
+		}
+
+		// Temporarily put a placeholder in `a`'s place so that both nodes can be borrowed.
+		let placeholder = Node::new_group(Orientation::LeftToRight);
+
+		let node_a = self.node_at_mut(&path_a).expect("we just found the path to `a`");
+		let mut node_a = mem::replace(node_a, placeholder);
+
+		let swapped = match (&mut node_a, self.node_at_mut(&path_b)) {
+			(Node::Window(node_a), Some(Node::Window(node_b))) => {
+				node_a.swap_windows(node_b);
+
+				true
+			},
+
+			_ => false,
+		};
+
+		*self.node_at_mut(&path_a).expect("we just found the path to `a`") = node_a;
+
+		swapped
+	}
+
+	/// Moves the descendent [node] with the given `id` into the descendent group with the given
+	/// `group` ID, inserting it at the given `index` (or at the end of the group, if the `index` is
+	/// out of bounds).
+	///
+	/// The node keeps its ID. Returns [`false`] if either node is not a descendent of this group
+	/// (the `group` may be this group itself), if the `group` is not a group, or if the node
+	/// contains the `group`.
+	///
+	/// # Errors
+	/// If moving a group would nest groups too deeply, they are [flattened] or, if that is
+	/// [rejected], nothing is moved and a [`DepthError`] is returned.
+	///
+	/// [node]: Node
+	/// [flattened]: DepthPolicy::Flatten
+	/// [rejected]: DepthPolicy::Reject
+	pub fn transfer_by_id(&mut self, id: NodeId, group: NodeId, index: usize) -> Result<bool, DepthError> {
+		let (Some(path), Some(group_path)) = (self.path_of(id), self.path_of(group)) else {
+			return Ok(false);
+		};
+		if path.is_empty() || group_path.starts_with(&path) {
+			return Ok(false);
+		}
+		let Some(target) = self.group_at(&group_path) else {
+			return Ok(false);
+		};
+
+		if let Some(Node::Group(moved)) = self.node_by_id(id) {
+			target.check_nesting(moved.nested_levels())?;
+		}
+
+		let node = self.remove_by_id(id).expect("we just found the path to the node");
+
+		// The path to the group may have changed by removing the node.
+		let group_path = self.path_of(group).expect("the group was not removed");
+		let group = self
+			.group_at_mut(&group_path)
+			.expect("we just found the path to the group");
+
+		let nesting = group.nesting.child();
+		let index = group.insert_node(index.min(group.len()), node);
+
+		if let Node::Group(moved) = &mut group.children[index] {
+			moved.set_nesting(nesting);
+		}
+		group.flatten_too_deep();
+
+		debug_assert_eq!(
+			self.validate(),
+			Ok(()),
+			"moving a node broke one of the layout's invariants"
+		);
+
+		Ok(true)
+	}
+
+	/// Moves the descendent window node with the given `id` beside the descendent window node with
+	/// the given `target` ID, on the given `side` of it along the given `axis`.
+	///
+	/// If the target's group is oriented along the `axis`, the node is moved into that group next
+	/// to the target. Otherwise, the target's tile is split into a new group along the `axis`
+	/// containing both nodes, unless that group would be nested too deeply, in which case the node
+	/// is moved next to the target in its group regardless.
+	///
+	/// The node keeps its ID. Returns [`false`] if either node is not a descendent window node of
+	/// this group, or if they are the same node.
+	pub fn move_beside_by_id(&mut self, id: NodeId, target: NodeId, axis: Axis, side: Side) -> bool {
+		let is_window = |group: &Self, id| matches!(group.node_by_id(id), Some(Node::Window(_)));
+		if id == target || !is_window(self, id) || !is_window(self, target) {
+			return false;
+		}
+
+		let node = self.remove_by_id(id).expect("we just found the node");
+
+		// The path to the target may have changed by removing the node.
+		let path = self.path_of(target).expect("the target was not removed");
+		let (&index, group_path) = path.split_last().expect("the target is a descendent");
+		let group = self
+			.group_at_mut(group_path)
+			.expect("we just found the path to the target's group");
+
+		let after = usize::from(side == Side::After);
+
+		if group.orientation().axis() == axis || !group.can_nest_group() {
+			group.insert_node(index + after, node);
+		} else {
+			let orientation = match axis {
+				Axis::Horizontal => Orientation::LeftToRight,
+				Axis::Vertical => Orientation::TopToBottom,
+			};
+
+			// Insert the new group before the target so that the target can then be moved into it.
+			group
+				.insert_group_with(index, orientation, |_| {})
+				.expect("we just checked that the group can be nested");
+			let target = group.remove(index + 1).expect("the target is after the new group");
+
+			let new_group = group[index].unwrap_group_mut();
+			new_group.push_node_back(target);
+			new_group.insert_node(after, node);
+		}
+
+		debug_assert_eq!(
+			self.validate(),
+			Ok(()),
+			"moving a node broke one of the layout's invariants"
+		);
+
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	/// Tests that ID-based operations keep targeting the same node after structural mutations
+	/// around it.
+	#[test]
+	fn stable_ids() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1200, 600, &settings);
+		layout.push_windows_back([1, 2, 3]);
+
+		let id = layout[1].id();
+		let window = |layout: &TilingLayout<u32>| *layout.node_by_id(id).unwrap().unwrap_window_ref().window();
+
+		// 1. Insert windows before it.
+		layout.push_windows_front([4, 5]);
+		// 2. Insert a window after it.
+		layout.push_window_back(6);
+		// 3. Remove a window before it.
+		layout.remove(0);
+		// 4. Reverse the group.
+		layout.set_orientation(Orientation::RightToLeft);
+		// 5. Add a group, and 6. transfer the node into it.
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_window_back(7))
+			.unwrap();
+		let group = layout.last().unwrap().id();
+		assert_eq!(layout.transfer_by_id(id, group, 0), Ok(true));
+		// 7. Apply the changes.
+		apply(&mut layout, &settings);
+		// 8. Insert a window before the group.
+		layout.insert_window(0, 8);
+		// 9. Swap two other windows.
+		let (a, b) = (layout[0].id(), layout[1].id());
+		assert!(layout.swap_ids(a, b));
+		// 10. Remove another window by its ID.
+		let other = layout[2].id();
+		assert!(layout.remove_by_id(other).is_some());
+		// 11. Push another window to the node's group.
+		layout.last_mut().unwrap().unwrap_group_mut().push_window_front(9);
+		// 12. Apply the changes.
+		apply(&mut layout, &settings);
+
+		assert_eq!(window(&layout), 2);
+		assert_eq!(layout.id_of_window(&2), Some(id));
+
+		let path = layout.path_of(id).unwrap();
+		assert_eq!(path, [layout.len() - 1, 1]);
+		assert_eq!(layout.last().unwrap().unwrap_group_ref().position_of(id), Some(1));
+
+		// Swapping the node with a window in another group moves it there, keeping its ID.
+		let first = layout[0].id();
+		assert!(layout.swap_ids(id, first));
+		assert_eq!(layout.path_of(id).unwrap(), [0]);
+		assert_eq!(layout.path_of(first).unwrap(), path);
+		assert_eq!(window(&layout), 2);
+
+		// A group can't be swapped with its own descendent.
+		let descendent = layout.last().unwrap().unwrap_group_ref()[0].id();
+		assert!(!layout.swap_ids(group, descendent));
+
+		let removed = layout.remove_by_id(id).unwrap();
+		assert_eq!(removed.unwrap_window().into_window(), 2);
+		assert_eq!(layout.path_of(id), None);
+	}
+
+	/// Tests that paths which no longer exist are clamped to the nearest window node.
+	#[test]
+	fn nearest_path() {
+		let settings = LayoutSettings::new();
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1200, 600, &settings);
+		layout.push_window_back(1);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([2, 3]))
+			.unwrap();
+
+		let window = |path: &[usize]| {
+			let id = layout.window_near_path(path)?;
+
+			Some(*layout.node_by_id(id)?.unwrap_window_ref().window())
+		};
+
+		assert_eq!(window(&[1, 1]), Some(3));
+		// Indexes past the end of a group are clamped to its last child.
+		assert_eq!(window(&[1, 5]), Some(3));
+		assert_eq!(window(&[7, 0]), Some(2));
+		// Paths ending at a group use its first window, and paths continuing past a window use it.
+		assert_eq!(window(&[1]), Some(2));
+		assert_eq!(window(&[0, 2, 1]), Some(1));
+		assert_eq!(window(&[]), Some(1));
+
+		assert_eq!(
+			TilingLayout::<u32>::new(Orientation::LeftToRight, 0, 0, 1200, 600, &settings).window_near_path(&[0]),
+			None
+		);
+	}
+
+	/// Tests that windows are moved beside others within groups along the same axis, and split into
+	/// new groups along the other axis, including in reversed groups.
+	#[test]
+	fn move_beside() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1200, 600, &settings);
+		layout.push_windows_back([1, 2, 3]);
+		apply(&mut layout, &settings);
+		let id = |layout: &TilingLayout<u32>, window| layout.id_of_window(&window).unwrap();
+
+		let (one, three) = (id(&layout, 1), id(&layout, 3));
+		assert!(layout.move_beside_by_id(one, three, Axis::Horizontal, Side::After));
+		assert_eq!(layout.windows().into_iter().copied().collect::<Vec<_>>(), [2, 3, 1]);
+		assert!(!layout.move_beside_by_id(one, one, Axis::Horizontal, Side::Before));
+
+		// Moving a window below another splits the other's tile.
+		let two = id(&layout, 2);
+		assert!(layout.move_beside_by_id(two, three, Axis::Vertical, Side::After));
+		apply(&mut layout, &settings);
+		assert_eq!(layout.validate(), Ok(()));
+		assert_eq!(layout.len(), 2);
+		assert_eq!(layout[0].unwrap_group_ref().orientation(), Orientation::TopToBottom);
+		assert_eq!(layout.windows().into_iter().copied().collect::<Vec<_>>(), [3, 2, 1]);
+		assert_eq!(layout.window_at(100, 500).map(|(&window, _)| window), Some(2));
+
+		// Before and after are on screen, even in a reversed group.
+		layout.set_orientation(Orientation::RightToLeft);
+		apply(&mut layout, &settings);
+		assert_eq!(layout.window_at(100, 100).map(|(&window, _)| window), Some(1));
+		assert!(layout.move_beside_by_id(two, one, Axis::Horizontal, Side::Before));
+		apply(&mut layout, &settings);
+		assert_eq!(layout.validate(), Ok(()));
+		assert_eq!(layout.window_at(100, 100).map(|(&window, _)| window), Some(2));
+		assert_eq!(layout.window_at(500, 100).map(|(&window, _)| window), Some(1));
+		assert_eq!(layout.window_at(1000, 100).map(|(&window, _)| window), Some(3));
+	}
+
+	/// Tests that IDs survive serialization, and that nodes created after a layout is deserialized
+	/// are given IDs which aren't in it.
+	#[test]
+	fn serialized_ids() {
+		let settings = LayoutSettings::new();
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1200, 600, &settings);
+		layout.push_window_back(1);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([2, 3]))
+			.unwrap();
+		let ids: Vec<_> = [1, 2, 3].iter().map(|window| layout.id_of_window(window)).collect();
+
+		let json = serde_json::to_string(&layout).unwrap();
+		let mut restored: TilingLayout<u32> = serde_json::from_str(&json).unwrap();
+
+		let restored_ids: Vec<_> = [1, 2, 3].iter().map(|window| restored.id_of_window(window)).collect();
+		assert_eq!(restored_ids, ids);
+		assert_eq!(restored[1].id(), layout[1].id());
+
+		// Even IDs higher than any given out yet are never given out again.
+		let json = json.replace(&format!("\"id\":{}", layout[0].id().0), "\"id\":1000000000");
+		let restored_high: TilingLayout<u32> = serde_json::from_str(&json).unwrap();
+		assert_eq!(restored_high[0].id(), NodeId(1_000_000_000));
+
+		restored.push_window_back(4);
+		assert!(restored.last().unwrap().id() > NodeId(1_000_000_000));
+	}
+}
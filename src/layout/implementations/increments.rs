@@ -0,0 +1,387 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fmt::{self, Display, Formatter};
+
+use super::*;
+
+impl Increments {
+	/// Returns the base size and step along the given `axis`, or [`None`] if the window isn't
+	/// constrained along it.
+	#[inline]
+	pub(super) const fn along(&self, axis: Axis) -> Option<(u32, u32)> {
+		let (base, step) = match axis {
+			Axis::Horizontal => (self.base_width, self.width),
+			Axis::Vertical => (self.base_height, self.height),
+		};
+
+		match step {
+			0 | 1 => None,
+			step => Some((base, step)),
+		}
+	}
+
+	/// Returns the given `width` and `height` rounded down to the nearest size which is a whole
+	/// number of steps beyond the base size.
+	///
+	/// A dimension no bigger than the base size is left as it is.
+	#[inline]
+	pub const fn fit(&self, width: u32, height: u32) -> (u32, u32) {
+		(
+			fit(self.along(Axis::Horizontal), width),
+			fit(self.along(Axis::Vertical), height),
+		)
+	}
+}
+
+/// Rounds the given `size` down to a whole number of steps beyond the base size, given as
+/// `(base, step)`, if there are increments along its axis.
+pub(super) const fn fit(increments: Option<(u32, u32)>, size: u32) -> u32 {
+	match increments {
+		Some((base, step)) if size > base => base + (size - base) / step * step,
+		_ => size,
+	}
+}
+
+impl<Window> WindowNode<Window> {
+	/// Returns the window's [resize increments], if it has any.
+	///
+	/// [resize increments]: Increments
+	#[inline(always)]
+	pub const fn increments(&self) -> Option<Increments> {
+		self.increments
+	}
+
+	/// Sets the window's [resize increments].
+	///
+	/// If they have changed, the window node's group is laid out again the next time changes are
+	/// applied.
+	///
+	/// [resize increments]: Increments
+	pub fn set_increments(&mut self, increments: Option<Increments>) {
+		if self.increments != increments {
+			self.increments = increments;
+			self.increments_changed = true;
+		}
+	}
+}
+
+impl<Window> Node<Window> {
+	/// Returns the base size and step of the node's [resize increments] along the given `axis`, if
+	/// it is a window constrained along it.
+	///
+	/// [resize increments]: Increments
+	#[inline]
+	pub(super) fn increments_along(&self, axis: Axis) -> Option<(u32, u32)> {
+		match self {
+			Self::Window(node) => node.increments?.along(axis),
+			Self::Group(_) => None,
+		}
+	}
+
+	/// Returns whether the node is a window whose [resize increments] have changed since changes
+	/// were last applied to its group.
+	///
+	/// [resize increments]: Increments
+	#[inline]
+	pub(super) const fn increments_changed(&self) -> bool {
+		match self {
+			Self::Window(node) => node.increments_changed,
+			Self::Group(_) => false,
+		}
+	}
+
+	/// Returns the number of pixels by which the node's tile in its group is bigger than the
+	/// node, because of [resize increments].
+	///
+	/// [resize increments]: Increments
+	#[inline]
+	pub(super) const fn slack(&self) -> i32 {
+		match self {
+			Self::Window(node) => node.slack,
+			Self::Group(node) => node.slack,
+		}
+	}
+
+	/// Records that the node was given the given `tile` along its group's axis, marking its
+	/// [resize increments] as applied.
+	///
+	/// [resize increments]: Increments
+	#[inline]
+	pub(super) fn set_tile(&mut self, tile: u32, primary: u32) {
+		let slack = (i64::from(tile) - i64::from(primary)).clamp(i32::MIN.into(), i32::MAX.into()) as i32;
+
+		match self {
+			Self::Window(node) => {
+				node.slack = slack;
+				node.increments_changed = false;
+			},
+			Self::Group(node) => node.slack = slack,
+		}
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Sets the [resize increments] of the descendent window node containing the given `window`.
+	///
+	/// Returns [`false`] if there is no such descendent.
+	///
+	/// [resize increments]: Increments
+	pub fn set_increments(&mut self, window: &Window, increments: Option<Increments>) -> bool
+	where
+		Window: PartialEq,
+	{
+		let Some(path) = self.id_of_window(window).and_then(|id| self.path_of(id)) else {
+			return false;
+		};
+
+		match self.node_at_mut(&path) {
+			Some(Node::Window(node)) => {
+				node.set_increments(increments);
+
+				true
+			},
+
+			_ => false,
+		}
+	}
+
+	/// Marks every descendent window with [resize increments] as changed, so that they are sized
+	/// again, e.g. because [whether they are honored] has changed.
+	///
+	/// [resize increments]: Increments
+	/// [whether they are honored]: LayoutSettings::honor_increments
+	pub(super) fn refresh_increments(&mut self) {
+		// Groups are visited with an explicit stack, rather than recursively, so that deeply nested
+		// layouts can't overflow the stack.
+		let mut groups = vec![self];
+
+		while let Some(group) = groups.pop() {
+			for node in &mut group.children {
+				match node {
+					Node::Window(node) => node.increments_changed |= node.increments.is_some(),
+					Node::Group(child) => groups.push(child),
+				}
+			}
+		}
+	}
+}
+
+impl Display for LeftoverPixels {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::LastUnconstrained => write!(f, "last-unconstrained"),
+			Self::Gaps => write!(f, "gaps"),
+			Self::Margin => write!(f, "margin"),
+		}
+	}
+}
+
+impl FromStr for LeftoverPixels {
+	type Err = ParseLeftoverPixelsError;
+
+	/// Parses `last-unconstrained`, `gaps`, or `margin`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim() {
+			"last-unconstrained" => Ok(Self::LastUnconstrained),
+			"gaps" => Ok(Self::Gaps),
+			"margin" => Ok(Self::Margin),
+
+			other => Err(ParseLeftoverPixelsError(other.to_owned())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	/// A terminal's increments: 9x18 cells, with a base size of 2x4.
+	const TERMINAL: Increments = Increments {
+		width: 9,
+		height: 18,
+
+		base_width: 2,
+		base_height: 4,
+	};
+
+	/// Applies the changes made to the given `layout`, returning the geometry of each of its
+	/// windows, in order.
+	fn geometries(layout: &mut TilingLayout<u32>, settings: &LayoutSettings) -> Vec<(u32, i32, i32, u32, u32)> {
+		apply(layout, settings);
+
+		layout
+			.iter()
+			.map(|node| match node {
+				Node::Window(node) => (*node.window(), node.x, node.y, node.width, node.height),
+				Node::Group(_) => unreachable!("there are no groups"),
+			})
+			.collect()
+	}
+
+	/// Creates a layout of the given `windows`, of which the `terminals` have [`TERMINAL`]'s
+	/// increments.
+	fn layout(windows: &[u32], terminals: &[u32], settings: &LayoutSettings) -> TilingLayout<u32> {
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 600, settings);
+		layout.push_windows_back(windows.iter().copied());
+
+		for terminal in terminals {
+			assert!(layout.set_increments(terminal, Some(TERMINAL)));
+		}
+
+		layout
+	}
+
+	#[test]
+	fn fit() {
+		assert_eq!(TERMINAL.fit(500, 600), (497, 598));
+		// Sizes which are already whole cells, or too small for the base size, are left as they are.
+		assert_eq!(TERMINAL.fit(497, 598), (497, 598));
+		assert_eq!(TERMINAL.fit(1, 3), (1, 3));
+
+		let unconstrained = Increments {
+			width: 1,
+			height: 0,
+			..TERMINAL
+		};
+		assert_eq!(unconstrained.fit(500, 600), (500, 600));
+	}
+
+	#[test]
+	fn two_windows() {
+		for leftover in [
+			LeftoverPixels::LastUnconstrained,
+			LeftoverPixels::Gaps,
+			LeftoverPixels::Margin,
+		] {
+			let settings = LayoutSettings::new()
+				.window_gap(0)
+				.honor_increments(true)
+				.leftover_pixels(leftover);
+
+			// With no unconstrained window to give them to, the leftover pixels are a margin...
+			let mut both = layout(&[1, 2], &[1, 2], &settings);
+			let second_x = match leftover {
+				// ...unless they widen the gap.
+				LeftoverPixels::Gaps => 503,
+				_ => 497,
+			};
+			assert_eq!(
+				geometries(&mut both, &settings),
+				[(1, 0, 0, 497, 598), (2, second_x, 0, 497, 598)],
+				"{leftover}"
+			);
+
+			let mut one = layout(&[1, 2], &[1], &settings);
+			let expected = match leftover {
+				LeftoverPixels::LastUnconstrained => [(1, 0, 0, 497, 598), (2, 497, 0, 503, 600)],
+				LeftoverPixels::Gaps => [(1, 0, 0, 497, 598), (2, 500, 0, 500, 600)],
+				LeftoverPixels::Margin => [(1, 0, 0, 497, 598), (2, 497, 0, 500, 600)],
+			};
+			assert_eq!(geometries(&mut one, &settings), expected, "{leftover}");
+		}
+	}
+
+	#[test]
+	fn three_windows() {
+		// The tiles are 334, 333 and 333 pixels wide: the terminals lose 8 and 7 pixels.
+		#[rustfmt::skip]
+		let cases = [
+			(LeftoverPixels::LastUnconstrained, [(1, 0, 0, 326, 598), (2, 326, 0, 348, 600), (3, 674, 0, 326, 598)]),
+			(LeftoverPixels::Gaps, [(1, 0, 0, 326, 598), (2, 334, 0, 333, 600), (3, 674, 0, 326, 598)]),
+			(LeftoverPixels::Margin, [(1, 0, 0, 326, 598), (2, 326, 0, 333, 600), (3, 659, 0, 326, 598)]),
+		];
+
+		for (leftover, expected) in cases {
+			let settings = LayoutSettings::new()
+				.window_gap(0)
+				.honor_increments(true)
+				.leftover_pixels(leftover);
+
+			let mut layout = layout(&[1, 2, 3], &[1, 3], &settings);
+			assert_eq!(geometries(&mut layout, &settings), expected, "{leftover}");
+		}
+
+		// Gaps are kept between the windows.
+		let settings = LayoutSettings::new().window_gap(10).honor_increments(true);
+		let mut layout = layout(&[1, 2, 3], &[1, 3], &settings);
+		assert_eq!(
+			geometries(&mut layout, &settings),
+			[(1, 10, 10, 317, 580), (2, 337, 10, 326, 580), (3, 673, 10, 317, 580)]
+		);
+	}
+
+	/// Tests that laying windows out again with no other change gives them the same geometries,
+	/// rather than the leftover pixels accumulating.
+	#[test]
+	fn idempotent() {
+		for leftover in [
+			LeftoverPixels::LastUnconstrained,
+			LeftoverPixels::Gaps,
+			LeftoverPixels::Margin,
+		] {
+			let settings = LayoutSettings::new()
+				.window_gap(0)
+				.honor_increments(true)
+				.leftover_pixels(leftover);
+
+			let mut layout = layout(&[1, 2, 3], &[1, 3], &settings);
+			let first = geometries(&mut layout, &settings);
+
+			for _ in 0..3 {
+				layout.update_settings(&settings);
+				assert_eq!(geometries(&mut layout, &settings), first, "{leftover}");
+			}
+		}
+	}
+
+	#[test]
+	fn changes() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		// Increments are ignored unless they are honored.
+		let mut layout = layout(&[1, 2], &[1], &settings);
+		assert_eq!(
+			geometries(&mut layout, &settings),
+			[(1, 0, 0, 500, 600), (2, 500, 0, 500, 600)]
+		);
+
+		let honored = settings.clone().honor_increments(true);
+		layout.update_settings(&honored);
+		assert_eq!(
+			geometries(&mut layout, &honored),
+			[(1, 0, 0, 497, 598), (2, 497, 0, 503, 600)]
+		);
+
+		// Windows are sized again when their increments change...
+		assert!(layout.set_increments(&1, None));
+		assert_eq!(
+			geometries(&mut layout, &honored),
+			[(1, 0, 0, 500, 600), (2, 500, 0, 500, 600)]
+		);
+
+		// ...and their increments follow them when they are swapped.
+		assert!(layout.set_increments(&2, Some(TERMINAL)));
+		assert!(layout.swap_with_first(&2));
+		assert_eq!(
+			geometries(&mut layout, &honored),
+			[(2, 0, 0, 497, 598), (1, 497, 0, 503, 600)]
+		);
+
+		assert!(!layout.set_increments(&3, Some(TERMINAL)));
+	}
+
+	#[test]
+	fn parse() {
+		assert_eq!(
+			"last-unconstrained".parse::<LeftoverPixels>().unwrap(),
+			LeftoverPixels::LastUnconstrained
+		);
+		assert_eq!(" gaps".parse::<LeftoverPixels>().unwrap(), LeftoverPixels::Gaps);
+		assert_eq!("margin".parse::<LeftoverPixels>().unwrap(), LeftoverPixels::Margin);
+
+		assert!("last".parse::<LeftoverPixels>().is_err());
+	}
+}
@@ -41,6 +41,45 @@ pub struct IterMut<'group, Window> {
 	iter_mut: GroupIterator<vec_deque::IterMut<'group, Node<Window>>>,
 }
 
+/// Takes or borrows the children of a [group] to iterate over them.
+///
+/// [group]: GroupNode
+trait IntoChildren {
+	type Children: IntoIterator;
+
+	fn into_children(self) -> Self::Children;
+}
+
+impl<'group, Window> IntoChildren for &'group GroupNode<Window> {
+	type Children = &'group VecDeque<Node<Window>>;
+
+	#[inline(always)]
+	fn into_children(self) -> Self::Children {
+		&self.children
+	}
+}
+
+impl<'group, Window> IntoChildren for &'group mut GroupNode<Window> {
+	type Children = &'group mut VecDeque<Node<Window>>;
+
+	#[inline(always)]
+	fn into_children(self) -> Self::Children {
+		// The children may be changed without the group knowing.
+		self.invalidate_tally();
+
+		&mut self.children
+	}
+}
+
+impl<Window> IntoChildren for GroupNode<Window> {
+	type Children = VecDeque<Node<Window>>;
+
+	#[inline(always)]
+	fn into_children(self) -> Self::Children {
+		self.children
+	}
+}
+
 macro_rules! impl_iterator {
 	(
 		for $($Iter:ident<$($lt:lifetime $($mut:ident)?,)? $Window:ident> { $iter:ident }),+$(,)?
@@ -76,11 +115,14 @@ macro_rules! impl_iterator {
 
 		impl<$($lt,)? $Window> $Iter<$($lt,)? $Window> {
 			fn new(group: $(&$lt $($mut)?)? GroupNode<$Window>) -> Self {
+				let reversed = group.orientation().reversed();
+				let children = group.into_children().into_iter();
+
 				Self {
-					$iter: if !group.orientation().reversed() {
-						GroupIterator::Normal(group.children.$iter())
+					$iter: if !reversed {
+						GroupIterator::Normal(children)
 					} else {
-						GroupIterator::Rev(group.children.$iter().rev())
+						GroupIterator::Rev(children.rev())
 					},
 				}
 			}
@@ -195,4 +237,31 @@ impl<Window> GroupNode<Window> {
 	pub fn iter_mut(&mut self) -> <&mut Self as IntoIterator>::IntoIter {
 		self.into_iter()
 	}
+
+	/// Returns every window in this group and its descendents, in order.
+	///
+	/// Windows are listed depth-first, with each group's children in index order, which is right
+	/// to left or bottom to top on screen for [reversed] groups. This is also the order in which
+	/// windows are reconfigured when [changes are applied].
+	///
+	/// [reversed]: Orientation::reversed
+	/// [changes are applied]: GroupNode::apply_changes
+	pub fn windows(&self) -> Vec<&Window> {
+		let mut windows = Vec::new();
+		// The groups being iterated over, innermost last.
+		let mut groups = vec![self.iter()];
+
+		while let Some(children) = groups.last_mut() {
+			match children.next() {
+				Some(Node::Window(node)) => windows.push(node.window()),
+				Some(Node::Group(group)) => groups.push(group.iter()),
+
+				None => {
+					groups.pop();
+				},
+			}
+		}
+
+		windows
+	}
 }
@@ -0,0 +1,183 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl<Window> GroupNode<Window> {
+	/// Returns whether the group is manual.
+	///
+	/// A manual group's sizes are left as they were arranged: the layout stops balancing the
+	/// group and everything within it.
+	///
+	/// - New windows are not [grouped] into it, nor added to it by the built-in [layout managers].
+	/// - When changes are applied, its children are only rescaled to fill it: they are not
+	///   [equalized], grown to their groups' minimum sizes, or sized to their [resize increments].
+	/// - It is never [pruned], collapsed, [compacted], or dissolved automatically.
+	///
+	/// # See also
+	/// - [`set_manual`](Self::set_manual)
+	///
+	/// [grouped]: Self::group_window_by
+	/// [layout managers]: crate::layout::managers
+	/// [equalized]: Self::equalize
+	/// [resize increments]: Increments
+	/// [pruned]: Self::prune_empty
+	/// [compacted]: TilingLayout::compact
+	#[inline(always)]
+	pub const fn is_manual(&self) -> bool {
+		self.manual
+	}
+
+	/// Sets whether the group is [manual].
+	///
+	/// # See also
+	/// - [`is_manual`](Self::is_manual)
+	///
+	/// [manual]: Self::is_manual
+	#[inline(always)]
+	pub const fn set_manual(&mut self, manual: bool) {
+		self.manual = manual;
+	}
+
+	/// Returns whether the node with the given `id` is within a [manual] group, including this
+	/// group itself.
+	///
+	/// [manual]: Self::is_manual
+	pub fn is_within_manual(&self, id: NodeId) -> bool {
+		let Some(path) = self.path_of(id) else {
+			return false;
+		};
+
+		(0..path.len()).any(|depth| self.group_at(&path[..depth]).is_some_and(Self::is_manual))
+	}
+}
+
+impl<Window> TilingLayout<Window> {
+	/// Toggles whether the group containing the given `window` is [manual], returning whether it
+	/// now is, or [`None`] if the window isn't in the layout.
+	///
+	/// [manual]: GroupNode::is_manual
+	pub fn toggle_manual(&mut self, window: &Window) -> Option<bool>
+	where
+		Window: PartialEq,
+	{
+		let path = self.id_of_window(window).and_then(|id| self.path_of(id))?;
+		let group = self.group_at_mut(&path[..path.len() - 1])?;
+
+		group.manual = !group.manual;
+
+		Some(group.manual)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	/// Parses a window named by a single character.
+	fn window(name: &str) -> Option<char> {
+		let mut chars = name.chars();
+
+		chars.next().filter(|_| chars.next().is_none())
+	}
+
+	/// Returns the widths of the children of the given `group`.
+	fn widths(group: &GroupNode<char>) -> Vec<u32> {
+		group.iter().map(Node::width).collect()
+	}
+
+	#[test]
+	fn toggle() {
+		let mut layout = TilingLayout::from_description("h(a v(b c))", window).unwrap();
+
+		assert_eq!(layout.toggle_manual(&'b'), Some(true));
+		assert!(layout[1].unwrap_group_ref().is_manual());
+		assert!(!layout.is_manual());
+
+		let id = layout.id_of_window(&'c').unwrap();
+		assert!(layout.is_within_manual(id));
+		assert!(!layout.is_within_manual(layout.id_of_window(&'a').unwrap()));
+
+		assert_eq!(layout.toggle_manual(&'c'), Some(false));
+		assert!(!layout.is_within_manual(id));
+		assert_eq!(layout.toggle_manual(&'e'), None);
+	}
+
+	/// Tests that a manual group is rescaled as a single block while the rest of the layout is
+	/// balanced around it.
+	#[test]
+	fn rescaled_as_block() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout = TilingLayout::from_description("h(50% h(1/6 a 2/6 b 3/6 c) 50% d)", window).unwrap();
+		layout.resize(0, 0, 1200, 600, &settings);
+		apply(&mut layout, &settings);
+
+		let original = widths(layout[0].unwrap_group_ref());
+
+		// Resize increments would otherwise shrink window `a` to a multiple of 64 pixels.
+		layout[0].unwrap_group_mut().set_manual(true);
+		layout[0].unwrap_group_mut()[0]
+			.unwrap_window_mut()
+			.set_increments(Some(Increments {
+				width: 64,
+				height: 1,
+				..Increments::default()
+			}));
+
+		// New windows at the root, with the root equalized, the manual group's equalization
+		// ignored, and the root quadrupling in width.
+		layout.push_window_back('e');
+		layout.push_window_back('f');
+		layout.equalize();
+		layout[0].unwrap_group_mut().equalize();
+		layout.resize(0, 0, 4800, 600, &settings);
+		assert!(layout.compact().is_empty());
+		let settings = LayoutSettings {
+			honor_increments: true,
+			..settings
+		};
+		apply(&mut layout, &settings);
+
+		// The siblings were equalized...
+		assert_eq!(widths(&layout), [1200, 1200, 1200, 1200]);
+		// ...while the manual group's children were exactly scaled.
+		let scaled = widths(layout[0].unwrap_group_ref());
+		let (old_total, new_total) = (600, 1200);
+		for (&old, &new) in original.iter().zip(&scaled) {
+			assert_eq!(new * old_total, old * new_total);
+		}
+
+		// Compacting doesn't touch the manual group either.
+		assert!(layout.compact().is_empty());
+		assert_eq!(widths(layout[0].unwrap_group_ref()), scaled);
+	}
+
+	#[test]
+	fn not_grouped_or_pruned() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout = TilingLayout::from_description("h(a v(b c) d)", window).unwrap();
+		layout.resize(0, 0, 1200, 600, &settings);
+		layout[1].unwrap_group_mut().set_manual(true);
+		apply(&mut layout, &settings);
+
+		// Windows aren't grouped into the manual group...
+		assert_eq!(layout.find_group_by(|&window| window == 'b'), None);
+		assert_eq!(layout.group_window_by('e', |&window| window == 'b'), Err('e'));
+		assert_eq!(layout.find_group_by(|&window| window == 'd'), Some(vec![]));
+
+		// ...and it isn't pruned or collapsed once it's left with one child, or none at all.
+		layout[1].unwrap_group_mut().remove(0);
+		apply(&mut layout, &settings);
+		assert_eq!(layout[1].unwrap_group_ref().len(), 1);
+		assert_eq!(widths(&layout), [400, 400, 400]);
+
+		layout[1].unwrap_group_mut().remove(0);
+		apply(&mut layout, &settings);
+		assert!(layout[1].unwrap_group_ref().is_empty());
+		assert_eq!(layout.len(), 3);
+	}
+}
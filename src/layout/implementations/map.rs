@@ -0,0 +1,163 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl<Window> TilingLayout<Window> {
+	/// Returns a copy of the layout with every window replaced by the one returned by `f` for it.
+	///
+	/// The copy has the same shape, sizes and node IDs. This converts layouts between window types,
+	/// e.g. to persist a layout by its windows' [IDs].
+	///
+	/// [IDs]: crate::window::WindowId
+	pub fn map_windows<Other>(&self, mut f: impl FnMut(&Window) -> Other) -> TilingLayout<Other> {
+		self.try_map_windows(|window| Some(f(window)))
+			.expect("every window was mapped")
+	}
+
+	/// Returns a copy of the layout with every window replaced by the one returned by `f` for it,
+	/// as with [`map_windows`], or [`None`] if `f` returns [`None`] for any window.
+	///
+	/// This is used to resolve the window [IDs] in a persisted layout to windows again.
+	///
+	/// [`map_windows`]: Self::map_windows
+	/// [IDs]: crate::window::WindowId
+	pub fn try_map_windows<Other>(&self, mut f: impl FnMut(&Window) -> Option<Other>) -> Option<TilingLayout<Other>> {
+		Some(TilingLayout {
+			root: self.root.try_map_windows(&mut f)?,
+
+			x: self.x,
+			y: self.y,
+
+			width: self.width,
+			height: self.height,
+
+			deferred: self.deferred,
+			coverage: self.coverage,
+		})
+	}
+}
+
+impl<Window> Node<Window> {
+	fn try_map_windows<Other>(&self, f: &mut impl FnMut(&Window) -> Option<Other>) -> Option<Node<Other>> {
+		match self {
+			Self::Group(group) => group.try_map_windows(f).map(Node::Group),
+			Self::Window(node) => node.try_map_windows(f).map(Node::Window),
+		}
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	fn try_map_windows<Other>(&self, f: &mut impl FnMut(&Window) -> Option<Other>) -> Option<GroupNode<Other>> {
+		let children = self
+			.children
+			.iter()
+			.map(|node| node.try_map_windows(f))
+			.collect::<Option<_>>()?;
+
+		Some(GroupNode {
+			id: self.id,
+			nesting: self.nesting,
+			tally: self.tally,
+
+			orientation: self.orientation,
+
+			children,
+			total_node_primary: self.total_node_primary,
+
+			additions: self.additions.clone(),
+			total_removed_primary: self.total_removed_primary,
+			addition_sizing: self.addition_sizing,
+
+			new_orientation: self.new_orientation,
+
+			gap: self.gap,
+			new_gap: self.new_gap,
+			applied_gap: self.applied_gap,
+
+			stacked: self.stacked,
+			new_stacked: self.new_stacked,
+			active: self.active,
+			new_active: self.new_active,
+
+			dissolving: self.dissolving,
+			keep_alive: self.keep_alive,
+			manual: self.manual,
+			maximize: self.maximize.clone(),
+
+			sizing: self.sizing,
+			new_sizing: self.new_sizing,
+			slack: self.slack,
+			equalize: self.equalize,
+
+			new_width: self.new_width,
+			new_height: self.new_height,
+
+			new_x: self.new_x,
+			new_y: self.new_y,
+
+			width: self.width,
+			height: self.height,
+
+			x: self.x,
+			y: self.y,
+		})
+	}
+}
+
+impl<Window> WindowNode<Window> {
+	fn try_map_windows<Other>(&self, f: &mut impl FnMut(&Window) -> Option<Other>) -> Option<WindowNode<Other>> {
+		Some(WindowNode {
+			id: self.id,
+
+			window: f(&self.window)?,
+			window_changed: self.window_changed,
+			visibility: self.visibility,
+
+			sizing: self.sizing,
+			new_sizing: self.new_sizing,
+
+			increments: self.increments,
+			increments_changed: self.increments_changed,
+			slack: self.slack,
+
+			min_size: self.min_size,
+			border_width: self.border_width,
+
+			width: self.width,
+			height: self.height,
+
+			x: self.x,
+			y: self.y,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	#[test]
+	fn map_windows() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = TilingLayout::<u32>::new(Orientation::LeftToRight, 0, 0, 1200, 600, &settings);
+
+		layout.push_window_back(1);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([2, 3]))
+			.unwrap();
+		apply(&mut layout, &settings);
+
+		let mapped = layout.map_windows(|window| u64::from(*window) * 10);
+		assert_eq!(mapped.windows(), [&10, &20, &30]);
+		assert_eq!(mapped.snapshot(), layout.snapshot());
+		assert_eq!(mapped.path_of(mapped.id_of_window(&30).unwrap()), Some(vec![1, 1]));
+		assert_eq!(mapped.id_of_window(&20), layout.id_of_window(&2));
+
+		assert!(layout
+			.try_map_windows(|&window| (window != 3).then_some(window))
+			.is_none());
+	}
+}
@@ -0,0 +1,347 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use truncate_integer::Shrink;
+
+use super::{node_changes::distribute_remainder, *};
+
+impl<Window> GroupNode<Window> {
+	/// Returns the ID of the group's maximized child, if any.
+	///
+	/// A maximized child takes up the whole group along its [axis], but for a strip of the
+	/// [maximize strip] size for each of its siblings.
+	///
+	/// - Children added to the group while one is maximized join its siblings' strips.
+	/// - The sizes the children had before are restored once the child is no longer maximized, or
+	///   once it is removed from the group. They are rescaled if the group has been resized in the
+	///   meantime.
+	/// - [Resizing a child] or [equalizing] the group ends the maximize, and the children keep the
+	///   sizes they have.
+	///
+	/// # See also
+	/// - [`toggle_maximized`](Self::toggle_maximized)
+	///
+	/// [axis]: Orientation::axis
+	/// [maximize strip]: LayoutSettings::maximize_strip
+	/// [Resizing a child]: Self::resize_child
+	/// [equalizing]: Self::equalize
+	#[inline]
+	pub const fn maximized(&self) -> Option<NodeId> {
+		match self.maximize {
+			Maximize::Maximized { child, .. } => Some(child),
+			_ => None,
+		}
+	}
+
+	/// Maximizes the child with the given `id` within the group, shrinking each of its siblings to
+	/// a strip of `strip` pixels, or restores the children's sizes if it is maximized already.
+	///
+	/// Returns whether the child is now [maximized], or [`None`] if there is no such child or the
+	/// group is [stacked]. Maximizing a child while one of its siblings is maximized keeps the
+	/// sizes from before the sibling was maximized to be restored.
+	///
+	/// [maximized]: Self::maximized
+	/// [stacked]: Self::is_stacked
+	pub fn toggle_maximized(&mut self, id: NodeId, strip: u32) -> Option<bool> {
+		if self.is_stacked() || self.position_of(id).is_none() {
+			return None;
+		}
+
+		let maximized = match mem::take(&mut self.maximize) {
+			Maximize::Maximized { child, restore, .. } if child == id => {
+				self.maximize = Maximize::Restoring(restore);
+
+				false
+			},
+
+			Maximize::Maximized { restore, .. } | Maximize::Restoring(restore) => {
+				self.maximize = Maximize::Maximized {
+					child: id,
+					strip,
+					restore,
+				};
+
+				true
+			},
+
+			Maximize::None => {
+				let axis = self.orientation.axis();
+				// The size of each child's tile, including any space left by its resize increments.
+				let restore = self
+					.children
+					.iter()
+					.map(|node| {
+						let tile = i64::from(node.primary_dimension(axis)) + i64::from(node.slack());
+
+						(node.id(), tile.clamp(0, u32::MAX.into()) as u32)
+					})
+					.collect();
+				self.maximize = Maximize::Maximized {
+					child: id,
+					strip,
+					restore,
+				};
+
+				true
+			},
+		};
+
+		// Make sure the group lays out its children again, even if its size hasn't changed.
+		self.new_width.get_or_insert(self.width);
+		self.new_height.get_or_insert(self.height);
+
+		Some(maximized)
+	}
+
+	/// Returns the sizes of the group's children, in the order they are stored, sharing the
+	/// `available` space along the group's axis if a child is [maximized] or the sizes from
+	/// before are to be restored.
+	///
+	/// If the maximized child is no longer in the group, the maximize ends and the sizes are
+	/// restored. Restored sizes are rescaled to fill the `available` space, with children added
+	/// since they were recorded given the average of the recorded sizes. Pixels lost to rounding
+	/// go one each to the leftmost or topmost children on screen, as with applying changes.
+	///
+	/// [maximized]: Self::maximized
+	pub(super) fn maximized_primaries(&mut self, available: u32, reversed: bool) -> Option<Vec<u32>> {
+		let restore = match mem::take(&mut self.maximize) {
+			Maximize::None => return None,
+
+			Maximize::Maximized { child, strip, restore } => {
+				if let Some(index) = self.children.iter().position(|node| node.id() == child) {
+					self.maximize = Maximize::Maximized { child, strip, restore };
+
+					// Strips which don't fit are narrowed, so that the maximized child is never
+					// narrower than its siblings.
+					let len = self.children.len() as u32;
+					let strip = strip.min(available / len);
+
+					let mut primaries = vec![strip; len as usize];
+					primaries[index] = available - strip * (len - 1);
+
+					return Some(primaries);
+				}
+
+				restore
+			},
+
+			Maximize::Restoring(restore) => restore,
+		};
+
+		let len = self.children.len();
+		if len == 0 {
+			return None;
+		}
+
+		let recorded = |id: NodeId| {
+			restore
+				.iter()
+				.find(|&&(recorded, _)| recorded == id)
+				.map(|&(_, size)| u64::from(size))
+		};
+		let known: Vec<u64> = self.children.iter().filter_map(|node| recorded(node.id())).collect();
+		let average = match known.len() {
+			0 => 1,
+			count => known.iter().sum::<u64>() / count as u64,
+		};
+
+		// `u64` is used because we will be multiplying two 'u32' values, and `u64::MAX` is
+		// `u32::MAX * u32::MAX`.
+		let sizes: Vec<u64> = self
+			.children
+			.iter()
+			.map(|node| recorded(node.id()).unwrap_or(average))
+			.collect();
+		let total: u64 = sizes.iter().sum();
+
+		let mut primaries: Vec<u32> = sizes
+			.into_iter()
+			.map(|size| match total {
+				0 => (u64::from(available) / len as u64).shrink(),
+				total => (size * u64::from(available) / total).shrink(),
+			})
+			.collect();
+		distribute_remainder(&mut primaries, &vec![true; len], available, reversed);
+
+		Some(primaries)
+	}
+}
+
+impl<Window> TilingLayout<Window> {
+	/// Toggles whether the node of the given `window` is [maximized] within its group, shrinking
+	/// its siblings to the [maximize strip], and returns whether it now is.
+	///
+	/// The node is maximized within the deepest group containing the window which isn't [stacked]
+	/// and has more than one child: if the window has no siblings, the group containing it is
+	/// maximized within its own group, and so on. Returns [`None`] if the `window` isn't in the
+	/// layout, or if there is no such group.
+	///
+	/// [maximized]: GroupNode::maximized
+	/// [maximize strip]: LayoutSettings::maximize_strip
+	/// [stacked]: GroupNode::is_stacked
+	pub fn toggle_group_maximize(&mut self, window: &Window, settings: &LayoutSettings) -> Option<bool>
+	where
+		Window: PartialEq,
+	{
+		let path = self.id_of_window(window).and_then(|id| self.path_of(id))?;
+		let depth = (0..path.len()).rev().find(|&depth| {
+			self.group_at(&path[..depth])
+				.is_some_and(|group| !group.is_stacked() && group.len() > 1)
+		})?;
+
+		let group = self.group_at_mut(&path[..depth])?;
+		let id = group[path[depth]].id();
+
+		group.toggle_maximized(id, settings.maximize_strip)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	/// Parses a window named by a single character.
+	fn window(name: &str) -> Option<char> {
+		let mut chars = name.chars();
+
+		chars.next().filter(|_| chars.next().is_none())
+	}
+
+	/// Applies the changes made to the given `layout`, returning the widths of its children.
+	fn widths(layout: &mut TilingLayout<char>, settings: &LayoutSettings) -> Vec<u32> {
+		apply(layout, settings);
+
+		layout.iter().map(Node::width).collect()
+	}
+
+	/// Returns a layout of three windows, `a`, `b` and `c`, 300, 500, and 400 pixels wide.
+	fn layout() -> (TilingLayout<char>, LayoutSettings) {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout = TilingLayout::from_description("h(a b c)", window).unwrap();
+		layout.resize(0, 0, 1200, 600, &settings);
+		assert_eq!(widths(&mut layout, &settings), [400, 400, 400]);
+		assert!(layout.resize_child(0, -100));
+		assert_eq!(widths(&mut layout, &settings), [300, 500, 400]);
+
+		(layout, settings)
+	}
+
+	#[test]
+	fn toggle() {
+		let (mut layout, settings) = layout();
+
+		assert_eq!(layout.toggle_group_maximize(&'b', &settings), Some(true));
+		assert_eq!(layout.maximized(), layout.id_of_window(&'b'));
+		assert_eq!(widths(&mut layout, &settings), [40, 1120, 40]);
+
+		// The sizes from before are restored exactly...
+		assert_eq!(layout.toggle_group_maximize(&'b', &settings), Some(false));
+		assert_eq!(layout.maximized(), None);
+		assert_eq!(widths(&mut layout, &settings), [300, 500, 400]);
+
+		// ...or rescaled if the group was resized in between.
+		assert_eq!(layout.toggle_group_maximize(&'b', &settings), Some(true));
+		layout.resize(0, 0, 600, 600, &settings);
+		assert_eq!(widths(&mut layout, &settings), [40, 520, 40]);
+		assert_eq!(layout.toggle_group_maximize(&'b', &settings), Some(false));
+		assert_eq!(widths(&mut layout, &settings), [150, 250, 200]);
+
+		assert_eq!(layout.toggle_group_maximize(&'d', &settings), None);
+	}
+
+	/// Tests that maximizing a sibling of the maximized child keeps the sizes from before either
+	/// was maximized, and that strips which don't fit are narrowed.
+	#[test]
+	fn replaced_and_narrowed() {
+		let (mut layout, settings) = layout();
+
+		assert_eq!(layout.toggle_group_maximize(&'a', &settings), Some(true));
+		assert_eq!(widths(&mut layout, &settings), [1120, 40, 40]);
+		assert_eq!(layout.toggle_group_maximize(&'c', &settings), Some(true));
+		assert_eq!(widths(&mut layout, &settings), [40, 40, 1120]);
+
+		layout.resize(0, 0, 90, 600, &settings);
+		assert_eq!(widths(&mut layout, &settings), [30, 30, 30]);
+
+		layout.resize(0, 0, 1200, 600, &settings);
+		assert_eq!(layout.toggle_group_maximize(&'c', &settings), Some(false));
+		assert_eq!(widths(&mut layout, &settings), [300, 500, 400]);
+	}
+
+	/// Tests that windows added while a child is maximized join the strips, and are given the
+	/// average of the restored sizes.
+	#[test]
+	fn addition() {
+		let (mut layout, settings) = layout();
+
+		layout.toggle_group_maximize(&'b', &settings);
+		layout.push_window_back('d');
+		assert_eq!(widths(&mut layout, &settings), [40, 1080, 40, 40]);
+
+		assert_eq!(layout.toggle_group_maximize(&'b', &settings), Some(false));
+		assert_eq!(widths(&mut layout, &settings), [225, 375, 300, 300]);
+	}
+
+	/// Tests that removing the maximized child restores its siblings' sizes, while removing one of
+	/// its siblings doesn't.
+	#[test]
+	fn removal() {
+		let (mut layout, settings) = layout();
+
+		layout.toggle_group_maximize(&'b', &settings);
+		assert_eq!(widths(&mut layout, &settings), [40, 1120, 40]);
+		layout.remove(2);
+		assert_eq!(widths(&mut layout, &settings), [40, 1160]);
+		assert_eq!(layout.maximized(), layout.id_of_window(&'b'));
+
+		layout.push_window_back('c');
+		assert_eq!(widths(&mut layout, &settings), [40, 1120, 40]);
+		layout.remove(1);
+		assert_eq!(widths(&mut layout, &settings), [600, 600]);
+		assert_eq!(layout.maximized(), None);
+	}
+
+	/// Tests that resizing a child or equalizing the group ends the maximize without restoring the
+	/// sizes from before.
+	#[test]
+	fn resize_ends_maximize() {
+		let (mut layout, settings) = layout();
+
+		layout.toggle_group_maximize(&'b', &settings);
+		assert_eq!(widths(&mut layout, &settings), [40, 1120, 40]);
+		assert!(layout.resize_child(0, 20));
+		assert_eq!(layout.maximized(), None);
+		assert_eq!(widths(&mut layout, &settings), [60, 1100, 40]);
+
+		// The children are rescaled as usual once the maximize has ended.
+		layout.resize(0, 0, 600, 600, &settings);
+		assert_eq!(widths(&mut layout, &settings), [30, 550, 20]);
+
+		assert_eq!(layout.toggle_group_maximize(&'a', &settings), Some(true));
+		layout.equalize();
+		assert_eq!(widths(&mut layout, &settings), [200, 200, 200]);
+		assert_eq!(layout.maximized(), None);
+	}
+
+	/// Tests that a window without siblings is maximized by maximizing its group.
+	#[test]
+	fn nested() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout = TilingLayout::from_description("h(a v(b) v(c d))", window).unwrap();
+		layout.resize(0, 0, 1200, 600, &settings);
+		layout[2].unwrap_group_mut().set_stacked(true);
+		assert_eq!(widths(&mut layout, &settings), [400, 400, 400]);
+
+		assert_eq!(layout.toggle_group_maximize(&'b', &settings), Some(true));
+		assert_eq!(layout.maximized(), Some(layout[1].id()));
+		assert_eq!(widths(&mut layout, &settings), [40, 1120, 40]);
+
+		// Windows in stacked groups maximize the stacked group.
+		assert_eq!(layout.toggle_group_maximize(&'d', &settings), Some(true));
+		assert_eq!(widths(&mut layout, &settings), [40, 40, 1120]);
+	}
+}
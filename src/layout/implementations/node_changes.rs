@@ -2,12 +2,46 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::mem;
+use std::{
+	collections::{vec_deque, HashMap},
+	mem,
+};
 
+use tracing::{event, Level};
 use truncate_integer::Shrink;
 
 use super::*;
 
+/// The coordinates and dimensions of a rectangle, in the order `(x, y, width, height)`.
+type Rect = (i32, i32, u32, u32);
+
+/// Returns whether the given rectangles overlap.
+const fn intersects((x1, y1, width1, height1): Rect, (x2, y2, width2, height2): Rect) -> bool {
+	let (x1, y1, x2, y2) = (x1 as i64, y1 as i64, x2 as i64, y2 as i64);
+
+	x1 < x2 + (width2 as i64) && x2 < x1 + (width1 as i64) && y1 < y2 + (height2 as i64) && y2 < y1 + (height1 as i64)
+}
+
+impl From<()> for Reconfigured {
+	#[inline(always)]
+	fn from((): ()) -> Self {
+		Self::Applied
+	}
+}
+
+impl Reconfigured {
+	/// Returns the coordinates and dimensions a window given the `requested` ones really has after
+	/// being reconfigured with this outcome, or [`None`] if it was skipped.
+	pub const fn actual(self, requested: Rect) -> Option<Rect> {
+		match self {
+			Self::Applied => Some(requested),
+			Self::Adjusted { x, y, width, height } => Some((x, y, width, height)),
+
+			Self::SkipWindow => None,
+		}
+	}
+}
+
 impl<Window> GroupNode<Window> {
 	/// Rotates the group's [`orientation`] by the given number of `rotations`.
 	///
@@ -48,6 +82,37 @@ impl<Window> GroupNode<Window> {
 	pub fn set_orientation(&mut self, new: Orientation) {
 		self.new_orientation = Some(new);
 	}
+
+	/// Returns the group's [window gap] override, if any.
+	///
+	/// If this is [`None`], the group uses the effective window gap of its parent group or, for
+	/// the root group, the layout-wide [window gap].
+	///
+	/// # See also
+	/// - [`set_gap`](Self::set_gap)
+	///
+	/// [window gap]: LayoutSettings::window_gap
+	// NOTE: This will return the `new_gap` if it is set - for the current gap override before that
+	//       is applied, use the `self.gap` field.
+	pub const fn gap(&self) -> Option<u32> {
+		match self.new_gap {
+			Some(gap) => gap,
+			None => self.gap,
+		}
+	}
+
+	/// Sets the group's [window gap] override.
+	///
+	/// Setting an override of [`None`] makes the group inherit the effective window gap of its
+	/// parent group again. Descendent groups without their own override inherit the new gap.
+	///
+	/// # See also
+	/// - [`gap`](Self::gap)
+	///
+	/// [window gap]: LayoutSettings::window_gap
+	pub const fn set_gap(&mut self, gap: Option<u32>) {
+		self.new_gap = Some(gap);
+	}
 }
 
 impl<Window> GroupNode<Window> {
@@ -56,7 +121,9 @@ impl<Window> GroupNode<Window> {
 	/// [node]: Node
 	pub fn remove(&mut self, index: usize) -> Option<Node<Window>> {
 		if index < self.children.len() {
-			let index = if !self.orientation.reversed() {
+			self.activate_neighbor(index);
+
+			let index = if !self.orientation().reversed() {
 				index
 			} else {
 				let last = self.children.len() - 1;
@@ -67,6 +134,7 @@ impl<Window> GroupNode<Window> {
 
 			if let Some(node) = &node {
 				self.track_remove(index);
+				self.count_removed(node);
 
 				self.total_removed_primary += node.primary_dimension(self.orientation.axis());
 			}
@@ -77,6 +145,129 @@ impl<Window> GroupNode<Window> {
 		}
 	}
 
+	/// Moves the [node] at the index `from` to the index `to`, shifting the nodes between them
+	/// over by one.
+	///
+	/// This only changes the order of the group's children: every node keeps its size, and the
+	/// [active] child stays active wherever it is moved. A node which was added since changes were
+	/// last applied is still added, and still splits the node it was added to split.
+	///
+	/// # Panics
+	/// Panics if there is no node at either index.
+	///
+	/// [node]: Node
+	/// [active]: Self::active
+	pub fn move_child(&mut self, from: usize, to: usize) {
+		let len = self.children.len();
+		assert!(from < len, "there is no node at index {from} to move");
+		assert!(to < len, "there is no index {to} to move a node to");
+
+		if from == to {
+			return;
+		}
+
+		let (from, to) = if !self.orientation().reversed() {
+			(from, to)
+		} else {
+			(len - 1 - from, len - 1 - to)
+		};
+
+		let node = self.children.remove(from).expect("we just checked the index");
+		self.children.insert(to, node);
+
+		for addition in &mut self.additions {
+			addition.moved(from, to);
+		}
+		self.additions
+			.make_contiguous()
+			.sort_unstable_by_key(|addition| addition.index);
+
+		// Make sure the group lays out its children again, even if its size hasn't changed.
+		self.new_width.get_or_insert(self.width);
+		self.new_height.get_or_insert(self.height);
+	}
+
+	/// Replaces the [node] at the given `index` with the given `node`, returning the node it
+	/// replaced.
+	///
+	/// The new node takes the old node's place, coordinates, and dimensions: it is not tracked as
+	/// an addition, nor the old node as a removal, so no other nodes are resized when changes are
+	/// next applied. A new group lays out its children within the old node's place.
+	///
+	/// If the new node is a group which would be nested too deeply, it is [flattened].
+	///
+	/// # Panics
+	/// Panics if there is no node at the given `index`.
+	///
+	/// [node]: Node
+	/// [flattened]: DepthPolicy::Flatten
+	pub fn replace(&mut self, index: usize, node: Node<Window>) -> Node<Window> {
+		self.swap_in(index, |old| (node, old))
+	}
+
+	/// Replaces the [node] at the given `index` with the node returned by `f` given that node, as
+	/// if with [`replace`].
+	///
+	/// This is useful to transform a node in place, like wrapping a window in a new group to split
+	/// it.
+	///
+	/// # Panics
+	/// Panics if there is no node at the given `index`.
+	///
+	/// [node]: Node
+	/// [`replace`]: Self::replace
+	pub fn replace_with(&mut self, index: usize, f: impl FnOnce(Node<Window>) -> Node<Window>) {
+		self.swap_in(index, |old| (f(old), ()));
+	}
+
+	/// Replaces the node at the given `index` with the node returned by `f` given that node,
+	/// without tracking any changes, and returns what else `f` returns.
+	fn swap_in<T>(&mut self, index: usize, f: impl FnOnce(Node<Window>) -> (Node<Window>, T)) -> T {
+		let len = self.children.len();
+		assert!(index < len, "there is no node at index {index} to replace");
+
+		let index = if !self.orientation().reversed() {
+			index
+		} else {
+			len - 1 - index
+		};
+
+		let old = self.children.remove(index).expect("we just checked the index");
+		let (x, y, width, height) = (old.x(), old.y(), old.width(), old.height());
+		self.count_removed(&old);
+
+		let (mut node, other) = f(old);
+
+		match &mut node {
+			Node::Window(window) => {
+				(window.x, window.y, window.width, window.height) = (x, y, width, height);
+				window.window_changed = true;
+			},
+
+			Node::Group(group) => {
+				(group.x, group.y, group.width, group.height) = (x, y, width, height);
+				(group.new_x, group.new_y) = (None, None);
+
+				// Make sure the group lays out its children within its new place, even if its size
+				// hasn't changed.
+				(group.new_width, group.new_height) = (Some(width), Some(height));
+
+				group.set_nesting(self.nesting.child());
+				group.flatten_too_deep();
+			},
+		}
+
+		self.count_added(&node);
+		let group = node.is_group();
+		self.children.insert(index, node);
+
+		if group && !self.can_nest_group() {
+			self.flatten_too_deep();
+		}
+
+		other
+	}
+
 	/// Removes the [node] at the end of the group.
 	///
 	/// [node]: Node
@@ -85,20 +276,24 @@ impl<Window> GroupNode<Window> {
 			// `children` is empty
 			0 => None,
 			// `children` is not empty
-			_ => {
-				if !self.orientation.reversed() {
+			len => {
+				self.activate_neighbor(len - 1);
+
+				if !self.orientation().reversed() {
 					let node = self.children.pop_back();
 
-					if node.is_some() {
+					if let Some(node) = &node {
 						self.track_pop_back();
+						self.count_removed(node);
 					}
 
 					node
 				} else {
 					let node = self.children.pop_front();
 
-					if node.is_some() {
+					if let Some(node) = &node {
 						self.track_pop_front();
+						self.count_removed(node);
 					}
 
 					node
@@ -116,19 +311,23 @@ impl<Window> GroupNode<Window> {
 			0 => None,
 			// `children` is not empty
 			_ => {
-				if !self.orientation.reversed() {
+				self.activate_neighbor(0);
+
+				if !self.orientation().reversed() {
 					let node = self.children.pop_front();
 
-					if node.is_some() {
+					if let Some(node) = &node {
 						self.track_pop_front();
+						self.count_removed(node);
 					}
 
 					node
 				} else {
 					let node = self.children.pop_back();
 
-					if node.is_some() {
+					if let Some(node) = &node {
 						self.track_pop_back();
+						self.count_removed(node);
 					}
 
 					node
@@ -137,6 +336,47 @@ impl<Window> GroupNode<Window> {
 		}
 	}
 
+	/// Removes every [node] from the group and its descendents, returning their windows in order.
+	///
+	/// Unlike [removing] nodes one by one, this leaves no changes pending: the group is simply
+	/// empty.
+	///
+	/// [node]: Node
+	/// [removing]: Self::remove
+	pub fn clear(&mut self) -> Vec<Window> {
+		let mut windows = Vec::new();
+		self.drain_windows(&mut windows);
+		self.clear_tally();
+
+		self.total_node_primary = 0;
+		self.additions.clear();
+		self.total_removed_primary = 0;
+
+		self.active = None;
+		self.new_active = None;
+		self.equalize = false;
+
+		windows
+	}
+
+	/// Removes every [node] from the group and its descendents, pushing their windows to `windows`
+	/// in order.
+	///
+	/// [node]: Node
+	fn drain_windows(&mut self, windows: &mut Vec<Window>) {
+		let mut children = mem::take(&mut self.children);
+		if self.orientation().reversed() {
+			children.make_contiguous().reverse();
+		}
+
+		for node in children {
+			match node {
+				Node::Window(node) => windows.push(node.into_window()),
+				Node::Group(mut group) => group.drain_windows(windows),
+			}
+		}
+	}
+
 	/// Pushes a new [window node] with the given `window` to the end of the group.
 	///
 	/// [window node]: WindowNode
@@ -153,6 +393,57 @@ impl<Window> GroupNode<Window> {
 		self.push_nodes_back(windows.into_iter().map(Node::new_window));
 	}
 
+	/// Pushes a new [window node] with the given `window` to the end of the group, keeping the
+	/// window's existing coordinates and dimensions.
+	///
+	/// Unlike with [`push_window_back`], the node isn't given an equal share of the group when
+	/// changes are next applied: it is rescaled along with the group's other nodes, so windows
+	/// which are adopted with their existing geometries keep their relative sizes.
+	///
+	/// [window node]: WindowNode
+	/// [`push_window_back`]: Self::push_window_back
+	#[inline]
+	pub fn adopt_window_back(&mut self, window: Window, (x, y, width, height): (i32, i32, u32, u32)) {
+		self.adopt_node_back(Node::new_window_with(window, x, y, width, height));
+	}
+
+	/// Pushes a new [group node] of the given `orientation` to the end of the group with the given
+	/// coordinates and dimensions, then initialises it with the given `init` function.
+	///
+	/// As with [`adopt_window_back`], the group is rescaled along with the other nodes when changes
+	/// are next applied, rather than being given an equal share of the group.
+	///
+	/// # Errors
+	/// See [nesting groups too deeply]. If the new group is flattened, its children are adopted
+	/// with their own coordinates and dimensions instead.
+	///
+	/// [group node]: GroupNode
+	/// [`adopt_window_back`]: Self::adopt_window_back
+	/// [nesting groups too deeply]: Self#nesting-groups-too-deeply
+	#[inline]
+	pub fn adopt_group_back_with(
+		&mut self,
+		orientation: Orientation,
+		(x, y, width, height): (i32, i32, u32, u32),
+		init: impl FnOnce(&mut GroupNode<Window>),
+	) -> Result<(), DepthError> {
+		let flatten = self.check_nesting(0)?;
+
+		let mut group = GroupNode::with(orientation, x, y, width, height);
+		group.nesting = self.nesting.child();
+		init(&mut group);
+
+		if flatten {
+			for node in group {
+				self.adopt_node_back(node);
+			}
+		} else {
+			self.adopt_node_back(Node::Group(group));
+		}
+
+		Ok(())
+	}
+
 	/// Pushes a new [window node] with the given `window` to the beginning of the group.
 	///
 	/// [window node]: WindowNode
@@ -187,97 +478,179 @@ impl<Window> GroupNode<Window> {
 
 	/// Pushes a new [group node] of the given `orientation` to the end of the group.
 	///
+	/// # Errors
+	/// See [nesting groups too deeply].
+	///
 	/// [group node]: GroupNode
+	/// [nesting groups too deeply]: Self#nesting-groups-too-deeply
 	#[inline]
-	pub fn push_group_back(&mut self, orientation: Orientation) {
-		self.push_node_back(Node::new_group(orientation));
+	pub fn push_group_back(&mut self, orientation: Orientation) -> Result<(), DepthError> {
+		self.push_group_back_with(orientation, |_| ())
 	}
 
 	/// Pushes a new [group node] of the given `orientation` to the end of the group, then
 	/// initialises it with the given `init` function.
 	///
+	/// # Errors
+	/// See [nesting groups too deeply].
+	///
 	/// [group node]: GroupNode
+	/// [nesting groups too deeply]: Self#nesting-groups-too-deeply
 	#[inline]
-	pub fn push_group_back_with(&mut self, orientation: Orientation, init: impl FnOnce(&mut GroupNode<Window>)) {
-		let index = self.push_node_back(Node::new_group(orientation));
-
-		match &mut self.children[index] {
-			Node::Group(group) => init(group),
-			Node::Window(_) => unreachable!("we know the this node is a group, because we just added it"),
-		}
+	pub fn push_group_back_with(
+		&mut self,
+		orientation: Orientation,
+		init: impl FnOnce(&mut GroupNode<Window>),
+	) -> Result<(), DepthError> {
+		self.add_group_with(orientation, init, Self::push_node_back, false)
 	}
 
 	/// Pushes new [group nodes] of the given `orientations` to the end of the group.
 	///
+	/// # Errors
+	/// See [nesting groups too deeply].
+	///
 	/// [group nodes]: GroupNode
+	/// [nesting groups too deeply]: Self#nesting-groups-too-deeply
 	#[inline]
-	pub fn push_groups_back(&mut self, orientations: impl IntoIterator<Item = Orientation>) {
-		self.push_nodes_back(orientations.into_iter().map(Node::new_group));
+	pub fn push_groups_back(&mut self, orientations: impl IntoIterator<Item = Orientation>) -> Result<(), DepthError> {
+		self.add_groups(orientations, Self::push_nodes_back)
 	}
 
 	/// Pushes a new [group node] of the given `orientation` to the beginning of the group.
 	///
+	/// # Errors
+	/// See [nesting groups too deeply].
+	///
 	/// [group node]: GroupNode
+	/// [nesting groups too deeply]: Self#nesting-groups-too-deeply
 	#[inline]
-	pub fn push_group_front(&mut self, orientation: Orientation) {
-		self.push_node_front(Node::new_group(orientation));
+	pub fn push_group_front(&mut self, orientation: Orientation) -> Result<(), DepthError> {
+		self.push_group_front_with(orientation, |_| ())
 	}
 
 	/// Pushes a new [group node] of the given `orientation` to the beginning of the group, then
 	/// initialises it with the given `init` function.
 	///
+	/// # Errors
+	/// See [nesting groups too deeply].
+	///
 	/// [group node]: GroupNode
+	/// [nesting groups too deeply]: Self#nesting-groups-too-deeply
 	#[inline]
-	pub fn push_group_front_with(&mut self, orientation: Orientation, init: impl FnOnce(&mut GroupNode<Window>)) {
-		let index = self.push_node_front(Node::new_group(orientation));
-
-		match &mut self.children[index] {
-			Node::Group(group) => init(group),
-			Node::Window(_) => unreachable!("we know the this node is a group, because we just added it"),
-		}
+	pub fn push_group_front_with(
+		&mut self,
+		orientation: Orientation,
+		init: impl FnOnce(&mut GroupNode<Window>),
+	) -> Result<(), DepthError> {
+		self.add_group_with(orientation, init, Self::push_node_front, true)
 	}
 
 	/// Pushes new [group nodes] of the given `orientations` to the beginning of the group.
 	///
+	/// # Errors
+	/// See [nesting groups too deeply].
+	///
 	/// [group nodes]: GroupNode
+	/// [nesting groups too deeply]: Self#nesting-groups-too-deeply
 	#[inline]
-	pub fn push_groups_front(&mut self, orientations: impl IntoIterator<Item = Orientation>) {
-		self.push_nodes_front(orientations.into_iter().map(Node::new_group))
+	pub fn push_groups_front(&mut self, orientations: impl IntoIterator<Item = Orientation>) -> Result<(), DepthError> {
+		self.add_groups(orientations, Self::push_nodes_front)
 	}
 
 	/// Inserts a new [group node] of the given `orientation` at the given `index` in the group.
 	///
+	/// # Errors
+	/// See [nesting groups too deeply].
+	///
 	/// [group node]: GroupNode
+	/// [nesting groups too deeply]: Self#nesting-groups-too-deeply
 	#[inline]
-	pub fn insert_group(&mut self, index: usize, orientation: Orientation) {
-		self.insert_node(index, Node::new_group(orientation));
+	pub fn insert_group(&mut self, index: usize, orientation: Orientation) -> Result<(), DepthError> {
+		self.insert_group_with(index, orientation, |_| ())
 	}
 
 	/// Inserts a new [group node] of the given `orientation` at the given `index` in the group,
 	/// then initialises it with the given `init` function.
 	///
+	/// # Errors
+	/// See [nesting groups too deeply].
+	///
 	/// [group node]: GroupNode
+	/// [nesting groups too deeply]: Self#nesting-groups-too-deeply
 	#[inline]
 	pub fn insert_group_with(
 		&mut self,
 		index: usize,
 		orientation: Orientation,
 		init: impl FnOnce(&mut GroupNode<Window>),
-	) {
-		let index = self.insert_node(index, Node::new_group(orientation));
+	) -> Result<(), DepthError> {
+		self.add_group_with(orientation, init, |group, node| group.insert_node(index, node), true)
+	}
 
-		match &mut self.children[index] {
-			Node::Group(group) => init(group),
-			Node::Window(_) => unreachable!("we know this node is a group, because we just added it"),
-		}
+	/// Inserts a new [window node] with the given `window` directly after the node at the given
+	/// `split` index, which it splits.
+	///
+	/// With [`FractionOfSplitNode`] [addition sizing], the new node takes its fraction of the split
+	/// node's size when changes are next applied.
+	///
+	/// # Panics
+	/// This panics if there is no node at the `split` index.
+	///
+	/// [window node]: WindowNode
+	/// [`FractionOfSplitNode`]: AdditionSizing::FractionOfSplitNode
+	/// [addition sizing]: Self::addition_sizing
+	#[inline]
+	pub fn insert_window_splitting(&mut self, split: usize, window: Window) {
+		self.insert_node_splitting(split, Node::new_window(window));
+	}
+
+	/// Inserts a new [group node] of the given `orientation` directly after the node at the given
+	/// `split` index, which it splits, then initialises it with the given `init` function.
+	///
+	/// With [`FractionOfSplitNode`] [addition sizing], the new node takes its fraction of the split
+	/// node's size when changes are next applied.
+	///
+	/// # Panics
+	/// This panics if there is no node at the `split` index.
+	///
+	/// # Errors
+	/// See [nesting groups too deeply]. If the new group is flattened, each of its children splits
+	/// the node at the `split` index instead.
+	///
+	/// [group node]: GroupNode
+	/// [`FractionOfSplitNode`]: AdditionSizing::FractionOfSplitNode
+	/// [addition sizing]: Self::addition_sizing
+	/// [nesting groups too deeply]: Self#nesting-groups-too-deeply
+	#[inline]
+	pub fn insert_group_splitting_with(
+		&mut self,
+		split: usize,
+		orientation: Orientation,
+		init: impl FnOnce(&mut GroupNode<Window>),
+	) -> Result<(), DepthError> {
+		self.add_group_with(
+			orientation,
+			init,
+			|group, node| group.insert_node_splitting(split, node),
+			true,
+		)
 	}
 
 	/// Inserts new [group nodes] of the given `orientations` at the given `index` in the group.
 	///
+	/// # Errors
+	/// See [nesting groups too deeply].
+	///
 	/// [group nodes]: GroupNode
+	/// [nesting groups too deeply]: Self#nesting-groups-too-deeply
 	#[inline]
-	pub fn insert_groups(&mut self, index: usize, orientations: impl IntoIterator<Item = Orientation>) {
-		self.insert_nodes(index, orientations.into_iter().map(Node::new_group));
+	pub fn insert_groups(
+		&mut self,
+		index: usize,
+		orientations: impl IntoIterator<Item = Orientation>,
+	) -> Result<(), DepthError> {
+		self.add_groups(orientations, |group, nodes| group.insert_nodes(index, nodes))
 	}
 
 	/// Push the given `node` to the list, and return the index it was pushed to.
@@ -285,7 +658,9 @@ impl<Window> GroupNode<Window> {
 	/// The index is affected by whether this group is [reversed] or not.
 	///
 	/// [reversed]: Orientation::reversed
-	fn push_node_back(&mut self, node: Node<Window>) -> usize {
+	pub(super) fn push_node_back(&mut self, node: Node<Window>) -> usize {
+		self.count_added(&node);
+
 		if !self.orientation().reversed() {
 			// The orientation is not reversed; we push to the end of the list as usual.
 
@@ -308,7 +683,67 @@ impl<Window> GroupNode<Window> {
 		}
 	}
 
+	/// Insert the given `node` directly after the node at the `split` index, tracking it as an
+	/// addition which splits that node, and return the index it was inserted at.
+	///
+	/// The indexes are affected by whether this group is [reversed] or not.
+	///
+	/// [reversed]: Orientation::reversed
+	fn insert_node_splitting(&mut self, split: usize, node: Node<Window>) -> usize {
+		assert!(
+			split < self.children.len(),
+			"there is no node at index {split} to split"
+		);
+
+		let (index, split) = if !self.orientation().reversed() {
+			(split + 1, split)
+		} else {
+			// The orientation is reversed; the node after the split node is stored before it.
+			let split = self.children.len() - 1 - split;
+
+			(split, split + 1)
+		};
+
+		self.count_added(&node);
+		self.children.insert(index, node);
+		self.track_insert(index);
+
+		if let Ok(addition) = self.additions.binary_search_by_key(&index, |addition| addition.index) {
+			self.additions[addition].splits = Some(split);
+		}
+
+		index
+	}
+
+	/// Push the given `node` to the list without tracking it as an addition, so that it keeps its
+	/// size relative to the other nodes.
+	///
+	/// The node's size counts toward the total size of the nodes, so it is rescaled along with them
+	/// when changes are next applied.
+	fn adopt_node_back(&mut self, node: Node<Window>) {
+		let primary = node.primary_dimension(self.orientation.axis());
+		self.total_node_primary = self.total_node_primary.saturating_add(primary);
+
+		// Make sure the group lays out its children again, even if its size hasn't changed.
+		self.new_width.get_or_insert(self.width);
+		self.new_height.get_or_insert(self.height);
+
+		self.count_added(&node);
+		if !self.orientation().reversed() {
+			self.children.push_back(node);
+		} else {
+			self.children.push_front(node);
+
+			// Move all the additions over by one.
+			for addition in &mut self.additions {
+				addition.inserted(0);
+			}
+		}
+	}
+
 	fn push_node_front(&mut self, node: Node<Window>) -> usize {
+		self.count_added(&node);
+
 		if !self.orientation().reversed() {
 			const INDEX: usize = 0;
 
@@ -334,11 +769,13 @@ impl<Window> GroupNode<Window> {
 
 		if !self.orientation().reversed() {
 			for node in nodes {
+				self.count_added(&node);
 				self.children.push_back(node);
 				self.track_push_back();
 			}
 		} else {
 			for node in nodes {
+				self.count_added(&node);
 				self.children.push_front(node);
 				self.track_push_back();
 			}
@@ -353,11 +790,13 @@ impl<Window> GroupNode<Window> {
 
 		if !self.orientation().reversed() {
 			for node in nodes {
+				self.count_added(&node);
 				self.children.push_front(node);
 				self.track_push_front();
 			}
 		} else {
 			for node in nodes {
+				self.count_added(&node);
 				self.children.push_back(node);
 				self.track_push_front();
 			}
@@ -369,7 +808,9 @@ impl<Window> GroupNode<Window> {
 	/// The index is affected by whether this group is [reversed] or not.
 	///
 	/// [reversed]: Orientation::reversed
-	fn insert_node(&mut self, index: usize, node: Node<Window>) -> usize {
+	pub(super) fn insert_node(&mut self, index: usize, node: Node<Window>) -> usize {
+		self.count_added(&node);
+
 		if !self.orientation().reversed() {
 			// The orientation is not reversed; we insert as usual.
 
@@ -382,8 +823,7 @@ impl<Window> GroupNode<Window> {
 			// impression we are inserting at `index` counting from the front in the non-reversed orientation
 			// equivalent.
 
-			let last = self.children.len() - 1;
-			let index = last - index;
+			let index = self.children.len() - index;
 
 			self.children.insert(index, node);
 			self.track_insert(index);
@@ -400,14 +840,15 @@ impl<Window> GroupNode<Window> {
 
 		if !self.orientation().reversed() {
 			for (index, node) in nodes.enumerate().map(|(i, node)| (index + i, node)) {
+				self.count_added(&node);
 				self.children.insert(index, node);
 				self.track_insert(index);
 			}
 		} else {
-			let last = self.children.len() - 1;
-			let index = last - index;
+			let index = self.children.len() - index;
 
 			for node in nodes {
+				self.count_added(&node);
 				self.children.insert(index, node);
 				self.track_insert(index);
 			}
@@ -416,13 +857,13 @@ impl<Window> GroupNode<Window> {
 
 	/// Update `additions` to reflect a node being inserted at `index`.
 	fn track_insert(&mut self, index: usize) {
-		let insertion_point = self.additions.partition_point(|&i| i < index);
-		self.additions.insert(insertion_point, index);
-
-		// Move following additions over by 1.
-		for addition in &mut self.additions.make_contiguous()[(insertion_point + 1)..] {
-			*addition += 1;
+		// Move following additions, and the nodes they split, over by 1.
+		for addition in &mut self.additions {
+			addition.inserted(index);
 		}
+
+		let insertion_point = self.additions.partition_point(|addition| addition.index < index);
+		self.additions.insert(insertion_point, Addition::new(index));
 	}
 
 	/// Update `additions` to reflect a node being pushed to the end of `nodes`.
@@ -431,7 +872,7 @@ impl<Window> GroupNode<Window> {
 		let index = self.children.len() - 1;
 
 		// If the node has been pushed to the end, then it must have the greatest index.
-		self.additions.push_back(index);
+		self.additions.push_back(Addition::new(index));
 
 		// There will be no additions following it to move over, as it was pushed to the end.
 	}
@@ -440,30 +881,23 @@ impl<Window> GroupNode<Window> {
 		// Move every existing addition over by 1; if the node has been pushed to the front, it has the
 		// lowest index.
 		for addition in &mut self.additions {
-			*addition += 1;
+			addition.inserted(0);
 		}
 
 		// Push the addition to the front.
-		self.additions.push_front(0);
+		self.additions.push_front(Addition::new(0));
 	}
 
 	/// Update `additions` to reflect the removal of a node at `index`.
 	fn track_remove(&mut self, index: usize) {
-		let shifted_additions = match self.additions.binary_search(&index) {
-			// An addition we were tracking was removed.
-			Ok(addition) => {
-				self.additions.remove(addition);
-
-				addition..
-			},
-
-			// The removed node was not an addition we were tracking.
-			Err(removal_point) => removal_point..,
-		};
+		// An addition we were tracking was removed.
+		if let Ok(addition) = self.additions.binary_search_by_key(&index, |addition| addition.index) {
+			self.additions.remove(addition);
+		}
 
-		// Move following additions back by 1.
-		for addition in &mut self.additions.make_contiguous()[shifted_additions] {
-			*addition -= 1;
+		// Move following additions, and the nodes they split, back by 1.
+		for addition in &mut self.additions {
+			addition.removed(index);
 		}
 	}
 
@@ -474,9 +908,14 @@ impl<Window> GroupNode<Window> {
 			let index = self.children.len();
 
 			// If it was one of our own additions, pop that addition.
-			if self.additions[self.additions.len() - 1] == index {
+			if self.additions[self.additions.len() - 1].index == index {
 				self.additions.pop_back();
 			}
+
+			// Additions which split the popped node no longer split anything.
+			for addition in &mut self.additions {
+				addition.removed(index);
+			}
 		}
 	}
 
@@ -486,83 +925,873 @@ impl<Window> GroupNode<Window> {
 			const INDEX: usize = 0;
 
 			// If it was one of our own additions, pop that addition.
-			if self.additions[0] == INDEX {
+			if self.additions[0].index == INDEX {
 				self.additions.pop_front();
 			}
 		}
 
 		// Move all the additions back by one.
 		for addition in &mut self.additions {
-			*addition -= 1;
+			addition.removed(0);
 		}
 	}
 }
 
-impl<Window> GroupNode<Window> {
-	/// Returns whether any changes have been made by the [layout manager] to this group (directly
-	/// or indirectly).
-	///
-	/// [layout manager]: TilingLayoutManager
-	fn changes_made(&self) -> bool {
-		!self.additions.is_empty()
-			|| self.total_removed_primary != 0
-			|| self.new_orientation.is_some()
-			|| self.new_width.is_some()
-			|| self.new_height.is_some()
-			|| self.new_x.is_some()
-			|| self.new_y.is_some()
+impl Addition {
+	/// Creates an addition of the node at the given `index` which doesn't split another node.
+	#[inline]
+	const fn new(index: usize) -> Self {
+		Self { index, splits: None }
 	}
 
-	/// Applies the changes made by the [layout manager].
+	/// Updates the addition to reflect a node being inserted at `index`.
+	const fn inserted(&mut self, index: usize) {
+		if self.index >= index {
+			self.index += 1;
+		}
+
+		if let Some(split) = &mut self.splits {
+			if *split >= index {
+				*split += 1;
+			}
+		}
+	}
+
+	/// Updates the addition to reflect the node at `from` being moved to `to`.
+	const fn moved(&mut self, from: usize, to: usize) {
+		self.index = moved_index(self.index, from, to);
+
+		if let Some(split) = &mut self.splits {
+			*split = moved_index(*split, from, to);
+		}
+	}
+
+	/// Updates the addition to reflect another node being removed from `index`.
+	const fn removed(&mut self, index: usize) {
+		if self.index > index {
+			self.index -= 1;
+		}
+
+		self.splits = match self.splits {
+			// The split node was removed.
+			Some(split) if split == index => None,
+			Some(split) if split > index => Some(split - 1),
+
+			splits => splits,
+		};
+	}
+}
+
+/// Returns the index of the node at `index` after the node at `from` is moved to `to`.
+const fn moved_index(index: usize, from: usize, to: usize) -> usize {
+	if index == from {
+		to
+	} else if from < index && index <= to {
+		index - 1
+	} else if to <= index && index < from {
+		index + 1
+	} else {
+		index
+	}
+}
+
+impl<Window> TilingLayout<Window> {
+	/// Applies the changes made by the [layout manager], unless the layout's dimensions are
+	/// degenerate.
 	///
-	/// `resize_window` is a function that resizes the given window based on the given x and y
-	/// coordinates and width and height (in that order).
+	/// If the layout is too small to fit its nodes (see [`is_deferred`]), the changes are left
+	/// pending and `reconfigure_window` is not called. They will be applied by the first call
+	/// after the layout is [resized] to dimensions that fit.
 	///
-	/// [layout manager]: TilingLayoutManager
+	/// Empty groups and chains of groups are [pruned] first.
 	///
-	/// [primary]: Node::primary_dimension
-	/// [secondary]: Node::secondary_dimension
-	pub(crate) fn apply_changes<Error>(
+	/// Returns the windows which were [skipped], as with [`GroupNode::apply_changes`].
+	///
+	/// [layout manager]: TilingLayoutManager
+	/// [`is_deferred`]: TilingLayout::is_deferred
+	/// [resized]: TilingLayout::resize
+	/// [pruned]: GroupNode::prune_empty
+	/// [skipped]: Reconfigured::SkipWindow
+	pub(crate) fn apply_changes<Outcome, Error>(
 		&mut self,
-		reconfigure_window: &mut impl FnMut(&Window, i32, i32, u32, u32) -> Result<(), Error>,
+		reconfigure_window: &mut impl FnMut(&Window, i32, i32, u32, u32, Visibility) -> Result<Outcome, Error>,
 		settings: &LayoutSettings,
-	) -> Result<(), Error> {
-		// FIXME: need to also determine whether changes have been made to the layout settings.
-		// If no changes have been made to this group, apply all the child groups' changes and return.
-		if !self.changes_made() {
-			for node in self {
-				match node {
-					Node::Group(group) => group.apply_changes(reconfigure_window, settings)?,
+	) -> Result<Vec<Window>, Error>
+	where
+		Window: Clone,
+		Outcome: Into<Reconfigured>,
+	{
+		let pruned = self.root.prune_empty();
+		if pruned != 0 {
+			event!(
+				Level::DEBUG,
+				"Pruned {pruned} empty or redundant groups from the layout"
+			);
+		}
+		// Collapsing chains of groups moves their descendents up, and layouts which have been
+		// deserialized don't know their groups' depths at all.
+		self.root.set_nesting(Nesting::root(settings));
+
+		// Whether smart gaps apply depends on whether there is a lone window, which windows being
+		// added or removed may have changed.
+		let (x, y, width, height) = self.root_bounds(settings);
+		let root = &mut self.root;
+		if (x, y, width, height)
+			!= (
+				root.new_x.unwrap_or(root.x),
+				root.new_y.unwrap_or(root.y),
+				root.new_width.unwrap_or(root.width),
+				root.new_height.unwrap_or(root.height),
+			) {
+			root.new_x = Some(x);
+			root.new_y = Some(y);
+
+			root.new_width = Some(width);
+			root.new_height = Some(height);
+		}
 
-					Node::Window(WindowNode {
-						window,
-						window_changed,
-						x,
-						y,
-						width,
-						height,
-					}) => {
-						if mem::take(window_changed) {
-							reconfigure_window(window, *x, *y, *width, *height)?
-						}
-					},
-				}
-			}
+		let (width, height) = (
+			self.root.new_width.unwrap_or(self.root.width),
+			self.root.new_height.unwrap_or(self.root.height),
+		);
+		let (min_width, min_height) = self.root.minimum_size(settings.window_gap);
+
+		self.deferred = width == 0 || height == 0 || width < min_width || height < min_height;
 
-			return Ok(());
+		if self.deferred {
+			return Ok(Vec::new());
 		}
 
-		let additions = mem::take(&mut self.additions);
-		let total_removed_primary = mem::take(&mut self.total_removed_primary);
+		let skipped = self.root.apply_changes(reconfigure_window, settings)?;
+		self.update_stats();
 
-		let new_orientation = mem::take(&mut self.new_orientation);
+		#[cfg(debug_assertions)]
+		if let Err(error) = self.validate() {
+			panic!("applying changes to the layout broke one of its invariants: {error}");
+		}
 
-		let new_width = mem::take(&mut self.new_width);
-		let new_height = mem::take(&mut self.new_height);
+		Ok(skipped)
+	}
 
-		let new_x = mem::take(&mut self.new_x);
+	/// Removes every window from the layout, returning them in order, and gives the layout the
+	/// given `orientation`.
+	///
+	/// The layout is left as if it were newly created: there are no changes pending. See
+	/// [`GroupNode::clear`].
+	pub fn reset(&mut self, orientation: Orientation) -> Vec<Window> {
+		let windows = self.root.clear();
+
+		self.root.orientation = orientation;
+		self.root.new_orientation = None;
+
+		windows
+	}
+
+	/// Adjusts the effective [window gap] of the group containing the given `window` by `delta`
+	/// pixels, clamped at zero.
+	///
+	/// The adjusted gap is set as the group's [gap override]. Returns [`false`] if the `window` is
+	/// not in the layout.
+	///
+	/// [window gap]: LayoutSettings::window_gap
+	/// [gap override]: GroupNode::set_gap
+	pub fn adjust_gap(&mut self, window: &Window, delta: i32, settings: &LayoutSettings) -> bool
+	where
+		Window: PartialEq,
+	{
+		self.root.adjust_gap(window, delta, settings.window_gap)
+	}
+}
+
+impl<Window> WindowNode<Window> {
+	/// Returns the minimum width and height of the window's tile: the minimum size from its size
+	/// hints, or one pixel if it has none, plus its border on either side.
+	pub fn minimum_size(&self) -> (u32, u32) {
+		let (width, height) = self.min_size.unwrap_or((1, 1));
+		let border = self.border_width.saturating_mul(2);
+
+		(
+			width.max(1).saturating_add(border),
+			height.max(1).saturating_add(border),
+		)
+	}
+
+	/// Sets the minimum size from the window's size hints, and the width of its border.
+	///
+	/// Changes are taken into account the next time changes are applied.
+	pub const fn set_minimum_size(&mut self, min_size: Option<(u32, u32)>, border_width: u32) {
+		self.min_size = min_size;
+		self.border_width = border_width;
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Returns the minimum width and height required to fit every node in the group (and its
+	/// descendents) with the [window gap] between them.
+	///
+	/// Each window needs at least its [minimum size].
+	///
+	/// `inherited_gap` is the effective [window gap] of the group's parent.
+	///
+	/// [window gap]: LayoutSettings::window_gap
+	/// [minimum size]: WindowNode::minimum_size
+	pub(super) fn minimum_size(&self, inherited_gap: u32) -> (u32, u32) {
+		self.measure(inherited_gap, |_, _| ())
+	}
+
+	/// Returns the [minimum size] of the group and of every group within it, by their [IDs].
+	///
+	/// `inherited_gap` is the effective [window gap] of the group's parent.
+	///
+	/// [minimum size]: Self::minimum_size
+	/// [IDs]: NodeId
+	/// [window gap]: LayoutSettings::window_gap
+	fn minimum_sizes(&self, inherited_gap: u32) -> HashMap<NodeId, (u32, u32)> {
+		let mut sizes = HashMap::new();
+		self.measure(inherited_gap, |id, size| {
+			sizes.insert(id, size);
+		});
+
+		sizes
+	}
+
+	/// Returns the [minimum size] of the group, calling `record` with the minimum size of it and
+	/// of every group within it once each is measured.
+	///
+	/// [minimum size]: Self::minimum_size
+	fn measure(&self, inherited_gap: u32, mut record: impl FnMut(NodeId, (u32, u32))) -> (u32, u32) {
+		// Groups are measured with an explicit stack, rather than recursively, so that deeply
+		// nested layouts can't overflow the stack.
+		let mut groups = vec![MinimumSize::new(self, inherited_gap)];
+
+		loop {
+			let group = groups.last_mut().expect("the outermost group is measured last");
+
+			match group.children.next() {
+				Some(Node::Group(child)) => {
+					let gap = group.gap;
+					groups.push(MinimumSize::new(child, gap));
+				},
+				Some(Node::Window(node)) => group.add(node.minimum_size()),
+
+				None => {
+					let group = groups.pop().expect("we just found the last group");
+					let (id, size) = (group.id, group.finish());
+					record(id, size);
+
+					match groups.last_mut() {
+						Some(parent) => parent.add(size),
+						None => return size,
+					}
+				},
+			}
+		}
+	}
+
+	/// Sets the [minimum size] from the size hints of the descendent window node containing the
+	/// given `window`, and the width of its border.
+	///
+	/// Returns [`false`] if there is no such descendent.
+	///
+	/// [minimum size]: WindowNode::minimum_size
+	pub fn set_minimum_size(&mut self, window: &Window, min_size: Option<(u32, u32)>, border_width: u32) -> bool
+	where
+		Window: PartialEq,
+	{
+		let Some(path) = self.id_of_window(window).and_then(|id| self.path_of(id)) else {
+			return false;
+		};
+
+		match self.node_at_mut(&path) {
+			Some(Node::Window(node)) => {
+				node.set_minimum_size(min_size, border_width);
+
+				true
+			},
+
+			_ => false,
+		}
+	}
+
+	/// Adjusts the effective [window gap] of the group directly containing the given `window` by
+	/// `delta` pixels, clamped at zero.
+	///
+	/// `inherited_gap` is the effective [window gap] of the group's parent.
+	///
+	/// [window gap]: LayoutSettings::window_gap
+	fn adjust_gap(&mut self, window: &Window, delta: i32, inherited_gap: u32) -> bool
+	where
+		Window: PartialEq,
+	{
+		let gap = self.gap().unwrap_or(inherited_gap);
+
+		let contains_window = self
+			.children
+			.iter()
+			.any(|node| matches!(node, Node::Window(node) if node.window() == window));
+
+		if contains_window {
+			self.set_gap(Some(gap.saturating_add_signed(delta)));
+
+			return true;
+		}
+
+		self.children.iter_mut().any(|node| match node {
+			Node::Group(group) => group.adjust_gap(window, delta, gap),
+			Node::Window(_) => false,
+		})
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Grows the child [node] at the given `index` by `delta` pixels along the group's [axis],
+	/// shrinking its next sibling (or, for the last child, its previous sibling) by the same
+	/// amount.
+	///
+	/// A negative `delta` shrinks the node instead. Both nodes are kept at least one pixel in
+	/// size, and [fixed] nodes are fixed at their new size. The nodes are reconfigured the next
+	/// time changes are applied. Returns [`false`] if there is no child at the given `index`, or
+	/// if it has no siblings.
+	///
+	/// If a child of the group is [maximized], it no longer is: the children keep the sizes they
+	/// have, rather than being restored.
+	///
+	/// [node]: Node
+	/// [axis]: Orientation::axis
+	/// [fixed]: Sizing::Fixed
+	/// [maximized]: Self::maximized
+	pub fn resize_child(&mut self, index: usize, delta: i32) -> bool {
+		if index >= self.len() || self.len() < 2 {
+			return false;
+		}
+		let sibling = if index + 1 < self.len() { index + 1 } else { index - 1 };
+
+		// The nodes' current coordinates and dimensions are those of the applied orientation.
+		let axis = self.orientation.axis();
+		let primary = |(x, y, width, height): Rect| match axis {
+			Axis::Horizontal => (x, width),
+			Axis::Vertical => (y, height),
+		};
+		let with_primary = |(x, y, width, height): Rect, coord: i32, dimension: u32| match axis {
+			Axis::Horizontal => (coord, y, dimension, height),
+			Axis::Vertical => (x, coord, width, dimension),
+		};
+
+		let (slot, sibling_slot) = (self[index].slot(), self[sibling].slot());
+		let ((coord, dimension), (sibling_coord, sibling_dimension)) = (primary(slot), primary(sibling_slot));
+
+		let total = dimension.saturating_add(sibling_dimension);
+		if total < 2 {
+			return false;
+		}
+
+		let new_dimension = (dimension as i64 + delta as i64).clamp(1, (total - 1) as i64) as u32;
+		let new_sibling_dimension = total - new_dimension;
+
+		// Whichever node comes first keeps its coordinate, and the gap between them is preserved.
+		let (new_coord, new_sibling_coord) = if coord <= sibling_coord {
+			let gap = sibling_coord - (coord + dimension as i32);
+
+			(coord, coord + (new_dimension as i32) + gap)
+		} else {
+			let gap = coord - (sibling_coord + sibling_dimension as i32);
+
+			(sibling_coord + (new_sibling_dimension as i32) + gap, sibling_coord)
+		};
+
+		self[index].take_slot(with_primary(slot, new_coord, new_dimension));
+		self[sibling].take_slot(with_primary(sibling_slot, new_sibling_coord, new_sibling_dimension));
+		self.maximize = Maximize::None;
+
+		// Fixed nodes stay at the size they are resized to.
+		for (index, dimension) in [(index, new_dimension), (sibling, new_sibling_dimension)] {
+			if let Sizing::Fixed(_) = self[index].sizing() {
+				self[index].set_sizing(Sizing::Fixed(dimension));
+			}
+		}
+
+		true
+	}
+}
+
+/// The [minimum size] of a group, while it is being measured.
+///
+/// [minimum size]: GroupNode::minimum_size
+struct MinimumSize<'group, Window> {
+	id: NodeId,
+	/// The children which are still to be measured.
+	children: vec_deque::Iter<'group, Node<Window>>,
+	len: usize,
+
+	axis: Axis,
+	/// The group's effective [window gap].
+	///
+	/// [window gap]: LayoutSettings::window_gap
+	gap: u32,
+	/// Whether the group's children overlap, rather than being placed side by side.
+	stacked: bool,
+
+	min_primary: u32,
+	min_secondary: u32,
+}
+
+impl<'group, Window> MinimumSize<'group, Window> {
+	/// Starts measuring the given `group`, whose parent has the given effective `inherited_gap`.
+	fn new(group: &'group GroupNode<Window>, inherited_gap: u32) -> Self {
+		Self {
+			id: group.id(),
+			children: group.children.iter(),
+			len: group.len(),
+
+			axis: group.orientation().axis(),
+			gap: group.gap().unwrap_or(inherited_gap),
+			stacked: group.is_stacked(),
+
+			min_primary: 0,
+			min_secondary: 0,
+		}
+	}
+
+	/// Adds the minimum width and height of one of the group's children.
+	fn add(&mut self, (node_width, node_height): (u32, u32)) {
+		let (node_primary, node_secondary) = match self.axis {
+			Axis::Horizontal => (node_width, node_height),
+			Axis::Vertical => (node_height, node_width),
+		};
+
+		self.min_primary = if self.stacked {
+			self.min_primary.max(node_primary)
+		} else {
+			self.min_primary.saturating_add(node_primary)
+		};
+		self.min_secondary = self.min_secondary.max(node_secondary);
+	}
+
+	/// Returns the minimum width and height of the group, once all its children have been added.
+	const fn finish(self) -> (u32, u32) {
+		let mut min_primary = self.min_primary;
+
+		if !self.stacked {
+			let total_gap = (self.len.saturating_sub(1) as u32).saturating_mul(self.gap);
+			min_primary = min_primary.saturating_add(total_gap);
+		}
+
+		match self.axis {
+			Axis::Horizontal => (min_primary, self.min_secondary),
+			Axis::Vertical => (self.min_secondary, min_primary),
+		}
+	}
+}
+
+/// Raises each of the `primaries` of a group's children to at least its corresponding minimum,
+/// taking the pixels needed from any of the `available` space left over, and then from the
+/// children with more than their minimum, in proportion to how much more they have.
+///
+/// The `primaries` are left as they are if there isn't enough space for every minimum.
+fn fit_to_minimums(primaries: &mut [u32], minimums: &[u32], available: u32) {
+	let deficit: u64 = primaries
+		.iter()
+		.zip(minimums)
+		.map(|(&primary, &minimum)| u64::from(minimum.saturating_sub(primary)))
+		.sum();
+	if deficit == 0 || minimums.iter().map(|&minimum| u64::from(minimum)).sum::<u64>() > u64::from(available) {
+		return;
+	}
+
+	let total: u64 = primaries.iter().map(|&primary| u64::from(primary)).sum();
+	let mut deficit = deficit.saturating_sub(u64::from(available).saturating_sub(total));
+
+	let surplus: u64 = primaries
+		.iter()
+		.zip(minimums)
+		.map(|(&primary, &minimum)| u64::from(primary.saturating_sub(minimum)))
+		.sum();
+	let needed = deficit.min(surplus);
+
+	for (primary, &minimum) in primaries.iter_mut().zip(minimums) {
+		*primary = (*primary).max(minimum);
+	}
+
+	// Take from each child its share, rounded down...
+	if needed != 0 {
+		for (primary, &minimum) in primaries.iter_mut().zip(minimums) {
+			let taken = (u64::from(*primary - minimum) * needed / surplus).min(deficit);
+
+			*primary -= taken as u32;
+			deficit -= taken;
+		}
+	}
+	// ...and then what's left from the first children with any to spare.
+	for (primary, &minimum) in primaries.iter_mut().zip(minimums) {
+		let taken = u64::from(*primary - minimum).min(deficit);
+
+		*primary -= taken as u32;
+		deficit -= taken;
+	}
+}
+
+/// Gives the pixels of the `available` space which are left over once the `primaries` of a
+/// group's children have been rounded down to the children which are `proportional`, so that the
+/// children fill the group exactly.
+///
+/// This is the only rule by which leftover pixels are shared out: they are shared evenly, and any
+/// which can't be shared evenly go one each to the leftmost or topmost children on screen, i.e.
+/// the last children of a [reversed] group.
+///
+/// [reversed]: Orientation::reversed
+pub(super) fn distribute_remainder(primaries: &mut [u32], proportional: &[bool], available: u32, reversed: bool) {
+	let total: u64 = primaries.iter().map(|&primary| u64::from(primary)).sum();
+	let remainder = u64::from(available).saturating_sub(total);
+
+	let mut eligible: Vec<usize> = (0..primaries.len()).filter(|&index| proportional[index]).collect();
+	if remainder == 0 || eligible.is_empty() {
+		return;
+	}
+	if reversed {
+		eligible.reverse();
+	}
+
+	let len = eligible.len() as u64;
+	for (i, index) in eligible.into_iter().enumerate() {
+		let extra = remainder / len + u64::from((i as u64) < remainder % len);
+
+		primaries[index] = primaries[index].saturating_add(extra.shrink());
+	}
+}
+
+/// Rounds each of the `primaries` of a group's children down to its corresponding
+/// [resize increments], given as `(base, step)`, if it has any along the group's axis.
+///
+/// The pixels left over are dealt with according to the given [`LeftoverPixels`] policy. Returns
+/// the number of extra pixels to leave after each child, which is only ever non-zero if the
+/// leftover pixels widen the [gaps].
+///
+/// [resize increments]: Increments
+/// [gaps]: LeftoverPixels::Gaps
+fn fit_to_increments(primaries: &mut [u32], increments: &[Option<(u32, u32)>], leftover: LeftoverPixels) -> Vec<u32> {
+	let mut offsets = vec![0; primaries.len()];
+
+	let mut remaining = 0u64;
+	for (primary, &increments) in primaries.iter_mut().zip(increments) {
+		let fitted = increments::fit(increments, *primary);
+
+		remaining += u64::from(*primary - fitted);
+		*primary = fitted;
+	}
+	if remaining == 0 {
+		return offsets;
+	}
+
+	match leftover {
+		LeftoverPixels::LastUnconstrained => {
+			if let Some(index) = increments.iter().rposition(Option::is_none) {
+				primaries[index] = primaries[index].saturating_add(remaining.shrink());
+			}
+		},
+
+		// Gaps between the children come after every child but the last.
+		LeftoverPixels::Gaps if primaries.len() > 1 => {
+			let gaps = (primaries.len() - 1) as u64;
+
+			for (i, offset) in offsets[..primaries.len() - 1].iter_mut().enumerate() {
+				*offset = (remaining / gaps + u64::from((i as u64) < remaining % gaps)).shrink();
+			}
+		},
+
+		LeftoverPixels::Gaps | LeftoverPixels::Margin => {},
+	}
+
+	offsets
+}
+
+/// A node waiting to have its changes applied by [`GroupNode::apply_changes`].
+enum Pending<'group, Window> {
+	/// A group waiting to have its changes applied, then its children added in turn.
+	Group {
+		group: &'group mut GroupNode<Window>,
+		/// The effective [window gap] of the group's parent.
+		///
+		/// [window gap]: LayoutSettings::window_gap
+		inherited_gap: u32,
+		/// The effective [addition sizing] of the group's parent.
+		///
+		/// [addition sizing]: GroupNode::addition_sizing
+		inherited_sizing: AdditionSizing,
+		/// Whether every window in the group is [hidden].
+		///
+		/// [hidden]: Visibility::Hidden
+		hidden: bool,
+		/// Whether the group is within a [manual] group.
+		///
+		/// [manual]: GroupNode::is_manual
+		manual: bool,
+	},
+
+	/// A window waiting to be [reported].
+	///
+	/// [reported]: WindowNode::report_changes
+	Window {
+		window: &'group mut WindowNode<Window>,
+		hidden: bool,
+		relaid_out: bool,
+	},
+}
+
+impl<'group, Window> Pending<'group, Window> {
+	/// Creates a pending `node` in a group with the given effective `gap` and `sizing`.
+	///
+	/// `hidden` is whether the node is [hidden], and `relaid_out` is whether its coordinates and
+	/// dimensions have just been calculated again.
+	///
+	/// [hidden]: Visibility::Hidden
+	const fn new(
+		node: &'group mut Node<Window>,
+		gap: u32,
+		sizing: AdditionSizing,
+		hidden: bool,
+		relaid_out: bool,
+	) -> Self {
+		match node {
+			Node::Group(group) => Self::Group {
+				group,
+				inherited_gap: gap,
+				inherited_sizing: sizing,
+				hidden,
+				manual: false,
+			},
+			Node::Window(window) => Self::Window {
+				window,
+				hidden,
+				relaid_out,
+			},
+		}
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Returns whether any changes have been made by the [layout manager] to this group (directly
+	/// or indirectly).
+	///
+	/// [layout manager]: TilingLayoutManager
+	pub(super) fn changes_made(&self) -> bool {
+		!self.additions.is_empty()
+			|| self.total_removed_primary != 0
+			|| self.new_orientation.is_some()
+			|| self.new_gap.is_some()
+			|| self.new_stacked.is_some()
+			|| self.new_active.is_some()
+			|| self.new_width.is_some()
+			|| self.new_height.is_some()
+			|| self.new_x.is_some()
+			|| self.new_y.is_some()
+			|| self.equalize
+			|| self.children.iter().any(Node::sizing_changed)
+			|| self.children.iter().any(Node::increments_changed)
+	}
+
+	/// Applies the changes made by the [layout manager].
+	///
+	/// `resize_window` is a function that resizes the given window based on the given x and y
+	/// coordinates and width and height (in that order), and shows or hides it based on the given
+	/// [visibility]. [Hidden] windows are only passed to `resize_window` when they become hidden;
+	/// they are passed again, with their up-to-date coordinates and dimensions, once they become
+	/// visible.
+	///
+	/// If `resize_window` returns an error, applying the changes is aborted. If it [skips] a
+	/// window, the rest of the changes are still applied, and the skipped windows are returned in
+	/// the order they were skipped so that they can be removed from the layout.
+	///
+	/// Applying changes is deterministic: the same changes always give the same geometries, and
+	/// windows are reconfigured (and so skipped) in the order of [`windows`]. Pixels which can't
+	/// be shared evenly between nodes go one each to the leftmost or topmost nodes on screen.
+	///
+	/// [visibility]: Visibility
+	/// [`windows`]: Self::windows
+	/// [Hidden]: Visibility::Hidden
+	/// [skips]: Reconfigured::SkipWindow
+	///
+	/// [layout manager]: TilingLayoutManager
+	///
+	/// [primary]: Node::primary_dimension
+	/// [secondary]: Node::secondary_dimension
+	pub(crate) fn apply_changes<Outcome, Error>(
+		&mut self,
+		reconfigure_window: &mut impl FnMut(&Window, i32, i32, u32, u32, Visibility) -> Result<Outcome, Error>,
+		settings: &LayoutSettings,
+	) -> Result<Vec<Window>, Error>
+	where
+		Window: Clone,
+		Outcome: Into<Reconfigured>,
+	{
+		// Windows outside of the group's (new) bounds are hidden.
+		let viewport = (
+			self.new_x.unwrap_or(self.x),
+			self.new_y.unwrap_or(self.y),
+			self.new_width.unwrap_or(self.width),
+			self.new_height.unwrap_or(self.height),
+		);
+
+		let mut skipped = Vec::new();
+		let mut reconfigure_window = |window: &Window, x, y, width, height, visibility| {
+			if reconfigure_window(window, x, y, width, height, visibility)?.into() == Reconfigured::SkipWindow {
+				skipped.push(window.clone());
+			}
+
+			Ok(())
+		};
+
+		// Groups are laid out with an explicit stack, rather than recursively, so that deeply
+		// nested layouts can't overflow the stack. Nodes are taken from the end of the stack, so
+		// each group's children are reversed once they have been added to it, unless the group
+		// is reversed: its children are then already in the reverse of layout order.
+		// Groups are given at least their minimum size, which is measured for every group at once.
+		let minimums = self.minimum_sizes(settings.window_gap);
+		let increments = settings.honor_increments.then_some(settings.leftover_pixels);
+		let mut pending = vec![Pending::Group {
+			group: self,
+			inherited_gap: settings.window_gap,
+			inherited_sizing: AdditionSizing::default(),
+			hidden: false,
+			manual: false,
+		}];
+
+		while let Some(node) = pending.pop() {
+			match node {
+				Pending::Group {
+					group,
+					inherited_gap,
+					inherited_sizing,
+					hidden,
+					manual,
+				} => {
+					let start = pending.len();
+					let manual = manual || group.manual;
+
+					// The children of manual groups, and of every group within them, are only
+					// rescaled to fill their groups: they aren't equalized, or fit to minimum sizes or
+					// resize increments.
+					let reversed = match manual {
+						false => group.apply_changes_with(
+							inherited_gap,
+							inherited_sizing,
+							hidden,
+							&minimums,
+							increments,
+							&mut pending,
+						),
+
+						true => {
+							group.equalize = false;
+
+							group.apply_changes_with(
+								inherited_gap,
+								inherited_sizing,
+								hidden,
+								&HashMap::new(),
+								None,
+								&mut pending,
+							)
+						},
+					};
+
+					if manual {
+						for node in &mut pending[start..] {
+							if let Pending::Group { manual, .. } = node {
+								*manual = true;
+							}
+						}
+					}
+					if !reversed {
+						pending[start..].reverse();
+					}
+				},
+
+				Pending::Window {
+					window,
+					hidden,
+					relaid_out,
+				} => window.report_changes(&mut reconfigure_window, hidden, viewport, relaid_out)?,
+			}
+		}
+
+		Ok(skipped)
+	}
+
+	/// Applies the changes made by the [layout manager] to this group, resolving the group's
+	/// effective [window gap] from its override or, if it has none, the `inherited_gap` of its
+	/// parent.
+	///
+	/// Likewise, the group's effective [addition sizing] is its override or, if it has none, the
+	/// `inherited_sizing` of its parent.
+	///
+	/// If `hidden` is true, every window in the group is [hidden]. Otherwise, windows are hidden if
+	/// they are not in the [active] child of a [stacked] group, or if they lie entirely outside of
+	/// the viewport.
+	///
+	/// Child groups are given at least their size in `minimums`, as measured by
+	/// [`minimum_sizes`], if there is room for them all.
+	///
+	/// If `increments` is [`Some`], windows are sized to their [resize increments] within their
+	/// tiles, and the pixels left over are dealt with according to the given policy.
+	///
+	/// The group's children are added to `pending` in index order, to have their own changes
+	/// applied (or, for windows, be reported) in turn. Returns whether the group is [reversed], in
+	/// which case index order is the reverse of layout order.
+	///
+	/// [layout manager]: TilingLayoutManager
+	/// [window gap]: LayoutSettings::window_gap
+	/// [addition sizing]: Self::addition_sizing
+	/// [hidden]: Visibility::Hidden
+	/// [active]: Self::active
+	/// [stacked]: Self::is_stacked
+	/// [`minimum_sizes`]: Self::minimum_sizes
+	/// [resize increments]: Increments
+	/// [reversed]: Orientation::reversed
+	fn apply_changes_with<'group>(
+		&'group mut self,
+		inherited_gap: u32,
+		inherited_sizing: AdditionSizing,
+		hidden: bool,
+		minimums: &HashMap<NodeId, (u32, u32)>,
+		increments: Option<LeftoverPixels>,
+		pending: &mut Vec<Pending<'group, Window>>,
+	) -> bool {
+		let gap = self.gap().unwrap_or(inherited_gap);
+		let addition_sizing = self.addition_sizing.unwrap_or(inherited_sizing);
+
+		// If no changes have been made to this group, apply all the child groups' changes and return.
+		if !self.changes_made() && gap == self.applied_gap {
+			let active = self.stacked.then(|| self.active()).flatten();
+
+			for node in &mut self.children {
+				let hidden = hidden || active.is_some_and(|active| node.id() != active);
+
+				pending.push(Pending::new(node, gap, addition_sizing, hidden, false));
+			}
+
+			return self.orientation.reversed();
+		}
+
+		let additions = mem::take(&mut self.additions);
+		let total_removed_primary = mem::take(&mut self.total_removed_primary);
+
+		let new_orientation = mem::take(&mut self.new_orientation);
+		let new_gap = mem::take(&mut self.new_gap);
+		let new_stacked = mem::take(&mut self.new_stacked);
+		let new_active = mem::take(&mut self.new_active);
+
+		let new_width = mem::take(&mut self.new_width);
+		let new_height = mem::take(&mut self.new_height);
+
+		let new_x = mem::take(&mut self.new_x);
 		let new_y = mem::take(&mut self.new_y);
 
+		let equalize = mem::take(&mut self.equalize);
+		// Equalizing the group ends any maximize in it, rather than restoring the sizes from before.
+		if equalize {
+			self.maximize = Maximize::None;
+		}
+
 		// The old axis of the group, before any orientation change.
 		let old_axis = self.orientation.axis();
 
@@ -570,6 +1799,18 @@ impl<Window> GroupNode<Window> {
 		if let Some(orientation) = new_orientation {
 			self.orientation = orientation;
 		}
+		// Apply the change in gap override, if any.
+		if let Some(gap) = new_gap {
+			self.gap = gap;
+		}
+		self.applied_gap = gap;
+		// Apply the change in stacking and active child, if any.
+		if let Some(stacked) = new_stacked {
+			self.stacked = stacked;
+		}
+		if new_active.is_some() {
+			self.active = new_active;
+		}
 		// Apply the change in width, if any.
 		if let Some(width) = new_width {
 			self.width = width;
@@ -587,10 +1828,6 @@ impl<Window> GroupNode<Window> {
 
 		let new_axis = self.orientation.axis();
 
-		// `u64` is used because we will be multiplying two 'u32' values, and `u64::MAX` is
-		// `u32::MAX * u32::MAX`.
-		let old_total_node_primary = (self.total_node_primary - total_removed_primary) as u64;
-
 		// The order of dimensions used for nodes depends on the orientation of the group. The first
 		// dimension, `primary`, is the dimension that is affected by the node's size within the
 		// group, while the second dimension, `secondary`, is the dimension that is only affected by
@@ -615,96 +1852,360 @@ impl<Window> GroupNode<Window> {
 
 		let (group_primary, group_secondary) = (self.primary_dimension(), self.secondary_dimension());
 		let (group_primary_coord, group_secondary_coord) = (self.primary_coord(), self.secondary_coord());
-		// Set a node's dimensions and call `reconfigure_window` if it is a window.
-		let mut configure_node = |node: &mut Node<Window>, mut primary_coord, primary_dimension| {
+		// Only the active child of a stacked group is visible.
+		let active = self.stacked.then(|| self.active()).flatten();
+		// Set a node's dimensions and add it to `pending`.
+		let reversed = self.orientation.reversed();
+		let mut configure_node = |node: &'group mut Node<Window>, mut primary_coord, tile, primary_dimension| {
+			let hidden = hidden || active.is_some_and(|active| node.id() != active);
+
 			// If the orientation is reversed, then reverse the coordinates.
-			if self.orientation.reversed() {
+			if reversed {
 				primary_coord = (group_primary as i32) - primary_coord - (primary_dimension as i32);
 			}
 
+			// Pixels left over along the secondary dimension are left as a margin.
+			let secondary_dimension = match increments {
+				Some(_) => increments::fit(node.increments_along(new_axis.flipped()), group_secondary),
+				None => group_secondary,
+			};
+
 			node.set_primary_coord(group_primary_coord + primary_coord, new_axis);
 			node.set_secondary_coord(group_secondary_coord, new_axis);
 
 			node.set_primary_dimension(primary_dimension, new_axis);
-			node.set_secondary_dimension(group_secondary, new_axis);
+			node.set_secondary_dimension(secondary_dimension, new_axis);
+			node.set_tile(tile, primary_dimension);
 
-			match node {
-				Node::Group(group) => group.apply_changes(reconfigure_window, settings),
-
-				Node::Window(WindowNode {
-					window,
-					window_changed,
-					x,
-					y,
-					width,
-					height,
-				}) => {
-					*window_changed = false;
-					reconfigure_window(window, *x, *y, *width, *height)
-				},
-			}
+			pending.push(Pending::new(node, gap, addition_sizing, hidden, true));
 		};
 
 		let current_nodes_len = self.children.len();
+
+		// Every child of a stacked group fills the whole group, as does a lone proportional child,
+		// which has nothing to share the group with.
+		let lone = current_nodes_len == 1 && self.children[0].sizing().weight().is_some();
+		if self.stacked || lone {
+			// There is nothing to maximize if every child fills the group, but the maximized child may
+			// have been removed, which ends the maximize.
+			self.maximized_primaries(group_primary, reversed);
+
+			let mut total_node_primary = 0u32;
+
+			for node in &mut self.children {
+				node.apply_sizing();
+
+				let primary = match increments {
+					Some(_) => increments::fit(node.increments_along(new_axis), group_primary),
+					None => group_primary,
+				};
+				configure_node(node, 0, group_primary, primary);
+
+				total_node_primary = total_node_primary.saturating_add(primary);
+			}
+
+			self.total_node_primary = total_node_primary;
+
+			return reversed;
+		}
 		let new_nodes_len = (current_nodes_len + self.additions.len()) as u32;
 		let total_gap = if new_nodes_len == 0 {
 			0
 		} else {
-			(new_nodes_len - 1) * settings.window_gap
+			(new_nodes_len - 1).saturating_mul(gap)
 		};
-		// The size of new additions.
-		let new_primary = if new_nodes_len == 0 {
-			group_primary
-		} else {
-			(group_primary - total_gap) / new_nodes_len
+		// The space to be shared between the nodes.
+		let available_primary = group_primary.saturating_sub(total_gap);
+
+		let is_addition = |index: usize| {
+			additions
+				.binary_search_by_key(&index, |addition| addition.index)
+				.is_ok()
 		};
-		let mut new_total_node_primary = 0;
-		// The new total size for the existing nodes to be resized to fit within.
+
+		// The size of each node's tile along the old axis, which is bigger than the node itself if
+		// it was sized to its resize increments. Additions which split a node take their fraction
+		// of its size here, so that they are rescaled along with it.
 		//
 		// `u64` is used because we will be multiplying two 'u32' values, and `u64::MAX` is
 		// `u32::MAX * u32::MAX`.
-		let rescaling_primary = (group_primary - (new_primary * (additions.len() as u32)) - total_gap) as u64;
+		let mut old_primaries: Vec<u64> = self
+			.children
+			.iter()
+			.map(|node| (i64::from(node.primary_dimension(old_axis)) + i64::from(node.slack())).max(0) as u64)
+			.collect();
+		// Tiles are rescaled, rather than the nodes sized to their resize increments within them,
+		// so that applying changes again doesn't shrink those nodes further.
+		let old_slack: i64 = self
+			.children
+			.iter()
+			.enumerate()
+			.filter(|&(index, _)| !is_addition(index))
+			.map(|(_, node)| i64::from(node.slack()))
+			.sum();
+		let old_total_node_primary =
+			(i64::from(self.total_node_primary.saturating_sub(total_removed_primary)) + old_slack).max(0) as u64;
+		// Whether each node is an addition which took its size from the node it splits.
+		let mut split = vec![false; current_nodes_len];
+
+		if let (AdditionSizing::FractionOfSplitNode(fraction), false) = (addition_sizing, equalize) {
+			let proportional = |index: usize| self.children[index].sizing().weight().is_some();
+
+			for addition in &additions {
+				// Additions can't split other additions, which have no size to take from yet.
+				let Some(splits) = addition.splits.filter(|&splits| !is_addition(splits)) else {
+					continue;
+				};
+				if !proportional(addition.index) || !proportional(splits) {
+					continue;
+				}
 
-		let mut additions = additions.into_iter();
-		let mut next_addition = additions.next();
+				let taken = AdditionSizing::fraction_of(fraction, old_primaries[splits]);
+
+				old_primaries[splits] -= taken;
+				old_primaries[addition.index] = taken;
+				split[addition.index] = true;
+			}
+		}
+
+		// Whether the node at the given index is given its size afresh, rather than rescaled.
+		let fresh = |index: usize| equalize || (is_addition(index) && !split[index]);
+
+		// Fixed nodes are given their pixels first, and proportional nodes share what is left.
+		let mut total_fixed_primary = 0u32;
+		// The size of existing nodes which are now fixed, which is not to be rescaled.
+		let mut old_fixed_primary = 0u64;
+		// The total weight of proportional nodes which are given a share of the space afresh.
+		let mut new_weight = 0u64;
+		// The total weight of every proportional node.
+		let mut total_weight = 0u64;
 
-		// Resize all the nodes appropriately.
 		for (index, node) in self.children.iter_mut().enumerate() {
-			let gap = (settings.window_gap as i32) * (index as i32);
-			let coord = (new_total_node_primary as i32) + gap;
+			match node.apply_sizing() {
+				Sizing::Fixed(pixels) => {
+					total_fixed_primary = total_fixed_primary.saturating_add(pixels);
 
-			// If `node` is an addition, resize it with the new size.
-			if let Some(addition) = next_addition {
-				if index == addition {
-					configure_node(node, coord, new_primary)?;
+					if !is_addition(index) {
+						old_fixed_primary += old_primaries[index];
+					}
+				},
 
-					next_addition = additions.next();
+				sizing => {
+					let weight = sizing.weight().unwrap_or(1) as u64;
 
-					new_total_node_primary += new_primary;
-					continue;
-				}
+					if fresh(index) {
+						new_weight += weight;
+					}
+					total_weight += weight;
+				},
 			}
+		}
+		let total_fixed_primary = total_fixed_primary.min(available_primary);
 
-			// `node` is not an addition: rescale it.
+		// `u64` is used because we will be multiplying two 'u32' values, and `u64::MAX` is
+		// `u32::MAX * u32::MAX`.
+		let proportional_primary = (available_primary - total_fixed_primary) as u64;
+		// The size of a proportional node given a share of the space afresh.
+		let share = |weight: u32| -> u32 {
+			match total_weight {
+				0 => 0,
+				total_weight => ((proportional_primary * weight as u64) / total_weight).shrink(),
+			}
+		};
+
+		// The total weight of the existing proportional nodes which are to be rescaled.
+		let rescaled_weight = total_weight - new_weight;
+
+		// The size of each proportional node given its size afresh.
+		let mut available = proportional_primary;
+		let fresh_primaries: Vec<Option<u32>> = self
+			.children
+			.iter()
+			.enumerate()
+			.map(|(index, node)| {
+				let weight = node.sizing().weight().filter(|_| fresh(index))?;
+
+				let primary = match addition_sizing {
+					// If there are no existing nodes to make room, the additions share the group.
+					AdditionSizing::FractionOfGroup(fraction) if !equalize && rescaled_weight != 0 => {
+						AdditionSizing::fraction_of(fraction, proportional_primary).min(available)
+					},
 
-			// `u64` is used because we will be multiplying two 'u32' values, and `u64::MAX` is
-			// `u32::MAX * u32::MAX`.
-			let old_primary = node.primary_dimension(old_axis) as u64;
-			// Determine the rescaled size.
-			//
-			// This is `shrink`ed back into a `u32` value (a value `> u32::MAX` will be clipped to
-			// `u32::MAX`), though in practice it almost certainly will never get anywhere near that
-			// large - monitors don't tend to be millions of pixels in width or height.
-			let rescaled_primary: u32 = ((old_primary * rescaling_primary) / old_total_node_primary).shrink();
+					_ => share(weight) as u64,
+				};
+				available = available.saturating_sub(primary);
 
-			configure_node(node, coord, rescaled_primary)?;
+				Some(primary.shrink())
+			})
+			.collect();
 
-			new_total_node_primary += rescaled_primary;
+		let mut new_total_node_primary = 0;
+		// The new total size for the existing proportional nodes to be resized to fit within.
+		//
+		// `u64` is used because we will be multiplying two 'u32' values, and `u64::MAX` is
+		// `u32::MAX * u32::MAX`.
+		let rescaling_primary =
+			proportional_primary.saturating_sub(fresh_primaries.iter().flatten().map(|&primary| primary as u64).sum());
+		// The total size of the existing proportional nodes which are to be rescaled.
+		let old_proportional_primary = old_total_node_primary.saturating_sub(old_fixed_primary);
+		// The space left for fixed nodes that come after the current one.
+		let mut fixed_remaining = total_fixed_primary;
+
+		// Determine the new size of each node.
+		let mut primaries: Vec<u32> = self
+			.children
+			.iter()
+			.enumerate()
+			.map(|(index, node)| match node.sizing() {
+				// Fixed nodes get exactly their pixels, unless they don't fit.
+				Sizing::Fixed(pixels) => {
+					let primary = pixels.min(fixed_remaining);
+					fixed_remaining -= primary;
+
+					primary
+				},
+
+				// Additions, and every proportional node if the group is being equalized, are
+				// given their size afresh according to the addition sizing.
+				_ if fresh(index) => fresh_primaries[index].unwrap_or_default(),
+
+				// Other proportional nodes are rescaled.
+				sizing => {
+					// `u64` is used because we will be multiplying two 'u32' values, and `u64::MAX`
+					// is `u32::MAX * u32::MAX`.
+					let old_primary = old_primaries[index];
+					// Determine the rescaled size.
+					//
+					// This is `shrink`ed back into a `u32` value (a value `> u32::MAX` will be clipped
+					// to `u32::MAX`), though in practice it almost certainly will never get anywhere
+					// near that large - monitors don't tend to be millions of pixels in width or
+					// height.
+					match (old_primary * rescaling_primary).checked_div(old_proportional_primary) {
+						Some(primary) => primary.shrink(),
+
+						// If the existing nodes have no size to rescale (e.g. because the group had
+						// degenerate dimensions), share the space between them by weight instead.
+						None => {
+							let weight = sizing.weight().unwrap_or(1) as u64;
+
+							((rescaling_primary * weight) / rescaled_weight).shrink()
+						},
+					}
+				},
+			})
+			.collect();
+
+		// Pixels lost to rounding down are given back, so that the nodes fill the group.
+		let proportional: Vec<bool> = self
+			.children
+			.iter()
+			.map(|node| node.sizing().weight().is_some())
+			.collect();
+		distribute_remainder(&mut primaries, &proportional, available_primary, reversed);
+
+		// A maximized child takes up the group but for its siblings' strips, and once it is no
+		// longer maximized, the sizes the children had before are restored.
+		if let Some(maximized) = self.maximized_primaries(available_primary, reversed) {
+			primaries = maximized;
+		}
+
+		// Groups given less than their minimum size would spill out of their bounds, so they take
+		// what they need from their siblings, as do windows with a minimum size in their size
+		// hints. Other windows may be left with no space.
+		let minimums: Vec<u32> = self
+			.children
+			.iter()
+			.map(|node| match node {
+				Node::Window(node) if node.min_size.is_some() => match (new_axis, node.minimum_size()) {
+					(Axis::Horizontal, (width, _)) => width,
+					(Axis::Vertical, (_, height)) => height,
+				},
+				Node::Window(_) => 0,
+				Node::Group(group) => match (new_axis, minimums.get(&group.id()).copied().unwrap_or_default()) {
+					(Axis::Horizontal, (width, _)) => width,
+					(Axis::Vertical, (_, height)) => height,
+				},
+			})
+			.collect();
+		fit_to_minimums(&mut primaries, &minimums, available_primary);
+
+		// Windows are sized to their resize increments within their tiles, if they are honored.
+		let tiles = primaries.clone();
+		let offsets = match increments {
+			Some(leftover) => {
+				let increments: Vec<_> = self
+					.children
+					.iter()
+					.map(|node| node.increments_along(new_axis))
+					.collect();
+
+				fit_to_increments(&mut primaries, &increments, leftover)
+			},
+
+			None => vec![0; primaries.len()],
+		};
+
+		// Resize all the nodes appropriately.
+		let mut offset = 0;
+		for (index, (node, (tile, primary))) in self
+			.children
+			.iter_mut()
+			.zip(tiles.into_iter().zip(primaries))
+			.enumerate()
+		{
+			let coord = (new_total_node_primary as i32) + (gap as i32) * (index as i32) + (offset as i32);
+
+			configure_node(node, coord, tile, primary);
+
+			new_total_node_primary += primary;
+			offset += offsets[index];
 		}
 
 		self.total_node_primary = new_total_node_primary;
 
-		Ok(())
+		reversed
+	}
+}
+
+impl<Window> WindowNode<Window> {
+	/// Calls `reconfigure_window` for the window if it has been changed or its [visibility] has
+	/// changed.
+	///
+	/// `relaid_out` is whether the window's coordinates and dimensions have just been calculated
+	/// again. [Hidden] windows are only reconfigured when they become hidden, and are reconfigured
+	/// with their up-to-date coordinates and dimensions when they become [visible] again.
+	///
+	/// [visibility]: Visibility
+	/// [Hidden]: Visibility::Hidden
+	/// [visible]: Visibility::Visible
+	fn report_changes<Error>(
+		&mut self,
+		reconfigure_window: &mut impl FnMut(&Window, i32, i32, u32, u32, Visibility) -> Result<(), Error>,
+		hidden: bool,
+		viewport: Rect,
+		relaid_out: bool,
+	) -> Result<(), Error> {
+		let (x, y, width, height) = (self.x, self.y, self.width, self.height);
+
+		let visibility = if hidden || !intersects((x, y, width, height), viewport) {
+			Visibility::Hidden
+		} else {
+			Visibility::Visible
+		};
+
+		let changed = mem::take(&mut self.window_changed) || relaid_out;
+		let visibility_changed = mem::replace(&mut self.visibility, visibility) != visibility;
+
+		match visibility {
+			Visibility::Visible if changed || visibility_changed => {
+				reconfigure_window(&self.window, x, y, width, height, visibility)
+			},
+			Visibility::Hidden if visibility_changed => {
+				reconfigure_window(&self.window, x, y, width, height, visibility)
+			},
+
+			_ => Ok(()),
+		}
 	}
 }
 
@@ -715,10 +2216,36 @@ mod tests {
 	/// No-op resize_window function to pass to [`apply_resizes`].
 	///
 	/// [`apply_resizes`]: GroupNode::apply_changes
-	const fn resize_window<Window>(_window: &Window, _x: i32, _y: i32, _width: u32, _height: u32) -> Result<(), ()> {
+	const fn resize_window<Window>(
+		_window: &Window,
+		_x: i32,
+		_y: i32,
+		_width: u32,
+		_height: u32,
+		_visibility: Visibility,
+	) -> Result<(), ()> {
 		Ok(())
 	}
 
+	/// Applies the changes made to the given `layout`, returning the windows that were
+	/// reconfigured.
+	fn apply_changes(layout: &mut TilingLayout<u32>, settings: &LayoutSettings) -> Vec<(u32, i32, i32, u32, u32)> {
+		let mut reconfigured = Vec::new();
+
+		layout
+			.apply_changes(
+				&mut |window: &u32, x, y, width, height, _| -> Result<(), ()> {
+					reconfigured.push((*window, x, y, width, height));
+
+					Ok(())
+				},
+				settings,
+			)
+			.unwrap();
+
+		reconfigured
+	}
+
 	#[test]
 	fn group_orientations() {
 		const INITIAL_ORIENTATION: Orientation = Orientation::LeftToRight;
@@ -747,6 +2274,45 @@ mod tests {
 		assert_eq!(group.orientation(), NEW_ORIENTATION);
 	}
 
+	/// Tests the geometry of a lone window with and without the window gap and smart gaps, and as a
+	/// second window is added and removed.
+	#[test]
+	fn single_window() {
+		for (window_gap, smart_gaps, expected) in [
+			(0, false, (1, 0, 0, 1000, 600)),
+			(0, true, (1, 0, 0, 1000, 600)),
+			(10, false, (1, 10, 10, 980, 580)),
+			(10, true, (1, 0, 0, 1000, 600)),
+		] {
+			let settings = LayoutSettings::new().window_gap(window_gap).smart_gaps(smart_gaps);
+
+			let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 600, &settings);
+			assert_eq!(layout.single_window(), None);
+
+			layout.push_window_back(1);
+			assert_eq!(layout.single_window(), Some(&1));
+			assert_eq!(apply_changes(&mut layout, &settings), [expected]);
+		}
+
+		let settings = LayoutSettings::new().window_gap(10).smart_gaps(true);
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 600, &settings);
+		layout.push_window_back(1);
+		apply_changes(&mut layout, &settings);
+
+		// A second window brings the gap back...
+		layout.push_window_back(2);
+		assert_eq!(layout.single_window(), None);
+		assert_eq!(
+			apply_changes(&mut layout, &settings),
+			[(1, 10, 10, 485, 580), (2, 505, 10, 485, 580)]
+		);
+
+		// ...until it is removed again.
+		layout.remove(1);
+		assert_eq!(layout.single_window(), Some(&1));
+		assert_eq!(apply_changes(&mut layout, &settings), [(1, 0, 0, 1000, 600)]);
+	}
+
 	#[test]
 	fn push_windows() {
 		const WINDOWS: [u32; 5] = [1, 2, 3, 4, 5];
@@ -892,4 +2458,437 @@ mod tests {
 			);
 		}
 	}
+
+	/// Tests that windows in nested groups are resized when only an ancestor's secondary dimension
+	/// changes.
+	#[test]
+	fn nested_secondary_resize() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 500, &settings);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([1, 2]))
+			.unwrap();
+		assert_eq!(
+			apply_changes(&mut layout, &settings),
+			[(1, 0, 0, 1000, 250), (2, 0, 250, 1000, 250)]
+		);
+
+		layout.resize(0, 0, 1000, 800, &settings);
+		assert_eq!(
+			apply_changes(&mut layout, &settings),
+			[(1, 0, 0, 1000, 400), (2, 0, 400, 1000, 400)]
+		);
+	}
+
+	/// Tests that [`TilingLayout::apply_changes`] is deferred while the layout has degenerate
+	/// dimensions, and that the pending changes are applied once it is resized.
+	#[test]
+	fn degenerate_dimensions() {
+		let settings = LayoutSettings::new().window_gap(10);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 0, 0, &settings);
+		layout.push_windows_back([1, 2]);
+
+		let mut reconfigured = Vec::new();
+		let mut reconfigure_window = |window: &u32, x, y, width, height, _| -> Result<(), ()> {
+			reconfigured.push((*window, x, y, width, height));
+
+			Ok(())
+		};
+
+		layout.apply_changes(&mut reconfigure_window, &settings).unwrap();
+		assert!(layout.is_deferred());
+
+		// A layout which is only slightly too small to fit the gaps must also be deferred.
+		layout.resize(0, 0, 30, 1080, &settings);
+		layout.apply_changes(&mut reconfigure_window, &settings).unwrap();
+		assert!(layout.is_deferred());
+
+		layout.resize(0, 0, 1920, 1080, &settings);
+		layout.apply_changes(&mut reconfigure_window, &settings).unwrap();
+		assert!(!layout.is_deferred());
+
+		assert_eq!(reconfigured, [(1, 10, 10, 945, 1060), (2, 965, 10, 945, 1060)]);
+	}
+
+	/// Tests that [clearing] a layout returns its windows in order, and leaves it as if it were
+	/// newly created.
+	///
+	/// [clearing]: TilingLayout::reset
+	#[test]
+	fn reset() {
+		let settings = LayoutSettings::new().window_gap(10);
+
+		// ┌───┬───────────┐
+		// │   │     3     │
+		// │ 1 ├─────┬─────┤
+		// │   │  5  │  4  │
+		// ├───┤     │     │
+		// │ 2 │     │     │
+		// └───┴─────┴─────┘
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 500, &settings);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([1, 2]))
+			.unwrap();
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| {
+				group
+					.push_group_back_with(Orientation::LeftToRight, |group| group.push_windows_back([4, 5]))
+					.unwrap();
+				group.push_window_back(3);
+			})
+			.unwrap();
+		apply_changes(&mut layout, &settings);
+
+		// Reverse the nested groups once their nodes are in place.
+		let group = layout[1].unwrap_group_mut();
+		group.set_orientation(Orientation::BottomToTop);
+		group[1].unwrap_group_mut().set_orientation(Orientation::RightToLeft);
+		apply_changes(&mut layout, &settings);
+
+		let windows: Vec<_> = layout.windows().into_iter().copied().collect();
+		assert_eq!(windows, [1, 2, 3, 5, 4]);
+		assert_eq!(layout.reset(Orientation::TopToBottom), windows);
+		assert!(layout.is_empty());
+
+		let mut fresh: TilingLayout<u32> = TilingLayout::new(Orientation::TopToBottom, 0, 0, 1000, 500, &settings);
+		for layout in [&mut layout, &mut fresh] {
+			layout.push_windows_back([1, 2]);
+		}
+		assert_eq!(
+			apply_changes(&mut layout, &settings),
+			apply_changes(&mut fresh, &settings)
+		);
+		assert_eq!(layout.root, fresh.root);
+	}
+
+	/// Tests that nested groups with different [gap overrides] are laid out with their effective
+	/// gaps, and that groups without an override inherit their parent's.
+	///
+	/// [gap overrides]: GroupNode::set_gap
+	#[test]
+	fn gap_overrides() {
+		let settings = LayoutSettings::new().window_gap(10);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 500, &settings);
+		layout.set_gap(Some(20));
+
+		// ┌──────────┬───────────┐
+		// │          │     2     │
+		// │    1     ├─────┬─────┤
+		// │          │  3  │  4  │
+		// └──────────┴─────┴─────┘
+		layout.push_window_back(1);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| {
+				group.push_window_back(2);
+				group
+					.push_group_back_with(Orientation::LeftToRight, |group| {
+						group.set_gap(Some(4));
+						group.push_windows_back([3, 4]);
+					})
+					.unwrap();
+			})
+			.unwrap();
+
+		assert_eq!(
+			apply_changes(&mut layout, &settings),
+			[
+				(1, 10, 10, 480, 480),
+				(2, 510, 10, 480, 230),
+				(3, 510, 260, 238, 230),
+				(4, 752, 260, 238, 230),
+			]
+		);
+
+		// Removing the innermost override makes that group inherit the gap of 20.
+		assert!(layout.adjust_gap(&3, 16, &settings));
+		assert_eq!(layout[1].unwrap_group_ref()[1].unwrap_group_ref().gap(), Some(20));
+
+		assert_eq!(
+			apply_changes(&mut layout, &settings),
+			[(3, 510, 260, 230, 230), (4, 760, 260, 230, 230)]
+		);
+	}
+
+	/// Tests that pixels which can't be shared evenly go to the leftmost or topmost nodes on screen
+	/// whatever the group's orientation, and that windows are reconfigured in layout order.
+	#[test]
+	fn remainder() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		for orientation in [
+			Orientation::LeftToRight,
+			Orientation::RightToLeft,
+			Orientation::TopToBottom,
+			Orientation::BottomToTop,
+		] {
+			let mut layout: TilingLayout<u32> = TilingLayout::new(orientation, 0, 0, 1002, 1002, &settings);
+			layout.push_windows_back(1..=4);
+			let reconfigured = apply_changes(&mut layout, &settings);
+
+			assert_eq!(
+				reconfigured.iter().map(|&(window, ..)| window).collect::<Vec<_>>(),
+				layout.windows().into_iter().copied().collect::<Vec<_>>()
+			);
+
+			let mut tiles: Vec<(i32, u32)> = reconfigured
+				.iter()
+				.map(|&(_, x, y, width, height)| match orientation.axis() {
+					Axis::Horizontal => (x, width),
+					Axis::Vertical => (y, height),
+				})
+				.collect();
+			tiles.sort_unstable();
+			assert_eq!(tiles, [(0, 251), (251, 251), (502, 250), (752, 250)], "{orientation:?}");
+		}
+	}
+
+	/// Tests that replacing a window with a group mid-layout gives the group exactly the window's
+	/// place without resizing its siblings, whatever the group's orientation.
+	#[test]
+	fn replace() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		for orientation in [Orientation::LeftToRight, Orientation::RightToLeft] {
+			let mut layout: TilingLayout<u32> = TilingLayout::new(orientation, 0, 0, 1000, 500, &settings);
+			layout.push_windows_back(1..=4);
+			apply_changes(&mut layout, &settings);
+			// Give the nodes unequal sizes, which a rebalance would undo.
+			layout.resize_window(&1, Axis::Horizontal, Amount::Pixels(100), &settings);
+			apply_changes(&mut layout, &settings);
+
+			let rects: Vec<_> = layout
+				.iter()
+				.map(|node| (node.x(), node.y(), node.width(), node.height()))
+				.collect();
+
+			let mut group = GroupNode::new(Orientation::TopToBottom);
+			group.push_windows_back([5, 6]);
+			let old = layout.replace(1, Node::Group(group));
+			assert_eq!(old.unwrap_window().window(), &2);
+
+			let reconfigured = apply_changes(&mut layout, &settings);
+			let (x, y, width, height) = rects[1];
+			assert_eq!(
+				reconfigured,
+				[
+					(5, x, y, width, height / 2),
+					(6, x, y + (height / 2) as i32, width, height / 2)
+				],
+				"{orientation:?}"
+			);
+			assert_eq!(
+				layout
+					.iter()
+					.map(|node| (node.x(), node.y(), node.width(), node.height()))
+					.collect::<Vec<_>>(),
+				rects,
+				"{orientation:?}"
+			);
+
+			// Windows can be wrapped in place too.
+			layout.replace_with(0, |node| {
+				let mut group = GroupNode::new(Orientation::TopToBottom);
+				group.push_node_back(node);
+
+				Node::Group(group)
+			});
+			let (x, y, width, height) = rects[0];
+			assert_eq!(apply_changes(&mut layout, &settings), [(1, x, y, width, height)]);
+		}
+	}
+
+	/// Tests that a group squeezed below its minimum size takes what it needs from its siblings,
+	/// rather than its windows spilling out of it.
+	#[test]
+	fn minimum_group_size() {
+		let settings = LayoutSettings::new().window_gap(15);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::TopToBottom, 0, 0, 1000, 1000, &settings);
+		layout.push_window_back(1);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back(2..12))
+			.unwrap();
+		apply_changes(&mut layout, &settings);
+
+		// The stack needs 1 pixel for each of its ten windows and 15 for each of its nine gaps, more
+		// than its half of the layout.
+		layout.resize(0, 0, 1000, 300, &settings);
+		apply_changes(&mut layout, &settings);
+
+		assert!(!layout.is_deferred());
+		assert_eq!(layout[1].height(), 10 + 9 * 15);
+
+		let (top, bottom) = (layout[1].y(), layout[1].y() + layout[1].height() as i32);
+		for node in layout[1].unwrap_group_ref() {
+			assert!(
+				node.y() >= top && node.y() + node.height() as i32 <= bottom,
+				"node = {node:?}"
+			);
+		}
+	}
+
+	/// Tests that windows' minimum sizes from their size hints, and their borders, count towards
+	/// the minimum size of the layout, and that windows are given at least their minimum size.
+	#[test]
+	fn client_minimum_size() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 500, &settings);
+		layout.push_windows_back([1, 2]);
+		assert!(layout.set_minimum_size(&1, Some((300, 200)), 2));
+		assert!(!layout.set_minimum_size(&3, Some((300, 200)), 2));
+
+		assert_eq!(layout.minimum_size(0), (304 + 1, 204));
+
+		// The window with a minimum size takes what it needs from its sibling.
+		layout.resize(0, 0, 400, 500, &settings);
+		apply_changes(&mut layout, &settings);
+		assert!(!layout.is_deferred());
+		assert_eq!((layout[0].width(), layout[1].width()), (304, 96));
+
+		// A layout too small for the minimum size is deferred.
+		layout.resize(0, 0, 1000, 150, &settings);
+		apply_changes(&mut layout, &settings);
+		assert!(layout.is_deferred());
+	}
+
+	/// Tests that gaps larger than the space available to a group clamp rather than underflow.
+	#[test]
+	fn gap_clamping() {
+		let settings = LayoutSettings::new().window_gap(15);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 500, &settings);
+		layout.push_windows_back([1, 2, 3]);
+
+		// Shrinking the gap below zero clamps it at zero.
+		assert!(layout.adjust_gap(&1, -100, &settings));
+		assert_eq!(layout.gap(), Some(0));
+		assert!(!layout.adjust_gap(&4, -100, &settings));
+
+		// A group too small to fit its gaps gives its nodes no space rather than underflowing.
+		let mut group: GroupNode<u32> = GroupNode::with(Orientation::LeftToRight, 0, 0, 20, 100);
+		group.set_gap(Some(15));
+		group.push_windows_back([1, 2, 3]);
+
+		group.apply_changes(&mut resize_window, &settings).unwrap();
+
+		for node in &group {
+			assert_eq!(node.width(), 0, "node = {node:?}");
+		}
+
+		// A layout shrunk below its total gap requirement is deferred.
+		layout.set_gap(None);
+		layout.resize(0, 0, 60, 500, &settings);
+		layout.apply_changes(&mut resize_window, &settings).unwrap();
+
+		assert!(layout.is_deferred());
+	}
+
+	/// Tests that only the active child of a stacked group is visible, and that cycling the active
+	/// child only reconfigures the windows whose visibility changed.
+	#[test]
+	fn stacked_visibility() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1920, 1080, &settings);
+		layout.set_stacked(true);
+		layout.push_windows_back(1..=10);
+
+		let apply_changes = |layout: &mut TilingLayout<u32>| {
+			let mut reconfigured = Vec::new();
+
+			layout
+				.apply_changes(
+					&mut |&window: &u32, _, _, _, _, visibility| -> Result<(), ()> {
+						reconfigured.push((window, visibility));
+
+						Ok(())
+					},
+					&settings,
+				)
+				.unwrap();
+
+			reconfigured
+		};
+
+		let reconfigured = apply_changes(&mut layout);
+		let count = |visibility| reconfigured.iter().filter(|&&(_, v)| v == visibility).count();
+
+		assert_eq!(reconfigured.len(), 10);
+		assert_eq!((count(Visibility::Visible), count(Visibility::Hidden)), (1, 9));
+		assert!(reconfigured.contains(&(1, Visibility::Visible)));
+		// Every child fills the whole group.
+		assert!(layout
+			.iter()
+			.all(|node| (node.x(), node.y(), node.width(), node.height()) == (0, 0, 1920, 1080)));
+
+		// Cycling the active child shows the new one and hides the old one.
+		layout.cycle_active(1);
+		assert_eq!(
+			apply_changes(&mut layout),
+			[(1, Visibility::Hidden), (2, Visibility::Visible)]
+		);
+
+		layout.cycle_active(-2);
+		assert_eq!(
+			apply_changes(&mut layout),
+			[(2, Visibility::Hidden), (10, Visibility::Visible)]
+		);
+		assert_eq!(layout.active(), Some(layout[9].id()));
+
+		// Unstacking the group shows every window side by side.
+		layout.set_stacked(false);
+		let reconfigured = apply_changes(&mut layout);
+
+		assert_eq!(reconfigured.len(), 10);
+		assert!(reconfigured
+			.iter()
+			.all(|&(_, visibility)| visibility == Visibility::Visible));
+	}
+
+	/// Tests that adopted windows keep their relative sizes when changes are first applied.
+	#[test]
+	fn adoption() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let widths = |layout: &TilingLayout<u32>| layout.iter().map(Node::width).collect::<Vec<_>>();
+
+		let windows = [
+			(1, (0, 0, 1000, 1000)),
+			(2, (1000, 0, 500, 1000)),
+			(3, (1500, 0, 500, 1000)),
+		];
+
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 2000, 1000, &settings);
+		for (window, rect) in windows {
+			layout.adopt_window_back(window, rect);
+		}
+		apply_changes(&mut layout, &settings);
+		assert_eq!(widths(&layout), [1000, 500, 500]);
+
+		// The proportions survive being adopted into a layout of a different size.
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 1000, &settings);
+		for (window, rect) in windows {
+			layout.adopt_window_back(window, rect);
+		}
+		apply_changes(&mut layout, &settings);
+		assert_eq!(widths(&layout), [500, 250, 250]);
+		assert_eq!(layout[2].x(), 750);
+
+		// Windows which are added as usual are still given an equal share.
+		let mut layout = TilingLayout::new(Orientation::RightToLeft, 0, 0, 2000, 1000, &settings);
+		layout.push_window_back(4);
+		layout.adopt_window_back(1, (0, 0, 1500, 1000));
+		apply_changes(&mut layout, &settings);
+		assert_eq!(
+			layout
+				.iter()
+				.map(|node| *node.unwrap_window_ref().window())
+				.collect::<Vec<_>>(),
+			[4, 1]
+		);
+		assert_eq!(widths(&layout), [1000, 1000]);
+	}
 }
@@ -0,0 +1,170 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl<Window> GroupNode<Window> {
+	/// Returns the group's first window node, or [`None`] if there are no windows in the group.
+	///
+	/// This is the group's first child if it is a window node; if it is a group, that group's first
+	/// window node is found in turn. The first child of a [stacked] group is taken to be its
+	/// [active] child, as that is the only one shown.
+	///
+	/// [stacked]: Self::is_stacked
+	/// [active]: Self::active
+	pub fn first_window(&self) -> Option<&WindowNode<Window>> {
+		let mut group = self;
+
+		loop {
+			let node = match group.is_stacked() {
+				true => group.get(group.active().and_then(|id| group.position_of(id))?)?,
+				false => group.first()?,
+			};
+
+			match node {
+				Node::Window(node) => return Some(node),
+				Node::Group(child) => group = child,
+			}
+		}
+	}
+}
+
+impl<Window> TilingLayout<Window> {
+	/// Swaps the given `window` with the layout's [first window], returning whether they were
+	/// swapped.
+	///
+	/// Only the windows are swapped, as with [`WindowNode::swap_windows`]: the layout's structure
+	/// and the sizes of its nodes are left untouched. Returns [`false`] if the `window` is not in
+	/// the layout, or if it already is the first window.
+	///
+	/// [first window]: GroupNode::first_window
+	pub fn swap_with_first(&mut self, window: &Window) -> bool
+	where
+		Window: PartialEq,
+	{
+		let (Some(id), Some(first)) = (self.id_of_window(window), self.first_window().map(WindowNode::id)) else {
+			return false;
+		};
+
+		id != first && self.swap_windows_by_id(id, first)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	/// Returns a layout with the first window nested two groups deep, and the others in other
+	/// groups at other depths.
+	fn layout() -> (TilingLayout<u32>, LayoutSettings) {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1200, 600, &settings);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| {
+				group
+					.push_group_back_with(Orientation::LeftToRight, |group| group.push_windows_back([1, 2]))
+					.unwrap();
+				group.push_window_back(3);
+			})
+			.unwrap();
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([4, 5]))
+			.unwrap();
+		apply(&mut layout, &settings);
+
+		(layout, settings)
+	}
+
+	/// Returns the layout with the windows `a` and `b` swapped back and its changes applied, so
+	/// that it can be compared with the layout before they were swapped.
+	fn swapped_back(layout: &TilingLayout<u32>, a: u32, b: u32, settings: &LayoutSettings) -> TilingLayout<u32> {
+		let mut layout = layout.clone();
+		let (a, b) = (layout.id_of_window(&a).unwrap(), layout.id_of_window(&b).unwrap());
+
+		assert!(layout.swap_windows_by_id(a, b));
+		apply(&mut layout, settings);
+
+		layout
+	}
+
+	/// Tests that swapping a window in another group and at another depth with the first window
+	/// swaps only the two windows.
+	#[test]
+	fn swap_with_first() {
+		let (mut layout, settings) = layout();
+		let original = layout.clone();
+
+		assert!(layout.swap_with_first(&5));
+		assert_eq!(layout.first_window().map(WindowNode::window), Some(&5));
+
+		// The windows swapped back are exactly what the layout was, sizes and all.
+		assert_ne!(*layout, *original);
+		assert_eq!(*swapped_back(&layout, 1, 5, &settings), *original);
+
+		// Only the two swapped windows are reconfigured.
+		let mut reconfigured = Vec::new();
+		layout
+			.apply_changes(
+				&mut |window, _, _, _, _, _| {
+					reconfigured.push(*window);
+
+					Ok::<_, ()>(())
+				},
+				&settings,
+			)
+			.unwrap();
+		reconfigured.sort_unstable();
+		assert_eq!(reconfigured, [1, 5]);
+	}
+
+	/// Tests that swapping the first window with itself does nothing.
+	#[test]
+	fn already_first() {
+		let (mut layout, _) = layout();
+		let original = layout.clone();
+
+		assert!(!layout.swap_with_first(&1));
+		assert!(!layout.swap_with_first(&6));
+		assert_eq!(*layout, *original);
+	}
+
+	/// Tests that the first window of a stacked group is its active child.
+	#[test]
+	fn stacked() {
+		let (mut layout, settings) = layout();
+
+		let group = layout[0].unwrap_group_mut()[0].unwrap_group_mut();
+		group.set_stacked(true);
+		let active = group[1].id();
+		group.set_active(active);
+		apply(&mut layout, &settings);
+		let original = layout.clone();
+
+		assert_eq!(layout.first_window().map(WindowNode::window), Some(&2));
+
+		assert!(layout.swap_with_first(&4));
+		assert_eq!(*swapped_back(&layout, 2, 4, &settings), *original);
+
+		// The hidden window swapped into the active child is shown, and the window swapped out of
+		// it is hidden.
+		let mut layout = original;
+		assert!(layout.swap_with_first(&1));
+
+		let mut visibilities = Vec::new();
+		layout
+			.apply_changes(
+				&mut |window, _, _, _, _, visibility| {
+					visibilities.push((*window, visibility));
+
+					Ok::<_, ()>(())
+				},
+				&settings,
+			)
+			.unwrap();
+		visibilities.sort_unstable_by_key(|&(window, _)| window);
+		assert_eq!(visibilities, [(1, Visibility::Visible), (2, Visibility::Hidden)]);
+	}
+}
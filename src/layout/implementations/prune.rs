@@ -0,0 +1,307 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl<Window> GroupNode<Window> {
+	/// Returns whether the group is kept alive while it is empty.
+	///
+	/// Empty groups are otherwise [pruned] when changes are applied, so that they don't take up
+	/// space in the layout. Layout managers may keep a group alive to use it as a placeholder.
+	///
+	/// # See also
+	/// - [`set_keep_alive`](Self::set_keep_alive)
+	///
+	/// [pruned]: Self::prune_empty
+	#[inline(always)]
+	pub const fn is_kept_alive(&self) -> bool {
+		self.keep_alive
+	}
+
+	/// Sets whether the group is [kept alive] while it is empty.
+	///
+	/// # See also
+	/// - [`is_kept_alive`](Self::is_kept_alive)
+	///
+	/// [kept alive]: Self::is_kept_alive
+	#[inline(always)]
+	pub const fn set_keep_alive(&mut self, keep_alive: bool) {
+		self.keep_alive = keep_alive;
+	}
+
+	/// Removes every empty descendent group which is not [kept alive] or [manual], and collapses
+	/// chains of groups, returning the number of groups removed.
+	///
+	/// A group whose only child is another group along the same [axis] takes that group's children
+	/// (and its orientation, if it is reversed differently), keeping their sizes. Groups are not
+	/// collapsed if either is [stacked] or [manual], if the child is [dissolving] or [kept alive],
+	/// if their gap overrides differ, or if either has an orientation change pending.
+	///
+	/// This is done automatically when changes are applied. The space of removed groups is shared
+	/// between their siblings when changes are next applied.
+	///
+	/// [kept alive]: Self::is_kept_alive
+	/// [manual]: Self::is_manual
+	/// [axis]: Orientation::axis
+	/// [stacked]: Self::is_stacked
+	/// [dissolving]: Self::is_dissolving
+	pub fn prune_empty(&mut self) -> usize {
+		let mut pruned = 0;
+
+		// Groups are pruned with an explicit stack, rather than recursively, so that deeply nested
+		// layouts can't overflow the stack. Each group being pruned has its children taken out of
+		// it, along with the children which have been pruned so far, until they have all been
+		// pruned. This group is represented by `None`.
+		let mut groups = vec![(None, mem::take(&mut self.children).into_iter(), VecDeque::new())];
+
+		while let Some((_, children, pruned_children)) = groups.last_mut() {
+			match children.next() {
+				Some(Node::Group(mut group)) => {
+					let children = mem::take(&mut group.children);
+					let capacity = children.len();
+
+					groups.push((Some(group), children.into_iter(), VecDeque::with_capacity(capacity)));
+				},
+				Some(window) => pruned_children.push_back(window),
+
+				None => {
+					let (group, _, children) = groups.pop().expect("we just found the last group");
+
+					// Pruning a group changes the tallies of every group it is within.
+					let Some(mut group) = group else {
+						self.children = children;
+						self.invalidate_tally();
+						pruned += self.prune_children();
+
+						break;
+					};
+
+					group.children = children;
+					group.invalidate_tally();
+					pruned += group.prune_children();
+
+					let (.., parent_children) = groups.last_mut().expect("the group's parent is still being pruned");
+					parent_children.push_back(Node::Group(group));
+				},
+			}
+		}
+
+		pruned
+	}
+
+	/// Removes every empty child group which is not [kept alive] or [manual], and collapses a chain
+	/// of groups, returning the number of groups removed.
+	///
+	/// [kept alive]: Self::is_kept_alive
+	/// [manual]: Self::is_manual
+	fn prune_children(&mut self) -> usize {
+		let mut pruned = 0;
+
+		// Removed in reverse so that the indexes of the remaining children don't change.
+		for index in (0..self.len()).rev() {
+			if let Node::Group(group) = &self[index] {
+				if group.is_empty() && !group.keep_alive && !group.manual {
+					self.remove(index);
+					pruned += 1;
+				}
+			}
+		}
+
+		if self.collapse_chain() {
+			pruned += 1;
+		}
+
+		pruned
+	}
+
+	/// Replaces the group's only child with that child's own children, if it is a group along the
+	/// same axis which can be collapsed, returning whether it was.
+	///
+	/// See [`prune_empty`](Self::prune_empty) for when groups are collapsed.
+	fn collapse_chain(&mut self) -> bool {
+		if self.is_stacked() || self.manual || self.new_orientation.is_some() {
+			return false;
+		}
+		let (gap, axis) = (self.gap(), self.orientation.axis());
+
+		let [Node::Group(child)] = self.children.make_contiguous() else {
+			return false;
+		};
+
+		let collapsible = !child.is_stacked()
+			&& !child.dissolving
+			&& !child.keep_alive
+			&& !child.manual
+			&& child.gap().is_none_or(|child_gap| gap == Some(child_gap))
+			&& child.new_orientation.is_none()
+			&& child.orientation.axis() == axis;
+		if !collapsible {
+			return false;
+		}
+
+		let Some(Node::Group(mut child)) = self.children.pop_front() else {
+			unreachable!("the group's only child is a group");
+		};
+
+		// Children are stored in the order of their group's orientation, so the orientation is taken
+		// with them.
+		self.orientation = child.orientation;
+
+		// The child's children are already sized to fill it, and so this group.
+		self.children = mem::take(&mut child.children);
+		self.tally = child.tally;
+		self.total_node_primary = child.total_node_primary;
+
+		self.additions = mem::take(&mut child.additions);
+		self.total_removed_primary = child.total_removed_primary;
+		self.equalize |= child.equalize;
+		self.maximize = mem::take(&mut child.maximize);
+
+		// Make sure the group lays out its new children, even if its size hasn't changed.
+		self.new_width.get_or_insert(self.width);
+		self.new_height.get_or_insert(self.height);
+
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::reconfigured;
+
+	fn layout(orientation: Orientation) -> (TilingLayout<u32>, LayoutSettings) {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		(TilingLayout::new(orientation, 0, 0, 1200, 600, &settings), settings)
+	}
+
+	#[test]
+	fn empty_groups() {
+		let (mut layout, settings) = layout(Orientation::LeftToRight);
+
+		// [1 | 2 | (3 / [4])]
+		layout.push_windows_back([1, 2]);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| {
+				group.push_window_back(3);
+				group
+					.push_group_back_with(Orientation::LeftToRight, |group| group.push_window_back(4))
+					.unwrap();
+			})
+			.unwrap();
+		reconfigured(&mut layout, &settings);
+
+		// Leave the nested groups empty: [1 | 2 | ([])].
+		let group = layout[2].unwrap_group_mut();
+		group.remove(0);
+		group[0].unwrap_group_mut().remove(0);
+
+		assert_eq!(layout.prune_empty(), 2);
+		assert_eq!(layout.len(), 2);
+		assert_eq!(layout.prune_empty(), 0);
+
+		// The remaining windows reclaim the groups' space.
+		assert_eq!(
+			reconfigured(&mut layout, &settings),
+			[(1, 0, 0, 600, 600), (2, 600, 0, 600, 600)]
+		);
+	}
+
+	#[test]
+	fn kept_alive() {
+		let (mut layout, settings) = layout(Orientation::LeftToRight);
+
+		layout.push_windows_back([1, 2]);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.set_keep_alive(true))
+			.unwrap();
+		layout.push_group_back(Orientation::TopToBottom).unwrap();
+
+		// Empty groups are pruned automatically when changes are applied, unless kept alive.
+		assert_eq!(
+			reconfigured(&mut layout, &settings),
+			[(1, 0, 0, 400, 600), (2, 400, 0, 400, 600)]
+		);
+		assert_eq!(layout.len(), 3);
+		assert!(layout[2].unwrap_group_ref().is_kept_alive());
+	}
+
+	#[test]
+	fn chains() {
+		let (mut layout, settings) = layout(Orientation::LeftToRight);
+
+		// [[(1 / 2) | 3]], where the groups around `3` are along the same axis as the root.
+		layout
+			.push_group_back_with(Orientation::LeftToRight, |group| {
+				group
+					.push_group_back_with(Orientation::RightToLeft, |group| {
+						group
+							.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([1, 2]))
+							.unwrap();
+						group.push_window_back(3);
+					})
+					.unwrap();
+			})
+			.unwrap();
+
+		// Both groups in the chain are collapsed into the root, which takes the orientation of the
+		// innermost one.
+		assert_eq!(layout.prune_empty(), 2);
+		assert_eq!(layout.orientation(), Orientation::RightToLeft);
+		assert_eq!(layout.len(), 2);
+
+		// The layout is the same as without the chain.
+		let (mut flat, _) = self::layout(Orientation::RightToLeft);
+		flat.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([1, 2]))
+			.unwrap();
+		flat.push_window_back(3);
+		assert_eq!(reconfigured(&mut layout, &settings), reconfigured(&mut flat, &settings));
+	}
+
+	#[test]
+	fn chain_sizes() {
+		let (mut layout, settings) = layout(Orientation::LeftToRight);
+
+		// [4 | [(1 / 2) | 3]], with `(1 / 2)` twice as wide as `3`.
+		layout.push_window_back(4);
+		layout
+			.push_group_back_with(Orientation::LeftToRight, |group| {
+				group
+					.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([1, 2]))
+					.unwrap();
+				group.push_window_back(3);
+			})
+			.unwrap();
+		reconfigured(&mut layout, &settings);
+		assert!(layout[1].unwrap_group_mut().resize_child(0, 100));
+		reconfigured(&mut layout, &settings);
+
+		// Removing `4` leaves a chain, which is collapsed keeping the sizes of its children in
+		// proportion.
+		layout.remove(0);
+		assert_eq!(layout.prune_empty(), 1);
+		assert_eq!(
+			reconfigured(&mut layout, &settings),
+			[(1, 0, 0, 800, 300), (2, 0, 300, 800, 300), (3, 800, 0, 400, 600)]
+		);
+	}
+
+	#[test]
+	fn different_axes() {
+		let (mut layout, settings) = layout(Orientation::LeftToRight);
+
+		// [(1 / 2)]: the group is along a different axis, so it isn't collapsed.
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([1, 2]))
+			.unwrap();
+
+		assert_eq!(layout.prune_empty(), 0);
+		assert_eq!(
+			reconfigured(&mut layout, &settings),
+			[(1, 0, 0, 1200, 300), (2, 0, 300, 1200, 300)]
+		);
+		assert_eq!(layout.len(), 1);
+	}
+}
@@ -0,0 +1,280 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+/// The smallest share of its group, as a percentage, that a node can be resized to.
+const MIN_SHARE_PERCENT: u64 = 5;
+
+impl<Window> TilingLayout<Window> {
+	/// Resizes the node of the given `window` along the given `axis` by the given `amount`,
+	/// returning the number of pixels it was actually resized by.
+	///
+	/// The node is resized in the deepest group along the `axis` which it is in. If the window's
+	/// own group is perpendicular to the `axis`, its ancestors are tried in turn, so that, e.g.,
+	/// widening a window in a vertical group widens that group within its horizontal parent. The
+	/// resized node trades space with a sibling, as with [`resize_child`].
+	///
+	/// Neither node is shrunk below 5% of the group, nor below the minimum size needed to fit its
+	/// descendents. If the full `amount` can't be applied, the largest possible portion of it is.
+	///
+	/// Returns [`None`] if the `window` is not in the layout, or if there is no group along the
+	/// `axis` to resize it in.
+	///
+	/// [`resize_child`]: GroupNode::resize_child
+	pub fn resize_window(
+		&mut self,
+		window: &Window,
+		axis: Axis,
+		amount: Amount,
+		settings: &LayoutSettings,
+	) -> Option<i32>
+	where
+		Window: PartialEq,
+	{
+		self.resize_node(self.id_of_window(window)?, axis, amount, settings)
+	}
+
+	/// Resizes the node with the given `id`, which may be a group, along the given `axis` by the
+	/// given `amount`, as with [`resize_window`].
+	///
+	/// A group is resized within its own ancestors, keeping the shares of the nodes within it.
+	/// Returns [`None`] if there is no such node, if it is the root group, or if there is no group
+	/// along the `axis` to resize it in.
+	///
+	/// [`resize_window`]: Self::resize_window
+	pub fn resize_node(&mut self, id: NodeId, axis: Axis, amount: Amount, settings: &LayoutSettings) -> Option<i32> {
+		let path = self.path_of(id)?;
+
+		self.root.resize_along(&path, axis, amount, settings.window_gap)
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Resizes the descendent node at the given `path` (or its nearest ancestor) in the deepest
+	/// group along the given `axis`.
+	///
+	/// `inherited_gap` is the effective [window gap] of the group's parent.
+	///
+	/// See [`TilingLayout::resize_window`].
+	///
+	/// [window gap]: LayoutSettings::window_gap
+	fn resize_along(&mut self, path: &[usize], axis: Axis, amount: Amount, inherited_gap: u32) -> Option<i32> {
+		let gap = self.gap().unwrap_or(inherited_gap);
+		let (&index, rest) = path.split_first()?;
+
+		// Deeper groups take precedence over this one.
+		if !rest.is_empty() {
+			if let Node::Group(group) = &mut self[index] {
+				if let Some(delta) = group.resize_along(rest, axis, amount, gap) {
+					return Some(delta);
+				}
+			}
+		}
+
+		self.resize_child_clamped(index, axis, amount, gap)
+	}
+
+	/// Resizes the child at the given `index` by the given `amount`, clamped so that neither it nor
+	/// the sibling it trades space with is shrunk below its minimum size.
+	///
+	/// Returns [`None`] if the group isn't along the given `axis`, or if the child can't be
+	/// resized in it at all.
+	fn resize_child_clamped(&mut self, index: usize, axis: Axis, amount: Amount, gap: u32) -> Option<i32> {
+		// Nodes in a stacked group all take up the whole group.
+		if self.orientation.axis() != axis || self.is_stacked() || index >= self.len() || self.len() < 2 {
+			return None;
+		}
+		let sibling = if index + 1 < self.len() { index + 1 } else { index - 1 };
+
+		// The nodes' current dimensions are those of the applied orientation.
+		let group_dimension = match axis {
+			Axis::Horizontal => self.width,
+			Axis::Vertical => self.height,
+		};
+
+		let requested = match amount {
+			Amount::Pixels(pixels) => i64::from(pixels),
+			Amount::Percent(percent) => i64::from(group_dimension) * i64::from(percent) / 100,
+		};
+
+		let min_share = (u64::from(group_dimension) * MIN_SHARE_PERCENT).div_ceil(100) as u32;
+		let minimum = |node: &Node<Window>| {
+			let (min_width, min_height) = match node {
+				Node::Group(group) => group.minimum_size(gap),
+				Node::Window(_) => (1, 1),
+			};
+
+			match axis {
+				Axis::Horizontal => min_width,
+				Axis::Vertical => min_height,
+			}
+			.max(min_share)
+		};
+
+		let max_shrink = self[index]
+			.primary_dimension(axis)
+			.saturating_sub(minimum(&self[index]));
+		let max_grow = self[sibling]
+			.primary_dimension(axis)
+			.saturating_sub(minimum(&self[sibling]));
+
+		let delta = requested.clamp(-i64::from(max_shrink), i64::from(max_grow)) as i32;
+		if delta != 0 {
+			self.resize_child(index, delta);
+		}
+
+		Some(delta)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Applies the changes made to the given `layout`, returning the windows that were
+	/// reconfigured.
+	fn apply_changes(layout: &mut TilingLayout<u32>, settings: &LayoutSettings) -> Vec<(u32, i32, i32, u32, u32)> {
+		let mut reconfigured = Vec::new();
+
+		layout
+			.apply_changes(
+				&mut |window: &u32, x, y, width, height, _| -> Result<(), ()> {
+					reconfigured.push((*window, x, y, width, height));
+
+					Ok(())
+				},
+				settings,
+			)
+			.unwrap();
+
+		reconfigured
+	}
+
+	/// Creates a layout with nested groups of alternating axes.
+	///
+	/// ```text
+	/// ┌────────┬────────┐
+	/// │        │   2    │
+	/// │   1    ├────┬───┤
+	/// │        │ 3  │ 4 │
+	/// └────────┴────┴───┘
+	/// ```
+	fn nested_layout(settings: &LayoutSettings) -> TilingLayout<u32> {
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 500, settings);
+		layout.push_window_back(1);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| {
+				group.push_window_back(2);
+				group
+					.push_group_back_with(Orientation::LeftToRight, |group| group.push_windows_back([3, 4]))
+					.unwrap();
+			})
+			.unwrap();
+		apply_changes(&mut layout, settings);
+
+		layout
+	}
+
+	/// Tests that windows are resized in the deepest group along the axis, walking up through
+	/// perpendicular ancestors.
+	#[test]
+	fn nested_axes() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = nested_layout(&settings);
+
+		// 4 is resized within its own horizontal group, trading space with 3.
+		assert_eq!(
+			layout.resize_window(&4, Axis::Horizontal, Amount::Pixels(50), &settings),
+			Some(50)
+		);
+		assert_eq!(
+			apply_changes(&mut layout, &settings),
+			[(3, 500, 250, 200, 250), (4, 700, 250, 300, 250)]
+		);
+
+		// 4's group is horizontal, so its vertical parent group resizes the group 4 is in instead.
+		assert_eq!(
+			layout.resize_window(&4, Axis::Vertical, Amount::Percent(10), &settings),
+			Some(50)
+		);
+		assert_eq!(
+			apply_changes(&mut layout, &settings),
+			[(2, 500, 0, 500, 200), (3, 500, 200, 200, 300), (4, 700, 200, 300, 300)]
+		);
+
+		// 2's group is vertical, so the root resizes 2's group, which trades space with 1.
+		assert_eq!(
+			layout.resize_window(&2, Axis::Horizontal, Amount::Pixels(-100), &settings),
+			Some(-100)
+		);
+		assert_eq!(
+			apply_changes(&mut layout, &settings),
+			[
+				(1, 0, 0, 600, 500),
+				(2, 600, 0, 400, 200),
+				(3, 600, 200, 160, 300),
+				(4, 760, 200, 240, 300)
+			]
+		);
+
+		// There is no vertical group for 1 to be resized in.
+		assert_eq!(
+			layout.resize_window(&1, Axis::Vertical, Amount::Pixels(10), &settings),
+			None
+		);
+		// 5 isn't in the layout.
+		assert_eq!(
+			layout.resize_window(&5, Axis::Horizontal, Amount::Pixels(10), &settings),
+			None
+		);
+	}
+
+	/// Tests that resizes are clamped so that nodes aren't shrunk below 5% of their group or their
+	/// minimum size, and that the portion that was applied is returned.
+	#[test]
+	fn clamping() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = nested_layout(&settings);
+
+		// 1 can only be shrunk to 5% of the root group's width.
+		assert_eq!(
+			layout.resize_window(&1, Axis::Horizontal, Amount::Percent(-200), &settings),
+			Some(-450)
+		);
+		assert_eq!(apply_changes(&mut layout, &settings)[0], (1, 0, 0, 50, 500));
+
+		// Nothing more can be applied.
+		assert_eq!(
+			layout.resize_window(&1, Axis::Horizontal, Amount::Pixels(-1), &settings),
+			Some(0)
+		);
+
+		// 2's sibling group needs room for its gaps as well as its windows.
+		let mut layout = nested_layout(&settings);
+		layout[1].unwrap_group_mut()[1].unwrap_group_mut().set_gap(Some(100));
+		apply_changes(&mut layout, &settings);
+
+		// 3 and 4's group needs 1 + 100 + 1 pixels horizontally, but only 25 pixels (5% of 500)
+		// vertically.
+		assert_eq!(
+			layout.resize_window(&2, Axis::Vertical, Amount::Pixels(1000), &settings),
+			Some(225)
+		);
+		apply_changes(&mut layout, &settings);
+		assert_eq!(
+			layout.resize_window(&1, Axis::Horizontal, Amount::Pixels(1000), &settings),
+			Some(398)
+		);
+		assert_eq!(
+			apply_changes(&mut layout, &settings),
+			[
+				(1, 0, 0, 898, 500),
+				(2, 898, 0, 102, 475),
+				(3, 898, 475, 1, 25),
+				(4, 999, 475, 1, 25)
+			]
+		);
+	}
+}
@@ -0,0 +1,427 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl Default for Sizing {
+	#[inline(always)]
+	fn default() -> Self {
+		Self::Proportional(1)
+	}
+}
+
+impl Sizing {
+	/// Returns the weight of a [proportional] node, treating a weight of `0` as `1`, or [`None`]
+	/// for a [fixed] node.
+	///
+	/// [proportional]: Sizing::Proportional
+	/// [fixed]: Sizing::Fixed
+	#[inline]
+	pub(super) const fn weight(&self) -> Option<u32> {
+		match self {
+			Self::Proportional(0) => Some(1),
+			Self::Proportional(weight) => Some(*weight),
+
+			Self::Fixed(_) => None,
+		}
+	}
+}
+
+impl Default for AdditionSizing {
+	#[inline(always)]
+	fn default() -> Self {
+		Self::EqualShare
+	}
+}
+
+// Fractions are compared and hashed by their bits, so that nodes can still be compared
+// structurally.
+impl PartialEq for AdditionSizing {
+	fn eq(&self, other: &Self) -> bool {
+		match (self, other) {
+			(Self::EqualShare, Self::EqualShare) => true,
+			(Self::FractionOfGroup(fraction), Self::FractionOfGroup(other))
+			| (Self::FractionOfSplitNode(fraction), Self::FractionOfSplitNode(other)) => fraction.to_bits() == other.to_bits(),
+
+			_ => false,
+		}
+	}
+}
+
+impl Eq for AdditionSizing {}
+
+impl Hash for AdditionSizing {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		mem::discriminant(self).hash(state);
+
+		match self {
+			Self::EqualShare => (),
+			Self::FractionOfGroup(fraction) | Self::FractionOfSplitNode(fraction) => fraction.to_bits().hash(state),
+		}
+	}
+}
+
+impl AdditionSizing {
+	/// Returns the given `fraction` of the given `primary` size, with the fraction clamped between
+	/// `0.0` and `1.0`.
+	pub(super) fn fraction_of(fraction: f32, primary: u64) -> u64 {
+		let fraction = f64::from(fraction).clamp(0.0, 1.0);
+
+		// NaN is clamped to NaN, which is cast to `0`.
+		((primary as f64) * fraction).round() as u64
+	}
+}
+
+impl<Window> Node<Window> {
+	/// Returns the node's [sizing] within its group.
+	///
+	/// # See also
+	/// - [`set_sizing`](Self::set_sizing)
+	///
+	/// [sizing]: Sizing
+	// NOTE: This will return the `new_sizing` if it is set - for the current sizing before that is
+	//       applied, use the `sizing` field.
+	pub const fn sizing(&self) -> Sizing {
+		let (sizing, new_sizing) = match self {
+			Self::Window(node) => (node.sizing, node.new_sizing),
+			Self::Group(node) => (node.sizing, node.new_sizing),
+		};
+
+		match new_sizing {
+			Some(sizing) => sizing,
+			None => sizing,
+		}
+	}
+
+	/// Sets the node's [sizing] within its group.
+	///
+	/// The node's group is laid out again the next time changes are applied.
+	///
+	/// # See also
+	/// - [`sizing`](Self::sizing)
+	///
+	/// [sizing]: Sizing
+	pub const fn set_sizing(&mut self, sizing: Sizing) {
+		match self {
+			Self::Window(node) => node.new_sizing = Some(sizing),
+			Self::Group(node) => node.new_sizing = Some(sizing),
+		}
+	}
+
+	/// Returns whether the node's [sizing] has been changed since changes were last applied to its
+	/// group.
+	///
+	/// [sizing]: Sizing
+	#[inline]
+	pub(super) const fn sizing_changed(&self) -> bool {
+		match self {
+			Self::Window(node) => node.new_sizing.is_some(),
+			Self::Group(node) => node.new_sizing.is_some(),
+		}
+	}
+
+	/// Applies the change in the node's [sizing], if any, returning its sizing.
+	///
+	/// [sizing]: Sizing
+	pub(super) const fn apply_sizing(&mut self) -> Sizing {
+		let (sizing, new_sizing) = match self {
+			Self::Window(node) => (&mut node.sizing, &mut node.new_sizing),
+			Self::Group(node) => (&mut node.sizing, &mut node.new_sizing),
+		};
+
+		if let Some(new_sizing) = new_sizing.take() {
+			*sizing = new_sizing;
+		}
+
+		*sizing
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Returns the group's [addition sizing] override, if any.
+	///
+	/// If this is [`None`], the group uses the effective addition sizing of its parent group or,
+	/// for the root group, [`EqualShare`].
+	///
+	/// # See also
+	/// - [`set_addition_sizing`](Self::set_addition_sizing)
+	///
+	/// [addition sizing]: AdditionSizing
+	/// [`EqualShare`]: AdditionSizing::EqualShare
+	#[inline]
+	pub const fn addition_sizing(&self) -> Option<AdditionSizing> {
+		self.addition_sizing
+	}
+
+	/// Sets the group's [addition sizing] override, which sizes the nodes added to it and to
+	/// descendent groups without their own override.
+	///
+	/// This affects every addition which hasn't been sized yet, i.e. every node added since
+	/// changes were last applied.
+	///
+	/// # See also
+	/// - [`addition_sizing`](Self::addition_sizing)
+	///
+	/// [addition sizing]: AdditionSizing
+	#[inline]
+	pub const fn set_addition_sizing(&mut self, sizing: Option<AdditionSizing>) {
+		self.addition_sizing = sizing;
+	}
+
+	/// Resizes the group's [proportional] children to share the space left by its [fixed] children
+	/// according to their weights, leaving the fixed children as they are.
+	///
+	/// The children are resized the next time changes are applied.
+	///
+	/// [proportional]: Sizing::Proportional
+	/// [fixed]: Sizing::Fixed
+	#[inline]
+	pub const fn equalize(&mut self) {
+		self.equalize = true;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	/// Applies the changes made to the given `layout`, returning the widths of its children.
+	fn widths(layout: &mut TilingLayout<u32>, settings: &LayoutSettings) -> Vec<u32> {
+		apply(layout, settings);
+
+		layout.iter().map(Node::width).collect()
+	}
+
+	#[test]
+	fn fixed() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 500, &settings);
+		layout.push_window_back(1);
+		layout[0].set_sizing(Sizing::Fixed(250));
+		layout.push_windows_back([2, 3, 4]);
+
+		assert_eq!(widths(&mut layout, &settings), [250, 250, 250, 250]);
+
+		// Proportional nodes share the space left by the fixed node...
+		layout.remove(3);
+		assert_eq!(widths(&mut layout, &settings), [250, 375, 375]);
+
+		layout.push_window_back(4);
+		assert_eq!(widths(&mut layout, &settings), [250, 250, 250, 250]);
+
+		// ...even when the group is resized, the leftmost getting the pixel left over.
+		layout.resize(0, 0, 800, 500, &settings);
+		assert_eq!(widths(&mut layout, &settings), [250, 184, 183, 183]);
+		assert_eq!(layout[0].sizing(), Sizing::Fixed(250));
+
+		// Changing orientation leaves the fixed node with its pixels along the new axis.
+		layout.set_orientation(Orientation::TopToBottom);
+		apply(&mut layout, &settings);
+		assert_eq!(layout[0].height(), 250);
+	}
+
+	#[test]
+	fn clamping() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 500, &settings);
+		layout.push_windows_back([1, 2, 3]);
+		layout[0].set_sizing(Sizing::Fixed(600));
+		layout[1].set_sizing(Sizing::Fixed(600));
+
+		// Fixed nodes get whatever is left of the group if their pixels don't fit.
+		assert_eq!(widths(&mut layout, &settings), [600, 400, 0]);
+	}
+
+	#[test]
+	fn equalize() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 500, &settings);
+		layout.push_windows_back([1, 2, 3, 4]);
+		layout[0].set_sizing(Sizing::Fixed(100));
+		assert_eq!(widths(&mut layout, &settings), [100, 300, 300, 300]);
+
+		assert!(layout.resize_child(1, 200));
+		assert_eq!(widths(&mut layout, &settings), [100, 500, 100, 300]);
+
+		// Only the proportional nodes are equalized, according to their weights.
+		layout[3].set_sizing(Sizing::Proportional(2));
+		layout.equalize();
+		assert_eq!(widths(&mut layout, &settings), [100, 225, 225, 450]);
+	}
+
+	/// Tests that additions take `1 / φ` of the group, or of the node they split, to the pixel.
+	#[test]
+	fn golden_ratio() {
+		const INVERSE_PHI: f32 = 0.618_034;
+
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 500, &settings);
+		layout.set_addition_sizing(Some(AdditionSizing::FractionOfSplitNode(INVERSE_PHI)));
+		layout.push_window_back(1);
+		assert_eq!(widths(&mut layout, &settings), [1000]);
+
+		layout.insert_window_splitting(0, 2);
+		assert_eq!(widths(&mut layout, &settings), [382, 618]);
+
+		// Only the split node gives up space.
+		layout.insert_window_splitting(1, 3);
+		assert_eq!(widths(&mut layout, &settings), [382, 236, 382]);
+
+		// Additions which don't split a node are given an equal share.
+		layout.push_window_back(4);
+		assert_eq!(widths(&mut layout, &settings), [287, 177, 286, 250]);
+
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 500, &settings);
+		layout.set_addition_sizing(Some(AdditionSizing::FractionOfGroup(INVERSE_PHI)));
+		layout.push_window_back(1);
+		assert_eq!(widths(&mut layout, &settings), [1000]);
+
+		layout.push_window_back(2);
+		assert_eq!(widths(&mut layout, &settings), [382, 618]);
+
+		// Reversed groups split the node on the same side.
+		let mut layout: TilingLayout<u32> = TilingLayout::new(Orientation::RightToLeft, 0, 0, 1000, 500, &settings);
+		layout.set_addition_sizing(Some(AdditionSizing::FractionOfSplitNode(INVERSE_PHI)));
+		layout.push_windows_back([1, 2]);
+		assert_eq!(widths(&mut layout, &settings), [500, 500]);
+
+		layout.insert_window_splitting(0, 3);
+		assert_eq!(widths(&mut layout, &settings), [191, 309, 500]);
+		assert_eq!(layout.windows(), [&1, &3, &2]);
+	}
+
+	/// Applies random changes to a group under each addition sizing, checking the group against a
+	/// model of its windows and the space each addition should take.
+	#[test]
+	fn randomized_additions() {
+		const WIDTH: u32 = 1000;
+
+		let settings = LayoutSettings::new().window_gap(0);
+
+		// A xorshift generator, so that failures can be reproduced.
+		let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+		let mut random = |bound: usize| {
+			seed ^= seed << 13;
+			seed ^= seed >> 7;
+			seed ^= seed << 17;
+
+			(seed % bound as u64) as usize
+		};
+
+		let policies = [
+			AdditionSizing::EqualShare,
+			AdditionSizing::FractionOfGroup(0.25),
+			AdditionSizing::FractionOfSplitNode(0.5),
+			AdditionSizing::FractionOfSplitNode(0.618_034),
+		];
+
+		for policy in policies {
+			let mut layout: TilingLayout<u32> =
+				TilingLayout::new(Orientation::LeftToRight, 0, 0, WIDTH, 500, &settings);
+			layout.set_addition_sizing(Some(policy));
+
+			let mut model: Vec<u32> = Vec::new();
+			let mut next_window = 0;
+
+			for _ in 0..200 {
+				let before: Vec<(u32, u32)> = model.iter().copied().zip(layout.iter().map(Node::width)).collect();
+				// Which window each addition split, if only splits are made this round.
+				let mut splits = Some(Vec::new());
+
+				for _ in 0..=random(3) {
+					next_window += 1;
+					let window = next_window;
+
+					match random(6) {
+						// Split one of the windows which were there before this round.
+						0 | 1
+							if model
+								.iter()
+								.any(|window| before.iter().any(|&(other, _)| other == *window)) =>
+						{
+							let index = loop {
+								let index = random(model.len());
+
+								if before.iter().any(|&(other, _)| other == model[index]) {
+									break index;
+								}
+							};
+							let split = model[index];
+
+							layout.insert_window_splitting(index, window);
+							model.insert(index + 1, window);
+
+							if let Some(splits) = &mut splits {
+								splits.push((window, split));
+							}
+						},
+
+						2 => {
+							layout.push_window_back(window);
+							model.push(window);
+							splits = None;
+						},
+						3 => {
+							let index = random(model.len() + 1);
+
+							layout.insert_window(index, window);
+							model.insert(index, window);
+							splits = None;
+						},
+
+						_ if !model.is_empty() => {
+							let index = random(model.len());
+
+							layout.remove(index);
+							model.remove(index);
+							splits = None;
+						},
+						_ => {
+							layout.push_window_front(window);
+							model.insert(0, window);
+							splits = None;
+						},
+					}
+				}
+
+				let widths = widths(&mut layout, &settings);
+				assert_eq!(layout.windows().into_iter().copied().collect::<Vec<_>>(), model);
+
+				// The windows fill the group exactly, pixels lost to rounding being given back.
+				let total: u32 = widths.iter().sum();
+				if !model.is_empty() {
+					assert_eq!(total, WIDTH);
+				}
+
+				// When only splits are made, no other window moves, and each split window shares its
+				// old space with the windows which split it.
+				let (Some(splits), AdditionSizing::FractionOfSplitNode(_)) = (splits, policy) else {
+					continue;
+				};
+				let width_of = |window: u32| widths[model.iter().position(|&other| other == window).unwrap()];
+
+				// Pixels lost to rounding last time are shared out again.
+				let scale = f64::from(WIDTH) / f64::from(before.iter().map(|&(_, width)| width).sum::<u32>());
+
+				for &(window, old) in &before {
+					let splitting = splits.iter().filter(|&&(_, split)| split == window);
+					let shared =
+						width_of(window) + splitting.clone().map(|&(addition, _)| width_of(addition)).sum::<u32>();
+					let expected = f64::from(old) * scale;
+					let slack = splitting.count() as f64 + 1.0;
+
+					assert!(
+						(f64::from(shared) - expected).abs() <= slack,
+						"window {window} had {old}, now shares {shared}"
+					);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,341 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+/// The number of units a weight of `1` is converted to when [applying a snapshot].
+///
+/// Nodes are given their weight in these units as their size along their group's axis so that
+/// they are rescaled proportionally when the layout is next applied.
+///
+/// [applying a snapshot]: TilingLayout::apply_snapshot
+const WEIGHT_UNITS: f64 = 1_000_000.0;
+
+impl<Window> TilingLayout<Window> {
+	/// Returns the shape of the layout, without the windows within it.
+	///
+	/// Each node's weight is its share of its group's size along the group's [axis].
+	///
+	/// [axis]: Orientation::axis
+	pub fn snapshot(&self) -> LayoutSnapshot {
+		self.root.snapshot()
+	}
+
+	/// Rearranges the layout's existing windows into the shape of the given `snapshot`.
+	///
+	/// Window slots are filled with the layout's windows in the order that they are [iterated] in.
+	/// If there are more windows than slots, the remaining windows are added to the end of the
+	/// group containing the last filled slot. If there are fewer windows than slots, the unfilled
+	/// slots (and any groups left empty) are left out, and their weight is shared between the
+	/// others in proportion to their weights.
+	///
+	/// The root group's orientation is replaced with the snapshot's, but its other settings
+	/// (like its [gap override]) are kept; groups within it are created anew.
+	///
+	/// Please note that for the nodes in the layout to be updated, [state::AquariWm::apply_changes]
+	#[cfg_attr(feature = "async", doc = "or [state::AquariWm::apply_changes_async]")]
+	/// must be called.
+	///
+	/// [iterated]: GroupNode::windows
+	/// [gap override]: GroupNode::gap
+	///
+	/// [state::AquariWm::apply_changes]: crate::state::AquariWm::apply_changes
+	#[cfg_attr(
+		feature = "async",
+		doc = "[state::AquariWm::apply_changes_async]: crate::state::AquariWm::apply_changes_async"
+	)]
+	pub fn apply_snapshot(&mut self, snapshot: &LayoutSnapshot) {
+		let root = &mut self.root;
+
+		let mut windows = VecDeque::new();
+		root.take_windows(&mut windows);
+
+		// Any pending changes to the root's children are discarded with them; the whole root is laid
+		// out again anyway.
+		root.orientation = snapshot.orientation;
+		root.new_orientation = None;
+		root.additions.clear();
+		root.total_removed_primary = 0;
+
+		root.fill(snapshot, &mut windows);
+
+		if !windows.is_empty() {
+			let mut path = Vec::new();
+			root.last_window_path(&mut path);
+
+			if let Some(group) = root.group_at_mut(&path) {
+				for node in windows {
+					group.push_node_back(Node::Window(node));
+				}
+			}
+		}
+
+		// Make sure the root lays out its new children, even if its size hasn't changed.
+		root.new_width.get_or_insert(root.width);
+		root.new_height.get_or_insert(root.height);
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Returns the shape of this group, without the windows within it.
+	fn snapshot(&self) -> LayoutSnapshot {
+		// The children's sizes are those from when changes were last applied, so they are measured
+		// along the axis from then too.
+		let axis = self.orientation.axis();
+		let total: u64 = self.iter().map(|node| node.primary_dimension(axis) as u64).sum();
+
+		let children = self
+			.iter()
+			.map(|node| SnapshotNode {
+				// If the children have no size to compare (e.g. because changes have never been
+				// applied), they are given equal weights.
+				weight: match total {
+					0 => 1.0 / (self.len() as f64),
+					total => (node.primary_dimension(axis) as f64) / (total as f64),
+				},
+
+				shape: match node {
+					Node::Window(_) => Shape::Window,
+					Node::Group(group) => Shape::Group(group.snapshot()),
+				},
+			})
+			.collect();
+
+		LayoutSnapshot {
+			orientation: self.orientation(),
+			children,
+		}
+	}
+
+	/// Removes every descendent of the group, adding its window nodes to `windows` in the order
+	/// that they are [iterated] in.
+	///
+	/// [iterated]: Self::windows
+	fn take_windows(&mut self, windows: &mut VecDeque<WindowNode<Window>>) {
+		let mut children = mem::take(&mut self.children);
+		self.clear_tally();
+		self.total_node_primary = 0;
+
+		// Reversed groups store their children in reverse.
+		if self.orientation().reversed() {
+			children.make_contiguous().reverse();
+		}
+
+		for node in children {
+			match node {
+				Node::Window(node) => windows.push_back(node),
+				Node::Group(mut group) => group.take_windows(windows),
+			}
+		}
+	}
+
+	/// Adds children to this (empty) group in the shape of the `snapshot`, filling its window
+	/// slots from the front of `windows`.
+	///
+	/// Unfilled slots and empty groups are left out.
+	pub(super) fn fill(&mut self, snapshot: &LayoutSnapshot, windows: &mut VecDeque<WindowNode<Window>>) {
+		let axis = self.orientation.axis();
+
+		for SnapshotNode { weight, shape } in &snapshot.children {
+			let mut node = match shape {
+				Shape::Window => match windows.pop_front() {
+					Some(node) => Node::Window(node),
+					None => continue,
+				},
+
+				Shape::Group(snapshot) => {
+					// The group is created with no size so that it is laid out once it is given one.
+					let mut group = GroupNode::new(snapshot.orientation);
+					group.fill(snapshot, windows);
+
+					if group.is_empty() {
+						continue;
+					}
+
+					Node::Group(group)
+				},
+			};
+
+			// The node is not tracked as an addition: it is given its weight as its size so that it
+			// is rescaled in proportion to the other nodes.
+			let primary = (weight.max(0.0) * WEIGHT_UNITS).round() as u32;
+			let (width, height) = match &mut node {
+				Node::Window(node) => (&mut node.width, &mut node.height),
+				Node::Group(node) => (&mut node.width, &mut node.height),
+			};
+			match axis {
+				Axis::Horizontal => *width = primary,
+				Axis::Vertical => *height = primary,
+			}
+			self.total_node_primary = self.total_node_primary.saturating_add(primary);
+
+			self.count_added(&node);
+			match self.orientation.reversed() {
+				false => self.children.push_back(node),
+				true => self.children.push_front(node),
+			}
+		}
+	}
+
+	/// Sets `path` to the path of indexes to the group containing the last window in the group,
+	/// returning whether there is one.
+	///
+	/// If there is no window in the group, `path` is left empty.
+	fn last_window_path(&self, path: &mut Vec<usize>) -> bool {
+		for (index, node) in self.iter().enumerate().rev() {
+			match node {
+				Node::Window(_) => return true,
+
+				Node::Group(group) => {
+					path.push(index);
+
+					if group.last_window_path(path) {
+						return true;
+					}
+
+					path.pop();
+				},
+			}
+		}
+
+		false
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::reconfigured;
+
+	const fn window(weight: f64) -> SnapshotNode {
+		SnapshotNode {
+			weight,
+			shape: Shape::Window,
+		}
+	}
+
+	fn group(weight: f64, orientation: Orientation, children: Vec<SnapshotNode>) -> SnapshotNode {
+		SnapshotNode {
+			weight,
+			shape: Shape::Group(LayoutSnapshot { orientation, children }),
+		}
+	}
+
+	/// Creates a 1000 by 600 layout of the given `windows` in a single left-to-right group.
+	fn layout(windows: impl IntoIterator<Item = u32>) -> (TilingLayout<u32>, LayoutSettings) {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 600, &settings);
+		layout.push_windows_back(windows);
+		assert_eq!(reconfigured(&mut layout, &settings).len(), layout.windows().len());
+
+		(layout, settings)
+	}
+
+	/// An editor taking up 70% of the width, with two terminals stacked to its right.
+	fn coding() -> LayoutSnapshot {
+		LayoutSnapshot {
+			orientation: Orientation::LeftToRight,
+			children: vec![
+				window(0.7),
+				group(0.3, Orientation::TopToBottom, vec![window(0.5), window(0.5)]),
+			],
+		}
+	}
+
+	#[test]
+	fn exact_fit() {
+		let (mut layout, settings) = layout(1..=3);
+
+		layout.apply_snapshot(&coding());
+
+		assert_eq!(
+			reconfigured(&mut layout, &settings),
+			[(1, 0, 0, 700, 600), (2, 700, 0, 300, 300), (3, 700, 300, 300, 300)]
+		);
+		assert_eq!(layout.snapshot(), coding());
+	}
+
+	#[test]
+	fn extra_windows() {
+		let (mut layout, settings) = layout(1..=4);
+
+		layout.apply_snapshot(&coding());
+
+		// The extra window is added to the group of the last slot.
+		assert_eq!(
+			reconfigured(&mut layout, &settings),
+			[
+				(1, 0, 0, 700, 600),
+				(2, 700, 0, 300, 200),
+				(3, 700, 200, 300, 200),
+				(4, 700, 400, 300, 200),
+			]
+		);
+	}
+
+	#[test]
+	fn missing_windows() {
+		let (mut layout, settings) = layout(1..=2);
+
+		let snapshot = LayoutSnapshot {
+			orientation: Orientation::LeftToRight,
+			children: vec![
+				window(0.6),
+				window(0.2),
+				group(0.2, Orientation::TopToBottom, vec![window(0.5), window(0.5)]),
+			],
+		};
+		layout.apply_snapshot(&snapshot);
+
+		// The empty group's weight is shared between the filled slots.
+		assert_eq!(
+			reconfigured(&mut layout, &settings),
+			[(1, 0, 0, 750, 600), (2, 750, 0, 250, 600)]
+		);
+		assert_eq!(layout.len(), 2);
+	}
+
+	#[test]
+	fn deeply_nested() {
+		let (mut layout, settings) = layout(1..=4);
+
+		// [1 | (2 / [3 | 4])], each split in half.
+		let snapshot = LayoutSnapshot {
+			orientation: Orientation::LeftToRight,
+			children: vec![
+				window(0.5),
+				group(
+					0.5,
+					Orientation::TopToBottom,
+					vec![
+						window(0.5),
+						group(0.5, Orientation::RightToLeft, vec![window(0.5), window(0.5)]),
+					],
+				),
+			],
+		};
+		layout.apply_snapshot(&snapshot);
+
+		// Groups are iterated in visual order, even if they are reversed.
+		assert_eq!(
+			reconfigured(&mut layout, &settings),
+			[
+				(1, 0, 0, 500, 600),
+				(2, 500, 0, 500, 300),
+				(3, 500, 300, 250, 300),
+				(4, 750, 300, 250, 300),
+			]
+		);
+
+		// Snapshots survive a round trip through JSON, and applying one to a layout already in its
+		// shape leaves the windows where they are.
+		let json = serde_json::to_string(&layout.snapshot()).unwrap();
+		let restored: LayoutSnapshot = serde_json::from_str(&json).unwrap();
+		assert_eq!(restored, snapshot);
+
+		layout.apply_snapshot(&restored);
+		assert_eq!(reconfigured(&mut layout, &settings)[2], (3, 500, 300, 250, 300));
+	}
+}
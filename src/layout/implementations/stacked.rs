@@ -0,0 +1,332 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fmt::{self, Display, Formatter};
+
+use super::*;
+
+impl<Window> GroupNode<Window> {
+	/// Returns whether the group is stacked.
+	///
+	/// Each child of a stacked group fills the whole group, and only the [active] child is
+	/// [visible]; like tabs, the other children are [hidden] behind it.
+	///
+	/// # See also
+	/// - [`set_stacked`](Self::set_stacked)
+	///
+	/// [active]: Self::active
+	/// [visible]: Visibility::Visible
+	/// [hidden]: Visibility::Hidden
+	// NOTE: This will return `new_stacked` if it is set - for whether the group is currently
+	//       stacked before that is applied, use the `self.stacked` field.
+	pub const fn is_stacked(&self) -> bool {
+		match self.new_stacked {
+			Some(stacked) => stacked,
+			None => self.stacked,
+		}
+	}
+
+	/// Sets whether the group is [stacked].
+	///
+	/// # See also
+	/// - [`is_stacked`](Self::is_stacked)
+	///
+	/// [stacked]: Self::is_stacked
+	pub const fn set_stacked(&mut self, stacked: bool) {
+		self.new_stacked = Some(stacked);
+	}
+
+	/// Returns the [ID] of the group's active child, or [`None`] if the group is empty.
+	///
+	/// The active child is the only [visible] child of a [stacked] group. If no child has been
+	/// made active, or the active child has been removed, the first child is active.
+	///
+	/// # See also
+	/// - [`set_active`](Self::set_active)
+	/// - [`cycle_active`](Self::cycle_active)
+	///
+	/// [ID]: NodeId
+	/// [visible]: Visibility::Visible
+	/// [stacked]: Self::is_stacked
+	pub fn active(&self) -> Option<NodeId> {
+		self.new_active
+			.or(self.active)
+			.filter(|&id| self.position_of(id).is_some())
+			.or_else(|| self.get(0).map(Node::id))
+	}
+
+	/// Makes the child with the given `id` the group's [active] child.
+	///
+	/// Returns [`false`] if there is no such child.
+	///
+	/// [active]: Self::active
+	pub fn set_active(&mut self, id: NodeId) -> bool {
+		let is_child = self.position_of(id).is_some();
+
+		if is_child {
+			self.new_active = Some(id);
+		}
+
+		is_child
+	}
+
+	/// Makes the child `delta` positions after the current [active] child active, wrapping around
+	/// the ends of the group.
+	///
+	/// A negative `delta` cycles backwards.
+	///
+	/// [active]: Self::active
+	pub fn cycle_active(&mut self, delta: i32) {
+		let Some(index) = self.active().and_then(|id| self.position_of(id)) else {
+			return;
+		};
+
+		let index = (index as i64 + delta as i64).rem_euclid(self.len() as i64) as usize;
+
+		if let Some(id) = self.get(index).map(Node::id) {
+			self.new_active = Some(id);
+		}
+	}
+
+	/// Makes a neighbor of the child at the given `index` [active] if that child is active, as it
+	/// is about to be removed.
+	///
+	/// Which neighbor is made active is given by the layout's [active on removal] setting.
+	///
+	/// [active]: Self::active
+	/// [active on removal]: LayoutSettings::active_on_removal
+	pub(super) fn activate_neighbor(&mut self, index: usize) {
+		let Some(removed) = self.get(index).map(Node::id) else {
+			return;
+		};
+		if self.active() != Some(removed) {
+			return;
+		}
+
+		let before = index.checked_sub(1);
+		let after = Some(index + 1).filter(|&after| after < self.len());
+
+		let neighbor = match self.nesting.active_on_removal {
+			TabNeighbor::Right => after.or(before),
+			TabNeighbor::Left => before.or(after),
+		};
+
+		self.new_active = neighbor.and_then(|neighbor| self.get(neighbor)).map(Node::id);
+	}
+}
+
+impl<Window> TilingLayout<Window> {
+	/// Moves the tab containing the given `window` one place to the given `side` within the
+	/// innermost [stacked] group containing it, as with [`move_child`].
+	///
+	/// The tab is the stacked group's child which is, or contains, the window. Returns [`false`]
+	/// if the window isn't in a stacked group; a tab which is already at the end of its group is
+	/// left where it is.
+	///
+	/// [stacked]: GroupNode::is_stacked
+	/// [`move_child`]: GroupNode::move_child
+	pub fn move_tab(&mut self, window: &Window, side: Side) -> bool
+	where
+		Window: PartialEq,
+	{
+		let Some(path) = self.id_of_window(window).and_then(|id| self.path_of(id)) else {
+			return false;
+		};
+		let Some(depth) = (0..path.len())
+			.rev()
+			.find(|&depth| self.group_at(&path[..depth]).is_some_and(GroupNode::is_stacked))
+		else {
+			return false;
+		};
+
+		let group = self.group_at_mut(&path[..depth]).expect("we just found the group");
+		let index = path[depth];
+
+		let to = match side {
+			Side::Before => index.checked_sub(1),
+			Side::After => Some(index + 1).filter(|&to| to < group.len()),
+		};
+		if let Some(to) = to {
+			group.move_child(index, to);
+		}
+
+		true
+	}
+}
+
+impl Display for TabNeighbor {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Right => write!(f, "right"),
+			Self::Left => write!(f, "left"),
+		}
+	}
+}
+
+impl FromStr for TabNeighbor {
+	type Err = ParseTabNeighborError;
+
+	/// Parses `right` or `left`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim() {
+			"right" => Ok(Self::Right),
+			"left" => Ok(Self::Left),
+
+			other => Err(ParseTabNeighborError(other.to_owned())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures;
+
+	/// Creates a stacked layout of the given `windows` with the given `settings`, and applies it.
+	fn stacked(windows: impl IntoIterator<Item = u32>, settings: &LayoutSettings) -> TilingLayout<u32> {
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 600, settings);
+		layout.set_stacked(true);
+		layout.push_windows_back(windows);
+
+		apply(&mut layout, settings);
+
+		layout
+	}
+
+	/// Applies the changes to the `layout`, returning the windows and their x coordinates and
+	/// widths in order.
+	fn apply(layout: &mut TilingLayout<u32>, settings: &LayoutSettings) -> Vec<(u32, i32, u32)> {
+		fixtures::apply(layout, settings);
+
+		layout
+			.iter()
+			.map(|node| match node {
+				Node::Window(window) => (*window.window(), node.x(), node.width()),
+				Node::Group(_) => unreachable!("the layout only contains windows"),
+			})
+			.collect()
+	}
+
+	fn activate(layout: &mut TilingLayout<u32>, index: usize) {
+		let id = layout[index].id();
+		layout.set_active(id);
+	}
+
+	fn windows(layout: &TilingLayout<u32>) -> Vec<u32> {
+		layout.windows().into_iter().copied().collect()
+	}
+
+	#[test]
+	fn move_child() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		for orientation in [Orientation::LeftToRight, Orientation::RightToLeft] {
+			let mut layout = TilingLayout::new(orientation, 0, 0, 1000, 600, &settings);
+			layout.push_windows_back([1, 2]);
+			apply(&mut layout, &settings);
+
+			// Windows 3 and 4 are pending additions, and 4 splits 1.
+			layout.push_window_back(3);
+			layout.insert_window_splitting(0, 4);
+			assert_eq!(windows(&layout), [1, 4, 2, 3]);
+
+			layout.move_child(3, 0);
+			layout.move_child(2, 1);
+			assert_eq!(windows(&layout), [3, 4, 1, 2], "{orientation:?}");
+
+			// The additions moved with their windows: everything shares the group equally.
+			let widths: Vec<_> = apply(&mut layout, &settings)
+				.into_iter()
+				.map(|(window, _, width)| (window, width))
+				.collect();
+			assert_eq!(widths, [(3, 250), (4, 250), (1, 250), (2, 250)], "{orientation:?}");
+		}
+
+		// Moving an existing window only changes its position, not any sizes.
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 600, &settings);
+		layout.push_windows_back([1, 2, 3, 4]);
+		apply(&mut layout, &settings);
+		layout.resize_child(0, 100);
+		apply(&mut layout, &settings);
+
+		layout.move_child(0, 3);
+		assert_eq!(
+			apply(&mut layout, &settings),
+			[(2, 0, 150), (3, 150, 250), (4, 400, 250), (1, 650, 350)]
+		);
+	}
+
+	#[test]
+	fn active() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = stacked(1..=4, &settings);
+
+		// The active child stays active wherever it is moved.
+		activate(&mut layout, 1);
+		assert!(layout.move_tab(&2, Side::After));
+		assert!(layout.move_tab(&2, Side::After));
+		assert!(layout.move_tab(&2, Side::After));
+		assert_eq!(windows(&layout), [1, 3, 4, 2]);
+		assert_eq!(layout.active(), Some(layout[3].id()));
+
+		// Removing the active child activates the child to its right, or to its left if it is last.
+		activate(&mut layout, 1);
+		layout.remove(1);
+		assert_eq!(layout.active(), Some(layout[1].id()));
+		assert_eq!(windows(&layout), [1, 4, 2]);
+
+		activate(&mut layout, 2);
+		layout.pop_back();
+		assert_eq!(layout.active(), Some(layout[1].id()));
+
+		// With the left preference, the child to its left, or to its right if it is first.
+		let settings = settings.active_on_removal(TabNeighbor::Left);
+		let mut layout = stacked(1..=4, &settings);
+
+		activate(&mut layout, 2);
+		layout.remove(2);
+		assert_eq!(layout.active(), Some(layout[1].id()));
+
+		activate(&mut layout, 0);
+		layout.pop_front();
+		assert_eq!(layout.active(), Some(layout[0].id()));
+		assert_eq!(windows(&layout), [2, 4]);
+
+		// Removing the last child leaves nothing active.
+		layout.pop_back();
+		layout.pop_back();
+		assert_eq!(layout.active(), None);
+	}
+
+	#[test]
+	fn move_tab() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		// Windows not in a stacked group have no tab to move.
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 600, &settings);
+		layout.push_windows_back([1, 2]);
+		assert!(!layout.move_tab(&1, Side::After));
+		assert!(!layout.move_tab(&3, Side::After));
+
+		// A window in a group within a stacked group moves that group.
+		let mut layout = stacked([1], &settings);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([2, 3]))
+			.unwrap();
+
+		assert!(layout.move_tab(&3, Side::Before));
+		assert_eq!(windows(&layout), [2, 3, 1]);
+		// It is already first.
+		assert!(layout.move_tab(&2, Side::Before));
+		assert_eq!(windows(&layout), [2, 3, 1]);
+	}
+
+	#[test]
+	fn parse() {
+		for neighbor in [TabNeighbor::Right, TabNeighbor::Left] {
+			assert_eq!(neighbor.to_string().parse(), Ok(neighbor));
+		}
+		assert!("up".parse::<TabNeighbor>().is_err());
+	}
+}
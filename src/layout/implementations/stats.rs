@@ -0,0 +1,446 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+
+impl Tally {
+	/// The tally of an empty group.
+	pub(super) const EMPTY: Self = Self {
+		windows: 0,
+		groups: 0,
+		height: 0,
+
+		stale: false,
+	};
+
+	/// Returns a tally which is counted when it is next needed.
+	pub(crate) const fn stale() -> Self {
+		Self {
+			stale: true,
+			..Self::EMPTY
+		}
+	}
+
+	/// Counts the given `node` being added to the group.
+	const fn add<Window>(&mut self, node: &Node<Window>) {
+		match node {
+			Node::Window(_) => self.windows += 1,
+			Node::Group(group) => self.add_group(group.tally),
+		}
+	}
+
+	/// Counts a group with the given `tally` being added to the group.
+	const fn add_group(&mut self, tally: Self) {
+		self.windows += tally.windows;
+		self.groups += tally.groups + 1;
+		if tally.height + 1 > self.height {
+			self.height = tally.height + 1;
+		}
+
+		self.stale |= tally.stale;
+	}
+
+	/// Counts a child group's tally changing from `old` to `new`.
+	const fn replace_group(&mut self, old: Self, new: Self) {
+		// If the group was one of the most deeply nested and no longer is, only counting again will
+		// tell the new height.
+		self.stale |= new.stale || (new.height < old.height && old.height + 1 >= self.height);
+
+		self.windows = (self.windows + new.windows).saturating_sub(old.windows);
+		self.groups = (self.groups + new.groups).saturating_sub(old.groups);
+		if new.height + 1 > self.height {
+			self.height = new.height + 1;
+		}
+	}
+
+	/// Counts the given `node` being removed from the group.
+	const fn remove<Window>(&mut self, node: &Node<Window>) {
+		match node {
+			Node::Window(_) => self.windows = self.windows.saturating_sub(1),
+
+			Node::Group(group) => {
+				let tally = group.tally;
+
+				self.windows = self.windows.saturating_sub(tally.windows);
+				self.groups = self.groups.saturating_sub(tally.groups + 1);
+
+				// If the group was one of the most deeply nested, the height may have changed, and only
+				// counting it again will tell.
+				self.stale |= tally.stale || tally.height + 1 >= self.height;
+			},
+		}
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Counts the given `node` being added to the group's children.
+	#[inline]
+	pub(super) const fn count_added(&mut self, node: &Node<Window>) {
+		self.tally.add(node);
+	}
+
+	/// Counts the given `node` being removed from the group's children.
+	#[inline]
+	pub(super) const fn count_removed(&mut self, node: &Node<Window>) {
+		self.tally.remove(node);
+	}
+
+	/// Counts the child group at the given `index` being changed since it was counted with the
+	/// `old` tally, e.g. by initialising it once it has been added.
+	pub(super) fn count_changed(&mut self, index: usize, old: Tally) {
+		if let Node::Group(group) = &self.children[index] {
+			self.tally.replace_group(old, group.tally);
+		}
+	}
+
+	/// Marks the group's tally as stale, as its children are being changed without being counted.
+	#[inline(always)]
+	pub(super) const fn invalidate_tally(&mut self) {
+		self.tally.stale = true;
+	}
+
+	/// Sets the group's tally to that of an empty group, as every child has been removed.
+	#[inline(always)]
+	pub(super) const fn clear_tally(&mut self) {
+		self.tally = Tally::EMPTY;
+	}
+
+	/// Returns the group's tally, counting it from its children's tallies if it is stale.
+	fn counted(&self) -> Tally {
+		match self.tally.stale {
+			false => self.tally,
+			true => self.count_stale()[0],
+		}
+	}
+
+	/// Counts the tallies of the group and every stale group within it, in the order that a
+	/// depth-first traversal visits them.
+	fn count_stale(&self) -> Vec<Tally> {
+		let mut tallies = vec![Tally::EMPTY];
+
+		// Groups are counted with an explicit stack, rather than recursively, so that deeply nested
+		// layouts can't overflow the stack. Each group being counted is represented by its
+		// remaining children and the index of its tally.
+		let mut groups = vec![(self.children.iter(), 0)];
+
+		while let Some((children, index)) = groups.last_mut() {
+			match children.next() {
+				Some(Node::Group(group)) if group.tally.stale => {
+					tallies.push(Tally::EMPTY);
+					groups.push((group.children.iter(), tallies.len() - 1));
+				},
+				Some(node) => tallies[*index].add(node),
+
+				None => {
+					let (_, index) = groups.pop().expect("we just found the last group");
+
+					if let Some((_, parent)) = groups.last() {
+						let tally = tallies[index];
+						tallies[*parent].add_group(tally);
+					}
+				},
+			}
+		}
+
+		tallies
+	}
+
+	/// Counts the tallies of the group and every stale group within it again, so that they are
+	/// no longer stale.
+	pub(super) fn recount(&mut self) {
+		if !self.tally.stale {
+			return;
+		}
+
+		let mut tallies = self.count_stale().into_iter();
+		// The groups are visited in the same order as they were counted in: the next group in that
+		// order is always last.
+		let mut groups = vec![self];
+
+		while let Some(group) = groups.pop() {
+			group.tally = tallies.next().expect("every stale group has been counted");
+
+			groups.extend(group.children.iter_mut().rev().filter_map(|node| match node {
+				Node::Group(child) if child.tally.stale => Some(child),
+				_ => None,
+			}));
+		}
+	}
+
+	/// Counts the windows and groups within the group by walking every descendent, ignoring the
+	/// tallies.
+	fn walk_tally(&self) -> Tally {
+		let mut tally = Tally::EMPTY;
+		let mut groups = vec![self];
+
+		while let Some(group) = groups.pop() {
+			for node in &group.children {
+				match node {
+					Node::Window(_) => tally.windows += 1,
+					Node::Group(child) => {
+						tally.groups += 1;
+
+						groups.push(child);
+					},
+				}
+			}
+		}
+		tally.height = self.nested_levels();
+
+		tally
+	}
+
+	/// Checks that the group's tally matches the windows and groups actually within it.
+	pub(super) fn validate_tally(&self) -> Result<(), TreeInvariantError> {
+		let (counted, actual) = (self.counted(), self.walk_tally());
+
+		let counts = [
+			("windows", counted.windows, actual.windows),
+			("groups", counted.groups, actual.groups),
+			("levels of groups", counted.height, actual.height),
+		];
+
+		for (what, counted, actual) in counts {
+			if counted != actual {
+				return Err(TreeInvariantError::Miscounted { what, counted, actual });
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Returns the percentage of the group's size along its [axis] which its children and the gaps
+	/// between them filled when changes were last applied to it, or [`None`] if it is empty.
+	///
+	/// [axis]: Orientation::axis
+	fn coverage(&self) -> Option<f64> {
+		let (len, primary) = (self.children.len() as u32, self.primary_dimension());
+		if len == 0 || primary == 0 {
+			return None;
+		}
+
+		let covered = match self.stacked {
+			// Every child of a stacked group fills the whole group.
+			true => self.total_node_primary / len,
+			false => self
+				.total_node_primary
+				.saturating_add((len - 1).saturating_mul(self.applied_gap)),
+		};
+
+		Some(f64::from(covered) / f64::from(primary) * 100.0)
+	}
+}
+
+impl<Window> TilingLayout<Window> {
+	/// Returns [statistics] about the layout.
+	///
+	/// The numbers of windows and groups, and how deeply groups are nested, are counted as the
+	/// layout is changed, so they are cheap to query. Groups with pending changes are found by
+	/// walking the layout's groups.
+	///
+	/// [statistics]: LayoutStats
+	pub fn stats(&self) -> LayoutStats {
+		let Tally {
+			windows,
+			groups,
+			height,
+			..
+		} = self.root.counted();
+
+		let root_windows = self
+			.root
+			.iter()
+			.map(|node| match node {
+				Node::Window(_) => 1,
+				Node::Group(group) => group.counted().windows,
+			})
+			.collect();
+
+		let mut pending_changes = 0;
+		let mut pending = vec![&self.root];
+
+		while let Some(group) = pending.pop() {
+			if group.changes_made() {
+				pending_changes += 1;
+			}
+
+			pending.extend(group.children.iter().filter_map(|node| match node {
+				Node::Group(child) => Some(child),
+				Node::Window(_) => None,
+			}));
+		}
+
+		LayoutStats {
+			windows,
+			groups,
+			max_depth: height,
+			root_windows,
+
+			pending_changes,
+			coverage: self.coverage,
+		}
+	}
+
+	/// Records how much of the root group was covered by the changes which were just applied, and
+	/// counts any stale tallies again.
+	pub(super) fn update_stats(&mut self) {
+		self.coverage = self.root.coverage();
+		self.root.recount();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	/// Asserts that the layout's counted statistics match those found by walking it.
+	fn assert_counted(layout: &TilingLayout<u32>) {
+		let stats = layout.stats();
+		let actual = layout.root.walk_tally();
+
+		assert_eq!(stats.windows, actual.windows);
+		assert_eq!(stats.groups, actual.groups);
+		assert_eq!(stats.max_depth, actual.height);
+		assert_eq!(stats.windows, layout.windows().len());
+
+		let root_windows: Vec<_> = layout
+			.iter()
+			.map(|node| match node {
+				Node::Window(_) => 1,
+				Node::Group(group) => group.windows().len(),
+			})
+			.collect();
+		assert_eq!(stats.root_windows, root_windows);
+	}
+
+	/// Returns the path to a random group in the `layout`.
+	fn random_group(layout: &TilingLayout<u32>, random: &mut impl FnMut(usize) -> usize) -> Vec<usize> {
+		let mut path = Vec::new();
+		let mut group: &GroupNode<u32> = layout;
+
+		while random(5) < 3 {
+			let groups: Vec<_> = group
+				.iter()
+				.enumerate()
+				.filter_map(|(index, node)| match node {
+					Node::Group(group) => Some((index, group)),
+					Node::Window(_) => None,
+				})
+				.collect();
+			if groups.is_empty() {
+				break;
+			}
+
+			let (index, child) = groups[random(groups.len())];
+			path.push(index);
+			group = child;
+		}
+
+		path
+	}
+
+	/// Applies random changes to a layout, checking its counted statistics against those found by
+	/// walking it after each.
+	#[test]
+	fn randomized_counts() {
+		let settings = LayoutSettings::new().window_gap(0).max_depth(4);
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 4000, 4000, &settings);
+
+		// A xorshift generator, so that failures can be reproduced.
+		let mut seed = 0x5eed_7a11_4c0d_e001_u64;
+		let mut random = |bound: usize| {
+			seed ^= seed << 13;
+			seed ^= seed >> 7;
+			seed ^= seed << 17;
+
+			(seed % bound as u64) as usize
+		};
+		let mut next_window = 0;
+
+		for _ in 0..2_000 {
+			let path = random_group(&layout, &mut random);
+			let group = layout.group_at_mut(&path).expect("we just found the group");
+
+			match random(12) {
+				0..=2 => {
+					next_window += 1;
+					group.insert_window(random(group.len() + 1), next_window);
+				},
+				3 => {
+					let windows = [next_window + 1, next_window + 2];
+					next_window += 2;
+
+					let _ = group.push_group_back_with(Orientation::TopToBottom, |group| {
+						group.push_windows_back(windows);
+					});
+				},
+				4 | 5 if !group.is_empty() => {
+					group.remove(random(group.len()));
+				},
+				6 | 7 => {
+					// Transfer a node to another group.
+					let Some(id) = group.first().map(Node::id) else {
+						continue;
+					};
+
+					let other = random_group(&layout, &mut random);
+					let other = layout.group_at(&other).expect("we just found the group").id();
+
+					let _ = layout.transfer_by_id(id, other, random(3));
+				},
+				8 if random(10) == 0 => {
+					group.clear();
+				},
+				_ => apply(&mut layout, &settings),
+			}
+
+			assert_counted(&layout);
+		}
+
+		apply(&mut layout, &settings);
+		assert_counted(&layout);
+		assert_eq!(layout.validate(), Ok(()));
+	}
+
+	#[test]
+	fn stats() {
+		let settings = LayoutSettings::new().window_gap(10);
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1220, 620, &settings);
+
+		assert_eq!(layout.stats().coverage, None);
+
+		layout.push_window_back(1);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| {
+				group.push_window_back(2);
+				group
+					.push_group_back_with(Orientation::LeftToRight, |group| group.push_windows_back([3, 4]))
+					.unwrap();
+			})
+			.unwrap();
+
+		let stats = layout.stats();
+		assert_eq!(stats.windows, 4);
+		assert_eq!(stats.groups, 2);
+		assert_eq!(stats.max_depth, 2);
+		assert_eq!(stats.root_windows, [1, 3]);
+		assert_eq!(stats.pending_changes, 3);
+
+		apply(&mut layout, &settings);
+
+		let stats = layout.stats();
+		assert_eq!(stats.pending_changes, 0);
+		assert_eq!(stats.coverage, Some(100.0));
+
+		// Nodes removed through mutable references to nested groups are still counted.
+		layout[1].unwrap_group_mut().remove(1);
+
+		let stats = layout.stats();
+		assert_eq!((stats.windows, stats.groups, stats.max_depth), (2, 1, 1));
+
+		assert_eq!(layout.reset(Orientation::LeftToRight), [1, 2]);
+		assert_eq!(layout.stats().windows, 0);
+	}
+}
@@ -0,0 +1,317 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::iter::FusedIterator;
+
+use super::{iter::Iter, *};
+
+/// The number of groups the stack of a [`SnapshotIter`] is allocated for if the height of the
+/// group it walks isn't known without counting it.
+const DEFAULT_HEIGHT: usize = 8;
+
+/// An iterator over [snapshots] of every node in a [group], including the group itself, in
+/// pre-order.
+///
+/// This is returned by [`GroupNode::snapshot_iter()`].
+///
+/// [snapshots]: NodeSnapshot
+/// [group]: GroupNode
+pub struct SnapshotIter<'layout, Window> {
+	/// The group being walked, until its own snapshot has been yielded.
+	root: Option<&'layout GroupNode<Window>>,
+
+	/// The children left to walk in each group containing the next node, outermost first, along
+	/// with the index of the next child and whether the group has pending changes.
+	groups: Vec<(Iter<'layout, Window>, usize, bool)>,
+	/// The path of the node last yielded.
+	path: Vec<usize>,
+
+	/// The path of the focused window, if there is one.
+	focus: Option<Vec<usize>>,
+}
+
+impl<Window> GroupNode<Window> {
+	/// Returns an iterator over [snapshots] of every node in the group, starting with the group
+	/// itself, in pre-order.
+	///
+	/// Each snapshot gives the node's depth, index, kind, flags, and its coordinates and
+	/// dimensions, as they were the last time changes were [applied]: exactly those given to
+	/// `resize_window`, since they are the same values. Windows are borrowed, and everything else
+	/// is copied, so this is cheap enough to run on every frame of a renderer.
+	///
+	/// # Allocation
+	/// Nothing is allocated per node: the only allocations are the iterator's stack and path,
+	/// which are allocated once, for the height of the group, when the iterator is created. The
+	/// path of the node last yielded can be borrowed with [`path`].
+	///
+	/// [snapshots]: NodeSnapshot
+	/// [applied]: TilingLayout::apply_changes
+	/// [`path`]: SnapshotIter::path
+	pub fn snapshot_iter(&self) -> SnapshotIter<'_, Window> {
+		let height = match self.tally.stale {
+			false => self.tally.height,
+			true => DEFAULT_HEIGHT,
+		};
+
+		SnapshotIter {
+			root: Some(self),
+
+			groups: Vec::with_capacity(height + 1),
+			path: Vec::with_capacity(height + 1),
+
+			focus: None,
+		}
+	}
+}
+
+impl<'layout, Window> SnapshotIter<'layout, Window> {
+	/// Marks the given `window`, and the groups containing it, as [focused] in the snapshots.
+	///
+	/// Windows which aren't in the group are ignored.
+	///
+	/// [focused]: NodeFlags::focused
+	pub fn focused(mut self, window: &Window) -> Self
+	where
+		Window: PartialEq,
+	{
+		if let Some(root) = self.root {
+			self.focus = root.id_of_window(window).and_then(|id| root.path_of(id));
+		}
+
+		self
+	}
+
+	/// Returns the path of indexes to the node in the snapshot last yielded, as returned by
+	/// [`path_of`]; empty for the group being walked and before anything has been yielded.
+	///
+	/// [`path_of`]: GroupNode::path_of
+	pub fn path(&self) -> &[usize] {
+		&self.path
+	}
+
+	/// Returns whether the node at the current [path] is focused.
+	///
+	/// [path]: Self::path
+	fn is_focused(&self) -> bool {
+		self.focus.as_ref().is_some_and(|focus| focus.starts_with(&self.path))
+	}
+}
+
+impl<'layout, Window> Iterator for SnapshotIter<'layout, Window> {
+	type Item = NodeSnapshot<'layout, Window>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(root) = self.root.take() {
+			let pending = root.changes_made();
+			self.groups.push((root.iter(), 0, pending));
+
+			return Some(NodeSnapshot {
+				id: root.id(),
+				depth: 0,
+				index: 0,
+				kind: NodeKind::Group {
+					orientation: root.orientation,
+					stacked: root.stacked,
+					len: root.children.len(),
+				},
+				rect: (root.x, root.y, root.width, root.height),
+				flags: NodeFlags {
+					focused: self.is_focused(),
+					pending,
+				},
+			});
+		}
+
+		let (node, index, inherited) = loop {
+			let (children, next_index, pending) = self.groups.last_mut()?;
+
+			match children.next() {
+				Some(node) => {
+					let index = *next_index;
+					*next_index += 1;
+
+					break (node, index, *pending);
+				},
+
+				None => {
+					self.groups.pop();
+				},
+			}
+		};
+
+		let depth = self.groups.len();
+		self.path.truncate(depth - 1);
+		self.path.push(index);
+
+		let (kind, pending) = match node {
+			Node::Window(window) => {
+				let kind = NodeKind::Window {
+					window: &window.window,
+					visibility: window.visibility,
+				};
+
+				(kind, inherited || window.window_changed)
+			},
+
+			Node::Group(group) => {
+				let pending = inherited || group.changes_made();
+				self.groups.push((group.iter(), 0, pending));
+
+				let kind = NodeKind::Group {
+					orientation: group.orientation,
+					stacked: group.stacked,
+					len: group.children.len(),
+				};
+
+				(kind, pending)
+			},
+		};
+
+		Some(NodeSnapshot {
+			id: node.id(),
+			depth,
+			index,
+			kind,
+			rect: (node.x(), node.y(), node.width(), node.height()),
+			flags: NodeFlags {
+				focused: self.is_focused(),
+				pending,
+			},
+		})
+	}
+}
+
+impl<'layout, Window> FusedIterator for SnapshotIter<'layout, Window> {}
+
+impl<'layout, Window> Clone for NodeKind<'layout, Window> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<'layout, Window> Copy for NodeKind<'layout, Window> {}
+
+impl<'layout, Window> Clone for NodeSnapshot<'layout, Window> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<'layout, Window> Copy for NodeSnapshot<'layout, Window> {}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use super::*;
+
+	type Rect = (i32, i32, u32, u32);
+
+	/// Creates a layout from the given `description`, with windows named by single characters,
+	/// and applies its changes, returning it with the rectangles given to `resize_window`.
+	fn applied(description: &str) -> (TilingLayout<char>, HashMap<char, Rect>) {
+		let settings = LayoutSettings::new().window_gap(7);
+
+		let mut layout = TilingLayout::from_description(description, |name| {
+			let mut chars = name.chars();
+
+			chars.next().filter(|_| chars.next().is_none())
+		})
+		.unwrap();
+		layout.resize(13, 29, 1001, 599, &settings);
+
+		let mut rects = HashMap::new();
+		layout
+			.apply_changes(
+				&mut |window: &char, x, y, width, height, _| -> Result<(), ()> {
+					rects.insert(*window, (x, y, width, height));
+
+					Ok(())
+				},
+				&settings,
+			)
+			.unwrap();
+
+		(layout, rects)
+	}
+
+	#[test]
+	fn rects_match_apply() {
+		for description in [
+			"h(a b c)",
+			"h(60% v(a b) 40% c)",
+			"hr(a v(b hr(c d e) f) vr(g h))",
+			"vr(1/3 a 2/3 h(b vr(c d)) e)",
+		] {
+			let (layout, rects) = applied(description);
+
+			let mut windows = 0;
+			for node in layout.snapshot_iter() {
+				if let NodeKind::Window { window, .. } = node.kind {
+					assert_eq!(Some(&node.rect), rects.get(window), "{window} in {description}");
+					windows += 1;
+				}
+			}
+			assert_eq!(windows, rects.len(), "{description}");
+		}
+	}
+
+	#[test]
+	fn paths() {
+		let (mut layout, _) = applied("h(a v(b hr(c d) e) f)");
+
+		let (mut names, mut focused) = (String::new(), String::new());
+
+		let mut snapshots = layout.snapshot_iter().focused(&'d');
+		while let Some(node) = snapshots.next() {
+			let path = snapshots.path();
+
+			assert_eq!(layout.path_of(node.id).as_deref(), Some(path));
+			assert_eq!(node.depth, path.len());
+			assert_eq!(node.index, path.last().copied().unwrap_or(0));
+			assert!(!node.flags.pending);
+
+			let name = match node.kind {
+				NodeKind::Window { window, .. } => *window,
+				NodeKind::Group { .. } => '+',
+			};
+			names.push(name);
+			if node.flags.focused {
+				focused.push(name);
+			}
+		}
+		drop(snapshots);
+
+		// Pre-order, with `d` and the groups containing it focused.
+		assert_eq!(names, "+a+b+cdef");
+		assert_eq!(focused, "+++d");
+
+		// Changes to a group are pending for everything within it.
+		layout
+			.group_at_mut(&[1])
+			.unwrap()
+			.set_orientation(Orientation::LeftToRight);
+		let pending: Vec<_> = layout.snapshot_iter().map(|node| node.flags.pending).collect();
+		assert_eq!(pending, [false, false, true, true, true, true, true, true, false]);
+	}
+
+	#[test]
+	fn stack_doesnt_grow() {
+		// 1000 nodes within the root group, nested 4 groups deep.
+		let mut description = String::from("h(");
+		for _ in 0..50 {
+			description.push_str("v(h(a b c) h(a b v(a b c h(a b c) h(a b c))))");
+		}
+		description.push(')');
+
+		let (layout, _) = applied(&description);
+		assert_eq!(layout.snapshot_iter().count(), 1001);
+
+		let mut snapshots = layout.snapshot_iter();
+		let capacities = (snapshots.groups.capacity(), snapshots.path.capacity());
+		for _ in snapshots.by_ref() {}
+
+		assert_eq!((snapshots.groups.capacity(), snapshots.path.capacity()), capacities);
+	}
+}
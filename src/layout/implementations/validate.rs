@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::collections::HashSet;
+
+use super::*;
+
+impl<Window> TilingLayout<Window> {
+	/// Checks the invariants of the layout's tree.
+	///
+	/// No group may be nested deeper than the [maximum depth], no node may appear more than once
+	/// (so no group can be its own ancestor), and the sizes of each group's children must add up
+	/// to the total the group has recorded for them, unless the group has changes pending. In debug
+	/// builds, the numbers of windows and groups counted for the layout's [statistics] must also
+	/// match those found by walking the tree.
+	///
+	/// This is checked automatically after changes are [applied] in debug builds.
+	///
+	/// [maximum depth]: LayoutSettings::max_depth
+	/// [statistics]: TilingLayout::stats
+	/// [applied]: TilingLayout::apply_changes
+	pub fn validate(&self) -> Result<(), TreeInvariantError> {
+		self.root.validate()
+	}
+}
+
+impl<Window> GroupNode<Window> {
+	/// Checks the invariants of the tree within this group, as with [`TilingLayout::validate`].
+	///
+	/// The group's depth is taken to be its recorded [depth].
+	///
+	/// [depth]: Self::depth
+	pub(super) fn validate(&self) -> Result<(), TreeInvariantError> {
+		let max_depth = self.nesting.max_depth;
+
+		let mut ids = HashSet::from([self.id.0]);
+		let mut groups = vec![(self, self.depth())];
+
+		while let Some((group, depth)) = groups.pop() {
+			if depth > max_depth {
+				return Err(TreeInvariantError::TooDeep {
+					group: group.id.0,
+					depth,
+					max_depth,
+				});
+			}
+
+			for node in &group.children {
+				if !ids.insert(node.id()) {
+					return Err(TreeInvariantError::Repeated(node.id()));
+				}
+
+				if let Node::Group(child) = node {
+					groups.push((child, depth + 1));
+				}
+			}
+
+			group.validate_dimensions()?;
+		}
+
+		#[cfg(debug_assertions)]
+		self.validate_tally()?;
+
+		Ok(())
+	}
+
+	/// Checks that the sizes of the group's children add up to the total it has recorded for
+	/// them, unless it has changes pending.
+	fn validate_dimensions(&self) -> Result<(), TreeInvariantError> {
+		if self.changes_made() {
+			return Ok(());
+		}
+
+		let axis = self.orientation.axis();
+		// Groups' pending dimensions are the size their parent has given them.
+		let actual = self
+			.children
+			.iter()
+			.map(|node| match node {
+				Node::Group(group) => match axis {
+					Axis::Horizontal => group.new_width.unwrap_or(group.width),
+					Axis::Vertical => group.new_height.unwrap_or(group.height),
+				},
+				Node::Window(_) => node.primary_dimension(axis),
+			})
+			.fold(0u32, u32::saturating_add);
+
+		if actual != self.total_node_primary {
+			return Err(TreeInvariantError::DimensionMismatch {
+				group: self.id.0,
+				expected: self.total_node_primary,
+				actual,
+			});
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::nested;
+
+	/// Returns the settings of the layouts which are validated, which can be nested two groups
+	/// deep.
+	fn settings() -> LayoutSettings {
+		LayoutSettings::new().window_gap(0).max_depth(2)
+	}
+
+	#[test]
+	fn valid() {
+		let layout = nested(&settings());
+
+		assert_eq!(layout.validate(), Ok(()));
+	}
+
+	/// Tests that a group nested deeper than the maximum depth, e.g. because it was deserialized,
+	/// is found.
+	#[test]
+	fn too_deep() {
+		let mut layout = nested(&settings());
+
+		let mut group = GroupNode::new(Orientation::LeftToRight);
+		group.push_window_back(4);
+		let mut outer = GroupNode::new(Orientation::TopToBottom);
+		outer.children.push_back(Node::Group(group));
+
+		let id = outer.children[0].id();
+		layout[1].unwrap_group_mut().children.push_back(Node::Group(outer));
+
+		// The new groups' changes are pending, but their depth is wrong regardless.
+		assert_eq!(
+			layout.validate(),
+			Err(TreeInvariantError::TooDeep {
+				group: id,
+				depth: 3,
+				max_depth: 2,
+			})
+		);
+	}
+
+	/// Tests that a node which appears twice, as if a group had been moved into itself, is found.
+	#[test]
+	fn repeated() {
+		let mut layout = nested(&settings());
+
+		let group = layout[1].clone();
+		let id = group.id();
+		layout[1].unwrap_group_mut().children.push_back(group);
+
+		assert_eq!(layout.validate(), Err(TreeInvariantError::Repeated(id)));
+	}
+
+	/// Tests that children whose sizes don't add up to the size their group has recorded are
+	/// found.
+	#[test]
+	fn dimension_mismatch() {
+		let mut layout = nested(&settings());
+
+		let id = layout[1].id();
+		let group = layout[1].unwrap_group_mut();
+		group.children[0].unwrap_window_mut().height -= 10;
+
+		assert_eq!(
+			layout.validate(),
+			Err(TreeInvariantError::DimensionMismatch {
+				group: id,
+				expected: 600,
+				actual: 590,
+			})
+		);
+	}
+}
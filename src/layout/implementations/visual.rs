@@ -0,0 +1,227 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::*;
+use crate::placement::Rect;
+
+impl<Window> TilingLayout<Window> {
+	/// Returns the layout's windows in the order they are read on screen: top-to-bottom, then
+	/// left-to-right.
+	///
+	/// This is the same as <code>[visual_order_by]\([ReadingOrder::Rows])</code>.
+	///
+	/// [visual_order_by]: Self::visual_order_by
+	#[inline]
+	pub fn visual_order(&self) -> Vec<&Window> {
+		self.visual_order_by(ReadingOrder::Rows)
+	}
+
+	/// Returns the layout's windows sorted by the position of their nodes, reading along the
+	/// given [reading order]'s primary axis first.
+	///
+	/// Unlike [`windows()`], the order doesn't depend on where windows were inserted into the
+	/// tree, nor on the [orientation] of their groups: reversing a group leaves its windows in the
+	/// same order on screen, so it leaves them in the same visual order too. Windows whose nodes
+	/// share the same position, such as those [stacked] on top of one another, are ordered by
+	/// their [node IDs], so that the order is stable.
+	///
+	/// [reading order]: ReadingOrder
+	/// [`windows()`]: GroupNode::windows
+	/// [orientation]: Orientation
+	/// [stacked]: GroupNode::is_stacked
+	/// [node IDs]: NodeId
+	pub fn visual_order_by(&self, order: ReadingOrder) -> Vec<&Window> {
+		let mut nodes = Vec::new();
+		// The groups being iterated over, innermost last.
+		let mut groups = vec![self.root.iter()];
+
+		while let Some(children) = groups.last_mut() {
+			match children.next() {
+				Some(node @ Node::Window(window)) => nodes.push((node.x(), node.y(), window.id(), window.window())),
+				Some(Node::Group(group)) => groups.push(group.iter()),
+
+				None => {
+					groups.pop();
+				},
+			}
+		}
+
+		match order {
+			ReadingOrder::Rows => nodes.sort_unstable_by_key(|&(x, y, id, _)| (y, x, id)),
+			ReadingOrder::Columns => nodes.sort_unstable_by_key(|&(x, y, id, _)| (x, y, id)),
+		}
+
+		nodes.into_iter().map(|(.., window)| window).collect()
+	}
+
+	/// Returns the visible window whose tile contains the given point, along with its tile, if any.
+	///
+	/// Points in the gaps between tiles aren't in any tile. Positions are those from when changes
+	/// were last applied.
+	pub fn window_at(&self, x: i32, y: i32) -> Option<(&Window, Rect)> {
+		let mut groups = vec![self.root.iter()];
+
+		while let Some(children) = groups.last_mut() {
+			match children.next() {
+				Some(node @ Node::Window(window)) if window.visibility() == Visibility::Visible => {
+					let tile @ (tile_x, tile_y, width, height) = node.slot();
+					let contains =
+						(tile_x..tile_x + width as i32).contains(&x) && (tile_y..tile_y + height as i32).contains(&y);
+
+					if contains {
+						return Some((window.window(), tile));
+					}
+				},
+				Some(Node::Window(_)) => (),
+				Some(Node::Group(group)) => groups.push(group.iter()),
+
+				None => {
+					groups.pop();
+				},
+			}
+		}
+
+		None
+	}
+}
+
+impl FromStr for ReadingOrder {
+	type Err = ParseReadingOrderError;
+
+	/// Parses `rows` or `columns`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim() {
+			"rows" => Ok(Self::Rows),
+			"columns" => Ok(Self::Columns),
+
+			other => Err(ParseReadingOrderError(other.to_owned())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::implementations::fixtures::apply;
+
+	fn order(layout: &TilingLayout<u32>, order: ReadingOrder) -> Vec<u32> {
+		layout.visual_order_by(order).into_iter().copied().collect()
+	}
+
+	/// Tests the visual order of a nested column, then of the same layout with both groups
+	/// reversed.
+	///
+	/// ```text
+	/// ┌───┬───┬───┐      ┌───┬───┬───┐
+	/// │   │ 2 │   │      │   │ 3 │   │
+	/// │ 1 ├───┤ 4 │  ->  │ 4 ├───┤ 1 │
+	/// │   │ 3 │   │      │   │ 2 │   │
+	/// └───┴───┴───┘      └───┴───┴───┘
+	/// ```
+	#[test]
+	fn reversed_and_nested() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 900, 600, &settings);
+
+		layout.push_window_back(1);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([2, 3]))
+			.unwrap();
+		layout.push_window_back(4);
+		apply(&mut layout, &settings);
+
+		assert_eq!(order(&layout, ReadingOrder::Rows), [1, 2, 4, 3]);
+		assert_eq!(order(&layout, ReadingOrder::Columns), [1, 2, 3, 4]);
+
+		layout.set_orientation(Orientation::RightToLeft);
+		layout[1].unwrap_group_mut().set_orientation(Orientation::BottomToTop);
+		apply(&mut layout, &settings);
+
+		assert_eq!(order(&layout, ReadingOrder::Rows), [4, 3, 1, 2]);
+		assert_eq!(order(&layout, ReadingOrder::Columns), [4, 3, 2, 1]);
+	}
+
+	/// Tests that windows sharing a position, like those in a stacked group, are ordered by their
+	/// node IDs.
+	#[test]
+	fn ties() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 800, 600, &settings);
+
+		layout.push_window_back(1);
+		layout
+			.push_group_back_with(Orientation::LeftToRight, |group| {
+				group.set_stacked(true);
+				group.push_windows_back([3, 2]);
+			})
+			.unwrap();
+		apply(&mut layout, &settings);
+
+		assert_eq!(order(&layout, ReadingOrder::Rows), [1, 3, 2]);
+	}
+
+	/// Applies random changes to a layout, checking that its visual order is always a permutation
+	/// of its windows and that it doesn't change when nothing in the layout does.
+	#[test]
+	fn randomized_permutation() {
+		let settings = LayoutSettings::new().window_gap(5).max_depth(4);
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 4000, 4000, &settings);
+
+		// A xorshift generator, so that failures can be reproduced.
+		let mut seed = 0x0b5e_4e5d_7a11_0de3_u64;
+		let mut random = |bound: usize| {
+			seed ^= seed << 13;
+			seed ^= seed >> 7;
+			seed ^= seed << 17;
+
+			(seed % bound as u64) as usize
+		};
+		let mut next_window = 0;
+
+		for _ in 0..1_000 {
+			let len = layout.len();
+
+			match random(6) {
+				0 | 1 => {
+					next_window += 1;
+					layout.insert_window(random(len + 1), next_window);
+				},
+				2 => {
+					let windows = [next_window + 1, next_window + 2];
+					next_window += 2;
+
+					let orientation = [Orientation::TopToBottom, Orientation::BottomToTop][random(2)];
+					let _ = layout.insert_group_with(random(len + 1), orientation, |group| {
+						group.push_windows_back(windows);
+					});
+				},
+				3 if len > 0 => {
+					layout.remove(random(len));
+				},
+				_ => {},
+			}
+			apply(&mut layout, &settings);
+
+			for reading in [ReadingOrder::Rows, ReadingOrder::Columns] {
+				let visual = order(&layout, reading);
+
+				let mut sorted = visual.clone();
+				sorted.sort_unstable();
+				let mut windows: Vec<_> = layout.windows().into_iter().copied().collect();
+				windows.sort_unstable();
+				assert_eq!(sorted, windows);
+
+				apply(&mut layout, &settings);
+				assert_eq!(order(&layout, reading), visual);
+			}
+		}
+	}
+
+	#[test]
+	fn parse() {
+		assert_eq!("rows".parse(), Ok(ReadingOrder::Rows));
+		assert_eq!(" columns ".parse(), Ok(ReadingOrder::Columns));
+		assert!("diagonal".parse::<ReadingOrder>().is_err());
+	}
+}
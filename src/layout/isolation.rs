@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{
+	any::Any,
+	mem,
+	panic::{self, AssertUnwindSafe},
+};
+
+use super::{
+	managers::{Append, BuiltIn},
+	*,
+};
+
+/// A record of a [tiling layout manager] panicking, after which AquariWM [fell back] to the
+/// [`Append`] layout manager.
+///
+/// [tiling layout manager]: TilingLayoutManager
+/// [fell back]: CurrentLayout::isolate
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Error)]
+#[error("the {manager} layout manager panicked in `{hook}`: {message}")]
+pub struct ManagerPanic {
+	/// The [name] of the layout manager which panicked.
+	///
+	/// [name]: TilingLayoutManager::name
+	pub manager: &'static str,
+	/// The name of the [`TilingLayoutManager`] method which panicked, e.g. `add_window`.
+	pub hook: &'static str,
+	/// The panic's message, if it had one.
+	pub message: String,
+
+	/// The [invariant] the panic broke in the layout's tree, if it broke one, in which case the
+	/// tree was rebuilt from its windows.
+	///
+	/// [invariant]: TilingLayout::validate
+	pub broken: Option<TreeInvariantError>,
+}
+
+impl<Window> CurrentLayout<Window>
+where
+	Window: Clone + PartialEq + Send + Sync + 'static,
+{
+	/// Replaces the tiling layout manager with a new `Manager`, returning the replaced manager.
+	///
+	/// The new manager is [initialized] with the windows of the current layout, in order, in the
+	/// current layout's tree, emptied (so the tree keeps its coordinates, dimensions and
+	/// orientation). Returns [`None`], leaving the layout as it is, if the layout is [floating].
+	///
+	/// [initialized]: TilingLayoutManager::init
+	/// [floating]: Self::Floating
+	pub fn swap_manager<Manager>(&mut self) -> Option<Box<dyn TilingLayoutManager<Window>>>
+	where
+		Manager: TilingLayoutManager<Window>,
+	{
+		self.swap_with(|layout, windows| Box::new(Manager::init(layout, windows)))
+	}
+
+	/// Replaces the tiling layout manager with the given [built-in] layout manager, as with
+	/// [`swap_manager`], returning the replaced manager.
+	///
+	/// [built-in]: BuiltIn
+	/// [`swap_manager`]: Self::swap_manager
+	pub fn swap_built_in(&mut self, manager: BuiltIn) -> Option<Box<dyn TilingLayoutManager<Window>>> {
+		self.swap_with(|layout, windows| manager.init(layout, windows))
+	}
+
+	/// Replaces the tiling layout manager with the one created by `init` from the current layout's
+	/// tree, emptied, and its windows, returning the replaced manager.
+	fn swap_with(
+		&mut self,
+		init: impl FnOnce(TilingLayout<Window>, Vec<Window>) -> Box<dyn TilingLayoutManager<Window>>,
+	) -> Option<Box<dyn TilingLayoutManager<Window>>> {
+		let Self::Tiled(manager) = self else {
+			return None;
+		};
+
+		let orientation = manager.layout().orientation();
+		let windows = manager.layout_mut().reset(orientation);
+		// The tree is now empty, so this is cheap.
+		let layout = manager.layout().clone();
+
+		Some(mem::replace(manager, init(layout, windows)))
+	}
+
+	/// Calls the given `hook` of the tiling layout manager, isolating AquariWM from it panicking.
+	///
+	/// Returns [`Ok(None)`] without calling the `hook` if the layout is [floating].
+	///
+	/// If the layout manager panics, it is discarded and the layout falls back to the [`Append`]
+	/// layout manager, with which AquariWM keeps working: a [`ManagerPanic`] is returned, and the
+	/// caller may call the `hook` again on the fallback manager. If the panic broke one of the
+	/// [invariants] of the layout's tree, a new tree is built (with the given `settings`) from the
+	/// windows in the broken one.
+	///
+	/// # Unwind safety
+	/// The layout manager is only touched again through its [`layout`] methods, which must not
+	/// panic, to take its layout's windows, so whatever state it was left in can't be observed.
+	/// The layout's tree, on the other hand, is [validated] before its windows are taken, but that
+	/// can't catch every way a panic could leave it half-changed: windows may be in the wrong place
+	/// (but are never lost), and the same window may appear twice (in which case only its first
+	/// appearance is kept). The `hook` is passed through [`AssertUnwindSafe`], so it too must not
+	/// rely on anything it changed before the panic.
+	///
+	/// [`Ok(None)`]: Ok
+	/// [floating]: Self::Floating
+	/// [`layout`]: TilingLayoutManager::layout
+	/// [invariants]: TilingLayout::validate
+	/// [validated]: TilingLayout::validate
+	pub fn isolate<T>(
+		&mut self,
+		hook: &'static str,
+		settings: &LayoutSettings,
+		call: impl FnOnce(&mut dyn TilingLayoutManager<Window>) -> T,
+	) -> Result<Option<T>, ManagerPanic> {
+		let Self::Tiled(manager) = self else {
+			return Ok(None);
+		};
+		let name = manager.name();
+
+		let payload = match panic::catch_unwind(AssertUnwindSafe(|| call(&mut **manager))) {
+			Ok(output) => return Ok(Some(output)),
+			Err(payload) => payload,
+		};
+
+		let broken = self.fall_back(settings);
+
+		Err(ManagerPanic {
+			manager: name,
+			hook,
+			message: message(&*payload),
+
+			broken,
+		})
+	}
+
+	/// Replaces the tiling layout manager, which panicked, with the [`Append`] layout manager,
+	/// returning the [invariant] its tree broke, if any.
+	///
+	/// [invariant]: TilingLayout::validate
+	fn fall_back(&mut self, settings: &LayoutSettings) -> Option<TreeInvariantError> {
+		let Self::Tiled(manager) = self else {
+			return None;
+		};
+
+		let broken = manager.layout().validate().err();
+
+		if broken.is_some() {
+			let old = manager.layout();
+			let layout = TilingLayout::new(old.orientation(), old.x, old.y, old.width, old.height, settings);
+			// `Append` skips windows which are already in the layout.
+			let windows: Vec<_> = old.windows().into_iter().cloned().collect();
+
+			let old = mem::replace(manager, Box::new(Append::init(layout, windows)));
+			// Whatever state the manager was left in, a panic while dropping it shouldn't take
+			// AquariWM with it.
+			let _ = panic::catch_unwind(AssertUnwindSafe(move || drop(old)));
+		} else if let Some(old) = self.swap_manager::<Append<Window>>() {
+			let _ = panic::catch_unwind(AssertUnwindSafe(move || drop(old)));
+		}
+
+		broken
+	}
+}
+
+/// Returns the message of the given panic `payload`.
+fn message(payload: &(dyn Any + Send)) -> String {
+	if let Some(message) = payload.downcast_ref::<&str>() {
+		(*message).to_owned()
+	} else if let Some(message) = payload.downcast_ref::<String>() {
+		message.clone()
+	} else {
+		"(no message)".to_owned()
+	}
+}
@@ -2,17 +2,117 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::{
+	fmt,
+	fmt::{Display, Formatter},
+	str::FromStr,
+};
+
 use super::*;
 
+/// A layout manager that comes with AquariWM, which can be chosen by its [name] at runtime, e.g.
+/// in the configuration file or with [`SetWorkspaceLayout`].
+///
+/// [name]: Self::name
+/// [`SetWorkspaceLayout`]: crate::action::Action::SetWorkspaceLayout
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum BuiltIn {
+	/// The [`Stack`] layout manager.
+	#[default]
+	Stack,
+	/// The [`Spiral`] layout manager.
+	Spiral,
+	/// The [`Append`] layout manager.
+	Append,
+}
+
+/// An error returned when parsing a [`BuiltIn`] layout manager fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error("unknown layout manager {0:?}; expected `Stack`, `Spiral`, or `Append`")]
+pub struct UnknownManagerError(String);
+
+impl BuiltIn {
+	/// Every built-in layout manager.
+	pub const ALL: [Self; 3] = [Self::Stack, Self::Spiral, Self::Append];
+
+	/// Returns the layout manager's [name], which is also the name it is chosen by.
+	///
+	/// [name]: TilingLayoutManager::name
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Stack => "Stack",
+			Self::Spiral => "Spiral",
+			Self::Append => "Append",
+		}
+	}
+
+	/// Creates the layout manager with the given `windows` in the given `layout`, as with
+	/// [`TilingLayoutManager::init`].
+	pub fn init<Window, Windows>(
+		self,
+		layout: TilingLayout<Window>,
+		windows: Windows,
+	) -> Box<dyn TilingLayoutManager<Window>>
+	where
+		Window: Send + Sync + PartialEq + 'static,
+		Windows: IntoIterator<Item = Window>,
+		Windows::IntoIter: ExactSizeIterator,
+	{
+		match self {
+			Self::Stack => Box::new(Stack::init(layout, windows)),
+			Self::Spiral => Box::new(Spiral::init(layout, windows)),
+			Self::Append => Box::new(Append::init(layout, windows)),
+		}
+	}
+}
+
+impl Display for BuiltIn {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.name())
+	}
+}
+
+impl FromStr for BuiltIn {
+	type Err = UnknownManagerError;
+
+	/// Parses a layout manager from its [name], ignoring case.
+	///
+	/// [name]: Self::name
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let name = s.trim();
+
+		Self::ALL
+			.into_iter()
+			.find(|manager| manager.name().eq_ignore_ascii_case(name))
+			.ok_or_else(|| UnknownManagerError(name.to_owned()))
+	}
+}
+
 pub struct Stack<Window: Send + Sync + PartialEq + 'static> {
 	layout: TilingLayout<Window>,
 }
 
 #[allow(unused)]
 impl<Window: Send + Sync + PartialEq + 'static> Stack<Window> {
+	/// Returns the index of the main window in the layout, if there is one.
+	///
+	/// The main window and the stack are found by their kind of node, rather than their index, as
+	/// the indexes of the root group's children are reversed if it is [rotated] to a reversed
+	/// orientation.
+	///
+	/// [rotated]: GroupNode::rotate_by
+	fn main_index(&self) -> Option<usize> {
+		self.layout.iter().position(|node| matches!(node, Node::Window(_)))
+	}
+
+	/// Returns the index of the stack in the layout, if there is one.
+	fn stack_index(&self) -> Option<usize> {
+		self.layout.iter().position(|node| matches!(node, Node::Group(_)))
+	}
+
 	/// Returns a shared reference to the main window, if there is one.
 	fn main(&self) -> Option<&WindowNode<Window>> {
-		self.layout.first().and_then(|node| match node {
+		self.layout.get(self.main_index()?).and_then(|node| match node {
 			Node::Group(_) => None,
 			Node::Window(node) => Some(node),
 		})
@@ -20,7 +120,7 @@ impl<Window: Send + Sync + PartialEq + 'static> Stack<Window> {
 
 	/// Returns a shared reference to the stack, if there is one.
 	fn stack(&self) -> Option<&GroupNode<Window>> {
-		self.layout.get(1).and_then(|node| match node {
+		self.layout.get(self.stack_index()?).and_then(|node| match node {
 			Node::Group(node) => Some(node),
 			Node::Window(_) => None,
 		})
@@ -28,7 +128,9 @@ impl<Window: Send + Sync + PartialEq + 'static> Stack<Window> {
 
 	/// Returns a mutable reference to the main window, if there is one.
 	fn main_mut(&mut self) -> Option<&mut WindowNode<Window>> {
-		self.layout.first_mut().and_then(|node| match node {
+		let index = self.main_index()?;
+
+		self.layout.get_mut(index).and_then(|node| match node {
 			Node::Group(_) => None,
 			Node::Window(node) => Some(node),
 		})
@@ -36,11 +138,26 @@ impl<Window: Send + Sync + PartialEq + 'static> Stack<Window> {
 
 	/// Returns a mutable reference to the stack, if there is one.
 	fn stack_mut(&mut self) -> Option<&mut GroupNode<Window>> {
-		self.layout.get_mut(1).and_then(|node| match node {
+		let index = self.stack_index()?;
+
+		self.layout.get_mut(index).and_then(|node| match node {
 			Node::Group(node) => Some(node),
 			Node::Window(_) => None,
 		})
 	}
+
+	/// Returns the [ID] of the node containing the given `window`, if it is in the layout.
+	///
+	/// [ID]: NodeId
+	fn id_of(&self, window: &Window) -> Option<NodeId> {
+		let stack = self.stack().into_iter().flat_map(|stack| stack.iter());
+
+		// The root group's only window is the main window.
+		self.layout.iter().chain(stack).find_map(|node| match node {
+			Node::Window(node) if node.window() == window => Some(node.id()),
+			_ => None,
+		})
+	}
 }
 
 unsafe impl<Window> TilingLayoutManager<Window> for Stack<Window>
@@ -73,7 +190,57 @@ where
 			if windows.len() > 0 {
 				stack
 					.layout
-					.push_group_back_with(Orientation::TopToBottom, |stack| stack.push_windows_back(windows));
+					.push_group_back_with(Orientation::TopToBottom, |stack| stack.push_windows_back(windows))
+					.expect("groups can always be added to the root group");
+			}
+		}
+
+		stack
+	}
+
+	/// Creates the layout with the largest of the given `windows` as the main window and the rest
+	/// in the stack, keeping the windows' relative sizes.
+	fn init_with_geometries<WindowsIter>(layout: TilingLayout<Window>, windows: WindowsIter) -> Self
+	where
+		Self: Sized,
+		WindowsIter: IntoIterator<Item = (Window, (i32, i32, u32, u32))>,
+		WindowsIter::IntoIter: ExactSizeIterator,
+	{
+		let mut stack = Self { layout };
+
+		let mut windows: Vec<_> = windows.into_iter().collect();
+
+		// The first of the largest windows becomes the main window.
+		let main = windows
+			.iter()
+			.enumerate()
+			.rev()
+			.max_by_key(|(_, (_, (_, _, width, height)))| u64::from(*width) * u64::from(*height))
+			.map(|(index, _)| index);
+
+		if let Some(main) = main {
+			let (main, rect) = windows.remove(main);
+			stack.layout.adopt_window_back(main, rect);
+
+			// If there are more windows, then add them in a stack as wide as the widest of them.
+			if !windows.is_empty() {
+				let x = windows.iter().map(|(_, (x, ..))| *x).min().unwrap_or_default();
+				let y = windows.iter().map(|(_, (_, y, ..))| *y).min().unwrap_or_default();
+				let width = windows
+					.iter()
+					.map(|(_, (_, _, width, _))| *width)
+					.max()
+					.unwrap_or_default();
+				let height = windows.iter().map(|(_, (.., height))| *height).sum();
+
+				stack
+					.layout
+					.adopt_group_back_with(Orientation::TopToBottom, (x, y, width, height), |stack| {
+						for (window, rect) in windows {
+							stack.adopt_window_back(window, rect);
+						}
+					})
+					.expect("groups can always be added to the root group");
 			}
 		}
 
@@ -96,24 +263,43 @@ where
 
 			// Add the window as a main.
 			self.layout.push_window_back(window);
-		} else if let Some(stack) = self.stack_mut() {
+		} else if let Some(stack) = self.stack_mut().filter(|stack| !stack.is_manual()) {
 			// Main and stack.
 
 			// Add the window to the stack.
 			stack.push_window_back(window);
+		} else if self.stack().is_some() {
+			// Main and manual stack.
+
+			// Leave the stack as it is, and add the window alongside it.
+			self.layout.push_window_back(window);
 		} else {
 			// Main, no stack.
 
 			// Add the window to a new stack.
 			self.layout
-				.push_group_back_with(Orientation::TopToBottom, |stack| stack.push_window_back(window));
+				.push_group_back_with(Orientation::TopToBottom, |stack| stack.push_window_back(window))
+				.expect("groups can always be added to the root group");
 		}
 	}
 
 	fn remove_window(&mut self, window: &Window) {
+		// Windows in manual groups are removed without rearranging anything else.
+		if let Some(id) = self
+			.layout
+			.id_of_window(window)
+			.filter(|&id| self.layout.is_within_manual(id))
+		{
+			self.layout.remove_by_id(id);
+
+			return;
+		}
+
 		if let Some(main) = self.main() {
 			if main.window() == window {
-				if let Some(Node::Window(new_main)) = self.stack_mut().and_then(|stack| stack.remove(0)) {
+				let stack = self.stack_mut().filter(|stack| !stack.is_manual());
+
+				if let Some(Node::Window(new_main)) = stack.and_then(|stack| stack.remove(0)) {
 					// If there is a window to replace the main window with, do that.
 					self.main_mut()
 						.expect("We've already established `main` is present.")
@@ -121,7 +307,9 @@ where
 				} else {
 					// Otherwise, if there is no window to replace the main window with, remove the
 					// node.
-					self.layout.pop_front();
+					if let Some(index) = self.main_index() {
+						self.layout.remove(index);
+					}
 				}
 
 				return;
@@ -131,7 +319,9 @@ where
 		// Otherwise, if the main window does not match...
 
 		// If there is a stack...
-		if let Some(stack) = self.stack_mut() {
+		if let Some(stack_index) = self.stack_index() {
+			let stack = self.layout[stack_index].unwrap_group_mut();
+
 			let window_nodes = stack.iter_mut().enumerate().filter_map(|(i, node)| match node {
 				Node::Group(_) => None,
 				Node::Window(window_node) => Some((i, window_node)),
@@ -146,13 +336,47 @@ where
 						stack.remove(i);
 					} else {
 						// Otherwise, if it is the last window in the stack, remove the stack.
-						self.layout.pop_back();
+						self.layout.remove(stack_index);
 					}
 
 					return;
 				}
 			}
 		}
+
+		// Otherwise, the window may have been added alongside a manual stack.
+		let index = self.layout.iter().position(|node| match node {
+			Node::Window(node) => node.window() == window,
+			Node::Group(_) => false,
+		});
+		if let Some(index) = index {
+			self.layout.remove(index);
+		}
+	}
+
+	fn resize_primary(&mut self, delta: i32) {
+		// Without a stack, the main window fills the whole layout.
+		if let (Some(main), Some(_)) = (self.main_index(), self.stack()) {
+			self.layout.resize_child(main, delta);
+		}
+	}
+
+	fn swap_with_primary(&mut self, focused: &Window) -> bool {
+		let (Some(main), Some(focused)) = (self.main().map(WindowNode::id), self.id_of(focused)) else {
+			return false;
+		};
+
+		// If the main window is focused, swap it with the top of the stack instead.
+		let other = if focused == main {
+			self.stack().and_then(|stack| match stack.first()? {
+				Node::Window(node) => Some(node.id()),
+				Node::Group(_) => None,
+			})
+		} else {
+			Some(focused)
+		};
+
+		other.is_some_and(|other| self.layout.swap_windows_by_id(main, other))
 	}
 }
 
@@ -179,24 +403,13 @@ where
 		WindowsIter::IntoIter: ExactSizeIterator,
 	{
 		let mut spiral = Self { layout };
-
-		let mut target_group: Option<&mut GroupNode<_>> = Some(&mut spiral.layout);
+		// Each new window takes half of the window it splits.
+		spiral
+			.layout
+			.set_addition_sizing(Some(AdditionSizing::FractionOfSplitNode(0.5)));
 
 		for window in windows {
-			match target_group.take() {
-				Some(target) => {
-					target.push_group_back(target.orientation.rotated_by(1));
-
-					if let Node::Group(group) = &mut target[1] {
-						group.push_window_front(window);
-						target_group = Some(group);
-					}
-				},
-
-				// TODO: see if there is a way to avoid using `Option` here (its usage avoids
-				//     : multiple mutable borrows issues)
-				None => unreachable!(),
-			}
+			spiral.add_window(window);
 		}
 
 		spiral
@@ -213,83 +426,372 @@ where
 	}
 
 	fn add_window(&mut self, window: Window) {
-		let group = {
-			// TODO: avoid using `Option` if possible (only used to avoid issues with multiple
-			//     : mutable borrows, which using `take()` solves)
-			let mut target_group: Option<&mut GroupNode<_>> = Some(&mut self.layout);
+		let group = Self::innermost_group(&mut self.layout);
 
-			while let Some(Node::Group(group)) = target_group.take().unwrap().get_mut(1) {
-				target_group = Some(group);
-			}
-
-			target_group.unwrap()
-		};
-
-		group.push_group_back_with(group.orientation(), |group| {
-			group.push_window_front(window);
-		});
+		if group.is_empty() {
+			group.push_window_back(window);
+		} else if !group.can_nest_group() {
+			// Beyond the maximum depth, the rest of the windows share the innermost group.
+			group.insert_window_splitting(group.len() - 1, window);
+		} else {
+			group
+				.insert_group_splitting_with(group.len() - 1, group.orientation().rotated_by(1), |group| {
+					group.push_window_back(window);
+				})
+				.expect("we just checked that the group can be nested");
+		}
 	}
 
 	fn remove_window(&mut self, window: &Window) {
-		// If the first window matches, remove it and return.
-		if let Some(Node::Window(window_node)) = self.layout.get(0) {
-			if window_node.window() == window {
-				if self.layout.get(1).is_some() {
-					// If there are more groups, move the windows up to replace the first window.
-
-					Self::move_window_up(&mut self.layout);
-				} else {
-					// Otherwise, remove the first window node.
+		// Windows in manual groups are removed without rearranging anything else.
+		if let Some(id) = self
+			.layout
+			.id_of_window(window)
+			.filter(|&id| self.layout.is_within_manual(id))
+		{
+			self.layout.remove_by_id(id);
+
+			return;
+		}
 
-					self.layout.pop_front();
+		let mut group: &mut GroupNode<_> = &mut self.layout;
+
+		loop {
+			let Some(inner) = Self::inner_index(group) else {
+				// Beyond the maximum depth, the innermost group may contain more windows.
+				let index = group.iter().position(|node| match node {
+					Node::Window(node) => node.window() == window,
+					Node::Group(_) => false,
+				});
+				if let Some(index) = index {
+					// If the group is left empty, it is pruned.
+					group.remove(index);
 				}
 
 				return;
-			}
-		}
+			};
 
-		let mut target_group: Option<&mut GroupNode<_>> = Some(&mut self.layout);
-
-		while let Some(target) = target_group.take() {
-			if let Some(Node::Group(group)) = target.get_mut(1) {
-				if group[0].unwrap_window_ref().window() == window {
-					// Window matches.
-
-					match group.get_mut(1) {
-						// Has a child group: shift all the windows up to replace the removed one.
-						Some(Node::Group(group)) => {
-							Self::move_window_up(group);
-						},
-
-						// Doesn't have a child group: remove the node.
-						_ => {
-							// FIXME: Why doesn't this work?! It's fine if you remove the `else`
-							//      : branch below, but it is so obviously not accessible because
-							//      : this if branch ends in `return`. The compiler should
-							//      : absolutely be able to work that out.
-							// target.remove(1);
-						},
-					}
+			if matches!(Self::window_node(group), Some(node) if node.window() == window) {
+				// Move the windows in the inner groups up to replace the removed window.
+				Self::move_window_up(group);
 
-					return;
-				} else {
-					// Window doesn't match; move onto the next group.
-
-					target_group = Some(group);
-				}
+				return;
 			}
+			group = group[inner].unwrap_group_mut();
 		}
 	}
 }
 
 impl<Window: Send + Sync + PartialEq + 'static> Spiral<Window> {
+	/// Returns the index of the inner group of the given spiral `group`, if it contains another
+	/// group with a window in it.
+	///
+	/// Inner groups left empty by removing their window are not pruned until changes are applied.
+	/// [Manual] groups are treated as leaves of the spiral, rather than inner groups.
+	///
+	/// The inner group and the window are found by their kind of node, rather than their index, as
+	/// the indexes of a group's children are reversed if it is [rotated] to a reversed orientation.
+	///
+	/// [Manual]: GroupNode::is_manual
+	/// [rotated]: GroupNode::rotate_by
+	fn inner_index(group: &GroupNode<Window>) -> Option<usize> {
+		group
+			.iter()
+			.position(|node| matches!(node, Node::Group(inner) if !inner.is_empty() && !inner.is_manual()))
+	}
+
+	/// Returns the window node of the given spiral `group`, if it has one.
+	fn window_node(group: &GroupNode<Window>) -> Option<&WindowNode<Window>> {
+		group.iter().find_map(|node| match node {
+			Node::Window(node) => Some(node),
+			Node::Group(_) => None,
+		})
+	}
+
+	/// Returns the innermost group of the spiral, which may be empty.
+	///
+	/// [Manual] groups are not descended into.
+	///
+	/// [Manual]: GroupNode::is_manual
+	fn innermost_group(mut group: &mut GroupNode<Window>) -> &mut GroupNode<Window> {
+		while let Some(inner) = group
+			.iter()
+			.position(|node| matches!(node, Node::Group(inner) if !inner.is_manual()))
+		{
+			group = group[inner].unwrap_group_mut();
+		}
+
+		group
+	}
+
+	/// Replaces the window of the given spiral `group`, which must contain an inner group, with the
+	/// window of that inner group, and so on, removing the innermost group. Returns the window
+	/// that was replaced.
 	fn move_window_up(group: &mut GroupNode<Window>) -> Window {
-		let window = if let Some(Node::Group(group)) = group[1].unwrap_group_mut().get_mut(1) {
-			Self::move_window_up(group)
+		let index = Self::inner_index(group).expect("the group must contain an inner group");
+		let inner = group[index].unwrap_group_mut();
+
+		let window = if Self::inner_index(inner).is_some() {
+			Self::move_window_up(inner)
 		} else {
-			group.remove(1).unwrap().unwrap_window().into_window()
+			let window = inner.pop_front().unwrap().unwrap_window().into_window();
+			// Beyond the maximum depth, the innermost group may contain more windows.
+			if inner.is_empty() {
+				group.remove(index);
+			}
+
+			window
 		};
 
-		group[0].unwrap_window_mut().replace_window(window)
+		let index = group
+			.iter()
+			.position(|node| matches!(node, Node::Window(_)))
+			.expect("the group must contain a window");
+		group[index].unwrap_window_mut().replace_window(window)
+	}
+}
+
+/// A layout manager which appends every window to the root group.
+///
+/// Windows are removed from wherever they are in the tree, so `Append` can take over any layout:
+/// AquariWM [falls back] to it if another layout manager panics.
+///
+/// [falls back]: CurrentLayout::isolate
+pub struct Append<Window: Send + Sync + PartialEq + 'static> {
+	layout: TilingLayout<Window>,
+}
+
+unsafe impl<Window> TilingLayoutManager<Window> for Append<Window>
+where
+	Window: Send + Sync + PartialEq + 'static,
+{
+	#[inline(always)]
+	fn orientation() -> Orientation
+	where
+		Self: Sized,
+	{
+		Orientation::LeftToRight
+	}
+
+	fn init<WindowsIter>(layout: TilingLayout<Window>, windows: WindowsIter) -> Self
+	where
+		Self: Sized,
+		WindowsIter: IntoIterator<Item = Window>,
+		WindowsIter::IntoIter: ExactSizeIterator,
+	{
+		let mut append = Self { layout };
+
+		for window in windows {
+			append.add_window(window);
+		}
+
+		append
+	}
+
+	#[inline(always)]
+	fn layout(&self) -> &TilingLayout<Window> {
+		&self.layout
+	}
+
+	#[inline(always)]
+	fn layout_mut(&mut self) -> &mut TilingLayout<Window> {
+		&mut self.layout
+	}
+
+	/// Appends the `window` to the root group, unless it is already in the layout.
+	///
+	/// A window may already be in the layout if the layout manager this manager took over from
+	/// panicked after adding it.
+	fn add_window(&mut self, window: Window) {
+		if !self.layout.contains_window(&window) {
+			self.layout.push_window_back(window);
+		}
+	}
+
+	fn remove_window(&mut self, window: &Window) {
+		// Groups left empty are pruned when changes are applied.
+		while let Some(id) = self.layout.id_of_window(window) {
+			self.layout.remove_by_id(id);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::conformance::{self, ConformanceConfig};
+
+	/// Returns the main window and the windows in the stack.
+	fn windows(stack: &Stack<u32>) -> (Option<u32>, Vec<u32>) {
+		let main = stack.main().map(|node| *node.window());
+		let stack = stack.stack().map_or_else(Vec::new, |stack| {
+			stack.iter().map(|node| *node.unwrap_window_ref().window()).collect()
+		});
+
+		(main, stack)
+	}
+
+	/// Returns the primary dimensions of the main window and the stack.
+	fn widths(stack: &Stack<u32>) -> (u32, u32) {
+		(stack.layout[0].width(), stack.layout[1].width())
+	}
+
+	/// Drives each of the [`TilingLayoutManager`] methods in turn, checking the layout after each.
+	#[test]
+	fn stack_manager() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let apply = |stack: &mut Stack<u32>| {
+			stack
+				.layout_mut()
+				.apply_changes(&mut |_, _, _, _, _, _| Ok::<_, ()>(()), &settings)
+				.unwrap();
+		};
+
+		let layout = TilingLayout::new(Stack::<u32>::orientation(), 0, 0, 1200, 600, &settings);
+		let mut stack = Stack::init(layout, [1, 2, 3]);
+		apply(&mut stack);
+		assert_eq!(windows(&stack), (Some(1), vec![2, 3]));
+		assert_eq!(widths(&stack), (600, 600));
+
+		stack.add_window(4);
+		apply(&mut stack);
+		assert_eq!(windows(&stack), (Some(1), vec![2, 3, 4]));
+
+		// Swapping the focused window moves it into the main slot.
+		assert!(stack.swap_with_primary(&3));
+		apply(&mut stack);
+		assert_eq!(windows(&stack), (Some(3), vec![2, 1, 4]));
+		assert_eq!(widths(&stack), (600, 600));
+
+		// Swapping the focused main window swaps it with the top of the stack.
+		assert!(stack.swap_with_primary(&3));
+		apply(&mut stack);
+		assert_eq!(windows(&stack), (Some(2), vec![3, 1, 4]));
+
+		stack.resize_primary(100);
+		apply(&mut stack);
+		assert_eq!(widths(&stack), (700, 500));
+		assert_eq!(stack.layout[1].x(), 700);
+		assert!(stack.stack().unwrap().iter().all(|node| node.width() == 500));
+
+		// The primary area can't be resized beyond the layout.
+		stack.resize_primary(-1000);
+		apply(&mut stack);
+		assert_eq!(widths(&stack), (1, 1199));
+
+		// The focused window is removed.
+		stack.remove_window(&3);
+		apply(&mut stack);
+		assert_eq!(windows(&stack), (Some(2), vec![1, 4]));
+
+		// Without the focused window in the layout, nothing is swapped.
+		assert!(!stack.swap_with_primary(&3));
+		apply(&mut stack);
+		assert_eq!(windows(&stack), (Some(2), vec![1, 4]));
+
+		assert!(stack.swap_with_primary(&4));
+		stack.remove_window(&1);
+		apply(&mut stack);
+		assert_eq!(windows(&stack), (Some(4), vec![2]));
+	}
+
+	#[test]
+	fn spiral_manager() {
+		let settings = LayoutSettings::new().window_gap(0);
+		let apply = |spiral: &mut Spiral<u32>| {
+			spiral
+				.layout_mut()
+				.apply_changes(&mut |_, _, _, _, _, _| Ok::<_, ()>(()), &settings)
+				.unwrap();
+		};
+		let windows = |spiral: &Spiral<u32>| -> Vec<u32> { spiral.layout.windows().into_iter().copied().collect() };
+
+		let layout = TilingLayout::new(Spiral::<u32>::orientation(), 0, 0, 1200, 600, &settings);
+		let mut spiral = Spiral::init(layout, [1, 2]);
+		spiral.add_window(3);
+		spiral.add_window(4);
+		apply(&mut spiral);
+		assert_eq!(windows(&spiral), [1, 2, 3, 4]);
+
+		// Each window takes half of the space left by the windows before it.
+		assert_eq!(spiral.layout[0].width(), 600);
+		let inner = spiral.layout[1].unwrap_group_ref();
+		assert_eq!((inner[0].width(), inner[0].height()), (600, 300));
+
+		// The windows after a removed window move up to replace it.
+		spiral.remove_window(&2);
+		apply(&mut spiral);
+		assert_eq!(windows(&spiral), [1, 3, 4]);
+		assert_eq!(spiral.layout[1].unwrap_group_ref()[0].height(), 300);
+
+		spiral.remove_window(&4);
+		spiral.remove_window(&1);
+		apply(&mut spiral);
+		assert_eq!(windows(&spiral), [3]);
+	}
+
+	/// Tests that new windows are added alongside a manual stack, and that removing windows from
+	/// it doesn't replace the main window.
+	#[test]
+	fn manual_stack() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let layout = TilingLayout::new(Stack::<u32>::orientation(), 0, 0, 1200, 600, &settings);
+		let mut stack = Stack::init(layout, [1, 2, 3]);
+		stack.stack_mut().unwrap().set_manual(true);
+
+		stack.add_window(4);
+		assert_eq!(windows(&stack), (Some(1), vec![2, 3]));
+		assert_eq!(stack.layout.len(), 3);
+
+		stack.remove_window(&2);
+		assert_eq!(windows(&stack), (Some(1), vec![3]));
+
+		stack.remove_window(&1);
+		stack.remove_window(&3);
+		assert_eq!(windows(&stack), (Some(4), vec![]));
+		assert!(stack.layout.windows().into_iter().eq([&4]));
+	}
+
+	/// Tests that adopting windows with their geometries picks the largest as the main window and
+	/// keeps their proportions.
+	#[test]
+	fn stack_adoption() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let layout = TilingLayout::new(Stack::<u32>::orientation(), 0, 0, 1800, 600, &settings);
+		let mut stack = Stack::init_with_geometries(
+			layout,
+			[
+				(2, (800, 0, 400, 200)),
+				(1, (0, 0, 800, 600)),
+				(3, (800, 200, 300, 400)),
+			],
+		);
+		stack
+			.layout_mut()
+			.apply_changes(&mut |_, _, _, _, _, _| Ok::<_, ()>(()), &settings)
+			.unwrap();
+
+		assert_eq!(windows(&stack), (Some(1), vec![2, 3]));
+		assert_eq!(widths(&stack), (1200, 600));
+
+		let heights: Vec<_> = stack.stack().unwrap().iter().map(Node::height).collect();
+		assert_eq!(heights, [200, 400]);
+	}
+
+	#[test]
+	fn stack_conformance() {
+		conformance::check_manager::<Stack<u32>>(ConformanceConfig::new());
+	}
+
+	#[test]
+	fn append_conformance() {
+		conformance::check_manager::<Append<u32>>(ConformanceConfig::new());
+	}
+
+	#[test]
+	fn spiral_conformance() {
+		conformance::check_manager::<Spiral<u32>>(ConformanceConfig::new());
 	}
 }
@@ -0,0 +1,76 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{convert::Infallible, iter};
+
+use super::{managers::BuiltIn, *};
+use crate::placement::Rect;
+
+/// A window in a copy of a layout in which an addition is [predicted]: either one of the layout's
+/// windows, or the placeholder for the window being added.
+///
+/// [predicted]: CurrentLayout::predict_addition
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Slot<Window> {
+	/// One of the layout's windows.
+	Window(Window),
+	/// The placeholder for the window being added, or for a window not yet mapped in a layout
+	/// created from a [description].
+	///
+	/// [description]: TilingLayout::from_description_with_placeholders
+	Placeholder,
+}
+
+impl<Window> CurrentLayout<Window>
+where
+	Window: Clone + PartialEq + Send + Sync + 'static,
+{
+	/// Predicts the tile a window would be given if it were added to the layout by `add`, without
+	/// changing the layout.
+	///
+	/// A copy of the layout, with a [placeholder] for the new window, is given to a new instance of
+	/// the layout manager, which is told that the `focused` window is focused. The placeholder is
+	/// added to it by `add`, and the copy's changes are applied with the given `settings`, exactly
+	/// as the layout's own would be once the window is added.
+	///
+	/// Returns [`None`] if the layout is [floating], its dimensions are degenerate, or its layout
+	/// manager isn't [built in], as only built-in layout managers are known to keep no state other
+	/// than their layout.
+	///
+	/// [placeholder]: Slot::Placeholder
+	/// [floating]: Self::Floating
+	/// [built in]: BuiltIn
+	pub fn predict_addition(
+		&self,
+		focused: Option<&Window>,
+		settings: &LayoutSettings,
+		add: impl FnOnce(&mut dyn TilingLayoutManager<Slot<Window>>),
+	) -> Option<Rect> {
+		let Self::Tiled(manager) = self else {
+			return None;
+		};
+
+		let built_in: BuiltIn = manager.name().parse().ok()?;
+		let layout = manager.layout().map_windows(|window| Slot::Window(window.clone()));
+
+		let mut copy = built_in.init(layout, iter::empty());
+		copy.focused_changed(focused.cloned().map(Slot::Window).as_ref());
+		add(&mut *copy);
+
+		let mut predicted = None;
+		let result = copy.layout_mut().apply_changes(
+			&mut |slot: &Slot<Window>, x, y, width, height, _| -> Result<(), Infallible> {
+				if *slot == Slot::Placeholder {
+					predicted = Some((x, y, width, height));
+				}
+
+				Ok(())
+			},
+			settings,
+		);
+		let Ok(_) = result;
+
+		predicted
+	}
+}
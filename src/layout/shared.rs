@@ -0,0 +1,570 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{
+	convert::Infallible,
+	sync::{Mutex, MutexGuard},
+};
+
+use super::*;
+
+/// An internally synchronized handle to a [tiling layout], which can be shared between threads
+/// and tasks.
+///
+/// Every method takes `&self`, so a `SharedLayout` can be placed in an [`Arc`] (or borrowed by
+/// scoped threads) without wrapping it in a [`Mutex`] at each call site.
+///
+/// # Applying changes
+/// Reconfiguring windows can be slow (it typically involves I/O with the display server), so
+/// [`apply`] does not hold the lock while doing so. Instead, applying changes happens in two
+/// stages:
+/// 1. **Compute**: with the lock held, the pending changes are applied to the layout in place,
+///    recording the new coordinates and dimensions of each window to reconfigure.
+/// 2. **Reconfigure**: with the lock released, the recorded windows are reconfigured.
+///
+/// The layout is mutated in place, so a mutation which lands while windows are being reconfigured
+/// is made to the layout as it has just been applied, and is left pending for the next call to
+/// [`apply`].
+///
+/// There is therefore no separate commit stage which re-acquires the lock afterwards, and no
+/// generation counter to check for mutations made in between: the computed layout is never written
+/// back over the live one, so such mutations can't be lost. The only thing done with the lock
+/// re-acquired is [refreshing] the windows which weren't reconfigured because of an error.
+///
+/// [refreshing]: GroupNode::refresh_window
+/// [tiling layout]: TilingLayout
+/// [`Arc`]: std::sync::Arc
+/// [`apply`]: SharedLayout::apply
+pub struct SharedLayout<Window> {
+	inner: Mutex<Shared<Window>>,
+}
+
+struct Shared<Window> {
+	layout: TilingLayout<Window>,
+}
+
+impl<Window> From<TilingLayout<Window>> for SharedLayout<Window> {
+	#[inline]
+	fn from(layout: TilingLayout<Window>) -> Self {
+		Self::new(layout)
+	}
+}
+
+impl<Window> SharedLayout<Window> {
+	/// Creates a new shared handle to the given `layout`.
+	#[inline]
+	pub const fn new(layout: TilingLayout<Window>) -> Self {
+		Self {
+			inner: Mutex::new(Shared { layout }),
+		}
+	}
+
+	/// Consumes the shared handle, returning the [layout] it wraps.
+	///
+	/// [layout]: TilingLayout
+	#[inline]
+	pub fn into_inner(self) -> TilingLayout<Window> {
+		self.inner
+			.into_inner()
+			.expect("the shared layout's lock was poisoned")
+			.layout
+	}
+
+	fn lock(&self) -> MutexGuard<'_, Shared<Window>> {
+		self.inner.lock().expect("the shared layout's lock was poisoned")
+	}
+
+	/// Calls `f` with a shared reference to the layout's root [group].
+	///
+	/// The lock is held for the duration of `f`.
+	///
+	/// [group]: GroupNode
+	#[inline]
+	pub fn read<R>(&self, f: impl FnOnce(&GroupNode<Window>) -> R) -> R {
+		f(&self.lock().layout)
+	}
+
+	/// Calls `f` with a mutable reference to the [layout].
+	///
+	/// The lock is held for the duration of `f`.
+	///
+	/// [layout]: TilingLayout
+	#[inline]
+	pub fn write<R>(&self, f: impl FnOnce(&mut TilingLayout<Window>) -> R) -> R {
+		f(&mut self.lock().layout)
+	}
+
+	/// Returns whether the latest attempt to [apply changes] to the layout was deferred.
+	///
+	/// See [`TilingLayout::is_deferred`] for more information.
+	///
+	/// [apply changes]: Self::apply
+	#[inline]
+	pub fn is_deferred(&self) -> bool {
+		self.lock().layout.is_deferred()
+	}
+
+	/// Pushes a new [window node] containing the given `window` to the end of the root group.
+	///
+	/// [window node]: WindowNode
+	#[inline]
+	pub fn push_window_back(&self, window: Window) {
+		self.write(|layout| layout.push_window_back(window));
+	}
+
+	/// Pushes a new [window node] containing the given `window` to the beginning of the root group.
+	///
+	/// [window node]: WindowNode
+	#[inline]
+	pub fn push_window_front(&self, window: Window) {
+		self.write(|layout| layout.push_window_front(window));
+	}
+
+	/// Inserts a new [window node] containing the given `window` at the given `index` in the root
+	/// group.
+	///
+	/// [window node]: WindowNode
+	#[inline]
+	pub fn insert_window(&self, index: usize, window: Window) {
+		self.write(|layout| layout.insert_window(index, window));
+	}
+
+	/// Removes the [node] at the given `index` from the root group.
+	///
+	/// [node]: Node
+	#[inline]
+	pub fn remove(&self, index: usize) -> Option<Node<Window>> {
+		self.write(|layout| layout.remove(index))
+	}
+
+	/// Sets the root group's [`orientation`].
+	///
+	/// [`orientation`]: GroupNode::orientation()
+	#[inline]
+	pub fn set_orientation(&self, orientation: Orientation) {
+		self.write(|layout| layout.set_orientation(orientation));
+	}
+
+	/// Rotates the root group's [`orientation`] by the given number of `rotations`.
+	///
+	/// [`orientation`]: GroupNode::orientation()
+	#[inline]
+	pub fn rotate_by(&self, rotations: i32) {
+		self.write(|layout| layout.rotate_by(rotations));
+	}
+
+	/// Sets the root group's [window gap] override.
+	///
+	/// [window gap]: GroupNode::set_gap
+	#[inline]
+	pub fn set_gap(&self, gap: Option<u32>) {
+		self.write(|layout| layout.set_gap(gap));
+	}
+
+	/// Adjusts the effective [window gap] of the group containing the given `window`.
+	///
+	/// See [`TilingLayout::adjust_gap`] for more information.
+	///
+	/// [window gap]: LayoutSettings::window_gap
+	#[inline]
+	pub fn adjust_gap(&self, window: &Window, delta: i32, settings: &LayoutSettings) -> bool
+	where
+		Window: PartialEq,
+	{
+		self.write(|layout| layout.adjust_gap(window, delta, settings))
+	}
+
+	/// Resizes the layout to the given coordinates and dimensions.
+	///
+	/// See [`TilingLayout::resize`] for more information.
+	#[inline]
+	pub fn resize(&self, x: i32, y: i32, width: u32, height: u32, settings: &LayoutSettings) {
+		self.write(|layout| layout.resize(x, y, width, height, settings));
+	}
+
+	/// Applies the pending changes to the layout, calling `reconfigure_window` for each window that
+	/// needs to be reconfigured.
+	///
+	/// `reconfigure_window` is called *without* the lock held, so it may be slow, and it may even
+	/// mutate the layout through this handle. See the [type-level documentation] for how changes
+	/// are applied.
+	///
+	/// Returns the windows which were [skipped], which should be removed from the layout, along
+	/// with the [changes] made to the visible windows which were reconfigured, including the
+	/// geometries of any windows the display server [adjusted]. If `reconfigure_window` returns an
+	/// error, the windows which were still to be reconfigured are [refreshed], so that the next
+	/// call reconfigures them.
+	///
+	/// [type-level documentation]: Self#applying-changes
+	/// [skipped]: Reconfigured::SkipWindow
+	/// [changes]: AppliedChange
+	/// [adjusted]: Reconfigured::Adjusted
+	/// [refreshed]: GroupNode::refresh_window
+	pub fn apply<Outcome, Error>(
+		&self,
+		mut reconfigure_window: impl FnMut(&Window, i32, i32, u32, u32, Visibility) -> Result<Outcome, Error>,
+		settings: &LayoutSettings,
+	) -> Result<(Vec<Window>, Vec<AppliedChange<Window>>), Error>
+	where
+		Window: Clone + PartialEq,
+		Outcome: Into<Reconfigured>,
+	{
+		// Apply the changes while the lock is held...
+		let assignments = {
+			let mut assignments = Vec::new();
+
+			let result = self.lock().layout.apply_changes(
+				&mut |window: &Window, x, y, width, height, visibility| -> Result<(), Infallible> {
+					assignments.push((window.clone(), x, y, width, height, visibility));
+
+					Ok(())
+				},
+				settings,
+			);
+			let Ok(_) = result;
+
+			assignments
+		};
+
+		// ...and reconfigure the windows while it is released.
+		let mut skipped = Vec::new();
+		let mut changes = Vec::new();
+
+		for (index, (window, x, y, width, height, visibility)) in assignments.iter().enumerate() {
+			match reconfigure_window(window, *x, *y, *width, *height, *visibility) {
+				Ok(outcome) => {
+					let outcome = outcome.into();
+					let requested = (*x, *y, *width, *height);
+
+					if *visibility == Visibility::Visible {
+						changes.push(AppliedChange {
+							window: window.clone(),
+							requested,
+							actual: outcome.actual(requested),
+						});
+					}
+					if outcome == Reconfigured::SkipWindow {
+						skipped.push(window.clone());
+					}
+				},
+
+				Err(error) => {
+					self.write(|layout| {
+						for (window, ..) in &assignments[index..] {
+							layout.refresh_window(window);
+						}
+					});
+
+					return Err(error);
+				},
+			}
+		}
+
+		Ok((skipped, changes))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		collections::{BTreeSet, HashMap},
+		sync::{
+			atomic::{AtomicBool, Ordering},
+			mpsc,
+		},
+		thread,
+		time::Duration,
+	};
+
+	use super::*;
+
+	type Applied = HashMap<u32, (i32, i32, u32, u32)>;
+
+	fn new_layout() -> SharedLayout<u32> {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		SharedLayout::new(TilingLayout::new(Orientation::LeftToRight, 0, 0, 1200, 600, &settings))
+	}
+
+	/// Applies changes to the `layout`, recording the latest geometry of each reconfigured window.
+	fn apply(layout: &SharedLayout<u32>, reconfigured: &mut Applied) {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let (skipped, _) = layout
+			.apply(
+				|window, x, y, width, height, _| -> Result<(), ()> {
+					reconfigured.insert(*window, (x, y, width, height));
+
+					Ok(())
+				},
+				&settings,
+			)
+			.unwrap();
+
+		assert!(skipped.is_empty());
+	}
+
+	/// Tests that a mutation made while windows are being reconfigured is left pending for the
+	/// next call.
+	#[test]
+	fn mutation_while_reconfiguring() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let layout = new_layout();
+		layout.push_window_back(1);
+
+		let mut reconfigured = Applied::new();
+		let mut mutated = false;
+
+		// The lock is not held while reconfiguring windows, so mutating the layout in the callback
+		// must not deadlock.
+		layout
+			.apply(
+				|window, x, y, width, height, _| -> Result<(), ()> {
+					if !mutated {
+						layout.push_window_back(2);
+						mutated = true;
+					}
+
+					reconfigured.insert(*window, (x, y, width, height));
+
+					Ok(())
+				},
+				&settings,
+			)
+			.unwrap();
+
+		assert_eq!(reconfigured[&1], (0, 0, 1200, 600));
+		assert_eq!(layout.read(|root| root.len()), 2);
+
+		// The second window's addition is still pending.
+		apply(&layout, &mut reconfigured);
+
+		assert_eq!(reconfigured[&1], (0, 0, 600, 600));
+		assert_eq!(reconfigured[&2], (600, 0, 600, 600));
+
+		// With nothing else pending, applying again reconfigures nothing.
+		let mut reconfigured = Applied::new();
+		apply(&layout, &mut reconfigured);
+
+		assert!(reconfigured.is_empty());
+	}
+
+	/// Tests that a mutation made by another thread while windows are being reconfigured is left
+	/// pending for the next call, rather than being lost when the reconfiguration finishes.
+	#[test]
+	fn mutation_from_another_thread() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let layout = new_layout();
+		layout.push_window_back(1);
+
+		let mut reconfigured = Applied::new();
+
+		thread::scope(|scope| {
+			let (sender, receiver) = mpsc::channel();
+			let mut mutator = Some((scope, sender));
+
+			layout
+				.apply(
+					|window, x, y, width, height, _| -> Result<(), ()> {
+						// Wait for another thread to add a window part way through reconfiguring,
+						// which it can only do if the lock isn't held.
+						if let Some((scope, sender)) = mutator.take() {
+							let layout = &layout;
+
+							scope.spawn(move || {
+								layout.push_window_back(2);
+								sender.send(()).unwrap();
+							});
+							receiver
+								.recv_timeout(Duration::from_secs(10))
+								.expect("the lock should not be held while reconfiguring windows");
+						}
+
+						reconfigured.insert(*window, (x, y, width, height));
+
+						Ok(())
+					},
+					&settings,
+				)
+				.unwrap();
+		});
+
+		assert_eq!(reconfigured, Applied::from([(1, (0, 0, 1200, 600))]));
+		assert_eq!(layout.read(|root| root.len()), 2);
+
+		// The second window's addition was not lost, and is still pending.
+		let mut reconfigured = Applied::new();
+		apply(&layout, &mut reconfigured);
+
+		assert_eq!(
+			reconfigured,
+			Applied::from([(1, (0, 0, 600, 600)), (2, (600, 0, 600, 600))])
+		);
+	}
+
+	/// Tests that windows which are [skipped] are returned along with the changes made to each
+	/// window, including those the display server [adjusted], and that windows which weren't
+	/// reconfigured because of an error are reconfigured by the next call.
+	///
+	/// [skipped]: Reconfigured::SkipWindow
+	/// [adjusted]: Reconfigured::Adjusted
+	#[test]
+	fn outcomes() {
+		let settings = LayoutSettings::new().window_gap(0);
+
+		let layout = new_layout();
+		layout.write(|layout| layout.push_windows_back([1, 2, 3]));
+
+		let (skipped, changes) = layout
+			.apply(
+				|&window, x, y, _, height, _| -> Result<_, ()> {
+					Ok(match window {
+						2 => Reconfigured::SkipWindow,
+						3 => Reconfigured::Adjusted {
+							x,
+							y,
+							width: 450,
+							height,
+						},
+						_ => Reconfigured::Applied,
+					})
+				},
+				&settings,
+			)
+			.unwrap();
+		assert_eq!(skipped, [2]);
+		assert_eq!(
+			changes,
+			[
+				AppliedChange {
+					window: 1,
+					requested: (0, 0, 400, 600),
+					actual: Some((0, 0, 400, 600)),
+				},
+				AppliedChange {
+					window: 2,
+					requested: (400, 0, 400, 600),
+					actual: None,
+				},
+				AppliedChange {
+					window: 3,
+					requested: (800, 0, 400, 600),
+					actual: Some((800, 0, 450, 600)),
+				},
+			]
+		);
+
+		layout.write(|layout| layout.resize(0, 0, 900, 600, &settings));
+		let result = layout.apply(
+			|&window, _, _, _, _, _| match window {
+				1 => Ok(()),
+				_ => Err(window),
+			},
+			&settings,
+		);
+		assert_eq!(result, Err(2));
+
+		// The windows after the error are reconfigured again, even though the layout is unchanged.
+		let mut reconfigured = Applied::new();
+		apply(&layout, &mut reconfigured);
+
+		assert_eq!(
+			reconfigured.keys().copied().collect::<BTreeSet<_>>(),
+			BTreeSet::from([2, 3])
+		);
+	}
+
+	/// Tests that concurrent mutations and applications leave the layout consistent.
+	#[test]
+	fn concurrent_stress() {
+		const THREADS: u32 = 4;
+		const WINDOWS_PER_THREAD: u32 = 50;
+
+		let layout = new_layout();
+		let done = AtomicBool::new(false);
+
+		let mut reconfigured = Applied::new();
+
+		thread::scope(|scope| {
+			let writers: Vec<_> = (0..THREADS)
+				.map(|thread| {
+					let layout = &layout;
+
+					scope.spawn(move || {
+						for i in 0..WINDOWS_PER_THREAD {
+							let window = thread * WINDOWS_PER_THREAD + i;
+
+							layout.push_window_back(window);
+							// Remove every other window again.
+							if i % 2 == 1 {
+								layout.write(|layout| {
+									let index = layout
+										.iter()
+										.position(|node| *node.unwrap_window_ref().window() == window)
+										.unwrap();
+
+									layout.remove(index);
+								});
+							}
+
+							thread::yield_now();
+						}
+					})
+				})
+				.collect();
+
+			let applier = scope.spawn(|| {
+				let settings = LayoutSettings::new().window_gap(0);
+
+				while !done.load(Ordering::Acquire) {
+					layout
+						.apply(
+							|_window, _x, _y, _width, _height, _visibility| -> Result<(), ()> {
+								thread::yield_now();
+
+								Ok(())
+							},
+							&settings,
+						)
+						.unwrap();
+				}
+			});
+
+			for writer in writers {
+				writer.join().unwrap();
+			}
+
+			done.store(true, Ordering::Release);
+			applier.join().unwrap();
+		});
+
+		apply(&layout, &mut reconfigured);
+
+		// Only the windows which weren't removed remain, and they tile the whole layout.
+		let windows = layout.read(|root| {
+			root.iter()
+				.map(|node| {
+					let node = node.unwrap_window_ref();
+
+					(*node.window(), (node.x, node.y, node.width, node.height))
+				})
+				.collect::<Vec<_>>()
+		});
+
+		assert_eq!(windows.len(), (THREADS * WINDOWS_PER_THREAD / 2) as usize);
+
+		let mut expected_x = 0;
+
+		for (window, (x, y, width, height)) in windows {
+			assert_eq!(window % 2, 0, "window {window} should have been removed");
+
+			assert_eq!((x, y, height), (expected_x, 0, 600));
+			expected_x += width as i32;
+		}
+
+		assert_eq!(expected_x, 1200);
+	}
+}
@@ -0,0 +1,95 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![warn(clippy::missing_const_for_fn)]
+// Feature flags
+#![feature(impl_trait_in_assoc_type)]
+#![feature(iterator_try_collect)]
+#![feature(doc_cfg)]
+
+use std::{env, ffi::OsString, io, process};
+
+use thiserror::Error;
+
+pub mod action;
+pub mod admission;
+pub mod appearance;
+pub mod autosave;
+pub mod coalesce;
+pub mod config;
+pub mod configure_guard;
+pub mod desktop;
+pub mod dispatch_queue;
+pub mod display_server;
+pub mod fullscreen;
+pub mod ignore;
+pub mod launch;
+pub mod layout;
+pub mod marks;
+pub mod metrics;
+pub mod moveresize;
+pub mod output;
+pub mod pid;
+pub mod ping;
+pub mod placement;
+pub mod placement_memory;
+pub mod pointer_warp;
+pub mod presets;
+pub mod preview;
+pub mod query;
+pub mod replay;
+pub mod rules;
+pub mod session_env;
+pub mod stacking;
+pub mod state;
+pub mod status;
+pub mod switcher;
+pub mod theme;
+pub mod urgency;
+pub mod wallpaper;
+pub mod window;
+pub mod window_groups;
+pub mod window_list;
+pub mod workspaces;
+
+#[cfg(not(any(feature = "wayland", feature = "x11")))]
+compile_error!("At least one display server feature must be enabled for AquariWM to function.");
+
+/// An error returned by [`launch_terminal`].
+#[derive(Debug, Error)]
+pub enum LaunchTerminalError {
+	/// The `TERM` environment variable was not set to any terminal.
+	#[error("the `TERM` environment variable is not set")]
+	VarNotPresent,
+
+	/// An IO error occurred trying to launch the `TERM` terminal.
+	#[error(transparent)]
+	Io(#[from] io::Error),
+}
+
+/// Attempts to launch the terminal set in the `TERM` environment variable.
+///
+/// If successful, returns the launched terminal process and the contents of the `TERM` environment
+/// variable launched.
+pub fn launch_terminal() -> Result<(OsString, process::Child), LaunchTerminalError> {
+	match env::var_os("TERM") {
+		// `TERM` is present.
+		Some(terminal) => {
+			let process = process::Command::new(&terminal).spawn()?;
+
+			Ok((terminal, process))
+		},
+
+		// `TERM` is not present.
+		None => Err(LaunchTerminalError::VarNotPresent),
+	}
+}
+
+/// Runs the given shell `command` with `sh -c`, as for [key bindings] and [autostart] commands.
+///
+/// [key bindings]: config::Command::Spawn
+/// [autostart]: config::Config::autostart
+pub fn spawn(command: &str) -> io::Result<process::Child> {
+	process::Command::new("sh").arg("-c").arg(command).spawn()
+}
@@ -3,28 +3,27 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 #![warn(clippy::missing_const_for_fn)]
-// Feature flags
-#![feature(impl_trait_in_assoc_type)]
-#![feature(iterator_try_collect)]
-#![feature(doc_cfg)]
 
-use std::{env, ffi::OsString, io, process};
-
-use clap::Parser;
+use std::process;
+#[cfg(feature = "x11")]
+use std::{fs::File, io::BufReader, path::Path};
+
+#[cfg(feature = "x11")]
+use aquariwm::replay::ReplayError;
+use aquariwm::{
+	config,
+	display_server::{self, DisplayServer},
+};
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use thiserror::Error;
 
-use crate::{display_server::DisplayServer, layout::LayoutSettings};
-
 mod cli;
-pub mod display_server;
-pub mod layout;
-pub mod state;
-
-#[cfg(not(any(feature = "wayland", feature = "x11")))]
-compile_error!("At least one display server feature must be enabled for AquariWM to function.");
 
 #[derive(Debug, Error)]
 pub enum Error {
+	#[error(transparent)]
+	Config(#[from] config::Error),
+
 	#[cfg(feature = "wayland")]
 	#[error(transparent)]
 	Wayland(#[from] display_server::wayland::Error),
@@ -32,6 +31,10 @@ pub enum Error {
 	#[cfg(feature = "x11")]
 	#[error(transparent)]
 	X11(#[from] display_server::x11::Error),
+
+	#[cfg(feature = "x11")]
+	#[error(transparent)]
+	Replay(#[from] ReplayError),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -48,51 +51,94 @@ fn main() -> Result<()> {
 	let args = cli::Cli::parse();
 	// Whether testing is enabled.
 	let testing = args.testing();
+	// Whether changes to windows are made, only logged, or left to another window manager.
+	let dispatch_mode = args.dispatch_mode();
+
+	let options = display_server::Options {
+		testing,
+
+		output_assignment: args.output_assignment(),
+		restore: args.restore,
+		reconnect: args.reconnect,
+		latency_metrics: args.latency_metrics,
+		map_other_screens: args.map_other_screens,
+		session_env: !args.no_session_env,
+		dispatch_mode,
+		record: args.record.clone(),
+
+		config: args.config_path(),
+		overrides: args.overrides(),
+
+		..display_server::Options::default()
+	}
+	.reload()?;
 
-	let settings = match args.window_gap {
-		Some(window_gap) => LayoutSettings::new().window_gap(window_gap),
-		None => LayoutSettings::default(),
+	#[cfg(feature = "x11")]
+	if let Some(path) = &args.replay {
+		return replay(path, &options);
+	}
+	let Some(subcommand) = &args.subcommand else {
+		cli::Cli::command()
+			.error(
+				ErrorKind::MissingSubcommand,
+				"a display server must be given, unless a recording is replayed with `--replay`",
+			)
+			.exit()
 	};
 
-	match &args.subcommand {
+	match subcommand {
 		#[cfg(feature = "wayland")]
-		cli::Subcommand::Wayland => Ok(display_server::Wayland::run(testing, settings)?),
+		cli::Subcommand::Wayland => Ok(display_server::Wayland::run(options)?),
 
 		#[cfg(feature = "x11")]
-		cli::Subcommand::X11 => Ok(tokio::runtime::Builder::new_multi_thread()
+		cli::Subcommand::X11 => match tokio::runtime::Builder::new_multi_thread()
 			.enable_all()
 			.build()
 			.unwrap()
-			.block_on(async { display_server::X11::run(testing, settings).await })?),
-	}
-}
+			.block_on(async { display_server::X11::run(options).await })
+		{
+			// Exit with a distinct code if the connection was lost, so that a session supervisor can
+			// restart AquariWM.
+			Err(error) if error.is_connection_loss() => process::exit(display_server::x11::CONNECTION_LOST_EXIT_CODE),
 
-/// An error returned by [`launch_terminal`].
-#[derive(Debug, Error)]
-pub enum LaunchTerminalError {
-	/// The `TERM` environment variable was not set to any terminal.
-	#[error("the `TERM` environment variable is not set")]
-	VarNotPresent,
+			result => Ok(result?),
+		},
 
-	/// An IO error occurred trying to launch the `TERM` terminal.
-	#[error(transparent)]
-	Io(#[from] io::Error),
+		#[cfg(all(feature = "x11", feature = "testing"))]
+		cli::Subcommand::TestClient {
+			class,
+			hints,
+			pid,
+			client_machine,
+			hang,
+			group,
+			override_redirect,
+		} => {
+			let identity = display_server::x11::test_client::Identity {
+				pid: *pid,
+				machine: client_machine.clone(),
+			};
+
+			Ok(tokio::runtime::Builder::new_multi_thread()
+				.enable_all()
+				.build()
+				.unwrap()
+				.block_on(async {
+					display_server::x11::test_client::run(class, hints, &identity, *hang, *group, *override_redirect)
+						.await
+				})?)
+		},
+	}
 }
 
-/// Attempts to launch the terminal set in the `TERM` environment variable.
-///
-/// If successful, returns the launched terminal process and the contents of the `TERM` environment
-/// variable launched.
-pub fn launch_terminal() -> Result<(OsString, process::Child), LaunchTerminalError> {
-	match env::var_os("TERM") {
-		// `TERM` is present.
-		Some(terminal) => {
-			let process = process::Command::new(&terminal).spawn()?;
-
-			Ok((terminal, process))
-		},
+/// Replays the recording at the given `path` with the given `options`, printing the state after
+/// each step.
+#[cfg(feature = "x11")]
+fn replay(path: &Path, options: &display_server::Options) -> Result<()> {
+	let recording = BufReader::new(File::open(path).map_err(ReplayError::Io)?);
+	let steps = display_server::x11::replay::replay(recording, options, |step| print!("{step}"))?;
 
-		// `TERM` is not present.
-		None => Err(LaunchTerminalError::VarNotPresent),
-	}
+	println!("Replayed {steps} steps, each of which reached its recorded state");
+
+	Ok(())
 }
@@ -0,0 +1,230 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Marks: names given to windows, by which they can later be [focused] or [swapped with],
+//! wherever they are.
+//!
+//! Each mark names a single window, so marking a window with a mark which already names another
+//! window moves the mark. A window may have any number of marks. Marks are forgotten when their
+//! window stops being managed, and are kept in [layout snapshots] by their windows' [IDs], so that
+//! they survive AquariWM being restarted.
+//!
+//! [focused]: crate::action::Action::FocusMark
+//! [swapped with]: crate::action::Action::SwapWithMark
+//! [layout snapshots]: crate::autosave
+//! [IDs]: WindowId
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::window::{ManagedWindow, WindowId};
+
+/// The [marks] of windows.
+///
+/// Marks are persisted as a map from each mark to its window's [ID], as returned by [`ids`], and
+/// [resolved] to windows again when they are restored.
+///
+/// [marks]: self
+/// [ID]: WindowId
+/// [`ids`]: Self::ids
+/// [resolved]: Self::resolve
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Marks<Window: ManagedWindow> {
+	/// The window named by each mark.
+	windows: BTreeMap<String, Window>,
+	/// The marks of each marked window.
+	marks: HashMap<Window, BTreeSet<String>>,
+}
+
+impl<Window: ManagedWindow> Default for Marks<Window> {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			windows: BTreeMap::new(),
+			marks: HashMap::new(),
+		}
+	}
+}
+
+impl<Window: ManagedWindow> Marks<Window> {
+	/// Creates an empty set of marks.
+	#[inline]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns whether no window is marked.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.windows.is_empty()
+	}
+
+	/// Returns the window named by the given `mark`, if any.
+	#[inline]
+	pub fn window(&self, mark: &str) -> Option<&Window> {
+		self.windows.get(mark)
+	}
+
+	/// Returns the marks of the given `window`, in order.
+	pub fn marks_of(&self, window: &Window) -> impl Iterator<Item = &str> {
+		self.marks.get(window).into_iter().flatten().map(String::as_str)
+	}
+
+	/// Returns every mark and the window it names, ordered by mark.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &Window)> {
+		self.windows.iter().map(|(mark, window)| (mark.as_str(), window))
+	}
+
+	/// Marks the given `window` with the given `mark`, returning the other window the mark was
+	/// moved from, if it named one.
+	pub fn mark(&mut self, mark: impl Into<String>, window: Window) -> Option<Window> {
+		let mark = mark.into();
+
+		self.marks.entry(window.clone()).or_default().insert(mark.clone());
+		let previous = self.windows.insert(mark.clone(), window.clone())?;
+
+		if previous == window {
+			return None;
+		}
+		self.forget_mark_of(&previous, &mark);
+
+		Some(previous)
+	}
+
+	/// Removes the given `mark`, returning the window it named, if any.
+	pub fn unmark(&mut self, mark: &str) -> Option<Window> {
+		let window = self.windows.remove(mark)?;
+		self.forget_mark_of(&window, mark);
+
+		Some(window)
+	}
+
+	/// Removes every mark of the given `window`, returning them, e.g. because it is no longer
+	/// managed.
+	pub fn unmark_window(&mut self, window: &Window) -> BTreeSet<String> {
+		let marks = self.marks.remove(window).unwrap_or_default();
+
+		for mark in &marks {
+			self.windows.remove(mark);
+		}
+
+		marks
+	}
+
+	/// Removes the marks of windows for which `keep` returns `false`, e.g. after [restoring] marks
+	/// of windows which no longer exist.
+	///
+	/// [restoring]: crate::autosave::restore
+	pub fn retain(&mut self, mut keep: impl FnMut(&Window) -> bool) {
+		self.marks.retain(|window, _| keep(window));
+		self.windows.retain(|_, window| self.marks.contains_key(window));
+	}
+
+	/// Returns the [ID] of the window named by each mark, ordered by mark, to be persisted.
+	///
+	/// [ID]: WindowId
+	pub fn ids(&self) -> BTreeMap<String, WindowId> {
+		self.windows
+			.iter()
+			.map(|(mark, window)| (mark.clone(), window.id()))
+			.collect()
+	}
+
+	/// Restores marks from the persisted [IDs] of the windows they name, where `resolve` returns
+	/// the window with the given ID.
+	///
+	/// Marks whose window can't be resolved are dropped.
+	///
+	/// [IDs]: Self::ids
+	pub fn resolve(ids: BTreeMap<String, WindowId>, mut resolve: impl FnMut(WindowId) -> Option<Window>) -> Self {
+		let windows: BTreeMap<_, _> = ids
+			.into_iter()
+			.filter_map(|(mark, id)| Some((mark, resolve(id)?)))
+			.collect();
+
+		windows.into()
+	}
+
+	/// Removes the given `mark` from the marks of the given `window`.
+	fn forget_mark_of(&mut self, window: &Window, mark: &str) {
+		if let Some(marks) = self.marks.get_mut(window) {
+			marks.remove(mark);
+
+			if marks.is_empty() {
+				self.marks.remove(window);
+			}
+		}
+	}
+}
+
+impl<Window: ManagedWindow> From<BTreeMap<String, Window>> for Marks<Window> {
+	fn from(windows: BTreeMap<String, Window>) -> Self {
+		let mut marks: HashMap<Window, BTreeSet<String>> = HashMap::new();
+
+		for (mark, window) in &windows {
+			marks.entry(window.clone()).or_default().insert(mark.clone());
+		}
+
+		Self { windows, marks }
+	}
+}
+
+impl<Window: ManagedWindow> From<Marks<Window>> for BTreeMap<String, Window> {
+	#[inline]
+	fn from(marks: Marks<Window>) -> Self {
+		marks.windows
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn marks() {
+		let mut marks = Marks::new();
+		assert_eq!(marks.mark("mail", 1), None);
+		assert_eq!(marks.mark("editor", 2), None);
+		assert_eq!(marks.mark("notes", 2), None);
+
+		assert_eq!(marks.window("mail"), Some(&1));
+		assert_eq!(marks.marks_of(&2).collect::<Vec<_>>(), ["editor", "notes"]);
+
+		// Marks are unique: marking another window moves the mark.
+		assert_eq!(marks.mark("mail", 2), Some(1));
+		assert_eq!(marks.mark("mail", 2), None);
+		assert_eq!(marks.window("mail"), Some(&2));
+		assert_eq!(marks.marks_of(&1).count(), 0);
+
+		assert_eq!(marks.unmark("notes"), Some(2));
+		assert_eq!(marks.unmark("notes"), None);
+		assert_eq!(marks.marks_of(&2).collect::<Vec<_>>(), ["editor", "mail"]);
+
+		assert_eq!(
+			marks.unmark_window(&2).into_iter().collect::<Vec<_>>(),
+			["editor", "mail"]
+		);
+		assert!(marks.is_empty());
+		assert_eq!(marks, Marks::new());
+	}
+
+	#[test]
+	fn round_trip() {
+		let mut marks = Marks::new();
+		marks.mark("mail", 1);
+		marks.mark("editor", 2);
+		marks.mark("notes", 2);
+
+		let json = serde_json::to_string(&marks.ids()).unwrap();
+		assert_eq!(json, r#"{"editor":2,"mail":1,"notes":2}"#);
+
+		let ids = serde_json::from_str(&json).unwrap();
+		let mut restored = Marks::resolve(ids, |id| u32::try_from(id.0).ok());
+		assert_eq!(restored, marks);
+
+		// Marks of windows which no longer exist are dropped.
+		restored.retain(|&window| window != 2);
+		assert_eq!(restored.iter().collect::<Vec<_>>(), [("mail", &1)]);
+		assert_eq!(restored.marks_of(&2).count(), 0);
+	}
+}
@@ -0,0 +1,493 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Measuring how long user-visible operations take, from the event which started them to the
+//! requests which finished them being flushed.
+//!
+//! Each [operation] is [begun] when its event is decoded, keyed by the window it is for, and
+//! [settled] at the flush point of each event loop iteration, once the display server says it is
+//! done. The [latencies] of the last [`WINDOW`] operations of each kind are kept, from which the
+//! median, the 95th percentile and the maximum are [reported]. Operations which never finish within
+//! the [`ORPHAN_TIMEOUT`] are counted as orphans and forgotten.
+//!
+//! Metrics do nothing unless they are [enabled]: while they are off, every method returns straight
+//! away. Nothing is allocated while operations are begun or settled either, as both the operations
+//! in progress and the latencies are kept in fixed-size arrays.
+//!
+//! [operation]: Operation
+//! [begun]: Metrics::begin
+//! [settled]: Metrics::settle
+//! [latencies]: Latencies
+//! [reported]: Metrics::report
+//! [enabled]: Metrics::new
+
+use std::{
+	array,
+	fmt::{self, Display, Formatter},
+	time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The number of latest operations of each kind whose latencies are reported.
+pub const WINDOW: usize = 256;
+/// The maximum number of operations in progress at once; beginning another forgets the oldest as
+/// an orphan.
+pub const MAX_PENDING: usize = 64;
+/// How long an operation may be in progress before it is counted as an orphan.
+pub const ORPHAN_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the [report] is logged.
+///
+/// [report]: Metrics::report
+pub const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A user-visible operation whose latency is measured.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Operation {
+	/// A window was asked to be mapped, and is mapped at its final geometry.
+	Map,
+	/// A key was pressed, and the focus changed.
+	Focus,
+	/// A window was unmapped or destroyed, and the layout has settled without it.
+	Unmap,
+}
+
+/// How an [operation] in progress is getting on, as told by the display server when it is
+/// [settled].
+///
+/// [operation]: Operation
+/// [settled]: Metrics::settle
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Progress {
+	/// The operation isn't finished yet.
+	Pending,
+	/// The operation is finished, and its latency is recorded.
+	Done,
+	/// The operation will never finish, e.g. because its window is gone, or because the key press
+	/// didn't change the focus. It is forgotten without being counted as an orphan.
+	Cancelled,
+}
+
+/// The latencies of the last [`WINDOW`] operations of a kind, kept in a fixed-size ring.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Latencies {
+	/// The latencies in microseconds, of which the first `len` are filled.
+	samples: [u32; WINDOW],
+	len: usize,
+	/// The index at which the next latency is recorded, replacing the oldest once the ring is
+	/// full.
+	next: usize,
+
+	/// The number of operations which have completed, including those no longer in the ring.
+	completed: u64,
+}
+
+/// A summary of [latencies].
+///
+/// [latencies]: Latencies
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Summary {
+	/// The number of operations which have completed.
+	pub completed: u64,
+
+	/// The median latency, in microseconds.
+	pub p50_us: u32,
+	/// The 95th percentile latency, in microseconds.
+	pub p95_us: u32,
+	/// The maximum latency, in microseconds.
+	pub max_us: u32,
+}
+
+/// The [summaries] of each operation's latencies.
+///
+/// [summaries]: Summary
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Report {
+	pub map: Summary,
+	pub focus: Summary,
+	pub unmap: Summary,
+
+	/// The number of operations which never finished.
+	pub orphans: u64,
+}
+
+/// An operation in progress.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+struct Begun<Key> {
+	key: Key,
+	operation: Operation,
+	at: Instant,
+}
+
+/// The latencies of user-visible [operations], correlated by the `Key` of the window they are
+/// for.
+///
+/// [operations]: Operation
+#[derive(Debug, Clone)]
+pub struct Metrics<Key> {
+	enabled: bool,
+
+	/// The operations in progress.
+	pending: [Option<Begun<Key>>; MAX_PENDING],
+	/// The latencies of each kind of operation, in the order of [`Operation::ALL`].
+	latencies: [Latencies; 3],
+	orphans: u64,
+
+	/// Whether any operation has completed or been orphaned since this was last [taken].
+	///
+	/// [taken]: Metrics::take_changed
+	changed: bool,
+	/// When the report is next due to be logged, once any operation has begun.
+	next_report: Option<Instant>,
+}
+
+impl Operation {
+	/// Every operation.
+	pub const ALL: [Self; 3] = [Self::Map, Self::Focus, Self::Unmap];
+
+	/// Returns the index of the operation in [`ALL`].
+	///
+	/// [`ALL`]: Self::ALL
+	const fn index(self) -> usize {
+		match self {
+			Self::Map => 0,
+			Self::Focus => 1,
+			Self::Unmap => 2,
+		}
+	}
+}
+
+impl Latencies {
+	/// Creates an empty ring of latencies.
+	pub const fn new() -> Self {
+		Self {
+			samples: [0; WINDOW],
+			len: 0,
+			next: 0,
+
+			completed: 0,
+		}
+	}
+
+	/// Records the latency of an operation which has completed, replacing the oldest if there are
+	/// already [`WINDOW`] latencies.
+	pub fn record(&mut self, latency: Duration) {
+		self.samples[self.next] = u32::try_from(latency.as_micros()).unwrap_or(u32::MAX);
+		self.next = (self.next + 1) % WINDOW;
+		self.len = (self.len + 1).min(WINDOW);
+
+		self.completed += 1;
+	}
+
+	/// Returns the [summary] of the latencies in the ring.
+	///
+	/// Percentiles are taken by the nearest rank. The latencies are sorted in a copy of the ring,
+	/// so nothing is allocated.
+	///
+	/// [summary]: Summary
+	pub fn summary(&self) -> Summary {
+		let mut sorted = self.samples;
+		let sorted = &mut sorted[..self.len];
+		sorted.sort_unstable();
+
+		let percentile = |percent: usize| match sorted.len() {
+			0 => 0,
+			len => sorted[(len * percent).div_ceil(100) - 1],
+		};
+
+		Summary {
+			completed: self.completed,
+
+			p50_us: percentile(50),
+			p95_us: percentile(95),
+			max_us: sorted.last().copied().unwrap_or(0),
+		}
+	}
+}
+
+impl Default for Latencies {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<Key> Metrics<Key> {
+	/// Creates metrics with no operations measured yet, which do nothing unless they are
+	/// `enabled`.
+	pub fn new(enabled: bool) -> Self {
+		Self {
+			enabled,
+
+			pending: array::from_fn(|_| None),
+			latencies: array::from_fn(|_| Latencies::new()),
+			orphans: 0,
+
+			changed: false,
+			next_report: None,
+		}
+	}
+
+	/// Returns whether the metrics are enabled.
+	#[inline(always)]
+	pub const fn is_enabled(&self) -> bool {
+		self.enabled
+	}
+
+	/// Begins the given `operation` for the given `key` at the time `now`.
+	///
+	/// If the same operation is already in progress for the same key, it is left to continue from
+	/// when it began. If [`MAX_PENDING`] operations are already in progress, the oldest is counted
+	/// as an orphan.
+	pub fn begin(&mut self, key: Key, operation: Operation, now: Instant)
+	where
+		Key: PartialEq,
+	{
+		if !self.enabled {
+			return;
+		}
+		self.next_report.get_or_insert(now + REPORT_INTERVAL);
+
+		let in_progress = self
+			.pending
+			.iter()
+			.flatten()
+			.any(|begun| begun.key == key && begun.operation == operation);
+		if in_progress {
+			return;
+		}
+
+		let slot = match self.pending.iter().position(Option::is_none) {
+			Some(slot) => slot,
+
+			None => {
+				self.orphans += 1;
+				self.changed = true;
+
+				self.pending
+					.iter()
+					.enumerate()
+					.min_by_key(|(_, begun)| begun.as_ref().map(|begun| begun.at))
+					.map_or(0, |(slot, _)| slot)
+			},
+		};
+		self.pending[slot] = Some(Begun {
+			key,
+			operation,
+			at: now,
+		});
+	}
+
+	/// Settles the operations in progress at the time `now`, as told by `progress`, recording the
+	/// latencies of those which are done.
+	///
+	/// Operations which are still pending after the [`ORPHAN_TIMEOUT`] are counted as orphans and
+	/// forgotten.
+	pub fn settle(&mut self, now: Instant, mut progress: impl FnMut(&Key, Operation) -> Progress) {
+		if !self.enabled {
+			return;
+		}
+
+		for slot in &mut self.pending {
+			let Some(Begun { key, operation, at }) = slot else {
+				continue;
+			};
+			let latency = now.saturating_duration_since(*at);
+
+			match progress(key, *operation) {
+				Progress::Done => {
+					self.latencies[operation.index()].record(latency);
+					self.changed = true;
+				},
+				Progress::Cancelled => (),
+
+				Progress::Pending if latency >= ORPHAN_TIMEOUT => {
+					self.orphans += 1;
+					self.changed = true;
+				},
+				Progress::Pending => continue,
+			}
+
+			*slot = None;
+		}
+	}
+
+	/// Returns the [report] of the latencies measured so far.
+	///
+	/// [report]: Report
+	pub fn report(&self) -> Report {
+		let [map, focus, unmap] = Operation::ALL.map(|operation| self.latencies[operation.index()].summary());
+
+		Report {
+			map,
+			focus,
+			unmap,
+
+			orphans: self.orphans,
+		}
+	}
+
+	/// Returns whether any operation has completed or been orphaned since this was last called.
+	pub fn take_changed(&mut self) -> bool {
+		std::mem::take(&mut self.changed)
+	}
+
+	/// Returns when the report is next due to be logged, if it is.
+	#[inline(always)]
+	pub const fn report_deadline(&self) -> Option<Instant> {
+		self.next_report
+	}
+
+	/// Returns the report if it is due to be logged at the time `now`, scheduling the next one.
+	pub fn poll_report(&mut self, now: Instant) -> Option<Report> {
+		if self.next_report.is_none_or(|deadline| now < deadline) {
+			return None;
+		}
+		self.next_report = Some(now + REPORT_INTERVAL);
+
+		Some(self.report())
+	}
+}
+
+impl Report {
+	/// Encodes the report as JSON, for external tools to read.
+	pub fn encode(&self) -> String {
+		serde_json::to_string(self).expect("the report is always serializable")
+	}
+}
+
+impl Display for Summary {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let ms = |us: u32| f64::from(us) / 1000.0;
+
+		write!(
+			f,
+			"{} completed, p50 {:.1}ms, p95 {:.1}ms, max {:.1}ms",
+			self.completed,
+			ms(self.p50_us),
+			ms(self.p95_us),
+			ms(self.max_us)
+		)
+	}
+}
+
+impl Display for Report {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"map: {}; focus: {}; unmap: {}; {} orphaned",
+			self.map, self.focus, self.unmap, self.orphans
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const MS: Duration = Duration::from_millis(1);
+
+	#[test]
+	fn latencies() {
+		let mut latencies = Latencies::new();
+		assert_eq!(latencies.summary(), Summary::default());
+
+		for ms in (1..=100).rev() {
+			latencies.record(MS * ms);
+		}
+		assert_eq!(
+			latencies.summary(),
+			Summary {
+				completed: 100,
+				p50_us: 50_000,
+				p95_us: 95_000,
+				max_us: 100_000,
+			}
+		);
+
+		// Only the last `WINDOW` latencies are summarized.
+		for _ in 0..WINDOW {
+			latencies.record(MS * 2);
+		}
+		let summary = latencies.summary();
+		assert_eq!(summary.completed, 100 + WINDOW as u64);
+		assert_eq!((summary.p50_us, summary.max_us), (2_000, 2_000));
+	}
+
+	#[test]
+	fn correlation() {
+		let start = Instant::now();
+		let mut metrics = Metrics::new(true);
+
+		metrics.begin(1, Operation::Map, start);
+		metrics.begin(2, Operation::Map, start + MS);
+		metrics.begin(1, Operation::Unmap, start + MS);
+		// Beginning an operation which is already in progress keeps when it began.
+		metrics.begin(1, Operation::Map, start + MS * 2);
+
+		metrics.settle(start + MS * 5, |&key, operation| match (key, operation) {
+			(1, Operation::Map) => Progress::Done,
+			(2, _) => Progress::Cancelled,
+			_ => Progress::Pending,
+		});
+		assert!(metrics.take_changed());
+
+		let report = metrics.report();
+		assert_eq!((report.map.completed, report.map.max_us), (1, 5_000));
+		assert_eq!(report.unmap.completed, 0);
+
+		// The unmap is still in progress until it is orphaned.
+		metrics.settle(start + ORPHAN_TIMEOUT, |_, _| Progress::Pending);
+		assert_eq!(metrics.report().orphans, 0);
+		metrics.settle(start + MS + ORPHAN_TIMEOUT, |_, _| Progress::Pending);
+		assert_eq!(metrics.report().orphans, 1);
+
+		metrics.settle(start + ORPHAN_TIMEOUT * 2, |_, _| Progress::Done);
+		assert_eq!(metrics.report().unmap.completed, 0);
+	}
+
+	#[test]
+	fn full() {
+		let start = Instant::now();
+		let mut metrics = Metrics::new(true);
+
+		for key in 0..=MAX_PENDING {
+			metrics.begin(key, Operation::Focus, start + MS * key as u32);
+		}
+		assert_eq!(metrics.report().orphans, 1);
+
+		// The oldest operation was the one forgotten.
+		let mut settled = Vec::new();
+		metrics.settle(start + MS * 100, |&key, _| {
+			settled.push(key);
+			Progress::Done
+		});
+		settled.sort_unstable();
+		assert_eq!(settled, (1..=MAX_PENDING).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn disabled() {
+		let start = Instant::now();
+		let mut metrics = Metrics::new(false);
+
+		metrics.begin(1, Operation::Map, start);
+		metrics.settle(start + MS, |_, _| unreachable!("disabled metrics settle nothing"));
+
+		assert_eq!(metrics.report(), Report::default());
+		assert!(!metrics.take_changed());
+		assert_eq!(metrics.report_deadline(), None);
+	}
+
+	#[test]
+	fn periodic_report() {
+		let start = Instant::now();
+		let mut metrics = Metrics::new(true);
+		assert_eq!(metrics.poll_report(start), None);
+
+		metrics.begin(1, Operation::Map, start);
+		assert_eq!(metrics.poll_report(start), None);
+		assert!(metrics.poll_report(start + REPORT_INTERVAL).is_some());
+		assert_eq!(metrics.report_deadline(), Some(start + REPORT_INTERVAL * 2));
+	}
+}
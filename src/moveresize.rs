@@ -0,0 +1,724 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Interactive moving and resizing of windows requested by clients, e.g. when a client-side
+//! decorated window's header bar is dragged.
+//!
+//! [Floating] windows are moved and resized freely. [Tiled] windows can't be placed freely, so
+//! moving one [drops] it onto the tiled window under the pointer, and resizing one moves the
+//! [edge] it shares with its neighbor on that side. Where in the target's tile the window is
+//! dropped decides whether the two windows are swapped or the window is inserted beside the
+//! target: see [`drop_region`].
+//!
+//! Requests are [decoded] from `_NET_WM_MOVERESIZE` client messages and start a [drag], which
+//! follows the pointer one [step] at a time.
+//!
+//! [Floating]: crate::layout::Mode::Floating
+//! [Tiled]: crate::layout::Mode::Tiled
+//! [drops]: Drop
+//! [edge]: EdgeHit
+//! [decoded]: Request::decode
+//! [drag]: Drag
+//! [step]: Step
+
+use crate::{
+	layout::{Axis, EdgeHit, Side, TilingLayout},
+	placement::Rect,
+};
+
+/// The fraction of a tile's width or height, from each of its edges, within which a window is
+/// [inserted beside] it rather than swapped with it when dropped.
+///
+/// [inserted beside]: DropRegion::Insert
+const EDGE_REGION: (i64, i64) = (1, 4);
+
+/// The edges of a window which are moved by a resize.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct Edges {
+	pub left: bool,
+	pub top: bool,
+	pub right: bool,
+	pub bottom: bool,
+}
+
+/// What a client requested to be done with its window.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Direction {
+	/// Resizes the window by dragging the given edges with the pointer.
+	Resize(Edges),
+	/// Moves the window with the pointer.
+	Move,
+
+	/// Resizes the window with the keyboard.
+	ResizeKeyboard,
+	/// Moves the window with the keyboard.
+	MoveKeyboard,
+
+	/// Cancels the ongoing move or resize, e.g. because the client missed the button release.
+	Cancel,
+}
+
+/// A request to move or resize a window.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Request {
+	/// The x-coordinate of the pointer, relative to the root window.
+	pub x: i32,
+	/// The y-coordinate of the pointer, relative to the root window.
+	pub y: i32,
+
+	pub direction: Direction,
+	/// The pointer button that was pressed to start the drag, or `0` if unknown.
+	pub button: u32,
+}
+
+impl Direction {
+	/// Decodes a `_NET_WM_MOVERESIZE` direction.
+	///
+	/// Returns [`None`] if the direction is unknown.
+	pub const fn from_ewmh(direction: u32) -> Option<Self> {
+		const fn resize(left: bool, top: bool, right: bool, bottom: bool) -> Direction {
+			Direction::Resize(Edges {
+				left,
+				top,
+				right,
+				bottom,
+			})
+		}
+
+		Some(match direction {
+			0 => resize(true, true, false, false),
+			1 => resize(false, true, false, false),
+			2 => resize(false, true, true, false),
+			3 => resize(false, false, true, false),
+			4 => resize(false, false, true, true),
+			5 => resize(false, false, false, true),
+			6 => resize(true, false, false, true),
+			7 => resize(true, false, false, false),
+
+			8 => Self::Move,
+			9 => Self::ResizeKeyboard,
+			10 => Self::MoveKeyboard,
+			11 => Self::Cancel,
+
+			_ => return None,
+		})
+	}
+}
+
+impl Request {
+	/// Decodes the data of a `_NET_WM_MOVERESIZE` client message: the pointer's coordinates, the
+	/// direction, the button, and the source indication (which is ignored).
+	///
+	/// Returns [`None`] if the direction is unknown.
+	pub const fn decode(data: [u32; 5]) -> Option<Self> {
+		let [x, y, direction, button, _source] = data;
+
+		let Some(direction) = Direction::from_ewmh(direction) else {
+			return None;
+		};
+
+		Some(Self {
+			x: x as i32,
+			y: y as i32,
+
+			direction,
+			button,
+		})
+	}
+}
+
+/// An ongoing move or resize of a window with the pointer.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Drag<Window> {
+	/// A [floating] window being moved (if there are no `edges`) or resized.
+	///
+	/// [floating]: crate::layout::Mode::Floating
+	Floating {
+		window: Window,
+		edges: Option<Edges>,
+
+		/// The pointer's position when the drag started.
+		start: (i32, i32),
+		/// The window's geometry when the drag started.
+		original: Rect,
+	},
+
+	/// A tiled window being moved, to be [dropped] onto the tiled window under the pointer.
+	///
+	/// [dropped]: Drop
+	Swap {
+		window: Window,
+		/// Where the window would be dropped if the pointer were released now, as of the last
+		/// [hover].
+		///
+		/// [hover]: Drag::hover
+		target: Option<Drop<Window>>,
+	},
+
+	/// A tiled window being resized by moving the `edge` it shares with its neighbor.
+	Edge {
+		window: Window,
+		edge: EdgeHit,
+
+		/// The pointer's coordinate along the edge's axis when the drag started.
+		start: i32,
+	},
+}
+
+/// Where in a tile a tiled window being [dragged] is dropped, which decides what is done with it.
+///
+/// [dragged]: Drag::Swap
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum DropRegion {
+	/// The center of the tile: the windows are swapped.
+	Swap,
+	/// Near an edge of the tile: the window is inserted beside the target on that `side` of it,
+	/// along the given `axis`.
+	Insert { axis: Axis, side: Side },
+}
+
+/// Where a tiled window being [dragged] would be dropped: onto the `target` window, in the given
+/// `region` of its tile.
+///
+/// [dragged]: Drag::Swap
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Drop<Window> {
+	pub target: Window,
+	pub region: DropRegion,
+
+	/// The target's tile.
+	pub tile: Rect,
+}
+
+impl<Window> Drop<Window> {
+	/// Returns the part of the target's tile which is highlighted to show what the drop would do.
+	///
+	/// The whole tile is highlighted for a [swap], and the half on the window's side of it for an
+	/// [insertion].
+	///
+	/// [swap]: DropRegion::Swap
+	/// [insertion]: DropRegion::Insert
+	pub const fn highlight(&self) -> Rect {
+		let (x, y, width, height) = self.tile;
+
+		match self.region {
+			DropRegion::Swap => self.tile,
+
+			DropRegion::Insert {
+				axis: Axis::Horizontal,
+				side,
+			} => {
+				let half = width / 2;
+				match side {
+					Side::Before => (x, y, half, height),
+					Side::After => (x + (width - half) as i32, y, half, height),
+				}
+			},
+			DropRegion::Insert {
+				axis: Axis::Vertical,
+				side,
+			} => {
+				let half = height / 2;
+				match side {
+					Side::Before => (x, y, width, half),
+					Side::After => (x, y + (height - half) as i32, width, half),
+				}
+			},
+		}
+	}
+}
+
+/// What is done to carry out a [drag] as the pointer moves.
+///
+/// [drag]: Drag
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Step<'drag> {
+	/// Configures the dragged window with the given geometry.
+	Configure(Rect),
+	/// [Moves] the given edge so that the node before it ends at the given position.
+	///
+	/// [Moves]: TilingLayout::move_edge
+	MoveEdge(&'drag EdgeHit, i32),
+}
+
+impl<Window> Drag<Window> {
+	/// Starts a drag of the given `window` for the given `request`.
+	///
+	/// `geometry` is the window's current geometry, and `layout` is the tiling layout if the window
+	/// is tiled in it.
+	///
+	/// Returns [`None`] if the request doesn't start a pointer drag, or if a tiled window is
+	/// resized on a side that it shares with no other node.
+	pub fn start(
+		window: Window,
+		request: &Request,
+		geometry: Rect,
+		layout: Option<&TilingLayout<Window>>,
+	) -> Option<Self> {
+		let pointer = (request.x, request.y);
+
+		let edges = match request.direction {
+			Direction::Move => None,
+			Direction::Resize(edges) => Some(edges),
+
+			Direction::ResizeKeyboard | Direction::MoveKeyboard | Direction::Cancel => return None,
+		};
+
+		match (layout, edges) {
+			(None, edges) => Some(Self::Floating {
+				window,
+				edges,
+
+				start: pointer,
+				original: geometry,
+			}),
+
+			(Some(_), None) => Some(Self::Swap { window, target: None }),
+
+			(Some(layout), Some(edges)) => {
+				let edge = shared_edge(layout, geometry, edges, pointer)?;
+				let start = match edge.axis {
+					Axis::Horizontal => pointer.0,
+					Axis::Vertical => pointer.1,
+				};
+
+				Some(Self::Edge { window, edge, start })
+			},
+		}
+	}
+
+	/// Returns the window being dragged.
+	pub const fn window(&self) -> &Window {
+		match self {
+			Self::Floating { window, .. } | Self::Swap { window, .. } | Self::Edge { window, .. } => window,
+		}
+	}
+
+	/// Hit-tests the pointer at the given position against the `layout`, updating where a tiled
+	/// window being [swapped] would be dropped.
+	///
+	/// Nothing is dropped if the pointer is over the dragged window's own tile or outside of every
+	/// tile. Returns whether the target changed, e.g. so that it can be highlighted.
+	///
+	/// [swapped]: Self::Swap
+	pub fn hover(&mut self, layout: &TilingLayout<Window>, (x, y): (i32, i32)) -> bool
+	where
+		Window: PartialEq + Clone,
+	{
+		let Self::Swap { window, target } = self else {
+			return false;
+		};
+
+		let new_target = layout
+			.window_at(x, y)
+			.filter(|&(under, _)| under != &*window)
+			.and_then(|(under, tile)| {
+				drop_region(tile, (x, y)).map(|region| Drop {
+					target: under.clone(),
+					region,
+					tile,
+				})
+			});
+
+		let changed = *target != new_target;
+		*target = new_target;
+
+		changed
+	}
+
+	/// Returns what is done for the pointer having moved to the given position, if anything.
+	pub fn motion(&self, (x, y): (i32, i32)) -> Option<Step<'_>> {
+		match self {
+			Self::Floating {
+				edges,
+				start: (start_x, start_y),
+				original,
+				..
+			} => Some(Step::Configure(floating_geometry(
+				*original,
+				*edges,
+				(x - start_x, y - start_y),
+			))),
+
+			// Tiled windows are only moved once they are dropped.
+			Self::Swap { .. } => None,
+
+			Self::Edge { edge, start, .. } => {
+				let coord = match edge.axis {
+					Axis::Horizontal => x,
+					Axis::Vertical => y,
+				};
+
+				Some(Step::MoveEdge(edge, edge.position + (coord - start)))
+			},
+		}
+	}
+
+	/// Returns what is done to restore the window's original geometry when the drag is cancelled,
+	/// if anything.
+	pub const fn cancel(&self) -> Option<Step<'_>> {
+		match self {
+			Self::Floating { original, .. } => Some(Step::Configure(*original)),
+			Self::Swap { .. } => None,
+			Self::Edge { edge, .. } => Some(Step::MoveEdge(edge, edge.position)),
+		}
+	}
+}
+
+/// Returns the region of the given `tile` which the given `pointer` is in, or [`None`] if it is
+/// outside the tile.
+///
+/// Within [a quarter] of the tile's width or height of an edge, the window is [inserted] on that
+/// side of the target; elsewhere, it is [swapped] with the target. Near a corner, the edge which
+/// is closer relative to the tile's size wins, and of equally close edges, the left or right edge.
+///
+/// [a quarter]: EDGE_REGION
+/// [inserted]: DropRegion::Insert
+/// [swapped]: DropRegion::Swap
+pub fn drop_region((x, y, width, height): Rect, (pointer_x, pointer_y): (i32, i32)) -> Option<DropRegion> {
+	let (width, height) = (i64::from(width), i64::from(height));
+	let (dx, dy) = (i64::from(pointer_x) - i64::from(x), i64::from(pointer_y) - i64::from(y));
+	if !(0..width).contains(&dx) || !(0..height).contains(&dy) {
+		return None;
+	}
+
+	let (numerator, denominator) = EDGE_REGION;
+	let edges = [
+		(dx, width, Axis::Horizontal, Side::Before),
+		(width - 1 - dx, width, Axis::Horizontal, Side::After),
+		(dy, height, Axis::Vertical, Side::Before),
+		(height - 1 - dy, height, Axis::Vertical, Side::After),
+	];
+
+	let region = edges
+		.into_iter()
+		// Distances are compared relative to the tile's size along their axis.
+		.filter(|&(distance, size, ..)| distance * denominator < size * numerator)
+		.reduce(|closest, edge| match edge.0 * closest.1 < closest.0 * edge.1 {
+			true => edge,
+			false => closest,
+		})
+		.map_or(DropRegion::Swap, |(.., axis, side)| DropRegion::Insert { axis, side });
+
+	Some(region)
+}
+
+/// Returns the geometry of a floating window which had the given `original` geometry, after its
+/// `edges` have been dragged by the given `delta` (or the whole window, if there are no `edges`).
+///
+/// Resized windows keep a size of at least one pixel; their opposite edges stay where they are.
+pub fn floating_geometry(original: Rect, edges: Option<Edges>, (dx, dy): (i32, i32)) -> Rect {
+	let (x, y, width, height) = original;
+
+	let Some(edges) = edges else {
+		return (x + dx, y + dy, width, height);
+	};
+
+	// Returns the new coordinate and dimension along one axis.
+	let resize = |coord: i32, dimension: u32, start: bool, end: bool, delta: i32| {
+		let dimension = dimension as i64;
+
+		if start {
+			let new_dimension = (dimension - delta as i64).max(1);
+
+			(coord + (dimension - new_dimension) as i32, new_dimension as u32)
+		} else if end {
+			(coord, (dimension + delta as i64).max(1) as u32)
+		} else {
+			(coord, dimension as u32)
+		}
+	};
+
+	let (x, width) = resize(x, width, edges.left, edges.right, dx);
+	let (y, height) = resize(y, height, edges.top, edges.bottom, dy);
+
+	(x, y, width, height)
+}
+
+/// Returns the edge that a tiled window with the given `geometry` shares with its neighbor on the
+/// side given by `edges`, near the `pointer`.
+///
+/// If both a left or right edge and a top or bottom edge are given (i.e. the window is resized by
+/// a corner), the left or right edge is preferred, unless the window has no neighbor on that side.
+pub fn shared_edge<Window>(
+	layout: &TilingLayout<Window>,
+	(x, y, width, height): Rect,
+	edges: Edges,
+	(pointer_x, pointer_y): (i32, i32),
+) -> Option<EdgeHit> {
+	let (right, bottom) = (x + (width as i32), y + (height as i32));
+
+	// The edge is looked for on the window's side, level with the pointer (as far as that lies
+	// alongside the window).
+	let horizontal = match (edges.left, edges.right) {
+		(true, _) => Some(x),
+		(_, true) => Some(right),
+		_ => None,
+	}
+	.map(|coord| (coord, pointer_y.clamp(y, bottom), Axis::Horizontal));
+	let vertical = match (edges.top, edges.bottom) {
+		(true, _) => Some(y),
+		(_, true) => Some(bottom),
+		_ => None,
+	}
+	.map(|coord| (pointer_x.clamp(x, right), coord, Axis::Vertical));
+
+	horizontal
+		.into_iter()
+		.chain(vertical)
+		.find_map(|(x, y, axis)| layout.edge_at(x, y, 0).filter(|edge| edge.axis == axis))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::{LayoutSettings, Orientation};
+
+	const fn edges(left: bool, top: bool, right: bool, bottom: bool) -> Edges {
+		Edges {
+			left,
+			top,
+			right,
+			bottom,
+		}
+	}
+
+	/// A 2 by 2 layout, `[(1 / 2) | (3 / 4)]`, with a window gap of 10 pixels: window 1 is at
+	/// `(10, 10, 485, 285)` and window 4 at `(505, 305, 485, 285)`.
+	fn layout() -> TilingLayout<u32> {
+		let settings = LayoutSettings::new().window_gap(10);
+
+		let mut layout = TilingLayout::new(Orientation::LeftToRight, 0, 0, 1000, 600, &settings);
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([1, 2]))
+			.unwrap();
+		layout
+			.push_group_back_with(Orientation::TopToBottom, |group| group.push_windows_back([3, 4]))
+			.unwrap();
+
+		layout
+			.apply_changes(&mut |_, _, _, _, _, _| Ok::<_, ()>(()), &settings)
+			.unwrap();
+
+		layout
+	}
+
+	const fn request(x: i32, y: i32, direction: Direction) -> Request {
+		Request {
+			x,
+			y,
+			direction,
+			button: 1,
+		}
+	}
+
+	#[test]
+	fn drop_regions() {
+		let tile = (100, 100, 400, 200);
+		let insert = |axis, side| Some(DropRegion::Insert { axis, side });
+
+		assert_eq!(drop_region(tile, (300, 200)), Some(DropRegion::Swap));
+		// The outer quarter toward each edge inserts on that side.
+		assert_eq!(drop_region(tile, (100, 200)), insert(Axis::Horizontal, Side::Before));
+		assert_eq!(drop_region(tile, (199, 200)), insert(Axis::Horizontal, Side::Before));
+		assert_eq!(drop_region(tile, (200, 200)), Some(DropRegion::Swap));
+		assert_eq!(drop_region(tile, (499, 200)), insert(Axis::Horizontal, Side::After));
+		assert_eq!(drop_region(tile, (300, 149)), insert(Axis::Vertical, Side::Before));
+		assert_eq!(drop_region(tile, (300, 299)), insert(Axis::Vertical, Side::After));
+
+		// Near a corner, the edge which is closer relative to the tile's size wins.
+		assert_eq!(drop_region(tile, (150, 120)), insert(Axis::Vertical, Side::Before));
+		assert_eq!(drop_region(tile, (120, 140)), insert(Axis::Horizontal, Side::Before));
+		assert_eq!(drop_region(tile, (100, 100)), insert(Axis::Horizontal, Side::Before));
+
+		// Points outside the tile aren't in any region.
+		assert_eq!(drop_region(tile, (500, 200)), None);
+		assert_eq!(drop_region(tile, (300, 99)), None);
+		assert_eq!(drop_region((0, 0, 0, 0), (0, 0)), None);
+	}
+
+	/// Tests that hovering over the layout while moving a tiled window finds where it would be
+	/// dropped.
+	#[test]
+	fn hover() {
+		let layout = layout();
+		let window_1 = (10, 10, 485, 285);
+		let mut drag = Drag::start(1, &request(100, 100, Direction::Move), window_1, Some(&layout)).unwrap();
+
+		// Window 1's own tile and the gaps are nowhere to drop it.
+		assert!(!drag.hover(&layout, (100, 100)));
+		assert!(!drag.hover(&layout, (500, 100)));
+
+		assert!(drag.hover(&layout, (750, 150)));
+		let Drag::Swap { target, .. } = &drag else {
+			unreachable!()
+		};
+		assert_eq!(
+			*target,
+			Some(Drop {
+				target: 3,
+				region: DropRegion::Swap,
+				tile: (505, 10, 485, 285),
+			})
+		);
+		assert!(!drag.hover(&layout, (751, 151)));
+
+		assert!(drag.hover(&layout, (750, 580)));
+		let Drag::Swap { target, .. } = &drag else {
+			unreachable!()
+		};
+		assert_eq!(
+			target.map(|drop| (drop.target, drop.region)),
+			Some((
+				4,
+				DropRegion::Insert {
+					axis: Axis::Vertical,
+					side: Side::After
+				}
+			))
+		);
+
+		assert!(drag.hover(&layout, (5, 5)));
+		assert!(matches!(drag, Drag::Swap { target: None, .. }));
+	}
+
+	#[test]
+	fn highlight() {
+		let drop = |region| Drop {
+			target: 1,
+			region,
+			tile: (100, 100, 401, 200),
+		};
+		let insert = |axis, side| drop(DropRegion::Insert { axis, side }).highlight();
+
+		assert_eq!(drop(DropRegion::Swap).highlight(), (100, 100, 401, 200));
+		assert_eq!(insert(Axis::Horizontal, Side::Before), (100, 100, 200, 200));
+		assert_eq!(insert(Axis::Horizontal, Side::After), (301, 100, 200, 200));
+		assert_eq!(insert(Axis::Vertical, Side::Before), (100, 100, 401, 100));
+		assert_eq!(insert(Axis::Vertical, Side::After), (100, 200, 401, 100));
+	}
+
+	#[test]
+	fn decode() {
+		assert_eq!(
+			Request::decode([100, 200, 8, 1, 1]),
+			Some(request(100, 200, Direction::Move))
+		);
+
+		let directions = [
+			edges(true, true, false, false),
+			edges(false, true, false, false),
+			edges(false, true, true, false),
+			edges(false, false, true, false),
+			edges(false, false, true, true),
+			edges(false, false, false, true),
+			edges(true, false, false, true),
+			edges(true, false, false, false),
+		];
+		for (direction, edges) in directions.into_iter().enumerate() {
+			assert_eq!(Direction::from_ewmh(direction as u32), Some(Direction::Resize(edges)));
+		}
+
+		assert_eq!(Direction::from_ewmh(9), Some(Direction::ResizeKeyboard));
+		assert_eq!(Direction::from_ewmh(10), Some(Direction::MoveKeyboard));
+		assert_eq!(Direction::from_ewmh(11), Some(Direction::Cancel));
+		assert_eq!(Request::decode([0, 0, 12, 0, 0]), None);
+	}
+
+	#[test]
+	fn floating() {
+		let original = (100, 100, 400, 300);
+
+		assert_eq!(floating_geometry(original, None, (-50, 20)), (50, 120, 400, 300));
+
+		// The opposite edges stay where they are.
+		assert_eq!(
+			floating_geometry(original, Some(edges(true, true, false, false)), (50, -20)),
+			(150, 80, 350, 320)
+		);
+		assert_eq!(
+			floating_geometry(original, Some(edges(false, false, true, false)), (50, 20)),
+			(100, 100, 450, 300)
+		);
+		// Windows keep a size of at least one pixel.
+		assert_eq!(
+			floating_geometry(original, Some(edges(true, false, false, true)), (500, -500)),
+			(499, 100, 1, 1)
+		);
+
+		// Cancelling restores the original geometry.
+		let resize = request(500, 400, Direction::Resize(edges(false, false, true, true)));
+		let drag = Drag::start(1, &resize, original, None).unwrap();
+		assert_eq!(drag.motion((510, 380)), Some(Step::Configure((100, 100, 410, 280))));
+		assert_eq!(drag.cancel(), Some(Step::Configure(original)));
+	}
+
+	#[test]
+	fn tiled() {
+		let layout = layout();
+		let window_1 = (10, 10, 485, 285);
+		let window_4 = (505, 305, 485, 285);
+
+		let root = layout.edge_at(500, 100, 0).unwrap();
+		let left = layout.edge_at(200, 300, 0).unwrap();
+		let right = layout.edge_at(800, 300, 0).unwrap();
+
+		let shared = |geometry, edges, pointer| shared_edge(&layout, geometry, edges, pointer);
+
+		assert_eq!(
+			shared(window_1, edges(false, false, true, false), (480, 100)),
+			Some(root.clone())
+		);
+		assert_eq!(
+			shared(window_1, edges(false, false, false, true), (100, 280)),
+			Some(left)
+		);
+		assert_eq!(
+			shared(window_4, edges(true, false, false, false), (510, 400)),
+			Some(root.clone())
+		);
+		assert_eq!(
+			shared(window_4, edges(false, true, false, false), (800, 310)),
+			Some(right.clone())
+		);
+
+		// The edges of the layout itself are shared with no other node.
+		assert_eq!(shared(window_1, edges(true, false, false, false), (15, 100)), None);
+		assert_eq!(shared(window_4, edges(false, false, false, true), (800, 590)), None);
+
+		// Corners prefer the left or right edge, unless there is none on that side.
+		assert_eq!(
+			shared(window_4, edges(true, true, false, false), (510, 310)),
+			Some(root.clone())
+		);
+		assert_eq!(
+			shared(window_4, edges(false, true, true, false), (980, 310)),
+			Some(right)
+		);
+
+		// Moving a tiled window only moves it once it is dropped.
+		let layout = Some(&layout);
+		let drag = Drag::start(1, &request(100, 10, Direction::Move), window_1, layout).unwrap();
+		assert_eq!(
+			drag,
+			Drag::Swap {
+				window: 1,
+				target: None
+			}
+		);
+		assert_eq!(drag.motion((800, 400)), None);
+
+		// Resizing moves the shared edge with the pointer, and cancelling moves it back.
+		let resize = request(480, 100, Direction::Resize(edges(false, false, true, false)));
+		let drag = Drag::start(1, &resize, window_1, layout).unwrap();
+		assert_eq!(drag.motion((530, 200)), Some(Step::MoveEdge(&root, 545)));
+		assert_eq!(drag.cancel(), Some(Step::MoveEdge(&root, 495)));
+
+		// Keyboard requests don't start pointer drags.
+		assert_eq!(
+			Drag::start(1, &request(0, 0, Direction::MoveKeyboard), window_1, layout),
+			None
+		);
+	}
+}
@@ -0,0 +1,1358 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{collections::HashMap, fmt, num::ParseFloatError, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::layout::Orientation;
+
+/// A monitor (or similar region of the screen) on which windows are displayed.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Output {
+	/// The name of the output, as reported by the display server (e.g. `"HDMI-1"`).
+	pub name: String,
+
+	pub x: i32,
+	pub y: i32,
+
+	pub width: u32,
+	pub height: u32,
+
+	/// Whether this is the primary output.
+	pub primary: bool,
+	/// The [scale factor] used to convert logical sizes to device pixels on this output.
+	///
+	/// [scale factor]: Scale
+	pub scale: Scale,
+
+	/// The names of the monitors which [clone] this output, showing the same region of the screen.
+	///
+	/// [clone]: Output::group_clones
+	pub clones: Vec<String>,
+}
+
+impl Output {
+	/// Returns whether the given point lies within the output.
+	pub const fn contains(&self, x: i32, y: i32) -> bool {
+		let (right, bottom) = (
+			(self.x as i64) + (self.width as i64),
+			(self.y as i64) + (self.height as i64),
+		);
+
+		x >= self.x && y >= self.y && (x as i64) < right && (y as i64) < bottom
+	}
+
+	/// Returns the primary output from the given `outputs`, or the first output if none of them
+	/// are marked as primary.
+	///
+	/// Returns [`None`] if there are no `outputs`.
+	pub fn primary(outputs: &[Self]) -> Option<&Self> {
+		outputs.iter().find(|output| output.primary).or_else(|| outputs.first())
+	}
+
+	/// Returns the output of the given `name` from the given `outputs`, if it is connected.
+	///
+	/// A monitor which [clones] another output is part of that output, so that output is returned
+	/// for its name.
+	///
+	/// [clones]: Self::clones
+	pub fn named<'outputs>(outputs: &'outputs [Self], name: &str) -> Option<&'outputs Self> {
+		Self::exactly_named(outputs, name).or_else(|| {
+			outputs
+				.iter()
+				.find(|output| output.clones.iter().any(|clone| clone == name))
+		})
+	}
+
+	/// Returns the output of the given `name` from the given `outputs`, ignoring their [clones].
+	///
+	/// [clones]: Self::clones
+	fn exactly_named<'outputs>(outputs: &'outputs [Self], name: &str) -> Option<&'outputs Self> {
+		outputs.iter().find(|output| output.name == name)
+	}
+
+	/// Returns the output containing the given point from the given `outputs`, if any.
+	pub fn at(outputs: &[Self], x: i32, y: i32) -> Option<&Self> {
+		outputs.iter().find(|output| output.contains(x, y))
+	}
+
+	/// Returns the center of the output.
+	pub const fn center(&self) -> (i32, i32) {
+		(self.x + ((self.width / 2) as i32), self.y + ((self.height / 2) as i32))
+	}
+
+	/// Returns the area of the output, in pixels.
+	pub const fn area(&self) -> u64 {
+		(self.width as u64) * (self.height as u64)
+	}
+
+	/// Returns the area in which the output overlaps the `other` output, in pixels.
+	pub fn overlap(&self, other: &Self) -> u64 {
+		let span = |start: i32, length: u32, other_start: i32, other_length: u32| {
+			let end = (i64::from(start) + i64::from(length)).min(i64::from(other_start) + i64::from(other_length));
+
+			(end - i64::from(start.max(other_start))).max(0) as u64
+		};
+
+		span(self.x, self.width, other.x, other.width) * span(self.y, self.height, other.y, other.height)
+	}
+
+	/// Returns whether the output and the `other` output are clones of each other, i.e. whether at
+	/// least the `threshold` of the smaller of the two is overlapped by the larger.
+	///
+	/// Mirrored monitors, like a laptop's screen and a projector showing the same thing, are
+	/// reported as outputs with the same or overlapping regions of the screen, often with
+	/// different resolutions.
+	pub fn is_clone_of(&self, other: &Self, threshold: CloneOverlap) -> bool {
+		let smaller = self.area().min(other.area());
+
+		smaller != 0 && (self.overlap(other) as f64) >= threshold.get() * (smaller as f64)
+	}
+
+	/// Groups the given `monitors` which [clone] each other into single logical outputs, so that
+	/// mirrored monitors share one layout rather than fighting over the same region of the screen.
+	///
+	/// Clones are grouped transitively, so three monitors mirroring each other become one output.
+	/// Each group is named after its primary monitor (or its first, if none of them are primary),
+	/// so that it keeps that monitor's layout when the group is split up again, and takes the
+	/// region and scale factor of its monitor with the highest resolution. Its other monitors are
+	/// listed in its [`clones`].
+	///
+	/// [clone]: Self::is_clone_of
+	/// [`clones`]: Self::clones
+	pub fn group_clones(monitors: Vec<Self>, threshold: CloneOverlap) -> Vec<Self> {
+		let mut groups: Vec<Vec<Self>> = Vec::new();
+
+		for monitor in monitors {
+			let cloned: Vec<usize> = groups
+				.iter()
+				.enumerate()
+				.filter(|(_, group)| group.iter().any(|member| member.is_clone_of(&monitor, threshold)))
+				.map(|(index, _)| index)
+				.collect();
+
+			match cloned.split_first() {
+				Some((&first, rest)) => {
+					// The monitor may bridge several groups, which are then merged into the first.
+					let merged: Vec<_> = rest.iter().rev().map(|&index| groups.remove(index)).collect();
+					for group in merged.into_iter().rev() {
+						groups[first].extend(group);
+					}
+
+					groups[first].push(monitor);
+				},
+
+				None => groups.push(vec![monitor]),
+			}
+		}
+
+		groups.into_iter().map(Self::from_clones).collect()
+	}
+
+	/// Combines the given `members` of a [group of clones] into a single logical output.
+	///
+	/// [group of clones]: Self::group_clones
+	fn from_clones(members: Vec<Self>) -> Self {
+		let named = members.iter().position(|member| member.primary).unwrap_or(0);
+		let largest = members
+			.iter()
+			.enumerate()
+			.max_by_key(|&(index, member)| (member.area(), index == named))
+			.map_or(named, |(index, _)| index);
+
+		let primary = members.iter().any(|member| member.primary);
+		let mut output = Self {
+			name: members[named].name.clone(),
+			primary,
+
+			..members[largest].clone()
+		};
+
+		output.clones = members
+			.into_iter()
+			.enumerate()
+			.filter(|&(index, _)| index != named)
+			.map(|(_, member)| member.name)
+			.collect();
+
+		output
+	}
+
+	/// Returns the output in the given `direction` from the output `from`, out of the given
+	/// `outputs`.
+	///
+	/// Outputs are compared by their centers, so this works for arbitrary arrangements of outputs:
+	/// of the outputs whose centers lie in the half-plane in that `direction` from `from`'s center,
+	/// the one whose center is nearest is chosen. At the edge of the arrangement, there is no such
+	/// output, so [`OutputEdges`] decides whether to wrap around to the output furthest in the
+	/// opposite direction or to return [`None`].
+	pub fn in_direction<'outputs>(
+		outputs: &'outputs [Self],
+		from: &Self,
+		direction: Direction,
+		edges: OutputEdges,
+	) -> Option<&'outputs Self> {
+		let (from_x, from_y) = from.center();
+		// The offset of the given output's center along the `direction`, and perpendicular to it.
+		let offsets = |output: &Self| {
+			let (x, y) = output.center();
+			let (dx, dy) = (i64::from(x) - i64::from(from_x), i64::from(y) - i64::from(from_y));
+
+			match direction {
+				Direction::Left => (-dx, dy),
+				Direction::Right => (dx, dy),
+				Direction::Up => (-dy, dx),
+				Direction::Down => (dy, dx),
+			}
+		};
+
+		let others = || outputs.iter().filter(|output| output.name != from.name);
+
+		let nearest = others().filter(|output| offsets(output).0 > 0).min_by_key(|output| {
+			let (along, across) = offsets(output);
+
+			along * along + across * across
+		});
+
+		match (nearest, edges) {
+			(Some(output), _) => Some(output),
+			(None, OutputEdges::Clamp) => None,
+
+			// Wrap around to the output furthest in the opposite direction, preferring outputs in
+			// line with `from`.
+			(None, OutputEdges::Wrap) => others().filter(|output| offsets(output).0 < 0).min_by_key(|output| {
+				let (along, across) = offsets(output);
+
+				(along, across.abs())
+			}),
+		}
+	}
+}
+
+/// A direction in which to move between [outputs].
+///
+/// [outputs]: Output
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Direction {
+	Left,
+	Right,
+	Up,
+	Down,
+}
+
+/// What happens when moving [in a direction] past the last [output] in that direction.
+///
+/// [in a direction]: Output::in_direction
+/// [output]: Output
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum OutputEdges {
+	/// Moving past the last output stays on it.
+	#[default]
+	Clamp,
+	/// Moving past the last output wraps around to the output furthest in the opposite direction.
+	Wrap,
+}
+
+/// How the orientation of the root group of a new output's layout is chosen.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum DefaultOrientation {
+	/// Every output uses the given orientation.
+	Fixed(Orientation),
+	/// Outputs wider than they are tall (or square) use the `landscape` orientation, and outputs
+	/// taller than they are wide use the `portrait` orientation.
+	ByAspect {
+		landscape: Orientation,
+		portrait: Orientation,
+	},
+}
+
+impl DefaultOrientation {
+	/// Returns the orientation chosen for an output of the given dimensions.
+	pub const fn choose(&self, width: u32, height: u32) -> Orientation {
+		match self {
+			Self::Fixed(orientation) => *orientation,
+
+			Self::ByAspect { portrait, .. } if height > width => *portrait,
+			Self::ByAspect { landscape, .. } => *landscape,
+		}
+	}
+
+	/// Returns the orientation chosen for the given `output`.
+	#[inline]
+	pub const fn for_output(&self, output: &Output) -> Orientation {
+		self.choose(output.width, output.height)
+	}
+
+	/// Returns the number of clockwise rotations by which to rotate the root group of the layout
+	/// spanning the `old` primary output when it is replaced by the `new` primary output, if it
+	/// should be rotated at all.
+	///
+	/// A different primary output gets the orientation chosen for it, as it is new to the layout.
+	/// The same primary output only gets one when it is rotated (i.e. its aspect changes) if
+	/// `follow_rotation` is `true`, so that rotating a monitor doesn't unexpectedly reorient its
+	/// layout. The root group is rotated by the difference between the two choices, rather than set
+	/// to the new one, so that a layout which was reoriented by hand stays reoriented in the same
+	/// way.
+	pub fn rotations(&self, old: Option<&Output>, new: Option<&Output>, follow_rotation: bool) -> Option<i32> {
+		let (Some(old), Some(new)) = (old, new) else {
+			return None;
+		};
+		if old.name == new.name && !follow_rotation {
+			return None;
+		}
+
+		let rotations = self.for_output(old).rotations_to(&self.for_output(new));
+
+		(rotations != 0).then_some(rotations)
+	}
+}
+
+/// How the [active output] was last changed.
+///
+/// [active output]: ActiveOutput
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum FocusSource {
+	/// Focus was changed explicitly, e.g. with a key binding.
+	Explicit,
+	/// Focus followed the pointer.
+	Pointer,
+}
+
+/// The output which is currently active, i.e. the one on which focus moves between windows and
+/// from which other outputs are [focused in a direction].
+///
+/// The active output follows both focus and the pointer. When focus is changed [explicitly], the
+/// pointer crossing onto other outputs is ignored until the next time focus follows the pointer,
+/// so that the pointer resting on another output doesn't undo, e.g., focusing an output with a
+/// key binding.
+///
+/// [focused in a direction]: crate::action::Action::FocusOutput
+/// [explicitly]: FocusSource::Explicit
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct ActiveOutput {
+	/// The name of the active output, if any output has been made active.
+	name: Option<String>,
+	/// Whether the active output was last changed [explicitly].
+	///
+	/// [explicitly]: FocusSource::Explicit
+	explicit: bool,
+}
+
+impl ActiveOutput {
+	/// Returns the name of the active output, if any output has been made active.
+	pub fn name(&self) -> Option<&str> {
+		self.name.as_deref()
+	}
+
+	/// Returns the active output from the given `outputs`.
+	///
+	/// If no output has been made active, or the active output is disconnected, the [primary
+	/// output] is returned.
+	///
+	/// [primary output]: Output::primary
+	pub fn get<'outputs>(&self, outputs: &'outputs [Output]) -> Option<&'outputs Output> {
+		self.name()
+			.and_then(|name| Output::named(outputs, name))
+			.or_else(|| Output::primary(outputs))
+	}
+
+	/// Updates the active output to reflect focus having moved to the output of the given `name`.
+	pub fn focus(&mut self, name: &str, source: FocusSource) {
+		self.name = Some(name.to_owned());
+		self.explicit = source == FocusSource::Explicit;
+	}
+
+	/// Updates the active output to reflect the pointer having crossed onto the output of the
+	/// given `name`, unless focus was last changed [explicitly].
+	///
+	/// Returns whether the active output changed.
+	///
+	/// [explicitly]: FocusSource::Explicit
+	pub fn pointer_crossed(&mut self, name: &str) -> bool {
+		if self.explicit || self.name() == Some(name) {
+			return false;
+		}
+
+		self.name = Some(name.to_owned());
+
+		true
+	}
+}
+
+/// The scale factor of an [output], used to convert sizes specified in logical pixels to device
+/// pixels.
+///
+/// User-facing sizes (such as the [window gap]) are specified in logical pixels so that they look
+/// the same on outputs of different pixel densities. The layout itself is always in device pixels:
+/// conversion happens at the boundary, when settings are applied to a particular output.
+///
+/// # Rounding
+/// Conversions always round half up (i.e. `x.5` rounds towards positive infinity). Rectangles are
+/// converted by rounding their edges rather than their sizes, so two rectangles that share an edge
+/// in logical pixels still share an edge in device pixels: there is never an overlap or a seam
+/// between adjacent tiles after conversion.
+///
+/// [output]: Output
+/// [window gap]: crate::layout::LayoutSettings::window_gap
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(into = "f64", try_from = "f64")]
+pub struct Scale(f64);
+
+impl Default for Scale {
+	#[inline]
+	fn default() -> Self {
+		Self::IDENTITY
+	}
+}
+
+impl Scale {
+	/// A scale factor of `1`: logical pixels are device pixels.
+	pub const IDENTITY: Self = Self(1.0);
+
+	/// The DPI for which a scale factor of `1` is intended.
+	pub const BASE_DPI: f64 = 96.0;
+
+	/// Creates a new scale factor.
+	///
+	/// Returns [`None`] if the `factor` is not a finite number greater than zero.
+	pub fn new(factor: f64) -> Option<Self> {
+		(factor.is_finite() && factor > 0.0).then_some(Self(factor))
+	}
+
+	/// Derives a scale factor from an output's physical dimensions.
+	///
+	/// The factor is the output's DPI relative to [`BASE_DPI`], rounded to the nearest quarter and
+	/// never less than `1`. If the physical size is unknown (i.e. `0`), the [identity] scale is
+	/// returned.
+	///
+	/// [`BASE_DPI`]: Self::BASE_DPI
+	/// [identity]: Self::IDENTITY
+	pub fn from_physical(width: u32, width_mm: u32) -> Self {
+		if width == 0 || width_mm == 0 {
+			return Self::IDENTITY;
+		}
+
+		const MM_PER_INCH: f64 = 25.4;
+
+		let dpi = (width as f64) / ((width_mm as f64) / MM_PER_INCH);
+		let factor = ((dpi / Self::BASE_DPI) * 4.0).round() / 4.0;
+
+		Self(factor.max(1.0))
+	}
+
+	/// Returns the scale factor as a floating point number.
+	#[inline(always)]
+	pub const fn factor(&self) -> f64 {
+		self.0
+	}
+
+	/// Converts a logical `size` to device pixels, rounding half up.
+	#[inline]
+	pub fn to_device(&self, size: u32) -> u32 {
+		((size as f64) * self.0 + 0.5).floor() as u32
+	}
+
+	/// Converts a logical `coord` to device pixels, rounding half up.
+	#[inline]
+	pub fn to_device_coord(&self, coord: i32) -> i32 {
+		((coord as f64) * self.0 + 0.5).floor() as i32
+	}
+
+	/// Converts a `coord` in device pixels to logical pixels, rounding to the nearest pixel.
+	#[inline]
+	pub fn to_logical_coord(&self, coord: i32) -> i32 {
+		((coord as f64) / self.0).round() as i32
+	}
+
+	/// Converts a logical rectangle to device pixels.
+	///
+	/// The edges of the rectangle are converted, rather than its dimensions, so that adjacent
+	/// rectangles remain adjacent after conversion.
+	pub fn rect_to_device(&self, x: i32, y: i32, width: u32, height: u32) -> (i32, i32, u32, u32) {
+		let (left, top) = (self.to_device_coord(x), self.to_device_coord(y));
+		let (right, bottom) = (
+			self.to_device_coord(x + (width as i32)),
+			self.to_device_coord(y + (height as i32)),
+		);
+
+		(left, top, (right - left) as u32, (bottom - top) as u32)
+	}
+}
+
+impl From<Scale> for f64 {
+	#[inline(always)]
+	fn from(scale: Scale) -> Self {
+		scale.0
+	}
+}
+
+impl TryFrom<f64> for Scale {
+	type Error = ParseScaleError;
+
+	fn try_from(factor: f64) -> Result<Self, Self::Error> {
+		Self::new(factor).ok_or(ParseScaleError::OutOfRange)
+	}
+}
+
+impl fmt::Display for Scale {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.0)
+	}
+}
+
+/// An error returned when parsing a [`Scale`] or [`ScaleOverride`] fails.
+#[derive(Debug, Error)]
+pub enum ParseScaleError {
+	#[error(transparent)]
+	Float(#[from] ParseFloatError),
+
+	/// The scale factor was not a finite number greater than zero.
+	#[error("scale factors must be greater than zero")]
+	OutOfRange,
+}
+
+impl FromStr for Scale {
+	type Err = ParseScaleError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::new(s.trim().parse()?).ok_or(ParseScaleError::OutOfRange)
+	}
+}
+
+/// A [scale factor] explicitly given for an [output] (or for every output), overriding the scale
+/// factor derived from its physical dimensions.
+///
+/// This is parsed from `OUTPUT=FACTOR` (e.g. `HDMI-1=1.5`) or just `FACTOR` to apply to every
+/// output without a more specific override.
+///
+/// [scale factor]: Scale
+/// [output]: Output
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScaleOverride {
+	/// The name of the [output] this override applies to, or [`None`] if it applies to all outputs.
+	///
+	/// [output]: Output
+	pub output: Option<String>,
+	pub scale: Scale,
+}
+
+impl FromStr for ScaleOverride {
+	type Err = ParseScaleError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.rsplit_once('=') {
+			Some((output, scale)) => Ok(Self {
+				output: Some(output.trim().to_owned()),
+				scale: scale.parse()?,
+			}),
+
+			None => Ok(Self {
+				output: None,
+				scale: s.parse()?,
+			}),
+		}
+	}
+}
+
+impl ScaleOverride {
+	/// Returns the overridden scale factor for the output of the given `name` from the given
+	/// `overrides`, if there is one.
+	///
+	/// An override naming the output takes precedence over one that applies to all outputs. If
+	/// there are multiple matching overrides, the last one is used.
+	pub fn find(overrides: &[Self], name: &str) -> Option<Scale> {
+		let named = overrides
+			.iter()
+			.rev()
+			.find(|scale| scale.output.as_deref() == Some(name));
+
+		named
+			.or_else(|| overrides.iter().rev().find(|scale| scale.output.is_none()))
+			.map(|scale| scale.scale)
+	}
+}
+
+/// The fraction of the smaller of two monitors which must be overlapped by the other for them to
+/// be treated as [clones] of each other, from `0` (exclusive) to `1` (inclusive).
+///
+/// [clones]: Output::is_clone_of
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CloneOverlap(f64);
+
+impl Default for CloneOverlap {
+	#[inline]
+	fn default() -> Self {
+		Self::DEFAULT
+	}
+}
+
+impl CloneOverlap {
+	/// Monitors are clones if nine tenths of the smaller of them is overlapped.
+	///
+	/// This allows for mirrored monitors whose resolutions have slightly different aspect ratios.
+	pub const DEFAULT: Self = Self(0.9);
+
+	/// Creates a new clone overlap threshold.
+	///
+	/// Returns [`None`] if the `threshold` is not greater than `0` and at most `1`.
+	pub fn new(threshold: f64) -> Option<Self> {
+		(threshold > 0.0 && threshold <= 1.0).then_some(Self(threshold))
+	}
+
+	/// Returns the threshold as a floating point number.
+	#[inline(always)]
+	pub const fn get(&self) -> f64 {
+		self.0
+	}
+}
+
+impl fmt::Display for CloneOverlap {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.0)
+	}
+}
+
+/// An error returned when parsing a [`CloneOverlap`] fails.
+#[derive(Debug, Error)]
+pub enum ParseCloneOverlapError {
+	#[error(transparent)]
+	Float(#[from] ParseFloatError),
+
+	/// The threshold was not greater than `0` and at most `1`.
+	#[error("clone overlaps must be greater than 0 and at most 1")]
+	OutOfRange,
+}
+
+impl FromStr for CloneOverlap {
+	type Err = ParseCloneOverlapError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::new(s.trim().parse()?).ok_or(ParseCloneOverlapError::OutOfRange)
+	}
+}
+
+/// The policy for choosing which [output] a new window opens on.
+///
+/// Whichever policy is used, the [primary output] is used as a fallback when the policy's output
+/// cannot be determined (e.g. there is no focused window, or a pinned output is disconnected).
+///
+/// [output]: Output
+/// [primary output]: Output::primary
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub enum OutputAssignment {
+	/// New windows open on the output containing the focused window.
+	#[default]
+	FollowFocus,
+	/// New windows open on the output containing the pointer.
+	FollowPointer,
+	/// New windows always open on the primary output.
+	Primary,
+
+	/// Workspaces are pinned to the outputs of the given names, and new windows open on their
+	/// workspace's output.
+	///
+	/// While a pinned output is disconnected, its workspaces fall back to the primary output. New
+	/// windows on workspaces that aren't pinned open on the output containing the focused window.
+	Pinned(HashMap<u32, String>),
+}
+
+/// The state used by an [`OutputAssignment`] to choose an [output].
+///
+/// [output]: Output
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Routing<'outputs> {
+	/// The currently connected outputs.
+	pub outputs: &'outputs [Output],
+	/// The workspace the new window is opened on.
+	pub workspace: u32,
+
+	/// The center of the focused window, if any window is focused.
+	pub focus: Option<(i32, i32)>,
+	/// The position of the pointer, if known.
+	pub pointer: Option<(i32, i32)>,
+}
+
+/// A pinned workspace which has moved from one [output] to another because its pinned output was
+/// disconnected or reconnected.
+///
+/// [output]: Output
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Migration {
+	pub workspace: u32,
+
+	/// The name of the output the workspace was on.
+	pub from: String,
+	/// The name of the output the workspace is now on.
+	pub to: String,
+}
+
+impl OutputAssignment {
+	/// Chooses the [output] on which a new window is opened.
+	///
+	/// Returns [`None`] only if there are no outputs.
+	///
+	/// [output]: Output
+	pub fn route<'outputs>(&self, routing: Routing<'outputs>) -> Option<&'outputs Output> {
+		let Routing {
+			outputs,
+			workspace,
+			focus,
+			pointer,
+		} = routing;
+
+		let at = |point: Option<(i32, i32)>| point.and_then(|(x, y)| Output::at(outputs, x, y));
+
+		match self {
+			Self::FollowFocus => at(focus),
+			Self::FollowPointer => at(pointer),
+			Self::Primary => None,
+
+			Self::Pinned(_) => match self.pinned_output(outputs, workspace) {
+				Some(output) => Some(output),
+				None => at(focus),
+			},
+		}
+		.or_else(|| Output::primary(outputs))
+	}
+
+	/// Returns the [output] on which the given pinned `workspace` is displayed.
+	///
+	/// If the `workspace` is pinned to an output which is disconnected, the [primary output] is
+	/// returned. Returns [`None`] if the `workspace` is not pinned.
+	///
+	/// [output]: Output
+	/// [primary output]: Output::primary
+	pub fn pinned_output<'outputs>(&self, outputs: &'outputs [Output], workspace: u32) -> Option<&'outputs Output> {
+		let Self::Pinned(pins) = self else {
+			return None;
+		};
+
+		let name = pins.get(&workspace)?;
+
+		Output::named(outputs, name).or_else(|| Output::primary(outputs))
+	}
+
+	/// Returns the pinned workspaces which move to a different [output] when the connected outputs
+	/// change from `old` to `new`, ordered by workspace.
+	///
+	/// [output]: Output
+	pub fn migrations(&self, old: &[Output], new: &[Output]) -> Vec<Migration> {
+		let Self::Pinned(pins) = self else {
+			return Vec::new();
+		};
+
+		let mut workspaces: Vec<_> = pins.keys().copied().collect();
+		workspaces.sort_unstable();
+
+		workspaces
+			.into_iter()
+			.filter_map(|workspace| {
+				let from = self.pinned_output(old, workspace)?;
+				let to = self.pinned_output(new, workspace)?;
+
+				(from.name != to.name).then(|| Migration {
+					workspace,
+
+					from: from.name.clone(),
+					to: to.name.clone(),
+				})
+			})
+			.collect()
+	}
+}
+
+impl Migration {
+	/// Returns the names of the outputs affected by the given `migrations`, each only once.
+	///
+	/// Each affected output needs to be re-tiled once, however many workspaces moved to or from it.
+	pub fn affected_outputs(migrations: &[Self]) -> Vec<&str> {
+		let mut outputs = Vec::new();
+
+		for migration in migrations {
+			for name in [&migration.from, &migration.to] {
+				if !outputs.contains(&name.as_str()) {
+					outputs.push(name.as_str());
+				}
+			}
+		}
+
+		outputs
+	}
+}
+
+/// A change to which monitors are [grouped] into one output as clones of each other, as when
+/// monitors start or stop mirroring each other.
+///
+/// [grouped]: Output::group_clones
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum CloneChange {
+	/// The monitors named `clones` started mirroring the `output`, which they are now part of.
+	Grouped { output: String, clones: Vec<String> },
+	/// The monitors named `clones` stopped mirroring the `output`, which keeps its layout, and are
+	/// now outputs of their own.
+	Split { output: String, clones: Vec<String> },
+}
+
+impl CloneChange {
+	/// Returns the changes to which monitors are clones when the connected outputs change from
+	/// `old` to `new`: first the groups which were split up, then the groups which were formed,
+	/// each in the order of their outputs.
+	pub fn between(old: &[Output], new: &[Output]) -> Vec<Self> {
+		let split = old.iter().filter_map(|output| {
+			// Clones which are now outputs of their own.
+			let clones: Vec<_> = output
+				.clones
+				.iter()
+				.filter(|clone| Output::exactly_named(new, clone).is_some())
+				.cloned()
+				.collect();
+
+			(!clones.is_empty()).then(|| Self::Split {
+				output: output.name.clone(),
+				clones,
+			})
+		});
+
+		let grouped = new.iter().filter_map(|output| {
+			let previous = Output::exactly_named(old, &output.name).map_or(&[][..], |output| &output.clones);
+			// Clones which weren't already part of this output.
+			let clones: Vec<_> = output
+				.clones
+				.iter()
+				.filter(|clone| !previous.contains(clone))
+				.cloned()
+				.collect();
+
+			(!clones.is_empty()).then(|| Self::Grouped {
+				output: output.name.clone(),
+				clones,
+			})
+		});
+
+		split.chain(grouped).collect()
+	}
+}
+
+/// An error returned when parsing a workspace [pin] fails.
+///
+/// [pin]: OutputAssignment::Pinned
+#[derive(Debug, Error)]
+pub enum ParsePinError {
+	/// The pin was not of the form `WORKSPACE=OUTPUT`.
+	#[error("expected `WORKSPACE=OUTPUT`")]
+	MissingOutput,
+
+	#[error("invalid workspace number: {0}")]
+	Workspace(#[from] std::num::ParseIntError),
+}
+
+/// Parses a workspace [pin] of the form `WORKSPACE=OUTPUT` (e.g. `1=HDMI-1`).
+///
+/// [pin]: OutputAssignment::Pinned
+pub fn parse_pin(s: &str) -> Result<(u32, String), ParsePinError> {
+	let (workspace, output) = s.split_once('=').ok_or(ParsePinError::MissingOutput)?;
+
+	Ok((workspace.trim().parse()?, output.trim().to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const AWKWARD_SCALES: [f64; 4] = [1.25, 1.5, 1.75, 2.25];
+
+	#[test]
+	fn rounding() {
+		let scale = Scale::new(1.5).unwrap();
+
+		// 3 * 1.5 = 4.5, which rounds up.
+		assert_eq!(scale.to_device(3), 5);
+		assert_eq!(scale.to_device(15), 23);
+		assert_eq!(scale.to_device_coord(-3), -4);
+
+		let scale = Scale::new(1.25).unwrap();
+
+		// 2 * 1.25 = 2.5, which rounds up; 6 * 1.25 = 7.5, which rounds up.
+		assert_eq!(scale.to_device(2), 3);
+		assert_eq!(scale.to_device(6), 8);
+		assert_eq!(scale.to_device(15), 19);
+	}
+
+	/// Tests that adjacent logical rectangles neither overlap nor leave a seam after conversion.
+	#[test]
+	fn seam_free_rects() {
+		for factor in AWKWARD_SCALES {
+			let scale = Scale::new(factor).unwrap();
+
+			// A row of tiles with awkward sizes, each starting where the previous one ends.
+			let mut x = 7;
+			let mut previous_right = None;
+
+			for width in [333, 1, 2, 17, 499, 3] {
+				let (left, _, device_width, _) = scale.rect_to_device(x, 0, width, 10);
+
+				if let Some(previous_right) = previous_right {
+					assert_eq!(left, previous_right, "seam or overlap at scale {factor}");
+				}
+
+				previous_right = Some(left + (device_width as i32));
+				x += width as i32;
+			}
+
+			// The total converted width is the converted total width.
+			let (left, ..) = scale.rect_to_device(7, 0, 0, 0);
+			assert_eq!(
+				previous_right.unwrap() - left,
+				scale.rect_to_device(7, 0, 855, 0).2 as i32
+			);
+		}
+	}
+
+	#[test]
+	fn from_physical() {
+		// 1920 pixels across 508mm (20 inches) is 96 DPI.
+		assert_eq!(Scale::from_physical(1920, 508), Scale::IDENTITY);
+		// 3840 pixels across the same width is 192 DPI.
+		assert_eq!(Scale::from_physical(3840, 508).factor(), 2.0);
+		// 2880 pixels across the same width is 144 DPI.
+		assert_eq!(Scale::from_physical(2880, 508).factor(), 1.5);
+
+		// Unknown physical sizes and low DPIs use the identity scale.
+		assert_eq!(Scale::from_physical(1920, 0), Scale::IDENTITY);
+		assert_eq!(Scale::from_physical(1024, 508), Scale::IDENTITY);
+	}
+
+	#[test]
+	fn overrides() {
+		let overrides: Vec<ScaleOverride> = ["1.25", "HDMI-1=1.5", "eDP-1 = 2"]
+			.into_iter()
+			.map(|s| s.parse().unwrap())
+			.collect();
+
+		assert_eq!(ScaleOverride::find(&overrides, "HDMI-1"), Scale::new(1.5));
+		assert_eq!(ScaleOverride::find(&overrides, "eDP-1"), Scale::new(2.0));
+		assert_eq!(ScaleOverride::find(&overrides, "DP-2"), Scale::new(1.25));
+		assert_eq!(ScaleOverride::find(&[], "DP-2"), None);
+
+		assert!("HDMI-1=0".parse::<ScaleOverride>().is_err());
+		assert!("HDMI-1=big".parse::<ScaleOverride>().is_err());
+	}
+
+	fn output(name: &str, x: i32, primary: bool) -> Output {
+		Output {
+			name: name.to_owned(),
+
+			x,
+			y: 0,
+
+			width: 1920,
+			height: 1080,
+
+			primary,
+			scale: Scale::IDENTITY,
+
+			clones: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn routing() {
+		// DP-1 is to the left of the primary HDMI-1.
+		let outputs = [output("DP-1", 0, false), output("HDMI-1", 1920, true)];
+		let routing = Routing {
+			outputs: &outputs,
+			workspace: 1,
+
+			focus: Some((100, 100)),
+			pointer: Some((2000, 100)),
+		};
+		let route = |assignment: &OutputAssignment, routing| assignment.route(routing).map(|output| &*output.name);
+
+		assert_eq!(route(&OutputAssignment::FollowFocus, routing), Some("DP-1"));
+		assert_eq!(route(&OutputAssignment::FollowPointer, routing), Some("HDMI-1"));
+		assert_eq!(route(&OutputAssignment::Primary, routing), Some("HDMI-1"));
+
+		// Without a focused window, or with the focus outside every output, the primary is used.
+		let unfocused = Routing { focus: None, ..routing };
+		let offscreen = Routing {
+			focus: Some((-5000, 0)),
+			..routing
+		};
+		assert_eq!(route(&OutputAssignment::FollowFocus, unfocused), Some("HDMI-1"));
+		assert_eq!(route(&OutputAssignment::FollowFocus, offscreen), Some("HDMI-1"));
+
+		let pinned = OutputAssignment::Pinned(HashMap::from([(2, "DP-1".to_owned()), (3, "DP-2".to_owned())]));
+
+		// A workspace pinned to a connected output uses it...
+		let workspace_2 = Routing {
+			workspace: 2,
+			focus: Some((2000, 100)),
+			..routing
+		};
+		assert_eq!(route(&pinned, workspace_2), Some("DP-1"));
+		// ...one pinned to a disconnected output falls back to the primary...
+		assert_eq!(
+			route(
+				&pinned,
+				Routing {
+					workspace: 3,
+					..routing
+				}
+			),
+			Some("HDMI-1")
+		);
+		// ...and an unpinned workspace follows focus.
+		assert_eq!(route(&pinned, routing), Some("DP-1"));
+
+		assert_eq!(
+			OutputAssignment::Primary.route(Routing {
+				outputs: &[],
+				..routing
+			}),
+			None
+		);
+	}
+
+	#[test]
+	fn migrations() {
+		let pinned = OutputAssignment::Pinned(HashMap::from([
+			(1, "HDMI-1".to_owned()),
+			(2, "DP-1".to_owned()),
+			(3, "DP-1".to_owned()),
+		]));
+
+		let connected = [output("DP-1", 0, false), output("HDMI-1", 1920, true)];
+		let disconnected = [output("HDMI-1", 1920, true)];
+
+		let migrations = pinned.migrations(&connected, &disconnected);
+		assert_eq!(
+			migrations,
+			[
+				Migration {
+					workspace: 2,
+					from: "DP-1".to_owned(),
+					to: "HDMI-1".to_owned(),
+				},
+				Migration {
+					workspace: 3,
+					from: "DP-1".to_owned(),
+					to: "HDMI-1".to_owned(),
+				},
+			]
+		);
+		// Both workspaces moved between the same two outputs, so each is only re-tiled once.
+		assert_eq!(Migration::affected_outputs(&migrations), ["DP-1", "HDMI-1"]);
+
+		// When DP-1 returns, its workspaces migrate back.
+		let migrations = pinned.migrations(&disconnected, &connected);
+		assert_eq!(migrations.len(), 2);
+		assert!(migrations.iter().all(|migration| migration.to == "DP-1"));
+
+		assert!(OutputAssignment::Primary
+			.migrations(&connected, &disconnected)
+			.is_empty());
+	}
+
+	fn output_at(name: &str, (x, y): (i32, i32), (width, height): (u32, u32)) -> Output {
+		Output {
+			name: name.to_owned(),
+
+			x,
+			y,
+
+			width,
+			height,
+
+			primary: false,
+			scale: Scale::IDENTITY,
+
+			clones: Vec::new(),
+		}
+	}
+
+	/// Returns the name of the output in the given `direction` from the output named `from`.
+	fn neighbour<'outputs>(
+		outputs: &'outputs [Output],
+		from: &str,
+		direction: Direction,
+		edges: OutputEdges,
+	) -> Option<&'outputs str> {
+		let from = Output::named(outputs, from).unwrap();
+
+		Output::in_direction(outputs, from, direction, edges).map(|output| &*output.name)
+	}
+
+	/// Tests directional output resolution for an L-shaped arrangement of outputs.
+	#[test]
+	fn l_shaped_directions() {
+		// ┌──────┐
+		// │  A   │
+		// ├──────┼──────┬──────┐
+		// │  B   │  C   │  D   │
+		// └──────┴──────┴──────┘
+		let outputs = [
+			output_at("A", (0, 0), (1920, 1080)),
+			output_at("B", (0, 1080), (1920, 1080)),
+			output_at("C", (1920, 1080), (1920, 1080)),
+			output_at("D", (3840, 1080), (1920, 1080)),
+		];
+		let clamp = |from, direction| neighbour(&outputs, from, direction, OutputEdges::Clamp);
+		let wrap = |from, direction| neighbour(&outputs, from, direction, OutputEdges::Wrap);
+
+		assert_eq!(clamp("A", Direction::Down), Some("B"));
+		assert_eq!(clamp("B", Direction::Up), Some("A"));
+		assert_eq!(clamp("B", Direction::Right), Some("C"));
+		assert_eq!(clamp("C", Direction::Right), Some("D"));
+		// C's center is below and to the right of A's, so the nearest output up from it is A.
+		assert_eq!(clamp("C", Direction::Up), Some("A"));
+		// B is directly below A, so C is the nearest output to its right.
+		assert_eq!(clamp("A", Direction::Right), Some("C"));
+
+		// Nothing is to the left of A or B.
+		assert_eq!(clamp("A", Direction::Left), None);
+		assert_eq!(clamp("D", Direction::Right), None);
+		assert_eq!(clamp("D", Direction::Down), None);
+
+		// Wrapping moves to the furthest output in the opposite direction, preferring those in line.
+		assert_eq!(wrap("D", Direction::Right), Some("B"));
+		assert_eq!(wrap("B", Direction::Left), Some("D"));
+		assert_eq!(wrap("B", Direction::Down), Some("A"));
+		// Nothing is below D, so it wraps to the only output above it.
+		assert_eq!(wrap("D", Direction::Down), Some("A"));
+	}
+
+	/// Tests directional output resolution for vertically stacked outputs of different sizes.
+	#[test]
+	fn stacked_directions() {
+		// ┌──────────┐
+		// │   top    │
+		// └┬────────┬┘
+		//  │ middle │
+		// ┌┴────────┴──┐
+		// │   bottom   │
+		// └────────────┘
+		let outputs = [
+			output_at("top", (0, 0), (2560, 1440)),
+			output_at("middle", (320, 1440), (1920, 1080)),
+			output_at("bottom", (-240, 2520), (3000, 1200)),
+		];
+		let clamp = |from, direction| neighbour(&outputs, from, direction, OutputEdges::Clamp);
+		let wrap = |from, direction| neighbour(&outputs, from, direction, OutputEdges::Wrap);
+
+		assert_eq!(clamp("top", Direction::Down), Some("middle"));
+		assert_eq!(clamp("middle", Direction::Down), Some("bottom"));
+		assert_eq!(clamp("bottom", Direction::Up), Some("middle"));
+		assert_eq!(clamp("bottom", Direction::Down), None);
+
+		assert_eq!(wrap("bottom", Direction::Down), Some("top"));
+		assert_eq!(wrap("top", Direction::Up), Some("bottom"));
+
+		// Any output whose center is even slightly to the left is in that direction.
+		assert_eq!(clamp("middle", Direction::Left), Some("bottom"));
+		assert_eq!(clamp("top", Direction::Left), Some("bottom"));
+		assert_eq!(clamp("top", Direction::Right), None);
+		assert_eq!(clamp("middle", Direction::Right), None);
+	}
+
+	/// Tests that the active output follows explicit focus and the pointer, with explicit focus
+	/// taking precedence until focus next follows the pointer.
+	#[test]
+	fn active_output() {
+		let outputs = [output("DP-1", 0, false), output("HDMI-1", 1920, true)];
+		let mut active = ActiveOutput::default();
+
+		// The primary output is active until another is made active.
+		assert_eq!(active.get(&outputs).map(|output| &*output.name), Some("HDMI-1"));
+
+		assert!(active.pointer_crossed("DP-1"));
+		assert_eq!(active.name(), Some("DP-1"));
+
+		// Explicitly focusing an output means the pointer crossing outputs is ignored...
+		active.focus("HDMI-1", FocusSource::Explicit);
+		assert!(!active.pointer_crossed("DP-1"));
+		assert_eq!(active.name(), Some("HDMI-1"));
+
+		// ...until focus next follows the pointer.
+		active.focus("DP-1", FocusSource::Pointer);
+		assert!(active.pointer_crossed("HDMI-1"));
+		assert_eq!(active.name(), Some("HDMI-1"));
+
+		// A disconnected active output falls back to the primary output.
+		active.focus("DP-2", FocusSource::Explicit);
+		assert_eq!(active.get(&outputs).map(|output| &*output.name), Some("HDMI-1"));
+	}
+
+	#[test]
+	fn default_orientation() {
+		let by_aspect = DefaultOrientation::ByAspect {
+			landscape: Orientation::LeftToRight,
+			portrait: Orientation::TopToBottom,
+		};
+
+		assert_eq!(by_aspect.choose(1920, 1080), Orientation::LeftToRight);
+		assert_eq!(by_aspect.choose(1080, 1920), Orientation::TopToBottom);
+		// Square outputs count as landscape.
+		assert_eq!(by_aspect.choose(1200, 1200), Orientation::LeftToRight);
+
+		let fixed = DefaultOrientation::Fixed(Orientation::RightToLeft);
+		assert_eq!(fixed.choose(1080, 1920), Orientation::RightToLeft);
+	}
+
+	#[test]
+	fn rotated_outputs() {
+		let policy = DefaultOrientation::ByAspect {
+			landscape: Orientation::LeftToRight,
+			portrait: Orientation::TopToBottom,
+		};
+		let landscape = output("HDMI-1", 0, true);
+		let portrait = Output {
+			width: 1080,
+			height: 1920,
+			..landscape.clone()
+		};
+
+		// Rotating a monitor only reorients its layout if asked to...
+		assert_eq!(policy.rotations(Some(&landscape), Some(&portrait), false), None);
+		assert_eq!(policy.rotations(Some(&landscape), Some(&portrait), true), Some(1));
+		assert_eq!(policy.rotations(Some(&portrait), Some(&landscape), true), Some(-1));
+
+		// ...but a new portrait primary output is always oriented for it.
+		let rotated = Output {
+			name: "DP-1".to_owned(),
+			..portrait.clone()
+		};
+		assert_eq!(policy.rotations(Some(&landscape), Some(&rotated), false), Some(1));
+
+		assert_eq!(policy.rotations(Some(&landscape), Some(&landscape), true), None);
+		assert_eq!(policy.rotations(None, Some(&portrait), true), None);
+	}
+
+	#[test]
+	fn pins() {
+		assert_eq!(parse_pin("1=HDMI-1").unwrap(), (1, "HDMI-1".to_owned()));
+		assert_eq!(parse_pin(" 2 = DP-1").unwrap(), (2, "DP-1".to_owned()));
+
+		assert!(parse_pin("HDMI-1").is_err());
+		assert!(parse_pin("one=HDMI-1").is_err());
+	}
+
+	/// Returns the names of the given `outputs`, each with the names of its clones.
+	fn clone_groups(outputs: &[Output]) -> Vec<(&str, Vec<&str>)> {
+		outputs
+			.iter()
+			.map(|output| (&*output.name, output.clones.iter().map(String::as_str).collect()))
+			.collect()
+	}
+
+	#[test]
+	fn clone_grouping() {
+		let threshold = CloneOverlap::DEFAULT;
+		let laptop = Output {
+			primary: true,
+			..output_at("eDP-1", (0, 0), (1366, 768))
+		};
+
+		// A projector mirroring the laptop's screen at a higher resolution is part of the laptop's
+		// output, which takes the projector's size.
+		let projector = output_at("HDMI-1", (0, 0), (1920, 1080));
+		let mirrored = Output::group_clones(vec![projector.clone(), laptop.clone()], threshold);
+		assert_eq!(clone_groups(&mirrored), [("eDP-1", vec!["HDMI-1"])]);
+		assert_eq!((mirrored[0].width, mirrored[0].height), (1920, 1080));
+		assert!(mirrored[0].primary);
+		assert_eq!(
+			Output::named(&mirrored, "HDMI-1").map(|output| &*output.name),
+			Some("eDP-1")
+		);
+
+		// Extended monitors aren't clones.
+		let extended = Output::group_clones(
+			vec![laptop.clone(), output_at("HDMI-1", (1366, 0), (1920, 1080))],
+			threshold,
+		);
+		assert_eq!(clone_groups(&extended), [("eDP-1", vec![]), ("HDMI-1", vec![])]);
+
+		// Monitors which only partly overlap are clones if enough of the smaller one is overlapped.
+		let left = output_at("DP-1", (0, 0), (1920, 1080));
+		let half = output_at("DP-2", (960, 0), (1920, 1080));
+		let nearly = output_at("DP-2", (96, 0), (1920, 1080));
+
+		assert_eq!(Output::group_clones(vec![left.clone(), half], threshold).len(), 2);
+		assert_eq!(
+			clone_groups(&Output::group_clones(vec![left.clone(), nearly.clone()], threshold)),
+			[("DP-1", vec!["DP-2"])]
+		);
+		assert_eq!(
+			Output::group_clones(vec![left, nearly], CloneOverlap::new(0.99).unwrap()).len(),
+			2
+		);
+
+		// Three monitors mirroring each other are one output, even if one only bridges the other
+		// two.
+		let three = Output::group_clones(
+			vec![
+				output_at("DP-1", (0, 0), (100, 100)),
+				output_at("DP-2", (200, 0), (100, 100)),
+				output_at("DP-3", (0, 0), (300, 100)),
+			],
+			threshold,
+		);
+		assert_eq!(clone_groups(&three), [("DP-1", vec!["DP-2", "DP-3"])]);
+		assert_eq!((three[0].width, three[0].height), (300, 100));
+
+		assert!(CloneOverlap::new(0.0).is_none());
+		assert!("1.5".parse::<CloneOverlap>().is_err());
+		assert_eq!("0.75".parse::<CloneOverlap>().ok(), CloneOverlap::new(0.75));
+	}
+
+	#[test]
+	fn clone_changes() {
+		let threshold = CloneOverlap::DEFAULT;
+		let laptop = Output {
+			primary: true,
+			..output_at("eDP-1", (0, 0), (1920, 1080))
+		};
+
+		let mirrored = Output::group_clones(
+			vec![laptop.clone(), output_at("HDMI-1", (0, 0), (1920, 1080))],
+			threshold,
+		);
+		let extended = Output::group_clones(
+			vec![laptop.clone(), output_at("HDMI-1", (1920, 0), (1920, 1080))],
+			threshold,
+		);
+
+		// Switching to extended mode splits the projector back out of the laptop's output, which
+		// keeps its layout...
+		assert_eq!(
+			CloneChange::between(&mirrored, &extended),
+			[CloneChange::Split {
+				output: "eDP-1".to_owned(),
+				clones: vec!["HDMI-1".to_owned()],
+			}]
+		);
+		// ...and mirroring again makes it part of the laptop's output.
+		assert_eq!(
+			CloneChange::between(&extended, &mirrored),
+			[CloneChange::Grouped {
+				output: "eDP-1".to_owned(),
+				clones: vec!["HDMI-1".to_owned()],
+			}]
+		);
+		assert!(CloneChange::between(&mirrored, &mirrored).is_empty());
+
+		// A workspace pinned to the projector follows it into the laptop's output and back out.
+		let pinned = OutputAssignment::Pinned(HashMap::from([(2, "HDMI-1".to_owned())]));
+		assert_eq!(
+			pinned.migrations(&extended, &mirrored),
+			[Migration {
+				workspace: 2,
+				from: "HDMI-1".to_owned(),
+				to: "eDP-1".to_owned(),
+			}]
+		);
+		assert_eq!(pinned.migrations(&mirrored, &extended).len(), 1);
+	}
+}
@@ -0,0 +1,385 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Verifying the process IDs which windows advertise before anything acts on them.
+//!
+//! Clients advertise the process which owns each of their windows in its `_NET_WM_PID` property,
+//! which is how the windows of programs AquariWM launched are [matched to their launches]. A
+//! process ID only means something on the machine the client runs on, though, which isn't
+//! AquariWM's when the client's display is forwarded over SSH, and clients can advertise any
+//! process ID they like. A window's process ID is only [verified] if:
+//!
+//! 1. the window's `WM_CLIENT_MACHINE` property names the [local host], with or without its domain;
+//! 2. a process with that ID exists;
+//! 3. that process [started] no later than the window was managed: a process which started since
+//!    can't have created the window, so it must have been given the ID of the window's process
+//!    after that exited.
+//!
+//! Features which act on process IDs only ever use [verified process IDs]; windows whose process
+//! IDs can't be verified are treated as though they advertised none.
+//!
+//! Like [`launch`], this reads `/proc`, so process IDs can't be verified where it isn't available.
+//!
+//! [matched to their launches]: crate::launch
+//! [verified]: verify
+//! [local host]: Host
+//! [started]: start_time
+//! [verified process IDs]: VerifiedPid
+//! [`launch`]: crate::launch
+
+use std::{
+	fmt::{self, Display, Formatter},
+	fs,
+	io,
+	sync::OnceLock,
+};
+
+use thiserror::Error;
+
+/// How many clock ticks `/proc` counts times in per second (`USER_HZ`) if the system doesn't say.
+///
+/// This is what Linux uses on almost every architecture.
+const DEFAULT_TICKS_PER_SECOND: u64 = 100;
+
+/// How many clock ticks a process may seem to have started after its window was managed and still
+/// be trusted, allowing for the rounding of both times to whole ticks.
+const START_TOLERANCE: u64 = 1;
+
+/// A window's process ID, if it was [verified].
+///
+/// This can only be created by verifying a process ID, so features given a `VerifiedPid` can't act
+/// on a window's raw `_NET_WM_PID` property by mistake.
+///
+/// [verified]: verify
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct VerifiedPid(Option<u32>);
+
+/// Why a window's process ID couldn't be [verified].
+///
+/// [verified]: verify
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Error)]
+pub enum Unverified {
+	/// The window doesn't advertise a process ID at all.
+	#[error("it has no `_NET_WM_PID` property")]
+	NoPid,
+	/// The window doesn't say which machine its process runs on.
+	#[error("it has no `WM_CLIENT_MACHINE` property to say where process {0} runs")]
+	NoMachine(u32),
+	/// The window's process runs on another machine.
+	#[error("process {pid} runs on {machine:?} rather than on {host}")]
+	Remote { pid: u32, machine: String, host: Host },
+	/// No process with the window's process ID exists.
+	#[error("process {0} doesn't exist")]
+	NoProcess(u32),
+	/// The process with the window's process ID started after the window was managed, so it was
+	/// given the ID of another process which had exited.
+	#[error("process {0} started after the window was managed, so its ID was reused")]
+	Recycled(u32),
+	/// When the window was managed isn't known, as `/proc` isn't available.
+	#[error("process {0} can't be checked without `/proc`")]
+	Unavailable(u32),
+}
+
+/// The name of the machine AquariWM runs on, and its domain, if it has one.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct Host {
+	/// The hostname, which may or may not include the domain.
+	pub name: String,
+	/// The domain, if one is set.
+	pub domain: Option<String>,
+}
+
+impl VerifiedPid {
+	/// Returns the verified process ID, or [`None`] if the window's process ID couldn't be
+	/// verified.
+	#[inline]
+	pub const fn get(self) -> Option<u32> {
+		self.0
+	}
+
+	/// Kills the process with `SIGKILL`, returning whether there was a verified process to kill.
+	///
+	/// # Errors
+	/// Returns an error if the signal couldn't be sent, e.g. because the process had already
+	/// exited.
+	pub fn kill(self) -> io::Result<bool> {
+		let Some(pid) = self.0 else {
+			return Ok(false);
+		};
+
+		let pid = libc::pid_t::try_from(pid)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("{pid} isn't a process ID")))?;
+		// SAFETY: `kill` has no memory safety requirements; it only sends a signal to the process.
+		match unsafe { libc::kill(pid, libc::SIGKILL) } {
+			0 => Ok(true),
+			_ => Err(io::Error::last_os_error()),
+		}
+	}
+}
+
+impl From<&Result<u32, Unverified>> for VerifiedPid {
+	#[inline]
+	fn from(verified: &Result<u32, Unverified>) -> Self {
+		Self(verified.as_ref().ok().copied())
+	}
+}
+
+impl Host {
+	/// Creates a host with the given `hostname` and `domainname`, as reported by the kernel.
+	///
+	/// Domain names which aren't set are reported as `(none)`, or left empty.
+	pub fn new(hostname: &str, domainname: &str) -> Self {
+		let domain = domainname.trim().trim_end_matches('.');
+
+		Self {
+			name: hostname.trim().trim_end_matches('.').to_owned(),
+			domain: (!domain.is_empty() && domain != "(none)").then(|| domain.to_owned()),
+		}
+	}
+
+	/// Returns the host AquariWM runs on, as listed in `/proc`.
+	///
+	/// Its name is empty if `/proc` isn't available, so that no machine matches it.
+	pub fn local() -> Self {
+		let read = |name| fs::read_to_string(format!("/proc/sys/kernel/{name}")).unwrap_or_default();
+
+		Self::new(&read("hostname"), &read("domainname"))
+	}
+
+	/// Returns whether the given `machine`, as given in a window's `WM_CLIENT_MACHINE` property,
+	/// names this host.
+	///
+	/// Names are compared without regard to case, both with and without the domain. Names which
+	/// are both qualified with a domain must match exactly; a name without a domain matches a
+	/// qualified name whose first label it is.
+	pub fn matches(&self, machine: &str) -> bool {
+		let machine = machine.trim().trim_end_matches('.').to_ascii_lowercase();
+		if self.name.is_empty() || machine.is_empty() {
+			return false;
+		}
+
+		let name = self.name.to_ascii_lowercase();
+		let qualified = self
+			.domain
+			.as_ref()
+			.map(|domain| format!("{name}.{}", domain.to_ascii_lowercase()));
+		if machine == name || Some(&machine) == qualified.as_ref() {
+			return true;
+		}
+
+		let short = |name: &str| name.split('.').next().unwrap_or_default().to_owned();
+		let local_qualified = name.contains('.') || qualified.is_some();
+
+		match (machine.contains('.'), local_qualified) {
+			(true, true) => false,
+			_ => short(&machine) == short(&name),
+		}
+	}
+}
+
+impl Display for Host {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match &self.domain {
+			Some(domain) => write!(f, "{}.{domain}", self.name),
+			None => write!(f, "{}", self.name),
+		}
+	}
+}
+
+/// Verifies the process ID a window advertised in its `_NET_WM_PID` property, if any, returning
+/// it if it can be trusted.
+///
+/// `machine` is the window's `WM_CLIENT_MACHINE` property, `host` is the machine AquariWM runs on,
+/// and `managed` is when the window was managed, in clock ticks since boot, as given by
+/// [`uptime`]. `start_time` returns when a process started in clock ticks since boot, such as
+/// [`start_time`], or [`None`] if it doesn't exist.
+pub fn verify(
+	pid: Option<u32>,
+	machine: Option<&str>,
+	host: &Host,
+	managed: Option<u64>,
+	start_time: impl Fn(u32) -> Option<u64>,
+) -> Result<u32, Unverified> {
+	let pid = pid.ok_or(Unverified::NoPid)?;
+	let machine = machine.ok_or(Unverified::NoMachine(pid))?;
+
+	if !host.matches(machine) {
+		return Err(Unverified::Remote {
+			pid,
+			machine: machine.to_owned(),
+			host: host.clone(),
+		});
+	}
+
+	let managed = managed.ok_or(Unverified::Unavailable(pid))?;
+	let started = start_time(pid).ok_or(Unverified::NoProcess(pid))?;
+
+	match started > managed + START_TOLERANCE {
+		true => Err(Unverified::Recycled(pid)),
+		false => Ok(pid),
+	}
+}
+
+/// Returns how many clock ticks `/proc` counts times in per second (`USER_HZ`).
+///
+/// This is read from `sysconf(_SC_CLK_TCK)` the first time it is needed, falling back to 100 if
+/// that fails.
+pub fn ticks_per_second() -> u64 {
+	static TICKS_PER_SECOND: OnceLock<u64> = OnceLock::new();
+
+	*TICKS_PER_SECOND.get_or_init(|| {
+		// SAFETY: `sysconf` has no memory safety requirements; it only reads a configuration value.
+		match u64::try_from(unsafe { libc::sysconf(libc::_SC_CLK_TCK) }) {
+			Ok(ticks) if ticks > 0 => ticks,
+			_ => DEFAULT_TICKS_PER_SECOND,
+		}
+	})
+}
+
+/// Returns how long the machine has been up for, in clock ticks, as listed in `/proc`.
+///
+/// Returns [`None`] if `/proc` isn't available.
+pub fn uptime() -> Option<u64> {
+	parse_uptime(&fs::read_to_string("/proc/uptime").ok()?, ticks_per_second())
+}
+
+/// Returns when the process with the given `pid` started, in clock ticks since boot, as listed in
+/// `/proc`.
+///
+/// Returns [`None`] if the process doesn't exist (anymore), or if `/proc` isn't available.
+pub fn start_time(pid: u32) -> Option<u64> {
+	parse_start_time(&fs::read_to_string(format!("/proc/{pid}/stat")).ok()?)
+}
+
+/// Parses the uptime, in clock ticks of which there are `ticks_per_second`, from the contents of
+/// the `/proc/uptime` file.
+///
+/// The file gives the uptime in seconds, with two decimal places, followed by the time spent idle.
+fn parse_uptime(uptime: &str, ticks_per_second: u64) -> Option<u64> {
+	let uptime = uptime.split_whitespace().next()?;
+	let (seconds, fraction) = uptime.split_once('.').unwrap_or((uptime, ""));
+
+	let hundredths = match fraction.len() {
+		0 => 0,
+		1 => fraction.parse::<u64>().ok()? * 10,
+		_ => fraction.get(..2)?.parse().ok()?,
+	};
+
+	Some(seconds.parse::<u64>().ok()? * ticks_per_second + hundredths * ticks_per_second / 100)
+}
+
+/// Parses when a process started, in clock ticks since boot, from the contents of its
+/// `/proc/<pid>/stat` file.
+///
+/// The start time is the 22nd field. As in [parsing the parent process ID], fields are counted
+/// from the last closing parenthesis, after the process's name.
+///
+/// [parsing the parent process ID]: crate::launch::parent_pid
+fn parse_start_time(stat: &str) -> Option<u64> {
+	let (_, fields) = stat.rsplit_once(')')?;
+
+	// The fields after the name start with the 3rd, the state.
+	fields.split_whitespace().nth(22 - 3)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A `/proc/<pid>/stat` file of a process which started 5000 ticks after boot.
+	const STAT: &str =
+		"4242 (Web Content (1)) S 4000 4000 4000 0 -1 4194560 1000 0 0 0 12 3 0 0 20 0 28 0 5000 1000000 500";
+
+	#[test]
+	fn hostnames() {
+		let host = Host::new("aquarium\n", "example.org\n");
+		assert_eq!(host.to_string(), "aquarium.example.org");
+
+		for machine in ["aquarium", "AQUARIUM", "aquarium.example.org", "aquarium.example.org."] {
+			assert!(host.matches(machine), "{machine}");
+		}
+		for machine in ["aquarium.example.com", "tank", "tank.example.org", "", "aquarium-2"] {
+			assert!(!host.matches(machine), "{machine}");
+		}
+
+		// Without a domain set, a qualified machine name matches by its first label...
+		let host = Host::new("aquarium", "(none)");
+		assert_eq!(host.domain, None);
+		assert!(host.matches("aquarium.lan"));
+		assert!(!host.matches("tank.lan"));
+
+		// ...as does an unqualified one if the hostname itself is qualified.
+		let host = Host::new("aquarium.example.org", "");
+		assert!(host.matches("aquarium"));
+		assert!(host.matches("aquarium.example.org"));
+		assert!(!host.matches("aquarium.example.com"));
+
+		// Nothing matches an unknown host.
+		assert!(!Host::default().matches(""));
+	}
+
+	#[test]
+	fn proc() {
+		assert_eq!(parse_start_time(STAT), Some(5000));
+		assert_eq!(parse_start_time("4242 (bash) S 4000"), None);
+		assert_eq!(parse_start_time(""), None);
+
+		assert_eq!(parse_uptime("12345.67 54321.00\n", 100), Some(1_234_567));
+		assert_eq!(parse_uptime("12.5 1.00", 100), Some(1250));
+		assert_eq!(parse_uptime("12 1", 100), Some(1200));
+		assert_eq!(parse_uptime("12.5 1.00", 1000), Some(12_500));
+		assert_eq!(parse_uptime("twelve", 100), None);
+
+		assert!(ticks_per_second() > 0);
+	}
+
+	#[test]
+	fn verification() {
+		let host = Host::new("aquarium", "example.org");
+		let start_time = |pid| match pid {
+			4242 => parse_start_time(STAT),
+			_ => None,
+		};
+		let verify = |pid, machine, managed| verify(pid, machine, &host, managed, start_time);
+
+		assert_eq!(verify(Some(4242), Some("aquarium"), Some(6000)), Ok(4242));
+		// The start time is allowed to be rounded up past when the window was managed.
+		assert_eq!(verify(Some(4242), Some("aquarium"), Some(4999)), Ok(4242));
+
+		assert_eq!(verify(None, Some("aquarium"), Some(6000)), Err(Unverified::NoPid));
+		assert_eq!(verify(Some(4242), None, Some(6000)), Err(Unverified::NoMachine(4242)));
+		assert!(matches!(
+			verify(Some(4242), Some("tank"), Some(6000)),
+			Err(Unverified::Remote { pid: 4242, .. })
+		));
+		assert_eq!(
+			verify(Some(7), Some("aquarium"), Some(6000)),
+			Err(Unverified::NoProcess(7))
+		);
+		assert_eq!(
+			verify(Some(4242), Some("aquarium"), Some(4000)),
+			Err(Unverified::Recycled(4242))
+		);
+		assert_eq!(
+			verify(Some(4242), Some("aquarium"), None),
+			Err(Unverified::Unavailable(4242))
+		);
+
+		assert_eq!(VerifiedPid::from(&Ok(4242)).get(), Some(4242));
+		assert_eq!(VerifiedPid::from(&Err(Unverified::NoProcess(7))).get(), None);
+	}
+
+	#[test]
+	fn kill() {
+		use std::{os::unix::process::ExitStatusExt, process::Command};
+
+		let mut child = Command::new("sleep").arg("60").spawn().expect("failed to run `sleep`");
+		assert!(VerifiedPid::from(&Ok(child.id())).kill().unwrap());
+		assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGKILL));
+
+		// The process has exited, so there is nothing to kill.
+		assert!(VerifiedPid::from(&Ok(child.id())).kill().is_err());
+		assert!(!VerifiedPid::default().kill().unwrap());
+	}
+}
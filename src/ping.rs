@@ -0,0 +1,369 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Detecting hung clients by pinging their windows.
+//!
+//! Clients which list `_NET_WM_PING` in their windows' `WM_PROTOCOLS` property answer a ping
+//! message sent to one of their windows by sending it back, as long as they are still processing
+//! events. A window is pinged when it is focused, and every window is pinged [periodically] if
+//! that is enabled. If a ping goes unanswered for the [timeout], the window's client is marked
+//! [unresponsive] until it answers it after all.
+//!
+//! The windows of unresponsive clients are shown with a border of a distinct [color], and the
+//! focused window's client can be [force killed], which must be confirmed by asking for it again
+//! within [`KILL_CONFIRMATION`].
+//!
+//! [periodically]: PingSettings::interval
+//! [timeout]: PingSettings::timeout
+//! [unresponsive]: Pings::is_unresponsive
+//! [color]: crate::theme::Slot::HungBorder
+//! [force killed]: crate::action::Action::ForceKillFocused
+
+use std::{
+	collections::{HashMap, HashSet},
+	hash::Hash,
+	time::{Duration, Instant},
+};
+
+/// The default time a ping may go unanswered before the window's client is marked unresponsive.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How soon a [force kill] must be asked for again to be confirmed.
+///
+/// [force kill]: crate::action::Action::ForceKillFocused
+pub const KILL_CONFIRMATION: Duration = Duration::from_secs(2);
+
+/// Settings for how hung clients are detected and dealt with.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct PingSettings {
+	/// How often every window is pinged, or [`None`] if windows are only pinged when they are
+	/// focused.
+	pub interval: Option<Duration>,
+	/// How long a ping may go unanswered before the window's client is marked unresponsive.
+	pub timeout: Duration,
+	/// Whether force killing a client also kills its [verified process] with `SIGKILL`, rather
+	/// than only closing its connection to the display server.
+	///
+	/// [verified process]: crate::pid
+	pub kill_process: bool,
+}
+
+/// The outstanding pings of windows, and which windows' clients are unresponsive.
+///
+/// Times are passed in explicitly rather than read from the clock, so that pings can be driven by
+/// any timer.
+#[derive(Debug, Clone)]
+pub struct Pings<Window> {
+	settings: PingSettings,
+
+	/// The pings which haven't been answered, by the windows they were sent to.
+	outstanding: HashMap<Window, Ping>,
+	/// The windows whose clients haven't answered a ping within the timeout.
+	unresponsive: HashSet<Window>,
+	/// When every window is next pinged, if windows are pinged periodically.
+	next_round: Option<Instant>,
+
+	/// The window whose force kill was asked for, and when, until it is confirmed or expires.
+	kill: Option<(Window, Instant)>,
+}
+
+/// A ping which hasn't been answered.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+struct Ping {
+	/// The timestamp sent with the ping, which is sent back with its answer.
+	timestamp: u32,
+	/// When the ping was sent.
+	sent: Instant,
+}
+
+/// What an answer to a ping meant, as returned by [`Pings::pong`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Pong {
+	/// The answer didn't match an outstanding ping, e.g. because it was for an older ping.
+	Unmatched,
+	/// The ping was answered, and the window's client was already responsive.
+	Answered,
+	/// The ping was answered late, so the window's client is responsive again.
+	Recovered,
+}
+
+/// Whether a [force kill] has been confirmed, as returned by [`Pings::confirm_kill`].
+///
+/// [force kill]: crate::action::Action::ForceKillFocused
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum KillConfirmation {
+	/// The force kill must be asked for again within [`KILL_CONFIRMATION`] to be confirmed.
+	Pending,
+	/// The force kill was asked for twice in a row, and may go ahead.
+	Confirmed,
+}
+
+impl Default for PingSettings {
+	fn default() -> Self {
+		Self {
+			interval: None,
+			timeout: DEFAULT_TIMEOUT,
+			kill_process: false,
+		}
+	}
+}
+
+impl<Window> Pings<Window>
+where
+	Window: Eq + Hash + Clone,
+{
+	/// Creates a record of pings with the given `settings` at the time `now`, with no pings
+	/// outstanding.
+	pub fn new(settings: PingSettings, now: Instant) -> Self {
+		Self {
+			next_round: settings.interval.map(|interval| now + interval),
+			settings,
+
+			outstanding: HashMap::new(),
+			unresponsive: HashSet::new(),
+
+			kill: None,
+		}
+	}
+
+	/// Returns the settings with which pings are sent.
+	#[inline(always)]
+	pub const fn settings(&self) -> &PingSettings {
+		&self.settings
+	}
+
+	/// Replaces the `settings` with which pings are sent at the time `now`, as when the
+	/// configuration is reloaded.
+	///
+	/// Periodic pings are rescheduled if their interval changed.
+	pub fn set_settings(&mut self, settings: PingSettings, now: Instant) {
+		if settings.interval != self.settings.interval {
+			self.next_round = settings.interval.map(|interval| now + interval);
+		}
+
+		self.settings = settings;
+	}
+
+	/// Records a ping with the given server `timestamp` being sent to the given `window` at the
+	/// time `now`, returning whether it should be sent.
+	///
+	/// A window is only pinged once at a time, so that a client which is slow to answer isn't
+	/// flooded with pings: no ping is sent while one is outstanding.
+	pub fn ping(&mut self, window: Window, timestamp: u32, now: Instant) -> bool {
+		if self.outstanding.contains_key(&window) {
+			return false;
+		}
+
+		self.outstanding.insert(window, Ping { timestamp, sent: now });
+
+		true
+	}
+
+	/// Records the answer of the given `window`'s client to the ping with the given `timestamp`.
+	pub fn pong(&mut self, window: &Window, timestamp: u32) -> Pong {
+		match self.outstanding.get(window) {
+			Some(ping) if ping.timestamp == timestamp => {
+				self.outstanding.remove(window);
+
+				match self.unresponsive.remove(window) {
+					true => Pong::Recovered,
+					false => Pong::Answered,
+				}
+			},
+
+			_ => Pong::Unmatched,
+		}
+	}
+
+	/// Marks the clients of the windows whose pings have gone unanswered for the [timeout] as of
+	/// the time `now` as unresponsive, returning the windows which weren't already.
+	///
+	/// [timeout]: PingSettings::timeout
+	pub fn sweep(&mut self, now: Instant) -> Vec<Window> {
+		let timeout = self.settings.timeout;
+		let expired: Vec<_> = self
+			.outstanding
+			.iter()
+			.filter(|(window, ping)| now >= ping.sent + timeout && !self.unresponsive.contains(*window))
+			.map(|(window, _)| window.clone())
+			.collect();
+
+		self.unresponsive.extend(expired.iter().cloned());
+
+		expired
+	}
+
+	/// Returns whether every window is due to be pinged at the time `now`, scheduling the next
+	/// round of pings if so.
+	///
+	/// This is never the case if windows aren't pinged [periodically].
+	///
+	/// [periodically]: PingSettings::interval
+	pub fn round_due(&mut self, now: Instant) -> bool {
+		match (self.next_round, self.settings.interval) {
+			(Some(next_round), Some(interval)) if now >= next_round => {
+				self.next_round = Some(now + interval);
+
+				true
+			},
+
+			_ => false,
+		}
+	}
+
+	/// Returns when a ping next times out or the next round of pings is due, if either is pending.
+	pub fn deadline(&self) -> Option<Instant> {
+		let timeout = self.settings.timeout;
+		let timeouts = self
+			.outstanding
+			.iter()
+			.filter(|(window, _)| !self.unresponsive.contains(*window))
+			.map(|(_, ping)| ping.sent + timeout);
+
+		timeouts.chain(self.next_round).min()
+	}
+
+	/// Returns whether the given `window`'s client didn't answer a ping within the timeout, and
+	/// hasn't since.
+	#[inline]
+	pub fn is_unresponsive(&self, window: &Window) -> bool {
+		self.unresponsive.contains(window)
+	}
+
+	/// Returns the windows whose clients are unresponsive, in no particular order.
+	pub fn unresponsive(&self) -> impl Iterator<Item = &Window> {
+		self.unresponsive.iter()
+	}
+
+	/// Records a force kill of the given `window`'s client being asked for at the time `now`,
+	/// returning whether it is confirmed.
+	///
+	/// A force kill is confirmed if it is asked for a second time, for the same window, within
+	/// [`KILL_CONFIRMATION`] of the first. Confirmed force kills must be asked for twice again.
+	pub fn confirm_kill(&mut self, window: Window, now: Instant) -> KillConfirmation {
+		match self.kill.take() {
+			Some((pending, asked)) if pending == window && now < asked + KILL_CONFIRMATION => {
+				KillConfirmation::Confirmed
+			},
+
+			_ => {
+				self.kill = Some((window, now));
+
+				KillConfirmation::Pending
+			},
+		}
+	}
+
+	/// Forgets the given `window`, e.g. because it was destroyed.
+	pub fn forget(&mut self, window: &Window) {
+		self.outstanding.remove(window);
+		self.unresponsive.remove(window);
+
+		if self.kill.as_ref().is_some_and(|(pending, _)| pending == window) {
+			self.kill = None;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SECOND: Duration = Duration::from_secs(1);
+
+	fn pings(interval: Option<Duration>, start: Instant) -> Pings<u32> {
+		let settings = PingSettings {
+			interval,
+			..PingSettings::default()
+		};
+
+		Pings::new(settings, start)
+	}
+
+	/// Tests that a window is marked unresponsive once its ping times out, and responsive again
+	/// once it is answered late.
+	#[test]
+	fn responsiveness() {
+		let start = Instant::now();
+		let mut pings = pings(None, start);
+		assert_eq!(pings.deadline(), None);
+
+		assert!(pings.ping(1, 100, start));
+		assert!(pings.ping(2, 100, start + SECOND));
+		// Window 1 is still being waited on, so it isn't pinged again.
+		assert!(!pings.ping(1, 200, start + SECOND));
+		assert_eq!(pings.deadline(), Some(start + DEFAULT_TIMEOUT));
+
+		assert_eq!(pings.pong(&2, 100), Pong::Answered);
+		assert!(pings.sweep(start + DEFAULT_TIMEOUT - SECOND).is_empty());
+		assert_eq!(pings.sweep(start + DEFAULT_TIMEOUT), [1]);
+		assert!(pings.is_unresponsive(&1) && !pings.is_unresponsive(&2));
+
+		// Unresponsive windows are neither reported again nor waited on.
+		assert!(pings.sweep(start + DEFAULT_TIMEOUT * 2).is_empty());
+		assert_eq!(pings.deadline(), None);
+		assert!(!pings.ping(1, 300, start + DEFAULT_TIMEOUT * 2));
+
+		// Answers must be to the outstanding ping, and from the window it was sent to.
+		assert_eq!(pings.pong(&1, 200), Pong::Unmatched);
+		assert_eq!(pings.pong(&2, 100), Pong::Unmatched);
+		assert_eq!(pings.pong(&1, 100), Pong::Recovered);
+		assert!(!pings.is_unresponsive(&1));
+
+		assert!(pings.ping(1, 300, start + DEFAULT_TIMEOUT * 2));
+		pings.forget(&1);
+		assert!(pings.sweep(start + DEFAULT_TIMEOUT * 4).is_empty());
+	}
+
+	/// Tests that rounds of pings are only due if pings are periodic, once per interval.
+	#[test]
+	fn rounds() {
+		let start = Instant::now();
+		let interval = SECOND * 30;
+
+		let mut never = pings(None, start);
+		assert!(!never.round_due(start + interval * 100));
+
+		let mut periodic = pings(Some(interval), start);
+		assert_eq!(periodic.deadline(), Some(start + interval));
+		assert!(!periodic.round_due(start + SECOND));
+		assert!(periodic.round_due(start + interval + SECOND));
+		assert!(!periodic.round_due(start + interval * 2));
+		assert_eq!(periodic.deadline(), Some(start + interval * 2 + SECOND));
+
+		// A ping which times out sooner comes first.
+		assert!(periodic.ping(1, 0, start + interval));
+		assert_eq!(periodic.deadline(), Some(start + interval + DEFAULT_TIMEOUT));
+
+		// Turning periodic pings off stops the rounds.
+		periodic.set_settings(PingSettings::default(), start + interval * 2);
+		assert!(!periodic.round_due(start + interval * 10));
+	}
+
+	/// Tests that force kills are only confirmed when asked for twice for the same window within
+	/// the confirmation period.
+	#[test]
+	fn kill_confirmation() {
+		let start = Instant::now();
+		let mut pings = pings(None, start);
+
+		assert_eq!(pings.confirm_kill(1, start), KillConfirmation::Pending);
+		assert_eq!(pings.confirm_kill(1, start + SECOND), KillConfirmation::Confirmed);
+		// A confirmed force kill has to be confirmed again.
+		assert_eq!(pings.confirm_kill(1, start + SECOND), KillConfirmation::Pending);
+
+		// Too late.
+		assert_eq!(
+			pings.confirm_kill(1, start + SECOND + KILL_CONFIRMATION),
+			KillConfirmation::Pending
+		);
+		// Another window was focused in between.
+		assert_eq!(pings.confirm_kill(2, start + SECOND * 4), KillConfirmation::Pending);
+		assert_eq!(pings.confirm_kill(1, start + SECOND * 5), KillConfirmation::Pending);
+		// The window went away.
+		pings.forget(&1);
+		assert_eq!(pings.confirm_kill(1, start + SECOND * 5), KillConfirmation::Pending);
+		assert_eq!(pings.confirm_kill(1, start + SECOND * 6), KillConfirmation::Confirmed);
+	}
+}
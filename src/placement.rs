@@ -0,0 +1,984 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Placement of [floating] windows when they are first mapped, and when they ask to be
+//! configured.
+//!
+//! Placement is decided by [`place_floating`] and [`configure_floating`]. Floating windows can also
+//! be moved to a [preset] geometry on demand.
+//!
+//! [floating]: crate::layout::Mode::Floating
+//! [preset]: FloatPreset
+
+use std::{
+	fmt,
+	fmt::{Display, Formatter},
+	str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{layout::Increments, output::Output};
+
+/// A rectangle: its x and y coordinates, then its width and height.
+pub type Rect = (i32, i32, u32, u32);
+
+/// How much of a floating window, in pixels along each axis, is kept on its output when it asks
+/// to be configured.
+const MIN_VISIBLE: u32 = 32;
+
+/// The geometry requested by a window, e.g. when it was created.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Geometry {
+	/// The x-coordinate of the outside of the window's border.
+	pub x: i32,
+	/// The y-coordinate of the outside of the window's border.
+	pub y: i32,
+
+	/// The width of the window, not including its border.
+	pub width: u32,
+	/// The height of the window, not including its border.
+	pub height: u32,
+
+	pub border_width: u32,
+}
+
+/// The parts of a window's geometry which it asked to change in a configure request.
+///
+/// Fields which are [`None`] were left out of the request, and keep their current values.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct Requested {
+	/// The x-coordinate of the outside of the window's border.
+	pub x: Option<i32>,
+	/// The y-coordinate of the outside of the window's border.
+	pub y: Option<i32>,
+
+	/// The width of the window, not including its border.
+	pub width: Option<u32>,
+	/// The height of the window, not including its border.
+	pub height: Option<u32>,
+
+	pub border_width: Option<u32>,
+}
+
+/// The point of a window which its requested position refers to.
+///
+/// For every gravity but [`Static`], this is a point on the outside of the window's border: the
+/// requested position of a window with [`Center`] gravity is where its center should be placed,
+/// for example.
+///
+/// [`Static`]: Gravity::Static
+/// [`Center`]: Gravity::Center
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum Gravity {
+	#[default]
+	NorthWest,
+	North,
+	NorthEast,
+	West,
+	Center,
+	East,
+	SouthWest,
+	South,
+	SouthEast,
+
+	/// The requested position is that of the inside of the window's border.
+	Static,
+}
+
+/// Hints given by a window about its size and position.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct SizeHints {
+	/// Whether the window's requested position was chosen by the user or the program (rather than
+	/// being left for the window manager to choose).
+	pub position: bool,
+
+	/// The size the window would like to be if its requested size is unusable.
+	pub base_size: Option<(u32, u32)>,
+	/// The minimum size of the window.
+	pub min_size: Option<(u32, u32)>,
+	/// The maximum size of the window.
+	pub max_size: Option<(u32, u32)>,
+	/// The steps in which the window would like to be resized beyond its base size, e.g. a
+	/// terminal's cell size.
+	pub size_increment: Option<(u32, u32)>,
+
+	/// The point of the window which its requested position refers to.
+	pub gravity: Gravity,
+}
+
+impl SizeHints {
+	/// Returns the window's [resize increments], if it has any.
+	///
+	/// Steps are counted from the window's [base size] or, if it has none, its [minimum size].
+	///
+	/// [resize increments]: Increments
+	/// [base size]: Self::base_size
+	/// [minimum size]: Self::min_size
+	pub fn increments(&self) -> Option<Increments> {
+		// Steps of 0 or 1 don't constrain the window.
+		let (width, height) = self.size_increment.filter(|&(width, height)| width > 1 || height > 1)?;
+		let (base_width, base_height) = self.base_size.or(self.min_size).unwrap_or_default();
+
+		Some(Increments {
+			width,
+			height,
+
+			base_width,
+			base_height,
+		})
+	}
+}
+
+impl Gravity {
+	/// Returns the offset of this gravity's reference point from the top-left corner of a window of
+	/// the given outer dimensions (including its border) and `border_width`.
+	const fn offset(&self, width: u32, height: u32, border_width: u32) -> (i32, i32) {
+		let (width, height) = (width as i32, height as i32);
+
+		match self {
+			Self::NorthWest => (0, 0),
+			Self::North => (width / 2, 0),
+			Self::NorthEast => (width, 0),
+
+			Self::West => (0, height / 2),
+			Self::Center => (width / 2, height / 2),
+			Self::East => (width, height / 2),
+
+			Self::SouthWest => (0, height),
+			Self::South => (width / 2, height),
+			Self::SouthEast => (width, height),
+
+			Self::Static => (border_width as i32, border_width as i32),
+		}
+	}
+}
+
+/// A geometry for [floating] windows, in percentages of the [work area] of their output, which
+/// they can be moved to with [`ApplyFloatPreset`].
+///
+/// [floating]: crate::layout::Mode::Floating
+/// [work area]: work_area
+/// [`ApplyFloatPreset`]: crate::action::Action::ApplyFloatPreset
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum FloatPreset {
+	/// Centered on the work area, with the given width and height (`centered-<width>x<height>`, or
+	/// `centered-<size>` for the same width and height).
+	Centered { width_pct: u8, height_pct: u8 },
+
+	/// The left half of the work area (`half-left`).
+	HalfLeft,
+	/// The right half of the work area (`half-right`).
+	HalfRight,
+	/// The top half of the work area (`half-top`).
+	HalfTop,
+	/// The bottom half of the work area (`half-bottom`).
+	HalfBottom,
+
+	/// The top-left quarter of the work area (`quarter-top-left`).
+	QuarterTopLeft,
+	/// The top-right quarter of the work area (`quarter-top-right`).
+	QuarterTopRight,
+	/// The bottom-left quarter of the work area (`quarter-bottom-left`).
+	QuarterBottomLeft,
+	/// The bottom-right quarter of the work area (`quarter-bottom-right`).
+	QuarterBottomRight,
+
+	/// The given rectangle of the work area (`<width>x<height>+<x>+<y>`, like an X geometry string
+	/// in percent).
+	Custom {
+		x_pct: u8,
+		y_pct: u8,
+		width_pct: u8,
+		height_pct: u8,
+	},
+}
+
+/// The names of the [presets] which don't take any sizes.
+///
+/// [presets]: FloatPreset
+const PRESETS: &[(&str, FloatPreset)] = &[
+	("half-left", FloatPreset::HalfLeft),
+	("half-right", FloatPreset::HalfRight),
+	("half-top", FloatPreset::HalfTop),
+	("half-bottom", FloatPreset::HalfBottom),
+	("quarter-top-left", FloatPreset::QuarterTopLeft),
+	("quarter-top-right", FloatPreset::QuarterTopRight),
+	("quarter-bottom-left", FloatPreset::QuarterBottomLeft),
+	("quarter-bottom-right", FloatPreset::QuarterBottomRight),
+];
+
+/// An error returned when parsing a [`FloatPreset`] fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error(
+	"unknown float preset `{0}`; expected `centered-<width>x<height>`, `half-left`, `quarter-top-right`, \
+	 `<width>x<height>+<x>+<y>` or similar, in percent"
+)]
+pub struct ParseFloatPresetError(String);
+
+impl FloatPreset {
+	/// Returns the preset's x and y coordinates, then its width and height, in percentages of the
+	/// work area.
+	pub const fn percentages(&self) -> (u8, u8, u8, u8) {
+		match *self {
+			Self::Centered { width_pct, height_pct } => {
+				let (width_pct, height_pct) = (min_u8(width_pct, 100), min_u8(height_pct, 100));
+
+				((100 - width_pct) / 2, (100 - height_pct) / 2, width_pct, height_pct)
+			},
+
+			Self::HalfLeft => (0, 0, 50, 100),
+			Self::HalfRight => (50, 0, 50, 100),
+			Self::HalfTop => (0, 0, 100, 50),
+			Self::HalfBottom => (0, 50, 100, 50),
+
+			Self::QuarterTopLeft => (0, 0, 50, 50),
+			Self::QuarterTopRight => (50, 0, 50, 50),
+			Self::QuarterBottomLeft => (0, 50, 50, 50),
+			Self::QuarterBottomRight => (50, 50, 50, 50),
+
+			Self::Custom {
+				x_pct,
+				y_pct,
+				width_pct,
+				height_pct,
+			} => (x_pct, y_pct, width_pct, height_pct),
+		}
+	}
+}
+
+/// Returns the lesser of `a` and `b`, as [`Ord::min`] isn't `const`.
+const fn min_u8(a: u8, b: u8) -> u8 {
+	if a < b {
+		a
+	} else {
+		b
+	}
+}
+
+impl FromStr for FloatPreset {
+	type Err = ParseFloatPresetError;
+
+	/// Parses a preset from its name, like `half-left`, `centered-60` or `centered-60x70`, or a
+	/// custom rectangle like `50x50+25+0`, all in percent.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let error = || ParseFloatPresetError(s.to_owned());
+		// Percentages over 100 would place windows beyond the work area.
+		let percent = |percent: &str| percent.parse().ok().filter(|&percent: &u8| percent <= 100);
+		let size = |size: &str| match size.split_once('x') {
+			Some((width, height)) => percent(width).zip(percent(height)),
+			None => percent(size).map(|size| (size, size)),
+		};
+
+		if let Some(&(_, preset)) = PRESETS.iter().find(|(name, _)| *name == s) {
+			return Ok(preset);
+		}
+
+		if let Some(dimensions) = s.strip_prefix("centered-") {
+			let (width_pct, height_pct) = size(dimensions).ok_or_else(error)?;
+
+			return Ok(Self::Centered { width_pct, height_pct });
+		}
+
+		let mut parts = s.split('+');
+		let (Some(dimensions), Some(x), Some(y), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+		else {
+			return Err(error());
+		};
+		let (width_pct, height_pct) = dimensions
+			.split_once('x')
+			.and_then(|(width, height)| percent(width).zip(percent(height)))
+			.ok_or_else(error)?;
+		let (x_pct, y_pct) = percent(x).zip(percent(y)).ok_or_else(error)?;
+
+		Ok(Self::Custom {
+			x_pct,
+			y_pct,
+			width_pct,
+			height_pct,
+		})
+	}
+}
+
+impl Display for FloatPreset {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		if let Some((name, _)) = PRESETS.iter().find(|(_, preset)| preset == self) {
+			return f.write_str(name);
+		}
+
+		match *self {
+			Self::Centered { width_pct, height_pct } => write!(f, "centered-{width_pct}x{height_pct}"),
+			preset => {
+				let (x_pct, y_pct, width_pct, height_pct) = preset.percentages();
+
+				write!(f, "{width_pct}x{height_pct}+{x_pct}+{y_pct}")
+			},
+		}
+	}
+}
+
+/// Returns the work area of the `preferred` output if there is one, or otherwise that of the
+/// [primary output] of the given `outputs`, or the given `screen` if there are no outputs.
+///
+/// AquariWM doesn't reserve space for panels' struts yet, so an output's work area is the whole
+/// output.
+///
+/// [primary output]: Output::primary
+pub fn work_area(preferred: Option<&Output>, outputs: &[Output], screen: Rect) -> Rect {
+	preferred
+		.or_else(|| Output::primary(outputs))
+		.map_or(screen, |output| (output.x, output.y, output.width, output.height))
+}
+
+/// Decides the geometry of a floating window with the given `hints` and `border_width` when it is
+/// moved to the given `preset` within the given `work_area`.
+///
+/// The window fills the preset's rectangle, unless that would make it smaller than its [minimum]
+/// or larger than its [maximum] size. It is then kept against the edges of the work area which the
+/// rectangle is against, or centered in the rectangle along axes where it is against neither or
+/// both, and moved as little as possible to lie entirely within the work area. Fitting within the
+/// work area takes precedence over the minimum size.
+///
+/// Returns the coordinates of the outside of the window's border and the dimensions of the window,
+/// not including its border.
+///
+/// [minimum]: SizeHints::min_size
+/// [maximum]: SizeHints::max_size
+pub fn place_preset(preset: &FloatPreset, work_area: Rect, hints: &SizeHints, border_width: u32) -> Rect {
+	let (area_x, area_y, area_width, area_height) = work_area;
+	let (x_pct, y_pct, width_pct, height_pct) = preset.percentages();
+
+	// The edges of the preset's rectangle, so that rectangles which reach the far edge of the work
+	// area aren't a pixel short of it.
+	let edge = |start: i32, length: u32, pct: u8| start + ((u64::from(length) * u64::from(pct.min(100)) / 100) as i32);
+	let (left, right) = (
+		edge(area_x, area_width, x_pct),
+		edge(area_x, area_width, x_pct.saturating_add(width_pct)),
+	);
+	let (top, bottom) = (
+		edge(area_y, area_height, y_pct),
+		edge(area_y, area_height, y_pct.saturating_add(height_pct)),
+	);
+
+	let border = 2 * border_width;
+	let (mut width, mut height) = (
+		((right - left) as u32).saturating_sub(border),
+		((bottom - top) as u32).saturating_sub(border),
+	);
+	if let Some((max_width, max_height)) = hints.max_size {
+		(width, height) = (width.min(max_width), height.min(max_height));
+	}
+	if let Some((min_width, min_height)) = hints.min_size {
+		(width, height) = (width.max(min_width), height.max(min_height));
+	}
+	width = width.min(area_width.saturating_sub(border)).max(1);
+	height = height.min(area_height.saturating_sub(border)).max(1);
+
+	// Places a window of the given outer `size` along one axis.
+	let align = |start: i32, end: i32, area_start: i32, area_length: u32, size: u32| {
+		let area_end = area_start + (area_length as i32);
+		let size = size as i32;
+
+		let coord = match (start == area_start, end == area_end) {
+			(true, false) => start,
+			(false, true) => end - size,
+			_ => start + (end - start - size) / 2,
+		};
+
+		coord.min(area_end - size).max(area_start)
+	};
+
+	(
+		align(left, right, area_x, area_width, width + border),
+		align(top, bottom, area_y, area_height, height + border),
+		width,
+		height,
+	)
+}
+
+/// Decides where a newly mapped floating window is placed within the given `output`.
+///
+/// The window keeps its `requested` size, unless that is no larger than 1 by 1, in which case its
+/// [base size] is used instead (if it has one). That size is kept between its [minimum] and
+/// [maximum] sizes, and shrunk to fit on the `output` if needed.
+///
+/// The window is then placed:
+/// - at its `requested` position, interpreted according to its [gravity], if that [position] was
+///   chosen by the user or the program; the window is moved as little as possible to lie entirely
+///   on the `output`.
+/// - centered on its `parent`, if it is a transient window (like a dialog) for that parent.
+/// - centered on the `output` otherwise.
+///
+/// Returns the coordinates of the outside of the window's border and the dimensions of the window,
+/// not including its border.
+///
+/// [base size]: SizeHints::base_size
+/// [minimum]: SizeHints::min_size
+/// [maximum]: SizeHints::max_size
+/// [gravity]: SizeHints::gravity
+/// [position]: SizeHints::position
+pub fn place_floating(requested: Geometry, hints: &SizeHints, output: Rect, parent: Option<Rect>) -> Rect {
+	let border = requested.border_width;
+	let (output_x, output_y, output_width, output_height) = output;
+
+	// Choose the window's size.
+	let (mut width, mut height) = match (requested.width, requested.height, hints.base_size) {
+		(..=1, ..=1, Some(base_size)) => base_size,
+		(width, height, _) => (width, height),
+	};
+	if let Some((max_width, max_height)) = hints.max_size {
+		(width, height) = (width.min(max_width), height.min(max_height));
+	}
+	if let Some((min_width, min_height)) = hints.min_size {
+		(width, height) = (width.max(min_width), height.max(min_height));
+	}
+	// Fitting on the output takes precedence over the minimum size.
+	width = width.min(output_width.saturating_sub(2 * border)).max(1);
+	height = height.min(output_height.saturating_sub(2 * border)).max(1);
+
+	let (outer_width, outer_height) = (width + 2 * border, height + 2 * border);
+	let center_on = |(x, y, area_width, area_height): Rect| {
+		(
+			x + ((area_width as i32) - (outer_width as i32)) / 2,
+			y + ((area_height as i32) - (outer_height as i32)) / 2,
+		)
+	};
+
+	// Choose the window's position.
+	let (x, y) = if hints.position {
+		let (offset_x, offset_y) = hints.gravity.offset(outer_width, outer_height, border);
+
+		(requested.x - offset_x, requested.y - offset_y)
+	} else if let Some(parent) = parent {
+		center_on(parent)
+	} else {
+		center_on(output)
+	};
+
+	// Move the window entirely onto the output.
+	let clamp = |coord: i32, start: i32, area: u32, size: u32| {
+		let end = start + (area as i32) - (size as i32);
+
+		coord.min(end).max(start)
+	};
+
+	(
+		clamp(x, output_x, output_width, outer_width),
+		clamp(y, output_y, output_height, outer_height),
+		width,
+		height,
+	)
+}
+
+/// Decides the geometry of a floating window with the `current` geometry after it asks to be
+/// configured with the `requested` changes.
+///
+/// The `requested` fields are merged over the `current` geometry. If the window is resized along
+/// an axis without being given a new position along it, it is moved so that the reference point
+/// of its [gravity] stays where it was: a window with [`SouthEast`] gravity grows up and to the
+/// left, for example.
+///
+/// The window is then moved as little as possible to keep at least 32 pixels of it (or all of it,
+/// if it is smaller) on the `output` along each axis, so that it can't be lost off-screen.
+///
+/// Returns the window's new geometry, and whether every `requested` field was honored as asked.
+///
+/// [gravity]: SizeHints::gravity
+/// [`SouthEast`]: Gravity::SouthEast
+pub fn configure_floating(
+	current: Geometry,
+	requested: &Requested,
+	gravity: Gravity,
+	output: Rect,
+) -> (Geometry, bool) {
+	let (output_x, output_y, output_width, output_height) = output;
+
+	let border = requested.border_width.unwrap_or(current.border_width);
+	let width = requested.width.unwrap_or(current.width).max(1);
+	let height = requested.height.unwrap_or(current.height).max(1);
+
+	let (outer_width, outer_height) = (width + 2 * border, height + 2 * border);
+	let (current_outer_width, current_outer_height) = (
+		current.width + 2 * current.border_width,
+		current.height + 2 * current.border_width,
+	);
+
+	// Keep the gravity's reference point in place along axes without a requested position.
+	let (current_offset_x, current_offset_y) =
+		gravity.offset(current_outer_width, current_outer_height, current.border_width);
+	let (offset_x, offset_y) = gravity.offset(outer_width, outer_height, border);
+
+	let x = requested.x.unwrap_or(current.x + current_offset_x - offset_x);
+	let y = requested.y.unwrap_or(current.y + current_offset_y - offset_y);
+
+	// Keep part of the window on the output.
+	let clamp = |coord: i32, start: i32, area: u32, size: u32| {
+		let visible = size.min(area).min(MIN_VISIBLE) as i32;
+
+		coord.clamp(start + visible - (size as i32), start + (area as i32) - visible)
+	};
+
+	let geometry = Geometry {
+		x: clamp(x, output_x, output_width, outer_width),
+		y: clamp(y, output_y, output_height, outer_height),
+
+		width,
+		height,
+
+		border_width: border,
+	};
+
+	let honored = requested.x.is_none_or(|x| x == geometry.x)
+		&& requested.y.is_none_or(|y| y == geometry.y)
+		&& requested.width.is_none_or(|width| width == geometry.width)
+		&& requested.height.is_none_or(|height| height == geometry.height);
+
+	(geometry, honored)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::output::Scale;
+
+	const OUTPUT: Rect = (1920, 0, 1920, 1080);
+
+	const fn geometry(x: i32, y: i32, width: u32, height: u32) -> Geometry {
+		Geometry {
+			x,
+			y,
+			width,
+			height,
+			border_width: 0,
+		}
+	}
+
+	fn positioned(gravity: Gravity) -> SizeHints {
+		SizeHints {
+			position: true,
+			gravity,
+
+			..SizeHints::default()
+		}
+	}
+
+	#[test]
+	fn no_position() {
+		let hints = SizeHints::default();
+
+		// Centered on the output, not at the requested (0, 0), which may be under a dock.
+		assert_eq!(
+			place_floating(geometry(0, 0, 400, 300), &hints, OUTPUT, None),
+			(2680, 390, 400, 300)
+		);
+
+		// Transient windows are centered on their parent instead...
+		let parent = (1920, 0, 960, 1080);
+		assert_eq!(
+			place_floating(geometry(0, 0, 400, 300), &hints, OUTPUT, Some(parent)),
+			(2200, 390, 400, 300)
+		);
+		// ...as far as they fit on the output.
+		let parent = (1920, 900, 200, 180);
+		assert_eq!(
+			place_floating(geometry(0, 0, 400, 300), &hints, OUTPUT, Some(parent)),
+			(1920, 780, 400, 300)
+		);
+	}
+
+	#[test]
+	fn position() {
+		let requested = geometry(2000, 100, 400, 300);
+
+		// An explicit position is honored, even for transient windows.
+		let placed = (2000, 100, 400, 300);
+		assert_eq!(
+			place_floating(requested, &positioned(Gravity::NorthWest), OUTPUT, None),
+			placed
+		);
+		assert_eq!(
+			place_floating(requested, &positioned(Gravity::NorthWest), OUTPUT, Some(OUTPUT)),
+			placed
+		);
+
+		// Other gravities move the reference point to the requested position.
+		assert_eq!(
+			place_floating(requested, &positioned(Gravity::Center), OUTPUT, None),
+			(1920, 0, 400, 300)
+		);
+		assert_eq!(
+			place_floating(
+				geometry(2600, 700, 400, 300),
+				&positioned(Gravity::Center),
+				OUTPUT,
+				None
+			),
+			(2400, 550, 400, 300)
+		);
+		assert_eq!(
+			place_floating(
+				geometry(2600, 700, 400, 300),
+				&positioned(Gravity::SouthEast),
+				OUTPUT,
+				None
+			),
+			(2200, 400, 400, 300)
+		);
+
+		// Static gravity refers to the inside of the border.
+		let bordered = Geometry {
+			border_width: 5,
+			..requested
+		};
+		assert_eq!(
+			place_floating(bordered, &positioned(Gravity::Static), OUTPUT, None),
+			(1995, 95, 400, 300)
+		);
+		assert_eq!(
+			place_floating(bordered, &positioned(Gravity::NorthWest), OUTPUT, None),
+			(2000, 100, 400, 300)
+		);
+	}
+
+	#[test]
+	fn clamped_on_screen() {
+		let hints = positioned(Gravity::NorthWest);
+
+		// Off either edge of the output.
+		assert_eq!(
+			place_floating(geometry(3700, 1000, 400, 300), &hints, OUTPUT, None),
+			(3440, 780, 400, 300)
+		);
+		assert_eq!(
+			place_floating(geometry(0, -50, 400, 300), &hints, OUTPUT, None),
+			(1920, 0, 400, 300)
+		);
+
+		// Too large for the output: shrunk to fit.
+		assert_eq!(
+			place_floating(geometry(1920, 0, 4000, 2000), &hints, OUTPUT, None),
+			(1920, 0, 1920, 1080)
+		);
+		let bordered = Geometry {
+			border_width: 2,
+			..geometry(1920, 0, 4000, 2000)
+		};
+		assert_eq!(place_floating(bordered, &hints, OUTPUT, None), (1920, 0, 1916, 1076));
+	}
+
+	#[test]
+	fn size_hints() {
+		let hints = SizeHints {
+			base_size: Some((640, 480)),
+			min_size: Some((200, 100)),
+			max_size: Some((800, 600)),
+
+			..SizeHints::default()
+		};
+
+		// The base size is used for windows without a usable size of their own.
+		assert_eq!(
+			place_floating(geometry(0, 0, 1, 1), &hints, OUTPUT, None),
+			(2560, 300, 640, 480)
+		);
+		// The requested size is kept within the minimum and maximum sizes.
+		assert_eq!(
+			place_floating(geometry(0, 0, 100, 50), &hints, OUTPUT, None),
+			(2780, 490, 200, 100)
+		);
+		assert_eq!(
+			place_floating(geometry(0, 0, 1000, 1000), &hints, OUTPUT, None),
+			(2480, 240, 800, 600)
+		);
+
+		// Resize increments are counted from the base size or, failing that, the minimum size.
+		assert_eq!(hints.increments(), None);
+		let terminal = SizeHints {
+			size_increment: Some((9, 18)),
+			..hints
+		};
+		assert_eq!(
+			terminal.increments(),
+			Some(Increments {
+				width: 9,
+				height: 18,
+
+				base_width: 640,
+				base_height: 480,
+			})
+		);
+		let terminal = SizeHints {
+			base_size: None,
+			..terminal
+		};
+		assert_eq!(terminal.increments().map(|increments| increments.base_width), Some(200));
+		let unconstrained = SizeHints {
+			size_increment: Some((1, 1)),
+			..terminal
+		};
+		assert_eq!(unconstrained.increments(), None);
+	}
+
+	/// Tests configure requests with every combination of fields against each gravity.
+	#[test]
+	fn configure_masks() {
+		let current = geometry(2000, 100, 400, 300);
+
+		let moved = Requested {
+			x: Some(2100),
+			y: Some(200),
+
+			..Requested::default()
+		};
+		let resized = Requested {
+			width: Some(500),
+			height: Some(400),
+
+			..Requested::default()
+		};
+		let widened = Requested {
+			width: Some(500),
+
+			..Requested::default()
+		};
+		let both = Requested {
+			x: moved.x,
+			y: moved.y,
+
+			..resized
+		};
+		let requests = [Requested::default(), moved, resized, widened, both];
+
+		let gravities = [
+			(
+				Gravity::NorthWest,
+				[
+					(2000, 100, 400, 300),
+					(2100, 200, 400, 300),
+					(2000, 100, 500, 400),
+					(2000, 100, 500, 300),
+					(2100, 200, 500, 400),
+				],
+			),
+			(
+				Gravity::Center,
+				[
+					(2000, 100, 400, 300),
+					(2100, 200, 400, 300),
+					(1950, 50, 500, 400),
+					(1950, 100, 500, 300),
+					(2100, 200, 500, 400),
+				],
+			),
+			(
+				Gravity::SouthEast,
+				[
+					(2000, 100, 400, 300),
+					(2100, 200, 400, 300),
+					(1900, 0, 500, 400),
+					(1900, 100, 500, 300),
+					(2100, 200, 500, 400),
+				],
+			),
+			(
+				Gravity::Static,
+				[
+					(2000, 100, 400, 300),
+					(2100, 200, 400, 300),
+					(2000, 100, 500, 400),
+					(2000, 100, 500, 300),
+					(2100, 200, 500, 400),
+				],
+			),
+		];
+
+		for (gravity, expected) in gravities {
+			for (requested, (x, y, width, height)) in requests.iter().zip(expected) {
+				assert_eq!(
+					configure_floating(current, requested, gravity, OUTPUT),
+					(geometry(x, y, width, height), true),
+					"{requested:?} with {gravity:?} gravity"
+				);
+			}
+		}
+
+		// Static gravity keeps the inside of the border in place as the border changes.
+		let bordered = Geometry {
+			border_width: 5,
+			..current
+		};
+		let unbordered = Requested {
+			border_width: Some(0),
+
+			..Requested::default()
+		};
+		assert_eq!(
+			configure_floating(bordered, &unbordered, Gravity::Static, OUTPUT),
+			(geometry(2005, 105, 400, 300), true)
+		);
+		assert_eq!(
+			configure_floating(bordered, &unbordered, Gravity::NorthWest, OUTPUT),
+			(geometry(2000, 100, 400, 300), true)
+		);
+	}
+
+	/// Tests that configured windows are kept partially on their output, and that requests which
+	/// had to be changed are reported as such.
+	#[test]
+	fn configure_clamped() {
+		let current = geometry(2000, 100, 400, 300);
+
+		// Partially off the output is fine...
+		let partially = Requested {
+			x: Some(1700),
+			y: Some(-200),
+
+			..Requested::default()
+		};
+		assert_eq!(
+			configure_floating(current, &partially, Gravity::NorthWest, OUTPUT),
+			(geometry(1700, -200, 400, 300), true)
+		);
+
+		// ...but 32 pixels of the window are kept on it.
+		let off = Requested {
+			x: Some(5000),
+			y: Some(-1000),
+
+			..Requested::default()
+		};
+		assert_eq!(
+			configure_floating(current, &off, Gravity::NorthWest, OUTPUT),
+			(geometry(3808, -268, 400, 300), false)
+		);
+
+		// Windows which are already too far off the output are moved back onto it, even if only
+		// their size was requested.
+		let widened = Requested {
+			width: Some(500),
+
+			..Requested::default()
+		};
+		assert_eq!(
+			configure_floating(geometry(3830, 100, 400, 300), &widened, Gravity::NorthWest, OUTPUT),
+			(geometry(3808, 100, 500, 300), true)
+		);
+
+		// Empty sizes can't be honored.
+		let empty = Requested {
+			width: Some(0),
+
+			..Requested::default()
+		};
+		assert_eq!(
+			configure_floating(current, &empty, Gravity::NorthWest, OUTPUT),
+			(geometry(2000, 100, 1, 300), false)
+		);
+	}
+
+	#[test]
+	fn parse_presets() {
+		assert_eq!("half-left".parse(), Ok(FloatPreset::HalfLeft));
+		assert_eq!(
+			"centered-60".parse(),
+			Ok(FloatPreset::Centered {
+				width_pct: 60,
+				height_pct: 60,
+			})
+		);
+		assert_eq!(
+			"50x100+25+0".parse(),
+			Ok(FloatPreset::Custom {
+				x_pct: 25,
+				y_pct: 0,
+				width_pct: 50,
+				height_pct: 100,
+			})
+		);
+		assert!("centered-160".parse::<FloatPreset>().is_err());
+		assert!("half".parse::<FloatPreset>().is_err());
+		assert!("50x50+25".parse::<FloatPreset>().is_err());
+
+		for preset in ["centered-60x70", "quarter-bottom-right", "50x50+25+25"] {
+			assert_eq!(preset.parse::<FloatPreset>().unwrap().to_string(), preset);
+		}
+	}
+
+	#[test]
+	fn presets() {
+		let output = |name: &str, rect: Rect, primary| {
+			let (x, y, width, height) = rect;
+
+			Output {
+				name: name.to_owned(),
+				x,
+				y,
+				width,
+				height,
+				primary,
+				scale: Scale::IDENTITY,
+
+				clones: Vec::new(),
+			}
+		};
+		// A 1080p laptop screen to the right of a primary 1440p monitor.
+		let outputs = [
+			output("DP-1", (0, 0, 2560, 1440), true),
+			output("eDP-1", (2560, 0, 1920, 1080), false),
+		];
+		let hints = SizeHints::default();
+
+		let laptop = work_area(Some(&outputs[1]), &outputs, (0, 0, 4480, 1440));
+		assert_eq!(laptop, (2560, 0, 1920, 1080));
+		let monitor = work_area(None, &outputs, (0, 0, 4480, 1440));
+		assert_eq!(monitor, (0, 0, 2560, 1440));
+		assert_eq!(work_area(None, &[], (0, 0, 4480, 1440)), (0, 0, 4480, 1440));
+
+		let centered = FloatPreset::Centered {
+			width_pct: 60,
+			height_pct: 70,
+		};
+		assert_eq!(place_preset(&centered, laptop, &hints, 0), (2944, 162, 1152, 756));
+		assert_eq!(place_preset(&centered, monitor, &hints, 0), (512, 216, 1536, 1008));
+
+		// Borders are kept within the preset's rectangle.
+		assert_eq!(
+			place_preset(&FloatPreset::HalfRight, laptop, &hints, 2),
+			(3520, 0, 956, 1076)
+		);
+		assert_eq!(
+			place_preset(&FloatPreset::QuarterBottomLeft, monitor, &hints, 0),
+			(0, 720, 1280, 720)
+		);
+		// Odd sizes still reach the far edge of the work area.
+		assert_eq!(
+			place_preset(&FloatPreset::HalfRight, (0, 0, 1921, 1080), &hints, 0),
+			(960, 0, 961, 1080)
+		);
+
+		// Windows which can't fill the rectangle are kept against the edges it is against...
+		let hints = SizeHints {
+			min_size: Some((1000, 100)),
+			max_size: Some((4000, 500)),
+
+			..SizeHints::default()
+		};
+		assert_eq!(
+			place_preset(&FloatPreset::QuarterBottomRight, laptop, &hints, 0),
+			(3480, 580, 1000, 500)
+		);
+		// ...or centered in it, and always kept within the work area.
+		assert_eq!(place_preset(&centered, laptop, &hints, 0), (2944, 290, 1152, 500));
+		let hints = SizeHints {
+			min_size: Some((3000, 100)),
+
+			..SizeHints::default()
+		};
+		assert_eq!(
+			place_preset(&FloatPreset::HalfLeft, laptop, &hints, 0),
+			(2560, 0, 1920, 1080)
+		);
+	}
+}
@@ -0,0 +1,575 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Remembering where applications' windows were, so that they reopen there.
+//!
+//! When a window is closed, its [placement] - the output it was on, where its tile was in the
+//! tiling layout or its geometry if it was floating, and whether it was sticky - is [remembered]
+//! under its class. The next time a window of that class is mapped, the placement is [recalled]
+//! and used instead of the insertion strategy, unless the window is placed explicitly, e.g. because
+//! it was [launched] on a particular output.
+//!
+//! Only the most recently used [`DEFAULT_CAPACITY`] classes are remembered, and placements older
+//! than [`MAX_AGE`] are ignored. Placements are written to `placements.json` alongside the
+//! [layout snapshots], so that they persist across sessions.
+//!
+//! Workspaces are created and removed as they are used, so which workspace a window was on isn't
+//! remembered: the output it was on stands in for it.
+//!
+//! [placement]: Placement
+//! [remembered]: PlacementMemory::remember
+//! [recalled]: PlacementMemory::recall
+//! [launched]: crate::launch
+//! [layout snapshots]: crate::autosave
+
+use std::{
+	collections::VecDeque,
+	fs,
+	io::{self, Write},
+	path::Path,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::{
+	layout,
+	output::Output,
+	placement::Rect,
+	state::{AquariWm, MapState},
+	window::ManagedWindow,
+};
+
+/// The default number of classes whose placements are remembered.
+pub const DEFAULT_CAPACITY: usize = 128;
+/// How long a placement is remembered for.
+pub const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// The file name of the remembered placements.
+const FILE_NAME: &str = "placements.json";
+
+/// Where a window was when it was closed.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct Placement {
+	/// The output the window was on, if it was on any.
+	pub output: Option<RememberedOutput>,
+
+	/// The [path] of indexes to the window's tile in the tiling layout, if it was tiled.
+	///
+	/// [path]: layout::GroupNode::path_of
+	pub path: Option<Vec<usize>>,
+	/// The window's geometry, if it was floating.
+	pub floating: Option<Rect>,
+
+	/// Whether the window was [sticky].
+	///
+	/// [sticky]: crate::state::WindowState::sticky
+	pub sticky: bool,
+}
+
+/// The [output] a window was on.
+///
+/// [output]: Output
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct RememberedOutput {
+	pub name: String,
+	/// The [center] of the output, which is used to find the nearest output if this one is no
+	/// longer connected.
+	///
+	/// [center]: Output::center
+	pub center: (i32, i32),
+}
+
+/// The [placement] remembered for a class.
+///
+/// [placement]: Placement
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+struct Entry {
+	class: String,
+	placement: Placement,
+
+	/// When the placement was remembered, in seconds since the Unix epoch.
+	saved: u64,
+}
+
+/// Remembers the [placements] of the most recently closed windows of each class.
+///
+/// [placements]: Placement
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PlacementMemory {
+	/// The remembered placements, least recently used first.
+	entries: VecDeque<Entry>,
+	/// The maximum number of classes whose placements are remembered.
+	capacity: usize,
+
+	/// Whether the placements have changed since [`take_changed`] was last called.
+	///
+	/// [`take_changed`]: Self::take_changed
+	changed: bool,
+}
+
+impl Placement {
+	/// Captures the placement of the given `window` in the given AquariWM `state`, where `floating`
+	/// is its geometry if it is floating.
+	///
+	/// Returns [`None`] if the `window` isn't mapped.
+	pub fn capture<Window>(state: &AquariWm<Window>, window: &Window, floating: Option<Rect>) -> Option<Self>
+	where
+		Window: ManagedWindow + Send + Sync,
+	{
+		let window_state = state.windows.get(window)?;
+		if window_state.mapped != MapState::Mapped {
+			return None;
+		}
+
+		let (output, path, floating) = match window_state.mode {
+			layout::Mode::Tiled => {
+				let layout = state.tiling_layout();
+				let path = layout.and_then(|layout| layout.path_of(layout.id_of_window(window)?));
+
+				(state.output_of(window), path, None)
+			},
+
+			layout::Mode::Floating => {
+				let output = floating.and_then(|(x, y, width, height)| {
+					Output::at(&state.outputs, x + ((width / 2) as i32), y + ((height / 2) as i32))
+				});
+
+				(output, None, floating)
+			},
+		};
+
+		Some(Self {
+			output: output.map(|output| RememberedOutput {
+				name: output.name.clone(),
+				center: output.center(),
+			}),
+
+			path,
+			floating,
+
+			sticky: window_state.sticky,
+		})
+	}
+
+	/// Returns the output the window was on out of the given `outputs` or, if it is no longer
+	/// connected, the output whose center is nearest to its center.
+	///
+	/// Returns [`None`] if the window wasn't on any output, or if there are no `outputs`.
+	pub fn output<'outputs>(&self, outputs: &'outputs [Output]) -> Option<&'outputs Output> {
+		let remembered = self.output.as_ref()?;
+
+		Output::named(outputs, &remembered.name).or_else(|| {
+			let (x, y) = remembered.center;
+
+			outputs.iter().min_by_key(|output| {
+				let (output_x, output_y) = output.center();
+				let (dx, dy) = (i64::from(output_x) - i64::from(x), i64::from(output_y) - i64::from(y));
+
+				dx * dx + dy * dy
+			})
+		})
+	}
+
+	/// Moves the given tiled `window` to where the window was in the tiling layout, returning
+	/// whether it was moved.
+	///
+	/// If the output the window was on is still connected, the `window` is [put in the tile] at the
+	/// remembered path. Otherwise, the remembered path is probably elsewhere now, so the `window`
+	/// is [moved to] the output nearest to where it was instead.
+	///
+	/// [put in the tile]: AquariWm::place_window_at
+	/// [moved to]: AquariWm::move_window_to_output
+	pub fn restore<Window>(&self, state: &mut AquariWm<Window>, window: &Window) -> bool
+	where
+		Window: ManagedWindow + Send + Sync,
+	{
+		let connected = self
+			.output
+			.as_ref()
+			.is_none_or(|output| Output::named(&state.outputs, &output.name).is_some());
+
+		match &self.path {
+			Some(path) if connected => state.place_window_at(window, path),
+
+			_ => match self.output(&state.outputs).map(|output| output.name.clone()) {
+				Some(name) => state.move_window_to_output(window, &name),
+				None => false,
+			},
+		}
+	}
+
+	/// Returns the window's floating geometry moved onto the given `output`, if it was floating.
+	///
+	/// If the `output` isn't the one the window was on, the window keeps its position relative to
+	/// the output's center. Either way, it is then moved (and if necessary, shrunk) to fit entirely
+	/// on the `output`, in case the output has changed since.
+	pub fn floating_on(&self, output: Option<&Output>) -> Option<Rect> {
+		let (mut x, mut y, mut width, mut height) = self.floating?;
+		let Some(output) = output else {
+			return self.floating;
+		};
+
+		if let Some(remembered) = self.output.as_ref().filter(|remembered| remembered.name != output.name) {
+			let ((from_x, from_y), (to_x, to_y)) = (remembered.center, output.center());
+
+			(x, y) = (x + (to_x - from_x), y + (to_y - from_y));
+		}
+
+		(width, height) = (width.min(output.width).max(1), height.min(output.height).max(1));
+
+		let clamp = |coord: i32, start: i32, area: u32, size: u32| coord.min(start + ((area - size) as i32)).max(start);
+
+		Some((
+			clamp(x, output.x, output.width, width),
+			clamp(y, output.y, output.height, height),
+			width,
+			height,
+		))
+	}
+}
+
+impl Default for PlacementMemory {
+	#[inline]
+	fn default() -> Self {
+		Self::new(DEFAULT_CAPACITY)
+	}
+}
+
+impl PlacementMemory {
+	/// Creates an empty memory which remembers the placements of up to `capacity` classes.
+	#[inline]
+	pub const fn new(capacity: usize) -> Self {
+		Self {
+			entries: VecDeque::new(),
+			capacity,
+
+			changed: false,
+		}
+	}
+
+	/// Returns the number of classes whose placements are remembered, including any which are
+	/// too old to be [recalled].
+	///
+	/// [recalled]: Self::recall
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// Returns whether no placements are remembered.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Remembers the `placement` of the most recently closed window of the given `class` at the
+	/// given time (`now`), replacing any placement remembered for that class.
+	///
+	/// If the memory is full, the least recently used placement is forgotten.
+	pub fn remember(&mut self, class: &str, placement: Placement, now: SystemTime) {
+		self.entries.retain(|entry| entry.class != class);
+
+		self.entries.push_back(Entry {
+			class: class.to_owned(),
+			placement,
+
+			saved: seconds_since_epoch(now),
+		});
+		while self.entries.len() > self.capacity {
+			self.entries.pop_front();
+		}
+
+		self.changed = true;
+	}
+
+	/// Returns the placement remembered for the given `class`, if it was remembered no longer than
+	/// [`MAX_AGE`] before the given time (`now`).
+	///
+	/// Recalling a placement makes it the most recently used. Placements which are too old are
+	/// forgotten.
+	pub fn recall(&mut self, class: &str, now: SystemTime) -> Option<&Placement> {
+		self.expire(now);
+
+		let index = self.entries.iter().position(|entry| entry.class == class)?;
+		let entry = self.entries.remove(index)?;
+		self.entries.push_back(entry);
+		self.changed = true;
+
+		self.entries.back().map(|entry| &entry.placement)
+	}
+
+	/// Returns whether the placements have changed since this was last called, so that they need
+	/// to be [written].
+	///
+	/// [written]: Self::write
+	pub fn take_changed(&mut self) -> bool {
+		std::mem::take(&mut self.changed)
+	}
+
+	/// Forgets placements which are older than [`MAX_AGE`] at the given time (`now`).
+	fn expire(&mut self, now: SystemTime) {
+		let now = seconds_since_epoch(now);
+		let len = self.entries.len();
+
+		self.entries
+			.retain(|entry| now.saturating_sub(entry.saved) <= MAX_AGE.as_secs());
+
+		self.changed |= self.entries.len() != len;
+	}
+
+	/// Reads the placements remembered in the given `dir`, remembering up to [`DEFAULT_CAPACITY`]
+	/// classes.
+	///
+	/// If there are no remembered placements, or they can't be read, the memory is empty.
+	pub fn read(dir: &Path) -> Self {
+		let path = dir.join(FILE_NAME);
+		let mut memory = Self::default();
+
+		let contents = match fs::read(&path) {
+			Ok(contents) => contents,
+
+			Err(error) if error.kind() == io::ErrorKind::NotFound => return memory,
+			Err(error) => {
+				event!(Level::WARN, "Failed to read window placements {path:?}: {error}");
+
+				return memory;
+			},
+		};
+
+		match serde_json::from_slice::<Vec<Entry>>(&contents) {
+			Ok(entries) => {
+				let skip = entries.len().saturating_sub(memory.capacity);
+				memory.entries.extend(entries.into_iter().skip(skip));
+			},
+
+			Err(error) => event!(Level::WARN, "Ignoring invalid window placements {path:?}: {error}"),
+		}
+
+		memory
+	}
+
+	/// Atomically writes the placements to `placements.json` in the given `dir`, logging any
+	/// failure.
+	///
+	/// Returns whether the placements were written.
+	pub fn write(&self, dir: &Path) -> bool {
+		match self.try_write(dir) {
+			Ok(()) => true,
+
+			Err(error) => {
+				event!(Level::WARN, "Failed to write window placements to {dir:?}: {error}");
+
+				false
+			},
+		}
+	}
+
+	fn try_write(&self, dir: &Path) -> io::Result<()> {
+		fs::create_dir_all(dir)?;
+
+		let temp = dir.join(format!("{FILE_NAME}.tmp"));
+		{
+			let mut file = fs::File::create(&temp)?;
+
+			file.write_all(&serde_json::to_vec(&self.entries)?)?;
+			file.sync_all()?;
+		}
+
+		fs::rename(&temp, dir.join(FILE_NAME))
+	}
+}
+
+/// Returns the given time in seconds since the Unix epoch, or `0` if it is before the epoch.
+fn seconds_since_epoch(time: SystemTime) -> u64 {
+	time.duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, process};
+
+	use super::*;
+	use crate::{
+		layout::{managers::Stack, LayoutSettings},
+		output::Scale,
+	};
+
+	const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+	fn output(name: &str, x: i32) -> Output {
+		Output {
+			name: name.to_owned(),
+
+			x,
+			y: 0,
+
+			width: 1920,
+			height: 1080,
+
+			primary: x == 0,
+			scale: Scale::IDENTITY,
+
+			clones: Vec::new(),
+		}
+	}
+
+	fn tiled(path: &[usize]) -> Placement {
+		Placement {
+			output: None,
+
+			path: Some(path.to_vec()),
+			floating: None,
+
+			sticky: false,
+		}
+	}
+
+	#[test]
+	fn least_recently_used() {
+		let now = SystemTime::now();
+		let mut memory = PlacementMemory::new(2);
+
+		memory.remember("a", tiled(&[0]), now);
+		memory.remember("b", tiled(&[1]), now);
+		// Recalling `a` makes `b` the least recently used...
+		assert_eq!(memory.recall("a", now), Some(&tiled(&[0])));
+		// ...so `b` is forgotten to make room for `c`.
+		memory.remember("c", tiled(&[2]), now);
+
+		assert_eq!(memory.len(), 2);
+		assert_eq!(memory.recall("b", now), None);
+		assert_eq!(memory.recall("c", now), Some(&tiled(&[2])));
+
+		// Remembering a class again replaces its placement.
+		memory.remember("a", tiled(&[3]), now);
+		assert_eq!(memory.len(), 2);
+		assert_eq!(memory.recall("a", now), Some(&tiled(&[3])));
+	}
+
+	#[test]
+	fn staleness() {
+		let start = SystemTime::now();
+		let mut memory = PlacementMemory::default();
+
+		memory.remember("a", tiled(&[0]), start);
+		memory.remember("b", tiled(&[1]), start + 10 * DAY);
+		assert!(memory.take_changed());
+
+		assert_eq!(memory.recall("a", start + 30 * DAY), Some(&tiled(&[0])));
+		// Placements older than 30 days are ignored, and forgotten.
+		assert_eq!(memory.recall("a", start + 31 * DAY), None);
+		assert_eq!(memory.recall("b", start + 31 * DAY), Some(&tiled(&[1])));
+		assert_eq!(memory.len(), 1);
+
+		assert!(memory.take_changed());
+		assert!(!memory.take_changed());
+	}
+
+	#[test]
+	fn fallback() {
+		let outputs = [output("DP-1", 0), output("HDMI-1", 1920)];
+		let placement = |name: &str, center| Placement {
+			output: Some(RememberedOutput {
+				name: name.to_owned(),
+				center,
+			}),
+
+			path: None,
+			floating: Some((4500, 100, 400, 300)),
+
+			sticky: false,
+		};
+
+		// The remembered output is used if it is connected...
+		let connected = placement("HDMI-1", (2880, 540));
+		let output = connected.output(&outputs).unwrap();
+		assert_eq!(output.name, "HDMI-1");
+		// ...and floating windows are kept on it.
+		assert_eq!(connected.floating_on(Some(output)), Some((3440, 100, 400, 300)));
+
+		// Otherwise, the output nearest to where it was is used instead, and floating windows keep
+		// their position relative to its center.
+		let disconnected = placement("DP-2", (4800, 540));
+		let output = disconnected.output(&outputs).unwrap();
+		assert_eq!(output.name, "HDMI-1");
+		assert_eq!(disconnected.floating_on(Some(output)), Some((2580, 100, 400, 300)));
+
+		assert_eq!(disconnected.output(&[]), None);
+		assert_eq!(tiled(&[0]).output(&outputs), None);
+	}
+
+	/// Tests that a window which is closed and reopened returns to the output and tile it was
+	/// closed on.
+	#[test]
+	fn reopen() {
+		let now = SystemTime::now();
+		let windows = [(1, MapState::Mapped), (2, MapState::Mapped), (3, MapState::Mapped)];
+		let mut state = AquariWm::with_tiling_layout_and_windows::<Stack<u32>>(
+			0,
+			0,
+			3840,
+			1080,
+			windows,
+			LayoutSettings::default(),
+		);
+		state.outputs = vec![output("DP-1", 0), output("HDMI-1", 1920)];
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+
+		let mut memory = PlacementMemory::default();
+
+		// Close window 2, the first window in the stack on the right-hand output.
+		let placement = Placement::capture(&state, &2, None).unwrap();
+		assert_eq!(placement.path.as_deref(), Some(&[1, 0][..]));
+		assert_eq!(placement.output.as_ref().map(|output| &*output.name), Some("HDMI-1"));
+		memory.remember("editor", placement, now);
+		state.remove_window(&2);
+
+		// Reopen it as window 4, which would otherwise be added to the end of the stack.
+		let placement = memory.recall("editor", now).unwrap().clone();
+		state.add_window(4, MapState::Mapped).unwrap();
+		assert!(placement.restore(&mut state, &4));
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+
+		let layout = state.tiling_layout().unwrap();
+		assert_eq!(layout.path_of(layout.id_of_window(&4).unwrap()).unwrap(), [1, 0]);
+		assert_eq!(state.output_of(&4).map(|output| &*output.name), Some("HDMI-1"));
+
+		// If its output was disconnected, it opens on the nearest output instead: the main window is
+		// on the left-hand output, so the window is placed alongside it.
+		let disconnected = Placement {
+			output: Some(RememberedOutput {
+				name: "DP-2".to_owned(),
+				center: (-960, 540),
+			}),
+			..placement
+		};
+		assert!(disconnected.restore(&mut state, &3));
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+
+		assert_eq!(state.output_of(&3).map(|output| &*output.name), Some("DP-1"));
+	}
+
+	#[test]
+	fn persistence() {
+		let dir = env::temp_dir().join(format!("aquariwm-placements-{}", process::id()));
+		let now = SystemTime::now();
+
+		let mut memory = PlacementMemory::default();
+		memory.remember("a", tiled(&[0, 1]), now);
+		memory.remember("b", tiled(&[2]), now);
+		assert!(memory.write(&dir));
+
+		let mut read = PlacementMemory::read(&dir);
+		assert_eq!(read.recall("a", now), Some(&tiled(&[0, 1])));
+		assert_eq!(read.len(), 2);
+
+		// Invalid placements are ignored.
+		fs::write(dir.join(FILE_NAME), "{").unwrap();
+		assert!(PlacementMemory::read(&dir).is_empty());
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
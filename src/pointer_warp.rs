@@ -0,0 +1,270 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Warping the pointer to the focused window after it is focused or moved with the keyboard.
+//!
+//! When focus follows the pointer, focusing or moving a window with the keyboard leaves the
+//! pointer somewhere else, so the pointer and focus disagree until the pointer next moves. With a
+//! [pointer warp] setting, the pointer is moved to the center of the focused window once its new
+//! geometry has been applied.
+//!
+//! Whether the pointer is warped is [decided] from the focused window's rectangle, the pointer's
+//! position, and whether the pointer is grabbed.
+//!
+//! [pointer warp]: PointerWarp
+//! [decided]: decide
+
+use std::{
+	fmt,
+	fmt::{Display, Formatter},
+	str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::{placement::Rect, state::FocusCause};
+
+/// When the pointer is warped to the focused window.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum PointerWarp {
+	/// The pointer is never warped.
+	#[default]
+	Never,
+	/// The pointer is warped when focus changes.
+	OnFocusChange,
+	/// The pointer is warped when the focused window is moved, e.g. to another output.
+	OnWindowMove,
+	/// The pointer is warped both when focus changes and when the focused window is moved.
+	Both,
+}
+
+/// What happened to the focused window which may warp the pointer.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Trigger {
+	/// Focus changed for the given cause.
+	FocusChange(FocusCause),
+	/// The focused window was moved, e.g. swapped with the primary window or moved to another
+	/// output.
+	WindowMove,
+}
+
+/// Whether the pointer is warped, as [decided] by a [pointer warp] setting.
+///
+/// [decided]: decide
+/// [pointer warp]: PointerWarp
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Decision {
+	/// The pointer is warped to the given position.
+	Warp(i32, i32),
+	/// The pointer is left where it is.
+	Skip(Skipped),
+}
+
+/// Why the pointer was not warped.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Skipped {
+	/// The [pointer warp] setting doesn't warp the pointer for the trigger.
+	///
+	/// [pointer warp]: PointerWarp
+	Disabled,
+	/// Focus followed the pointer, so warping it would fight the user's hand.
+	PointerInitiated,
+	/// The pointer is grabbed, e.g. because a window is being dragged.
+	Grabbed,
+	/// The pointer is already inside the focused window.
+	AlreadyInside,
+}
+
+impl PointerWarp {
+	/// Returns whether the pointer is warped when focus changes.
+	#[inline(always)]
+	pub const fn on_focus_change(self) -> bool {
+		matches!(self, Self::OnFocusChange | Self::Both)
+	}
+
+	/// Returns whether the pointer is warped when the focused window is moved.
+	#[inline(always)]
+	pub const fn on_window_move(self) -> bool {
+		matches!(self, Self::OnWindowMove | Self::Both)
+	}
+}
+
+/// Decides whether the pointer at `pointer` is warped to the focused window, whose (final)
+/// rectangle is `rect`, after the given `trigger`, according to the given `setting`.
+///
+/// The pointer is warped to the center of the window, unless it is [grabbed] or already inside the
+/// window, or focus followed the pointer in the first place.
+///
+/// [grabbed]: Skipped::Grabbed
+pub fn decide(setting: PointerWarp, trigger: Trigger, rect: Rect, pointer: (i32, i32), grabbed: bool) -> Decision {
+	let enabled = match trigger {
+		Trigger::FocusChange(_) => setting.on_focus_change(),
+		Trigger::WindowMove => setting.on_window_move(),
+	};
+
+	if !enabled {
+		return Decision::Skip(Skipped::Disabled);
+	}
+	if trigger == Trigger::FocusChange(FocusCause::Pointer) {
+		return Decision::Skip(Skipped::PointerInitiated);
+	}
+	if grabbed {
+		return Decision::Skip(Skipped::Grabbed);
+	}
+
+	let (x, y, width, height) = rect;
+	let (pointer_x, pointer_y) = (i64::from(pointer.0), i64::from(pointer.1));
+
+	let inside_x = (i64::from(x)..i64::from(x) + i64::from(width)).contains(&pointer_x);
+	let inside_y = (i64::from(y)..i64::from(y) + i64::from(height)).contains(&pointer_y);
+	if inside_x && inside_y {
+		return Decision::Skip(Skipped::AlreadyInside);
+	}
+
+	Decision::Warp(x + (width / 2) as i32, y + (height / 2) as i32)
+}
+
+impl Display for Skipped {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Disabled => write!(f, "pointer warping is disabled"),
+			Self::PointerInitiated => write!(f, "focus followed the pointer"),
+			Self::Grabbed => write!(f, "the pointer is grabbed"),
+			Self::AlreadyInside => write!(f, "the pointer is already inside the focused window"),
+		}
+	}
+}
+
+/// An error returned when parsing a [`PointerWarp`] setting fails.
+#[derive(Debug, Error)]
+#[error("expected `never`, `on-focus-change`, `on-window-move`, or `both`, found {0:?}")]
+pub struct ParsePointerWarpError(String);
+
+impl FromStr for PointerWarp {
+	type Err = ParsePointerWarpError;
+
+	/// Parses `never`, `on-focus-change`, `on-window-move`, or `both`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim() {
+			"never" => Ok(Self::Never),
+			"on-focus-change" => Ok(Self::OnFocusChange),
+			"on-window-move" => Ok(Self::OnWindowMove),
+			"both" => Ok(Self::Both),
+
+			other => Err(ParsePointerWarpError(other.to_owned())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		action::Action,
+		layout::{managers::Stack, LayoutSettings, Reconfigured},
+		state::{AquariWm, MapState},
+	};
+
+	const RECT: Rect = (100, 100, 200, 100);
+	const OUTSIDE: (i32, i32) = (0, 0);
+
+	#[test]
+	fn skips() {
+		use Decision::{Skip, Warp};
+		use Skipped::*;
+
+		let keyboard = Trigger::FocusChange(FocusCause::Keyboard);
+		let pointer = Trigger::FocusChange(FocusCause::Pointer);
+
+		// (setting, trigger, pointer, grabbed, decision)
+		#[rustfmt::skip]
+		let cases = [
+			(PointerWarp::Never, keyboard, OUTSIDE, false, Skip(Disabled)),
+			(PointerWarp::Never, Trigger::WindowMove, OUTSIDE, false, Skip(Disabled)),
+			(PointerWarp::OnWindowMove, keyboard, OUTSIDE, false, Skip(Disabled)),
+			(PointerWarp::OnFocusChange, Trigger::WindowMove, OUTSIDE, false, Skip(Disabled)),
+
+			(PointerWarp::OnFocusChange, keyboard, OUTSIDE, false, Warp(200, 150)),
+			(PointerWarp::OnWindowMove, Trigger::WindowMove, OUTSIDE, false, Warp(200, 150)),
+			(PointerWarp::Both, Trigger::FocusChange(FocusCause::WindowClosed), OUTSIDE, false, Warp(200, 150)),
+			(PointerWarp::Both, Trigger::FocusChange(FocusCause::Urgency), OUTSIDE, false, Warp(200, 150)),
+
+			(PointerWarp::Both, pointer, OUTSIDE, false, Skip(PointerInitiated)),
+			(PointerWarp::Both, keyboard, OUTSIDE, true, Skip(Grabbed)),
+			(PointerWarp::Both, Trigger::WindowMove, OUTSIDE, true, Skip(Grabbed)),
+			(PointerWarp::Both, keyboard, (100, 100), false, Skip(AlreadyInside)),
+			(PointerWarp::Both, keyboard, (299, 199), false, Skip(AlreadyInside)),
+			// The right and bottom edges are outside the window.
+			(PointerWarp::Both, keyboard, (300, 150), false, Warp(200, 150)),
+		];
+
+		for (setting, trigger, pointer, grabbed, decision) in cases {
+			assert_eq!(
+				decide(setting, trigger, RECT, pointer, grabbed),
+				decision,
+				"{setting:?} after {trigger:?} with the pointer at {pointer:?} (grabbed: {grabbed})"
+			);
+		}
+	}
+
+	/// Tests that the cause of each focus change is recorded, so that only those not caused by the
+	/// pointer warp it.
+	#[test]
+	fn causes() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+		state
+			.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(Reconfigured::Applied))
+			.unwrap();
+		assert_eq!(state.focus_cause, FocusCause::Startup);
+
+		state.set_focused_from(Some(2), FocusCause::Pointer);
+		assert_eq!(state.focus_cause, FocusCause::Pointer);
+		let trigger = Trigger::FocusChange(state.focus_cause);
+		let rect = state.tile_of(&2).unwrap();
+		assert_eq!(
+			decide(PointerWarp::Both, trigger, rect, (0, 0), false),
+			Decision::Skip(Skipped::PointerInitiated)
+		);
+
+		// Focusing the same window again doesn't change the cause.
+		state.set_focused_from(Some(2), FocusCause::Keyboard);
+		assert_eq!(state.focus_cause, FocusCause::Pointer);
+
+		// Swapping with the primary window is a keyboard-driven move: the pointer follows the window
+		// to its new tile.
+		state.perform(Action::SwapWithPrimary).unwrap();
+		state
+			.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(Reconfigured::Applied))
+			.unwrap();
+		let rect = state.tile_of(&2).unwrap();
+		assert_eq!(rect.0, 0);
+		assert_eq!(
+			decide(PointerWarp::OnWindowMove, Trigger::WindowMove, rect, (750, 150), false),
+			Decision::Warp(250, 300)
+		);
+
+		// Closing the focused window moves focus on.
+		let focused = state.focused.unwrap();
+		state.remove_window(&focused);
+		assert_eq!(state.focus_cause, FocusCause::WindowClosed);
+	}
+
+	#[test]
+	fn parse() {
+		assert_eq!("never".parse::<PointerWarp>().unwrap(), PointerWarp::Never);
+		assert_eq!(
+			" on-focus-change".parse::<PointerWarp>().unwrap(),
+			PointerWarp::OnFocusChange
+		);
+		assert_eq!(
+			"on-window-move".parse::<PointerWarp>().unwrap(),
+			PointerWarp::OnWindowMove
+		);
+		assert_eq!("both".parse::<PointerWarp>().unwrap(), PointerWarp::Both);
+
+		assert!("always".parse::<PointerWarp>().is_err());
+	}
+}
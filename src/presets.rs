@@ -0,0 +1,80 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Named [layout snapshots] saved by the user, so that window arrangements like "coding" or
+//! "review" can be recalled later.
+//!
+//! Presets are stored as JSON files named `<name>.json` in `$XDG_CONFIG_HOME/aquariwm/layouts`
+//! (or `~/.config/aquariwm/layouts` if `XDG_CONFIG_HOME` is not set).
+//!
+//! [layout snapshots]: LayoutSnapshot
+
+use std::{
+	env,
+	fs,
+	io,
+	path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::layout::LayoutSnapshot;
+
+/// An error saving or loading a preset.
+#[derive(Debug, Error)]
+pub enum PresetError {
+	/// The name can't be used as a file name.
+	#[error("invalid layout preset name {0:?}")]
+	InvalidName(String),
+	/// Neither `XDG_CONFIG_HOME` nor `HOME` is set.
+	#[error("could not find the config directory: neither `XDG_CONFIG_HOME` nor `HOME` is set")]
+	NoConfigDir,
+
+	#[error(transparent)]
+	Io(#[from] io::Error),
+	#[error(transparent)]
+	Json(#[from] serde_json::Error),
+}
+
+/// Returns the directory in which presets are stored.
+///
+/// Returns [`None`] if neither `XDG_CONFIG_HOME` nor `HOME` is set.
+pub fn dir() -> Option<PathBuf> {
+	let config = match env::var_os("XDG_CONFIG_HOME") {
+		Some(config) if !config.is_empty() => PathBuf::from(config),
+		_ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+	};
+
+	Some(config.join("aquariwm").join("layouts"))
+}
+
+/// Returns the path of the preset of the given `name` in the given `dir`.
+///
+/// Names must be non-empty, must not start with a `.`, and must not contain path separators.
+fn path(dir: &Path, name: &str) -> Result<PathBuf, PresetError> {
+	if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\', '\0']) {
+		return Err(PresetError::InvalidName(name.to_owned()));
+	}
+
+	Ok(dir.join(format!("{name}.json")))
+}
+
+/// Saves the `snapshot` as the preset of the given `name`, replacing any existing preset of that
+/// name.
+pub fn save(name: &str, snapshot: &LayoutSnapshot) -> Result<(), PresetError> {
+	let dir = dir().ok_or(PresetError::NoConfigDir)?;
+	let path = path(&dir, name)?;
+
+	fs::create_dir_all(&dir)?;
+	fs::write(path, serde_json::to_vec_pretty(snapshot)?)?;
+
+	Ok(())
+}
+
+/// Loads the preset of the given `name`.
+pub fn load(name: &str) -> Result<LayoutSnapshot, PresetError> {
+	let dir = dir().ok_or(PresetError::NoConfigDir)?;
+
+	Ok(serde_json::from_slice(&fs::read(path(&dir, name)?)?)?)
+}
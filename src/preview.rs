@@ -0,0 +1,197 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Renders [tiling layouts] as boxes of characters, e.g. to preview them in a terminal.
+//!
+//! A layout's window [tiles] are [scaled] to a grid of character cells, then [rendered] as boxes.
+//!
+//! [tiling layouts]: crate::layout::TilingLayout
+//! [tiles]: tiles
+//! [scaled]: scale_to_grid
+//! [rendered]: render
+
+use std::fmt::{Display, Write};
+
+use crate::{
+	layout::{GroupNode, NodeKind, Visibility},
+	placement::Rect,
+};
+
+/// A rectangle of character cells: its column and row, then its number of columns and rows.
+pub type CellRect = (usize, usize, usize, usize);
+
+/// Returns the rectangle of every visible window in the given `group` and its descendents, in
+/// order.
+///
+/// The rectangles are those calculated when changes were last applied to the layout.
+pub fn tiles<Window>(group: &GroupNode<Window>) -> Vec<(&Window, Rect)> {
+	group
+		.snapshot_iter()
+		.filter_map(|node| match node.kind {
+			NodeKind::Window {
+				window,
+				visibility: Visibility::Visible,
+			} => Some((window, node.rect)),
+
+			_ => None,
+		})
+		.collect()
+}
+
+/// Scales the given `rect` within `bounds` to a grid of `columns` by `rows` character cells.
+///
+/// The rectangle's edges, rather than its dimensions, are scaled, so rectangles which don't overlap
+/// don't overlap in the grid either, and rectangles which share an edge share it in the grid.
+/// Anything outside of `bounds` is clipped.
+pub fn scale_to_grid(rect: Rect, bounds: Rect, columns: usize, rows: usize) -> CellRect {
+	let (x, y, width, height) = rect;
+	let (bounds_x, bounds_y, bounds_width, bounds_height) = bounds;
+
+	// Scales a coordinate along one axis to the index of a cell along that axis.
+	let scale = |coord: i64, origin: i32, size: u32, cells: usize| -> usize {
+		if size == 0 {
+			return 0;
+		}
+
+		let offset = (coord - i64::from(origin)).clamp(0, i64::from(size)) as u128;
+		(offset * cells as u128 / u128::from(size)) as usize
+	};
+
+	let (left, right) = (
+		scale(i64::from(x), bounds_x, bounds_width, columns),
+		scale(i64::from(x) + i64::from(width), bounds_x, bounds_width, columns),
+	);
+	let (top, bottom) = (
+		scale(i64::from(y), bounds_y, bounds_height, rows),
+		scale(i64::from(y) + i64::from(height), bounds_y, bounds_height, rows),
+	);
+
+	(left, top, right - left, bottom - top)
+}
+
+/// Draws the given `boxes` with their labels on a grid of `columns` by `rows` character cells,
+/// returning the grid's lines.
+///
+/// Each box is drawn with an ASCII border, and its label is written on the first line inside it,
+/// truncated to fit. Boxes too small to have an inside are filled with `#` instead. Nothing is
+/// drawn outside of a box's cells.
+pub fn render<'label>(
+	boxes: impl IntoIterator<Item = (CellRect, &'label str)>,
+	columns: usize,
+	rows: usize,
+) -> Vec<String> {
+	let mut grid = vec![vec![' '; columns]; rows];
+
+	for ((column, row, width, height), label) in boxes {
+		// Clip the box to the grid.
+		let (right, bottom) = ((column + width).min(columns), (row + height).min(rows));
+		if column >= right || row >= bottom {
+			continue;
+		}
+		let (last_column, last_row) = (right - 1, bottom - 1);
+
+		for (y, line) in grid.iter_mut().enumerate().take(bottom).skip(row) {
+			for (x, cell) in line.iter_mut().enumerate().take(right).skip(column) {
+				let (vertical_edge, horizontal_edge) = (x == column || x == last_column, y == row || y == last_row);
+
+				*cell = match (vertical_edge, horizontal_edge) {
+					_ if right - column < 2 || bottom - row < 2 => '#',
+
+					(true, true) => '+',
+					(false, true) => '-',
+					(true, false) => '|',
+					(false, false) => ' ',
+				};
+			}
+		}
+
+		// Write the label inside the border, if there is an inside.
+		if right - column > 2 && bottom - row > 2 {
+			let inside = &mut grid[row + 1][column + 1..last_column];
+
+			for (cell, char) in inside.iter_mut().zip(label.chars()) {
+				*cell = char;
+			}
+		}
+	}
+
+	grid.into_iter().map(String::from_iter).collect()
+}
+
+/// Returns an indented outline of the given `group` and its descendents, with a line for each
+/// group's orientation and each window.
+pub fn outline<Window: Display>(group: &GroupNode<Window>) -> String {
+	let mut outline = String::new();
+
+	for node in group.snapshot_iter() {
+		let indent = node.depth * 2;
+
+		// Writing to a `String` can't fail.
+		let _ = match node.kind {
+			NodeKind::Window { window, .. } => writeln!(outline, "{:indent$}{window}", ""),
+
+			NodeKind::Group {
+				orientation, stacked, ..
+			} => {
+				let stacked = if stacked { " (stacked)" } else { "" };
+
+				writeln!(outline, "{:indent$}{orientation:?}{stacked}", "")
+			},
+		};
+	}
+
+	outline
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn no_overlap() {
+		let bounds = (0, 0, 1000, 600);
+		// Uneven tiles which don't divide evenly into cells.
+		let tiles = [
+			(0, 0, 333, 600),
+			(333, 0, 334, 199),
+			(333, 199, 334, 401),
+			(667, 0, 333, 600),
+		];
+		let (columns, rows) = (80, 24);
+
+		let cells: Vec<CellRect> = tiles
+			.into_iter()
+			.map(|tile| scale_to_grid(tile, bounds, columns, rows))
+			.collect();
+
+		// Every cell is covered by exactly one tile.
+		let mut covered = vec![vec![0; columns]; rows];
+		for (column, row, width, height) in cells {
+			for line in &mut covered[row..row + height] {
+				for cell in &mut line[column..column + width] {
+					*cell += 1;
+				}
+			}
+		}
+		assert!(covered.iter().flatten().all(|&count| count == 1));
+
+		// Tiles outside of the bounds are clipped.
+		assert_eq!(
+			scale_to_grid((-100, 300, 2000, 600), bounds, columns, rows),
+			(0, 12, 80, 12)
+		);
+	}
+
+	#[test]
+	fn labels() {
+		let lines = render(
+			[((0, 0, 6, 3), "window 1"), ((6, 0, 4, 4), "2"), ((0, 3, 1, 1), "3")],
+			10,
+			4,
+		);
+
+		// Labels are truncated to fit inside their boxes.
+		assert_eq!(lines, ["+----++--+", "|wind||2 |", "+----+|  |", "#     +--+"]);
+	}
+}
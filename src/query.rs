@@ -0,0 +1,450 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Geometry queries for scripts, e.g. to take screenshots of windows or parts of the layout with
+//! tools like `maim`.
+//!
+//! Queries are answered from what AquariWM believes the geometry to be, rather than by asking the
+//! display server, so that answers are consistent with the layout even while changes to it are
+//! waiting to be applied. In that case, the geometry the changes will give is answered, and the
+//! answer is marked as [pending].
+//!
+//! Every answer is in root coordinates: rectangles in the tiling layout are [translated] through
+//! the coordinate spaces which contain it.
+//!
+//! [pending]: Answer::pending
+//! [translated]: Spaces::to_root
+
+use std::{
+	convert::Infallible,
+	fmt::{self, Display, Formatter},
+};
+
+use crate::{
+	action,
+	layout::{self, Node, TilingLayout, Visibility},
+	output::Output,
+	placement::Rect,
+	state::{AquariWm, MapState},
+	window::{ManagedWindow, WindowId},
+};
+
+/// A query for the geometry of something AquariWM manages.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Query {
+	/// The geometry of a window (`geometry-of <window-id|mark|focused>`).
+	GeometryOf(WindowQuery),
+	/// The geometry of a group in the tiling layout (`geometry-of-group <path|focused-group>`).
+	GeometryOfGroup(GroupQuery),
+	/// The geometry of the [active output] (`focused-output-geometry`).
+	///
+	/// [active output]: crate::output::ActiveOutput
+	FocusedOutputGeometry,
+	/// The area of the workspace of the given name in which windows are tiled (`workarea
+	/// <workspace>`).
+	///
+	/// Every workspace is tiled in the same tiling layout, so they share the same work area.
+	/// AquariWM doesn't reserve space for panels' struts yet, so the work area is the whole of the
+	/// tiling layout.
+	Workarea(String),
+}
+
+/// The window a [query] is for.
+///
+/// [query]: Query::GeometryOf
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum WindowQuery {
+	/// The window with the given [ID].
+	///
+	/// [ID]: WindowId
+	Id(WindowId),
+	/// The window named by the given [mark].
+	///
+	/// [mark]: crate::marks
+	Mark(String),
+	/// The focused window.
+	Focused,
+}
+
+/// The group in the tiling layout a [query] is for.
+///
+/// [query]: Query::GeometryOfGroup
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum GroupQuery {
+	/// The group at the given [path], written as indexes separated by `/` (e.g. `1/0`); the root
+	/// group is `/`.
+	///
+	/// [path]: layout::GroupNode::path_of
+	Path(Vec<usize>),
+	/// The group containing the focused window.
+	Focused,
+}
+
+/// The answer to a [query].
+///
+/// [query]: Query
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Answer {
+	/// The geometry, in root coordinates.
+	pub rect: Rect,
+	/// Whether changes which affect the geometry are waiting to be applied.
+	///
+	/// If they are, the geometry is what it will be once they have been applied.
+	pub pending: bool,
+}
+
+/// The origins of the coordinate spaces which contain the tiling layout, each within the next.
+///
+/// Every workspace spans the whole screen rather than a single output, and the tiling layout they
+/// share spans the workspace, so these are all [`Spaces::ROOT`] for the tiling layout.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct Spaces {
+	/// The origin of the tiling layout within its workspace.
+	pub layout: (i32, i32),
+	/// The origin of the workspace within its output.
+	pub workspace: (i32, i32),
+	/// The origin of the output within the root window.
+	pub output: (i32, i32),
+}
+
+impl Spaces {
+	/// The spaces of rectangles which are already in root coordinates.
+	pub const ROOT: Self = Self {
+		layout: (0, 0),
+		workspace: (0, 0),
+		output: (0, 0),
+	};
+
+	/// Returns the spaces of rectangles relative to the given `output`.
+	pub const fn on_output(output: &Output) -> Self {
+		Self {
+			output: (output.x, output.y),
+			..Self::ROOT
+		}
+	}
+
+	/// Returns the origin of the innermost space in root coordinates.
+	pub const fn origin(&self) -> (i32, i32) {
+		let Self {
+			layout: (layout_x, layout_y),
+			workspace: (workspace_x, workspace_y),
+			output: (output_x, output_y),
+		} = *self;
+
+		(
+			layout_x.saturating_add(workspace_x).saturating_add(output_x),
+			layout_y.saturating_add(workspace_y).saturating_add(output_y),
+		)
+	}
+
+	/// Translates the given `rect` from the innermost space to root coordinates.
+	pub const fn to_root(&self, rect: Rect) -> Rect {
+		let ((origin_x, origin_y), (x, y, width, height)) = (self.origin(), rect);
+
+		(x.saturating_add(origin_x), y.saturating_add(origin_y), width, height)
+	}
+
+	/// Translates the given `rect` from root coordinates to the innermost space.
+	pub const fn from_root(&self, rect: Rect) -> Rect {
+		let ((origin_x, origin_y), (x, y, width, height)) = (self.origin(), rect);
+
+		(x.saturating_sub(origin_x), y.saturating_sub(origin_y), width, height)
+	}
+}
+
+/// Answers the given `query` about the given AquariWM `state`, or returns [`None`] if there is
+/// nothing shown for it to be answered about.
+///
+/// `resolve` returns the window with the given [ID], if it is managed, and `floating` returns
+/// the geometry of the given floating window, which only the display server knows.
+///
+/// [ID]: WindowId
+pub fn answer<Window>(
+	state: &AquariWm<Window>,
+	query: &Query,
+	resolve: impl Fn(WindowId) -> Option<Window>,
+	floating: impl Fn(&Window) -> Option<Rect>,
+) -> Option<Answer>
+where
+	Window: ManagedWindow + Send + Sync,
+{
+	let in_layout = |rect| Answer {
+		rect: Spaces::ROOT.to_root(rect),
+		pending: false,
+	};
+
+	match query {
+		Query::GeometryOf(target) => {
+			let window = match target {
+				WindowQuery::Id(id) => resolve(*id)?,
+				WindowQuery::Mark(mark) => state.marks.window(mark)?.clone(),
+				WindowQuery::Focused => state.focused.clone()?,
+			};
+			let window_state = state.windows.get(&window)?;
+
+			if window_state.mapped != MapState::Mapped || state.is_iconified(&window) {
+				return None;
+			}
+
+			match window_state.mode {
+				layout::Mode::Tiled => {
+					let (layout, pending) = projected(state)?;
+					let node = layout.node_by_id(layout.id_of_window(&window)?)?;
+
+					match node {
+						Node::Window(tile) if tile.visibility() == Visibility::Visible => Some(Answer {
+							pending,
+							..in_layout(bounds(node))
+						}),
+
+						_ => None,
+					}
+				},
+				layout::Mode::Floating => Some(Answer {
+					rect: floating(&window)?,
+					pending: false,
+				}),
+			}
+		},
+
+		Query::GeometryOfGroup(target) => {
+			let (layout, pending) = projected(state)?;
+			let path = match target {
+				GroupQuery::Path(path) => path.clone(),
+				GroupQuery::Focused => {
+					let mut path = layout.path_of(layout.id_of_window(state.focused.as_ref()?)?)?;
+					path.pop();
+
+					path
+				},
+			};
+			let group = layout.group_at(&path)?;
+
+			Some(Answer {
+				pending,
+				..in_layout(group.bounds())
+			})
+		},
+
+		Query::FocusedOutputGeometry => {
+			let output = state.active_output.get(&state.outputs)?;
+
+			Some(Answer {
+				rect: Spaces::on_output(output).to_root((0, 0, output.width, output.height)),
+				pending: false,
+			})
+		},
+
+		Query::Workarea(workspace)
+			if state
+				.workspaces
+				.names()
+				.iter()
+				.any(|name| name.to_string() == *workspace) =>
+		{
+			let (layout, pending) = projected(state)?;
+
+			Some(Answer {
+				pending,
+				..in_layout(layout.bounds())
+			})
+		},
+		Query::Workarea(_) => None,
+	}
+}
+
+/// Returns the tiling layout as it will be once its pending changes are applied, and whether there
+/// were any.
+fn projected<Window>(state: &AquariWm<Window>) -> Option<(TilingLayout<Window>, bool)>
+where
+	Window: ManagedWindow + Send + Sync,
+{
+	let mut layout = state.tiling_layout()?.clone();
+	let pending = layout.stats().pending_changes != 0;
+
+	if pending {
+		// Nothing is reconfigured, so nothing can fail.
+		let Ok(_) = layout.apply_changes(
+			&mut |_, _, _, _, _, _| Ok::<_, Infallible>(()),
+			&state.settings.scaled(state.scale),
+		);
+	}
+
+	Some((layout, pending))
+}
+
+/// Returns the bounds of the given `node`.
+const fn bounds<Window>(node: &Node<Window>) -> Rect {
+	(node.x(), node.y(), node.width(), node.height())
+}
+
+/// Queries are written as the [actions] which ask them.
+///
+/// [actions]: crate::action::Action::Query
+impl Display for Query {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::GeometryOf(WindowQuery::Id(id)) => write!(f, "geometry-of {id:#x}"),
+			Self::GeometryOf(WindowQuery::Mark(mark)) => write!(f, "geometry-of {}", action::quote(mark)),
+			Self::GeometryOf(WindowQuery::Focused) => write!(f, "geometry-of focused"),
+
+			Self::GeometryOfGroup(GroupQuery::Path(path)) => {
+				let path: Vec<_> = path.iter().map(ToString::to_string).collect();
+
+				write!(f, "geometry-of-group /{}", path.join("/"))
+			},
+			Self::GeometryOfGroup(GroupQuery::Focused) => write!(f, "geometry-of-group focused-group"),
+
+			Self::FocusedOutputGeometry => write!(f, "focused-output-geometry"),
+			Self::Workarea(workspace) => write!(f, "workarea {}", action::quote(workspace)),
+		}
+	}
+}
+
+/// Answers are written as X geometries (`WIDTHxHEIGHT+X+Y`), as taken by tools like `maim -g`,
+/// followed by `(pending)` if they are [pending].
+///
+/// [pending]: Answer::pending
+impl Display for Answer {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let (x, y, width, height) = self.rect;
+
+		write!(f, "{width}x{height}{x:+}{y:+}")?;
+		if self.pending {
+			write!(f, " (pending)")?;
+		}
+
+		Ok(())
+	}
+}
+
+impl WindowQuery {
+	/// Parses the window of a [query]: a window ID (in decimal or hexadecimal, with `0x`),
+	/// `focused`, or otherwise a [mark].
+	///
+	/// [query]: Query::GeometryOf
+	/// [mark]: crate::marks
+	pub fn parse(s: &str) -> Self {
+		match (s, s.parse().ok()) {
+			("focused", _) => Self::Focused,
+			(_, Some(id)) => Self::Id(id),
+			(mark, None) => Self::Mark(mark.to_owned()),
+		}
+	}
+}
+
+impl GroupQuery {
+	/// Parses the group of a [query]: `focused-group`, or a [path] of indexes separated by `/`.
+	///
+	/// [query]: Query::GeometryOfGroup
+	/// [path]: GroupQuery::Path
+	pub fn parse(s: &str) -> Option<Self> {
+		if s == "focused-group" {
+			return Some(Self::Focused);
+		}
+
+		s.split('/')
+			.filter(|index| !index.is_empty())
+			.map(str::parse)
+			.try_collect()
+			.ok()
+			.map(Self::Path)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		layout::{managers::Stack, LayoutSettings},
+		output::Scale,
+		state::FocusCause,
+	};
+
+	#[test]
+	fn spaces() {
+		let spaces = Spaces {
+			layout: (10, 20),
+			workspace: (5, 0),
+			output: (1920, 0),
+		};
+		let rect = (100, 50, 640, 480);
+
+		assert_eq!(spaces.to_root(rect), (2035, 70, 640, 480));
+		assert_eq!(spaces.from_root(spaces.to_root(rect)), rect);
+		assert_eq!(Spaces::ROOT.to_root(rect), rect);
+	}
+
+	#[test]
+	fn geometry() {
+		let output = |name: &str, x| Output {
+			name: name.to_owned(),
+
+			x,
+			y: 0,
+
+			width: 1920,
+			height: 1080,
+
+			primary: x == 0,
+			scale: Scale::IDENTITY,
+
+			clones: Vec::new(),
+		};
+
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 3840, 1080, LayoutSettings::new().window_gap(0));
+		state.outputs = vec![output("DP-1", 0), output("HDMI-1", 1920)];
+		state.add_windows([(1, MapState::Mapped), (2, MapState::Mapped)]);
+		state.add_window(3, MapState::Unmapped).unwrap();
+		state.windows.get_mut(&3).unwrap().set_floating();
+		state.map_window(&3).unwrap();
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+		state.set_focused_from(Some(2), FocusCause::Keyboard);
+		state.marks.mark("pip", 3);
+
+		let answer = |state: &AquariWm<u32>, query: &str| {
+			let query = match query.parse() {
+				Ok(crate::action::Action::Query(query)) => query,
+				other => panic!("`{query}` is not a query: {other:?}"),
+			};
+
+			answer(
+				state,
+				&query,
+				|id| {
+					u32::try_from(id.0)
+						.ok()
+						.filter(|window| state.windows.contains_key(window))
+				},
+				|&window| (window == 3).then_some((2000, 100, 400, 300)),
+			)
+			.map(|answer| answer.to_string())
+		};
+
+		assert_eq!(answer(&state, "geometry-of 1").as_deref(), Some("1920x1080+0+0"));
+		assert_eq!(
+			answer(&state, "geometry-of focused").as_deref(),
+			Some("1920x1080+1920+0")
+		);
+		assert_eq!(answer(&state, "geometry-of pip").as_deref(), Some("400x300+2000+100"));
+		assert_eq!(answer(&state, "geometry-of 0x4"), None);
+		assert_eq!(answer(&state, "geometry-of-group /").as_deref(), Some("3840x1080+0+0"));
+		assert_eq!(answer(&state, "geometry-of-group 0/1"), None);
+		assert_eq!(
+			answer(&state, "focused-output-geometry").as_deref(),
+			Some("1920x1080+1920+0")
+		);
+		assert_eq!(answer(&state, "workarea 1").as_deref(), Some("3840x1080+0+0"));
+		assert_eq!(answer(&state, "workarea 2"), None);
+
+		// While a new window waits to be tiled, the geometries it will give are answered.
+		state.add_window(4, MapState::Mapped).unwrap();
+		let pending = answer(&state, "geometry-of 1").unwrap();
+		assert!(pending.ends_with(" (pending)"), "{pending}");
+
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+		assert_eq!(Some(pending.replace(" (pending)", "")), answer(&state, "geometry-of 1"));
+	}
+}
@@ -0,0 +1,534 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Recording the inputs AquariWM acts on, so that hard-to-reproduce layout bugs can be replayed
+//! against the layout model without a display server.
+//!
+//! A recording is a file of JSON lines: a header, which describes the state the recording starts
+//! from, followed by one [record] for each step which changed the state. Each record gives the
+//! [input] acted on, when it was acted on, the focused window afterwards, whether changes were
+//! left to be applied, and a [fingerprint] of the state afterwards. Replaying a recording feeds the
+//! inputs through the same handlers again and compares the fingerprints, so that the first step
+//! whose state diverges from the recording is pinpointed.
+//!
+//! What the inputs are, and how they are replayed, is up to each display server; this module only
+//! deals with the format. Focus isn't part of the fingerprint: it follows the pointer and clients,
+//! which can't be replayed, so it is restored from each record instead.
+//!
+//! Lines are flushed as they are written, so a recording survives AquariWM crashing.
+//!
+//! [record]: Record
+//! [input]: Input
+//! [fingerprint]: Fingerprint
+
+use std::{
+	fmt,
+	fmt::{Display, Formatter, Write as _},
+	fs::File,
+	io::{self, BufRead, LineWriter, Write},
+	num::ParseIntError,
+	path::Path,
+	time::{Duration, Instant},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{event, Level};
+
+use crate::{
+	layout::NodeKind,
+	state::AquariWm,
+	window::{ManagedWindow, WindowId},
+};
+
+/// The version of the recording format, which is written in each recording's header.
+///
+/// Recordings of other versions aren't replayed.
+pub const VERSION: u32 = 1;
+
+/// An input to AquariWM which is recorded, as defined by a display server.
+pub trait Input: Serialize + DeserializeOwned + Display {
+	/// Returns whether the input is replayed, rather than only marking that the state was changed
+	/// by something which can't be.
+	///
+	/// Inputs which aren't replayed are only recorded if the state changed.
+	fn is_replayed(&self) -> bool;
+
+	/// Returns an input standing for a change to the state which wasn't attributed to any input.
+	fn unattributed() -> Self;
+}
+
+/// A step of a recording.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Record<Input> {
+	/// The number of the step, counting from 1: the header is step 0.
+	pub seq: u64,
+	/// The number of microseconds between the recording starting and the input being acted on.
+	pub at: u64,
+	pub input: Input,
+
+	/// The focused window after the step.
+	pub focused: Option<WindowId>,
+	/// Whether the tiling layout was left with changes which weren't applied yet.
+	pub pending: bool,
+	/// The [fingerprint] of the state after the step.
+	///
+	/// [fingerprint]: Fingerprint::of
+	pub state: Fingerprint,
+}
+
+/// The header written as the first line of a recording, with the display server's own `header`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+struct Preamble<Header> {
+	version: u32,
+	#[serde(flatten)]
+	header: Header,
+}
+
+/// A 64-bit FNV-1a hash of the [description] of AquariWM's state, which is stable between builds
+/// and platforms.
+///
+/// Fingerprints are written in hexadecimal.
+///
+/// [description]: describe
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Fingerprint(pub u64);
+
+impl Fingerprint {
+	const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+	const PRIME: u64 = 0x0100_0000_01b3;
+
+	/// Hashes the given `bytes`.
+	pub const fn hash(bytes: &[u8]) -> Self {
+		let mut hash = Self::OFFSET_BASIS;
+
+		let mut i = 0;
+		while i < bytes.len() {
+			hash ^= bytes[i] as u64;
+			hash = hash.wrapping_mul(Self::PRIME);
+
+			i += 1;
+		}
+
+		Self(hash)
+	}
+
+	/// Returns the fingerprint of the given `state`'s [description].
+	///
+	/// [description]: describe
+	pub fn of<Window: ManagedWindow + Send + Sync>(state: &AquariWm<Window>) -> Self {
+		Self::hash(describe(state).as_bytes())
+	}
+}
+
+impl Display for Fingerprint {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{:016x}", self.0)
+	}
+}
+
+impl From<Fingerprint> for String {
+	fn from(fingerprint: Fingerprint) -> Self {
+		fingerprint.to_string()
+	}
+}
+
+impl TryFrom<String> for Fingerprint {
+	type Error = ParseIntError;
+
+	fn try_from(hex: String) -> Result<Self, Self::Error> {
+		u64::from_str_radix(&hex, 16).map(Self)
+	}
+}
+
+/// Describes the given `state` as replaying compares it: the tiling layout's tree, with each node's
+/// geometry as it was last applied and whether it has changes waiting to be applied, followed by
+/// every window's [mode], whether it is mapped, and the windows which are iconified.
+///
+/// [mode]: crate::layout::Mode
+pub fn describe<Window: ManagedWindow + Send + Sync>(state: &AquariWm<Window>) -> String {
+	let mut description = String::new();
+
+	match state.tiling_layout() {
+		Some(layout) => {
+			for node in layout.snapshot_iter() {
+				let (x, y, width, height) = node.rect;
+				let indent = "  ".repeat(node.depth);
+				let pending = if node.flags.pending { " pending" } else { "" };
+
+				let _ = match node.kind {
+					NodeKind::Group {
+						orientation, stacked, ..
+					} => {
+						let stacked = if stacked { " stacked" } else { "" };
+
+						writeln!(
+							description,
+							"{indent}group {orientation:?}{stacked} {x},{y} {width}x{height}{pending}"
+						)
+					},
+					NodeKind::Window { window, visibility } => writeln!(
+						description,
+						"{indent}window {} {x},{y} {width}x{height} {visibility:?}{pending}",
+						window.id()
+					),
+				};
+			}
+		},
+
+		None => description.push_str("floating layout\n"),
+	}
+
+	let mut windows: Vec<_> = state
+		.windows
+		.iter()
+		.map(|(window, state)| (window.id(), state))
+		.collect();
+	windows.sort_unstable_by_key(|(id, _)| *id);
+
+	for (id, window_state) in windows {
+		let sticky = if window_state.sticky { " sticky" } else { "" };
+		let _ = writeln!(
+			description,
+			"{id} {:?} {:?}{sticky}",
+			window_state.mode, window_state.mapped
+		);
+	}
+
+	let iconified: Vec<_> = state.iconified().iter().map(|window| window.id().to_string()).collect();
+	let _ = writeln!(description, "iconified: {}", iconified.join(" "));
+
+	description
+}
+
+/// Returns whether the given `state`'s tiling layout has changes which haven't been applied yet.
+pub fn is_pending<Window: ManagedWindow + Send + Sync>(state: &AquariWm<Window>) -> bool {
+	state
+		.tiling_layout()
+		.is_some_and(|layout| layout.stats().pending_changes != 0)
+}
+
+/// Returns the number of whole microseconds between `start` and `now`.
+fn micros_since(start: Instant, now: Instant) -> u64 {
+	u64::try_from(now.saturating_duration_since(start).as_micros()).unwrap_or(u64::MAX)
+}
+
+/// Returns the instant the given number of microseconds after `start`, as recorded in a
+/// [record]'s `at`.
+///
+/// [record]: Record
+pub fn instant_at(start: Instant, at: u64) -> Instant {
+	start + Duration::from_micros(at)
+}
+
+/// Appends the steps which change AquariWM's state to a recording.
+///
+/// An input is [noted] as it is acted on, and recorded at the next [checkpoint], once everything
+/// it caused has been done. If writing to the recording fails, recording stops; AquariWM carries on
+/// regardless.
+///
+/// [noted]: Self::note
+/// [checkpoint]: Self::checkpoint
+#[derive(Debug)]
+pub struct Recorder<I> {
+	/// The recording, if recording.
+	file: Option<LineWriter<File>>,
+	/// When the recording started.
+	start: Instant,
+	/// The number of the last step recorded.
+	seq: u64,
+
+	/// The input acted on since the last checkpoint, and when it was acted on.
+	noted: Option<(I, Instant)>,
+	/// The fingerprint of the state and the focused window at the last checkpoint.
+	last: (Fingerprint, Option<WindowId>),
+}
+
+impl<I: Input> Recorder<I> {
+	/// Returns a recorder which doesn't record anything.
+	pub fn disabled() -> Self {
+		Self {
+			file: None,
+			start: Instant::now(),
+			seq: 0,
+
+			noted: None,
+			last: (Fingerprint(0), None),
+		}
+	}
+
+	/// Starts a recording at the given `path`, replacing any file there, from the given `state`
+	/// with the display server's `header`.
+	///
+	/// The `state` should have no changes waiting to be applied, so that replaying starts from
+	/// exactly the same geometries.
+	pub fn create<Window, Header>(path: &Path, header: &Header, state: &AquariWm<Window>) -> io::Result<Self>
+	where
+		Window: ManagedWindow + Send + Sync,
+		Header: Serialize,
+	{
+		let mut file = LineWriter::new(File::create(path)?);
+
+		let preamble = Preamble {
+			version: VERSION,
+			header,
+		};
+		serde_json::to_writer(&mut file, &preamble)?;
+		file.write_all(b"\n")?;
+
+		event!(Level::INFO, "Recording inputs to {}", path.display());
+
+		Ok(Self {
+			file: Some(file),
+			start: Instant::now(),
+			seq: 0,
+
+			noted: None,
+			last: (Fingerprint::of(state), state.focused.as_ref().map(ManagedWindow::id)),
+		})
+	}
+
+	/// Returns whether inputs are being recorded.
+	pub const fn is_recording(&self) -> bool {
+		self.file.is_some()
+	}
+
+	/// Notes the given `input`, acted on at the time `now`, to be recorded at the next
+	/// [checkpoint], replacing any input noted since the last one.
+	///
+	/// [checkpoint]: Self::checkpoint
+	pub fn note(&mut self, input: I, now: Instant) {
+		if self.is_recording() {
+			self.noted = Some((input, now));
+		}
+	}
+
+	/// Records the input [noted] since the last checkpoint with the resulting `state`.
+	///
+	/// The input is only recorded if it [is replayed] or the state changed; a change to the state
+	/// without an input is recorded as [unattributed].
+	///
+	/// [noted]: Self::note
+	/// [is replayed]: Input::is_replayed
+	/// [unattributed]: Input::unattributed
+	pub fn checkpoint<Window: ManagedWindow + Send + Sync>(&mut self, state: &AquariWm<Window>) {
+		if !self.is_recording() {
+			return;
+		}
+
+		let current = (Fingerprint::of(state), state.focused.as_ref().map(ManagedWindow::id));
+		let changed = current != self.last;
+
+		let (input, at) = match self.noted.take() {
+			Some((input, at)) if changed || input.is_replayed() => (input, at),
+			None if changed => (I::unattributed(), Instant::now()),
+
+			_ => return,
+		};
+
+		self.seq += 1;
+		let record = Record {
+			seq: self.seq,
+			at: micros_since(self.start, at),
+			input,
+
+			focused: current.1,
+			pending: is_pending(state),
+			state: current.0,
+		};
+		self.last = current;
+
+		if let Err(error) = self.write(&record) {
+			event!(Level::ERROR, "Stopped recording inputs: {error}");
+
+			self.file = None;
+		}
+	}
+
+	/// Writes the given `record` as a line of the recording.
+	fn write(&mut self, record: &Record<I>) -> io::Result<()> {
+		let Some(file) = &mut self.file else {
+			return Ok(());
+		};
+
+		serde_json::to_writer(&mut *file, record)?;
+		file.write_all(b"\n")
+	}
+}
+
+/// Reads a recording, returning its display server's header and its records.
+pub fn read<Header, I>(reader: impl BufRead) -> Result<(Header, Vec<Record<I>>), ReplayError>
+where
+	Header: DeserializeOwned,
+	I: Input,
+{
+	let mut lines = reader.lines().enumerate().filter(|(_, line)| {
+		// Blank lines, like a trailing one, are skipped.
+		!line.as_ref().is_ok_and(|line| line.trim().is_empty())
+	});
+
+	let (_, first) = lines.next().ok_or(ReplayError::Empty)?;
+	let preamble: Preamble<Header> =
+		serde_json::from_str(&first?).map_err(|error| ReplayError::Parse { line: 1, error })?;
+	if preamble.version != VERSION {
+		return Err(ReplayError::Version {
+			found: preamble.version,
+		});
+	}
+
+	let records = lines
+		.map(|(index, line)| {
+			serde_json::from_str(&line?).map_err(|error| ReplayError::Parse { line: index + 1, error })
+		})
+		.try_collect::<Vec<_>>()?;
+
+	Ok((preamble.header, records))
+}
+
+/// An error returned when replaying a recording fails.
+#[derive(Debug, Error)]
+pub enum ReplayError {
+	#[error(transparent)]
+	Io(#[from] io::Error),
+
+	/// The recording has no header.
+	#[error("the recording is empty")]
+	Empty,
+	/// A line of the recording couldn't be parsed.
+	#[error("line {line} of the recording is invalid: {error}")]
+	Parse { line: usize, error: serde_json::Error },
+	/// The recording was written in another version of the format.
+	#[error("the recording is version {found}, but only version {VERSION} recordings can be replayed")]
+	Version { found: u32 },
+
+	/// The state after replaying a step didn't match the recorded state.
+	#[error("step {seq} ({input}) diverged from the recording: expected state {expected}, found {found}")]
+	Diverged {
+		seq: u64,
+		input: String,
+		expected: Fingerprint,
+		found: Fingerprint,
+	},
+	/// The state was changed by a step which can't be replayed, so the rest of the recording can't
+	/// be replayed either.
+	#[error("step {seq} ({input}) changed the state, but it can't be replayed")]
+	NotReplayed { seq: u64, input: String },
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::BTreeMap, env, fs};
+
+	use super::*;
+	use crate::{layout::managers::Stack, state::MapState};
+
+	#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+	enum Mock {
+		Map(u32),
+		Other,
+	}
+
+	impl Display for Mock {
+		fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+			write!(f, "{self:?}")
+		}
+	}
+
+	impl Input for Mock {
+		fn is_replayed(&self) -> bool {
+			matches!(self, Self::Map(_))
+		}
+
+		fn unattributed() -> Self {
+			Self::Other
+		}
+	}
+
+	fn state(windows: &[u32]) -> AquariWm<u32> {
+		let mut state = AquariWm::with_tiling_layout_and_windows::<Stack<u32>>(
+			0,
+			0,
+			1200,
+			600,
+			windows.iter().map(|&window| (window, MapState::Mapped)),
+			Default::default(),
+		);
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+
+		state
+	}
+
+	/// Tests that fingerprints are stable, and only change with what they describe.
+	#[test]
+	fn fingerprint() {
+		// The FNV-1a test vectors.
+		assert_eq!(Fingerprint::hash(b""), Fingerprint(0xcbf2_9ce4_8422_2325));
+		assert_eq!(Fingerprint::hash(b"a"), Fingerprint(0xaf63_dc4c_8601_ec8c));
+
+		let mut state = state(&[1, 2]);
+		let before = Fingerprint::of(&state);
+		assert_eq!(Fingerprint::of(&self::state(&[1, 2])), before);
+
+		// Focus isn't part of the state replaying compares.
+		state.focused = Some(2);
+		assert_eq!(Fingerprint::of(&state), before);
+
+		state.resize(0, 0, 1000, 600);
+		assert_ne!(Fingerprint::of(&state), before);
+
+		assert_eq!(
+			serde_json::to_string(&Fingerprint(0xab)).unwrap(),
+			r#""00000000000000ab""#
+		);
+	}
+
+	#[test]
+	fn record_and_read() {
+		let path = env::temp_dir().join(format!("aquariwm-replay-{}.jsonl", std::process::id()));
+		let mut state = state(&[1]);
+
+		let mut recorder = Recorder::create(&path, &BTreeMap::from([("root", 1)]), &state).unwrap();
+		let start = recorder.start;
+
+		// Inputs which aren't replayed are left out unless the state changed.
+		recorder.note(Mock::Other, start);
+		recorder.checkpoint(&state);
+		recorder.note(Mock::Map(2), start + Duration::from_millis(5));
+		state.add_window(2, MapState::Mapped).unwrap();
+		recorder.checkpoint(&state);
+		state.focused = Some(2);
+		recorder.checkpoint(&state);
+		recorder.checkpoint(&state);
+
+		let file = io::BufReader::new(File::open(&path).unwrap());
+		let (header, records): (BTreeMap<String, u32>, Vec<Record<Mock>>) = read(file).unwrap();
+		fs::remove_file(&path).unwrap();
+
+		assert_eq!(header, BTreeMap::from([("root".to_owned(), 1)]));
+		assert_eq!(records.len(), 2);
+		assert_eq!((records[0].seq, records[0].at), (1, 5000));
+		assert_eq!(records[0].input, Mock::Map(2));
+		assert!(records[0].pending);
+		assert_eq!(records[0].state, Fingerprint::of(&state));
+
+		assert_eq!(records[1].input, Mock::Other);
+		assert_eq!(records[1].focused, Some(WindowId(2)));
+	}
+
+	#[test]
+	fn invalid() {
+		let read = |recording: &str| read::<BTreeMap<String, u32>, Mock>(recording.as_bytes());
+
+		assert!(matches!(read(""), Err(ReplayError::Empty)));
+		assert!(matches!(
+			read(r#"{"version":0}"#),
+			Err(ReplayError::Version { found: 0 })
+		));
+		assert!(matches!(
+			read("{\"version\":1}\n\n{\"seq\":1}"),
+			Err(ReplayError::Parse { line: 3, .. })
+		));
+	}
+}
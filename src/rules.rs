@@ -0,0 +1,633 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Rules which change where windows are placed according to their class and title, like floating
+//! picture-in-picture windows.
+//!
+//! Rules are evaluated when a window is mapped and, if they [ask for it], whenever its title
+//! changes, since browsers retitle their windows as their tabs change. The first matching rule
+//! applies. When a rule applies, what it changes is recorded, so that the window can be reverted
+//! to where it was if the rule stops matching: a tiled window which was floated is returned to its
+//! tile, and a window which was moved to another output is moved back.
+//!
+//! [ask for it]: Rule::reevaluate_on_title_change
+
+use std::{collections::HashMap, mem, str::FromStr};
+
+use thiserror::Error;
+use tracing::{event, Level};
+
+use crate::{
+	ignore,
+	layout,
+	placement::Rect,
+	placement_memory::Placement,
+	state::{AquariWm, MapState},
+	window::ManagedWindow,
+};
+
+/// A rule which changes where matching windows are placed.
+///
+/// Rules are parsed from `<action>:<conditions>`, where the action is `float`, `tile`, or
+/// `output=<name>`, and the conditions are a comma-separated list of `class=<glob>` and
+/// `title=<glob>`, optionally followed by `retitle` to [re-evaluate the rule] when the window's
+/// title changes, e.g. `float:title=*Picture-in-Picture*,retitle`.
+///
+/// [re-evaluate the rule]: Self::reevaluate_on_title_change
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Rule {
+	/// A glob which the window's class must match, if any.
+	pub class: Option<String>,
+	/// A glob which the window's title must match, if any.
+	pub title: Option<String>,
+
+	/// What the rule changes about matching windows.
+	pub action: RuleAction,
+	/// Whether the rule is evaluated again whenever the window's title changes, rather than only
+	/// when it is mapped.
+	pub reevaluate_on_title_change: bool,
+}
+
+/// What a [rule] changes about the windows it matches.
+///
+/// [rule]: Rule
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum RuleAction {
+	/// The window floats above the tiling layout.
+	Float,
+	/// The window is tiled, even if it would otherwise float.
+	Tile,
+	/// The window is moved to the output of the given name.
+	///
+	/// AquariWM has no workspaces yet, so windows are moved between outputs instead.
+	Output(String),
+}
+
+/// The [rules] and which of them have applied to which windows.
+///
+/// [rules]: Rule
+#[derive(Debug, Clone)]
+pub struct Rules<Window> {
+	rules: Vec<Rule>,
+	/// The rule which has applied to each window, and what it changed.
+	applied: HashMap<Window, Applied>,
+}
+
+/// A [rule] which has applied to a window.
+///
+/// [rule]: Rule
+#[derive(Debug, Clone)]
+struct Applied {
+	/// The index of the rule.
+	rule: usize,
+	/// Where the window was before the rule applied.
+	previous: Previous,
+}
+
+/// Where a window was before a [rule] applied to it, which it is reverted to when the rule stops
+/// matching.
+///
+/// [rule]: Rule
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Previous {
+	/// Whether the window was tiled or floating.
+	pub mode: layout::Mode,
+	/// Where the window was, if it was mapped.
+	pub placement: Option<Placement>,
+}
+
+/// What changed about a window's placement when its [rules] were evaluated.
+///
+/// The tiling layout is updated by the rules themselves; the display server is responsible for
+/// giving windows which newly float a geometry.
+///
+/// [rules]: Rules
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Transition {
+	/// Where the window was reverted to, if the rule which applied to it stopped matching.
+	pub reverted: Option<Previous>,
+	/// The action of the rule which newly applied to the window, if any.
+	pub applied: Option<RuleAction>,
+}
+
+impl Rule {
+	/// Returns whether the rule matches a window with the given `class` and `title`.
+	pub fn matches(&self, class: Option<&str>, title: Option<&str>) -> bool {
+		let matches = |glob: &Option<String>, text: Option<&str>| match glob {
+			Some(glob) => text.is_some_and(|text| ignore::glob_matches(glob, text)),
+			None => true,
+		};
+
+		matches(&self.class, class) && matches(&self.title, title)
+	}
+}
+
+impl Transition {
+	/// Returns whether nothing changed.
+	#[inline]
+	pub const fn is_unchanged(&self) -> bool {
+		self.reverted.is_none() && self.applied.is_none()
+	}
+}
+
+impl<Window: ManagedWindow + Send + Sync> Rules<Window> {
+	/// Creates the given `rules`, which haven't applied to any windows yet.
+	pub fn new(rules: Vec<Rule>) -> Self {
+		Self {
+			rules,
+			applied: HashMap::new(),
+		}
+	}
+
+	/// Returns the rules.
+	#[inline(always)]
+	pub fn rules(&self) -> &[Rule] {
+		&self.rules
+	}
+
+	/// Replaces the rules with the given `rules` all at once, as when the [configuration] is
+	/// reloaded.
+	///
+	/// Windows keep the rules which applied to them if those rules are among the new rules, so that
+	/// they can still be reverted when the rules stop matching. Windows whose rules were removed
+	/// stay where they are.
+	///
+	/// [configuration]: crate::config
+	pub fn replace(&mut self, rules: Vec<Rule>) {
+		let old = mem::replace(&mut self.rules, rules);
+
+		self.applied.retain(
+			|_, applied| match self.rules.iter().position(|rule| *rule == old[applied.rule]) {
+				Some(index) => {
+					applied.rule = index;
+
+					true
+				},
+				None => false,
+			},
+		);
+	}
+
+	/// Returns whether any of the rules are [re-evaluated on title changes].
+	///
+	/// [re-evaluated on title changes]: Rule::reevaluate_on_title_change
+	pub fn reevaluated_on_title_change(&self) -> bool {
+		self.rules.iter().any(|rule| rule.reevaluate_on_title_change)
+	}
+
+	/// Returns the rule which has applied to the given `window`, if any.
+	pub fn applied_to(&self, window: &Window) -> Option<&Rule> {
+		self.applied.get(window).map(|applied| &self.rules[applied.rule])
+	}
+
+	/// Evaluates every rule for the given `window`, which has just been mapped with the given
+	/// `class` and `title`.
+	///
+	/// `floating` is the window's geometry if it is floating.
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`](AquariWm::apply_changes) must be called.
+	pub fn on_map(
+		&mut self,
+		state: &mut AquariWm<Window>,
+		window: &Window,
+		class: Option<&str>,
+		title: Option<&str>,
+		floating: Option<Rect>,
+	) -> Transition {
+		self.evaluate(state, window, class, title, floating, false)
+	}
+
+	/// Evaluates the rules which are [re-evaluated on title changes] for the given `window`, whose
+	/// title has changed to `title`.
+	///
+	/// A rule which applied when the window was mapped and isn't re-evaluated keeps applying.
+	///
+	/// `floating` is the window's geometry if it is floating.
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`](AquariWm::apply_changes) must be called.
+	///
+	/// [re-evaluated on title changes]: Rule::reevaluate_on_title_change
+	pub fn on_title_change(
+		&mut self,
+		state: &mut AquariWm<Window>,
+		window: &Window,
+		class: Option<&str>,
+		title: Option<&str>,
+		floating: Option<Rect>,
+	) -> Transition {
+		self.evaluate(state, window, class, title, floating, true)
+	}
+
+	/// Forgets which rule applied to the given `window`, because it was unmapped or destroyed.
+	///
+	/// If the window is still tracked, it gets back the [mode] it had before the rule applied, so
+	/// that the rule can apply afresh when the window is mapped again.
+	///
+	/// [mode]: layout::Mode
+	pub fn withdraw(&mut self, state: &mut AquariWm<Window>, window: &Window) {
+		let Some(applied) = self.applied.remove(window) else {
+			return;
+		};
+
+		if let Some(window_state) = state.windows.get_mut(window) {
+			if window_state.mapped == MapState::Unmapped {
+				window_state.mode = applied.previous.mode;
+			}
+		}
+	}
+
+	fn evaluate(
+		&mut self,
+		state: &mut AquariWm<Window>,
+		window: &Window,
+		class: Option<&str>,
+		title: Option<&str>,
+		floating: Option<Rect>,
+		title_changed: bool,
+	) -> Transition {
+		let current = self.applied.get(window).map(|applied| applied.rule);
+
+		if title_changed && current.is_some_and(|rule| !self.rules[rule].reevaluate_on_title_change) {
+			return Transition::default();
+		}
+
+		let matching = self
+			.rules
+			.iter()
+			.position(|rule| (!title_changed || rule.reevaluate_on_title_change) && rule.matches(class, title));
+		if matching == current {
+			return Transition::default();
+		}
+
+		let reverted = self.revert(state, window);
+		// The window's floating geometry is stale once it is reverted to its tile.
+		let floating = match state.windows.get(window) {
+			Some(window_state) if window_state.mode == layout::Mode::Floating => floating,
+			_ => None,
+		};
+		let applied = matching.and_then(|rule| self.apply(state, window, rule, floating));
+
+		Transition { reverted, applied }
+	}
+
+	/// Applies the rule at the given index to the given mapped `window`, recording where the
+	/// window was beforehand.
+	fn apply(
+		&mut self,
+		state: &mut AquariWm<Window>,
+		window: &Window,
+		rule: usize,
+		floating: Option<Rect>,
+	) -> Option<RuleAction> {
+		let mode = state.windows.get(window)?.mode;
+		let previous = Previous {
+			mode,
+			placement: Placement::capture(state, window, floating),
+		};
+		previous.placement.as_ref()?;
+
+		let action = self.rules[rule].action.clone();
+		event!(Level::DEBUG, "Applying rule {rule} to a window: {action:?}");
+
+		match (&action, mode) {
+			(RuleAction::Float, layout::Mode::Tiled) => {
+				state.float_window(window);
+			},
+			(RuleAction::Tile, layout::Mode::Floating) => {
+				state.unfloat_window(window);
+			},
+			(RuleAction::Output(name), layout::Mode::Tiled) => {
+				if state.output_of(window).is_none_or(|output| output.name != *name) {
+					state.move_window_to_output(window, name);
+				}
+			},
+
+			// Floating windows are moved between outputs by the display server.
+			(RuleAction::Output(_), layout::Mode::Floating)
+			| (RuleAction::Float, layout::Mode::Floating)
+			| (RuleAction::Tile, layout::Mode::Tiled) => (),
+		}
+
+		self.applied.insert(window.clone(), Applied { rule, previous });
+
+		Some(action)
+	}
+
+	/// Reverts the given `window` to where it was before the rule which applied to it, if any,
+	/// returning where that was.
+	fn revert(&mut self, state: &mut AquariWm<Window>, window: &Window) -> Option<Previous> {
+		let Applied { rule, previous } = self.applied.remove(window)?;
+		let mode = state.windows.get(window)?.mode;
+
+		event!(Level::DEBUG, "Rule {rule} no longer applies to a window; reverting it");
+
+		match (previous.mode, mode) {
+			(layout::Mode::Tiled, layout::Mode::Floating) => {
+				state.unfloat_window(window);
+			},
+			(layout::Mode::Floating, layout::Mode::Tiled) => {
+				state.float_window(window);
+			},
+
+			_ => (),
+		}
+		// Tiled windows which were floated return to their tile; windows which were moved to
+		// another output are moved back along with the windows there, as the layout of the output
+		// they left has probably changed since.
+		if let (layout::Mode::Tiled, Some(placement)) = (previous.mode, &previous.placement) {
+			match &self.rules[rule].action {
+				RuleAction::Output(_) => {
+					let name = placement.output(&state.outputs).map(|output| output.name.clone());
+
+					if let Some(name) =
+						name.filter(|name| state.output_of(window).is_none_or(|output| output.name != *name))
+					{
+						state.move_window_to_output(window, &name);
+					}
+				},
+
+				RuleAction::Float | RuleAction::Tile => {
+					placement.restore(state, window);
+				},
+			}
+		}
+
+		Some(previous)
+	}
+}
+
+/// An error returned when parsing a [`Rule`] fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum ParseRuleError {
+	/// There was no `:` between the action and the conditions.
+	#[error("expected `<action>:<conditions>`")]
+	MissingConditions,
+	/// The action wasn't `float`, `tile`, or `output=<name>`.
+	#[error("expected `float`, `tile`, or `output=<name>`, found {0:?}")]
+	Action(String),
+	/// A condition wasn't `class=<glob>`, `title=<glob>`, or `retitle`.
+	#[error("expected `class=<glob>`, `title=<glob>`, or `retitle`, found {0:?}")]
+	Condition(String),
+	/// There was neither a class nor a title to match.
+	#[error("expected a class or a title to match")]
+	Unconditional,
+}
+
+impl FromStr for Rule {
+	type Err = ParseRuleError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (action, conditions) = s.split_once(':').ok_or(ParseRuleError::MissingConditions)?;
+
+		let action = match action.trim() {
+			"float" => RuleAction::Float,
+			"tile" => RuleAction::Tile,
+
+			other => match other.strip_prefix("output=") {
+				Some(name) if !name.is_empty() => RuleAction::Output(name.to_owned()),
+				_ => return Err(ParseRuleError::Action(other.to_owned())),
+			},
+		};
+
+		let mut rule = Self {
+			class: None,
+			title: None,
+
+			action,
+			reevaluate_on_title_change: false,
+		};
+		for condition in conditions.split(',') {
+			match condition.split_once('=') {
+				Some(("class", glob)) if !glob.is_empty() => rule.class = Some(glob.to_owned()),
+				Some(("title", glob)) if !glob.is_empty() => rule.title = Some(glob.to_owned()),
+				None if condition.trim() == "retitle" => rule.reevaluate_on_title_change = true,
+
+				_ => return Err(ParseRuleError::Condition(condition.to_owned())),
+			}
+		}
+
+		match (&rule.class, &rule.title) {
+			(None, None) => Err(ParseRuleError::Unconditional),
+			_ => Ok(rule),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		layout::{managers::Stack, LayoutSettings},
+		output::{Output, Scale},
+	};
+
+	const PIP: &str = "Picture-in-Picture";
+
+	fn state(windows: &[u32]) -> AquariWm<u32> {
+		AquariWm::with_tiling_layout_and_windows::<Stack<u32>>(
+			0,
+			0,
+			1920,
+			1080,
+			windows.iter().map(|&window| (window, MapState::Mapped)),
+			LayoutSettings::default(),
+		)
+	}
+
+	fn tiled(state: &AquariWm<u32>) -> Vec<u32> {
+		state.tiling_layout().unwrap().windows().into_iter().copied().collect()
+	}
+
+	fn mode(state: &AquariWm<u32>, window: u32) -> layout::Mode {
+		state.windows[&window].mode
+	}
+
+	#[test]
+	fn parse() {
+		assert_eq!(
+			format!("float:title=*{PIP}*,retitle").parse(),
+			Ok(Rule {
+				class: None,
+				title: Some(format!("*{PIP}*")),
+				action: RuleAction::Float,
+				reevaluate_on_title_change: true,
+			})
+		);
+		assert_eq!(
+			"output=HDMI-1:class=firefox,title=Meet - *".parse(),
+			Ok(Rule {
+				class: Some("firefox".to_owned()),
+				title: Some("Meet - *".to_owned()),
+				action: RuleAction::Output("HDMI-1".to_owned()),
+				reevaluate_on_title_change: false,
+			})
+		);
+
+		assert_eq!("float".parse::<Rule>(), Err(ParseRuleError::MissingConditions));
+		assert_eq!(
+			"sink:class=firefox".parse::<Rule>(),
+			Err(ParseRuleError::Action("sink".to_owned()))
+		);
+		assert_eq!(
+			"float:name=firefox".parse::<Rule>(),
+			Err(ParseRuleError::Condition("name=firefox".to_owned()))
+		);
+		assert_eq!("tile:retitle".parse::<Rule>(), Err(ParseRuleError::Unconditional));
+	}
+
+	#[test]
+	fn float_cycles() {
+		let mut state = state(&[1, 2, 3]);
+		let mut rules = Rules::new(vec![format!("float:title=*{PIP}*,retitle").parse().unwrap()]);
+		assert_eq!(tiled(&state), [1, 2, 3]);
+
+		for _ in 0..3 {
+			// The window floats once its title matches...
+			let transition = rules.on_title_change(&mut state, &2, None, Some(PIP), None);
+			assert_eq!(transition.applied, Some(RuleAction::Float));
+			assert_eq!(mode(&state, 2), layout::Mode::Floating);
+			assert_eq!(tiled(&state), [1, 3]);
+
+			// ...doesn't change while it keeps matching...
+			let transition = rules.on_title_change(&mut state, &2, None, Some(&format!("{PIP} - 2")), None);
+			assert!(transition.is_unchanged());
+
+			// ...and returns to its tile once it no longer matches.
+			let transition = rules.on_title_change(&mut state, &2, None, Some("Video"), Some((5, 5, 10, 10)));
+			assert_eq!(
+				transition.reverted.map(|previous| previous.mode),
+				Some(layout::Mode::Tiled)
+			);
+			assert_eq!(mode(&state, 2), layout::Mode::Tiled);
+			assert_eq!(tiled(&state), [1, 2, 3]);
+			assert!(rules.applied.is_empty());
+		}
+	}
+
+	#[test]
+	fn rules_on_map() {
+		let mut state = state(&[1]);
+		let mut rules = Rules::new(vec![
+			"tile:class=Dialog".parse().unwrap(),
+			format!("float:title=*{PIP}*,retitle").parse().unwrap(),
+		]);
+
+		// Rules which aren't re-evaluated keep applying whatever the window's title changes to.
+		state.add_window(2, MapState::Unmapped).unwrap();
+		state.windows.get_mut(&2).unwrap().set_floating();
+		state.map_window(&2).unwrap();
+		let transition = rules.on_map(&mut state, &2, Some("Dialog"), Some(PIP), Some((0, 0, 100, 100)));
+		assert_eq!(transition.applied, Some(RuleAction::Tile));
+		assert_eq!(tiled(&state), [1, 2]);
+		assert!(rules
+			.on_title_change(&mut state, &2, Some("Dialog"), Some(PIP), None)
+			.is_unchanged());
+
+		// Windows which are unmapped while a rule applies get their mode back, so that the rule
+		// applies afresh when they are mapped again.
+		state.unmap_window(&2);
+		rules.withdraw(&mut state, &2);
+		assert_eq!(mode(&state, 2), layout::Mode::Floating);
+		assert!(rules.applied_to(&2).is_none());
+
+		// Rules which are re-evaluated can stop applying, even if they applied on map.
+		state.add_window(3, MapState::Unmapped).unwrap();
+		state.map_window(&3).unwrap();
+		assert_eq!(
+			rules.on_map(&mut state, &3, None, Some(PIP), None).applied,
+			Some(RuleAction::Float)
+		);
+		assert_eq!(tiled(&state), [1]);
+		assert!(rules
+			.on_title_change(&mut state, &3, None, Some("Video"), Some((0, 0, 100, 100)))
+			.reverted
+			.is_some());
+		assert_eq!(tiled(&state), [1, 3]);
+
+		// Destroyed windows leave nothing behind.
+		rules.on_title_change(&mut state, &3, None, Some(PIP), None);
+		state.remove_window(&3);
+		rules.withdraw(&mut state, &3);
+		assert!(rules.applied.is_empty());
+	}
+
+	/// Tests that windows keep the rules which applied to them across a reload only if the rules
+	/// are still present.
+	#[test]
+	fn replace() {
+		let mut state = state(&[1]);
+		let float: Rule = format!("float:title=*{PIP}*,retitle").parse().unwrap();
+		let mut rules = Rules::new(vec![float.clone()]);
+
+		for window in [2, 3] {
+			state.add_window(window, MapState::Unmapped).unwrap();
+			state.map_window(&window).unwrap();
+			rules.on_map(&mut state, &window, None, Some(PIP), None);
+		}
+		assert_eq!(tiled(&state), [1]);
+
+		rules.replace(vec!["tile:class=Dialog".parse().unwrap(), float]);
+		assert_eq!(
+			rules.applied_to(&2).map(|rule| rule.action.clone()),
+			Some(RuleAction::Float)
+		);
+
+		// The float rule still applies, so the window is returned to its tile when it stops matching.
+		rules.on_title_change(&mut state, &2, None, Some("Video"), Some((0, 0, 100, 100)));
+		assert_eq!(tiled(&state), [1, 2]);
+
+		// Without the rule, the window stays floating.
+		rules.replace(Vec::new());
+		assert!(rules.applied_to(&3).is_none());
+		assert!(rules
+			.on_title_change(&mut state, &3, None, Some("Video"), Some((0, 0, 100, 100)))
+			.is_unchanged());
+		assert_eq!(tiled(&state), [1, 2]);
+	}
+
+	#[test]
+	fn output_cycles() {
+		let output = |name: &str, x, primary| Output {
+			name: name.to_owned(),
+
+			x,
+			y: 0,
+
+			width: 1920,
+			height: 1080,
+
+			primary,
+			scale: Scale::IDENTITY,
+
+			clones: Vec::new(),
+		};
+
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 3840, 1080, LayoutSettings::new().window_gap(0));
+		state.outputs = vec![output("DP-1", 0, false), output("HDMI-1", 1920, true)];
+		state.add_windows((1..=4).map(|window| (window, MapState::Mapped)));
+
+		let apply_changes = |state: &mut AquariWm<u32>| {
+			state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+		};
+		let output_of = |state: &AquariWm<u32>, window| state.output_of(&window).map(|output| output.name.clone());
+		apply_changes(&mut state);
+		assert_eq!(output_of(&state, 4).as_deref(), Some("HDMI-1"));
+
+		let mut rules = Rules::new(vec!["output=DP-1:title=Meet - *,retitle".parse().unwrap()]);
+		for _ in 0..2 {
+			rules.on_title_change(&mut state, &4, None, Some("Meet - standup"), None);
+			apply_changes(&mut state);
+			assert_eq!(output_of(&state, 4).as_deref(), Some("DP-1"));
+
+			// The window is moved back to its tile when the rule stops matching.
+			rules.on_title_change(&mut state, &4, None, Some("Inbox"), None);
+			apply_changes(&mut state);
+			assert_eq!(output_of(&state, 4).as_deref(), Some("HDMI-1"));
+			assert!(rules.applied.is_empty());
+		}
+	}
+}
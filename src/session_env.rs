@@ -0,0 +1,392 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Exporting the display AquariWM manages into the session environment.
+//!
+//! Programs launched by AquariWM inherit its environment, but those started by services don't:
+//! systemd user units, D-Bus activated programs and portals like `xdg-desktop-portal` are started
+//! with the environment of the [systemd user manager] and of the [D-Bus activation environment]
+//! respectively, which know nothing of the display unless they are told. Once AquariWM has
+//! registered as the window manager, it exports `DISPLAY` (and `XAUTHORITY`, if it is set) into
+//! both, as other window managers do, by running:
+//!
+//! - `systemctl --user set-environment DISPLAY=<display>`, and
+//! - `dbus-update-activation-environment DISPLAY=<display>`.
+//!
+//! Each is only run if its executable and the session it updates are [found], and each is
+//! allowed to fail on its own: failures are logged, as AquariWM manages windows just as well
+//! without them.
+//!
+//! [systemd user manager]: Step::Systemd
+//! [D-Bus activation environment]: Step::Activation
+//! [found]: Session::detect
+
+use std::{
+	env,
+	ffi::{OsStr, OsString},
+	fmt::{self, Display, Formatter},
+	fs,
+	io,
+	os::unix::fs::PermissionsExt,
+	path::{Path, PathBuf},
+	process,
+};
+
+use thiserror::Error;
+use tracing::{event, Level};
+
+/// The variables exported into the session environment, besides `DISPLAY`, if they are set.
+const INHERITED: &[&str] = &["XAUTHORITY"];
+
+/// Where the session environment is kept, as [detected] from AquariWM's own environment.
+///
+/// [detected]: Self::detect
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct Session {
+	/// The `systemctl` executable, if a systemd user manager is running.
+	pub systemctl: Option<PathBuf>,
+	/// The `dbus-update-activation-environment` executable, if a D-Bus session bus is running.
+	pub dbus_update: Option<PathBuf>,
+}
+
+/// A step of exporting the session environment.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Step {
+	/// Setting the environment of the systemd user manager, with which user units are started.
+	Systemd,
+	/// Updating the D-Bus activation environment, with which D-Bus activated programs are started.
+	Activation,
+}
+
+/// A command which [exports] variables into the session environment.
+///
+/// [exports]: export
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Invocation {
+	/// The step the command carries out.
+	pub step: Step,
+	/// The executable which is run.
+	pub program: PathBuf,
+	/// The arguments it is run with.
+	pub args: Vec<OsString>,
+}
+
+/// An error returned when a [step] of exporting the session environment fails.
+///
+/// [step]: Step
+#[derive(Debug, Error)]
+pub enum ExportError {
+	/// The executable couldn't be run.
+	#[error("failed to run {}: {source}", program.display())]
+	Spawn { program: PathBuf, source: io::Error },
+	/// The executable couldn't reach the session, e.g. because the bus has gone away since it was
+	/// detected.
+	#[error("{} couldn't reach the session: {message}", program.display())]
+	Unreachable { program: PathBuf, message: String },
+	/// The executable ran, but failed for another reason.
+	#[error("{} failed ({status}): {message}", program.display())]
+	Failed {
+		program: PathBuf,
+		status: process::ExitStatus,
+		message: String,
+	},
+}
+
+impl Display for Step {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Systemd => write!(f, "the systemd user manager"),
+			Self::Activation => write!(f, "the D-Bus activation environment"),
+		}
+	}
+}
+
+impl Session {
+	/// Detects the session from AquariWM's environment.
+	///
+	/// A systemd user manager is running if its private socket exists in `$XDG_RUNTIME_DIR`, and a
+	/// D-Bus session bus if `DBUS_SESSION_BUS_ADDRESS` is set or its socket exists in
+	/// `$XDG_RUNTIME_DIR`. The executables are searched for in `PATH`.
+	pub fn detect() -> Self {
+		let runtime_dir = env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+
+		Self::detect_in(
+			env::var_os("PATH").as_deref(),
+			runtime_dir.as_deref(),
+			env::var_os("DBUS_SESSION_BUS_ADDRESS").as_deref(),
+		)
+	}
+
+	/// Detects the session from the given `PATH`, runtime directory and session bus address.
+	fn detect_in(path: Option<&OsStr>, runtime_dir: Option<&Path>, bus_address: Option<&OsStr>) -> Self {
+		let in_runtime_dir = |name: &str| runtime_dir.is_some_and(|dir| dir.join(name).exists());
+
+		let systemd = in_runtime_dir("systemd/private");
+		let bus = bus_address.is_some_and(|address| !address.is_empty()) || in_runtime_dir("bus");
+
+		Self {
+			systemctl: systemd.then(|| executable(path, "systemctl")).flatten(),
+			dbus_update: bus
+				.then(|| executable(path, "dbus-update-activation-environment"))
+				.flatten(),
+		}
+	}
+
+	/// Returns whether there is anywhere to export the session environment to.
+	pub const fn is_empty(&self) -> bool {
+		self.systemctl.is_none() && self.dbus_update.is_none()
+	}
+
+	/// Returns the commands which export the given `variables` into this session, one for each step
+	/// which is available.
+	pub fn invocations(&self, variables: &[(&str, &OsStr)]) -> Vec<Invocation> {
+		let assignments: Vec<OsString> = variables
+			.iter()
+			.map(|(name, value)| {
+				let mut assignment = OsString::from(format!("{name}="));
+				assignment.push(value);
+
+				assignment
+			})
+			.collect();
+
+		let systemd = self.systemctl.as_ref().map(|program| Invocation {
+			step: Step::Systemd,
+			program: program.clone(),
+			args: ["--user", "set-environment"]
+				.into_iter()
+				.map(OsString::from)
+				.chain(assignments.iter().cloned())
+				.collect(),
+		});
+		let activation = self.dbus_update.as_ref().map(|program| Invocation {
+			step: Step::Activation,
+			program: program.clone(),
+			args: assignments.clone(),
+		});
+
+		systemd.into_iter().chain(activation).collect()
+	}
+}
+
+impl Invocation {
+	/// Runs the command, waiting for it to finish.
+	pub fn run(&self) -> Result<(), ExportError> {
+		let output = process::Command::new(&self.program)
+			.args(&self.args)
+			.stdin(process::Stdio::null())
+			.output()
+			.map_err(|source| ExportError::Spawn {
+				program: self.program.clone(),
+				source,
+			})?;
+
+		match output.status.success() {
+			true => Ok(()),
+			false => Err(classify(
+				&self.program,
+				output.status,
+				String::from_utf8_lossy(&output.stderr).trim(),
+			)),
+		}
+	}
+}
+
+/// Classifies the failure of the given `program`, which exited with the given `status` and the
+/// given `message` on its standard error.
+fn classify(program: &Path, status: process::ExitStatus, message: &str) -> ExportError {
+	// What `systemctl` and libdbus say when there is nothing listening on the socket.
+	const UNREACHABLE: &[&str] = &[
+		"Failed to connect to bus",
+		"Failed to connect to user scope bus",
+		"Unable to connect to the session bus",
+		"Connection refused",
+		"No such file or directory",
+	];
+
+	let program = program.to_owned();
+	let message = message.to_owned();
+
+	match UNREACHABLE.iter().any(|pattern| message.contains(pattern)) {
+		true => ExportError::Unreachable { program, message },
+		false => ExportError::Failed {
+			program,
+			status,
+			message,
+		},
+	}
+}
+
+/// Returns the path of the executable with the given `name` in the given `PATH`, if there is one.
+fn executable(path: Option<&OsStr>, name: &str) -> Option<PathBuf> {
+	env::split_paths(path?).map(|dir| dir.join(name)).find(|candidate| {
+		fs::metadata(candidate).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+	})
+}
+
+/// Exports the given `display`, along with the [inherited] variables which are set, into the
+/// given `session`, logging each step's success or failure.
+///
+/// [inherited]: INHERITED
+pub fn export(session: &Session, display: &str) {
+	if session.is_empty() {
+		event!(
+			Level::DEBUG,
+			"Neither a systemd user manager nor a D-Bus session bus was found; not exporting the session environment"
+		);
+
+		return;
+	}
+
+	let inherited: Vec<_> = INHERITED
+		.iter()
+		.filter_map(|&name| Some((name, env::var_os(name)?)))
+		.collect();
+	let variables: Vec<_> = [("DISPLAY", OsStr::new(display))]
+		.into_iter()
+		.chain(inherited.iter().map(|(name, value)| (*name, value.as_os_str())))
+		.collect();
+
+	// `display` can't be named in `event!`, which shadows it.
+	let exported = format!("DISPLAY={display}");
+
+	for invocation in session.invocations(&variables) {
+		match invocation.run() {
+			Ok(()) => event!(Level::INFO, "Exported {exported} into {}", invocation.step),
+			Err(error) => event!(
+				Level::WARN,
+				"Failed to export the session environment into {}: {error}",
+				invocation.step
+			),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::os::unix::process::ExitStatusExt;
+
+	use super::*;
+
+	/// Returns a fresh directory for a test, after clearing it.
+	fn test_dir(test: &str) -> PathBuf {
+		let dir = env::temp_dir().join(format!("aquariwm-session-env-{test}-{}", process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+
+		dir
+	}
+
+	/// Creates an empty file at the given `path` with the given permission `mode`.
+	fn touch(path: &Path, mode: u32) {
+		fs::create_dir_all(path.parent().unwrap()).unwrap();
+		fs::write(path, "").unwrap();
+		fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+	}
+
+	/// Tests that each step is only available if both its executable and its session are found.
+	#[test]
+	fn detection() {
+		let dir = test_dir("detection");
+		let (bin, runtime_dir) = (dir.join("bin"), dir.join("runtime"));
+		let path = env::join_paths([dir.join("missing"), bin.clone()]).unwrap();
+		fs::create_dir_all(&runtime_dir).unwrap();
+
+		touch(&bin.join("systemctl"), 0o755);
+		// Not executable.
+		touch(&bin.join("dbus-update-activation-environment"), 0o644);
+
+		let detect = |bus_address: Option<&str>| {
+			Session::detect_in(Some(&path), Some(&runtime_dir), bus_address.map(OsStr::new))
+		};
+
+		// Neither the user manager nor the bus is running.
+		assert!(detect(Some("")).is_empty());
+
+		touch(&runtime_dir.join("systemd/private"), 0o600);
+		assert_eq!(
+			detect(Some("unix:path=/run/user/1000/bus")),
+			Session {
+				systemctl: Some(bin.join("systemctl")),
+				dbus_update: None,
+			}
+		);
+
+		touch(&bin.join("dbus-update-activation-environment"), 0o755);
+		assert_eq!(
+			detect(Some("unix:path=/run/user/1000/bus")).dbus_update,
+			Some(bin.join("dbus-update-activation-environment"))
+		);
+		// The bus's socket in the runtime directory is enough to find it.
+		assert!(detect(None).dbus_update.is_none());
+		touch(&runtime_dir.join("bus"), 0o600);
+		assert!(detect(None).dbus_update.is_some());
+
+		assert!(Session::detect_in(None, None, Some(OsStr::new("unix:path=/bus"))).is_empty());
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	/// Tests that the commands export every variable, and only into the available steps.
+	#[test]
+	fn invocations() {
+		let session = Session {
+			systemctl: Some(PathBuf::from("/usr/bin/systemctl")),
+			dbus_update: Some(PathBuf::from("/usr/bin/dbus-update-activation-environment")),
+		};
+		let variables = [("DISPLAY", OsStr::new(":1")), ("XAUTHORITY", OsStr::new("/tmp/.Xauth"))];
+
+		assert_eq!(
+			session.invocations(&variables),
+			[
+				Invocation {
+					step: Step::Systemd,
+					program: PathBuf::from("/usr/bin/systemctl"),
+					args: ["--user", "set-environment", "DISPLAY=:1", "XAUTHORITY=/tmp/.Xauth"]
+						.map(OsString::from)
+						.into(),
+				},
+				Invocation {
+					step: Step::Activation,
+					program: PathBuf::from("/usr/bin/dbus-update-activation-environment"),
+					args: ["DISPLAY=:1", "XAUTHORITY=/tmp/.Xauth"].map(OsString::from).into(),
+				},
+			]
+		);
+
+		let systemd_only = Session {
+			dbus_update: None,
+			..session
+		};
+		let invocations = systemd_only.invocations(&variables[..1]);
+		assert_eq!(invocations.len(), 1);
+		assert_eq!(invocations[0].args, ["--user", "set-environment", "DISPLAY=:1"]);
+
+		assert!(Session::default().invocations(&variables).is_empty());
+	}
+
+	/// Tests that failures to reach the session are told apart from other failures, and that
+	/// executables which can't be run are reported as such.
+	#[test]
+	fn errors() {
+		let program = Path::new("systemctl");
+		let status = process::ExitStatus::from_raw(1 << 8);
+
+		assert!(matches!(
+			classify(program, status, "Failed to connect to bus: No medium found"),
+			ExportError::Unreachable { .. }
+		));
+		assert!(matches!(
+			classify(program, status, "Invalid environment assignments"),
+			ExportError::Failed { .. }
+		));
+
+		let missing = Invocation {
+			step: Step::Systemd,
+			program: PathBuf::from("/nonexistent/systemctl"),
+			args: Vec::new(),
+		};
+		assert!(matches!(missing.run(), Err(ExportError::Spawn { .. })));
+	}
+}
@@ -0,0 +1,330 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The stacking order of floating windows.
+//!
+//! Tiled windows don't overlap, so their order in the window stack doesn't matter; they are kept
+//! below the floating windows. Floating windows do overlap, and clients order their own floating
+//! windows relative to each other, e.g. to keep a toolbox above its document window.
+//!
+//! AquariWM keeps its own model of the floating windows' order in a [`Layer`]. Restacks happen in
+//! the model first, where a window can only be moved relative to the other windows in the layer,
+//! so a floating window can never be pushed below the tiled windows. The [operations] to realize
+//! the model's new order are then sent to the display server.
+//!
+//! [operations]: operations
+
+/// Which side of its sibling a window is [restacked] on.
+///
+/// [restacked]: Restack
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Position {
+	/// Directly above the sibling.
+	Above,
+	/// Directly below the sibling.
+	Below,
+}
+
+/// An operation which moves a `window` directly [above] or [below] its `sibling` in the stack.
+///
+/// [above]: Position::Above
+/// [below]: Position::Below
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Restack<Window> {
+	pub window: Window,
+	pub position: Position,
+	pub sibling: Window,
+}
+
+/// The stacking order of a layer of windows, from the bottom to the top.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Layer<Window> {
+	order: Vec<Window>,
+}
+
+impl<Window> Default for Layer<Window> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<Window> Layer<Window> {
+	/// Creates an empty layer.
+	pub const fn new() -> Self {
+		Self { order: Vec::new() }
+	}
+
+	/// Returns the windows in the layer, from the bottom to the top.
+	pub fn order(&self) -> &[Window] {
+		&self.order
+	}
+}
+
+impl<Window: PartialEq + Clone> Layer<Window> {
+	/// Updates the layer to contain exactly the given `windows`.
+	///
+	/// Windows which are still in the layer keep their order; windows which are new to it are put
+	/// on top, as newly mapped windows are.
+	pub fn sync(&mut self, windows: impl IntoIterator<Item = Window>) {
+		let windows: Vec<_> = windows.into_iter().collect();
+
+		self.order.retain(|window| windows.contains(window));
+		for window in windows {
+			if !self.order.contains(&window) {
+				self.order.push(window);
+			}
+		}
+	}
+
+	/// Moves the given `window` to the given `position` relative to its `sibling`, returning the
+	/// operations to realize the new order.
+	///
+	/// Only the given `window` is moved, so there is at most one operation: directly above the
+	/// window which ends up below it, or directly below the window which ends up above it if it is
+	/// moved to the bottom.
+	///
+	/// If there is no `sibling`, or it isn't another window in the layer, the window is moved to
+	/// the top of the layer if the `position` is [above], or to the bottom if it is [below], as the
+	/// ICCCM says for stacking requests without a sibling. If the `window` isn't in the layer,
+	/// nothing is moved.
+	///
+	/// [above]: Position::Above
+	/// [below]: Position::Below
+	pub fn restack(&mut self, window: &Window, position: Position, sibling: Option<&Window>) -> Vec<Restack<Window>> {
+		let Some(from) = self.order.iter().position(|other| other == window) else {
+			return Vec::new();
+		};
+		let window = self.order.remove(from);
+		let sibling = sibling
+			.filter(|&sibling| sibling != &window)
+			.and_then(|sibling| self.order.iter().position(|other| other == sibling));
+
+		let to = match (position, sibling) {
+			(Position::Above, Some(sibling)) => sibling + 1,
+			(Position::Below, Some(sibling)) => sibling,
+
+			(Position::Above, None) => self.order.len(),
+			(Position::Below, None) => 0,
+		};
+		self.order.insert(to, window.clone());
+
+		if to == from {
+			return Vec::new();
+		}
+		let restack = match to.checked_sub(1) {
+			Some(below) => Restack {
+				window,
+				position: Position::Above,
+				sibling: self.order[below].clone(),
+			},
+			None => Restack {
+				window,
+				position: Position::Below,
+				sibling: self.order[1].clone(),
+			},
+		};
+
+		vec![restack]
+	}
+
+	/// Moves the given `window` to the top of the layer, returning the operations to do so.
+	pub fn raise(&mut self, window: &Window) -> Vec<Restack<Window>> {
+		self.restack(window, Position::Above, None)
+	}
+}
+
+/// Returns a minimal list of operations which turn the `old` order of a layer into the `new`
+/// order of the same windows, both from the bottom to the top.
+///
+/// This realizes arbitrary reorders of a layer; a single [restack] needs at most one operation.
+///
+/// The longest run of windows which keep their relative order stays put; every other window is
+/// moved once, directly above the window below it in the `new` order (or below the window above
+/// it, for the bottom window). The operations must be performed in order.
+///
+/// [restack]: Layer::restack
+pub fn operations<Window: PartialEq + Clone>(old: &[Window], new: &[Window]) -> Vec<Restack<Window>> {
+	let indexes: Vec<_> = new
+		.iter()
+		.map(|window| old.iter().position(|other| other == window))
+		.collect();
+	let stable = longest_increasing(&indexes);
+
+	let mut restacks = Vec::new();
+	for (i, window) in new.iter().enumerate() {
+		if stable[i] {
+			continue;
+		}
+
+		let restack = match i.checked_sub(1) {
+			Some(below) => Restack {
+				window: window.clone(),
+				position: Position::Above,
+				sibling: new[below].clone(),
+			},
+
+			// The windows between the bottom window and the first window which stays put are moved
+			// above it in turn, so the bottom window is placed below that window.
+			None => match stable.iter().position(|&stable| stable) {
+				Some(above) => Restack {
+					window: window.clone(),
+					position: Position::Below,
+					sibling: new[above].clone(),
+				},
+				None => continue,
+			},
+		};
+		restacks.push(restack);
+	}
+
+	restacks
+}
+
+/// Returns which of the given `indexes` are in a longest strictly increasing subsequence of them.
+///
+/// Missing indexes are never part of the subsequence.
+fn longest_increasing(indexes: &[Option<usize>]) -> Vec<bool> {
+	// The length of the longest subsequence ending at each index, and the index before it.
+	let mut lengths = vec![0; indexes.len()];
+	let mut previous = vec![None; indexes.len()];
+
+	for (i, index) in indexes.iter().enumerate() {
+		let Some(index) = index else {
+			continue;
+		};
+		lengths[i] = 1;
+
+		for j in 0..i {
+			if indexes[j].is_some_and(|other| other < *index) && lengths[j] + 1 > lengths[i] {
+				lengths[i] = lengths[j] + 1;
+				previous[i] = Some(j);
+			}
+		}
+	}
+
+	let mut stable = vec![false; indexes.len()];
+	let mut end = (0..indexes.len())
+		.filter(|&i| lengths[i] > 0)
+		.max_by_key(|&i| lengths[i]);
+	while let Some(i) = end {
+		stable[i] = true;
+		end = previous[i];
+	}
+
+	stable
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Performs the given `restacks` on the given `order`.
+	fn perform(order: &[u32], restacks: &[Restack<u32>]) -> Vec<u32> {
+		let mut order = order.to_vec();
+
+		for restack in restacks {
+			let from = order.iter().position(|&window| window == restack.window).unwrap();
+			order.remove(from);
+
+			let sibling = order.iter().position(|&window| window == restack.sibling).unwrap();
+			match restack.position {
+				Position::Above => order.insert(sibling + 1, restack.window),
+				Position::Below => order.insert(sibling, restack.window),
+			}
+		}
+
+		order
+	}
+
+	fn layer(order: &[u32]) -> Layer<u32> {
+		let mut layer = Layer::new();
+		layer.sync(order.iter().copied());
+
+		layer
+	}
+
+	#[test]
+	fn restack() {
+		let mut layer = layer(&[1, 2, 3, 4]);
+
+		// Relative to a sibling.
+		assert_eq!(
+			layer.restack(&1, Position::Above, Some(&3)),
+			[Restack {
+				window: 1,
+				position: Position::Above,
+				sibling: 3
+			}]
+		);
+		assert_eq!(layer.order(), [2, 3, 1, 4]);
+
+		assert_eq!(
+			layer.restack(&4, Position::Below, Some(&2)),
+			[Restack {
+				window: 4,
+				position: Position::Below,
+				sibling: 2
+			}]
+		);
+		assert_eq!(layer.order(), [4, 2, 3, 1]);
+
+		// Siblings which aren't in the layer fall back to the top or bottom of the layer.
+		assert_eq!(
+			layer.restack(&2, Position::Below, Some(&9)),
+			[Restack {
+				window: 2,
+				position: Position::Below,
+				sibling: 4
+			}]
+		);
+		assert_eq!(layer.order(), [2, 4, 3, 1]);
+		assert_eq!(layer.restack(&3, Position::Above, Some(&3)).len(), 1);
+		assert_eq!(layer.order(), [2, 4, 1, 3]);
+
+		// Nothing is sent when the order doesn't change, or the window isn't in the layer.
+		assert!(layer.raise(&3).is_empty());
+		assert!(layer.restack(&4, Position::Above, Some(&2)).is_empty());
+		assert!(layer.restack(&9, Position::Below, None).is_empty());
+		assert_eq!(layer.order(), [2, 4, 1, 3]);
+
+		// The operations realize the layer's new order.
+		for (window, position, sibling) in [
+			(3, Position::Below, Some(2)),
+			(2, Position::Above, Some(4)),
+			(4, Position::Below, None),
+		] {
+			let old = layer.order().to_vec();
+			let restacks = layer.restack(&window, position, sibling.as_ref());
+
+			assert_eq!(perform(&old, &restacks), layer.order());
+		}
+	}
+
+	#[test]
+	fn sync() {
+		let mut layer = layer(&[1, 2, 3]);
+
+		layer.sync([3, 4, 1]);
+		assert_eq!(layer.order(), [1, 3, 4]);
+	}
+
+	#[test]
+	fn minimal_operations() {
+		let cases: [(&[u32], &[u32], usize); 6] = [
+			(&[1, 2, 3], &[1, 2, 3], 0),
+			(&[1, 2, 3], &[3, 1, 2], 1),
+			(&[1, 2, 3], &[2, 3, 1], 1),
+			(&[1, 2, 3, 4, 5], &[5, 4, 3, 2, 1], 4),
+			(&[1, 2, 3, 4, 5], &[2, 1, 4, 3, 5], 2),
+			(&[1, 2, 3, 4, 5, 6], &[4, 5, 1, 2, 6, 3], 3),
+		];
+
+		for (old, new, moves) in cases {
+			let restacks = operations(old, new);
+
+			assert_eq!(perform(old, &restacks), new, "{old:?} -> {new:?}: {restacks:?}");
+			assert_eq!(restacks.len(), moves, "{old:?} -> {new:?}: {restacks:?}");
+		}
+	}
+}
@@ -2,23 +2,121 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::{collections::HashMap, hash::Hash};
+use std::{
+	collections::{HashMap, HashSet},
+	time::Instant,
+};
 
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
 #[cfg(feature = "async")]
 use {futures::future, std::future::Future};
 
-use crate::layout::{self, CurrentLayout, LayoutSettings};
+use crate::{
+	action::{Action, ActionError, ActionResult, Outcome},
+	admission::Admission,
+	autosave::{PersistedWorkspace, WorkspaceSnapshot},
+	desktop::Desktop,
+	ignore::IgnoreList,
+	layout::{
+		self,
+		managers::BuiltIn,
+		AddWindowError,
+		AppliedChange,
+		CurrentLayout,
+		DuplicateWindows,
+		Increments,
+		InsertionStrategy,
+		LayoutSettings,
+		ManagerPanic,
+		Reconfigured,
+		Slot,
+		SwapFocus,
+		Visibility,
+	},
+	marks::Marks,
+	moveresize,
+	output::{ActiveOutput, FocusSource, Output, OutputAssignment, OutputEdges, Scale},
+	placement::Rect,
+	presets,
+	switcher::{FocusHistory, Key, Scope, Session, Step},
+	window::ManagedWindow,
+	workspaces::{Policy, WorkspaceError, Workspaces},
+};
 
+/// What keyboard actions like [moving] or [resizing] act on: the [`focused`] window, or a group
+/// containing it which was selected with [`FocusParent`].
+///
+/// [moving]: Action::MoveWindowToOutput
+/// [resizing]: Action::ResizeFocused
+/// [`focused`]: AquariWm::focused
+/// [`FocusParent`]: Action::FocusParent
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum FocusTarget<Window> {
+	/// The focused window.
+	Window(Window),
+	/// The group with the given ID in the tiling layout, and every window within it.
+	Group(layout::NodeId),
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum MapState {
 	Mapped,
 	Unmapped,
 }
 
+/// What caused the [`focused`] window to change.
+///
+/// Policies which react to focus changes can use this to tell the user's explicit requests apart
+/// from focus following the pointer, or from focus moving on because a window went away.
+///
+/// [`focused`]: AquariWm::focused
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum FocusCause {
+	/// Focus followed the pointer.
+	Pointer,
+	/// The user explicitly changed focus, e.g. with a key binding or a window switcher.
+	Keyboard,
+	/// A client, like a taskbar, asked for a window to be activated.
+	Activation,
+	/// The focused window was closed, or otherwise went away (e.g. it was minimized), so focus
+	/// moved on.
+	WindowClosed,
+	/// An [urgent] window stole focus.
+	///
+	/// [urgent]: crate::urgency
+	Urgency,
+	/// Focus hasn't changed since AquariWM started.
+	#[default]
+	Startup,
+}
+
+impl FocusCause {
+	/// Returns whether the [active output] follows a focus change of this cause [explicitly], or
+	/// as it follows the pointer.
+	///
+	/// [active output]: AquariWm::active_output
+	/// [explicitly]: FocusSource::Explicit
+	#[inline]
+	pub const fn source(self) -> FocusSource {
+		match self {
+			Self::Pointer => FocusSource::Pointer,
+			_ => FocusSource::Explicit,
+		}
+	}
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct WindowState {
 	pub mode: layout::Mode,
 	pub mapped: MapState,
+
+	/// Whether the window is shown on every workspace, rather than only its own.
+	pub sticky: bool,
+	/// Where the window is in being [admitted] into the tiling layout.
+	///
+	/// [admitted]: crate::admission
+	pub admission: Admission,
 }
 
 impl WindowState {
@@ -27,12 +125,21 @@ impl WindowState {
 		Self {
 			mode: layout::Mode::default(),
 			mapped,
+
+			sticky: false,
+			admission: Admission::Admitted,
 		}
 	}
 
 	#[inline]
 	pub const fn with_layout_mode(mode: layout::Mode, mapped: MapState) -> Self {
-		Self { mode, mapped }
+		Self {
+			mode,
+			mapped,
+
+			sticky: false,
+			admission: Admission::Admitted,
+		}
 	}
 
 	#[inline]
@@ -45,6 +152,11 @@ impl WindowState {
 		self.mode = layout::Mode::Tiled;
 	}
 
+	#[inline]
+	pub fn set_sticky(&mut self, sticky: bool) {
+		self.sticky = sticky;
+	}
+
 	#[inline]
 	pub fn set_unmapped(&mut self) {
 		self.mapped = MapState::Unmapped;
@@ -56,29 +168,157 @@ impl WindowState {
 	}
 }
 
-pub struct AquariWm<Window: Eq + Hash + Clone + 'static> {
+/// What changed when [switching] to a workspace or [moving] a window between workspaces, which the
+/// display server must show.
+///
+/// [switching]: AquariWm::switch_to_workspace
+/// [moving]: AquariWm::move_window_to_workspace
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WorkspaceChange<Window> {
+	/// The windows which were hidden, as they are no longer on the current workspace, in the order
+	/// they were hidden.
+	pub hidden: Vec<Window>,
+	/// The windows which were shown again, as they are now on the current workspace, in the order
+	/// they were shown.
+	pub shown: Vec<Window>,
+	/// The windows whose desktops changed, with the desktops they are now on.
+	pub desktops: Vec<(Window, Desktop)>,
+}
+
+pub struct AquariWm<Window: ManagedWindow + 'static> {
 	/// The current window layout.
 	pub layout: CurrentLayout<Window>,
 	pub settings: LayoutSettings,
+	/// The [scale factor] of the output on which the layout is displayed.
+	///
+	/// [scale factor]: Scale
+	pub scale: Scale,
 
 	/// A [`HashMap`] of windows and their current [`WindowState`s].
 	///
 	/// [`WindowState`s]: WindowState
 	pub windows: HashMap<Window, WindowState>,
+	/// The window which currently has input focus, if any.
+	pub focused: Option<Window>,
+	/// What caused the [`focused`] window to last change.
+	///
+	/// [`focused`]: Self::focused
+	pub focus_cause: FocusCause,
+	/// The groups selected with [`FocusParent`], innermost first, the first containing the
+	/// [`focused`] window and each containing the one before it.
+	///
+	/// The last is the [focus target], if it still contains the [`focused`] window.
+	///
+	/// [`FocusParent`]: Action::FocusParent
+	/// [`focused`]: Self::focused
+	/// [focus target]: Self::focus_target
+	focused_parents: Vec<layout::NodeId>,
+	/// The windows which are [ignored]: passed through by the display server rather than managed.
+	///
+	/// [ignored]: crate::ignore
+	pub ignored: IgnoreList,
+	/// The names given to windows, by which they can be [focused] or [swapped with].
+	///
+	/// [focused]: Action::FocusMark
+	/// [swapped with]: Action::SwapWithMark
+	pub marks: Marks<Window>,
+	/// The windows which are [iconified], oldest first.
+	///
+	/// Iconified windows stay on their workspaces until they are restored, which brings them to the
+	/// current workspace.
+	///
+	/// [iconified]: Self::iconify_window
+	iconified: Vec<Window>,
+	/// The windows which are [away] on workspaces other than the current one, in the order they
+	/// were hidden.
+	///
+	/// [away]: Self::is_away
+	away: Vec<Window>,
+	/// The [workspaces], and the desktop each window is on while it is mapped, iconified or [away].
+	///
+	/// [workspaces]: crate::workspaces
+	/// [away]: Self::is_away
+	pub workspaces: Workspaces<Window>,
+
+	/// The currently connected outputs.
+	pub outputs: Vec<Output>,
+	/// The [output] which is currently active.
+	///
+	/// [output]: Output
+	pub active_output: ActiveOutput,
+	/// Whether [focusing outputs in a direction] wraps around at the edges of the outputs.
+	///
+	/// [focusing outputs in a direction]: Action::FocusOutput
+	pub output_edges: OutputEdges,
+
+	/// The windows in the order they were most recently focused.
+	pub focus_history: FocusHistory<Window>,
+	/// Which windows are cycled through when [switching windows].
+	///
+	/// [switching windows]: Self::switch_windows
+	pub switch_scope: Scope,
+	/// The ongoing [window switching] session, if any.
+	///
+	/// [window switching]: Self::switch_windows
+	switcher: Option<Session<Window>>,
+
+	/// The last time the layout manager panicked, if it has, after which the layout [fell back] to
+	/// the [`Append`] layout manager.
+	///
+	/// [fell back]: CurrentLayout::isolate
+	/// [`Append`]: layout::managers::Append
+	pub manager_panic: Option<ManagerPanic>,
+
+	/// Whether the layout has changed since [`take_changed`] was last called.
+	///
+	/// [`take_changed`]: Self::take_changed
+	changed: bool,
+}
+
+impl<Window> Default for WorkspaceChange<Window> {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			hidden: Vec::new(),
+			shown: Vec::new(),
+			desktops: Vec::new(),
+		}
+	}
 }
 
-impl<Window: Eq + Hash + Clone> Default for AquariWm<Window> {
+impl<Window: ManagedWindow> Default for AquariWm<Window> {
 	#[inline]
 	fn default() -> Self {
 		Self {
 			layout: Default::default(),
 			settings: Default::default(),
+			scale: Default::default(),
 			windows: Default::default(),
+			focused: None,
+			focus_cause: FocusCause::Startup,
+			focused_parents: Vec::new(),
+			ignored: IgnoreList::new(),
+			marks: Marks::new(),
+			iconified: Vec::new(),
+			away: Vec::new(),
+			workspaces: Workspaces::new(Policy::default()),
+
+			outputs: Vec::new(),
+			active_output: ActiveOutput::default(),
+			output_edges: OutputEdges::default(),
+
+			focus_history: FocusHistory::new(),
+			switch_scope: Scope::default(),
+			switcher: None,
+
+			manager_panic: None,
+
+			changed: false,
 		}
 	}
 }
 
-impl<Window: Eq + Hash + Clone> AquariWm<Window> {
+impl<Window: ManagedWindow + Send + Sync> AquariWm<Window> {
 	/// Creates a new AquariWM state struct with the default [`CurrentLayout`] and no windows.
 	#[inline]
 	pub fn new(settings: LayoutSettings) -> Self {
@@ -98,8 +338,29 @@ impl<Window: Eq + Hash + Clone> AquariWm<Window> {
 		Self {
 			layout: CurrentLayout::new_tiled::<Manager>(x, y, width, height, &settings),
 			settings,
+			scale: Scale::default(),
 
 			windows: HashMap::new(),
+			focused: None,
+			focus_cause: FocusCause::Startup,
+			focused_parents: Vec::new(),
+			ignored: IgnoreList::new(),
+			marks: Marks::new(),
+			iconified: Vec::new(),
+			away: Vec::new(),
+			workspaces: Workspaces::new(Policy::default()),
+
+			outputs: Vec::new(),
+			active_output: ActiveOutput::default(),
+			output_edges: OutputEdges::default(),
+
+			focus_history: FocusHistory::new(),
+			switch_scope: Scope::default(),
+			switcher: None,
+
+			manager_panic: None,
+
+			changed: false,
 		}
 	}
 
@@ -109,8 +370,29 @@ impl<Window: Eq + Hash + Clone> AquariWm<Window> {
 		let mut aquariwm = Self {
 			layout: CurrentLayout::default(),
 			settings,
+			scale: Scale::default(),
 
 			windows: HashMap::new(),
+			focused: None,
+			focus_cause: FocusCause::Startup,
+			focused_parents: Vec::new(),
+			ignored: IgnoreList::new(),
+			marks: Marks::new(),
+			iconified: Vec::new(),
+			away: Vec::new(),
+			workspaces: Workspaces::new(Policy::default()),
+
+			outputs: Vec::new(),
+			active_output: ActiveOutput::default(),
+			output_edges: OutputEdges::default(),
+
+			focus_history: FocusHistory::new(),
+			switch_scope: Scope::default(),
+			switcher: None,
+
+			manager_panic: None,
+
+			changed: false,
 		};
 
 		aquariwm.add_windows(windows);
@@ -133,8 +415,29 @@ impl<Window: Eq + Hash + Clone> AquariWm<Window> {
 		let mut aquariwm = Self {
 			layout: CurrentLayout::new_tiled::<Manager>(x, y, width, height, &settings),
 			settings,
+			scale: Scale::default(),
 
 			windows: HashMap::new(),
+			focused: None,
+			focus_cause: FocusCause::Startup,
+			focused_parents: Vec::new(),
+			ignored: IgnoreList::new(),
+			marks: Marks::new(),
+			iconified: Vec::new(),
+			away: Vec::new(),
+			workspaces: Workspaces::new(Policy::default()),
+
+			outputs: Vec::new(),
+			active_output: ActiveOutput::default(),
+			output_edges: OutputEdges::default(),
+
+			focus_history: FocusHistory::new(),
+			switch_scope: Scope::default(),
+			switcher: None,
+
+			manager_panic: None,
+
+			changed: false,
 		};
 
 		aquariwm.add_windows(windows);
@@ -142,158 +445,3667 @@ impl<Window: Eq + Hash + Clone> AquariWm<Window> {
 		aquariwm
 	}
 
-	pub fn add_window(&mut self, window: Window, mapped: MapState) {
-		let state = WindowState::new(mapped);
+	/// Creates a new AquariWM state struct with the given `layout` and `windows`, adopting the
+	/// mapped windows' existing coordinates and dimensions.
+	///
+	/// Unlike with [`with_tiling_layout_and_windows`], windows which were already open keep their
+	/// relative sizes in the layout, as far as the layout manager [allows].
+	///
+	/// [`with_tiling_layout_and_windows`]: Self::with_tiling_layout_and_windows
+	/// [allows]: layout::TilingLayoutManager::init_with_geometries
+	pub fn with_tiling_layout_and_geometries<Manager>(
+		x: i32,
+		y: i32,
+		width: u32,
+		height: u32,
+		windows: impl IntoIterator<Item = (Window, MapState, (i32, i32, u32, u32))>,
+		settings: LayoutSettings,
+	) -> Self
+	where
+		Manager: layout::TilingLayoutManager<Window>,
+	{
+		let mut windows: Vec<_> = windows.into_iter().collect();
+		// Windows listed twice are only adopted once.
+		let mut seen = HashSet::new();
+		windows.retain(|(window, ..)| seen.insert(window.clone()));
 
-		if state.mode == layout::Mode::Tiled && state.mapped == MapState::Mapped {
-			if let CurrentLayout::Tiled(manager) = &mut self.layout {
-				manager.add_window(window.clone());
+		let tiled: Vec<_> = windows
+			.iter()
+			.filter(|(_, mapped, _)| *mapped == MapState::Mapped)
+			.map(|(window, _, geometry)| (window.clone(), *geometry))
+			.collect();
+		let changed = !tiled.is_empty();
+
+		let mut aquariwm = Self {
+			layout: CurrentLayout::tiled_with_geometries::<Manager, _>(x, y, width, height, tiled, &settings),
+			settings,
+			scale: Scale::default(),
+
+			windows: HashMap::new(),
+			focused: None,
+			focus_cause: FocusCause::Startup,
+			focused_parents: Vec::new(),
+			ignored: IgnoreList::new(),
+			marks: Marks::new(),
+			iconified: Vec::new(),
+			away: Vec::new(),
+			workspaces: Workspaces::new(Policy::default()),
+
+			outputs: Vec::new(),
+			active_output: ActiveOutput::default(),
+			output_edges: OutputEdges::default(),
+
+			focus_history: FocusHistory::new(),
+			switch_scope: Scope::default(),
+			switcher: None,
+
+			manager_panic: None,
+
+			changed,
+		};
+
+		for (window, mapped, _) in windows {
+			aquariwm.focus_history.add(window.clone());
+			if mapped == MapState::Mapped {
+				aquariwm.workspaces.add_window(window.clone(), Desktop::Index(0));
 			}
+			aquariwm.windows.insert(window, WindowState::new(mapped));
 		}
 
-		self.windows.insert(window, state);
+		aquariwm
 	}
 
+	/// Creates a new AquariWM state struct with the given restored tiling `layout`, resized to the
+	/// given coordinates and dimensions, and the given `windows`.
+	///
+	/// Windows in the restored `layout` which no longer exist are removed from it, and existing
+	/// windows which are not in the `layout` are added to it as usual. Windows in the `layout` are
+	/// considered [mapped] even if they are not currently mapped, as they may have been unmapped
+	/// to hide them; the display server is responsible for mapping them again.
+	///
+	/// [mapped]: MapState::Mapped
 	#[inline]
-	pub fn add_windows(&mut self, windows: impl IntoIterator<Item = (Window, MapState)>) {
+	pub fn with_restored_tiling_layout<Manager>(
+		layout: layout::TilingLayout<Window>,
+		rect: (i32, i32, u32, u32),
+		windows: impl IntoIterator<Item = (Window, MapState)>,
+		settings: LayoutSettings,
+	) -> Self
+	where
+		Manager: layout::TilingLayoutManager<Window>,
+	{
+		let manager = Box::new(Manager::init(layout, std::iter::empty()));
+
+		Self::with_restored_manager(manager, rect, windows, settings)
+	}
+
+	/// Creates a new AquariWM state struct with the given layout `manager` of a restored tiling
+	/// layout, as with [`with_restored_tiling_layout`], e.g. for a layout manager chosen by name.
+	///
+	/// [`with_restored_tiling_layout`]: Self::with_restored_tiling_layout
+	pub fn with_restored_manager(
+		mut manager: Box<dyn layout::TilingLayoutManager<Window>>,
+		(x, y, width, height): (i32, i32, u32, u32),
+		windows: impl IntoIterator<Item = (Window, MapState)>,
+		settings: LayoutSettings,
+	) -> Self {
+		// The windows are kept in the order they were given, so that new windows are added in a
+		// predictable order.
+		let windows: Vec<_> = windows.into_iter().collect();
+		let existing: HashSet<_> = windows.iter().map(|(window, _)| window.clone()).collect();
+
+		// Stale windows are removed in layout order.
+		let stale: Vec<_> = manager
+			.layout()
+			.windows()
+			.into_iter()
+			.filter(|window| !existing.contains(window))
+			.cloned()
+			.collect();
+		let mut restored: HashSet<_> = manager.layout().windows().into_iter().cloned().collect();
+
+		for window in &stale {
+			manager.remove_window_grouped(window);
+			restored.remove(window);
+		}
+
+		let mut aquariwm = Self {
+			layout: CurrentLayout::Tiled(manager),
+			settings,
+			scale: Scale::default(),
+
+			windows: HashMap::new(),
+			focused: None,
+			focus_cause: FocusCause::Startup,
+			focused_parents: Vec::new(),
+			ignored: IgnoreList::new(),
+			marks: Marks::new(),
+			iconified: Vec::new(),
+			away: Vec::new(),
+			workspaces: Workspaces::new(Policy::default()),
+
+			outputs: Vec::new(),
+			active_output: ActiveOutput::default(),
+			output_edges: OutputEdges::default(),
+
+			focus_history: FocusHistory::new(),
+			switch_scope: Scope::default(),
+			switcher: None,
+
+			manager_panic: None,
+
+			changed: true,
+		};
+
+		let mut new_windows = Vec::new();
 		for (window, mapped) in windows {
-			self.add_window(window, mapped);
+			if restored.contains(&window) {
+				aquariwm.focus_history.add(window.clone());
+				aquariwm.workspaces.add_window(window.clone(), Desktop::Index(0));
+				aquariwm.windows.insert(window, WindowState::new(MapState::Mapped));
+			} else {
+				new_windows.push((window, mapped));
+			}
 		}
+		aquariwm.add_windows(new_windows);
+		aquariwm.resize(x, y, width, height);
+
+		aquariwm
 	}
 
-	/// Updates AquariWM's state to reflect the given `window` being destroyed.
+	/// Focuses the window in the tiling layout with the given [node ID].
 	///
-	/// In order to apply any changes that may have been made to the tiling layout,
-	/// [`apply_changes`]
-	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
-	/// must be called.
+	/// This updates the [`focused`] window tracked by AquariWM; the display server is responsible
+	/// for giving that window input focus. Returns [`false`] if there is no tiling layout, or if
+	/// there is no window node with the given `id` in it.
 	///
-	/// [`apply_changes`]: Self::apply_changes
-	pub fn remove_window(&mut self, window: &Window) {
-		let state = self.windows.remove(window);
+	/// [node ID]: layout::NodeId
+	/// [`focused`]: Self::focused
+	pub fn focus_by_id(&mut self, id: layout::NodeId, cause: FocusCause) -> bool {
+		let CurrentLayout::Tiled(manager) = &self.layout else {
+			return false;
+		};
 
-		// Remove the window from the tiling layout if needed.
-		if let Some(state) = state {
-			if state.mode == layout::Mode::Tiled && state.mapped == MapState::Mapped {
-				if let CurrentLayout::Tiled(manager) = &mut self.layout {
-					manager.remove_window(window);
-				}
-			}
+		match manager.layout().node_by_id(id) {
+			Some(layout::Node::Window(node)) => {
+				let window = node.window().clone();
+				self.set_focused(Some(window), cause);
+
+				true
+			},
+
+			_ => false,
 		}
 	}
 
-	/// Updates AquariWM's state to reflect the given `window` being [mapped].
+	/// Sets the [`focused`] window, notifying the [layout manager] and recording the [`cause`] if
+	/// it has changed.
 	///
-	/// In order to apply any changes that may have been made to the tiling layout,
-	/// [`apply_changes`]
-	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
-	/// must be called.
+	/// This updates the [`focused`] window tracked by AquariWM; the display server is responsible
+	/// for giving that window input focus.
 	///
-	/// [mapped]: MapState::Mapped
-	/// [`apply_changes`]: Self::apply_changes
-	pub fn map_window(&mut self, window: &Window) {
-		let state = self
-			.windows
-			.get_mut(window)
-			.expect("the window we are attempting to map is not tracked");
+	/// [`focused`]: Self::focused
+	/// [layout manager]: layout::TilingLayoutManager
+	/// [`cause`]: Self::focus_cause
+	pub fn set_focused(&mut self, window: Option<Window>, cause: FocusCause) {
+		if self.focused == window {
+			return;
+		}
+		self.focus_cause = cause;
 
-		if state.mode == layout::Mode::Tiled && state.mapped == MapState::Unmapped {
-			if let CurrentLayout::Tiled(manager) = &mut self.layout {
-				manager.add_window(window.clone());
-			}
+		self.with_manager("focused_changed", |manager| manager.focused_changed(window.as_ref()));
+		// Selected groups are only kept while the window within them stays focused.
+		self.focused_parents.clear();
+
+		// Candidates focused while switching windows are only remembered if they are committed to.
+		if let (Some(window), None) = (&window, &self.switcher) {
+			self.focus_history.focus(window.clone());
 		}
 
-		state.set_mapped();
+		self.focused = window;
 	}
 
-	/// Updates AquariWM's state to reflect the given `window` being [unmapped].
+	/// Sets the [`focused`] window as with [`set_focused`], and makes the output it is on the
+	/// [active output].
 	///
-	/// In order to apply any changes that may have been made to the tiling layout,
-	/// [`apply_changes`]
-	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
-	/// must be called.
+	/// Only the outputs of tiled windows are known to AquariWM, so focusing a floating window
+	/// leaves the active output unchanged. Whether the active output then follows the pointer
+	/// depends on the [`cause`].
 	///
-	/// [unmapped]: MapState::Unmapped
-	/// [`apply_changes`]: Self::apply_changes
-	pub fn unmap_window(&mut self, window: &Window) {
-		let state = self
-			.windows
-			.get_mut(window)
-			.expect("the window we are attempting to unmap is not tracked");
+	/// [`focused`]: Self::focused
+	/// [`set_focused`]: Self::set_focused
+	/// [active output]: Self::active_output
+	/// [`cause`]: FocusCause::source
+	pub fn set_focused_from(&mut self, window: Option<Window>, cause: FocusCause) {
+		if let Some(output) = window.as_ref().and_then(|window| self.output_of(window)) {
+			let name = output.name.clone();
+			self.active_output.focus(&name, cause.source());
+		}
 
-		if state.mode == layout::Mode::Tiled && state.mapped == MapState::Mapped {
-			if let CurrentLayout::Tiled(manager) = &mut self.layout {
-				manager.remove_window(window);
-			}
+		self.set_focused(window, cause);
+	}
+
+	/// Returns what keyboard actions act on: the group last selected with [`FocusParent`], if it
+	/// still contains the [`focused`] window, or else the [`focused`] window itself.
+	///
+	/// Returns [`None`] if no window is focused.
+	///
+	/// [`FocusParent`]: Action::FocusParent
+	/// [`focused`]: Self::focused
+	pub fn focus_target(&self) -> Option<FocusTarget<Window>> {
+		let focused = self.focused.as_ref()?;
+
+		let group = self
+			.focused_parents
+			.last()
+			.copied()
+			.filter(|&id| self.group_by_id(id).is_some_and(|group| group.contains_window(focused)));
+
+		Some(group.map_or_else(|| FocusTarget::Window(focused.clone()), FocusTarget::Group))
+	}
+
+	/// Returns the windows in the [focus target], in the order they are listed in the tiling
+	/// layout.
+	///
+	/// [focus target]: Self::focus_target
+	pub fn focus_target_windows(&self) -> Vec<Window> {
+		match self.focus_target() {
+			Some(FocusTarget::Window(window)) => vec![window],
+			Some(FocusTarget::Group(id)) => self
+				.group_by_id(id)
+				.map(|group| group.windows().into_iter().cloned().collect())
+				.unwrap_or_default(),
+
+			None => Vec::new(),
+		}
+	}
+
+	/// Returns whether the given `window` is in the [focus target], i.e. whether it is the
+	/// [`focused`] window or in the group selected around it.
+	///
+	/// [focus target]: Self::focus_target
+	/// [`focused`]: Self::focused
+	pub fn in_focus_target(&self, window: &Window) -> bool {
+		match self.focus_target() {
+			Some(FocusTarget::Window(focused)) => focused == *window,
+			Some(FocusTarget::Group(id)) => self.group_by_id(id).is_some_and(|group| group.contains_window(window)),
+
+			None => false,
 		}
+	}
 
-		state.set_unmapped();
+	/// Returns the group with the given `id` in the tiling layout.
+	fn group_by_id(&self, id: layout::NodeId) -> Option<&layout::GroupNode<Window>> {
+		match self.tiling_layout()?.node_by_id(id)? {
+			layout::Node::Group(group) => Some(group),
+			layout::Node::Window(_) => None,
+		}
 	}
 
-	/// Applies changes made by the [layout manager] by calling [`apply_resizes`] with the given
-	/// `resize_window` function.
+	/// Selects the group around the [focus target] as the new focus target, returning whether
+	/// there was one.
 	///
-	/// [layout manager]: layout::TilingLayoutManager
-	/// [`apply_resizes`]: layout::GroupNode::apply_changes
-	#[cfg_attr(
-		feature = "async",
-		doc = "",
-		doc = " # See also",
-		doc = "[`apply_changes_async`] allows using a `resize_window` function that returns a",
-		doc = "[future].",
-		doc = "",
-		doc = "[`apply_changes_async`]: Self::apply_changes_async",
-		doc = "[future]: Future"
-	)]
-	pub fn apply_changes<Error>(
-		&mut self,
-		mut reconfigure_window: impl FnMut(&Window, i32, i32, u32, u32) -> Result<(), Error>,
-	) -> Result<(), Error> {
-		if let CurrentLayout::Tiled(manager) = &mut self.layout {
-			manager
-				.layout_mut()
-				.apply_changes(&mut reconfigure_window, &self.settings)?;
+	/// The root group of the tiling layout is never selected: it is always the whole layout.
+	///
+	/// [focus target]: Self::focus_target
+	fn focus_parent(&mut self) -> bool {
+		let (Some(target), Some(layout)) = (self.focus_target(), self.tiling_layout()) else {
+			return false;
+		};
+
+		let node = match &target {
+			FocusTarget::Group(id) => Some(*id),
+			FocusTarget::Window(window) => layout.id_of_window(window),
+		};
+		let Some(mut path) = node.and_then(|id| layout.path_of(id)) else {
+			return false;
+		};
+
+		path.pop();
+		if path.is_empty() {
+			return false;
 		}
+		let Some(parent) = layout.group_at(&path).map(layout::GroupNode::id) else {
+			return false;
+		};
 
-		Ok(())
+		// Groups which no longer contain the focused window aren't returned to.
+		if matches!(target, FocusTarget::Window(_)) {
+			self.focused_parents.clear();
+		}
+		self.focused_parents.push(parent);
+
+		true
 	}
 
-	#[doc(cfg(feature = "async"))]
-	/// Applies changes made by the [layout manager] by calling `apply_changes` with the given
-	/// `resize_window` function.
+	/// Returns the mapped windows in the order they are read on screen.
 	///
-	/// # See also
-	/// [`apply_changes`] allows using a `resize_window` function that doesn't return a [future].
+	/// Tiled windows come first, in the [visual order] of the tiling layout according to the
+	/// [reading order] setting. The `floating` windows, given from the bottom of the stack to the
+	/// top by the display server, come after them, followed by any other floating windows ordered
+	/// by their IDs.
 	///
-	/// [layout manager]: layout::TilingLayoutManager
-	/// [future]: Future
+	/// [visual order]: layout::TilingLayout::visual_order_by
+	/// [reading order]: LayoutSettings::reading_order
+	pub fn visual_order(&self, floating: &[Window]) -> Vec<Window> {
+		let mapped = |window: &&Window, mode| {
+			self.windows
+				.get(window)
+				.is_some_and(|state| state.mode == mode && state.mapped == MapState::Mapped)
+		};
+
+		let mut order: Vec<_> = self
+			.tiling_layout()
+			.map(|layout| layout.visual_order_by(self.settings.reading_order))
+			.unwrap_or_default()
+			.into_iter()
+			.filter(|window| mapped(window, layout::Mode::Tiled))
+			.chain(floating.iter().filter(|window| mapped(window, layout::Mode::Floating)))
+			.cloned()
+			.collect();
+
+		let mut unstacked: Vec<_> = self
+			.windows
+			.keys()
+			.filter(|window| mapped(window, layout::Mode::Floating) && !floating.contains(window))
+			.cloned()
+			.collect();
+		unstacked.sort_unstable_by_key(ManagedWindow::id);
+		order.extend(unstacked);
+
+		order
+	}
+
+	/// Focuses the window after the [`focused`] window in [visual order], wrapping around to the
+	/// first window, and returns it.
 	///
-	/// [`apply_changes`]: Self::apply_changes
-	#[cfg(feature = "async")]
-	pub async fn apply_changes_async<ResizeWindowFuture, Error>(
-		&mut self,
-		mut reconfigure_window: impl FnMut(&Window, i32, i32, u32, u32) -> ResizeWindowFuture,
-	) -> Result<(), Error>
-	where
-		ResizeWindowFuture: Future<Output = Result<(), Error>>,
-	{
-		if let CurrentLayout::Tiled(manager) = &mut self.layout {
-			// Add all the `resize_window` futures to this list...
-			let mut futures = Vec::new();
+	/// `floating` is the display server's stacking order of the floating windows, from the bottom
+	/// to the top. If no window is focused, the first window is focused.
+	///
+	/// [`focused`]: Self::focused
+	/// [visual order]: Self::visual_order
+	pub fn focus_next(&mut self, floating: &[Window]) -> Option<Window> {
+		self.cycle_focus(floating, true)
+	}
 
-			manager.layout_mut().apply_changes(
-				&mut |window, x, y, width, height| -> Result<(), Error> {
-					futures.push(reconfigure_window(window, x, y, width, height));
+	/// Focuses the window before the [`focused`] window in [visual order], wrapping around to the
+	/// last window, and returns it.
+	///
+	/// `floating` is the display server's stacking order of the floating windows, from the bottom
+	/// to the top. If no window is focused, the last window is focused.
+	///
+	/// [`focused`]: Self::focused
+	/// [visual order]: Self::visual_order
+	pub fn focus_previous(&mut self, floating: &[Window]) -> Option<Window> {
+		self.cycle_focus(floating, false)
+	}
 
-					Ok(())
-				},
-				&self.settings,
-			)?;
+	/// Focuses the window after the [`focused`] window in [visual order] if `forwards`, or the one
+	/// before it otherwise.
+	///
+	/// [`focused`]: Self::focused
+	/// [visual order]: Self::visual_order
+	fn cycle_focus(&mut self, floating: &[Window], forwards: bool) -> Option<Window> {
+		let order = self.visual_order(floating);
+		let len = order.len();
 
-			// Await all the `resize_window` futures.
-			future::try_join_all(futures).await?;
-		}
+		let current = self
+			.focused
+			.as_ref()
+			.and_then(|focused| order.iter().position(|window| window == focused));
+		let index = match (current, forwards) {
+			_ if len == 0 => return None,
 
-		Ok(())
+			(Some(index), true) => (index + 1) % len,
+			(Some(index), false) => (index + len - 1) % len,
+			(None, true) => 0,
+			(None, false) => len - 1,
+		};
+
+		let window = order[index].clone();
+		self.set_focused(Some(window.clone()), FocusCause::Keyboard);
+
+		Some(window)
+	}
+
+	/// Returns whether a [window switching] session is ongoing.
+	///
+	/// [window switching]: Self::switch_windows
+	#[inline]
+	pub const fn is_switching(&self) -> bool {
+		self.switcher.is_some()
+	}
+
+	/// Feeds the given `key` to the ongoing window switching [session], starting one if there is
+	/// none and the `key` selects a candidate, then updates the [`focused`] window according to the
+	/// returned [step].
+	///
+	/// Candidates are the mapped windows in the [`switch_scope`], most recently focused first.
+	/// Windows which have never been focused come last, in [visual order]; `floating` is the
+	/// display server's stacking order of the floating windows, from the bottom to the top.
+	/// While the session is ongoing, focusing candidates doesn't reorder the [`focus_history`];
+	/// only the candidate which is committed to is moved to the front of it.
+	///
+	/// Returns [`None`] if no session is ongoing and none was started, e.g. because there are no
+	/// candidates.
+	///
+	/// [session]: Session
+	/// [`focused`]: Self::focused
+	/// [step]: Step
+	/// [`switch_scope`]: Self::switch_scope
+	/// [visual order]: Self::visual_order
+	/// [`focus_history`]: Self::focus_history
+	pub fn switch_windows(&mut self, key: Key, floating: &[Window]) -> Option<Step<Window>> {
+		let session = match &mut self.switcher {
+			Some(session) => session,
+
+			None if matches!(key, Key::Next | Key::Previous) => {
+				let session = Session::start(self.switch_candidates(floating), self.focused.clone())?;
+				self.switcher.insert(session)
+			},
+			None => return None,
+		};
+
+		let step = session.feed(key);
+		match &step {
+			Step::Focus(window) => self.set_focused_from(Some(window.clone()), FocusCause::Keyboard),
+
+			Step::Commit(window) => {
+				self.switcher = None;
+
+				self.set_focused_from(Some(window.clone()), FocusCause::Keyboard);
+				self.focus_history.focus(window.clone());
+			},
+			Step::Cancel(original) => {
+				self.switcher = None;
+
+				self.set_focused_from(original.clone(), FocusCause::Keyboard);
+			},
+		}
+
+		Some(step)
+	}
+
+	/// Returns the mapped windows in the [`switch_scope`], most recently focused first, followed by
+	/// those which have never been focused in [visual order].
+	///
+	/// [`switch_scope`]: Self::switch_scope
+	/// [visual order]: Self::visual_order
+	fn switch_candidates(&self, floating: &[Window]) -> Vec<Window> {
+		let active = self.active_output.get(&self.outputs).map(|output| &output.name);
+		let unfocused = self.focus_history.unfocused();
+
+		self.focus_history
+			.focused()
+			.iter()
+			.filter(|window| {
+				self.windows
+					.get(window)
+					.is_some_and(|state| state.mapped == MapState::Mapped)
+			})
+			.cloned()
+			.chain(
+				self.visual_order(floating)
+					.into_iter()
+					.filter(|window| unfocused.contains(window)),
+			)
+			.filter(|window| match self.switch_scope {
+				Scope::AllOutputs => true,
+				// Windows which aren't known to be on any output are always candidates.
+				Scope::ActiveOutput => self.output_of(window).is_none_or(|output| Some(&output.name) == active),
+			})
+			.collect()
+	}
+
+	/// Updates the [active output] to reflect the pointer having moved to the given point.
+	///
+	/// [active output]: Self::active_output
+	pub fn pointer_moved(&mut self, x: i32, y: i32) {
+		if let Some(output) = Output::at(&self.outputs, x, y) {
+			self.active_output.pointer_crossed(&output.name);
+		}
+	}
+
+	/// Returns the output on which the given tiled `window` is displayed, i.e. the output
+	/// containing the center of its tile.
+	///
+	/// Returns [`None`] if the `window` is not visible in the tiling layout.
+	pub fn output_of(&self, window: &Window) -> Option<&Output> {
+		let (x, y) = center(self.tile_of(window)?);
+
+		Output::at(&self.outputs, x, y)
+	}
+
+	/// Returns the tile of the given `window`, if it is visible in the tiling layout.
+	pub fn tile_of(&self, window: &Window) -> Option<Rect> {
+		let layout = self.tiling_layout()?;
+		let node = layout.node_by_id(layout.id_of_window(window)?)?;
+
+		match node {
+			layout::Node::Window(window) if window.visibility() == Visibility::Visible => {
+				Some((node.x(), node.y(), node.width(), node.height()))
+			},
+
+			_ => None,
+		}
+	}
+
+	/// Returns the visible tiled window on the given `output` (other than those in `except`) whose
+	/// center is nearest to the output's center.
+	fn central_window(&self, output: &Output, except: &[&Window]) -> Option<Window> {
+		let (output_x, output_y) = output.center();
+
+		self.tiling_layout()?
+			.windows()
+			.into_iter()
+			.filter(|window| !except.contains(window))
+			.filter_map(|window| {
+				let (x, y) = center(self.tile_of(window)?);
+
+				output.contains(x, y).then_some((window, (x, y)))
+			})
+			.min_by_key(|&(_, (x, y))| {
+				let (dx, dy) = (i64::from(x) - i64::from(output_x), i64::from(y) - i64::from(output_y));
+
+				dx * dx + dy * dy
+			})
+			.map(|(window, _)| window.clone())
+	}
+
+	/// Makes the output of the given `name` the [active output], focusing the tiled window nearest
+	/// to its center.
+	///
+	/// If there are no tiled windows on the output, no window is focused.
+	///
+	/// [active output]: Self::active_output
+	fn focus_output(&mut self, name: &str) {
+		self.active_output.focus(name, FocusSource::Explicit);
+
+		let window = Output::named(&self.outputs, name).and_then(|output| self.central_window(output, &[]));
+		self.set_focused(window, FocusCause::Keyboard);
+	}
+
+	/// Moves the given tiled `window` onto the given `output`, splitting the tile of the tiled
+	/// window nearest to the output's center to place it alongside that window.
+	///
+	/// The window is placed as if it were [grouped by class] with that window.
+	///
+	/// Returns [`false`] if the `window` isn't in the tiling layout, or if there are no other tiled
+	/// windows on the `output`.
+	///
+	/// [grouped by class]: InsertionStrategy::GroupByClass
+	fn move_tiled_window_to(&mut self, window: &Window, output: &Output) -> bool {
+		let Some(anchor) = self.central_window(output, &[window]) else {
+			return false;
+		};
+		if !self
+			.tiling_layout()
+			.is_some_and(|layout| layout.contains_window(window))
+		{
+			return false;
+		}
+
+		self.with_manager("remove_window_grouped", |manager| manager.remove_window_grouped(window));
+		self.with_manager("add_window_grouped", |manager| {
+			manager.add_window_grouped(window.clone(), &|other: &Window| *other == anchor)
+		});
+		self.changed = true;
+
+		true
+	}
+
+	/// Moves the group with the given `id` onto the given `output` intact, placing it after the
+	/// tiled window nearest to the output's center, in that window's group.
+	///
+	/// The group keeps its structure and the shares of the nodes within it.
+	///
+	/// Returns [`false`] if there is no such group, if there are no tiled windows outside it on the
+	/// `output`, or if moving it would nest groups too deeply.
+	fn move_group_to(&mut self, id: layout::NodeId, output: &Output) -> bool {
+		let Some(group) = self.group_by_id(id) else {
+			return false;
+		};
+		let Some(anchor) = self.central_window(output, &group.windows()) else {
+			return false;
+		};
+		let CurrentLayout::Tiled(manager) = &mut self.layout else {
+			return false;
+		};
+		let layout = manager.layout_mut();
+
+		let Some(mut path) = layout.id_of_window(&anchor).and_then(|anchor| layout.path_of(anchor)) else {
+			return false;
+		};
+		let mut index = path.pop().expect("the anchor is not the root group") + 1;
+		let Some(parent) = layout.group_at(&path).map(layout::GroupNode::id) else {
+			return false;
+		};
+		// The anchor moves back a place if the group is removed from before it.
+		if let Some((&from, group_parent)) = layout.path_of(id).as_deref().and_then(<[_]>::split_last) {
+			if group_parent == path && from < index {
+				index -= 1;
+			}
+		}
+
+		match layout.transfer_by_id(id, parent, index) {
+			Ok(moved) => {
+				self.changed |= moved;
+
+				moved
+			},
+
+			Err(error) => {
+				event!(Level::WARN, "Not moving the focused group: {error}");
+
+				false
+			},
+		}
+	}
+
+	/// Swaps the places of the group with the given `id` and the given tiled `window`, each taking
+	/// the other's tile.
+	///
+	/// Returns [`false`] if either isn't in the tiling layout, or if the `window` is within the
+	/// group.
+	fn swap_group_with(&mut self, id: layout::NodeId, window: &Window) -> bool {
+		let CurrentLayout::Tiled(manager) = &mut self.layout else {
+			return false;
+		};
+		let layout = manager.layout_mut();
+
+		let swapped = layout
+			.id_of_window(window)
+			.is_some_and(|other| layout.swap_ids(id, other));
+		self.changed |= swapped;
+
+		swapped
+	}
+
+	/// Moves the given tiled `window` onto the output of the given `name`, e.g. because it was
+	/// [launched] while that output was active.
+	///
+	/// The window is placed alongside the tiled window nearest to the output's center, as with
+	/// [`MoveWindowToOutput`].
+	///
+	/// Returns [`false`] if there is no output of the given `name`, if the `window` isn't in the
+	/// tiling layout, or if there are no other tiled windows on the output.
+	///
+	/// [launched]: crate::launch
+	/// [`MoveWindowToOutput`]: Action::MoveWindowToOutput
+	pub fn move_window_to_output(&mut self, window: &Window, name: &str) -> bool {
+		let Some(output) = Output::named(&self.outputs, name).cloned() else {
+			return false;
+		};
+
+		self.move_tiled_window_to(window, &output)
+	}
+
+	/// Moves the given tiled `window` to the tile at the given `path` in the tiling layout, e.g.
+	/// because a window of the same class was [last closed] there.
+	///
+	/// The window swaps places with the window at the [nearest valid path], so that the layout
+	/// manager's structure is kept.
+	///
+	/// Returns [`false`] if the `window` isn't in the tiling layout.
+	///
+	/// [last closed]: crate::placement_memory
+	/// [nearest valid path]: layout::GroupNode::window_near_path
+	pub fn place_window_at(&mut self, window: &Window, path: &[usize]) -> bool {
+		let CurrentLayout::Tiled(manager) = &mut self.layout else {
+			return false;
+		};
+		let layout = manager.layout_mut();
+
+		let (Some(id), Some(target)) = (layout.id_of_window(window), layout.window_near_path(path)) else {
+			return false;
+		};
+
+		if id != target && layout.swap_ids(id, target) {
+			self.changed = true;
+		}
+
+		true
+	}
+
+	/// Resizes the tiling layout, if there is one, to the given coordinates and dimensions.
+	///
+	/// In order to apply the new dimensions to the tiling layout, [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn resize(&mut self, x: i32, y: i32, width: u32, height: u32) {
+		if let CurrentLayout::Tiled(manager) = &mut self.layout {
+			manager
+				.layout_mut()
+				.resize(x, y, width, height, &self.settings.scaled(self.scale));
+
+			self.changed = true;
+		}
+	}
+
+	/// Sets the [orientation] of the tiling layout's root group.
+	///
+	/// [orientation]: layout::Orientation
+	pub fn set_root_orientation(&mut self, orientation: layout::Orientation) {
+		if let CurrentLayout::Tiled(manager) = &mut self.layout {
+			manager.layout_mut().set_orientation(orientation);
+
+			self.changed = true;
+		}
+	}
+
+	/// Rotates the [orientation] of the tiling layout's root group by the given number of
+	/// clockwise `rotations`.
+	///
+	/// [orientation]: layout::Orientation
+	pub fn rotate_root(&mut self, rotations: i32) {
+		if let CurrentLayout::Tiled(manager) = &mut self.layout {
+			manager.layout_mut().rotate_by(rotations);
+
+			self.changed = true;
+		}
+	}
+
+	/// Returns the [name] of the current layout manager, or `"Floating"` if there is no tiling
+	/// layout.
+	///
+	/// [name]: layout::TilingLayoutManager::name
+	pub fn layout_name(&self) -> &'static str {
+		match &self.layout {
+			CurrentLayout::Tiled(manager) => manager.name(),
+			CurrentLayout::Floating => "Floating",
+		}
+	}
+
+	/// Replaces the layout manager of the current workspace with the given [built-in] layout
+	/// manager, which takes over the layout's windows.
+	///
+	/// Every workspace shares a single tiling layout, so this replaces the only layout manager.
+	/// Returns [`false`], leaving the layout as it is, if there is no tiling layout or it is
+	/// already managed by that layout manager.
+	///
+	/// In order to apply the new layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [built-in]: BuiltIn
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn set_layout_manager(&mut self, manager: BuiltIn) -> bool {
+		if self.layout_name() == manager.name() {
+			return false;
+		}
+
+		let swapped = self.layout.swap_built_in(manager).is_some();
+		self.changed |= swapped;
+
+		swapped
+	}
+
+	/// Returns a [snapshot] of the workspace, to be [autosaved], if there is a tiling layout.
+	///
+	/// Windows are given by their [IDs] in the snapshot.
+	///
+	/// [snapshot]: PersistedWorkspace
+	/// [autosaved]: crate::autosave
+	/// [IDs]: crate::window::WindowId
+	pub fn workspace_snapshot(&self) -> Option<PersistedWorkspace> {
+		Some(WorkspaceSnapshot {
+			manager: Some(self.layout_name().to_owned()),
+			layout: self.tiling_layout()?.map_windows(ManagedWindow::id),
+			// Every workspace shares the tiling layout, so every mark is kept with it.
+			marks: self.marks.ids(),
+		})
+	}
+
+	/// Returns the tiling layout, if there is one.
+	///
+	/// This is what is written to [snapshots].
+	///
+	/// [snapshots]: crate::autosave
+	pub fn tiling_layout(&self) -> Option<&layout::TilingLayout<Window>> {
+		match &self.layout {
+			CurrentLayout::Tiled(manager) => Some(manager.layout()),
+			CurrentLayout::Floating => None,
+		}
+	}
+
+	/// Moves the given `edge` in the tiling layout so that the node before it ends at the given
+	/// `position`, e.g. while it is being dragged.
+	///
+	/// Returns [`false`] if there is no tiling layout, or if the edge is no longer in it. See
+	/// [`TilingLayout::move_edge`] for more information.
+	///
+	/// In order to apply the changes to the tiling layout, [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [`TilingLayout::move_edge`]: layout::TilingLayout::move_edge
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn move_edge(&mut self, edge: &layout::EdgeHit, position: i32) -> bool {
+		let CurrentLayout::Tiled(manager) = &mut self.layout else {
+			return false;
+		};
+
+		let moved = manager.layout_mut().move_edge(edge, position);
+		self.changed |= moved;
+
+		moved
+	}
+
+	/// Swaps the positions of the given tiled windows in the tiling layout.
+	///
+	/// Returns [`false`] if there is no tiling layout, or if either window is not in it.
+	///
+	/// In order to apply the changes to the tiling layout, [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn swap_windows(&mut self, a: &Window, b: &Window) -> bool {
+		let CurrentLayout::Tiled(manager) = &mut self.layout else {
+			return false;
+		};
+
+		let layout = manager.layout_mut();
+		let (Some(a), Some(b)) = (layout.id_of_window(a), layout.id_of_window(b)) else {
+			return false;
+		};
+
+		let swapped = layout.swap_ids(a, b);
+		self.changed |= swapped;
+
+		swapped
+	}
+
+	/// Drops the given tiled window being [dragged] where it was dropped: swapping it with the
+	/// target window, or moving it beside the target.
+	///
+	/// Returns [`false`] if there is no tiling layout, or if either window is not in it.
+	///
+	/// In order to apply the changes to the tiling layout, [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [dragged]: crate::moveresize::Drag::Swap
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn drop_window(&mut self, window: &Window, drop: &moveresize::Drop<Window>) -> bool {
+		let (axis, side) = match drop.region {
+			moveresize::DropRegion::Swap => return self.swap_windows(window, &drop.target),
+			moveresize::DropRegion::Insert { axis, side } => (axis, side),
+		};
+		let CurrentLayout::Tiled(manager) = &mut self.layout else {
+			return false;
+		};
+
+		let layout = manager.layout_mut();
+		let (Some(id), Some(target)) = (layout.id_of_window(window), layout.id_of_window(&drop.target)) else {
+			return false;
+		};
+
+		let moved = layout.move_beside_by_id(id, target, axis, side);
+		self.changed |= moved;
+
+		moved
+	}
+
+	/// [Compacts] the tiling layout, repairing any sizes which have drifted.
+	///
+	/// Compacting a healthy layout does nothing, and doesn't mark the layout as [changed].
+	///
+	/// In order to apply the repaired sizes to the tiling layout, [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [Compacts]: layout::TilingLayout::compact
+	/// [changed]: Self::take_changed
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn compact_layout(&mut self) -> layout::Compaction {
+		let CurrentLayout::Tiled(manager) = &mut self.layout else {
+			return layout::Compaction::default();
+		};
+
+		let compaction = manager.layout_mut().compact();
+		self.changed |= !compaction.is_empty();
+
+		compaction
+	}
+
+	/// Returns whether the layout has changed since this was last called.
+	///
+	/// The layout is considered changed by any window being added to or removed from it, by it
+	/// being resized or rescaled, and by any [action] being [performed].
+	///
+	/// [action]: Action
+	/// [performed]: Self::perform
+	pub fn take_changed(&mut self) -> bool {
+		std::mem::take(&mut self.changed)
+	}
+
+	/// Sets the [scale factor] of the output on which the layout is displayed.
+	///
+	/// In order to apply the rescaled [settings] to the tiling layout, [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [scale factor]: Scale
+	/// [settings]: LayoutSettings
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn set_scale(&mut self, scale: Scale) {
+		self.scale = scale;
+
+		if let CurrentLayout::Tiled(manager) = &mut self.layout {
+			manager.layout_mut().update_settings(&self.settings.scaled(scale));
+
+			self.changed = true;
+		}
+	}
+
+	/// Replaces the [settings] of the tiling layout, as when the configuration is reloaded.
+	///
+	/// In order to apply the new settings to the tiling layout, [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [settings]: LayoutSettings
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn set_settings(&mut self, settings: LayoutSettings) {
+		self.settings = settings;
+
+		if let CurrentLayout::Tiled(manager) = &mut self.layout {
+			manager.layout_mut().update_settings(&self.settings.scaled(self.scale));
+
+			self.changed = true;
+		}
+	}
+
+	/// Performs the given `action`, returning its [outcome], or why it couldn't be performed.
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [outcome]: Outcome
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn perform(&mut self, action: Action) -> ActionResult {
+		self.changed = true;
+
+		match action {
+			Action::AdjustGap(delta) => {
+				let window = self.focused.as_ref().ok_or(ActionError::NoFocusedWindow)?;
+				let CurrentLayout::Tiled(manager) = &mut self.layout else {
+					return Err(ActionError::NoTilingLayout);
+				};
+
+				let settings = self.settings.scaled(self.scale);
+
+				if !manager
+					.layout_mut()
+					.adjust_gap(window, self.scale.to_device_coord(delta), &settings)
+				{
+					return Err(ActionError::NotTiled);
+				}
+			},
+
+			Action::ResizePrimary(delta) => {
+				let delta = self.scale.to_device_coord(delta);
+				self.with_manager("resize_primary", |manager| manager.resize_primary(delta))
+					.ok_or(ActionError::NoTilingLayout)?;
+			},
+
+			Action::ResizeFocused { axis, amount } => {
+				let target = self.focus_target().ok_or(ActionError::NoFocusedWindow)?;
+				let CurrentLayout::Tiled(manager) = &mut self.layout else {
+					return Err(ActionError::NoTilingLayout);
+				};
+				let layout = manager.layout_mut();
+
+				let settings = self.settings.scaled(self.scale);
+				let amount = match amount {
+					layout::Amount::Pixels(pixels) => layout::Amount::Pixels(self.scale.to_device_coord(pixels)),
+					layout::Amount::Percent(percent) => layout::Amount::Percent(percent),
+				};
+
+				let resized = match &target {
+					FocusTarget::Window(window) => layout.resize_window(window, axis, amount, &settings),
+					FocusTarget::Group(id) => layout.resize_node(*id, axis, amount, &settings),
+				};
+				let delta = resized.ok_or(ActionError::NoGroupToResize(axis))?;
+
+				return Ok(Outcome::Resized(self.scale.to_logical_coord(delta)));
+			},
+
+			Action::FocusOutput(direction) => {
+				let name = self
+					.active_output
+					.get(&self.outputs)
+					.and_then(|from| Output::in_direction(&self.outputs, from, direction, self.output_edges))
+					.map(|output| output.name.clone())
+					.ok_or(ActionError::NoOutputInDirection(direction))?;
+
+				self.focus_output(&name);
+			},
+
+			Action::FocusOutputByName(name) => match Output::named(&self.outputs, &name) {
+				Some(_) => self.focus_output(&name),
+				None => return Err(ActionError::UnknownOutput(name)),
+			},
+
+			Action::MoveWindowToOutput(direction) | Action::MoveToOutputAndFollow(direction) => {
+				let window = self.focused.clone().ok_or(ActionError::NoFocusedWindow)?;
+
+				let target = self
+					.output_of(&window)
+					.or_else(|| self.active_output.get(&self.outputs))
+					.and_then(|from| Output::in_direction(&self.outputs, from, direction, self.output_edges))
+					.cloned()
+					.ok_or(ActionError::NoOutputInDirection(direction))?;
+
+				if let Some(FocusTarget::Group(group)) = self.focus_target() {
+					if !self.move_group_to(group, &target) {
+						return Err(ActionError::NothingToJoin(target.name));
+					}
+
+					self.active_output.focus(&target.name, FocusSource::Explicit);
+
+					return Ok(Outcome::MovedToOutput(target.name));
+				}
+
+				// Floating windows are moved by the display server.
+				let floating = self
+					.windows
+					.get(&window)
+					.is_some_and(|state| state.mode == layout::Mode::Floating);
+
+				if !floating && !self.move_tiled_window_to(&window, &target) {
+					return Err(ActionError::NothingToJoin(target.name));
+				}
+
+				// The window stays focused on its new output.
+				self.active_output.focus(&target.name, FocusSource::Explicit);
+
+				return Ok(Outcome::MovedToOutput(target.name));
+			},
+
+			Action::SwapWithPrimary => {
+				let focused = self.focused.clone().ok_or(ActionError::NoFocusedWindow)?;
+				// Layout managers only know how to swap windows into their primary slot.
+				if let Some(FocusTarget::Group(_)) = self.focus_target() {
+					return Err(ActionError::GroupNotSwappable);
+				}
+				let slot = self
+					.tiling_layout()
+					.and_then(|layout| layout.id_of_window(&focused))
+					.ok_or(ActionError::NotTiled)?;
+
+				let swapped = self.with_manager("swap_with_primary", |manager| manager.swap_with_primary(&focused));
+				// Focus stays in the focused window's old slot, moving to the window swapped into it.
+				if swapped == Some(true) && self.settings.swap_focus == SwapFocus::Stay {
+					self.focus_by_id(slot, FocusCause::Keyboard);
+				}
+			},
+
+			Action::MoveTabLeft | Action::MoveTabRight => {
+				let window = self.focused.as_ref().ok_or(ActionError::NoFocusedWindow)?;
+				let CurrentLayout::Tiled(manager) = &mut self.layout else {
+					return Err(ActionError::NoTilingLayout);
+				};
+
+				let side = match action {
+					Action::MoveTabLeft => layout::Side::Before,
+					_ => layout::Side::After,
+				};
+				if !manager.layout_mut().move_tab(window, side) {
+					return Err(ActionError::NotStacked);
+				}
+			},
+
+			Action::ToggleManual => {
+				let window = self.focused.as_ref().ok_or(ActionError::NoFocusedWindow)?;
+				let CurrentLayout::Tiled(manager) = &mut self.layout else {
+					return Err(ActionError::NoTilingLayout);
+				};
+
+				manager
+					.layout_mut()
+					.toggle_manual(window)
+					.ok_or(ActionError::NotTiled)?;
+			},
+
+			Action::ToggleGroupMaximize => {
+				let window = self.focused.as_ref().ok_or(ActionError::NoFocusedWindow)?;
+				let CurrentLayout::Tiled(manager) = &mut self.layout else {
+					return Err(ActionError::NoTilingLayout);
+				};
+
+				let settings = self.settings.scaled(self.scale);
+				manager
+					.layout_mut()
+					.toggle_group_maximize(window, &settings)
+					.ok_or(ActionError::NotTiled)?;
+			},
+
+			Action::FocusParent => {
+				if !self.focus_parent() {
+					return Err(ActionError::NoParentGroup);
+				}
+			},
+			Action::FocusChild => match self.focus_target() {
+				Some(FocusTarget::Group(_)) => {
+					self.focused_parents.pop();
+				},
+				// Groups which no longer contain the focused window aren't returned to.
+				_ => self.focused_parents.clear(),
+			},
+			Action::FocusNext => return Ok(Outcome::FocusNext),
+			Action::FocusPrevious => return Ok(Outcome::FocusPrevious),
+
+			Action::ToggleSticky => {
+				let window = self.focused.clone().ok_or(ActionError::NoFocusedWindow)?;
+				let sticky = !self.windows.get(&window).is_some_and(|state| state.sticky);
+
+				self.set_sticky(&window, sticky);
+
+				return Ok(Outcome::Sticky(sticky));
+			},
+			// Only the display server knows floating windows' geometry and size hints, so it must
+			// move the window.
+			Action::ApplyFloatPreset(preset) => {
+				let window = self.focused.clone().ok_or(ActionError::NoFocusedWindow)?;
+
+				// Tiled windows float first; they return to the layout when they are tiled again.
+				self.float_window(&window);
+
+				return Ok(Outcome::ApplyFloatPreset(preset));
+			},
+
+			// Only the display server can unmap the focused window, which it must expect the
+			// `UnmapNotify` event for.
+			Action::MinimizeFocused => match self.focused {
+				Some(_) => return Ok(Outcome::MinimizeFocused),
+				None => return Err(ActionError::NoFocusedWindow),
+			},
+			Action::RestoreMinimized => match self.iconified.is_empty() {
+				false => return Ok(Outcome::RestoreMinimized),
+				true => return Err(ActionError::NothingMinimized),
+			},
+			Action::ForceKillFocused => match self.focused {
+				Some(_) => return Ok(Outcome::ForceKillFocused),
+				None => return Err(ActionError::NoFocusedWindow),
+			},
+
+			Action::Ignore(matcher) => {
+				if self.ignored.add(matcher) {
+					return Ok(Outcome::IgnoreListChanged);
+				}
+			},
+			Action::Unignore(matcher) => {
+				if self.ignored.remove(&matcher) {
+					return Ok(Outcome::IgnoreListChanged);
+				}
+			},
+			Action::ListIgnored => return Ok(Outcome::IgnoreList(self.ignored.matchers().to_vec())),
+			// Only the display server knows the ID of the focused window, which it is ignored by.
+			Action::IgnoreFocusedWindow => match self.focused {
+				Some(_) => return Ok(Outcome::IgnoreFocused),
+				None => return Err(ActionError::NoFocusedWindow),
+			},
+			Action::AdoptUnignored => return Ok(Outcome::AdoptUnignored),
+
+			Action::SaveLayout(name) => {
+				let CurrentLayout::Tiled(manager) = &self.layout else {
+					return Err(ActionError::NoTilingLayout);
+				};
+
+				match presets::save(&name, &manager.layout().snapshot()) {
+					Ok(()) => event!(Level::INFO, "Saved layout preset {name:?}"),
+					Err(source) => return Err(ActionError::SavePreset { name, source }),
+				}
+			},
+
+			Action::LoadLayout(name) => {
+				let CurrentLayout::Tiled(manager) = &mut self.layout else {
+					return Err(ActionError::NoTilingLayout);
+				};
+
+				match presets::load(&name) {
+					Ok(snapshot) => manager.layout_mut().apply_snapshot(&snapshot),
+					Err(source) => return Err(ActionError::LoadPreset { name, source }),
+				}
+			},
+
+			Action::SetWorkspaceLayout(name) => {
+				let manager = name.parse()?;
+
+				if self.set_layout_manager(manager) {
+					event!(Level::INFO, "Switched to the {manager} layout manager");
+				}
+			},
+			Action::CompactLayout => {
+				let compaction = self.compact_layout();
+				event!(Level::DEBUG, "Compacted the layout: {compaction:?}");
+			},
+
+			Action::Mark(mark) => {
+				let window = self.focused.clone().ok_or(ActionError::NoFocusedWindow)?;
+
+				if self.marks.mark(mark.clone(), window).is_some() {
+					event!(Level::DEBUG, "Moved mark {mark:?} to the focused window");
+				}
+				self.changed = true;
+			},
+			Action::Unmark(mark) => {
+				let unmarked = match (mark, &self.focused) {
+					(Some(mark), _) => self.marks.unmark(&mark).is_some(),
+					(None, Some(focused)) => !self.marks.unmark_window(&focused.clone()).is_empty(),
+
+					(None, None) => false,
+				};
+
+				self.changed |= unmarked;
+			},
+			Action::FocusMark(mark) => match self.marked_window(&mark) {
+				Some(window) => self.set_focused(Some(window), FocusCause::Keyboard),
+				None => return Err(ActionError::MarkNotShown(mark)),
+			},
+			Action::SwapWithMark(mark) => {
+				let focused = self.focused.clone().ok_or(ActionError::NoFocusedWindow)?;
+				let Some(marked) = self.marked_window(&mark) else {
+					return Err(ActionError::MarkNotShown(mark));
+				};
+
+				let swapped = match self.focus_target() {
+					Some(FocusTarget::Group(group)) => self.swap_group_with(group, &marked),
+					// Focus stays with the focused window, wherever it is moved to.
+					_ => self.swap_window_contents(&focused, &marked),
+				};
+				if !swapped {
+					return Err(ActionError::NotSwappable(mark));
+				}
+			},
+			// Only the display server knows windows' classes, which windows shown again are grouped by.
+			Action::SendMarkToCurrentWorkspace(mark) => match self.marks.window(&mark) {
+				Some(_) => return Ok(Outcome::SendMarkToCurrentWorkspace(mark)),
+				None => return Err(ActionError::UnknownMark(mark)),
+			},
+
+			Action::SwitchToWorkspace(index) => {
+				self.workspace_exists(index)?;
+
+				return Ok(Outcome::SwitchToWorkspace(index));
+			},
+			Action::MoveToWorkspace(index) | Action::MoveToWorkspaceAndFollow(index) => {
+				if self.focused.is_none() {
+					return Err(ActionError::NoFocusedWindow);
+				}
+				self.workspace_exists(index)?;
+
+				return match action {
+					Action::MoveToWorkspace(_) => Ok(Outcome::MoveToWorkspace(index)),
+					_ => Ok(Outcome::MoveToWorkspaceAndFollow(index)),
+				};
+			},
+
+			Action::ListKeyGrabs => return Ok(Outcome::ListKeyGrabs),
+			Action::QueryLatency => return Ok(Outcome::QueryLatency),
+			Action::ToggleDryRun => return Ok(Outcome::ToggleDryRun),
+			Action::Query(query) => return Ok(Outcome::Query(query)),
+			Action::SetWallpaper(path) => return Ok(Outcome::SetWallpaper(path)),
+			Action::ReloadConfig => return Ok(Outcome::ReloadConfig),
+		}
+
+		Ok(Outcome::Done)
+	}
+
+	/// Returns an error if there is no workspace at the given `index`, and it isn't the workspace
+	/// after the last, which would be created.
+	fn workspace_exists(&self, index: usize) -> Result<(), WorkspaceError> {
+		let count = self.workspaces.names().len();
+
+		match index <= count {
+			true => Ok(()),
+			false => Err(WorkspaceError::NoSuchWorkspace { index, count }),
+		}
+	}
+
+	/// Returns the window named by the given `mark`, if it is mapped.
+	fn marked_window(&self, mark: &str) -> Option<Window> {
+		self.marks
+			.window(mark)
+			.filter(|&window| {
+				self.windows
+					.get(window)
+					.is_some_and(|state| state.mapped == MapState::Mapped)
+			})
+			.cloned()
+	}
+
+	/// Swaps the given tiled windows between their nodes, which may be in different groups, leaving
+	/// the layout's structure and the sizes of its nodes untouched.
+	///
+	/// Returns [`false`] if either window isn't in the tiling layout, or if they are the same
+	/// window.
+	fn swap_window_contents(&mut self, a: &Window, b: &Window) -> bool {
+		let CurrentLayout::Tiled(manager) = &mut self.layout else {
+			return false;
+		};
+		let layout = manager.layout_mut();
+
+		let (Some(a), Some(b)) = (layout.id_of_window(a), layout.id_of_window(b)) else {
+			return false;
+		};
+		let swapped = a != b && layout.swap_windows_by_id(a, b);
+		self.changed |= swapped;
+
+		swapped
+	}
+
+	/// Starts tracking the given `window`, adding it to the tiling layout if it is `mapped`.
+	///
+	/// If the `window` is already in the tiling layout, it is handled according to the
+	/// [duplicate windows] policy: if it is rejected, the window's state is left as it was and
+	/// [`AddWindowError::AlreadyPresent`] is returned.
+	///
+	/// [duplicate windows]: LayoutSettings::duplicate_windows
+	pub fn add_window(&mut self, window: Window, mapped: MapState) -> Result<(), AddWindowError> {
+		let state = WindowState::new(mapped);
+
+		if state.mode == layout::Mode::Tiled && state.mapped == MapState::Mapped {
+			self.tile_window(window.clone(), "add_window", |manager, window| {
+				manager.add_window(window)
+			})?;
+		}
+
+		if state.mapped == MapState::Mapped {
+			let current = Desktop::Index(self.workspaces.current() as u32);
+			self.workspaces.add_window(window.clone(), current);
+		}
+		self.focus_history.add(window.clone());
+		self.windows.insert(window, state);
+
+		Ok(())
+	}
+
+	/// [Adds] each of the given `windows`, warning about (and skipping) any which are already in
+	/// the tiling layout.
+	///
+	/// [Adds]: Self::add_window
+	#[inline]
+	pub fn add_windows(&mut self, windows: impl IntoIterator<Item = (Window, MapState)>) {
+		for (window, mapped) in windows {
+			if let Err(error) = self.add_window(window, mapped) {
+				event!(Level::WARN, "Not adding a window twice: {error}");
+			}
+		}
+	}
+
+	/// Adds the given `window` to the tiling layout with `add`, which calls the layout manager's
+	/// `hook`, if there is a tiling layout.
+	///
+	/// If the `window` is already in the layout, it is handled according to the [duplicate
+	/// windows] policy.
+	///
+	/// [duplicate windows]: LayoutSettings::duplicate_windows
+	fn tile_window(
+		&mut self,
+		window: Window,
+		hook: &'static str,
+		mut add: impl FnMut(&mut dyn layout::TilingLayoutManager<Window>, Window),
+	) -> Result<(), AddWindowError> {
+		let Some(layout) = self.tiling_layout() else {
+			return Ok(());
+		};
+
+		if layout.contains_window(&window) {
+			match self.settings.duplicate_windows {
+				DuplicateWindows::Reject => return Err(AddWindowError::AlreadyPresent),
+				DuplicateWindows::Relocate => {
+					self.with_manager("remove_window_grouped", |manager| {
+						manager.remove_window_grouped(&window)
+					});
+				},
+			}
+		}
+
+		self.with_manager(hook, |manager| add(manager, window.clone()));
+		self.changed = true;
+
+		debug_assert!(
+			self.tiling_layout()
+				.into_iter()
+				.flat_map(|layout| layout.windows())
+				.filter(|&other| *other == window)
+				.count() == 1,
+			"a window was tiled twice"
+		);
+
+		Ok(())
+	}
+
+	/// Calls the given `hook` of the tiling layout manager with `call`, if there is a tiling
+	/// layout, returning its result.
+	///
+	/// If the layout manager panics, the layout [falls back] to the [`Append`] layout manager: the
+	/// panic is logged and recorded as the [`manager_panic`], any mapped tiled windows missing from
+	/// the layout are added back to it, and `call` is called again with the fallback manager.
+	///
+	/// [falls back]: CurrentLayout::isolate
+	/// [`Append`]: layout::managers::Append
+	/// [`manager_panic`]: Self::manager_panic
+	fn with_manager<T>(
+		&mut self,
+		hook: &'static str,
+		mut call: impl FnMut(&mut dyn layout::TilingLayoutManager<Window>) -> T,
+	) -> Option<T> {
+		let settings = self.settings.scaled(self.scale);
+
+		let panic = match self.layout.isolate(hook, &settings, &mut call) {
+			Ok(output) => return output,
+			Err(panic) => panic,
+		};
+
+		event!(
+			Level::ERROR,
+			"{panic}; falling back to the {} layout manager",
+			self.layout_name()
+		);
+		if let Some(error) = &panic.broken {
+			event!(Level::ERROR, "The panic broke the layout ({error}), so it was rebuilt");
+		}
+		self.manager_panic = Some(panic);
+		self.changed = true;
+
+		// Windows the layout manager had taken out of the layout when it panicked are put back, in
+		// the order they were focused (the order of `windows` isn't meaningful).
+		let missing: Vec<_> = self
+			.focus_history
+			.windows()
+			.iter()
+			.filter(|window| {
+				self.windows
+					.get(window)
+					.is_some_and(|state| state.mode == layout::Mode::Tiled && state.mapped == MapState::Mapped)
+			})
+			.filter(|window| {
+				!self
+					.tiling_layout()
+					.is_some_and(|layout| layout.contains_window(window))
+			})
+			.cloned()
+			.collect();
+
+		for window in missing {
+			let _ = self
+				.layout
+				.isolate("add_window", &settings, |manager| manager.add_window(window));
+		}
+
+		self.layout.isolate(hook, &settings, call).ok().flatten()
+	}
+
+	/// Updates AquariWM's state to reflect the given `window` being destroyed.
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn remove_window(&mut self, window: &Window) {
+		let state = self.windows.remove(window);
+
+		self.focus_history.remove(window);
+		self.iconified.retain(|other| other != window);
+		self.away.retain(|other| other != window);
+		self.workspaces.remove_window(window);
+		self.changed |= !self.marks.unmark_window(window).is_empty();
+		if let Some(session) = &mut self.switcher {
+			session.remove(window);
+		}
+
+		if self.focused.as_ref() == Some(window) {
+			self.set_focused(None, FocusCause::WindowClosed);
+		}
+
+		// Remove the window from the tiling layout if needed.
+		if let Some(state) = state {
+			if state.mode == layout::Mode::Tiled
+				&& state.mapped == MapState::Mapped
+				&& self
+					.with_manager("remove_window_grouped", |manager| manager.remove_window_grouped(window))
+					.is_some()
+			{
+				self.changed = true;
+			}
+		}
+	}
+
+	/// Updates AquariWM's state to reflect the given `window` being [mapped].
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// See [`map_window_grouped_by`] for when [`AddWindowError::AlreadyPresent`] is returned.
+	///
+	/// [mapped]: MapState::Mapped
+	/// [`apply_changes`]: Self::apply_changes
+	/// [`map_window_grouped_by`]: Self::map_window_grouped_by
+	#[inline]
+	pub fn map_window(&mut self, window: &Window) -> Result<(), AddWindowError> {
+		self.map_window_grouped_by(window, |_| false)
+	}
+
+	/// Updates AquariWM's state to reflect the given `window` being [mapped], grouping it with the
+	/// first window matching `same_class` if the [insertion strategy] is [`GroupByClass`].
+	///
+	/// Windows are generic, so AquariWM doesn't know their classes: the display server is
+	/// responsible for looking up the class of the `window` and of the windows passed to
+	/// `same_class`.
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [mapped]: MapState::Mapped
+	/// [insertion strategy]: LayoutSettings::insertion_strategy
+	/// [`GroupByClass`]: InsertionStrategy::GroupByClass
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn map_window_grouped_by(
+		&mut self,
+		window: &Window,
+		same_class: impl Fn(&Window) -> bool,
+	) -> Result<(), AddWindowError> {
+		// A window mapped by its client while iconified is no longer iconified, and windows are always
+		// mapped onto the current workspace, unless they are sticky.
+		self.iconified.retain(|other| other != window);
+		self.away.retain(|other| other != window);
+
+		let state = self
+			.windows
+			.get_mut(window)
+			.expect("the window we are attempting to map is not tracked");
+
+		let desktop = match state.sticky {
+			true => Desktop::All,
+			false => Desktop::Index(self.workspaces.current() as u32),
+		};
+		self.workspaces.add_window(window.clone(), desktop);
+
+		let (mode, mapped) = (state.mode, state.mapped);
+		state.set_mapped();
+		// A window which vanished before it was admitted starts afresh.
+		if state.admission == Admission::Vanished {
+			state.admission = Admission::Admitted;
+		}
+
+		if mode != layout::Mode::Tiled {
+			return Ok(());
+		}
+
+		match mapped {
+			MapState::Unmapped => self.insert_window(window.clone(), same_class),
+
+			// The window is being mapped again without having been unmapped, e.g. because its unmap
+			// was missed.
+			MapState::Mapped => match self.tiling_layout() {
+				Some(layout) if layout.contains_window(window) => Err(AddWindowError::AlreadyPresent),
+				_ => Ok(()),
+			},
+		}
+	}
+
+	/// Adds the given `window` to the tiling layout where the [insertion strategy] places it,
+	/// grouping it with the first window matching `same_class` if that is [`GroupByClass`].
+	///
+	/// [insertion strategy]: LayoutSettings::insertion_strategy
+	/// [`GroupByClass`]: InsertionStrategy::GroupByClass
+	fn insert_window(&mut self, window: Window, same_class: impl Fn(&Window) -> bool) -> Result<(), AddWindowError> {
+		let insertion_strategy = self.settings.insertion_strategy;
+
+		let hook = match insertion_strategy {
+			InsertionStrategy::Manager => "add_window",
+			InsertionStrategy::GroupByClass => "add_window_grouped",
+		};
+
+		self.tile_window(window, hook, |manager, window| match insertion_strategy {
+			InsertionStrategy::Manager => manager.add_window(window),
+			InsertionStrategy::GroupByClass => manager.add_window_grouped(window, &same_class),
+		})
+	}
+
+	/// Holds the given `window`, which is about to be [mapped], back from the tiling layout until
+	/// the `deadline`: it floats in the meantime, and is only tiled once it is [admitted].
+	///
+	/// Returns [`false`] if the `window` wouldn't be tiled anyway, or is already mapped.
+	///
+	/// [mapped]: Self::map_window_grouped_by
+	/// [admitted]: Self::admit_window_grouped_by
+	pub fn hold_admission(&mut self, window: &Window, deadline: Instant) -> bool {
+		let Some(state) = self.windows.get_mut(window) else {
+			return false;
+		};
+		if state.mode != layout::Mode::Tiled || state.mapped == MapState::Mapped || self.iconified.contains(window) {
+			return false;
+		}
+
+		state.set_floating();
+		state.admission = Admission::Pending { deadline };
+
+		true
+	}
+
+	/// Returns when the next window [held back] from the tiling layout is due to be admitted, or
+	/// [`None`] if no windows are held back.
+	///
+	/// [held back]: Self::hold_admission
+	pub fn admission_deadline(&self) -> Option<Instant> {
+		self.windows
+			.values()
+			.filter_map(|state| match state.admission {
+				Admission::Pending { deadline } => Some(deadline),
+				_ => None,
+			})
+			.min()
+	}
+
+	/// Returns the windows [held back] from the tiling layout which are due to be admitted at the
+	/// time `now`, in [ID] order.
+	///
+	/// [held back]: Self::hold_admission
+	/// [ID]: ManagedWindow::id
+	pub fn due_admissions(&self, now: Instant) -> Vec<Window> {
+		let mut due: Vec<_> = self
+			.windows
+			.iter()
+			.filter(|(_, state)| state.admission.is_due(now))
+			.map(|(window, _)| window.clone())
+			.collect();
+		due.sort_unstable_by_key(ManagedWindow::id);
+
+		due
+	}
+
+	/// Admits the given `window` [held back] from the tiling layout, tiling it as it would have
+	/// been when it was [mapped] if it is still floating, grouped with the first window matching
+	/// `same_class`.
+	///
+	/// A window held back which has since been tiled, e.g. by a rule, is only marked as admitted.
+	/// Returns [`false`] if the `window` isn't held back.
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [held back]: Self::hold_admission
+	/// [mapped]: Self::map_window_grouped_by
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn admit_window_grouped_by(&mut self, window: &Window, same_class: impl Fn(&Window) -> bool) -> bool {
+		let Some(state) = self.windows.get_mut(window) else {
+			return false;
+		};
+		if !state.admission.is_pending() {
+			return false;
+		}
+
+		state.admission = Admission::Admitted;
+		if state.mode != layout::Mode::Floating || state.mapped != MapState::Mapped {
+			return true;
+		}
+
+		state.set_tiled();
+		if let Err(error) = self.insert_window(window.clone(), same_class) {
+			event!(Level::WARN, "Failed to admit a window into the tiling layout: {error}");
+		}
+
+		true
+	}
+
+	/// Predicts the tile the next window to be [mapped] would be given in the tiling layout,
+	/// without changing the layout.
+	///
+	/// The prediction is exactly the tile the window is given when it is mapped with
+	/// [`map_window_grouped_by`] and changes are applied, if nothing else changes in the meantime:
+	/// the window is placed by the [insertion strategy], grouped with the first window matching
+	/// `same_class` if that is [`GroupByClass`]. Returns [`None`] if the window wouldn't be tiled,
+	/// or the prediction isn't [possible].
+	///
+	/// [mapped]: MapState::Mapped
+	/// [`map_window_grouped_by`]: Self::map_window_grouped_by
+	/// [insertion strategy]: LayoutSettings::insertion_strategy
+	/// [`GroupByClass`]: InsertionStrategy::GroupByClass
+	/// [possible]: CurrentLayout::predict_addition
+	pub fn predict_insertion(&self, same_class: impl Fn(&Window) -> bool) -> Option<Rect> {
+		let settings = self.settings.scaled(self.scale);
+		let insertion_strategy = self.settings.insertion_strategy;
+
+		self.layout
+			.predict_addition(self.focused.as_ref(), &settings, |manager| match insertion_strategy {
+				InsertionStrategy::Manager => manager.add_window(Slot::Placeholder),
+				InsertionStrategy::GroupByClass => manager.add_window_grouped(
+					Slot::Placeholder,
+					&|slot| matches!(slot, Slot::Window(window) if same_class(window)),
+				),
+			})
+	}
+
+	/// Marks the given tiled `window` to be reconfigured the next time changes are applied, even if
+	/// its tile hasn't changed, e.g. so that its geometry is applied again after it is re-mapped.
+	///
+	/// Returns [`false`] if the `window` isn't in the tiling layout.
+	pub fn refresh_window(&mut self, window: &Window) -> bool {
+		match &mut self.layout {
+			CurrentLayout::Tiled(manager) => manager.layout_mut().refresh_window(window),
+			CurrentLayout::Floating => false,
+		}
+	}
+
+	/// Sets the [resize increments] of the given tiled `window`, e.g. after its `WM_NORMAL_HINTS`
+	/// have changed.
+	///
+	/// The window is only sized to them if they are [honored]; if they have changed, its group is
+	/// laid out again the next time changes are applied. Returns [`false`] if the `window` isn't in
+	/// the tiling layout.
+	///
+	/// [resize increments]: Increments
+	/// [honored]: LayoutSettings::honor_increments
+	pub fn set_increments(&mut self, window: &Window, increments: Option<Increments>) -> bool {
+		match &mut self.layout {
+			CurrentLayout::Tiled(manager) => manager.layout_mut().set_increments(window, increments),
+			CurrentLayout::Floating => false,
+		}
+	}
+
+	/// Sets the minimum size from the given tiled `window`'s size hints, and the width of its
+	/// border, e.g. after its `WM_NORMAL_HINTS` have changed.
+	///
+	/// The layout isn't applied while it is too small to give every window its [minimum size].
+	/// Returns [`false`] if the `window` isn't in the tiling layout.
+	///
+	/// [minimum size]: crate::layout::WindowNode::minimum_size
+	pub fn set_minimum_size(&mut self, window: &Window, min_size: Option<(u32, u32)>, border_width: u32) -> bool {
+		match &mut self.layout {
+			CurrentLayout::Tiled(manager) => manager.layout_mut().set_minimum_size(window, min_size, border_width),
+			CurrentLayout::Floating => false,
+		}
+	}
+
+	/// Makes the given tiled `window` float, removing it from the tiling layout if it is mapped.
+	///
+	/// The display server is responsible for giving the window a floating geometry. Returns
+	/// [`false`] if the `window` isn't tiled.
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn float_window(&mut self, window: &Window) -> bool {
+		let Some(state) = self.windows.get(window) else {
+			return false;
+		};
+		if state.mode != layout::Mode::Tiled {
+			return false;
+		}
+
+		if state.mapped == MapState::Mapped
+			&& self
+				.with_manager("remove_window_grouped", |manager| manager.remove_window_grouped(window))
+				.is_some()
+		{
+			self.changed = true;
+		}
+		if let Some(state) = self.windows.get_mut(window) {
+			state.set_floating();
+		}
+
+		true
+	}
+
+	/// Tiles the given floating `window`, adding it to the tiling layout where the layout manager
+	/// places it if it is mapped.
+	///
+	/// Returns [`false`] if the `window` isn't floating.
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn unfloat_window(&mut self, window: &Window) -> bool {
+		let Some(state) = self.windows.get_mut(window) else {
+			return false;
+		};
+		if state.mode != layout::Mode::Floating {
+			return false;
+		}
+
+		let mapped = state.mapped == MapState::Mapped;
+		state.set_tiled();
+
+		if mapped {
+			if let Err(error) = self.tile_window(window.clone(), "add_window", |manager, window| {
+				manager.add_window(window)
+			}) {
+				event!(Level::WARN, "Failed to tile a floating window: {error}");
+			}
+		}
+
+		true
+	}
+
+	/// Updates AquariWM's state to reflect the given `window` being [unmapped].
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [unmapped]: MapState::Unmapped
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn unmap_window(&mut self, window: &Window) {
+		self.hide(window);
+
+		// A window unmapped by its client while iconified or away is withdrawn, leaving its workspace.
+		self.iconified.retain(|other| other != window);
+		self.away.retain(|other| other != window);
+		self.workspaces.remove_window(window);
+	}
+
+	/// Takes the given `window` out of the tiling layout and marks it as [unmapped], leaving it on
+	/// its workspace.
+	///
+	/// [unmapped]: MapState::Unmapped
+	fn hide(&mut self, window: &Window) {
+		let state = self
+			.windows
+			.get(window)
+			.expect("the window we are attempting to unmap is not tracked");
+
+		if state.mode == layout::Mode::Tiled
+			&& state.mapped == MapState::Mapped
+			&& self
+				.with_manager("remove_window_grouped", |manager| manager.remove_window_grouped(window))
+				.is_some()
+		{
+			self.changed = true;
+		}
+
+		if let Some(state) = self.windows.get_mut(window) {
+			state.set_unmapped();
+
+			// A window which vanishes before it is admitted never floated of its own accord.
+			if state.admission.is_pending() {
+				state.set_tiled();
+				state.admission = Admission::Vanished;
+			}
+		}
+	}
+
+	/// Returns the [iconified] windows, oldest first.
+	///
+	/// [iconified]: Self::iconify_window
+	#[inline]
+	pub fn iconified(&self) -> &[Window] {
+		&self.iconified
+	}
+
+	/// Returns whether the given `window` is [iconified].
+	///
+	/// [iconified]: Self::iconify_window
+	#[inline]
+	pub fn is_iconified(&self, window: &Window) -> bool {
+		self.iconified.contains(window)
+	}
+
+	/// Iconifies (minimizes) the given mapped `window`: it is [unmapped], removing it from the
+	/// tiling layout, and remembered as iconified until it is [restored].
+	///
+	/// If the `window` was focused, the most recently focused window which is still mapped is
+	/// focused instead. The display server is responsible for unmapping the window and marking it
+	/// as iconified.
+	///
+	/// Returns [`false`] if the `window` isn't tracked, isn't mapped, or is already iconified.
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [unmapped]: Self::unmap_window
+	/// [restored]: Self::restore_window_grouped_by
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn iconify_window(&mut self, window: &Window) -> bool {
+		let mapped = self
+			.windows
+			.get(window)
+			.is_some_and(|state| state.mapped == MapState::Mapped);
+		if !mapped || self.is_iconified(window) {
+			return false;
+		}
+
+		self.hide(window);
+		self.iconified.push(window.clone());
+
+		if self.focused.as_ref() == Some(window) {
+			self.set_focused(self.most_recently_focused(), FocusCause::WindowClosed);
+		}
+
+		true
+	}
+
+	/// Returns the most recently focused window which is still mapped, if any.
+	fn most_recently_focused(&self) -> Option<Window> {
+		self.focus_history
+			.windows()
+			.iter()
+			.find(|window| {
+				self.windows
+					.get(window)
+					.is_some_and(|state| state.mapped == MapState::Mapped)
+			})
+			.cloned()
+	}
+
+	/// Restores the given [iconified] `window`, mapping it again where the [insertion strategy]
+	/// places it, as with [`map_window_grouped_by`], and focusing it.
+	///
+	/// The display server is responsible for mapping the window and marking it as no longer
+	/// iconified. Returns [`false`] if the `window` isn't iconified.
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [iconified]: Self::iconify_window
+	/// [insertion strategy]: LayoutSettings::insertion_strategy
+	/// [`map_window_grouped_by`]: Self::map_window_grouped_by
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn restore_window_grouped_by(&mut self, window: &Window, same_class: impl Fn(&Window) -> bool) -> bool {
+		if !self.is_iconified(window) {
+			return false;
+		}
+
+		if let Err(error) = self.map_window_grouped_by(window, same_class) {
+			event!(Level::WARN, "Failed to restore an iconified window: {error}");
+		}
+		self.set_focused(Some(window.clone()), FocusCause::Keyboard);
+
+		true
+	}
+
+	/// Returns whether the given `window` is away: hidden because it is on a workspace other than
+	/// the current one.
+	///
+	/// Away windows are out of the tiling layout, and [unmapped], until their workspace is switched
+	/// to.
+	///
+	/// [unmapped]: MapState::Unmapped
+	#[inline]
+	pub fn is_away(&self, window: &Window) -> bool {
+		self.away.contains(window)
+	}
+
+	/// Returns the windows which are [away] on other workspaces, in the order they were hidden.
+	///
+	/// [away]: Self::is_away
+	#[inline]
+	pub fn away(&self) -> &[Window] {
+		&self.away
+	}
+
+	/// Returns the [desktop] the given `window` is on: every desktop if it is [sticky], or else the
+	/// workspace it is on, which is the current workspace for windows which aren't on any.
+	///
+	/// [desktop]: Desktop
+	/// [sticky]: WindowState::sticky
+	pub fn desktop_of(&self, window: &Window) -> Desktop {
+		if self.windows.get(window).is_some_and(|state| state.sticky) {
+			return Desktop::All;
+		}
+
+		(self.workspaces.desktop_of(window)).unwrap_or(Desktop::Index(self.workspaces.current() as u32))
+	}
+
+	/// Makes the given `window` [sticky], shown on every workspace, or no longer sticky, leaving it
+	/// on the current workspace.
+	///
+	/// Returns [`false`] if the `window` isn't tracked.
+	///
+	/// [sticky]: WindowState::sticky
+	pub fn set_sticky(&mut self, window: &Window, sticky: bool) -> bool {
+		let Some(state) = self.windows.get_mut(window) else {
+			return false;
+		};
+		state.set_sticky(sticky);
+
+		let desktop = match (sticky, self.workspaces.desktop_of(window)) {
+			// Windows which aren't on a workspace join one when they are mapped.
+			(_, None) => return true,
+
+			(true, _) => Desktop::All,
+			(false, Some(Desktop::All)) => Desktop::Index(self.workspaces.current() as u32),
+			(false, Some(desktop)) => desktop,
+		};
+		self.workspaces.add_window(window.clone(), desktop);
+
+		true
+	}
+
+	/// Switches to the workspace at the given `index`, creating it if it is the workspace after the
+	/// last, and then [collecting] empty workspaces as the workspaces' policy says, along with
+	/// their `pins`.
+	///
+	/// The windows on the workspace switched away from are taken out of the tiling layout, and
+	/// those on the new workspace are put back where the [insertion strategy] places them, as
+	/// restored windows are, grouped with the first window which has the same class according to
+	/// `same_class` if that is [`GroupByClass`]. If the focused window was hidden, the most
+	/// recently focused window which is still mapped is focused instead.
+	///
+	/// The display server is responsible for hiding and showing the windows in the returned
+	/// [change], and for updating their desktops.
+	///
+	/// In order to apply any changes that may have been made to the tiling layout,
+	/// [`apply_changes`]
+	#[cfg_attr(feature = "async", doc = "or [`apply_changes_async`](Self::apply_changes_async)")]
+	/// must be called.
+	///
+	/// [collecting]: Workspaces::collection
+	/// [insertion strategy]: LayoutSettings::insertion_strategy
+	/// [`GroupByClass`]: InsertionStrategy::GroupByClass
+	/// [change]: WorkspaceChange
+	/// [`apply_changes`]: Self::apply_changes
+	pub fn switch_to_workspace(
+		&mut self,
+		index: usize,
+		pins: &mut OutputAssignment,
+		same_class: impl Fn(&Window, &Window) -> bool,
+	) -> Result<WorkspaceChange<Window>, WorkspaceError> {
+		let before = self.desktop_snapshot();
+		self.workspaces.switch_to(index, pins)?;
+
+		Ok(self.show_current_workspace(before, same_class))
+	}
+
+	/// Moves the given `window` to the workspace at the given `index`, creating it if it is the
+	/// workspace after the last; a [sticky] window stops being sticky.
+	///
+	/// If the workspace isn't the current one, the window is hidden, as with [switching
+	/// workspaces], and if it is, an [away] window is shown again. The workspace the window leaves
+	/// is only collected once it is switched away from. Windows which aren't mapped, iconified or
+	/// away aren't on any workspace, so they aren't moved.
+	///
+	/// [sticky]: WindowState::sticky
+	/// [switching workspaces]: Self::switch_to_workspace
+	/// [away]: Self::is_away
+	pub fn move_window_to_workspace(
+		&mut self,
+		window: &Window,
+		index: usize,
+		same_class: impl Fn(&Window, &Window) -> bool,
+	) -> Result<WorkspaceChange<Window>, WorkspaceError> {
+		if self.workspaces.desktop_of(window).is_none() {
+			return Ok(WorkspaceChange::default());
+		}
+
+		let before = self.desktop_snapshot();
+		self.workspaces.move_window(window.clone(), index)?;
+		if let Some(state) = self.windows.get_mut(window) {
+			state.set_sticky(false);
+		}
+
+		Ok(self.show_current_workspace(before, same_class))
+	}
+
+	/// Moves the given `window` to the workspace at the given `index` and switches to it, creating
+	/// it if it is the workspace after the last; a [sticky] window stops being sticky.
+	///
+	/// This is a [move] and a [switch] in one step: the window is never hidden, and the tiling
+	/// layout is only changed once, so that the windows on both workspaces are reconfigured at
+	/// most once when the changes are applied. The window stays focused if it was.
+	///
+	/// [sticky]: WindowState::sticky
+	/// [move]: Self::move_window_to_workspace
+	/// [switch]: Self::switch_to_workspace
+	pub fn move_window_to_workspace_and_follow(
+		&mut self,
+		window: &Window,
+		index: usize,
+		pins: &mut OutputAssignment,
+		same_class: impl Fn(&Window, &Window) -> bool,
+	) -> Result<WorkspaceChange<Window>, WorkspaceError> {
+		if self.workspaces.desktop_of(window).is_none() {
+			return Ok(WorkspaceChange::default());
+		}
+
+		let before = self.desktop_snapshot();
+		self.workspaces.move_window(window.clone(), index)?;
+		if let Some(state) = self.windows.get_mut(window) {
+			state.set_sticky(false);
+		}
+		self.workspaces.switch_to(index, pins)?;
+
+		Ok(self.show_current_workspace(before, same_class))
+	}
+
+	/// Hides the mapped windows which aren't on the current workspace and shows the [away] windows
+	/// which are, returning what changed since the desktops were `before`.
+	///
+	/// [away]: Self::is_away
+	fn show_current_workspace(
+		&mut self,
+		before: HashMap<Window, Desktop>,
+		same_class: impl Fn(&Window, &Window) -> bool,
+	) -> WorkspaceChange<Window> {
+		let current = Desktop::Index(self.workspaces.current() as u32);
+		let elsewhere =
+			|desktop: Option<Desktop>| desktop.is_some_and(|desktop| desktop != current && desktop != Desktop::All);
+
+		// Tiled windows are hidden in layout order, so that they are put back in the same order,
+		// followed by the other windows, least recently focused first.
+		let tiled: Vec<_> = (self.tiling_layout().into_iter())
+			.flat_map(|layout| layout.windows())
+			.cloned()
+			.collect();
+		let others = (self.focus_history.windows().iter().rev()).filter(|window| !tiled.contains(window));
+		let hidden: Vec<_> = (tiled.iter().chain(others))
+			.filter(|window| {
+				self.windows
+					.get(window)
+					.is_some_and(|state| state.mapped == MapState::Mapped)
+			})
+			.filter(|window| elsewhere(self.workspaces.desktop_of(window)))
+			.cloned()
+			.collect();
+
+		for window in &hidden {
+			self.hide(window);
+			self.away.push(window.clone());
+		}
+
+		let shown: Vec<_> = (self.away.iter())
+			.filter(|window| !elsewhere(self.workspaces.desktop_of(window)))
+			.cloned()
+			.collect();
+
+		for window in &shown {
+			if let Err(error) = self.map_window_grouped_by(window, |other| same_class(window, other)) {
+				event!(Level::WARN, "Failed to show a window on the current workspace: {error}");
+			}
+		}
+
+		let focused_hidden = self.focused.as_ref().is_some_and(|focused| hidden.contains(focused));
+		if focused_hidden {
+			self.set_focused(self.most_recently_focused(), FocusCause::WindowClosed);
+		}
+		self.changed = true;
+
+		// Windows are listed in the order they were focused, so that the order is predictable.
+		let desktops = (self.focus_history.windows().iter())
+			.filter_map(|window| {
+				let desktop = self.workspaces.desktop_of(window)?;
+
+				(before.get(window) != Some(&desktop)).then(|| (window.clone(), desktop))
+			})
+			.collect();
+
+		WorkspaceChange {
+			hidden,
+			shown,
+			desktops,
+		}
+	}
+
+	/// Returns the desktop each window on a workspace is on.
+	fn desktop_snapshot(&self) -> HashMap<Window, Desktop> {
+		(self.workspaces.desktops())
+			.map(|(window, desktop)| (window.clone(), desktop))
+			.collect()
+	}
+
+	/// Applies changes made by the [layout manager] by calling [`apply_resizes`] with the given
+	/// `resize_window` function.
+	///
+	/// Windows which `resize_window` [skips] are removed, and the layout is rebalanced once without
+	/// them. The skipped windows are returned in the order they were skipped: in [layout order],
+	/// followed by any skipped by the rebalance.
+	///
+	/// The visible windows which were reconfigured, and the geometries they really have, are then
+	/// [reported] to the layout manager.
+	///
+	/// [layout order]: layout::GroupNode::windows
+	///
+	/// [layout manager]: layout::TilingLayoutManager
+	/// [`apply_resizes`]: layout::GroupNode::apply_changes
+	/// [skips]: Reconfigured::SkipWindow
+	/// [reported]: layout::TilingLayoutManager::changes_applied
+	#[cfg_attr(
+		feature = "async",
+		doc = "",
+		doc = " # See also",
+		doc = "[`apply_changes_async`] allows using a `resize_window` function that returns a",
+		doc = "[future].",
+		doc = "",
+		doc = "[`apply_changes_async`]: Self::apply_changes_async",
+		doc = "[future]: Future"
+	)]
+	pub fn apply_changes<Outcome, Error>(
+		&mut self,
+		mut reconfigure_window: impl FnMut(&Window, i32, i32, u32, u32, Visibility) -> Result<Outcome, Error>,
+	) -> Result<Vec<Window>, Error>
+	where
+		Outcome: Into<Reconfigured>,
+	{
+		let settings = self.settings.scaled(self.scale);
+		let mut skipped = Vec::new();
+		let mut changes = Vec::new();
+
+		// Skipped windows are removed and the layout is rebalanced once without them. Windows skipped
+		// by that rebalance are removed too, but the layout isn't rebalanced without them until
+		// changes are next applied.
+		for _ in 0..2 {
+			let CurrentLayout::Tiled(manager) = &mut self.layout else {
+				break;
+			};
+
+			let newly_skipped = manager.layout_mut().apply_changes(
+				&mut |window: &Window, x, y, width, height, visibility| {
+					let outcome = reconfigure_window(window, x, y, width, height, visibility)?.into();
+					if visibility == Visibility::Visible {
+						changes.push(AppliedChange {
+							window: window.clone(),
+							requested: (x, y, width, height),
+							actual: outcome.actual((x, y, width, height)),
+						});
+					}
+
+					Ok(outcome)
+				},
+				&settings,
+			)?;
+
+			if manager.layout().is_deferred() {
+				event!(
+					Level::DEBUG,
+					"Tiling layout dimensions are degenerate; deferring changes"
+				);
+			}
+
+			if newly_skipped.is_empty() {
+				break;
+			}
+			self.remove_skipped(&newly_skipped);
+			skipped.extend(newly_skipped);
+		}
+		self.report_applied(changes);
+
+		Ok(skipped)
+	}
+
+	#[doc(cfg(feature = "async"))]
+	/// Applies changes made by the [layout manager] by calling `apply_changes` with the given
+	/// `resize_window` function.
+	///
+	/// # See also
+	/// [`apply_changes`] allows using a `resize_window` function that doesn't return a [future].
+	///
+	/// [layout manager]: layout::TilingLayoutManager
+	/// [future]: Future
+	///
+	/// [`apply_changes`]: Self::apply_changes
+	#[cfg(feature = "async")]
+	pub async fn apply_changes_async<ResizeWindowFuture, Outcome, Error>(
+		&mut self,
+		mut reconfigure_window: impl FnMut(&Window, i32, i32, u32, u32, Visibility) -> ResizeWindowFuture,
+	) -> Result<Vec<Window>, Error>
+	where
+		ResizeWindowFuture: Future<Output = Result<Outcome, Error>>,
+		Outcome: Into<Reconfigured>,
+	{
+		let settings = self.settings.scaled(self.scale);
+		let mut skipped = Vec::new();
+		let mut changes = Vec::new();
+
+		// Skipped windows are removed and the layout is rebalanced once without them, as in
+		// `apply_changes`.
+		for _ in 0..2 {
+			let CurrentLayout::Tiled(manager) = &mut self.layout else {
+				break;
+			};
+
+			// Add all the `resize_window` futures to this list, along with their windows and the
+			// geometries they were given...
+			let mut futures = Vec::new();
+			let mut windows = Vec::new();
+
+			manager.layout_mut().apply_changes(
+				&mut |window: &Window, x, y, width, height, visibility| -> Result<(), Error> {
+					futures.push(reconfigure_window(window, x, y, width, height, visibility));
+					windows.push((window.clone(), (x, y, width, height), visibility));
+
+					Ok(())
+				},
+				&settings,
+			)?;
+
+			if manager.layout().is_deferred() {
+				event!(
+					Level::DEBUG,
+					"Tiling layout dimensions are degenerate; deferring changes"
+				);
+			}
+
+			// Await all the `resize_window` futures.
+			let outcomes = future::try_join_all(futures).await?;
+			let mut newly_skipped = Vec::new();
+			for ((window, requested, visibility), outcome) in windows.into_iter().zip(outcomes) {
+				let outcome: Reconfigured = outcome.into();
+
+				if visibility == Visibility::Visible {
+					changes.push(AppliedChange {
+						window: window.clone(),
+						requested,
+						actual: outcome.actual(requested),
+					});
+				}
+				if outcome == Reconfigured::SkipWindow {
+					newly_skipped.push(window);
+				}
+			}
+
+			if newly_skipped.is_empty() {
+				break;
+			}
+			self.remove_skipped(&newly_skipped);
+			skipped.extend(newly_skipped);
+		}
+		self.report_applied(changes);
+
+		Ok(skipped)
+	}
+
+	/// [Reports] the given `changes` made by applying the layout to the layout manager, if there
+	/// are any.
+	///
+	/// Windows reconfigured again by the rebalance after skipped windows are removed are only
+	/// reported with their final geometries.
+	///
+	/// [Reports]: layout::TilingLayoutManager::changes_applied
+	fn report_applied(&mut self, mut changes: Vec<AppliedChange<Window>>) {
+		if changes.is_empty() {
+			return;
+		}
+
+		let mut reported = HashSet::new();
+		changes.reverse();
+		changes.retain(|change| reported.insert(change.window.clone()));
+		changes.reverse();
+
+		self.with_manager("changes_applied", |manager| manager.changes_applied(&changes));
+	}
+
+	/// Removes the given [skipped] windows, which could not be reconfigured, from AquariWM's state.
+	///
+	/// [skipped]: Reconfigured::SkipWindow
+	fn remove_skipped(&mut self, skipped: &[Window]) {
+		event!(
+			Level::DEBUG,
+			"Removing {} windows which could not be reconfigured",
+			skipped.len()
+		);
+
+		for window in skipped {
+			self.remove_window(window);
+		}
+	}
+}
+
+/// Returns the center of the given `rect`.
+const fn center((x, y, width, height): Rect) -> (i32, i32) {
+	(x + ((width / 2) as i32), y + ((height / 2) as i32))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+	use crate::{layout::managers::Stack, output, window::WindowId};
+
+	#[test]
+	fn skipped_windows() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=5).map(|window| (window, MapState::Mapped)));
+
+		let mut reconfigured = Vec::new();
+		let skipped = state
+			.apply_changes(|&window, x, y, width, height, _| -> Result<_, ()> {
+				// Windows `2` and `4` have been destroyed.
+				if window == 2 || window == 4 {
+					return Ok(Reconfigured::SkipWindow);
+				}
+
+				reconfigured.push((window, x, y, width, height));
+				Ok(Reconfigured::Applied)
+			})
+			.unwrap();
+
+		assert_eq!(skipped, [2, 4]);
+		assert!(!state.windows.contains_key(&2) && !state.windows.contains_key(&4));
+
+		// The other windows were reconfigured, then rebalanced without the skipped windows.
+		let (first, rebalanced) = reconfigured.split_at(3);
+		assert_eq!(first.iter().map(|&(window, ..)| window).collect::<Vec<_>>(), [1, 3, 5]);
+		assert_eq!(first[0], (1, 0, 0, 500, 600));
+		assert_eq!(rebalanced, [(3, 500, 0, 500, 300), (5, 500, 300, 500, 300)]);
+	}
+
+	/// Tests that the layout manager is told which windows were reconfigured, with the geometries
+	/// the display server really gave them, once per apply.
+	#[test]
+	fn applied_changes() {
+		use std::sync::Mutex;
+
+		use crate::layout::{Orientation, TilingLayout, TilingLayoutManager};
+
+		/// The changes reported to the layout manager, one entry per report.
+		static REPORTS: Mutex<Vec<Vec<AppliedChange<u32>>>> = Mutex::new(Vec::new());
+
+		/// A layout manager which records the changes reported to it.
+		struct Recording(Stack<u32>);
+
+		unsafe impl TilingLayoutManager<u32> for Recording {
+			fn orientation() -> Orientation {
+				Stack::<u32>::orientation()
+			}
+
+			fn init<WindowsIter>(layout: TilingLayout<u32>, windows: WindowsIter) -> Self
+			where
+				WindowsIter: IntoIterator<Item = u32>,
+				WindowsIter::IntoIter: ExactSizeIterator,
+			{
+				Self(Stack::init(layout, windows))
+			}
+
+			fn layout(&self) -> &TilingLayout<u32> {
+				self.0.layout()
+			}
+
+			fn layout_mut(&mut self) -> &mut TilingLayout<u32> {
+				self.0.layout_mut()
+			}
+
+			fn add_window(&mut self, window: u32) {
+				self.0.add_window(window);
+			}
+
+			fn remove_window(&mut self, window: &u32) {
+				self.0.remove_window(window);
+			}
+
+			fn changes_applied(&mut self, changes: &[AppliedChange<u32>]) {
+				REPORTS.lock().unwrap().push(changes.to_vec());
+			}
+		}
+
+		/// Applies the layout, as a display server which keeps window `3` at least 400 pixels high
+		/// and for which window `2` has been destroyed if `destroyed`.
+		fn apply(state: &mut AquariWm<u32>, destroyed: bool) -> Vec<Vec<AppliedChange<u32>>> {
+			state
+				.apply_changes(|&window, x, y, width, height, _| -> Result<_, ()> {
+					Ok(match window {
+						2 if destroyed => Reconfigured::SkipWindow,
+						3 if height < 400 => Reconfigured::Adjusted {
+							x,
+							y,
+							width,
+							height: 400,
+						},
+
+						_ => Reconfigured::Applied,
+					})
+				})
+				.unwrap();
+
+			std::mem::take(&mut *REPORTS.lock().unwrap())
+		}
+		let change = |window, requested, actual| AppliedChange {
+			window,
+			requested,
+			actual,
+		};
+
+		let mut state = AquariWm::with_tiling_layout::<Recording>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+
+		assert_eq!(
+			apply(&mut state, false),
+			[vec![
+				change(1, (0, 0, 500, 600), Some((0, 0, 500, 600))),
+				change(2, (500, 0, 500, 300), Some((500, 0, 500, 300))),
+				change(3, (500, 300, 500, 300), Some((500, 300, 500, 400))),
+			]]
+		);
+		// Nothing is reported if no windows are reconfigured.
+		assert_eq!(apply(&mut state, false), Vec::<Vec<_>>::new());
+
+		// Windows reconfigured again by the rebalance without a skipped window are reported once,
+		// with their final geometries.
+		state.add_windows([(4, MapState::Mapped)]);
+		let reports = apply(&mut state, true);
+		assert_eq!(reports.len(), 1);
+		let mut changes = reports[0].clone();
+		changes.sort_by_key(|change| change.window);
+		assert_eq!(
+			changes,
+			[
+				change(2, (500, 0, 500, 200), None),
+				change(3, (500, 0, 500, 300), Some((500, 0, 500, 400))),
+				change(4, (500, 300, 500, 300), Some((500, 300, 500, 300))),
+			]
+		);
+	}
+
+	/// Tests focusing outputs and moving windows between them, and that the active output follows
+	/// focus and the pointer.
+	#[test]
+	fn outputs() {
+		let output = |name: &str, x, primary| Output {
+			name: name.to_owned(),
+
+			x,
+			y: 0,
+
+			width: 1920,
+			height: 1080,
+
+			primary,
+			scale: Scale::IDENTITY,
+
+			clones: Vec::new(),
+		};
+
+		// ┌───────┬───────┐
+		// │       │   2   │
+		// │   1   ├───────┤
+		// │       │   3   │
+		// └───────┴───────┘
+		//   DP-1    HDMI-1
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 3840, 1080, LayoutSettings::new().window_gap(0));
+		state.outputs = vec![output("DP-1", 0, false), output("HDMI-1", 1920, true)];
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+
+		let apply_changes = |state: &mut AquariWm<u32>| {
+			state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+		};
+		let output_of = |state: &AquariWm<u32>, window| state.output_of(&window).map(|output| output.name.clone());
+		apply_changes(&mut state);
+
+		state.set_focused_from(Some(3), FocusCause::Pointer);
+		assert_eq!(state.active_output.name(), Some("HDMI-1"));
+
+		// Focusing the output to the left focuses its window.
+		assert_eq!(
+			state.perform(Action::FocusOutput(output::Direction::Left)).unwrap(),
+			Outcome::Done
+		);
+		assert_eq!((state.focused, state.active_output.name()), (Some(1), Some("DP-1")));
+
+		// There is nothing further left unless the edges wrap around. Windows `2` and `3` are
+		// equally near the center of HDMI-1, so the first is focused.
+		assert!(matches!(
+			state.perform(Action::FocusOutput(output::Direction::Left)),
+			Err(ActionError::NoOutputInDirection(output::Direction::Left))
+		));
+		assert_eq!(state.focused, Some(1));
+		state.output_edges = OutputEdges::Wrap;
+		state.perform(Action::FocusOutput(output::Direction::Left)).unwrap();
+		assert_eq!((state.focused, state.active_output.name()), (Some(2), Some("HDMI-1")));
+
+		state.perform(Action::FocusOutputByName("DP-1".to_owned())).unwrap();
+		assert_eq!(state.focused, Some(1));
+		let error = state.perform(Action::FocusOutputByName("DP-2".to_owned())).unwrap_err();
+		assert!(error.is_failure(), "{error}");
+
+		// Moving a window to another output keeps it focused.
+		state.set_focused_from(Some(3), FocusCause::Keyboard);
+		assert_eq!(
+			state
+				.perform(Action::MoveWindowToOutput(output::Direction::Left))
+				.unwrap(),
+			Outcome::MovedToOutput("DP-1".to_owned())
+		);
+		apply_changes(&mut state);
+		assert_eq!(output_of(&state, 3).as_deref(), Some("DP-1"));
+		assert_eq!((state.focused, state.active_output.name()), (Some(3), Some("DP-1")));
+
+		// Following a window to another output re-tiles both outputs together: every window on
+		// them is reconfigured once.
+		assert_eq!(
+			state
+				.perform(Action::MoveToOutputAndFollow(output::Direction::Right))
+				.unwrap(),
+			Outcome::MovedToOutput("HDMI-1".to_owned())
+		);
+		let mut reconfigured = Vec::new();
+		state
+			.apply_changes(|&window, _, _, _, _, _| {
+				reconfigured.push(window);
+
+				Ok::<_, ()>(())
+			})
+			.unwrap();
+		reconfigured.sort_unstable();
+		assert_eq!(reconfigured, [1, 2, 3]);
+		assert_eq!(output_of(&state, 3).as_deref(), Some("HDMI-1"));
+		assert_eq!((state.focused, state.active_output.name()), (Some(3), Some("HDMI-1")));
+		state
+			.perform(Action::MoveWindowToOutput(output::Direction::Left))
+			.unwrap();
+		apply_changes(&mut state);
+
+		// The pointer crossing outputs is ignored after explicit focus changes, until focus next
+		// follows the pointer.
+		state.pointer_moved(3000, 500);
+		assert_eq!(state.active_output.name(), Some("DP-1"));
+		state.set_focused_from(Some(2), FocusCause::Pointer);
+		state.pointer_moved(100, 500);
+		assert_eq!(state.active_output.name(), Some("DP-1"));
+		state.pointer_moved(3000, 500);
+		assert_eq!(state.active_output.name(), Some("HDMI-1"));
+	}
+
+	/// Tests selecting groups around the focused window with [`FocusParent`] and [`FocusChild`],
+	/// and that moving a focused group to another output keeps its shape.
+	///
+	/// [`FocusParent`]: Action::FocusParent
+	/// [`FocusChild`]: Action::FocusChild
+	#[test]
+	fn group_focus() {
+		let output = |name: &str, x| Output {
+			name: name.to_owned(),
+
+			x,
+			y: 0,
+
+			width: 1920,
+			height: 1080,
+
+			primary: false,
+			scale: Scale::IDENTITY,
+
+			clones: Vec::new(),
+		};
+
+		// ┌───────┬───────┐
+		// │   1   │   3   │
+		// ├───────┼───┬───┤
+		// │   2   │ 4 │ 5 │
+		// └───────┴───┴───┘
+		//   DP-1    HDMI-1
+		let settings = LayoutSettings::new().window_gap(0);
+		let mut layout = layout::TilingLayout::new(layout::Orientation::LeftToRight, 0, 0, 3840, 1080, &settings);
+		layout
+			.push_group_back_with(layout::Orientation::TopToBottom, |group| {
+				group.push_windows_back([1, 2])
+			})
+			.unwrap();
+		layout
+			.push_group_back_with(layout::Orientation::TopToBottom, |group| {
+				group.push_window_back(3);
+				group
+					.push_group_back_with(layout::Orientation::LeftToRight, |group| {
+						group.push_windows_back([4, 5])
+					})
+					.unwrap();
+			})
+			.unwrap();
+		let (outer, inner) = (
+			layout.group_at(&[1]).unwrap().id(),
+			layout.group_at(&[1, 1]).unwrap().id(),
+		);
+
+		let mut state = AquariWm::with_restored_tiling_layout::<layout::managers::Append<u32>>(
+			layout,
+			(0, 0, 3840, 1080),
+			(1..=5).map(|window| (window, MapState::Mapped)),
+			settings,
+		);
+		state.outputs = vec![output("DP-1", 0), output("HDMI-1", 1920)];
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+
+		state.set_focused_from(Some(4), FocusCause::Keyboard);
+		assert_eq!(state.focus_target(), Some(FocusTarget::Window(4)));
+
+		// Each group around the focused window is selected in turn, up to the root's children.
+		state.perform(Action::FocusParent).unwrap();
+		assert_eq!(state.focus_target(), Some(FocusTarget::Group(inner)));
+		assert_eq!(state.focus_target_windows(), [4, 5]);
+		assert!(state.in_focus_target(&5) && !state.in_focus_target(&3));
+
+		state.perform(Action::FocusParent).unwrap();
+		assert!(matches!(
+			state.perform(Action::FocusParent),
+			Err(ActionError::NoParentGroup)
+		));
+		assert_eq!(state.focus_target(), Some(FocusTarget::Group(outer)));
+		assert_eq!(state.focus_target_windows(), [3, 4, 5]);
+
+		// Descending returns to the groups selected on the way up.
+		state.perform(Action::FocusChild).unwrap();
+		assert_eq!(state.focus_target(), Some(FocusTarget::Group(inner)));
+
+		// The group is moved intact, keeping its windows side by side with equal sizes.
+		assert_eq!(
+			state
+				.perform(Action::MoveWindowToOutput(output::Direction::Left))
+				.unwrap(),
+			Outcome::MovedToOutput("DP-1".to_owned())
+		);
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+
+		let layout = state.tiling_layout().unwrap();
+		assert_eq!(layout.path_of(inner), Some(vec![0, 1]));
+		assert_eq!(layout.group_at(&[0, 1]).unwrap().windows(), [&4, &5]);
+		assert_eq!(state.tile_of(&4), Some((0, 360, 960, 360)));
+		assert_eq!(state.tile_of(&5), Some((960, 360, 960, 360)));
+		assert_eq!(state.focus_target(), Some(FocusTarget::Group(inner)));
+		assert_eq!(state.active_output.name(), Some("DP-1"));
+
+		// Focusing another window forgets the selected groups.
+		state.set_focused(Some(1), FocusCause::Keyboard);
+		state.set_focused(Some(4), FocusCause::Keyboard);
+		state.perform(Action::FocusChild).unwrap();
+		assert_eq!(state.focus_target(), Some(FocusTarget::Window(4)));
+	}
+
+	/// Tests that [resizing the focused window] reports the clamped amount it was resized by, in
+	/// logical pixels.
+	///
+	/// [resizing the focused window]: Action::ResizeFocused
+	#[test]
+	fn resize_focused() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.set_scale(Scale::new(2.0).unwrap());
+		state.add_windows([(1, MapState::Mapped), (2, MapState::Mapped)]);
+		state.set_focused(Some(1), FocusCause::Keyboard);
+
+		let apply_changes = |state: &mut AquariWm<u32>| {
+			state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+		};
+		let resize = |pixels| Action::ResizeFocused {
+			axis: layout::Axis::Horizontal,
+			amount: layout::Amount::Pixels(pixels),
+		};
+
+		apply_changes(&mut state);
+		assert_eq!(state.perform(resize(100)).unwrap(), Outcome::Resized(100));
+
+		// Window `2` can't be shrunk below 5% of the layout's 1000 device pixels.
+		apply_changes(&mut state);
+		assert_eq!(state.perform(resize(1000)).unwrap(), Outcome::Resized(125));
+
+		// There is no vertical group for the windows to be resized in.
+		let vertical = Action::ResizeFocused {
+			axis: layout::Axis::Vertical,
+			amount: layout::Amount::Percent(10),
+		};
+		assert!(matches!(
+			state.perform(vertical),
+			Err(ActionError::NoGroupToResize(layout::Axis::Vertical))
+		));
+	}
+
+	#[test]
+	fn toggle_manual() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+		state.set_focused(Some(2), FocusCause::Keyboard);
+
+		state.perform(Action::ToggleManual).unwrap();
+
+		// New windows are no longer added to the focused window's group.
+		state.add_window(4, MapState::Mapped).unwrap();
+		let layout = state.tiling_layout().unwrap();
+		let stack = layout.find_group_by(|&window| window == 3);
+		assert_eq!(stack, None);
+		assert_eq!(layout.len(), 3);
+	}
+
+	/// Tests that the focused window is maximized within its own group, with its siblings shrunk to
+	/// strips of the maximize strip in device pixels.
+	#[test]
+	fn toggle_group_maximize() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.set_scale(Scale::new(2.0).unwrap());
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+		state.set_focused(Some(2), FocusCause::Keyboard);
+
+		let apply_changes = |state: &mut AquariWm<u32>| {
+			state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+		};
+		let heights = |state: &AquariWm<u32>| [2, 3].map(|window| state.tile_of(&window).unwrap().3);
+
+		apply_changes(&mut state);
+		assert_eq!(heights(&state), [300, 300]);
+
+		state.perform(Action::ToggleGroupMaximize).unwrap();
+		apply_changes(&mut state);
+		assert_eq!(heights(&state), [520, 80]);
+
+		state.perform(Action::ToggleGroupMaximize).unwrap();
+		apply_changes(&mut state);
+		assert_eq!(heights(&state), [300, 300]);
+	}
+
+	#[test]
+	fn move_tab() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+		state.set_focused(Some(1), FocusCause::Keyboard);
+
+		let windows = |state: &AquariWm<u32>| -> Vec<u32> {
+			state.tiling_layout().unwrap().windows().into_iter().copied().collect()
+		};
+
+		assert!(matches!(
+			state.perform(Action::MoveTabRight),
+			Err(ActionError::NotStacked)
+		));
+
+		let CurrentLayout::Tiled(manager) = &mut state.layout else {
+			unreachable!("the state has a tiling layout");
+		};
+		manager.layout_mut().set_stacked(true);
+
+		state.perform(Action::MoveTabRight).unwrap();
+		assert_eq!(windows(&state), [2, 3, 1]);
+
+		state.perform(Action::MoveTabLeft).unwrap();
+		assert_eq!(windows(&state), [1, 2, 3]);
+	}
+
+	/// Tests that a window which is already in the tiling layout is rejected or relocated,
+	/// depending on the [`DuplicateWindows`] policy, rather than being tiled twice.
+	#[test]
+	fn duplicate_windows() {
+		let windows = |state: &AquariWm<u32>| -> Vec<u32> {
+			state.tiling_layout().unwrap().windows().into_iter().copied().collect()
+		};
+
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+
+		assert_eq!(
+			state.add_window(1, MapState::Mapped),
+			Err(AddWindowError::AlreadyPresent)
+		);
+		assert_eq!(windows(&state), [1, 2, 3]);
+
+		let settings = LayoutSettings::new()
+			.window_gap(0)
+			.duplicate_windows(DuplicateWindows::Relocate);
+		let mut state = AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, settings);
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+
+		assert_eq!(state.add_window(1, MapState::Mapped), Ok(()));
+		assert_eq!(windows(&state), [2, 3, 1]);
+	}
+
+	/// Tests that a replayed map request for a tiled window re-applies its existing geometry.
+	#[test]
+	fn replayed_map() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows([(1, MapState::Unmapped), (2, MapState::Mapped)]);
+
+		let apply_changes = |state: &mut AquariWm<u32>| {
+			let mut reconfigured = Vec::new();
+			state
+				.apply_changes(|&window, x, y, width, height, _| -> Result<_, ()> {
+					reconfigured.push((window, x, y, width, height));
+					Ok(Reconfigured::Applied)
+				})
+				.unwrap();
+
+			reconfigured
+		};
+
+		assert_eq!(state.map_window(&1), Ok(()));
+		let geometry = apply_changes(&mut state);
+		let geometry = *geometry.iter().find(|&&(window, ..)| window == 1).unwrap();
+
+		assert_eq!(state.map_window(&1), Err(AddWindowError::AlreadyPresent));
+		assert!(state.refresh_window(&1));
+		assert_eq!(apply_changes(&mut state), [geometry]);
+	}
+
+	/// Tests that the predicted tile of the next window is exactly the tile it is given once it is
+	/// mapped, for every layout manager and insertion strategy, in layouts of different shapes.
+	#[test]
+	fn predict_insertion() {
+		let strategies = [InsertionStrategy::Manager, InsertionStrategy::GroupByClass];
+		// The next window is of the same class as the windows with even numbers.
+		let same_class = |window: &u32| window.is_multiple_of(2);
+
+		let cases = BuiltIn::ALL.into_iter().flat_map(|manager| {
+			strategies
+				.into_iter()
+				.flat_map(move |strategy| (0..7).map(move |count| (manager, strategy, count)))
+		});
+
+		for (manager, insertion_strategy, count) in cases {
+			let settings = LayoutSettings::new()
+				.window_gap(10)
+				.insertion_strategy(insertion_strategy);
+			let mut state = AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1200, 800, settings);
+			state.set_layout_manager(manager);
+			for window in 1..=count {
+				state.add_window(window, MapState::Unmapped).unwrap();
+				state.map_window_grouped_by(&window, same_class).unwrap();
+			}
+			// Changes which are still pending are predicted too.
+			if count.is_multiple_of(2) {
+				state
+					.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(Reconfigured::Applied))
+					.unwrap();
+			}
+			state.set_focused(count.checked_div(2).filter(|&window| window != 0), FocusCause::Keyboard);
+
+			let predicted = state.predict_insertion(same_class);
+
+			let next = count + 1;
+			state.add_window(next, MapState::Unmapped).unwrap();
+			state.map_window_grouped_by(&next, same_class).unwrap();
+			let mut tile = None;
+			state
+				.apply_changes(|&window, x, y, width, height, _| -> Result<_, ()> {
+					if window == next {
+						tile = Some((x, y, width, height));
+					}
+
+					Ok(Reconfigured::Applied)
+				})
+				.unwrap();
+
+			assert!(tile.is_some());
+			assert_eq!(
+				predicted, tile,
+				"{manager} with {insertion_strategy:?} and {count} windows"
+			);
+		}
+	}
+
+	/// Tests that switching windows cycles through them in most-recently-used order, and that only
+	/// the committed window is moved to the front of the focus history.
+	#[test]
+	fn switch_windows() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=4).map(|window| (window, MapState::Mapped)));
+		for window in [4, 3, 2, 1] {
+			state.set_focused(Some(window), FocusCause::Keyboard);
+		}
+		assert_eq!(state.focus_history.windows(), [1, 2, 3, 4]);
+
+		// A single press switches to the previously focused window...
+		assert_eq!(state.switch_windows(Key::Next, &[]), Some(Step::Focus(2)));
+		assert_eq!(state.switch_windows(Key::Release, &[]), Some(Step::Commit(2)));
+		assert_eq!(state.focus_history.windows(), [2, 1, 3, 4]);
+
+		// ...and back again.
+		state.switch_windows(Key::Next, &[]);
+		state.switch_windows(Key::Release, &[]);
+		assert_eq!(
+			(state.focused, state.focus_history.windows()),
+			(Some(1), &[1, 2, 3, 4][..])
+		);
+
+		// Repeated presses walk further back, without reordering the history until committing.
+		state.switch_windows(Key::Next, &[]);
+		assert_eq!(state.switch_windows(Key::Next, &[]), Some(Step::Focus(3)));
+		assert_eq!(state.focused, Some(3));
+		assert_eq!(state.focus_history.windows(), [1, 2, 3, 4]);
+		state.switch_windows(Key::Release, &[]);
+		assert_eq!(state.focus_history.windows(), [3, 1, 2, 4]);
+
+		// Cancelling restores the original focus.
+		state.switch_windows(Key::Previous, &[]);
+		assert_eq!(state.focused, Some(4));
+		assert_eq!(state.switch_windows(Key::Cancel, &[]), Some(Step::Cancel(Some(3))));
+		assert_eq!((state.focused, state.is_switching()), (Some(3), false));
+		assert_eq!(state.focus_history.windows(), [3, 1, 2, 4]);
+
+		// Keys other than Tab don't start a session.
+		assert_eq!(state.switch_windows(Key::Release, &[]), None);
+	}
+
+	/// Tests that focus cycles through tiled windows by their positions, then floating windows
+	/// from the bottom of the stack up, and that switching windows falls back to that order for
+	/// windows which have never been focused.
+	#[test]
+	fn visual_order() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+		for window in [4, 5] {
+			state.add_window(window, MapState::Unmapped).unwrap();
+			state.windows.get_mut(&window).unwrap().set_floating();
+			state.map_window(&window).unwrap();
+		}
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+
+		let floating = [5, 4];
+		assert_eq!(state.visual_order(&floating), [1, 2, 3, 5, 4]);
+		// Floating windows the display server didn't give are ordered by their IDs.
+		assert_eq!(state.visual_order(&[]), [1, 2, 3, 4, 5]);
+
+		assert_eq!(state.focus_previous(&floating), Some(4));
+		assert_eq!(state.focus_next(&floating), Some(1));
+		assert_eq!(state.focus_next(&floating), Some(2));
+		assert_eq!(state.focused, Some(2));
+
+		// Windows which have been focused come first, most recently focused first.
+		assert_eq!(state.switch_candidates(&floating), [2, 1, 4, 3, 5]);
+	}
+
+	/// Tests dropping a dragged tiled window onto another window and beside it.
+	#[test]
+	fn drop_window() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+		let drop = |target, region| moveresize::Drop {
+			target,
+			region,
+			tile: (0, 0, 0, 0),
+		};
+
+		assert!(state.drop_window(&3, &drop(1, moveresize::DropRegion::Swap)));
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+		assert_eq!(state.visual_order(&[]), [3, 2, 1]);
+
+		let before = moveresize::DropRegion::Insert {
+			axis: layout::Axis::Horizontal,
+			side: layout::Side::Before,
+		};
+		assert!(state.drop_window(&1, &drop(3, before)));
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+		assert_eq!(state.visual_order(&[]), [1, 3, 2]);
+
+		assert!(!state.drop_window(&1, &drop(4, before)));
+	}
+
+	/// Tests that toggling stickiness affects only the focused window.
+	#[test]
+	fn sticky() {
+		let mut state = AquariWm::with_windows(
+			[(1, MapState::Mapped), (2, MapState::Mapped)],
+			LayoutSettings::default(),
+		);
+		let sticky = |state: &AquariWm<u32>| state.windows.iter().filter(|(_, window)| window.sticky).count();
+
+		// Without a focused window, there is nothing to make sticky.
+		assert!(matches!(
+			state.perform(Action::ToggleSticky),
+			Err(ActionError::NoFocusedWindow)
+		));
+		assert_eq!(sticky(&state), 0);
+
+		state.set_focused(Some(2), FocusCause::Keyboard);
+		assert_eq!(state.perform(Action::ToggleSticky).unwrap(), Outcome::Sticky(true));
+		assert!(state.windows[&2].sticky && !state.windows[&1].sticky);
+
+		assert_eq!(state.perform(Action::ToggleSticky).unwrap(), Outcome::Sticky(false));
+		assert_eq!(sticky(&state), 0);
+	}
+
+	/// Tests that switching workspaces shows only the windows on the current workspace and sticky
+	/// windows, and that workspaces are created when used and collected when left empty.
+	#[test]
+	fn workspaces() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+		for window in [1, 2, 3] {
+			state.set_focused(Some(window), FocusCause::Keyboard);
+		}
+		let mut pins = OutputAssignment::default();
+		let tiled = |state: &AquariWm<u32>| -> Vec<u32> {
+			let mut windows: Vec<_> = state.tiling_layout().unwrap().windows().into_iter().copied().collect();
+			windows.sort_unstable();
+
+			windows
+		};
+		let names = |state: &AquariWm<u32>| -> Vec<String> {
+			state.workspaces.names().iter().map(ToString::to_string).collect()
+		};
+
+		// Only the workspace after the last can be created.
+		assert!(matches!(
+			state.perform(Action::SwitchToWorkspace(2)),
+			Err(ActionError::Workspace(WorkspaceError::NoSuchWorkspace {
+				index: 2,
+				count: 1
+			}))
+		));
+		assert_eq!(
+			state.perform(Action::MoveToWorkspace(1)).unwrap(),
+			Outcome::MoveToWorkspace(1)
+		);
+
+		// A window moved to another workspace is hidden, and focus falls back to the most recently
+		// focused window still shown.
+		let change = state.move_window_to_workspace(&3, 1, |_, _| false).unwrap();
+		assert_eq!(
+			change,
+			WorkspaceChange {
+				hidden: vec![3],
+				shown: Vec::new(),
+				desktops: vec![(3, Desktop::Index(1))],
+			}
+		);
+		assert_eq!(names(&state), ["1", "2"]);
+		assert_eq!(tiled(&state), [1, 2]);
+		assert!(state.is_away(&3));
+		assert_eq!(state.focused, Some(2));
+
+		// Sticky windows are shown on every workspace.
+		assert!(state.set_sticky(&1, true));
+		let change = state.switch_to_workspace(1, &mut pins, |_, _| false).unwrap();
+		assert_eq!((change.hidden, change.shown), (vec![2], vec![3]));
+		assert!(change.desktops.is_empty());
+		assert_eq!(tiled(&state), [1, 3]);
+		assert_eq!(state.desktop_of(&1), Desktop::All);
+		assert_eq!(state.focused, Some(3));
+
+		// A workspace is only collected once it is left empty and switched away from.
+		let change = state.move_window_to_workspace(&3, 0, |_, _| false).unwrap();
+		assert_eq!(change.hidden, [3]);
+		assert_eq!(names(&state), ["1", "2"]);
+		let change = state.switch_to_workspace(0, &mut pins, |_, _| false).unwrap();
+		assert_eq!((change.hidden, change.shown), (Vec::new(), vec![2, 3]));
+		assert_eq!(names(&state), ["1"]);
+		assert_eq!(tiled(&state), [1, 2, 3]);
+
+		// Moving a sticky window leaves it on a single workspace.
+		state.move_window_to_workspace(&1, 1, |_, _| false).unwrap();
+		assert!(!state.windows[&1].sticky);
+		assert_eq!(state.desktop_of(&1), Desktop::Index(1));
+		assert_eq!(tiled(&state), [2, 3]);
+
+		// Closed windows leave their workspaces.
+		state.remove_window(&1);
+		assert!(!state.is_away(&1));
+		state.switch_to_workspace(1, &mut pins, |_, _| false).unwrap();
+		state.switch_to_workspace(0, &mut pins, |_, _| false).unwrap();
+		assert_eq!(names(&state), ["1"]);
+	}
+
+	/// Tests that moving a window to another workspace and following it there never hides the
+	/// window, and re-tiles the windows on the new workspace once.
+	#[test]
+	fn move_and_follow() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+		for window in [1, 2, 3] {
+			state.set_focused(Some(window), FocusCause::Keyboard);
+		}
+		let mut pins = OutputAssignment::default();
+		state.move_window_to_workspace(&2, 1, |_, _| false).unwrap();
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+
+		assert_eq!(
+			state.perform(Action::MoveToWorkspaceAndFollow(1)).unwrap(),
+			Outcome::MoveToWorkspaceAndFollow(1)
+		);
+		let change = state
+			.move_window_to_workspace_and_follow(&3, 1, &mut pins, |_, _| false)
+			.unwrap();
+		assert_eq!(
+			change,
+			WorkspaceChange {
+				hidden: vec![1],
+				shown: vec![2],
+				desktops: vec![(3, Desktop::Index(1))],
+			}
+		);
+		assert_eq!(state.workspaces.current(), 1);
+		assert_eq!(state.focused, Some(3));
+
+		let mut reconfigured = Vec::new();
+		state
+			.apply_changes(|&window, _, _, _, _, _| {
+				reconfigured.push(window);
+
+				Ok::<_, ()>(())
+			})
+			.unwrap();
+		reconfigured.sort_unstable();
+		assert_eq!(reconfigured, [2, 3]);
+	}
+
+	/// Tests that iconified windows leave the layout and focus until they are restored, oldest
+	/// first.
+	#[test]
+	fn iconify() {
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=4).map(|window| (window, MapState::Mapped)));
+		for window in [1, 2, 3] {
+			state.set_focused(Some(window), FocusCause::Keyboard);
+		}
+		let tiled = |state: &AquariWm<u32>| -> Vec<u32> {
+			let mut windows: Vec<_> = state.tiling_layout().unwrap().windows().into_iter().copied().collect();
+			windows.sort_unstable();
+
+			windows
+		};
+
+		// Focus falls back to the most recently focused window still mapped.
+		assert!(state.iconify_window(&3));
+		assert!(state.iconify_window(&2));
+		assert!(!state.iconify_window(&2));
+		assert_eq!(state.iconified(), [3, 2]);
+		assert_eq!(tiled(&state), [1, 4]);
+		assert_eq!(state.focused, Some(1));
+
+		// The oldest iconified window is restored first, and focused.
+		assert_eq!(
+			state.perform(Action::RestoreMinimized).unwrap(),
+			Outcome::RestoreMinimized
+		);
+		assert!(state.restore_window_grouped_by(&3, |_| false));
+		assert!(!state.restore_window_grouped_by(&3, |_| false));
+		assert_eq!(state.iconified(), [2]);
+		assert_eq!(tiled(&state), [1, 3, 4]);
+		assert_eq!(state.focused, Some(3));
+
+		// Windows mapped or destroyed by their clients are no longer iconified.
+		state.map_window(&2).unwrap();
+		assert!(state.iconified().is_empty());
+		state.iconify_window(&4);
+		state.remove_window(&4);
+		assert!(state.iconified().is_empty());
+		assert!(matches!(
+			state.perform(Action::RestoreMinimized),
+			Err(ActionError::NothingMinimized)
+		));
+	}
+
+	/// Tests that windows held back from the tiling layout only change it once they are admitted,
+	/// and never if they vanish first.
+	#[test]
+	fn admission() {
+		let start = Instant::now();
+		let at = |millis| start + Duration::from_millis(millis);
+
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows([(1, MapState::Mapped), (2, MapState::Unmapped), (3, MapState::Unmapped)]);
+		state
+			.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(Reconfigured::Applied))
+			.unwrap();
+		state.take_changed();
+		let tiled = |state: &AquariWm<u32>| state.tiling_layout().unwrap().windows().len();
+
+		// Mapped windows which don't need to wait can't be held back.
+		assert!(!state.hold_admission(&1, at(150)));
+
+		for (window, deadline) in [(2, at(150)), (3, at(200))] {
+			assert!(state.hold_admission(&window, deadline));
+			state.map_window(&window).unwrap();
+		}
+		assert_eq!(state.windows[&2].mode, layout::Mode::Floating);
+		assert_eq!(state.admission_deadline(), Some(at(150)));
+		assert!(state.due_admissions(at(149)).is_empty());
+
+		// A window which vanishes before its deadline never touches the layout.
+		state.unmap_window(&3);
+		assert_eq!(state.windows[&3].admission, Admission::Vanished);
+		assert_eq!(state.windows[&3].mode, layout::Mode::Tiled);
+		assert!(!state.take_changed());
+		assert_eq!(tiled(&state), 1);
+
+		// A window still mapped at its deadline is tiled.
+		assert_eq!(state.due_admissions(at(300)), [2]);
+		assert!(state.admit_window_grouped_by(&2, |_| false));
+		assert!(!state.admit_window_grouped_by(&2, |_| false));
+		assert_eq!(state.windows[&2].admission, Admission::Admitted);
+		assert_eq!(state.windows[&2].mode, layout::Mode::Tiled);
+		assert_eq!(tiled(&state), 2);
+		assert_eq!(state.admission_deadline(), None);
+
+		// A vanished window starts afresh when it is mapped again.
+		state.map_window(&3).unwrap();
+		assert_eq!(state.windows[&3].admission, Admission::Admitted);
+		assert_eq!(tiled(&state), 3);
+	}
+
+	/// Tests that the same scripted scenario gives byte-identical geometries and window lists every
+	/// time it is run, whatever order the client map happens to iterate in.
+	#[test]
+	fn determinism() {
+		use crate::window_list::{Details, WindowList};
+
+		let run = || {
+			let output = |name: &str, x, primary| Output {
+				name: name.to_owned(),
+
+				x,
+				y: 0,
+
+				width: 1001,
+				height: 767,
+
+				primary,
+				scale: output::Scale::IDENTITY,
+
+				clones: Vec::new(),
+			};
+
+			let mut state = AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 2002, 767, LayoutSettings::default());
+			state.outputs = vec![output("DP-1", 0, true), output("HDMI-1", 1001, false)];
+			let mut table = String::new();
+
+			let operations: [fn(&mut AquariWm<u32>); 20] = [
+				|state| state.add_windows((1..=3).map(|window| (window, MapState::Mapped))),
+				|state| state.resize(0, 0, 2003, 767),
+				|state| state.add_windows([(4, MapState::Mapped), (5, MapState::Mapped)]),
+				|state| state.set_focused_from(Some(4), FocusCause::Keyboard),
+				|state| assert!(state.float_window(&2)),
+				|state| assert!(state.iconify_window(&3)),
+				|state| state.rotate_root(1),
+				|state| state.set_focused_from(Some(5), FocusCause::Keyboard),
+				|state| assert!(state.restore_window_grouped_by(&3, |_| false)),
+				|state| assert!(state.unfloat_window(&2)),
+				|state| state.remove_window(&1),
+				|state| state.add_windows([(6, MapState::Mapped)]),
+				|state| {
+					let _ = state.perform(Action::ResizePrimary(37));
+				},
+				|state| {
+					let _ = state.perform(Action::SwapWithPrimary);
+				},
+				|state| {
+					let _ = state.perform(Action::MoveWindowToOutput(output::Direction::Right));
+				},
+				|state| {
+					let _ = state.perform(Action::ToggleSticky);
+				},
+				|state| state.unmap_window(&4),
+				|state| state.add_windows([(7, MapState::Mapped), (8, MapState::Mapped)]),
+				|state| state.rotate_root(-1),
+				|state| state.resize(0, 0, 2002, 767),
+			];
+
+			for (i, operation) in operations.into_iter().enumerate() {
+				operation(&mut state);
+
+				table.push_str(&format!("{i}:"));
+				state
+					.apply_changes(|window, x, y, width, height, visibility| {
+						table.push_str(&format!(" {window}@{x},{y},{width}x{height}:{visibility:?}"));
+
+						Ok::<_, ()>(())
+					})
+					.unwrap();
+				table.push('\n');
+			}
+
+			let list = WindowList::of(&state, &[], |_| Details::default());
+
+			(table, list.encode())
+		};
+
+		let (table, list) = run();
+		for _ in 0..4 {
+			let (other_table, other_list) = run();
+
+			assert_eq!(table, other_table);
+			assert_eq!(list, other_list);
+		}
+	}
+
+	/// Tests that a layout manager panicking is replaced by the fallback layout manager, with every
+	/// window still tiled, and that the layout keeps working afterwards.
+	#[test]
+	fn panicking_manager() {
+		use crate::{
+			layout::{Orientation, TilingLayout, TilingLayoutManager},
+			status::Status,
+		};
+
+		/// A layout manager which panics after adding window `4`.
+		struct Fragile(Stack<u32>);
+
+		unsafe impl TilingLayoutManager<u32> for Fragile {
+			fn orientation() -> Orientation {
+				Stack::<u32>::orientation()
+			}
+
+			fn init<WindowsIter>(layout: TilingLayout<u32>, windows: WindowsIter) -> Self
+			where
+				WindowsIter: IntoIterator<Item = u32>,
+				WindowsIter::IntoIter: ExactSizeIterator,
+			{
+				Self(Stack::init(layout, windows))
+			}
+
+			fn layout(&self) -> &TilingLayout<u32> {
+				self.0.layout()
+			}
+
+			fn layout_mut(&mut self) -> &mut TilingLayout<u32> {
+				self.0.layout_mut()
+			}
+
+			fn add_window(&mut self, window: u32) {
+				self.0.add_window(window);
+
+				assert_ne!(window, 4, "window 4 is cursed");
+			}
+
+			fn remove_window(&mut self, window: &u32) {
+				self.0.remove_window(window);
+			}
+		}
+
+		let windows = |state: &AquariWm<u32>| -> Vec<u32> {
+			state.tiling_layout().unwrap().windows().into_iter().copied().collect()
+		};
+		let apply_changes = |state: &mut AquariWm<u32>| {
+			let mut reconfigured = Vec::new();
+			state
+				.apply_changes(|&window, x, y, width, height, _| -> Result<_, ()> {
+					reconfigured.push((window, x, y, width, height));
+					Ok(Reconfigured::Applied)
+				})
+				.unwrap();
+
+			reconfigured
+		};
+
+		let mut state = AquariWm::with_tiling_layout::<Fragile>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+		apply_changes(&mut state);
+		assert_eq!(state.layout_name(), "Fragile");
+
+		state.add_windows([(4, MapState::Mapped)]);
+		let panic = state.manager_panic.clone().unwrap();
+		assert_eq!((panic.manager, panic.hook), ("Fragile", "add_window"));
+		assert!(panic.message.contains("window 4 is cursed"));
+		assert_eq!(panic.broken, None);
+
+		// Every window is still tiled, once.
+		assert_eq!(state.layout_name(), "Append");
+		assert_eq!(windows(&state), [1, 2, 3, 4]);
+		let reconfigured = apply_changes(&mut state);
+		assert!(reconfigured
+			.iter()
+			.all(|&(_, _, _, width, height)| width > 0 && height > 0));
+		assert_eq!(state.tile_of(&4), Some((750, 0, 250, 600)));
+
+		// The layout keeps working with the fallback layout manager.
+		state.remove_window(&2);
+		state.add_windows([(5, MapState::Mapped)]);
+		assert!(state.float_window(&1));
+		apply_changes(&mut state);
+		assert_eq!(windows(&state), [3, 4, 5]);
+		assert_eq!(state.tile_of(&3), Some((0, 0, 334, 600)));
+
+		let status = Status::of(&state, |_| false);
+		assert_eq!(status.layout, "Append");
+		assert_eq!(status.manager_panic, Some(panic.to_string()));
+	}
+
+	/// Tests that the layout manager can be swapped by name, keeping the layout's windows, and that
+	/// the swapped-in layout manager survives being snapshotted and restored.
+	#[test]
+	fn workspace_layout() {
+		let resolve = |id: WindowId| u32::try_from(id.0).ok();
+
+		let tiles = |state: &mut AquariWm<u32>| {
+			let mut tiles = Vec::new();
+			state
+				.apply_changes(|&window, x, y, width, height, _| {
+					tiles.push((window, x, y, width, height));
+
+					Ok::<_, ()>(Reconfigured::Applied)
+				})
+				.unwrap();
+			tiles.sort_unstable();
+
+			tiles
+		};
+
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 1000, 600, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=4).map(|window| (window, MapState::Mapped)));
+		let stack = tiles(&mut state);
+
+		// Unknown layout managers are rejected.
+		assert!(matches!(
+			state.perform(Action::SetWorkspaceLayout("Tabbed".to_owned())),
+			Err(ActionError::UnknownLayoutManager(_))
+		));
+		assert_eq!(state.layout_name(), "Stack");
+		assert!(tiles(&mut state).is_empty());
+
+		state.perform(Action::SetWorkspaceLayout("spiral".to_owned())).unwrap();
+		assert_eq!(state.layout_name(), "Spiral");
+		let spiral = tiles(&mut state);
+		assert_eq!(
+			spiral.iter().map(|&(window, ..)| window).collect::<Vec<_>>(),
+			[1, 2, 3, 4]
+		);
+		assert_ne!(spiral, stack);
+
+		// Swapping to the current layout manager does nothing.
+		assert!(!state.set_layout_manager(BuiltIn::Spiral));
+
+		let json = serde_json::to_string(&state.workspace_snapshot().unwrap()).unwrap();
+		let snapshot: PersistedWorkspace = serde_json::from_str(&json).unwrap();
+		let snapshot = snapshot.resolve(resolve).unwrap();
+		assert_eq!(snapshot.manager.as_deref(), Some("Spiral"));
+
+		let manager: BuiltIn = snapshot.manager.unwrap().parse().unwrap();
+		let mut restored = AquariWm::with_restored_manager(
+			manager.init(snapshot.layout, std::iter::empty()),
+			(0, 0, 1000, 600),
+			(1..=4).map(|window| (window, MapState::Mapped)),
+			LayoutSettings::new().window_gap(0),
+		);
+		assert_eq!(restored.layout_name(), "Spiral");
+		tiles(&mut restored);
+		for &(window, x, y, width, height) in &spiral {
+			assert_eq!(restored.tile_of(&window), Some((x, y, width, height)));
+		}
+
+		// Snapshots from before layout managers were recorded are still restored.
+		let layout = serde_json::to_string(state.tiling_layout().unwrap()).unwrap();
+		let snapshot: PersistedWorkspace = serde_json::from_str(&layout).unwrap();
+		assert_eq!(snapshot.resolve(resolve).unwrap().manager, None);
+	}
+
+	/// Tests focusing and swapping with marked windows in other groups on other outputs, and that
+	/// marks are forgotten with their windows.
+	#[test]
+	fn marks() {
+		// ┌───────┬───────┐
+		// │       │   2   │
+		// │   1   ├───────┤
+		// │       │   3   │
+		// └───────┴───────┘
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 3840, 1080, LayoutSettings::new().window_gap(0));
+		state.add_windows((1..=3).map(|window| (window, MapState::Mapped)));
+		state
+			.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(Reconfigured::Applied))
+			.unwrap();
+		let (left, bottom_right) = (state.tile_of(&1), state.tile_of(&3));
+
+		// Marking a window which isn't focused does nothing.
+		state.set_focused(None, FocusCause::Keyboard);
+		assert!(matches!(
+			state.perform(Action::Mark("editor".to_owned())),
+			Err(ActionError::NoFocusedWindow)
+		));
+		assert!(state.marks.is_empty());
+
+		state.set_focused(Some(1), FocusCause::Keyboard);
+		state.perform(Action::Mark("editor".to_owned())).unwrap();
+		state.set_focused(Some(3), FocusCause::Keyboard);
+		state.perform(Action::Mark("mail".to_owned())).unwrap();
+		assert!(state.take_changed());
+
+		state.perform(Action::FocusMark("editor".to_owned())).unwrap();
+		assert_eq!(state.focused, Some(1));
+
+		// The focused window takes the marked window's tile, and keeps focus.
+		state.perform(Action::SwapWithMark("mail".to_owned())).unwrap();
+		state
+			.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(Reconfigured::Applied))
+			.unwrap();
+		assert_eq!((state.tile_of(&1), state.tile_of(&3)), (bottom_right, left));
+		assert_eq!(state.focused, Some(1));
+		// The marks follow their windows.
+		assert_eq!(state.marks.window("mail"), Some(&3));
+
+		// Floating windows can't be swapped.
+		assert!(state.float_window(&3));
+		assert!(matches!(
+			state.perform(Action::SwapWithMark("mail".to_owned())),
+			Err(ActionError::NotSwappable(_))
+		));
+		assert_eq!(state.tile_of(&1), bottom_right);
+
+		state.perform(Action::Unmark(None)).unwrap();
+		assert_eq!(state.marks.window("editor"), None);
+		// Marks are forgotten when their window is.
+		state.remove_window(&3);
+		assert!(state.marks.is_empty());
+		assert!(matches!(
+			state.perform(Action::FocusMark("mail".to_owned())),
+			Err(ActionError::MarkNotShown(_))
+		));
+		assert_eq!(state.focused, Some(1));
 	}
 }
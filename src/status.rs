@@ -0,0 +1,348 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The status of AquariWM's workspaces, exported for external status bars.
+//!
+//! Many windows can be mapped, unmapped and focused while handling a single event, and a bar which
+//! redraws after each change would flicker. Instead, the display server [updates] the status as
+//! often as it likes, and [flushes] it once per event loop iteration: only then, and only if the
+//! status actually changed since it was last published, is it published.
+//!
+//! Sticky windows are counted on the workspace being shown, as they are shown on every workspace.
+//!
+//! [updates]: StatusPublisher::update
+//! [flushes]: StatusPublisher::flush
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	desktop::Desktop,
+	state::{AquariWm, MapState},
+	window::ManagedWindow,
+};
+
+/// The name of the first workspace, which the layout manager is configured for.
+pub(crate) const WORKSPACE: &str = "1";
+
+/// The status of a workspace.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct WorkspaceStatus {
+	pub name: String,
+
+	/// The number of windows on the workspace which aren't iconified.
+	pub windows: usize,
+	/// Whether any window on the workspace is demanding attention.
+	pub urgent: bool,
+
+	/// Whether the workspace is the one being shown.
+	pub active: bool,
+}
+
+/// The status of AquariWM as a whole.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct Status {
+	pub workspaces: Vec<WorkspaceStatus>,
+
+	/// The [name] of the current layout manager.
+	///
+	/// [name]: crate::layout::TilingLayoutManager::name
+	pub layout: String,
+	/// What happened the last time the layout manager [panicked], if it has, so that bars can let
+	/// the user know their layout manager was replaced.
+	///
+	/// [panicked]: crate::layout::ManagerPanic
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub manager_panic: Option<String>,
+}
+
+/// A part of the [status] which is published separately, e.g. as its own property.
+///
+/// [status]: Status
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum Part {
+	/// The number of workspaces.
+	WorkspaceCount,
+	/// The names of the workspaces.
+	WorkspaceNames,
+	/// The index of the active workspace.
+	ActiveWorkspace,
+
+	/// The whole status, [encoded] together.
+	///
+	/// [encoded]: Status::encode
+	Status,
+}
+
+/// Publishes the [status] at most once per event loop iteration.
+///
+/// [status]: Status
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct StatusPublisher {
+	/// The status which was last published, if any has been.
+	published: Option<Status>,
+	/// The latest status, if it has been updated since it was last published.
+	pending: Option<Status>,
+
+	/// The parts of the pending status which differ from the published status.
+	dirty: BTreeSet<Part>,
+}
+
+impl Status {
+	/// Returns the status of the given AquariWM `state`, where `urgent` returns whether a window is
+	/// demanding attention.
+	pub fn of<Window>(state: &AquariWm<Window>, urgent: impl Fn(&Window) -> bool) -> Self
+	where
+		Window: ManagedWindow + Send + Sync,
+	{
+		let current = state.workspaces.current();
+		let mut workspaces: Vec<_> = (state.workspaces.names().iter().enumerate())
+			.map(|(index, name)| WorkspaceStatus {
+				name: name.to_string(),
+
+				windows: 0,
+				urgent: false,
+
+				active: index == current,
+			})
+			.collect();
+
+		let shown = state
+			.windows
+			.iter()
+			.filter(|(window, window_state)| window_state.mapped == MapState::Mapped || state.is_away(window))
+			.map(|(window, _)| window);
+
+		for window in shown {
+			let index = match state.desktop_of(window) {
+				Desktop::Index(index) => index as usize,
+				Desktop::All => current,
+			};
+
+			if let Some(workspace) = workspaces.get_mut(index) {
+				workspace.windows += 1;
+				workspace.urgent |= urgent(window);
+			}
+		}
+
+		Self {
+			workspaces,
+
+			layout: state.layout_name().to_owned(),
+			manager_panic: state.manager_panic.as_ref().map(ToString::to_string),
+		}
+	}
+
+	/// Returns the index of the active workspace, or `0` if no workspace is active.
+	pub fn active_workspace(&self) -> usize {
+		self.workspaces
+			.iter()
+			.position(|workspace| workspace.active)
+			.unwrap_or_default()
+	}
+
+	/// Returns the names of the workspaces, in order.
+	pub fn workspace_names(&self) -> impl Iterator<Item = &str> {
+		self.workspaces.iter().map(|workspace| workspace.name.as_str())
+	}
+
+	/// Encodes the status as JSON, for bars to read.
+	pub fn encode(&self) -> String {
+		serde_json::to_string(self).expect("the status is always serializable")
+	}
+
+	/// Returns the parts of this status which differ from the `other` status.
+	fn diff(&self, other: Option<&Self>) -> BTreeSet<Part> {
+		let Some(other) = other else {
+			return BTreeSet::from([
+				Part::WorkspaceCount,
+				Part::WorkspaceNames,
+				Part::ActiveWorkspace,
+				Part::Status,
+			]);
+		};
+
+		let mut dirty = BTreeSet::new();
+
+		if self.workspaces.len() != other.workspaces.len() {
+			dirty.insert(Part::WorkspaceCount);
+		}
+		if !self.workspace_names().eq(other.workspace_names()) {
+			dirty.insert(Part::WorkspaceNames);
+		}
+		if self.active_workspace() != other.active_workspace() {
+			dirty.insert(Part::ActiveWorkspace);
+		}
+		if self != other {
+			dirty.insert(Part::Status);
+		}
+
+		dirty
+	}
+}
+
+impl StatusPublisher {
+	/// Creates a publisher which hasn't published any status yet.
+	#[inline]
+	pub const fn new() -> Self {
+		Self {
+			published: None,
+			pending: None,
+
+			dirty: BTreeSet::new(),
+		}
+	}
+
+	/// Returns the status which was last published, if any has been.
+	#[inline]
+	pub const fn published(&self) -> Option<&Status> {
+		self.published.as_ref()
+	}
+
+	/// Updates the status to be published at the next [flush].
+	///
+	/// [flush]: Self::flush
+	pub fn update(&mut self, status: Status) {
+		self.dirty = status.diff(self.published.as_ref());
+		self.pending = Some(status);
+	}
+
+	/// Publishes the latest status, returning it along with the parts of it which changed.
+	///
+	/// Returns [`None`] if the status hasn't changed since it was last published.
+	pub fn flush(&mut self) -> Option<(&Status, BTreeSet<Part>)> {
+		let pending = self.pending.take()?;
+		let dirty = std::mem::take(&mut self.dirty);
+
+		if dirty.is_empty() {
+			return None;
+		}
+
+		Some((self.published.insert(pending), dirty))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		layout::{managers::Stack, LayoutSettings},
+		output::OutputAssignment,
+	};
+
+	/// Tests that a scripted sequence of changes publishes the status once per iteration, with
+	/// its final contents.
+	#[test]
+	fn once_per_iteration() {
+		let windows = [(1, MapState::Mapped)];
+		let mut state = AquariWm::with_tiling_layout_and_windows::<Stack<u32>>(
+			0,
+			0,
+			1920,
+			1080,
+			windows,
+			LayoutSettings::default(),
+		);
+		let mut urgent = Vec::new();
+		let mut publisher = StatusPublisher::new();
+
+		// Runs one event loop iteration, updating the status after every change.
+		let mut iteration = |state: &mut AquariWm<u32>, urgent: &[u32], changes: &[(u32, bool)]| {
+			for &(window, mapped) in changes {
+				match mapped {
+					true => {
+						if !state.windows.contains_key(&window) {
+							state.add_window(window, MapState::Unmapped).unwrap();
+						}
+						state.map_window(&window).unwrap();
+					},
+					false => state.unmap_window(&window),
+				}
+
+				publisher.update(Status::of(state, |window| urgent.contains(window)));
+			}
+
+			publisher
+				.flush()
+				.map(|(status, dirty)| (status.clone(), dirty.into_iter().collect::<Vec<_>>()))
+		};
+
+		// Everything is published the first time.
+		let (status, dirty) = iteration(&mut state, &urgent, &[(2, true), (3, true), (2, false)]).unwrap();
+		assert_eq!(
+			dirty,
+			[
+				Part::WorkspaceCount,
+				Part::WorkspaceNames,
+				Part::ActiveWorkspace,
+				Part::Status
+			]
+		);
+		assert_eq!(status.workspaces[0].windows, 2);
+		assert_eq!(status.layout, "Stack");
+
+		// Changes which cancel out aren't published.
+		assert_eq!(iteration(&mut state, &urgent, &[(4, true), (4, false)]), None);
+		assert_eq!(iteration(&mut state, &urgent, &[]), None);
+
+		// Only the status changes when windows are mapped or become urgent.
+		urgent.push(3);
+		let (status, dirty) = iteration(&mut state, &urgent, &[(2, true)]).unwrap();
+		assert_eq!(dirty, [Part::Status]);
+		assert_eq!(
+			status.encode(),
+			r#"{"workspaces":[{"name":"1","windows":3,"urgent":true,"active":true}],"layout":"Stack"}"#
+		);
+		assert_eq!(publisher.published(), Some(&status));
+	}
+	/// Tests that windows are counted on their workspaces, with sticky windows counted on the
+	/// workspace being shown, and that switching workspaces publishes the active workspace.
+	#[test]
+	fn multiple_workspaces() {
+		let windows = (1..=3).map(|window| (window, MapState::Mapped));
+		let mut state = AquariWm::with_tiling_layout_and_windows::<Stack<u32>>(
+			0,
+			0,
+			1920,
+			1080,
+			windows,
+			LayoutSettings::default(),
+		);
+		let mut pins = OutputAssignment::default();
+		let mut publisher = StatusPublisher::new();
+		let urgent = |window: &u32| *window == 2;
+
+		state.move_window_to_workspace(&2, 1, |_, _| false).unwrap();
+		state.set_sticky(&3, true);
+		publisher.update(Status::of(&state, urgent));
+		publisher.flush();
+
+		fn counts(status: &Status) -> Vec<(&str, usize, bool, bool)> {
+			(status.workspaces.iter())
+				.map(|workspace| {
+					(
+						workspace.name.as_str(),
+						workspace.windows,
+						workspace.urgent,
+						workspace.active,
+					)
+				})
+				.collect()
+		}
+		assert_eq!(
+			counts(publisher.published().unwrap()),
+			[("1", 2, false, true), ("2", 1, true, false)]
+		);
+
+		state.switch_to_workspace(1, &mut pins, |_, _| false).unwrap();
+		publisher.update(Status::of(&state, urgent));
+		let (status, dirty) = publisher.flush().unwrap();
+		assert_eq!(
+			dirty.into_iter().collect::<Vec<_>>(),
+			[Part::ActiveWorkspace, Part::Status]
+		);
+		assert_eq!(counts(status), [("1", 1, false, false), ("2", 2, true, true)]);
+	}
+}
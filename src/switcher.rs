@@ -0,0 +1,346 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Alt-tab style switching between windows in most-recently-used order.
+//!
+//! Windows are remembered in a [focus history] as they are focused. Holding the modifier and
+//! pressing Tab starts a [session] which cycles through the history, focusing each candidate in
+//! turn, until the modifier is released to commit to the focused candidate or Escape is pressed
+//! to go back to the window which was focused before.
+//!
+//! The keyboard is grabbed for the duration of a session, whose [keys] each lead to a [step].
+//!
+//! [focus history]: FocusHistory
+//! [session]: Session
+//! [keys]: Key
+//! [step]: Step
+
+use crate::window::ManagedWindow;
+
+/// Which windows are cycled through by a [session].
+///
+/// [session]: Session
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum Scope {
+	/// Windows on the [active output].
+	///
+	/// Windows which aren't known to be on any output, like floating windows, are always included.
+	///
+	/// [active output]: crate::output::ActiveOutput
+	#[default]
+	ActiveOutput,
+	/// Windows on every output.
+	AllOutputs,
+}
+
+/// Windows in the order they were most recently focused.
+///
+/// Windows which have never been focused come after those which have, in the order they were
+/// added, so the order is stable even before every window has been focused. Those windows are
+/// told apart by [`unfocused`], so that they can be put in a more useful order, like the order in
+/// which they are shown on screen.
+///
+/// [`unfocused`]: Self::unfocused
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FocusHistory<Window> {
+	/// The windows, most recently focused first.
+	windows: Vec<Window>,
+	/// The number of windows at the front of [`windows`] which have been focused.
+	///
+	/// [`windows`]: Self::windows
+	focused: usize,
+}
+
+/// A key pressed or released during a [session].
+///
+/// [session]: Session
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Key {
+	/// Tab was pressed: the next, less recently focused, candidate is selected.
+	Next,
+	/// Shift+Tab was pressed: the previous, more recently focused, candidate is selected.
+	Previous,
+
+	/// Escape was pressed: the session is cancelled.
+	Cancel,
+	/// The modifier which started the session was released: the selected candidate is committed
+	/// to.
+	Release,
+}
+
+/// What the display server should do after a [key] is fed to a [session].
+///
+/// [key]: Key
+/// [session]: Session
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Step<Window> {
+	/// Focuses and raises the given candidate, leaving the session ongoing.
+	Focus(Window),
+
+	/// Ends the session, keeping the given candidate focused.
+	Commit(Window),
+	/// Ends the session, focusing the given window which was focused before it started.
+	Cancel(Option<Window>),
+}
+
+/// An ongoing cycle through the windows in a [focus history].
+///
+/// [focus history]: FocusHistory
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Session<Window> {
+	/// The windows cycled through, most recently focused first.
+	candidates: Vec<Window>,
+	/// The index of the selected candidate, if one has been selected.
+	selected: Option<usize>,
+
+	/// The window which was focused when the session started.
+	original: Option<Window>,
+}
+
+impl<Window> Default for FocusHistory<Window> {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<Window> FocusHistory<Window> {
+	/// Creates an empty focus history.
+	#[inline]
+	pub const fn new() -> Self {
+		Self {
+			windows: Vec::new(),
+			focused: 0,
+		}
+	}
+
+	/// Returns the windows, most recently focused first.
+	#[inline]
+	pub fn windows(&self) -> &[Window] {
+		&self.windows
+	}
+
+	/// Returns the windows which have been focused, most recently focused first.
+	#[inline]
+	pub fn focused(&self) -> &[Window] {
+		&self.windows[..self.focused]
+	}
+
+	/// Returns the windows which have never been focused, in the order they were added.
+	#[inline]
+	pub fn unfocused(&self) -> &[Window] {
+		&self.windows[self.focused..]
+	}
+
+	/// Adds the given `window` after every other window, if it isn't in the history already.
+	pub fn add(&mut self, window: Window)
+	where
+		Window: ManagedWindow,
+	{
+		if !self.windows.contains(&window) {
+			self.windows.push(window);
+		}
+	}
+
+	/// Moves the given `window` to the front of the history, adding it if it isn't there already.
+	pub fn focus(&mut self, window: Window)
+	where
+		Window: ManagedWindow,
+	{
+		self.remove(&window);
+		self.windows.insert(0, window);
+		self.focused += 1;
+	}
+
+	/// Removes the given `window` from the history.
+	pub fn remove(&mut self, window: &Window)
+	where
+		Window: ManagedWindow,
+	{
+		if let Some(index) = self.windows.iter().position(|other| other == window) {
+			self.windows.remove(index);
+
+			if index < self.focused {
+				self.focused -= 1;
+			}
+		}
+	}
+}
+
+impl<Window: ManagedWindow> Session<Window> {
+	/// Starts a session cycling through the given `candidates`, most recently focused first, with
+	/// the `original` window focused.
+	///
+	/// Returns [`None`] if there are no candidates.
+	pub fn start(candidates: Vec<Window>, original: Option<Window>) -> Option<Self> {
+		if candidates.is_empty() {
+			return None;
+		}
+
+		let selected = candidates
+			.iter()
+			.position(|candidate| Some(candidate) == original.as_ref());
+
+		Some(Self {
+			candidates,
+			selected,
+
+			original,
+		})
+	}
+
+	/// Returns the windows cycled through, most recently focused first.
+	#[inline]
+	pub fn candidates(&self) -> &[Window] {
+		&self.candidates
+	}
+
+	/// Returns the selected candidate, if one has been selected.
+	#[inline]
+	pub fn selected(&self) -> Option<&Window> {
+		self.candidates.get(self.selected?)
+	}
+
+	/// Feeds the given `key` to the session, returning what the display server should do.
+	///
+	/// Once a [`Commit`] or [`Cancel`] step has been returned, the session has ended.
+	///
+	/// [`Commit`]: Step::Commit
+	/// [`Cancel`]: Step::Cancel
+	pub fn feed(&mut self, key: Key) -> Step<Window> {
+		let len = self.candidates.len();
+
+		match key {
+			Key::Next | Key::Previous if len == 0 => Step::Cancel(self.original.clone()),
+
+			Key::Next => {
+				let selected = self.selected.map_or(0, |selected| (selected + 1) % len);
+				self.selected = Some(selected);
+
+				Step::Focus(self.candidates[selected].clone())
+			},
+			Key::Previous => {
+				let selected = self.selected.map_or(len - 1, |selected| (selected + len - 1) % len);
+				self.selected = Some(selected);
+
+				Step::Focus(self.candidates[selected].clone())
+			},
+
+			Key::Release => match self.selected() {
+				Some(selected) => Step::Commit(selected.clone()),
+				None => Step::Cancel(self.original.clone()),
+			},
+			Key::Cancel => Step::Cancel(self.original.clone()),
+		}
+	}
+
+	/// Removes the given `window` from the candidates, e.g. because it was destroyed.
+	///
+	/// If the `window` was selected, the candidate after it is selected instead. If the `window`
+	/// was the `original` window, cancelling the session leaves no window focused.
+	pub fn remove(&mut self, window: &Window) {
+		if self.original.as_ref() == Some(window) {
+			self.original = None;
+		}
+
+		let Some(index) = self.candidates.iter().position(|candidate| candidate == window) else {
+			return;
+		};
+		self.candidates.remove(index);
+
+		self.selected = match self.selected {
+			_ if self.candidates.is_empty() => None,
+
+			Some(selected) if selected > index => Some(selected - 1),
+			Some(selected) if selected == index => Some(selected % self.candidates.len()),
+			selected => selected,
+		};
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Tests that windows which have never been focused are told apart from those which have.
+	#[test]
+	fn unfocused() {
+		let mut history = FocusHistory::new();
+		for window in 1..=4 {
+			history.add(window);
+		}
+		history.focus(3);
+		history.focus(1);
+		assert_eq!(history.focused(), [1, 3]);
+		assert_eq!(history.unfocused(), [2, 4]);
+
+		history.focus(3);
+		assert_eq!(history.focused(), [3, 1]);
+
+		history.remove(&1);
+		history.remove(&4);
+		assert_eq!(history.focused(), [3]);
+		assert_eq!(history.unfocused(), [2]);
+	}
+
+	/// Tests that a single press switches to the previously focused window, and repeated presses
+	/// walk further back through the history.
+	#[test]
+	fn cycling() {
+		let mut history = FocusHistory::new();
+		for window in 1..=4 {
+			history.add(window);
+		}
+		history.focus(3);
+		history.focus(1);
+		assert_eq!(history.windows(), [1, 3, 2, 4]);
+
+		let mut session = Session::start(history.windows().to_vec(), Some(1)).unwrap();
+		assert_eq!(session.feed(Key::Next), Step::Focus(3));
+		assert_eq!(session.feed(Key::Release), Step::Commit(3));
+
+		let mut session = Session::start(history.windows().to_vec(), Some(1)).unwrap();
+		assert_eq!(session.feed(Key::Next), Step::Focus(3));
+		assert_eq!(session.feed(Key::Next), Step::Focus(2));
+		assert_eq!(session.feed(Key::Previous), Step::Focus(3));
+		assert_eq!(session.feed(Key::Next), Step::Focus(2));
+		assert_eq!(session.feed(Key::Next), Step::Focus(4));
+		// Cycling wraps around.
+		assert_eq!(session.feed(Key::Next), Step::Focus(1));
+		assert_eq!(session.feed(Key::Previous), Step::Focus(4));
+		assert_eq!(session.feed(Key::Cancel), Step::Cancel(Some(1)));
+
+		// With nothing focused, the most recently focused window is selected first.
+		let mut session = Session::start(history.windows().to_vec(), None).unwrap();
+		assert_eq!(session.feed(Key::Next), Step::Focus(1));
+		let mut session = Session::start(history.windows().to_vec(), None).unwrap();
+		assert_eq!(session.feed(Key::Previous), Step::Focus(4));
+		assert_eq!(session.feed(Key::Previous), Step::Focus(2));
+
+		assert_eq!(Session::<u32>::start(Vec::new(), None), None);
+	}
+
+	/// Tests that releasing the modifier before any candidate is selected cancels the session, and
+	/// that removed windows are skipped.
+	#[test]
+	fn removed_candidates() {
+		let mut session = Session::start(vec![1, 2, 3], None).unwrap();
+		assert_eq!(session.feed(Key::Release), Step::Cancel(None));
+
+		let mut session = Session::start(vec![1, 2, 3], Some(1)).unwrap();
+
+		assert_eq!(session.feed(Key::Next), Step::Focus(2));
+		session.remove(&2);
+		assert_eq!(session.selected(), Some(&3));
+		assert_eq!(session.feed(Key::Next), Step::Focus(1));
+
+		// The original window was destroyed, so there is nothing to go back to.
+		session.remove(&1);
+		assert_eq!(session.candidates(), [3]);
+		assert_eq!(session.feed(Key::Cancel), Step::Cancel(None));
+
+		session.remove(&3);
+		assert_eq!(session.feed(Key::Next), Step::Cancel(None));
+	}
+}
@@ -0,0 +1,447 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The colors of everything AquariWM draws itself, like window borders and overlays.
+//!
+//! A [theme] gives a color to each of a fixed set of named [slots]. Themes start from a built-in
+//! [preset], like the high-contrast preset, and any slot can be overridden in the [configuration
+//! file]:
+//!
+//! ```toml
+//! [theme]
+//! preset = "high-contrast"
+//! focused-border = "#ffcc00"
+//! overlay-fill = "#1d547060"
+//! ```
+//!
+//! Colors are only parsed here: the display server resolves each theme into the pixels of the
+//! visuals it draws with once, and again whenever the theme changes, so that drawing never parses
+//! or converts colors itself.
+//!
+//! [theme]: Theme
+//! [slots]: Slot
+//! [preset]: Preset
+//! [configuration file]: crate::config
+
+use std::{
+	fmt::{self, Display, Formatter},
+	str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::{config::suggestion, wallpaper::Color};
+
+/// The named colors which can be given instead of hex colors, with their 24-bit RGB values.
+const NAMED: &[(&str, u32)] = &[
+	("black", 0x00_00_00),
+	("white", 0xff_ff_ff),
+	("gray", 0x80_80_80),
+	("grey", 0x80_80_80),
+	("red", 0xff_00_00),
+	("green", 0x00_80_00),
+	("lime", 0x00_ff_00),
+	("blue", 0x00_00_ff),
+	("yellow", 0xff_ff_00),
+	("cyan", 0x00_ff_ff),
+	("magenta", 0xff_00_ff),
+	("orange", 0xff_a5_00),
+	("purple", 0x80_00_80),
+];
+
+/// A color with an alpha channel, written as `#rgb`, `#rgba`, `#rrggbb`, or `#rrggbbaa`, or by
+/// name, like `yellow`.
+///
+/// The alpha channel is only honored where it can be: drawn with a visual with an alpha channel,
+/// or as the opacity of an overlay for compositors to honor. Everywhere else, colors are opaque.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Rgba(u32);
+
+impl Rgba {
+	/// Creates a color from its 24-bit `rgb` value, ignoring any bits above the lowest 24, and its
+	/// `alpha`, where `0` is fully transparent and `255` is fully opaque.
+	#[inline]
+	pub const fn new(rgb: u32, alpha: u8) -> Self {
+		Self(((rgb & 0xff_ff_ff) << 8) | alpha as u32)
+	}
+
+	/// Creates an opaque color from its 24-bit `rgb` value.
+	#[inline]
+	pub const fn opaque(rgb: u32) -> Self {
+		Self::new(rgb, u8::MAX)
+	}
+
+	/// Returns the color as a 24-bit RGB value, without its alpha channel.
+	#[inline(always)]
+	pub const fn rgb(&self) -> u32 {
+		self.0 >> 8
+	}
+
+	/// Returns the color's alpha channel, where `0` is fully transparent and `255` is fully opaque.
+	#[inline(always)]
+	pub const fn alpha(&self) -> u8 {
+		self.0 as u8
+	}
+}
+
+impl From<Color> for Rgba {
+	#[inline]
+	fn from(color: Color) -> Self {
+		Self::opaque(color.rgb())
+	}
+}
+
+impl Display for Rgba {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self.alpha() {
+			u8::MAX => write!(f, "#{:06x}", self.rgb()),
+			alpha => write!(f, "#{:06x}{alpha:02x}", self.rgb()),
+		}
+	}
+}
+
+/// An error returned when parsing an [`Rgba`] color fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum ParseColorError {
+	#[error("expected a color like `#1d1f21`, `#1d1f2180`, `#222`, or `yellow`; found `{0}`")]
+	Format(String),
+	#[error("unknown color `{0}`{}", suggestion(.0, NAMED.iter().map(|(name, _)| *name)))]
+	UnknownName(String),
+}
+
+impl FromStr for Rgba {
+	type Err = ParseColorError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		let Some(hex) = s.strip_prefix('#') else {
+			return NAMED
+				.iter()
+				.find(|(name, _)| name.eq_ignore_ascii_case(s))
+				.map(|&(_, rgb)| Self::opaque(rgb))
+				.ok_or_else(|| ParseColorError::UnknownName(s.to_owned()));
+		};
+
+		let format = || ParseColorError::Format(s.to_owned());
+		if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+			return Err(format());
+		}
+		let value = u32::from_str_radix(hex, 16).map_err(|_| format())?;
+		// Each digit of the short forms is doubled, so `#f80` is `#ff8800`.
+		let expand = |short: u32, digits: u32| {
+			(0..digits)
+				.rev()
+				.fold(0, |value, digit| (value << 8) | (((short >> (digit * 4)) & 0xf) * 0x11))
+		};
+
+		match hex.len() {
+			3 => Ok(Self::opaque(expand(value, 3))),
+			4 => Ok(Self(expand(value, 4))),
+			6 => Ok(Self::opaque(value)),
+			8 => Ok(Self(value)),
+
+			_ => Err(format()),
+		}
+	}
+}
+
+/// A named color of a [theme], each used for one part of what AquariWM draws.
+///
+/// [theme]: Theme
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum Slot {
+	/// The border of the focused window (`focused-border`).
+	FocusedBorder,
+	/// The border of unfocused windows (`unfocused-border`).
+	UnfocusedBorder,
+	/// The border of windows which demand attention (`urgent-border`).
+	UrgentBorder,
+	/// The border of the windows of unresponsive clients (`hung-border`).
+	HungBorder,
+
+	/// The fill of overlays, like the insertion preview and the drop zone of a dragged window
+	/// (`overlay-fill`). Its alpha channel is the overlays' opacity.
+	OverlayFill,
+	/// The outline of overlays (`overlay-outline`).
+	OverlayOutline,
+
+	/// The background of a stacked group's active tab (`tab-active-background`).
+	TabActiveBackground,
+	/// The text of a stacked group's active tab (`tab-active-text`).
+	TabActiveText,
+	/// The background of a stacked group's other tabs (`tab-inactive-background`).
+	TabInactiveBackground,
+	/// The text of a stacked group's other tabs (`tab-inactive-text`).
+	TabInactiveText,
+
+	/// The background of the strip of iconified windows (`strip-background`).
+	StripBackground,
+	/// The text of the strip of iconified windows (`strip-text`).
+	StripText,
+}
+
+impl Slot {
+	/// The number of slots.
+	pub const COUNT: usize = Self::ALL.len();
+
+	/// Every slot, in order.
+	pub const ALL: [Self; 12] = [
+		Self::FocusedBorder,
+		Self::UnfocusedBorder,
+		Self::UrgentBorder,
+		Self::HungBorder,
+		Self::OverlayFill,
+		Self::OverlayOutline,
+		Self::TabActiveBackground,
+		Self::TabActiveText,
+		Self::TabInactiveBackground,
+		Self::TabInactiveText,
+		Self::StripBackground,
+		Self::StripText,
+	];
+
+	/// Returns the slot's name in the configuration file.
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::FocusedBorder => "focused-border",
+			Self::UnfocusedBorder => "unfocused-border",
+			Self::UrgentBorder => "urgent-border",
+			Self::HungBorder => "hung-border",
+
+			Self::OverlayFill => "overlay-fill",
+			Self::OverlayOutline => "overlay-outline",
+
+			Self::TabActiveBackground => "tab-active-background",
+			Self::TabActiveText => "tab-active-text",
+			Self::TabInactiveBackground => "tab-inactive-background",
+			Self::TabInactiveText => "tab-inactive-text",
+
+			Self::StripBackground => "strip-background",
+			Self::StripText => "strip-text",
+		}
+	}
+
+	/// Returns the slot's position in [`ALL`](Self::ALL).
+	#[inline(always)]
+	pub const fn index(&self) -> usize {
+		*self as usize
+	}
+}
+
+impl Display for Slot {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+/// An error returned when parsing a [`Slot`] fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error("unknown theme color `{0}`{}", suggestion(.0, Slot::ALL.iter().map(Slot::name)))]
+pub struct ParseSlotError(String);
+
+impl FromStr for Slot {
+	type Err = ParseSlotError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::ALL
+			.into_iter()
+			.find(|slot| slot.name() == s)
+			.ok_or_else(|| ParseSlotError(s.to_owned()))
+	}
+}
+
+/// The color of each [slot].
+///
+/// [slot]: Slot
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Theme {
+	colors: [Rgba; Slot::COUNT],
+}
+
+impl Default for Theme {
+	#[inline]
+	fn default() -> Self {
+		Preset::Default.theme()
+	}
+}
+
+impl Theme {
+	/// Returns the color of the given `slot`.
+	#[inline(always)]
+	pub const fn get(&self, slot: Slot) -> Rgba {
+		self.colors[slot.index()]
+	}
+
+	/// Sets the color of the given `slot`.
+	#[inline(always)]
+	pub const fn set(&mut self, slot: Slot, color: Rgba) {
+		self.colors[slot.index()] = color;
+	}
+
+	/// Returns an iterator over each slot with its color, in order.
+	pub fn iter(&self) -> impl Iterator<Item = (Slot, Rgba)> + '_ {
+		Slot::ALL.into_iter().zip(self.colors)
+	}
+}
+
+/// A built-in [theme], selected by name.
+///
+/// [theme]: Theme
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum Preset {
+	/// Muted grays with a blue accent (`default`).
+	#[default]
+	Default,
+	/// Pure black, white and yellow, for the strongest contrast (`high-contrast`).
+	HighContrast,
+}
+
+impl Preset {
+	/// The presets' names.
+	const NAMES: &'static [&'static str] = &["default", "high-contrast"];
+
+	/// Returns the preset's theme.
+	pub const fn theme(&self) -> Theme {
+		let colors = match self {
+			Self::Default => [
+				Rgba::opaque(0x3d_ae_e9),
+				Rgba::opaque(0x4d_4d_4d),
+				Rgba::opaque(0xf6_74_00),
+				Rgba::opaque(0xcc_24_1d),
+				Rgba::new(0x1d_54_70, 0x60),
+				Rgba::opaque(0x3d_ae_e9),
+				Rgba::opaque(0x3d_ae_e9),
+				Rgba::opaque(0xff_ff_ff),
+				Rgba::opaque(0x31_36_3b),
+				Rgba::opaque(0xbd_c3_c7),
+				Rgba::opaque(0x23_26_29),
+				Rgba::opaque(0xef_f0_f1),
+			],
+			Self::HighContrast => [
+				Rgba::opaque(0xff_ff_00),
+				Rgba::opaque(0xff_ff_ff),
+				Rgba::opaque(0xff_00_ff),
+				Rgba::opaque(0xff_00_00),
+				Rgba::new(0x00_00_00, 0xb0),
+				Rgba::opaque(0xff_ff_00),
+				Rgba::opaque(0xff_ff_00),
+				Rgba::opaque(0x00_00_00),
+				Rgba::opaque(0x00_00_00),
+				Rgba::opaque(0xff_ff_ff),
+				Rgba::opaque(0x00_00_00),
+				Rgba::opaque(0xff_ff_ff),
+			],
+		};
+
+		Theme { colors }
+	}
+}
+
+/// An error returned when parsing a [`Preset`] fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error("unknown theme `{0}`{}", suggestion(.0, Preset::NAMES.iter().copied()))]
+pub struct ParsePresetError(String);
+
+impl FromStr for Preset {
+	type Err = ParsePresetError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim() {
+			"default" => Ok(Self::Default),
+			"high-contrast" => Ok(Self::HighContrast),
+
+			other => Err(ParsePresetError(other.to_owned())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_colors() {
+		let table = [
+			("#1d1f21", Rgba::opaque(0x1d_1f_21)),
+			("#1d1f2180", Rgba::new(0x1d_1f_21, 0x80)),
+			("#F80", Rgba::opaque(0xff_88_00)),
+			("#f808", Rgba::new(0xff_88_00, 0x88)),
+			(" #000000ff ", Rgba::opaque(0)),
+			("Yellow", Rgba::opaque(0xff_ff_00)),
+			("grey", Rgba::opaque(0x80_80_80)),
+		];
+		for (s, expected) in table {
+			assert_eq!(s.parse(), Ok(expected), "{s:?}");
+		}
+
+		for s in ["#12", "#12345", "#1234567", "#123456789", "#ggg", "#-12", "#", ""] {
+			assert!(
+				matches!(
+					s.parse::<Rgba>(),
+					Err(ParseColorError::Format(_) | ParseColorError::UnknownName(_))
+				),
+				"{s:?}"
+			);
+		}
+		assert_eq!(
+			"yelow".parse::<Rgba>().unwrap_err().to_string(),
+			"unknown color `yelow`; did you mean `yellow`?"
+		);
+		assert_eq!("#12".parse::<Rgba>(), Err(ParseColorError::Format("#12".to_owned())));
+	}
+
+	#[test]
+	fn display() {
+		assert_eq!(Rgba::opaque(0x00_00_ff).to_string(), "#0000ff");
+		assert_eq!(Rgba::new(0x00_00_ff, 0x08).to_string(), "#0000ff08");
+		assert_eq!(Rgba::from(Color::new(0x28_28_28)), Rgba::opaque(0x28_28_28));
+
+		for color in [Rgba::opaque(0x12_34_56), Rgba::new(0xab_cd_ef, 0x01)] {
+			assert_eq!(color.to_string().parse(), Ok(color));
+		}
+	}
+
+	#[test]
+	fn slots() {
+		for (index, slot) in Slot::ALL.into_iter().enumerate() {
+			assert_eq!(slot.index(), index);
+			assert_eq!(slot.name().parse(), Ok(slot));
+		}
+		assert_eq!(
+			"focused-bordr".parse::<Slot>().unwrap_err().to_string(),
+			"unknown theme color `focused-bordr`; did you mean `focused-border`?"
+		);
+
+		let mut theme = Theme::default();
+		theme.set(Slot::StripText, Rgba::opaque(0x12_34_56));
+		assert_eq!(theme.get(Slot::StripText), Rgba::opaque(0x12_34_56));
+		assert_eq!(theme.iter().count(), Slot::COUNT);
+	}
+
+	#[test]
+	fn presets() {
+		assert_eq!("high-contrast".parse(), Ok(Preset::HighContrast));
+		assert_eq!(
+			"high-contrst".parse::<Preset>().unwrap_err().to_string(),
+			"unknown theme `high-contrst`; did you mean `high-contrast`?"
+		);
+
+		// Every border is opaque, and distinct from the others.
+		for preset in [Preset::Default, Preset::HighContrast] {
+			let theme = preset.theme();
+			let borders = [
+				Slot::FocusedBorder,
+				Slot::UnfocusedBorder,
+				Slot::UrgentBorder,
+				Slot::HungBorder,
+			]
+			.map(|slot| theme.get(slot));
+
+			for (i, border) in borders.iter().enumerate() {
+				assert_eq!(border.alpha(), u8::MAX, "{preset:?}");
+				assert!(!borders[..i].contains(border), "{preset:?}");
+			}
+		}
+	}
+}
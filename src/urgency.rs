@@ -0,0 +1,178 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Whether windows which demand attention (i.e. which are *urgent*) may steal focus.
+//!
+//! This is decided by [`decide`] when a window becomes urgent, from when the user last gave input.
+
+use std::{
+	fmt,
+	fmt::{Display, Formatter},
+	num::ParseIntError,
+	str::FromStr,
+	time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+/// When an urgent window may steal focus from the focused window.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum FocusStealing {
+	/// Urgent windows never steal focus.
+	#[default]
+	Never,
+	/// Urgent windows always steal focus.
+	Always,
+	/// Urgent [dialogs] steal focus if the user has given no input for at least the given
+	/// duration, so that what they are typing doesn't end up in the dialog instead.
+	///
+	/// [dialogs]: WindowKind::Dialog
+	IfIdleFor(Duration),
+}
+
+/// The kind of window which has become urgent.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum WindowKind {
+	/// A short-lived window belonging to another window, like an authentication dialog or a file
+	/// picker.
+	Dialog,
+	/// Any other window.
+	///
+	/// Normal windows, like a chat client with a new message, tend to demand attention without
+	/// needing it right away, so they only steal focus if they [always] may.
+	///
+	/// [always]: FocusStealing::Always
+	Normal,
+}
+
+/// Whether an urgent window steals focus, as [decided] by a [focus stealing policy].
+///
+/// [decided]: decide
+/// [focus stealing policy]: FocusStealing
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Decision {
+	/// The window steals focus.
+	Steal,
+	/// The window doesn't steal focus, and stays urgent.
+	Suppress(Suppressed),
+}
+
+/// Why an urgent window was not allowed to steal focus.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Suppressed {
+	/// Focus stealing is [disabled].
+	///
+	/// [disabled]: FocusStealing::Never
+	Disabled,
+	/// The window is not a [dialog].
+	///
+	/// [dialog]: WindowKind::Dialog
+	NotDialog,
+	/// The user gave input too recently: only the given duration before the window became urgent.
+	Busy(Duration),
+}
+
+/// Decides whether a window of the given `kind` which became urgent at the time `now` steals
+/// focus, according to the given `policy`.
+///
+/// `last_input` is when the user last gave input, if they have since AquariWM started.
+pub fn decide(policy: FocusStealing, last_input: Option<Instant>, now: Instant, kind: WindowKind) -> Decision {
+	match (policy, kind) {
+		(FocusStealing::Never, _) => Decision::Suppress(Suppressed::Disabled),
+		(FocusStealing::Always, _) => Decision::Steal,
+
+		(FocusStealing::IfIdleFor(_), WindowKind::Normal) => Decision::Suppress(Suppressed::NotDialog),
+		(FocusStealing::IfIdleFor(threshold), WindowKind::Dialog) => {
+			let idle = last_input.map(|last_input| now.saturating_duration_since(last_input));
+
+			match idle {
+				Some(idle) if idle < threshold => Decision::Suppress(Suppressed::Busy(idle)),
+				_ => Decision::Steal,
+			}
+		},
+	}
+}
+
+impl Display for Suppressed {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Disabled => write!(f, "focus stealing is disabled"),
+			Self::NotDialog => write!(f, "it is not a dialog"),
+			Self::Busy(idle) => write!(f, "the user gave input only {}ms earlier", idle.as_millis()),
+		}
+	}
+}
+
+/// An error returned when parsing a [`FocusStealing`] policy fails.
+#[derive(Debug, Error)]
+pub enum ParseFocusStealingError {
+	/// The policy was neither `never`, `always`, nor a number of milliseconds.
+	#[error("expected `never`, `always`, or a number of milliseconds: {0}")]
+	Millis(#[from] ParseIntError),
+}
+
+impl FromStr for FocusStealing {
+	type Err = ParseFocusStealingError;
+
+	/// Parses `never`, `always`, or a number of milliseconds for [`IfIdleFor`].
+	///
+	/// [`IfIdleFor`]: FocusStealing::IfIdleFor
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim() {
+			"never" => Ok(Self::Never),
+			"always" => Ok(Self::Always),
+
+			millis => Ok(Self::IfIdleFor(Duration::from_millis(millis.parse()?))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn policies() {
+		let now = Instant::now();
+		let policy = FocusStealing::IfIdleFor(Duration::from_millis(500));
+
+		// Dialogs steal focus once the user has been idle for long enough.
+		let idle = |millis| Some(now - Duration::from_millis(millis));
+		assert_eq!(decide(policy, idle(1000), now, WindowKind::Dialog), Decision::Steal);
+		assert_eq!(decide(policy, idle(500), now, WindowKind::Dialog), Decision::Steal);
+		assert_eq!(
+			decide(policy, idle(200), now, WindowKind::Dialog),
+			Decision::Suppress(Suppressed::Busy(Duration::from_millis(200)))
+		);
+		assert_eq!(decide(policy, None, now, WindowKind::Dialog), Decision::Steal);
+
+		// Normal windows only steal focus if they always may.
+		assert_eq!(
+			decide(policy, idle(1000), now, WindowKind::Normal),
+			Decision::Suppress(Suppressed::NotDialog)
+		);
+		assert_eq!(
+			decide(FocusStealing::Always, idle(0), now, WindowKind::Normal),
+			Decision::Steal
+		);
+
+		assert_eq!(
+			decide(FocusStealing::Never, None, now, WindowKind::Dialog),
+			Decision::Suppress(Suppressed::Disabled)
+		);
+	}
+
+	#[test]
+	fn parse() {
+		assert_eq!("never".parse::<FocusStealing>().unwrap(), FocusStealing::Never);
+		assert_eq!(" always".parse::<FocusStealing>().unwrap(), FocusStealing::Always);
+		assert_eq!(
+			"750".parse::<FocusStealing>().unwrap(),
+			FocusStealing::IfIdleFor(Duration::from_millis(750))
+		);
+
+		assert!("sometimes".parse::<FocusStealing>().is_err());
+		assert!("-5".parse::<FocusStealing>().is_err());
+	}
+}
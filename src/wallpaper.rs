@@ -0,0 +1,468 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The background shown wherever no window covers the screen.
+//!
+//! Unless something draws the background, whatever windows left behind stays there after they
+//! move, which makes every re-tile look broken without a compositor. AquariWM fills the background
+//! with a solid [color], and optionally with an [image] scaled to cover each output.
+//!
+//! The image is [composed] into a single picture the size of the whole screen: each output shows
+//! the image scaled to cover it, cropped evenly on the sides which don't fit, and any part of the
+//! screen which isn't on an output, like the gaps between outputs of different resolutions, is
+//! filled with the color.
+//!
+//! [color]: Wallpaper::color
+//! [image]: Wallpaper::image
+//! [composed]: compose
+
+use std::{
+	fmt::{self, Display, Formatter},
+	io,
+	num::ParseIntError,
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::output::Output;
+
+/// What the background shows.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Wallpaper {
+	/// The color with which the background is filled wherever the [image] doesn't cover it.
+	///
+	/// [image]: Self::image
+	pub color: Color,
+	/// The path of a PNG image scaled to cover each output, if any.
+	pub image: Option<PathBuf>,
+}
+
+/// A 24-bit RGB color, written as `#rrggbb` or `#rgb`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Color(u32);
+
+impl Default for Color {
+	#[inline]
+	fn default() -> Self {
+		Self::DEFAULT
+	}
+}
+
+impl Color {
+	/// The default background color, a dark gray.
+	pub const DEFAULT: Self = Self(0x1d_1f_21);
+
+	/// Creates a color from its `rgb` value, ignoring any bits above the lowest 24.
+	#[inline]
+	pub const fn new(rgb: u32) -> Self {
+		Self(rgb & 0xff_ff_ff)
+	}
+
+	/// Returns the color as a 24-bit RGB value, e.g. `0xff8000` for orange.
+	#[inline(always)]
+	pub const fn rgb(&self) -> u32 {
+		self.0
+	}
+}
+
+impl Display for Color {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "#{:06x}", self.0)
+	}
+}
+
+/// An error returned when parsing a [`Color`] fails.
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum ParseColorError {
+	#[error("expected a color like `#1d1f21` or `#222`")]
+	Format,
+	#[error(transparent)]
+	Int(#[from] ParseIntError),
+}
+
+impl FromStr for Color {
+	type Err = ParseColorError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let hex = s.strip_prefix('#').ok_or(ParseColorError::Format)?;
+		if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+			return Err(ParseColorError::Format);
+		}
+
+		match hex.len() {
+			6 => Ok(Self(u32::from_str_radix(hex, 16)?)),
+			// Each digit is doubled, so `#f80` is `#ff8800`.
+			3 => {
+				let short = u32::from_str_radix(hex, 16)?;
+				let channel = |shift: u32| ((short >> shift) & 0xf) * 0x11;
+
+				Ok(Self((channel(8) << 16) | (channel(4) << 8) | channel(0)))
+			},
+
+			_ => Err(ParseColorError::Format),
+		}
+	}
+}
+
+/// A decoded image, as 24-bit RGB pixels.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Image {
+	width: u32,
+	height: u32,
+	/// The pixels of each row in turn, from the top left.
+	pixels: Vec<u32>,
+}
+
+/// An error returned when [loading] an [`Image`] fails.
+///
+/// [loading]: Image::load
+#[derive(Debug, Error)]
+pub enum LoadError {
+	#[error("failed to read {}: {source}", path.display())]
+	Read { path: PathBuf, source: io::Error },
+	#[cfg(feature = "image")]
+	#[error("failed to decode {}: {source}", path.display())]
+	Decode { path: PathBuf, source: png::DecodingError },
+	#[error("{} has no pixels", path.display())]
+	Empty { path: PathBuf },
+
+	/// AquariWM was built without the `image` feature, so images can't be decoded.
+	#[error("can't load {}: AquariWM was built without the `image` feature", path.display())]
+	Unsupported { path: PathBuf },
+}
+
+impl Image {
+	/// Creates an image of the given size from its `pixels`, given row by row from the top left.
+	///
+	/// Returns [`None`] if the image has no pixels or the number of `pixels` doesn't match its
+	/// size.
+	pub fn new(width: u32, height: u32, pixels: Vec<u32>) -> Option<Self> {
+		let size = (width as usize).checked_mul(height as usize)?;
+
+		(size != 0 && pixels.len() == size).then_some(Self { width, height, pixels })
+	}
+
+	#[inline(always)]
+	pub const fn width(&self) -> u32 {
+		self.width
+	}
+
+	#[inline(always)]
+	pub const fn height(&self) -> u32 {
+		self.height
+	}
+
+	/// Returns the pixel at the given position.
+	#[inline]
+	fn pixel(&self, x: u32, y: u32) -> u32 {
+		self.pixels[(y as usize) * (self.width as usize) + (x as usize)]
+	}
+
+	/// Decodes the PNG image at the given `path`.
+	///
+	/// Any transparency is dropped, as there is nothing behind the background to show through it.
+	#[cfg(feature = "image")]
+	pub fn load(path: &Path) -> Result<Self, LoadError> {
+		use std::fs::File;
+
+		let file = File::open(path).map_err(|source| LoadError::Read {
+			path: path.to_owned(),
+			source,
+		})?;
+		let decode_error = |source| LoadError::Decode {
+			path: path.to_owned(),
+			source,
+		};
+
+		let mut decoder = png::Decoder::new(file);
+		// Palettes are expanded and 16-bit channels stripped to 8 bits, leaving only the color types
+		// matched below.
+		decoder.set_transformations(png::Transformations::normalize_to_color8());
+		let mut reader = decoder.read_info().map_err(decode_error)?;
+
+		let mut buffer = vec![0; reader.output_buffer_size()];
+		let info = reader.next_frame(&mut buffer).map_err(decode_error)?;
+		let bytes = &buffer[..info.buffer_size()];
+
+		let rgb = |r: u8, g: u8, b: u8| (u32::from(r) << 16) | (u32::from(g) << 8) | u32::from(b);
+		let pixels = match info.color_type {
+			png::ColorType::Rgb => bytes.chunks_exact(3).map(|p| rgb(p[0], p[1], p[2])).collect(),
+			png::ColorType::Rgba => bytes.chunks_exact(4).map(|p| rgb(p[0], p[1], p[2])).collect(),
+			png::ColorType::Grayscale => bytes.iter().map(|&v| rgb(v, v, v)).collect(),
+			png::ColorType::GrayscaleAlpha => bytes.chunks_exact(2).map(|p| rgb(p[0], p[0], p[0])).collect(),
+
+			png::ColorType::Indexed => unreachable!("palettes are expanded"),
+		};
+
+		Self::new(info.width, info.height, pixels).ok_or_else(|| LoadError::Empty { path: path.to_owned() })
+	}
+
+	/// Fails to load the image at the given `path`, as AquariWM was built without the `image`
+	/// feature.
+	#[cfg(not(feature = "image"))]
+	pub fn load(path: &Path) -> Result<Self, LoadError> {
+		Err(LoadError::Unsupported { path: path.to_owned() })
+	}
+}
+
+/// Composes the background of a screen of the given size with the given `outputs`: the `image`, if
+/// any, scaled to cover each output, with the rest of the screen filled with the `color`.
+///
+/// Returns the pixels of each row in turn, from the top left.
+pub fn compose(width: u32, height: u32, outputs: &[Output], image: Option<&Image>, color: Color) -> Vec<u32> {
+	let mut pixels = vec![color.rgb(); (width as usize) * (height as usize)];
+	let Some(image) = image else {
+		return pixels;
+	};
+
+	for output in outputs {
+		if output.width == 0 || output.height == 0 {
+			continue;
+		}
+		let crop = Crop::cover(image, output.width, output.height);
+
+		// Only the part of the output which is on the screen is drawn.
+		let (left, top) = (output.x.max(0) as i64, output.y.max(0) as i64);
+		let right = (output.x as i64 + output.width as i64).min(width as i64);
+		let bottom = (output.y as i64 + output.height as i64).min(height as i64);
+
+		for y in top..bottom {
+			let source_y = crop.source_y((y - output.y as i64) as u32, output.height);
+			let row = (y as usize) * (width as usize);
+
+			for x in left..right {
+				let source_x = crop.source_x((x - output.x as i64) as u32, output.width);
+
+				pixels[row + x as usize] = image.pixel(source_x, source_y);
+			}
+		}
+	}
+
+	pixels
+}
+
+/// The part of an [image] shown on an output, which is scaled to cover it.
+///
+/// [image]: Image
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Crop {
+	x: u32,
+	y: u32,
+	width: u32,
+	height: u32,
+}
+
+impl Crop {
+	/// Returns the largest part of the `image` with the same aspect ratio as an output of the given
+	/// size, centered in the image.
+	fn cover(image: &Image, width: u32, height: u32) -> Self {
+		let (image_width, image_height) = (image.width as u64, image.height as u64);
+		let (width, height) = (width as u64, height as u64);
+
+		// The image is wider than the output: its sides are cropped.
+		if image_width * height > image_height * width {
+			let cropped = (image_height * width / height).max(1);
+
+			Self {
+				x: ((image_width - cropped) / 2) as u32,
+				y: 0,
+				width: cropped as u32,
+				height: image.height,
+			}
+		// The image is taller than the output: its top and bottom are cropped.
+		} else {
+			let cropped = (image_width * height / width).max(1);
+
+			Self {
+				x: 0,
+				y: ((image_height - cropped) / 2) as u32,
+				width: image.width,
+				height: cropped as u32,
+			}
+		}
+	}
+
+	/// Returns the column of the image shown at the given column of an output of the given `width`.
+	#[inline]
+	const fn source_x(&self, x: u32, width: u32) -> u32 {
+		self.x + ((x as u64) * (self.width as u64) / (width as u64)) as u32
+	}
+
+	/// Returns the row of the image shown at the given row of an output of the given `height`.
+	#[inline]
+	const fn source_y(&self, y: u32, height: u32) -> u32 {
+		self.y + ((y as u64) * (self.height as u64) / (height as u64)) as u32
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::output::Scale;
+
+	fn output(x: i32, y: i32, width: u32, height: u32) -> Output {
+		Output {
+			name: format!("{width}x{height}+{x}+{y}"),
+			x,
+			y,
+			width,
+			height,
+			primary: false,
+			scale: Scale::IDENTITY,
+
+			clones: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn colors() {
+		assert_eq!("#1d1f21".parse(), Ok(Color::new(0x1d1f21)));
+		assert_eq!("#F80".parse(), Ok(Color::new(0xff8800)));
+		assert_eq!(Color::new(0x0000ff).to_string(), "#0000ff");
+		assert_eq!(Color::new(0xff_00_ff_00), Color::new(0x00ff00));
+
+		for invalid in ["1d1f21", "#1d1f2", "#1d1f21ff", "#+1d1f2", "#ggg", ""] {
+			assert_eq!(invalid.parse::<Color>(), Err(ParseColorError::Format), "{invalid:?}");
+		}
+	}
+
+	#[test]
+	fn images() {
+		assert!(Image::new(2, 2, vec![0; 4]).is_some());
+		assert!(Image::new(2, 2, vec![0; 3]).is_none());
+		assert!(Image::new(0, 2, Vec::new()).is_none());
+	}
+
+	/// Tests that PNG images are decoded, whatever their color type, with transparency dropped.
+	#[cfg(feature = "image")]
+	#[test]
+	fn load() {
+		use std::{env, fs, process};
+
+		let dir = env::temp_dir().join(format!("aquariwm-wallpaper-test-{}", process::id()));
+		fs::create_dir_all(&dir).unwrap();
+
+		let write = |name: &str, color_type, depth, data: &[u8]| {
+			let path = dir.join(name);
+			let mut encoder = png::Encoder::new(fs::File::create(&path).unwrap(), 2, 1);
+			encoder.set_color(color_type);
+			encoder.set_depth(depth);
+			encoder.write_header().unwrap().write_image_data(data).unwrap();
+
+			path
+		};
+
+		let rgba = write(
+			"rgba.png",
+			png::ColorType::Rgba,
+			png::BitDepth::Eight,
+			&[0x1d, 0x1f, 0x21, 0xff, 0xff, 0x88, 0x00, 0x00],
+		);
+		let gray = write(
+			"gray.png",
+			png::ColorType::Grayscale,
+			png::BitDepth::Eight,
+			&[0x00, 0x80],
+		);
+		// 16-bit channels are stripped to their high bytes.
+		let deep = write(
+			"deep.png",
+			png::ColorType::Rgb,
+			png::BitDepth::Sixteen,
+			&[0x12, 0xff, 0x34, 0xff, 0x56, 0xff, 0xab, 0x00, 0xcd, 0x00, 0xef, 0x00],
+		);
+
+		for (path, pixels) in [
+			(&rgba, [0x1d1f21, 0xff8800]),
+			(&gray, [0x000000, 0x808080]),
+			(&deep, [0x123456, 0xabcdef]),
+		] {
+			let image = Image::load(path).unwrap();
+
+			assert_eq!((image.width(), image.height()), (2, 1), "{}", path.display());
+			assert_eq!(image.pixels, pixels, "{}", path.display());
+		}
+
+		let garbage = dir.join("garbage.png");
+		fs::write(&garbage, "not a PNG").unwrap();
+		assert!(matches!(Image::load(&garbage), Err(LoadError::Decode { .. })));
+		assert!(matches!(
+			Image::load(&dir.join("missing.png")),
+			Err(LoadError::Read { .. })
+		));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	/// Tests that images are cropped evenly to the aspect ratio of each output.
+	#[test]
+	fn crops() {
+		let image = Image::new(40, 20, vec![0; 800]).unwrap();
+
+		let table = [
+			// The same aspect ratio, at any size: nothing is cropped.
+			((80, 40), (0, 0, 40, 20)),
+			((20, 10), (0, 0, 40, 20)),
+			// A square output: the sides are cropped.
+			((100, 100), (10, 0, 20, 20)),
+			// A wider output: the top and bottom are cropped.
+			((80, 20), (0, 5, 40, 10)),
+			// An output too wide for a single row still shows one.
+			((1000, 1), (0, 9, 40, 1)),
+		];
+
+		for ((width, height), (x, y, crop_width, crop_height)) in table {
+			let expected = Crop {
+				x,
+				y,
+				width: crop_width,
+				height: crop_height,
+			};
+
+			assert_eq!(Crop::cover(&image, width, height), expected, "{width}x{height}");
+		}
+	}
+
+	/// Tests that a screen with outputs of different resolutions shows the image on each output,
+	/// with the gap between them filled with the color.
+	#[test]
+	fn composition() {
+		const COLOR: Color = Color::new(0x11_11_11);
+		// A 2x2 image with a different color in each corner.
+		let image = Image::new(2, 2, vec![0xa, 0xb, 0xc, 0xd]).unwrap();
+
+		// A 4x4 output beside a 2x2 output, with a 2x2 gap below the smaller output.
+		let outputs = [output(0, 0, 4, 4), output(4, 0, 2, 2)];
+		let pixels = compose(6, 4, &outputs, Some(&image), COLOR);
+
+		#[rustfmt::skip]
+		let expected = [
+			0xa, 0xa, 0xb, 0xb,   0xa, 0xb,
+			0xa, 0xa, 0xb, 0xb,   0xc, 0xd,
+			0xc, 0xc, 0xd, 0xd,   COLOR.rgb(), COLOR.rgb(),
+			0xc, 0xc, 0xd, 0xd,   COLOR.rgb(), COLOR.rgb(),
+		];
+		assert_eq!(pixels, expected);
+
+		// Without an image, the whole screen is filled with the color.
+		assert_eq!(compose(6, 4, &outputs, None, COLOR), vec![COLOR.rgb(); 24]);
+	}
+
+	/// Tests that outputs which extend past the screen are clipped to it.
+	#[test]
+	fn clipping() {
+		let image = Image::new(2, 1, vec![0xa, 0xb]).unwrap();
+		let pixels = compose(
+			2,
+			1,
+			&[output(-1, 0, 2, 1), output(1, 0, 2, 1)],
+			Some(&image),
+			Color::DEFAULT,
+		);
+
+		assert_eq!(pixels, [0xb, 0xa]);
+	}
+}
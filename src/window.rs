@@ -0,0 +1,105 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! What AquariWM's shared layers need of a display server's windows.
+//!
+//! The layout, the [state], and the policies built on it are generic over the window type, so that
+//! each display server can use its own window handles: an X11 window ID, or a Wayland surface
+//! object which can't be copied. Those layers only ever require a window to be a
+//! [`ManagedWindow`].
+//!
+//! Window handles themselves are never persisted or sent over the wire. [Layout snapshots],
+//! [marks], the [window list] and [queries] use each window's [`WindowId`] instead, which the
+//! display server resolves to its window again.
+//!
+//! [state]: crate::state::AquariWm
+//! [Layout snapshots]: crate::autosave::WorkspaceSnapshot
+//! [marks]: crate::marks
+//! [window list]: crate::window_list
+//! [queries]: crate::query
+
+use std::{
+	fmt,
+	fmt::{Debug, Display, Formatter, LowerHex},
+	hash::Hash,
+	str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// A window which can be managed by AquariWM.
+///
+/// Nothing display-server-specific is required; only that windows can be compared, hashed and
+/// cloned, and that each has a stable [ID].
+///
+/// [ID]: WindowId
+pub trait ManagedWindow: Clone + PartialEq + Eq + Hash + Debug {
+	/// Returns the window's ID, which identifies it for as long as it exists.
+	fn id(&self) -> WindowId;
+}
+
+/// A stable ID of a [managed window], with which it is persisted and referred to externally.
+///
+/// IDs are written in decimal, and parsed from decimal or `0x`-prefixed hexadecimal.
+///
+/// [managed window]: ManagedWindow
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WindowId(pub u64);
+
+/// Mock windows in tests are plain numbers, which are their own IDs.
+impl ManagedWindow for u32 {
+	#[inline(always)]
+	fn id(&self) -> WindowId {
+		WindowId(u64::from(*self))
+	}
+}
+
+impl From<u32> for WindowId {
+	#[inline(always)]
+	fn from(id: u32) -> Self {
+		Self(u64::from(id))
+	}
+}
+
+impl FromStr for WindowId {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let id = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+			Some(hex) => u64::from_str_radix(hex, 16),
+			None => s.parse(),
+		};
+
+		id.map(Self).map_err(|_| "expected a window ID")
+	}
+}
+
+impl Display for WindowId {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		Display::fmt(&self.0, f)
+	}
+}
+
+impl LowerHex for WindowId {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		LowerHex::fmt(&self.0, f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse() {
+		assert_eq!("0x1a00003".parse(), Ok(WindowId(0x1a00003)));
+		assert_eq!("0X2A".parse(), Ok(WindowId(42)));
+		assert_eq!("27262979".parse(), Ok(WindowId(27262979)));
+		assert!("0x".parse::<WindowId>().is_err());
+		assert!("mail".parse::<WindowId>().is_err());
+
+		assert_eq!(format!("{:#x}", WindowId(42)), "0x2a");
+	}
+}
@@ -0,0 +1,385 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Treating the windows of an application's window group as a unit.
+//!
+//! Applications with several top-level windows, like image editors with their tool palettes, name
+//! a *group leader* in each window's `WM_HINTS` property. The leader is often a window which is
+//! never mapped, standing in for the application as a whole, but it may also be one of the
+//! application's visible windows, naming itself.
+//!
+//! Depending on the [behavior], iconifying a window of a group iconifies the rest of the group,
+//! moving a window to another output takes the rest of the group along, and urgency on any window
+//! of a group marks its leader as urgent too. Windows of the group on other outputs than the window
+//! the user acted on are left alone unless [configured] otherwise, as the user can't see them.
+//!
+//! [behavior]: GroupBehavior
+//! [configured]: GroupSettings::across_outputs
+
+use std::{
+	collections::HashMap,
+	fmt::{self, Display, Formatter},
+	hash::Hash,
+	str::FromStr,
+};
+
+use thiserror::Error;
+
+/// Which actions apply to the whole window group of the window they are performed on.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum GroupBehavior {
+	/// Window groups are ignored: actions only apply to the window they are performed on.
+	#[default]
+	Ignore,
+	/// Iconifying a window iconifies the rest of its group.
+	IconifyTogether,
+	/// Moving a window to another output takes the rest of its group along.
+	MoveTogether,
+	/// Windows are iconified and moved together, and urgency on any window of a group marks its
+	/// leader as urgent too.
+	Full,
+}
+
+/// An action which may apply to a whole window group, depending on the [behavior].
+///
+/// [behavior]: GroupBehavior
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum GroupAction {
+	/// The window is iconified.
+	Iconify,
+	/// The window is moved to another output.
+	Move,
+}
+
+/// Settings for how window groups are treated.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct GroupSettings {
+	/// Which actions apply to the whole window group.
+	pub behavior: GroupBehavior,
+	/// Whether actions also apply to windows of the group on other outputs than the window they
+	/// are performed on.
+	pub across_outputs: bool,
+}
+
+/// The window groups of the managed windows, by their leaders.
+#[derive(Debug, Clone)]
+pub struct WindowGroups<Window> {
+	settings: GroupSettings,
+
+	/// The group leader named by each window which names one.
+	leaders: HashMap<Window, Window>,
+	/// The windows naming each group leader, in the order they named it.
+	members: HashMap<Window, Vec<Window>>,
+}
+
+impl GroupBehavior {
+	/// Returns whether the given `action` applies to the whole window group.
+	pub const fn applies_to(self, action: GroupAction) -> bool {
+		matches!(
+			(self, action),
+			(Self::Full, _) | (Self::IconifyTogether, GroupAction::Iconify) | (Self::MoveTogether, GroupAction::Move)
+		)
+	}
+
+	/// Returns whether urgency on any window of a group marks its leader as urgent too.
+	pub const fn shares_urgency(self) -> bool {
+		matches!(self, Self::Full)
+	}
+}
+
+impl<Window: Eq + Hash + Clone> WindowGroups<Window> {
+	/// Creates window groups with the given `settings`, with no windows in any group.
+	pub fn new(settings: GroupSettings) -> Self {
+		Self {
+			settings,
+
+			leaders: HashMap::new(),
+			members: HashMap::new(),
+		}
+	}
+
+	/// Returns the settings for how window groups are treated.
+	pub const fn settings(&self) -> &GroupSettings {
+		&self.settings
+	}
+
+	/// Replaces the settings for how window groups are treated, e.g. when the configuration is
+	/// reloaded.
+	pub const fn set_settings(&mut self, settings: GroupSettings) {
+		self.settings = settings;
+	}
+
+	/// Sets the group leader named by the given `window`, or removes it from its group if it
+	/// names none.
+	///
+	/// Returns whether the window's group changed.
+	pub fn set_leader(&mut self, window: Window, leader: Option<Window>) -> bool {
+		if self.leaders.get(&window) == leader.as_ref() {
+			return false;
+		}
+
+		self.leave(&window);
+
+		if let Some(leader) = leader {
+			self.members.entry(leader.clone()).or_default().push(window.clone());
+			self.leaders.insert(window, leader);
+		}
+
+		true
+	}
+
+	/// Forgets the given (destroyed) `window`, removing it from its group and dissolving the group
+	/// it leads, if any, as its ID may be reused.
+	pub fn forget(&mut self, window: &Window) {
+		self.leave(window);
+
+		for member in self.members.remove(window).unwrap_or_default() {
+			self.leaders.remove(&member);
+		}
+	}
+
+	/// Returns the group leader of the given `window`: the leader it names, or the window itself
+	/// if other windows name it as their leader.
+	pub fn leader_of<'window>(&'window self, window: &'window Window) -> Option<&'window Window> {
+		self.leaders
+			.get(window)
+			.or_else(|| self.members.contains_key(window).then_some(window))
+	}
+
+	/// Returns the windows naming the given `leader` as their group leader, in the order they
+	/// named it.
+	pub fn members(&self, leader: &Window) -> &[Window] {
+		self.members.get(leader).map_or(&[], Vec::as_slice)
+	}
+
+	/// Returns the windows which the given `action` performed on the `target` window applies to:
+	/// the `target` followed by the rest of its group if the [behavior] says so.
+	///
+	/// Each window is only returned once, whichever window of the group is targeted. Windows for
+	/// which `output_of` returns [`None`], like unmanaged leaders, are left out, as are windows on
+	/// other outputs than the `target` unless [`across_outputs`] is set.
+	///
+	/// [behavior]: GroupSettings::behavior
+	/// [`across_outputs`]: GroupSettings::across_outputs
+	pub fn expand<Output: PartialEq>(
+		&self,
+		action: GroupAction,
+		target: Window,
+		output_of: impl Fn(&Window) -> Option<Output>,
+	) -> Vec<Window> {
+		let mut windows = vec![target.clone()];
+
+		let Some(leader) = self.leader_of(&target) else {
+			return windows;
+		};
+		if !self.settings.behavior.applies_to(action) {
+			return windows;
+		}
+
+		let output = output_of(&target);
+		for window in [leader].into_iter().chain(self.members(leader)) {
+			if windows.contains(window) {
+				continue;
+			}
+
+			let included = match output_of(window) {
+				Some(other) => self.settings.across_outputs || output.as_ref() == Some(&other),
+				None => false,
+			};
+			if included {
+				windows.push(window.clone());
+			}
+		}
+
+		windows
+	}
+
+	/// Returns whether the given `window` is urgent: whether `is_urgent` says it is, or, if the
+	/// [behavior] shares urgency, whether any window naming it as its group leader is.
+	///
+	/// [behavior]: GroupSettings::behavior
+	pub fn is_urgent(&self, window: &Window, is_urgent: impl Fn(&Window) -> bool) -> bool {
+		is_urgent(window) || (self.settings.behavior.shares_urgency() && self.members(window).iter().any(is_urgent))
+	}
+
+	/// Removes the given `window` from its group, if it is in one.
+	fn leave(&mut self, window: &Window) {
+		let Some(leader) = self.leaders.remove(window) else {
+			return;
+		};
+
+		if let Some(members) = self.members.get_mut(&leader) {
+			members.retain(|member| member != window);
+
+			if members.is_empty() {
+				self.members.remove(&leader);
+			}
+		}
+	}
+}
+
+impl<Window: Eq + Hash + Clone> Default for WindowGroups<Window> {
+	fn default() -> Self {
+		Self::new(GroupSettings::default())
+	}
+}
+
+impl Display for GroupBehavior {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Ignore => write!(f, "ignore"),
+			Self::IconifyTogether => write!(f, "iconify-together"),
+			Self::MoveTogether => write!(f, "move-together"),
+			Self::Full => write!(f, "full"),
+		}
+	}
+}
+
+/// An error returned when parsing a [`GroupBehavior`] fails.
+#[derive(Debug, Error)]
+#[error("expected `ignore`, `iconify-together`, `move-together`, or `full`, found {0:?}")]
+pub struct ParseGroupBehaviorError(String);
+
+impl FromStr for GroupBehavior {
+	type Err = ParseGroupBehaviorError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim() {
+			"ignore" => Ok(Self::Ignore),
+			"iconify-together" => Ok(Self::IconifyTogether),
+			"move-together" => Ok(Self::MoveTogether),
+			"full" => Ok(Self::Full),
+
+			other => Err(ParseGroupBehaviorError(other.to_owned())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The group leader, which names itself, as visible group leaders do.
+	const LEADER: u32 = 1;
+	/// An unmapped window which only stands in for its application, naming no leader itself.
+	const HIDDEN_LEADER: u32 = 10;
+
+	/// Creates window groups with the given `behavior` from synthetic `WM_HINTS` window groups:
+	/// each window and the leader it names.
+	fn groups(behavior: GroupBehavior, hints: &[(u32, u32)]) -> WindowGroups<u32> {
+		let mut groups = WindowGroups::new(GroupSettings {
+			behavior,
+			across_outputs: false,
+		});
+		for &(window, leader) in hints {
+			groups.set_leader(window, Some(leader));
+		}
+
+		groups
+	}
+
+	#[test]
+	fn index() {
+		let mut groups = groups(
+			GroupBehavior::Full,
+			&[(LEADER, LEADER), (2, LEADER), (3, LEADER), (11, HIDDEN_LEADER)],
+		);
+
+		assert_eq!(groups.members(&LEADER), [LEADER, 2, 3]);
+		assert_eq!(groups.leader_of(&2), Some(&LEADER));
+		// Windows named as leaders lead their group even if they don't name themselves.
+		assert_eq!(groups.leader_of(&HIDDEN_LEADER), Some(&HIDDEN_LEADER));
+		assert_eq!(groups.leader_of(&4), None);
+
+		// Naming the same leader again changes nothing.
+		assert!(!groups.set_leader(2, Some(LEADER)));
+		// Windows move between groups as their hints change.
+		assert!(groups.set_leader(2, Some(HIDDEN_LEADER)));
+		assert_eq!(groups.members(&LEADER), [LEADER, 3]);
+		assert_eq!(groups.members(&HIDDEN_LEADER), [11, 2]);
+
+		// Groups are removed once they are empty.
+		assert!(groups.set_leader(11, None));
+		groups.forget(&2);
+		assert!(groups.members(&HIDDEN_LEADER).is_empty());
+		assert_eq!(groups.leader_of(&HIDDEN_LEADER), None);
+
+		// A forgotten leader's group is dissolved.
+		groups.forget(&LEADER);
+		assert_eq!(groups.leader_of(&3), None);
+		assert!(groups.members(&LEADER).is_empty());
+	}
+
+	#[test]
+	fn expansion() {
+		let hints = [
+			(LEADER, LEADER),
+			(2, LEADER),
+			(3, LEADER),
+			(4, HIDDEN_LEADER),
+			(5, HIDDEN_LEADER),
+		];
+		let output_of = |window: &u32| match window {
+			3 => Some("HDMI-1"),
+			// The hidden leader is never managed, so it isn't on any output.
+			&HIDDEN_LEADER => None,
+			_ => Some("eDP-1"),
+		};
+
+		let iconify = groups(GroupBehavior::IconifyTogether, &hints);
+		assert_eq!(iconify.expand(GroupAction::Iconify, LEADER, output_of), [LEADER, 2]);
+		// Targeting a member expands to the same group, without repeating the member.
+		assert_eq!(iconify.expand(GroupAction::Iconify, 2, output_of), [2, LEADER]);
+		assert_eq!(iconify.expand(GroupAction::Iconify, 5, output_of), [5, 4]);
+		// Windows on other outputs than the target are left alone...
+		assert_eq!(iconify.expand(GroupAction::Iconify, 3, output_of), [3]);
+		// ...and only the behavior's actions are expanded.
+		assert_eq!(iconify.expand(GroupAction::Move, LEADER, output_of), [LEADER]);
+
+		let mut full = groups(GroupBehavior::Full, &hints);
+		assert_eq!(full.expand(GroupAction::Move, 2, output_of), [2, LEADER]);
+		full.set_settings(GroupSettings {
+			behavior: GroupBehavior::Full,
+			across_outputs: true,
+		});
+		assert_eq!(full.expand(GroupAction::Move, 2, output_of), [2, LEADER, 3]);
+
+		// Windows in no group are only expanded to themselves.
+		assert_eq!(full.expand(GroupAction::Iconify, 6, output_of), [6]);
+		assert_eq!(
+			groups(GroupBehavior::Ignore, &hints).expand(GroupAction::Iconify, LEADER, output_of),
+			[LEADER]
+		);
+	}
+
+	#[test]
+	fn urgency() {
+		let hints = [(LEADER, LEADER), (2, LEADER)];
+		let urgent = |window: &u32| *window == 2;
+
+		let full = groups(GroupBehavior::Full, &hints);
+		assert!(full.is_urgent(&LEADER, urgent));
+		assert!(full.is_urgent(&2, urgent));
+
+		// Urgency is only shared with the leader, not the other way around.
+		assert!(!full.is_urgent(&2, |window| *window == LEADER));
+
+		let iconify = groups(GroupBehavior::IconifyTogether, &hints);
+		assert!(!iconify.is_urgent(&LEADER, urgent));
+	}
+
+	#[test]
+	fn parse() {
+		for behavior in [
+			GroupBehavior::Ignore,
+			GroupBehavior::IconifyTogether,
+			GroupBehavior::MoveTogether,
+			GroupBehavior::Full,
+		] {
+			assert_eq!(behavior.to_string().parse::<GroupBehavior>().unwrap(), behavior);
+		}
+
+		assert!("together".parse::<GroupBehavior>().is_err());
+	}
+}
@@ -0,0 +1,328 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The list of managed windows, exported for external window switchers.
+//!
+//! Switchers, like rofi's window mode, present the list as a picker and then ask for the picked
+//! window to be focused. The list is assembled from what AquariWM already knows about its windows,
+//! so that it can be exported as often as it changes without querying the display server.
+//!
+//! The list is encoded as JSON with a [version], which is incremented whenever its schema changes
+//! incompatibly, so that switchers can detect changes.
+//!
+//! Windows on other workspaces are listed too, without geometry as they aren't shown, so that
+//! switchers can switch to their workspaces. Sticky windows are listed on the workspace being
+//! shown.
+//!
+//! [version]: WindowList::version
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	desktop::Desktop,
+	layout,
+	output::Output,
+	placement::Rect,
+	state::{AquariWm, MapState},
+	window::{ManagedWindow, WindowId},
+};
+
+/// The current [version] of the window list's schema.
+///
+/// [version]: WindowList::version
+pub const VERSION: u32 = 1;
+
+/// The list of managed windows.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct WindowList {
+	/// The version of the list's schema, which is [`VERSION`] for lists made by this version of
+	/// AquariWM.
+	pub version: u32,
+
+	/// The mapped windows in the [order they are read on screen], followed by the iconified
+	/// windows and the windows on other workspaces, ordered by their IDs.
+	///
+	/// [order they are read on screen]: AquariWm::visual_order
+	pub windows: Vec<WindowInfo>,
+}
+
+/// What is known about a window in the [window list].
+///
+/// [window list]: WindowList
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct WindowInfo {
+	/// The window's [ID], with which it can be focused.
+	///
+	/// [ID]: WindowId
+	pub id: WindowId,
+	pub title: Option<String>,
+	pub class: Option<String>,
+
+	/// The name of the workspace the window is on.
+	pub workspace: String,
+	/// The name of the output the window is on, if it is on one.
+	pub output: Option<String>,
+	/// The window's geometry, if it is shown.
+	pub geometry: Option<Geometry>,
+
+	pub focused: bool,
+	/// Whether the window is demanding attention.
+	pub urgent: bool,
+	/// Whether the window's client has [stopped responding].
+	///
+	/// [stopped responding]: crate::ping
+	#[serde(default)]
+	pub unresponsive: bool,
+	pub floating: bool,
+	pub fullscreen: bool,
+	/// Whether the window is shown on every workspace.
+	pub sticky: bool,
+	/// Whether the window is iconified (minimized).
+	pub iconified: bool,
+	/// The window's [marks], in order.
+	///
+	/// [marks]: crate::marks
+	#[serde(default)]
+	pub marks: Vec<String>,
+}
+
+/// A window's geometry in the [window list].
+///
+/// [window list]: WindowList
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct Geometry {
+	pub x: i32,
+	pub y: i32,
+
+	pub width: u32,
+	pub height: u32,
+}
+
+/// What only the display server knows about a window, which is cached as it changes.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct Details<'a> {
+	pub title: Option<&'a str>,
+	pub class: Option<&'a str>,
+
+	/// The window's geometry, if it is floating.
+	pub floating: Option<Rect>,
+	/// Whether the window is demanding attention.
+	pub urgent: bool,
+	/// Whether the window's client has [stopped responding].
+	///
+	/// [stopped responding]: crate::ping
+	pub unresponsive: bool,
+	pub fullscreen: bool,
+}
+
+impl WindowList {
+	/// Returns the list of the mapped and iconified windows, and the windows on other workspaces,
+	/// in the given AquariWM `state`, where `floating` is the stacking order of the floating
+	/// windows from the bottom to the top, and `details` returns what the display server knows
+	/// about a window.
+	pub fn of<'a, Window>(
+		state: &AquariWm<Window>,
+		floating: &[Window],
+		details: impl Fn(&Window) -> Details<'a>,
+	) -> Self
+	where
+		Window: ManagedWindow + Send + Sync,
+	{
+		let order = state.visual_order(floating);
+
+		let mut windows: Vec<_> = state
+			.windows
+			.iter()
+			.filter(|(window, window_state)| {
+				window_state.mapped == MapState::Mapped || state.is_iconified(window) || state.is_away(window)
+			})
+			.map(|(window, window_state)| {
+				// Windows which aren't in the visual order, like iconified windows, come last.
+				let position = order.iter().position(|other| other == window).unwrap_or(order.len());
+
+				let Details {
+					title,
+					class,
+					floating,
+					urgent,
+					unresponsive,
+					fullscreen,
+				} = details(window);
+				let iconified = state.is_iconified(window);
+				let names = state.workspaces.names();
+				let workspace = match state.desktop_of(window) {
+					Desktop::Index(index) => names.get(index as usize),
+					Desktop::All => names.get(state.workspaces.current()),
+				};
+
+				let geometry = match window_state.mode {
+					_ if iconified || state.is_away(window) => None,
+
+					layout::Mode::Tiled => state.tile_of(window),
+					layout::Mode::Floating => floating,
+				};
+				let output = geometry.and_then(|(x, y, width, height)| {
+					Output::at(&state.outputs, x + (width / 2) as i32, y + (height / 2) as i32)
+				});
+
+				let info = WindowInfo {
+					id: window.id(),
+					title: title.map(str::to_owned),
+					class: class.map(str::to_owned),
+
+					workspace: workspace.map(ToString::to_string).unwrap_or_default(),
+					output: output.map(|output| output.name.clone()),
+					geometry: geometry.map(|(x, y, width, height)| Geometry { x, y, width, height }),
+
+					focused: state.focused.as_ref() == Some(window),
+					urgent,
+					unresponsive,
+					floating: window_state.mode == layout::Mode::Floating,
+					fullscreen,
+					sticky: window_state.sticky,
+					iconified,
+					marks: state.marks.marks_of(window).map(str::to_owned).collect(),
+				};
+
+				(position, info)
+			})
+			.collect();
+		windows.sort_by_key(|(position, window)| (*position, window.id));
+
+		Self {
+			version: VERSION,
+			windows: windows.into_iter().map(|(_, window)| window).collect(),
+		}
+	}
+
+	/// Encodes the list as JSON, for window switchers to read.
+	pub fn encode(&self) -> String {
+		serde_json::to_string(self).expect("the window list is always serializable")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		layout::{managers::Stack, LayoutSettings},
+		output::Scale,
+		state::FocusCause,
+	};
+
+	/// Tests that three windows across two outputs are listed with their details, and that focusing
+	/// the window on the other output switches to that output.
+	#[test]
+	fn three_windows() {
+		let output = |name: &str, x, primary| Output {
+			name: name.to_owned(),
+
+			x,
+			y: 0,
+
+			width: 1920,
+			height: 1080,
+
+			primary,
+			scale: Scale::IDENTITY,
+
+			clones: Vec::new(),
+		};
+
+		let mut state =
+			AquariWm::with_tiling_layout::<Stack<u32>>(0, 0, 3840, 1080, LayoutSettings::new().window_gap(0));
+		state.outputs = vec![output("DP-1", 0, true), output("HDMI-1", 1920, false)];
+		state.add_windows([(1, MapState::Mapped), (2, MapState::Mapped)]);
+		state.add_window(3, MapState::Unmapped).unwrap();
+		state.windows.get_mut(&3).unwrap().set_floating();
+		state.map_window(&3).unwrap();
+		state.apply_changes(|_, _, _, _, _, _| Ok::<_, ()>(())).unwrap();
+		state.set_focused_from(Some(1), FocusCause::Keyboard);
+		state.marks.mark("pip", 3);
+
+		let titles = ["", "Terminal", "Inbox", "Picture-in-Picture"];
+		let list = |state: &AquariWm<u32>| {
+			WindowList::of(state, &[3], |&window| Details {
+				title: Some(titles[window as usize]),
+				class: (window != 3).then_some("firefox"),
+
+				floating: (window == 3).then_some((2000, 100, 400, 300)),
+				urgent: window == 2,
+				unresponsive: window == 1,
+				fullscreen: false,
+			})
+		};
+
+		let windows = list(&state).windows;
+		assert_eq!(
+			windows.iter().map(|window| window.id).collect::<Vec<_>>(),
+			[WindowId(1), WindowId(2), WindowId(3)]
+		);
+		assert_eq!(
+			windows
+				.iter()
+				.map(|window| window.output.as_deref())
+				.collect::<Vec<_>>(),
+			[Some("DP-1"), Some("HDMI-1"), Some("HDMI-1")]
+		);
+		assert_eq!(
+			windows[2],
+			WindowInfo {
+				id: WindowId(3),
+				title: Some("Picture-in-Picture".to_owned()),
+				class: None,
+
+				workspace: "1".to_owned(),
+				output: Some("HDMI-1".to_owned()),
+				geometry: Some(Geometry {
+					x: 2000,
+					y: 100,
+					width: 400,
+					height: 300,
+				}),
+
+				focused: false,
+				urgent: false,
+				unresponsive: false,
+				floating: true,
+				fullscreen: false,
+				sticky: false,
+				iconified: false,
+				marks: vec!["pip".to_owned()],
+			}
+		);
+		assert!(windows[0].focused && windows[1].urgent && !windows[1].floating);
+		assert!(windows[0].unresponsive && !windows[1].unresponsive);
+
+		// Focusing a window on the other output switches to it.
+		state.set_focused_from(Some(2), FocusCause::Keyboard);
+		assert_eq!(state.active_output.name(), Some("HDMI-1"));
+		let windows = list(&state).windows;
+		assert!(!windows[0].focused && windows[1].focused);
+
+		// Iconified windows are listed last, without a geometry.
+		state.iconify_window(&1);
+		let encoded = list(&state).encode();
+		assert!(encoded.starts_with(r#"{"version":1,"windows":[{"id":2,"title":"Inbox","#));
+		assert!(encoded.contains(r#"{"id":1,"title":"Terminal","#));
+		assert!(encoded.contains(r#""geometry":null,"focused":false,"urgent":false,"unresponsive":true,"floating":false,"fullscreen":false,"sticky":false,"iconified":true,"marks":[]"#));
+
+		// Windows on other workspaces are listed on them, without a geometry, and sticky windows on
+		// the workspace being shown.
+		state.move_window_to_workspace(&2, 1, |_, _| false).unwrap();
+		state.set_sticky(&3, true);
+		let windows = list(&state).windows;
+		let workspaces: Vec<_> = (windows.iter())
+			.map(|window| (window.id, window.workspace.as_str(), window.geometry.is_some()))
+			.collect();
+		assert_eq!(
+			workspaces,
+			[
+				(WindowId(3), "1", true),
+				(WindowId(1), "1", false),
+				(WindowId(2), "2", false)
+			]
+		);
+	}
+}
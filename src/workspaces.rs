@@ -0,0 +1,703 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Dynamic workspaces, which are created when they are first used and removed once they are left
+//! empty, like dwm's tags, while being numbered and ordered like i3's workspaces.
+//!
+//! Switching to, or moving a window to, the workspace after the last one [creates] it. When the
+//! current workspace is switched away from, the empty numbered workspaces after the last one still
+//! in use are [collected], leaving at least the policy's [minimum] number of workspaces. With
+//! [auto-renumbering], every empty numbered workspace other than the current one is collected, and
+//! the numbered workspaces which remain are renumbered so that their numbers stay contiguous:
+//! closing workspace 3 of 5 renumbers 4 to 3 and 5 to 4. Workspaces with [explicit names] are never
+//! collected or renumbered.
+//!
+//! Workspaces are referred to by their index (the EWMH desktop) or their number in several places:
+//! the current workspace, the most recently used workspaces, the desktop of each window, and the
+//! outputs workspaces are [pinned] to. Every change to workspaces' indices goes through
+//! [`remap_workspace_indices`], so that none of them is missed.
+//!
+//! Only the windows on the current workspace, and sticky windows, are shown: [`AquariWm`] takes the
+//! windows on other workspaces out of the tiling layout and puts them back when their workspace is
+//! switched to.
+//!
+//! [creates]: Workspaces::switch_to
+//! [collected]: Workspaces::collection
+//! [minimum]: Policy::minimum
+//! [auto-renumbering]: Policy::auto_renumber
+//! [explicit names]: Name::Named
+//! [pinned]: OutputAssignment::Pinned
+//! [`remap_workspace_indices`]: Workspaces::remap_workspace_indices
+//! [`AquariWm`]: crate::state::AquariWm
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	convert::Infallible,
+	fmt::{self, Display, Formatter},
+	hash::Hash,
+	str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::{desktop::Desktop, output::OutputAssignment};
+
+/// The name of a workspace.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Name {
+	/// The workspace is numbered, so it may be [renumbered] and [collected] when it is empty.
+	///
+	/// [renumbered]: Policy::auto_renumber
+	/// [collected]: Workspaces::collection
+	Number(u32),
+	/// The workspace was given an explicit name, so it is left untouched.
+	Named(String),
+}
+
+/// When workspaces are created and removed.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Policy {
+	/// The fewest workspaces there may be; empty workspaces aren't collected beyond it.
+	///
+	/// This is `1` by default.
+	pub minimum: usize,
+	/// Whether numbered workspaces are kept contiguous, collecting every empty numbered workspace
+	/// rather than only trailing ones, and renumbering those after it.
+	pub auto_renumber: bool,
+}
+
+/// An error returned when switching or moving a window to a workspace fails.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Error)]
+pub enum WorkspaceError {
+	/// Only the workspace after the last can be created.
+	#[error("there is no workspace at index {index}: there are {count} workspaces, and only the next can be created")]
+	NoSuchWorkspace { index: usize, count: usize },
+}
+
+/// How the indices and numbers of workspaces change when workspaces are [collected].
+///
+/// [collected]: Workspaces::collection
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct Remap {
+	/// The new index of each workspace, by its old index, or [`None`] if it is removed.
+	pub indices: Vec<Option<usize>>,
+	/// The new number of each numbered workspace whose number changes, by its old number, or
+	/// [`None`] if it is removed.
+	pub numbers: BTreeMap<u32, Option<u32>>,
+}
+
+/// What changed when [switching] or [moving a window] to a workspace.
+///
+/// [switching]: Workspaces::switch_to
+/// [moving a window]: Workspaces::move_window
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct Change {
+	/// Whether the workspace was created.
+	pub created: bool,
+	/// How workspaces were [remapped] as empty workspaces were collected, if any were.
+	///
+	/// The EWMH desktop properties must be published again if workspaces were created or remapped.
+	///
+	/// [remapped]: Workspaces::remap_workspace_indices
+	pub remap: Option<Remap>,
+}
+
+/// The workspaces, which are created and removed according to a [policy].
+///
+/// [policy]: Policy
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Workspaces<Window: Eq + Hash> {
+	policy: Policy,
+
+	/// The names of the workspaces, by index.
+	names: Vec<Name>,
+	/// The index of the current workspace.
+	current: usize,
+	/// The indices of the workspaces which have been current, most recently used first.
+	recent: Vec<usize>,
+
+	/// The desktop each window is on.
+	desktops: HashMap<Window, Desktop>,
+}
+
+impl Display for Name {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Number(number) => write!(f, "{number}"),
+			Self::Named(name) => f.write_str(name),
+		}
+	}
+}
+
+impl FromStr for Name {
+	type Err = Infallible;
+
+	/// Parses a workspace's name, which is [numbered] if it is a number.
+	///
+	/// [numbered]: Name::Number
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(match s.parse() {
+			Ok(number) => Self::Number(number),
+			Err(_) => Self::Named(s.to_owned()),
+		})
+	}
+}
+
+impl Default for Policy {
+	fn default() -> Self {
+		Self {
+			minimum: 1,
+			auto_renumber: false,
+		}
+	}
+}
+
+impl Remap {
+	/// Returns the new index of the workspace at the given `old` index, or [`None`] if it is
+	/// removed.
+	pub fn index(&self, old: usize) -> Option<usize> {
+		self.indices.get(old).copied().flatten()
+	}
+}
+
+impl<Window: Eq + Hash> Workspaces<Window> {
+	/// Creates the policy's [minimum] number of workspaces, or one if that is `0`, numbered from
+	/// `1`, with the first being current.
+	///
+	/// [minimum]: Policy::minimum
+	pub fn new(policy: Policy) -> Self {
+		Self {
+			policy,
+
+			names: (1..=policy.minimum.max(1) as u32).map(Name::Number).collect(),
+			current: 0,
+			recent: vec![0],
+
+			desktops: HashMap::new(),
+		}
+	}
+
+	#[inline(always)]
+	pub const fn policy(&self) -> Policy {
+		self.policy
+	}
+
+	/// Returns the names of the workspaces, by index.
+	#[inline(always)]
+	pub fn names(&self) -> &[Name] {
+		&self.names
+	}
+
+	/// Returns the index of the current workspace.
+	#[inline(always)]
+	pub const fn current(&self) -> usize {
+		self.current
+	}
+
+	/// Returns the indices of the workspaces which have been current, most recently used first.
+	#[inline(always)]
+	pub fn recent(&self) -> &[usize] {
+		&self.recent
+	}
+
+	/// Returns the desktop the given `window` is on, if it is tracked.
+	pub fn desktop_of(&self, window: &Window) -> Option<Desktop> {
+		self.desktops.get(window).copied()
+	}
+
+	/// Returns the tracked windows and the desktops they are on.
+	pub fn desktops(&self) -> impl Iterator<Item = (&Window, Desktop)> {
+		self.desktops.iter().map(|(window, &desktop)| (window, desktop))
+	}
+
+	/// Returns whether no window is on the workspace at the given `index`.
+	///
+	/// Windows on every desktop (i.e. sticky windows) don't keep any workspace in use.
+	pub fn is_empty(&self, index: usize) -> bool {
+		!self
+			.desktops
+			.values()
+			.any(|&desktop| desktop == Desktop::Index(index as u32))
+	}
+
+	/// Starts tracking the given `window` on the given `desktop`.
+	pub fn add_window(&mut self, window: Window, desktop: Desktop) {
+		self.desktops.insert(window, desktop);
+	}
+
+	/// Stops tracking the given `window`, returning the desktop it was on.
+	///
+	/// The window's workspace is only collected once it is switched away from.
+	pub fn remove_window(&mut self, window: &Window) -> Option<Desktop> {
+		self.desktops.remove(window)
+	}
+
+	/// Switches to the workspace at the given `index`, creating it if it is the workspace after
+	/// the last.
+	///
+	/// Empty workspaces are then [collected], and every index and number referring to workspaces,
+	/// including those of the workspaces `pins`, is [remapped] accordingly.
+	///
+	/// [collected]: Self::collection
+	/// [remapped]: Self::remap_workspace_indices
+	pub fn switch_to(&mut self, index: usize, pins: &mut OutputAssignment) -> Result<Change, WorkspaceError> {
+		let created = self.create(index)?;
+
+		if index == self.current {
+			return Ok(Change { created, remap: None });
+		}
+
+		self.current = index;
+		self.recent.retain(|&recent| recent != index);
+		self.recent.insert(0, index);
+
+		let remap = self.collection();
+		if let Some(remap) = &remap {
+			self.remap_workspace_indices(remap, pins);
+		}
+
+		Ok(Change { created, remap })
+	}
+
+	/// Moves the given `window` to the workspace at the given `index`, creating it if it is the
+	/// workspace after the last.
+	///
+	/// The workspace the window leaves isn't collected, even if it is left empty, until it is
+	/// switched away from.
+	pub fn move_window(&mut self, window: Window, index: usize) -> Result<Change, WorkspaceError> {
+		let created = self.create(index)?;
+		self.desktops.insert(window, Desktop::Index(index as u32));
+
+		Ok(Change { created, remap: None })
+	}
+
+	/// Creates the workspace at the given `index` if it is the workspace after the last, numbered
+	/// after the highest numbered workspace.
+	///
+	/// Returns whether the workspace was created.
+	fn create(&mut self, index: usize) -> Result<bool, WorkspaceError> {
+		let count = self.names.len();
+
+		match index {
+			index if index < count => Ok(false),
+
+			index if index == count => {
+				let highest = self.names.iter().filter_map(|name| match name {
+					Name::Number(number) => Some(*number),
+					Name::Named(_) => None,
+				});
+				self.names.push(Name::Number(highest.max().unwrap_or_default() + 1));
+
+				Ok(true)
+			},
+
+			index => Err(WorkspaceError::NoSuchWorkspace { index, count }),
+		}
+	}
+
+	/// Decides which empty workspaces are collected, and how the remaining workspaces are
+	/// renumbered, returning [`None`] if nothing changes.
+	///
+	/// Only numbered workspaces which are empty and aren't current may be collected. Without
+	/// [auto-renumbering], only those after the last workspace which can't be collected are; with
+	/// it, all of them are. The last are collected first, and no more are collected once there are
+	/// as few workspaces as the [minimum].
+	///
+	/// [auto-renumbering]: Policy::auto_renumber
+	/// [minimum]: Policy::minimum
+	pub fn collection(&self) -> Option<Remap> {
+		let collectable = |index: usize| {
+			index != self.current && matches!(self.names[index], Name::Number(_)) && self.is_empty(index)
+		};
+
+		let candidates: Vec<usize> = match self.policy.auto_renumber {
+			true => (0..self.names.len())
+				.rev()
+				.filter(|&index| collectable(index))
+				.collect(),
+			false => (0..self.names.len())
+				.rev()
+				.take_while(|&index| collectable(index))
+				.collect(),
+		};
+		let removed: Vec<usize> = candidates
+			.into_iter()
+			.take(self.names.len().saturating_sub(self.policy.minimum))
+			.collect();
+
+		let mut remap = Remap::default();
+		let (mut next_index, mut next_number) = (0, 1);
+
+		for (index, name) in self.names.iter().enumerate() {
+			let removed = removed.contains(&index);
+
+			remap.indices.push((!removed).then(|| {
+				next_index += 1;
+				next_index - 1
+			}));
+
+			if let Name::Number(number) = *name {
+				match removed {
+					true => {
+						remap.numbers.insert(number, None);
+					},
+
+					false if self.policy.auto_renumber => {
+						if number != next_number {
+							remap.numbers.insert(number, Some(next_number));
+						}
+						next_number += 1;
+					},
+					false => (),
+				}
+			}
+		}
+
+		(!removed.is_empty() || !remap.numbers.is_empty()).then_some(remap)
+	}
+
+	/// Applies the given `remap` to everything which refers to workspaces by their indices or
+	/// numbers: the workspaces' names, the current workspace, the most recently used workspaces,
+	/// the desktop of each window, and the outputs workspaces are pinned to in the given `pins`.
+	///
+	/// Windows on removed workspaces are moved to the current workspace, and pins of removed
+	/// workspaces are forgotten; renumbered workspaces keep their pins.
+	pub fn remap_workspace_indices(&mut self, remap: &Remap, pins: &mut OutputAssignment) {
+		let renumber = |number: u32| remap.numbers.get(&number).copied().unwrap_or(Some(number));
+
+		self.names = self
+			.names
+			.drain(..)
+			.enumerate()
+			.filter(|&(index, _)| remap.index(index).is_some())
+			.map(|(_, name)| match name {
+				Name::Number(number) => Name::Number(renumber(number).unwrap_or(number)),
+				name => name,
+			})
+			.collect();
+
+		self.current = remap.index(self.current).unwrap_or_default();
+
+		let mut recent = Vec::with_capacity(self.recent.len());
+		for index in self.recent.iter().filter_map(|&index| remap.index(index)) {
+			if !recent.contains(&index) {
+				recent.push(index);
+			}
+		}
+		self.recent = recent;
+
+		for desktop in self.desktops.values_mut() {
+			if let Desktop::Index(index) = *desktop {
+				let index = remap.index(index as usize).unwrap_or(self.current);
+				*desktop = Desktop::Index(index as u32);
+			}
+		}
+
+		if let OutputAssignment::Pinned(pinned) = pins {
+			*pinned = pinned
+				.drain()
+				.filter_map(|(number, output)| Some((renumber(number)?, output)))
+				.collect();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Returns the written names of the `workspaces`.
+	fn names(workspaces: &Workspaces<u32>) -> Vec<String> {
+		workspaces.names().iter().map(ToString::to_string).collect()
+	}
+
+	/// Returns the workspaces `pins`, ordered by workspace number.
+	fn pinned(pins: &OutputAssignment) -> Vec<(u32, &str)> {
+		let OutputAssignment::Pinned(pins) = pins else {
+			return Vec::new();
+		};
+		let mut pinned: Vec<_> = pins.iter().map(|(&number, output)| (number, output.as_str())).collect();
+		pinned.sort_unstable();
+
+		pinned
+	}
+
+	/// Creates workspaces numbered `1..=count`, switching to each in turn and moving a window
+	/// (with the same number as the workspace) onto each.
+	fn numbered(policy: Policy, count: u32) -> Workspaces<u32> {
+		let mut workspaces = Workspaces::new(policy);
+
+		for number in 1..=count {
+			let index = number as usize - 1;
+
+			workspaces.move_window(number, index).unwrap();
+			workspaces.switch_to(index, &mut OutputAssignment::Primary).unwrap();
+		}
+
+		workspaces
+	}
+
+	#[test]
+	fn names_parse() {
+		assert_eq!("3".parse(), Ok(Name::Number(3)));
+		assert_eq!("web".parse(), Ok(Name::Named("web".to_owned())));
+		assert_eq!("-1".parse(), Ok(Name::Named("-1".to_owned())));
+		assert_eq!(Name::Number(3).to_string(), "3");
+	}
+
+	#[test]
+	fn auto_create() {
+		let mut pins = OutputAssignment::Primary;
+		let mut workspaces = Workspaces::<u32>::new(Policy::default());
+		assert_eq!(names(&workspaces), ["1"]);
+
+		// Switching to the workspace after the last creates it...
+		let change = workspaces.switch_to(1, &mut pins).unwrap();
+		assert!(change.created);
+		assert_eq!(names(&workspaces), ["1", "2"]);
+		assert_eq!(workspaces.current(), 1);
+
+		// ...but no workspace further than that can be created.
+		assert_eq!(
+			workspaces.switch_to(3, &mut pins),
+			Err(WorkspaceError::NoSuchWorkspace { index: 3, count: 2 })
+		);
+		assert_eq!(workspaces.current(), 1);
+
+		// Moving a window creates its workspace too, numbered after the highest.
+		let change = workspaces.move_window(7, 2).unwrap();
+		assert_eq!(
+			change,
+			Change {
+				created: true,
+				remap: None
+			}
+		);
+		assert_eq!(names(&workspaces), ["1", "2", "3"]);
+		assert_eq!(workspaces.desktop_of(&7), Some(Desktop::Index(2)));
+		assert!(workspaces.move_window(7, 4).is_err());
+
+		// Switching to the current workspace changes nothing.
+		assert_eq!(workspaces.switch_to(1, &mut pins), Ok(Change::default()));
+
+		// The minimum number of workspaces exist from the start.
+		let workspaces = Workspaces::<u32>::new(Policy {
+			minimum: 3,
+			..Policy::default()
+		});
+		assert_eq!(names(&workspaces), ["1", "2", "3"]);
+		assert_eq!(
+			names(&Workspaces::new(Policy {
+				minimum: 0,
+				..Policy::default()
+			})),
+			["1"]
+		);
+	}
+
+	/// Tests which workspaces are collected, with and without auto-renumbering.
+	#[test]
+	fn collection() {
+		// (policy, names, windows' desktops, current) => collected indices, new numbers
+		type Case = ((usize, bool), &'static [&'static str], &'static [u32], usize);
+		type Expected = (&'static [usize], &'static [(u32, Option<u32>)]);
+		let table: &[(Case, Expected)] = &[
+			// Empty trailing workspaces are collected...
+			(
+				((1, false), &["1", "2", "3", "4"], &[0], 0),
+				(&[1, 2, 3], &[(2, None), (3, None), (4, None)]),
+			),
+			// ...but not the current workspace...
+			(((1, false), &["1", "2", "3"], &[0], 2), (&[], &[])),
+			// ...nor any before a workspace in use.
+			(((1, false), &["1", "2", "3", "4"], &[0, 2], 0), (&[3], &[(4, None)])),
+			// At least the minimum number of workspaces is kept.
+			(
+				((3, false), &["1", "2", "3", "4", "5"], &[0], 0),
+				(&[3, 4], &[(4, None), (5, None)]),
+			),
+			(((2, false), &["1", "2"], &[], 0), (&[], &[])),
+			// Named workspaces aren't collected, nor are those before them.
+			(((1, false), &["1", "2", "web", "4"], &[0], 0), (&[3], &[(4, None)])),
+			// Sticky windows don't keep workspaces in use.
+			(
+				((1, false), &["1", "2"], &[crate::desktop::ALL_DESKTOPS], 0),
+				(&[1], &[(2, None)]),
+			),
+			// Auto-renumbering collects empty workspaces anywhere, renumbering those after them.
+			(
+				((1, true), &["1", "2", "3", "4", "5"], &[0, 1, 3, 4], 0),
+				(&[2], &[(3, None), (4, Some(3)), (5, Some(4))]),
+			),
+			(
+				((1, true), &["1", "2", "3", "4", "5"], &[4], 4),
+				(
+					&[0, 1, 2, 3],
+					&[(1, None), (2, None), (3, None), (4, None), (5, Some(1))],
+				),
+			),
+			// Named workspaces keep their names, and are skipped when renumbering.
+			(
+				((1, true), &["1", "web", "2", "3"], &[0, 1, 3], 0),
+				(&[2], &[(2, None), (3, Some(2))]),
+			),
+			// The minimum counts named workspaces, and the last workspaces are collected first.
+			(((3, true), &["1", "2", "web", "3"], &[2], 2), (&[3], &[(3, None)])),
+		];
+
+		for &(((minimum, auto_renumber), workspace_names, windows, current), (collected, numbers)) in table {
+			let names: Vec<Name> = workspace_names.iter().map(|name| name.parse().unwrap()).collect();
+			let workspaces = Workspaces {
+				policy: Policy { minimum, auto_renumber },
+
+				recent: vec![current],
+				current,
+				desktops: windows
+					.iter()
+					.enumerate()
+					.map(|(window, &desktop)| (window as u32, Desktop::from_ewmh(desktop)))
+					.collect(),
+
+				names,
+			};
+
+			let remap = workspaces.collection();
+			let case = (minimum, auto_renumber, workspace_names, windows, current);
+
+			let removed: Vec<usize> = remap
+				.iter()
+				.flat_map(|remap| (0..workspace_names.len()).filter(|&index| remap.index(index).is_none()))
+				.collect();
+			assert_eq!(removed, collected, "{case:?}");
+
+			let renumbered: Vec<(u32, Option<u32>)> = remap.iter().flat_map(|remap| remap.numbers.clone()).collect();
+			assert_eq!(renumbered, numbers, "{case:?}");
+		}
+	}
+
+	/// Tests that every index and number referring to a workspace follows it when workspaces are
+	/// remapped.
+	#[test]
+	fn remap() {
+		let mut workspaces = Workspaces {
+			policy: Policy::default(),
+
+			names: ["1", "2", "web", "3", "4"].map(|name| name.parse().unwrap()).to_vec(),
+			current: 3,
+			recent: vec![3, 1, 4, 0, 2],
+
+			desktops: HashMap::from([
+				(10, Desktop::Index(0)),
+				(11, Desktop::Index(1)),
+				(12, Desktop::Index(3)),
+				(13, Desktop::All),
+				(14, Desktop::Index(4)),
+			]),
+		};
+		let mut pins = OutputAssignment::Pinned(HashMap::from([
+			(1, "DP-1".to_owned()),
+			(2, "DP-2".to_owned()),
+			(3, "HDMI-1".to_owned()),
+			(4, "HDMI-2".to_owned()),
+			(9, "eDP-1".to_owned()),
+		]));
+
+		// Workspace 2 (at index 1) is removed, and 3 and 4 are renumbered to 2 and 3.
+		let remap = Remap {
+			indices: vec![Some(0), None, Some(1), Some(2), Some(3)],
+			numbers: BTreeMap::from([(2, None), (3, Some(2)), (4, Some(3))]),
+		};
+		workspaces.remap_workspace_indices(&remap, &mut pins);
+
+		assert_eq!(names(&workspaces), ["1", "web", "2", "3"]);
+		assert_eq!(workspaces.current(), 2);
+		// The removed workspace is forgotten, and the rest keep their order of use.
+		assert_eq!(workspaces.recent(), [2, 3, 0, 1]);
+
+		assert_eq!(workspaces.desktop_of(&10), Some(Desktop::Index(0)));
+		// The window on the removed workspace is moved to the current workspace.
+		assert_eq!(workspaces.desktop_of(&11), Some(Desktop::Index(2)));
+		assert_eq!(workspaces.desktop_of(&12), Some(Desktop::Index(2)));
+		assert_eq!(workspaces.desktop_of(&13), Some(Desktop::All));
+		assert_eq!(workspaces.desktop_of(&14), Some(Desktop::Index(3)));
+
+		// Renumbered workspaces keep their pins; the removed workspace's pin is forgotten, and
+		// the pins of workspaces which don't exist are left alone.
+		assert_eq!(pinned(&pins), [(1, "DP-1"), (2, "HDMI-1"), (3, "HDMI-2"), (9, "eDP-1")]);
+
+		// Other output assignments have no pins to remap.
+		let mut primary = OutputAssignment::Primary;
+		workspaces.remap_workspace_indices(
+			&Remap {
+				indices: vec![Some(0), Some(1), Some(2), Some(3)],
+				..Remap::default()
+			},
+			&mut primary,
+		);
+		assert_eq!(primary, OutputAssignment::Primary);
+		assert_eq!(names(&workspaces), ["1", "web", "2", "3"]);
+	}
+
+	/// Tests that closing workspace 3 of 5 renumbers workspaces 4 and 5 once it is switched away
+	/// from, keeping the most recently used workspaces and pins consistent.
+	#[test]
+	fn renumber() {
+		let policy = Policy {
+			minimum: 1,
+			auto_renumber: true,
+		};
+		let mut workspaces = numbered(policy, 5);
+		let mut pins = OutputAssignment::Pinned(HashMap::from([(3, "DP-1".to_owned()), (5, "HDMI-1".to_owned())]));
+		assert_eq!(workspaces.recent(), [4, 3, 2, 1, 0]);
+
+		// Workspace 3's only window is closed while it is current; it isn't collected yet.
+		workspaces.switch_to(2, &mut pins).unwrap();
+		assert_eq!(workspaces.remove_window(&3), Some(Desktop::Index(2)));
+		assert_eq!(names(&workspaces), ["1", "2", "3", "4", "5"]);
+
+		let change = workspaces.switch_to(4, &mut pins).unwrap();
+		assert!(!change.created);
+		assert_eq!(
+			change.remap.map(|remap| remap.numbers),
+			Some(BTreeMap::from([(3, None), (4, Some(3)), (5, Some(4))]))
+		);
+
+		assert_eq!(names(&workspaces), ["1", "2", "3", "4"]);
+		// Workspace 5, now 4, is still current.
+		assert_eq!(workspaces.current(), 3);
+		assert_eq!(workspaces.recent(), [3, 2, 1, 0]);
+		assert_eq!(workspaces.desktop_of(&4), Some(Desktop::Index(2)));
+		assert_eq!(workspaces.desktop_of(&5), Some(Desktop::Index(3)));
+		assert_eq!(pinned(&pins), [(4, "HDMI-1")]);
+
+		// The next workspace created is numbered after the last.
+		assert!(workspaces.switch_to(4, &mut pins).unwrap().created);
+		assert_eq!(names(&workspaces), ["1", "2", "3", "4", "5"]);
+	}
+
+	/// Tests that empty trailing workspaces are only collected once they are switched away from.
+	#[test]
+	fn collect_on_switch() {
+		let mut pins = OutputAssignment::Primary;
+		let mut workspaces = numbered(Policy::default(), 4);
+
+		for window in [2, 3, 4] {
+			workspaces.remove_window(&window);
+		}
+		// Moving the last window away from the current workspace doesn't collect it...
+		workspaces.move_window(1, 1).unwrap();
+		assert_eq!(names(&workspaces), ["1", "2", "3", "4"]);
+
+		// ...but switching away from it does, along with the other empty trailing workspaces.
+		let change = workspaces.switch_to(1, &mut pins).unwrap();
+		assert!(change.remap.is_some());
+		assert_eq!(names(&workspaces), ["1", "2"]);
+		assert_eq!((workspaces.current(), workspaces.recent()), (1, &[1, 0][..]));
+		assert_eq!(workspaces.desktop_of(&1), Some(Desktop::Index(1)));
+
+		// The empty first workspace isn't trailing, so it is kept.
+		assert_eq!(workspaces.switch_to(0, &mut pins).map(|change| change.remap), Ok(None));
+		assert_eq!(names(&workspaces), ["1", "2"]);
+	}
+}